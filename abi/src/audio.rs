@@ -0,0 +1,51 @@
+//! PCM audio output shared by the sound drivers and userland.
+//!
+//! There is a single playback format: 48 kHz, signed 16-bit little-endian,
+//! interleaved stereo.  Applications convert anything else themselves.
+
+pub const AUDIO_SAMPLE_RATE: u32 = 48_000;
+pub const AUDIO_CHANNELS: u32 = 2;
+pub const AUDIO_BITS_PER_SAMPLE: u32 = 16;
+/// Bytes per interleaved frame (one sample for each channel).
+pub const AUDIO_FRAME_BYTES: usize = 4;
+
+/// Software volume range accepted by `AUDIO_CTL_SET_VOLUME`.
+pub const AUDIO_VOLUME_MAX: u32 = 100;
+
+/// Which controller drives the output, as reported in [`AudioInfo::backend`].
+pub const AUDIO_BACKEND_NONE: u32 = 0;
+pub const AUDIO_BACKEND_HDA: u32 = 1;
+pub const AUDIO_BACKEND_AC97: u32 = 2;
+
+/// `SYSCALL_AUDIO_CTL` commands.
+///
+/// * `AUDIO_CTL_INFO`: arg is a pointer to an [`AudioInfo`] to fill
+/// * `AUDIO_CTL_SET_VOLUME`: arg is 0..=[`AUDIO_VOLUME_MAX`]
+/// * `AUDIO_CTL_STOP`: drop everything queued and halt playback
+pub const AUDIO_CTL_INFO: u64 = 0;
+pub const AUDIO_CTL_SET_VOLUME: u64 = 1;
+pub const AUDIO_CTL_STOP: u64 = 2;
+
+/// Output stream state returned by `AUDIO_CTL_INFO`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct AudioInfo {
+    /// `AUDIO_BACKEND_*`; `AUDIO_BACKEND_NONE` when no device was found.
+    pub backend: u32,
+    pub sample_rate: u32,
+    pub channels: u32,
+    pub bits_per_sample: u32,
+    /// Capacity of the kernel's PCM ring.
+    pub ring_bytes: u32,
+    /// Bytes written but not yet played.
+    pub queued_bytes: u32,
+    pub volume: u32,
+    pub _reserved: u32,
+}
+
+impl AudioInfo {
+    #[inline]
+    pub const fn is_present(&self) -> bool {
+        self.backend != AUDIO_BACKEND_NONE
+    }
+}
@@ -0,0 +1,134 @@
+//! Audit log records returned by `SYSCALL_AUDIT_CTL`.
+//!
+//! The kernel records privileged operations, and the exit of every user
+//! process, into an in-memory ring.  Once the root filesystem is writable
+//! the ring is appended to `/var/log/audit` (syscalls) and `/var/log/acct`
+//! (process exits).  Each kind of record belongs to one `AUDIT_CAT_*`
+//! category, and only enabled categories are recorded.
+//!
+//! There is no mount or setuid syscall yet; they get a category when they
+//! are added.
+
+/// `SYSCALL_HALT` and `SYSCALL_REBOOT`.  Recorded before the call, since it
+/// does not return.
+pub const AUDIT_CAT_POWER: u32 = 1 << 0;
+/// Packet filter and routing table changes, and opening raw sockets.
+pub const AUDIT_CAT_NET: u32 = 1 << 1;
+/// Keyboard layout changes.
+pub const AUDIT_CAT_INPUT: u32 = 1 << 2;
+/// Kernel tracing, log levels and the `panic_on_warn` knob.
+pub const AUDIT_CAT_DEBUG: u32 = 1 << 3;
+/// Changes to the set of enabled categories.  Always recorded.
+pub const AUDIT_CAT_AUDIT: u32 = 1 << 4;
+/// Process accounting: one record per user process exit.
+pub const AUDIT_CAT_ACCT: u32 = 1 << 5;
+
+pub const AUDIT_CAT_ALL: u32 = AUDIT_CAT_POWER
+    | AUDIT_CAT_NET
+    | AUDIT_CAT_INPUT
+    | AUDIT_CAT_DEBUG
+    | AUDIT_CAT_AUDIT
+    | AUDIT_CAT_ACCT;
+
+/// Categories enabled at boot.
+pub const AUDIT_CAT_DEFAULT: u32 = AUDIT_CAT_ALL;
+
+/// Short names of the categories, in bit order, as `auditctl` and the log
+/// files spell them.
+pub const AUDIT_CAT_NAMES: [(&str, u32); 6] = [
+    ("power", AUDIT_CAT_POWER),
+    ("net", AUDIT_CAT_NET),
+    ("input", AUDIT_CAT_INPUT),
+    ("debug", AUDIT_CAT_DEBUG),
+    ("audit", AUDIT_CAT_AUDIT),
+    ("acct", AUDIT_CAT_ACCT),
+];
+
+/// Name of a single category bit, `"?"` for anything else.
+pub fn audit_cat_name(category: u32) -> &'static str {
+    AUDIT_CAT_NAMES
+        .iter()
+        .find(|(_, bit)| *bit == category)
+        .map_or("?", |(name, _)| name)
+}
+
+/// Most records `SYSCALL_AUDIT_CTL` keeps in memory.
+pub const AUDIT_RING_LEN: usize = 128;
+
+/// Bytes in [`AuditRecord::comm`], including the NUL terminator.
+pub const AUDIT_COMM_LEN: usize = 16;
+
+/// One audited event.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// Position in the audit stream since boot, starting at 0.
+    pub seq: u64,
+    /// Wall-clock time in nanoseconds since the Unix epoch, or since boot
+    /// when the clock has not been set.
+    pub time_ns: u64,
+    pub pid: u32,
+    /// Tasks carry no credentials yet, so this is always 0.
+    pub uid: u32,
+    /// One `AUDIT_CAT_*` bit.
+    pub category: u32,
+    /// The syscall number, or for [`AUDIT_CAT_ACCT`] the exit reason.
+    pub op: u32,
+    /// The syscall's first two arguments, or for [`AUDIT_CAT_ACCT`] the
+    /// process lifetime in milliseconds and the fault reason.
+    pub args: [u64; 2],
+    /// The syscall's return value, or for [`AUDIT_CAT_ACCT`] the exit code.
+    pub result: i64,
+    /// NUL-terminated name of the calling task.
+    pub comm: [u8; AUDIT_COMM_LEN],
+}
+
+const _: () = assert!(core::mem::size_of::<AuditRecord>() == 72);
+
+impl AuditRecord {
+    /// Task name as a string, up to the first NUL.
+    pub fn comm_str(&self) -> &str {
+        let len = self
+            .comm
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.comm.len());
+        core::str::from_utf8(&self.comm[..len]).unwrap_or("")
+    }
+}
+
+/// Counters and settings returned by `AUDIT_OP_STATUS`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AuditStatus {
+    /// Enabled `AUDIT_CAT_*` bits.
+    pub enabled: u32,
+    /// Nonzero once the log files have been written at least once.
+    pub logging: u32,
+    /// Records made since boot.
+    pub recorded: u64,
+    /// Records appended to the log files.
+    pub written: u64,
+    /// Records overwritten in the ring before they could be written.
+    pub dropped: u64,
+}
+
+/// Commands accepted by `SYSCALL_AUDIT_CTL`.
+///
+/// * `AUDIT_OP_STATUS`: arg1 points to an [`AuditStatus`] to fill.
+/// * `AUDIT_OP_SET`: arg1 is the new `AUDIT_CAT_*` mask; returns the old
+///   one.  [`AUDIT_CAT_AUDIT`] cannot be cleared.
+/// * `AUDIT_OP_READ`: arg1 points to an array of arg2 [`AuditRecord`]s;
+///   copies the newest records still in memory, oldest first, and returns
+///   how many.
+/// * `AUDIT_OP_FLUSH`: append pending records to the log files now;
+///   returns how many were written.
+pub const AUDIT_OP_STATUS: u64 = 0;
+pub const AUDIT_OP_SET: u64 = 1;
+pub const AUDIT_OP_READ: u64 = 2;
+pub const AUDIT_OP_FLUSH: u64 = 3;
+
+/// Where syscall records are appended.
+pub const AUDIT_LOG_PATH: &[u8] = b"/var/log/audit";
+/// Where [`AUDIT_CAT_ACCT`] records are appended.
+pub const AUDIT_ACCT_PATH: &[u8] = b"/var/log/acct";
@@ -0,0 +1,77 @@
+//! Crash bundles, announced through `SYSCALL_CRASH_NOTICE`.
+//!
+//! When a user task dies of a CPU fault the kernel snapshots its registers,
+//! the bytes around its stack and instruction pointers and its memory map.
+//! The `crashd` kernel thread turns each snapshot into a directory under
+//! [`CRASH_DIR`], named `<yyyymmdd>-<hhmmss>-<comm>-<pid>`, holding:
+//!
+//! * `report`: the fault, the registers, and the stack and code bytes
+//! * `maps`: the task's memory map, one region per line
+//! * `klog`: the tail of the kernel log
+//! * `screen.ppm`: a half-size screenshot, when there is a display
+//!
+//! and publishes a [`CrashNotice`] for it.  The compositor shows each new
+//! notice as a toast.
+
+/// Directory the bundles are written under.
+pub const CRASH_DIR: &[u8] = b"/var/crash";
+
+/// Bytes in [`CrashNotice::path`], including the NUL terminator.
+pub const CRASH_PATH_LEN: usize = 64;
+
+/// Bytes in [`CrashNotice::comm`], including the NUL terminator.
+pub const CRASH_COMM_LEN: usize = 16;
+
+/// Most notices the kernel keeps; older ones can no longer be read.
+pub const CRASH_NOTICE_LEN: usize = 8;
+
+/// A crash bundle that has been written.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrashNotice {
+    /// Position among the bundles written since boot, starting at 0.
+    pub seq: u64,
+    /// When the task crashed, as in `AuditRecord::time_ns`.
+    pub time_ns: u64,
+    pub pid: u32,
+    /// The [`TaskFaultReason`](crate::task::TaskFaultReason).
+    pub fault: u32,
+    /// NUL-terminated name of the task.
+    pub comm: [u8; CRASH_COMM_LEN],
+    /// NUL-terminated path of the bundle directory.
+    pub path: [u8; CRASH_PATH_LEN],
+}
+
+const _: () = assert!(core::mem::size_of::<CrashNotice>() == 104);
+
+impl Default for CrashNotice {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+impl CrashNotice {
+    pub const EMPTY: Self = Self {
+        seq: 0,
+        time_ns: 0,
+        pid: 0,
+        fault: 0,
+        comm: [0; CRASH_COMM_LEN],
+        path: [0; CRASH_PATH_LEN],
+    };
+
+    fn str_field(bytes: &[u8]) -> &str {
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        core::str::from_utf8(&bytes[..len]).unwrap_or("")
+    }
+
+    /// Task name as a string, up to the first NUL.
+    pub fn comm_str(&self) -> &str {
+        Self::str_field(&self.comm)
+    }
+
+    /// Bundle path as a string, up to the first NUL.
+    pub fn path_str(&self) -> &str {
+        Self::str_field(&self.path)
+    }
+}
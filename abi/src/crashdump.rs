@@ -0,0 +1,348 @@
+//! Kernel crash dumps.
+//!
+//! When the kernel panics it writes a dump into [`CRASHDUMP_PATH`], a file
+//! of [`CRASHDUMP_SIZE`] bytes reserved at boot, by writing the disk blocks
+//! under it directly.  After a reboot the file reads back like any other.
+//!
+//! A dump is a 32-byte header followed by sections.  Each section is a
+//! tag, its payload length and the payload, padded to 8 bytes.  Integers
+//! are little-endian.
+//!
+//! | Section     | Payload                                              |
+//! |-------------|------------------------------------------------------|
+//! | `MESSAGE`   | the panic message, UTF-8                             |
+//! | `REGS`      | `u64` values in [`CRASHDUMP_REG_NAMES`] order        |
+//! | `EXCEPTION` | the vector and error code of the exception, as `u64` |
+//! | `BACKTRACE` | return addresses, most recent call first, as `u64`   |
+//! | `STACK`     | the stack pointer as `u64`, then the bytes above it  |
+//! | `TASKS`     | [`CrashDumpTask`] records                            |
+//! | `MEMORY`    | [`CrashDumpMemory`]                                  |
+//! | `LOG`       | the tail of the kernel log                           |
+//!
+//! Sections that could not be collected are left out, and a section that
+//! does not fit is cut short.  Readers skip tags they do not know.
+
+use crate::task::TASK_NAME_MAX_LEN;
+
+/// File the kernel reserves for its dump.
+pub const CRASHDUMP_PATH: &[u8] = b"/var/crash/panic.dump";
+
+/// Size of [`CRASHDUMP_PATH`]; a dump never grows past it.
+pub const CRASHDUMP_SIZE: usize = 64 * 1024;
+
+pub const CRASHDUMP_MAGIC: [u8; 8] = *b"SLOPDUMP";
+pub const CRASHDUMP_VERSION: u32 = 1;
+
+const HEADER_LEN: usize = 32;
+const SECTION_HEADER_LEN: usize = 8;
+
+pub const CRASHDUMP_SECTION_MESSAGE: u16 = 1;
+pub const CRASHDUMP_SECTION_REGS: u16 = 2;
+pub const CRASHDUMP_SECTION_EXCEPTION: u16 = 3;
+pub const CRASHDUMP_SECTION_BACKTRACE: u16 = 4;
+pub const CRASHDUMP_SECTION_STACK: u16 = 5;
+pub const CRASHDUMP_SECTION_TASKS: u16 = 6;
+pub const CRASHDUMP_SECTION_MEMORY: u16 = 7;
+pub const CRASHDUMP_SECTION_LOG: u16 = 8;
+
+/// Register names in `REGS` order.
+pub const CRASHDUMP_REG_NAMES: [&str; 24] = [
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip", "rflags", "cs", "ss", "cr0", "cr2", "cr3", "cr4",
+];
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(bytes: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(bytes.get(at..at + 8)?.try_into().ok()?))
+}
+
+const fn align8(n: usize) -> usize {
+    (n + 7) & !7
+}
+
+/// One task in the `TASKS` section.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CrashDumpTask {
+    pub task_id: u32,
+    pub parent_id: u32,
+    /// The [`TaskStatus`](crate::task::TaskStatus).
+    pub status: u8,
+    /// CPU the task last ran on.
+    pub cpu: u8,
+    pub priority: u8,
+    pub flags: u16,
+    /// CPU time used, in TSC cycles.
+    pub runtime: u64,
+    pub name: [u8; TASK_NAME_MAX_LEN],
+}
+
+impl CrashDumpTask {
+    pub const ENCODED_LEN: usize = 24 + TASK_NAME_MAX_LEN;
+
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        let mut out = [0u8; Self::ENCODED_LEN];
+        out[0..4].copy_from_slice(&self.task_id.to_le_bytes());
+        out[4..8].copy_from_slice(&self.parent_id.to_le_bytes());
+        out[8] = self.status;
+        out[9] = self.cpu;
+        out[10] = self.priority;
+        out[12..14].copy_from_slice(&self.flags.to_le_bytes());
+        out[16..24].copy_from_slice(&self.runtime.to_le_bytes());
+        out[24..].copy_from_slice(&self.name);
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut name = [0u8; TASK_NAME_MAX_LEN];
+        name.copy_from_slice(bytes.get(24..Self::ENCODED_LEN)?);
+        Some(Self {
+            task_id: read_u32(bytes, 0)?,
+            parent_id: read_u32(bytes, 4)?,
+            status: bytes[8],
+            cpu: bytes[9],
+            priority: bytes[10],
+            flags: read_u16(bytes, 12)?,
+            runtime: read_u64(bytes, 16)?,
+            name,
+        })
+    }
+
+    /// Task name as a string, up to the first NUL.
+    pub fn name_str(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+}
+
+/// The `MEMORY` section.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CrashDumpMemory {
+    pub total_pages: u64,
+    pub free_pages: u64,
+    pub allocated_pages: u64,
+    pub heap_total: u64,
+    pub heap_allocated: u64,
+    pub heap_free: u64,
+}
+
+impl CrashDumpMemory {
+    pub const ENCODED_LEN: usize = 48;
+
+    fn fields(&self) -> [u64; 6] {
+        [
+            self.total_pages,
+            self.free_pages,
+            self.allocated_pages,
+            self.heap_total,
+            self.heap_allocated,
+            self.heap_free,
+        ]
+    }
+
+    pub fn encode(&self) -> [u8; Self::ENCODED_LEN] {
+        let mut out = [0u8; Self::ENCODED_LEN];
+        for (chunk, value) in out.chunks_exact_mut(8).zip(self.fields()) {
+            chunk.copy_from_slice(&value.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            total_pages: read_u64(bytes, 0)?,
+            free_pages: read_u64(bytes, 8)?,
+            allocated_pages: read_u64(bytes, 16)?,
+            heap_total: read_u64(bytes, 24)?,
+            heap_allocated: read_u64(bytes, 32)?,
+            heap_free: read_u64(bytes, 40)?,
+        })
+    }
+}
+
+/// Builds a dump in a caller-provided buffer without allocating.
+pub struct CrashDumpWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    /// Start of the open section's header.
+    open: Option<usize>,
+    truncated: bool,
+}
+
+impl<'a> CrashDumpWriter<'a> {
+    /// Start a dump taken `time_ns` after boot, at `unix_secs` wall clock
+    /// time (0 when unknown).  `buf` must hold at least the header.
+    pub fn new(buf: &'a mut [u8], time_ns: u64, unix_secs: u64) -> Option<Self> {
+        let header = buf.get_mut(..HEADER_LEN)?;
+        header.fill(0);
+        header[0..8].copy_from_slice(&CRASHDUMP_MAGIC);
+        header[8..12].copy_from_slice(&CRASHDUMP_VERSION.to_le_bytes());
+        header[16..24].copy_from_slice(&time_ns.to_le_bytes());
+        header[24..32].copy_from_slice(&unix_secs.to_le_bytes());
+        Some(Self {
+            buf,
+            len: HEADER_LEN,
+            open: None,
+            truncated: false,
+        })
+    }
+
+    /// Whether some section was cut short or left out for lack of room.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Open a section; its payload is whatever is [`push`](Self::push)ed
+    /// until [`end`](Self::end).
+    pub fn begin(&mut self, tag: u16) {
+        self.end();
+        if self.len + SECTION_HEADER_LEN > self.buf.len() {
+            self.truncated = true;
+            return;
+        }
+        self.buf[self.len..self.len + SECTION_HEADER_LEN].fill(0);
+        self.buf[self.len..self.len + 2].copy_from_slice(&tag.to_le_bytes());
+        self.open = Some(self.len);
+        self.len += SECTION_HEADER_LEN;
+    }
+
+    /// Append to the open section as much of `bytes` as fits.
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.open.is_none() {
+            return;
+        }
+        let n = bytes.len().min(self.buf.len() - self.len);
+        if n < bytes.len() {
+            self.truncated = true;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+    }
+
+    pub fn push_u64(&mut self, value: u64) {
+        self.push(&value.to_le_bytes());
+    }
+
+    /// Close the open section, if any.
+    pub fn end(&mut self) {
+        let Some(start) = self.open.take() else {
+            return;
+        };
+        let payload = (self.len - start - SECTION_HEADER_LEN) as u32;
+        self.buf[start + 4..start + 8].copy_from_slice(&payload.to_le_bytes());
+        let padded = align8(self.len).min(self.buf.len());
+        self.buf[self.len..padded].fill(0);
+        self.len = padded;
+    }
+
+    /// A whole section at once.
+    pub fn section(&mut self, tag: u16, payload: &[u8]) {
+        self.begin(tag);
+        self.push(payload);
+        self.end();
+    }
+
+    pub fn section_u64s(&mut self, tag: u16, values: &[u64]) {
+        self.begin(tag);
+        for &value in values {
+            self.push_u64(value);
+        }
+        self.end();
+    }
+
+    /// Close the dump.  Returns its length in bytes.
+    pub fn finish(mut self) -> usize {
+        self.end();
+        let len = self.len as u32;
+        self.buf[12..16].copy_from_slice(&len.to_le_bytes());
+        self.len
+    }
+}
+
+/// A dump read back.
+#[derive(Clone, Copy)]
+pub struct CrashDump<'a> {
+    pub version: u32,
+    pub time_ns: u64,
+    pub unix_secs: u64,
+    body: &'a [u8],
+}
+
+impl<'a> CrashDump<'a> {
+    /// Parse the dump at the start of `bytes`, or `None` when there is none.
+    pub fn parse(bytes: &'a [u8]) -> Option<Self> {
+        if bytes.get(..8)? != CRASHDUMP_MAGIC {
+            return None;
+        }
+        let version = read_u32(bytes, 8)?;
+        let len = read_u32(bytes, 12)? as usize;
+        if version != CRASHDUMP_VERSION || len < HEADER_LEN {
+            return None;
+        }
+        Some(Self {
+            version,
+            time_ns: read_u64(bytes, 16)?,
+            unix_secs: read_u64(bytes, 24)?,
+            body: bytes.get(HEADER_LEN..len.min(bytes.len()))?,
+        })
+    }
+
+    /// Tag and payload of each section, in order.
+    pub fn sections(&self) -> CrashDumpSections<'a> {
+        CrashDumpSections { rest: self.body }
+    }
+
+    /// Payload of the first section tagged `tag`.
+    pub fn section(&self, tag: u16) -> Option<&'a [u8]> {
+        self.sections()
+            .find(|&(t, _)| t == tag)
+            .map(|(_, payload)| payload)
+    }
+
+    /// The `u64` values of section `tag`; empty when it is missing.
+    pub fn u64s(&self, tag: u16) -> impl Iterator<Item = u64> + 'a {
+        self.section(tag)
+            .unwrap_or(&[])
+            .chunks_exact(8)
+            .filter_map(|chunk| read_u64(chunk, 0))
+    }
+
+    pub fn tasks(&self) -> impl Iterator<Item = CrashDumpTask> + 'a {
+        self.section(CRASHDUMP_SECTION_TASKS)
+            .unwrap_or(&[])
+            .chunks_exact(CrashDumpTask::ENCODED_LEN)
+            .filter_map(CrashDumpTask::decode)
+    }
+
+    pub fn memory(&self) -> Option<CrashDumpMemory> {
+        CrashDumpMemory::decode(self.section(CRASHDUMP_SECTION_MEMORY)?)
+    }
+}
+
+pub struct CrashDumpSections<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for CrashDumpSections<'a> {
+    type Item = (u16, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tag = read_u16(self.rest, 0)?;
+        let len = read_u32(self.rest, 4)? as usize;
+        let payload = &self.rest[SECTION_HEADER_LEN..];
+        let payload = &payload[..len.min(payload.len())];
+        let next = (SECTION_HEADER_LEN + align8(len)).min(self.rest.len());
+        self.rest = &self.rest[next..];
+        Some((tag, payload))
+    }
+}
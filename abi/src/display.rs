@@ -22,13 +22,19 @@ pub struct DisplayInfo {
     pub pitch: u32,
     /// Pixel format (determines bytes per pixel and channel layout)
     pub format: PixelFormat,
+    /// Integer UI scale: how many pixels the desktop draws per logical
+    /// pixel.  Zero from old producers reads as 1; see [`Self::scale_factor`].
+    pub scale: u32,
 }
 
 impl DisplayInfo {
     /// Maximum supported display dimension (sanity bound)
     pub const MAX_DIMENSION: u32 = 8192;
+    /// Largest supported UI scale.
+    pub const MAX_SCALE: u32 = 4;
 
-    /// Create a new DisplayInfo with the given parameters.
+    /// Create a new DisplayInfo with the given parameters, at the default
+    /// scale for its size.
     #[inline]
     pub const fn new(width: u32, height: u32, pitch: u32, format: PixelFormat) -> Self {
         Self {
@@ -36,9 +42,30 @@ impl DisplayInfo {
             height,
             pitch,
             format,
+            scale: Self::default_scale(height),
         }
     }
 
+    /// UI scale for a display `height` pixels tall: one step per 1080
+    /// lines, so a 4K display gets 2 and the UI keeps its 1080p size.
+    #[inline]
+    pub const fn default_scale(height: u32) -> u32 {
+        let scale = height / 1080;
+        if scale < 1 {
+            1
+        } else if scale > Self::MAX_SCALE {
+            Self::MAX_SCALE
+        } else {
+            scale
+        }
+    }
+
+    /// The UI scale, from 1 to [`Self::MAX_SCALE`].
+    #[inline]
+    pub fn scale_factor(&self) -> u32 {
+        self.scale.clamp(1, Self::MAX_SCALE)
+    }
+
     /// Returns bytes per pixel for this display's format.
     #[inline]
     pub fn bytes_per_pixel(&self) -> u8 {
@@ -68,12 +95,7 @@ impl DisplayInfo {
     #[inline]
     pub fn from_raw(width: u64, height: u64, pitch: u64, bpp: u16) -> Self {
         let format = PixelFormat::from_bpp(bpp as u8);
-        Self {
-            width: width as u32,
-            height: height as u32,
-            pitch: pitch as u32,
-            format,
-        }
+        Self::new(width as u32, height as u32, pitch as u32, format)
     }
 }
 
@@ -90,6 +112,77 @@ impl PixelFormat {
     }
 }
 
+/// Most outputs `SYSCALL_OUTPUT_ENUMERATE` reports.
+pub const MAX_OUTPUTS: usize = 4;
+
+/// [`OutputInfo::flags`]: windows with nowhere else to go open here.
+pub const OUTPUT_FLAG_PRIMARY: u32 = 1 << 0;
+
+/// `SYSCALL_OUTPUT_CONFIGURE` flag: make the output primary.
+pub const OUTPUT_CONFIGURE_PRIMARY: u32 = 1 << 0;
+/// Every `SYSCALL_OUTPUT_CONFIGURE` flag.
+pub const OUTPUT_CONFIGURE_FLAGS: u32 = OUTPUT_CONFIGURE_PRIMARY;
+
+/// One display output (a monitor on its own pipe).
+///
+/// The desktop is the framebuffer [`DisplayInfo`] describes, and each
+/// output scans out the `width` x `height` rectangle of it at `x, y`.
+/// Outputs keep the mode the firmware set.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutputInfo {
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// `OUTPUT_FLAG_*`
+    pub flags: u32,
+    /// Connector name, NUL-padded.
+    pub name: [u8; 16],
+}
+
+impl OutputInfo {
+    /// An output named `name` (truncated to fit) showing the rectangle at
+    /// `x, y`.
+    pub fn new(id: u32, name: &[u8], x: i32, y: i32, width: u32, height: u32) -> Self {
+        let mut info = Self {
+            id,
+            x,
+            y,
+            width,
+            height,
+            ..Self::default()
+        };
+        let len = name.len().min(info.name.len() - 1);
+        info.name[..len].copy_from_slice(&name[..len]);
+        info
+    }
+
+    #[inline]
+    pub fn is_primary(&self) -> bool {
+        self.flags & OUTPUT_FLAG_PRIMARY != 0
+    }
+
+    pub fn name_str(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+
+    /// Whether desktop point `x, y` is on this output.
+    #[inline]
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && ((x - self.x) as u32) < self.width
+            && ((y - self.y) as u32) < self.height
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct FramebufferData {
     pub address: *mut u8,
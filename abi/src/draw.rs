@@ -31,6 +31,12 @@ impl Color32 {
         Self::new(r, g, b, 0xFF)
     }
 
+    /// The same color with alpha `a`.
+    #[inline]
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self((self.0 & 0x00FF_FFFF) | ((a as u32) << 24))
+    }
+
     /// Extract the alpha component.
     #[inline]
     pub const fn alpha(self) -> u8 {
@@ -131,6 +137,15 @@ pub trait Canvas {
     /// Callers must ensure `byte_offset` is within buffer bounds.
     fn write_encoded_at(&mut self, byte_offset: usize, pixel: EncodedPixel);
 
+    /// Read back the pixel at the given byte offset, for blending onto it.
+    ///
+    /// The default returns `None`: surfaces that are slow or unsafe to
+    /// read (such as write-combined framebuffer memory) don't offer it.
+    #[inline]
+    fn read_encoded_at(&self, _byte_offset: usize) -> Option<EncodedPixel> {
+        None
+    }
+
     #[inline]
     fn clip_row_span(&self, row: i32, x0: i32, x1: i32) -> Option<(usize, usize, usize)> {
         clip_row_span_bounds(self.width(), self.height(), row, x0, x1)
@@ -181,6 +196,17 @@ pub trait Canvas {
         self.write_encoded_at(off, pixel);
     }
 
+    /// Read a single pixel, if the surface supports reading back.
+    #[inline]
+    fn get_pixel(&self, x: i32, y: i32) -> Option<EncodedPixel> {
+        if x < 0 || y < 0 || x >= self.width() as i32 || y >= self.height() as i32 {
+            return None;
+        }
+        let off =
+            (y as usize) * self.pitch_bytes() + (x as usize) * self.bytes_per_pixel() as usize;
+        self.read_encoded_at(off)
+    }
+
     /// Draw a horizontal line from `x0` to `x1` (inclusive).
     #[inline]
     fn hline(&mut self, x0: i32, x1: i32, y: i32, pixel: EncodedPixel) {
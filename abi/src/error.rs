@@ -2,119 +2,193 @@
 
 use core::ffi::c_int;
 
-/// Implement common methods for kernel error enums.
+/// Define a kernel error enum shared with userland.
 ///
-/// Generates `as_c_int()`, `from_c_int()`, `is_success()`, and `is_error()` methods
-/// for `#[repr(i32)]` error enums that follow the kernel's error convention.
-macro_rules! impl_kernel_error {
-    ($ty:ty, fallback: $fallback:ident, variants: { $($val:literal => $variant:ident),* $(,)? }) => {
+/// Every variant carries an explicit discriminant and the enum is
+/// `#[non_exhaustive]`: the numbers are ABI, and kernels and userland
+/// binaries built at different times have to agree on them.  New variants
+/// take fresh numbers; existing ones are never renumbered.
+///
+/// Generates `as_c_int()`, `try_from_c_int()`, `from_c_int()`,
+/// `is_success()` and `is_error()`, plus a compile-time check that each
+/// variant round-trips through its number.
+macro_rules! kernel_error_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $ty:ident (fallback: $fallback:ident) {
+            $(
+                $(#[$vmeta:meta])*
+                $variant:ident = $val:literal
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(i32)]
+        #[non_exhaustive]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $ty {
+            $(
+                $(#[$vmeta])*
+                $variant = $val,
+            )*
+        }
+
+        impl Default for $ty {
+            fn default() -> Self {
+                Self::Success
+            }
+        }
+
         impl $ty {
+            /// Every variant, in declaration order.
+            pub const ALL: &'static [Self] = &[$(Self::$variant),*];
+
             /// Convert to C-style integer for syscall returns.
             #[inline]
-            pub fn as_c_int(self) -> c_int {
+            pub const fn as_c_int(self) -> c_int {
                 self as c_int
             }
 
-            /// Convert from C-style integer.
+            /// Convert from C-style integer, or `None` for a number this
+            /// build does not know (e.g. from a newer kernel).
             #[inline]
-            pub fn from_c_int(val: c_int) -> Self {
+            pub const fn try_from_c_int(val: c_int) -> Option<Self> {
                 match val {
-                    $($val => Self::$variant,)*
-                    _ => Self::$fallback,
+                    $($val => Some(Self::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// Convert from C-style integer, mapping unknown numbers to
+            #[doc = concat!("`", stringify!($fallback), "`.")]
+            #[inline]
+            pub const fn from_c_int(val: c_int) -> Self {
+                match Self::try_from_c_int(val) {
+                    Some(err) => err,
+                    None => Self::$fallback,
                 }
             }
 
             /// Check if this is a success result.
             #[inline]
-            pub fn is_success(self) -> bool {
+            pub const fn is_success(self) -> bool {
                 matches!(self, Self::Success)
             }
 
             /// Check if this is an error result.
             #[inline]
-            pub fn is_error(self) -> bool {
+            pub const fn is_error(self) -> bool {
                 !self.is_success()
             }
         }
+
+        const _: () = {
+            $(
+                assert!(matches!($ty::try_from_c_int($val), Some($ty::$variant)));
+                assert!($ty::$variant.as_c_int() == $val);
+            )*
+        };
     };
 }
 
 /// Compositor operation result type
 pub type CompositorResult<T> = Result<T, CompositorError>;
 
-/// Errors returned by compositor operations
-#[repr(i32)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum CompositorError {
-    /// Operation succeeded
-    #[default]
-    Success = 0,
-    /// Surface not found for given task ID
-    SurfaceNotFound = -1,
-    /// Invalid role value
-    InvalidRole = -2,
-    /// Role already set (can only be set once per surface)
-    RoleAlreadySet = -3,
-    /// Parent surface not found
-    ParentNotFound = -4,
-    /// Maximum number of children reached
-    ChildLimitReached = -5,
-    /// Invalid argument
-    InvalidArgument = -6,
-    /// Memory allocation failed
-    OutOfMemory = -7,
-    /// Operation not permitted
-    PermissionDenied = -8,
-    /// Buffer not found
-    BufferNotFound = -9,
-    /// Invalid buffer token
-    InvalidToken = -10,
+kernel_error_enum! {
+    /// Errors returned by compositor operations
+    pub enum CompositorError (fallback: InvalidArgument) {
+        /// Operation succeeded
+        Success = 0,
+        /// Surface not found for given task ID
+        SurfaceNotFound = -1,
+        /// Invalid role value
+        InvalidRole = -2,
+        /// Role already set (can only be set once per surface)
+        RoleAlreadySet = -3,
+        /// Parent surface not found
+        ParentNotFound = -4,
+        /// Maximum number of children reached
+        ChildLimitReached = -5,
+        /// Invalid argument
+        InvalidArgument = -6,
+        /// Memory allocation failed
+        OutOfMemory = -7,
+        /// Operation not permitted
+        PermissionDenied = -8,
+        /// Buffer not found
+        BufferNotFound = -9,
+        /// Invalid buffer token
+        InvalidToken = -10,
+    }
 }
 
-impl_kernel_error!(CompositorError, fallback: InvalidArgument, variants: {
-    0 => Success,
-    -1 => SurfaceNotFound,
-    -2 => InvalidRole,
-    -3 => RoleAlreadySet,
-    -4 => ParentNotFound,
-    -5 => ChildLimitReached,
-    -6 => InvalidArgument,
-    -7 => OutOfMemory,
-    -8 => PermissionDenied,
-    -9 => BufferNotFound,
-    -10 => InvalidToken,
-});
-
-/// Shared memory operation errors
-#[repr(i32)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum ShmError {
-    /// Operation succeeded
-    #[default]
-    Success = 0,
-    /// Failed to allocate physical memory for buffer
-    AllocationFailed = -1,
-    /// Failed to map buffer into address space
-    MappingFailed = -2,
-    /// Invalid or expired buffer token
-    InvalidToken = -3,
-    /// Operation not permitted (e.g., non-owner trying to destroy)
-    PermissionDenied = -4,
-    /// Maximum number of shared buffers reached
-    BufferLimitReached = -5,
-    /// Maximum number of mappings per buffer reached
-    MappingLimitReached = -6,
-    /// Invalid size (zero or too large)
-    InvalidSize = -7,
+kernel_error_enum! {
+    /// Shared memory operation errors
+    pub enum ShmError (fallback: InvalidToken) {
+        /// Operation succeeded
+        Success = 0,
+        /// Failed to allocate physical memory for buffer
+        AllocationFailed = -1,
+        /// Failed to map buffer into address space
+        MappingFailed = -2,
+        /// Invalid or expired buffer token
+        InvalidToken = -3,
+        /// Operation not permitted (e.g., non-owner trying to destroy)
+        PermissionDenied = -4,
+        /// Maximum number of shared buffers reached
+        BufferLimitReached = -5,
+        /// Maximum number of mappings per buffer reached
+        MappingLimitReached = -6,
+        /// Invalid size (zero or too large)
+        InvalidSize = -7,
+    }
 }
 
-impl_kernel_error!(ShmError, fallback: InvalidToken, variants: {
-    0 => Success,
-    -1 => AllocationFailed,
-    -2 => MappingFailed,
-    -3 => InvalidToken,
-    -4 => PermissionDenied,
-    -5 => BufferLimitReached,
-    -6 => MappingLimitReached,
-    -7 => InvalidSize,
-});
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compositor_error_round_trips() {
+        for &err in CompositorError::ALL {
+            assert_eq!(CompositorError::try_from_c_int(err.as_c_int()), Some(err));
+            assert_eq!(CompositorError::from_c_int(err.as_c_int()), err);
+        }
+    }
+
+    #[test]
+    fn shm_error_round_trips() {
+        for &err in ShmError::ALL {
+            assert_eq!(ShmError::try_from_c_int(err.as_c_int()), Some(err));
+            assert_eq!(ShmError::from_c_int(err.as_c_int()), err);
+        }
+    }
+
+    #[test]
+    fn discriminants_are_stable() {
+        assert_eq!(CompositorError::Success.as_c_int(), 0);
+        assert_eq!(CompositorError::InvalidArgument.as_c_int(), -6);
+        assert_eq!(CompositorError::InvalidToken.as_c_int(), -10);
+        assert_eq!(ShmError::Success.as_c_int(), 0);
+        assert_eq!(ShmError::InvalidToken.as_c_int(), -3);
+        assert_eq!(ShmError::InvalidSize.as_c_int(), -7);
+    }
+
+    #[test]
+    fn unknown_codes_use_fallback() {
+        assert_eq!(CompositorError::try_from_c_int(-11), None);
+        assert_eq!(
+            CompositorError::from_c_int(-11),
+            CompositorError::InvalidArgument
+        );
+        assert_eq!(ShmError::try_from_c_int(1), None);
+        assert_eq!(ShmError::from_c_int(1), ShmError::InvalidToken);
+    }
+
+    #[test]
+    fn success_is_default() {
+        assert!(CompositorError::default().is_success());
+        assert!(ShmError::default().is_success());
+        assert!(ShmError::MappingFailed.is_error());
+    }
+}
@@ -0,0 +1,124 @@
+//! Typed, generation-checked kernel object handles.
+//!
+//! A handle packs the object kind, a per-slot generation, and the slot index
+//! into one `u32`, so it fits the existing 32-bit token/index slots in the
+//! syscall ABI.  Closing an object bumps its slot's generation: a stale
+//! handle then fails lookup instead of aliasing whatever reused the slot.
+//!
+//! ```text
+//!  31      28 27                 12 11          0
+//! +----------+---------------------+-------------+
+//! |   kind   |     generation      |    index    |
+//! +----------+---------------------+-------------+
+//! ```
+//!
+//! Generation 0 is never issued, so a valid handle is never 0 and the
+//! historical "0 means failure" convention still holds.
+
+pub const HANDLE_INDEX_BITS: u32 = 12;
+pub const HANDLE_GENERATION_BITS: u32 = 16;
+pub const HANDLE_KIND_SHIFT: u32 = HANDLE_INDEX_BITS + HANDLE_GENERATION_BITS;
+
+/// Largest number of slots a single handle table can address.
+pub const HANDLE_MAX_INDEX: usize = 1 << HANDLE_INDEX_BITS;
+
+const INDEX_MASK: u32 = (1 << HANDLE_INDEX_BITS) - 1;
+const GENERATION_MASK: u32 = (1 << HANDLE_GENERATION_BITS) - 1;
+
+/// Object kinds that can sit behind a [`Handle`].
+#[repr(u8)]
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleKind {
+    /// Shared-memory buffer (also names a compositor surface once attached).
+    Shm = 1,
+    /// Network socket held by a file descriptor.
+    Socket = 2,
+}
+
+impl HandleKind {
+    pub const fn from_u8(raw: u8) -> Option<Self> {
+        match raw {
+            1 => Some(Self::Shm),
+            2 => Some(Self::Socket),
+            _ => None,
+        }
+    }
+}
+
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Handle(u32);
+
+impl Handle {
+    pub const NULL: Self = Self(0);
+
+    /// Pack a handle.  `index` and `generation` are truncated to their
+    /// field widths; callers keep them in range.
+    pub const fn new(kind: HandleKind, index: usize, generation: u16) -> Self {
+        Self(
+            ((kind as u32) << HANDLE_KIND_SHIFT)
+                | ((generation as u32 & GENERATION_MASK) << HANDLE_INDEX_BITS)
+                | (index as u32 & INDEX_MASK),
+        )
+    }
+
+    #[inline]
+    pub const fn from_raw(raw: u32) -> Self {
+        Self(raw)
+    }
+
+    #[inline]
+    pub const fn raw(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub const fn is_null(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline]
+    pub const fn kind(self) -> Option<HandleKind> {
+        HandleKind::from_u8((self.0 >> HANDLE_KIND_SHIFT) as u8)
+    }
+
+    #[inline]
+    pub const fn index(self) -> usize {
+        (self.0 & INDEX_MASK) as usize
+    }
+
+    #[inline]
+    pub const fn generation(self) -> u16 {
+        ((self.0 >> HANDLE_INDEX_BITS) & GENERATION_MASK) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_roundtrip() {
+        let h = Handle::new(HandleKind::Socket, 4095, 0xBEEF);
+        assert_eq!(h.kind(), Some(HandleKind::Socket));
+        assert_eq!(h.index(), 4095);
+        assert_eq!(h.generation(), 0xBEEF);
+        assert_eq!(Handle::from_raw(h.raw()), h);
+    }
+
+    #[test]
+    fn issued_handles_are_never_null() {
+        let h = Handle::new(HandleKind::Shm, 0, 1);
+        assert!(!h.is_null());
+        assert_ne!(h.raw(), 0);
+    }
+
+    #[test]
+    fn kinds_do_not_alias() {
+        let shm = Handle::new(HandleKind::Shm, 3, 7);
+        let sock = Handle::new(HandleKind::Socket, 3, 7);
+        assert_ne!(shm, sock);
+        assert_eq!(Handle::NULL.kind(), None);
+    }
+}
@@ -0,0 +1,90 @@
+//! Hardware inventory records returned by `SYSCALL_HW_INVENTORY`.
+//!
+//! The kernel assembles the inventory once at boot, after PCI drivers have
+//! probed and the display is up.  Every device is one [`HwDevice`]; the
+//! meaning of [`HwDevice::attrs`] depends on the class and is listed next to
+//! each `HW_CLASS_*` constant.
+
+/// A logical CPU.  attrs: APIC id, CPU index, CPUID family/model/stepping
+/// packed as `family << 16 | model << 8 | stepping`.
+pub const HW_CLASS_CPU: u8 = 1;
+/// A memory node.  attrs: lowest usable address, end of the highest usable
+/// region, usable bytes.
+pub const HW_CLASS_MEMORY: u8 = 2;
+/// A PCI function.  attrs: [`hw_pci_bdf`] location, `vendor << 16 | device`,
+/// `class << 16 | subclass << 8 | prog_if`, `revision << 8 | irq_line`.
+pub const HW_CLASS_PCI: u8 = 3;
+/// A block device.  attrs: capacity in bytes, logical block size.
+pub const HW_CLASS_BLOCK: u8 = 4;
+/// A network interface.  attrs: MAC address (big-endian in the low 48
+/// bits), MTU.
+pub const HW_CLASS_NET: u8 = 5;
+/// A display.  attrs: width, height, pitch, bits per pixel.
+pub const HW_CLASS_DISPLAY: u8 = 6;
+/// An input device.  attrs: one of `HW_INPUT_*`, protocol id.
+pub const HW_CLASS_INPUT: u8 = 7;
+
+/// [`HW_CLASS_INPUT`] kinds.
+pub const HW_INPUT_KEYBOARD: u64 = 1;
+pub const HW_INPUT_POINTER: u64 = 2;
+
+/// [`HwDevice::parent`] of a device that hangs off nothing else.
+pub const HW_NO_PARENT: u16 = u16::MAX;
+
+/// [`HwDevice::flags`]: a driver claimed the device.
+pub const HW_FLAG_BOUND: u8 = 1 << 0;
+/// [`HwDevice::flags`]: the bootstrap processor.
+pub const HW_FLAG_BSP: u8 = 1 << 1;
+
+/// Bytes in [`HwDevice::name`] and [`HwDevice::driver`], including the NUL
+/// terminator.
+pub const HW_NAME_LEN: usize = 16;
+
+/// Most devices `SYSCALL_HW_INVENTORY` reports.
+pub const HW_MAX_DEVICES: usize = 64;
+
+/// One entry of the hardware inventory.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HwDevice {
+    /// Position in the inventory; stable for the life of the boot.
+    pub id: u16,
+    /// `id` of the device this one is attached through, or
+    /// [`HW_NO_PARENT`].  A block device's parent is its PCI function.
+    pub parent: u16,
+    /// One of `HW_CLASS_*`.
+    pub class: u8,
+    /// `HW_FLAG_*` bits.
+    pub flags: u8,
+    pub _pad: [u8; 2],
+    /// NUL-terminated device name: `cpu0`, `00:03.0`, `vda`, `eth0`.
+    pub name: [u8; HW_NAME_LEN],
+    /// NUL-terminated name of the bound driver; empty when unbound.
+    pub driver: [u8; HW_NAME_LEN],
+    /// Class-specific values, see the `HW_CLASS_*` constants.
+    pub attrs: [u64; 4],
+}
+
+const _: () = assert!(core::mem::size_of::<HwDevice>() == 72);
+
+/// Pack a PCI location the way [`HW_CLASS_PCI`] reports it.
+pub const fn hw_pci_bdf(bus: u8, device: u8, function: u8) -> u64 {
+    (bus as u64) << 16 | (device as u64) << 8 | function as u64
+}
+
+impl HwDevice {
+    /// Name as a string, up to the first NUL.
+    pub fn name_str(&self) -> &str {
+        nul_str(&self.name)
+    }
+
+    /// Driver name as a string; empty when unbound.
+    pub fn driver_str(&self) -> &str {
+        nul_str(&self.driver)
+    }
+}
+
+fn nul_str(bytes: &[u8]) -> &str {
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    core::str::from_utf8(&bytes[..len]).unwrap_or("")
+}
@@ -1,15 +1,87 @@
 //! Input event types (Wayland-style per-task input queues)
 
+use crate::syscall::{ERRNO_EINVAL, ERRNO_ENOENT, ERRNO_ERANGE};
+
 /// Maximum number of tasks that can have input queues
 pub const MAX_INPUT_TASKS: usize = 32;
 
 /// Maximum events per task queue
 pub const MAX_EVENTS_PER_TASK: usize = 64;
 pub const CLIPBOARD_MAX_SIZE: usize = 4096;
+/// Longest MIME type the clipboard stores.
+pub const CLIPBOARD_MIME_MAX: usize = 64;
+/// MIME type of plain text, which `SYSCALL_CLIPBOARD_COPY`/`_PASTE` use.
+pub const CLIPBOARD_MIME_TEXT: &[u8] = b"text/plain";
+
+/// Whether `mime` is a well-formed `type/subtype` (optionally followed by
+/// `;parameters`) that fits in the clipboard.
+pub fn clipboard_mime_valid(mime: &[u8]) -> bool {
+    let essence = mime.split(|&b| b == b';').next().unwrap_or(&[]);
+    let mut parts = essence.split(|&b| b == b'/');
+    let token = |part: Option<&[u8]>| {
+        part.is_some_and(|p| {
+            !p.is_empty()
+                && p.iter()
+                    .all(|&b| b.is_ascii_alphanumeric() || b"!#$&-^_.+".contains(&b))
+        })
+    };
+    mime.len() <= CLIPBOARD_MIME_MAX
+        && mime.iter().all(|&b| b.is_ascii_graphic() || b == b' ')
+        && token(parts.next())
+        && token(parts.next())
+        && parts.next().is_none()
+}
+
+/// Reasons the clipboard rejects a `SYSCALL_CLIPBOARD_SET`/`_GET`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardError {
+    /// The MIME type is not a well-formed `type/subtype`.
+    BadMime,
+    /// The data does not fit in [`CLIPBOARD_MAX_SIZE`].
+    TooLarge,
+    /// The clipboard is empty or holds another MIME type.
+    NoMatch,
+}
+
+impl ClipboardError {
+    pub const fn errno(self) -> u64 {
+        match self {
+            Self::BadMime => ERRNO_EINVAL,
+            Self::TooLarge => ERRNO_ERANGE,
+            Self::NoMatch => ERRNO_ENOENT,
+        }
+    }
+}
 
 /// Focus type for input_set_focus syscall
 pub const INPUT_FOCUS_KEYBOARD: u32 = 0;
 pub const INPUT_FOCUS_POINTER: u32 = 1;
+/// The task that gets key presses made with Super held, instead of the
+/// keyboard focus or the TTY.  Compositor only.
+pub const INPUT_FOCUS_SHORTCUTS: u32 = 2;
+
+/// Have key presses delivered to the calling task's event queue instead of
+/// the TTY while it has keyboard focus.  The target task ID is ignored.
+pub const INPUT_FOCUS_KEYS: u32 = 3;
+
+/// Key event modifier bit: a Super (Windows) key was held.
+pub const KEY_MOD_SUPER: u8 = 1 << 0;
+/// Key event modifier bit: a Shift key was held.
+pub const KEY_MOD_SHIFT: u8 = 1 << 1;
+/// Key event modifier bit: a Ctrl key was held.
+pub const KEY_MOD_CTRL: u8 = 1 << 2;
+
+/// Set-1 make codes of the arrow and editing keys (sent after an `0xE0`
+/// prefix).  Key events for them carry ASCII 0.
+pub const SCANCODE_UP: u8 = 0x48;
+pub const SCANCODE_DOWN: u8 = 0x50;
+pub const SCANCODE_LEFT: u8 = 0x4B;
+pub const SCANCODE_RIGHT: u8 = 0x4D;
+pub const SCANCODE_HOME: u8 = 0x47;
+pub const SCANCODE_END: u8 = 0x4F;
+pub const SCANCODE_PAGE_UP: u8 = 0x49;
+pub const SCANCODE_PAGE_DOWN: u8 = 0x51;
+pub const SCANCODE_DELETE: u8 = 0x53;
 
 /// Type of input event
 #[repr(u8)]
@@ -32,6 +104,10 @@ pub enum InputEventType {
     PointerLeave = 6,
     /// Window manager requests this app to close gracefully
     CloseRequest = 7,
+    /// Scroll wheel moved
+    PointerScroll = 8,
+    /// Window manager resized this app's window
+    Configure = 9,
 }
 
 impl InputEventType {
@@ -47,6 +123,8 @@ impl InputEventType {
             5 => Some(Self::PointerEnter),
             6 => Some(Self::PointerLeave),
             7 => Some(Self::CloseRequest),
+            8 => Some(Self::PointerScroll),
+            9 => Some(Self::Configure),
             _ => None,
         }
     }
@@ -67,6 +145,7 @@ impl InputEventType {
                 | Self::PointerButtonRelease
                 | Self::PointerEnter
                 | Self::PointerLeave
+                | Self::PointerScroll
         )
     }
 }
@@ -76,7 +155,10 @@ impl InputEventType {
 /// For key events: data0 contains scancode in low 16 bits, ASCII in high 16 bits
 /// For pointer motion: data0 is x coordinate, data1 is y coordinate
 /// For pointer button: data0 contains button code
+/// For pointer scroll: data0 is the horizontal delta, data1 the vertical
+/// delta (positive scrolls down, towards the user)
 /// For close request: data0/data1 are zero
+/// For configure: data0 is the new width, data1 the new height
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Default)]
 pub struct InputEventData {
@@ -112,12 +194,23 @@ impl Default for InputEvent {
 impl InputEvent {
     /// Create a key event
     pub fn key(event_type: InputEventType, scancode: u8, ascii: u8, timestamp_ms: u64) -> Self {
+        Self::key_with_modifiers(event_type, scancode, ascii, 0, timestamp_ms)
+    }
+
+    /// Create a key event with `KEY_MOD_*` modifier bits
+    pub fn key_with_modifiers(
+        event_type: InputEventType,
+        scancode: u8,
+        ascii: u8,
+        modifiers: u8,
+        timestamp_ms: u64,
+    ) -> Self {
         Self {
             event_type,
             _padding: [0; 3],
             timestamp_ms,
             data: InputEventData {
-                data0: (scancode as u32) | ((ascii as u32) << 16),
+                data0: (scancode as u32) | ((modifiers as u32) << 8) | ((ascii as u32) << 16),
                 data1: 0,
             },
         }
@@ -170,6 +263,19 @@ impl InputEvent {
         }
     }
 
+    /// Create a pointer scroll event.  Deltas are in wheel detents.
+    pub fn pointer_scroll(dx: i32, dy: i32, timestamp_ms: u64) -> Self {
+        Self {
+            event_type: InputEventType::PointerScroll,
+            _padding: [0; 3],
+            timestamp_ms,
+            data: InputEventData {
+                data0: dx as u32,
+                data1: dy as u32,
+            },
+        }
+    }
+
     /// Create a close-request event
     pub fn close_request(timestamp_ms: u64) -> Self {
         Self {
@@ -180,6 +286,19 @@ impl InputEvent {
         }
     }
 
+    /// Create a configure event asking the app to resize to `width` x `height`
+    pub fn configure(width: u32, height: u32, timestamp_ms: u64) -> Self {
+        Self {
+            event_type: InputEventType::Configure,
+            _padding: [0; 3],
+            timestamp_ms,
+            data: InputEventData {
+                data0: width,
+                data1: height,
+            },
+        }
+    }
+
     /// Extract scancode from key event
     #[inline]
     pub fn key_scancode(&self) -> u8 {
@@ -192,6 +311,12 @@ impl InputEvent {
         ((self.data.data0 >> 16) & 0xFF) as u8
     }
 
+    /// Extract `KEY_MOD_*` bits from key event
+    #[inline]
+    pub fn key_modifiers(&self) -> u8 {
+        ((self.data.data0 >> 8) & 0xFF) as u8
+    }
+
     /// Extract X coordinate from pointer event
     #[inline]
     pub fn pointer_x(&self) -> i32 {
@@ -204,9 +329,58 @@ impl InputEvent {
         self.data.data1 as i32
     }
 
+    /// Extract horizontal delta from pointer scroll event
+    #[inline]
+    pub fn scroll_dx(&self) -> i32 {
+        self.data.data0 as i32
+    }
+
+    /// Extract vertical delta from pointer scroll event
+    #[inline]
+    pub fn scroll_dy(&self) -> i32 {
+        self.data.data1 as i32
+    }
+
+    /// Extract the new size from a configure event
+    #[inline]
+    pub fn configure_size(&self) -> (u32, u32) {
+        (self.data.data0, self.data.data1)
+    }
+
     /// Extract button from pointer button event
     #[inline]
     pub fn pointer_button_code(&self) -> u8 {
         (self.data.data0 & 0xFF) as u8
     }
 }
+
+// =============================================================================
+// Keyboard layouts
+// =============================================================================
+
+/// US QWERTY (the boot default).
+pub const KEYMAP_US: u32 = 0;
+/// German QWERTZ with dead acute, grave and circumflex.
+pub const KEYMAP_DE: u32 = 1;
+/// French AZERTY with dead circumflex, diaeresis, grave and tilde.
+pub const KEYMAP_FR: u32 = 2;
+/// Number of built-in layouts; valid IDs are `0..KEYMAP_COUNT`.
+pub const KEYMAP_COUNT: u32 = 3;
+
+/// Pass to `SYSCALL_SET_KEYMAP` to read the active layout without changing it.
+pub const KEYMAP_QUERY: u64 = u64::MAX;
+
+const KEYMAP_NAMES: [&str; KEYMAP_COUNT as usize] = ["us", "de", "fr"];
+
+/// Short name of a layout (`"us"`, `"de"`, `"fr"`).
+pub fn keymap_name(id: u32) -> Option<&'static str> {
+    KEYMAP_NAMES.get(id as usize).copied()
+}
+
+/// Look up a layout by its short name.
+pub fn keymap_from_name(name: &[u8]) -> Option<u32> {
+    KEYMAP_NAMES
+        .iter()
+        .position(|n| n.as_bytes() == name)
+        .map(|idx| idx as u32)
+}
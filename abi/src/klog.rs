@@ -0,0 +1,55 @@
+//! Kernel log levels and subsystems, as `SYSCALL_KLOG_LEVEL` and the
+//! `loglevel` builtin name them.
+//!
+//! Every kernel log line belongs to one subsystem, taken from the module
+//! that logged it unless the call names one.  A subsystem either follows
+//! the default level or has its own, so one driver can be debugged without
+//! flooding the log with everything else.
+
+/// Level numbers, most severe first.  A line is logged when its level is
+/// at or below the level of its subsystem.
+pub const KLOG_LEVEL_ERROR: u32 = 0;
+pub const KLOG_LEVEL_WARN: u32 = 1;
+pub const KLOG_LEVEL_INFO: u32 = 2;
+pub const KLOG_LEVEL_DEBUG: u32 = 3;
+pub const KLOG_LEVEL_TRACE: u32 = 4;
+
+/// Names of the levels, by level number.
+pub const KLOG_LEVEL_NAMES: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// `SYSCALL_KLOG_LEVEL` level argument: change nothing, only read.
+pub const KLOG_LEVEL_KEEP: u64 = u64::MAX;
+/// `SYSCALL_KLOG_LEVEL` level argument and result: the subsystem follows
+/// the default level.
+pub const KLOG_LEVEL_INHERIT: u64 = 0xFF;
+
+/// `SYSCALL_KLOG_LEVEL` subsystem argument for the default level.
+pub const KLOG_MODULE_DEFAULT: u64 = u64::MAX;
+
+/// Names of the subsystems, by subsystem number.
+pub const KLOG_MODULE_NAMES: [&str; 10] = [
+    "core", "boot", "mm", "sched", "net", "video", "fs", "input", "audio", "drivers",
+];
+
+pub const KLOG_MODULE_COUNT: usize = KLOG_MODULE_NAMES.len();
+
+/// Level number of `name`.
+pub fn klog_level_from_name(name: &[u8]) -> Option<u32> {
+    KLOG_LEVEL_NAMES
+        .iter()
+        .position(|n| n.as_bytes() == name)
+        .map(|idx| idx as u32)
+}
+
+/// Name of level `level`, `"?"` for anything else.
+pub fn klog_level_name(level: u64) -> &'static str {
+    KLOG_LEVEL_NAMES.get(level as usize).copied().unwrap_or("?")
+}
+
+/// Subsystem number of `name`.
+pub fn klog_module_from_name(name: &[u8]) -> Option<u32> {
+    KLOG_MODULE_NAMES
+        .iter()
+        .position(|n| n.as_bytes() == name)
+        .map(|idx| idx as u32)
+}
@@ -12,7 +12,11 @@
 #![forbid(unsafe_code)]
 
 pub mod addr;
+pub mod audio;
+pub mod audit;
 pub mod auxv;
+pub mod crash;
+pub mod crashdump;
 pub mod damage;
 pub mod display;
 pub mod draw;
@@ -20,14 +24,22 @@ pub mod error;
 pub mod fate;
 pub mod font;
 pub mod fs;
+pub mod handle;
+pub mod hw;
 pub mod input;
+pub mod klog;
 pub mod net;
+pub mod perf;
 pub mod pixel;
+pub mod present;
+pub mod rlimit;
 pub mod shm;
 pub mod signal;
 pub mod surface;
 pub mod syscall;
+pub mod syscall_filter;
 pub mod task;
+pub mod time;
 pub mod video_traits;
 pub mod window;
 
@@ -35,12 +47,15 @@ pub mod window;
 pub const PAGE_SIZE: u64 = 0x1000;
 
 pub use addr::*;
+pub use audit::*;
 pub use damage::{DamageRect, MAX_DAMAGE_REGIONS, MAX_INTERNAL_DAMAGE_REGIONS};
-pub use display::{DisplayInfo, FramebufferData};
+pub use display::{DisplayInfo, FramebufferData, OutputInfo};
 pub use draw::{Canvas, Color32, EncodedPixel};
 pub use error::*;
 pub use fate::FateResult;
 pub use fs::*;
+pub use handle::{Handle, HandleKind};
+pub use hw::*;
 pub use input::*;
 pub use net::*;
 pub use pixel::*;
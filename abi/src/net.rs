@@ -17,9 +17,68 @@ pub struct UserNetInfo {
     pub mtu: u16,
     pub link_up: u8,
     pub nic_ready: u8,
+    /// How the address was obtained, one of `NET_CONFIG_*`.
+    pub config_source: u8,
+    pub _pad: [u8; 1],
+}
+
+/// [`UserNetInfo::config_source`]: no address configured.
+pub const NET_CONFIG_NONE: u8 = 0;
+/// [`UserNetInfo::config_source`]: address leased over DHCP.
+pub const NET_CONFIG_DHCP: u8 = 1;
+/// [`UserNetInfo::config_source`]: static address from the kernel cmdline.
+pub const NET_CONFIG_STATIC: u8 = 2;
+
+/// [`NetLease::state`] values, following the RFC 2131 client states.
+///
+/// `NET_LEASE_NONE` means the DHCP client is not running (no NIC, or a
+/// static address).  `NET_LEASE_INIT` is a client waiting for link.
+pub const NET_LEASE_NONE: u8 = 0;
+pub const NET_LEASE_INIT: u8 = 1;
+pub const NET_LEASE_SELECTING: u8 = 2;
+pub const NET_LEASE_REQUESTING: u8 = 3;
+pub const NET_LEASE_BOUND: u8 = 4;
+pub const NET_LEASE_RENEWING: u8 = 5;
+pub const NET_LEASE_REBINDING: u8 = 6;
+
+/// Lease time meaning the lease never expires; also used for the
+/// `*_in` countdowns of such a lease.
+pub const NET_LEASE_INFINITE: u32 = u32::MAX;
+
+/// DHCP lease state, filled by `SYSCALL_NET_LEASE`.
+///
+/// Times are seconds.  The `*_in` fields count down from the moment of the
+/// query and stop at zero; they are zero when no lease is held.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NetLease {
+    pub ipv4: [u8; 4],
+    pub subnet_mask: [u8; 4],
+    pub router: [u8; 4],
+    pub dns: [u8; 4],
+    pub dns_secondary: [u8; 4],
+    /// Server that granted the lease.
+    pub server: [u8; 4],
+    pub lease_secs: u32,
+    /// Renewal (T1) and rebinding (T2) times, from the start of the lease.
+    pub t1_secs: u32,
+    pub t2_secs: u32,
+    pub expires_in: u32,
+    pub renew_in: u32,
+    pub rebind_in: u32,
+    /// Leases extended since the first one was bound.
+    pub renewals: u32,
+    /// One of `NET_LEASE_*`.
+    pub state: u8,
+    pub link_up: u8,
     pub _pad: [u8; 2],
 }
 
+const _: () = assert!(
+    core::mem::size_of::<NetLease>() == 56,
+    "NetLease must be exactly 56 bytes"
+);
+
 pub const USER_NET_MEMBER_FLAG_ARP: u16 = 1 << 0;
 pub const USER_NET_MEMBER_FLAG_IPV4: u16 = 1 << 1;
 
@@ -36,6 +95,16 @@ pub const AF_INET: u16 = 2;
 pub const SOCK_STREAM: u16 = 1;
 /// Socket type: datagram (UDP).
 pub const SOCK_DGRAM: u16 = 2;
+/// Socket type: raw IPv4 datagrams of one protocol, header included on
+/// receive.  Needs [`TASK_FLAG_NET_RAW`](crate::task::TASK_FLAG_NET_RAW).
+pub const SOCK_RAW: u16 = 3;
+
+/// Largest listen backlog; larger values passed to `listen` are clamped.
+pub const SOMAXCONN: u32 = 128;
+
+/// Raw socket protocol that only sends, always with `IP_HDRINCL`, and
+/// receives nothing.
+pub const IPPROTO_RAW: u16 = 255;
 
 /// IPv4 socket address — mirrors POSIX `sockaddr_in` layout.
 #[repr(C)]
@@ -54,8 +123,267 @@ const _: () = assert!(
     "SockAddrIn must be exactly 16 bytes"
 );
 
+/// `SO_LINGER` value — mirrors POSIX `struct linger`.
+///
+/// With `l_onoff` set, a blocking `close` of a TCP socket waits up to
+/// `l_linger` seconds for the peer to acknowledge everything sent; a zero
+/// timeout resets the connection instead of closing it gracefully.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Linger {
+    pub l_onoff: i32,
+    /// Timeout in seconds.
+    pub l_linger: i32,
+}
+
+/// `IP_ADD_MEMBERSHIP` / `IP_DROP_MEMBERSHIP` value — mirrors POSIX
+/// `struct ip_mreq`.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct IpMreq {
+    /// Multicast group address.
+    pub imr_multiaddr: [u8; 4],
+    /// Local interface address; `0.0.0.0` picks the default interface.
+    pub imr_interface: [u8; 4],
+}
+
+/// Packet filter direction bits ([`NetFilterRule::direction`]).
+pub const NET_FILTER_IN: u8 = 1 << 0;
+pub const NET_FILTER_OUT: u8 = 1 << 1;
+
+/// Packet filter verdicts ([`NetFilterRule::action`] and policies).
+pub const NET_FILTER_ACCEPT: u8 = 0;
+pub const NET_FILTER_DROP: u8 = 1;
+
+/// [`NetFilterRule::proto`] value that matches every protocol.
+pub const NET_FILTER_PROTO_ANY: u8 = 0;
+
+/// Maximum number of packet filter rules.
+pub const NET_FILTER_MAX_RULES: usize = 32;
+
+/// `SYSCALL_NET_FILTER` commands (arg0).
+///
+/// * `NET_FILTER_OP_APPEND`: arg1 points to a [`NetFilterRule`]; returns its index
+/// * `NET_FILTER_OP_DELETE`: arg1 is a rule index; later rules move up
+/// * `NET_FILTER_OP_FLUSH`: remove every rule and reset the counters
+/// * `NET_FILTER_OP_LIST`: arg1 points to an array of arg2 rules; returns
+///   the number written
+/// * `NET_FILTER_OP_POLICY`: arg1 is a direction, arg2 the verdict for
+///   packets no rule matches
+/// * `NET_FILTER_OP_STATUS`: arg1 points to a [`NetFilterStatus`] to fill
+pub const NET_FILTER_OP_APPEND: u64 = 0;
+pub const NET_FILTER_OP_DELETE: u64 = 1;
+pub const NET_FILTER_OP_FLUSH: u64 = 2;
+pub const NET_FILTER_OP_LIST: u64 = 3;
+pub const NET_FILTER_OP_POLICY: u64 = 4;
+pub const NET_FILTER_OP_STATUS: u64 = 5;
+
+/// One packet filter rule.  Rules are tried in order; the first match
+/// decides.
+///
+/// The address is the remote end (source on input, destination on output);
+/// the port range applies to the destination port of TCP and UDP packets.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetFilterRule {
+    /// `NET_FILTER_IN`, `NET_FILTER_OUT`, or both.
+    pub direction: u8,
+    /// `NET_FILTER_ACCEPT` or `NET_FILTER_DROP`.
+    pub action: u8,
+    /// IP protocol number, or `NET_FILTER_PROTO_ANY`.
+    pub proto: u8,
+    /// CIDR prefix length of `addr`; 0 matches every address.
+    pub prefix_len: u8,
+    pub addr: [u8; 4],
+    /// Inclusive port range in host byte order; `0..=0` matches every port.
+    pub port_min: u16,
+    pub port_max: u16,
+    pub _pad: [u8; 4],
+    /// Packets matched so far (filled in by `NET_FILTER_OP_LIST`).
+    pub hits: u64,
+}
+
+/// Filter state returned by `NET_FILTER_OP_STATUS`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetFilterStatus {
+    pub rule_count: u32,
+    /// Verdict for input packets no rule matches.
+    pub policy_in: u8,
+    /// Verdict for output packets no rule matches.
+    pub policy_out: u8,
+    pub _pad: [u8; 2],
+    pub dropped_in: u64,
+    pub dropped_out: u64,
+}
+
+/// Largest echo payload `SYSCALL_NET_PING` sends (1500-byte MTU, no
+/// fragmentation).
+pub const NET_PING_MAX_PAYLOAD: u16 = 1472;
+
+/// One ICMP echo request for `SYSCALL_NET_PING`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetPingRequest {
+    /// Destination IPv4 address.
+    pub addr: [u8; 4],
+    /// Echo identifier; 0 lets the kernel pick one.
+    pub ident: u16,
+    /// Echo sequence number.
+    pub seq: u16,
+    /// Data bytes after the ICMP header (the classic default is 56).
+    pub payload_len: u16,
+    pub _pad: [u8; 2],
+    /// How long to wait for the reply, in milliseconds.
+    pub timeout_ms: u32,
+}
+
+/// The echo reply `SYSCALL_NET_PING` waited for.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetPingReply {
+    /// Round-trip time in microseconds.
+    pub rtt_us: u64,
+    /// Data bytes in the reply.
+    pub payload_len: u16,
+    /// TTL of the reply datagram.
+    pub ttl: u8,
+    pub _pad: [u8; 5],
+}
+
+/// `SYSCALL_RESOLVE` flag: start the lookup and return -EAGAIN rather than
+/// wait for it.  Repeating the call with the same name returns the answer
+/// once it has arrived.
+pub const NET_RESOLVE_NONBLOCK: u64 = 1 << 0;
+
+/// Maximum number of routes `NET_ROUTE_OP_LIST` copies out.
+pub const NET_ROUTE_MAX_ROUTES: usize = 32;
+
+/// [`NetRoute::dev`] value asking the kernel to pick the interface whose
+/// connected subnet holds the gateway.
+pub const NET_ROUTE_DEV_AUTO: u8 = u8::MAX;
+
+/// `SYSCALL_NET_ROUTE` commands (arg0).
+///
+/// * `NET_ROUTE_OP_ADD`: arg1 points to a [`NetRoute`]; an existing route
+///   for the same prefix and interface is replaced
+/// * `NET_ROUTE_OP_DEL`: arg1 points to a [`NetRoute`]; removes the route
+///   for its prefix, on its interface unless that is `NET_ROUTE_DEV_AUTO`
+/// * `NET_ROUTE_OP_LIST`: arg1 points to an array of arg2 routes; returns
+///   the number written
+pub const NET_ROUTE_OP_ADD: u64 = 0;
+pub const NET_ROUTE_OP_DEL: u64 = 1;
+pub const NET_ROUTE_OP_LIST: u64 = 2;
+
+/// One IPv4 routing table entry.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetRoute {
+    /// Network prefix; host bits beyond `prefix_len` must be zero.
+    pub prefix: [u8; 4],
+    /// Next hop, or `0.0.0.0` for a directly connected route.
+    pub gateway: [u8; 4],
+    /// Lower is preferred among routes of the same prefix length.
+    pub metric: u32,
+    pub prefix_len: u8,
+    /// Interface index (0 is loopback), or `NET_ROUTE_DEV_AUTO`.
+    pub dev: u8,
+    pub _pad: [u8; 2],
+}
+
+const _: () = assert!(
+    core::mem::size_of::<NetRoute>() == 16,
+    "NetRoute must be exactly 16 bytes"
+);
+
 /// Maximum number of kernel sockets (shared across all processes).
 pub const MAX_SOCKETS: usize = 64;
 
+/// [`NetSocketStat::proto`] values (IP protocol numbers).
+pub const NET_SOCK_PROTO_TCP: u8 = 6;
+pub const NET_SOCK_PROTO_UDP: u8 = 17;
+pub const NET_SOCK_PROTO_RAW: u8 = 255;
+
+/// [`NetSocketStat::state`] values.  A TCP socket with a connection
+/// reports the connection's RFC 793 state; a connected UDP socket reports
+/// `NET_SOCK_ESTABLISHED`.
+pub const NET_SOCK_UNBOUND: u8 = 0;
+pub const NET_SOCK_BOUND: u8 = 1;
+pub const NET_SOCK_LISTEN: u8 = 2;
+pub const NET_SOCK_SYN_SENT: u8 = 3;
+pub const NET_SOCK_SYN_RECEIVED: u8 = 4;
+pub const NET_SOCK_ESTABLISHED: u8 = 5;
+pub const NET_SOCK_FIN_WAIT1: u8 = 6;
+pub const NET_SOCK_FIN_WAIT2: u8 = 7;
+pub const NET_SOCK_CLOSE_WAIT: u8 = 8;
+pub const NET_SOCK_CLOSING: u8 = 9;
+pub const NET_SOCK_LAST_ACK: u8 = 10;
+pub const NET_SOCK_TIME_WAIT: u8 = 11;
+pub const NET_SOCK_CLOSED: u8 = 12;
+
+/// Name of a `NET_SOCK_*` state as `netstat` prints it.
+pub fn net_sock_state_name(state: u8) -> &'static str {
+    match state {
+        NET_SOCK_UNBOUND => "UNBOUND",
+        NET_SOCK_BOUND => "BOUND",
+        NET_SOCK_LISTEN => "LISTEN",
+        NET_SOCK_SYN_SENT => "SYN_SENT",
+        NET_SOCK_SYN_RECEIVED => "SYN_RECV",
+        NET_SOCK_ESTABLISHED => "ESTABLISHED",
+        NET_SOCK_FIN_WAIT1 => "FIN_WAIT1",
+        NET_SOCK_FIN_WAIT2 => "FIN_WAIT2",
+        NET_SOCK_CLOSE_WAIT => "CLOSE_WAIT",
+        NET_SOCK_CLOSING => "CLOSING",
+        NET_SOCK_LAST_ACK => "LAST_ACK",
+        NET_SOCK_TIME_WAIT => "TIME_WAIT",
+        NET_SOCK_CLOSED => "CLOSED",
+        _ => "?",
+    }
+}
+
+/// [`NetSocketStat::flags`] bits.
+pub const NET_SOCK_FLAG_NONBLOCK: u8 = 1 << 0;
+pub const NET_SOCK_FLAG_SHUT_RD: u8 = 1 << 1;
+pub const NET_SOCK_FLAG_SHUT_WR: u8 = 1 << 2;
+
+/// One kernel socket, as listed by `SYSCALL_NET_STAT`.
+///
+/// Ports are in host byte order.  For a listening socket `recv_queue`
+/// counts connections waiting for `accept` and `backlog` is the listen
+/// backlog; otherwise the queues are in bytes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetSocketStat {
+    /// Kernel socket index.
+    pub index: u32,
+    /// `NET_SOCK_PROTO_*`.
+    pub proto: u8,
+    /// `NET_SOCK_*` state.
+    pub state: u8,
+    /// `NET_SOCK_FLAG_*` bits.
+    pub flags: u8,
+    pub _pad: u8,
+    pub local_addr: [u8; 4],
+    pub remote_addr: [u8; 4],
+    pub local_port: u16,
+    pub remote_port: u16,
+    /// Received data not yet read by the application.
+    pub recv_queue: u32,
+    /// TCP data written but not yet acknowledged by the peer.
+    pub send_queue: u32,
+    pub backlog: u32,
+    /// Payload bytes read by the application.
+    pub rx_bytes: u64,
+    /// Payload bytes accepted from the application.
+    pub tx_bytes: u64,
+    /// Datagrams discarded because the receive queue was full.
+    pub rx_dropped: u64,
+}
+
+const _: () = assert!(
+    core::mem::size_of::<NetSocketStat>() == 56,
+    "NetSocketStat must be exactly 56 bytes"
+);
+
 /// Socket descriptor index indicating "no socket".
 pub const INVALID_SOCKET_IDX: u32 = u32::MAX;
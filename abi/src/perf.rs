@@ -0,0 +1,71 @@
+//! Hardware performance counters, as `SYSCALL_PERF_OPEN` and the `perf`
+//! builtin name them.
+//!
+//! A counter counts one event for one task, and optionally for the tasks
+//! it spawns or forks afterwards.  The kernel saves and restores the
+//! hardware counters on every context switch, so a counter only sees its
+//! own tasks however many share the CPU.
+
+/// Core clock cycles while not halted.
+pub const PERF_EVENT_CYCLES: u32 = 0;
+/// Instructions retired.
+pub const PERF_EVENT_INSTRUCTIONS: u32 = 1;
+/// Last-level cache references.
+pub const PERF_EVENT_CACHE_REFERENCES: u32 = 2;
+/// Last-level cache misses.
+pub const PERF_EVENT_CACHE_MISSES: u32 = 3;
+/// Branch instructions retired.
+pub const PERF_EVENT_BRANCHES: u32 = 4;
+/// Mispredicted branch instructions retired.
+pub const PERF_EVENT_BRANCH_MISSES: u32 = 5;
+
+/// Names of the events, by event number.
+pub const PERF_EVENT_NAMES: [&str; 6] = [
+    "cycles",
+    "instructions",
+    "cache-references",
+    "cache-misses",
+    "branches",
+    "branch-misses",
+];
+
+pub const PERF_EVENT_COUNT: usize = PERF_EVENT_NAMES.len();
+
+/// `SYSCALL_PERF_OPEN` flag: also count tasks the target spawns or forks
+/// after the counter is opened, and theirs in turn.
+pub const PERF_FLAG_INHERIT: u64 = 1 << 0;
+/// Count only the tasks `PERF_FLAG_INHERIT` adds, not the target itself;
+/// implies `PERF_FLAG_INHERIT`.  `perf stat` opens its counters this way
+/// on the shell before running the command.
+pub const PERF_FLAG_CHILDREN_ONLY: u64 = 1 << 1;
+/// Count user mode only, not the kernel working on the task's behalf.
+pub const PERF_FLAG_USER_ONLY: u64 = 1 << 2;
+
+pub const PERF_FLAGS_ALL: u64 = PERF_FLAG_INHERIT | PERF_FLAG_CHILDREN_ONLY | PERF_FLAG_USER_ONLY;
+
+/// Counters open at once across the system.
+pub const PERF_MAX_COUNTERS: usize = 32;
+
+/// A counter's value, returned by `SYSCALL_PERF_READ`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PerfReading {
+    pub value: u64,
+    /// A `PERF_EVENT_*` value.
+    pub event: u32,
+    /// Tasks counted so far: the target and any it passed the counter to.
+    pub tasks: u32,
+}
+
+/// Event number of `name`.
+pub fn perf_event_from_name(name: &[u8]) -> Option<u32> {
+    PERF_EVENT_NAMES
+        .iter()
+        .position(|n| n.as_bytes() == name)
+        .map(|idx| idx as u32)
+}
+
+/// Name of event `event`, `"?"` for anything else.
+pub fn perf_event_name(event: u32) -> &'static str {
+    PERF_EVENT_NAMES.get(event as usize).copied().unwrap_or("?")
+}
@@ -97,6 +97,20 @@ impl PixelFormat {
         })
     }
 
+    /// Decode a native pixel back into an opaque `Color32`; inverse of
+    /// [`encode`](Self::encode) with alpha forced to 0xFF.
+    #[inline]
+    pub fn decode(self, px: EncodedPixel) -> Color32 {
+        let v = px.0;
+        let (r, g, b) = match self {
+            Self::Argb8888 | Self::Xrgb8888 | Self::Rgb888 => (v >> 16, v >> 8, v),
+            Self::Rgba8888 => (v >> 24, v >> 16, v >> 8),
+            Self::Bgra8888 => (v >> 8, v >> 16, v >> 24),
+            Self::Bgr888 => (v, v >> 8, v >> 16),
+        };
+        Color32::rgb(r as u8, g as u8, b as u8)
+    }
+
     /// Get a bitmap of all supported formats
     ///
     /// Returns a u32 where bit N is set if format with value N is supported.
@@ -0,0 +1,69 @@
+//! Timed presentation: clients queue buffers with a target time and the
+//! kernel shows each one on the refresh closest to it.
+//!
+//! Composited surfaces latch their next buffer when the compositor drains
+//! its queue at the start of a frame.  Display-exclusive tasks can pass
+//! [`PRESENT_FLAG_BYPASS`] to have the kernel copy the buffer straight to
+//! the scanout instead, with no compositor in the loop.
+
+use crate::syscall::{ERRNO_EAGAIN, ERRNO_EINVAL, ERRNO_ENOENT, ERRNO_EPERM};
+
+/// Buffers a task may have queued at once.
+pub const PRESENT_QUEUE_DEPTH: usize = 4;
+
+/// Default refresh period, until `video.refresh_hz=` sets another.  The
+/// kernel's vblank timer, the bypass pacer and timed presents all run on a
+/// grid of the refresh period.
+pub const PRESENT_REFRESH_NS: u64 = 16_666_667;
+
+/// Scan out directly, skipping the compositor.  Display-exclusive tasks
+/// only; the buffer must cover the whole framebuffer.
+pub const PRESENT_FLAG_BYPASS: u32 = 1 << 0;
+pub const PRESENT_FLAGS_ALL: u32 = PRESENT_FLAG_BYPASS;
+
+/// Presentation statistics returned by `SYSCALL_PRESENT_FEEDBACK`.
+///
+/// A queued buffer may be reused once `presented_seq` is past its
+/// sequence number: it has either been replaced on screen or dropped.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct PresentFeedback {
+    /// Sequence number of the buffer now on screen, 0 if none yet.
+    pub presented_seq: u64,
+    /// `CLOCK_MONOTONIC` time that buffer reached the screen.
+    pub present_time_ns: u64,
+    /// Buffers shown so far.
+    pub presented: u64,
+    /// Buffers skipped because a later one was due on the same refresh.
+    pub dropped: u64,
+    pub refresh_ns: u64,
+    /// Buffers still waiting for their target time.
+    pub queued: u32,
+    pub _reserved: u32,
+}
+
+/// Reasons `SYSCALL_SURFACE_PRESENT` rejects a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresentError {
+    /// Composited present without an attached surface.
+    SurfaceNotFound,
+    /// Not a buffer of the caller's, or too small for the target.
+    InvalidToken,
+    /// Unknown flags, or a target earlier than the last queued one.
+    InvalidArgument,
+    /// Bypass requested by a task that is not display-exclusive.
+    PermissionDenied,
+    /// [`PRESENT_QUEUE_DEPTH`] buffers pending and none retired in time.
+    QueueFull,
+}
+
+impl PresentError {
+    pub const fn errno(self) -> u64 {
+        match self {
+            Self::SurfaceNotFound => ERRNO_ENOENT,
+            Self::InvalidToken | Self::InvalidArgument => ERRNO_EINVAL,
+            Self::PermissionDenied => ERRNO_EPERM,
+            Self::QueueFull => ERRNO_EAGAIN,
+        }
+    }
+}
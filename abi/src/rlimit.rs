@@ -0,0 +1,43 @@
+//! Resource limits, as `SYSCALL_GETRLIMIT` and `SYSCALL_SETRLIMIT` take
+//! them.
+//!
+//! Only [`RLIMIT_CORE`] exists so far.  Limits are per task: fork, clone
+//! and spawn copy them and exec keeps them.  Anyone may lower a limit or
+//! move the soft limit up to the hard one; raising the hard limit takes
+//! root.
+
+/// Largest core file, in bytes, the kernel writes when the task dies of a
+/// CPU fault.  0 disables core dumps; it is the default.
+pub const RLIMIT_CORE: u32 = 4;
+
+/// No limit.
+pub const RLIM_INFINITY: u64 = u64::MAX;
+
+/// Core files are named `core.<pid>` and written to the task's working
+/// directory, or [`CORE_FALLBACK_DIR`] if that fails.
+pub const CORE_FILE_PREFIX: &[u8] = b"core.";
+pub const CORE_FALLBACK_DIR: &[u8] = b"/tmp";
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RLimit {
+    /// Soft limit, the one enforced.
+    pub cur: u64,
+    /// Ceiling for `cur`.
+    pub max: u64,
+}
+
+impl RLimit {
+    pub const fn new(cur: u64, max: u64) -> Self {
+        Self { cur, max }
+    }
+
+    /// What a task starts with: no core dumps, but free to enable them.
+    pub const CORE_DEFAULT: Self = Self::new(0, RLIM_INFINITY);
+
+    /// Whether a task holding `self` may switch to `new`; `privileged`
+    /// tasks may also raise the hard limit.
+    pub const fn may_set(&self, new: &RLimit, privileged: bool) -> bool {
+        new.cur <= new.max && (privileged || new.max <= self.max)
+    }
+}
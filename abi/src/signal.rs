@@ -34,6 +34,8 @@ pub const SIGTSTP: u8 = 20;
 pub const SIGTTIN: u8 = 21;
 pub const SIGTTOU: u8 = 22;
 pub const SIGWINCH: u8 = 28;
+/// Bad system call: sent by a task's syscall filter.
+pub const SIGSYS: u8 = 31;
 
 // =============================================================================
 // Signal set — bitmask of up to 32 signals
@@ -3,7 +3,23 @@
 /// Window state constants
 pub const WINDOW_STATE_NORMAL: u8 = 0;
 pub const WINDOW_STATE_MINIMIZED: u8 = 1;
+/// Fills the work area of its output.
 pub const WINDOW_STATE_MAXIMIZED: u8 = 2;
+/// Fills the left half of the work area of its output.
+pub const WINDOW_STATE_TILED_LEFT: u8 = 3;
+/// Fills the right half of the work area of its output.
+pub const WINDOW_STATE_TILED_RIGHT: u8 = 4;
+pub const WINDOW_STATE_MAX: u8 = WINDOW_STATE_TILED_RIGHT;
+
+/// Whether `state` sizes the window to its output, so leaving it returns
+/// the window to the geometry it had before.
+#[inline]
+pub const fn window_state_is_fitted(state: u8) -> bool {
+    matches!(
+        state,
+        WINDOW_STATE_MAXIMIZED | WINDOW_STATE_TILED_LEFT | WINDOW_STATE_TILED_RIGHT
+    )
+}
 
 /// Maximum number of child subsurfaces per surface
 pub const MAX_CHILDREN: usize = 8;
@@ -24,6 +40,9 @@ pub enum SurfaceRole {
     Popup = 2,
     /// Subsurface (child surface positioned relative to parent)
     Subsurface = 3,
+    /// Off-screen buffer, never composited itself; other tasks can still
+    /// render thumbnails of it
+    Offscreen = 4,
 }
 
 impl SurfaceRole {
@@ -35,6 +54,7 @@ impl SurfaceRole {
             1 => Some(Self::Toplevel),
             2 => Some(Self::Popup),
             3 => Some(Self::Subsurface),
+            4 => Some(Self::Offscreen),
             _ => None,
         }
     }
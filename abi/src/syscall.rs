@@ -76,7 +76,7 @@ pub const SYSCALL_REBOOT: u64 = 85;
 /// Query a high-resolution clock.
 ///
 /// # Arguments (via registers)
-/// * rdi (arg0): clock ID (`CLOCK_MONOTONIC` = 0)
+/// * rdi (arg0): clock ID (`CLOCK_MONOTONIC` or `CLOCK_REALTIME`)
 /// * rsi (arg1): pointer to [`Timespec`] output struct
 ///
 /// # Returns
@@ -91,6 +91,8 @@ pub const SYSCALL_CLOCK_GETTIME: u64 = 125;
 
 /// Monotonic clock — nanoseconds since boot, never adjusted.
 pub const CLOCK_MONOTONIC: u64 = 0;
+/// Wall clock — UTC since the Unix epoch, anchored from the RTC at boot.
+pub const CLOCK_REALTIME: u64 = 1;
 
 // =============================================================================
 // Window management
@@ -110,13 +112,66 @@ pub const SYSCALL_INPUT_POLL_BATCH: u64 = 34;
 pub const SYSCALL_INPUT_POLL: u64 = 60;
 pub const SYSCALL_INPUT_HAS_EVENTS: u64 = 61;
 pub const SYSCALL_INPUT_SET_FOCUS: u64 = 62;
+/// Give a window pointer focus (compositor only).  Pointer positions sent
+/// to it are made window-local: less the offset, divided by the scale.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): task ID
+/// * rsi (arg1): window x on the desktop
+/// * rdx (arg2): window y on the desktop
+/// * r10 (arg3): desktop pixels per buffer pixel; 0 reads as 1
 pub const SYSCALL_INPUT_SET_FOCUS_WITH_OFFSET: u64 = 65;
 pub const SYSCALL_INPUT_GET_POINTER_POS: u64 = 66;
 pub const SYSCALL_INPUT_GET_BUTTON_STATE: u64 = 67;
 pub const SYSCALL_INPUT_REQUEST_CLOSE: u64 = 84;
+
+/// Ask a task to resize its window (compositor only).  The task gets an
+/// `InputEventType::Configure` event and answers by attaching a buffer of
+/// the new size with `SYSCALL_SURFACE_ATTACH`.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): target task ID
+/// * rsi (arg1): new width in pixels
+/// * rdx (arg2): new height in pixels
+///
+/// # Returns
+/// * 0 on success
+/// * Negative value for a bad task or an empty size
+pub const SYSCALL_INPUT_REQUEST_CONFIGURE: u64 = 160;
+/// Put `text/plain` on the clipboard; shorthand for `SYSCALL_CLIPBOARD_SET`
+/// that truncates to `CLIPBOARD_MAX_SIZE` and returns the bytes stored.
 pub const SYSCALL_CLIPBOARD_COPY: u64 = 116;
+/// Read `text/plain` from the clipboard; returns the bytes copied, 0 when
+/// the clipboard is empty or holds another type.
 pub const SYSCALL_CLIPBOARD_PASTE: u64 = 117;
 
+/// Replace the clipboard contents with `data` of MIME type `mime`.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to the MIME type, e.g. `text/plain`
+/// * rsi (arg1): MIME type length, at most `CLIPBOARD_MIME_MAX`
+/// * rdx (arg2): pointer to the data
+/// * r10 (arg3): data length, at most `CLIPBOARD_MAX_SIZE`
+///
+/// # Returns
+/// * Number of bytes stored
+/// * `-EINVAL` for a malformed MIME type, `-ERANGE` for too much data
+pub const SYSCALL_CLIPBOARD_SET: u64 = 166;
+
+/// Read the clipboard if it holds MIME type `mime`.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to the MIME type wanted
+/// * rsi (arg1): MIME type length
+/// * rdx (arg2): buffer for the data
+/// * r10 (arg3): buffer capacity
+///
+/// # Returns
+/// * Length of the clipboard data, which may exceed the capacity; only
+///   the first `capacity` bytes are written
+/// * `-ENOENT` when the clipboard is empty or holds another type
+pub const SYSCALL_CLIPBOARD_GET: u64 = 167;
+
 // =============================================================================
 // Surface / Compositor
 // =============================================================================
@@ -133,6 +188,132 @@ pub const SYSCALL_SURFACE_SET_PARENT: u64 = 58;
 pub const SYSCALL_SURFACE_SET_REL_POS: u64 = 59;
 pub const SYSCALL_SURFACE_SET_TITLE: u64 = 63;
 
+/// Set how the caller's surface is blended onto what is under it.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): opacity, 0 (invisible) to 255 (opaque)
+/// * rsi (arg1): `SURFACE_BLEND_*` flags (see [`crate::window`])
+///
+/// # Returns
+/// * 0 on success
+/// * Negative value for an opacity above 255 or unknown flags
+pub const SYSCALL_SURFACE_SET_OPACITY: u64 = 159;
+
+/// Draw a scaled-down copy of a task's surface into a buffer the caller
+/// owns, fitted to a maximum size with the aspect ratio kept and packed
+/// at the thumbnail's width (4 bytes per pixel).  Works for off-screen
+/// surfaces too.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): task ID of the surface
+/// * rsi (arg1): shared memory token of the destination buffer
+/// * rdx (arg2): maximum width
+/// * r10 (arg3): maximum height
+///
+/// # Returns
+/// * `(width << 32) | height` of the thumbnail drawn
+/// * Negative value if the task has no surface or the buffer is too small
+///   or not the caller's
+pub const SYSCALL_SURFACE_THUMBNAIL: u64 = 168;
+
+/// Set the caller's window flags, e.g. to go without decorations.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `WINDOW_FLAG_*` flags (see [`crate::window`])
+///
+/// # Returns
+/// * 0 on success
+/// * Negative value for unknown flags
+pub const SYSCALL_SURFACE_SET_WINDOW_FLAGS: u64 = 171;
+
+/// Tell the compositor what scale the caller's buffer is drawn at.  A
+/// buffer drawn at a lower scale than the display's is magnified to match.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): buffer scale, 1 to `DisplayInfo::MAX_SCALE`
+///
+/// # Returns
+/// * 0 on success
+/// * Negative value for a scale out of range
+pub const SYSCALL_SURFACE_SET_BUFFER_SCALE: u64 = 172;
+
+/// Ask the compositor to reload the desktop wallpaper and its scaling
+/// mode from disk (see `setwallpaper`).
+///
+/// # Returns
+/// * 0
+pub const SYSCALL_WALLPAPER_RELOAD: u64 = 169;
+
+/// Collect a pending wallpaper reload request (compositor only).
+///
+/// # Returns
+/// * 1 if a reload was requested since the last call, else 0
+/// * -1: caller is not the compositor
+pub const SYSCALL_WALLPAPER_TAKE_RELOAD: u64 = 170;
+
+/// Block until the display's next vertical blank.  Vblanks are numbered
+/// from boot on a grid of the refresh period (`video.refresh_hz=`).
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): last vblank number the caller has seen; returns once a
+///   later one has happened.  0 waits for the next one.
+///
+/// # Returns
+/// * The number of the vblank that ended the wait
+pub const SYSCALL_WAIT_VBLANK: u64 = 161;
+
+/// Show an image on the display's hardware cursor plane (compositor only).
+/// Without a cursor plane the compositor draws the cursor itself.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to `CURSOR_IMAGE_BYTES` of premultiplied
+///   ARGB8888 (see [`crate::window`]), or 0 to hide the cursor
+/// * rsi (arg1): hotspot x within the image
+/// * rdx (arg2): hotspot y within the image
+///
+/// # Returns
+/// * 0 when the cursor plane shows the image
+/// * `-ENODEV` when the display has no cursor plane
+/// * Negative value for a bad pointer or hotspot
+pub const SYSCALL_CURSOR_SET_IMAGE: u64 = 162;
+
+/// Move the hardware cursor's hotspot to a screen position (compositor
+/// only).  Does nothing without a cursor plane.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): x
+/// * rsi (arg1): y
+pub const SYSCALL_CURSOR_MOVE: u64 = 163;
+
+/// List the display outputs and where each sits on the desktop.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to an array of [`crate::display::OutputInfo`]
+/// * rsi (arg1): capacity of the array
+///
+/// # Returns
+/// * Number of outputs, which may exceed the capacity; only the first
+///   `capacity` are written
+pub const SYSCALL_OUTPUT_ENUMERATE: u64 = 164;
+
+/// Configure an output, `xrandr`-style: move it on the desktop, check its
+/// mode and optionally make it primary.  Outputs may overlap, which
+/// mirrors the shared part.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): output ID
+/// * rsi (arg1): desktop x of the output's top left corner
+/// * rdx (arg2): desktop y
+/// * r10 (arg3): mode width, or 0 to keep the current mode
+/// * r8 (arg4): mode height, or 0 to keep the current mode
+/// * r9 (arg5): `OUTPUT_CONFIGURE_*` flags
+///
+/// # Returns
+/// * 0 on success
+/// * Negative value for an unknown output, a mode it cannot show, a
+///   position that leaves the desktop, or unknown flags
+pub const SYSCALL_OUTPUT_CONFIGURE: u64 = 165;
+
 // =============================================================================
 // Shared memory
 // =============================================================================
@@ -167,6 +348,48 @@ pub const SYSCALL_SHM_CREATE_WITH_FORMAT: u64 = 54;
 /// * positive task ID on success
 /// * negative `ExecError` code on failure
 pub const SYSCALL_SPAWN_PATH: u64 = 64;
+
+/// Spawn a new userspace task with an argument vector and environment.
+///
+/// The arrays use the same layout as `SYSCALL_EXEC`; the child starts
+/// with a copy of the caller's descriptors minus those marked `FD_CLOEXEC`.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to null-terminated path string
+/// * rsi (arg1): argv pointer -- null-terminated array of string pointers, or 0
+/// * rdx (arg2): envp pointer -- null-terminated array of `KEY=VALUE` pointers, or 0
+/// * r10 (arg3): task priority (`u8`)
+/// * r8  (arg4): task flags (`u16`, kernel enforces user-mode bit)
+///
+/// # Returns
+/// * positive task ID on success
+/// * negative `ExecError` code on failure
+pub const SYSCALL_SPAWNVE: u64 = 182;
+
+/// Read one of the caller's resource limits (see [`crate::rlimit`]).
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `RLIMIT_*` resource
+/// * rsi (arg1): pointer to an `RLimit` to fill in
+///
+/// # Returns
+/// * 0 on success
+/// * -EINVAL: unknown resource
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_GETRLIMIT: u64 = 183;
+
+/// Change one of the caller's resource limits (see [`crate::rlimit`]).
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `RLIMIT_*` resource
+/// * rsi (arg1): pointer to the new `RLimit`
+///
+/// # Returns
+/// * 0 on success
+/// * -EINVAL: unknown resource, or the soft limit is above the hard one
+/// * -EPERM: raising the hard limit without root
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_SETRLIMIT: u64 = 184;
 pub const SYSCALL_WAITPID: u64 = 68;
 pub const SYSCALL_TERMINATE_TASK: u64 = 69;
 
@@ -191,6 +414,12 @@ pub const SYSCALL_TERMINATE_TASK: u64 = 69;
 /// * -EFAULT: Invalid pointer
 pub const SYSCALL_EXEC: u64 = 70;
 
+/// Most argv entries `SYSCALL_EXEC` and `SYSCALL_SPAWNVE` accept, not
+/// counting the null terminator.
+pub const EXEC_MAX_ARGS: usize = 32;
+/// Most envp entries, not counting the null terminator.
+pub const EXEC_MAX_ENVS: usize = 32;
+
 // =============================================================================
 // Memory management
 // =============================================================================
@@ -229,6 +458,29 @@ pub const SYSCALL_GETGID: u64 = 89;
 pub const SYSCALL_GETEUID: u64 = 90;
 pub const SYSCALL_GETEGID: u64 = 91;
 
+/// Set the calling task's user ID.
+///
+/// With effective uid 0 this sets both the real and effective uid; any
+/// other task may only set its effective uid back to its real uid.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): new uid
+///
+/// # Returns
+/// * 0 on success
+/// * -EPERM: caller is not uid 0 and `uid` is not its real uid
+pub const SYSCALL_SETUID: u64 = 178;
+
+/// Set the calling task's group ID; same rules as `SYSCALL_SETUID`.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): new gid
+///
+/// # Returns
+/// * 0 on success
+/// * -EPERM: caller is not uid 0 and `gid` is not its real gid
+pub const SYSCALL_SETGID: u64 = 179;
+
 // =============================================================================
 // Filesystem process context
 // =============================================================================
@@ -271,6 +523,36 @@ pub const SYSCALL_GETCWD: u64 = 121;
 /// * -EFAULT: invalid pointer
 pub const SYSCALL_RENAME: u64 = 122;
 
+/// Change the permission bits of a file or directory.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to null-terminated path string
+/// * rsi (arg1): new mode; only the low 12 bits (`0o7777`) are used
+///
+/// # Returns
+/// * 0 on success
+/// * -EPERM: caller is neither the owner nor uid 0
+/// * -ENOENT: path not found
+/// * -ENOTSUP: filesystem doesn't keep permissions
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_CHMOD: u64 = 180;
+
+/// Change the owner and group of a file or directory.  Only uid 0 may
+/// call this.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to null-terminated path string
+/// * rsi (arg1): new uid, or `u32::MAX` to leave it
+/// * rdx (arg2): new gid, or `u32::MAX` to leave it
+///
+/// # Returns
+/// * 0 on success
+/// * -EPERM: caller is not uid 0
+/// * -ENOENT: path not found
+/// * -ENOTSUP: filesystem doesn't keep ownership
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_CHOWN: u64 = 181;
+
 // =============================================================================
 // Socket operations
 // =============================================================================
@@ -279,12 +561,14 @@ pub const SYSCALL_RENAME: u64 = 122;
 ///
 /// # Arguments (via registers)
 /// * rdi (arg0): domain (AF_INET = 2)
-/// * rsi (arg1): type (SOCK_STREAM = 1, SOCK_DGRAM = 2)
-/// * rdx (arg2): protocol (0 = auto-select)
+/// * rsi (arg1): type (SOCK_STREAM = 1, SOCK_DGRAM = 2, SOCK_RAW = 3)
+/// * rdx (arg2): protocol (0 = auto-select; for SOCK_RAW the IP protocol
+///   number, 1..=255)
 ///
 /// # Returns
 /// * File descriptor on success
-/// * Negative errno on failure
+/// * -EPERM: SOCK_RAW without `TASK_FLAG_NET_RAW`
+/// * Negative errno on other failures
 pub const SYSCALL_SOCKET: u64 = 126;
 
 /// Bind a socket to a local address.
@@ -301,9 +585,14 @@ pub const SYSCALL_BIND: u64 = 127;
 
 /// Mark a socket as listening for incoming connections.
 ///
+/// The backlog bounds the connections, half-open or complete, that wait
+/// for `accept()`; SYNs beyond it are dropped.  It is clamped to
+/// `1..=`[`SOMAXCONN`](crate::net::SOMAXCONN).  Listening again on a
+/// listening socket changes the backlog.
+///
 /// # Arguments (via registers)
 /// * rdi (arg0): socket file descriptor
-/// * rsi (arg1): backlog (ignored for now)
+/// * rsi (arg1): backlog
 ///
 /// # Returns
 /// * 0 on success
@@ -312,6 +601,9 @@ pub const SYSCALL_LISTEN: u64 = 128;
 
 /// Accept an incoming connection on a listening socket.
 ///
+/// Sleeps until a connection completes unless the socket is non-blocking;
+/// `SO_RCVTIMEO` bounds the wait.
+///
 /// # Arguments (via registers)
 /// * rdi (arg0): listening socket file descriptor
 /// * rsi (arg1): pointer to SockAddrIn for peer address (or 0)
@@ -368,20 +660,23 @@ pub const SYSCALL_RECVFROM: u64 = 134;
 /// * rdi (arg0): pointer to hostname bytes (not NUL-terminated)
 /// * rsi (arg1): hostname length in bytes
 /// * rdx (arg2): pointer to `[u8; 4]` output for resolved address
+/// * r10 (arg3): flags; `NET_RESOLVE_NONBLOCK` returns -EAGAIN instead of
+///   waiting while the lookup is in flight
 ///
 /// # Returns
 /// * 0 on success
-/// * -EHOSTUNREACH: DNS resolution failed
-/// * -ETIMEDOUT: DNS server did not respond
+/// * -EHOSTUNREACH: the name does not exist
+/// * -ETIMEDOUT: no DNS server answered
+/// * -EAGAIN: non-blocking lookup still in flight; call again to collect it
 /// * -EFAULT: invalid pointer
-/// * -EINVAL: hostname too long (>253 bytes)
+/// * -EINVAL: hostname too long (>253 bytes) or unknown flags
 pub const SYSCALL_RESOLVE: u64 = 135;
 
 /// Set a socket option.
 ///
 /// # Arguments (via registers)
 /// * rdi (arg0): socket file descriptor
-/// * rsi (arg1): option level (SOL_SOCKET, IPPROTO_TCP)
+/// * rsi (arg1): option level (SOL_SOCKET, IPPROTO_IP, IPPROTO_TCP)
 /// * rdx (arg2): option name (SO_REUSEADDR, SO_RCVBUF, etc.)
 /// * r10 (arg3): pointer to option value
 /// * r8  (arg4): option value length in bytes
@@ -416,12 +711,441 @@ pub const SYSCALL_GETSOCKOPT: u64 = 137;
 /// * Negative errno on failure
 pub const SYSCALL_SHUTDOWN: u64 = 138;
 
+/// Fill a buffer with bytes from the kernel CSPRNG.
+///
+/// Blocks only until the RNG has been seeded for the first time; after that
+/// it never blocks. `GRND_RANDOM` is accepted for compatibility and behaves
+/// like the default source.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to output buffer
+/// * rsi (arg1): buffer length in bytes (at most `GETRANDOM_MAX` per call)
+/// * rdx (arg2): flags (`GRND_NONBLOCK`, `GRND_RANDOM`)
+///
+/// # Returns
+/// * Number of bytes written on success
+/// * -EAGAIN: `GRND_NONBLOCK` and the RNG is not yet seeded
+/// * -EINVAL: unknown flags
+/// * -EFAULT: invalid buffer
+pub const SYSCALL_GETRANDOM: u64 = 139;
+
+/// getrandom flags (Linux-compatible values)
+pub const GRND_NONBLOCK: u64 = 0x1;
+pub const GRND_RANDOM: u64 = 0x2;
+
+/// Largest request served by a single getrandom call.
+pub const GETRANDOM_MAX: usize = 1 << 20;
+
+/// Read kernel warning counters and optionally change `panic_on_warn`.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to a `KwarnStats` to fill
+/// * rsi (arg1): `KWARN_PANIC_KEEP`, `KWARN_PANIC_OFF` or `KWARN_PANIC_ON`
+///
+/// # Returns
+/// * 0 on success; the returned stats reflect the knob after any change
+/// * -EINVAL: unknown knob value
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_KWARN_STATS: u64 = 140;
+
+/// `panic_on_warn` updates accepted by `SYSCALL_KWARN_STATS`.
+pub const KWARN_PANIC_KEEP: u64 = 0;
+pub const KWARN_PANIC_OFF: u64 = 1;
+pub const KWARN_PANIC_ON: u64 = 2;
+
+/// Read or change the kernel log level of a subsystem (see
+/// [`crate::klog`]).
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): a subsystem number, or `KLOG_MODULE_DEFAULT` for the
+///   default level
+/// * rsi (arg1): a level number, `KLOG_LEVEL_INHERIT` to have a subsystem
+///   follow the default again, or `KLOG_LEVEL_KEEP` to only read
+///
+/// # Returns
+/// * The setting after any change: a level number, or `KLOG_LEVEL_INHERIT`
+/// * -EINVAL: unknown subsystem or level, or `KLOG_LEVEL_INHERIT` for the
+///   default level
+pub const SYSCALL_KLOG_LEVEL: u64 = 173;
+
+/// Open a hardware performance counter (see [`crate::perf`]).
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): a `PERF_EVENT_*` event
+/// * rsi (arg1): task to count, or 0 for the caller; another task must be
+///   a child of the caller
+/// * rdx (arg2): `PERF_FLAG_*` bits
+///
+/// # Returns
+/// * A counter id for `SYSCALL_PERF_READ` and `SYSCALL_PERF_CLOSE`
+/// * -EINVAL: unknown event or flags
+/// * -ESRCH: no such task
+/// * -EPERM: the task is not the caller or its child
+/// * -EBUSY: every counter is in use, or the task already has as many
+///   events as the CPU can count at once
+/// * -EOPNOTSUPP: the CPU has no usable performance counters or cannot
+///   count the event
+pub const SYSCALL_PERF_OPEN: u64 = 174;
+
+/// Read a counter opened by the caller.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): counter id
+/// * rsi (arg1): pointer to a `PerfReading` to fill
+///
+/// # Returns
+/// * 0 on success
+/// * -EINVAL: not a counter the caller opened
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_PERF_READ: u64 = 175;
+
+/// Close a counter opened by the caller.  Counters are also closed when
+/// the task that opened them exits.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): counter id
+///
+/// # Returns
+/// * 0 on success
+/// * -EINVAL: not a counter the caller opened
+pub const SYSCALL_PERF_CLOSE: u64 = 176;
+
+/// Install a syscall filter on the caller (see [`crate::syscall_filter`]).
+/// It stays in place across exec and is inherited by children; a second
+/// filter stacks on the first.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to a `SyscallFilter`
+///
+/// # Returns
+/// * 0 on success
+/// * -EINVAL: unknown mode or action, or a nonzero reserved field
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_SET_SYSCALL_FILTER: u64 = 177;
+
+/// Control the kernel event tracer (ktrace), export its Chrome trace or
+/// read its events back.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `KTRACE_OP_*` command
+/// * rsi (arg1): for `KTRACE_OP_EXPORT_FILE`, pointer to a NUL-terminated
+///   absolute path; the file is created or truncated.  For
+///   `KTRACE_OP_READ`, pointer to an array of [`KtraceRecord`]
+/// * rdx (arg2): for `KTRACE_OP_READ`, capacity of the array
+/// * r10 (arg3): for `KTRACE_OP_READ`, index of the first event to copy,
+///   counting from the oldest
+///
+/// # Returns
+/// * Number of events exported for the export commands
+/// * Number of events recorded, whether copied or not, for `KTRACE_OP_READ`
+/// * 0 otherwise
+/// * -EINVAL: unknown command or bad path
+/// * -EBUSY: another export is in progress
+/// * -EIO: the trace file could not be written
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_KTRACE: u64 = 150;
+
+/// Commands accepted by `SYSCALL_KTRACE`.  Both exports stop tracing first.
+pub const KTRACE_OP_START: u64 = 0;
+pub const KTRACE_OP_STOP: u64 = 1;
+pub const KTRACE_OP_EXPORT_FILE: u64 = 2;
+pub const KTRACE_OP_EXPORT_SERIAL: u64 = 3;
+/// Stop tracing and copy events, oldest first and merged across CPUs.
+pub const KTRACE_OP_READ: u64 = 4;
+
+/// Event kinds in [`KtraceRecord::kind`].
+///
+/// | kind                 | `arg0`           | `arg1`          | `addr`        |
+/// |----------------------|------------------|-----------------|---------------|
+/// | `SCHED_SWITCH`       | outgoing task    | incoming task   |               |
+/// | `SYSCALL_ENTER/EXIT` | syscall number   | task            |               |
+/// | `IRQ_ENTER/EXIT`     | interrupt vector |                 |               |
+/// | `SCHED_WAKEUP`       | woken task       | waking task     |               |
+/// | `PAGE_FAULT`         | error code       | task            | fault address |
+pub const KTRACE_KIND_SCHED_SWITCH: u8 = 0;
+pub const KTRACE_KIND_SYSCALL_ENTER: u8 = 1;
+pub const KTRACE_KIND_SYSCALL_EXIT: u8 = 2;
+pub const KTRACE_KIND_IRQ_ENTER: u8 = 3;
+pub const KTRACE_KIND_IRQ_EXIT: u8 = 4;
+pub const KTRACE_KIND_SCHED_WAKEUP: u8 = 5;
+pub const KTRACE_KIND_PAGE_FAULT: u8 = 6;
+
+/// Task id in a [`KtraceRecord`] for "no task", e.g. a wakeup from an
+/// interrupt before the first task ran.
+pub const KTRACE_NO_TASK: u32 = u32::MAX;
+
+/// One traced event, as copied out by `KTRACE_OP_READ`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KtraceRecord {
+    /// Monotonic clock, nanoseconds since boot.
+    pub timestamp_ns: u64,
+    pub addr: u64,
+    pub arg0: u32,
+    pub arg1: u32,
+    /// A `KTRACE_KIND_*` value.
+    pub kind: u8,
+    pub cpu: u8,
+    pub _reserved: [u8; 6],
+}
+
+/// Read lock contention counters, one [`LockStat`] per lock class.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to an array of `LockStat`
+/// * rsi (arg1): capacity of the array (at most `LOCK_STATS_MAX_CLASSES`
+///   are filled)
+/// * rdx (arg2): flags; `LOCK_STATS_RESET` zeroes the counters after
+///   reading them
+///
+/// # Returns
+/// * Number of classes written
+/// * -EOPNOTSUPP: the kernel was built without the `lockstat` feature
+/// * -EINVAL: unknown flags
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_LOCK_STATS: u64 = 151;
+
+/// `SYSCALL_LOCK_STATS` flag: start a new measurement window after reading.
+pub const LOCK_STATS_RESET: u64 = 1 << 0;
+
+/// Read the kernel stack high-water mark of every live task, one
+/// [`TaskStackUsage`](crate::task::TaskStackUsage) per task.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to an array of `TaskStackUsage`
+/// * rsi (arg1): capacity of the array (at most `MAX_TASKS` are filled)
+///
+/// # Returns
+/// * Number of tasks written
+/// * -EOPNOTSUPP: stack tracking is compiled out (release builds)
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_TASK_STACK_USAGE: u64 = 152;
+
+/// Read the scheduler's busy and idle time of each online CPU, one
+/// [`CpuUsage`](crate::task::CpuUsage) per CPU.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to an array of `CpuUsage`
+/// * rsi (arg1): capacity of the array
+///
+/// # Returns
+/// * Number of CPUs written
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_CPU_USAGE: u64 = 185;
+
+/// Describe every live task in one call, one
+/// [`TaskSnapshot`](crate::task::TaskSnapshot) per task.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to an array of `TaskSnapshot`
+/// * rsi (arg1): capacity of the array
+///
+/// # Returns
+/// * Number of tasks written
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_TASK_SNAPSHOT: u64 = 186;
+
+/// Read the hardware inventory assembled at boot, one
+/// [`HwDevice`](crate::hw::HwDevice) per device.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to an array of `HwDevice`
+/// * rsi (arg1): capacity of the array (at most `HW_MAX_DEVICES` are filled)
+/// * rdx (arg2): an `HW_CLASS_*` value to list only that class, or 0 for all
+///
+/// # Returns
+/// * Number of devices written, in inventory order
+/// * -EINVAL: unknown class
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_HW_INVENTORY: u64 = 154;
+
+/// Query and configure the audit log of privileged operations.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `AUDIT_OP_*` command
+/// * rsi, rdx (arg1, arg2): command arguments (see [`crate::audit`])
+///
+/// # Returns
+/// * Command result (old mask, record count, or 0)
+/// * -EINVAL: unknown command or category bits
+/// * -EBUSY: a flush is already in progress
+/// * -EIO: the log files could not be written
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_AUDIT_CTL: u64 = 155;
+
+/// Block the compositor until it has work: a queued client operation,
+/// pointer input, or a timed present coming due.  Compositor only.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): timeout in milliseconds; 0 only consumes a pending wakeup,
+///   `COMPOSITOR_WAIT_FOREVER` never times out
+///
+/// # Returns
+/// * 1 if woken by work, 0 on timeout
+/// * -1: caller is not the compositor
+pub const SYSCALL_COMPOSITOR_WAIT: u64 = 156;
+
+/// List kernel sockets, one [`NetSocketStat`](crate::net::NetSocketStat)
+/// per socket, in socket index order.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to an array of `NetSocketStat`
+/// * rsi (arg1): capacity of the array
+/// * rdx (arg2): lowest socket index to report, to continue a listing
+///   from one past the last `index` returned
+///
+/// # Returns
+/// * Number of sockets written
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_NET_STAT: u64 = 157;
+
+/// Read the notice of a crash bundle (see [`crate::crash`]).
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): lowest bundle sequence number wanted
+/// * rsi (arg1): pointer to a [`CrashNotice`](crate::crash::CrashNotice)
+///
+/// # Returns
+/// * 1: the oldest retained notice with `seq` at or above arg0 was written
+/// * 0: no such notice
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_CRASH_NOTICE: u64 = 158;
+
+/// Push direct framebuffer writes (from a `MAP_FRAMEBUFFER` mapping) to the
+/// display.  A no-op on linear framebuffers; virtio-gpu needs it to
+/// transfer the backing to the host.  Display-exclusive tasks only.
+///
+/// # Returns
+/// * 0 on success
+/// * -1: caller is not display-exclusive, or the flush failed
+pub const SYSCALL_FB_FLUSH: u64 = 141;
+
+/// Queue PCM for playback (48 kHz s16le stereo, see `slopos_abi::audio`).
+/// Blocks while the kernel's ring is full.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to interleaved frames
+/// * rsi (arg1): length in bytes; a trailing partial frame is ignored
+///
+/// # Returns
+/// * Bytes queued; short only if playback stalled
+/// * -ENODEV: no sound device
+/// * -EINVAL: shorter than one frame
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_AUDIO_WRITE: u64 = 142;
+
+/// Query or control the audio output.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `AUDIO_CTL_*` command
+/// * rsi (arg1): command argument
+///
+/// # Returns
+/// * 0 on success
+/// * -ENODEV: no sound device (`AUDIO_CTL_INFO` still succeeds)
+/// * -EINVAL: unknown command or volume out of range
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_AUDIO_CTL: u64 = 143;
+
+/// Queue a buffer to be shown at a target time (see `slopos_abi::present`).
+/// Blocks while `PRESENT_QUEUE_DEPTH` buffers are already pending.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): SHM token of a buffer owned by the caller
+/// * rsi (arg1): target `CLOCK_MONOTONIC` time in ns; 0 for the next refresh
+/// * rdx (arg2): `PRESENT_FLAG_*`
+///
+/// # Returns
+/// * Sequence number of the queued buffer (starts at 1)
+/// * -ENOENT: no surface attached and `PRESENT_FLAG_BYPASS` not set
+/// * -EPERM: bypass requested by a task that is not display-exclusive
+/// * -EINVAL: bad token, buffer too small, unknown flags, or a target
+///   earlier than the previous one
+/// * -EAGAIN: the queue stayed full
+pub const SYSCALL_SURFACE_PRESENT: u64 = 144;
+
+/// Read the caller's presentation statistics.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to a `PresentFeedback` to fill
+///
+/// # Returns
+/// * 0 on success
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_PRESENT_FEEDBACK: u64 = 145;
+
+/// Select the keyboard layout used to translate PS/2 scancodes.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `KEYMAP_*` layout ID, or `KEYMAP_QUERY` to leave it unchanged
+///
+/// # Returns
+/// * ID of the layout that was active before the call
+/// * -EINVAL: unknown layout
+pub const SYSCALL_SET_KEYMAP: u64 = 146;
+
+/// Configure the IPv4 packet filter.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `NET_FILTER_OP_*` command
+/// * rsi, rdx (arg1, arg2): command arguments (see [`crate::net`])
+///
+/// # Returns
+/// * Command result (rule index, rule count, or 0)
+/// * -EINVAL: unknown command, malformed rule, or no rule at that index
+/// * -ENOBUFS: rule table full
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_NET_FILTER: u64 = 147;
+
+/// Send one ICMP echo request and wait for the reply.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to a [`crate::net::NetPingRequest`]
+/// * rsi (arg1): pointer to a [`crate::net::NetPingReply`] to fill
+///
+/// # Returns
+/// * 0 once the reply has arrived
+/// * -ETIMEDOUT: no reply within the timeout
+/// * -ENETUNREACH: no route to the address
+/// * -EPERM: the packet filter dropped the request
+/// * -EINVAL: payload too long, zero timeout, or unspecified address
+/// * -ENOBUFS: too many pings in flight
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_NET_PING: u64 = 148;
+
+/// Manage the IPv4 routing table.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): `NET_ROUTE_OP_*` command
+/// * rsi, rdx (arg1, arg2): command arguments (see [`crate::net`])
+///
+/// # Returns
+/// * Command result (route count for a list, otherwise 0)
+/// * -EINVAL: unknown command, malformed route, unknown interface, or no
+///   such route to delete
+/// * -ENETUNREACH: the gateway is not on a connected subnet
+/// * -ENOBUFS: too many routes of that prefix length
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_NET_ROUTE: u64 = 149;
+
+/// Query the DHCP lease of the network interface.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to a [`crate::net::NetLease`] to fill
+///
+/// # Returns
+/// * 0 on success; `state` is `NET_LEASE_NONE` when no client runs
+/// * -EFAULT: invalid pointer
+pub const SYSCALL_NET_LEASE: u64 = 153;
+
 // =============================================================================
 // Socket option constants
 // =============================================================================
 
 /// Socket option level: generic socket options.
 pub const SOL_SOCKET: i32 = 1;
+/// Socket option level: IPv4 options.
+pub const IPPROTO_IP: i32 = 0;
 /// Socket option level: TCP protocol options.
 pub const IPPROTO_TCP: i32 = 6;
 
@@ -429,12 +1153,16 @@ pub const IPPROTO_TCP: i32 = 6;
 pub const SO_REUSEADDR: i32 = 2;
 /// Retrieve and clear pending socket error.
 pub const SO_ERROR: i32 = 4;
+/// Allow sending to broadcast addresses (UDP only).
+pub const SO_BROADCAST: i32 = 6;
 /// Send buffer size in bytes.
 pub const SO_SNDBUF: i32 = 7;
 /// Receive buffer size in bytes.
 pub const SO_RCVBUF: i32 = 8;
 /// Enable keepalive probes.
 pub const SO_KEEPALIVE: i32 = 9;
+/// Close behaviour for unsent data (as [`Linger`](crate::net::Linger)).
+pub const SO_LINGER: i32 = 13;
 /// Receive timeout in milliseconds (as u64).
 pub const SO_RCVTIMEO: i32 = 20;
 /// Send timeout in milliseconds (as u64).
@@ -443,6 +1171,17 @@ pub const SO_SNDTIMEO: i32 = 21;
 /// Disable Nagle's algorithm (TCP only).
 pub const TCP_NODELAY: i32 = 1;
 
+/// Datagrams sent on a raw socket start with their own IPv4 header (as i32
+/// bool; always on for `IPPROTO_RAW`).  The kernel still fills in the total
+/// length and header checksum, and a zero source address.
+pub const IP_HDRINCL: i32 = 3;
+/// TTL of outgoing multicast datagrams (as i32, 0..=255; default 1).
+pub const IP_MULTICAST_TTL: i32 = 33;
+/// Join a multicast group (as [`IpMreq`](crate::net::IpMreq)).
+pub const IP_ADD_MEMBERSHIP: i32 = 35;
+/// Leave a multicast group (as [`IpMreq`](crate::net::IpMreq)).
+pub const IP_DROP_MEMBERSHIP: i32 = 36;
+
 // =============================================================================
 // Shutdown constants
 // =============================================================================
@@ -515,6 +1254,19 @@ pub const SYSCALL_SETPGID: u64 = 113;
 pub const SYSCALL_GETPGID: u64 = 114;
 pub const SYSCALL_SETSID: u64 = 115;
 
+/// Allocate a pseudo-terminal pair and open both ends, neither as the
+/// controlling terminal.  Window-size ioctls on the master act on the
+/// slave.
+///
+/// # Arguments (via registers)
+/// * rdi (arg0): pointer to `[i32; 2]`, filled with the master and slave fds
+/// * rsi (arg1): `O_CLOEXEC` and/or `O_NONBLOCK`, applied to both
+///
+/// # Returns
+/// * 0 on success
+/// * -1: bad flags, no free pty or no free fds
+pub const SYSCALL_OPENPTY: u64 = 187;
+
 // =============================================================================
 // mmap constants
 // =============================================================================
@@ -529,6 +1281,11 @@ pub const PROT_EXEC: u64 = 4;
 pub const MAP_PRIVATE: u64 = 0x02;
 pub const MAP_ANONYMOUS: u64 = 0x20;
 pub const MAP_FIXED: u64 = 0x10;
+/// SlopOS extension: map the display scanout itself, write-combined, in
+/// place of anonymous memory.  Only display-exclusive tasks may use it;
+/// `fd` must be -1 and `offset` is a page-aligned byte offset into the
+/// scanout.  `MAP_FIXED` is not supported.
+pub const MAP_FRAMEBUFFER: u64 = 0x4000_0000;
 
 // =============================================================================
 // fcntl constants
@@ -539,6 +1296,8 @@ pub const F_GETFD: u64 = 1;
 pub const F_SETFD: u64 = 2;
 pub const F_GETFL: u64 = 3;
 pub const F_SETFL: u64 = 4;
+/// Like `F_DUPFD`, but the new descriptor has `FD_CLOEXEC` set.
+pub const F_DUPFD_CLOEXEC: u64 = 1030;
 pub const FD_CLOEXEC: u64 = 1;
 
 pub const O_NONBLOCK: u64 = 0x800;
@@ -591,6 +1350,9 @@ pub const TIOCGWINSZ: u64 = 0x5413;
 pub const TIOCSWINSZ: u64 = 0x5414;
 pub const TIOCSCTTY: u64 = 0x540E;
 
+/// `/dev/fb0`: copy the current mode out as a [`crate::DisplayInfo`].
+pub const FBIOGET_VSCREENINFO: u64 = 0x4600;
+
 pub const N_TTY: u32 = 0;
 pub const N_RAW: u32 = 1;
 
@@ -853,6 +1615,7 @@ pub const ERRNO_EAGAIN: u64 = (-11i64) as u64;
 pub const ERRNO_ESRCH: u64 = (-3i64) as u64;
 pub const ERRNO_EFAULT: u64 = (-14i64) as u64;
 pub const ERRNO_ENOENT: u64 = (-2i64) as u64;
+pub const ERRNO_ENODEV: u64 = (-19i64) as u64;
 pub const ERRNO_ENOTDIR: u64 = (-20i64) as u64;
 pub const ERRNO_ERANGE: u64 = (-34i64) as u64;
 pub const ERRNO_ETIMEDOUT: u64 = (-110i64) as u64;
@@ -874,13 +1637,16 @@ pub const ERRNO_EINPROGRESS: u64 = (-115i64) as u64;
 pub const ERRNO_EOPNOTSUPP: u64 = (-95i64) as u64;
 pub const ERRNO_EPIPE: u64 = (-32i64) as u64;
 pub const ERRNO_EPERM: u64 = (-1i64) as u64;
+pub const ERRNO_EACCES: u64 = (-13i64) as u64;
+pub const ERRNO_EBUSY: u64 = (-16i64) as u64;
+pub const ERRNO_EIO: u64 = (-5i64) as u64;
 
 // =============================================================================
 // Syscall ABI stability
 // =============================================================================
 
 /// Total size of the dispatch table. All syscall numbers must be below this.
-pub const SYSCALL_TABLE_SIZE: usize = 139;
+pub const SYSCALL_TABLE_SIZE: usize = 188;
 
 /// Standard return value for unimplemented syscalls: -ENOSYS (negated errno 38).
 pub const ENOSYS_RETURN: u64 = (-38i64) as u64;
@@ -906,6 +1672,42 @@ pub struct UserSysInfo {
     pub wl_balance: i64,
 }
 
+/// Kernel warning counters returned by `SYSCALL_KWARN_STATS`.
+#[repr(C)]
+#[derive(Default, Copy, Clone)]
+pub struct KwarnStats {
+    /// `kwarn!`/`kwarn_once!` conditions that fired, logged or not.
+    pub warnings: u64,
+    /// Hits swallowed by rate limiting or once-only sites.
+    pub suppressed: u64,
+    /// `kbug!` conditions that fired.
+    pub bugs: u64,
+    /// Non-zero when warnings escalate to a panic.
+    pub panic_on_warn: u32,
+    pub _reserved: u32,
+}
+
+/// Bytes in [`LockStat::name`], including the NUL terminator.
+pub const LOCK_CLASS_NAME_LEN: usize = 24;
+
+/// Most lock classes `SYSCALL_LOCK_STATS` reports in one call.
+pub const LOCK_STATS_MAX_CLASSES: usize = 32;
+
+/// Contention counters of one lock class, returned by `SYSCALL_LOCK_STATS`.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LockStat {
+    /// NUL-terminated class name.
+    pub name: [u8; LOCK_CLASS_NAME_LEN],
+    pub acquisitions: u64,
+    /// Acquisitions that found the lock held and had to spin.
+    pub contended: u64,
+    /// Time spent spinning, summed over contended acquisitions.
+    pub total_wait_ns: u64,
+    pub max_wait_ns: u64,
+    pub total_held_ns: u64,
+}
+
 /// POSIX-style timespec returned by `SYSCALL_CLOCK_GETTIME`.
 #[repr(C)]
 #[derive(Default, Copy, Clone)]
@@ -0,0 +1,140 @@
+//! Per-task syscall filters, as `SYSCALL_SET_SYSCALL_FILTER` takes them.
+//!
+//! A filter is a bitmap of syscall numbers plus a mode saying whether the
+//! marked syscalls are the only ones allowed or the ones refused.  A task
+//! installs a filter on itself, typically between fork and exec, and keeps
+//! it across exec; children inherit it on fork, clone and spawn.
+//!
+//! Filters stack: installing another one can only take syscalls away.  A
+//! call refused by any kill filter kills the task; one refused only by
+//! errno filters returns the errno of the first errno filter installed.
+//! `SYSCALL_EXIT` and `SYSCALL_RT_SIGRETURN` are always allowed.
+
+use crate::syscall::{
+    SYSCALL_ACCEPT, SYSCALL_BIND, SYSCALL_CHDIR, SYSCALL_CONNECT, SYSCALL_EXEC, SYSCALL_FS_LIST,
+    SYSCALL_FS_MKDIR, SYSCALL_FS_OPEN, SYSCALL_FS_STAT, SYSCALL_FS_UNLINK, SYSCALL_GETSOCKOPT,
+    SYSCALL_LISTEN, SYSCALL_NET_FILTER, SYSCALL_NET_INFO, SYSCALL_NET_LEASE, SYSCALL_NET_PING,
+    SYSCALL_NET_ROUTE, SYSCALL_NET_SCAN, SYSCALL_NET_STAT, SYSCALL_RECV, SYSCALL_RECVFROM,
+    SYSCALL_RENAME, SYSCALL_RESOLVE, SYSCALL_SEND, SYSCALL_SENDTO, SYSCALL_SETSOCKOPT,
+    SYSCALL_SHUTDOWN, SYSCALL_SOCKET, SYSCALL_SPAWN_PATH, SYSCALL_SPAWNVE,
+};
+
+/// Syscall numbers a filter can name.
+pub const SYSCALL_FILTER_MAX: usize = 256;
+pub const SYSCALL_FILTER_WORDS: usize = SYSCALL_FILTER_MAX / 64;
+
+/// The marked syscalls are the only ones allowed.
+pub const SYSCALL_FILTER_MODE_ALLOW: u32 = 0;
+/// The marked syscalls are refused; everything else is allowed.
+pub const SYSCALL_FILTER_MODE_DENY: u32 = 1;
+
+/// A refused syscall returns `-errno` (`EPERM` when `errno` is 0).
+pub const SYSCALL_FILTER_ACTION_ERRNO: u32 = 0;
+/// A refused syscall kills the task.
+pub const SYSCALL_FILTER_ACTION_KILL: u32 = 1;
+
+/// Syscalls that reach the filesystem by path.  Reads and writes on
+/// descriptors the task already holds stay allowed.
+pub const SYSCALL_FILTER_FS: &[u64] = &[
+    SYSCALL_FS_OPEN,
+    SYSCALL_FS_STAT,
+    SYSCALL_FS_MKDIR,
+    SYSCALL_FS_UNLINK,
+    SYSCALL_FS_LIST,
+    SYSCALL_CHDIR,
+    SYSCALL_RENAME,
+    SYSCALL_SPAWN_PATH,
+    SYSCALL_SPAWNVE,
+    SYSCALL_EXEC,
+];
+
+/// Sockets, name resolution and network configuration.
+pub const SYSCALL_FILTER_NET: &[u64] = &[
+    SYSCALL_SOCKET,
+    SYSCALL_BIND,
+    SYSCALL_LISTEN,
+    SYSCALL_ACCEPT,
+    SYSCALL_CONNECT,
+    SYSCALL_SEND,
+    SYSCALL_RECV,
+    SYSCALL_SENDTO,
+    SYSCALL_RECVFROM,
+    SYSCALL_RESOLVE,
+    SYSCALL_SETSOCKOPT,
+    SYSCALL_GETSOCKOPT,
+    SYSCALL_SHUTDOWN,
+    SYSCALL_NET_SCAN,
+    SYSCALL_NET_INFO,
+    SYSCALL_NET_STAT,
+    SYSCALL_NET_FILTER,
+    SYSCALL_NET_PING,
+    SYSCALL_NET_ROUTE,
+    SYSCALL_NET_LEASE,
+];
+
+/// Argument of `SYSCALL_SET_SYSCALL_FILTER`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyscallFilter {
+    /// A `SYSCALL_FILTER_MODE_*` value.
+    pub mode: u32,
+    /// A `SYSCALL_FILTER_ACTION_*` value.
+    pub action: u32,
+    /// Positive errno for `SYSCALL_FILTER_ACTION_ERRNO`.
+    pub errno: u32,
+    pub reserved: u32,
+    /// Bit `n % 64` of word `n / 64` marks syscall `n`.
+    pub bits: [u64; SYSCALL_FILTER_WORDS],
+}
+
+impl SyscallFilter {
+    pub const fn new(mode: u32, action: u32) -> Self {
+        Self {
+            mode,
+            action,
+            errno: 0,
+            reserved: 0,
+            bits: [0; SYSCALL_FILTER_WORDS],
+        }
+    }
+
+    /// Refuse `syscalls`, allow the rest.
+    pub fn deny(syscalls: &[u64], action: u32) -> Self {
+        let mut filter = Self::new(SYSCALL_FILTER_MODE_DENY, action);
+        filter.mark_all(syscalls);
+        filter
+    }
+
+    /// Allow only `syscalls`.
+    pub fn allow_only(syscalls: &[u64], action: u32) -> Self {
+        let mut filter = Self::new(SYSCALL_FILTER_MODE_ALLOW, action);
+        filter.mark_all(syscalls);
+        filter
+    }
+
+    /// Mark syscall `sysno`; numbers past `SYSCALL_FILTER_MAX` are ignored.
+    pub fn mark(&mut self, sysno: u64) {
+        if (sysno as usize) < SYSCALL_FILTER_MAX {
+            self.bits[sysno as usize / 64] |= 1 << (sysno % 64);
+        }
+    }
+
+    pub fn mark_all(&mut self, syscalls: &[u64]) {
+        for &sysno in syscalls {
+            self.mark(sysno);
+        }
+    }
+
+    pub fn is_marked(&self, sysno: u64) -> bool {
+        (sysno as usize) < SYSCALL_FILTER_MAX
+            && self.bits[sysno as usize / 64] & (1 << (sysno % 64)) != 0
+    }
+
+    /// Whether this filter alone lets `sysno` through.
+    pub fn permits(&self, sysno: u64) -> bool {
+        match self.mode {
+            SYSCALL_FILTER_MODE_DENY => !self.is_marked(sysno),
+            _ => self.is_marked(sysno),
+        }
+    }
+}
@@ -14,6 +14,70 @@ pub const TASK_NAME_MAX_LEN: usize = 32;
 pub const INVALID_TASK_ID: u32 = 0xFFFF_FFFF;
 pub const INVALID_PROCESS_ID: u32 = 0xFFFF_FFFF;
 
+// --- Credentials ---
+
+pub const ROOT_UID: u32 = 0;
+pub const ROOT_GID: u32 = 0;
+
+/// User and group IDs a task acts with.  Permission checks use the
+/// effective IDs; the real IDs say who the task may switch back to.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub euid: u32,
+    pub egid: u32,
+}
+
+impl Credentials {
+    pub const ROOT: Self = Self::new(ROOT_UID, ROOT_GID);
+
+    pub const fn new(uid: u32, gid: u32) -> Self {
+        Self {
+            uid,
+            gid,
+            euid: uid,
+            egid: gid,
+        }
+    }
+
+    /// Whether permission checks are bypassed.
+    #[inline]
+    pub const fn is_root(&self) -> bool {
+        self.euid == ROOT_UID
+    }
+
+    /// `setuid`: root sets the real and effective uid, anyone else may
+    /// only set the effective uid back to the real one.
+    pub fn set_uid(&mut self, uid: u32) -> bool {
+        if self.is_root() {
+            self.uid = uid;
+        } else if uid != self.uid {
+            return false;
+        }
+        self.euid = uid;
+        true
+    }
+
+    /// `setgid`, with the same rules as [`Self::set_uid`].
+    pub fn set_gid(&mut self, gid: u32) -> bool {
+        if self.is_root() {
+            self.gid = gid;
+        } else if gid != self.gid {
+            return false;
+        }
+        self.egid = gid;
+        true
+    }
+
+    /// Whether a task with these credentials may signal one owned by
+    /// `target`: root may signal anyone, others only tasks of their uid.
+    pub const fn may_signal(&self, target: &Credentials) -> bool {
+        self.is_root() || self.euid == target.uid || self.uid == target.uid
+    }
+}
+
 // --- TaskStatus ---
 
 /// Type-safe task status with explicit state-machine semantics.
@@ -121,6 +185,9 @@ pub const TASK_FLAG_SYSTEM: u16 = 0x08;
 pub const TASK_FLAG_COMPOSITOR: u16 = 0x10;
 pub const TASK_FLAG_DISPLAY_EXCLUSIVE: u16 = 0x20;
 pub const TASK_FLAG_FPU_INITIALIZED: u16 = 0x40;
+/// May open `SOCK_RAW` sockets.  Kept across fork and exec; init starts
+/// with it, and a task can only hand it to tasks it spawns if it has it.
+pub const TASK_FLAG_NET_RAW: u16 = 0x80;
 
 // --- Task Exit/Fault Reason ---
 
@@ -145,6 +212,8 @@ pub enum TaskFaultReason {
     UserGp = 2,
     UserUd = 3,
     UserDeviceNa = 4,
+    /// Killed by its syscall filter.
+    SyscallFilter = 5,
 }
 
 // --- TaskExitRecord ---
@@ -169,3 +238,153 @@ impl TaskExitRecord {
         }
     }
 }
+
+// --- TaskStackUsage ---
+
+/// Kernel stack use of one task, returned by `SYSCALL_TASK_STACK_USAGE`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskStackUsage {
+    pub task_id: u32,
+    /// `TASK_FLAG_*` bits of the task.
+    pub flags: u32,
+    /// NUL-terminated task name.
+    pub name: [u8; TASK_NAME_MAX_LEN],
+    /// Size of the kernel stack in bytes.
+    pub stack_size: u64,
+    /// Deepest use of the kernel stack seen so far, in bytes.
+    pub high_water: u64,
+}
+
+impl Default for TaskStackUsage {
+    fn default() -> Self {
+        Self {
+            task_id: INVALID_TASK_ID,
+            flags: 0,
+            name: [0; TASK_NAME_MAX_LEN],
+            stack_size: 0,
+            high_water: 0,
+        }
+    }
+}
+
+// --- CpuUsage ---
+
+/// Scheduler time of one CPU, returned by `SYSCALL_CPU_USAGE`.
+///
+/// Cycles are TSC cycles since the CPU's scheduler started, split by
+/// whether the CPU was running its idle task.  Utilization is the share of
+/// busy cycles between two samples; see [`CpuUsage::busy_percent_since`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CpuUsage {
+    pub cpu: u32,
+    /// Tasks waiting in the CPU's run queues.
+    pub ready: u32,
+    pub busy_cycles: u64,
+    pub idle_cycles: u64,
+    /// Scheduler ticks taken.  An idle CPU stops its tick.
+    pub ticks: u64,
+    pub switches: u64,
+    pub preemptions: u64,
+}
+
+impl CpuUsage {
+    /// Percentage of the cycles between `earlier` and `self` the CPU spent
+    /// busy; 0 if no time passed.
+    pub fn busy_percent_since(&self, earlier: &CpuUsage) -> u32 {
+        let busy = self.busy_cycles.saturating_sub(earlier.busy_cycles);
+        let idle = self.idle_cycles.saturating_sub(earlier.idle_cycles);
+        match busy as u128 + idle as u128 {
+            0 => 0,
+            total => (busy as u128 * 100 / total) as u32,
+        }
+    }
+}
+
+// --- TaskSnapshot ---
+
+/// Length of [`TaskSnapshot::title`], the same as a window title.
+pub const TASK_SNAPSHOT_TITLE_LEN: usize = 32;
+
+/// One live task, returned by `SYSCALL_TASK_SNAPSHOT`.
+///
+/// Times are in the kernel's cycle clock.  CPU use is the share of the
+/// time between two snapshots the task ran; see
+/// [`TaskSnapshot::cpu_percent_since`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskSnapshot {
+    pub task_id: u32,
+    pub parent_id: u32,
+    /// [`TaskStatus`] as a `u8`.
+    pub status: u8,
+    pub priority: u8,
+    /// CPU the task last ran on.
+    pub cpu: u8,
+    pub _pad: u8,
+    /// `TASK_FLAG_*` bits of the task.
+    pub flags: u32,
+    pub uid: u32,
+    /// Pages mapped into the task's address space; 0 for kernel tasks.
+    pub rss_pages: u32,
+    /// Time run so far, including the current stretch of a running task.
+    pub runtime: u64,
+    /// When the snapshot was taken; the same for every record of a call.
+    pub taken_at: u64,
+    /// NUL-terminated task name.
+    pub name: [u8; TASK_NAME_MAX_LEN],
+    /// NUL-terminated title of the task's window; empty without one.
+    pub title: [u8; TASK_SNAPSHOT_TITLE_LEN],
+}
+
+impl Default for TaskSnapshot {
+    fn default() -> Self {
+        Self {
+            task_id: INVALID_TASK_ID,
+            parent_id: INVALID_TASK_ID,
+            status: TaskStatus::Invalid.as_u8(),
+            priority: 0,
+            cpu: 0,
+            _pad: 0,
+            flags: 0,
+            uid: 0,
+            rss_pages: 0,
+            runtime: 0,
+            taken_at: 0,
+            name: [0; TASK_NAME_MAX_LEN],
+            title: [0; TASK_SNAPSHOT_TITLE_LEN],
+        }
+    }
+}
+
+impl TaskSnapshot {
+    fn str_field(bytes: &[u8]) -> &str {
+        let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        core::str::from_utf8(&bytes[..len]).unwrap_or("")
+    }
+
+    pub fn status(&self) -> TaskStatus {
+        TaskStatus::from_u8(self.status)
+    }
+
+    /// Task name, up to the first NUL.
+    pub fn name_str(&self) -> &str {
+        Self::str_field(&self.name)
+    }
+
+    /// Window title, up to the first NUL.
+    pub fn title_str(&self) -> &str {
+        Self::str_field(&self.title)
+    }
+
+    /// Percentage of one CPU the task used between `earlier`, a snapshot
+    /// of the same task, and `self`; 0 if no time passed.
+    pub fn cpu_percent_since(&self, earlier: &TaskSnapshot) -> u32 {
+        let ran = self.runtime.saturating_sub(earlier.runtime);
+        match self.taken_at.saturating_sub(earlier.taken_at) {
+            0 => 0,
+            elapsed => (ran as u128 * 100 / elapsed as u128).min(100) as u32,
+        }
+    }
+}
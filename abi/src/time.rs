@@ -0,0 +1,141 @@
+//! Calendar conversions shared by the RTC driver and userland `date`.
+//!
+//! All times are UTC; the day-count math is Howard Hinnant's
+//! `days_from_civil` / `civil_from_days`, valid for any proleptic
+//! Gregorian date from 1970 onwards.
+
+pub const SECS_PER_MINUTE: u64 = 60;
+pub const SECS_PER_HOUR: u64 = 60 * SECS_PER_MINUTE;
+pub const SECS_PER_DAY: u64 = 24 * SECS_PER_HOUR;
+pub const NSEC_PER_SEC: u64 = 1_000_000_000;
+
+/// Broken-down UTC calendar time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CivilTime {
+    pub year: u32,
+    /// 1..=12
+    pub month: u8,
+    /// 1..=31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl CivilTime {
+    /// Range-check the fields.  Does not reject Feb 30 and friends; the
+    /// conversion simply rolls those over.
+    pub const fn is_plausible(&self) -> bool {
+        self.year >= 1970
+            && self.month >= 1
+            && self.month <= 12
+            && self.day >= 1
+            && self.day <= 31
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    /// Seconds since the Unix epoch, or `None` before 1970.
+    pub const fn to_unix_secs(&self) -> Option<u64> {
+        if !self.is_plausible() {
+            return None;
+        }
+        let days = days_from_civil(self.year, self.month, self.day);
+        Some(
+            days * SECS_PER_DAY
+                + self.hour as u64 * SECS_PER_HOUR
+                + self.minute as u64 * SECS_PER_MINUTE
+                + self.second as u64,
+        )
+    }
+
+    pub const fn from_unix_secs(secs: u64) -> Self {
+        let (year, month, day) = civil_from_days(secs / SECS_PER_DAY);
+        let rem = secs % SECS_PER_DAY;
+        Self {
+            year,
+            month,
+            day,
+            hour: (rem / SECS_PER_HOUR) as u8,
+            minute: (rem % SECS_PER_HOUR / SECS_PER_MINUTE) as u8,
+            second: (rem % SECS_PER_MINUTE) as u8,
+        }
+    }
+}
+
+/// Days since 1970-01-01 for a date on or after it.
+pub const fn days_from_civil(year: u32, month: u8, day: u8) -> u64 {
+    let y = if month <= 2 { year - 1 } else { year } as u64;
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let m = month as u64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as u64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of [`days_from_civil`]: `(year, month, day)`.
+pub const fn civil_from_days(days: u64) -> (u32, u8, u8) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+    let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as u32;
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn epoch_is_day_zero() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+    }
+
+    #[test]
+    fn known_timestamps() {
+        let t = CivilTime {
+            year: 2000,
+            month: 2,
+            day: 29,
+            hour: 12,
+            minute: 34,
+            second: 56,
+        };
+        assert_eq!(t.to_unix_secs(), Some(951_827_696));
+        assert_eq!(CivilTime::from_unix_secs(951_827_696), t);
+
+        let y2038 = CivilTime::from_unix_secs(0x8000_0000);
+        assert_eq!((y2038.year, y2038.month, y2038.day), (2038, 1, 19));
+        assert_eq!((y2038.hour, y2038.minute, y2038.second), (3, 14, 8));
+    }
+
+    #[test]
+    fn roundtrip_across_leap_years() {
+        let mut days = 0;
+        while days < 200 * 366 {
+            let (y, m, d) = civil_from_days(days);
+            assert_eq!(days_from_civil(y, m, d), days);
+            days += 17;
+        }
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        let bad = CivilTime {
+            year: 2024,
+            month: 13,
+            day: 1,
+            ..CivilTime::default()
+        };
+        assert_eq!(bad.to_unix_secs(), None);
+        assert_eq!(CivilTime::default().to_unix_secs(), None);
+    }
+}
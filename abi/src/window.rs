@@ -10,6 +10,32 @@ pub const CURSOR_SHAPE_DEFAULT: u8 = 0;
 pub const CURSOR_SHAPE_TEXT: u8 = 1;
 pub const CURSOR_SHAPE_POINTER: u8 = 2;
 
+/// Width and height of a `SYSCALL_CURSOR_SET_IMAGE` image.
+pub const CURSOR_IMAGE_SIZE: u32 = 64;
+/// Bytes in a cursor image: premultiplied ARGB8888, rows packed.
+pub const CURSOR_IMAGE_BYTES: usize = (CURSOR_IMAGE_SIZE * CURSOR_IMAGE_SIZE * 4) as usize;
+
+/// `SYSCALL_COMPOSITOR_WAIT` timeout that never expires.
+pub const COMPOSITOR_WAIT_FOREVER: u64 = u64::MAX;
+
+/// [`WindowInfo::opacity`] of a window drawn without blending.
+pub const WINDOW_OPACITY_OPAQUE: u8 = 255;
+
+/// `SYSCALL_SURFACE_SET_OPACITY` flag: the buffer's alpha channel holds
+/// premultiplied per-pixel alpha.  Without it the alpha channel is ignored.
+pub const SURFACE_BLEND_PIXEL_ALPHA: u8 = 1 << 0;
+/// Every `SYSCALL_SURFACE_SET_OPACITY` flag.
+pub const SURFACE_BLEND_FLAGS: u8 = SURFACE_BLEND_PIXEL_ALPHA;
+
+/// `SYSCALL_SURFACE_SET_WINDOW_FLAGS` flag: draw the window without a
+/// title bar or buttons, e.g. for fullscreen apps.
+pub const WINDOW_FLAG_UNDECORATED: u8 = 1 << 0;
+/// `SYSCALL_SURFACE_SET_WINDOW_FLAGS` flag: when the buffer is magnified
+/// for a high-DPI display, filter it smoothly instead of repeating pixels.
+pub const WINDOW_FLAG_SMOOTH_SCALE: u8 = 1 << 1;
+/// Every `SYSCALL_SURFACE_SET_WINDOW_FLAGS` flag.
+pub const WINDOW_FLAGS: u8 = WINDOW_FLAG_UNDECORATED | WINDOW_FLAG_SMOOTH_SCALE;
+
 #[repr(C)]
 #[derive(Copy, Clone)]
 pub struct WindowInfo {
@@ -21,10 +47,27 @@ pub struct WindowInfo {
     pub state: u8,
     pub damage_count: u8,
     pub cursor_shape: u8,
-    pub _padding: u8,
+    /// Opacity the content is composited with, from 0 (invisible) to
+    /// [`WINDOW_OPACITY_OPAQUE`].
+    pub opacity: u8,
     pub shm_token: u32,
     pub damage_regions: [DamageRect; MAX_WINDOW_DAMAGE_REGIONS],
     pub title: [u8; 32],
+    /// `SURFACE_BLEND_*` flags.
+    pub blend_flags: u8,
+    /// `WINDOW_FLAG_*` flags.
+    pub window_flags: u8,
+    /// Desktop pixels per pixel of the client's buffer.  `width` and
+    /// `height` are in desktop pixels, so the buffer is `width /
+    /// content_scale` pixels wide.
+    pub content_scale: u8,
+    pub _padding: [u8; 1],
+    /// Content area the window had before it was maximized or tiled, to
+    /// return to on restore.  Zero-sized while the window floats.
+    pub restore_x: i32,
+    pub restore_y: i32,
+    pub restore_width: u32,
+    pub restore_height: u32,
 }
 
 impl WindowInfo {
@@ -48,6 +91,19 @@ impl WindowInfo {
         core::str::from_utf8(&self.title[..len]).unwrap_or("<invalid>")
     }
 
+    /// Whether the compositor draws a title bar and buttons for the window.
+    #[inline]
+    pub fn is_decorated(&self) -> bool {
+        self.window_flags & WINDOW_FLAG_UNDECORATED == 0
+    }
+
+    /// Size of the client's buffer, in its own pixels.
+    #[inline]
+    pub fn buffer_size(&self) -> (u32, u32) {
+        let scale = self.content_scale.max(1) as u32;
+        (self.width / scale, self.height / scale)
+    }
+
     #[inline]
     pub fn bounds(&self) -> DamageRect {
         DamageRect {
@@ -58,6 +114,20 @@ impl WindowInfo {
         }
     }
 
+    /// Geometry to return to when leaving a maximized or tiled state.
+    #[inline]
+    pub fn restore_bounds(&self) -> Option<DamageRect> {
+        if self.restore_width == 0 || self.restore_height == 0 {
+            return None;
+        }
+        Some(DamageRect {
+            x0: self.restore_x,
+            y0: self.restore_y,
+            x1: self.restore_x + self.restore_width as i32 - 1,
+            y1: self.restore_y + self.restore_height as i32 - 1,
+        })
+    }
+
     #[inline]
     pub fn damage_regions(&self) -> &[DamageRect] {
         if self.is_full_damage() {
@@ -80,10 +150,18 @@ impl Default for WindowInfo {
             state: 0,
             damage_count: 0,
             cursor_shape: 0,
-            _padding: 0,
+            opacity: WINDOW_OPACITY_OPAQUE,
             shm_token: 0,
             damage_regions: [DamageRect::default(); MAX_WINDOW_DAMAGE_REGIONS],
             title: [0; 32],
+            blend_flags: 0,
+            window_flags: 0,
+            content_scale: 1,
+            _padding: [0; 1],
+            restore_x: 0,
+            restore_y: 0,
+            restore_width: 0,
+            restore_height: 0,
         }
     }
 }
@@ -85,6 +85,10 @@ pub struct HpetInfo {
     pub num_comparators: u8,
     /// Whether the main counter is 64-bit capable.
     pub counter_64bit: bool,
+    /// Whether comparators 0/1 can take over the legacy PIT/RTC IRQs.
+    pub legacy_replacement: bool,
+    /// PCI vendor ID of the timer block.
+    pub pci_vendor_id: u16,
     /// Minimum clock tick value for periodic mode (from the ACPI table).
     pub minimum_tick: u16,
 }
@@ -134,6 +138,8 @@ impl Hpet {
         let block_id = raw.event_timer_block_id;
         let num_comparators = (((block_id >> 8) & 0x1F) as u8).wrapping_add(1);
         let counter_64bit = (block_id >> 13) & 1 != 0;
+        let legacy_replacement = (block_id >> 15) & 1 != 0;
+        let pci_vendor_id = (block_id >> 16) as u16;
         let minimum_tick = raw.minimum_tick;
         let hpet_number = raw.hpet_number;
 
@@ -143,6 +149,8 @@ impl Hpet {
                 hpet_number,
                 num_comparators,
                 counter_64bit,
+                legacy_replacement,
+                pci_vendor_id,
                 minimum_tick,
             },
         })
@@ -0,0 +1,304 @@
+//! Autopilot: an unattended boot → GUI → shutdown smoke test.
+//!
+//! Enabled with `autopilot=1` on the kernel command line. Once `/sbin/init`
+//! is running, a kernel thread waits for the compositor and shell to come
+//! up, focuses the shell, types a canned command list through the active
+//! TTY exactly as the keyboard ISR would, checks that each command printed
+//! its expected marker to the console (one of them runs a dynamically
+//! linked program through `ld.slop`), dumps a screenshot to
+//! `/autopilot.ppm` and powers off through the QEMU debug-exit port
+//! (exit status 1 = pass, 3 = fail, same as the test harness).
+//!
+//! Roulette losses are non-fatal while autopilot is active so every run
+//! reaches the desktop.
+//!
+//! Options:
+//! - `autopilot.timeout=<ms>`: give up waiting for userland after this long.
+
+use core::ffi::{CStr, c_char, c_void};
+
+use slopos_core::exec::is_dynamically_linked;
+use slopos_core::fate_api::fate_set_loss_reboots;
+use slopos_core::kthread::kthread_spawn;
+use slopos_core::scheduler::sleep::sleep_current_task_ms;
+use slopos_core::task::{
+    INVALID_TASK_ID, TASK_NAME_MAX_LEN, Task, TaskStatus, task_iterate_active,
+};
+use slopos_drivers::tty;
+use slopos_lib::clock;
+use slopos_lib::ports::QEMU_DEBUG_EXIT;
+use slopos_lib::{klog_debug, klog_info};
+
+use crate::early_init::{boot_get_cmdline, boot_init_priority};
+use crate::screenshot::write_screenshot;
+use crate::shutdown::kernel_shutdown;
+
+const DEFAULT_TIMEOUT_MS: u32 = 60_000;
+
+/// How often to re-check the task table while waiting for userland.
+const POLL_INTERVAL_MS: u32 = 100;
+
+/// Grace period after the shell appears, so it can map its window and
+/// print the first prompt before we start typing.
+const SETTLE_MS: u32 = 2_000;
+
+/// How long a command gets to print its marker before it counts as failed.
+const COMMAND_TIMEOUT_MS: u64 = 5_000;
+
+/// Commands typed into the shell, each followed by Enter, paired with a
+/// marker its output must contain. Markers never appear in the command line
+/// itself, so the TTY echo of the typed input cannot satisfy them.
+const COMMANDS: &[(&[u8], &[u8])] = &[
+    (b"help", b"SlopOS Shell"),
+    (b"ls /", b"sbin"),
+    (b"cat /proc/sys/kernel/random/stats", b"bytes_generated"),
+    (b"slop-netstat", b"Foreign Address"),
+    (b"echo autopilot \"done\"", b"autopilot done"),
+];
+
+/// Program the script runs to prove dynamic linking works end to end.
+const DYNAMIC_PROGRAM: &[u8] = b"/bin/slop-netstat";
+
+const SCREENSHOT_PATH: &[u8] = b"/autopilot.ppm";
+
+#[derive(Clone, Copy)]
+struct AutopilotConfig {
+    enabled: bool,
+    timeout_ms: u32,
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "1" | "on" | "true" | "yes" => Some(true),
+        "0" | "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn config_from_cmdline(cmdline: Option<&str>) -> AutopilotConfig {
+    let mut cfg = AutopilotConfig {
+        enabled: false,
+        timeout_ms: DEFAULT_TIMEOUT_MS,
+    };
+    let Some(cmdline) = cmdline else {
+        return cfg;
+    };
+    for token in cmdline.split_whitespace() {
+        if token == "autopilot" {
+            cfg.enabled = true;
+        } else if let Some(value) = token.strip_prefix("autopilot=") {
+            cfg.enabled = parse_bool(value).unwrap_or(false);
+        } else if let Some(value) = token.strip_prefix("autopilot.timeout=") {
+            if let Ok(parsed) = value.trim_end_matches("ms").parse::<u32>() {
+                cfg.timeout_ms = parsed;
+            }
+        }
+    }
+    cfg
+}
+
+struct TaskLookup {
+    name: &'static [u8],
+    task_id: u32,
+}
+
+fn match_task_name(task: *mut Task, context: *mut c_void) {
+    // SAFETY: task_iterate_active hands us live task slots, and `context`
+    // is the TaskLookup owned by find_task for the duration of the walk.
+    let (task, lookup) = unsafe { (&*task, &mut *(context as *mut TaskLookup)) };
+    if task.status() == TaskStatus::Terminated {
+        return;
+    }
+    let len = task
+        .name
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(TASK_NAME_MAX_LEN);
+    if &task.name[..len] == lookup.name {
+        lookup.task_id = task.task_id;
+    }
+}
+
+/// Task ID of a live task called `name`, or `INVALID_TASK_ID`.
+fn find_task(name: &'static [u8]) -> u32 {
+    let mut lookup = TaskLookup {
+        name,
+        task_id: INVALID_TASK_ID,
+    };
+    task_iterate_active(
+        Some(match_task_name),
+        &mut lookup as *mut TaskLookup as *mut c_void,
+    );
+    lookup.task_id
+}
+
+fn wait_for_task(name: &'static [u8], deadline_ms: u64) -> Option<u32> {
+    loop {
+        let task_id = find_task(name);
+        if task_id != INVALID_TASK_ID {
+            return Some(task_id);
+        }
+        if clock::uptime_ms() >= deadline_ms {
+            return None;
+        }
+        sleep_current_task_ms(POLL_INTERVAL_MS);
+    }
+}
+
+/// Wait until the console output captured since the command was typed
+/// contains `marker`.
+fn wait_for_marker(marker: &[u8]) -> bool {
+    let deadline = clock::uptime_ms() + COMMAND_TIMEOUT_MS;
+    loop {
+        if tty::console_capture_contains(marker) {
+            return true;
+        }
+        if clock::uptime_ms() >= deadline {
+            return false;
+        }
+        sleep_current_task_ms(POLL_INTERVAL_MS);
+    }
+}
+
+fn type_line(line: &[u8]) {
+    let idx = tty::active_tty();
+    for &byte in line {
+        tty::push_input(idx, byte);
+    }
+    tty::push_input(idx, b'\n');
+}
+
+/// Run the script; returns the number of failed checks.
+fn run(timeout_ms: u32) -> u32 {
+    let deadline = clock::uptime_ms() + timeout_ms as u64;
+    let mut failed = 0;
+
+    let Some(compositor) = wait_for_task(b"compositor", deadline) else {
+        klog_info!("AUTOPILOT: FAIL compositor did not start");
+        return 1;
+    };
+    klog_info!("AUTOPILOT: compositor is task {}", compositor);
+
+    let Some(shell) = wait_for_task(b"shell", deadline) else {
+        klog_info!("AUTOPILOT: FAIL shell did not start");
+        return 1;
+    };
+    klog_info!("AUTOPILOT: shell is task {}", shell);
+
+    sleep_current_task_ms(SETTLE_MS);
+    if tty::set_compositor_focus(shell).is_err() {
+        klog_info!("AUTOPILOT: FAIL could not focus the shell");
+        failed += 1;
+    }
+
+    match is_dynamically_linked(DYNAMIC_PROGRAM) {
+        Ok(true) => {}
+        Ok(false) => {
+            klog_info!("AUTOPILOT: FAIL /bin/slop-netstat is not dynamically linked");
+            failed += 1;
+        }
+        Err(err) => {
+            klog_info!(
+                "AUTOPILOT: FAIL cannot inspect /bin/slop-netstat: {:?}",
+                err
+            );
+            failed += 1;
+        }
+    }
+
+    for &(command, marker) in COMMANDS {
+        let command_str = core::str::from_utf8(command).unwrap_or("?");
+        klog_debug!("AUTOPILOT: typing '{}'", command_str);
+        tty::console_capture_start();
+        type_line(command);
+        if !wait_for_marker(marker) {
+            klog_info!(
+                "AUTOPILOT: FAIL '{}' printed no '{}'",
+                command_str,
+                core::str::from_utf8(marker).unwrap_or("?")
+            );
+            failed += 1;
+        }
+    }
+    tty::console_capture_stop();
+
+    for (name, task_id) in [(&b"compositor"[..], compositor), (&b"shell"[..], shell)] {
+        if find_task(name) != task_id {
+            klog_info!(
+                "AUTOPILOT: FAIL {} exited during the run",
+                core::str::from_utf8(name).unwrap_or("?")
+            );
+            failed += 1;
+        }
+    }
+
+    match write_screenshot(SCREENSHOT_PATH) {
+        Ok(Some(shot)) => klog_info!(
+            "AUTOPILOT: screenshot {}x{} ({} bytes) -> /autopilot.ppm",
+            shot.width,
+            shot.height,
+            shot.bytes
+        ),
+        Ok(None) => klog_info!("AUTOPILOT: no framebuffer, skipping screenshot"),
+        Err(()) => {
+            klog_info!("AUTOPILOT: FAIL could not write screenshot");
+            failed += 1;
+        }
+    }
+
+    failed
+}
+
+fn autopilot_task(arg: *mut c_void) {
+    // The boot step passes the timeout by value in the argument pointer.
+    let failed = run(arg as usize as u32);
+
+    if failed == 0 {
+        klog_info!("AUTOPILOT: PASS");
+    } else {
+        klog_info!("AUTOPILOT: FAIL ({} checks failed)", failed);
+    }
+
+    unsafe { QEMU_DEBUG_EXIT.write(if failed == 0 { 0 } else { 1 }) };
+    kernel_shutdown(if failed == 0 {
+        b"Autopilot passed\0".as_ptr() as *const c_char
+    } else {
+        b"Autopilot failed\0".as_ptr() as *const c_char
+    });
+}
+
+fn boot_step_autopilot() -> i32 {
+    let cmdline = boot_get_cmdline();
+    let cmdline_str = if cmdline.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(cmdline) }.to_str().ok()
+    };
+    let cfg = config_from_cmdline(cmdline_str);
+    if !cfg.enabled {
+        return 0;
+    }
+
+    klog_info!("AUTOPILOT: enabled (timeout {} ms)", cfg.timeout_ms);
+    fate_set_loss_reboots(false);
+
+    let task_id = kthread_spawn(
+        b"autopilot\0".as_ptr() as *const c_char,
+        Some(autopilot_task),
+        cfg.timeout_ms as usize as *mut c_void,
+    );
+    if task_id == INVALID_TASK_ID {
+        klog_info!("AUTOPILOT: failed to spawn driver thread");
+        return -1;
+    }
+    0
+}
+
+crate::boot_init!(
+    BOOT_STEP_AUTOPILOT,
+    services,
+    b"autopilot\0",
+    boot_step_autopilot,
+    fallible,
+    flags = boot_init_priority(59)
+);
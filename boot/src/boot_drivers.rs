@@ -1,6 +1,4 @@
-use core::ffi::CStr;
-#[cfg(feature = "xe-gpu")]
-use core::ffi::c_char;
+use core::ffi::{CStr, c_char};
 
 use slopos_lib::klog::{self, KlogLevel};
 use slopos_lib::{klog_debug, klog_info};
@@ -9,7 +7,7 @@ use slopos_tests::{
 };
 use slopos_video as video;
 
-use crate::early_init::{boot_get_cmdline, boot_init_priority};
+use crate::early_init::{BOOT_INIT_FLAG_UNBOUNDED, boot_get_cmdline, boot_init_priority};
 use crate::idt::{idt_init, idt_load};
 use crate::ist_stacks::ist_stacks_init;
 use crate::limine_protocol;
@@ -17,10 +15,19 @@ use crate::smp::smp_init;
 #[cfg(feature = "xe-gpu")]
 use slopos_drivers::xe;
 use slopos_drivers::{
-    apic, hpet, ioapic,
+    apic,
+    audio::{ac97::ac97_register_driver, hda::hda_register_driver},
+    hpet,
+    hwinfo::{self, hwinfo_assemble},
+    ioapic,
+    net::netstack::{NET_STACK, StaticNetConfig},
+    nvme::nvme_register_driver,
     pci::{pci_get_primary_gpu, pci_init, pci_probe_drivers},
     pic::pic_quiesce_disable,
+    random, rtc,
+    usb::xhci_register_driver,
     virtio_blk::virtio_blk_register_driver,
+    virtio_gpu::{virtio_gpu_framebuffer_init, virtio_gpu_register_driver},
     virtio_net::virtio_net_register_driver,
 };
 use slopos_mm::tlb;
@@ -41,7 +48,6 @@ fn serial_note(msg: &str) {
     slopos_drivers::serial::write_line(msg);
 }
 
-#[cfg(feature = "xe-gpu")]
 fn cmdline_contains(cmdline: *const c_char, needle: &str) -> bool {
     if cmdline.is_null() {
         return false;
@@ -59,12 +65,13 @@ fn cmdline_contains(cmdline: *const c_char, needle: &str) -> bool {
 }
 
 fn boot_video_backend() -> video::VideoBackend {
+    let cmdline = boot_get_cmdline();
     #[cfg(feature = "xe-gpu")]
-    {
-        let cmdline = boot_get_cmdline();
-        if cmdline_contains(cmdline, "video=xe") {
-            return video::VideoBackend::Xe;
-        }
+    if cmdline_contains(cmdline, "video=xe") {
+        return video::VideoBackend::Xe;
+    }
+    if cmdline_contains(cmdline, "video=virtio-gpu") {
+        return video::VideoBackend::VirtioGpu;
     }
     video::VideoBackend::Framebuffer
 }
@@ -118,8 +125,7 @@ fn boot_step_timer_setup_fn() {
         );
     }
     let backend = boot_video_backend();
-    #[cfg(feature = "xe-gpu")]
-    if backend == video::VideoBackend::Xe {
+    if backend != video::VideoBackend::Framebuffer {
         klog_info!("BOOT: deferring video init until PCI for GPU backend");
         return;
     }
@@ -179,6 +185,26 @@ fn boot_step_hpet_setup_fn() {
         panic!("SlopOS requires HPET — ACPI HPET table not found or hardware unavailable");
     }
     klog_debug!("HPET: Initialization complete, main counter running.");
+    if hpet::calibrate_tsc().is_none() {
+        klog_info!("HPET: TSC calibration failed, elapsed-time math uses CPUID estimate");
+    }
+}
+
+fn boot_step_rtc_fn() {
+    if rtc::rtc_sync_walltime().is_none() {
+        klog_info!("RTC: unreadable, wall clock starts at the Unix epoch");
+    }
+}
+
+fn boot_step_rng_seed_fn() {
+    let arch = random::random_init();
+    let stats = random::random_stats();
+    klog_debug!(
+        "RNG: {} bits credited ({} RDSEED/RDRAND values), seeded={}",
+        stats.entropy_avail,
+        arch,
+        stats.seeded
+    );
 }
 
 fn boot_step_lapic_calibration_fn() {
@@ -195,30 +221,75 @@ const LAPIC_TIMER_PERIOD_MS: u32 = 10;
 fn boot_step_lapic_timer_start_fn() {
     use slopos_lib::arch::idt::LAPIC_TIMER_VECTOR;
 
-    if !apic::timer::set_periodic_ms(LAPIC_TIMER_VECTOR, LAPIC_TIMER_PERIOD_MS) {
+    // One-shot: every tick re-arms the next one, and the scheduler stops
+    // the timer on idle CPUs with nothing pending.
+    if !apic::timer::enable_oneshot(LAPIC_TIMER_VECTOR) {
         panic!(
-            "SlopOS requires LAPIC timer — set_periodic_ms failed (vector 0x{:x}, {}ms)",
-            LAPIC_TIMER_VECTOR, LAPIC_TIMER_PERIOD_MS
+            "SlopOS requires LAPIC timer — enable_oneshot failed (vector 0x{:x})",
+            LAPIC_TIMER_VECTOR
         );
     }
+    apic::timer::arm_ns(LAPIC_TIMER_PERIOD_MS as u64 * 1_000_000);
 
     klog_info!(
-        "BOOT: LAPIC timer started — vector 0x{:x}, period {}ms ({}Hz)",
+        "BOOT: LAPIC timer started — vector 0x{:x}, {:?} one-shot, tick {}ms ({}Hz)",
         LAPIC_TIMER_VECTOR,
+        apic::timer::oneshot_mode(),
         LAPIC_TIMER_PERIOD_MS,
         1000 / LAPIC_TIMER_PERIOD_MS,
     );
 }
 
+/// Record an `ip=` static network config from the cmdline so the NIC
+/// skips DHCP when it is probed.
+fn boot_static_net_config() {
+    let cmdline = boot_get_cmdline();
+    if cmdline.is_null() {
+        return;
+    }
+    let Ok(cmdline) = unsafe { CStr::from_ptr(cmdline) }.to_str() else {
+        return;
+    };
+    if !cmdline
+        .split_whitespace()
+        .any(|token| token.starts_with(StaticNetConfig::CMDLINE_KEY))
+    {
+        return;
+    }
+    match StaticNetConfig::from_cmdline(cmdline) {
+        Some(config) => {
+            klog_info!(
+                "BOOT: static network config {}/{} gw {}",
+                config.addr,
+                config.netmask.to_u32_be().leading_ones(),
+                config.gateway
+            );
+            NET_STACK.set_static_config(Some(config));
+        }
+        None => klog_info!("BOOT: ignoring malformed ip= option; using DHCP"),
+    }
+}
+
 fn boot_step_pci_init_fn() {
     // Phase 3C: register the loopback device BEFORE any physical NIC so it
     // gets DevIndex(0) by convention.  This must happen before pci_init()
     // triggers VirtIO-net probe.
     slopos_drivers::net::loopback::init_loopback();
+    boot_static_net_config();
 
     klog_debug!("Enumerating PCI devices...");
     virtio_blk_register_driver();
     virtio_net_register_driver();
+    nvme_register_driver();
+    xhci_register_driver();
+    hda_register_driver();
+    ac97_register_driver();
+    // Probing resets the device, which would blank the firmware's
+    // virtio-gpu scanout behind the boot framebuffer, so only claim it
+    // when it is going to drive the display.
+    if boot_video_backend() == video::VideoBackend::VirtioGpu {
+        virtio_gpu_register_driver();
+    }
     pci_init();
     pci_probe_drivers();
     #[cfg(feature = "xe-gpu")]
@@ -262,6 +333,33 @@ fn boot_step_pci_init_fn() {
             sync_mouse_bounds(xe_fb);
         }
     }
+
+    if boot_video_backend() == video::VideoBackend::VirtioGpu {
+        let boot_fb = limine_protocol::boot_info().framebuffer;
+        let fb = boot_fb.map(|bf| slopos_abi::FramebufferData {
+            address: bf.address,
+            info: bf.info,
+        });
+        match virtio_gpu_framebuffer_init(fb) {
+            Some(gpu_fb) => {
+                video::init(Some(gpu_fb), video::VideoBackend::VirtioGpu);
+                sync_mouse_bounds(Some(gpu_fb));
+            }
+            None => {
+                klog_info!("BOOT: virtio-gpu unavailable; using boot framebuffer");
+                video::init(fb, video::VideoBackend::Framebuffer);
+                sync_mouse_bounds(fb);
+            }
+        }
+    }
+}
+
+fn boot_step_hwinfo_fn() {
+    let display = video::framebuffer::get_display_info().map(|info| hwinfo::HwDisplay {
+        info,
+        driver: video::active_backend().driver_name(),
+    });
+    hwinfo_assemble(display);
 }
 
 use slopos_lib::testing::config_from_cmdline;
@@ -355,6 +453,20 @@ crate::boot_init!(
     boot_step_hpet_setup_fn,
     flags = boot_init_priority(55)
 );
+crate::boot_init!(
+    BOOT_STEP_RTC,
+    drivers,
+    b"rtc wall clock\0",
+    boot_step_rtc_fn,
+    flags = boot_init_priority(56)
+);
+crate::boot_init!(
+    BOOT_STEP_RNG_SEED,
+    drivers,
+    b"rng seed\0",
+    boot_step_rng_seed_fn,
+    flags = boot_init_priority(56)
+);
 crate::boot_init!(
     BOOT_STEP_LAPIC_CALIBRATION,
     drivers,
@@ -390,11 +502,18 @@ crate::boot_init!(
     boot_step_pci_init_fn,
     flags = boot_init_priority(80)
 );
+crate::boot_init!(
+    BOOT_STEP_HWINFO,
+    drivers,
+    b"hardware inventory\0",
+    boot_step_hwinfo_fn,
+    flags = boot_init_priority(85)
+);
 crate::boot_init!(
     BOOT_STEP_INTERRUPT_TESTS,
     drivers,
     b"interrupt tests\0",
     boot_step_interrupt_tests_fn,
     fallible,
-    flags = boot_init_priority(90)
+    flags = boot_init_priority(90) | BOOT_INIT_FLAG_UNBOUNDED
 );
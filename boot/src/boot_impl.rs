@@ -1,6 +1,7 @@
 use core::ffi::{c_char, c_int, c_void};
 
 use crate::{early_init, gdt, idt, limine_protocol, shutdown};
+use slopos_core::per_cpu;
 use slopos_drivers::{apic, hpet, ioapic, random, serial};
 use slopos_lib::kernel_services::platform::{PlatformServices, register_platform_services};
 
@@ -28,15 +29,21 @@ fn idt_get_gate_fn(vector: u8, entry: *mut c_void) -> c_int {
     idt::idt_get_gate_opaque(vector, entry)
 }
 
+/// Scheduler tick rate: one time-slice unit every 10 ms.
+const TIMER_HZ: u32 = 100;
+
 static PLATFORM_SERVICES: PlatformServices = PlatformServices {
-    timer_ticks: || slopos_core::irq::get_timer_ticks(),
-    // LAPIC timer runs at a fixed 100 Hz (10 ms period). HPET + LAPIC are
-    // mandatory; there is no PIT fallback.
-    timer_frequency: || 100,
+    // The LAPIC timer is one-shot and idle CPUs stop it, so scheduler ticks
+    // are derived from the HPET clock rather than counted interrupts.
+    // HPET + LAPIC are mandatory; there is no PIT fallback.
+    timer_ticks: || hpet::nanoseconds(hpet::read_counter()) / (1_000_000_000 / TIMER_HZ as u64),
+    timer_frequency: || TIMER_HZ,
     timer_poll_delay_ms: |ms| hpet::delay_ms(ms),
     timer_sleep_ms: |ms| hpet::delay_ms(ms),
     timer_enable_irq: || apic::timer::unmask(),
     timer_disable_irq: || apic::timer::mask(),
+    timer_arm_ns: |ns| apic::timer::arm_ns(ns),
+    timer_disarm: || apic::timer::disarm(),
     console_putc: |c| serial::serial_putc_com1(c),
     console_puts: |s| {
         for &c in s {
@@ -44,6 +51,11 @@ static PLATFORM_SERVICES: PlatformServices = PlatformServices {
         }
     },
     rng_next: || random::random_next(),
+    rng_fill: random::random_fill,
+    rng_add_entropy: random::random_add_entropy,
+    rng_is_seeded: random::random_is_seeded,
+    rng_wait_seeded: random::random_wait_seeded,
+    rng_stats: random::random_stats,
     gdt_set_kernel_rsp0: gdt::gdt_set_kernel_rsp0,
     kernel_shutdown: kernel_shutdown_fn,
     kernel_reboot: kernel_reboot_fn,
@@ -55,6 +67,7 @@ static PLATFORM_SERVICES: PlatformServices = PlatformServices {
     irq_mask_gsi: |gsi| ioapic::mask_gsi(gsi),
     irq_unmask_gsi: |gsi| ioapic::unmask_gsi(gsi),
     clock_monotonic_ns: || hpet::nanoseconds(hpet::read_counter()),
+    cpu_usage: per_cpu::cpu_usage,
 };
 
 pub fn register_boot_services() {
@@ -1,35 +1,50 @@
 use slopos_lib::klog_info;
 
 use crate::early_init::{boot_init_priority, boot_mark_initialized};
-use slopos_core::exec;
+use crate::screenshot::write_screenshot;
 use slopos_core::sched::{
     boot_step_idle_task, boot_step_scheduler_init, boot_step_task_manager_init,
 };
-use slopos_drivers::virtio_blk;
+use slopos_core::{audit, crash, exec};
+use slopos_drivers::{nvme, virtio_blk};
 use slopos_fs::{
-    ext2_vfs_init_with_callbacks, ext2_vfs_is_initialized, vfs_init_builtin_filesystems,
+    CapacityFn, ReadFn, WriteFn, ext2_vfs_init_with_callbacks, ext2_vfs_is_initialized,
+    vfs_init_builtin_filesystems,
 };
 
 fn boot_step_fs_init() -> i32 {
-    if virtio_blk::virtio_blk_is_ready() {
-        if ext2_vfs_init_with_callbacks(
-            virtio_blk::virtio_blk_read,
-            virtio_blk::virtio_blk_write,
-            virtio_blk::virtio_blk_capacity,
-        )
-        .is_ok()
-        {
-            klog_info!("FS: ext2 initialized from virtio-blk");
+    // virtio-blk carries the root image; NVMe is only used when it is absent.
+    let root = if virtio_blk::virtio_blk_is_ready() {
+        Some((
+            "virtio-blk",
+            virtio_blk::virtio_blk_read as ReadFn,
+            virtio_blk::virtio_blk_write as WriteFn,
+            virtio_blk::virtio_blk_capacity as CapacityFn,
+        ))
+    } else if nvme::nvme_is_ready() {
+        Some((
+            "nvme",
+            nvme::nvme_read as ReadFn,
+            nvme::nvme_write as WriteFn,
+            nvme::nvme_capacity as CapacityFn,
+        ))
+    } else {
+        None
+    };
+
+    if let Some((name, read, write, capacity)) = root {
+        if ext2_vfs_init_with_callbacks(read, write, capacity).is_ok() {
+            klog_info!("FS: ext2 initialized from {}", name);
         } else {
-            klog_info!("FS: virtio-blk found but ext2 init failed");
+            klog_info!("FS: {} found but ext2 init failed", name);
         }
     }
 
     if vfs_init_builtin_filesystems().is_ok() {
         if ext2_vfs_is_initialized() {
-            klog_info!("VFS: mounted / (ext2), /tmp (ramfs), /dev (devfs)");
+            klog_info!("VFS: mounted / (ext2), /tmp (ramfs), /dev (devfs), /proc (procfs)");
         } else {
-            klog_info!("VFS: mounted /tmp (ramfs), /dev (devfs)");
+            klog_info!("VFS: mounted /tmp (ramfs), /dev (devfs), /proc (procfs)");
         }
     } else {
         klog_info!("VFS: failed to mount builtin filesystems");
@@ -39,6 +54,27 @@ fn boot_step_fs_init() -> i32 {
     0
 }
 
+fn boot_step_audit_start() -> i32 {
+    if !audit::audit_start() {
+        klog_info!("AUDIT: failed to spawn auditd");
+        return -1;
+    }
+    0
+}
+
+fn crash_screenshot(path: &[u8]) -> bool {
+    matches!(write_screenshot(path), Ok(Some(_)))
+}
+
+fn boot_step_crashd_start() -> i32 {
+    crash::crash_register_screenshot(crash_screenshot);
+    if !crash::crash_start() {
+        klog_info!("CRASH: failed to spawn crashd");
+        return -1;
+    }
+    0
+}
+
 fn boot_step_init_launch() -> i32 {
     match exec::launch_init() {
         Ok(task_id) => {
@@ -84,6 +120,22 @@ crate::boot_init!(
     fallible,
     flags = boot_init_priority(55)
 );
+crate::boot_init!(
+    BOOT_STEP_AUDIT,
+    services,
+    b"audit\0",
+    boot_step_audit_start,
+    fallible,
+    flags = boot_init_priority(56)
+);
+crate::boot_init!(
+    BOOT_STEP_CRASHD,
+    services,
+    b"crashd\0",
+    boot_step_crashd_start,
+    fallible,
+    flags = boot_init_priority(57)
+);
 crate::boot_init!(
     BOOT_STEP_INIT_LAUNCH,
     services,
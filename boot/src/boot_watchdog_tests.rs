@@ -0,0 +1,69 @@
+//! Boot-step watchdog tests.
+//!
+//! The harness itself runs as the unbounded "interrupt tests" boot step, so
+//! these tests borrow the watchdog and hand an unbounded watch back when
+//! they finish.
+
+use slopos_drivers::hpet;
+use slopos_lib::boot_watchdog::{boot_watchdog_begin, boot_watchdog_end, boot_watchdog_poll};
+use slopos_lib::{assert_test, pass, testing::TestResult};
+
+struct WatchdogFixture;
+
+impl WatchdogFixture {
+    fn new(budget_ms: u64) -> Self {
+        boot_watchdog_begin("watchdog selftest", budget_ms);
+        Self
+    }
+}
+
+impl Drop for WatchdogFixture {
+    fn drop(&mut self) {
+        let _ = boot_watchdog_end();
+        boot_watchdog_begin("interrupt tests", 0);
+    }
+}
+
+pub fn test_boot_watchdog_tick_flags_overrun() -> TestResult {
+    let _fixture = WatchdogFixture::new(20);
+    hpet::delay_ms(80);
+    let report = boot_watchdog_end();
+    assert_test!(report.overran, "80 ms step should overrun a 20 ms budget");
+    assert_test!(
+        report.expired,
+        "LAPIC tick never caught the overrun ({} ms)",
+        report.elapsed_ms
+    );
+    pass!()
+}
+
+pub fn test_boot_watchdog_poll_tells_loops_to_bail() -> TestResult {
+    let _fixture = WatchdogFixture::new(10);
+    assert_test!(!boot_watchdog_poll(), "fresh step should not be overdue");
+    hpet::delay_ms(30);
+    assert_test!(boot_watchdog_poll(), "poll should report the overrun");
+    assert_test!(boot_watchdog_poll(), "poll should keep saying bail out");
+    let report = boot_watchdog_end();
+    assert_test!(report.expired, "overrun should be recorded as live");
+    assert_test!(!boot_watchdog_poll(), "poll after end should be quiet");
+    pass!()
+}
+
+pub fn test_boot_watchdog_unbounded_step() -> TestResult {
+    let _fixture = WatchdogFixture::new(0);
+    hpet::delay_ms(30);
+    assert_test!(!boot_watchdog_poll(), "unbounded step flagged as overdue");
+    let report = boot_watchdog_end();
+    assert_test!(!report.overran, "unbounded step reported an overrun");
+    assert_test!(report.elapsed_ms >= 30, "elapsed {} ms", report.elapsed_ms);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    boot_watchdog,
+    [
+        test_boot_watchdog_tick_flags_overrun,
+        test_boot_watchdog_poll_tells_loops_to_bail,
+        test_boot_watchdog_unbounded_step,
+    ]
+);
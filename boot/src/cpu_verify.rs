@@ -4,9 +4,13 @@ use slopos_lib::cpu::cpuid::{
     CPUID_LEAF_FEATURES,
 };
 use slopos_lib::cpu::msr::{EFER_LMA, EFER_LME, Msr};
+use slopos_lib::cpu::smap;
+use slopos_lib::klog_info;
 use slopos_mm::memory_layout_defs::KERNEL_VIRTUAL_BASE;
 use slopos_mm::paging_defs::{PAGE_SIZE_1GB, PAGE_SIZE_4KB};
 
+use crate::early_init::boot_init_priority;
+
 pub fn verify_cpu_state() {
     let cr0 = cpu::read_cr0();
     let cr4 = cpu::read_cr4();
@@ -79,9 +83,37 @@ pub fn verify_cpu_features() {
     }
 }
 
+/// Turn on SMEP and SMAP where the CPU has them and check CR4 took the bits.
+///
+/// APs replicate the result from `smp::ap_entry`.
+pub fn verify_supervisor_protection() {
+    let (smep, smap) = smap::init();
+    let cr4 = cpu::read_cr4();
+    if smep && (cr4 & cpu::CR4_SMEP) == 0 {
+        panic!("SMEP supported but not enabled in CR4");
+    }
+    if smap && (cr4 & cpu::CR4_SMAP) == 0 {
+        panic!("SMAP supported but not enabled in CR4");
+    }
+    klog_info!(
+        "CPU: SMEP {}, SMAP {}",
+        if smep { "on" } else { "unsupported" },
+        if smap { "on" } else { "unsupported" }
+    );
+}
+
 pub fn complete_system_verification() {
     verify_cpu_state();
     verify_memory_layout();
     check_stack_health();
     verify_cpu_features();
+    verify_supervisor_protection();
 }
+
+crate::boot_init!(
+    BOOT_STEP_SUPERVISOR_PROTECTION,
+    drivers,
+    b"smep/smap\0",
+    verify_supervisor_protection,
+    flags = boot_init_priority(43)
+);
@@ -0,0 +1,291 @@
+//! Kernel crash dumps.
+//!
+//! At boot [`CRASHDUMP_PATH`] is created at its full size and the disk
+//! blocks under it are looked up once.  On panic the dump is built in a
+//! static buffer and written straight to those blocks with polled
+//! virtio-blk writes: by then the filesystem locks, the heap and interrupts
+//! can no longer be trusted.  The `crashdump` shell builtin reads it back
+//! after the next boot.
+
+use core::cell::SyncUnsafeCell;
+use core::ptr;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+use slopos_abi::addr::VirtAddr;
+use slopos_abi::crash::CRASH_DIR;
+use slopos_abi::crashdump::{
+    CRASHDUMP_PATH, CRASHDUMP_SECTION_BACKTRACE, CRASHDUMP_SECTION_EXCEPTION,
+    CRASHDUMP_SECTION_LOG, CRASHDUMP_SECTION_MEMORY, CRASHDUMP_SECTION_MESSAGE,
+    CRASHDUMP_SECTION_REGS, CRASHDUMP_SECTION_STACK, CRASHDUMP_SECTION_TASKS, CRASHDUMP_SIZE,
+    CrashDumpMemory, CrashDumpTask, CrashDumpWriter,
+};
+use slopos_abi::task::MAX_TASKS;
+use slopos_core::task::task_crash_dump;
+use slopos_drivers::virtio_blk::{
+    VIRTIO_BLK_PANIC_WRITE_MAX, virtio_blk_is_ready, virtio_blk_panic_prepare,
+    virtio_blk_panic_write,
+};
+use slopos_fs::vfs::{VfsError, VfsResult, vfs_mkdir, vfs_open};
+use slopos_fs::{ext2_vfs_is_initialized, ext2_vfs_map_file};
+use slopos_lib::{StateFlag, clock, cpu, klog_info, walltime};
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::kernel_heap::try_heap_stats;
+use slopos_mm::page_alloc::try_page_allocator_stats;
+use slopos_mm::paging;
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+use slopos_video::panic_screen::PanicReport;
+
+use crate::early_init::boot_init_priority;
+
+/// ext2 blocks are at least 1 KiB.
+const DUMP_BLOCKS_MAX: usize = CRASHDUMP_SIZE / 1024;
+/// Bytes of the stack saved above the stack pointer.
+const STACK_DUMP_LEN: usize = 1024;
+const SECTOR_SIZE: usize = 512;
+
+static DUMP_ARMED: StateFlag = StateFlag::new();
+static DUMP_BLOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+static DUMP_BLOCK_SIZE: AtomicU32 = AtomicU32::new(0);
+/// Disk byte offset of each block of the dump file.  Written once at boot,
+/// before `DUMP_ARMED` is set.
+static DUMP_BLOCKS: SyncUnsafeCell<[u64; DUMP_BLOCKS_MAX]> =
+    SyncUnsafeCell::new([0; DUMP_BLOCKS_MAX]);
+/// The dump being written; only the CPU that won the panic touches it.
+static DUMP_BUF: SyncUnsafeCell<[u8; CRASHDUMP_SIZE]> = SyncUnsafeCell::new([0; CRASHDUMP_SIZE]);
+
+fn ensure_dir(path: &[u8]) -> VfsResult<()> {
+    match vfs_mkdir(path) {
+        Ok(()) | Err(VfsError::AlreadyExists) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Create the dump file at full size without touching a dump already in
+/// it, and record where its blocks are.
+fn crashdump_reserve() -> VfsResult<()> {
+    ensure_dir(b"/var").and_then(|()| ensure_dir(CRASH_DIR))?;
+
+    let handle = vfs_open(CRASHDUMP_PATH, true)?;
+    let zeros = [0u8; SECTOR_SIZE];
+    let mut size = handle.size()? as usize;
+    while size < CRASHDUMP_SIZE {
+        let chunk = (CRASHDUMP_SIZE - size).min(zeros.len());
+        match handle.write(size as u64, &zeros[..chunk])? {
+            0 => return Err(VfsError::NoSpace),
+            n => size += n,
+        }
+    }
+
+    // SAFETY: only the boot CPU runs this, before the dump is armed.
+    let blocks = unsafe { &mut *DUMP_BLOCKS.get() };
+    let (count, block_size) = ext2_vfs_map_file(CRASHDUMP_PATH, blocks)?;
+    let needed = CRASHDUMP_SIZE.div_ceil(block_size as usize);
+    if count < needed || needed > blocks.len() || block_size as usize % SECTOR_SIZE != 0 {
+        return Err(VfsError::NotSupported);
+    }
+    DUMP_BLOCK_COUNT.store(needed, Ordering::Relaxed);
+    DUMP_BLOCK_SIZE.store(block_size, Ordering::Relaxed);
+    Ok(())
+}
+
+fn boot_step_crashdump() -> i32 {
+    if !virtio_blk_is_ready() || !ext2_vfs_is_initialized() {
+        klog_info!("CRASHDUMP: no virtio-blk root; panics will not be saved");
+        return 0;
+    }
+    if let Err(err) = crashdump_reserve() {
+        klog_info!("CRASHDUMP: cannot reserve dump file ({:?})", err);
+        return -1;
+    }
+    if !virtio_blk_panic_prepare() {
+        klog_info!("CRASHDUMP: cannot set aside virtio-blk buffers");
+        return -1;
+    }
+    DUMP_ARMED.set_active();
+    klog_info!("CRASHDUMP: panics will be saved to /var/crash/panic.dump");
+    0
+}
+
+crate::boot_init!(
+    BOOT_STEP_CRASHDUMP,
+    services,
+    b"crash dump\0",
+    boot_step_crashdump,
+    fallible,
+    flags = boot_init_priority(56)
+);
+
+/// Whether a panic will be saved.
+pub fn crashdump_armed() -> bool {
+    DUMP_ARMED.is_active()
+}
+
+/// Copy kernel memory at `addr` into `out`, stopping at the end of the
+/// page.  Returns the bytes copied; 0 when unmapped.
+fn read_kernel(addr: u64, out: &mut [u8]) -> usize {
+    let Some(vaddr) = VirtAddr::try_new(addr) else {
+        return 0;
+    };
+    let len = out
+        .len()
+        .min((PAGE_SIZE_4KB - (addr & (PAGE_SIZE_4KB - 1))) as usize);
+    let phys = paging::virt_to_phys(vaddr);
+    if phys.is_null() {
+        return 0;
+    }
+    let Some(src) = phys.to_virt_checked() else {
+        return 0;
+    };
+    // SAFETY: `src` is the HHDM mapping of a present page and `len` stops
+    // at its end.
+    unsafe { ptr::copy_nonoverlapping(src.as_u64() as *const u8, out.as_mut_ptr(), len) };
+    len
+}
+
+/// Registers in `CRASHDUMP_REG_NAMES` order.  Without an exception frame
+/// the general registers are the panic handler's own, and `cs`/`ss` are
+/// left 0.
+fn dump_regs(report: &PanicReport) -> [u64; 24] {
+    let (cr0, cr2, cr3, cr4) = (report.cr0, report.cr2, report.cr3, report.cr4);
+    match report.frame {
+        Some(f) => [
+            f.rax, f.rbx, f.rcx, f.rdx, f.rsi, f.rdi, f.rbp, f.rsp, f.r8, f.r9, f.r10, f.r11,
+            f.r12, f.r13, f.r14, f.r15, f.rip, f.rflags, f.cs, f.ss, cr0, cr2, cr3, cr4,
+        ],
+        None => {
+            let r = &report.regs;
+            [
+                r.rax,
+                r.rbx,
+                r.rcx,
+                r.rdx,
+                r.rsi,
+                r.rdi,
+                r.rbp,
+                report.rsp,
+                r.r8,
+                r.r9,
+                r.r10,
+                r.r11,
+                r.r12,
+                r.r13,
+                r.r14,
+                r.r15,
+                report.rip.unwrap_or(0),
+                cpu::read_rflags(),
+                0,
+                0,
+                cr0,
+                cr2,
+                cr3,
+                cr4,
+            ]
+        }
+    }
+}
+
+fn dump_stack(w: &mut CrashDumpWriter<'_>, rsp: u64) {
+    w.begin(CRASHDUMP_SECTION_STACK);
+    w.push_u64(rsp);
+    let mut chunk = [0u8; STACK_DUMP_LEN];
+    let mut read = 0;
+    while read < STACK_DUMP_LEN {
+        let n = read_kernel(rsp.wrapping_add(read as u64), &mut chunk[read..]);
+        if n == 0 {
+            break;
+        }
+        read += n;
+    }
+    w.push(&chunk[..read]);
+    w.end();
+}
+
+fn dump_memory(w: &mut CrashDumpWriter<'_>) {
+    let Some((total, free, allocated)) = try_page_allocator_stats() else {
+        return;
+    };
+    let heap = try_heap_stats().unwrap_or_default();
+    let memory = CrashDumpMemory {
+        total_pages: total as u64,
+        free_pages: free as u64,
+        allocated_pages: allocated as u64,
+        heap_total: heap.total_size,
+        heap_allocated: heap.allocated_size,
+        heap_free: heap.free_size,
+    };
+    w.section(CRASHDUMP_SECTION_MEMORY, &memory.encode());
+}
+
+pub(crate) fn build_dump(w: &mut CrashDumpWriter<'_>, report: &PanicReport) {
+    w.section(
+        CRASHDUMP_SECTION_MESSAGE,
+        report.message.unwrap_or("").as_bytes(),
+    );
+    w.section_u64s(CRASHDUMP_SECTION_REGS, &dump_regs(report));
+    if let Some(frame) = report.frame {
+        w.section_u64s(
+            CRASHDUMP_SECTION_EXCEPTION,
+            &[frame.vector, frame.error_code],
+        );
+    }
+
+    w.begin(CRASHDUMP_SECTION_BACKTRACE);
+    for entry in report.backtrace {
+        w.push_u64(entry.return_address);
+    }
+    w.end();
+
+    dump_stack(w, report.frame.map_or(report.rsp, |f| f.rsp));
+
+    let mut tasks = [CrashDumpTask::default(); MAX_TASKS];
+    if let Some(count) = task_crash_dump(&mut tasks) {
+        w.begin(CRASHDUMP_SECTION_TASKS);
+        for task in &tasks[..count] {
+            w.push(&task.encode());
+        }
+        w.end();
+    }
+
+    dump_memory(w);
+    w.section(CRASHDUMP_SECTION_LOG, report.log);
+}
+
+/// Write `dump`, padded to whole sectors, over the reserved blocks.
+fn write_dump(dump: &[u8]) -> bool {
+    let count = DUMP_BLOCK_COUNT.load(Ordering::Relaxed);
+    let block_size = DUMP_BLOCK_SIZE.load(Ordering::Relaxed) as usize;
+    // SAFETY: written once at boot, before the dump was armed.
+    let blocks = unsafe { &*DUMP_BLOCKS.get() };
+    for (block, data) in blocks[..count].iter().zip(dump.chunks(block_size)) {
+        for (i, piece) in data.chunks(VIRTIO_BLK_PANIC_WRITE_MAX).enumerate() {
+            let offset = block + (i * VIRTIO_BLK_PANIC_WRITE_MAX) as u64;
+            if !virtio_blk_panic_write(offset, piece) {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Save `report` and the state around it to the reserved dump file.
+/// Returns `false` if the dump is not armed or the disk write failed.
+pub fn crashdump_write(report: &PanicReport) -> bool {
+    if !DUMP_ARMED.is_active() {
+        return false;
+    }
+    // SAFETY: only the CPU that won the panic gets here.
+    let buf = unsafe { &mut *DUMP_BUF.get() };
+    let unix_secs = if walltime::walltime_is_set() {
+        walltime::realtime_secs()
+    } else {
+        0
+    };
+    let Some(mut w) = CrashDumpWriter::new(&mut buf[..], clock::monotonic_ns(), unix_secs) else {
+        return false;
+    };
+    build_dump(&mut w, report);
+    let len = w.finish();
+    let padded = len.next_multiple_of(SECTOR_SIZE).min(buf.len());
+    buf[len..padded].fill(0);
+    write_dump(&buf[..padded])
+}
@@ -0,0 +1,191 @@
+//! Crash dump tests: building a dump from a panic report and reading it
+//! back, without touching the disk.
+
+use slopos_abi::crashdump::{
+    CRASHDUMP_SECTION_BACKTRACE, CRASHDUMP_SECTION_EXCEPTION, CRASHDUMP_SECTION_LOG,
+    CRASHDUMP_SECTION_MESSAGE, CRASHDUMP_SECTION_REGS, CRASHDUMP_SECTION_STACK, CrashDump,
+    CrashDumpMemory, CrashDumpTask, CrashDumpWriter,
+};
+use slopos_lib::stacktrace::StacktraceEntry;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{InterruptFrame, RegSnapshot, assert_eq_test, assert_test, fail, pass};
+use slopos_video::panic_screen::PanicReport;
+
+use crate::crashdump::build_dump;
+
+const LOG: &[u8] = b"boot\nsomething broke\n";
+
+fn report<'a>(
+    frame: Option<&'a InterruptFrame>,
+    backtrace: &'a [StacktraceEntry],
+) -> PanicReport<'a> {
+    PanicReport {
+        message: Some("kernel/src/main.rs:1:1: oops"),
+        regs: RegSnapshot {
+            rax: 0x1111,
+            ..RegSnapshot::default()
+        },
+        frame,
+        rip: Some(0xFFFF_FFFF_8000_1234),
+        rsp: 0,
+        cr0: 0x8005_0033,
+        cr2: 0xDEAD,
+        cr3: 0x1000,
+        cr4: 0x20,
+        backtrace,
+        log: LOG,
+    }
+}
+
+pub fn test_crashdump_round_trip() -> TestResult {
+    let backtrace = [
+        StacktraceEntry {
+            frame_pointer: 0x10,
+            return_address: 0xAAAA,
+        },
+        StacktraceEntry {
+            frame_pointer: 0x20,
+            return_address: 0xBBBB,
+        },
+    ];
+    let report = report(None, &backtrace);
+    let mut buf = [0u8; 8192];
+    let mut w = CrashDumpWriter::new(&mut buf, 42, 1_700_000_000).unwrap();
+    build_dump(&mut w, &report);
+    assert_test!(!w.truncated());
+    let len = w.finish();
+
+    let Some(dump) = CrashDump::parse(&buf[..len]) else {
+        return fail!("dump did not parse");
+    };
+    assert_eq_test!(dump.time_ns, 42);
+    assert_eq_test!(dump.unix_secs, 1_700_000_000);
+    assert_eq_test!(
+        dump.section(CRASHDUMP_SECTION_MESSAGE),
+        Some(&b"kernel/src/main.rs:1:1: oops"[..])
+    );
+    let mut regs = [0u64; 24];
+    for (slot, value) in regs.iter_mut().zip(dump.u64s(CRASHDUMP_SECTION_REGS)) {
+        *slot = value;
+    }
+    assert_eq_test!(regs[0], 0x1111, "rax");
+    assert_eq_test!(regs[16], 0xFFFF_FFFF_8000_1234, "rip");
+    assert_eq_test!(regs[21], 0xDEAD, "cr2");
+    assert_test!(dump.section(CRASHDUMP_SECTION_EXCEPTION).is_none());
+    let mut frames = dump.u64s(CRASHDUMP_SECTION_BACKTRACE);
+    assert_eq_test!(frames.next(), Some(0xAAAA));
+    assert_eq_test!(frames.next(), Some(0xBBBB));
+    assert_eq_test!(frames.next(), None);
+    assert_eq_test!(
+        dump.section(CRASHDUMP_SECTION_STACK),
+        Some(&[0u8; 8][..]),
+        "an unmapped stack keeps only its pointer"
+    );
+    assert_eq_test!(dump.section(CRASHDUMP_SECTION_LOG), Some(LOG));
+    pass!()
+}
+
+pub fn test_crashdump_exception_frame() -> TestResult {
+    let frame = InterruptFrame {
+        r15: 0,
+        r14: 0,
+        r13: 0,
+        r12: 0,
+        r11: 0,
+        r10: 0,
+        r9: 0,
+        r8: 0,
+        rbp: 0,
+        rdi: 0,
+        rsi: 0,
+        rdx: 0,
+        rcx: 0,
+        rbx: 0,
+        rax: 0x2222,
+        vector: 14,
+        error_code: 2,
+        rip: 0x4000,
+        cs: 0x08,
+        rflags: 0x202,
+        rsp: 0,
+        ss: 0x10,
+    };
+    let report = report(Some(&frame), &[]);
+    let mut buf = [0u8; 8192];
+    let mut w = CrashDumpWriter::new(&mut buf, 0, 0).unwrap();
+    build_dump(&mut w, &report);
+    let len = w.finish();
+
+    let Some(dump) = CrashDump::parse(&buf[..len]) else {
+        return fail!("dump did not parse");
+    };
+    assert_eq_test!(dump.u64s(CRASHDUMP_SECTION_REGS).next(), Some(0x2222));
+    assert_eq_test!(dump.u64s(CRASHDUMP_SECTION_REGS).nth(16), Some(0x4000));
+    let mut exception = dump.u64s(CRASHDUMP_SECTION_EXCEPTION);
+    assert_eq_test!(exception.next(), Some(14));
+    assert_eq_test!(exception.next(), Some(2));
+    pass!()
+}
+
+pub fn test_crashdump_truncates() -> TestResult {
+    let mut buf = [0u8; 64];
+    let mut w = CrashDumpWriter::new(&mut buf, 0, 0).unwrap();
+    w.section(CRASHDUMP_SECTION_MESSAGE, b"short");
+    w.section(CRASHDUMP_SECTION_LOG, &[b'x'; 100]);
+    assert_test!(w.truncated());
+    let len = w.finish();
+    assert_eq_test!(len, 64);
+
+    let Some(dump) = CrashDump::parse(&buf[..len]) else {
+        return fail!("dump did not parse");
+    };
+    assert_eq_test!(dump.section(CRASHDUMP_SECTION_MESSAGE), Some(&b"short"[..]));
+    assert_eq_test!(
+        dump.section(CRASHDUMP_SECTION_LOG).map(|log| log.len()),
+        Some(8),
+        "log cut at the end of the buffer"
+    );
+    assert_test!(CrashDumpWriter::new(&mut [0u8; 16], 0, 0).is_none());
+    assert_test!(CrashDump::parse(&[0u8; 64]).is_none(), "no magic");
+    pass!()
+}
+
+pub fn test_crashdump_records() -> TestResult {
+    let mut name = [0u8; 32];
+    name[..5].copy_from_slice(b"shell");
+    let task = CrashDumpTask {
+        task_id: 7,
+        parent_id: 1,
+        status: 2,
+        cpu: 1,
+        priority: 3,
+        flags: 0x41,
+        runtime: 123_456_789,
+        name,
+    };
+    let decoded = CrashDumpTask::decode(&task.encode());
+    assert_eq_test!(decoded, Some(task));
+    assert_eq_test!(task.name_str(), "shell");
+    assert_test!(CrashDumpTask::decode(&[0u8; 10]).is_none());
+
+    let memory = CrashDumpMemory {
+        total_pages: 65536,
+        free_pages: 1000,
+        allocated_pages: 64536,
+        heap_total: 1 << 20,
+        heap_allocated: 3 << 18,
+        heap_free: 1 << 18,
+    };
+    assert_eq_test!(CrashDumpMemory::decode(&memory.encode()), Some(memory));
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    crashdump,
+    [
+        test_crashdump_round_trip,
+        test_crashdump_exception_frame,
+        test_crashdump_truncates,
+        test_crashdump_records,
+    ]
+);
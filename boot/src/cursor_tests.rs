@@ -0,0 +1,114 @@
+//! Hardware cursor plane tests: image and hotspot validation, and requests
+//! reaching the backend's plane, using a fake plane swapped in for the
+//! duration of each test.
+
+use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+
+use slopos_abi::window::{CURSOR_IMAGE_BYTES, CURSOR_IMAGE_SIZE};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+use slopos_video::cursor::{
+    CursorPlane, cursor_plane_present, surface_move_cursor, surface_set_cursor_image,
+    swap_cursor_plane,
+};
+
+static IMAGES: AtomicU32 = AtomicU32::new(0);
+static HOTSPOT: AtomicU32 = AtomicU32::new(0);
+static HIDES: AtomicU32 = AtomicU32::new(0);
+static MOVE_X: AtomicI32 = AtomicI32::new(0);
+static MOVE_Y: AtomicI32 = AtomicI32::new(0);
+
+fn fake_set_image(image: &[u8], hot_x: u32, hot_y: u32) -> bool {
+    IMAGES.fetch_add(1, Ordering::Relaxed);
+    HOTSPOT.store(hot_x << 16 | hot_y, Ordering::Relaxed);
+    image.len() == CURSOR_IMAGE_BYTES
+}
+
+fn fake_move(x: i32, y: i32) {
+    MOVE_X.store(x, Ordering::Relaxed);
+    MOVE_Y.store(y, Ordering::Relaxed);
+}
+
+fn fake_hide() {
+    HIDES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// The fake plane, installed until drop.
+struct FakePlane {
+    saved: Option<CursorPlane>,
+}
+
+impl FakePlane {
+    fn install() -> Self {
+        IMAGES.store(0, Ordering::Relaxed);
+        HIDES.store(0, Ordering::Relaxed);
+        Self {
+            saved: swap_cursor_plane(Some(CursorPlane {
+                set_image: fake_set_image,
+                move_to: fake_move,
+                hide: fake_hide,
+            })),
+        }
+    }
+}
+
+impl Drop for FakePlane {
+    fn drop(&mut self) {
+        swap_cursor_plane(self.saved.take());
+    }
+}
+
+pub fn test_cursor_without_plane() -> TestResult {
+    let saved = swap_cursor_plane(None);
+    let image = [0u8; CURSOR_IMAGE_BYTES];
+    let shown = surface_set_cursor_image(&image, 0, 0);
+    let hidden = surface_set_cursor_image(&[], 0, 0);
+    let present = cursor_plane_present();
+    surface_move_cursor(10, 10);
+    swap_cursor_plane(saved);
+
+    assert_test!(!present);
+    assert_test!(!shown, "image shown without a cursor plane");
+    assert_test!(!hidden, "hide reported success without a cursor plane");
+    pass!()
+}
+
+pub fn test_cursor_image_validation() -> TestResult {
+    let _plane = FakePlane::install();
+    let image = [0u8; CURSOR_IMAGE_BYTES];
+    assert_test!(!surface_set_cursor_image(&image[..16], 0, 0), "short image");
+    assert_test!(!surface_set_cursor_image(&image, CURSOR_IMAGE_SIZE, 0));
+    assert_test!(!surface_set_cursor_image(&image, 0, CURSOR_IMAGE_SIZE));
+    assert_eq_test!(
+        IMAGES.load(Ordering::Relaxed),
+        0,
+        "bad image reached the plane"
+    );
+
+    assert_test!(surface_set_cursor_image(&image, 3, 5));
+    assert_eq_test!(IMAGES.load(Ordering::Relaxed), 1);
+    assert_eq_test!(HOTSPOT.load(Ordering::Relaxed), 3 << 16 | 5);
+    pass!()
+}
+
+pub fn test_cursor_move_and_hide() -> TestResult {
+    let _plane = FakePlane::install();
+    assert_test!(cursor_plane_present());
+    surface_move_cursor(-3, 700);
+    assert_eq_test!(MOVE_X.load(Ordering::Relaxed), -3);
+    assert_eq_test!(MOVE_Y.load(Ordering::Relaxed), 700);
+
+    assert_test!(surface_set_cursor_image(&[], 0, 0));
+    assert_eq_test!(HIDES.load(Ordering::Relaxed), 1);
+    assert_eq_test!(IMAGES.load(Ordering::Relaxed), 0, "hide uploaded an image");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    cursor,
+    [
+        test_cursor_without_plane,
+        test_cursor_image_validation,
+        test_cursor_move_and_hide,
+    ]
+);
@@ -5,16 +5,21 @@ use core::{
 };
 
 use core::sync::atomic::{AtomicUsize, Ordering};
+use slopos_abi::klog::{klog_level_from_name, klog_module_from_name};
 use slopos_drivers::serial;
-use slopos_lib::klog::{self, KlogLevel};
+use slopos_lib::boot_watchdog;
+use slopos_lib::klog::{self, KlogLevel, KlogModule};
+use slopos_lib::kwarn;
 use slopos_lib::wl_currency;
 use slopos_lib::{klog_debug, klog_info, klog_set_level};
-use slopos_video::splash;
+use slopos_video::{splash, vblank};
 
 use crate::limine_protocol;
 use crate::{gdt, idt};
 
 pub const BOOT_INIT_FLAG_OPTIONAL: u32 = 1 << 0;
+/// Exempt the step from the boot watchdog budget (long-running by design).
+pub const BOOT_INIT_FLAG_UNBOUNDED: u32 = 1 << 1;
 const BOOT_INIT_PRIORITY_SHIFT: u32 = 8;
 const BOOT_INIT_PRIORITY_MASK: u32 = 0xFF << BOOT_INIT_PRIORITY_SHIFT;
 
@@ -276,10 +281,28 @@ fn boot_run_step(phase_name: &[u8], step: &BootInitStep) -> i32 {
     serial::write_line("BOOT: running init step");
     boot_init_report_step(KlogLevel::Debug, b"step\0", Some(step.name));
 
-    let rc = (step.func)();
+    let budget_ms = if (step.flags & BOOT_INIT_FLAG_UNBOUNDED) != 0 {
+        0
+    } else {
+        boot_watchdog::boot_watchdog_budget_ms()
+    };
+    boot_watchdog::boot_watchdog_begin(bytes_to_str(step.name), budget_ms);
+    let mut rc = (step.func)();
+    let report = boot_watchdog::boot_watchdog_end();
+
+    let optional = (step.flags & BOOT_INIT_FLAG_OPTIONAL) != 0;
+    // Whatever an optional step left half-done, the kernel boots without
+    // it; a required step that did finish keeps its result.
+    if report.overran && optional && rc == 0 {
+        klog_info!(
+            "[boot:init] {} overran its budget ({} ms), marking failed",
+            bytes_to_str(step.name),
+            report.elapsed_ms
+        );
+        rc = -1;
+    }
 
     if rc != 0 {
-        let optional = (step.flags & BOOT_INIT_FLAG_OPTIONAL) != 0;
         boot_init_report_failure(phase_name, Some(step.name));
         if optional {
             boot_info(b"Optional boot step failed, continuing...\0");
@@ -457,6 +480,53 @@ fn boot_step_boot_config_fn() {
         klog_set_level(KlogLevel::Info);
         boot_debug(b"Boot option: debug logging disabled\0");
     }
+
+    if cmdline.contains("panic_on_warn=1") || cmdline.contains("panic_on_warn=on") {
+        kwarn::kwarn_set_panic_on_warn(true);
+        boot_info(b"Boot option: panic_on_warn enabled\0");
+    }
+
+    if let Some(ms) = cmdline_u64(cmdline, "boot.step_budget_ms=") {
+        boot_watchdog::boot_watchdog_set_budget_ms(ms);
+        klog_info!("Boot option: boot step budget {} ms", ms);
+    }
+
+    if let Some(hz) = cmdline_u64(cmdline, "video.refresh_hz=") {
+        if u32::try_from(hz).is_ok_and(vblank::set_refresh_hz) {
+            klog_info!("Boot option: display refresh {} Hz", hz);
+        } else {
+            klog_info!(
+                "Boot option: refresh {} Hz outside {}..={} Hz, ignored",
+                hz,
+                vblank::REFRESH_HZ_MIN,
+                vblank::REFRESH_HZ_MAX
+            );
+        }
+    }
+
+    for (module, level) in cmdline_klog_levels(cmdline) {
+        klog::klog_set_module_level(module, Some(level));
+        klog_info!("Boot option: {} log level {:?}", module.name(), level);
+    }
+}
+
+/// `klog.<subsystem>=<level>` options, e.g. `klog.net=debug`.  Unknown
+/// names are skipped.
+fn cmdline_klog_levels(cmdline: &str) -> impl Iterator<Item = (KlogModule, KlogLevel)> + '_ {
+    cmdline.split_ascii_whitespace().filter_map(|opt| {
+        let (module, level) = opt.strip_prefix("klog.")?.split_once('=')?;
+        let module = KlogModule::from_abi(klog_module_from_name(module.as_bytes())? as u64)?;
+        let level = KlogLevel::from_abi(klog_level_from_name(level.as_bytes())? as u64)?;
+        Some((module, level))
+    })
+}
+
+fn cmdline_u64(cmdline: &str, key: &str) -> Option<u64> {
+    let start = cmdline.find(key)? + key.len();
+    let digits = cmdline[start..]
+        .split(|c: char| c.is_ascii_whitespace())
+        .next()?;
+    digits.parse().ok()
 }
 
 boot_init!(
@@ -0,0 +1,72 @@
+//! SIMD span copy tests.
+//!
+//! Check [`copy_span`] against odd lengths and offsets that leave a byte
+//! tail after the vector blocks, and that it hands back the vector
+//! registers it borrowed with the values they held.
+
+use slopos_lib::cpu::fastcopy::{CopyPath, copy_path, copy_span};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+
+const BUF_LEN: usize = 1024;
+
+fn pattern(i: usize) -> u8 {
+    (i.wrapping_mul(31) ^ (i >> 3)) as u8
+}
+
+pub fn test_copy_span_matches_bytes() -> TestResult {
+    let mut src = [0u8; BUF_LEN];
+    for (i, byte) in src.iter_mut().enumerate() {
+        *byte = pattern(i);
+    }
+
+    for &(offset, len) in &[(0, 0), (0, 1), (3, 63), (1, 64), (5, 129), (7, 900)] {
+        let mut dst = [0xAAu8; BUF_LEN];
+        // SAFETY: both ranges lie inside their arrays and don't overlap.
+        unsafe {
+            copy_span(dst.as_mut_ptr().add(offset), src.as_ptr().add(offset), len);
+        }
+        assert_test!(
+            dst[offset..offset + len] == src[offset..offset + len],
+            "copied span differs"
+        );
+        assert_test!(
+            dst[..offset].iter().all(|&b| b == 0xAA),
+            "wrote before the span"
+        );
+        assert_test!(
+            dst[offset + len..].iter().all(|&b| b == 0xAA),
+            "wrote past the span"
+        );
+    }
+    pass!()
+}
+
+pub fn test_copy_span_preserves_vector_registers() -> TestResult {
+    if copy_path() == CopyPath::Bytes {
+        return pass!();
+    }
+    let src = [0x5Au8; 256];
+    let mut dst = [0u8; 256];
+    let marker: u64 = 0x1234_5678_9ABC_DEF0;
+    let after: u64;
+
+    // SAFETY: xmm0 is loaded with a marker and read back after the copy;
+    // the test owns both buffers.
+    unsafe {
+        core::arch::asm!("movq xmm0, {}", in(reg) marker, options(nostack));
+        copy_span(dst.as_mut_ptr(), src.as_ptr(), dst.len());
+        core::arch::asm!("movq {}, xmm0", out(reg) after, options(nostack));
+    }
+    assert_eq_test!(after, marker, "xmm0 clobbered by copy_span");
+    assert_test!(dst == src, "copied span differs");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    fastcopy,
+    [
+        test_copy_span_matches_bytes,
+        test_copy_span_preserves_vector_registers,
+    ]
+);
@@ -15,6 +15,8 @@
 /// Entry point called from limine_entry.s
 #[unsafe(no_mangle)]
 pub extern "C" fn kernel_main() {
+    // Never returns, so no protected frame outlives the old guard.
+    slopos_lib::stack_protector::init_guard();
     crate::early_init::kernel_main_impl();
 }
 #[unsafe(no_mangle)]
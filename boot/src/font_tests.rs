@@ -0,0 +1,153 @@
+//! PSF2 font loader and glyph rendering tests.
+//!
+//! A tiny 8x8 PSF2 font is built in memory: a blank glyph, a full block
+//! reached through its Unicode table entry for 'é', and a left-half block
+//! for 'L' that shows up grey when scaled down.
+
+use slopos_abi::damage::DamageRect;
+use slopos_abi::draw::{Canvas, Color32};
+use slopos_gfx::DrawBuffer;
+use slopos_gfx::font_render::{
+    Font, FontError, FontRegistry, PSF2_MAGIC, Psf2Font, draw_glyph, text_width,
+};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+const GLYPH: usize = 8;
+const UNICODE: &[u8] = b" \xFF\xC3\xA9\xFEe\xCC\x81\xFFL\xFF";
+const FONT_LEN: usize = 32 + 3 * GLYPH + UNICODE.len();
+
+const WHITE: Color32 = Color32::rgb(0xFF, 0xFF, 0xFF);
+const BLACK: Color32 = Color32::rgb(0, 0, 0);
+
+fn test_font() -> [u8; FONT_LEN] {
+    let mut data = [0u8; FONT_LEN];
+    let header = [0, 32, 1, 3, GLYPH as u32, 8, 8];
+    data[..4].copy_from_slice(&PSF2_MAGIC);
+    for (i, value) in header.iter().enumerate() {
+        data[4 + i * 4..8 + i * 4].copy_from_slice(&value.to_le_bytes());
+    }
+    data[32 + GLYPH..32 + 2 * GLYPH].fill(0xFF);
+    data[32 + 2 * GLYPH..32 + 3 * GLYPH].fill(0xF0);
+    data[32 + 3 * GLYPH..].copy_from_slice(UNICODE);
+    data
+}
+
+/// Red channel of every pixel after drawing `ch` into a `size` box.
+fn render(font: &Font, ch: char, size: u32) -> Option<[u8; 64]> {
+    let mut data = [0u8; 8 * 8 * 4];
+    let mut buf = DrawBuffer::new(&mut data, 8, 8, 8 * 4, 4)?;
+    let clip = DamageRect {
+        x0: 0,
+        y0: 0,
+        x1: 7,
+        y1: 7,
+    };
+    draw_glyph(&mut buf, font, ch, 0, 0, size, size, WHITE, BLACK, &clip);
+
+    let mut red = [0u8; 64];
+    for (i, r) in red.iter_mut().enumerate().take((size * size) as usize) {
+        let (x, y) = ((i as u32 % size) as i32, (i as u32 / size) as i32);
+        let px = buf.get_pixel(x, y)?;
+        *r = buf.pixel_format().decode(px).red();
+    }
+    Some(red)
+}
+
+pub fn test_psf2_rejects_bad_headers() -> TestResult {
+    let good = test_font();
+    assert_test!(Psf2Font::parse(&good).is_ok(), "valid font rejected");
+
+    let mut bad = good;
+    bad[0] = 0;
+    assert_eq_test!(
+        Psf2Font::parse(&bad).err(),
+        Some(FontError::BadMagic),
+        "bad magic"
+    );
+    assert_eq_test!(
+        Psf2Font::parse(&good[..40]).err(),
+        Some(FontError::Truncated),
+        "truncated glyphs"
+    );
+    let mut bad = good;
+    bad[28..32].fill(0);
+    assert_eq_test!(
+        Psf2Font::parse(&bad).err(),
+        Some(FontError::BadHeader),
+        "zero width"
+    );
+    pass!()
+}
+
+pub fn test_psf2_unicode_lookup() -> TestResult {
+    let data = test_font();
+    let Ok(font) = Psf2Font::parse(&data) else {
+        return fail!("font rejected");
+    };
+    let font = Font::Psf2(font);
+
+    let Some(block) = render(&font, 'é', 8) else {
+        return fail!("draw buffer setup failed");
+    };
+    assert_test!(block.iter().all(|&r| r == 0xFF), "'é' not the full block");
+    let Some(space) = render(&font, ' ', 8) else {
+        return fail!("draw buffer setup failed");
+    };
+    assert_test!(space.iter().all(|&r| r == 0), "space not blank");
+    let Some(missing) = render(&font, 'x', 8) else {
+        return fail!("draw buffer setup failed");
+    };
+    assert_test!(missing.iter().all(|&r| r == 0), "unmapped char not glyph 0");
+    pass!()
+}
+
+pub fn test_glyph_scaling_antialiases() -> TestResult {
+    let data = test_font();
+    let Ok(font) = Psf2Font::parse(&data) else {
+        return fail!("font rejected");
+    };
+    let font = Font::Psf2(font);
+
+    let Some(native) = render(&font, 'L', 8) else {
+        return fail!("draw buffer setup failed");
+    };
+    assert_eq_test!(native[3], 0xFF, "lit half not crisp");
+    assert_eq_test!(native[4], 0, "unlit half not crisp");
+
+    let Some(shrunk) = render(&font, 'L', 1) else {
+        return fail!("draw buffer setup failed");
+    };
+    assert_test!(
+        (0x70..=0x90).contains(&shrunk[0]),
+        "half-lit pixel not grey"
+    );
+    assert_eq_test!(text_width(&font, 16, "ab\nc"), 32, "advance not scaled");
+    pass!()
+}
+
+pub fn test_registry_picks_font_by_size() -> TestResult {
+    let data = test_font();
+    let Ok(small) = Psf2Font::parse(&data) else {
+        return fail!("font rejected");
+    };
+    let mut registry = FontRegistry::new();
+    assert_eq_test!(registry.font_for_size(8).height(), 16, "builtin missing");
+    assert_test!(registry.register(Font::Psf2(small)), "register failed");
+
+    assert_eq_test!(registry.font_for_size(8).height(), 8, "exact size");
+    assert_eq_test!(registry.font_for_size(6).height(), 8, "scale down");
+    assert_eq_test!(registry.font_for_size(12).height(), 16, "next larger");
+    assert_eq_test!(registry.font_for_size(40).height(), 16, "tallest");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    font,
+    [
+        test_psf2_rejects_bad_headers,
+        test_psf2_unicode_lookup,
+        test_glyph_scaling_antialiases,
+        test_registry_picks_font_by_size,
+    ]
+);
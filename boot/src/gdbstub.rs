@@ -0,0 +1,735 @@
+//! GDB remote stub on COM2.
+//!
+//! With `gdb=1` a kernel-mode breakpoint (`int3`) or single-step trap stops
+//! in the stub instead of being logged, a panic stops in it before the
+//! panic screen, and Alt+SysRq+G breaks in from the keyboard.  The stub
+//! then speaks the GDB remote serial protocol on COM2, polled with
+//! interrupts off, until GDB continues, steps or detaches.  With QEMU:
+//!
+//! ```text
+//! GDB_PORT=1234 just boot
+//! gdb <kernel elf> -ex 'target remote :1234'
+//! ```
+//!
+//! Only the stopped CPU waits in the stub; the others keep running.
+//! Memory is reached through the kernel page tables and written through
+//! the HHDM alias, so GDB can plant its own `int3` breakpoints in kernel
+//! text with `M`.  `Z` packets, `vCont` and Ctrl-C from GDB are not
+//! supported: break in with Alt+SysRq+G instead.  Continuing from a panic
+//! goes on to the panic screen.
+//!
+//! Options:
+//! - `gdb=1`: enable the stub.
+//! - `gdb.wait=1`: stop at boot and wait for GDB.
+
+use core::arch::asm;
+use core::ffi::CStr;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_abi::addr::VirtAddr;
+use slopos_drivers::keyboard::keyboard_register_sysrq;
+use slopos_drivers::serial::{init_port, serial_poll_byte, serial_port_present};
+use slopos_lib::ports::{COM1, COM2, serial_putc, serial_write_bytes};
+use slopos_lib::{InterruptFrame, StateFlag, cpu, klog_info};
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::paging;
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+use slopos_video::panic_screen::PanicReport;
+
+use crate::early_init::{boot_get_cmdline, boot_init_priority};
+use crate::watchdog::parse_bool;
+
+/// Largest packet either side sends, advertised in `qSupported`.
+pub const GDB_PACKET_MAX: usize = 0x400;
+const PACKET_SIZE_FEATURE: &[u8] = b"PacketSize=400";
+
+/// Registers in the order of GDB's x86-64 `g` packet: 17 64-bit general
+/// registers and `rip`, then `eflags` and the six segment registers as
+/// 32-bit values.
+pub const GDB_REG_COUNT: usize = 24;
+const GDB_REG_WIDE: usize = 17;
+
+pub const GDB_SIGTRAP: u8 = 5;
+pub const GDB_SIGABRT: u8 = 6;
+
+/// `rflags.TF`: trap after the next instruction.
+const RFLAGS_TF: u64 = 1 << 8;
+
+/// Make code of the G key, for Alt+SysRq+G.
+const SCANCODE_G: u8 = 0x22;
+
+/// Byte stream to the debugger.
+pub trait GdbLink {
+    /// Wait for the next byte.
+    fn read_byte(&mut self) -> u8;
+    fn write_byte(&mut self, byte: u8);
+}
+
+/// Memory of the stopped target.
+pub trait GdbMemory {
+    /// Copy memory at `addr` into `out`.  Returns the bytes copied, short
+    /// at the first unmapped page.
+    fn read(&mut self, addr: u64, out: &mut [u8]) -> usize;
+    /// Copy `data` to `addr`.  Returns the bytes written.
+    fn write(&mut self, addr: u64, data: &[u8]) -> usize;
+}
+
+/// What the stopped CPU does once a packet is handled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GdbResume {
+    /// Send the reply and wait for the next packet.
+    Stay,
+    Continue,
+    Step,
+    /// Send the reply, if any, and run without the debugger.
+    Detach,
+}
+
+/// Reply being built, without the `$`/`#` framing.
+pub struct GdbReply {
+    buf: [u8; GDB_PACKET_MAX],
+    len: usize,
+}
+
+impl GdbReply {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; GDB_PACKET_MAX],
+            len: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        let n = bytes.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&bytes[..n]);
+        self.len += n;
+    }
+
+    pub fn push_hex_byte(&mut self, byte: u8) {
+        self.push(&[
+            HEX_DIGITS[(byte >> 4) as usize],
+            HEX_DIGITS[(byte & 0xF) as usize],
+        ]);
+    }
+
+    /// `value` as `bytes` little-endian bytes, the way GDB sends registers.
+    fn push_hex_le(&mut self, value: u64, bytes: usize) {
+        for byte in &value.to_le_bytes()[..bytes] {
+            self.push_hex_byte(*byte);
+        }
+    }
+
+    fn error(&mut self, code: u8) {
+        self.clear();
+        self.push(b"E");
+        self.push_hex_byte(code);
+    }
+
+    fn stop(&mut self, signal: u8) {
+        self.push(b"S");
+        self.push_hex_byte(signal);
+    }
+}
+
+impl Default for GdbReply {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// `EFAULT`, GDB's usual answer for unreadable memory.
+const ERR_FAULT: u8 = 0x0E;
+const ERR_BAD_PACKET: u8 = 0x01;
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Big-endian hex number, as in addresses and lengths.
+fn parse_hex(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() > 16 {
+        return None;
+    }
+    s.iter()
+        .try_fold(0u64, |acc, &c| Some(acc << 4 | hex_digit(c)? as u64))
+}
+
+/// Little-endian hex value of up to 8 bytes, as in register writes.
+fn parse_hex_le(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() > 16 || s.len() % 2 != 0 {
+        return None;
+    }
+    let mut value = 0u64;
+    for (i, pair) in s.chunks(2).enumerate() {
+        let byte = hex_digit(pair[0])? << 4 | hex_digit(pair[1])?;
+        value |= (byte as u64) << (8 * i);
+    }
+    Some(value)
+}
+
+/// Sum of the packet bytes, modulo 256.
+pub fn gdb_checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+fn reg_width(n: usize) -> usize {
+    if n < GDB_REG_WIDE { 8 } else { 4 }
+}
+
+/// Register `n` of `frame` in `g` packet order.  The data segment
+/// registers are not saved by the interrupt entry and read as 0.
+pub fn gdb_reg(frame: &InterruptFrame, n: usize) -> Option<u64> {
+    let f = frame;
+    Some(match n {
+        0 => f.rax,
+        1 => f.rbx,
+        2 => f.rcx,
+        3 => f.rdx,
+        4 => f.rsi,
+        5 => f.rdi,
+        6 => f.rbp,
+        7 => f.rsp,
+        8 => f.r8,
+        9 => f.r9,
+        10 => f.r10,
+        11 => f.r11,
+        12 => f.r12,
+        13 => f.r13,
+        14 => f.r14,
+        15 => f.r15,
+        16 => f.rip,
+        17 => f.rflags,
+        18 => f.cs,
+        19 => f.ss,
+        20..GDB_REG_COUNT => 0,
+        _ => return None,
+    })
+}
+
+/// Set register `n` of `frame`.  Writes to the segment registers are
+/// ignored: changing them under a kernel frame would not end well.
+pub fn gdb_set_reg(frame: &mut InterruptFrame, n: usize, value: u64) -> bool {
+    let f = frame;
+    let slot = match n {
+        0 => &mut f.rax,
+        1 => &mut f.rbx,
+        2 => &mut f.rcx,
+        3 => &mut f.rdx,
+        4 => &mut f.rsi,
+        5 => &mut f.rdi,
+        6 => &mut f.rbp,
+        7 => &mut f.rsp,
+        8 => &mut f.r8,
+        9 => &mut f.r9,
+        10 => &mut f.r10,
+        11 => &mut f.r11,
+        12 => &mut f.r12,
+        13 => &mut f.r13,
+        14 => &mut f.r14,
+        15 => &mut f.r15,
+        16 => &mut f.rip,
+        17 => {
+            f.rflags = value & 0xFFFF_FFFF;
+            return true;
+        }
+        18..GDB_REG_COUNT => return true,
+        _ => return false,
+    };
+    *slot = value;
+    true
+}
+
+/// Signal reported for a stop on exception `vector`.
+pub fn gdb_signal_for_vector(vector: u64) -> u8 {
+    match vector {
+        1 | 3 => GDB_SIGTRAP,
+        // SIGFPE
+        0 | 16 | 19 => 8,
+        // SIGILL
+        6 => 4,
+        // SIGBUS
+        17 => 7,
+        // SIGSEGV
+        10..=14 => 11,
+        _ => GDB_SIGABRT,
+    }
+}
+
+/// Split `addr,len` into its numbers.
+fn parse_addr_len(s: &[u8]) -> Option<(u64, usize)> {
+    let comma = s.iter().position(|&c| c == b',')?;
+    let addr = parse_hex(&s[..comma])?;
+    let len = parse_hex(&s[comma + 1..])?;
+    Some((addr, len as usize))
+}
+
+fn read_memory(args: &[u8], mem: &mut impl GdbMemory, reply: &mut GdbReply) {
+    let Some((addr, len)) = parse_addr_len(args) else {
+        return reply.error(ERR_BAD_PACKET);
+    };
+    let mut chunk = [0u8; 64];
+    let mut done = 0;
+    let len = len.min((GDB_PACKET_MAX - 1) / 2);
+    while done < len {
+        let want = (len - done).min(chunk.len());
+        let n = mem.read(addr.wrapping_add(done as u64), &mut chunk[..want]);
+        for byte in &chunk[..n] {
+            reply.push_hex_byte(*byte);
+        }
+        done += n;
+        if n < want {
+            break;
+        }
+    }
+    // A partial read is fine; nothing at all is an error.
+    if done == 0 && len > 0 {
+        reply.error(ERR_FAULT);
+    }
+}
+
+fn write_memory(args: &[u8], mem: &mut impl GdbMemory, reply: &mut GdbReply) {
+    let Some(colon) = args.iter().position(|&c| c == b':') else {
+        return reply.error(ERR_BAD_PACKET);
+    };
+    let hex = &args[colon + 1..];
+    let Some((addr, len)) = parse_addr_len(&args[..colon]) else {
+        return reply.error(ERR_BAD_PACKET);
+    };
+    if hex.len() != len.saturating_mul(2) {
+        return reply.error(ERR_BAD_PACKET);
+    }
+    let mut chunk = [0u8; 64];
+    for (i, digits) in hex.chunks(chunk.len() * 2).enumerate() {
+        let n = digits.len() / 2;
+        for (byte, pair) in chunk.iter_mut().zip(digits.chunks(2)) {
+            let (Some(hi), Some(lo)) = (hex_digit(pair[0]), hex_digit(pair[1])) else {
+                return reply.error(ERR_BAD_PACKET);
+            };
+            *byte = hi << 4 | lo;
+        }
+        let at = addr.wrapping_add((i * chunk.len()) as u64);
+        if mem.write(at, &chunk[..n]) != n {
+            return reply.error(ERR_FAULT);
+        }
+    }
+    reply.push(b"OK");
+}
+
+fn write_registers(args: &[u8], frame: &mut InterruptFrame, reply: &mut GdbReply) {
+    let mut rest = args;
+    for n in 0..GDB_REG_COUNT {
+        let digits = reg_width(n) * 2;
+        if rest.len() < digits {
+            break;
+        }
+        let Some(value) = parse_hex_le(&rest[..digits]) else {
+            return reply.error(ERR_BAD_PACKET);
+        };
+        gdb_set_reg(frame, n, value);
+        rest = &rest[digits..];
+    }
+    reply.push(b"OK");
+}
+
+fn write_register(args: &[u8], frame: &mut InterruptFrame, reply: &mut GdbReply) {
+    let Some(eq) = args.iter().position(|&c| c == b'=') else {
+        return reply.error(ERR_BAD_PACKET);
+    };
+    let value = parse_hex_le(&args[eq + 1..]);
+    match (parse_hex(&args[..eq]), value) {
+        (Some(n), Some(value)) if gdb_set_reg(frame, n as usize, value) => reply.push(b"OK"),
+        _ => reply.error(ERR_BAD_PACKET),
+    }
+}
+
+fn query(packet: &[u8], reply: &mut GdbReply) {
+    match packet {
+        p if p.starts_with(b"qSupported") => reply.push(PACKET_SIZE_FEATURE),
+        b"qAttached" => reply.push(b"1"),
+        b"qC" => reply.push(b"QC1"),
+        b"qfThreadInfo" => reply.push(b"m1"),
+        b"qsThreadInfo" => reply.push(b"l"),
+        _ => {}
+    }
+}
+
+/// Handle one packet (without framing) for a CPU stopped at `frame` with
+/// `signal`.  Leaves the reply in `reply`; an empty reply tells GDB the
+/// packet is not supported.
+pub fn gdb_handle_packet(
+    packet: &[u8],
+    frame: &mut InterruptFrame,
+    signal: u8,
+    mem: &mut impl GdbMemory,
+    reply: &mut GdbReply,
+) -> GdbResume {
+    let Some((&kind, args)) = packet.split_first() else {
+        return GdbResume::Stay;
+    };
+    match kind {
+        b'?' => reply.stop(signal),
+        b'g' => {
+            for n in 0..GDB_REG_COUNT {
+                reply.push_hex_le(gdb_reg(frame, n).unwrap_or(0), reg_width(n));
+            }
+        }
+        b'G' => write_registers(args, frame, reply),
+        b'p' => match parse_hex(args).and_then(|n| Some((n, gdb_reg(frame, n as usize)?))) {
+            Some((n, value)) => reply.push_hex_le(value, reg_width(n as usize)),
+            None => reply.error(ERR_BAD_PACKET),
+        },
+        b'P' => write_register(args, frame, reply),
+        b'm' => read_memory(args, mem, reply),
+        b'M' => write_memory(args, mem, reply),
+        b'c' | b's' => {
+            if let Some(addr) = parse_hex(args) {
+                frame.rip = addr;
+            }
+            return if kind == b'c' {
+                GdbResume::Continue
+            } else {
+                GdbResume::Step
+            };
+        }
+        b'D' => {
+            reply.push(b"OK");
+            return GdbResume::Detach;
+        }
+        b'k' => return GdbResume::Detach,
+        b'H' | b'T' => reply.push(b"OK"),
+        b'q' => query(packet, reply),
+        _ => {}
+    }
+    GdbResume::Stay
+}
+
+/// Wait for a packet with a good checksum, ack it and return its length.
+/// Bad packets are nacked so GDB sends them again.
+pub fn gdb_recv_packet(link: &mut impl GdbLink, buf: &mut [u8; GDB_PACKET_MAX]) -> usize {
+    loop {
+        while link.read_byte() != b'$' {}
+        let mut len = 0;
+        let mut sum = 0u8;
+        let mut overflow = false;
+        loop {
+            match link.read_byte() {
+                b'#' => break,
+                // A new packet started before this one ended.
+                b'$' => {
+                    len = 0;
+                    sum = 0;
+                    overflow = false;
+                }
+                byte => {
+                    sum = sum.wrapping_add(byte);
+                    match buf.get_mut(len) {
+                        Some(slot) => *slot = byte,
+                        None => overflow = true,
+                    }
+                    len += 1;
+                }
+            }
+        }
+        let hi = hex_digit(link.read_byte());
+        let lo = hex_digit(link.read_byte());
+        if !overflow && hi.zip(lo).map(|(hi, lo)| hi << 4 | lo) == Some(sum) {
+            link.write_byte(b'+');
+            return len;
+        }
+        link.write_byte(b'-');
+    }
+}
+
+/// Send `data` framed, again each time GDB nacks it.
+pub fn gdb_send_packet(link: &mut impl GdbLink, data: &[u8]) {
+    let sum = gdb_checksum(data);
+    loop {
+        link.write_byte(b'$');
+        for &byte in data {
+            link.write_byte(byte);
+        }
+        link.write_byte(b'#');
+        link.write_byte(HEX_DIGITS[(sum >> 4) as usize]);
+        link.write_byte(HEX_DIGITS[(sum & 0xF) as usize]);
+        loop {
+            match link.read_byte() {
+                b'+' => return,
+                b'-' => break,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Serve GDB until it resumes the stopped CPU.  With `announce`, GDB is
+/// waiting on an earlier continue or step and is told about the stop first.
+/// Sets or clears the trap flag in `frame` for the way it resumes.
+pub fn gdb_session(
+    link: &mut impl GdbLink,
+    frame: &mut InterruptFrame,
+    signal: u8,
+    mem: &mut impl GdbMemory,
+    announce: bool,
+) -> GdbResume {
+    let mut packet = [0u8; GDB_PACKET_MAX];
+    let mut reply = GdbReply::new();
+    if announce {
+        reply.stop(signal);
+        gdb_send_packet(link, reply.as_bytes());
+    }
+    loop {
+        let len = gdb_recv_packet(link, &mut packet);
+        reply.clear();
+        let resume = gdb_handle_packet(&packet[..len], frame, signal, mem, &mut reply);
+        match resume {
+            GdbResume::Stay => gdb_send_packet(link, reply.as_bytes()),
+            GdbResume::Detach if !reply.as_bytes().is_empty() => {
+                gdb_send_packet(link, reply.as_bytes())
+            }
+            _ => {}
+        }
+        match resume {
+            GdbResume::Stay => continue,
+            GdbResume::Step => frame.rflags |= RFLAGS_TF,
+            GdbResume::Continue | GdbResume::Detach => frame.rflags &= !RFLAGS_TF,
+        }
+        return resume;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GdbConfig {
+    pub enabled: bool,
+    pub wait: bool,
+}
+
+pub fn config_from_cmdline(cmdline: Option<&str>) -> GdbConfig {
+    let mut cfg = GdbConfig {
+        enabled: false,
+        wait: false,
+    };
+    let Some(cmdline) = cmdline else {
+        return cfg;
+    };
+    for token in cmdline.split_whitespace() {
+        if let Some(value) = token.strip_prefix("gdb=") {
+            cfg.enabled = parse_bool(value).unwrap_or(false);
+        } else if let Some(value) = token.strip_prefix("gdb.wait=") {
+            cfg.wait = parse_bool(value).unwrap_or(false);
+        }
+    }
+    cfg
+}
+
+static GDB_ENABLED: StateFlag = StateFlag::new();
+/// Held by the CPU talking to GDB; other CPUs that stop wait for it.
+static GDB_IN_STUB: StateFlag = StateFlag::new();
+/// GDB is waiting for a stop reply after a continue or step.
+static GDB_RESUMED: AtomicBool = AtomicBool::new(false);
+
+struct Com2;
+
+impl GdbLink for Com2 {
+    fn read_byte(&mut self) -> u8 {
+        loop {
+            if let Some(byte) = serial_poll_byte(COM2.address()) {
+                return byte;
+            }
+            cpu::pause();
+        }
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        // SAFETY: COM2 was initialised at boot and only the CPU holding
+        // GDB_IN_STUB touches it.
+        unsafe { serial_putc(COM2, byte) };
+    }
+}
+
+/// Kernel memory through the current kernel page tables.
+struct KernelMemory;
+
+impl KernelMemory {
+    /// HHDM alias of `addr` and the bytes left in its page, up to `len`.
+    fn alias(addr: u64, len: usize) -> Option<(*mut u8, usize)> {
+        let vaddr = VirtAddr::try_new(addr)?;
+        let phys = paging::virt_to_phys(vaddr);
+        if phys.is_null() {
+            return None;
+        }
+        let alias = phys.to_virt_checked()?;
+        let len = len.min((PAGE_SIZE_4KB - (addr & (PAGE_SIZE_4KB - 1))) as usize);
+        Some((alias.as_u64() as *mut u8, len))
+    }
+}
+
+impl GdbMemory for KernelMemory {
+    fn read(&mut self, addr: u64, out: &mut [u8]) -> usize {
+        let mut done = 0;
+        while done < out.len() {
+            let at = addr.wrapping_add(done as u64);
+            let Some((src, n)) = Self::alias(at, out.len() - done) else {
+                break;
+            };
+            // SAFETY: `src` maps a present page and `n` stops at its end.
+            unsafe { ptr::copy_nonoverlapping(src, out[done..].as_mut_ptr(), n) };
+            done += n;
+        }
+        done
+    }
+
+    fn write(&mut self, addr: u64, data: &[u8]) -> usize {
+        let mut done = 0;
+        while done < data.len() {
+            let at = addr.wrapping_add(done as u64);
+            let Some((dst, n)) = Self::alias(at, data.len() - done) else {
+                break;
+            };
+            // SAFETY: as in `read`; the HHDM alias is writable even where
+            // the kernel mapping is not.
+            unsafe { ptr::copy_nonoverlapping(data[done..].as_ptr(), dst, n) };
+            done += n;
+        }
+        done
+    }
+}
+
+/// Whether kernel traps and panics stop in the stub.
+pub fn gdbstub_enabled() -> bool {
+    GDB_ENABLED.is_active()
+}
+
+/// Stop in an `int3`, which the exception path hands to the stub.
+pub fn gdbstub_breakpoint() {
+    // SAFETY: a breakpoint trap returns to the next instruction.
+    unsafe { asm!("int3", options(nomem, nostack)) };
+}
+
+fn gdbstub_stop(frame: &mut InterruptFrame, signal: u8) {
+    let flags = cpu::save_flags_cli();
+    while !GDB_IN_STUB.enter() {
+        cpu::pause();
+    }
+    let announce = GDB_RESUMED.load(Ordering::Relaxed);
+    if !announce {
+        // Not through klog: GDB may have stopped us inside it.
+        // SAFETY: COM1 is initialised long before the stub is enabled; at
+        // worst this line interleaves with another CPU's output.
+        unsafe { serial_write_bytes(COM1, b"GDB: stopped, waiting for the debugger on COM2\n") };
+    }
+    let resume = gdb_session(&mut Com2, frame, signal, &mut KernelMemory, announce);
+    GDB_RESUMED.store(resume != GdbResume::Detach, Ordering::Relaxed);
+    GDB_IN_STUB.leave();
+    cpu::restore_flags(flags);
+}
+
+/// Exception path hook for kernel-mode `#BP` and `#DB`.  Returns `false`
+/// when the stub is off and the exception should be handled as usual.
+pub fn gdbstub_trap(frame: *mut InterruptFrame) -> bool {
+    if !gdbstub_enabled() {
+        return false;
+    }
+    // SAFETY: the exception entry path hands us its live frame.
+    let Some(frame) = (unsafe { frame.as_mut() }) else {
+        return false;
+    };
+    gdbstub_stop(frame, GDB_SIGTRAP);
+    true
+}
+
+/// Stop in the stub on panic, at the exception frame if there is one and
+/// at the panic handler's registers otherwise.
+pub fn gdbstub_panic(report: &PanicReport) {
+    // A panic inside the stub would wait on itself.
+    if !gdbstub_enabled() || GDB_IN_STUB.is_active() {
+        return;
+    }
+    let (mut frame, signal) = match report.frame {
+        Some(f) => (*f, gdb_signal_for_vector(f.vector)),
+        None => {
+            let r = &report.regs;
+            let frame = InterruptFrame {
+                r15: r.r15,
+                r14: r.r14,
+                r13: r.r13,
+                r12: r.r12,
+                r11: r.r11,
+                r10: r.r10,
+                r9: r.r9,
+                r8: r.r8,
+                rbp: r.rbp,
+                rdi: r.rdi,
+                rsi: r.rsi,
+                rdx: r.rdx,
+                rcx: r.rcx,
+                rbx: r.rbx,
+                rax: r.rax,
+                vector: 0,
+                error_code: 0,
+                rip: report.rip.unwrap_or(0),
+                cs: 0,
+                rflags: cpu::read_rflags(),
+                rsp: report.rsp,
+                ss: 0,
+            };
+            (frame, GDB_SIGABRT)
+        }
+    };
+    gdbstub_stop(&mut frame, signal);
+}
+
+fn gdbstub_sysrq(code: u8) {
+    if code == SCANCODE_G {
+        klog_info!("GDB: Alt+SysRq+G");
+        gdbstub_breakpoint();
+    }
+}
+
+fn boot_step_gdbstub() -> i32 {
+    let cmdline = boot_get_cmdline();
+    let cmdline_str = if cmdline.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(cmdline) }.to_str().ok()
+    };
+    let cfg = config_from_cmdline(cmdline_str);
+    if !cfg.enabled {
+        return 0;
+    }
+    if !serial_port_present(COM2.address()) || init_port(COM2.address()).is_err() {
+        klog_info!("GDB: no UART on COM2, stub disabled");
+        return -1;
+    }
+    GDB_ENABLED.set_active();
+    keyboard_register_sysrq(gdbstub_sysrq);
+    klog_info!("GDB: stub on COM2, break in with Alt+SysRq+G");
+    if cfg.wait {
+        gdbstub_breakpoint();
+    }
+    0
+}
+
+crate::boot_init!(
+    BOOT_STEP_GDBSTUB,
+    drivers,
+    b"gdb stub\0",
+    boot_step_gdbstub,
+    fallible,
+    flags = boot_init_priority(35)
+);
@@ -0,0 +1,269 @@
+//! GDB stub tests: packet framing and handling against a scripted link and
+//! fake memory, without touching COM2.
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{InterruptFrame, assert_eq_test, assert_test, pass};
+
+use crate::gdbstub::{
+    GDB_SIGTRAP, GdbConfig, GdbLink, GdbMemory, GdbReply, GdbResume, config_from_cmdline,
+    gdb_checksum, gdb_handle_packet, gdb_session, gdb_signal_for_vector,
+};
+
+/// Plays back `input` and records what the stub sends.
+struct ScriptLink<'a> {
+    input: &'a [u8],
+    pos: usize,
+    output: [u8; 256],
+    out_len: usize,
+}
+
+impl<'a> ScriptLink<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            output: [0; 256],
+            out_len: 0,
+        }
+    }
+
+    fn output(&self) -> &[u8] {
+        &self.output[..self.out_len]
+    }
+}
+
+impl GdbLink for ScriptLink<'_> {
+    fn read_byte(&mut self) -> u8 {
+        // Running off the script acks and kills rather than hanging the
+        // test.
+        const TAIL: &[u8] = b"+$k#6b";
+        let byte = match self.input.get(self.pos) {
+            Some(&byte) => byte,
+            None => TAIL[(self.pos - self.input.len()) % TAIL.len()],
+        };
+        self.pos += 1;
+        byte
+    }
+
+    fn write_byte(&mut self, byte: u8) {
+        if let Some(slot) = self.output.get_mut(self.out_len) {
+            *slot = byte;
+            self.out_len += 1;
+        }
+    }
+}
+
+const MEM_BASE: u64 = 0x1000;
+
+/// 16 bytes at `MEM_BASE`; everything else is unmapped.
+struct FakeMemory([u8; 16]);
+
+impl FakeMemory {
+    fn range(&self, addr: u64, len: usize) -> Option<(usize, usize)> {
+        let start = addr.checked_sub(MEM_BASE)? as usize;
+        (start < self.0.len()).then(|| (start, len.min(self.0.len() - start)))
+    }
+}
+
+impl GdbMemory for FakeMemory {
+    fn read(&mut self, addr: u64, out: &mut [u8]) -> usize {
+        let Some((start, n)) = self.range(addr, out.len()) else {
+            return 0;
+        };
+        out[..n].copy_from_slice(&self.0[start..start + n]);
+        n
+    }
+
+    fn write(&mut self, addr: u64, data: &[u8]) -> usize {
+        let Some((start, n)) = self.range(addr, data.len()) else {
+            return 0;
+        };
+        self.0[start..start + n].copy_from_slice(&data[..n]);
+        n
+    }
+}
+
+fn frame() -> InterruptFrame {
+    InterruptFrame {
+        r15: 0,
+        r14: 0,
+        r13: 0,
+        r12: 0,
+        r11: 0,
+        r10: 0,
+        r9: 0,
+        r8: 0,
+        rbp: 0,
+        rdi: 0,
+        rsi: 0,
+        rdx: 0,
+        rcx: 0,
+        rbx: 0,
+        rax: 0x1122_3344_5566_7788,
+        vector: 3,
+        error_code: 0,
+        rip: 0xFFFF_FFFF_8000_1000,
+        cs: 0x08,
+        rflags: 0x202,
+        rsp: 0xFFFF_FFFF_8010_0000,
+        ss: 0x10,
+    }
+}
+
+fn handle(packet: &[u8], frame: &mut InterruptFrame, reply: &mut GdbReply) -> GdbResume {
+    let mut mem = FakeMemory(*b"0123456789abcdef");
+    reply.clear();
+    gdb_handle_packet(packet, frame, GDB_SIGTRAP, &mut mem, reply)
+}
+
+pub fn test_gdb_framing() -> TestResult {
+    assert_eq_test!(gdb_checksum(b"?"), 0x3F);
+    assert_eq_test!(gdb_checksum(b"S05"), 0xB8);
+
+    // `?` with a bad checksum is nacked and resent; the stop reply is acked
+    // by GDB before it continues.
+    let mut link = ScriptLink::new(b"$?#00$?#3f+$c#63");
+    let mut f = frame();
+    let mut mem = FakeMemory([0; 16]);
+    let resume = gdb_session(&mut link, &mut f, GDB_SIGTRAP, &mut mem, false);
+    assert_eq_test!(resume, GdbResume::Continue);
+    assert_eq_test!(link.output(), &b"-+$S05#b8+"[..]);
+
+    // An announced stop goes out before any packet is read, and a nacked
+    // reply is sent again.
+    let mut link = ScriptLink::new(b"-+$D#44+");
+    let resume = gdb_session(&mut link, &mut f, GDB_SIGTRAP, &mut mem, true);
+    assert_eq_test!(resume, GdbResume::Detach);
+    assert_eq_test!(link.output(), &b"$S05#b8$S05#b8+$OK#9a"[..]);
+    pass!()
+}
+
+pub fn test_gdb_registers() -> TestResult {
+    let mut f = frame();
+    let mut reply = GdbReply::new();
+    assert_eq_test!(handle(b"g", &mut f, &mut reply), GdbResume::Stay);
+    let regs = reply.as_bytes();
+    assert_eq_test!(regs.len(), 17 * 16 + 7 * 8);
+    assert_eq_test!(&regs[..16], &b"8877665544332211"[..], "rax little-endian");
+    assert_eq_test!(&regs[16 * 16..17 * 16], &b"00100080ffffffff"[..], "rip");
+    assert_eq_test!(&regs[17 * 16..17 * 16 + 8], &b"02020000"[..], "eflags");
+
+    handle(b"P10=efbeadde00000000", &mut f, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"OK"[..]);
+    assert_eq_test!(f.rip, 0xDEAD_BEEF);
+    handle(b"p10", &mut f, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"efbeadde00000000"[..]);
+
+    // Writing back what `g` returned, with rbx changed, only changes rbx.
+    let mut f2 = frame();
+    handle(b"g", &mut f2, &mut reply);
+    let mut packet = [0u8; 1 + 17 * 16 + 7 * 8];
+    packet[0] = b'G';
+    packet[1..].copy_from_slice(reply.as_bytes());
+    packet[1 + 16..1 + 32].copy_from_slice(b"0100000000000000");
+    handle(&packet, &mut f2, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"OK"[..]);
+    assert_eq_test!(f2.rbx, 1);
+    assert_eq_test!(f2.rax, 0x1122_3344_5566_7788);
+    assert_eq_test!(f2.cs, 0x08);
+
+    handle(b"p99", &mut f, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"E01"[..], "no such register");
+    pass!()
+}
+
+pub fn test_gdb_memory() -> TestResult {
+    let mut f = frame();
+    let mut mem = FakeMemory(*b"0123456789abcdef");
+    let mut reply = GdbReply::new();
+
+    gdb_handle_packet(b"m1002,4", &mut f, GDB_SIGTRAP, &mut mem, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"32333435"[..]);
+
+    reply.clear();
+    gdb_handle_packet(b"m100e,8", &mut f, GDB_SIGTRAP, &mut mem, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"6566"[..], "short read at the end");
+
+    reply.clear();
+    gdb_handle_packet(b"m9000,4", &mut f, GDB_SIGTRAP, &mut mem, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"E0e"[..]);
+
+    reply.clear();
+    gdb_handle_packet(b"M1000,2:cc90", &mut f, GDB_SIGTRAP, &mut mem, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"OK"[..]);
+    assert_eq_test!(&mem.0[..3], &[0xCC, 0x90, b'2'][..]);
+
+    reply.clear();
+    gdb_handle_packet(b"M100f,2:0000", &mut f, GDB_SIGTRAP, &mut mem, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"E0e"[..], "write past the end");
+
+    reply.clear();
+    gdb_handle_packet(b"M1000,2:cc", &mut f, GDB_SIGTRAP, &mut mem, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"E01"[..], "length mismatch");
+    pass!()
+}
+
+pub fn test_gdb_resume() -> TestResult {
+    let mut f = frame();
+    let mut reply = GdbReply::new();
+    assert_eq_test!(handle(b"s", &mut f, &mut reply), GdbResume::Step);
+    assert_eq_test!(handle(b"c4000", &mut f, &mut reply), GdbResume::Continue);
+    assert_eq_test!(f.rip, 0x4000);
+    assert_eq_test!(handle(b"k", &mut f, &mut reply), GdbResume::Detach);
+    assert_test!(reply.as_bytes().is_empty());
+
+    assert_eq_test!(handle(b"?", &mut f, &mut reply), GdbResume::Stay);
+    assert_eq_test!(reply.as_bytes(), &b"S05"[..]);
+    handle(b"qSupported:multiprocess+", &mut f, &mut reply);
+    assert_eq_test!(reply.as_bytes(), &b"PacketSize=400"[..]);
+    handle(b"vMustReplyEmpty", &mut f, &mut reply);
+    assert_test!(
+        reply.as_bytes().is_empty(),
+        "unsupported packets get an empty reply"
+    );
+
+    // The session sets the trap flag to step and clears it to continue.
+    let mut mem = FakeMemory([0; 16]);
+    let mut link = ScriptLink::new(b"$s#73");
+    gdb_session(&mut link, &mut f, GDB_SIGTRAP, &mut mem, false);
+    assert_test!(f.rflags & (1 << 8) != 0, "TF set for a step");
+    let mut link = ScriptLink::new(b"+$c#63");
+    gdb_session(&mut link, &mut f, GDB_SIGTRAP, &mut mem, true);
+    assert_test!(f.rflags & (1 << 8) == 0, "TF cleared to continue");
+    pass!()
+}
+
+pub fn test_gdb_config() -> TestResult {
+    let off = GdbConfig {
+        enabled: false,
+        wait: false,
+    };
+    assert_eq_test!(config_from_cmdline(None), off);
+    assert_eq_test!(config_from_cmdline(Some("itests=off")), off);
+    assert_eq_test!(
+        config_from_cmdline(Some("gdb=1 gdb.wait=on")),
+        GdbConfig {
+            enabled: true,
+            wait: true,
+        }
+    );
+    assert_eq_test!(config_from_cmdline(Some("gdb=maybe")), off);
+
+    assert_eq_test!(gdb_signal_for_vector(3), GDB_SIGTRAP);
+    assert_eq_test!(gdb_signal_for_vector(1), GDB_SIGTRAP);
+    assert_eq_test!(gdb_signal_for_vector(14), 11);
+    assert_eq_test!(gdb_signal_for_vector(6), 4);
+    assert_eq_test!(gdb_signal_for_vector(8), 6);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    gdbstub,
+    [
+        test_gdb_framing,
+        test_gdb_registers,
+        test_gdb_memory,
+        test_gdb_resume,
+        test_gdb_config,
+    ]
+);
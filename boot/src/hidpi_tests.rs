@@ -0,0 +1,111 @@
+//! HiDPI tests: the display's default UI scale, `surface_set_buffer_scale`
+//! reaching the compositor's window list, and the magnification the
+//! compositor draws low-DPI buffers with.
+
+use slopos_abi::display::DisplayInfo;
+use slopos_abi::error::CompositorError;
+use slopos_abi::pixel::PixelFormat;
+use slopos_gfx::scale::{ScaleFilter, ScaledSource};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+use slopos_video::compositor_context::{drain_queue, surface_set_buffer_scale};
+
+use crate::surface_fixture::SurfaceFixture;
+
+const TEST_TASK: u32 = 0xF00F;
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+fn fixture() -> Option<SurfaceFixture> {
+    SurfaceFixture::new(TEST_TASK, WIDTH, HEIGHT)
+}
+
+pub fn test_default_display_scale() -> TestResult {
+    assert_eq_test!(DisplayInfo::default_scale(768), 1);
+    assert_eq_test!(DisplayInfo::default_scale(1080), 1);
+    assert_eq_test!(DisplayInfo::default_scale(2160), 2);
+    assert_eq_test!(
+        DisplayInfo::default_scale(20_000),
+        DisplayInfo::MAX_SCALE,
+        "scale not clamped"
+    );
+    let info = DisplayInfo::new(3840, 2160, 3840 * 4, PixelFormat::Xrgb8888);
+    assert_eq_test!(info.scale_factor(), 2);
+    pass!()
+}
+
+pub fn test_window_size_is_in_desktop_pixels() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let Some(info) = fx.info() else {
+        return fail!("surface not enumerated");
+    };
+    assert_test!(info.content_scale >= 1, "content scale of zero");
+    assert_eq_test!(info.width, WIDTH * info.content_scale as u32);
+    assert_eq_test!(info.buffer_size(), (WIDTH, HEIGHT));
+
+    // A buffer drawn at the largest scale is never magnified.
+    assert_eq_test!(
+        surface_set_buffer_scale(TEST_TASK, DisplayInfo::MAX_SCALE),
+        Ok(())
+    );
+    drain_queue();
+    let Some(info) = fx.info() else {
+        return fail!("surface not enumerated");
+    };
+    assert_eq_test!(info.content_scale, 1);
+    assert_eq_test!(info.width, WIDTH);
+    assert_test!(info.is_dirty(), "scale change did not damage the window");
+    pass!()
+}
+
+pub fn test_buffer_scale_rejects_out_of_range() -> TestResult {
+    let Some(_fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    assert_eq_test!(
+        surface_set_buffer_scale(TEST_TASK, 0),
+        Err(CompositorError::InvalidArgument)
+    );
+    assert_eq_test!(
+        surface_set_buffer_scale(TEST_TASK, DisplayInfo::MAX_SCALE + 1),
+        Err(CompositorError::InvalidArgument)
+    );
+    pass!()
+}
+
+pub fn test_scaled_source_sampling() -> TestResult {
+    // Black then white, one row.
+    let data = [0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+    let src = ScaledSource {
+        data: &data,
+        pitch: 8,
+        width: 2,
+        height: 1,
+        bytes_pp: 4,
+        scale: 2,
+    };
+    assert_eq_test!(src.scaled_size(), (4, 2));
+
+    let nearest: [u8; 4] =
+        core::array::from_fn(|x| src.sample(x as u32, 0, ScaleFilter::Nearest)[0]);
+    assert_eq_test!(nearest, [0x00, 0x00, 0xFF, 0xFF]);
+
+    let linear: [u8; 4] = core::array::from_fn(|x| src.sample(x as u32, 1, ScaleFilter::Linear)[0]);
+    assert_eq_test!(linear, [0x00, 0x3F, 0xBF, 0xFF]);
+
+    // Past the edge repeats the edge pixel.
+    assert_eq_test!(src.sample(9, 9, ScaleFilter::Linear), [0xFF; 4]);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    hidpi,
+    [
+        test_default_display_scale,
+        test_window_size_is_in_desktop_pixels,
+        test_buffer_scale_rejects_out_of_range,
+        test_scaled_source_sampling,
+    ]
+);
@@ -8,8 +8,9 @@ use slopos_lib::cpu;
 use slopos_lib::string::cstr_to_str;
 use slopos_lib::{klog_debug, klog_info};
 
+use crate::gdbstub;
 use crate::ist_stacks;
-use crate::panic::set_panic_cpu_state;
+use crate::panic::set_panic_interrupt_frame;
 
 global_asm!(include_str!("../idt_handlers.s"));
 
@@ -76,10 +77,14 @@ pub enum ExceptionMode {
 }
 
 use slopos_abi::addr::{PhysAddr, VirtAddr};
+use slopos_core::crash;
 use slopos_core::irq::irq_dispatch;
 use slopos_core::syscall::syscall_handle;
 use slopos_drivers::apic::send_eoi;
 use slopos_lib::kdiag_dump_interrupt_frame;
+use slopos_lib::ktrace::{
+    KTRACE_NO_TASK, KtraceKind, ktrace_is_enabled, ktrace_record, ktrace_record_page_fault,
+};
 use slopos_mm::cow;
 use slopos_mm::demand;
 use slopos_mm::hhdm::PhysAddrHhdm;
@@ -95,7 +100,7 @@ use slopos_core::task::task_terminate;
 
 use slopos_abi::task::{INVALID_PROCESS_ID, INVALID_TASK_ID, TaskExitReason, TaskFaultReason};
 use slopos_core::scheduler::task_struct::Task;
-use slopos_mm::memory_layout_defs::MAX_PROCESSES;
+use slopos_mm::memory_layout_defs::{MAX_PROCESSES, USER_SPACE_END_VA};
 
 unsafe extern "C" {
     fn isr0();
@@ -377,6 +382,10 @@ impl Drop for IstPreemptHold {
 /// Implementation of common_exception_handler - called from FFI boundary
 pub fn common_exception_handler_impl(frame: *mut slopos_lib::InterruptFrame) {
     let frame_ref = unsafe { &mut *frame };
+
+    // An interrupt taken in the middle of a user copy arrives with AC set;
+    // the handler itself must run under SMAP.  iretq restores the old AC.
+    cpu::smap::clac();
     let vector = (frame_ref.vector & 0xFF) as u8;
 
     // Prevent deferred rescheduling during IST-based exception handlers.
@@ -422,8 +431,12 @@ pub fn common_exception_handler_impl(frame: *mut slopos_lib::InterruptFrame) {
     // LAPIC timer: per-CPU preemption tick — handled directly, not through
     // the IOAPIC IRQ dispatch table.  Each CPU has its own LAPIC timer.
     if vector == LAPIC_TIMER_VECTOR {
+        ktrace_record(KtraceKind::IrqEnter, vector as u32, 0);
         slopos_core::irq::increment_timer_ticks();
+        slopos_lib::boot_watchdog::boot_watchdog_tick(frame);
+        crate::watchdog::watchdog_pet(frame);
         slopos_core::sched::scheduler_handle_timer_interrupt(frame);
+        ktrace_record(KtraceKind::IrqExit, vector as u32, 0);
         send_eoi();
         scheduler_handoff_on_trap_exit(TrapExitSource::Irq);
         return;
@@ -435,11 +448,27 @@ pub fn common_exception_handler_impl(frame: *mut slopos_lib::InterruptFrame) {
     }
 
     if vector == EXCEPTION_PAGE_FAULT {
+        if ktrace_is_enabled() {
+            let task = scheduler_get_current_task();
+            let task_id = if task.is_null() {
+                KTRACE_NO_TASK
+            } else {
+                unsafe { (*task).task_id }
+            };
+            ktrace_record_page_fault(cpu::read_cr2(), frame_ref.error_code as u32, task_id);
+        }
         if try_handle_page_fault(frame) {
             return;
         }
     }
 
+    if (vector == EXCEPTION_BREAKPOINT || vector == EXCEPTION_DEBUG)
+        && !in_user(frame_ref)
+        && gdbstub::gdbstub_trap(frame)
+    {
+        return;
+    }
+
     let cr2 = cpu::read_cr2();
     klog_debug!(
         "EXCEPTION: vec={} rip=0x{:x} err=0x{:x} cs=0x{:x} ss=0x{:x} cr2=0x{:x}",
@@ -607,6 +636,7 @@ fn terminate_user_task(
     );
     kdiag_dump_interrupt_frame(frame as *const _);
     if !task.is_null() {
+        crash::crash_capture(unsafe { &*task }, frame, cr2, reason);
         unsafe {
             (*task).exit_reason = TaskExitReason::UserFault;
             (*task).fault_reason = reason;
@@ -806,6 +836,14 @@ fn exception_page_fault(frame: *mut slopos_lib::InterruptFrame) {
         privilege
     );
 
+    if !from_user && fault_addr < USER_SPACE_END_VA && (frame_ref.error_code & 1) != 0 {
+        if (frame_ref.error_code & 0x10) != 0 && cpu::smap::smep_enabled() {
+            klog_info!("SMEP: kernel tried to execute a user page");
+        } else if cpu::smap::smap_enabled() {
+            klog_info!("SMAP: kernel touched a user page outside a user copy");
+        }
+    }
+
     if from_user {
         log_user_page_fault_diagnostics(frame_ref, fault_addr);
         terminate_user_task(
@@ -884,6 +922,6 @@ fn log_user_page_fault_diagnostics(frame_ref: &slopos_lib::InterruptFrame, fault
 
 fn panic_with_frame(message: &str, frame: *mut slopos_lib::InterruptFrame) {
     let frame_ref = unsafe { &*frame };
-    set_panic_cpu_state(frame_ref.rip, frame_ref.rsp);
+    set_panic_interrupt_frame(frame_ref);
     panic!("{}", message);
 }
@@ -0,0 +1,313 @@
+//! Image decoding and scaling tests.
+//!
+//! A 2x2 BMP is built in memory: red and green on the top row, blue and
+//! white below, stored bottom-up unless a test flips it.  The PPM and QOI
+//! tests decode small files written out by hand.
+
+use slopos_abi::draw::{Canvas, Color32};
+use slopos_gfx::DrawBuffer;
+use slopos_gfx::image::{
+    BMP_MAGIC, Bmp, ImageError, ImageFormat, Ppm, Qoi, ScaleMode, draw_image_scaled,
+};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+/// File and info headers, then two 24-bit rows padded to 8 bytes.
+const BMP_LEN: usize = 54 + 2 * 8;
+
+const RED: Color32 = Color32::rgb(0xFF, 0, 0);
+const GREEN: Color32 = Color32::rgb(0, 0xFF, 0);
+const BLUE: Color32 = Color32::rgb(0, 0, 0xFF);
+const WHITE: Color32 = Color32::rgb(0xFF, 0xFF, 0xFF);
+const GREY: Color32 = Color32::rgb(0x40, 0x40, 0x40);
+
+fn test_bmp() -> [u8; BMP_LEN] {
+    let mut data = [0u8; BMP_LEN];
+    data[..2].copy_from_slice(&BMP_MAGIC);
+    data[2..6].copy_from_slice(&(BMP_LEN as u32).to_le_bytes());
+    data[10..14].copy_from_slice(&54u32.to_le_bytes());
+    data[14..18].copy_from_slice(&40u32.to_le_bytes());
+    data[18..22].copy_from_slice(&2i32.to_le_bytes());
+    data[22..26].copy_from_slice(&2i32.to_le_bytes());
+    data[26..28].copy_from_slice(&1u16.to_le_bytes());
+    data[28..30].copy_from_slice(&24u16.to_le_bytes());
+    // Bottom row first, BGR.
+    data[54..60].copy_from_slice(&[0xFF, 0, 0, 0xFF, 0xFF, 0xFF]);
+    data[62..68].copy_from_slice(&[0, 0, 0xFF, 0, 0xFF, 0]);
+    data
+}
+
+/// Draw the test image into a `w`x`h` canvas and read back every pixel.
+fn render(mode: ScaleMode, w: u32, h: u32) -> Option<[Color32; 16]> {
+    let data = test_bmp();
+    let image = Bmp::parse(&data).ok()?;
+    let mut pixels = [0u8; 4 * 4 * 4];
+    let mut buf = DrawBuffer::new(&mut pixels, w, h, w as usize * 4, 4)?;
+    draw_image_scaled(&mut buf, &image, mode, GREY);
+
+    let mut out = [Color32::TRANSPARENT; 16];
+    for (i, px) in out.iter_mut().enumerate().take((w * h) as usize) {
+        let encoded = buf.get_pixel((i as u32 % w) as i32, (i as u32 / w) as i32)?;
+        *px = buf.pixel_format().decode(encoded).with_alpha(0xFF);
+    }
+    Some(out)
+}
+
+pub fn test_bmp_rejects_bad_headers() -> TestResult {
+    let good = test_bmp();
+    assert_test!(Bmp::parse(&good).is_ok(), "valid image rejected");
+
+    let mut bad = good;
+    bad[0] = b'X';
+    assert_eq_test!(Bmp::parse(&bad).err(), Some(ImageError::BadMagic), "magic");
+    assert_eq_test!(
+        Bmp::parse(&good[..60]).err(),
+        Some(ImageError::Truncated),
+        "short pixel data"
+    );
+    let mut bad = good;
+    bad[28] = 8;
+    assert_eq_test!(
+        Bmp::parse(&bad).err(),
+        Some(ImageError::Unsupported),
+        "palettized"
+    );
+    let mut bad = good;
+    bad[30] = 1;
+    assert_eq_test!(
+        Bmp::parse(&bad).err(),
+        Some(ImageError::Unsupported),
+        "RLE compressed"
+    );
+    pass!()
+}
+
+pub fn test_bmp_row_order() -> TestResult {
+    let data = test_bmp();
+    let Ok(image) = Bmp::parse(&data) else {
+        return fail!("image rejected");
+    };
+    assert_eq_test!((image.width(), image.height()), (2, 2), "size");
+    assert_eq_test!(image.pixel(0, 0), RED, "top left");
+    assert_eq_test!(image.pixel(1, 0), GREEN, "top right");
+    assert_eq_test!(image.pixel(0, 1), BLUE, "bottom left");
+
+    // A negative height stores the rows top-down.
+    let mut flipped = data;
+    flipped[22..26].copy_from_slice(&(-2i32).to_le_bytes());
+    let Ok(image) = Bmp::parse(&flipped) else {
+        return fail!("top-down image rejected");
+    };
+    assert_eq_test!(image.pixel(0, 0), BLUE, "top-down top left");
+    assert_eq_test!(image.pixel(1, 1), GREEN, "top-down bottom right");
+    pass!()
+}
+
+pub fn test_scale_modes() -> TestResult {
+    let Some(stretched) = render(ScaleMode::Stretch, 4, 4) else {
+        return fail!("stretch setup failed");
+    };
+    assert_eq_test!(stretched[1], RED, "stretch top left");
+    assert_eq_test!(stretched[2], GREEN, "stretch top right");
+    assert_eq_test!(stretched[15], WHITE, "stretch bottom right");
+
+    let Some(tiled) = render(ScaleMode::Tile, 4, 4) else {
+        return fail!("tile setup failed");
+    };
+    assert_eq_test!(tiled[0], RED, "tile origin");
+    assert_eq_test!(tiled[2], RED, "tile repeats across");
+    assert_eq_test!(tiled[8], RED, "tile repeats down");
+    assert_eq_test!(tiled[13], WHITE, "tile second copy");
+
+    let Some(centred) = render(ScaleMode::Center, 4, 4) else {
+        return fail!("center setup failed");
+    };
+    assert_eq_test!(centred[0], GREY, "center border");
+    assert_eq_test!(centred[5], RED, "center top left");
+    assert_eq_test!(centred[10], WHITE, "center bottom right");
+
+    let Some(cropped) = render(ScaleMode::Center, 1, 1) else {
+        return fail!("crop setup failed");
+    };
+    assert_eq_test!(cropped[0], WHITE, "center crop");
+    pass!()
+}
+
+pub fn test_fit_keeps_aspect_ratio() -> TestResult {
+    // Wider than the image: bars left and right.
+    let Some(wide) = render(ScaleMode::Fit, 4, 2) else {
+        return fail!("wide setup failed");
+    };
+    assert_eq_test!(wide[0], GREY, "wide left bar");
+    assert_eq_test!(wide[1], RED, "wide top left");
+    assert_eq_test!(wide[2], GREEN, "wide top right");
+    assert_eq_test!(wide[6], WHITE, "wide bottom right");
+    assert_eq_test!(wide[7], GREY, "wide right bar");
+
+    // Taller than the image: bars above and below.
+    let Some(tall) = render(ScaleMode::Fit, 2, 4) else {
+        return fail!("tall setup failed");
+    };
+    assert_eq_test!(tall[0], GREY, "tall top bar");
+    assert_eq_test!(tall[2], RED, "tall top left");
+    assert_eq_test!(tall[5], WHITE, "tall bottom right");
+    assert_eq_test!(tall[7], GREY, "tall bottom bar");
+
+    // Same shape: scaled up with no bars.
+    let Some(square) = render(ScaleMode::Fit, 4, 4) else {
+        return fail!("square setup failed");
+    };
+    assert_eq_test!(square[0], RED, "square top left");
+    assert_eq_test!(square[15], WHITE, "square bottom right");
+    pass!()
+}
+
+pub fn test_scale_mode_names() -> TestResult {
+    for mode in [
+        ScaleMode::Center,
+        ScaleMode::Stretch,
+        ScaleMode::Tile,
+        ScaleMode::Fit,
+    ] {
+        assert_eq_test!(ScaleMode::from_name(mode.name()), Some(mode), "round trip");
+    }
+    assert_eq_test!(ScaleMode::from_name(b"zoom"), None, "unknown mode");
+    pass!()
+}
+
+pub fn test_ppm_decoding() -> TestResult {
+    let mut ppm = [0u8; 40];
+    let header = b"P6\n# comment\n2 2\n255\n";
+    ppm[..header.len()].copy_from_slice(header);
+    ppm[header.len()..header.len() + 12]
+        .copy_from_slice(&[0xFF, 0, 0, 0, 0xFF, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+    let ppm = &ppm[..header.len() + 12];
+    let Ok(image) = Ppm::parse(ppm) else {
+        return fail!("PPM rejected");
+    };
+    assert_eq_test!((image.width(), image.height()), (2, 2), "PPM size");
+    assert_eq_test!(image.pixel(1, 0), GREEN, "PPM top right");
+    assert_eq_test!(image.pixel(0, 1), BLUE, "PPM bottom left");
+    assert_eq_test!(
+        Ppm::parse(&ppm[..ppm.len() - 1]).err(),
+        Some(ImageError::Truncated),
+        "short PPM"
+    );
+
+    // Grey samples out of 15 are scaled up to 255.
+    let Ok(grey) = Ppm::parse(b"P5 2 1 15\n\x0F\x05") else {
+        return fail!("PGM rejected");
+    };
+    assert_eq_test!(grey.pixel(0, 0), WHITE, "PGM white");
+    assert_eq_test!(grey.pixel(1, 0), Color32::rgb(85, 85, 85), "PGM grey");
+
+    assert_eq_test!(
+        Ppm::parse(b"P3 1 1 255\n0 0 0\n").err(),
+        Some(ImageError::BadMagic),
+        "ASCII PPM"
+    );
+    assert_eq_test!(
+        Ppm::parse(b"P6 1 1 65535\n\0\0\0\0\0\0").err(),
+        Some(ImageError::Unsupported),
+        "16-bit PPM"
+    );
+    pass!()
+}
+
+/// A 6x1 QOI image using every chunk type: an RGB pixel, a run of two
+/// more, then a diff, a luma and an index chunk.
+const QOI_TEST: [u8; 31] = [
+    b'q', b'o', b'i', b'f', 0, 0, 0, 6, 0, 0, 0, 1, 4, 0, // header
+    0xFE, 0x10, 0x20, 0x30, // RGB
+    0xC1, // run of 2
+    0x76, // diff +1 -1 +0
+    0xAA, 0x5A, // luma dg +10, dr -3, db +2 relative to dg
+    0x15, // index of the first pixel
+    0, 0, 0, 0, 0, 0, 0, 1, // end marker
+];
+
+pub fn test_qoi_decoding() -> TestResult {
+    let Ok(image) = Qoi::parse(&QOI_TEST) else {
+        return fail!("QOI rejected");
+    };
+    assert_eq_test!((image.width(), image.height()), (6, 1), "QOI size");
+    assert_eq_test!(image.decoded_len(), 24, "decoded length");
+
+    let mut out = [0u8; 24];
+    assert_eq_test!(
+        image.decode_into(&mut out[..20]).err(),
+        Some(ImageError::BufferTooSmall),
+        "small buffer"
+    );
+    let Ok(decoded) = image.decode_into(&mut out) else {
+        return fail!("QOI decode failed");
+    };
+    let first = Color32::rgb(0x10, 0x20, 0x30);
+    assert_eq_test!(decoded.pixel(0, 0), first, "RGB chunk");
+    assert_eq_test!(decoded.pixel(2, 0), first, "run chunk");
+    assert_eq_test!(decoded.pixel(3, 0), Color32::rgb(17, 31, 48), "diff chunk");
+    assert_eq_test!(decoded.pixel(4, 0), Color32::rgb(24, 41, 60), "luma chunk");
+    assert_eq_test!(decoded.pixel(5, 0), first, "index chunk");
+    assert_eq_test!(out[3], 0xFF, "opaque alpha");
+
+    // Losing the last chunk leaves a pixel undecoded.
+    let mut short = [0u8; 30];
+    short[..22].copy_from_slice(&QOI_TEST[..22]);
+    short[22..].copy_from_slice(&QOI_TEST[23..]);
+    let Ok(image) = Qoi::parse(&short) else {
+        return fail!("short QOI header rejected");
+    };
+    assert_eq_test!(
+        image.decode_into(&mut out).err(),
+        Some(ImageError::Truncated),
+        "short QOI"
+    );
+
+    let mut bad = QOI_TEST;
+    bad[12] = 2;
+    assert_eq_test!(
+        Qoi::parse(&bad).err(),
+        Some(ImageError::Unsupported),
+        "two channels"
+    );
+    pass!()
+}
+
+pub fn test_image_format_detection() -> TestResult {
+    assert_eq_test!(
+        ImageFormat::detect(&test_bmp()),
+        Some(ImageFormat::Bmp),
+        "BMP"
+    );
+    assert_eq_test!(
+        ImageFormat::detect(b"P6 1 1"),
+        Some(ImageFormat::Ppm),
+        "PPM"
+    );
+    assert_eq_test!(
+        ImageFormat::detect(b"P5 1 1"),
+        Some(ImageFormat::Ppm),
+        "PGM"
+    );
+    assert_eq_test!(
+        ImageFormat::detect(&QOI_TEST),
+        Some(ImageFormat::Qoi),
+        "QOI"
+    );
+    assert_eq_test!(ImageFormat::detect(b"GIF89a"), None, "GIF");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    image,
+    [
+        test_bmp_rejects_bad_headers,
+        test_bmp_row_order,
+        test_scale_modes,
+        test_fit_keeps_aspect_ratio,
+        test_scale_mode_names,
+        test_ppm_decoding,
+        test_qoi_decoding,
+        test_image_format_detection,
+    ]
+);
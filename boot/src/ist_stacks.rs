@@ -59,6 +59,7 @@ use slopos_lib::arch::idt::{
     EXCEPTION_DOUBLE_FAULT, EXCEPTION_GENERAL_PROTECTION, EXCEPTION_PAGE_FAULT,
     EXCEPTION_STACK_FAULT, IRQ_BASE_VECTOR,
 };
+use slopos_lib::stack_watermark::{StackPeak, stack_high_water, stack_poison};
 use slopos_lib::{MAX_CPUS, get_current_cpu, klog_debug, klog_info};
 use slopos_mm::hhdm::PhysAddrHhdm;
 use slopos_mm::memory_layout_defs::{
@@ -187,7 +188,7 @@ impl IstStackConfig {
 /// Runtime metrics for a single IST stack.
 struct IstStackMetrics {
     /// Peak stack usage observed (bytes from top)
-    peak_usage: AtomicU64,
+    peak_usage: StackPeak,
     /// Whether we've reported an out-of-bounds RSP (report once to avoid spam)
     out_of_bounds_reported: AtomicBool,
     /// Number of times this stack was entered
@@ -197,14 +198,14 @@ struct IstStackMetrics {
 impl IstStackMetrics {
     const fn new() -> Self {
         Self {
-            peak_usage: AtomicU64::new(0),
+            peak_usage: StackPeak::new(),
             out_of_bounds_reported: AtomicBool::new(false),
             entry_count: AtomicU64::new(0),
         }
     }
 
     fn reset(&self) {
-        self.peak_usage.store(0, Ordering::Relaxed);
+        self.peak_usage.reset();
         self.out_of_bounds_reported.store(false, Ordering::Relaxed);
         self.entry_count.store(0, Ordering::Relaxed);
     }
@@ -218,19 +219,7 @@ impl IstStackMetrics {
 
     /// Records stack usage. Returns true if this is a new peak.
     fn record_usage(&self, usage: u64) -> bool {
-        let mut current = self.peak_usage.load(Ordering::Relaxed);
-        while usage > current {
-            match self.peak_usage.compare_exchange_weak(
-                current,
-                usage,
-                Ordering::SeqCst,
-                Ordering::Relaxed,
-            ) {
-                Ok(_) => return true,
-                Err(prev) => current = prev,
-            }
-        }
-        false
+        self.peak_usage.record(usage)
     }
 
     /// Increments entry count.
@@ -375,9 +364,11 @@ fn map_stack_pages(stack: &IstStackConfig, stack_base: u64) {
                 stack.name_str()
             );
         };
-        // Zero-initialize the stack page
+        // Zero-initialize the stack page, then poison it for high-water
+        // scans when those are enabled.
         unsafe {
             ptr::write_bytes(virt.as_mut_ptr::<u8>(), 0, PAGE_SIZE_4KB as usize);
+            stack_poison(virt.as_u64(), PAGE_SIZE_4KB);
         }
         if map_page_4kb(
             VirtAddr::new(virt_addr),
@@ -604,11 +595,28 @@ pub fn ist_get_stats(vector: u8) -> Option<(u64, u64)> {
     let idx = find_index_by_vector(vector)?;
     let metrics = &IST_METRICS[idx];
     Some((
-        metrics.peak_usage.load(Ordering::Relaxed),
+        metrics.peak_usage.get(),
         metrics.entry_count.load(Ordering::Relaxed),
     ))
 }
 
+/// Deepest use of an IST stack on any CPU, from scanning for the poison
+/// fill.  Catches handler frames the entry RSP sample cannot see.
+fn scanned_high_water(stack_idx: usize) -> u64 {
+    let mut deepest = 0;
+    for (cpu_id, mapped) in CPU_IST_MAPPED.iter().enumerate() {
+        if !mapped.load(Ordering::Acquire) {
+            continue;
+        }
+        let (_guard_start, _guard_end, stack_base, _stack_top) =
+            stack_bounds_for_cpu(cpu_id, stack_idx);
+        // SAFETY: the stack is mapped and was poisoned by map_stack_pages.
+        let used = unsafe { stack_high_water(stack_base, EXCEPTION_STACK_SIZE, deepest) };
+        deepest = deepest.max(used);
+    }
+    deepest
+}
+
 /// Dumps IST stack statistics to the kernel log.
 ///
 /// Useful for debugging and monitoring stack usage.
@@ -616,7 +624,8 @@ pub fn ist_dump_stats() {
     klog_info!("=== IST Stack Statistics ===");
     for (i, stack) in IST_CONFIGS.iter().enumerate() {
         let metrics = &IST_METRICS[i];
-        let peak = metrics.peak_usage.load(Ordering::Relaxed);
+        metrics.record_usage(scanned_high_water(i));
+        let peak = metrics.peak_usage.get();
         let entries = metrics.entry_count.load(Ordering::Relaxed);
         let pct_tenths = if stack.stack_size > 0 {
             (peak * 1000 / stack.stack_size) as u32
@@ -2,28 +2,71 @@
 #![feature(sync_unsafe_cell)]
 
 pub mod apic_id;
+pub mod autopilot;
 pub mod boot_drivers;
 pub mod boot_impl;
 pub mod boot_memory;
 pub mod boot_services;
+#[cfg(feature = "itests")]
+pub mod boot_watchdog_tests;
 pub mod cpu_verify;
+pub mod crashdump;
+#[cfg(feature = "itests")]
+pub mod crashdump_tests;
+#[cfg(feature = "itests")]
+pub mod cursor_tests;
 pub mod early_init;
+#[cfg(feature = "itests")]
+pub mod fastcopy_tests;
 pub mod ffi_boundary;
+#[cfg(feature = "itests")]
+pub mod font_tests;
+pub mod gdbstub;
+#[cfg(feature = "itests")]
+pub mod gdbstub_tests;
 pub mod gdt;
 pub use gdt::{gdt_set_kernel_rsp0, syscall_msr_init, syscall_update_kernel_rsp};
 #[cfg(feature = "itests")]
 pub mod gdt_tests;
+#[cfg(feature = "itests")]
+pub mod hidpi_tests;
 pub mod idt;
+#[cfg(feature = "itests")]
+pub mod image_tests;
 pub mod ist_stacks;
 pub mod limine_protocol;
+#[cfg(feature = "itests")]
+pub mod opacity_tests;
+#[cfg(feature = "itests")]
+pub mod output_tests;
 pub mod panic;
 #[cfg(feature = "itests")]
+pub mod panic_screen_tests;
+#[cfg(feature = "itests")]
+pub mod present_tests;
+#[cfg(feature = "itests")]
+pub mod resize_tests;
+#[cfg(feature = "itests")]
 pub mod shutdown_tests;
+#[cfg(feature = "itests")]
+pub mod smap_tests;
 pub mod smp;
+#[cfg(feature = "itests")]
+pub mod snap_tests;
 pub mod safe_stack {
     pub use crate::ist_stacks::{safe_stack_guard_fault, safe_stack_init, safe_stack_record_usage};
 }
+pub mod screenshot;
 pub mod shutdown;
+#[cfg(feature = "itests")]
+pub mod surface_fixture;
+#[cfg(feature = "itests")]
+pub mod thumbnail_tests;
+#[cfg(feature = "itests")]
+pub mod vblank_tests;
+pub mod watchdog;
+#[cfg(feature = "itests")]
+pub mod watchdog_tests;
 
 pub use early_init::{
     boot_get_cmdline, boot_get_hhdm_offset, boot_get_memmap, boot_init_run_all,
@@ -35,7 +78,7 @@ pub use limine_protocol::{
     BootFramebuffer, BootInfo, MemmapEntry, MemoryRegion, MemoryRegionKind, boot_info,
     ensure_base_revision, memmap_entry_count, memory_regions,
 };
-pub use panic::{panic_handler_impl, set_panic_cpu_state};
+pub use panic::{panic_handler_impl, set_panic_cpu_state, set_panic_interrupt_frame};
 pub use shutdown::{
     execute_kernel, kernel_drain_serial_output, kernel_quiesce_interrupts, kernel_reboot,
     kernel_shutdown,
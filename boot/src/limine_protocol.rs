@@ -228,10 +228,12 @@ pub fn init_limine_protocol() -> i32 {
     if let Some(ka) = KERNEL_ADDRESS_REQUEST.get_response() {
         info.kernel_phys_base = ka.physical_base();
         info.kernel_virt_base = ka.virtual_base();
+        slopos_mm::aslr::set_kernel_slide(ka.virtual_base());
         klog_debug!(
-            "Kernel phys base: 0x{:x} virt base: 0x{:x}",
+            "Kernel phys base: 0x{:x} virt base: 0x{:x} (slide 0x{:x})",
             ka.physical_base(),
-            ka.virtual_base()
+            ka.virtual_base(),
+            slopos_mm::aslr::kernel_slide()
         );
     }
 
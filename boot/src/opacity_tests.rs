@@ -0,0 +1,152 @@
+//! Window opacity tests: `surface_set_opacity` reaching the compositor's
+//! window list, and the blend routines the compositor composites with.
+
+use slopos_abi::damage::DamageRect;
+use slopos_abi::draw::Color32;
+use slopos_abi::error::CompositorError;
+use slopos_abi::pixel::PixelFormat;
+use slopos_abi::window::{SURFACE_BLEND_PIXEL_ALPHA, WINDOW_OPACITY_OPAQUE};
+use slopos_gfx::DrawBuffer;
+use slopos_gfx::blend::{Blend, alpha_byte, blend_span, fill_rect_blended_clipped, mix};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+use slopos_video::compositor_context::{drain_queue, surface_set_opacity};
+
+use crate::surface_fixture::SurfaceFixture;
+
+const TEST_TASK: u32 = 0xF00E;
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+fn fixture() -> Option<SurfaceFixture> {
+    SurfaceFixture::new(TEST_TASK, WIDTH, HEIGHT)
+}
+
+pub fn test_surface_opacity_reaches_window_list() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let Some(info) = fx.info() else {
+        return fail!("surface not enumerated");
+    };
+    assert_eq_test!(
+        info.opacity,
+        WINDOW_OPACITY_OPAQUE,
+        "new surface not opaque"
+    );
+    assert_eq_test!(info.blend_flags, 0);
+
+    assert_eq_test!(
+        surface_set_opacity(TEST_TASK, 0x80, SURFACE_BLEND_PIXEL_ALPHA),
+        Ok(())
+    );
+    drain_queue();
+    let Some(info) = fx.info() else {
+        return fail!("surface not enumerated");
+    };
+    assert_eq_test!(info.opacity, 0x80);
+    assert_eq_test!(info.blend_flags, SURFACE_BLEND_PIXEL_ALPHA);
+    assert_test!(info.is_dirty(), "opacity change did not damage the window");
+    pass!()
+}
+
+pub fn test_surface_opacity_rejects_unknown_flags() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    assert_eq_test!(
+        surface_set_opacity(TEST_TASK, 0x80, 0x80),
+        Err(CompositorError::InvalidArgument)
+    );
+    drain_queue();
+    assert_eq_test!(fx.info().map(|w| w.opacity), Some(WINDOW_OPACITY_OPAQUE));
+    pass!()
+}
+
+pub fn test_blend_span_opacity() -> TestResult {
+    // Half of white over black, with no per-pixel alpha.
+    let mut dst = [0x00, 0x00, 0x00, 0xFF, 0x10, 0x20, 0x30, 0xFF];
+    let src = [0xFF, 0xFF, 0xFF, 0xFF, 0x10, 0x20, 0x30, 0xFF];
+    let half = Blend {
+        opacity: 0x80,
+        alpha_byte: None,
+    };
+    blend_span(&mut dst, &src, 4, half);
+    assert_eq_test!(dst, [0x80, 0x80, 0x80, 0xFF, 0x10, 0x20, 0x30, 0xFF]);
+
+    let mut dst = [0x12u8; 4];
+    blend_span(&mut dst, &[0x34; 4], 4, Blend::OPAQUE);
+    assert_eq_test!(dst, [0x34; 4], "opaque blend is not a copy");
+
+    let mut dst = [0x12u8; 4];
+    let none = Blend {
+        opacity: 0,
+        alpha_byte: None,
+    };
+    blend_span(&mut dst, &[0x34; 4], 4, none);
+    assert_eq_test!(dst, [0x12; 4], "invisible blend changed the target");
+    pass!()
+}
+
+pub fn test_blend_span_premultiplied_alpha() -> TestResult {
+    let a = alpha_byte(PixelFormat::Argb8888);
+    assert_eq_test!(a, Some(3));
+    assert_eq_test!(alpha_byte(PixelFormat::Xrgb8888), None);
+
+    let per_pixel = Blend {
+        opacity: 0xFF,
+        alpha_byte: a,
+    };
+    // Transparent pixel, then premultiplied half-alpha red, over grey.
+    let mut dst = [0x40, 0x40, 0x40, 0xFF, 0x40, 0x40, 0x40, 0xFF];
+    let src = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x80];
+    blend_span(&mut dst, &src, 4, per_pixel);
+    assert_eq_test!(dst, [0x40, 0x40, 0x40, 0xFF, 0x20, 0x20, 0xA0, 0xFF]);
+
+    // Window opacity scales per-pixel alpha as well.
+    let mut dst = [0x00, 0x00, 0x00, 0xFF];
+    let half = Blend {
+        opacity: 0x80,
+        alpha_byte: a,
+    };
+    blend_span(&mut dst, &[0x00, 0x00, 0xFF, 0xFF], 4, half);
+    assert_eq_test!(dst, [0x00, 0x00, 0x80, 0xFF]);
+    pass!()
+}
+
+pub fn test_fill_rect_blended_clipped() -> TestResult {
+    let mut data = [0u8; 4 * 4 * 4];
+    let Some(mut buf) = DrawBuffer::new(&mut data, 4, 4, 16, 4) else {
+        return fail!("draw buffer");
+    };
+    buf.set_pixel_format(PixelFormat::Argb8888);
+    let clip = DamageRect {
+        x0: 0,
+        y0: 0,
+        x1: 1,
+        y1: 3,
+    };
+    fill_rect_blended_clipped(&mut buf, 1, 1, 4, 4, Color32::WHITE.with_alpha(0x80), &clip);
+    let pixel = |buf: &DrawBuffer, x: usize, y: usize| {
+        let at = y * 16 + x * 4;
+        [buf.data()[at], buf.data()[at + 1], buf.data()[at + 2]]
+    };
+    assert_eq_test!(pixel(&buf, 1, 1), [0x80; 3]);
+    assert_eq_test!(pixel(&buf, 0, 1), [0; 3], "filled outside the rect");
+    assert_eq_test!(pixel(&buf, 2, 1), [0; 3], "filled outside the clip");
+
+    let grey = mix(Color32::BLACK, Color32::WHITE, 0x80);
+    assert_eq_test!(grey, Color32::rgb(0x80, 0x80, 0x80));
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    opacity,
+    [
+        test_surface_opacity_reaches_window_list,
+        test_surface_opacity_rejects_unknown_flags,
+        test_blend_span_opacity,
+        test_blend_span_premultiplied_alpha,
+        test_fill_rect_blended_clipped,
+    ]
+);
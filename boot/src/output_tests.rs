@@ -0,0 +1,154 @@
+//! Display output tests: the single-framebuffer fallback, the primary
+//! flag, and configure requests validated before reaching the backend,
+//! using a fake two-output backend swapped in for each test.
+
+use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+
+use slopos_abi::display::{MAX_OUTPUTS, OUTPUT_CONFIGURE_PRIMARY};
+use slopos_abi::{CompositorError, OutputInfo};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+use slopos_video::framebuffer;
+use slopos_video::output::{
+    OutputBackend, output_configure, output_enumerate, swap_output_backend,
+};
+
+static MOVES: AtomicU32 = AtomicU32::new(0);
+static RIGHT_X: AtomicI32 = AtomicI32::new(640);
+
+/// Two 640x480 outputs side by side; output 1 may sit at x 0..=640.
+fn fake_enumerate(out: &mut [OutputInfo]) -> usize {
+    let outputs = [
+        OutputInfo::new(0, b"left", 0, 0, 640, 480),
+        OutputInfo::new(1, b"right", RIGHT_X.load(Ordering::Relaxed), 0, 640, 480),
+    ];
+    let count = outputs.len().min(out.len());
+    out[..count].copy_from_slice(&outputs[..count]);
+    count
+}
+
+fn fake_set_position(id: u32, x: i32, y: i32) -> bool {
+    MOVES.fetch_add(1, Ordering::Relaxed);
+    if id != 1 || y != 0 || !(0..=640).contains(&x) {
+        return false;
+    }
+    RIGHT_X.store(x, Ordering::Relaxed);
+    true
+}
+
+/// The fake backend, installed until drop along with a primary of 0.
+struct FakeOutputs {
+    saved: Option<OutputBackend>,
+}
+
+impl FakeOutputs {
+    fn install() -> Self {
+        MOVES.store(0, Ordering::Relaxed);
+        RIGHT_X.store(640, Ordering::Relaxed);
+        let fake = Self {
+            saved: swap_output_backend(Some(OutputBackend {
+                enumerate: fake_enumerate,
+                set_position: fake_set_position,
+            })),
+        };
+        let _ = output_configure(0, 0, 0, 0, 0, OUTPUT_CONFIGURE_PRIMARY);
+        fake
+    }
+}
+
+impl Drop for FakeOutputs {
+    fn drop(&mut self) {
+        let _ = output_configure(0, 0, 0, 0, 0, OUTPUT_CONFIGURE_PRIMARY);
+        swap_output_backend(self.saved.take());
+    }
+}
+
+fn enumerate() -> ([OutputInfo; MAX_OUTPUTS], usize) {
+    let mut outputs = [OutputInfo::default(); MAX_OUTPUTS];
+    let count = output_enumerate(&mut outputs);
+    (outputs, count)
+}
+
+pub fn test_output_single_framebuffer() -> TestResult {
+    let saved = swap_output_backend(None);
+    let (outputs, count) = enumerate();
+    let moved = output_configure(0, 10, 0, 0, 0, 0);
+    swap_output_backend(saved);
+
+    let Some(info) = framebuffer::get_display_info() else {
+        assert_eq_test!(count, 0, "outputs without a framebuffer");
+        return pass!();
+    };
+    assert_eq_test!(count, 1);
+    assert_eq_test!(
+        (outputs[0].width, outputs[0].height),
+        (info.width, info.height)
+    );
+    assert_eq_test!(outputs[0].name_str(), "fb0");
+    assert_test!(outputs[0].is_primary(), "sole output not primary");
+    assert_eq_test!(moved, Err(CompositorError::InvalidArgument), "fb0 moved");
+    pass!()
+}
+
+pub fn test_output_primary_flag() -> TestResult {
+    let _fake = FakeOutputs::install();
+    let (outputs, count) = enumerate();
+    assert_eq_test!(count, 2);
+    assert_test!(outputs[0].is_primary() && !outputs[1].is_primary());
+
+    assert_test!(output_configure(1, 640, 0, 0, 0, OUTPUT_CONFIGURE_PRIMARY).is_ok());
+    let (outputs, _) = enumerate();
+    assert_test!(!outputs[0].is_primary() && outputs[1].is_primary());
+    assert_eq_test!(MOVES.load(Ordering::Relaxed), 0, "unchanged position moved");
+
+    // A short buffer still learns how many outputs there are.
+    let mut first = [OutputInfo::default(); 1];
+    assert_eq_test!(output_enumerate(&mut first), 2);
+    assert_test!(first[0].id == 0 && !first[0].is_primary());
+    pass!()
+}
+
+pub fn test_output_configure_validation() -> TestResult {
+    let _fake = FakeOutputs::install();
+    let invalid = Err(CompositorError::InvalidArgument);
+    assert_eq_test!(
+        output_configure(7, 0, 0, 0, 0, 0),
+        invalid,
+        "unknown output"
+    );
+    assert_eq_test!(
+        output_configure(1, 640, 0, 0, 0, 1 << 5),
+        invalid,
+        "bad flags"
+    );
+    assert_eq_test!(
+        output_configure(1, 640, 0, 800, 600, 0),
+        invalid,
+        "new mode"
+    );
+    assert_eq_test!(
+        MOVES.load(Ordering::Relaxed),
+        0,
+        "bad request reached backend"
+    );
+
+    assert_eq_test!(
+        output_configure(1, 900, 0, 0, 0, 0),
+        invalid,
+        "off the desktop"
+    );
+    assert_test!(output_configure(1, 320, 0, 640, 480, 0).is_ok());
+    let (outputs, _) = enumerate();
+    assert_eq_test!(outputs[1].x, 320);
+    assert_test!(outputs[1].contains(320, 0) && outputs[0].contains(320, 0));
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    output,
+    [
+        test_output_single_framebuffer,
+        test_output_primary_flag,
+        test_output_configure_validation,
+    ]
+);
@@ -1,22 +1,30 @@
+use core::cell::SyncUnsafeCell;
 use core::ffi::c_int;
 use core::fmt::Write;
 use core::panic::PanicInfo;
 use core::sync::atomic::{AtomicU64, Ordering};
 
-use slopos_drivers::keyboard::poll_wait_enter;
+use slopos_drivers::keyboard::poll_scancode;
 use slopos_drivers::serial;
 use slopos_lib::panic_recovery;
 use slopos_lib::stacktrace::{self, StacktraceEntry};
-use slopos_lib::{StateFlag, cpu};
+use slopos_lib::{InterruptFrame, KLOG_TAIL_LEN, StateFlag, cpu, kdiag, klog_tail};
 use slopos_mm::memory_init::is_memory_system_initialized;
-use slopos_video::panic_screen;
+use slopos_video::panic_screen::{self, PanicKey, PanicReport};
 
+use crate::crashdump;
+use crate::gdbstub;
 use crate::shutdown::{execute_kernel, kernel_shutdown};
 
 static PANIC_IN_PROGRESS: StateFlag = StateFlag::new();
 static PANIC_RIP: AtomicU64 = AtomicU64::new(0);
 static PANIC_RSP: AtomicU64 = AtomicU64::new(0);
 static PANIC_HAS_CPU_STATE: StateFlag = StateFlag::new();
+static PANIC_FRAME: SyncUnsafeCell<Option<InterruptFrame>> = SyncUnsafeCell::new(None);
+static PANIC_HAS_FRAME: StateFlag = StateFlag::new();
+/// Log tail copied for the panic screen; only the CPU that wins
+/// `PANIC_IN_PROGRESS` touches it.
+static PANIC_LOG: SyncUnsafeCell<[u8; KLOG_TAIL_LEN]> = SyncUnsafeCell::new([0; KLOG_TAIL_LEN]);
 const PANIC_BACKTRACE_MAX: usize = 16;
 
 /// Set CPU state from an interrupt frame to be included in panic diagnostics.
@@ -27,6 +35,25 @@ pub fn set_panic_cpu_state(rip: u64, rsp: u64) {
     PANIC_HAS_CPU_STATE.set_active();
 }
 
+/// Keep the exception that is about to panic, for the panic screen.  Also
+/// sets the CPU state, as [`set_panic_cpu_state`].
+pub fn set_panic_interrupt_frame(frame: &InterruptFrame) {
+    // SAFETY: only written on the way into a panic; read once the panicking
+    // CPU has taken `PANIC_HAS_FRAME`.
+    unsafe { *PANIC_FRAME.get() = Some(*frame) };
+    PANIC_HAS_FRAME.set_active();
+    set_panic_cpu_state(frame.rip, frame.rsp);
+}
+
+fn take_panic_frame() -> Option<InterruptFrame> {
+    if PANIC_HAS_FRAME.take() {
+        // SAFETY: see `set_panic_interrupt_frame`.
+        unsafe { *PANIC_FRAME.get() }
+    } else {
+        None
+    }
+}
+
 fn take_panic_cpu_state() -> (Option<u64>, Option<u64>) {
     if PANIC_HAS_CPU_STATE.take() {
         (
@@ -42,26 +69,21 @@ fn panic_serial_write(s: &str) {
     serial::write_line(s);
 }
 
-fn panic_dump_backtrace() {
-    let rbp = cpu::read_rbp();
-    let mut entries: [StacktraceEntry; PANIC_BACKTRACE_MAX] = [StacktraceEntry {
-        frame_pointer: 0,
-        return_address: 0,
-    }; PANIC_BACKTRACE_MAX];
+/// Walk the stack from `rbp` into `entries`.  Returns the frames found.
+fn panic_capture_backtrace(rbp: u64, entries: &mut [StacktraceEntry]) -> &[StacktraceEntry] {
+    let captured =
+        stacktrace::stacktrace_capture_from(rbp, entries.as_mut_ptr(), entries.len() as c_int);
+    &entries[..captured.max(0) as usize]
+}
 
-    let captured = stacktrace::stacktrace_capture_from(
-        rbp,
-        entries.as_mut_ptr(),
-        PANIC_BACKTRACE_MAX as c_int,
-    );
-    if captured <= 0 {
+fn panic_dump_backtrace(entries: &[StacktraceEntry]) {
+    if entries.is_empty() {
         panic_serial_write("Backtrace: <empty>");
         return;
     }
 
     panic_serial_write("Backtrace (most recent call first):");
-    for i in 0..captured as usize {
-        let entry = &entries[i];
+    for (i, entry) in entries.iter().enumerate() {
         let mut line = MessageBuffer::new();
         let _ = write!(
             line,
@@ -72,6 +94,30 @@ fn panic_dump_backtrace() {
     }
 }
 
+/// Block until a key the panic screen knows is pressed.  Scancode set 1;
+/// the 0xE0 prefix of the cursor block is skipped, which leaves the same
+/// codes as the keypad's.
+fn panic_wait_key() -> PanicKey {
+    loop {
+        let key = match poll_scancode() {
+            Some(0x1C) => Some(PanicKey::Exit),
+            Some(0x0F) | Some(0x4D) => Some(PanicKey::NextPage),
+            Some(0x4B) => Some(PanicKey::PrevPage),
+            Some(0x48) => Some(PanicKey::Up),
+            Some(0x50) => Some(PanicKey::Down),
+            Some(0x49) => Some(PanicKey::PageUp),
+            Some(0x51) => Some(PanicKey::PageDown),
+            Some(0x47) => Some(PanicKey::Home),
+            Some(0x4F) => Some(PanicKey::End),
+            _ => None,
+        };
+        if let Some(key) = key {
+            return key;
+        }
+        cpu::pause();
+    }
+}
+
 /// Core panic implementation. Called by the kernel's `#[panic_handler]`.
 pub fn panic_handler_impl(info: &PanicInfo) -> ! {
     // Check test recovery BEFORE disabling interrupts so we can restore state properly
@@ -114,10 +160,13 @@ pub fn panic_handler_impl(info: &PanicInfo) -> ! {
         cpu::halt_loop();
     }
 
+    let regs = kdiag::kdiag_snapshot_regs();
+    let frame = take_panic_frame();
     let (extra_rip, extra_rsp) = take_panic_cpu_state();
 
     let current_rsp = cpu::read_rsp();
     let cr0 = cpu::read_cr0();
+    let cr2 = cpu::read_cr2();
     let cr3 = cpu::read_cr3();
     let cr4 = cpu::read_cr4();
 
@@ -160,6 +209,10 @@ pub fn panic_handler_impl(info: &PanicInfo) -> ! {
         let mut hex_buf = HexBuffer::new();
         panic_serial_write(hex_buf.format_labeled("CR0", cr0));
     }
+    {
+        let mut hex_buf = HexBuffer::new();
+        panic_serial_write(hex_buf.format_labeled("CR2", cr2));
+    }
     {
         let mut hex_buf = HexBuffer::new();
         panic_serial_write(hex_buf.format_labeled("CR3", cr3));
@@ -169,22 +222,50 @@ pub fn panic_handler_impl(info: &PanicInfo) -> ! {
         panic_serial_write(hex_buf.format_labeled("CR4", cr4));
     }
 
-    panic_dump_backtrace();
+    // An exception's own frame chain says more than the panic machinery's.
+    let rbp = frame.map_or_else(cpu::read_rbp, |f| f.rbp);
+    let mut entries = [StacktraceEntry {
+        frame_pointer: 0,
+        return_address: 0,
+    }; PANIC_BACKTRACE_MAX];
+    let backtrace = panic_capture_backtrace(rbp, &mut entries);
+    panic_dump_backtrace(backtrace);
 
     panic_serial_write("===================");
     panic_serial_write("Kernel panic: unrecoverable error");
 
-    if panic_screen::display_panic_screen(
-        Some(message_str),
-        display_rip,
-        Some(display_rsp),
+    // SAFETY: only the CPU that won `PANIC_IN_PROGRESS` gets here.
+    let log = unsafe { &mut *PANIC_LOG.get() };
+    let log_len = klog_tail(log);
+
+    let report = PanicReport {
+        message: Some(message_str),
+        regs,
+        frame: frame.as_ref(),
+        rip: display_rip,
+        rsp: display_rsp,
         cr0,
+        cr2,
         cr3,
         cr4,
-    ) {
+        backtrace,
+        log: &log[..log_len],
+    };
+    if crashdump::crashdump_armed() {
+        if crashdump::crashdump_write(&report) {
+            panic_serial_write("Crash dump written to /var/crash/panic.dump");
+        } else {
+            panic_serial_write("Crash dump: write failed");
+        }
+    }
+    if gdbstub::gdbstub_enabled() {
+        panic_serial_write("Waiting for GDB on COM2...");
+        gdbstub::gdbstub_panic(&report);
+    }
+    if panic_screen::is_available() {
         panic_serial_write("Press ENTER to shutdown...");
-        poll_wait_enter();
-    } else {
+    }
+    if !panic_screen::run_panic_screen(&report, panic_wait_key) {
         panic_serial_write("System halted.");
     }
 
@@ -0,0 +1,86 @@
+//! Panic screen tests: paging and scrolling through a panic report without
+//! drawing it.
+
+use slopos_lib::stacktrace::StacktraceEntry;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{RegSnapshot, assert_eq_test, pass};
+use slopos_video::panic_screen::{PanicBrowser, PanicKey, PanicPage, PanicReport};
+
+const LOG: &[u8] = b"one\ntwo\nthree\nfour\nfive\nsix\n";
+
+fn report<'a>(backtrace: &'a [StacktraceEntry], log: &'a [u8]) -> PanicReport<'a> {
+    PanicReport {
+        message: Some("test"),
+        regs: RegSnapshot::default(),
+        frame: None,
+        rip: None,
+        rsp: 0,
+        cr0: 0,
+        cr2: 0,
+        cr3: 0,
+        cr4: 0,
+        backtrace,
+        log,
+    }
+}
+
+pub fn test_panic_browser_pages() -> TestResult {
+    let report = report(&[], LOG);
+    let mut browser = PanicBrowser::new(&report, 4);
+    assert_eq_test!(browser.page(), PanicPage::Registers);
+    assert_eq_test!(browser.handle_key(PanicKey::NextPage), true);
+    assert_eq_test!(browser.page(), PanicPage::Stack);
+    assert_eq_test!(browser.line_count(PanicPage::Stack), 1, "empty stack");
+    browser.handle_key(PanicKey::NextPage);
+    assert_eq_test!(browser.page(), PanicPage::Log);
+    browser.handle_key(PanicKey::NextPage);
+    assert_eq_test!(browser.page(), PanicPage::Registers, "pages wrap");
+    browser.handle_key(PanicKey::PrevPage);
+    assert_eq_test!(browser.page(), PanicPage::Log);
+    assert_eq_test!(browser.handle_key(PanicKey::Exit), false);
+    pass!()
+}
+
+pub fn test_panic_browser_scrolls_log() -> TestResult {
+    let report = report(&[], LOG);
+    let mut browser = PanicBrowser::new(&report, 4);
+    assert_eq_test!(browser.line_count(PanicPage::Log), 6);
+    // Opens on the newest lines.
+    assert_eq_test!(browser.scroll(PanicPage::Log), 2);
+
+    browser.handle_key(PanicKey::PrevPage);
+    browser.handle_key(PanicKey::Down);
+    assert_eq_test!(browser.scroll(PanicPage::Log), 2, "scrolled past the end");
+    browser.handle_key(PanicKey::Up);
+    assert_eq_test!(browser.scroll(PanicPage::Log), 1);
+    browser.handle_key(PanicKey::PageUp);
+    assert_eq_test!(browser.scroll(PanicPage::Log), 0);
+    browser.handle_key(PanicKey::End);
+    assert_eq_test!(browser.scroll(PanicPage::Log), 2);
+    browser.handle_key(PanicKey::Home);
+    assert_eq_test!(browser.scroll(PanicPage::Log), 0);
+    assert_eq_test!(browser.scroll(PanicPage::Registers), 0, "other page moved");
+    pass!()
+}
+
+pub fn test_panic_browser_stack_lines() -> TestResult {
+    let frames = [StacktraceEntry {
+        frame_pointer: 0x1000,
+        return_address: 0x2000,
+    }; 3];
+    let report = report(&frames, b"");
+    let browser = PanicBrowser::new(&report, 4);
+    assert_eq_test!(browser.line_count(PanicPage::Stack), 3);
+    assert_eq_test!(browser.line_count(PanicPage::Log), 1, "empty log");
+    assert_eq_test!(browser.scroll(PanicPage::Log), 0);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    panic_screen,
+    [
+        test_panic_browser_pages,
+        test_panic_browser_scrolls_log,
+        test_panic_browser_stack_lines,
+    ]
+);
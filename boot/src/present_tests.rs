@@ -0,0 +1,184 @@
+//! Timed presentation queue tests.
+//!
+//! Drive the composited path by hand: a fake task with a registered
+//! surface queues buffers, and the test plays the compositor by draining,
+//! reporting flips and checking what would wake it.
+
+use slopos_abi::COMPOSITOR_WAIT_FOREVER;
+use slopos_abi::present::{PRESENT_FLAG_BYPASS, PRESENT_QUEUE_DEPTH, PresentError};
+use slopos_lib::clock::monotonic_ns;
+use slopos_lib::compositor_wake::compositor_take_wake;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+use slopos_video::compositor_context::{compositor_wait, drain_queue, surface_commit};
+use slopos_video::present::{
+    present_feedback, present_frames_done, present_latch_composited, surface_present,
+};
+
+use crate::surface_fixture::SurfaceFixture;
+
+const TEST_TASK: u32 = 0xF00D;
+const TEST_PROCESS: u32 = TEST_TASK;
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 32;
+const BUFFERS: usize = PRESENT_QUEUE_DEPTH;
+
+/// A registered surface plus a set of buffers to present.
+fn fixture() -> Option<SurfaceFixture<BUFFERS>> {
+    SurfaceFixture::with_buffers(
+        TEST_TASK,
+        WIDTH,
+        HEIGHT,
+        [(WIDTH * HEIGHT * 4) as u64; BUFFERS],
+    )
+}
+
+fn present(token: u32, target_ns: u64) -> Result<u64, PresentError> {
+    surface_present(TEST_TASK, TEST_PROCESS, token, target_ns, 0, false)
+}
+
+pub fn test_present_rejects_bad_requests() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let token = fx.tokens[0];
+
+    assert_eq_test!(
+        surface_present(TEST_TASK, TEST_PROCESS, token, 0, 0x80, false),
+        Err(PresentError::InvalidArgument),
+        "unknown flag accepted"
+    );
+    assert_eq_test!(
+        surface_present(
+            TEST_TASK,
+            TEST_PROCESS,
+            token,
+            0,
+            PRESENT_FLAG_BYPASS,
+            false
+        ),
+        Err(PresentError::PermissionDenied),
+        "bypass without display-exclusive"
+    );
+    assert_eq_test!(
+        surface_present(TEST_TASK, TEST_PROCESS + 1, token, 0, 0, false),
+        Err(PresentError::InvalidToken),
+        "foreign buffer accepted"
+    );
+    assert_eq_test!(
+        surface_present(TEST_TASK + 1, TEST_PROCESS, token, 0, 0, false),
+        Err(PresentError::SurfaceNotFound),
+        "present without a surface"
+    );
+    pass!()
+}
+
+pub fn test_present_targets_must_not_go_backwards() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let later = monotonic_ns() + 1_000_000_000;
+    assert_test!(present(fx.tokens[0], later).is_ok(), "first present failed");
+    assert_eq_test!(
+        present(fx.tokens[1], later - 1),
+        Err(PresentError::InvalidArgument),
+        "earlier target accepted"
+    );
+    pass!()
+}
+
+pub fn test_present_latches_newest_due() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let now = monotonic_ns();
+    let mut last_seq = 0;
+    for &token in &fx.tokens[..3] {
+        match present(token, now) {
+            Ok(seq) => {
+                assert_test!(seq > last_seq, "sequence numbers not increasing");
+                last_seq = seq;
+            }
+            Err(err) => return fail!("present failed: {:?}", err),
+        }
+    }
+    assert_eq_test!(present_feedback(TEST_TASK).queued, 3, "queued count");
+
+    present_latch_composited();
+    let latched = present_feedback(TEST_TASK);
+    assert_eq_test!(latched.queued, 0, "due buffers left queued");
+    assert_eq_test!(latched.dropped, 2, "older due buffers not dropped");
+    assert_eq_test!(latched.presented, 0, "presented before the flip");
+
+    present_frames_done();
+    let shown = present_feedback(TEST_TASK);
+    assert_eq_test!(shown.presented_seq, last_seq, "newest buffer not shown");
+    assert_eq_test!(shown.presented, 1, "presented count");
+    assert_test!(shown.present_time_ns >= now, "present time before queueing");
+    pass!()
+}
+
+pub fn test_present_holds_future_buffers() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let later = monotonic_ns() + 1_000_000_000;
+    assert_test!(present(fx.tokens[0], later).is_ok(), "present failed");
+    present_latch_composited();
+    present_frames_done();
+    let feedback = present_feedback(TEST_TASK);
+    assert_eq_test!(feedback.queued, 1, "future buffer latched early");
+    assert_eq_test!(feedback.presented, 0, "future buffer presented");
+    pass!()
+}
+
+pub fn test_client_ops_wake_compositor() -> TestResult {
+    let Some(_fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    compositor_take_wake();
+    assert_test!(surface_commit(TEST_TASK).is_ok(), "commit failed");
+    assert_test!(compositor_wait(0), "commit did not wake the compositor");
+    assert_test!(!compositor_wait(0), "wakeup not consumed");
+    drain_queue();
+    assert_test!(!compositor_wait(0), "empty queue left a wakeup");
+    pass!()
+}
+
+pub fn test_present_wakes_compositor() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    compositor_take_wake();
+    let later = monotonic_ns() + 1_000_000_000;
+    assert_test!(present(fx.tokens[0], later).is_ok(), "present failed");
+    assert_test!(compositor_wait(0), "present did not wake the compositor");
+    pass!()
+}
+
+pub fn test_due_present_ends_compositor_wait() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    assert_test!(present(fx.tokens[0], 0).is_ok(), "present failed");
+    compositor_take_wake();
+    // Would block forever if the due buffer did not bound the wait.
+    assert_test!(!compositor_wait(COMPOSITOR_WAIT_FOREVER));
+    present_latch_composited();
+    present_frames_done();
+    assert_eq_test!(present_feedback(TEST_TASK).presented, 1);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    present,
+    [
+        test_present_rejects_bad_requests,
+        test_present_targets_must_not_go_backwards,
+        test_present_latches_newest_due,
+        test_present_holds_future_buffers,
+        test_client_ops_wake_compositor,
+        test_present_wakes_compositor,
+        test_due_present_ends_compositor_wait,
+    ]
+);
@@ -0,0 +1,80 @@
+//! Window resize tests: the configure event reaching the client, and a
+//! second `surface_attach` moving the surface onto a new buffer.
+
+use slopos_abi::input::InputEventType;
+use slopos_drivers::input_event::{input_cleanup_task, input_poll, input_request_configure};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+use slopos_video::compositor_context::{
+    drain_queue, register_surface_for_task, surface_mark_frames_done, surface_set_window_position,
+    surface_size,
+};
+
+use crate::surface_fixture::SurfaceFixture;
+
+const TEST_TASK: u32 = 0xF011;
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 16;
+
+/// A registered surface plus a second, larger buffer to reattach to.
+fn fixture() -> Option<SurfaceFixture<2>> {
+    SurfaceFixture::with_buffers(
+        TEST_TASK,
+        WIDTH,
+        HEIGHT,
+        [(WIDTH * HEIGHT * 4) as u64, (WIDTH * HEIGHT * 16) as u64],
+    )
+}
+
+pub fn test_configure_event_reaches_task() -> TestResult {
+    assert_test!(
+        !input_request_configure(0, 10, 10, 0),
+        "configure for task 0 accepted"
+    );
+    assert_test!(input_request_configure(TEST_TASK, 640, 480, 7));
+    let event = input_poll(TEST_TASK);
+    input_cleanup_task(TEST_TASK);
+
+    let Some(event) = event else {
+        return fail!("no configure event queued");
+    };
+    assert_eq_test!(event.event_type, InputEventType::Configure);
+    assert_eq_test!(event.configure_size(), (640, 480));
+    assert_eq_test!(event.timestamp_ms, 7);
+    assert_eq_test!(InputEventType::from_u8(9), Some(InputEventType::Configure));
+    assert_test!(!InputEventType::Configure.is_pointer_event());
+    pass!()
+}
+
+pub fn test_surface_reattach_resizes() -> TestResult {
+    let Some(fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let _ = surface_set_window_position(TEST_TASK, 120, 80);
+    surface_mark_frames_done(0);
+
+    let _ = register_surface_for_task(TEST_TASK, WIDTH * 2, HEIGHT * 2, fx.tokens[1]);
+    drain_queue();
+    assert_eq_test!(surface_size(TEST_TASK), Some((WIDTH * 2, HEIGHT * 2)));
+    let Some(info) = fx.info() else {
+        return fail!("surface not enumerated");
+    };
+    assert_eq_test!(info.shm_token, fx.tokens[1]);
+    assert_eq_test!((info.x, info.y), (120, 80), "reattach moved the window");
+    assert_test!(info.is_dirty(), "reattach did not damage the window");
+
+    // Attaching the same buffer again changes nothing.
+    surface_mark_frames_done(0);
+    let _ = register_surface_for_task(TEST_TASK, WIDTH * 2, HEIGHT * 2, fx.tokens[1]);
+    drain_queue();
+    assert_test!(fx.info().is_some_and(|w| !w.is_dirty()));
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    resize,
+    [
+        test_configure_event_reaches_task,
+        test_surface_reattach_resizes,
+    ]
+);
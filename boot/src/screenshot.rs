@@ -0,0 +1,99 @@
+//! Framebuffer screenshots as binary PPM files, for autopilot runs and
+//! crash bundles.
+
+use core::fmt::{self, Write};
+
+use slopos_abi::draw::EncodedPixel;
+use slopos_fs::vfs::vfs_open;
+use slopos_video::framebuffer;
+
+/// Screenshots keep every Nth pixel in each direction to stay within
+/// what ext2's single-indirect blocks can hold.
+pub const SCREENSHOT_SCALE: usize = 2;
+
+/// Output pixels converted per framebuffer read.
+const SCREENSHOT_CHUNK: usize = 128;
+
+/// A screenshot that was written.
+#[derive(Clone, Copy, Debug)]
+pub struct Screenshot {
+    pub width: usize,
+    pub height: usize,
+    /// File size, header included.
+    pub bytes: u64,
+}
+
+/// PPM header sink; the header is a few dozen bytes at most.
+struct HeaderBuf {
+    buf: [u8; 32],
+    len: usize,
+}
+
+impl fmt::Write for HeaderBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Write a downscaled binary PPM of the scanout to `path`. Returns
+/// `Ok(None)` when there is no framebuffer to capture.
+pub(crate) fn write_screenshot(path: &[u8]) -> Result<Option<Screenshot>, ()> {
+    let Some(info) = framebuffer::get_display_info() else {
+        return Ok(None);
+    };
+    let bpp = info.format.bytes_per_pixel() as usize;
+    let out_w = info.width as usize / SCREENSHOT_SCALE;
+    let out_h = info.height as usize / SCREENSHOT_SCALE;
+
+    let file = vfs_open(path, true).map_err(|_| ())?;
+    file.fs.truncate(file.inode, 0).map_err(|_| ())?;
+
+    let mut header = HeaderBuf {
+        buf: [0; 32],
+        len: 0,
+    };
+    write!(header, "P6\n{} {}\n255\n", out_w, out_h).map_err(|_| ())?;
+    let mut offset = file.write(0, &header.buf[..header.len]).map_err(|_| ())? as u64;
+
+    let mut src = [0u8; SCREENSHOT_CHUNK * SCREENSHOT_SCALE * 4];
+    let mut dst = [0u8; SCREENSHOT_CHUNK * 3];
+    for y in 0..out_h {
+        let row_base = y * SCREENSHOT_SCALE * info.pitch as usize;
+        let mut x = 0;
+        while x < out_w {
+            let count = SCREENSHOT_CHUNK.min(out_w - x);
+            let span = count * SCREENSHOT_SCALE * bpp;
+            let src_off = row_base + x * SCREENSHOT_SCALE * bpp;
+            if framebuffer::fb_read(src_off, &mut src[..span]) != span {
+                return Err(());
+            }
+            for i in 0..count {
+                let at = i * SCREENSHOT_SCALE * bpp;
+                let mut raw = [0u8; 4];
+                raw[..bpp].copy_from_slice(&src[at..at + bpp]);
+                let color = info.format.decode(EncodedPixel(u32::from_le_bytes(raw)));
+                dst[i * 3] = color.red();
+                dst[i * 3 + 1] = color.green();
+                dst[i * 3 + 2] = color.blue();
+            }
+            let bytes = &dst[..count * 3];
+            if file.write(offset, bytes).map_err(|_| ())? != bytes.len() {
+                return Err(());
+            }
+            offset += bytes.len() as u64;
+            x += count;
+        }
+    }
+
+    Ok(Some(Screenshot {
+        width: out_w,
+        height: out_h,
+        bytes: offset,
+    }))
+}
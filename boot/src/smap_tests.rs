@@ -0,0 +1,379 @@
+//! SMEP/SMAP tests: the CR4 bits match CPUID, and stray kernel accesses to
+//! user pages fault while checked user copies still go through.
+//!
+//! The probes run on a test process's page tables with a page-fault
+//! override installed; a faulting probe resumes at the label after the
+//! access.  The probe tests pass trivially on CPUs without the feature.
+//!
+//! The UI syscalls that hand structures to or from userland are run the
+//! same way, so one that touched a user pointer outside a checked copy
+//! would fault here on any CPU with SMAP.
+
+use core::arch::asm;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use slopos_abi::input::InputEventType;
+use slopos_abi::task::TASK_FLAG_COMPOSITOR;
+use slopos_abi::{InputEvent, WindowInfo};
+use slopos_core::syscall::ui_handlers::{
+    syscall_enumerate_windows, syscall_input_poll, syscall_input_poll_batch,
+    syscall_surface_set_title,
+};
+use slopos_core::task::Task;
+use slopos_drivers::input_event::{
+    input_cleanup_task, input_request_close, input_request_configure,
+};
+use slopos_lib::cpu::smap::{self, UserAccess};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{InterruptFrame, assert_eq_test, assert_test, cpu, fail, pass};
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::paging::{paging_get_kernel_directory, switch_page_directory};
+use slopos_mm::paging_defs::PageFlags;
+use slopos_mm::test_fixtures::{ProcessVmGuard, map_test_page};
+use slopos_mm::user_copy::{copy_from_user, set_syscall_process_id};
+use slopos_mm::user_ptr::UserPtr;
+use slopos_video::compositor_context::drain_queue;
+
+use crate::idt::{
+    EXCEPTION_PAGE_FAULT, ExceptionMode, exception_set_mode, idt_install_exception_handler,
+};
+use crate::surface_fixture::{SurfaceFixture, window_info};
+
+const TEST_VADDR: u64 = 0x2000_0000;
+const TEST_BYTE: u8 = 0x5A;
+const NO_FAULT: u64 = u64::MAX;
+
+const PF_PRESENT: u64 = 1 << 0;
+const PF_USER: u64 = 1 << 2;
+const PF_FETCH: u64 = 1 << 4;
+
+const RFLAGS_AC: u64 = 1 << 18;
+
+/// Fake client the UI syscalls run as.
+const UI_TASK: u32 = 0xF014;
+const TITLE: &[u8] = b"smap";
+/// Where in the test page the syscalls write events and windows.
+const EVENTS_VADDR: u64 = TEST_VADDR + 0x100;
+const WINDOWS_VADDR: u64 = TEST_VADDR + 0x400;
+const MAX_WINDOWS: u64 = 4;
+
+/// Where the page-fault override resumes; zero outside a probe.
+static PROBE_RESUME: AtomicU64 = AtomicU64::new(0);
+static PROBE_ERROR: AtomicU64 = AtomicU64::new(NO_FAULT);
+
+fn probe_fault_handler(frame: *mut InterruptFrame) {
+    let frame = unsafe { &mut *frame };
+    let resume = PROBE_RESUME.swap(0, Ordering::AcqRel);
+    if resume == 0 {
+        panic!("SMAP test: unexpected page fault at rip {:#x}", frame.rip);
+    }
+    PROBE_ERROR.store(frame.error_code, Ordering::Release);
+    frame.rip = resume;
+}
+
+fn probe_result() -> Option<u64> {
+    PROBE_RESUME.store(0, Ordering::Release);
+    match PROBE_ERROR.load(Ordering::Acquire) {
+        NO_FAULT => None,
+        code => Some(code),
+    }
+}
+
+/// Load a byte from `addr`; the page-fault error code if that faulted.
+fn probe_read(addr: u64) -> Option<u64> {
+    PROBE_ERROR.store(NO_FAULT, Ordering::Relaxed);
+    unsafe {
+        asm!(
+            "lea {resume}, [rip + 2f]",
+            "mov qword ptr [{slot}], {resume}",
+            "mov {tmp:l}, byte ptr [{addr}]",
+            "2:",
+            resume = out(reg) _,
+            tmp = out(reg) _,
+            slot = in(reg) PROBE_RESUME.as_ptr(),
+            addr = in(reg) addr,
+            options(nostack),
+        );
+    }
+    probe_result()
+}
+
+/// Jump to `addr` with the resume label in rcx; the page there holds
+/// `jmp rcx`, so without SMEP it comes straight back.
+fn probe_exec(addr: u64) -> Option<u64> {
+    PROBE_ERROR.store(NO_FAULT, Ordering::Relaxed);
+    unsafe {
+        asm!(
+            "lea rcx, [rip + 2f]",
+            "mov qword ptr [{slot}], rcx",
+            "jmp {target}",
+            "2:",
+            slot = in(reg) PROBE_RESUME.as_ptr(),
+            target = in(reg) addr,
+            out("rcx") _,
+            options(nostack),
+        );
+    }
+    probe_result()
+}
+
+/// Run `f` on `vm`'s page tables with interrupts off and the probe
+/// handler installed.
+fn on_user_tables<R>(vm: &ProcessVmGuard, f: impl FnOnce() -> R) -> Option<R> {
+    let flags = cpu::save_flags_cli();
+    if switch_page_directory(vm.page_dir) != 0 {
+        cpu::restore_flags(flags);
+        return None;
+    }
+    exception_set_mode(ExceptionMode::Test);
+    idt_install_exception_handler(EXCEPTION_PAGE_FAULT, probe_fault_handler);
+
+    let out = f();
+
+    exception_set_mode(ExceptionMode::Normal);
+    let _ = switch_page_directory(paging_get_kernel_directory());
+    cpu::restore_flags(flags);
+    Some(out)
+}
+
+/// Map a user page at `TEST_VADDR` holding `bytes`.
+fn map_user_page(vm: &ProcessVmGuard, flags: PageFlags, bytes: &[u8]) -> bool {
+    let Some(phys) = map_test_page(vm.page_dir, TEST_VADDR, flags.bits()) else {
+        return false;
+    };
+    let virt = phys.to_virt();
+    if virt.is_null() {
+        return false;
+    }
+    unsafe {
+        core::ptr::copy_nonoverlapping(bytes.as_ptr(), virt.as_mut_ptr::<u8>(), bytes.len());
+    }
+    true
+}
+
+/// Run the syscall `handler` as `task` with `rdi` and `rsi`; its result.
+fn call<R>(
+    handler: fn(*mut Task, *mut InterruptFrame) -> R,
+    task: &mut Task,
+    rdi: u64,
+    rsi: u64,
+) -> u64 {
+    // SAFETY: InterruptFrame is plain registers; all zeroes is valid.
+    let mut frame: InterruptFrame = unsafe { core::mem::zeroed() };
+    frame.rdi = rdi;
+    frame.rsi = rsi;
+    let _ = handler(task, &mut frame);
+    frame.rax
+}
+
+/// The `T` at user address `addr`, read through a checked copy.
+fn read_user<T: Copy>(addr: u64) -> Option<T> {
+    copy_from_user(UserPtr::<T>::try_new(addr).ok()?).ok()
+}
+
+pub fn test_smep_smap_match_cpuid() -> TestResult {
+    let (smep, smap) = smap::supported();
+    assert_eq_test!(smap::smep_enabled(), smep, "SMEP state");
+    assert_eq_test!(smap::smap_enabled(), smap, "SMAP state");
+
+    let cr4 = cpu::read_cr4();
+    assert_eq_test!((cr4 & cpu::CR4_SMEP) != 0, smep, "CR4.SMEP");
+    assert_eq_test!((cr4 & cpu::CR4_SMAP) != 0, smap, "CR4.SMAP");
+    assert_eq_test!(cpu::read_rflags() & RFLAGS_AC, 0, "AC left set");
+    pass!()
+}
+
+pub fn test_smap_blocks_stray_user_read() -> TestResult {
+    if !smap::smap_enabled() {
+        return pass!();
+    }
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    if !map_user_page(
+        &vm,
+        PageFlags::USER_RW | PageFlags::NO_EXECUTE,
+        &[TEST_BYTE],
+    ) {
+        return fail!("map user page");
+    }
+
+    let Some((stray, windowed, ac_after)) = on_user_tables(&vm, || {
+        let stray = probe_read(TEST_VADDR);
+        let windowed = {
+            let _access = UserAccess::begin();
+            probe_read(TEST_VADDR)
+        };
+        (stray, windowed, cpu::read_rflags() & RFLAGS_AC)
+    }) else {
+        return fail!("switch to user page tables");
+    };
+
+    let Some(code) = stray else {
+        return fail!("kernel read a user page without faulting");
+    };
+    assert_test!(code & PF_PRESENT != 0, "fault on a non-present page");
+    assert_test!(code & PF_USER == 0, "fault reported as a user access");
+    assert_test!(
+        windowed.is_none(),
+        "read faulted inside a user-access window"
+    );
+    assert_eq_test!(ac_after, 0, "AC still set after the window closed");
+    pass!()
+}
+
+pub fn test_smep_blocks_user_exec() -> TestResult {
+    if !smap::smep_enabled() {
+        return pass!();
+    }
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    // jmp rcx
+    if !map_user_page(&vm, PageFlags::USER_RO, &[0xFF, 0xE1]) {
+        return fail!("map user page");
+    }
+
+    let Some(fault) = on_user_tables(&vm, || probe_exec(TEST_VADDR)) else {
+        return fail!("switch to user page tables");
+    };
+    let Some(code) = fault else {
+        return fail!("kernel executed a user page");
+    };
+    assert_test!(code & PF_FETCH != 0, "fault was not an instruction fetch");
+    assert_test!(code & PF_USER == 0, "fault reported as a user access");
+    pass!()
+}
+
+pub fn test_smap_user_copy_still_works() -> TestResult {
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    if !map_user_page(
+        &vm,
+        PageFlags::USER_RW | PageFlags::NO_EXECUTE,
+        &[TEST_BYTE],
+    ) {
+        return fail!("map user page");
+    }
+    let Ok(ptr) = UserPtr::<u8>::try_new(TEST_VADDR) else {
+        return fail!("user pointer rejected");
+    };
+
+    let Some(read) = on_user_tables(&vm, || {
+        let _guard = set_syscall_process_id(vm.pid);
+        copy_from_user(ptr)
+    }) else {
+        return fail!("switch to user page tables");
+    };
+    assert_eq_test!(read.ok(), Some(TEST_BYTE), "copy_from_user");
+    assert_eq_test!(cpu::read_rflags() & RFLAGS_AC, 0, "AC left set");
+    pass!()
+}
+
+/// Results of the UI syscalls, gathered on the test process's tables.
+struct UiCalls {
+    title: u64,
+    poll: u64,
+    polled: Option<InputEvent>,
+    batch: u64,
+    batched: Option<InputEvent>,
+    windows: u64,
+    listed: bool,
+}
+
+pub fn test_smap_ui_syscalls_use_user_copies() -> TestResult {
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    if !map_user_page(&vm, PageFlags::USER_RW | PageFlags::NO_EXECUTE, TITLE) {
+        return fail!("map user page");
+    }
+    let Some(_surface) = SurfaceFixture::new(UI_TASK, 16, 16) else {
+        return fail!("fixture setup failed");
+    };
+    input_request_close(UI_TASK, 1);
+    input_request_configure(UI_TASK, 32, 32, 2);
+    input_request_close(UI_TASK, 3);
+
+    let mut task = Task::invalid();
+    task.task_id = UI_TASK;
+    task.process_id = vm.pid;
+
+    let calls = on_user_tables(&vm, || {
+        let _guard = set_syscall_process_id(vm.pid);
+        let title = call(
+            syscall_surface_set_title,
+            &mut task,
+            TEST_VADDR,
+            TITLE.len() as u64,
+        );
+        let poll = call(syscall_input_poll, &mut task, EVENTS_VADDR, 0);
+        let polled = read_user(EVENTS_VADDR);
+        let batch = call(syscall_input_poll_batch, &mut task, EVENTS_VADDR, 8);
+        let batched = read_user(EVENTS_VADDR + size_of::<InputEvent>() as u64);
+
+        task.flags = TASK_FLAG_COMPOSITOR;
+        let windows = call(
+            syscall_enumerate_windows,
+            &mut task,
+            WINDOWS_VADDR,
+            MAX_WINDOWS,
+        );
+        let listed = (0..windows.min(MAX_WINDOWS)).any(|i| {
+            let addr = WINDOWS_VADDR + i * size_of::<WindowInfo>() as u64;
+            read_user::<WindowInfo>(addr).is_some_and(|w| w.task_id == UI_TASK)
+        });
+        UiCalls {
+            title,
+            poll,
+            polled,
+            batch,
+            batched,
+            windows,
+            listed,
+        }
+    });
+    input_cleanup_task(UI_TASK);
+    drain_queue();
+
+    let Some(calls) = calls else {
+        return fail!("switch to user page tables");
+    };
+    assert_eq_test!(cpu::read_rflags() & RFLAGS_AC, 0, "AC left set");
+
+    assert_eq_test!(calls.title, 0, "surface_set_title");
+    let title = window_info(UI_TASK).map(|w| w.title);
+    assert_test!(
+        title.is_some_and(|t| t.starts_with(TITLE)),
+        "title not copied in"
+    );
+
+    assert_eq_test!(calls.poll, 1, "input_poll");
+    assert_eq_test!(
+        calls.polled.map(|e| e.event_type),
+        Some(InputEventType::CloseRequest),
+        "polled event not copied out"
+    );
+    assert_eq_test!(calls.batch, 2, "input_poll_batch");
+    assert_eq_test!(
+        calls.batched.map(|e| e.event_type),
+        Some(InputEventType::CloseRequest),
+        "batched events not copied out"
+    );
+
+    assert_test!(calls.windows >= 1, "enumerate_windows");
+    assert_test!(calls.listed, "window list not copied out");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    smap,
+    [
+        test_smep_smap_match_cpuid,
+        test_smap_blocks_stray_user_read,
+        test_smep_blocks_user_exec,
+        test_smap_user_copy_still_works,
+        test_smap_ui_syscalls_use_user_copies,
+    ]
+);
@@ -5,7 +5,7 @@ use limine::mp::{Cpu as MpCpu, ResponseFlags as MpResponseFlags};
 use slopos_core::sched::{enter_scheduler, init_scheduler_for_ap};
 use slopos_drivers::apic;
 use slopos_lib::{cpu, is_cpu_online, klog_info, pcr};
-use slopos_mm::tlb;
+use slopos_mm::{pat, tlb};
 
 use crate::gdt::syscall_msr_init;
 use crate::idt::idt_load;
@@ -24,6 +24,12 @@ unsafe extern "C" fn ap_entry(cpu_info: &MpCpu) -> ! {
     // Replicate the BSP's XSAVE configuration (CR4.OSXSAVE + XCR0).
     slopos_lib::cpu::xsave::enable_on_current_cpu();
 
+    // And SMEP/SMAP, before anything on this CPU can touch a user page.
+    slopos_lib::cpu::smap::enable_on_current_cpu();
+
+    // Same for the PAT: WC framebuffer mappings are shared with the BSP.
+    pat::pat_init_ap();
+
     apic::enable();
 
     let apic_id = apic::get_id();
@@ -44,13 +50,15 @@ unsafe extern "C" fn ap_entry(cpu_info: &MpCpu) -> ! {
     idt_load();
     syscall_msr_init();
 
-    // Start per-CPU LAPIC timer using the BSP's calibrated frequency.
-    // The LAPIC_TIMER_FREQ_HZ global atomic is already set by boot-time
-    // calibration, so set_periodic_ms will compute the correct count.
+    // Start per-CPU LAPIC timer using the BSP's calibrated frequency and
+    // one-shot mode.  The first tick is armed here; the scheduler re-arms
+    // (or stops) it from then on.
     {
         use slopos_lib::arch::idt::LAPIC_TIMER_VECTOR;
         if apic::timer::is_calibrated() {
-            if !apic::timer::set_periodic_ms(LAPIC_TIMER_VECTOR, 10) {
+            if apic::timer::enable_oneshot(LAPIC_TIMER_VECTOR) {
+                apic::timer::arm_ns(10_000_000);
+            } else {
                 klog_info!("MP: CPU {} LAPIC timer start failed", cpu_idx);
             }
         }
@@ -0,0 +1,121 @@
+//! Window maximize/tile tests: the surface state machine remembering the
+//! floating geometry, and Super+key presses reaching the shortcut task.
+
+use slopos_abi::input::{InputEventType, KEY_MOD_SUPER, SCANCODE_LEFT};
+use slopos_abi::surface::{
+    WINDOW_STATE_MAX, WINDOW_STATE_MAXIMIZED, WINDOW_STATE_MINIMIZED, WINDOW_STATE_NORMAL,
+    WINDOW_STATE_TILED_LEFT,
+};
+use slopos_abi::window::WindowInfo;
+use slopos_drivers::input_event::{
+    input_cleanup_task, input_poll, input_route_shortcut, input_set_shortcut_focus,
+};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+use slopos_mm::shared_memory::{shm_create, shm_destroy};
+use slopos_video::compositor_context::{
+    drain_queue, register_surface_for_task, surface_set_window_position, surface_set_window_state,
+    unregister_surface_for_task,
+};
+
+use crate::surface_fixture;
+
+const TEST_TASK: u32 = 0xF01F;
+const TEST_PROCESS: u32 = 0xF01F;
+const WIDTH: u32 = 32;
+const HEIGHT: u32 = 16;
+
+fn window_info() -> Option<WindowInfo> {
+    surface_fixture::window_info(TEST_TASK)
+}
+
+fn check_state_machine() -> TestResult {
+    let _ = surface_set_window_position(TEST_TASK, 40, 30);
+    assert_test!(window_info().is_some_and(|w| w.restore_bounds().is_none()));
+
+    assert_test!(surface_set_window_state(TEST_TASK, WINDOW_STATE_TILED_LEFT).is_ok());
+    let _ = surface_set_window_position(TEST_TASK, 0, 0);
+    let Some(info) = window_info() else {
+        return fail!("surface not enumerated");
+    };
+    assert_eq_test!(info.state, WINDOW_STATE_TILED_LEFT);
+    assert_eq_test!(
+        (
+            info.restore_x,
+            info.restore_y,
+            info.restore_width,
+            info.restore_height
+        ),
+        (40, 30, WIDTH, HEIGHT)
+    );
+
+    // Switching between fitted states keeps the first floating geometry.
+    assert_test!(surface_set_window_state(TEST_TASK, WINDOW_STATE_MAXIMIZED).is_ok());
+    assert_test!(window_info().is_some_and(|w| (w.restore_x, w.restore_y) == (40, 30)));
+
+    // Minimizing and restoring from the taskbar returns to maximized.
+    assert_test!(surface_set_window_state(TEST_TASK, WINDOW_STATE_MINIMIZED).is_ok());
+    assert_test!(surface_set_window_state(TEST_TASK, WINDOW_STATE_NORMAL).is_ok());
+    assert_test!(window_info().is_some_and(|w| w.state == WINDOW_STATE_MAXIMIZED));
+
+    assert_test!(surface_set_window_state(TEST_TASK, WINDOW_STATE_NORMAL).is_ok());
+    let Some(info) = window_info() else {
+        return fail!("surface not enumerated");
+    };
+    assert_eq_test!(info.state, WINDOW_STATE_NORMAL);
+    assert_test!(info.restore_bounds().is_none(), "restore geometry kept");
+
+    assert_test!(surface_set_window_state(TEST_TASK, WINDOW_STATE_MAX + 1).is_err());
+    pass!()
+}
+
+pub fn test_fitted_state_remembers_geometry() -> TestResult {
+    let token = shm_create(TEST_PROCESS, (WIDTH * HEIGHT * 4) as u64, 0);
+    if token == 0 {
+        return fail!("shm_create failed");
+    }
+    if register_surface_for_task(TEST_TASK, WIDTH, HEIGHT, token).is_err() {
+        shm_destroy(TEST_PROCESS, token);
+        return fail!("surface registration failed");
+    }
+    drain_queue();
+
+    let result = check_state_machine();
+    unregister_surface_for_task(TEST_TASK);
+    shm_destroy(TEST_PROCESS, token);
+    result
+}
+
+pub fn test_shortcut_reaches_shortcut_task() -> TestResult {
+    input_set_shortcut_focus(0);
+    assert_test!(
+        !input_route_shortcut(SCANCODE_LEFT, 0, 5),
+        "shortcut routed with no shortcut task"
+    );
+
+    input_set_shortcut_focus(TEST_TASK);
+    let routed = input_route_shortcut(SCANCODE_LEFT, 0, 5);
+    let event = input_poll(TEST_TASK);
+    input_cleanup_task(TEST_TASK);
+
+    assert_test!(routed);
+    let Some(event) = event else {
+        return fail!("no shortcut event queued");
+    };
+    assert_eq_test!(event.event_type, InputEventType::KeyPress);
+    assert_eq_test!(event.key_scancode(), SCANCODE_LEFT);
+    assert_test!(event.key_modifiers() & KEY_MOD_SUPER != 0);
+    assert_test!(
+        !input_route_shortcut(SCANCODE_LEFT, 0, 6),
+        "cleanup left the shortcut focus"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    snap,
+    [
+        test_fitted_state_remembers_geometry,
+        test_shortcut_reaches_shortcut_task,
+    ]
+);
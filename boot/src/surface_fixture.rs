@@ -0,0 +1,73 @@
+//! Surface fixture shared by the compositor test suites.
+//!
+//! A fake task registers a surface on the first of its shm buffers, the
+//! way a client would, and everything is torn down on drop.  Each suite
+//! passes its own task id (used as the process id too), so a fixture one
+//! suite leaks cannot corrupt the next.
+
+use slopos_abi::window::WindowInfo;
+use slopos_mm::shared_memory::{shm_create, shm_destroy};
+use slopos_video::compositor_context::{
+    drain_queue, register_surface_for_task, surface_enumerate_windows, unregister_surface_for_task,
+};
+use slopos_video::present::present_forget_task;
+
+/// A registered surface for `task` plus `N` buffers, torn down on drop.
+pub struct SurfaceFixture<const N: usize = 1> {
+    pub task: u32,
+    pub tokens: [u32; N],
+}
+
+impl SurfaceFixture {
+    /// A `width` x `height` surface on one buffer of its size.
+    pub fn new(task: u32, width: u32, height: u32) -> Option<Self> {
+        Self::with_buffers(task, width, height, [(width * height * 4) as u64])
+    }
+}
+
+impl<const N: usize> SurfaceFixture<N> {
+    /// A `width` x `height` surface on the first of `N` buffers of `sizes`
+    /// bytes each.
+    pub fn with_buffers(task: u32, width: u32, height: u32, sizes: [u64; N]) -> Option<Self> {
+        let fixture = Self {
+            task,
+            tokens: sizes.map(|size| shm_create(task, size, 0)),
+        };
+        if fixture.tokens.contains(&0) {
+            return None;
+        }
+        register_surface_for_task(task, width, height, fixture.tokens[0]).ok()?;
+        drain_queue();
+        Some(fixture)
+    }
+
+    /// The buffer the surface was registered on.
+    pub fn token(&self) -> u32 {
+        self.tokens[0]
+    }
+
+    /// The compositor's view of the surface.
+    pub fn info(&self) -> Option<WindowInfo> {
+        window_info(self.task)
+    }
+}
+
+impl<const N: usize> Drop for SurfaceFixture<N> {
+    fn drop(&mut self) {
+        present_forget_task(self.task);
+        unregister_surface_for_task(self.task);
+        for &token in self.tokens.iter().filter(|&&t| t != 0) {
+            shm_destroy(self.task, token);
+        }
+    }
+}
+
+/// The compositor's window list entry for `task`.
+pub fn window_info(task: u32) -> Option<WindowInfo> {
+    let mut windows = [WindowInfo::default(); 32];
+    let count = surface_enumerate_windows(&mut windows);
+    windows[..count as usize]
+        .iter()
+        .find(|w| w.task_id == task)
+        .copied()
+}
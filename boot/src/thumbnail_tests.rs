@@ -0,0 +1,159 @@
+//! Surface thumbnail and off-screen role tests.
+//!
+//! A fake task registers a surface with a known pattern, and the test
+//! asks for thumbnails of it into a second buffer, the way a task
+//! switcher would.
+
+use slopos_abi::SurfaceRole;
+use slopos_abi::error::CompositorError;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::shared_memory::shm_get_buffer_info;
+use slopos_video::compositor_context::{drain_queue, surface_set_role};
+use slopos_video::thumbnail::{surface_render_thumbnail, thumbnail_size};
+
+use crate::surface_fixture::{SurfaceFixture, window_info};
+
+const TEST_TASK: u32 = 0xF012;
+const TEST_PROCESS: u32 = TEST_TASK;
+const OTHER_PROCESS: u32 = 0xF013;
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 8;
+const GREY: u32 = 0x00FE_FEFE;
+
+/// A registered surface and a second buffer to draw its thumbnails into.
+struct ThumbnailFixture {
+    surface: SurfaceFixture<2>,
+    thumb: u32,
+}
+
+impl ThumbnailFixture {
+    fn new() -> Option<Self> {
+        let size = (WIDTH * HEIGHT * 4) as u64;
+        let surface = SurfaceFixture::with_buffers(TEST_TASK, WIDTH, HEIGHT, [size; 2])?;
+        let thumb = surface.tokens[1];
+        Some(Self { surface, thumb })
+    }
+
+    fn pixels(token: u32) -> *mut u32 {
+        shm_get_buffer_info(token).0.to_virt().as_mut_ptr()
+    }
+
+    /// Left half black, right half alternating black and grey columns.
+    fn fill_pattern(&self) {
+        let px = Self::pixels(self.surface.token());
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let value = if x >= WIDTH / 2 && x % 2 == 1 {
+                    GREY
+                } else {
+                    0
+                };
+                // SAFETY: the buffer holds WIDTH * HEIGHT pixels.
+                unsafe { px.add((y * WIDTH + x) as usize).write(value) };
+            }
+        }
+    }
+
+    fn thumb_pixel(&self, index: usize) -> u32 {
+        // SAFETY: callers stay within the thumbnail drawn.
+        unsafe { Self::pixels(self.thumb).add(index).read() }
+    }
+}
+
+pub fn test_thumbnail_size_keeps_aspect() -> TestResult {
+    assert_eq_test!(thumbnail_size(800, 600, 200, 200), (200, 150), "wide");
+    assert_eq_test!(thumbnail_size(300, 900, 200, 200), (66, 200), "tall");
+    assert_eq_test!(thumbnail_size(100, 50, 200, 200), (100, 50), "upscaled");
+    assert_eq_test!(thumbnail_size(4000, 1, 100, 100), (100, 1), "zero height");
+    pass!()
+}
+
+pub fn test_thumbnail_scales_and_averages() -> TestResult {
+    let Some(fx) = ThumbnailFixture::new() else {
+        return fail!("fixture setup failed");
+    };
+    fx.fill_pattern();
+
+    let size = surface_render_thumbnail(TEST_TASK, TEST_PROCESS, fx.thumb, 4, 4);
+    assert_eq_test!(size, Ok((4, 2)), "thumbnail not fitted");
+    for row in 0..2 {
+        let base = row * 4;
+        assert_eq_test!(fx.thumb_pixel(base), 0, "black half changed");
+        assert_eq_test!(fx.thumb_pixel(base + 1), 0, "black half changed");
+        assert_eq_test!(
+            fx.thumb_pixel(base + 2),
+            0x007F_7F7F,
+            "columns not averaged"
+        );
+        assert_eq_test!(
+            fx.thumb_pixel(base + 3),
+            0x007F_7F7F,
+            "columns not averaged"
+        );
+    }
+    pass!()
+}
+
+pub fn test_thumbnail_checks_destination() -> TestResult {
+    let Some(fx) = ThumbnailFixture::new() else {
+        return fail!("fixture setup failed");
+    };
+
+    assert_eq_test!(
+        surface_render_thumbnail(TEST_TASK, OTHER_PROCESS, fx.thumb, 4, 4),
+        Err(CompositorError::PermissionDenied),
+        "drew into another process's buffer"
+    );
+    assert_eq_test!(
+        surface_render_thumbnail(TEST_TASK, TEST_PROCESS, fx.thumb, 4096, 4096),
+        Ok((WIDTH, HEIGHT)),
+        "small surface not copied at its own size"
+    );
+    assert_eq_test!(
+        surface_render_thumbnail(TEST_TASK, TEST_PROCESS, fx.thumb, 0, 4),
+        Err(CompositorError::InvalidArgument),
+        "zero size accepted"
+    );
+    assert_eq_test!(
+        surface_render_thumbnail(0xDEAD, TEST_PROCESS, fx.thumb, 4, 4),
+        Err(CompositorError::SurfaceNotFound),
+        "thumbnail of a task without a surface"
+    );
+    pass!()
+}
+
+pub fn test_offscreen_surface_not_composited() -> TestResult {
+    let Some(fx) = ThumbnailFixture::new() else {
+        return fail!("fixture setup failed");
+    };
+    if surface_set_role(TEST_TASK, SurfaceRole::Offscreen as u8).is_err() {
+        return fail!("offscreen role rejected");
+    }
+    drain_queue();
+
+    assert_test!(
+        window_info(TEST_TASK).is_none(),
+        "offscreen surface enumerated as a window"
+    );
+
+    fx.fill_pattern();
+    assert_eq_test!(
+        surface_render_thumbnail(TEST_TASK, TEST_PROCESS, fx.thumb, 4, 4),
+        Ok((4, 2)),
+        "no thumbnail of an offscreen surface"
+    );
+    assert_eq_test!(fx.thumb_pixel(3), 0x007F_7F7F, "offscreen content wrong");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    thumbnail,
+    [
+        test_thumbnail_size_keeps_aspect,
+        test_thumbnail_scales_and_averages,
+        test_thumbnail_checks_destination,
+        test_offscreen_surface_not_composited,
+    ]
+);
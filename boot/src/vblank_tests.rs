@@ -0,0 +1,97 @@
+//! Vblank tests: the refresh setting, waiting for a vblank, and frame
+//! callbacks completing on the vblank after the flip rather than at it.
+
+use slopos_abi::present::PRESENT_REFRESH_NS;
+use slopos_lib::clock::monotonic_ns;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+use slopos_video::compositor_context::{
+    drain_queue, surface_complete_frame_callbacks, surface_mark_frames_done,
+    surface_poll_frame_done, surface_request_frame_callback,
+};
+use slopos_video::vblank::{
+    refresh_ns, set_refresh_hz, set_refresh_ns, vblank_seq_at, vblank_time_ns, wait_vblank,
+};
+
+use crate::surface_fixture::SurfaceFixture;
+
+const TEST_TASK: u32 = 0xF010;
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+fn fixture() -> Option<SurfaceFixture> {
+    SurfaceFixture::new(TEST_TASK, WIDTH, HEIGHT)
+}
+
+pub fn test_refresh_rate_setting() -> TestResult {
+    let saved = refresh_ns();
+    assert_test!(!set_refresh_hz(0), "0 Hz accepted");
+    assert_test!(!set_refresh_hz(1000), "1000 Hz accepted");
+    assert_eq_test!(refresh_ns(), saved, "rejected rate changed the refresh");
+
+    assert_test!(set_refresh_hz(75));
+    assert_eq_test!(refresh_ns(), 1_000_000_000 / 75);
+    assert_eq_test!(vblank_seq_at(vblank_time_ns(40)), 40);
+    assert_eq_test!(vblank_seq_at(vblank_time_ns(40) - 1), 39);
+
+    assert_test!(
+        set_refresh_ns(PRESENT_REFRESH_NS),
+        "default refresh rejected"
+    );
+    assert_test!(set_refresh_ns(saved));
+    pass!()
+}
+
+pub fn test_wait_vblank_advances() -> TestResult {
+    let before = vblank_seq_at(monotonic_ns());
+    let first = wait_vblank(0);
+    assert_test!(first > before, "wait returned without a new vblank");
+    assert_test!(
+        monotonic_ns() >= vblank_time_ns(first),
+        "returned before the vblank"
+    );
+
+    let second = wait_vblank(first);
+    assert_test!(second > first, "waiting past a seen vblank did not advance");
+    // A vblank already past returns at once.
+    assert_test!(wait_vblank(first - 1) >= second);
+    pass!()
+}
+
+pub fn test_frame_callback_waits_for_vblank() -> TestResult {
+    let Some(_fx) = fixture() else {
+        return fail!("fixture setup failed");
+    };
+    let _ = surface_request_frame_callback(TEST_TASK);
+    drain_queue();
+    assert_eq_test!(surface_poll_frame_done(TEST_TASK), 0, "done before flip");
+
+    let seq = vblank_seq_at(monotonic_ns());
+    surface_mark_frames_done(1);
+    let done = surface_poll_frame_done(TEST_TASK);
+    // The vblank timer may legitimately have fired if a refresh passed.
+    if vblank_seq_at(monotonic_ns()) == seq {
+        assert_eq_test!(done, 0, "done at the flip, before the vblank");
+    }
+
+    let vblank_ms = vblank_time_ns(seq + 1) / 1_000_000;
+    surface_complete_frame_callbacks(vblank_ms);
+    if done == 0 {
+        assert_eq_test!(surface_poll_frame_done(TEST_TASK), vblank_ms.max(1));
+    }
+    assert_eq_test!(surface_poll_frame_done(TEST_TASK), 0, "not one-shot");
+
+    // Completing again without a new flip reports nothing.
+    surface_complete_frame_callbacks(vblank_ms + 16);
+    assert_eq_test!(surface_poll_frame_done(TEST_TASK), 0);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    vblank,
+    [
+        test_refresh_rate_setting,
+        test_wait_vblank_advances,
+        test_frame_callback_waits_for_vblank,
+    ]
+);
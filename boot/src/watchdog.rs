@@ -0,0 +1,297 @@
+//! Soft lockup watchdog.
+//!
+//! Every CPU pets the watchdog from its LAPIC timer tick, recording the time
+//! and the interrupted frame.  A kernel thread wakes once a second and looks
+//! for online CPUs whose tick has gone quiet for longer than the timeout:
+//! a CPU spinning with interrupts off, typically on a scheduler or IRQ lock.
+//! For each one it logs the last frame the CPU was seen in and the stack
+//! trace from there, using the same kdiag dumpers as exceptions, and with
+//! `watchdog.panic=1` it brings up the panic screen with that RIP.
+//!
+//! Idle CPUs stop their tick on purpose (see the scheduler's tickless
+//! policy) and are skipped.  A hung CPU is reported once; it is logged
+//! again as responsive when its tick comes back.
+//!
+//! Options:
+//! - `watchdog=0`: do not start the watchdog thread.
+//! - `watchdog.timeout=<s>`: seconds without a tick before a CPU counts as
+//!   hung (default 10).
+//! - `watchdog.panic=1`: panic instead of only logging.
+
+use core::ffi::CStr;
+use core::mem::size_of;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use slopos_core::kthread::kthread_spawn;
+use slopos_core::scheduler::sleep::sleep_current_task_ms;
+use slopos_core::scheduler::tickless::tick_stopped;
+use slopos_core::task::INVALID_TASK_ID;
+use slopos_lib::{
+    InterruptFrame, MAX_CPUS, clock, get_cpu_count, is_cpu_online, kdiag, klog_info, klog_warn, pcr,
+};
+
+use crate::early_init::{boot_get_cmdline, boot_init_priority};
+use crate::panic::set_panic_cpu_state;
+
+pub const WATCHDOG_TIMEOUT_MS_DEFAULT: u64 = 10_000;
+
+/// How often the watchdog thread looks at the CPUs.
+const CHECK_INTERVAL_MS: u32 = 1_000;
+
+const FRAME_WORDS: usize = size_of::<InterruptFrame>() / size_of::<u64>();
+const _: () = assert!(FRAME_WORDS * size_of::<u64>() == size_of::<InterruptFrame>());
+
+/// What the watchdog knows about one CPU.  The frame is a seqlock written
+/// only by the owning CPU's timer interrupt.
+struct CpuWatch {
+    last_pet_ms: AtomicU64,
+    seq: AtomicU64,
+    frame: [AtomicU64; FRAME_WORDS],
+    hung: AtomicBool,
+}
+
+impl CpuWatch {
+    const fn new() -> Self {
+        Self {
+            last_pet_ms: AtomicU64::new(0),
+            seq: AtomicU64::new(0),
+            frame: [const { AtomicU64::new(0) }; FRAME_WORDS],
+            hung: AtomicBool::new(false),
+        }
+    }
+
+    fn store_frame(&self, frame: &InterruptFrame) {
+        // SAFETY: InterruptFrame is repr(C) and made of u64 fields only.
+        let words: [u64; FRAME_WORDS] =
+            unsafe { ptr::read(frame as *const InterruptFrame as *const [u64; FRAME_WORDS]) };
+        let seq = self.seq.load(Ordering::Relaxed);
+        self.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+        core::sync::atomic::fence(Ordering::Release);
+        for (slot, word) in self.frame.iter().zip(words) {
+            slot.store(word, Ordering::Relaxed);
+        }
+        self.seq.store(seq.wrapping_add(2), Ordering::Release);
+    }
+
+    /// Last frame seen on this CPU, or `None` before its first tick or if
+    /// it keeps changing under us.
+    fn load_frame(&self) -> Option<InterruptFrame> {
+        for _ in 0..4 {
+            let before = self.seq.load(Ordering::Acquire);
+            if before == 0 || before & 1 != 0 {
+                continue;
+            }
+            let mut words = [0u64; FRAME_WORDS];
+            for (word, slot) in words.iter_mut().zip(&self.frame) {
+                *word = slot.load(Ordering::Relaxed);
+            }
+            core::sync::atomic::fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == before {
+                // SAFETY: same layout as in `store_frame`; any bit pattern
+                // is a valid InterruptFrame.
+                return Some(unsafe {
+                    core::mem::transmute::<[u64; FRAME_WORDS], InterruptFrame>(words)
+                });
+            }
+        }
+        None
+    }
+}
+
+static CPUS: [CpuWatch; MAX_CPUS] = [const { CpuWatch::new() }; MAX_CPUS];
+
+static TIMEOUT_MS: AtomicU64 = AtomicU64::new(WATCHDOG_TIMEOUT_MS_DEFAULT);
+static PANIC_ON_HANG: AtomicBool = AtomicBool::new(false);
+
+/// A CPU the watchdog has just found hung.
+#[derive(Clone, Copy, Debug)]
+pub struct WatchdogHang {
+    pub cpu: usize,
+    pub stalled_ms: u64,
+    /// Last interrupted RIP/RSP, if the CPU ever ticked.
+    pub rip: Option<u64>,
+    pub rsp: Option<u64>,
+}
+
+pub fn watchdog_timeout_ms() -> u64 {
+    TIMEOUT_MS.load(Ordering::Relaxed)
+}
+
+pub fn watchdog_set_timeout_ms(ms: u64) {
+    TIMEOUT_MS.store(ms.max(CHECK_INTERVAL_MS as u64), Ordering::Relaxed);
+}
+
+/// LAPIC timer hook, called on every CPU's tick.
+pub(crate) fn watchdog_pet(frame: *const InterruptFrame) {
+    let Some(watch) = CPUS.get(pcr::current_cpu_id()) else {
+        return;
+    };
+    watch
+        .last_pet_ms
+        .store(clock::uptime_ms(), Ordering::Relaxed);
+    // SAFETY: the interrupt entry path hands us its live frame.
+    if let Some(frame) = unsafe { frame.as_ref() } {
+        watch.store_frame(frame);
+    }
+}
+
+/// Milliseconds since `cpu` last ticked, as of `now_ms`.
+pub fn watchdog_stalled_ms(cpu: usize, now_ms: u64) -> u64 {
+    CPUS.get(cpu).map_or(0, |watch| {
+        now_ms.saturating_sub(watch.last_pet_ms.load(Ordering::Relaxed))
+    })
+}
+
+pub fn watchdog_cpu_hung(cpu: usize) -> bool {
+    CPUS.get(cpu)
+        .is_some_and(|watch| watch.hung.load(Ordering::Relaxed))
+}
+
+fn report_hang(cpu: usize, stalled_ms: u64, frame: Option<&InterruptFrame>) {
+    klog_warn!(
+        "WATCHDOG: CPU {} unresponsive for {} ms (timeout {} ms)",
+        cpu,
+        stalled_ms,
+        watchdog_timeout_ms()
+    );
+    match frame {
+        Some(frame) => {
+            klog_warn!("WATCHDOG: last frame seen on CPU {}:", cpu);
+            kdiag::kdiag_dump_interrupt_frame(frame);
+            kdiag::kdiag_dump_stack_trace_from_frame(frame);
+        }
+        None => klog_warn!("WATCHDOG: CPU {} never ticked", cpu),
+    }
+}
+
+/// Check every online CPU as of `now_ms`.  Reports CPUs that went quiet
+/// since the last scan and returns the first of them.
+pub fn watchdog_scan(now_ms: u64) -> Option<WatchdogHang> {
+    let timeout_ms = watchdog_timeout_ms();
+    let mut first = None;
+    for (cpu, watch) in CPUS.iter().enumerate().take(get_cpu_count()) {
+        if !is_cpu_online(cpu) || tick_stopped(cpu) {
+            watch.hung.store(false, Ordering::Relaxed);
+            continue;
+        }
+        let stalled_ms = watchdog_stalled_ms(cpu, now_ms);
+        if stalled_ms <= timeout_ms {
+            if watch.hung.swap(false, Ordering::Relaxed) {
+                klog_info!("WATCHDOG: CPU {} responsive again", cpu);
+            }
+            continue;
+        }
+        if watch.hung.swap(true, Ordering::Relaxed) {
+            continue;
+        }
+        let frame = watch.load_frame();
+        report_hang(cpu, stalled_ms, frame.as_ref());
+        first.get_or_insert(WatchdogHang {
+            cpu,
+            stalled_ms,
+            rip: frame.as_ref().map(|f| f.rip),
+            rsp: frame.as_ref().map(|f| f.rsp),
+        });
+    }
+    first
+}
+
+fn watchdog_task(_arg: *mut core::ffi::c_void) {
+    // CPUs that were idle or not yet ticking when we started get a full
+    // timeout from now rather than from boot.
+    let now_ms = clock::uptime_ms();
+    for watch in &CPUS {
+        let _ = watch
+            .last_pet_ms
+            .compare_exchange(0, now_ms, Ordering::Relaxed, Ordering::Relaxed);
+    }
+
+    loop {
+        sleep_current_task_ms(CHECK_INTERVAL_MS);
+        let Some(hang) = watchdog_scan(clock::uptime_ms()) else {
+            continue;
+        };
+        if PANIC_ON_HANG.load(Ordering::Relaxed) {
+            if let (Some(rip), Some(rsp)) = (hang.rip, hang.rsp) {
+                set_panic_cpu_state(rip, rsp);
+            }
+            panic!("watchdog: CPU {} hung for {} ms", hang.cpu, hang.stalled_ms);
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchdogConfig {
+    pub enabled: bool,
+    pub timeout_ms: u64,
+    pub panic: bool,
+}
+
+pub(crate) fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "1" | "on" | "true" | "yes" => Some(true),
+        "0" | "off" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn config_from_cmdline(cmdline: Option<&str>) -> WatchdogConfig {
+    let mut cfg = WatchdogConfig {
+        enabled: true,
+        timeout_ms: WATCHDOG_TIMEOUT_MS_DEFAULT,
+        panic: false,
+    };
+    let Some(cmdline) = cmdline else {
+        return cfg;
+    };
+    for token in cmdline.split_whitespace() {
+        if let Some(value) = token.strip_prefix("watchdog=") {
+            cfg.enabled = parse_bool(value).unwrap_or(true);
+        } else if let Some(value) = token.strip_prefix("watchdog.timeout=") {
+            if let Ok(secs) = value.trim_end_matches('s').parse::<u64>() {
+                cfg.timeout_ms = secs.saturating_mul(1000);
+            }
+        } else if let Some(value) = token.strip_prefix("watchdog.panic=") {
+            cfg.panic = parse_bool(value).unwrap_or(false);
+        }
+    }
+    cfg
+}
+
+fn boot_step_watchdog() -> i32 {
+    let cmdline = boot_get_cmdline();
+    let cmdline_str = if cmdline.is_null() {
+        None
+    } else {
+        unsafe { CStr::from_ptr(cmdline) }.to_str().ok()
+    };
+    let cfg = config_from_cmdline(cmdline_str);
+    if !cfg.enabled {
+        klog_info!("WATCHDOG: disabled");
+        return 0;
+    }
+    watchdog_set_timeout_ms(cfg.timeout_ms);
+    PANIC_ON_HANG.store(cfg.panic, Ordering::Relaxed);
+
+    let task_id = kthread_spawn(c"watchdog".as_ptr(), Some(watchdog_task), ptr::null_mut());
+    if task_id == INVALID_TASK_ID {
+        klog_info!("WATCHDOG: failed to spawn watchdog thread");
+        return -1;
+    }
+    klog_info!(
+        "WATCHDOG: {} ms timeout{}",
+        watchdog_timeout_ms(),
+        if cfg.panic { ", panic on hang" } else { "" }
+    );
+    0
+}
+
+crate::boot_init!(
+    BOOT_STEP_WATCHDOG,
+    services,
+    b"watchdog\0",
+    boot_step_watchdog,
+    fallible,
+    flags = boot_init_priority(57)
+);
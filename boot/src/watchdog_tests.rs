@@ -0,0 +1,74 @@
+//! Soft lockup watchdog tests.
+//!
+//! The watchdog thread is not running yet when the suites run, so scans
+//! here are driven by hand with a made-up "now".
+
+use slopos_drivers::hpet;
+use slopos_lib::{assert_eq_test, assert_test, clock, fail, pass, pcr, testing::TestResult};
+
+use crate::watchdog::{
+    WATCHDOG_TIMEOUT_MS_DEFAULT, WatchdogConfig, config_from_cmdline, watchdog_cpu_hung,
+    watchdog_scan, watchdog_stalled_ms, watchdog_timeout_ms,
+};
+
+pub fn test_watchdog_tick_pets_current_cpu() -> TestResult {
+    let cpu = pcr::current_cpu_id();
+    hpet::delay_ms(50);
+    let stalled = watchdog_stalled_ms(cpu, clock::uptime_ms());
+    assert_test!(
+        stalled < 50,
+        "CPU {} not petted for {} ms with its timer running",
+        cpu,
+        stalled
+    );
+    pass!()
+}
+
+pub fn test_watchdog_scan_reports_hang_once() -> TestResult {
+    let cpu = pcr::current_cpu_id();
+    let future = clock::uptime_ms() + watchdog_timeout_ms() * 2;
+
+    let Some(hang) = watchdog_scan(future) else {
+        return fail!("scan past the timeout found no hung CPU");
+    };
+    assert_test!(hang.stalled_ms > watchdog_timeout_ms());
+    assert_test!(watchdog_cpu_hung(cpu), "current CPU not marked hung");
+    assert_test!(
+        watchdog_scan(future).is_none(),
+        "hung CPUs reported a second time"
+    );
+
+    // The real clock is within the timeout again, so the CPU recovers.
+    hpet::delay_ms(20);
+    let _ = watchdog_scan(clock::uptime_ms());
+    assert_test!(!watchdog_cpu_hung(cpu), "current CPU still marked hung");
+    pass!()
+}
+
+pub fn test_watchdog_cmdline_options() -> TestResult {
+    let defaults = WatchdogConfig {
+        enabled: true,
+        timeout_ms: WATCHDOG_TIMEOUT_MS_DEFAULT,
+        panic: false,
+    };
+    assert_eq_test!(config_from_cmdline(None), defaults);
+    assert_eq_test!(config_from_cmdline(Some("quiet autopilot=1")), defaults);
+
+    let cfg = config_from_cmdline(Some("watchdog.timeout=3 watchdog.panic=1"));
+    assert_eq_test!(cfg.timeout_ms, 3000);
+    assert_test!(cfg.enabled && cfg.panic);
+
+    let cfg = config_from_cmdline(Some("watchdog=off watchdog.timeout=junk"));
+    assert_test!(!cfg.enabled);
+    assert_eq_test!(cfg.timeout_ms, WATCHDOG_TIMEOUT_MS_DEFAULT);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    watchdog,
+    [
+        test_watchdog_tick_pets_current_cpu,
+        test_watchdog_scan_reports_hang_once,
+        test_watchdog_cmdline_options,
+    ]
+);
@@ -0,0 +1,411 @@
+//! Audit log of privileged operations, and process accounting.
+//!
+//! The syscall dispatcher asks [`audit_syscall_enter`] about every call.
+//! Calls that change machine-wide state (power, packet filter and routes,
+//! keymap, tracing, the audit mask itself) or open raw sockets produce an [`AuditRecord`] with
+//! the caller and the result, and every user process leaves one
+//! [`AUDIT_CAT_ACCT`] record when it exits.  Records go into a fixed ring so
+//! recording never allocates or touches the filesystem.
+//!
+//! The `auditd` kernel thread appends pending records as text lines to
+//! [`AUDIT_LOG_PATH`] and [`AUDIT_ACCT_PATH`] every few seconds.  Until the
+//! root filesystem is mounted and writable the appends fail and records
+//! stay in the ring; ones overwritten in the meantime are counted as
+//! dropped.  Halt and reboot do not return, so their record is written
+//! before the call.
+
+use alloc::string::String;
+use core::fmt::Write;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use slopos_abi::audit::{
+    AUDIT_ACCT_PATH, AUDIT_CAT_ACCT, AUDIT_CAT_ALL, AUDIT_CAT_AUDIT, AUDIT_CAT_DEBUG,
+    AUDIT_CAT_DEFAULT, AUDIT_CAT_INPUT, AUDIT_CAT_NET, AUDIT_CAT_POWER, AUDIT_COMM_LEN,
+    AUDIT_LOG_PATH, AUDIT_OP_SET, AUDIT_RING_LEN, AuditRecord, AuditStatus, audit_cat_name,
+};
+use slopos_abi::input::KEYMAP_QUERY;
+use slopos_abi::klog::KLOG_LEVEL_KEEP;
+use slopos_abi::net::{NET_FILTER_OP_LIST, NET_FILTER_OP_STATUS, NET_ROUTE_OP_LIST, SOCK_RAW};
+use slopos_abi::syscall::{
+    KWARN_PANIC_KEEP, SYSCALL_AUDIT_CTL, SYSCALL_HALT, SYSCALL_KLOG_LEVEL, SYSCALL_KTRACE,
+    SYSCALL_KWARN_STATS, SYSCALL_NET_FILTER, SYSCALL_NET_ROUTE, SYSCALL_PERF_OPEN, SYSCALL_REBOOT,
+    SYSCALL_SET_KEYMAP, SYSCALL_SOCKET,
+};
+use slopos_abi::task::{TASK_FLAG_USER_MODE, TaskExitReason};
+use slopos_fs::vfs::ops::{vfs_mkdir, vfs_open};
+use slopos_fs::vfs::traits::VfsError;
+use slopos_lib::string::cstr_to_str;
+use slopos_lib::{InterruptFrame, IrqMutex, clock, klog_info, walltime};
+
+use crate::kthread::kthread_spawn;
+use crate::scheduler::sleep::sleep_current_task_ms;
+use crate::scheduler::task_struct::Task;
+use crate::syscall::handlers::syscall_lookup;
+use crate::task::INVALID_TASK_ID;
+
+/// How often `auditd` appends pending records.
+const FLUSH_INTERVAL_MS: u32 = 2_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditError {
+    /// Another flush is in progress.
+    Busy,
+    /// A log file could not be opened or written.
+    Fs(VfsError),
+}
+
+/// The last [`AUDIT_RING_LEN`] records, indexed by sequence number.
+pub(crate) struct AuditRing {
+    records: [AuditRecord; AUDIT_RING_LEN],
+    /// Sequence number the next record gets.
+    next: u64,
+    /// First record not yet appended to the log files.
+    unwritten: u64,
+    written: u64,
+    dropped: u64,
+}
+
+impl AuditRing {
+    pub(crate) const fn new() -> Self {
+        Self {
+            records: [AuditRecord {
+                seq: 0,
+                time_ns: 0,
+                pid: 0,
+                uid: 0,
+                category: 0,
+                op: 0,
+                args: [0; 2],
+                result: 0,
+                comm: [0; AUDIT_COMM_LEN],
+            }; AUDIT_RING_LEN],
+            next: 0,
+            unwritten: 0,
+            written: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Store `record` under the next sequence number and return it.
+    pub(crate) fn push(&mut self, mut record: AuditRecord) -> u64 {
+        let seq = self.next;
+        record.seq = seq;
+        self.records[seq as usize % AUDIT_RING_LEN] = record;
+        self.next += 1;
+        if self.next - self.unwritten > AUDIT_RING_LEN as u64 {
+            self.unwritten += 1;
+            self.dropped += 1;
+        }
+        seq
+    }
+
+    /// Sequence numbers still held, oldest first.
+    pub(crate) fn retained(&self) -> core::ops::Range<u64> {
+        self.next.saturating_sub(AUDIT_RING_LEN as u64)..self.next
+    }
+
+    /// Sequence numbers recorded but not yet written.
+    pub(crate) fn pending(&self) -> core::ops::Range<u64> {
+        self.unwritten..self.next
+    }
+
+    pub(crate) fn get(&self, seq: u64) -> Option<AuditRecord> {
+        self.retained()
+            .contains(&seq)
+            .then(|| self.records[seq as usize % AUDIT_RING_LEN])
+    }
+
+    /// Everything before `end` has reached the log files.  Returns how many
+    /// records that newly covers.
+    pub(crate) fn mark_written(&mut self, end: u64) -> u64 {
+        let newly = end.saturating_sub(self.unwritten);
+        self.unwritten = self.unwritten.max(end);
+        self.written += newly;
+        newly
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+static RING: IrqMutex<AuditRing> = IrqMutex::new(AuditRing::new());
+static ENABLED: AtomicU32 = AtomicU32::new(AUDIT_CAT_DEFAULT);
+/// Set by the first successful flush.
+static LOGGING: AtomicBool = AtomicBool::new(false);
+/// Serializes flushes so a record is never appended twice.
+static FLUSHING: AtomicBool = AtomicBool::new(false);
+
+struct FlushGuard;
+
+impl FlushGuard {
+    fn acquire() -> Result<Self, AuditError> {
+        if FLUSHING.swap(true, Ordering::Acquire) {
+            return Err(AuditError::Busy);
+        }
+        Ok(Self)
+    }
+}
+
+impl Drop for FlushGuard {
+    fn drop(&mut self) {
+        FLUSHING.store(false, Ordering::Release);
+    }
+}
+
+/// Enabled `AUDIT_CAT_*` bits.
+pub fn audit_mask() -> u32 {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Replace the enabled categories and return the old mask.
+/// [`AUDIT_CAT_AUDIT`] stays set whatever `mask` says.
+pub fn audit_set_mask(mask: u32) -> u32 {
+    ENABLED.swap((mask & AUDIT_CAT_ALL) | AUDIT_CAT_AUDIT, Ordering::Relaxed)
+}
+
+pub fn audit_status() -> AuditStatus {
+    let ring = RING.lock();
+    AuditStatus {
+        enabled: audit_mask(),
+        logging: LOGGING.load(Ordering::Relaxed) as u32,
+        recorded: ring.next,
+        written: ring.written,
+        dropped: ring.dropped(),
+    }
+}
+
+/// Sequence numbers of the records still in memory.
+pub fn audit_retained() -> core::ops::Range<u64> {
+    RING.lock().retained()
+}
+
+/// Record `seq`, if it has not been overwritten yet.
+pub fn audit_record(seq: u64) -> Option<AuditRecord> {
+    RING.lock().get(seq)
+}
+
+fn task_record(task: &Task, category: u32) -> AuditRecord {
+    let mut record = AuditRecord {
+        time_ns: walltime::realtime_ns(),
+        pid: task.task_id,
+        category,
+        ..AuditRecord::default()
+    };
+    let len = task
+        .name
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(task.name.len())
+        .min(AUDIT_COMM_LEN - 1);
+    record.comm[..len].copy_from_slice(&task.name[..len]);
+    record
+}
+
+fn push(record: AuditRecord) {
+    if audit_mask() & record.category != 0 {
+        RING.lock().push(record);
+    }
+}
+
+/// Category of a syscall, or `None` when it is not audited.  Read-only
+/// commands of the multiplexed syscalls are not audited.
+pub fn syscall_category(sysno: u64, arg0: u64, arg1: u64) -> Option<u32> {
+    match sysno {
+        SYSCALL_HALT | SYSCALL_REBOOT => Some(AUDIT_CAT_POWER),
+        SYSCALL_NET_FILTER => {
+            (arg0 != NET_FILTER_OP_LIST && arg0 != NET_FILTER_OP_STATUS).then_some(AUDIT_CAT_NET)
+        }
+        SYSCALL_NET_ROUTE => (arg0 != NET_ROUTE_OP_LIST).then_some(AUDIT_CAT_NET),
+        SYSCALL_SOCKET => (arg1 == SOCK_RAW as u64).then_some(AUDIT_CAT_NET),
+        SYSCALL_SET_KEYMAP => (arg0 != KEYMAP_QUERY).then_some(AUDIT_CAT_INPUT),
+        SYSCALL_KTRACE => Some(AUDIT_CAT_DEBUG),
+        SYSCALL_KWARN_STATS => (arg1 != KWARN_PANIC_KEEP).then_some(AUDIT_CAT_DEBUG),
+        SYSCALL_KLOG_LEVEL => (arg1 != KLOG_LEVEL_KEEP).then_some(AUDIT_CAT_DEBUG),
+        SYSCALL_PERF_OPEN => (arg1 != 0).then_some(AUDIT_CAT_DEBUG),
+        SYSCALL_AUDIT_CTL => (arg0 == AUDIT_OP_SET).then_some(AUDIT_CAT_AUDIT),
+        _ => None,
+    }
+}
+
+/// An audited syscall in flight, completed by [`PendingAudit::finish`].
+pub struct PendingAudit {
+    record: AuditRecord,
+}
+
+impl PendingAudit {
+    /// Record the call with its return value.
+    pub fn finish(self, rax: u64) {
+        push(AuditRecord {
+            result: rax as i64,
+            ..self.record
+        });
+    }
+}
+
+/// Called by the dispatcher before running `sysno`.  Returns the record to
+/// complete once the handler has returned, if the call is audited.
+pub fn audit_syscall_enter(
+    task: &Task,
+    sysno: u64,
+    frame: &InterruptFrame,
+) -> Option<PendingAudit> {
+    let category = syscall_category(sysno, frame.rdi, frame.rsi)?;
+    if audit_mask() & category == 0 {
+        return None;
+    }
+    let record = AuditRecord {
+        op: sysno as u32,
+        args: [frame.rdi, frame.rsi],
+        ..task_record(task, category)
+    };
+    if category == AUDIT_CAT_POWER {
+        push(record);
+        let _ = audit_flush();
+        return None;
+    }
+    Some(PendingAudit { record })
+}
+
+/// Accounting record for a terminating task.  Only the leader of a user
+/// thread group is recorded, so a process yields one record.
+pub fn audit_task_exit(task: &Task) {
+    if task.flags & TASK_FLAG_USER_MODE == 0 || task.tgid != task.task_id {
+        return;
+    }
+    let lifetime_ms = clock::monotonic_ns().saturating_sub(task.start_ns) / 1_000_000;
+    push(AuditRecord {
+        op: task.exit_reason as u32,
+        args: [lifetime_ms, task.fault_reason as u64],
+        result: task.exit_code as i64,
+        ..task_record(task, AUDIT_CAT_ACCT)
+    });
+}
+
+fn syscall_name(sysno: u32) -> &'static str {
+    let entry = syscall_lookup(sysno as u64);
+    if entry.is_null() {
+        return "unknown";
+    }
+    unsafe { cstr_to_str((*entry).name) }
+}
+
+fn exit_reason_name(reason: u32) -> &'static str {
+    match reason {
+        r if r == TaskExitReason::Normal as u32 => "exit",
+        r if r == TaskExitReason::UserFault as u32 => "fault",
+        r if r == TaskExitReason::Kernel as u32 => "killed",
+        _ => "unknown",
+    }
+}
+
+/// One log line, newline included.
+pub(crate) fn format_record(out: &mut String, record: &AuditRecord) {
+    let secs = record.time_ns / 1_000_000_000;
+    let nanos = record.time_ns % 1_000_000_000;
+    let _ = write!(
+        out,
+        "time={}.{:09} pid={} uid={} comm={} ",
+        secs,
+        nanos,
+        record.pid,
+        record.uid,
+        record.comm_str()
+    );
+    let _ = if record.category == AUDIT_CAT_ACCT {
+        writeln!(
+            out,
+            "reason={} status={} elapsed_ms={}",
+            exit_reason_name(record.op),
+            record.result,
+            record.args[0]
+        )
+    } else {
+        writeln!(
+            out,
+            "seq={} cat={} syscall={} args={:#x},{:#x} result={}",
+            record.seq,
+            audit_cat_name(record.category),
+            syscall_name(record.op),
+            record.args[0],
+            record.args[1],
+            record.result
+        )
+    };
+}
+
+pub(crate) fn ensure_dir(path: &[u8]) -> Result<(), VfsError> {
+    match vfs_mkdir(path) {
+        Ok(()) | Err(VfsError::AlreadyExists) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Append `text` to the file at `path`, creating it if needed.
+pub(crate) fn append(path: &[u8], text: &str) -> Result<(), VfsError> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    let handle = vfs_open(path, true)?;
+    let mut offset = handle.size()?;
+    let mut data = text.as_bytes();
+    while !data.is_empty() {
+        match handle.write(offset, data)? {
+            0 => return Err(VfsError::NoSpace),
+            n => {
+                data = &data[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Append every pending record to the log files.  Returns how many were
+/// written.
+pub fn audit_flush() -> Result<usize, AuditError> {
+    let _flushing = FlushGuard::acquire()?;
+    let pending = RING.lock().pending();
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let mut audit = String::new();
+    let mut acct = String::new();
+    for seq in pending.clone() {
+        let Some(record) = audit_record(seq) else {
+            continue;
+        };
+        let out = if record.category == AUDIT_CAT_ACCT {
+            &mut acct
+        } else {
+            &mut audit
+        };
+        format_record(out, &record);
+    }
+    ensure_dir(b"/var")
+        .and_then(|()| ensure_dir(b"/var/log"))
+        .map_err(AuditError::Fs)?;
+    append(AUDIT_LOG_PATH, &audit).map_err(AuditError::Fs)?;
+    append(AUDIT_ACCT_PATH, &acct).map_err(AuditError::Fs)?;
+
+    let written = RING.lock().mark_written(pending.end);
+    if !LOGGING.swap(true, Ordering::Relaxed) {
+        klog_info!("AUDIT: logging to /var/log/audit and /var/log/acct");
+    }
+    Ok(written as usize)
+}
+
+fn auditd_task(_arg: *mut core::ffi::c_void) {
+    loop {
+        sleep_current_task_ms(FLUSH_INTERVAL_MS);
+        let _ = audit_flush();
+    }
+}
+
+/// Start the `auditd` thread.  Returns false if it could not be spawned.
+pub fn audit_start() -> bool {
+    let task_id = kthread_spawn(c"auditd".as_ptr(), Some(auditd_task), ptr::null_mut());
+    task_id != INVALID_TASK_ID
+}
@@ -0,0 +1,210 @@
+//! Audit tests: the record ring, syscall classification, the mask and the
+//! log line format.
+
+use alloc::string::String;
+
+use slopos_abi::audit::{
+    AUDIT_CAT_ACCT, AUDIT_CAT_ALL, AUDIT_CAT_AUDIT, AUDIT_CAT_DEBUG, AUDIT_CAT_INPUT,
+    AUDIT_CAT_NET, AUDIT_CAT_POWER, AUDIT_OP_READ, AUDIT_OP_SET, AUDIT_RING_LEN, AuditRecord,
+};
+use slopos_abi::input::KEYMAP_QUERY;
+use slopos_abi::klog::{KLOG_LEVEL_INHERIT, KLOG_LEVEL_KEEP};
+use slopos_abi::net::{
+    NET_FILTER_OP_APPEND, NET_FILTER_OP_LIST, NET_ROUTE_OP_ADD, NET_ROUTE_OP_LIST, SOCK_DGRAM,
+    SOCK_RAW,
+};
+use slopos_abi::syscall::{
+    KWARN_PANIC_KEEP, KWARN_PANIC_ON, SYSCALL_AUDIT_CTL, SYSCALL_GETPID, SYSCALL_HALT,
+    SYSCALL_KLOG_LEVEL, SYSCALL_KTRACE, SYSCALL_KWARN_STATS, SYSCALL_NET_FILTER, SYSCALL_NET_ROUTE,
+    SYSCALL_PERF_OPEN, SYSCALL_REBOOT, SYSCALL_SET_KEYMAP, SYSCALL_SOCKET,
+};
+use slopos_abi::task::TaskExitReason;
+use slopos_fs::vfs::ops::{vfs_open, vfs_unlink};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::audit::{
+    AuditRing, append, audit_mask, audit_set_mask, format_record, syscall_category,
+};
+
+fn record(category: u32) -> AuditRecord {
+    AuditRecord {
+        category,
+        ..AuditRecord::default()
+    }
+}
+
+pub fn test_audit_ring_drops_oldest_unwritten() -> TestResult {
+    let mut ring = AuditRing::new();
+    for i in 0..AUDIT_RING_LEN as u64 + 5 {
+        assert_eq_test!(ring.push(record(AUDIT_CAT_NET)), i);
+    }
+    assert_eq_test!(ring.dropped(), 5);
+    assert_eq_test!(ring.retained(), 5..AUDIT_RING_LEN as u64 + 5);
+    assert_eq_test!(ring.pending(), ring.retained());
+    assert_test!(ring.get(4).is_none(), "overwritten record still readable");
+    assert_eq_test!(ring.get(5).map(|r| r.seq), Some(5));
+    assert_test!(ring.get(AUDIT_RING_LEN as u64 + 5).is_none());
+    pass!()
+}
+
+pub fn test_audit_ring_written_records_are_not_dropped() -> TestResult {
+    let mut ring = AuditRing::new();
+    for _ in 0..10 {
+        ring.push(record(AUDIT_CAT_ACCT));
+    }
+    assert_eq_test!(ring.mark_written(10), 10);
+    assert_eq_test!(ring.mark_written(4), 0, "wrote a range twice");
+    assert_test!(ring.pending().is_empty());
+    for _ in 0..AUDIT_RING_LEN {
+        ring.push(record(AUDIT_CAT_ACCT));
+    }
+    assert_eq_test!(ring.dropped(), 0, "written records counted as dropped");
+    assert_eq_test!(ring.pending(), 10..AUDIT_RING_LEN as u64 + 10);
+    pass!()
+}
+
+pub fn test_audit_syscall_categories() -> TestResult {
+    assert_eq_test!(
+        syscall_category(SYSCALL_REBOOT, 0, 0),
+        Some(AUDIT_CAT_POWER)
+    );
+    assert_eq_test!(syscall_category(SYSCALL_HALT, 0, 0), Some(AUDIT_CAT_POWER));
+    assert_eq_test!(
+        syscall_category(SYSCALL_NET_FILTER, NET_FILTER_OP_APPEND, 0),
+        Some(AUDIT_CAT_NET)
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_NET_FILTER, NET_FILTER_OP_LIST, 0),
+        None
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_NET_ROUTE, NET_ROUTE_OP_ADD, 0),
+        Some(AUDIT_CAT_NET)
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_NET_ROUTE, NET_ROUTE_OP_LIST, 0),
+        None
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_SET_KEYMAP, 1, 0),
+        Some(AUDIT_CAT_INPUT)
+    );
+    assert_eq_test!(syscall_category(SYSCALL_SET_KEYMAP, KEYMAP_QUERY, 0), None);
+    assert_eq_test!(
+        syscall_category(SYSCALL_KTRACE, 0, 0),
+        Some(AUDIT_CAT_DEBUG)
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_KWARN_STATS, 0, KWARN_PANIC_ON),
+        Some(AUDIT_CAT_DEBUG)
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_KWARN_STATS, 0, KWARN_PANIC_KEEP),
+        None
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_KLOG_LEVEL, 4, KLOG_LEVEL_INHERIT),
+        Some(AUDIT_CAT_DEBUG)
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_KLOG_LEVEL, 4, KLOG_LEVEL_KEEP),
+        None
+    );
+    assert_eq_test!(
+        syscall_category(SYSCALL_PERF_OPEN, 0, 7),
+        Some(AUDIT_CAT_DEBUG)
+    );
+    assert_eq_test!(syscall_category(SYSCALL_PERF_OPEN, 0, 0), None);
+    assert_eq_test!(
+        syscall_category(SYSCALL_AUDIT_CTL, AUDIT_OP_SET, 0),
+        Some(AUDIT_CAT_AUDIT)
+    );
+    assert_eq_test!(syscall_category(SYSCALL_AUDIT_CTL, AUDIT_OP_READ, 0), None);
+    assert_eq_test!(
+        syscall_category(SYSCALL_SOCKET, 2, SOCK_RAW as u64),
+        Some(AUDIT_CAT_NET)
+    );
+    assert_eq_test!(syscall_category(SYSCALL_SOCKET, 2, SOCK_DGRAM as u64), None);
+    assert_eq_test!(syscall_category(SYSCALL_GETPID, 0, 0), None);
+    pass!()
+}
+
+pub fn test_audit_mask_keeps_audit_category() -> TestResult {
+    let saved = audit_set_mask(0);
+    let mask = audit_mask();
+    let prev = audit_set_mask(AUDIT_CAT_ALL | 1 << 31);
+    let all = audit_mask();
+    audit_set_mask(saved);
+    assert_eq_test!(mask, AUDIT_CAT_AUDIT, "audit category was cleared");
+    assert_eq_test!(prev, AUDIT_CAT_AUDIT);
+    assert_eq_test!(all, AUDIT_CAT_ALL, "unknown bits were kept");
+    pass!()
+}
+
+pub fn test_audit_format_lines() -> TestResult {
+    let mut syscall = AuditRecord {
+        seq: 7,
+        time_ns: 1_700_000_000_000_000_005,
+        pid: 12,
+        category: AUDIT_CAT_NET,
+        op: SYSCALL_NET_ROUTE as u32,
+        args: [NET_ROUTE_OP_ADD, 0x1000],
+        result: -22,
+        ..AuditRecord::default()
+    };
+    syscall.comm[..5].copy_from_slice(b"route");
+    let mut line = String::new();
+    format_record(&mut line, &syscall);
+    assert_eq_test!(
+        line.as_str(),
+        "time=1700000000.000000005 pid=12 uid=0 comm=route seq=7 cat=net \
+         syscall=net_route args=0x0,0x1000 result=-22\n"
+    );
+
+    let acct = AuditRecord {
+        time_ns: 3_000_000_000,
+        pid: 4,
+        category: AUDIT_CAT_ACCT,
+        op: TaskExitReason::UserFault as u32,
+        args: [250, 1],
+        result: 139,
+        ..AuditRecord::default()
+    };
+    line.clear();
+    format_record(&mut line, &acct);
+    assert_eq_test!(
+        line.as_str(),
+        "time=3.000000000 pid=4 uid=0 comm= reason=fault status=139 elapsed_ms=250\n"
+    );
+    pass!()
+}
+
+pub fn test_audit_append_extends_file() -> TestResult {
+    const PATH: &[u8] = b"/tmp/audit_test.log";
+    let _ = vfs_unlink(PATH);
+    let first = append(PATH, "one\n");
+    let second = append(PATH, "two\n");
+    let empty = append(PATH, "");
+    let mut buf = [0u8; 16];
+    let read = vfs_open(PATH, false).and_then(|handle| handle.read(0, &mut buf));
+    let _ = vfs_unlink(PATH);
+    if first.is_err() || second.is_err() || empty.is_err() {
+        return fail!("append failed: {:?} {:?} {:?}", first, second, empty);
+    }
+    assert_eq_test!(read, Ok(8));
+    assert_eq_test!(&buf[..8], b"one\ntwo\n");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    audit,
+    [
+        test_audit_ring_drops_oldest_unwritten,
+        test_audit_ring_written_records_are_not_dropped,
+        test_audit_syscall_categories,
+        test_audit_mask_keeps_audit_category,
+        test_audit_format_lines,
+        test_audit_append_extends_file,
+    ]
+);
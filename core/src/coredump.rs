@@ -0,0 +1,497 @@
+//! ELF core dumps for user tasks that die of a CPU fault.
+//!
+//! [`crash_capture`](crate::crash::crash_capture) calls [`core_capture`]
+//! for every faulting task whose `RLIMIT_CORE` is nonzero.  The image has
+//! to be built right there, before the address space is torn down: an
+//! `ET_CORE` file whose `PT_NOTE` holds `NT_PRSTATUS` and `NT_PRPSINFO` in
+//! the Linux x86-64 layout, followed by one `PT_LOAD` per run of present
+//! pages in the task's regions.  Pages never touched are left out, and so
+//! is everything past the limit; such segments keep their `p_memsz` but
+//! carry fewer file bytes.
+//!
+//! `crashd` writes queued images to `core.<pid>` in the task's working
+//! directory, or in [`CORE_FALLBACK_DIR`] if that fails, with the task's
+//! credentials.  An image that cannot be written anywhere is dropped.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use slopos_abi::addr::VirtAddr;
+use slopos_abi::rlimit::{CORE_FALLBACK_DIR, CORE_FILE_PREFIX};
+use slopos_abi::signal::{SIGFPE, SIGILL, SIGSEGV, SIGSYS};
+use slopos_abi::task::{Credentials, TaskFaultReason};
+use slopos_fs::vfs::ops::{vfs_open_as, vfs_unlink_as};
+use slopos_fs::vfs::perm::MAY_WRITE;
+use slopos_fs::vfs::traits::VfsError;
+use slopos_lib::{InterruptFrame, IrqMutex, klog_info};
+use slopos_mm::elf::{
+    ELF_MAGIC, ELFCLASS64, ELFDATA2LSB, ELFOSABI_NONE, EM_X86_64, ET_CORE, EV_CURRENT, NT_PRPSINFO,
+    NT_PRSTATUS, PF_R, PF_W, PF_X, PT_LOAD, PT_NOTE,
+};
+use slopos_mm::paging;
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+use slopos_mm::process_vm::{process_vm_for_each_vma, process_vm_get_page_dir};
+use slopos_mm::vma_flags::VmaFlags;
+
+use crate::crash::read_user;
+use crate::scheduler::task_struct::Task;
+
+/// Largest core file written, whatever the limit says.
+pub const CORE_MAX_BYTES: u64 = 32 * 1024 * 1024;
+/// Most `PT_LOAD` segments in a core file; later regions are left out.
+pub const CORE_MAX_SEGMENTS: usize = 64;
+/// Bytes of the task name kept, including the NUL terminator.
+pub const CORE_COMM_LEN: usize = 16;
+
+/// Images waiting for `crashd`.
+const QUEUE_LEN: usize = 2;
+
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+const NOTE_HEADER_SIZE: usize = 12;
+/// "CORE" and its NUL, padded to 4 bytes.
+const NOTE_NAME: &[u8; 8] = b"CORE\0\0\0\0";
+const NOTE_NAME_LEN: u32 = 5;
+/// `sizeof(struct elf_prstatus)` on x86-64.
+pub const PRSTATUS_SIZE: usize = 336;
+/// Offset of `pr_reg` in `elf_prstatus`.
+pub const PRSTATUS_REGS_OFFSET: usize = 112;
+/// `sizeof(struct elf_prpsinfo)` on x86-64.
+pub const PRPSINFO_SIZE: usize = 136;
+const NOTES_SIZE: usize = 2 * (NOTE_HEADER_SIZE + NOTE_NAME.len()) + PRSTATUS_SIZE + PRPSINFO_SIZE;
+
+/// `orig_rax` for a task that was not in a syscall.
+const NO_SYSCALL: u64 = u64::MAX;
+
+/// Who the core file describes.
+#[derive(Clone, Copy, Debug)]
+pub struct CoreProcess {
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgid: u32,
+    pub sid: u32,
+    /// Address space to dump.
+    pub process_id: u32,
+    pub creds: Credentials,
+    pub comm: [u8; CORE_COMM_LEN],
+    pub fs_base: u64,
+    /// Signal the fault stands for.
+    pub signo: u8,
+}
+
+impl CoreProcess {
+    pub fn of(task: &Task, fault: TaskFaultReason) -> Self {
+        let mut comm = [0; CORE_COMM_LEN];
+        let len = task
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(task.name.len())
+            .min(CORE_COMM_LEN - 1);
+        comm[..len].copy_from_slice(&task.name[..len]);
+        Self {
+            pid: task.task_id,
+            ppid: task.parent_task_id,
+            pgid: task.pgid,
+            sid: task.sid,
+            process_id: task.process_id,
+            creds: task.creds,
+            comm,
+            fs_base: task.fs_base,
+            signo: fault_signal(fault),
+        }
+    }
+}
+
+/// The signal a fault is reported as, in `pr_cursig`.
+pub fn fault_signal(fault: TaskFaultReason) -> u8 {
+    match fault {
+        TaskFaultReason::UserUd => SIGILL,
+        TaskFaultReason::UserDeviceNa => SIGFPE,
+        TaskFaultReason::SyscallFilter => SIGSYS,
+        TaskFaultReason::UserPage | TaskFaultReason::UserGp | TaskFaultReason::None => SIGSEGV,
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct CoreSegment {
+    start: u64,
+    end: u64,
+    /// Bytes of the segment stored in the file, from `start`.
+    file_len: u64,
+    flags: u32,
+}
+
+struct CorePlan {
+    segments: [CoreSegment; CORE_MAX_SEGMENTS],
+    count: usize,
+    /// File offset of the first segment's bytes.
+    data_offset: u64,
+    total: u64,
+}
+
+fn segment_flags(flags: VmaFlags) -> u32 {
+    let mut out = 0;
+    if flags.contains(VmaFlags::READ) {
+        out |= PF_R;
+    }
+    if flags.contains(VmaFlags::WRITE) {
+        out |= PF_W;
+    }
+    if flags.contains(VmaFlags::EXEC) {
+        out |= PF_X;
+    }
+    out
+}
+
+/// Split the regions of `process_id` into runs of present pages and fit
+/// them into `limit` bytes.  `None` if not even the headers fit.
+fn plan_core(process_id: u32, limit: u64) -> Option<CorePlan> {
+    let page_dir = process_vm_get_page_dir(process_id);
+    if page_dir.is_null() {
+        return None;
+    }
+    let present = |addr: u64| {
+        VirtAddr::try_new(addr)
+            .is_some_and(|vaddr| !paging::virt_to_phys_process(vaddr, page_dir).is_null())
+    };
+
+    let mut plan = CorePlan {
+        segments: [CoreSegment::default(); CORE_MAX_SEGMENTS],
+        count: 0,
+        data_offset: 0,
+        total: 0,
+    };
+    process_vm_for_each_vma(process_id, |start, end, flags| {
+        if flags.contains(VmaFlags::GUARD) || flags.contains(VmaFlags::DEVICE) {
+            return;
+        }
+        let mut addr = start;
+        while addr < end && plan.count < CORE_MAX_SEGMENTS {
+            if !present(addr) {
+                addr += PAGE_SIZE_4KB;
+                continue;
+            }
+            let run_start = addr;
+            while addr < end && present(addr) {
+                addr += PAGE_SIZE_4KB;
+            }
+            plan.segments[plan.count] = CoreSegment {
+                start: run_start,
+                end: addr,
+                file_len: 0,
+                flags: segment_flags(flags),
+            };
+            plan.count += 1;
+        }
+    });
+
+    let headers = (EHDR_SIZE + (plan.count + 1) * PHDR_SIZE + NOTES_SIZE) as u64;
+    plan.data_offset = headers.next_multiple_of(PAGE_SIZE_4KB);
+    if plan.data_offset > limit {
+        return None;
+    }
+    let mut budget = limit - plan.data_offset;
+    for seg in &mut plan.segments[..plan.count] {
+        seg.file_len = (seg.end - seg.start).min(budget & !(PAGE_SIZE_4KB - 1));
+        budget -= seg.file_len;
+    }
+    plan.total = plan.data_offset
+        + plan.segments[..plan.count]
+            .iter()
+            .map(|seg| seg.file_len)
+            .sum::<u64>();
+    Some(plan)
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_phdr(
+    out: &mut Vec<u8>,
+    p_type: u32,
+    flags: u32,
+    offset: u64,
+    seg: &CoreSegment,
+    align: u64,
+) {
+    put_u32(out, p_type);
+    put_u32(out, flags);
+    put_u64(out, offset);
+    put_u64(out, seg.start);
+    put_u64(out, 0);
+    put_u64(out, seg.file_len);
+    put_u64(out, seg.end - seg.start);
+    put_u64(out, align);
+}
+
+fn put_note(out: &mut Vec<u8>, n_type: u32, desc: &[u8]) {
+    put_u32(out, NOTE_NAME_LEN);
+    put_u32(out, desc.len() as u32);
+    put_u32(out, n_type);
+    out.extend_from_slice(NOTE_NAME);
+    out.extend_from_slice(desc);
+}
+
+/// `elf_prstatus` for the faulting thread.
+fn prstatus(proc: &CoreProcess, frame: &InterruptFrame) -> [u8; PRSTATUS_SIZE] {
+    let mut desc = [0u8; PRSTATUS_SIZE];
+    desc[0..4].copy_from_slice(&(proc.signo as i32).to_le_bytes());
+    desc[12..14].copy_from_slice(&(proc.signo as i16).to_le_bytes());
+    for (offset, id) in [
+        (32, proc.pid),
+        (36, proc.ppid),
+        (40, proc.pgid),
+        (44, proc.sid),
+    ] {
+        desc[offset..offset + 4].copy_from_slice(&id.to_le_bytes());
+    }
+    // `struct user_regs_struct` order.
+    let regs = [
+        frame.r15,
+        frame.r14,
+        frame.r13,
+        frame.r12,
+        frame.rbp,
+        frame.rbx,
+        frame.r11,
+        frame.r10,
+        frame.r9,
+        frame.r8,
+        frame.rax,
+        frame.rcx,
+        frame.rdx,
+        frame.rsi,
+        frame.rdi,
+        NO_SYSCALL,
+        frame.rip,
+        frame.cs,
+        frame.rflags,
+        frame.rsp,
+        frame.ss,
+        proc.fs_base,
+        0,
+        frame.ss,
+        frame.ss,
+        0,
+        0,
+    ];
+    for (i, reg) in regs.iter().enumerate() {
+        let offset = PRSTATUS_REGS_OFFSET + i * 8;
+        desc[offset..offset + 8].copy_from_slice(&reg.to_le_bytes());
+    }
+    desc
+}
+
+/// `elf_prpsinfo`: state, IDs and name.
+fn prpsinfo(proc: &CoreProcess) -> [u8; PRPSINFO_SIZE] {
+    let mut desc = [0u8; PRPSINFO_SIZE];
+    desc[1] = b'R';
+    for (offset, id) in [
+        (16, proc.creds.uid),
+        (20, proc.creds.gid),
+        (24, proc.pid),
+        (28, proc.ppid),
+        (32, proc.pgid),
+        (36, proc.sid),
+    ] {
+        desc[offset..offset + 4].copy_from_slice(&id.to_le_bytes());
+    }
+    desc[40..40 + CORE_COMM_LEN].copy_from_slice(&proc.comm);
+    desc[56..56 + CORE_COMM_LEN].copy_from_slice(&proc.comm);
+    desc
+}
+
+/// Build the core file of `proc`, stopped at `frame`, in at most `limit`
+/// bytes.  `None` if the headers do not fit or memory runs out.
+pub fn build_core(proc: &CoreProcess, frame: &InterruptFrame, limit: u64) -> Option<Vec<u8>> {
+    let plan = plan_core(proc.process_id, limit)?;
+    let mut out = Vec::new();
+    out.try_reserve_exact(plan.total as usize).ok()?;
+
+    out.extend_from_slice(&ELF_MAGIC);
+    out.extend_from_slice(&[ELFCLASS64, ELFDATA2LSB, EV_CURRENT, ELFOSABI_NONE]);
+    out.resize(16, 0);
+    put_u16(&mut out, ET_CORE);
+    put_u16(&mut out, EM_X86_64);
+    put_u32(&mut out, EV_CURRENT as u32);
+    put_u64(&mut out, 0); // e_entry
+    put_u64(&mut out, EHDR_SIZE as u64); // e_phoff
+    put_u64(&mut out, 0); // e_shoff
+    put_u32(&mut out, 0); // e_flags
+    put_u16(&mut out, EHDR_SIZE as u16);
+    put_u16(&mut out, PHDR_SIZE as u16);
+    put_u16(&mut out, (plan.count + 1) as u16);
+    put_u16(&mut out, 0); // e_shentsize
+    put_u16(&mut out, 0); // e_shnum
+    put_u16(&mut out, 0); // e_shstrndx
+
+    let notes_offset = (EHDR_SIZE + (plan.count + 1) * PHDR_SIZE) as u64;
+    let notes = CoreSegment {
+        file_len: NOTES_SIZE as u64,
+        end: NOTES_SIZE as u64,
+        ..CoreSegment::default()
+    };
+    put_phdr(&mut out, PT_NOTE, 0, notes_offset, &notes, 4);
+    let mut offset = plan.data_offset;
+    for seg in &plan.segments[..plan.count] {
+        put_phdr(&mut out, PT_LOAD, seg.flags, offset, seg, PAGE_SIZE_4KB);
+        offset += seg.file_len;
+    }
+
+    put_note(&mut out, NT_PRSTATUS, &prstatus(proc, frame));
+    put_note(&mut out, NT_PRPSINFO, &prpsinfo(proc));
+    out.resize(plan.data_offset as usize, 0);
+
+    for seg in &plan.segments[..plan.count] {
+        let mut addr = seg.start;
+        while addr < seg.start + seg.file_len {
+            let at = out.len();
+            out.resize(at + PAGE_SIZE_4KB as usize, 0);
+            // A page that vanished since planning stays zero.
+            read_user(proc.process_id, addr, &mut out[at..]);
+            addr += PAGE_SIZE_4KB;
+        }
+    }
+    Some(out)
+}
+
+struct PendingCore {
+    pid: u32,
+    creds: Credentials,
+    cwd: [u8; 256],
+    cwd_len: usize,
+    image: Vec<u8>,
+}
+
+struct CoreQueue {
+    slots: [Option<PendingCore>; QUEUE_LEN],
+    dropped: u64,
+}
+
+static QUEUE: IrqMutex<CoreQueue> = IrqMutex::new(CoreQueue {
+    slots: [const { None }; QUEUE_LEN],
+    dropped: 0,
+});
+
+/// Build and queue a core file for `task` if its limit allows one.
+/// Called from [`crash_capture`](crate::crash::crash_capture).
+pub fn core_capture(task: &Task, frame: &InterruptFrame, fault: TaskFaultReason) {
+    let limit = task.core_limit.cur.min(CORE_MAX_BYTES);
+    if limit == 0 {
+        return;
+    }
+    // Skip the copy when it would have nowhere to go.
+    {
+        let mut queue = QUEUE.lock();
+        if queue.slots.iter().all(Option::is_some) {
+            queue.dropped += 1;
+            return;
+        }
+    }
+    let proc = CoreProcess::of(task, fault);
+    let Some(image) = build_core(&proc, frame, limit) else {
+        klog_info!("COREDUMP: no core for pid {}: limit or memory", proc.pid);
+        return;
+    };
+
+    let mut pending = PendingCore {
+        pid: proc.pid,
+        creds: proc.creds,
+        cwd: [0; 256],
+        cwd_len: (task.cwd_len as usize).min(task.cwd.len()),
+        image,
+    };
+    pending.cwd[..pending.cwd_len].copy_from_slice(&task.cwd[..pending.cwd_len]);
+
+    let mut queue = QUEUE.lock();
+    match queue.slots.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => *slot = Some(pending),
+        None => queue.dropped += 1,
+    }
+}
+
+/// Core files dropped because the queue was full.
+pub fn core_dropped() -> u64 {
+    QUEUE.lock().dropped
+}
+
+/// `<dir>/core.<pid>`.
+pub fn core_path(dir: &[u8], pid: u32) -> String {
+    let mut path = String::from(core::str::from_utf8(dir).unwrap_or("/"));
+    if !path.ends_with('/') {
+        path.push('/');
+    }
+    path.push_str(core::str::from_utf8(CORE_FILE_PREFIX).unwrap_or("core."));
+    let _ = write!(path, "{}", pid);
+    path
+}
+
+/// Write `image` to `core.<pid>` in `dir` as `creds`, replacing any old
+/// file.  Returns the path.
+pub fn write_core_in(
+    dir: &[u8],
+    pid: u32,
+    image: &[u8],
+    creds: &Credentials,
+) -> Result<String, VfsError> {
+    let path = core_path(dir, pid);
+    match vfs_unlink_as(path.as_bytes(), creds) {
+        Ok(()) | Err(VfsError::NotFound) => {}
+        Err(err) => return Err(err),
+    }
+    let handle = vfs_open_as(path.as_bytes(), true, MAY_WRITE, creds)?;
+    let mut offset = 0;
+    while offset < image.len() {
+        match handle.write(offset as u64, &image[offset..])? {
+            0 => return Err(VfsError::NoSpace),
+            n => offset += n,
+        }
+    }
+    Ok(path)
+}
+
+/// Write `image` in the working directory `cwd`, falling back to
+/// [`CORE_FALLBACK_DIR`].
+pub fn write_core(
+    cwd: &[u8],
+    pid: u32,
+    image: &[u8],
+    creds: &Credentials,
+) -> Result<String, VfsError> {
+    write_core_in(cwd, pid, image, creds)
+        .or_else(|_| write_core_in(CORE_FALLBACK_DIR, pid, image, creds))
+}
+
+/// Write every queued core file.  Returns how many were written.
+pub fn core_flush() -> usize {
+    let mut written = 0;
+    for idx in 0..QUEUE_LEN {
+        let Some(core) = QUEUE.lock().slots[idx].take() else {
+            continue;
+        };
+        match write_core(
+            &core.cwd[..core.cwd_len],
+            core.pid,
+            &core.image,
+            &core.creds,
+        ) {
+            Ok(path) => {
+                klog_info!("COREDUMP: pid {} -> {}", core.pid, path);
+                written += 1;
+            }
+            Err(err) => klog_info!("COREDUMP: pid {}: not written: {:?}", core.pid, err),
+        }
+    }
+    written
+}
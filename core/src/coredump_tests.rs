@@ -0,0 +1,211 @@
+//! Core dump tests: the ELF image, the size limit, where the file goes and
+//! the limit rules.
+
+use slopos_abi::addr::VirtAddr;
+use slopos_abi::rlimit::{RLIM_INFINITY, RLimit};
+use slopos_abi::signal::{SIGSEGV, SIGSYS};
+use slopos_abi::task::{Credentials, INVALID_PROCESS_ID, TaskFaultReason};
+use slopos_fs::vfs::ops::{vfs_mkdir, vfs_stat, vfs_unlink};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{InterruptFrame, assert_eq_test, assert_test, fail, pass};
+use slopos_mm::elf::{ET_CORE, NT_PRSTATUS, PT_LOAD, PT_NOTE};
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::paging::virt_to_phys_in_dir;
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+use slopos_mm::process_vm;
+use slopos_mm::vma_flags::VmaFlags;
+
+use crate::coredump::{
+    CORE_COMM_LEN, CORE_MAX_BYTES, CoreProcess, PRSTATUS_REGS_OFFSET, build_core, core_path,
+    fault_signal, write_core,
+};
+
+const MARKER: u64 = 0xC0DE_D00D_5107_0500;
+
+fn u16_at(image: &[u8], off: usize) -> u16 {
+    u16::from_le_bytes(image[off..off + 2].try_into().unwrap())
+}
+
+fn u32_at(image: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes(image[off..off + 4].try_into().unwrap())
+}
+
+fn u64_at(image: &[u8], off: usize) -> u64 {
+    u64::from_le_bytes(image[off..off + 8].try_into().unwrap())
+}
+
+fn core_process(process_id: u32) -> CoreProcess {
+    let mut comm = [0; CORE_COMM_LEN];
+    comm[..7].copy_from_slice(b"crasher");
+    CoreProcess {
+        pid: 77,
+        ppid: 1,
+        pgid: 77,
+        sid: 1,
+        process_id,
+        creds: Credentials::ROOT,
+        comm,
+        fs_base: 0,
+        signo: SIGSEGV,
+    }
+}
+
+/// A fresh address space with `MARKER` in the top word of its stack.
+/// Returns the process and the marker's address.
+fn process_with_marker() -> Option<(u32, u64)> {
+    process_vm::init_process_vm();
+    let pid = process_vm::create_process_vm();
+    if pid == INVALID_PROCESS_ID {
+        return None;
+    }
+    let mut stack_end = 0;
+    process_vm::process_vm_for_each_vma(pid, |_, end, flags| {
+        if flags.contains(VmaFlags::STACK) {
+            stack_end = end;
+        }
+    });
+    let addr = stack_end - 8;
+    let page_dir = process_vm::process_vm_get_page_dir(pid);
+    let Some(virt) = virt_to_phys_in_dir(page_dir, VirtAddr::new(addr)).to_virt_checked() else {
+        process_vm::destroy_process_vm(pid);
+        return None;
+    };
+    unsafe { core::ptr::write_unaligned(virt.as_mut_ptr::<u64>(), MARKER) };
+    Some((pid, addr))
+}
+
+/// File offset of `addr` in the core `image`, if a PT_LOAD stores it.
+fn offset_of_addr(image: &[u8], addr: u64) -> Option<usize> {
+    let phnum = u16_at(image, 56) as usize;
+    (0..phnum).find_map(|i| {
+        let ph = 64 + i * 56;
+        let (offset, vaddr, filesz) = (
+            u64_at(image, ph + 8),
+            u64_at(image, ph + 16),
+            u64_at(image, ph + 32),
+        );
+        (u32_at(image, ph) == PT_LOAD && addr >= vaddr && addr < vaddr + filesz)
+            .then(|| (offset + (addr - vaddr)) as usize)
+    })
+}
+
+pub fn test_core_image_layout() -> TestResult {
+    let Some((pid, marker_addr)) = process_with_marker() else {
+        return fail!("could not set up a process");
+    };
+    let mut frame: InterruptFrame = unsafe { core::mem::zeroed() };
+    frame.rip = 0x40_1234;
+    frame.rsp = marker_addr;
+    let image = build_core(&core_process(pid), &frame, CORE_MAX_BYTES);
+    process_vm::destroy_process_vm(pid);
+
+    let Some(image) = image else {
+        return fail!("no core image built");
+    };
+    assert_eq_test!(&image[..4], &[0x7f, b'E', b'L', b'F'][..]);
+    assert_eq_test!(u16_at(&image, 16), ET_CORE);
+    assert_test!(u16_at(&image, 56) >= 2, "no PT_LOAD segments");
+
+    // The first program header is the note, starting with NT_PRSTATUS.
+    assert_eq_test!(u32_at(&image, 64), PT_NOTE);
+    let note = u64_at(&image, 64 + 8) as usize;
+    assert_eq_test!(u32_at(&image, note), 5, "note name size");
+    assert_eq_test!(u32_at(&image, note + 8), NT_PRSTATUS);
+    assert_eq_test!(&image[note + 12..note + 17], b"CORE\0");
+    let desc = note + 20;
+    assert_eq_test!(u32_at(&image, desc), SIGSEGV as u32, "si_signo");
+    assert_eq_test!(u32_at(&image, desc + 32), 77, "pr_pid");
+    // rip and rsp are user_regs_struct slots 16 and 19.
+    assert_eq_test!(
+        u64_at(&image, desc + PRSTATUS_REGS_OFFSET + 16 * 8),
+        0x40_1234
+    );
+    assert_eq_test!(
+        u64_at(&image, desc + PRSTATUS_REGS_OFFSET + 19 * 8),
+        marker_addr
+    );
+
+    let Some(offset) = offset_of_addr(&image, marker_addr) else {
+        return fail!("stack top not in the core");
+    };
+    assert_eq_test!(u64_at(&image, offset), MARKER);
+    pass!()
+}
+
+pub fn test_core_respects_limit() -> TestResult {
+    let Some((pid, _)) = process_with_marker() else {
+        return fail!("could not set up a process");
+    };
+    let frame: InterruptFrame = unsafe { core::mem::zeroed() };
+    let proc = core_process(pid);
+    let full = build_core(&proc, &frame, CORE_MAX_BYTES);
+    let tiny = build_core(&proc, &frame, 512);
+    // The first PT_LOAD starts right after the headers.
+    let data_offset = full.as_ref().map_or(0, |image| u64_at(image, 64 + 56 + 8));
+    let one_page = build_core(&proc, &frame, data_offset + PAGE_SIZE_4KB + 100);
+    process_vm::destroy_process_vm(pid);
+
+    let (Some(full), Some(one_page)) = (full, one_page) else {
+        return fail!("core image missing");
+    };
+    assert_test!(tiny.is_none(), "headers do not fit in 512 bytes");
+    assert_eq_test!(one_page.len() as u64, data_offset + PAGE_SIZE_4KB);
+    assert_test!(full.len() > one_page.len());
+    // Both keep every segment, with the same memory sizes.
+    assert_eq_test!(u16_at(&one_page, 56), u16_at(&full, 56));
+    let phnum = u16_at(&one_page, 56) as usize;
+    let stored: u64 = (1..phnum)
+        .map(|i| u64_at(&one_page, 64 + i * 56 + 32))
+        .sum();
+    assert_eq_test!(stored, PAGE_SIZE_4KB);
+    pass!()
+}
+
+pub fn test_core_path_and_fallback() -> TestResult {
+    let p = core_path(b"/", 5);
+    assert_eq_test!(p.as_str(), "/core.5");
+    let p = core_path(b"/home/user", 42);
+    assert_eq_test!(p.as_str(), "/home/user/core.42");
+
+    let _ = vfs_mkdir(b"/tmp");
+    let image = [0xAB_u8; 100];
+    let written = write_core(b"/no/such/dir", 4242, &image, &Credentials::ROOT);
+    let size = vfs_stat(b"/tmp/core.4242").map(|(_, size)| size);
+    let _ = vfs_unlink(b"/tmp/core.4242");
+
+    match written {
+        Ok(path) => assert_eq_test!(path.as_str(), "/tmp/core.4242"),
+        Err(err) => return fail!("write_core failed: {:?}", err),
+    }
+    assert_eq_test!(size, Ok(100));
+    pass!()
+}
+
+pub fn test_core_limit_rules() -> TestResult {
+    let limit = RLimit::CORE_DEFAULT;
+    assert_eq_test!(limit.cur, 0, "core dumps start disabled");
+    assert_test!(limit.may_set(&RLimit::new(RLIM_INFINITY, RLIM_INFINITY), false));
+
+    let capped = RLimit::new(0, 1 << 20);
+    assert_test!(capped.may_set(&RLimit::new(1 << 20, 1 << 20), false));
+    assert_test!(
+        !capped.may_set(&RLimit::new(0, 2 << 20), false),
+        "raised hard"
+    );
+    assert_test!(capped.may_set(&RLimit::new(0, 2 << 20), true));
+    assert_test!(!capped.may_set(&RLimit::new(2, 1), true), "soft above hard");
+
+    assert_eq_test!(fault_signal(TaskFaultReason::UserPage), SIGSEGV);
+    assert_eq_test!(fault_signal(TaskFaultReason::SyscallFilter), SIGSYS);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    coredump,
+    [
+        test_core_image_layout,
+        test_core_respects_limit,
+        test_core_path_and_fallback,
+        test_core_limit_rules,
+    ]
+);
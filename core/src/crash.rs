@@ -0,0 +1,498 @@
+//! Crash bundles for user tasks that die of a CPU fault.
+//!
+//! The exception handler calls [`crash_capture`] just before it terminates
+//! the task.  That snapshots the registers, the bytes at the stack and
+//! instruction pointers and the memory map into a small queue, without
+//! allocating or touching the filesystem.  The `crashd` kernel thread
+//! writes each snapshot out as a bundle under [`CRASH_DIR`] (layout in
+//! [`slopos_abi::crash`]) with the kernel log tail and, through the
+//! callback the display code registers with
+//! [`crash_register_screenshot`], a screenshot.  It then publishes a
+//! [`CrashNotice`] and wakes the compositor to show it.
+//!
+//! Snapshots arriving while the queue is full are dropped and counted;
+//! before the root filesystem is writable they wait in the queue.  Core
+//! files, for tasks that enable them, are built and written alongside by
+//! [`crate::coredump`].
+
+use alloc::string::String;
+use core::fmt::Write;
+use core::ptr;
+
+use slopos_abi::addr::VirtAddr;
+use slopos_abi::crash::{CRASH_COMM_LEN, CRASH_DIR, CRASH_NOTICE_LEN, CRASH_PATH_LEN, CrashNotice};
+use slopos_abi::task::TaskFaultReason;
+use slopos_abi::time::CivilTime;
+use slopos_fs::vfs::traits::VfsError;
+use slopos_lib::compositor_wake::compositor_wake;
+use slopos_lib::{InterruptFrame, IrqMutex, KLOG_TAIL_LEN, klog_info, klog_tail, walltime};
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::paging;
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+use slopos_mm::process_vm::{process_vm_for_each_vma, process_vm_get_page_dir};
+use slopos_mm::vma_flags::VmaFlags;
+
+use crate::audit::{append, ensure_dir};
+use crate::coredump;
+use crate::kthread::kthread_spawn;
+use crate::scheduler::sleep::sleep_current_task_ms;
+use crate::scheduler::task_struct::Task;
+use crate::task::INVALID_TASK_ID;
+
+/// Bytes copied from the top of the stack.
+pub const CRASH_STACK_BYTES: usize = 256;
+/// Bytes copied from the faulting instruction on.
+pub const CRASH_CODE_BYTES: usize = 16;
+/// Memory regions listed in `maps`; the rest are left out.
+pub const CRASH_MAX_REGIONS: usize = 32;
+
+/// Snapshots waiting for `crashd`.
+const QUEUE_LEN: usize = 2;
+/// How often `crashd` looks for snapshots.
+const WRITE_INTERVAL_MS: u32 = 1_000;
+/// File the screenshot callback writes in the bundle.
+pub const CRASH_SCREENSHOT_NAME: &[u8] = b"screen.ppm";
+
+/// The first user address past the canonical lower half.
+const USER_SPACE_END: u64 = 0x0000_8000_0000_0000;
+
+/// Register names in [`CrashSnapshot::regs`] order.
+pub const CRASH_REG_NAMES: [&str; 18] = [
+    "rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13",
+    "r14", "r15", "rip", "rflags",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrashRegion {
+    pub start: u64,
+    pub end: u64,
+    pub flags: VmaFlags,
+}
+
+/// What [`crash_capture`] records about a faulting task.
+#[derive(Clone, Copy)]
+pub struct CrashSnapshot {
+    pub time_ns: u64,
+    pub pid: u32,
+    pub comm: [u8; CRASH_COMM_LEN],
+    pub fault: TaskFaultReason,
+    pub vector: u64,
+    pub error_code: u64,
+    pub cr2: u64,
+    pub regs: [u64; CRASH_REG_NAMES.len()],
+    pub stack: [u8; CRASH_STACK_BYTES],
+    pub stack_len: usize,
+    pub code: [u8; CRASH_CODE_BYTES],
+    pub code_len: usize,
+    pub regions: [CrashRegion; CRASH_MAX_REGIONS],
+    pub region_count: usize,
+}
+
+impl CrashSnapshot {
+    pub const fn empty() -> Self {
+        Self {
+            time_ns: 0,
+            pid: 0,
+            comm: [0; CRASH_COMM_LEN],
+            fault: TaskFaultReason::None,
+            vector: 0,
+            error_code: 0,
+            cr2: 0,
+            regs: [0; CRASH_REG_NAMES.len()],
+            stack: [0; CRASH_STACK_BYTES],
+            stack_len: 0,
+            code: [0; CRASH_CODE_BYTES],
+            code_len: 0,
+            regions: [CrashRegion {
+                start: 0,
+                end: 0,
+                flags: VmaFlags::NONE,
+            }; CRASH_MAX_REGIONS],
+            region_count: 0,
+        }
+    }
+
+    fn comm_str(&self) -> &str {
+        let len = self
+            .comm
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(self.comm.len());
+        core::str::from_utf8(&self.comm[..len]).unwrap_or("?")
+    }
+
+    fn reg(&self, name: &str) -> u64 {
+        CRASH_REG_NAMES
+            .iter()
+            .position(|&n| n == name)
+            .map_or(0, |i| self.regs[i])
+    }
+}
+
+struct CrashQueue {
+    slots: [Option<CrashSnapshot>; QUEUE_LEN],
+    dropped: u64,
+}
+
+/// The last [`CRASH_NOTICE_LEN`] notices, indexed by sequence number.
+pub(crate) struct NoticeRing {
+    notices: [CrashNotice; CRASH_NOTICE_LEN],
+    /// Sequence number the next notice gets.
+    next: u64,
+}
+
+impl NoticeRing {
+    pub(crate) const fn new() -> Self {
+        Self {
+            notices: [CrashNotice::EMPTY; CRASH_NOTICE_LEN],
+            next: 0,
+        }
+    }
+
+    /// Store `notice` under the next sequence number, which is returned.
+    pub(crate) fn push(&mut self, mut notice: CrashNotice) -> u64 {
+        notice.seq = self.next;
+        self.notices[(self.next % CRASH_NOTICE_LEN as u64) as usize] = notice;
+        self.next += 1;
+        notice.seq
+    }
+
+    /// The oldest retained notice numbered `seq` or later.
+    pub(crate) fn first_from(&self, seq: u64) -> Option<CrashNotice> {
+        let oldest = self.next.saturating_sub(CRASH_NOTICE_LEN as u64);
+        let seq = seq.max(oldest);
+        (seq < self.next).then(|| self.notices[(seq % CRASH_NOTICE_LEN as u64) as usize])
+    }
+}
+
+static QUEUE: IrqMutex<CrashQueue> = IrqMutex::new(CrashQueue {
+    slots: [None; QUEUE_LEN],
+    dropped: 0,
+});
+static NOTICES: IrqMutex<NoticeRing> = IrqMutex::new(NoticeRing::new());
+static SCREENSHOT: IrqMutex<Option<ScreenshotFn>> = IrqMutex::new(None);
+
+/// Writes a screenshot to a path; false when there is nothing to capture.
+pub type ScreenshotFn = fn(&[u8]) -> bool;
+
+/// Install the function that writes the screenshot in each bundle.
+pub fn crash_register_screenshot(write: ScreenshotFn) {
+    *SCREENSHOT.lock() = Some(write);
+}
+
+/// Copy user memory of `process_id` at `addr` into `out`, stopping at the
+/// end of the page.  Returns the bytes copied; 0 when unmapped.
+pub(crate) fn read_user(process_id: u32, addr: u64, out: &mut [u8]) -> usize {
+    let page_dir = process_vm_get_page_dir(process_id);
+    let Some(vaddr) = VirtAddr::try_new(addr) else {
+        return 0;
+    };
+    if page_dir.is_null() || addr >= USER_SPACE_END {
+        return 0;
+    }
+    let len = out
+        .len()
+        .min((PAGE_SIZE_4KB - (addr & (PAGE_SIZE_4KB - 1))) as usize);
+    let phys = paging::virt_to_phys_process(vaddr, page_dir);
+    if phys.is_null() {
+        return 0;
+    }
+    let Some(src) = phys.to_virt_checked() else {
+        return 0;
+    };
+    // SAFETY: `src` is the HHDM mapping of a present page and `len` stops
+    // at its end.
+    unsafe { ptr::copy_nonoverlapping(src.as_u64() as *const u8, out.as_mut_ptr(), len) };
+    len
+}
+
+/// Build the snapshot of `task`, which faulted with `frame`.
+pub fn crash_snapshot(
+    task: &Task,
+    frame: &InterruptFrame,
+    cr2: u64,
+    fault: TaskFaultReason,
+) -> CrashSnapshot {
+    let mut snap = CrashSnapshot {
+        time_ns: walltime::realtime_ns(),
+        pid: task.task_id,
+        fault,
+        vector: frame.vector,
+        error_code: frame.error_code,
+        cr2,
+        regs: [
+            frame.rax,
+            frame.rbx,
+            frame.rcx,
+            frame.rdx,
+            frame.rsi,
+            frame.rdi,
+            frame.rbp,
+            frame.rsp,
+            frame.r8,
+            frame.r9,
+            frame.r10,
+            frame.r11,
+            frame.r12,
+            frame.r13,
+            frame.r14,
+            frame.r15,
+            frame.rip,
+            frame.rflags,
+        ],
+        ..CrashSnapshot::empty()
+    };
+    let len = task
+        .name
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(task.name.len())
+        .min(CRASH_COMM_LEN - 1);
+    snap.comm[..len].copy_from_slice(&task.name[..len]);
+
+    snap.stack_len = read_user(task.process_id, frame.rsp, &mut snap.stack);
+    snap.code_len = read_user(task.process_id, frame.rip, &mut snap.code);
+    process_vm_for_each_vma(task.process_id, |start, end, flags| {
+        if snap.region_count < CRASH_MAX_REGIONS {
+            snap.regions[snap.region_count] = CrashRegion { start, end, flags };
+            snap.region_count += 1;
+        }
+    });
+    snap
+}
+
+/// Queue a snapshot of `task` for `crashd`, and a core file if the task's
+/// `RLIMIT_CORE` asks for one.  Called from the exception handler before
+/// the task is terminated.
+pub fn crash_capture(task: &Task, frame: &InterruptFrame, cr2: u64, fault: TaskFaultReason) {
+    let snap = crash_snapshot(task, frame, cr2, fault);
+    {
+        let mut queue = QUEUE.lock();
+        match queue.slots.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(snap),
+            None => queue.dropped += 1,
+        }
+    }
+    coredump::core_capture(task, frame, fault);
+}
+
+/// Snapshots dropped because the queue was full.
+pub fn crash_dropped() -> u64 {
+    QUEUE.lock().dropped
+}
+
+/// The oldest retained notice numbered `seq` or later.
+pub fn crash_notice(seq: u64) -> Option<CrashNotice> {
+    NOTICES.lock().first_from(seq)
+}
+
+fn fault_name(fault: TaskFaultReason) -> &'static str {
+    match fault {
+        TaskFaultReason::UserPage => "page fault",
+        TaskFaultReason::UserGp => "general protection fault",
+        TaskFaultReason::UserUd => "invalid opcode",
+        TaskFaultReason::UserDeviceNa => "device not available",
+        TaskFaultReason::SyscallFilter => "syscall refused by filter",
+        TaskFaultReason::None => "fault",
+    }
+}
+
+fn write_hex_dump(out: &mut String, base: u64, bytes: &[u8]) {
+    for (i, row) in bytes.chunks(16).enumerate() {
+        let _ = write!(out, "  {:016x}:", base + (i * 16) as u64);
+        for b in row {
+            let _ = write!(out, " {:02x}", b);
+        }
+        out.push('\n');
+    }
+}
+
+/// The `report` file.
+pub(crate) fn format_report(out: &mut String, snap: &CrashSnapshot) {
+    let _ = writeln!(out, "task: {} (pid {})", snap.comm_str(), snap.pid);
+    let _ = writeln!(
+        out,
+        "fault: {} (vector {}, error {:#x})",
+        fault_name(snap.fault),
+        snap.vector,
+        snap.error_code
+    );
+    if snap.fault == TaskFaultReason::UserPage {
+        let _ = writeln!(out, "address: {:#018x}", snap.cr2);
+    }
+    let _ = writeln!(
+        out,
+        "time: {}.{:09}",
+        snap.time_ns / 1_000_000_000,
+        snap.time_ns % 1_000_000_000
+    );
+
+    out.push_str("\nregisters:\n");
+    for (i, (name, value)) in CRASH_REG_NAMES.iter().zip(snap.regs).enumerate() {
+        let _ = write!(out, "  {:>6} {:016x}", name, value);
+        if i % 3 == 2 || i + 1 == CRASH_REG_NAMES.len() {
+            out.push('\n');
+        }
+    }
+
+    let _ = writeln!(out, "\nstack ({} bytes at rsp):", snap.stack_len);
+    write_hex_dump(out, snap.reg("rsp"), &snap.stack[..snap.stack_len]);
+    let _ = writeln!(out, "\ncode ({} bytes at rip):", snap.code_len);
+    write_hex_dump(out, snap.reg("rip"), &snap.code[..snap.code_len]);
+}
+
+/// The `maps` file: range, permissions, size and kind of each region.
+pub(crate) fn format_maps(out: &mut String, snap: &CrashSnapshot) {
+    for region in &snap.regions[..snap.region_count] {
+        let flags = region.flags;
+        let perm = |flag: VmaFlags, ch: char| if flags.contains(flag) { ch } else { '-' };
+        let _ = write!(
+            out,
+            "{:016x}-{:016x} {}{}{}{} {:>8}K",
+            region.start,
+            region.end,
+            perm(VmaFlags::READ, 'r'),
+            perm(VmaFlags::WRITE, 'w'),
+            perm(VmaFlags::EXEC, 'x'),
+            if flags.contains(VmaFlags::SHARED) {
+                's'
+            } else {
+                'p'
+            },
+            (region.end - region.start) / 1024
+        );
+        for (flag, tag) in [
+            (VmaFlags::STACK, "stack"),
+            (VmaFlags::HEAP, "heap"),
+            (VmaFlags::GUARD, "guard"),
+            (VmaFlags::COW, "cow"),
+            (VmaFlags::DEVICE, "device"),
+        ] {
+            if flags.contains(flag) {
+                let _ = write!(out, " [{}]", tag);
+            }
+        }
+        out.push('\n');
+    }
+}
+
+/// `<base>/<yyyymmdd>-<hhmmss>-<comm>-<pid>`, with characters that do not
+/// belong in a file name replaced.
+pub(crate) fn bundle_path(base: &[u8], snap: &CrashSnapshot) -> String {
+    let t = CivilTime::from_unix_secs(snap.time_ns / 1_000_000_000);
+    let mut path = String::new();
+    path.push_str(core::str::from_utf8(base).unwrap_or("/"));
+    let _ = write!(
+        path,
+        "/{:04}{:02}{:02}-{:02}{:02}{:02}-",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    );
+    for ch in snap.comm_str().chars() {
+        path.push(if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+            ch
+        } else {
+            '_'
+        });
+    }
+    let _ = write!(path, "-{}", snap.pid);
+    path
+}
+
+/// Create `path` and every missing directory above it.
+fn ensure_dirs(path: &[u8]) -> Result<(), VfsError> {
+    for end in (1..=path.len()).filter(|&i| i == path.len() || path[i] == b'/') {
+        ensure_dir(&path[..end])?;
+    }
+    Ok(())
+}
+
+fn file_path(dir: &str, name: &[u8]) -> String {
+    let mut path = String::from(dir);
+    path.push('/');
+    path.push_str(core::str::from_utf8(name).unwrap_or(""));
+    path
+}
+
+/// Write the bundle for `snap` under `base`, with a screenshot if
+/// `screenshot` is given.  Returns the notice for it, not yet numbered.
+pub(crate) fn write_bundle(
+    base: &[u8],
+    snap: &CrashSnapshot,
+    screenshot: Option<ScreenshotFn>,
+) -> Result<CrashNotice, VfsError> {
+    let dir = bundle_path(base, snap);
+    ensure_dirs(dir.as_bytes())?;
+
+    let mut text = String::new();
+    format_report(&mut text, snap);
+    append(file_path(&dir, b"report").as_bytes(), &text)?;
+
+    text.clear();
+    format_maps(&mut text, snap);
+    append(file_path(&dir, b"maps").as_bytes(), &text)?;
+
+    let mut tail = [0u8; KLOG_TAIL_LEN];
+    let len = klog_tail(&mut tail);
+    append(
+        file_path(&dir, b"klog").as_bytes(),
+        &String::from_utf8_lossy(&tail[..len]),
+    )?;
+
+    // Not every machine has a display; the bundle is complete without it.
+    if let Some(write) = screenshot {
+        write(file_path(&dir, CRASH_SCREENSHOT_NAME).as_bytes());
+    }
+
+    let mut notice = CrashNotice {
+        time_ns: snap.time_ns,
+        pid: snap.pid,
+        fault: snap.fault as u32,
+        comm: snap.comm,
+        ..CrashNotice::default()
+    };
+    let path_len = dir.len().min(CRASH_PATH_LEN - 1);
+    notice.path[..path_len].copy_from_slice(&dir.as_bytes()[..path_len]);
+    Ok(notice)
+}
+
+/// Write a bundle for every queued snapshot.  Returns how many were
+/// written; a snapshot that fails stays queued for the next try.
+pub fn crash_flush() -> usize {
+    let mut written = 0;
+    for idx in 0..QUEUE_LEN {
+        let Some(snap) = QUEUE.lock().slots[idx] else {
+            continue;
+        };
+        let screenshot = *SCREENSHOT.lock();
+        let Ok(notice) = write_bundle(CRASH_DIR, &snap, screenshot) else {
+            continue;
+        };
+        QUEUE.lock().slots[idx] = None;
+        NOTICES.lock().push(notice);
+        klog_info!(
+            "CRASH: {} (pid {}) -> {}",
+            notice.comm_str(),
+            notice.pid,
+            notice.path_str()
+        );
+        written += 1;
+    }
+    if written > 0 {
+        compositor_wake();
+    }
+    written
+}
+
+fn crashd_task(_arg: *mut core::ffi::c_void) {
+    loop {
+        sleep_current_task_ms(WRITE_INTERVAL_MS);
+        crash_flush();
+        coredump::core_flush();
+    }
+}
+
+/// Start the `crashd` thread.  Returns false if it could not be spawned.
+pub fn crash_start() -> bool {
+    let task_id = kthread_spawn(c"crashd".as_ptr(), Some(crashd_task), ptr::null_mut());
+    task_id != INVALID_TASK_ID
+}
@@ -0,0 +1,159 @@
+//! Crash bundle tests: the report and maps text, the bundle directory, the
+//! notice ring and the kernel log tail.
+
+use alloc::string::String;
+
+use slopos_abi::crash::{CRASH_NOTICE_LEN, CrashNotice};
+use slopos_abi::task::TaskFaultReason;
+use slopos_fs::vfs::ops::{vfs_open, vfs_stat, vfs_unlink};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{KLOG_TAIL_LEN, assert_eq_test, assert_test, fail, klog_info, klog_tail, pass};
+use slopos_mm::vma_flags::VmaFlags;
+
+use crate::crash::{
+    CRASH_REG_NAMES, CrashRegion, CrashSnapshot, NoticeRing, bundle_path, format_maps,
+    format_report, write_bundle,
+};
+
+fn snapshot() -> CrashSnapshot {
+    let mut snap = CrashSnapshot {
+        // 2024-03-05 06:07:08 UTC
+        time_ns: 1_709_618_828_000_000_000,
+        pid: 42,
+        fault: TaskFaultReason::UserPage,
+        vector: 14,
+        error_code: 0x6,
+        cr2: 0x10,
+        ..CrashSnapshot::empty()
+    };
+    snap.comm[..8].copy_from_slice(b"my app/1");
+    let rip = CRASH_REG_NAMES.iter().position(|&n| n == "rip").unwrap();
+    snap.regs[rip] = 0x40_1000;
+    snap.code[..2].copy_from_slice(&[0x0f, 0x0b]);
+    snap.code_len = 2;
+    snap.regions[0] = CrashRegion {
+        start: 0x40_0000,
+        end: 0x40_2000,
+        flags: VmaFlags::READ | VmaFlags::EXEC,
+    };
+    snap.regions[1] = CrashRegion {
+        start: 0x7fff_f000_0000,
+        end: 0x7fff_f001_0000,
+        flags: VmaFlags::USER_STACK,
+    };
+    snap.region_count = 2;
+    snap
+}
+
+pub fn test_crash_report_format() -> TestResult {
+    let mut out = String::new();
+    format_report(&mut out, &snapshot());
+    assert_test!(out.starts_with("task: my app/1 (pid 42)\n"), "{}", out);
+    assert_test!(out.contains("fault: page fault (vector 14, error 0x6)\n"));
+    assert_test!(out.contains("address: 0x0000000000000010\n"));
+    assert_test!(out.contains("time: 1709618828.000000000\n"));
+    assert_test!(out.contains("     rip 0000000000401000"));
+    assert_test!(out.contains("stack (0 bytes at rsp):\n"));
+    assert_test!(out.contains("code (2 bytes at rip):\n  0000000000401000: 0f 0b\n"));
+    pass!()
+}
+
+pub fn test_crash_maps_format() -> TestResult {
+    let mut out = String::new();
+    format_maps(&mut out, &snapshot());
+    assert_eq_test!(
+        out.as_str(),
+        "0000000000400000-0000000000402000 r-xp        8K\n\
+         00007ffff0000000-00007ffff0010000 rw-p       64K [stack]\n"
+    );
+    pass!()
+}
+
+pub fn test_crash_bundle_path_is_sanitised() -> TestResult {
+    let path = bundle_path(b"/var/crash", &snapshot());
+    assert_eq_test!(path.as_str(), "/var/crash/20240305-060708-my_app_1-42");
+    pass!()
+}
+
+pub fn test_crash_notice_ring_keeps_newest() -> TestResult {
+    let mut ring = NoticeRing::new();
+    assert_test!(ring.first_from(0).is_none(), "empty ring returned a notice");
+    for i in 0..CRASH_NOTICE_LEN as u64 + 3 {
+        let notice = CrashNotice {
+            pid: i as u32,
+            ..CrashNotice::default()
+        };
+        assert_eq_test!(ring.push(notice), i);
+    }
+    assert_eq_test!(ring.first_from(0).map(|n| n.seq), Some(3), "oldest kept");
+    assert_eq_test!(ring.first_from(5).map(|n| n.pid), Some(5));
+    assert_test!(ring.first_from(CRASH_NOTICE_LEN as u64 + 3).is_none());
+    pass!()
+}
+
+pub fn test_crash_bundle_written() -> TestResult {
+    const BASE: &[u8] = b"/tmp/crash_test";
+    klog_info!("crash_test: bundle marker");
+    let snap = snapshot();
+    let dir = bundle_path(BASE, &snap);
+    let result = write_bundle(BASE, &snap, None);
+
+    let mut found = [false; 3];
+    let mut klog = [0u8; KLOG_TAIL_LEN];
+    let mut klog_len = 0;
+    for (i, name) in ["report", "maps", "klog"].iter().enumerate() {
+        let mut path = dir.clone();
+        path.push('/');
+        path.push_str(name);
+        found[i] = vfs_stat(path.as_bytes()).is_ok();
+        if *name == "klog"
+            && let Ok(handle) = vfs_open(path.as_bytes(), false)
+        {
+            klog_len = handle.read(0, &mut klog).unwrap_or(0);
+        }
+        let _ = vfs_unlink(path.as_bytes());
+    }
+    let _ = vfs_unlink(dir.as_bytes());
+    let _ = vfs_unlink(BASE);
+
+    let notice = match result {
+        Ok(notice) => notice,
+        Err(err) => return fail!("write_bundle failed: {:?}", err),
+    };
+    assert_eq_test!(notice.path_str(), dir.as_str());
+    assert_eq_test!(notice.comm_str(), "my app/1");
+    assert_eq_test!(notice.fault, TaskFaultReason::UserPage as u32);
+    assert_eq_test!(found, [true; 3]);
+    let klog = core::str::from_utf8(&klog[..klog_len]).unwrap_or("");
+    assert_test!(
+        klog.contains("crash_test: bundle marker"),
+        "marker not in klog"
+    );
+    pass!()
+}
+
+pub fn test_klog_tail_holds_latest_lines() -> TestResult {
+    klog_info!("crash_test: tail marker");
+    let mut tail = [0u8; KLOG_TAIL_LEN];
+    let len = klog_tail(&mut tail);
+    assert_test!(len > 0 && len <= KLOG_TAIL_LEN);
+    let text = core::str::from_utf8(&tail[..len]).unwrap_or("");
+    // Other CPUs may log after the marker, but not a whole tail's worth.
+    assert_test!(
+        text.contains("crash_test: tail marker\n"),
+        "marker not in tail"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    crash,
+    [
+        test_crash_report_format,
+        test_crash_maps_format,
+        test_crash_bundle_path_is_sanitised,
+        test_crash_notice_ring_keeps_newest,
+        test_crash_bundle_written,
+        test_klog_tail_holds_latest_lines,
+    ]
+);
@@ -48,6 +48,15 @@ fn runtime_current_task_sid() -> u32 {
     unsafe { (*task).sid }
 }
 
+/// Root when there is no current task: the kernel acts with full rights.
+fn runtime_current_task_creds() -> slopos_abi::task::Credentials {
+    let task = scheduler::scheduler_get_current_task();
+    if task.is_null() {
+        return slopos_abi::task::Credentials::ROOT;
+    }
+    unsafe { (*task).creds }
+}
+
 fn runtime_current_task_controlling_tty() -> Option<slopos_abi::syscall::TtyIndex> {
     let task = scheduler::scheduler_get_current_task();
     if task.is_null() {
@@ -180,6 +189,7 @@ static DRIVER_RUNTIME_SERVICES: DriverRuntimeServices = DriverRuntimeServices {
     current_task_id: runtime_current_task_id,
     current_task_pgid: runtime_current_task_pgid,
     current_task_sid: runtime_current_task_sid,
+    current_task_creds: runtime_current_task_creds,
     current_task_controlling_tty: runtime_current_task_controlling_tty,
     set_current_task_controlling_tty: runtime_set_current_task_controlling_tty,
     clear_session_controlling_tty: runtime_clear_session_controlling_tty,
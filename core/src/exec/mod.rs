@@ -8,20 +8,24 @@ use core::ffi::c_char;
 use core::ptr;
 
 use slopos_abi::addr::VirtAddr;
-use slopos_abi::auxv::{AT_ENTRY, AT_NULL, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM};
-use slopos_abi::task::{INVALID_PROCESS_ID, TASK_FLAG_USER_MODE, TASK_NAME_MAX_LEN};
-use slopos_fs::fileio::{fileio_clone_table_for_process, fileio_destroy_table_for_process};
+use slopos_abi::auxv::{AT_BASE, AT_ENTRY, AT_NULL, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM};
+use slopos_abi::task::{
+    INVALID_PROCESS_ID, TASK_FLAG_NET_RAW, TASK_FLAG_USER_MODE, TASK_NAME_MAX_LEN,
+};
+use slopos_fs::fileio::fileio_inherit_table_for_exec;
 use slopos_fs::vfs::ops::vfs_open;
+use slopos_fs::vfs::perm::{MAY_EXEC, check_access, current_credentials};
 use slopos_lib::klog_info;
-use slopos_mm::elf::{ElfError, ElfExecInfo};
+use slopos_mm::elf::{ElfError, ElfExecInfo, ElfValidator};
 use slopos_mm::hhdm::PhysAddrHhdm;
 use slopos_mm::memory_layout_defs::PROCESS_CODE_START_VA;
 use slopos_mm::paging_defs::PAGE_SIZE_4KB;
 use slopos_mm::process_vm::{
     process_vm_get_page_dir, process_vm_get_stack_top, process_vm_load_elf_data,
+    process_vm_load_interpreter,
 };
 
-use crate::sched::schedule_task;
+use crate::sched::{schedule_task, scheduler_get_current_task};
 use crate::scheduler::task_struct::Task;
 use crate::task::{TaskEntry, task_create, task_get_info, task_terminate};
 use slopos_abi::task::INVALID_TASK_ID;
@@ -30,10 +34,11 @@ extern crate alloc;
 
 pub const EXEC_MAX_PATH: usize = 256;
 pub const EXEC_MAX_ARG_STRLEN: usize = 4096;
-pub const EXEC_MAX_ARGS: usize = 32;
-pub const EXEC_MAX_ENVS: usize = 32;
+pub use slopos_abi::syscall::{EXEC_MAX_ARGS, EXEC_MAX_ENVS};
 pub const EXEC_MAX_ELF_SIZE: usize = 16 * 1024 * 1024;
 pub const EXEC_SPAWN_DEFAULT_PRIORITY: u8 = 5;
+/// Bytes of a `#!` line searched for its end.
+pub const SHEBANG_MAX: usize = 128;
 
 pub const INIT_PATH: &[u8] = b"/sbin/init";
 
@@ -43,6 +48,7 @@ pub enum ExecError {
     NoEntry = -2,
     NoExec = -8,
     NoMem = -12,
+    Access = -13,
     Fault = -14,
     NameTooLong = -36,
     IoError = -5,
@@ -64,8 +70,9 @@ pub fn launch_init() -> Result<u32, ExecError> {
     spawn_program_with_attrs(
         INIT_PATH,
         None,
+        None,
         EXEC_SPAWN_DEFAULT_PRIORITY,
-        TASK_FLAG_USER_MODE,
+        TASK_FLAG_USER_MODE | TASK_FLAG_NET_RAW,
         INVALID_PROCESS_ID,
     )
 }
@@ -94,6 +101,7 @@ fn task_name_from_path(path: &[u8]) -> Result<[u8; TASK_NAME_MAX_LEN], ExecError
 pub fn spawn_program_with_attrs(
     path: &[u8],
     argv: Option<&[&[u8]]>,
+    envp: Option<&[&[u8]]>,
     priority: u8,
     mut flags: u16,
     inherit_fds_from: u32,
@@ -135,7 +143,7 @@ pub fn spawn_program_with_attrs(
             process_id,
             normalized_path,
             argv,
-            None,
+            envp,
             &mut entry,
             &mut stack_ptr,
         ) {
@@ -152,10 +160,22 @@ pub fn spawn_program_with_attrs(
         // Clone the parent's fd table BEFORE scheduling so the child has
         // stdin/stdout/stderr available from the moment it starts running.
         // This avoids an SMP race where the child runs before the parent
-        // can set up the fd table post-spawn.
-        if inherit_fds_from != INVALID_PROCESS_ID {
-            fileio_destroy_table_for_process(process_id);
-            let _ = fileio_clone_table_for_process(inherit_fds_from, process_id);
+        // can set up the fd table post-spawn.  Spawn is fork + exec in one
+        // step, so the parent's FD_CLOEXEC descriptors stay behind.
+        if inherit_fds_from != INVALID_PROCESS_ID
+            && fileio_inherit_table_for_exec(inherit_fds_from, process_id) != 0
+        {
+            task_terminate(task_id);
+            return Err(ExecError::NoMem);
+        }
+        let parent = scheduler_get_current_task();
+        if !parent.is_null() {
+            unsafe {
+                crate::perf::perf_inherit(&*parent, &*task_info);
+                (*task_info).syscall_filter = (*parent).syscall_filter;
+                (*task_info).creds = (*parent).creds;
+                (*task_info).core_limit = (*parent).core_limit;
+            }
         }
 
         if schedule_task(task_info) != 0 {
@@ -181,6 +201,108 @@ pub fn do_exec(
         return Err(ExecError::NameTooLong);
     }
 
+    let image = read_exec_image(path)?;
+    let Some((interp, interp_arg)) = parse_shebang(&image) else {
+        return load_image(process_id, image, argv, envp, entry_out, stack_ptr_out);
+    };
+
+    // A script runs as `interp [arg] path args...`.  The interpreter must
+    // be a binary, not another script.
+    let interp_image = read_exec_image(interp)?;
+    if parse_shebang(&interp_image).is_some() {
+        return Err(ExecError::NoExec);
+    }
+    let mut script_argv: Vec<&[u8]> = Vec::new();
+    script_argv
+        .try_reserve(EXEC_MAX_ARGS)
+        .map_err(|_| ExecError::NoMem)?;
+    script_argv.push(interp);
+    script_argv.extend(interp_arg);
+    script_argv.push(path);
+    script_argv.extend(argv.unwrap_or(&[]).iter().skip(1));
+    if script_argv.len() > EXEC_MAX_ARGS {
+        return Err(ExecError::TooManyArgs);
+    }
+    load_image(
+        process_id,
+        interp_image,
+        Some(script_argv.as_slice()),
+        envp,
+        entry_out,
+        stack_ptr_out,
+    )
+}
+
+/// The interpreter path and optional argument on the `#!` line that starts
+/// a script, or `None` for anything else.
+pub fn parse_shebang(image: &[u8]) -> Option<(&[u8], Option<&[u8]>)> {
+    let rest = image.strip_prefix(b"#!")?;
+    let end = match rest.iter().take(SHEBANG_MAX).position(|&b| b == b'\n') {
+        Some(end) => end,
+        None if rest.len() <= SHEBANG_MAX => rest.len(),
+        None => return None,
+    };
+    let line = rest[..end].trim_ascii();
+    let split = line
+        .iter()
+        .position(|&b| b == b' ' || b == b'\t')
+        .unwrap_or(line.len());
+    let (interp, arg) = line.split_at(split);
+    if interp.is_empty() || interp.len() > EXEC_MAX_PATH {
+        return None;
+    }
+    let arg = arg.trim_ascii();
+    Some((interp, (!arg.is_empty()).then_some(arg)))
+}
+
+/// Whether the executable at `path` names a program interpreter, i.e. is
+/// dynamically linked and starts in `ld.slop`.
+pub fn is_dynamically_linked(path: &[u8]) -> Result<bool, ExecError> {
+    let image = read_exec_image(path)?;
+    Ok(ElfValidator::new(&image)?.interpreter()?.is_some())
+}
+
+/// Map an executable image into `process_id` and build its initial stack.
+fn load_image(
+    process_id: u32,
+    elf_data: Vec<u8>,
+    argv: Option<&[&[u8]]>,
+    envp: Option<&[&[u8]]>,
+    entry_out: &mut u64,
+    stack_ptr_out: &mut u64,
+) -> Result<(), ExecError> {
+    let interp_path = ElfValidator::new(&elf_data)?.interpreter()?;
+
+    let mut exec_info = process_vm_load_elf_data(process_id, elf_data.as_slice(), entry_out)
+        .map_err(ExecError::from)?;
+    if let Some(interp_path) = interp_path {
+        // The interpreter starts first and jumps to AT_ENTRY when done.
+        let interp_data = read_exec_image(interp_path)?;
+        let (interp_base, interp_entry) =
+            process_vm_load_interpreter(process_id, &interp_data).map_err(ExecError::from)?;
+        exec_info.interp_base = interp_base;
+        *entry_out = interp_entry;
+    }
+
+    let stack_top = setup_user_stack(process_id, argv, envp, &exec_info)?;
+    *stack_ptr_out = stack_top;
+
+    // POSIX: close all FDs with FD_CLOEXEC set after point of no return.
+    slopos_fs::fileio_close_on_exec(process_id);
+
+    klog_info!(
+        "exec: loaded ELF for process {}, entry={:#x}, stack={:#x}",
+        process_id,
+        *entry_out,
+        stack_top
+    );
+
+    Ok(())
+}
+
+/// Read the executable at `path` into memory, checking that the caller
+/// may execute it.
+fn read_exec_image(path: &[u8]) -> Result<Vec<u8>, ExecError> {
     let handle = vfs_open(path, false).map_err(|e| match e {
         slopos_fs::VfsError::NotFound => ExecError::NoEntry,
         slopos_fs::VfsError::IsDirectory => ExecError::NoExec,
@@ -192,9 +314,7 @@ pub fn do_exec(
         .fs
         .stat(handle.inode)
         .map_err(|_| ExecError::IoError)?;
-    if (file_stat.mode & 0o111) == 0 {
-        return Err(ExecError::NoExec);
-    }
+    check_access(&file_stat, &current_credentials(), MAY_EXEC).map_err(|_| ExecError::Access)?;
 
     let file_size = file_stat.size as usize;
     if file_size == 0 || file_size > EXEC_MAX_ELF_SIZE {
@@ -227,23 +347,7 @@ pub fn do_exec(
         elf_data.truncate(offset as usize);
     }
 
-    let exec_info = process_vm_load_elf_data(process_id, elf_data.as_slice(), entry_out)
-        .map_err(ExecError::from)?;
-
-    let stack_top = setup_user_stack(process_id, argv, envp, &exec_info)?;
-    *stack_ptr_out = stack_top;
-
-    // POSIX: close all FDs with FD_CLOEXEC set after point of no return.
-    slopos_fs::fileio_close_on_exec(process_id);
-
-    klog_info!(
-        "exec: loaded ELF for process {}, entry={:#x}, stack={:#x}",
-        process_id,
-        *entry_out,
-        stack_top
-    );
-
-    Ok(())
+    Ok(elf_data)
 }
 
 fn setup_user_stack(
@@ -305,24 +409,25 @@ fn setup_user_stack(
 
     sp &= !0xF;
 
-    // SysV ABI: rsp must be 16-byte aligned at _start with argc at [rsp].
-    // Total 8-byte slots below this point: auxv (12) + envp_null (1) + envc +
-    // argv_null (1) + argc_slots (argc) + argc_word (1) = argc + envc + 15.
-    // If that total is odd, insert one 8-byte padding slot here (between the
-    // string area and auxv) so the final sp stays 16-byte aligned.
-    let total_slots = argc + envc + 15; // 12 auxv + 3 sentinel/argc
-    if total_slots % 2 != 0 {
-        sp = sp.wrapping_sub(8);
-    }
-
     let auxv = [
         (AT_PHDR, exec_info.phdr_addr),
         (AT_PHENT, exec_info.phent_size as u64),
         (AT_PHNUM, exec_info.phnum as u64),
         (AT_PAGESZ, PAGE_SIZE_4KB),
+        (AT_BASE, exec_info.interp_base),
         (AT_ENTRY, exec_info.entry),
         (AT_NULL, 0),
     ];
+
+    // SysV ABI: rsp must be 16-byte aligned at _start with argc at [rsp].
+    // Total 8-byte slots below this point: auxv (2 per entry) + envp_null (1)
+    // + envc + argv_null (1) + argc_slots (argc) + argc_word (1).  If that
+    // total is odd, insert one 8-byte padding slot here (between the string
+    // area and auxv) so the final sp stays 16-byte aligned.
+    let total_slots = auxv.len() * 2 + argc + envc + 3;
+    if total_slots % 2 != 0 {
+        sp = sp.wrapping_sub(8);
+    }
     let aux_size = auxv.len() * (2 * core::mem::size_of::<u64>());
     sp = sp.wrapping_sub(aux_size as u64);
     for (idx, (a_type, a_val)) in auxv.iter().enumerate() {
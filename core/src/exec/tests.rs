@@ -1,18 +1,25 @@
 //! exec() ELF loader tests - targeting untested code paths likely to have bugs.
 
 use slopos_abi::addr::VirtAddr;
-use slopos_abi::auxv::{AT_ENTRY, AT_NULL, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM};
+use slopos_abi::auxv::{AT_BASE, AT_ENTRY, AT_NULL, AT_PAGESZ, AT_PHDR, AT_PHENT, AT_PHNUM};
 use slopos_abi::task::INVALID_PROCESS_ID;
 use slopos_lib::klog_info;
 use slopos_lib::testing::TestResult;
-use slopos_mm::elf::{ELF_MAGIC, ElfExecInfo, ElfValidator};
+use slopos_mm::elf::{
+    DT_JMPREL, DT_NEEDED, DT_NULL, DT_PLTRELSZ, DT_RELA, DT_RELASZ, DT_STRTAB, DT_SYMTAB,
+    DynamicInfo, ELF_MAGIC, ElfError, ElfExecInfo, ElfValidator, PT_DYNAMIC, PT_INTERP,
+    R_X86_64_GLOB_DAT, R_X86_64_JUMP_SLOT, R_X86_64_PC32, R_X86_64_RELATIVE, relocation_value,
+};
 use slopos_mm::hhdm::PhysAddrHhdm;
-use slopos_mm::memory_layout_defs::PROCESS_CODE_START_VA;
+use slopos_mm::memory_layout_defs::{PROCESS_CODE_START_VA, PROCESS_INTERP_BASE_VA};
 use slopos_mm::paging::virt_to_phys_in_dir;
 use slopos_mm::paging_defs::PAGE_SIZE_4KB;
 use slopos_mm::process_vm;
 
-use super::{EXEC_MAX_ELF_SIZE, EXEC_MAX_PATH, INIT_PATH};
+use super::{
+    EXEC_MAX_ELF_SIZE, EXEC_MAX_PATH, ExecError, INIT_PATH, SHEBANG_MAX, do_exec,
+    is_dynamically_linked, parse_shebang,
+};
 
 const MINIMAL_ELF_SIZE: usize = 64;
 
@@ -109,6 +116,30 @@ fn create_elf_with_load_segment(vaddr: u64, memsz: u64, filesz: u64, offset: u64
     elf
 }
 
+/// A one-segment image followed by a second program header of `p_type`
+/// whose contents are `payload`, placed at offset 176.
+fn create_elf_with_extra_phdr(p_type: u32, payload: &[u8]) -> alloc::vec::Vec<u8> {
+    let mut elf = alloc::vec::Vec::new();
+    elf.extend_from_slice(&create_elf_with_load_segment(
+        PROCESS_CODE_START_VA,
+        0x1000,
+        0x100,
+        0,
+    ));
+    elf[56..58].copy_from_slice(&2u16.to_le_bytes()); // e_phnum: 2 segments
+    elf.resize(176, 0);
+
+    let mut phdr = [0u8; 56];
+    phdr[0..4].copy_from_slice(&p_type.to_le_bytes());
+    phdr[4..8].copy_from_slice(&4u32.to_le_bytes()); // p_flags: PF_R
+    phdr[8..16].copy_from_slice(&176u64.to_le_bytes()); // p_offset
+    phdr[32..40].copy_from_slice(&(payload.len() as u64).to_le_bytes()); // p_filesz
+    phdr[40..48].copy_from_slice(&(payload.len() as u64).to_le_bytes()); // p_memsz
+    elf[120..176].copy_from_slice(&phdr);
+    elf.extend_from_slice(payload);
+    elf
+}
+
 pub fn test_elf_invalid_magic() -> TestResult {
     let mut elf = create_minimal_elf_header();
     elf[0] = 0x00; // Corrupt magic
@@ -417,6 +448,76 @@ pub fn test_init_path_within_exec_limit() -> TestResult {
     TestResult::Pass
 }
 
+pub fn test_elf_writable_executable_segment() -> TestResult {
+    let mut elf = create_elf_with_load_segment(PROCESS_CODE_START_VA, 0x1000, 120, 0);
+    elf[68..72].copy_from_slice(&7u32.to_le_bytes()); // p_flags: PF_R | PF_W | PF_X
+
+    let validator = match ElfValidator::new(&elf) {
+        Ok(v) => v.with_load_base(PROCESS_CODE_START_VA),
+        Err(_) => {
+            klog_info!("EXEC_TEST: Test setup error");
+            return TestResult::Fail;
+        }
+    };
+
+    match validator.validate_load_segments() {
+        Err(ElfError::WritableExecutable) => TestResult::Pass,
+        _ => {
+            klog_info!("EXEC_TEST: BUG - ElfValidator accepted a W+X segment");
+            TestResult::Fail
+        }
+    }
+}
+
+/// Privilege separation: after exec, no user page is both writable and
+/// executable, code stays executable and the stack does not.
+pub fn test_privsep_loaded_image_has_no_wx() -> TestResult {
+    use slopos_mm::paging::paging_get_pte_flags;
+    use slopos_mm::paging_defs::PageFlags;
+
+    process_vm::init_process_vm();
+    let pid = process_vm::create_process_vm();
+    if pid == INVALID_PROCESS_ID {
+        return TestResult::Fail;
+    }
+
+    let elf = create_elf_with_load_segment(PROCESS_CODE_START_VA, 0x1000, 120, 0);
+    let mut entry = 0u64;
+    if process_vm::process_vm_load_elf_data(pid, &elf, &mut entry).is_err() {
+        klog_info!("EXEC_TEST: loading a minimal RX image failed");
+        process_vm::destroy_process_vm(pid);
+        return TestResult::Fail;
+    }
+
+    let page_dir = process_vm::process_vm_get_page_dir(pid);
+    let code = paging_get_pte_flags(page_dir, VirtAddr::new(entry)).unwrap_or(PageFlags::empty());
+    let stack_top = process_vm::process_vm_get_stack_top(pid);
+    let stack =
+        paging_get_pte_flags(page_dir, VirtAddr::new(stack_top - 8)).unwrap_or(PageFlags::empty());
+    let audit = process_vm::process_vm_audit_wx(pid).unwrap_or_default();
+    process_vm::destroy_process_vm(pid);
+
+    if !code.contains(PageFlags::USER)
+        || code.contains(PageFlags::WRITABLE)
+        || code.contains(PageFlags::NO_EXECUTE)
+    {
+        klog_info!("EXEC_TEST: code page flags {:#x}", code.bits());
+        return TestResult::Fail;
+    }
+    if !stack.contains(PageFlags::NO_EXECUTE) {
+        klog_info!("EXEC_TEST: BUG - user stack is executable");
+        return TestResult::Fail;
+    }
+    if !audit.is_clean() {
+        klog_info!(
+            "EXEC_TEST: BUG - W+X user page at {:#x}",
+            audit.first_violation
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
 pub fn test_setup_user_stack_contract_layout() -> TestResult {
     process_vm::init_process_vm();
     let pid = process_vm::create_process_vm();
@@ -431,6 +532,7 @@ pub fn test_setup_user_stack_contract_layout() -> TestResult {
         phdr_addr: 0x402000,
         phent_size: 56,
         phnum: 3,
+        interp_base: 0,
     };
 
     let result = super::setup_user_stack(pid, Some(&args), Some(&envs), &exec_info);
@@ -490,6 +592,7 @@ pub fn test_setup_user_stack_auxv_required_entries() -> TestResult {
         phdr_addr: 0x7000_2000,
         phent_size: 56,
         phnum: 5,
+        interp_base: PROCESS_INTERP_BASE_VA,
     };
 
     let sp = match super::setup_user_stack(pid, Some(&args), Some(&envs), &exec_info) {
@@ -519,6 +622,7 @@ pub fn test_setup_user_stack_auxv_required_entries() -> TestResult {
     let mut saw_phent = false;
     let mut saw_phnum = false;
     let mut saw_pagesz = false;
+    let mut saw_base = false;
     let mut saw_entry = false;
     let mut saw_null = false;
 
@@ -533,6 +637,8 @@ pub fn test_setup_user_stack_auxv_required_entries() -> TestResult {
             saw_phnum = true;
         } else if key == AT_PAGESZ && val == PAGE_SIZE_4KB {
             saw_pagesz = true;
+        } else if key == AT_BASE && val == exec_info.interp_base {
+            saw_base = true;
         } else if key == AT_ENTRY && val == exec_info.entry {
             saw_entry = true;
         } else if key == AT_NULL && val == 0 {
@@ -543,13 +649,14 @@ pub fn test_setup_user_stack_auxv_required_entries() -> TestResult {
     }
 
     process_vm::destroy_process_vm(pid);
-    if !(saw_phdr && saw_phent && saw_phnum && saw_pagesz && saw_entry && saw_null) {
+    if !(saw_phdr && saw_phent && saw_phnum && saw_pagesz && saw_base && saw_entry && saw_null) {
         klog_info!(
-            "EXEC_TEST: auxv missing entries phdr={} phent={} phnum={} pagesz={} entry={} null={}",
+            "EXEC_TEST: auxv missing entries phdr={} phent={} phnum={} pagesz={} base={} entry={} null={}",
             saw_phdr,
             saw_phent,
             saw_phnum,
             saw_pagesz,
+            saw_base,
             saw_entry,
             saw_null
         );
@@ -576,6 +683,7 @@ pub fn test_setup_user_stack_argv_string_content() -> TestResult {
         phdr_addr: 0x402000,
         phent_size: 56,
         phnum: 1,
+        interp_base: 0,
     };
 
     let sp = match super::setup_user_stack(pid, Some(&args), Some(&envs), &exec_info) {
@@ -684,6 +792,209 @@ pub fn test_setup_user_stack_argv_string_content() -> TestResult {
     TestResult::Pass
 }
 
+pub fn test_elf_interpreter_path() -> TestResult {
+    let elf = create_elf_with_extra_phdr(PT_INTERP, b"/lib/ld.slop\0");
+    let validator = match ElfValidator::new(&elf) {
+        Ok(v) => v,
+        Err(_) => {
+            klog_info!("EXEC_TEST: Test setup error");
+            return TestResult::Fail;
+        }
+    };
+    if validator.interpreter() != Ok(Some(&b"/lib/ld.slop"[..])) {
+        klog_info!("EXEC_TEST: BUG - PT_INTERP path not returned");
+        return TestResult::Fail;
+    }
+
+    let static_elf = create_elf_with_load_segment(PROCESS_CODE_START_VA, 0x1000, 0x100, 0);
+    if ElfValidator::new(&static_elf).and_then(|v| v.interpreter()) != Ok(None) {
+        klog_info!("EXEC_TEST: BUG - static image reported an interpreter");
+        return TestResult::Fail;
+    }
+
+    for bad in [&b"/lib/ld.slop"[..], b"lib/ld.slop\0", b"\0"] {
+        let elf = create_elf_with_extra_phdr(PT_INTERP, bad);
+        if ElfValidator::new(&elf).and_then(|v| v.interpreter())
+            != Err(ElfError::InvalidInterpreter)
+        {
+            klog_info!("EXEC_TEST: BUG - malformed PT_INTERP accepted");
+            return TestResult::Fail;
+        }
+    }
+    TestResult::Pass
+}
+
+pub fn test_elf_dynamic_info() -> TestResult {
+    let entries: [(u64, u64); 9] = [
+        (DT_NEEDED, 1),
+        (DT_NEEDED, 12),
+        (DT_STRTAB, 0x40_1000),
+        (DT_SYMTAB, 0x40_2000),
+        (DT_RELA, 0x40_3000),
+        (DT_RELASZ, 48),
+        (DT_JMPREL, 0x40_4000),
+        (DT_PLTRELSZ, 24),
+        (DT_NULL, 0),
+    ];
+    let mut payload = alloc::vec::Vec::new();
+    for (tag, val) in entries {
+        payload.extend_from_slice(&tag.to_le_bytes());
+        payload.extend_from_slice(&val.to_le_bytes());
+    }
+
+    let elf = create_elf_with_extra_phdr(PT_DYNAMIC, &payload);
+    let expected = DynamicInfo {
+        needed_count: 2,
+        strtab: 0x40_1000,
+        symtab: 0x40_2000,
+        rela: 0x40_3000,
+        relasz: 48,
+        jmprel: 0x40_4000,
+        pltrelsz: 24,
+        ..DynamicInfo::default()
+    };
+    if ElfValidator::new(&elf).and_then(|v| v.dynamic_info()) != Ok(Some(expected)) {
+        klog_info!("EXEC_TEST: BUG - PT_DYNAMIC parsed incorrectly");
+        return TestResult::Fail;
+    }
+
+    // Without DT_NULL the array is unterminated.
+    let truncated = create_elf_with_extra_phdr(PT_DYNAMIC, &payload[..payload.len() - 16]);
+    if ElfValidator::new(&truncated).and_then(|v| v.dynamic_info()) != Err(ElfError::InvalidDynamic)
+    {
+        klog_info!("EXEC_TEST: BUG - unterminated PT_DYNAMIC accepted");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+pub fn test_elf_relocation_values() -> TestResult {
+    let base = 0x7000_0000u64;
+    let symbol = 0x7100_0040u64;
+    let cases = [
+        (R_X86_64_RELATIVE, Some(base + 0x20)),
+        (R_X86_64_GLOB_DAT, Some(symbol)),
+        (R_X86_64_JUMP_SLOT, Some(symbol)),
+        (R_X86_64_PC32, None),
+    ];
+    for (kind, expected) in cases {
+        if relocation_value(kind, base, symbol, 0x20) != expected {
+            klog_info!("EXEC_TEST: BUG - relocation type {} resolved wrongly", kind);
+            return TestResult::Fail;
+        }
+    }
+    if relocation_value(R_X86_64_RELATIVE, base, 0, -0x10) != Some(base - 0x10) {
+        klog_info!("EXEC_TEST: BUG - negative RELATIVE addend mishandled");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+/// The interpreter lands at the fixed window above the mmap region with
+/// its entry point biased to match.
+pub fn test_load_interpreter_at_interp_base() -> TestResult {
+    process_vm::init_process_vm();
+    let pid = process_vm::create_process_vm();
+    if pid == INVALID_PROCESS_ID {
+        return TestResult::Fail;
+    }
+
+    let mut elf = create_elf_with_load_segment(0, 0x1000, 120, 0);
+    elf[16..18].copy_from_slice(&3u16.to_le_bytes()); // e_type: ET_DYN
+    elf[24..32].copy_from_slice(&0x78u64.to_le_bytes()); // e_entry
+
+    let loaded = process_vm::process_vm_load_interpreter(pid, &elf);
+    let magic = read_user_u8(pid, PROCESS_INTERP_BASE_VA);
+
+    // An ET_EXEC image cannot serve as an interpreter.
+    let exec_elf = create_elf_with_load_segment(PROCESS_CODE_START_VA, 0x1000, 120, 0);
+    let rejected = process_vm::process_vm_load_interpreter(pid, &exec_elf);
+    process_vm::destroy_process_vm(pid);
+
+    if loaded != Ok((PROCESS_INTERP_BASE_VA, PROCESS_INTERP_BASE_VA + 0x78)) {
+        klog_info!("EXEC_TEST: BUG - interpreter load returned {:?}", loaded);
+        return TestResult::Fail;
+    }
+    if magic != Some(ELF_MAGIC[0]) {
+        klog_info!("EXEC_TEST: BUG - interpreter image not mapped at its base");
+        return TestResult::Fail;
+    }
+    if rejected != Err(ElfError::InvalidInterpreter) {
+        klog_info!("EXEC_TEST: BUG - ET_EXEC accepted as interpreter");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+/// A shipped program from the boot image execs through `/lib/ld.slop`:
+/// the loader is mapped and the process starts in it.
+pub fn test_exec_dynamic_program_from_image() -> TestResult {
+    const PROGRAM: &[u8] = b"/bin/slop-netstat";
+    match is_dynamically_linked(PROGRAM) {
+        Ok(true) => {}
+        // No image, or a USERLAND_LINK=static build.
+        Ok(false) | Err(ExecError::NoEntry) => return TestResult::Skipped,
+        Err(err) => {
+            klog_info!(
+                "EXEC_TEST: BUG - cannot inspect /bin/slop-netstat: {:?}",
+                err
+            );
+            return TestResult::Fail;
+        }
+    }
+
+    process_vm::init_process_vm();
+    let pid = process_vm::create_process_vm();
+    if pid == INVALID_PROCESS_ID {
+        return TestResult::Fail;
+    }
+    let (mut entry, mut stack_ptr) = (0u64, 0u64);
+    let result = do_exec(pid, PROGRAM, None, None, &mut entry, &mut stack_ptr);
+    let magic = read_user_u8(pid, PROCESS_INTERP_BASE_VA);
+    process_vm::destroy_process_vm(pid);
+
+    if result.is_err() {
+        klog_info!("EXEC_TEST: BUG - dynamic exec failed: {:?}", result);
+        return TestResult::Fail;
+    }
+    if entry < PROCESS_INTERP_BASE_VA || magic != Some(ELF_MAGIC[0]) {
+        klog_info!(
+            "EXEC_TEST: BUG - dynamic program entry {:#x} is not in ld.slop",
+            entry
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+pub fn test_shebang_parsing() -> TestResult {
+    let cases: [(&[u8], Option<(&[u8], Option<&[u8]>)>); 6] = [
+        (b"#!/bin/shell\necho hi\n", Some((b"/bin/shell", None))),
+        (b"#! /bin/shell -x \r\n", Some((b"/bin/shell", Some(b"-x")))),
+        (
+            b"#!/bin/env  shell  arg\n",
+            Some((b"/bin/env", Some(b"shell  arg"))),
+        ),
+        (b"#!/bin/shell", Some((b"/bin/shell", None))),
+        (b"#!  \n", None),
+        (b"\x7fELF", None),
+    ];
+    for (image, expected) in cases {
+        if parse_shebang(image) != expected {
+            klog_info!("EXEC_TEST: BUG - wrong #! line parse");
+            return TestResult::Fail;
+        }
+    }
+
+    let mut long = [b'a'; SHEBANG_MAX + 8];
+    long[..3].copy_from_slice(b"#!/");
+    if parse_shebang(&long).is_some() {
+        klog_info!("EXEC_TEST: BUG - unterminated long #! line accepted");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
 slopos_lib::define_test_suite!(
     exec,
     [
@@ -698,6 +1009,14 @@ slopos_lib::define_test_suite!(
         test_elf_segment_filesz_greater_than_memsz,
         test_elf_segment_offset_overflow,
         test_elf_kernel_address_entry,
+        test_elf_writable_executable_segment,
+        test_elf_interpreter_path,
+        test_shebang_parsing,
+        test_elf_dynamic_info,
+        test_elf_relocation_values,
+        test_load_interpreter_at_interp_base,
+        test_exec_dynamic_program_from_image,
+        test_privsep_loaded_image_has_no_wx,
         test_path_too_long,
         test_path_empty,
         test_translate_address_kernel_to_user,
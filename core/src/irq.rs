@@ -17,8 +17,9 @@ use slopos_lib::arch::idt::{
     IRQ_BASE_VECTOR, MSI_VECTOR_BASE, MSI_VECTOR_COUNT, MSI_VECTOR_END, SYSCALL_VECTOR,
 };
 pub use slopos_lib::kernel_services::driver_runtime::IRQ_LINES;
+use slopos_lib::ktrace::{KtraceKind, ktrace_record};
 use slopos_lib::string::cstr_to_str;
-use slopos_lib::{InterruptFrame, kdiag_dump_interrupt_frame, klog_debug, klog_info, tsc};
+use slopos_lib::{InterruptFrame, kbug, kdiag_dump_interrupt_frame, klog_debug, klog_info, tsc};
 
 use crate::platform;
 use crate::scheduler::scheduler::{TrapExitSource, scheduler_handoff_on_trap_exit};
@@ -358,7 +359,9 @@ pub fn irq_dispatch(frame: *mut InterruptFrame) {
     if irq as usize >= IRQ_LINES {
         // Check if this is an MSI vector before rejecting.
         if vector >= MSI_VECTOR_BASE && vector < MSI_VECTOR_END {
+            ktrace_record(KtraceKind::IrqEnter, vector as u32, 0);
             msi_dispatch_inner(vector, frame);
+            ktrace_record(KtraceKind::IrqExit, vector as u32, 0);
             acknowledge_irq();
             scheduler_handoff_on_trap_exit(TrapExitSource::Irq);
             return;
@@ -385,13 +388,19 @@ pub fn irq_dispatch(frame: *mut InterruptFrame) {
         return;
     };
 
+    ktrace_record(KtraceKind::IrqEnter, vector as u32, 0);
     handler(irq, frame, context);
+    ktrace_record(KtraceKind::IrqExit, vector as u32, 0);
 
-    if frame_ref.cs != expected_cs || frame_ref.rip != expected_rip {
-        klog_info!("IRQ: Frame corruption detected on IRQ {} - aborting", irq);
+    let corrupted = frame_ref.cs != expected_cs || frame_ref.rip != expected_rip;
+    if corrupted {
         kdiag_dump_interrupt_frame(frame);
-        panic!("IRQ: frame corrupted");
     }
+    kbug!(
+        corrupted,
+        "IRQ: handler for IRQ {} corrupted its frame",
+        irq
+    );
 
     acknowledge_irq();
     scheduler_handoff_on_trap_exit(TrapExitSource::Irq);
@@ -423,6 +432,8 @@ pub fn get_stats(irq: u8, out_stats: *mut IrqStats) -> i32 {
 // MSI bypasses the IOAPIC — devices write directly to the LAPIC.
 // Vectors 48–223 (MSI_VECTOR_BASE..MSI_VECTOR_END) are reserved for MSI.
 // The allocator is a simple atomic bitmap; the handler table is lock-protected.
+// This is the one vector domain every MSI/MSI-X device allocates from; the
+// per-device side (table entries, masking, retargeting) is `drivers::msix`.
 
 /// MSI handler function signature.
 ///
@@ -433,12 +444,13 @@ pub type MsiHandler = extern "C" fn(vector: u8, frame: *mut InterruptFrame, ctx:
 
 /// Per-vector MSI registration entry.
 #[derive(Clone, Copy)]
-#[allow(dead_code)] // device_bdf stored for future diagnostics / /proc/interrupts
 struct MsiEntry {
     handler: Option<MsiHandler>,
     context: *mut c_void,
     /// BDF identifier for diagnostics (bus << 16 | dev << 8 | func).
     device_bdf: u32,
+    /// APIC ID the device's message is addressed to.
+    target_apic: u8,
     count: u64,
 }
 
@@ -448,11 +460,21 @@ impl MsiEntry {
             handler: None,
             context: core::ptr::null_mut(),
             device_bdf: 0,
+            target_apic: 0,
             count: 0,
         }
     }
 }
 
+/// Diagnostic view of an allocated MSI vector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsiVectorInfo {
+    pub device_bdf: u32,
+    pub target_apic: u8,
+    pub registered: bool,
+    pub count: u64,
+}
+
 /// Bitmap words covering MSI_VECTOR_COUNT bits.
 /// 176 vectors → 3 × u64 = 192 bits (top 16 bits unused).
 const MSI_BITMAP_WORDS: usize = (MSI_VECTOR_COUNT + 63) / 64;
@@ -535,6 +557,33 @@ pub fn msi_alloc_vector() -> Option<u8> {
     None
 }
 
+/// Allocate one vector for each slot of `out`, all or nothing.
+///
+/// On failure every vector taken so far is freed again and `out` is zeroed.
+pub fn msi_alloc_vectors(out: &mut [u8]) -> bool {
+    let mut taken = 0;
+    for slot in out.iter_mut() {
+        let Some(vector) = msi_alloc_vector() else {
+            break;
+        };
+        *slot = vector;
+        taken += 1;
+    }
+    if taken == out.len() {
+        return true;
+    }
+    msi_free_vectors(&out[..taken]);
+    out.fill(0);
+    false
+}
+
+/// Free every vector in `vectors`; zero entries are skipped.
+pub fn msi_free_vectors(vectors: &[u8]) {
+    for &vector in vectors.iter().filter(|&&v| v != 0) {
+        msi_free_vector(vector);
+    }
+}
+
 /// Free a previously-allocated MSI vector.
 ///
 /// Also clears the handler table entry so stale interrupts are safely ignored.
@@ -578,6 +627,7 @@ pub fn msi_register_handler(
             handler: Some(handler),
             context,
             device_bdf,
+            target_apic: table[idx].target_apic,
             count: 0,
         };
     });
@@ -601,6 +651,18 @@ pub fn msi_unregister_handler(vector: u8) {
     klog_debug!("MSI: Unregistered handler for vector 0x{:02x}", vector);
 }
 
+/// Record that `vector` is now delivered to `apic_id`.
+///
+/// Bookkeeping only: the caller reprograms the device's message address.
+pub fn msi_set_affinity(vector: u8, apic_id: u8) -> bool {
+    if !msi_vector_is_allocated(vector) {
+        return false;
+    }
+    let idx = (vector - MSI_VECTOR_BASE) as usize;
+    with_msi_table(|table| table[idx].target_apic = apic_id);
+    true
+}
+
 // ---------------------------------------------------------------------------
 // Dispatch
 // ---------------------------------------------------------------------------
@@ -642,6 +704,23 @@ pub fn msi_allocated_count() -> usize {
     count
 }
 
+/// Owner, target CPU and hit count of an allocated vector.
+pub fn msi_vector_info(vector: u8) -> Option<MsiVectorInfo> {
+    if !msi_vector_is_allocated(vector) {
+        return None;
+    }
+    let idx = (vector - MSI_VECTOR_BASE) as usize;
+    Some(with_msi_table(|table| {
+        let entry = &table[idx];
+        MsiVectorInfo {
+            device_bdf: entry.device_bdf,
+            target_apic: entry.target_apic,
+            registered: entry.handler.is_some(),
+            count: entry.count,
+        }
+    }))
+}
+
 /// Check whether a specific vector is allocated.
 pub fn msi_vector_is_allocated(vector: u8) -> bool {
     if vector < MSI_VECTOR_BASE || vector >= MSI_VECTOR_END {
@@ -0,0 +1,101 @@
+//! klog tests: the subsystem a module path logs under, and per-subsystem
+//! levels filtering what reaches the log tail.
+
+use slopos_lib::klog::{KlogModule, klog_module_enabled};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{
+    KLOG_TAIL_LEN, KlogLevel, assert_eq_test, assert_test, klog_debug, klog_get_level,
+    klog_module_level, klog_set_level, klog_set_module_level, klog_tail, pass,
+};
+
+/// Whether `needle` is in the most recent log text.
+fn tail_contains(needle: &[u8]) -> bool {
+    let mut tail = [0u8; KLOG_TAIL_LEN];
+    let len = klog_tail(&mut tail);
+    tail[..len].windows(needle.len()).any(|w| w == needle)
+}
+
+/// Puts the levels a test changed back on drop.
+struct LevelGuard {
+    default: KlogLevel,
+    net: Option<KlogLevel>,
+}
+
+impl LevelGuard {
+    fn new() -> Self {
+        Self {
+            default: klog_get_level(),
+            net: klog_module_level(KlogModule::Net),
+        }
+    }
+}
+
+impl Drop for LevelGuard {
+    fn drop(&mut self) {
+        klog_set_level(self.default);
+        klog_set_module_level(KlogModule::Net, self.net);
+    }
+}
+
+pub fn test_klog_module_from_path() -> TestResult {
+    let cases = [
+        ("slopos_core::scheduler::scheduler", KlogModule::Sched),
+        ("slopos_core::syscall::net_handlers", KlogModule::Net),
+        ("slopos_core::syscall::fs", KlogModule::Core),
+        ("slopos_drivers::virtio_net", KlogModule::Net),
+        ("slopos_drivers::ps2::keyboard", KlogModule::Input),
+        ("slopos_drivers::pci", KlogModule::Drivers),
+        ("slopos_mm::page_alloc", KlogModule::Mm),
+        ("slopos_video::framebuffer", KlogModule::Video),
+        ("slopos_boot::early_init", KlogModule::Boot),
+        ("kernel", KlogModule::Core),
+    ];
+    for (path, module) in cases {
+        assert_eq_test!(KlogModule::from_module_path(path), module, path);
+    }
+    assert_eq_test!(
+        KlogModule::from_module_path(module_path!()),
+        KlogModule::Core
+    );
+    pass!()
+}
+
+pub fn test_klog_module_level_overrides_default() -> TestResult {
+    let _guard = LevelGuard::new();
+    klog_set_level(KlogLevel::Info);
+    klog_set_module_level(KlogModule::Net, Some(KlogLevel::Debug));
+
+    assert_test!(klog_module_enabled(KlogModule::Net, KlogLevel::Debug));
+    assert_test!(!klog_module_enabled(KlogModule::Mm, KlogLevel::Debug));
+    assert_test!(!klog_module_enabled(KlogModule::Net, KlogLevel::Trace));
+
+    klog_debug!(target: Net, "klog_tests: net debug line");
+    klog_debug!(target: Mm, "klog_tests: mm debug line");
+    assert_test!(
+        tail_contains(b"klog_tests: net debug line"),
+        "subsystem level ignored"
+    );
+    assert_test!(
+        !tail_contains(b"klog_tests: mm debug line"),
+        "default level ignored"
+    );
+
+    // A quieter subsystem than the default drops lines the default keeps.
+    klog_set_module_level(KlogModule::Net, Some(KlogLevel::Error));
+    assert_test!(!klog_module_enabled(KlogModule::Net, KlogLevel::Info));
+    assert_test!(klog_module_enabled(KlogModule::Core, KlogLevel::Info));
+
+    klog_set_module_level(KlogModule::Net, None);
+    assert_eq_test!(klog_module_level(KlogModule::Net), None);
+    assert_test!(klog_module_enabled(KlogModule::Net, KlogLevel::Info));
+    assert_test!(!klog_module_enabled(KlogModule::Net, KlogLevel::Debug));
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    klog,
+    [
+        test_klog_module_from_path,
+        test_klog_module_level_overrides_default,
+    ]
+);
@@ -0,0 +1,516 @@
+//! Chrome trace export for ktrace.
+//!
+//! Converts the events recorded by `slopos_lib::ktrace` into Chrome
+//! `trace_event` JSON, which Perfetto (ui.perfetto.dev) and
+//! `chrome://tracing` open directly:
+//!
+//! - process 0 ("CPUs") has a track per CPU with the task slices, the
+//!   interrupt handlers that ran on it and instant markers for wakeups and
+//!   page faults;
+//! - process 1 ("Tasks") has a track per task with its syscalls.
+//!
+//! [`export_to_path`] writes the JSON to a VFS file and [`export_to_serial`]
+//! streams it over COM1 between [`SERIAL_BEGIN_MARKER`] and
+//! [`SERIAL_END_MARKER`] lines, for when the filesystem is not an option.
+//! Both stop tracing first.
+
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_abi::task::TASK_NAME_MAX_LEN;
+use slopos_fs::vfs::ops::vfs_open;
+use slopos_fs::vfs::traits::VfsError;
+use slopos_lib::ktrace::{
+    KTRACE_NO_TASK, KtraceEvent, KtraceKind, ktrace_dropped, ktrace_for_each, ktrace_start,
+    ktrace_stop,
+};
+use slopos_lib::ports::serial_write_bytes;
+use slopos_lib::string::cstr_to_str;
+use slopos_lib::{COM1, klog_info};
+
+use crate::scheduler::task::task_find_by_id;
+use crate::syscall::handlers::syscall_lookup;
+
+pub const SERIAL_BEGIN_MARKER: &str = "=== KTRACE JSON BEGIN ===";
+pub const SERIAL_END_MARKER: &str = "=== KTRACE JSON END ===";
+
+const PID_CPUS: u32 = 0;
+const PID_TASKS: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KtraceError {
+    /// Another export or a restart is in progress.
+    Busy,
+    /// The output file could not be opened or written.
+    Fs(VfsError),
+}
+
+/// Serializes start and export: restarting clears the ring under a reader.
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+struct BusyGuard;
+
+impl BusyGuard {
+    fn acquire() -> Result<Self, KtraceError> {
+        if BUSY.swap(true, Ordering::Acquire) {
+            return Err(KtraceError::Busy);
+        }
+        Ok(Self)
+    }
+}
+
+impl Drop for BusyGuard {
+    fn drop(&mut self) {
+        BUSY.store(false, Ordering::Release);
+    }
+}
+
+/// Task name lookup used for track and slice names.
+pub type TaskNameFn<'a> = &'a dyn Fn(u32) -> Option<[u8; TASK_NAME_MAX_LEN]>;
+
+#[derive(Clone, Copy, Default)]
+struct CpuTrack {
+    seen: bool,
+    /// Task running since `since`, `KTRACE_NO_TASK` before the first switch.
+    task: u32,
+    since: u64,
+    irq_depth: u32,
+}
+
+struct TaskTrack {
+    id: u32,
+    syscall_depth: u32,
+}
+
+/// Streaming converter from ktrace events to Chrome trace JSON.
+pub struct ChromeTrace<'a, W: Write> {
+    out: &'a mut W,
+    task_name: TaskNameFn<'a>,
+    cpus: Vec<CpuTrack>,
+    tasks: Vec<TaskTrack>,
+    first_ts: Option<u64>,
+    last_ts: u64,
+    need_comma: bool,
+}
+
+/// Write `ns` as microseconds with nanosecond precision.
+fn write_us(out: &mut impl Write, ns: u64) -> fmt::Result {
+    write!(out, "{}.{:03}", ns / 1000, ns % 1000)
+}
+
+/// Write `bytes` (up to the first NUL) as a JSON string literal.
+fn write_json_str(out: &mut impl Write, bytes: &[u8]) -> fmt::Result {
+    out.write_char('"')?;
+    for &b in bytes.iter().take_while(|&&b| b != 0) {
+        match b {
+            b'"' => out.write_str("\\\"")?,
+            b'\\' => out.write_str("\\\\")?,
+            0x20..=0x7E => out.write_char(b as char)?,
+            _ => write!(out, "\\u{:04x}", b)?,
+        }
+    }
+    out.write_char('"')
+}
+
+fn syscall_name(sysno: u32) -> &'static str {
+    let entry = syscall_lookup(sysno as u64);
+    if entry.is_null() {
+        return "unknown";
+    }
+    unsafe { cstr_to_str((*entry).name) }
+}
+
+impl<'a, W: Write> ChromeTrace<'a, W> {
+    /// Open the JSON document.
+    pub fn begin(out: &'a mut W, task_name: TaskNameFn<'a>) -> Result<Self, fmt::Error> {
+        out.write_str("{\"displayTimeUnit\":\"ns\",\"traceEvents\":[\n")?;
+        Ok(Self {
+            out,
+            task_name,
+            cpus: Vec::new(),
+            tasks: Vec::new(),
+            first_ts: None,
+            last_ts: 0,
+            need_comma: false,
+        })
+    }
+
+    fn open_record(&mut self) -> fmt::Result {
+        if self.need_comma {
+            self.out.write_str(",\n")?;
+        }
+        self.need_comma = true;
+        self.out.write_char('{')
+    }
+
+    fn write_task_label(&mut self, task: u32) -> fmt::Result {
+        match (self.task_name)(task) {
+            Some(name) if name[0] != 0 => write_json_str(self.out, &name),
+            _ => write!(self.out, "\"task {}\"", task),
+        }
+    }
+
+    fn cpu(&mut self, cpu: u8) -> &mut CpuTrack {
+        let idx = cpu as usize;
+        if self.cpus.len() <= idx {
+            self.cpus.resize(idx + 1, CpuTrack::default());
+        }
+        &mut self.cpus[idx]
+    }
+
+    fn task(&mut self, id: u32) -> &mut TaskTrack {
+        let pos = match self.tasks.iter().position(|t| t.id == id) {
+            Some(pos) => pos,
+            None => {
+                self.tasks.push(TaskTrack {
+                    id,
+                    syscall_depth: 0,
+                });
+                self.tasks.len() - 1
+            }
+        };
+        &mut self.tasks[pos]
+    }
+
+    /// Emit the slice for the task that ran on `cpu` until `end`.
+    fn close_slice(&mut self, cpu: u8, end: u64) -> fmt::Result {
+        let track = *self.cpu(cpu);
+        if track.task == KTRACE_NO_TASK {
+            return Ok(());
+        }
+        self.task(track.task);
+        self.open_record()?;
+        self.out.write_str("\"name\":")?;
+        self.write_task_label(track.task)?;
+        self.out
+            .write_str(",\"cat\":\"sched\",\"ph\":\"X\",\"ts\":")?;
+        write_us(self.out, track.since)?;
+        self.out.write_str(",\"dur\":")?;
+        write_us(self.out, end.saturating_sub(track.since))?;
+        write!(
+            self.out,
+            ",\"pid\":{},\"tid\":{},\"args\":{{\"task\":{}}}}}",
+            PID_CPUS, cpu, track.task
+        )
+    }
+
+    fn duration_edge(
+        &mut self,
+        cat: &str,
+        name: fmt::Arguments<'_>,
+        begin: bool,
+        ts: u64,
+        pid: u32,
+        tid: u32,
+    ) -> fmt::Result {
+        self.open_record()?;
+        write!(
+            self.out,
+            "\"name\":\"{}\",\"cat\":\"{}\",\"ph\":",
+            name, cat
+        )?;
+        self.out.write_str(if begin { "\"B\"" } else { "\"E\"" })?;
+        self.out.write_str(",\"ts\":")?;
+        write_us(self.out, ts)?;
+        write!(self.out, ",\"pid\":{},\"tid\":{}}}", pid, tid)
+    }
+
+    /// An instant event on `cpu`'s track; the caller writes the `args`
+    /// object's fields and closes the record.
+    fn instant(&mut self, name: &str, cat: &str, ts: u64, cpu: u8) -> fmt::Result {
+        self.open_record()?;
+        write!(
+            self.out,
+            "\"name\":\"{}\",\"cat\":\"{}\",\"ph\":\"i\",\"s\":\"t\",\"ts\":",
+            name, cat
+        )?;
+        write_us(self.out, ts)?;
+        write!(
+            self.out,
+            ",\"pid\":{},\"tid\":{},\"args\":{{",
+            PID_CPUS, cpu
+        )
+    }
+
+    /// Convert one event.  Events must arrive in recording order.
+    pub fn event(&mut self, event: &KtraceEvent) -> fmt::Result {
+        let ts = event.timestamp_ns;
+        let first_ts = *self.first_ts.get_or_insert(ts);
+        self.last_ts = self.last_ts.max(ts);
+        let track = self.cpu(event.cpu);
+        if !track.seen {
+            *track = CpuTrack {
+                seen: true,
+                task: KTRACE_NO_TASK,
+                since: first_ts,
+                irq_depth: 0,
+            };
+        }
+
+        match event.kind {
+            KtraceKind::SchedSwitch => {
+                // Before the first switch the outgoing task has been running
+                // since the trace started.
+                if self.cpu(event.cpu).task == KTRACE_NO_TASK {
+                    self.cpu(event.cpu).task = event.arg0;
+                }
+                self.close_slice(event.cpu, ts)?;
+                let track = self.cpu(event.cpu);
+                track.task = event.arg1;
+                track.since = ts;
+                Ok(())
+            }
+            KtraceKind::IrqEnter | KtraceKind::IrqExit => {
+                let begin = event.kind == KtraceKind::IrqEnter;
+                let track = self.cpu(event.cpu);
+                if begin {
+                    track.irq_depth += 1;
+                } else if track.irq_depth == 0 {
+                    // Entered before tracing started.
+                    return Ok(());
+                } else {
+                    track.irq_depth -= 1;
+                }
+                self.duration_edge(
+                    "irq",
+                    format_args!("irq 0x{:02x}", event.arg0),
+                    begin,
+                    ts,
+                    PID_CPUS,
+                    event.cpu as u32,
+                )
+            }
+            KtraceKind::SyscallEnter | KtraceKind::SyscallExit => {
+                let begin = event.kind == KtraceKind::SyscallEnter;
+                let task = self.task(event.arg1);
+                if begin {
+                    task.syscall_depth += 1;
+                } else if task.syscall_depth == 0 {
+                    return Ok(());
+                } else {
+                    task.syscall_depth -= 1;
+                }
+                self.duration_edge(
+                    "syscall",
+                    format_args!("{}", syscall_name(event.arg0)),
+                    begin,
+                    ts,
+                    PID_TASKS,
+                    event.arg1,
+                )
+            }
+            KtraceKind::SchedWakeup => {
+                self.task(event.arg0);
+                self.instant("wakeup", "sched", ts, event.cpu)?;
+                write!(
+                    self.out,
+                    "\"task\":{},\"waker\":{}}}}}",
+                    event.arg0, event.arg1
+                )
+            }
+            KtraceKind::PageFault => {
+                self.instant("page fault", "mm", ts, event.cpu)?;
+                write!(
+                    self.out,
+                    "\"addr\":\"0x{:x}\",\"error\":\"0x{:x}\",\"task\":{}}}}}",
+                    event.addr, event.arg0, event.arg1
+                )
+            }
+        }
+    }
+
+    fn metadata(&mut self, kind: &str, pid: u32, tid: Option<u32>) -> fmt::Result {
+        self.open_record()?;
+        write!(
+            self.out,
+            "\"name\":\"{}\",\"ph\":\"M\",\"pid\":{}",
+            kind, pid
+        )?;
+        if let Some(tid) = tid {
+            write!(self.out, ",\"tid\":{}", tid)?;
+        }
+        self.out.write_str(",\"args\":{\"name\":")
+    }
+
+    /// Close open slices at the last event, name every track and close the
+    /// JSON document.
+    pub fn finish(mut self) -> fmt::Result {
+        let last_ts = self.last_ts;
+        for cpu in 0..self.cpus.len() {
+            if self.cpus[cpu].seen {
+                self.close_slice(cpu as u8, last_ts)?;
+            }
+        }
+
+        self.metadata("process_name", PID_CPUS, None)?;
+        self.out.write_str("\"CPUs\"}}")?;
+        for cpu in 0..self.cpus.len() {
+            if self.cpus[cpu].seen {
+                self.metadata("thread_name", PID_CPUS, Some(cpu as u32))?;
+                write!(self.out, "\"CPU {}\"}}}}", cpu)?;
+            }
+        }
+        self.metadata("process_name", PID_TASKS, None)?;
+        self.out.write_str("\"Tasks\"}}")?;
+        for i in 0..self.tasks.len() {
+            let id = self.tasks[i].id;
+            self.metadata("thread_name", PID_TASKS, Some(id))?;
+            self.write_task_label(id)?;
+            self.out.write_str("}}")?;
+        }
+        self.out.write_str("\n]}\n")
+    }
+}
+
+fn kernel_task_name(task_id: u32) -> Option<[u8; TASK_NAME_MAX_LEN]> {
+    let task = task_find_by_id(task_id);
+    if task.is_null() {
+        return None;
+    }
+    Some(unsafe { (*task).name })
+}
+
+/// Stop tracing and write everything recorded to `out`.  Returns the number
+/// of events converted.
+fn export(out: &mut impl Write) -> Result<usize, fmt::Error> {
+    ktrace_stop();
+    let mut trace = ChromeTrace::begin(out, &kernel_task_name)?;
+    let mut result = Ok(());
+    let count = ktrace_for_each(|event| {
+        if result.is_ok() {
+            result = trace.event(event);
+        }
+    })
+    .unwrap_or(0);
+    result?;
+    trace.finish()?;
+    Ok(count)
+}
+
+fn log_export(count: usize, destination: &str) {
+    let dropped = ktrace_dropped();
+    if dropped > 0 {
+        klog_info!(
+            "ktrace: exported {} events to {} ({} older events overwritten)",
+            count,
+            destination,
+            dropped
+        );
+    } else {
+        klog_info!("ktrace: exported {} events to {}", count, destination);
+    }
+}
+
+/// Buffered `fmt::Write` sink for a VFS file.
+struct FileSink {
+    handle: slopos_fs::vfs::ops::VfsHandle,
+    offset: u64,
+    buf: [u8; 512],
+    len: usize,
+    error: Option<VfsError>,
+}
+
+impl FileSink {
+    fn flush(&mut self) -> fmt::Result {
+        let mut done = 0;
+        while done < self.len && self.error.is_none() {
+            match self.handle.write(self.offset, &self.buf[done..self.len]) {
+                Ok(0) => self.error = Some(VfsError::NoSpace),
+                Ok(n) => {
+                    done += n;
+                    self.offset += n as u64;
+                }
+                Err(err) => self.error = Some(err),
+            }
+        }
+        self.len = 0;
+        if self.error.is_some() {
+            Err(fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Write for FileSink {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for chunk in s.as_bytes().chunks(self.buf.len()) {
+            if self.len + chunk.len() > self.buf.len() {
+                self.flush()?;
+            }
+            self.buf[self.len..self.len + chunk.len()].copy_from_slice(chunk);
+            self.len += chunk.len();
+        }
+        Ok(())
+    }
+}
+
+struct SerialSink;
+
+impl Write for SerialSink {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        unsafe { serial_write_bytes(COM1, s.as_bytes()) };
+        Ok(())
+    }
+}
+
+/// Clear the ring and start tracing.
+pub fn start() -> Result<(), KtraceError> {
+    let _busy = BusyGuard::acquire()?;
+    ktrace_start();
+    Ok(())
+}
+
+/// Stop tracing and pass every recorded event, merged across CPUs and
+/// oldest first, to `f` along with its index.  Returns the number of
+/// events.
+pub fn read(mut f: impl FnMut(usize, &KtraceEvent)) -> Result<usize, KtraceError> {
+    let _busy = BusyGuard::acquire()?;
+    ktrace_stop();
+    let mut index = 0;
+    ktrace_for_each(|event| {
+        f(index, event);
+        index += 1;
+    });
+    Ok(index)
+}
+
+/// Stop tracing and write the trace as JSON to the absolute `path`,
+/// replacing its contents.  Returns the number of events written.
+pub fn export_to_path(path: &[u8]) -> Result<usize, KtraceError> {
+    let _busy = BusyGuard::acquire()?;
+    let handle = vfs_open(path, true).map_err(KtraceError::Fs)?;
+    handle
+        .fs
+        .truncate(handle.inode, 0)
+        .map_err(KtraceError::Fs)?;
+    let mut sink = FileSink {
+        handle,
+        offset: 0,
+        buf: [0; 512],
+        len: 0,
+        error: None,
+    };
+    let result = export(&mut sink).and_then(|count| sink.flush().map(|()| count));
+    match (result, sink.error) {
+        (Ok(count), None) => {
+            log_export(count, "file");
+            Ok(count)
+        }
+        (_, Some(err)) => Err(KtraceError::Fs(err)),
+        (Err(_), None) => Err(KtraceError::Fs(VfsError::IoError)),
+    }
+}
+
+/// Stop tracing and stream the trace as JSON over COM1.  Returns the number
+/// of events written.
+pub fn export_to_serial() -> Result<usize, KtraceError> {
+    let _busy = BusyGuard::acquire()?;
+    let mut sink = SerialSink;
+    let _ = writeln!(sink, "\n{}", SERIAL_BEGIN_MARKER);
+    let count = export(&mut sink).map_err(|_| KtraceError::Fs(VfsError::IoError))?;
+    let _ = writeln!(sink, "{}", SERIAL_END_MARKER);
+    log_export(count, "serial");
+    Ok(count)
+}
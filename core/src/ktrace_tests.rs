@@ -0,0 +1,270 @@
+//! ktrace tests: the event rings, Chrome trace conversion, file export and
+//! reading events back.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use slopos_abi::syscall::SYSCALL_KWARN_STATS;
+use slopos_abi::task::TASK_NAME_MAX_LEN;
+use slopos_fs::vfs::ops::{vfs_open, vfs_unlink};
+use slopos_lib::ktrace::{
+    KtraceCpuRings, KtraceEvent, KtraceKind, KtraceRing, ktrace_is_enabled, ktrace_record,
+    ktrace_record_page_fault,
+};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::ktrace::{self, ChromeTrace};
+
+fn event(timestamp_ns: u64, kind: KtraceKind, cpu: u8, arg0: u32, arg1: u32) -> KtraceEvent {
+    KtraceEvent {
+        timestamp_ns,
+        kind,
+        cpu,
+        arg0,
+        arg1,
+        addr: 0,
+    }
+}
+
+fn collect<const N: usize>(ring: &KtraceRing<N>) -> Option<Vec<u32>> {
+    let mut seen = Vec::new();
+    ring.for_each(|e| seen.push(e.arg0))?;
+    Some(seen)
+}
+
+pub fn test_ktrace_ring_keeps_newest() -> TestResult {
+    let ring = KtraceRing::<4>::new();
+    ring.record(event(1, KtraceKind::IrqEnter, 0, 99, 0));
+    assert_eq_test!(collect(&ring), Some(Vec::new()), "recorded while stopped");
+
+    ring.start();
+    for i in 0..6 {
+        ring.record(event(i as u64, KtraceKind::IrqEnter, 0, i, 0));
+    }
+    assert_test!(collect(&ring).is_none(), "read while recording");
+    ring.stop();
+    assert_eq_test!(
+        collect(&ring),
+        Some(alloc::vec![2, 3, 4, 5]),
+        "oldest first"
+    );
+    assert_eq_test!(ring.dropped(), 2, "overwritten events");
+
+    ring.start();
+    ring.record(event(10, KtraceKind::IrqExit, 1, 7, 0));
+    ring.stop();
+    assert_eq_test!(collect(&ring), Some(alloc::vec![7]), "start did not clear");
+    assert_eq_test!(ring.dropped(), 0);
+    pass!()
+}
+
+pub fn test_ktrace_cpu_rings_merge() -> TestResult {
+    let rings = KtraceCpuRings::<2, 4>::new();
+    rings.start();
+    // CPU 2 shares CPU 0's ring.
+    for (ts, cpu) in [(1, 0), (2, 1), (3, 1), (4, 0), (6, 2), (7, 1), (9, 0)] {
+        rings.record(event(ts, KtraceKind::IrqEnter, cpu, ts as u32, 0));
+    }
+    let mut seen = Vec::new();
+    assert_test!(rings.for_each(|e| seen.push(e.arg0)).is_none());
+    rings.stop();
+    assert_eq_test!(rings.for_each(|e| seen.push(e.arg0)), Some(7));
+    assert_eq_test!(
+        seen.as_slice(),
+        &[1, 2, 3, 4, 6, 7, 9][..],
+        "merged by time"
+    );
+
+    rings.start();
+    for ts in 0..6 {
+        rings.record(event(ts, KtraceKind::IrqExit, 1, ts as u32, 0));
+    }
+    rings.record(event(3, KtraceKind::IrqExit, 0, 100, 0));
+    rings.stop();
+    seen.clear();
+    rings.for_each(|e| seen.push(e.arg0));
+    assert_eq_test!(
+        seen.as_slice(),
+        &[2, 100, 3, 4, 5][..],
+        "ties go to the lower ring"
+    );
+    assert_eq_test!(rings.dropped(), 2);
+    pass!()
+}
+
+fn test_task_name(task: u32) -> Option<[u8; TASK_NAME_MAX_LEN]> {
+    let mut name = [0u8; TASK_NAME_MAX_LEN];
+    let label: &[u8] = match task {
+        5 => b"idle",
+        7 => b"comp\"ositor",
+        _ => return None,
+    };
+    name[..label.len()].copy_from_slice(label);
+    Some(name)
+}
+
+pub fn test_ktrace_chrome_conversion() -> TestResult {
+    let sysno = SYSCALL_KWARN_STATS as u32;
+    let events = [
+        // Exit of an IRQ entered before the trace began: dropped.
+        event(500, KtraceKind::IrqExit, 0, 0x20, 0),
+        event(1_000, KtraceKind::SchedSwitch, 0, 5, 7),
+        event(1_500, KtraceKind::IrqEnter, 0, 0x21, 0),
+        event(1_750, KtraceKind::IrqExit, 0, 0x21, 0),
+        event(2_000, KtraceKind::SyscallEnter, 0, sysno, 7),
+        event(3_250, KtraceKind::SyscallExit, 0, sysno, 7),
+        event(4_000, KtraceKind::SchedWakeup, 0, 9, 7),
+        event(5_000, KtraceKind::SchedSwitch, 0, 7, 9),
+        KtraceEvent {
+            addr: 0x40_1000,
+            ..event(5_500, KtraceKind::PageFault, 0, 0x6, 9)
+        },
+        event(6_000, KtraceKind::SyscallExit, 1, sysno, 9),
+    ];
+
+    let mut json = String::new();
+    let Ok(mut trace) = ChromeTrace::begin(&mut json, &test_task_name) else {
+        return fail!("begin failed");
+    };
+    for e in &events {
+        if trace.event(e).is_err() {
+            return fail!("event failed");
+        }
+    }
+    if trace.finish().is_err() {
+        return fail!("finish failed");
+    }
+
+    let has = |needle: &str| json.contains(needle);
+    assert_test!(json.starts_with("{\"displayTimeUnit\":\"ns\",\"traceEvents\":["));
+    assert_test!(json.ends_with("]}\n"), "document not closed");
+    assert_test!(
+        has(
+            "{\"name\":\"idle\",\"cat\":\"sched\",\"ph\":\"X\",\"ts\":0.500,\"dur\":0.500,\"pid\":0,\"tid\":0,\"args\":{\"task\":5}}"
+        ),
+        "slice before the first switch"
+    );
+    assert_test!(
+        has(
+            "\"name\":\"comp\\\"ositor\",\"cat\":\"sched\",\"ph\":\"X\",\"ts\":1.000,\"dur\":4.000"
+        ),
+        "escaped task slice"
+    );
+    assert_test!(
+        has("\"name\":\"task 9\",\"cat\":\"sched\",\"ph\":\"X\",\"ts\":5.000,\"dur\":1.000"),
+        "open slice not closed at the last event"
+    );
+    assert_test!(has(
+        "{\"name\":\"irq 0x21\",\"cat\":\"irq\",\"ph\":\"B\",\"ts\":1.500,\"pid\":0,\"tid\":0}"
+    ));
+    assert_test!(has("\"ph\":\"E\",\"ts\":1.750,\"pid\":0,\"tid\":0}"));
+    assert_test!(!has("irq 0x20"), "unmatched IRQ exit emitted");
+    assert_test!(has(
+        "{\"name\":\"kwarn_stats\",\"cat\":\"syscall\",\"ph\":\"B\",\"ts\":2.000,\"pid\":1,\"tid\":7}"
+    ));
+    assert_test!(has("\"ph\":\"E\",\"ts\":3.250,\"pid\":1,\"tid\":7}"));
+    assert_test!(
+        !has("\"ts\":6.000,\"pid\":1"),
+        "unmatched syscall exit emitted"
+    );
+    assert_test!(has(
+        "{\"name\":\"wakeup\",\"cat\":\"sched\",\"ph\":\"i\",\"s\":\"t\",\"ts\":4.000,\"pid\":0,\"tid\":0,\"args\":{\"task\":9,\"waker\":7}}"
+    ));
+    assert_test!(has(
+        "{\"name\":\"page fault\",\"cat\":\"mm\",\"ph\":\"i\",\"s\":\"t\",\"ts\":5.500,\"pid\":0,\"tid\":0,\"args\":{\"addr\":\"0x401000\",\"error\":\"0x6\",\"task\":9}}"
+    ));
+    assert_test!(has(
+        "\"ph\":\"M\",\"pid\":0,\"tid\":1,\"args\":{\"name\":\"CPU 1\"}}"
+    ));
+    assert_test!(has(
+        "\"ph\":\"M\",\"pid\":1,\"tid\":7,\"args\":{\"name\":\"comp\\\"ositor\"}}"
+    ));
+    pass!()
+}
+
+pub fn test_ktrace_export_to_file() -> TestResult {
+    const PATH: &[u8] = b"/tmp/ktrace_test.json";
+    if ktrace::start().is_err() {
+        return fail!("tracer busy");
+    }
+    assert_test!(ktrace_is_enabled());
+    ktrace_record(KtraceKind::IrqEnter, 0x42, 0);
+    ktrace_record(KtraceKind::IrqExit, 0x42, 0);
+
+    let result = ktrace::export_to_path(PATH);
+    assert_test!(!ktrace_is_enabled(), "export left tracing on");
+    let count = match result {
+        Ok(count) => count,
+        Err(err) => return fail!("export failed: {:?}", err),
+    };
+    assert_test!(count >= 2, "exported {} events", count);
+
+    let Ok(handle) = vfs_open(PATH, false) else {
+        return fail!("trace file missing");
+    };
+    let size = handle.size().unwrap_or(0) as usize;
+    let mut head = [0u8; 17];
+    let mut tail = [0u8; 3];
+    let read_ok = handle.read(0, &mut head) == Ok(head.len())
+        && size > tail.len()
+        && handle.read((size - tail.len()) as u64, &mut tail) == Ok(tail.len());
+    let _ = vfs_unlink(PATH);
+    assert_test!(read_ok, "trace file too short ({} bytes)", size);
+    assert_eq_test!(&head, b"{\"displayTimeUnit", "file head");
+    assert_eq_test!(&tail, b"]}\n", "file tail");
+    pass!()
+}
+
+pub fn test_ktrace_read_events() -> TestResult {
+    if ktrace::start().is_err() {
+        return fail!("tracer busy");
+    }
+    ktrace_record(KtraceKind::SchedWakeup, 41, 40);
+    ktrace_record_page_fault(0xdead_b000, 0x7, 41);
+
+    let mut events = Vec::new();
+    let result = ktrace::read(|index, e| events.push((index, *e)));
+    assert_test!(!ktrace_is_enabled(), "read left tracing on");
+    let Ok(count) = result else {
+        return fail!("read failed");
+    };
+    assert_eq_test!(events.len(), count);
+    assert_test!(
+        events.iter().enumerate().all(|(i, (index, _))| i == *index),
+        "indices out of order"
+    );
+    assert_test!(
+        events
+            .windows(2)
+            .all(|w| w[0].1.timestamp_ns <= w[1].1.timestamp_ns),
+        "events not merged by time"
+    );
+    let wakeup = events
+        .iter()
+        .any(|(_, e)| e.kind == KtraceKind::SchedWakeup && e.arg0 == 41 && e.arg1 == 40);
+    assert_test!(wakeup, "wakeup missing");
+    let Some((_, fault)) = events
+        .iter()
+        .find(|(_, e)| e.kind == KtraceKind::PageFault && e.arg1 == 41)
+    else {
+        return fail!("page fault missing");
+    };
+    assert_eq_test!(fault.addr, 0xdead_b000);
+    let record = fault.to_record();
+    assert_eq_test!(record.addr, 0xdead_b000);
+    assert_eq_test!(record.arg0, 0x7);
+    assert_eq_test!(record.kind, KtraceKind::PageFault as u8);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    ktrace,
+    [
+        test_ktrace_ring_keeps_newest,
+        test_ktrace_cpu_rings_merge,
+        test_ktrace_chrome_conversion,
+        test_ktrace_export_to_file,
+        test_ktrace_read_events,
+    ]
+);
@@ -7,15 +7,38 @@ use core::arch::global_asm;
 
 global_asm!(include_str!("../context_switch.s"), options(att_syntax));
 
+pub mod audit;
+#[cfg(feature = "itests")]
+pub mod audit_tests;
+pub mod coredump;
+#[cfg(feature = "itests")]
+pub mod coredump_tests;
+pub mod crash;
+#[cfg(feature = "itests")]
+pub mod crash_tests;
 pub mod driver_hooks;
 pub mod exec;
 pub mod irq;
 #[cfg(feature = "itests")]
 pub mod irq_tests;
 #[cfg(feature = "itests")]
+pub mod klog_tests;
+pub mod ktrace;
+#[cfg(feature = "itests")]
+pub mod ktrace_tests;
+#[cfg(feature = "itests")]
+pub mod lockdep_tests;
+#[cfg(feature = "itests")]
+pub mod lockstat_tests;
+#[cfg(feature = "itests")]
 pub mod msi_tests;
+pub mod perf;
+#[cfg(feature = "itests")]
+pub mod perf_tests;
 pub mod platform;
 pub mod scheduler;
+#[cfg(feature = "itests")]
+pub mod stack_protector_tests;
 #[macro_use]
 pub mod syscall;
 
@@ -24,6 +47,7 @@ pub use scheduler::context_tests;
 pub use scheduler::fate_api;
 pub use scheduler::ffi_boundary;
 pub use scheduler::kthread;
+pub use scheduler::ktimer;
 pub use scheduler::per_cpu;
 #[cfg(feature = "itests")]
 pub use scheduler::sched_tests;
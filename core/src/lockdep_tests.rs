@@ -0,0 +1,112 @@
+//! lockdep tests: the order graph and the per-CPU held stack.  They pass
+//! with and without the `lockdep` feature; without it no order is ever
+//! recorded.  Every test uses its own classes, since recorded orders are
+//! never forgotten.
+
+use slopos_lib::lockdep::{lockdep_depends_on, lockdep_enabled, lockdep_held_count};
+use slopos_lib::lockstat::LockClass;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{IrqMutex, IrqRwLock, assert_eq_test, assert_test, fail, pass};
+
+pub fn test_lockdep_records_order() -> TestResult {
+    static OUTER: LockClass = LockClass::new("lockdep_test_outer");
+    static INNER: LockClass = LockClass::new("lockdep_test_inner");
+    static OUTER_LOCK: IrqMutex<u32> = IrqMutex::with_class(&OUTER, 0);
+    static INNER_LOCK: IrqMutex<u32> = IrqMutex::with_class(&INNER, 0);
+
+    {
+        let mut outer = OUTER_LOCK.lock();
+        let mut inner = INNER_LOCK.lock();
+        *outer += 1;
+        *inner += 1;
+    }
+    assert_eq_test!(
+        lockdep_depends_on(&OUTER, &INNER),
+        lockdep_enabled(),
+        "nested order"
+    );
+    assert_test!(!lockdep_depends_on(&INNER, &OUTER), "reverse order");
+
+    // Taking them again in the same order is fine.
+    let _outer = OUTER_LOCK.lock();
+    let _inner = INNER_LOCK.lock();
+    pass!()
+}
+
+pub fn test_lockdep_transitive_order() -> TestResult {
+    static A: LockClass = LockClass::new("lockdep_test_a");
+    static B: LockClass = LockClass::new("lockdep_test_b");
+    static C: LockClass = LockClass::new("lockdep_test_c");
+    static LOCK_A: IrqMutex<()> = IrqMutex::with_class(&A, ());
+    static LOCK_B: IrqRwLock<()> = IrqRwLock::with_class(&B, ());
+    static LOCK_C: IrqMutex<()> = IrqMutex::with_class(&C, ());
+
+    {
+        let _a = LOCK_A.lock();
+        let _b = LOCK_B.read();
+    }
+    {
+        let _b = LOCK_B.write();
+        let _c = LOCK_C.lock();
+    }
+    assert_eq_test!(lockdep_depends_on(&A, &C), lockdep_enabled(), "a -> b -> c");
+    assert_test!(!lockdep_depends_on(&C, &A), "c -> a");
+    pass!()
+}
+
+pub fn test_lockdep_trylock_adds_no_order() -> TestResult {
+    static HELD: LockClass = LockClass::new("lockdep_test_held");
+    static TRIED: LockClass = LockClass::new("lockdep_test_tried");
+    static HELD_LOCK: IrqMutex<()> = IrqMutex::with_class(&HELD, ());
+    static TRIED_LOCK: IrqMutex<()> = IrqMutex::with_class(&TRIED, ());
+
+    let base = lockdep_held_count();
+    let held = HELD_LOCK.lock();
+    let Some(tried) = TRIED_LOCK.try_lock() else {
+        return fail!("try_lock failed on a free lock");
+    };
+    let expected = if lockdep_enabled() { base + 2 } else { base };
+    assert_eq_test!(lockdep_held_count(), expected, "held while nested");
+    assert_test!(
+        !lockdep_depends_on(&HELD, &TRIED),
+        "try_lock recorded an order"
+    );
+
+    // Out-of-order release still leaves the stack balanced.
+    drop(held);
+    drop(tried);
+    assert_eq_test!(lockdep_held_count(), base, "held after release");
+    pass!()
+}
+
+pub fn test_lockdep_same_class_nests() -> TestResult {
+    static QUEUE: LockClass = LockClass::new("lockdep_test_queue");
+    static QUEUES: [IrqMutex<u32>; 2] = [
+        IrqMutex::with_class(&QUEUE, 0),
+        IrqMutex::with_class(&QUEUE, 1),
+    ];
+
+    {
+        let _first = QUEUES[0].lock();
+        let _second = QUEUES[1].lock();
+    }
+    {
+        let _second = QUEUES[1].lock();
+        let _first = QUEUES[0].lock();
+    }
+    assert_test!(
+        !lockdep_depends_on(&QUEUE, &QUEUE),
+        "class ordered after itself"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    lockdep,
+    [
+        test_lockdep_records_order,
+        test_lockdep_transitive_order,
+        test_lockdep_trylock_adds_no_order,
+        test_lockdep_same_class_nests,
+    ]
+);
@@ -0,0 +1,76 @@
+//! lockstat tests: per-class counters and the class registry.  They pass
+//! with and without the `lockstat` feature; without it every counter must
+//! stay zero and no class is listed.
+
+use slopos_abi::syscall::LOCK_CLASS_NAME_LEN;
+use slopos_lib::IrqMutex;
+use slopos_lib::lockstat::{LockClass, lockstat_enabled, lockstat_for_each};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+static TEST_LOCK_CLASS: LockClass = LockClass::new("lockstat_test");
+static TEST_LOCK: IrqMutex<u32> = IrqMutex::with_class(&TEST_LOCK_CLASS, 0);
+
+fn is_listed(class: &'static LockClass) -> bool {
+    let mut found = false;
+    lockstat_for_each(|c| found |= core::ptr::eq(c, class));
+    found
+}
+
+pub fn test_lockstat_counts_acquisitions() -> TestResult {
+    TEST_LOCK_CLASS.reset();
+    for _ in 0..3 {
+        *TEST_LOCK.lock() += 1;
+    }
+    match TEST_LOCK.try_lock() {
+        Some(mut guard) => *guard += 1,
+        None => return fail!("try_lock failed on a free lock"),
+    }
+
+    let stat = TEST_LOCK_CLASS.snapshot();
+    assert_eq_test!(&stat.name[..14], b"lockstat_test\0", "class name");
+    if !lockstat_enabled() {
+        assert_eq_test!(stat.acquisitions, 0, "counted without lockstat");
+        assert_test!(!is_listed(&TEST_LOCK_CLASS), "listed without lockstat");
+        return pass!();
+    }
+    assert_eq_test!(stat.acquisitions, 4, "acquisitions");
+    assert_eq_test!(stat.contended, 0, "uncontended lock counted as contended");
+    assert_eq_test!(stat.total_wait_ns, 0, "wait charged without contention");
+    assert_test!(is_listed(&TEST_LOCK_CLASS), "class not registered");
+    pass!()
+}
+
+pub fn test_lockstat_reset_keeps_class() -> TestResult {
+    drop(TEST_LOCK.lock());
+    TEST_LOCK_CLASS.reset();
+    let stat = TEST_LOCK_CLASS.snapshot();
+    assert_eq_test!(stat.acquisitions, 0, "reset left acquisitions");
+    assert_eq_test!(stat.total_held_ns, 0, "reset left held time");
+    assert_eq_test!(
+        is_listed(&TEST_LOCK_CLASS),
+        lockstat_enabled(),
+        "reset changed registration"
+    );
+    pass!()
+}
+
+pub fn test_lockstat_truncates_long_names() -> TestResult {
+    static LONG: LockClass = LockClass::new("a_lock_class_name_longer_than_the_abi_field");
+    let stat = LONG.snapshot();
+    assert_eq_test!(stat.name[LOCK_CLASS_NAME_LEN - 1], 0, "name not terminated");
+    assert_eq_test!(
+        &stat.name[..LOCK_CLASS_NAME_LEN - 1],
+        &LONG.name().as_bytes()[..LOCK_CLASS_NAME_LEN - 1]
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    lockstat,
+    [
+        test_lockstat_counts_acquisitions,
+        test_lockstat_reset_keeps_class,
+        test_lockstat_truncates_long_names,
+    ]
+);
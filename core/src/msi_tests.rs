@@ -11,8 +11,9 @@ use slopos_lib::testing::TestResult;
 use slopos_lib::{InterruptFrame, assert_eq_test, assert_ne_test, assert_test, klog_info};
 
 use crate::irq::{
-    msi_alloc_vector, msi_allocated_count, msi_free_vector, msi_register_handler,
-    msi_unregister_handler, msi_vector_is_allocated,
+    msi_alloc_vector, msi_alloc_vectors, msi_allocated_count, msi_free_vector, msi_free_vectors,
+    msi_register_handler, msi_set_affinity, msi_unregister_handler, msi_vector_info,
+    msi_vector_is_allocated,
 };
 use crate::platform::idt_get_gate;
 
@@ -375,6 +376,78 @@ pub fn test_msi_double_register_same_vector() -> TestResult {
     TestResult::Pass
 }
 
+pub fn test_msi_alloc_vectors_all_or_nothing() -> TestResult {
+    let baseline = msi_allocated_count();
+    let mut batch = [0u8; 3];
+    assert_test!(msi_alloc_vectors(&mut batch), "batch allocation failed");
+    assert_test!(
+        batch[0] != batch[1] && batch[1] != batch[2] && batch[0] != batch[2],
+        "batch handed out a vector twice"
+    );
+    assert_eq_test!(msi_allocated_count(), baseline + 3, "batch count");
+    msi_free_vectors(&batch);
+    assert_eq_test!(msi_allocated_count(), baseline, "batch not freed");
+
+    // Leave exactly one vector free, then ask for two.
+    let mut held = alloc::vec::Vec::new();
+    while let Some(v) = msi_alloc_vector() {
+        held.push(v);
+    }
+    let Some(spare) = held.pop() else {
+        klog_info!("MSI_TEST: no vectors free to exhaust");
+        return TestResult::Fail;
+    };
+    msi_free_vector(spare);
+    let before = msi_allocated_count();
+    let mut pair = [0u8; 2];
+    let ok = msi_alloc_vectors(&mut pair);
+    let after = msi_allocated_count();
+    msi_free_vectors(&held);
+
+    assert_test!(!ok, "batch larger than the free range succeeded");
+    assert_eq_test!(pair, [0, 0], "failed batch left vectors behind");
+    assert_eq_test!(after, before, "failed batch leaked a vector");
+    assert_eq_test!(msi_allocated_count(), baseline, "exhaustion cleanup");
+    TestResult::Pass
+}
+
+pub fn test_msi_vector_info_tracks_owner_and_affinity() -> TestResult {
+    let vector = match msi_alloc_vector() {
+        Some(v) => v,
+        None => {
+            klog_info!("MSI_TEST: allocator returned None");
+            return TestResult::Fail;
+        }
+    };
+
+    let Some(fresh) = msi_vector_info(vector) else {
+        msi_free_vector(vector);
+        klog_info!("MSI_TEST: no info for an allocated vector");
+        return TestResult::Fail;
+    };
+    assert_test!(!fresh.registered, "fresh vector reports a handler");
+
+    assert_test!(msi_set_affinity(vector, 3), "set affinity failed");
+    msi_register_handler(vector, dummy_msi_handler, ptr::null_mut(), 0x0001_0203);
+    let info = msi_vector_info(vector);
+    msi_unregister_handler(vector);
+    msi_free_vector(vector);
+
+    let Some(info) = info else {
+        klog_info!("MSI_TEST: info vanished after registration");
+        return TestResult::Fail;
+    };
+    assert_test!(info.registered, "handler not reported");
+    assert_eq_test!(info.device_bdf, 0x0001_0203, "owner not recorded");
+    assert_eq_test!(info.target_apic, 3, "registration reset the affinity");
+    assert_test!(msi_vector_info(vector).is_none(), "info for a freed vector");
+    assert_test!(
+        !msi_set_affinity(vector, 1),
+        "affinity accepted for a freed vector"
+    );
+    TestResult::Pass
+}
+
 pub fn test_msi_idt_entries_present() -> TestResult {
     for vector in MSI_SAMPLE_VECTORS {
         let entry = match load_idt_entry(vector) {
@@ -519,6 +592,7 @@ slopos_lib::define_test_suite!(
         test_msi_free_invalid_vector_no_panic,
         test_msi_free_unallocated_no_panic,
         test_msi_alloc_skips_syscall_vector,
+        test_msi_alloc_vectors_all_or_nothing,
     ]
 );
 
@@ -533,6 +607,7 @@ slopos_lib::define_test_suite!(
         test_msi_register_with_context,
         test_msi_register_with_device_bdf,
         test_msi_double_register_same_vector,
+        test_msi_vector_info_tracks_owner_and_affinity,
     ]
 );
 
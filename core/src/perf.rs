@@ -0,0 +1,560 @@
+//! Per-task hardware performance counters (`SYSCALL_PERF_*`).
+//!
+//! A counter is a slot in a global table holding a running total for one
+//! event.  Tasks point at the counters they feed through the links in
+//! their [`TaskPerf`].  On every context switch the outgoing task's
+//! hardware counts are added to its counters and the incoming task's
+//! events are programmed from zero, so a counter only sees its own tasks
+//! however many share the CPU.  Links marked for inheritance are copied to
+//! tasks spawned, forked or cloned afterwards.
+//!
+//! Links are written by whoever opens or inherits a counter and read by the
+//! CPU running the task, so every field is atomic and a link is published
+//! by storing its counter id last.  Closing a counter bumps its generation;
+//! links still naming the old generation are dropped the next time their
+//! task is switched.
+//!
+//! Nothing here runs until a counter is open: the switch hook is one
+//! relaxed load otherwise.
+
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+use slopos_abi::perf::{
+    PERF_EVENT_BRANCH_MISSES, PERF_EVENT_BRANCHES, PERF_EVENT_CACHE_MISSES,
+    PERF_EVENT_CACHE_REFERENCES, PERF_EVENT_CYCLES, PERF_EVENT_INSTRUCTIONS,
+    PERF_FLAG_CHILDREN_ONLY, PERF_FLAG_INHERIT, PERF_FLAG_USER_ONLY, PERF_FLAGS_ALL,
+    PERF_MAX_COUNTERS, PerfReading,
+};
+use slopos_abi::task::INVALID_TASK_ID;
+use slopos_lib::cpu::pmu::{
+    ArchEvent, PmuCounter, PmuInfo, pmu_configure, pmu_info, pmu_read, pmu_start, pmu_stop,
+};
+use slopos_lib::preempt::IrqPreemptGuard;
+
+use crate::scheduler::scheduler::scheduler_get_current_task;
+use crate::scheduler::task_struct::Task;
+
+/// Counters one task can feed at once.
+pub const PERF_TASK_LINKS: usize = 8;
+
+const LINK_FREE: u8 = 0xFF;
+/// Taken by a writer that has not published the counter id yet.
+const LINK_CLAIMED: u8 = 0xFE;
+
+/// The task's own events are counted.
+pub(crate) const LINK_COUNTING: u8 = 1 << 0;
+/// Tasks it creates get a counting, inheriting link too.
+pub(crate) const LINK_INHERIT: u8 = 1 << 1;
+
+const HW_NONE: u8 = 0xFF;
+const HW_FIXED: u8 = 0x80;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PerfError {
+    /// No usable PMU, or it cannot count the event.
+    Unsupported,
+    /// Unknown event, flags or counter id.
+    Invalid,
+    /// No free counter, link or hardware counter.
+    Busy,
+}
+
+fn arch_event(event: u32) -> Option<ArchEvent> {
+    Some(match event {
+        PERF_EVENT_CYCLES => ArchEvent::CoreCycles,
+        PERF_EVENT_INSTRUCTIONS => ArchEvent::Instructions,
+        PERF_EVENT_CACHE_REFERENCES => ArchEvent::LlcReferences,
+        PERF_EVENT_CACHE_MISSES => ArchEvent::LlcMisses,
+        PERF_EVENT_BRANCHES => ArchEvent::Branches,
+        PERF_EVENT_BRANCH_MISSES => ArchEvent::BranchMisses,
+        _ => return None,
+    })
+}
+
+fn encode_hw(counter: PmuCounter) -> u8 {
+    match counter {
+        PmuCounter::Fixed(n) => HW_FIXED | n,
+        PmuCounter::General(n) => n,
+    }
+}
+
+fn decode_hw(hw: u8) -> Option<PmuCounter> {
+    match hw {
+        HW_NONE => None,
+        hw if hw & HW_FIXED != 0 => Some(PmuCounter::Fixed(hw & !HW_FIXED)),
+        hw => Some(PmuCounter::General(hw)),
+    }
+}
+
+/// Pick a hardware counter for each event: its fixed-function counter when
+/// the CPU has one free, otherwise the next general-purpose counter.
+/// Events left without a counter cannot be counted.
+pub fn assign_counters<const N: usize>(
+    info: &PmuInfo,
+    events: &[Option<ArchEvent>; N],
+) -> [Option<PmuCounter>; N] {
+    let mut out = [None; N];
+    let mut fixed_used = 0u8;
+    let mut next_general = 0u8;
+    for (slot, event) in out.iter_mut().zip(events) {
+        let Some(event) = *event else {
+            continue;
+        };
+        match event.fixed_counter() {
+            Some(n) if n < info.fixed && fixed_used & (1 << n) == 0 => {
+                fixed_used |= 1 << n;
+                *slot = Some(PmuCounter::Fixed(n));
+            }
+            _ if next_general < info.general => {
+                *slot = Some(PmuCounter::General(next_general));
+                next_general += 1;
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+struct PerfCounter {
+    /// Task that opened the counter, `INVALID_TASK_ID` while free.
+    owner: AtomicU32,
+    generation: AtomicU32,
+    event: AtomicU32,
+    user_only: AtomicBool,
+    value: AtomicU64,
+    tasks: AtomicU32,
+}
+
+impl PerfCounter {
+    const fn new() -> Self {
+        Self {
+            owner: AtomicU32::new(INVALID_TASK_ID),
+            generation: AtomicU32::new(0),
+            event: AtomicU32::new(0),
+            user_only: AtomicBool::new(false),
+            value: AtomicU64::new(0),
+            tasks: AtomicU32::new(0),
+        }
+    }
+}
+
+/// Table of `N` counters.  The kernel uses the global instance behind the
+/// `perf_*` functions; tests drive private ones.
+pub struct PerfCounters<const N: usize> {
+    counters: [PerfCounter; N],
+    open: AtomicUsize,
+}
+
+impl<const N: usize> Default for PerfCounters<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> PerfCounters<N> {
+    pub const fn new() -> Self {
+        Self {
+            counters: [const { PerfCounter::new() }; N],
+            open: AtomicUsize::new(0),
+        }
+    }
+
+    /// Counters currently open.
+    #[inline]
+    pub fn open_count(&self) -> usize {
+        self.open.load(Ordering::Relaxed)
+    }
+
+    /// Claim a free counter for `owner`, zeroed.  Returns its id and
+    /// generation.
+    pub fn open(&self, owner: u32, event: u32, user_only: bool) -> Option<(u8, u32)> {
+        for (id, counter) in self.counters.iter().enumerate() {
+            if counter
+                .owner
+                .compare_exchange(INVALID_TASK_ID, owner, Ordering::AcqRel, Ordering::Relaxed)
+                .is_err()
+            {
+                continue;
+            }
+            counter.event.store(event, Ordering::Relaxed);
+            counter.user_only.store(user_only, Ordering::Relaxed);
+            counter.value.store(0, Ordering::Relaxed);
+            counter.tasks.store(0, Ordering::Relaxed);
+            let generation = counter
+                .generation
+                .fetch_add(1, Ordering::AcqRel)
+                .wrapping_add(1);
+            self.open.fetch_add(1, Ordering::Relaxed);
+            return Some((id as u8, generation));
+        }
+        None
+    }
+
+    fn owned(&self, owner: u32, id: u8) -> Option<&PerfCounter> {
+        let counter = self.counters.get(id as usize)?;
+        (owner != INVALID_TASK_ID && counter.owner.load(Ordering::Acquire) == owner)
+            .then_some(counter)
+    }
+
+    /// Close counter `id` if `owner` opened it.
+    pub fn close(&self, owner: u32, id: u8) -> bool {
+        let Some(counter) = self.owned(owner, id) else {
+            return false;
+        };
+        if counter
+            .owner
+            .compare_exchange(owner, INVALID_TASK_ID, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+        counter.generation.fetch_add(1, Ordering::AcqRel);
+        self.open.fetch_sub(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Close every counter `owner` opened.  Returns how many.
+    pub fn close_owned_by(&self, owner: u32) -> usize {
+        (0..N as u8).filter(|&id| self.close(owner, id)).count()
+    }
+
+    fn live(&self, id: u8, generation: u32) -> Option<&PerfCounter> {
+        let counter = self.counters.get(id as usize)?;
+        (counter.owner.load(Ordering::Acquire) != INVALID_TASK_ID
+            && counter.generation.load(Ordering::Acquire) == generation)
+            .then_some(counter)
+    }
+
+    pub fn is_live(&self, id: u8, generation: u32) -> bool {
+        self.live(id, generation).is_some()
+    }
+
+    /// Event and user-only setting of a live counter.
+    pub fn config(&self, id: u8, generation: u32) -> Option<(u32, bool)> {
+        let counter = self.live(id, generation)?;
+        Some((
+            counter.event.load(Ordering::Relaxed),
+            counter.user_only.load(Ordering::Relaxed),
+        ))
+    }
+
+    pub fn add(&self, id: u8, generation: u32, delta: u64) {
+        if let Some(counter) = self.live(id, generation) {
+            counter.value.fetch_add(delta, Ordering::Relaxed);
+        }
+    }
+
+    /// Note one more task feeding counter `id`.
+    pub fn add_task(&self, id: u8, generation: u32) {
+        if let Some(counter) = self.live(id, generation) {
+            counter.tasks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Current total of counter `id` if `owner` opened it.
+    pub fn read(&self, owner: u32, id: u8) -> Option<PerfReading> {
+        let counter = self.owned(owner, id)?;
+        Some(PerfReading {
+            value: counter.value.load(Ordering::Relaxed),
+            event: counter.event.load(Ordering::Relaxed),
+            tasks: counter.tasks.load(Ordering::Relaxed),
+        })
+    }
+}
+
+struct PerfLink {
+    /// Counter id, `LINK_FREE` or `LINK_CLAIMED`.
+    counter: AtomicU8,
+    flags: AtomicU8,
+    /// Hardware counter programmed for this link on the CPU running the
+    /// task; only that CPU touches it.
+    hw: AtomicU8,
+    generation: AtomicU32,
+}
+
+impl PerfLink {
+    const fn new() -> Self {
+        Self {
+            counter: AtomicU8::new(LINK_FREE),
+            flags: AtomicU8::new(0),
+            hw: AtomicU8::new(HW_NONE),
+            generation: AtomicU32::new(0),
+        }
+    }
+
+    /// Counter id, generation and flags of a published link.
+    fn get(&self) -> Option<(u8, u32, u8)> {
+        let id = self.counter.load(Ordering::Acquire);
+        if id == LINK_FREE || id == LINK_CLAIMED {
+            return None;
+        }
+        Some((
+            id,
+            self.generation.load(Ordering::Relaxed),
+            self.flags.load(Ordering::Relaxed),
+        ))
+    }
+}
+
+/// The counters a task feeds.
+pub struct TaskPerf {
+    links: [PerfLink; PERF_TASK_LINKS],
+}
+
+impl Default for TaskPerf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskPerf {
+    pub const fn new() -> Self {
+        Self {
+            links: [const { PerfLink::new() }; PERF_TASK_LINKS],
+        }
+    }
+
+    /// Drop every link, for a task slot being reused.
+    pub fn clear(&self) {
+        for link in &self.links {
+            link.hw.store(HW_NONE, Ordering::Relaxed);
+            link.counter.store(LINK_FREE, Ordering::Release);
+        }
+    }
+
+    /// Link to counter `id`.  Fails when every link is taken.
+    pub(crate) fn link(&self, id: u8, generation: u32, flags: u8) -> bool {
+        for link in &self.links {
+            if link
+                .counter
+                .compare_exchange(
+                    LINK_FREE,
+                    LINK_CLAIMED,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                )
+                .is_err()
+            {
+                continue;
+            }
+            link.flags.store(flags, Ordering::Relaxed);
+            link.generation.store(generation, Ordering::Relaxed);
+            link.hw.store(HW_NONE, Ordering::Relaxed);
+            link.counter.store(id, Ordering::Release);
+            return true;
+        }
+        false
+    }
+
+    /// Whether the task counts its own events for counter `id`.
+    pub fn counts<const N: usize>(&self, counters: &PerfCounters<N>, id: u8) -> bool {
+        self.links
+            .iter()
+            .filter_map(PerfLink::get)
+            .any(|(link_id, generation, flags)| {
+                link_id == id && flags & LINK_COUNTING != 0 && counters.is_live(id, generation)
+            })
+    }
+
+    /// Link `child` to the counters this task passes on, replacing
+    /// whatever links it had.  Returns how many.
+    pub fn inherit_into<const N: usize>(
+        &self,
+        child: &TaskPerf,
+        counters: &PerfCounters<N>,
+    ) -> usize {
+        child.clear();
+        let mut linked = 0;
+        for (id, generation, flags) in self.links.iter().filter_map(PerfLink::get) {
+            if flags & LINK_INHERIT == 0 || !counters.is_live(id, generation) {
+                continue;
+            }
+            if child.link(id, generation, LINK_COUNTING | LINK_INHERIT) {
+                counters.add_task(id, generation);
+                linked += 1;
+            }
+        }
+        linked
+    }
+
+    /// Events of the live links, in link order.
+    fn events<const N: usize>(
+        &self,
+        counters: &PerfCounters<N>,
+        counting_only: bool,
+    ) -> [Option<ArchEvent>; PERF_TASK_LINKS] {
+        let mut events = [None; PERF_TASK_LINKS];
+        for (slot, link) in events.iter_mut().zip(&self.links) {
+            let Some((id, generation, flags)) = link.get() else {
+                continue;
+            };
+            if counting_only && flags & LINK_COUNTING == 0 {
+                continue;
+            }
+            *slot = counters
+                .config(id, generation)
+                .and_then(|(event, _)| arch_event(event));
+        }
+        events
+    }
+}
+
+static COUNTERS: PerfCounters<PERF_MAX_COUNTERS> = PerfCounters::new();
+
+/// Add what `task`'s programmed hardware counters have counted to their
+/// counters and forget the hardware assignment.  Links to closed counters
+/// are dropped.
+fn account(info: &PmuInfo, task: &Task) {
+    for link in &task.perf.links {
+        let hw = link.hw.swap(HW_NONE, Ordering::Relaxed);
+        let Some((id, generation, _)) = link.get() else {
+            continue;
+        };
+        if !COUNTERS.is_live(id, generation) {
+            let _ =
+                link.counter
+                    .compare_exchange(id, LINK_FREE, Ordering::AcqRel, Ordering::Relaxed);
+            continue;
+        }
+        if let Some(counter) = decode_hw(hw) {
+            COUNTERS.add(id, generation, pmu_read(info, counter));
+        }
+    }
+}
+
+/// Program and start the hardware counters for `task`'s counting links.
+fn program(info: &PmuInfo, task: &Task) {
+    let events = task.perf.events(&COUNTERS, true);
+    let assigned = assign_counters(info, &events);
+    let mut mask = 0;
+    for ((link, event), counter) in task.perf.links.iter().zip(events).zip(assigned) {
+        let (Some(event), Some(counter)) = (event, counter) else {
+            continue;
+        };
+        let user_only = link
+            .get()
+            .and_then(|(id, generation, _)| COUNTERS.config(id, generation))
+            .is_some_and(|(_, user_only)| user_only);
+        pmu_configure(counter, event, user_only);
+        link.hw.store(encode_hw(counter), Ordering::Relaxed);
+        mask |= counter.enable_bit();
+    }
+    if mask != 0 {
+        pmu_start(mask);
+    }
+}
+
+/// Context switch hook: bank `from`'s counts and start `to`'s counters.
+/// Runs on the CPU doing the switch, before it.
+pub fn perf_switch(from: Option<&Task>, to: Option<&Task>) {
+    if COUNTERS.open_count() == 0 {
+        return;
+    }
+    let info = pmu_info();
+    if !info.usable() {
+        return;
+    }
+    if let Some(from) = from {
+        account(&info, from);
+    }
+    pmu_stop();
+    if let Some(to) = to {
+        program(&info, to);
+    }
+}
+
+/// Bank the current task's counts so far and restart its counters, e.g.
+/// after its links changed or before reading its counters.
+fn resync_current() {
+    let _guard = IrqPreemptGuard::new();
+    // SAFETY: the current task stays alive while preemption is off.
+    let current = unsafe { scheduler_get_current_task().as_ref() };
+    if current.is_some() {
+        perf_switch(current, current);
+    }
+}
+
+/// Give a new task the links `parent` passes on.  The child's own links
+/// were already cleared with the rest of its runtime state.
+pub fn perf_inherit(parent: &Task, child: &Task) {
+    if COUNTERS.open_count() == 0 {
+        return;
+    }
+    parent.perf.inherit_into(&child.perf, &COUNTERS);
+}
+
+/// Close the counters a task opened when it exits.
+pub fn perf_task_exit(task_id: u32) {
+    if COUNTERS.open_count() != 0 {
+        COUNTERS.close_owned_by(task_id);
+    }
+}
+
+/// Open a counter for `event` on `target` on behalf of `caller`.  Returns
+/// the counter id.
+pub fn open(caller: &Task, target: &Task, event: u32, flags: u64) -> Result<u8, PerfError> {
+    if flags & !PERF_FLAGS_ALL != 0 {
+        return Err(PerfError::Invalid);
+    }
+    let arch = arch_event(event).ok_or(PerfError::Invalid)?;
+    let info = pmu_info();
+    if !info.usable() || !info.has_event(arch) {
+        return Err(PerfError::Unsupported);
+    }
+
+    // The target and the tasks it passes counters to must be able to count
+    // every event at once.
+    let mut events = [None; PERF_TASK_LINKS + 1];
+    events[..PERF_TASK_LINKS].copy_from_slice(&target.perf.events(&COUNTERS, false));
+    events[PERF_TASK_LINKS] = Some(arch);
+    let assigned = assign_counters(&info, &events);
+    if events
+        .iter()
+        .zip(&assigned)
+        .any(|(e, c)| e.is_some() && c.is_none())
+    {
+        return Err(PerfError::Busy);
+    }
+
+    let user_only = flags & PERF_FLAG_USER_ONLY != 0;
+    let (id, generation) = COUNTERS
+        .open(caller.task_id, event, user_only)
+        .ok_or(PerfError::Busy)?;
+    let mut link_flags = 0;
+    if flags & (PERF_FLAG_INHERIT | PERF_FLAG_CHILDREN_ONLY) != 0 {
+        link_flags |= LINK_INHERIT;
+    }
+    if flags & PERF_FLAG_CHILDREN_ONLY == 0 {
+        link_flags |= LINK_COUNTING;
+    }
+    if !target.perf.link(id, generation, link_flags) {
+        COUNTERS.close(caller.task_id, id);
+        return Err(PerfError::Busy);
+    }
+    if link_flags & LINK_COUNTING != 0 {
+        COUNTERS.add_task(id, generation);
+        // A target on another CPU starts counting at its next switch.
+        if ptr::eq(target, scheduler_get_current_task().cast_const()) {
+            resync_current();
+        }
+    }
+    Ok(id)
+}
+
+/// Read counter `id`, which `caller` opened.
+pub fn read(caller: &Task, id: u8) -> Result<PerfReading, PerfError> {
+    if caller.perf.counts(&COUNTERS, id) {
+        resync_current();
+    }
+    COUNTERS.read(caller.task_id, id).ok_or(PerfError::Invalid)
+}
+
+/// Close counter `id`, which `caller` opened.
+pub fn close(caller: &Task, id: u8) -> Result<(), PerfError> {
+    if COUNTERS.close(caller.task_id, id) {
+        Ok(())
+    } else {
+        Err(PerfError::Invalid)
+    }
+}
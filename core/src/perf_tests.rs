@@ -0,0 +1,162 @@
+//! perf tests: PMU enumeration, hardware counter assignment, the counter
+//! table and counter inheritance.
+
+use slopos_abi::perf::{PERF_EVENT_CACHE_MISSES, PERF_EVENT_CYCLES, PERF_EVENT_INSTRUCTIONS};
+use slopos_lib::cpu::pmu::{ArchEvent, PmuCounter, PmuInfo};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::perf::{LINK_COUNTING, LINK_INHERIT, PerfCounters, TaskPerf, assign_counters};
+
+pub fn test_perf_pmu_info_decode() -> TestResult {
+    // Version 4, 8 counters of 48 bits, 7 event bits of which RefCycles is
+    // missing, 3 fixed counters of 48 bits.
+    let info = PmuInfo::from_cpuid(4 | 8 << 8 | 48 << 16 | 7 << 24, 1 << 2, 3 | 48 << 5);
+    assert_eq_test!(info.version, 4);
+    assert_eq_test!(info.general, 8);
+    assert_eq_test!(info.general_width, 48);
+    assert_eq_test!(info.fixed, 3);
+    assert_eq_test!(info.fixed_width, 48);
+    assert_test!(info.usable());
+    assert_test!(info.has_event(ArchEvent::Instructions));
+    assert_test!(
+        !info.has_event(ArchEvent::RefCycles),
+        "EBX bit not inverted"
+    );
+    assert_test!(info.has_event(ArchEvent::BranchMisses));
+
+    // Only two event bits are valid; fixed counters need version 2.
+    let info = PmuInfo::from_cpuid(1 | 16 << 8 | 40 << 16 | 2 << 24, 0, 3 | 48 << 5);
+    assert_eq_test!(info.general, 8, "general counters not capped");
+    assert_eq_test!(info.fixed, 0);
+    assert_eq_test!(info.events, 0b11);
+    assert_test!(!info.usable(), "version 1 is not driven");
+    assert_test!(!PmuInfo::default().usable());
+    pass!()
+}
+
+pub fn test_perf_assign_counters() -> TestResult {
+    let info = PmuInfo {
+        version: 2,
+        general: 2,
+        general_width: 48,
+        fixed: 2,
+        fixed_width: 48,
+        events: 0x7F,
+    };
+    let events = [
+        Some(ArchEvent::Instructions),
+        Some(ArchEvent::CoreCycles),
+        None,
+        Some(ArchEvent::RefCycles),
+        Some(ArchEvent::Instructions),
+        Some(ArchEvent::LlcMisses),
+    ];
+    assert_eq_test!(
+        assign_counters(&info, &events),
+        [
+            Some(PmuCounter::Fixed(0)),
+            Some(PmuCounter::Fixed(1)),
+            None,
+            Some(PmuCounter::General(0)),
+            Some(PmuCounter::General(1)),
+            None,
+        ]
+    );
+    assert_eq_test!(PmuCounter::Fixed(1).enable_bit(), 1 << 33);
+    assert_eq_test!(PmuCounter::General(1).enable_bit(), 1 << 1);
+    pass!()
+}
+
+pub fn test_perf_counter_table() -> TestResult {
+    let counters = PerfCounters::<2>::new();
+    assert_eq_test!(counters.open(5, PERF_EVENT_CYCLES, false), Some((0, 1)));
+    assert_eq_test!(
+        counters.open(6, PERF_EVENT_INSTRUCTIONS, true),
+        Some((1, 1))
+    );
+    assert_eq_test!(
+        counters.open(7, PERF_EVENT_CYCLES, false),
+        None,
+        "table full"
+    );
+    assert_eq_test!(counters.open_count(), 2);
+    assert_eq_test!(counters.config(1, 1), Some((PERF_EVENT_INSTRUCTIONS, true)));
+
+    counters.add(0, 1, 10);
+    counters.add(0, 1, 5);
+    counters.add_task(0, 1);
+    let Some(reading) = counters.read(5, 0) else {
+        return fail!("owner cannot read");
+    };
+    assert_eq_test!(reading.value, 15);
+    assert_eq_test!(reading.event, PERF_EVENT_CYCLES);
+    assert_eq_test!(reading.tasks, 1);
+    assert_test!(counters.read(6, 0).is_none(), "read another task's counter");
+    assert_test!(!counters.close(6, 0), "closed another task's counter");
+
+    assert_test!(counters.close(5, 0));
+    assert_test!(!counters.is_live(0, 1));
+    counters.add(0, 1, 100);
+    assert_eq_test!(
+        counters.open(8, PERF_EVENT_CACHE_MISSES, false),
+        Some((0, 2))
+    );
+    counters.add(0, 1, 100);
+    assert_eq_test!(
+        counters.read(8, 0).map(|r| r.value),
+        Some(0),
+        "stale generation counted"
+    );
+
+    assert_eq_test!(counters.close_owned_by(6), 1);
+    assert_eq_test!(counters.open_count(), 1);
+    pass!()
+}
+
+pub fn test_perf_inherit_links() -> TestResult {
+    let counters = PerfCounters::<4>::new();
+    let Some((children, children_gen)) = counters.open(1, PERF_EVENT_CYCLES, false) else {
+        return fail!("open failed");
+    };
+    let Some((own, own_gen)) = counters.open(1, PERF_EVENT_INSTRUCTIONS, false) else {
+        return fail!("open failed");
+    };
+    let parent = TaskPerf::new();
+    assert_test!(parent.link(children, children_gen, LINK_INHERIT));
+    assert_test!(parent.link(own, own_gen, LINK_COUNTING));
+    assert_test!(
+        !parent.counts(&counters, children),
+        "children-only counts parent"
+    );
+    assert_test!(parent.counts(&counters, own));
+
+    let child = TaskPerf::new();
+    assert_test!(child.link(own, own_gen, LINK_COUNTING));
+    assert_eq_test!(parent.inherit_into(&child, &counters), 1);
+    assert_test!(child.counts(&counters, children));
+    assert_test!(!child.counts(&counters, own), "old links kept");
+
+    let grandchild = TaskPerf::new();
+    assert_eq_test!(child.inherit_into(&grandchild, &counters), 1);
+    assert_test!(grandchild.counts(&counters, children));
+    assert_eq_test!(counters.read(1, children).map(|r| r.tasks), Some(2));
+
+    assert_test!(counters.close(1, children));
+    assert_test!(
+        !grandchild.counts(&counters, children),
+        "closed counter counts"
+    );
+    assert_eq_test!(grandchild.inherit_into(&TaskPerf::new(), &counters), 0);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    perf,
+    [
+        test_perf_pmu_info_decode,
+        test_perf_assign_counters,
+        test_perf_counter_table,
+        test_perf_inherit_links,
+    ]
+);
@@ -1,6 +1,7 @@
 use super::task::{Task, task_find_by_id};
 use crate::platform;
 use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, Ordering};
 use slopos_abi::fate::FateResult;
 use slopos_lib::wl_currency::{self, WL_DELTA};
 
@@ -17,6 +18,17 @@ where
     }
     0
 }
+static LOSS_REBOOTS: AtomicBool = AtomicBool::new(true);
+
+/// Whether a losing spin reboots the machine (the default). Unattended runs
+/// such as autopilot turn this off so the outcome stays deterministic; the
+/// W/L penalty is still applied.
+pub fn fate_set_loss_reboots(enabled: bool) {
+    LOSS_REBOOTS.store(enabled, Ordering::Relaxed);
+}
+pub fn fate_loss_reboots() -> bool {
+    LOSS_REBOOTS.load(Ordering::Relaxed)
+}
 pub fn fate_spin() -> FateResult {
     let val = platform::rng_next() as u32;
     FateResult {
@@ -12,6 +12,8 @@ use core::sync::atomic::{AtomicU32, Ordering};
 
 use slopos_abi::task::BlockReason;
 use slopos_lib::IrqMutex;
+use slopos_lib::cpu::smap::UserAccess;
+use slopos_lib::lockstat::LockClass;
 
 use super::scheduler::{block_current_task, scheduler_get_current_task, unblock_task};
 use super::task_struct::Task;
@@ -62,12 +64,11 @@ impl FutexBucket {
     }
 }
 
+static FUTEX_LOCK_CLASS: LockClass = LockClass::new("futex");
+
 // Wrap each bucket in an IrqMutex for interrupt-safe locking.
-static FUTEX_TABLE: [IrqMutex<FutexBucket>; FUTEX_HASH_BUCKETS] = {
-    // const-init all buckets
-    const BUCKET: IrqMutex<FutexBucket> = IrqMutex::new(FutexBucket::new());
-    [BUCKET; FUTEX_HASH_BUCKETS]
-};
+static FUTEX_TABLE: [IrqMutex<FutexBucket>; FUTEX_HASH_BUCKETS] =
+    [const { IrqMutex::with_class(&FUTEX_LOCK_CLASS, FutexBucket::new()) }; FUTEX_HASH_BUCKETS];
 
 /// Hash a futex address to a bucket index.
 #[inline]
@@ -103,11 +104,14 @@ pub fn futex_wait(uaddr: u64, expected: u32, _timeout_ms: u64) -> i64 {
     {
         let mut bucket = FUTEX_TABLE[bucket_idx].lock();
 
-        // Read the current value at the futex address.
+        // Read the current value at the futex address, inside a SMAP
+        // user-access window since it is a user page.
         // SAFETY: The syscall handler has validated that uaddr is a valid,
         // mapped, 4-byte-aligned user address in the current process.
-        let current_val =
-            unsafe { ptr::read_volatile(uaddr as *const AtomicU32) }.load(Ordering::SeqCst);
+        let current_val = {
+            let _access = UserAccess::begin();
+            unsafe { ptr::read_volatile(uaddr as *const AtomicU32) }.load(Ordering::SeqCst)
+        };
 
         if current_val != expected {
             return slopos_abi::syscall::ERRNO_EAGAIN as i64;
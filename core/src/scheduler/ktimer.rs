@@ -0,0 +1,391 @@
+//! One-shot and periodic kernel timers for drivers.
+//!
+//! [`ktimer_add`] arms a callback on a hashed timer wheel advanced by the
+//! BSP's scheduler tick, so drivers no longer count ticks themselves.
+//! Callbacks run in interrupt context on the BSP with the wheel unlocked:
+//! they may add or cancel timers, their own included, but must not sleep.
+//!
+//! Timers live in a fixed pool, so arming and cancelling never allocate and
+//! are safe from any context.  Once [`ktimer_cancel`] returns the callback
+//! will not start again, though an invocation already running on the BSP
+//! may still be finishing.  [`ktimer_cancel_sync`] also waits that out, and
+//! so must not be called from interrupt context or from the callback.
+//!
+//! An idle BSP with its tick stopped arms its one-shot LAPIC timer for the
+//! earliest ktimer deadline (see `tickless`).
+
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use slopos_lib::IrqMutex;
+use slopos_lib::lockstat::LockClass;
+
+use super::sleep::ms_to_sleep_ticks;
+use crate::platform;
+
+/// Wheel slots; deadlines further out than this take extra rotations.
+const NUM_SLOTS: usize = 256;
+
+/// Timers that can be armed at once across the whole kernel.
+pub const MAX_KTIMERS: usize = 64;
+
+const NIL: u8 = u8::MAX;
+const GENERATION_MASK: u32 = 0x00FF_FFFF;
+
+/// Timer callback, invoked in interrupt context with the `context` pointer
+/// passed to [`ktimer_add`].
+pub type KtimerCallback = fn(*mut c_void);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KtimerMode {
+    /// Fire once, then release the timer.
+    OneShot,
+    /// Fire every interval until cancelled.  Missed periods are not replayed.
+    Periodic,
+}
+
+/// Handle for cancelling a timer.  A handle outlives its timer harmlessly:
+/// pool slots carry a generation, so a stale handle never matches a reuse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KtimerHandle(u32);
+
+impl KtimerHandle {
+    /// Never refers to a timer; cancelling it is a no-op.
+    pub const INVALID: Self = Self(0);
+
+    fn new(index: usize, generation: u32) -> Self {
+        Self(generation << 8 | index as u32)
+    }
+
+    fn index(self) -> usize {
+        (self.0 & 0xFF) as usize
+    }
+
+    fn generation(self) -> u32 {
+        self.0 >> 8
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KtimerState {
+    Free,
+    /// Linked into the wheel slot for `deadline`.
+    Pending,
+    /// Expired and collected by [`KtimerWheel::run`], callback not yet started.
+    Firing,
+}
+
+#[derive(Clone, Copy)]
+struct Ktimer {
+    /// Bumped on every release; never 0 so [`KtimerHandle::INVALID`] misses.
+    generation: u32,
+    state: KtimerState,
+    mode: KtimerMode,
+    period_ticks: u64,
+    deadline: u64,
+    callback: Option<KtimerCallback>,
+    context: *mut c_void,
+    /// Next pool index in the same wheel slot.
+    next: u8,
+}
+
+impl Ktimer {
+    const fn empty() -> Self {
+        Self {
+            generation: 1,
+            state: KtimerState::Free,
+            mode: KtimerMode::OneShot,
+            period_ticks: 0,
+            deadline: 0,
+            callback: None,
+            context: core::ptr::null_mut(),
+            next: NIL,
+        }
+    }
+
+    fn release(&mut self) {
+        let generation = match (self.generation + 1) & GENERATION_MASK {
+            0 => 1,
+            g => g,
+        };
+        *self = Self {
+            generation,
+            ..Self::empty()
+        };
+    }
+}
+
+struct WheelInner {
+    timers: [Ktimer; MAX_KTIMERS],
+    heads: [u8; NUM_SLOTS],
+    /// Last tick whose slot has been processed.
+    current_tick: u64,
+    started: bool,
+}
+
+// SAFETY: the raw `context` pointers are only handed back to the callbacks
+// that registered them; all access is serialized by the IrqMutex.
+unsafe impl Send for WheelInner {}
+
+impl WheelInner {
+    fn handle_matches(&self, handle: KtimerHandle) -> bool {
+        let index = handle.index();
+        index < MAX_KTIMERS
+            && self.timers[index].state != KtimerState::Free
+            && self.timers[index].generation == handle.generation()
+    }
+
+    fn link(&mut self, index: usize, deadline: u64) {
+        // A deadline at or before the processed tick would wait a whole
+        // rotation for its slot to come round again.
+        let deadline = deadline.max(self.current_tick.wrapping_add(1));
+        let slot = (deadline % NUM_SLOTS as u64) as usize;
+        let timer = &mut self.timers[index];
+        timer.deadline = deadline;
+        timer.state = KtimerState::Pending;
+        timer.next = self.heads[slot];
+        self.heads[slot] = index as u8;
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let slot = (self.timers[index].deadline % NUM_SLOTS as u64) as usize;
+        let next = self.timers[index].next;
+        if self.heads[slot] as usize == index {
+            self.heads[slot] = next;
+            return;
+        }
+        let mut cursor = self.heads[slot];
+        while cursor != NIL {
+            let entry = &mut self.timers[cursor as usize];
+            if entry.next as usize == index {
+                entry.next = next;
+                return;
+            }
+            cursor = entry.next;
+        }
+    }
+
+    /// Move every expired timer in the slot for `tick` to `Firing`.
+    fn collect_slot(
+        &mut self,
+        tick: u64,
+        due: &mut [KtimerHandle; MAX_KTIMERS],
+        count: &mut usize,
+    ) {
+        let slot = (tick % NUM_SLOTS as u64) as usize;
+        let mut cursor = self.heads[slot];
+        while cursor != NIL {
+            let index = cursor as usize;
+            cursor = self.timers[index].next;
+            if self.timers[index].deadline <= tick {
+                self.unlink(index);
+                self.timers[index].state = KtimerState::Firing;
+                self.timers[index].next = NIL;
+                due[*count] = KtimerHandle::new(index, self.timers[index].generation);
+                *count += 1;
+            }
+        }
+    }
+}
+
+static KTIMER_LOCK_CLASS: LockClass = LockClass::new("ktimer");
+
+/// Fixed-pool timer wheel behind [`ktimer_add`].  Exposed so tests can drive
+/// a private instance with explicit ticks.
+pub struct KtimerWheel {
+    inner: IrqMutex<WheelInner>,
+    /// Handle whose callback is executing, [`KtimerHandle::INVALID`] if none.
+    running: AtomicU32,
+}
+
+impl Default for KtimerWheel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KtimerWheel {
+    pub const fn new() -> Self {
+        Self {
+            inner: IrqMutex::with_class(
+                &KTIMER_LOCK_CLASS,
+                WheelInner {
+                    timers: [Ktimer::empty(); MAX_KTIMERS],
+                    heads: [NIL; NUM_SLOTS],
+                    current_tick: 0,
+                    started: false,
+                },
+            ),
+            running: AtomicU32::new(0),
+        }
+    }
+
+    /// Arm a timer `delay_ticks` (at least one) after `now_tick`.  Periodic
+    /// timers then repeat every `delay_ticks`.  `None` when the pool is full.
+    pub fn add(
+        &self,
+        now_tick: u64,
+        mode: KtimerMode,
+        delay_ticks: u64,
+        callback: KtimerCallback,
+        context: *mut c_void,
+    ) -> Option<KtimerHandle> {
+        let delay_ticks = delay_ticks.max(1);
+        let mut inner = self.inner.lock();
+        let index = inner
+            .timers
+            .iter()
+            .position(|t| t.state == KtimerState::Free)?;
+        let timer = &mut inner.timers[index];
+        timer.mode = mode;
+        timer.period_ticks = delay_ticks;
+        timer.callback = Some(callback);
+        timer.context = context;
+        let generation = timer.generation;
+        inner.link(index, now_tick.wrapping_add(delay_ticks));
+        Some(KtimerHandle::new(index, generation))
+    }
+
+    /// Disarm `handle`.  Returns `false` if it had already fired (one-shot)
+    /// or been cancelled.  Never blocks, so it is safe from any context.
+    pub fn cancel(&self, handle: KtimerHandle) -> bool {
+        let mut inner = self.inner.lock();
+        if !inner.handle_matches(handle) {
+            return false;
+        }
+        let index = handle.index();
+        if inner.timers[index].state == KtimerState::Pending {
+            inner.unlink(index);
+        }
+        inner.timers[index].release();
+        true
+    }
+
+    /// [`cancel`](Self::cancel), then wait for a running invocation of the
+    /// callback to return.
+    pub fn cancel_sync(&self, handle: KtimerHandle) -> bool {
+        let cancelled = self.cancel(handle);
+        while handle != KtimerHandle::INVALID && self.running.load(Ordering::Acquire) == handle.0 {
+            core::hint::spin_loop();
+        }
+        cancelled
+    }
+
+    /// Advance the wheel to `now_tick` and invoke every expired callback.
+    /// Returns how many callbacks ran.
+    pub fn run(&self, now_tick: u64) -> usize {
+        let mut due = [KtimerHandle::INVALID; MAX_KTIMERS];
+        let mut count = 0;
+        {
+            let mut inner = self.inner.lock();
+            if !inner.started || now_tick.wrapping_sub(inner.current_tick) > NUM_SLOTS as u64 {
+                // First run, or far behind: visiting the last NUM_SLOTS ticks
+                // still covers every slot once.
+                inner.current_tick = now_tick.saturating_sub(NUM_SLOTS as u64);
+                inner.started = true;
+            }
+            while inner.current_tick < now_tick {
+                inner.current_tick += 1;
+                let tick = inner.current_tick;
+                inner.collect_slot(tick, &mut due, &mut count);
+            }
+        }
+
+        let mut ran = 0;
+        for &handle in &due[..count] {
+            let Some((callback, context)) = self.begin_fire(handle) else {
+                continue;
+            };
+            callback(context);
+            self.running
+                .store(KtimerHandle::INVALID.0, Ordering::Release);
+            ran += 1;
+        }
+        ran
+    }
+
+    /// Re-arm or release a collected timer and mark it running.  `None` if
+    /// it was cancelled after being collected.
+    fn begin_fire(&self, handle: KtimerHandle) -> Option<(KtimerCallback, *mut c_void)> {
+        let mut inner = self.inner.lock();
+        if !inner.handle_matches(handle)
+            || inner.timers[handle.index()].state != KtimerState::Firing
+        {
+            return None;
+        }
+        let index = handle.index();
+        let timer = inner.timers[index];
+        let callback = timer.callback?;
+        match timer.mode {
+            KtimerMode::Periodic => {
+                inner.link(index, timer.deadline.wrapping_add(timer.period_ticks));
+            }
+            KtimerMode::OneShot => inner.timers[index].release(),
+        }
+        self.running.store(handle.0, Ordering::Release);
+        Some((callback, timer.context))
+    }
+
+    /// Earliest pending deadline, if any timer is armed.
+    pub fn next_deadline(&self) -> Option<u64> {
+        let inner = self.inner.lock();
+        inner
+            .timers
+            .iter()
+            .filter(|t| t.state == KtimerState::Pending)
+            .map(|t| t.deadline)
+            .min()
+    }
+
+    /// Number of armed timers (diagnostic).
+    pub fn active_count(&self) -> usize {
+        let inner = self.inner.lock();
+        inner
+            .timers
+            .iter()
+            .filter(|t| t.state != KtimerState::Free)
+            .count()
+    }
+}
+
+static KTIMERS: KtimerWheel = KtimerWheel::new();
+
+/// Arm a kernel timer firing `ms` milliseconds from now, once or every `ms`.
+/// `callback` runs in interrupt context on the BSP.  Returns `None` when all
+/// [`MAX_KTIMERS`] timers are in use.
+pub fn ktimer_add(
+    mode: KtimerMode,
+    ms: u32,
+    callback: KtimerCallback,
+    context: *mut c_void,
+) -> Option<KtimerHandle> {
+    KTIMERS.add(
+        platform::timer_ticks(),
+        mode,
+        ms_to_sleep_ticks(ms),
+        callback,
+        context,
+    )
+}
+
+/// Disarm a timer without waiting.  Safe from interrupt context and from
+/// the timer's own callback.
+pub fn ktimer_cancel(handle: KtimerHandle) -> bool {
+    KTIMERS.cancel(handle)
+}
+
+/// Disarm a timer and wait for a running callback to finish, so `context`
+/// may be freed afterwards.  Task context only, never from the callback.
+pub fn ktimer_cancel_sync(handle: KtimerHandle) -> bool {
+    KTIMERS.cancel_sync(handle)
+}
+
+/// Scheduler tick on the BSP: fire whatever has expired.
+pub(crate) fn ktimer_tick() {
+    KTIMERS.run(platform::timer_ticks());
+}
+
+/// Tick of the earliest armed kernel timer.
+pub fn next_ktimer_tick() -> Option<u64> {
+    KTIMERS.next_deadline()
+}
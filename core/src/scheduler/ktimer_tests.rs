@@ -0,0 +1,224 @@
+//! Kernel timer tests: one-shot and periodic expiry, cancellation (including
+//! from the callback itself), stale handles, pool exhaustion and catch-up.
+
+use core::cell::Cell;
+use core::ffi::c_void;
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::ktimer::{
+    KtimerHandle, KtimerMode, KtimerWheel, MAX_KTIMERS, ktimer_add, ktimer_cancel,
+};
+
+const START: u64 = 1_000;
+
+fn count_fire(context: *mut c_void) {
+    let counter = unsafe { &*(context as *const AtomicU32) };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+fn counter_ptr(counter: &AtomicU32) -> *mut c_void {
+    counter as *const AtomicU32 as *mut c_void
+}
+
+/// Wheel that has already processed up to `START`.
+fn started_wheel() -> KtimerWheel {
+    let wheel = KtimerWheel::new();
+    wheel.run(START);
+    wheel
+}
+
+pub fn test_ktimer_oneshot_fires_once() -> TestResult {
+    let wheel = started_wheel();
+    let fired = AtomicU32::new(0);
+    let Some(handle) = wheel.add(
+        START,
+        KtimerMode::OneShot,
+        5,
+        count_fire,
+        counter_ptr(&fired),
+    ) else {
+        return fail!("add failed on an empty wheel");
+    };
+    assert_eq_test!(wheel.next_deadline(), Some(START + 5), "deadline");
+
+    wheel.run(START + 4);
+    assert_eq_test!(fired.load(Ordering::Relaxed), 0, "fired early");
+    assert_eq_test!(wheel.run(START + 5), 1, "callbacks run at the deadline");
+    wheel.run(START + 50);
+    assert_eq_test!(fired.load(Ordering::Relaxed), 1, "one-shot fired again");
+    assert_eq_test!(wheel.active_count(), 0, "one-shot not released");
+    assert_test!(!wheel.cancel(handle), "cancelled a fired one-shot");
+    pass!()
+}
+
+pub fn test_ktimer_periodic_until_cancelled() -> TestResult {
+    let wheel = started_wheel();
+    let fired = AtomicU32::new(0);
+    let Some(handle) = wheel.add(
+        START,
+        KtimerMode::Periodic,
+        10,
+        count_fire,
+        counter_ptr(&fired),
+    ) else {
+        return fail!("add failed");
+    };
+    for step in 1..=3u64 {
+        wheel.run(START + step * 10);
+    }
+    assert_eq_test!(fired.load(Ordering::Relaxed), 3, "one firing per period");
+
+    assert_test!(wheel.cancel(handle), "cancel of a live periodic timer");
+    assert_test!(!wheel.cancel(handle), "double cancel succeeded");
+    wheel.run(START + 100);
+    assert_eq_test!(fired.load(Ordering::Relaxed), 3, "fired after cancel");
+    assert_test!(!wheel.cancel(KtimerHandle::INVALID));
+    pass!()
+}
+
+struct SelfCancel<'a> {
+    wheel: &'a KtimerWheel,
+    handle: Cell<KtimerHandle>,
+    fired: AtomicU32,
+}
+
+fn cancel_self(context: *mut c_void) {
+    let state = unsafe { &*(context as *const SelfCancel<'_>) };
+    if state.fired.fetch_add(1, Ordering::Relaxed) == 1 {
+        state.wheel.cancel(state.handle.get());
+    }
+}
+
+pub fn test_ktimer_cancel_from_callback() -> TestResult {
+    let wheel = started_wheel();
+    let state = SelfCancel {
+        wheel: &wheel,
+        handle: Cell::new(KtimerHandle::INVALID),
+        fired: AtomicU32::new(0),
+    };
+    let context = &state as *const SelfCancel<'_> as *mut c_void;
+    let Some(handle) = wheel.add(START, KtimerMode::Periodic, 1, cancel_self, context) else {
+        return fail!("add failed");
+    };
+    state.handle.set(handle);
+
+    for tick in 1..=10u64 {
+        wheel.run(START + tick);
+    }
+    assert_eq_test!(
+        state.fired.load(Ordering::Relaxed),
+        2,
+        "periodic timer outlived its own cancel"
+    );
+    assert_eq_test!(wheel.active_count(), 0, "self-cancelled timer still armed");
+    assert_test!(!wheel.cancel_sync(handle), "cancel_sync of a dead timer");
+    pass!()
+}
+
+pub fn test_ktimer_pool_and_stale_handles() -> TestResult {
+    let wheel = started_wheel();
+    let fired = AtomicU32::new(0);
+    let mut first = KtimerHandle::INVALID;
+    for i in 0..MAX_KTIMERS {
+        match wheel.add(
+            START,
+            KtimerMode::OneShot,
+            1000,
+            count_fire,
+            counter_ptr(&fired),
+        ) {
+            Some(handle) if i == 0 => first = handle,
+            Some(_) => {}
+            None => return fail!("pool full after {} timers", i),
+        }
+    }
+    assert_test!(
+        wheel
+            .add(START, KtimerMode::OneShot, 1, count_fire, ptr::null_mut())
+            .is_none(),
+        "pool overfilled"
+    );
+
+    assert_test!(wheel.cancel(first));
+    let Some(reused) = wheel.add(
+        START,
+        KtimerMode::OneShot,
+        3,
+        count_fire,
+        counter_ptr(&fired),
+    ) else {
+        return fail!("cancelled slot not reused");
+    };
+    assert_test!(reused != first, "reused slot handed out the same handle");
+    assert_test!(!wheel.cancel(first), "stale handle cancelled its successor");
+    wheel.run(START + 3);
+    assert_eq_test!(fired.load(Ordering::Relaxed), 1, "successor did not fire");
+    pass!()
+}
+
+pub fn test_ktimer_catch_up_and_long_delays() -> TestResult {
+    let wheel = started_wheel();
+    let fired = AtomicU32::new(0);
+    // Beyond one rotation: lands in a slot that comes round earlier.
+    if wheel
+        .add(
+            START,
+            KtimerMode::OneShot,
+            300,
+            count_fire,
+            counter_ptr(&fired),
+        )
+        .is_none()
+    {
+        return fail!("add failed");
+    }
+    wheel.run(START + 44);
+    assert_eq_test!(fired.load(Ordering::Relaxed), 0, "fired a rotation early");
+
+    let Some(_) = wheel.add(
+        START + 44,
+        KtimerMode::OneShot,
+        20,
+        count_fire,
+        counter_ptr(&fired),
+    ) else {
+        return fail!("add failed");
+    };
+    // Skip far past both deadlines in one call, as after a long idle.
+    wheel.run(START + 5_000);
+    assert_eq_test!(
+        fired.load(Ordering::Relaxed),
+        2,
+        "missed a deadline on catch-up"
+    );
+    assert_eq_test!(wheel.active_count(), 0);
+    pass!()
+}
+
+pub fn test_ktimer_global_add_cancel() -> TestResult {
+    let fired = AtomicU32::new(0);
+    let Some(handle) = ktimer_add(KtimerMode::OneShot, 60_000, count_fire, counter_ptr(&fired))
+    else {
+        return fail!("global timer pool exhausted");
+    };
+    assert_test!(ktimer_cancel(handle), "cancel of a freshly armed timer");
+    assert_test!(!ktimer_cancel(handle), "double cancel succeeded");
+    assert_eq_test!(fired.load(Ordering::Relaxed), 0);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    ktimer,
+    [
+        test_ktimer_oneshot_fires_once,
+        test_ktimer_periodic_until_cancelled,
+        test_ktimer_cancel_from_callback,
+        test_ktimer_pool_and_stale_handles,
+        test_ktimer_catch_up_and_long_delays,
+        test_ktimer_global_add_cancel,
+    ]
+);
@@ -4,6 +4,9 @@ pub mod fate_api;
 pub mod ffi_boundary;
 pub mod futex;
 pub mod kthread;
+pub mod ktimer;
+#[cfg(feature = "itests")]
+pub mod ktimer_tests;
 pub mod lifecycle;
 pub mod per_cpu;
 pub mod runtime;
@@ -12,10 +15,13 @@ pub mod safe_switch;
 pub mod sched_tests;
 pub mod scheduler;
 pub mod sleep;
+#[cfg(feature = "itests")]
+pub mod stack_watermark_tests;
 pub mod switch_asm;
 pub mod switch_context;
 pub mod task;
 pub mod task_lock;
 pub mod task_struct;
+pub mod tickless;
 pub mod trap;
 pub mod work_steal;
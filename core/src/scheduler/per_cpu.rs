@@ -19,8 +19,9 @@ use core::ptr;
 use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicU64, Ordering};
 
 use super::task_struct::{Task, TaskContext};
-use slopos_abi::task::TaskStatus;
-use slopos_lib::{InitFlag, IrqMutex, MAX_CPUS, klog_debug, klog_info};
+use slopos_abi::task::{CpuUsage, TaskStatus};
+use slopos_lib::lockstat::LockClass;
+use slopos_lib::{InitFlag, IrqMutex, MAX_CPUS, klog_debug, klog_info, tsc};
 
 const NUM_PRIORITY_LEVELS: usize = 4;
 
@@ -185,6 +186,9 @@ impl ReadyQueue {
 
 const EMPTY_QUEUE: ReadyQueue = ReadyQueue::new();
 
+/// Shared by every CPU's `queue_lock`.
+static RUNQUEUE_LOCK_CLASS: LockClass = LockClass::new("runqueue");
+
 #[repr(C, align(64))]
 pub struct PerCpuScheduler {
     pub cpu_id: usize,
@@ -200,6 +204,13 @@ pub struct PerCpuScheduler {
     pub idle_time: AtomicU64,
     pub total_yields: AtomicU64,
     pub schedule_calls: AtomicU32,
+    /// TSC cycles spent running tasks other than the idle task.
+    pub busy_cycles: AtomicU64,
+    /// TSC cycles spent in the idle task.
+    pub idle_cycles: AtomicU64,
+    /// TSC value up to which cycles have been credited; 0 before the first
+    /// switch or tick.
+    accounted_at: AtomicU64,
     initialized: AtomicBool,
     pub return_context: UnsafeCell<TaskContext>,
     executing_task: AtomicBool,
@@ -215,7 +226,7 @@ impl PerCpuScheduler {
         Self {
             cpu_id: 0,
             ready_queues: UnsafeCell::new([EMPTY_QUEUE; NUM_PRIORITY_LEVELS]),
-            queue_lock: IrqMutex::new(()),
+            queue_lock: IrqMutex::with_class(&RUNQUEUE_LOCK_CLASS, ()),
             current_task_atomic: AtomicPtr::new(ptr::null_mut()),
             idle_task_atomic: AtomicPtr::new(ptr::null_mut()),
             enabled: AtomicBool::new(false),
@@ -226,6 +237,9 @@ impl PerCpuScheduler {
             idle_time: AtomicU64::new(0),
             total_yields: AtomicU64::new(0),
             schedule_calls: AtomicU32::new(0),
+            busy_cycles: AtomicU64::new(0),
+            idle_cycles: AtomicU64::new(0),
+            accounted_at: AtomicU64::new(0),
             initialized: AtomicBool::new(false),
             return_context: UnsafeCell::new(TaskContext::zero()),
             executing_task: AtomicBool::new(false),
@@ -289,6 +303,9 @@ impl PerCpuScheduler {
         self.idle_time.store(0, Ordering::Relaxed);
         self.total_yields.store(0, Ordering::Relaxed);
         self.schedule_calls.store(0, Ordering::Relaxed);
+        self.busy_cycles.store(0, Ordering::Relaxed);
+        self.idle_cycles.store(0, Ordering::Relaxed);
+        self.accounted_at.store(0, Ordering::Relaxed);
         self.initialized.store(true, Ordering::Release);
         self.clear_remote_inbox_with_ref_release();
     }
@@ -414,6 +431,49 @@ impl PerCpuScheduler {
         self.schedule_calls.fetch_add(1, Ordering::Relaxed);
     }
 
+    /// Credit the cycles since the last call, up to `now`, to idle or busy
+    /// time.  Called on the owning CPU at every context switch and tick with
+    /// what it was running until then.  The atomic max hands each stretch
+    /// to exactly one caller, so a tick landing in the middle of a switch
+    /// neither loses nor double-counts cycles.
+    pub fn account_cycles(&self, now: u64, was_idle: bool) {
+        let last = self.accounted_at.fetch_max(now, Ordering::AcqRel);
+        if last == 0 || now <= last {
+            return;
+        }
+        let counter = if was_idle {
+            &self.idle_cycles
+        } else {
+            &self.busy_cycles
+        };
+        counter.fetch_add(now - last, Ordering::Relaxed);
+    }
+
+    /// Whether the CPU is in its idle task, or has no task at all.
+    pub fn is_running_idle(&self) -> bool {
+        let current = self.current_task();
+        current.is_null() || current == self.idle_task()
+    }
+
+    /// Busy and idle cycles up to `now`, counting the stretch since the
+    /// last switch or tick as whatever the CPU is running.  Readable from
+    /// any CPU; a concurrent credit can skew the result by one stretch.
+    pub fn cycles_at(&self, now: u64) -> (u64, u64) {
+        let since = self.accounted_at.load(Ordering::Acquire);
+        let busy = self.busy_cycles.load(Ordering::Relaxed);
+        let idle = self.idle_cycles.load(Ordering::Relaxed);
+        let pending = if since == 0 {
+            0
+        } else {
+            now.saturating_sub(since)
+        };
+        if self.is_running_idle() {
+            (busy, idle + pending)
+        } else {
+            (busy + pending, idle)
+        }
+    }
+
     /// Push a task to this CPU's remote wake inbox.
     ///
     /// This is a lock-free MPSC (multi-producer single-consumer) push.
@@ -674,6 +734,25 @@ pub fn get_total_switches() -> u64 {
     total
 }
 
+/// Utilization counters of `cpu_id`, or `None` if it is not online.
+pub fn cpu_usage(cpu_id: usize) -> Option<CpuUsage> {
+    if cpu_id >= slopos_lib::get_cpu_count() {
+        return None;
+    }
+    with_cpu_scheduler(cpu_id, |sched| {
+        let (busy_cycles, idle_cycles) = sched.cycles_at(tsc::rdtsc());
+        CpuUsage {
+            cpu: cpu_id as u32,
+            ready: sched.total_ready_count(),
+            busy_cycles,
+            idle_cycles,
+            ticks: sched.total_ticks.load(Ordering::Relaxed),
+            switches: sched.total_switches.load(Ordering::Relaxed),
+            preemptions: sched.total_preemptions.load(Ordering::Relaxed),
+        }
+    })
+}
+
 pub fn get_total_yields() -> u64 {
     let mut total = 0u64;
     let cpu_count = slopos_lib::get_cpu_count();
@@ -10,6 +10,7 @@ use super::task::{
     INVALID_TASK_ID, TASK_FLAG_KERNEL_MODE, TASK_PRIORITY_IDLE, Task, reap_zombies, task_get_info,
     task_set_current,
 };
+use super::tickless;
 use super::work_steal::try_work_steal;
 
 static IDLE_WAKEUP_CB: OnceLock<IrqMutex<Option<fn() -> c_int>>> = OnceLock::new();
@@ -21,6 +22,12 @@ pub fn scheduler_register_idle_wakeup_callback(callback: Option<fn() -> c_int>)
     }
 }
 
+/// Whether a driver polls from the idle loop, which then must keep running
+/// periodically even with the tick stopped.
+pub(crate) fn idle_wakeup_registered() -> bool {
+    IDLE_WAKEUP_CB.get().is_some_and(|m| m.lock().is_some())
+}
+
 fn unified_idle_loop(_: *mut c_void) {
     loop {
         let cb = IDLE_WAKEUP_CB.get().and_then(|m| *m.lock());
@@ -34,6 +41,7 @@ fn unified_idle_loop(_: *mut c_void) {
         per_cpu::with_cpu_scheduler(cpu_id, |sched| {
             sched.increment_idle_time();
         });
+        tickless::enter_idle(cpu_id);
         unsafe {
             core::arch::asm!("sti; hlt; cli", options(nomem, nostack));
         }
@@ -195,6 +203,7 @@ fn scheduler_loop(cpu_id: usize, idle_task: *mut Task) -> ! {
         per_cpu::with_cpu_scheduler(cpu_id, |sched| {
             sched.increment_idle_time();
         });
+        tickless::enter_idle(cpu_id);
 
         unsafe {
             core::arch::asm!("sti; hlt; cli", options(nomem, nostack));
@@ -9,11 +9,14 @@
 
 use core::ffi::{c_char, c_void};
 use core::ptr;
+use core::sync::atomic::Ordering;
+
+use slopos_abi::task::{CpuUsage, TaskSnapshot};
 
 use slopos_lib::klog_info;
 use slopos_lib::testing::TestResult;
 
-use super::per_cpu::{pause_all_aps, resume_all_aps_if_not_nested};
+use super::per_cpu::{PerCpuScheduler, pause_all_aps, resume_all_aps_if_not_nested};
 use super::runtime::{self, IdleStackResolveError};
 use super::scheduler::{
     self, get_scheduler_stats, init_scheduler, schedule, schedule_task, scheduler_is_enabled,
@@ -23,7 +26,7 @@ use super::task::{
     INVALID_PROCESS_ID, INVALID_TASK_ID, IdtEntry, MAX_TASKS, TASK_FLAG_KERNEL_MODE,
     TASK_FLAG_USER_MODE, TASK_PRIORITY_HIGH, TASK_PRIORITY_IDLE, TASK_PRIORITY_LOW,
     TASK_PRIORITY_NORMAL, Task, TaskStatus, init_task_manager, task_create, task_find_by_id,
-    task_get_info, task_set_state, task_shutdown_all, task_terminate,
+    task_get_info, task_set_state, task_shutdown_all, task_snapshot, task_terminate,
 };
 use slopos_lib::arch::gdt::SegmentSelector;
 use slopos_lib::arch::idt::SYSCALL_VECTOR;
@@ -1602,6 +1605,100 @@ pub fn test_scheduler_wakeup_race_stress_baseline() -> TestResult {
     TestResult::Pass
 }
 
+// =============================================================================
+// CPU ACCOUNTING TESTS
+// Busy and idle cycles credited at switches and ticks
+// =============================================================================
+
+pub fn test_cpu_cycle_accounting() -> TestResult {
+    let sched = PerCpuScheduler::new();
+
+    // The first credit only starts the clock.
+    sched.account_cycles(1000, false);
+    sched.account_cycles(1500, false);
+    sched.account_cycles(1700, true);
+    if sched.busy_cycles.load(Ordering::Relaxed) != 500
+        || sched.idle_cycles.load(Ordering::Relaxed) != 200
+    {
+        return TestResult::Fail;
+    }
+
+    // A timestamp behind the last credit adds nothing and leaves the clock
+    // where it was.
+    sched.account_cycles(1600, false);
+    sched.account_cycles(1750, false);
+    if sched.busy_cycles.load(Ordering::Relaxed) != 550 {
+        return TestResult::Fail;
+    }
+
+    // With no current task the stretch since the last credit is idle.
+    if sched.cycles_at(1850) != (550, 300) {
+        return TestResult::Fail;
+    }
+
+    let earlier = CpuUsage {
+        busy_cycles: 100,
+        idle_cycles: 100,
+        ..CpuUsage::default()
+    };
+    let later = CpuUsage {
+        busy_cycles: 400,
+        idle_cycles: 200,
+        ..CpuUsage::default()
+    };
+    if later.busy_percent_since(&earlier) != 75 || earlier.busy_percent_since(&earlier) != 0 {
+        return TestResult::Fail;
+    }
+
+    TestResult::Pass
+}
+
+pub fn test_task_snapshot_reports_tasks() -> TestResult {
+    let _fixture = SchedFixture::new();
+    let task_id = task_create(
+        b"SnapshotTest\0".as_ptr() as *const c_char,
+        dummy_task_fn,
+        ptr::null_mut(),
+        TASK_PRIORITY_HIGH,
+        TASK_FLAG_KERNEL_MODE,
+    );
+    if task_id == INVALID_TASK_ID {
+        return TestResult::Fail;
+    }
+    let task = task_find_by_id(task_id);
+    if task.is_null() {
+        return TestResult::Fail;
+    }
+    unsafe { (*task).total_runtime = 5000 };
+
+    let mut snapshots = [TaskSnapshot::default(); MAX_TASKS];
+    let count = task_snapshot(&mut snapshots);
+    let Some(entry) = snapshots[..count].iter().find(|s| s.task_id == task_id) else {
+        return TestResult::Fail;
+    };
+    if entry.name_str() != "SnapshotTest"
+        || entry.priority != TASK_PRIORITY_HIGH
+        || entry.status() != TaskStatus::Ready
+        || entry.runtime != 5000
+        || entry.rss_pages != 0
+        || !entry.title_str().is_empty()
+    {
+        return TestResult::Fail;
+    }
+
+    // Half of the time between two snapshots spent running is 50%.
+    let later = TaskSnapshot {
+        runtime: entry.runtime + 500,
+        taken_at: entry.taken_at + 1000,
+        ..*entry
+    };
+    if later.cpu_percent_since(entry) != 50 || entry.cpu_percent_since(entry) != 0 {
+        return TestResult::Fail;
+    }
+
+    TestResult::Pass
+}
+
 slopos_lib::define_test_suite!(
     sched_core,
     [
@@ -1643,5 +1740,7 @@ slopos_lib::define_test_suite!(
         test_cross_cpu_schedule_lockfree,
         test_privilege_separation_invariants,
         test_scheduler_wakeup_race_stress_baseline,
+        test_cpu_cycle_accounting,
+        test_task_snapshot_reports_tasks,
     ]
 );
@@ -4,12 +4,15 @@ use core::sync::atomic::Ordering;
 
 use slopos_lib::cpu;
 use slopos_lib::preempt::PreemptGuard;
+use slopos_lib::tsc;
 
 use slopos_lib::kdiag_timestamp;
 use slopos_lib::klog_info;
+use slopos_lib::ktrace::{KTRACE_NO_TASK, KtraceKind, ktrace_is_enabled, ktrace_record};
 
 use crate::platform;
 
+use super::ktimer;
 pub use super::lifecycle::{
     boot_step_idle_task, boot_step_scheduler_init, boot_step_task_manager_init,
     get_percpu_scheduler_stats, get_scheduler_stats, get_total_ready_tasks_all_cpus,
@@ -29,6 +32,7 @@ use super::task::{
     task_pointer_is_valid, task_record_context_switch, task_record_yield, task_set_current,
     task_set_state,
 };
+use super::tickless;
 pub use super::trap::{
     RescheduleReason, TrapExitSource, save_preempt_context, save_task_context_from_interrupt_frame,
     scheduler_handle_post_irq, scheduler_handle_timer_interrupt, scheduler_handoff_on_trap_exit,
@@ -400,6 +404,10 @@ fn execute_task(cpu_id: usize, from_task: *mut Task, to_task: *mut Task) {
     });
     task_set_current(to_task);
 
+    if !per_cpu::is_idle_task(to_task) {
+        tickless::leave_idle(cpu_id);
+    }
+
     unsafe {
         let is_user_mode = (*to_task).flags & TASK_FLAG_USER_MODE != 0;
 
@@ -857,9 +865,24 @@ pub fn unblock_task(task: *mut Task) -> c_int {
 
     core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
 
+    trace_wakeup(task);
     schedule_task(task)
 }
 
+/// Record that the current task made `task` runnable.
+pub(crate) fn trace_wakeup(task: *mut Task) {
+    if !ktrace_is_enabled() {
+        return;
+    }
+    let current = scheduler_get_current_task();
+    let waker = if current.is_null() {
+        KTRACE_NO_TASK
+    } else {
+        unsafe { (*current).task_id }
+    };
+    ktrace_record(KtraceKind::SchedWakeup, unsafe { (*task).task_id }, waker);
+}
+
 /// Attempt to wake a task that was waiting on `completed_id`.
 /// Returns true if THIS caller won the wake race and should handle the task.
 /// Returns false if another caller already woke it or task wasn't waiting on this ID.
@@ -902,6 +925,7 @@ pub fn try_wake_from_task_wait(task: *mut Task, completed_id: u32) -> bool {
             core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
 
             // Enqueue the task
+            trace_wakeup(task);
             if schedule_task(task) != 0 {
                 klog_info!(
                     "try_wake_from_task_wait: failed to schedule task {}",
@@ -1036,6 +1060,10 @@ pub fn scheduler_is_preemption_enabled() -> c_int {
 
 pub fn scheduler_timer_tick() {
     let cpu_id = slopos_lib::get_current_cpu();
+    tickless::rearm_tick(cpu_id);
+    if cpu_id == 0 {
+        ktimer::ktimer_tick();
+    }
     let (current, idle_task) = scheduler_tasks_for_cpu(cpu_id);
 
     let preempt_active = PreemptGuard::is_active();
@@ -1049,6 +1077,7 @@ pub fn scheduler_timer_tick() {
     per_cpu::with_cpu_scheduler(cpu_id, |sched| {
         sched.drain_remote_inbox();
         sched.increment_ticks();
+        sched.account_cycles(tsc::rdtsc(), current.is_null() || running_idle);
     });
 
     wake_due_sleepers(platform::timer_ticks());
@@ -2,9 +2,11 @@ use core::ffi::c_int;
 
 use slopos_abi::task::{BlockReason, MAX_TASKS};
 use slopos_lib::IrqMutex;
+use slopos_lib::lockstat::LockClass;
 
 use super::scheduler::{
-    is_scheduling_active, schedule, schedule_task, scheduler_get_current_task, unschedule_task,
+    is_scheduling_active, schedule, schedule_task, scheduler_get_current_task, trace_wakeup,
+    unschedule_task,
 };
 use super::task::{
     INVALID_TASK_ID, TaskStatus, task_find_by_id, task_is_blocked, task_is_invalid,
@@ -77,6 +79,16 @@ impl SleepQueue {
         }
     }
 
+    /// Earliest wake tick, compared relative to `now_tick` so wraparound
+    /// orders correctly.
+    fn earliest(&self, now_tick: u64) -> Option<u64> {
+        self.entries
+            .iter()
+            .filter(|e| e.active)
+            .map(|e| e.wake_tick)
+            .min_by_key(|&tick| tick.wrapping_sub(now_tick) as i64)
+    }
+
     fn collect_due(&mut self, now_tick: u64, out: &mut [u32; MAX_TASKS]) -> usize {
         let mut count = 0usize;
         for entry in self.entries.iter_mut() {
@@ -95,14 +107,16 @@ impl SleepQueue {
     }
 }
 
-static SLEEP_QUEUE: IrqMutex<SleepQueue> = IrqMutex::new(SleepQueue::new());
+static SLEEP_QUEUE_LOCK_CLASS: LockClass = LockClass::new("sleep_queue");
+static SLEEP_QUEUE: IrqMutex<SleepQueue> =
+    IrqMutex::with_class(&SLEEP_QUEUE_LOCK_CLASS, SleepQueue::new());
 
 #[inline]
 fn tick_reached(now_tick: u64, deadline_tick: u64) -> bool {
     now_tick.wrapping_sub(deadline_tick) < (1u64 << 63)
 }
 
-fn ms_to_sleep_ticks(ms: u32) -> u64 {
+pub(crate) fn ms_to_sleep_ticks(ms: u32) -> u64 {
     let freq = platform::timer_frequency() as u64;
     if freq == 0 {
         return 1;
@@ -133,6 +147,7 @@ fn wake_sleeping_task(task_id: u32) {
     }
 
     core::sync::atomic::fence(core::sync::atomic::Ordering::SeqCst);
+    trace_wakeup(task);
     let _ = schedule_task(task);
 }
 
@@ -148,6 +163,11 @@ pub fn wake_due_sleepers(now_tick: u64) {
     }
 }
 
+/// Tick at which the next sleeper is due, if anyone is sleeping.
+pub fn next_wake_tick() -> Option<u64> {
+    SLEEP_QUEUE.lock().earliest(platform::timer_ticks())
+}
+
 pub fn reset_sleep_queue() {
     SLEEP_QUEUE.lock().clear();
 }
@@ -0,0 +1,113 @@
+//! Stack high-water tests: the poison scan, peak tracking and the per-task
+//! marks reported by `task_stack_usage`.
+
+use core::ffi::c_void;
+use core::ptr;
+
+use slopos_lib::stack_watermark::{
+    STACK_POISON, STACK_WATERMARK_ENABLED, StackPeak, stack_high_water, stack_poison,
+    stack_usage_is_high,
+};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::sched_tests::SchedFixture;
+use super::task::{
+    INVALID_TASK_ID, MAX_TASKS, TASK_FLAG_KERNEL_MODE, TASK_PRIORITY_NORMAL, TASK_STACK_SIZE,
+    TaskStackUsage, task_create, task_find_by_id, task_record_stack_usage, task_stack_usage,
+};
+
+fn idle_task_fn(_arg: *mut c_void) {}
+
+pub fn test_stack_scan_finds_deepest_write() -> TestResult {
+    if !STACK_WATERMARK_ENABLED {
+        return pass!();
+    }
+    let mut stack = [0u64; 64];
+    let base = stack.as_mut_ptr() as u64;
+    let size = (stack.len() * 8) as u64;
+    unsafe { stack_poison(base, size) };
+    assert_test!(stack.iter().all(|&w| w == STACK_POISON), "fill incomplete");
+    assert_eq_test!(
+        unsafe { stack_high_water(base, size, 0) },
+        0,
+        "unused stack"
+    );
+
+    // Two writes; the deeper one sets the mark even though the words
+    // between them still hold the pattern.
+    let words = base as *mut u64;
+    unsafe {
+        words.add(60).write_volatile(1);
+        words.add(40).write_volatile(0);
+    }
+    let used = unsafe { stack_high_water(base, size, 0) };
+    assert_eq_test!(used, size - 40 * 8, "deepest write");
+    assert_eq_test!(
+        unsafe { stack_high_water(base, size, size - 8) },
+        size - 8,
+        "known mark lowered"
+    );
+    pass!()
+}
+
+pub fn test_stack_peak_and_threshold() -> TestResult {
+    let peak = StackPeak::new();
+    assert_test!(peak.record(100), "first sample not a peak");
+    assert_test!(!peak.record(50), "smaller sample counted as peak");
+    assert_test!(peak.record(200));
+    assert_eq_test!(peak.get(), 200);
+    peak.reset();
+    assert_eq_test!(peak.get(), 0);
+
+    assert_test!(!stack_usage_is_high(3 * 1024, 4 * 1024), "75% is not above");
+    assert_test!(stack_usage_is_high(3 * 1024 + 8, 4 * 1024));
+    assert_test!(!stack_usage_is_high(0, 0), "empty stack");
+    pass!()
+}
+
+pub fn test_task_stack_usage_reports_tasks() -> TestResult {
+    let _fixture = SchedFixture::new();
+    let task_id = task_create(
+        c"StackTest".as_ptr(),
+        idle_task_fn,
+        ptr::null_mut(),
+        TASK_PRIORITY_NORMAL,
+        TASK_FLAG_KERNEL_MODE,
+    );
+    if task_id == INVALID_TASK_ID {
+        return fail!("task_create failed");
+    }
+    let task = task_find_by_id(task_id);
+    if task.is_null() {
+        return fail!("task {} not found", task_id);
+    }
+
+    // Pretend the task ran 1 KiB deep, then switched out.
+    let (base, size) = unsafe { ((*task).kernel_stack_base, (*task).kernel_stack_size) };
+    assert_eq_test!(size, TASK_STACK_SIZE);
+    unsafe { *((base + size - 1024) as *mut u64) = 0 };
+    task_record_stack_usage(task);
+
+    let mut usage = [TaskStackUsage::default(); MAX_TASKS];
+    let count = task_stack_usage(&mut usage);
+    let Some(entry) = usage[..count].iter().find(|u| u.task_id == task_id) else {
+        return fail!("task {} not listed", task_id);
+    };
+    assert_eq_test!(&entry.name[..10], b"StackTest\0");
+    assert_eq_test!(entry.stack_size, TASK_STACK_SIZE);
+    assert_eq_test!(entry.flags, TASK_FLAG_KERNEL_MODE as u32);
+    let expected = if STACK_WATERMARK_ENABLED { 1024 } else { 0 };
+    assert_eq_test!(entry.high_water, expected, "high-water mark");
+    assert_test!(unsafe { !(*task).kernel_stack_warned }, "warned at 1 KiB");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    stack_watermark,
+    [
+        test_stack_scan_finds_deepest_write,
+        test_stack_peak_and_threshold,
+        test_task_stack_usage_reports_tasks,
+    ]
+);
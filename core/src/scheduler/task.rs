@@ -3,13 +3,20 @@ use core::mem;
 use core::ptr;
 use core::sync::atomic::Ordering;
 
+use slopos_abi::crashdump::CrashDumpTask;
 use slopos_abi::syscall::TtyIndex;
 use slopos_lib::IrqMutex;
+use slopos_lib::lockstat::LockClass;
 
 use slopos_lib::cpu;
 use slopos_lib::kdiag_timestamp;
+use slopos_lib::ktrace::{KTRACE_NO_TASK, KtraceKind, ktrace_record};
+use slopos_lib::stack_watermark::{
+    STACK_WATERMARK_ENABLED, stack_high_water, stack_poison, stack_usage_is_high,
+};
 use slopos_lib::string::bytes_as_str;
-use slopos_lib::{klog_debug, klog_info};
+use slopos_lib::tsc;
+use slopos_lib::{klog_debug, klog_info, klog_warn};
 
 // =============================================================================
 // Zombie List for Deferred Task Reclamation
@@ -117,7 +124,7 @@ pub use slopos_abi::task::{
     TASK_FLAG_DISPLAY_EXCLUSIVE, TASK_FLAG_KERNEL_MODE, TASK_FLAG_NO_PREEMPT, TASK_FLAG_SYSTEM,
     TASK_FLAG_USER_MODE, TASK_KERNEL_STACK_SIZE, TASK_NAME_MAX_LEN, TASK_PRIORITY_HIGH,
     TASK_PRIORITY_IDLE, TASK_PRIORITY_LOW, TASK_PRIORITY_NORMAL, TASK_STACK_SIZE, TaskExitReason,
-    TaskExitRecord, TaskFaultReason, TaskStatus,
+    TaskExitRecord, TaskFaultReason, TaskSnapshot, TaskStackUsage, TaskStatus,
 };
 pub use slopos_lib::arch::idt::IdtEntry;
 
@@ -230,7 +237,9 @@ impl TaskManagerInner {
     }
 }
 
-static TASK_MANAGER: IrqMutex<TaskManagerInner> = IrqMutex::new(TaskManagerInner::new());
+static TASK_MANAGER_LOCK_CLASS: LockClass = LockClass::new("task_manager");
+static TASK_MANAGER: IrqMutex<TaskManagerInner> =
+    IrqMutex::with_class(&TASK_MANAGER_LOCK_CLASS, TaskManagerInner::new());
 
 use slopos_fs::fileio::{
     fileio_clone_table_for_process, fileio_create_table_for_process,
@@ -240,7 +249,7 @@ use slopos_lib::kernel_services::syscall_services::tty;
 use slopos_mm::kernel_heap::{kfree, kmalloc};
 use slopos_mm::process_vm::{
     create_process_vm, destroy_process_vm, process_vm_clone_cow, process_vm_get_page_dir,
-    process_vm_get_stack_top,
+    process_vm_get_stack_top, process_vm_resident_pages,
 };
 use slopos_mm::shared_memory::shm_cleanup_task;
 use slopos_mm::user_copy::copy_to_user;
@@ -475,6 +484,8 @@ impl KernelStackLease {
             klog_info!("{}", failure_message);
             return None;
         }
+        // SAFETY: fresh allocation of `size` bytes that no task runs on yet.
+        unsafe { stack_poison(stack as u64, size) };
         Some(Self { base: stack })
     }
 
@@ -556,6 +567,7 @@ fn reset_task_runtime_fields(task: &mut Task) {
     task.time_slice_remaining = task.time_slice;
     task.total_runtime = 0;
     task.creation_time = kdiag_timestamp();
+    task.start_ns = slopos_lib::clock::monotonic_ns();
     task.yield_count = 0;
     task.last_run_timestamp = 0;
     task.waiting_on.store(INVALID_TASK_ID, Ordering::Release);
@@ -568,6 +580,7 @@ fn reset_task_runtime_fields(task: &mut Task) {
     task.next_ready = ptr::null_mut();
     task.next_inbox.store(ptr::null_mut(), Ordering::Release);
     task.refcnt.store(0, Ordering::Release);
+    task.perf.clear();
 }
 
 enum TaskProcessCleanupMode {
@@ -888,9 +901,7 @@ pub fn task_create(
         return INVALID_TASK_ID;
     }
 
-    task_ref.kernel_stack_base = resources.kernel_stack_base;
-    task_ref.kernel_stack_top = resources.kernel_stack_base + resources.kernel_stack_size;
-    task_ref.kernel_stack_size = resources.kernel_stack_size;
+    task_ref.set_kernel_stack(resources.kernel_stack_base, resources.kernel_stack_size);
     task_ref.entry_point = entry_point as usize as u64;
     task_ref.entry_arg = arg;
     task_ref.time_slice = 10;
@@ -990,6 +1001,8 @@ fn mark_task_terminated(task_ptr: *mut Task, resolved_id: u32) {
             (*task_ptr).fault_reason,
             (*task_ptr).exit_code,
         );
+        crate::audit::audit_task_exit(&*task_ptr);
+        crate::perf::perf_task_exit(resolved_id);
         (*task_ptr).set_status(TaskStatus::Terminated);
         scheduler::cancel_sleep(resolved_id);
         (*task_ptr).fate_token = 0;
@@ -1202,6 +1215,10 @@ pub fn get_task_stats(total_tasks: *mut u32, active_tasks: *mut u32, context_swi
 }
 
 pub fn task_record_context_switch(from: *mut Task, to: *mut Task, timestamp: u64) {
+    crate::per_cpu::with_local_scheduler(|sched| {
+        sched.account_cycles(tsc::rdtsc(), from.is_null() || from == sched.idle_task());
+    });
+
     if !from.is_null() {
         unsafe {
             if (*from).last_run_timestamp != 0 && timestamp >= (*from).last_run_timestamp {
@@ -1220,6 +1237,144 @@ pub fn task_record_context_switch(from: *mut Task, to: *mut Task, timestamp: u64
             mgr.total_context_switches += 1;
         });
     }
+
+    if to != from {
+        task_record_stack_usage(from);
+        let id = |task: *mut Task| {
+            if task.is_null() {
+                KTRACE_NO_TASK
+            } else {
+                unsafe { (*task).task_id }
+            }
+        };
+        ktrace_record(KtraceKind::SchedSwitch, id(from), id(to));
+        // SAFETY: both are live task slots (or null) for the whole switch.
+        crate::perf::perf_switch(unsafe { from.as_ref() }, unsafe { to.as_ref() });
+    }
+}
+
+/// Update `task`'s kernel stack high-water mark.  Runs on the outgoing
+/// task's CPU before the switch, so the stack is not changing under it.
+pub(crate) fn task_record_stack_usage(task: *mut Task) {
+    if !STACK_WATERMARK_ENABLED || task.is_null() {
+        return;
+    }
+    let task = unsafe { &mut *task };
+    if task.kernel_stack_base == 0 {
+        return;
+    }
+    // SAFETY: the stack was poisoned by KernelStackLease::allocate and
+    // stays allocated while the task exists.
+    let used = unsafe {
+        stack_high_water(
+            task.kernel_stack_base,
+            task.kernel_stack_size,
+            task.kernel_stack_high_water,
+        )
+    };
+    task.kernel_stack_high_water = used;
+    if !task.kernel_stack_warned && stack_usage_is_high(used, task.kernel_stack_size) {
+        task.kernel_stack_warned = true;
+        klog_warn!(
+            "task {} ({}) has used {} of {} kernel stack bytes",
+            task.task_id,
+            bytes_as_str(&task.name),
+            used,
+            task.kernel_stack_size
+        );
+    }
+}
+
+/// Copy the kernel stack usage of live tasks into `out`.  Returns how many
+/// entries were written.
+pub fn task_stack_usage(out: &mut [TaskStackUsage]) -> usize {
+    with_task_manager(|mgr| {
+        let live = mgr
+            .tasks
+            .iter()
+            .filter(|task| task.status() != TaskStatus::Invalid && task.task_id != INVALID_TASK_ID);
+        let mut count = 0;
+        for (slot, task) in out.iter_mut().zip(live) {
+            *slot = TaskStackUsage {
+                task_id: task.task_id,
+                flags: task.flags as u32,
+                name: task.name,
+                stack_size: task.kernel_stack_size,
+                high_water: task.kernel_stack_high_water,
+            };
+            count += 1;
+        }
+        count
+    })
+}
+
+/// Describe the live tasks in `out` for `SYSCALL_TASK_SNAPSHOT`, leaving
+/// `title` empty.  Returns how many were written.
+pub fn task_snapshot(out: &mut [TaskSnapshot]) -> usize {
+    let mut process_ids = [INVALID_PROCESS_ID; MAX_TASKS];
+    let count = with_task_manager(|mgr| {
+        let now = kdiag_timestamp();
+        let live = mgr
+            .tasks
+            .iter()
+            .filter(|task| task.status() != TaskStatus::Invalid && task.task_id != INVALID_TASK_ID);
+        let mut count = 0;
+        for ((slot, process_id), task) in out.iter_mut().zip(&mut process_ids).zip(live) {
+            let mut runtime = task.total_runtime;
+            if task.status() == TaskStatus::Running && task.last_run_timestamp != 0 {
+                runtime += now.saturating_sub(task.last_run_timestamp);
+            }
+            *slot = TaskSnapshot {
+                task_id: task.task_id,
+                parent_id: task.parent_task_id,
+                status: task.status().as_u8(),
+                priority: task.priority,
+                cpu: task.last_cpu,
+                flags: task.flags as u32,
+                uid: task.creds.uid,
+                runtime,
+                taken_at: now,
+                name: task.name,
+                ..TaskSnapshot::default()
+            };
+            *process_id = task.process_id;
+            count += 1;
+        }
+        count
+    });
+    // The address space lock is taken outside the task table's.
+    for (slot, &process_id) in out[..count].iter_mut().zip(&process_ids) {
+        if process_id != INVALID_PROCESS_ID {
+            slot.rss_pages = process_vm_resident_pages(process_id);
+        }
+    }
+    count
+}
+
+/// Describe the live tasks in `out` for a kernel crash dump.  Returns how
+/// many were written, or `None` when the task table is locked; the panic
+/// path cannot wait for it.
+pub fn task_crash_dump(out: &mut [CrashDumpTask]) -> Option<usize> {
+    let mgr = TASK_MANAGER.try_lock()?;
+    let live = mgr
+        .tasks
+        .iter()
+        .filter(|task| task.status() != TaskStatus::Invalid && task.task_id != INVALID_TASK_ID);
+    let mut count = 0;
+    for (slot, task) in out.iter_mut().zip(live) {
+        *slot = CrashDumpTask {
+            task_id: task.task_id,
+            parent_id: task.parent_task_id,
+            status: task.status().as_u8(),
+            cpu: task.last_cpu,
+            priority: task.priority,
+            flags: task.flags,
+            runtime: task.total_runtime,
+            name: task.name,
+        };
+        count += 1;
+    }
+    Some(count)
 }
 
 pub fn task_record_yield(task: *mut Task) {
@@ -1419,9 +1574,7 @@ pub fn task_fork(parent_task: *mut Task, syscall_frame: *const slopos_lib::Inter
     child.clear_child_tid = 0;
     child.set_status(TaskStatus::Ready);
 
-    child.kernel_stack_base = child_kernel_stack_base;
-    child.kernel_stack_top = child_kernel_stack_base + TASK_KERNEL_STACK_SIZE;
-    child.kernel_stack_size = TASK_KERNEL_STACK_SIZE;
+    child.set_kernel_stack(child_kernel_stack_base, TASK_KERNEL_STACK_SIZE);
 
     // Build the child's context from the SYSCALL entry frame, NOT from
     // the parent's Task.context (which may reflect a timer-tick preemption
@@ -1465,6 +1618,7 @@ pub fn task_fork(parent_task: *mut Task, syscall_frame: *const slopos_lib::Inter
     }
 
     reset_task_runtime_fields(child);
+    crate::perf::perf_inherit(parent, child);
     // Ownership is now carried by the child task lifecycle.
     let _ = child_process.disarm();
     let _ = child_kernel_stack.disarm();
@@ -1604,9 +1758,7 @@ pub fn task_clone(
     child.set_status(TaskStatus::Ready);
 
     // Set up kernel stack.
-    child.kernel_stack_base = child_kernel_stack_base;
-    child.kernel_stack_top = child_kernel_stack_base + TASK_KERNEL_STACK_SIZE;
-    child.kernel_stack_size = TASK_KERNEL_STACK_SIZE;
+    child.set_kernel_stack(child_kernel_stack_base, TASK_KERNEL_STACK_SIZE);
 
     // Child returns 0 from clone.
     child.context.rax = 0;
@@ -1637,6 +1789,7 @@ pub fn task_clone(
     // If sharing VM, CR3 is already inherited from clone_from_raw.
 
     reset_task_runtime_fields(child);
+    crate::perf::perf_inherit(parent, child);
     // Ownership is now carried by the child task lifecycle.
     if !share_vm {
         let _ = child_process.disarm();
@@ -9,11 +9,15 @@ use core::mem::offset_of;
 use core::ptr;
 use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicU32, AtomicU64, Ordering};
 
+use slopos_abi::rlimit::RLimit;
 use slopos_abi::signal::{NSIG, SIG_DFL, SIG_EMPTY, SigSet};
 use slopos_abi::syscall::TtyIndex;
 
+use crate::perf::TaskPerf;
+use crate::syscall::filter::TaskSyscallFilter;
+
 pub use slopos_abi::task::{
-    BlockReason, INVALID_PROCESS_ID, INVALID_TASK_ID, MAX_TASKS, TASK_FLAG_COMPOSITOR,
+    BlockReason, Credentials, INVALID_PROCESS_ID, INVALID_TASK_ID, MAX_TASKS, TASK_FLAG_COMPOSITOR,
     TASK_FLAG_DISPLAY_EXCLUSIVE, TASK_FLAG_FPU_INITIALIZED, TASK_FLAG_KERNEL_MODE,
     TASK_FLAG_NO_PREEMPT, TASK_FLAG_SYSTEM, TASK_FLAG_USER_MODE, TASK_KERNEL_STACK_SIZE,
     TASK_NAME_MAX_LEN, TASK_PRIORITY_HIGH, TASK_PRIORITY_IDLE, TASK_PRIORITY_LOW,
@@ -386,6 +390,8 @@ pub struct Task {
     pub time_slice_remaining: u64,
     pub total_runtime: u64,
     pub creation_time: u64,
+    /// Monotonic clock at creation, for process accounting.
+    pub start_ns: u64,
     pub yield_count: u32,
     pub last_run_timestamp: u64,
     pub waiting_on: AtomicU32,
@@ -400,6 +406,19 @@ pub struct Task {
     pub cpu_affinity: u32,
     pub last_cpu: u8,
     pub migration_count: u32,
+    /// Deepest kernel stack use seen so far, in bytes from the top.  Only
+    /// maintained when `STACK_WATERMARK_ENABLED`.
+    pub kernel_stack_high_water: u64,
+    /// Set once the task has been reported as close to a stack overflow.
+    pub kernel_stack_warned: bool,
+    /// Performance counters this task feeds.
+    pub perf: TaskPerf,
+    /// Syscalls this task may make; kept across exec, copied to children.
+    pub syscall_filter: TaskSyscallFilter,
+    /// User and group IDs; inherited by children, kept across exec.
+    pub creds: Credentials,
+    /// `RLIMIT_CORE`; inherited by children, kept across exec.
+    pub core_limit: RLimit,
     // --- Signal state ---
     /// Bitmask of pending signals (written atomically by kill()).
     pub signal_pending: AtomicU64,
@@ -456,6 +475,7 @@ impl Task {
             time_slice_remaining: 0,
             total_runtime: 0,
             creation_time: 0,
+            start_ns: 0,
             yield_count: 0,
             last_run_timestamp: 0,
             waiting_on: AtomicU32::new(INVALID_TASK_ID),
@@ -470,6 +490,12 @@ impl Task {
             cpu_affinity: 0,
             last_cpu: 0,
             migration_count: 0,
+            kernel_stack_high_water: 0,
+            kernel_stack_warned: false,
+            perf: TaskPerf::new(),
+            syscall_filter: TaskSyscallFilter::new(),
+            creds: Credentials::ROOT,
+            core_limit: RLimit::CORE_DEFAULT,
             signal_pending: AtomicU64::new(0),
             signal_blocked: SIG_EMPTY,
             signal_actions: [SignalAction::default(); NSIG],
@@ -582,6 +608,15 @@ impl Task {
         self.signal_pending = AtomicU64::new(0);
     }
 
+    /// Install a kernel stack and restart its high-water tracking.
+    pub fn set_kernel_stack(&mut self, base: u64, size: u64) {
+        self.kernel_stack_base = base;
+        self.kernel_stack_top = base + size;
+        self.kernel_stack_size = size;
+        self.kernel_stack_high_water = 0;
+        self.kernel_stack_warned = false;
+    }
+
     #[inline]
     pub fn inc_ref(&self) -> u32 {
         let prev = self.refcnt.load(Ordering::Acquire);
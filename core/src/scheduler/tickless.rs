@@ -0,0 +1,91 @@
+//! Tickless timer policy.
+//!
+//! The LAPIC timer runs one-shot.  A CPU executing a task keeps it armed
+//! one tick ahead so time slices are still charged per tick; an idle CPU
+//! arms it only for the earliest sleeper, or not at all, and then sits in
+//! `hlt` until something else interrupts it.  Every idle CPU arms for the
+//! same sleeper deadline since the sleep queue is global.
+//!
+//! Drivers that poll from the idle loop (network timers, console input)
+//! get a coarse wakeup on the BSP only; the APs still halt indefinitely.
+//! Kernel timers fire on the BSP's tick, so it also wakes for the earliest
+//! of those.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_lib::MAX_CPUS;
+
+use super::ktimer::next_ktimer_tick;
+use super::runtime::idle_wakeup_registered;
+use super::sleep::next_wake_tick;
+use crate::platform;
+
+/// Idle-loop poll interval on the BSP while an idle wakeup callback exists.
+const IDLE_POLL_NS: u64 = 100_000_000;
+
+/// Set while a CPU's idle path has stopped its periodic tick.
+static TICK_STOPPED: [AtomicBool; MAX_CPUS] = [const { AtomicBool::new(false) }; MAX_CPUS];
+
+fn tick_ns() -> u64 {
+    match platform::timer_frequency() {
+        0 => 10_000_000,
+        hz => 1_000_000_000 / hz as u64,
+    }
+}
+
+fn delay_until_tick_ns(tick: u64) -> u64 {
+    let due_ns = tick.saturating_mul(tick_ns());
+    due_ns.saturating_sub(platform::clock_monotonic_ns())
+}
+
+/// Nanoseconds until the earliest sleeper is due, `None` with no sleepers.
+pub fn next_sleeper_delay_ns() -> Option<u64> {
+    next_wake_tick().map(delay_until_tick_ns)
+}
+
+/// Timer interrupt: arm the next tick for whatever this CPU runs next.
+/// The idle loop overrides this with [`enter_idle`] before halting.
+pub(crate) fn rearm_tick(cpu_id: usize) {
+    if let Some(flag) = TICK_STOPPED.get(cpu_id) {
+        flag.store(false, Ordering::Relaxed);
+    }
+    platform::timer_arm_ns(tick_ns());
+}
+
+/// Idle loop, interrupts disabled, right before `sti; hlt`.
+pub(crate) fn enter_idle(cpu_id: usize) {
+    if let Some(flag) = TICK_STOPPED.get(cpu_id) {
+        flag.store(true, Ordering::Relaxed);
+    }
+    let poll_ns = (cpu_id == 0 && idle_wakeup_registered()).then_some(IDLE_POLL_NS);
+    let ktimer_ns = if cpu_id == 0 {
+        next_ktimer_tick().map(delay_until_tick_ns)
+    } else {
+        None
+    };
+    let delay_ns = [next_sleeper_delay_ns(), poll_ns, ktimer_ns]
+        .into_iter()
+        .flatten()
+        .min();
+    match delay_ns {
+        Some(delay_ns) => platform::timer_arm_ns(delay_ns),
+        None => platform::timer_disarm(),
+    }
+}
+
+/// Dispatching a real task: restart the tick if the idle path stopped it.
+pub(crate) fn leave_idle(cpu_id: usize) {
+    let stopped = TICK_STOPPED
+        .get(cpu_id)
+        .is_some_and(|flag| flag.swap(false, Ordering::Relaxed));
+    if stopped {
+        platform::timer_arm_ns(tick_ns());
+    }
+}
+
+/// Whether `cpu_id` is currently idling with its tick stopped.
+pub fn tick_stopped(cpu_id: usize) -> bool {
+    TICK_STOPPED
+        .get(cpu_id)
+        .is_some_and(|flag| flag.load(Ordering::Relaxed))
+}
@@ -0,0 +1,40 @@
+//! Stack protector tests: the boot guard is random and protected frames
+//! built after it still check out.
+
+use core::hint::black_box;
+
+use slopos_lib::stack_protector::{STACK_GUARD_DEFAULT, guard, guard_randomized};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+
+/// Has a local array, so `-Z stack-protector=strong` gives it a canary.
+#[inline(never)]
+fn fill_and_sum(len: usize) -> u32 {
+    let mut buf = [0u8; 96];
+    for (i, byte) in buf.iter_mut().take(len).enumerate() {
+        *byte = i as u8;
+    }
+    black_box(&mut buf).iter().map(|&b| b as u32).sum()
+}
+
+pub fn test_stack_guard_randomized() -> TestResult {
+    let value = guard();
+    assert_test!(guard_randomized(), "guard still {:#x}", STACK_GUARD_DEFAULT);
+    assert_test!(value != 0, "zero guard");
+    assert_eq_test!(value & 0xFF, 0, "guard low byte not zero");
+    pass!()
+}
+
+pub fn test_stack_protected_frame_returns() -> TestResult {
+    assert_eq_test!(fill_and_sum(black_box(96)), (0..96).sum::<u32>());
+    assert_eq_test!(fill_and_sum(black_box(0)), 0);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    stack_protector,
+    [
+        test_stack_guard_randomized,
+        test_stack_protected_frame_returns,
+    ]
+);
@@ -0,0 +1,66 @@
+//! PCM audio syscalls.  The driver lives behind the `audio` syscall service.
+
+use slopos_abi::audio::{
+    AUDIO_CTL_INFO, AUDIO_CTL_SET_VOLUME, AUDIO_CTL_STOP, AUDIO_FRAME_BYTES, AUDIO_VOLUME_MAX,
+    AudioInfo,
+};
+use slopos_abi::syscall::ERRNO_ENODEV;
+use slopos_lib::kernel_services::syscall_services::audio;
+use slopos_mm::user_copy::{copy_bytes_from_user, copy_to_user};
+use slopos_mm::user_ptr::{UserBytes, UserPtr};
+
+define_syscall!(syscall_audio_write(ctx, args) {
+    let len = args.arg1_usize() / AUDIO_FRAME_BYTES * AUDIO_FRAME_BYTES;
+    if len == 0 {
+        return ctx.invalid_arg();
+    }
+    if !audio::info().is_present() {
+        return ctx.err_with(ERRNO_ENODEV);
+    }
+    // Validate the whole range before possibly blocking.
+    try_or_err!(ctx, UserBytes::try_new(args.arg0, len));
+
+    let mut chunk = [0u8; 1024];
+    let mut done = 0;
+    while done < len {
+        let take = (len - done).min(chunk.len());
+        let src = try_or_err!(ctx, UserBytes::try_new(args.arg0 + done as u64, take));
+        try_or_err!(ctx, copy_bytes_from_user(src, &mut chunk[..take]));
+        let queued = audio::write(&chunk[..take]);
+        done += queued;
+        if queued < take {
+            break;
+        }
+    }
+    ctx.ok(done as u64)
+});
+
+define_syscall!(syscall_audio_ctl(ctx, args) {
+    match args.arg0 {
+        AUDIO_CTL_INFO => {
+            require_nonzero!(ctx, args.arg1);
+            let info = audio::info();
+            let user_ptr = try_or_err!(ctx, UserPtr::<AudioInfo>::try_new(args.arg1));
+            try_or_err!(ctx, copy_to_user(user_ptr, &info));
+            ctx.ok(0)
+        }
+        AUDIO_CTL_SET_VOLUME => {
+            if args.arg1 > AUDIO_VOLUME_MAX as u64 {
+                return ctx.invalid_arg();
+            }
+            if !audio::info().is_present() {
+                return ctx.err_with(ERRNO_ENODEV);
+            }
+            audio::set_volume(args.arg1 as u32);
+            ctx.ok(0)
+        }
+        AUDIO_CTL_STOP => {
+            if !audio::info().is_present() {
+                return ctx.err_with(ERRNO_ENODEV);
+            }
+            audio::stop();
+            ctx.ok(0)
+        }
+        _ => ctx.invalid_arg(),
+    }
+});
@@ -1,11 +1,43 @@
 use core::ffi::c_char;
 use core::mem::size_of;
 
-use slopos_abi::syscall::{ERRNO_EINVAL, TtyIndex, UserSysInfo};
-use slopos_abi::task::{TaskExitReason, TaskFaultReason};
+use slopos_abi::audit::{
+    AUDIT_CAT_ALL, AUDIT_OP_FLUSH, AUDIT_OP_READ, AUDIT_OP_SET, AUDIT_OP_STATUS, AUDIT_RING_LEN,
+    AuditRecord, AuditStatus,
+};
+use slopos_abi::crash::CrashNotice;
+use slopos_abi::hw::{HW_CLASS_CPU, HW_CLASS_INPUT, HW_MAX_DEVICES, HwDevice};
+use slopos_abi::klog::{KLOG_LEVEL_INHERIT, KLOG_LEVEL_KEEP, KLOG_MODULE_DEFAULT};
+use slopos_abi::net::{
+    NET_FILTER_MAX_RULES, NET_FILTER_OP_APPEND, NET_FILTER_OP_DELETE, NET_FILTER_OP_FLUSH,
+    NET_FILTER_OP_LIST, NET_FILTER_OP_POLICY, NET_FILTER_OP_STATUS, NET_ROUTE_MAX_ROUTES,
+    NET_ROUTE_OP_ADD, NET_ROUTE_OP_DEL, NET_ROUTE_OP_LIST, NetFilterRule, NetFilterStatus,
+    NetLease, NetPingReply, NetPingRequest, NetRoute, NetSocketStat,
+};
+use slopos_abi::perf::PerfReading;
+use slopos_abi::syscall::{
+    ERRNO_EBUSY, ERRNO_EINVAL, ERRNO_EIO, ERRNO_EOPNOTSUPP, ERRNO_EPERM, ERRNO_ESRCH,
+    KTRACE_OP_EXPORT_FILE, KTRACE_OP_EXPORT_SERIAL, KTRACE_OP_READ, KTRACE_OP_START,
+    KTRACE_OP_STOP, KWARN_PANIC_KEEP, KWARN_PANIC_OFF, KWARN_PANIC_ON, KtraceRecord, KwarnStats,
+    LOCK_STATS_MAX_CLASSES, LOCK_STATS_RESET, LockStat, TtyIndex, UserSysInfo,
+};
+use slopos_abi::task::{
+    CpuUsage, MAX_TASKS, TaskExitReason, TaskFaultReason, TaskSnapshot, TaskStackUsage,
+};
 use slopos_abi::{USER_NET_MAX_MEMBERS, UserNetInfo, UserNetMember};
-use slopos_lib::{InterruptFrame, klog_debug};
+use slopos_lib::kwarn;
+use slopos_lib::lockstat::{lockstat_enabled, lockstat_for_each, lockstat_reset};
+use slopos_lib::stack_watermark::STACK_WATERMARK_ENABLED;
+use slopos_lib::{
+    InterruptFrame, KlogLevel, KlogModule, klog_debug, klog_get_level, klog_module_level,
+    klog_set_level, klog_set_module_level,
+};
 
+use crate::audit::{self, AuditError};
+use crate::crash;
+use crate::ktrace::{self, KtraceError};
+use crate::per_cpu;
+use crate::perf::{self, PerfError};
 use crate::platform;
 use crate::sched::{
     clear_scheduler_current_task, get_scheduler_stats, schedule, scheduler_is_preemption_enabled,
@@ -13,15 +45,17 @@ use crate::sched::{
 };
 use crate::scheduler::task_struct::Task;
 use crate::syscall::common::{
-    SyscallDisposition, USER_IO_MAX_BYTES, syscall_bounded_from_user, syscall_copy_to_user_bounded,
-    syscall_return_err,
+    SyscallDisposition, USER_IO_MAX_BYTES, USER_PATH_MAX, syscall_bounded_from_user,
+    syscall_copy_to_user_bounded, syscall_copy_user_str, syscall_return_err,
 };
 use crate::syscall::context::SyscallContext;
-use crate::task::{get_task_stats, task_terminate};
-use slopos_lib::kernel_services::syscall_services::{net, tty};
+use crate::task::{
+    get_task_stats, task_find_by_id, task_snapshot, task_stack_usage, task_terminate,
+};
+use slopos_lib::kernel_services::syscall_services::{hw, net, tty, video};
 
 use slopos_mm::page_alloc::get_page_allocator_stats;
-use slopos_mm::user_copy::copy_to_user;
+use slopos_mm::user_copy::{copy_from_user, copy_to_user};
 use slopos_mm::user_ptr::UserPtr;
 
 pub fn syscall_yield(task: *mut Task, frame: *mut InterruptFrame) -> SyscallDisposition {
@@ -40,16 +74,16 @@ define_syscall!(syscall_get_time_ms(ctx, args) {
 });
 
 define_syscall!(syscall_clock_gettime(ctx, args) {
-    use slopos_abi::syscall::{CLOCK_MONOTONIC, Timespec};
+    use slopos_abi::syscall::{CLOCK_MONOTONIC, CLOCK_REALTIME, Timespec};
 
-    let clock_id = args.arg0;
-    if clock_id != CLOCK_MONOTONIC {
-        return ctx.err();
-    }
+    let ns = match args.arg0 {
+        CLOCK_MONOTONIC => slopos_lib::clock::monotonic_ns(),
+        CLOCK_REALTIME => slopos_lib::walltime::realtime_ns(),
+        _ => return ctx.err(),
+    };
 
     require_nonzero!(ctx, args.arg1);
 
-    let ns = slopos_lib::clock::monotonic_ns();
     let ts = Timespec {
         tv_sec: ns / 1_000_000_000,
         tv_nsec: ns % 1_000_000_000,
@@ -60,13 +94,24 @@ define_syscall!(syscall_clock_gettime(ctx, args) {
     ctx.ok(0)
 });
 
-pub fn syscall_halt(_task: *mut Task, _frame: *mut InterruptFrame) -> SyscallDisposition {
+/// Halt and reboot are reserved for uid 0.
+fn caller_is_root(task: *mut Task) -> bool {
+    !task.is_null() && unsafe { (*task).creds.is_root() }
+}
+
+pub fn syscall_halt(task: *mut Task, frame: *mut InterruptFrame) -> SyscallDisposition {
+    if !caller_is_root(task) {
+        return syscall_return_err(frame, ERRNO_EPERM);
+    }
     platform::kernel_shutdown(b"user halt\0".as_ptr() as *const c_char);
     #[allow(unreachable_code)]
     SyscallDisposition::Ok
 }
 
-pub fn syscall_reboot(_task: *mut Task, _frame: *mut InterruptFrame) -> SyscallDisposition {
+pub fn syscall_reboot(task: *mut Task, frame: *mut InterruptFrame) -> SyscallDisposition {
+    if !caller_is_root(task) {
+        return syscall_return_err(frame, ERRNO_EPERM);
+    }
     platform::kernel_reboot(b"user reboot\0".as_ptr() as *const c_char);
     #[allow(unreachable_code)]
     SyscallDisposition::Ok
@@ -177,6 +222,350 @@ define_syscall!(syscall_sys_info(ctx, args) {
     ctx.ok(0)
 });
 
+define_syscall!(syscall_kwarn_stats(ctx, args) {
+    require_nonzero!(ctx, args.arg0);
+
+    match args.arg1 {
+        KWARN_PANIC_KEEP => {}
+        KWARN_PANIC_OFF => kwarn::kwarn_set_panic_on_warn(false),
+        KWARN_PANIC_ON => kwarn::kwarn_set_panic_on_warn(true),
+        _ => return ctx.invalid_arg(),
+    }
+
+    let stats = kwarn::kwarn_stats();
+    let user_ptr = try_or_err!(ctx, UserPtr::<KwarnStats>::try_new(args.arg0));
+    try_or_err!(ctx, copy_to_user(user_ptr, &stats));
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_klog_level(ctx, args) {
+    if args.arg0 == KLOG_MODULE_DEFAULT {
+        if args.arg1 != KLOG_LEVEL_KEEP {
+            let Some(level) = KlogLevel::from_abi(args.arg1) else {
+                return ctx.invalid_arg();
+            };
+            klog_set_level(level);
+        }
+        return ctx.ok(klog_get_level() as u64);
+    }
+
+    let Some(module) = KlogModule::from_abi(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    match args.arg1 {
+        KLOG_LEVEL_KEEP => {}
+        KLOG_LEVEL_INHERIT => klog_set_module_level(module, None),
+        raw => {
+            let Some(level) = KlogLevel::from_abi(raw) else {
+                return ctx.invalid_arg();
+            };
+            klog_set_module_level(module, Some(level));
+        }
+    }
+    ctx.ok(klog_module_level(module).map_or(KLOG_LEVEL_INHERIT, |level| level as u64))
+});
+
+define_syscall!(syscall_ktrace(ctx, args) {
+    let result = match args.arg0 {
+        KTRACE_OP_START => ktrace::start().map(|()| 0),
+        KTRACE_OP_STOP => {
+            slopos_lib::ktrace::ktrace_stop();
+            Ok(0)
+        }
+        KTRACE_OP_EXPORT_FILE => {
+            let mut path = [0u8; USER_PATH_MAX];
+            try_or_err!(ctx, syscall_copy_user_str(&mut path, args.arg1));
+            let len = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+            if len == 0 || path[0] != b'/' {
+                return ctx.invalid_arg();
+            }
+            ktrace::export_to_path(&path[..len])
+        }
+        KTRACE_OP_EXPORT_SERIAL => ktrace::export_to_serial(),
+        KTRACE_OP_READ => {
+            let capacity = args.arg2 as usize;
+            let first = args.arg3 as usize;
+            let mut copied = 0usize;
+            let mut faulted = false;
+            let result = ktrace::read(|index, event| {
+                if index < first || copied >= capacity || faulted {
+                    return;
+                }
+                let dst = args
+                    .arg1
+                    .wrapping_add((copied * size_of::<KtraceRecord>()) as u64);
+                let copy = UserPtr::<KtraceRecord>::try_new(dst)
+                    .and_then(|ptr| copy_to_user(ptr, &event.to_record()));
+                match copy {
+                    Ok(()) => copied += 1,
+                    Err(_) => faulted = true,
+                }
+            });
+            if faulted {
+                return ctx.err();
+            }
+            result
+        }
+        _ => return ctx.invalid_arg(),
+    };
+    match result {
+        Ok(count) => ctx.ok(count as u64),
+        Err(KtraceError::Busy) => ctx.err_with(ERRNO_EBUSY),
+        Err(KtraceError::Fs(_)) => ctx.err_with(ERRNO_EIO),
+    }
+});
+
+fn perf_errno(err: PerfError) -> u64 {
+    match err {
+        PerfError::Unsupported => ERRNO_EOPNOTSUPP,
+        PerfError::Invalid => ERRNO_EINVAL,
+        PerfError::Busy => ERRNO_EBUSY,
+    }
+}
+
+define_syscall!(syscall_perf_open(ctx, args) {
+    let Some(caller) = ctx.task_mut() else {
+        return ctx.err();
+    };
+    let target = if args.arg1 == 0 || args.arg1 == caller.task_id as u64 {
+        &*caller
+    } else {
+        let Ok(target_id) = u32::try_from(args.arg1) else {
+            return ctx.err_with(ERRNO_ESRCH);
+        };
+        let target = task_find_by_id(target_id);
+        if target.is_null() {
+            return ctx.err_with(ERRNO_ESRCH);
+        }
+        let target = unsafe { &*target };
+        if target.parent_task_id != caller.task_id {
+            return ctx.err_with(ERRNO_EPERM);
+        }
+        target
+    };
+    let Ok(event) = u32::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    match perf::open(caller, target, event, args.arg2) {
+        Ok(id) => ctx.ok(id as u64),
+        Err(err) => ctx.err_with(perf_errno(err)),
+    }
+});
+
+define_syscall!(syscall_perf_read(ctx, args) {
+    let Some(caller) = ctx.task_mut() else {
+        return ctx.err();
+    };
+    let Ok(id) = u8::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    let reading = match perf::read(caller, id) {
+        Ok(reading) => reading,
+        Err(err) => return ctx.err_with(perf_errno(err)),
+    };
+    let user_ptr = try_or_err!(ctx, UserPtr::<PerfReading>::try_new(args.arg1));
+    try_or_err!(ctx, copy_to_user(user_ptr, &reading));
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_perf_close(ctx, args) {
+    let Some(caller) = ctx.task_mut() else {
+        return ctx.err();
+    };
+    let Ok(id) = u8::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    match perf::close(caller, id) {
+        Ok(()) => ctx.ok(0),
+        Err(err) => ctx.err_with(perf_errno(err)),
+    }
+});
+
+define_syscall!(syscall_lock_stats(ctx, args) {
+    if !lockstat_enabled() {
+        return ctx.err_with(ERRNO_EOPNOTSUPP);
+    }
+    if args.arg2 & !LOCK_STATS_RESET != 0 {
+        return ctx.invalid_arg();
+    }
+
+    let max = (args.arg1 as usize).min(LOCK_STATS_MAX_CLASSES);
+    let mut scratch = [LockStat::default(); LOCK_STATS_MAX_CLASSES];
+    let mut count = 0usize;
+    lockstat_for_each(|class| {
+        if count < max {
+            scratch[count] = class.snapshot();
+            count += 1;
+        }
+    });
+    if count > 0 {
+        require_nonzero!(ctx, args.arg0);
+    }
+    for (i, stat) in scratch[..count].iter().enumerate() {
+        let dst = args.arg0.wrapping_add((i * size_of::<LockStat>()) as u64);
+        let user_ptr = try_or_err!(ctx, UserPtr::<LockStat>::try_new(dst));
+        try_or_err!(ctx, copy_to_user(user_ptr, stat));
+    }
+    if args.arg2 & LOCK_STATS_RESET != 0 {
+        lockstat_reset();
+    }
+    ctx.ok(count as u64)
+});
+
+define_syscall!(syscall_task_stack_usage(ctx, args) {
+    if !STACK_WATERMARK_ENABLED {
+        return ctx.err_with(ERRNO_EOPNOTSUPP);
+    }
+
+    let max = (args.arg1 as usize).min(MAX_TASKS);
+    if max == 0 {
+        return ctx.ok(0);
+    }
+    require_nonzero!(ctx, args.arg0);
+    let mut scratch = [TaskStackUsage::default(); MAX_TASKS];
+    let count = task_stack_usage(&mut scratch[..max]);
+    for (i, usage) in scratch[..count].iter().enumerate() {
+        let dst = args.arg0.wrapping_add((i * size_of::<TaskStackUsage>()) as u64);
+        let user_ptr = try_or_err!(ctx, UserPtr::<TaskStackUsage>::try_new(dst));
+        try_or_err!(ctx, copy_to_user(user_ptr, usage));
+    }
+    ctx.ok(count as u64)
+});
+
+define_syscall!(syscall_cpu_usage(ctx, args) {
+    let max = (args.arg1 as usize).min(slopos_lib::get_cpu_count());
+    if max == 0 {
+        return ctx.ok(0);
+    }
+    require_nonzero!(ctx, args.arg0);
+    let mut count = 0;
+    for usage in (0..max).filter_map(per_cpu::cpu_usage) {
+        let dst = args.arg0.wrapping_add((count * size_of::<CpuUsage>()) as u64);
+        let user_ptr = try_or_err!(ctx, UserPtr::<CpuUsage>::try_new(dst));
+        try_or_err!(ctx, copy_to_user(user_ptr, &usage));
+        count += 1;
+    }
+    ctx.ok(count as u64)
+});
+
+define_syscall!(syscall_task_snapshot(ctx, args) {
+    let max = (args.arg1 as usize).min(MAX_TASKS);
+    if max == 0 {
+        return ctx.ok(0);
+    }
+    require_nonzero!(ctx, args.arg0);
+    let mut scratch = [TaskSnapshot::default(); MAX_TASKS];
+    let count = task_snapshot(&mut scratch[..max]);
+    for (i, snapshot) in scratch[..count].iter_mut().enumerate() {
+        if video::is_video_initialized()
+            && let Some(title) = video::surface_title(snapshot.task_id)
+        {
+            snapshot.title = title;
+        }
+        let dst = args.arg0.wrapping_add((i * size_of::<TaskSnapshot>()) as u64);
+        let user_ptr = try_or_err!(ctx, UserPtr::<TaskSnapshot>::try_new(dst));
+        try_or_err!(ctx, copy_to_user(user_ptr, snapshot));
+    }
+    ctx.ok(count as u64)
+});
+
+define_syscall!(syscall_hw_inventory(ctx, args) {
+    let class = args.arg2;
+    if class != 0 && !(HW_CLASS_CPU as u64..=HW_CLASS_INPUT as u64).contains(&class) {
+        return ctx.invalid_arg();
+    }
+
+    let max = (args.arg1 as usize).min(HW_MAX_DEVICES);
+    if max == 0 {
+        return ctx.ok(0);
+    }
+    require_nonzero!(ctx, args.arg0);
+    let mut device = HwDevice::default();
+    let mut count = 0usize;
+    let mut index = 0usize;
+    while count < max && hw::device(index, &mut device) {
+        index += 1;
+        if class != 0 && device.class as u64 != class {
+            continue;
+        }
+        let dst = args.arg0.wrapping_add((count * size_of::<HwDevice>()) as u64);
+        let user_ptr = try_or_err!(ctx, UserPtr::<HwDevice>::try_new(dst));
+        try_or_err!(ctx, copy_to_user(user_ptr, &device));
+        count += 1;
+    }
+    ctx.ok(count as u64)
+});
+
+define_syscall!(syscall_net_stat(ctx, args) {
+    let max = args.arg1 as usize;
+    if max == 0 {
+        return ctx.ok(0);
+    }
+    require_nonzero!(ctx, args.arg0);
+    let mut count = 0usize;
+    let mut next = args.arg2 as usize;
+    while count < max
+        && let Some(stat) = net::socket_stat(next)
+    {
+        next = stat.index as usize + 1;
+        let dst = args.arg0.wrapping_add((count * size_of::<NetSocketStat>()) as u64);
+        let user_ptr = try_or_err!(ctx, UserPtr::<NetSocketStat>::try_new(dst));
+        try_or_err!(ctx, copy_to_user(user_ptr, &stat));
+        count += 1;
+    }
+    ctx.ok(count as u64)
+});
+
+define_syscall!(syscall_audit_ctl(ctx, args) {
+    match args.arg0 {
+        AUDIT_OP_STATUS => {
+            require_nonzero!(ctx, args.arg1);
+            let user_ptr = try_or_err!(ctx, UserPtr::<AuditStatus>::try_new(args.arg1));
+            try_or_err!(ctx, copy_to_user(user_ptr, &audit::audit_status()));
+            ctx.ok(0)
+        }
+        AUDIT_OP_SET => {
+            if args.arg1 & !(AUDIT_CAT_ALL as u64) != 0 {
+                return ctx.invalid_arg();
+            }
+            ctx.ok(audit::audit_set_mask(args.arg1 as u32) as u64)
+        }
+        AUDIT_OP_READ => {
+            let max = (args.arg2 as usize).min(AUDIT_RING_LEN);
+            let retained = audit::audit_retained();
+            let start = retained.start.max(retained.end.saturating_sub(max as u64));
+            let mut count = 0usize;
+            for seq in start..retained.end {
+                let Some(record) = audit::audit_record(seq) else {
+                    continue;
+                };
+                require_nonzero!(ctx, args.arg1);
+                let dst = args.arg1.wrapping_add((count * size_of::<AuditRecord>()) as u64);
+                let user_ptr = try_or_err!(ctx, UserPtr::<AuditRecord>::try_new(dst));
+                try_or_err!(ctx, copy_to_user(user_ptr, &record));
+                count += 1;
+            }
+            ctx.ok(count as u64)
+        }
+        AUDIT_OP_FLUSH => match audit::audit_flush() {
+            Ok(count) => ctx.ok(count as u64),
+            Err(AuditError::Busy) => ctx.err_with(ERRNO_EBUSY),
+            Err(AuditError::Fs(_)) => ctx.err_with(ERRNO_EIO),
+        },
+        _ => ctx.invalid_arg(),
+    }
+});
+
+define_syscall!(syscall_crash_notice(ctx, args) {
+    require_nonzero!(ctx, args.arg1);
+    let Some(notice) = crash::crash_notice(args.arg0) else {
+        return ctx.ok(0);
+    };
+    let user_ptr = try_or_err!(ctx, UserPtr::<CrashNotice>::try_new(args.arg1));
+    try_or_err!(ctx, copy_to_user(user_ptr, &notice));
+    ctx.ok(1)
+});
+
 define_syscall!(syscall_net_scan(ctx, args) {
     require_nonzero!(ctx, args.arg0);
 
@@ -218,3 +607,111 @@ define_syscall!(syscall_net_info(ctx, args) {
     try_or_err!(ctx, copy_to_user(user_ptr, &info));
     ctx.ok(0)
 });
+
+fn filter_rc(ctx: &SyscallContext, rc: i32) -> SyscallDisposition {
+    if rc < 0 {
+        ctx.err_with(rc as i64 as u64)
+    } else {
+        ctx.ok(rc as u64)
+    }
+}
+
+define_syscall!(syscall_net_filter(ctx, args) {
+    match args.arg0 {
+        NET_FILTER_OP_APPEND => {
+            require_nonzero!(ctx, args.arg1);
+            let user_ptr = try_or_err!(ctx, UserPtr::<NetFilterRule>::try_new(args.arg1));
+            let rule = try_or_err!(ctx, copy_from_user(user_ptr));
+            filter_rc(&ctx, net::filter_append(rule))
+        }
+        NET_FILTER_OP_DELETE => filter_rc(&ctx, net::filter_delete(args.arg1 as usize)),
+        NET_FILTER_OP_FLUSH => {
+            net::filter_flush();
+            ctx.ok(0)
+        }
+        NET_FILTER_OP_LIST => {
+            let max = (args.arg2 as usize).min(NET_FILTER_MAX_RULES);
+            if max == 0 {
+                return ctx.ok(0);
+            }
+            require_nonzero!(ctx, args.arg1);
+            let mut scratch = [NetFilterRule::default(); NET_FILTER_MAX_RULES];
+            let count = net::filter_list(&mut scratch[..max]);
+            for (i, rule) in scratch[..count].iter().enumerate() {
+                let dst = args.arg1.wrapping_add((i * size_of::<NetFilterRule>()) as u64);
+                let user_ptr = try_or_err!(ctx, UserPtr::<NetFilterRule>::try_new(dst));
+                try_or_err!(ctx, copy_to_user(user_ptr, rule));
+            }
+            ctx.ok(count as u64)
+        }
+        NET_FILTER_OP_POLICY => {
+            if args.arg1 > u8::MAX as u64 || args.arg2 > u8::MAX as u64 {
+                return ctx.invalid_arg();
+            }
+            filter_rc(&ctx, net::filter_set_policy(args.arg1 as u8, args.arg2 as u8))
+        }
+        NET_FILTER_OP_STATUS => {
+            require_nonzero!(ctx, args.arg1);
+            let status = net::filter_status();
+            let user_ptr = try_or_err!(ctx, UserPtr::<NetFilterStatus>::try_new(args.arg1));
+            try_or_err!(ctx, copy_to_user(user_ptr, &status));
+            ctx.ok(0)
+        }
+        _ => ctx.invalid_arg(),
+    }
+});
+
+define_syscall!(syscall_net_ping(ctx, args) {
+    require_nonzero!(ctx, args.arg0);
+    require_nonzero!(ctx, args.arg1);
+    let req_ptr = try_or_err!(ctx, UserPtr::<NetPingRequest>::try_new(args.arg0));
+    let reply_ptr = try_or_err!(ctx, UserPtr::<NetPingReply>::try_new(args.arg1));
+    let request = try_or_err!(ctx, copy_from_user(req_ptr));
+    let mut reply = NetPingReply::default();
+    let rc = net::ping(request, &mut reply);
+    if rc < 0 {
+        return ctx.err_with(rc as i64 as u64);
+    }
+    try_or_err!(ctx, copy_to_user(reply_ptr, &reply));
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_net_route(ctx, args) {
+    match args.arg0 {
+        NET_ROUTE_OP_ADD | NET_ROUTE_OP_DEL => {
+            require_nonzero!(ctx, args.arg1);
+            let user_ptr = try_or_err!(ctx, UserPtr::<NetRoute>::try_new(args.arg1));
+            let route = try_or_err!(ctx, copy_from_user(user_ptr));
+            let rc = if args.arg0 == NET_ROUTE_OP_ADD {
+                net::route_add(route)
+            } else {
+                net::route_del(route)
+            };
+            filter_rc(&ctx, rc)
+        }
+        NET_ROUTE_OP_LIST => {
+            let max = (args.arg2 as usize).min(NET_ROUTE_MAX_ROUTES);
+            if max == 0 {
+                return ctx.ok(0);
+            }
+            require_nonzero!(ctx, args.arg1);
+            let mut scratch = [NetRoute::default(); NET_ROUTE_MAX_ROUTES];
+            let count = net::route_list(&mut scratch[..max]);
+            for (i, route) in scratch[..count].iter().enumerate() {
+                let dst = args.arg1.wrapping_add((i * size_of::<NetRoute>()) as u64);
+                let user_ptr = try_or_err!(ctx, UserPtr::<NetRoute>::try_new(dst));
+                try_or_err!(ctx, copy_to_user(user_ptr, route));
+            }
+            ctx.ok(count as u64)
+        }
+        _ => ctx.invalid_arg(),
+    }
+});
+
+define_syscall!(syscall_net_lease(ctx, args) {
+    require_nonzero!(ctx, args.arg0);
+    let lease = net::lease_info();
+    let user_ptr = try_or_err!(ctx, UserPtr::<NetLease>::try_new(args.arg0));
+    try_or_err!(ctx, copy_to_user(user_ptr, &lease));
+    ctx.ok(0)
+});
@@ -1,7 +1,11 @@
 use slopos_lib::klog_info;
+use slopos_lib::ktrace::{KtraceKind, ktrace_record};
 
+use crate::audit::audit_syscall_enter;
 use crate::sched::save_task_context_from_interrupt_frame;
 use crate::sched::scheduler_get_current_task;
+use crate::syscall::common::SyscallHandler;
+use crate::syscall::filter::{FilterVerdict, kill_filtered_task};
 use crate::syscall::handlers::syscall_lookup;
 
 use crate::scheduler::task_struct::Task;
@@ -27,6 +31,15 @@ impl Drop for NoPreemptGuard {
     }
 }
 
+/// Run `func`, recording the call in the audit log if it is audited.
+fn run_audited(func: SyscallHandler, task: *mut Task, frame: *mut InterruptFrame, sysno: u64) {
+    let audit = audit_syscall_enter(unsafe { &*task }, sysno, unsafe { &*frame });
+    func(task, frame);
+    if let Some(audit) = audit {
+        audit.finish(unsafe { (*frame).rax });
+    }
+}
+
 pub fn syscall_handle(frame: *mut InterruptFrame) {
     if frame.is_null() {
         return;
@@ -73,21 +86,37 @@ pub fn syscall_handle(frame: *mut InterruptFrame) {
     }
 
     let pid = unsafe { (*task).process_id };
+    let task_id = unsafe { (*task).task_id };
+    ktrace_record(KtraceKind::SyscallEnter, sysno as u32, task_id);
     let _provider_guard = slopos_mm::user_copy::set_syscall_process_id(pid);
 
-    let entry = syscall_lookup(sysno);
-    if entry.is_null() {
-        klog_info!("SYSCALL: Unknown syscall {} -> ENOSYS", sysno);
-        unsafe {
-            (*frame).rax = slopos_abi::syscall::ENOSYS_RETURN;
+    match unsafe { (*task).syscall_filter.verdict(sysno) } {
+        FilterVerdict::Allow => {
+            let entry = syscall_lookup(sysno);
+            if entry.is_null() {
+                klog_info!("SYSCALL: Unknown syscall {} -> ENOSYS", sysno);
+                unsafe {
+                    (*frame).rax = slopos_abi::syscall::ENOSYS_RETURN;
+                }
+            } else {
+                let handler = unsafe { (*entry).handler };
+                if let Some(func) = handler {
+                    run_audited(func, task, frame, sysno);
+                    crate::syscall::signal::deliver_pending_signal(task, frame);
+                }
+            }
         }
-    } else {
-        let handler = unsafe { (*entry).handler };
-        if let Some(func) = handler {
-            func(task, frame);
+        FilterVerdict::Errno(ret) => {
+            unsafe {
+                (*frame).rax = ret;
+            }
             crate::syscall::signal::deliver_pending_signal(task, frame);
         }
+        // SAFETY: both were checked non-null above, and `task` is this
+        // CPU's current task.
+        FilterVerdict::Kill => kill_filtered_task(unsafe { &mut *task }, unsafe { &*frame }, sysno),
     }
+    ktrace_record(KtraceKind::SyscallExit, sysno as u32, task_id);
 
     // Sync all frame registers that may have been modified back to the
     // saved user context.  This MUST happen while NO_PREEMPT is still
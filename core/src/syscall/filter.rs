@@ -0,0 +1,152 @@
+//! Per-task syscall filters (`SYSCALL_SET_SYSCALL_FILTER`).
+//!
+//! Every filter a task installs is folded into two denied sets, one per
+//! action, so the dispatcher checks at most two bitmaps per call and a
+//! kill filter never turns an errno filter's refusals into kills.  See
+//! [`slopos_abi::syscall_filter`] for the user-facing rules.
+
+use slopos_abi::signal::SIGSYS;
+use slopos_abi::syscall::{ERRNO_EPERM, SYSCALL_EXIT, SYSCALL_RT_SIGRETURN};
+use slopos_abi::syscall_filter::{
+    SYSCALL_FILTER_ACTION_ERRNO, SYSCALL_FILTER_ACTION_KILL, SYSCALL_FILTER_MAX,
+    SYSCALL_FILTER_MODE_ALLOW, SYSCALL_FILTER_MODE_DENY, SYSCALL_FILTER_WORDS, SyscallFilter,
+};
+use slopos_abi::task::{TaskExitReason, TaskFaultReason};
+use slopos_lib::{InterruptFrame, klog_info};
+
+use crate::crash;
+use crate::sched::{clear_scheduler_current_task, schedule};
+use crate::scheduler::task::task_terminate;
+use crate::scheduler::task_struct::Task;
+
+/// Largest errno a filter may ask for.
+const FILTER_ERRNO_MAX: u32 = 4095;
+const ERRNO_EPERM_POSITIVE: u32 = (ERRNO_EPERM as i64).wrapping_neg() as u32;
+
+/// What the dispatcher does with a filtered call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterVerdict {
+    Allow,
+    /// Skip the handler and return this value (already negated).
+    Errno(u64),
+    Kill,
+}
+
+/// Why [`TaskSyscallFilter::install`] refused a filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterError {
+    /// `reserved` was not zero.
+    Reserved,
+    /// `errno` was above 4095.
+    ErrnoRange,
+    UnknownAction,
+    UnknownMode,
+}
+
+/// The filter state a task carries; inherited by copying the task.
+#[derive(Clone, Copy)]
+pub struct TaskSyscallFilter {
+    active: bool,
+    /// Errno for `errno_denied` calls, from the first errno filter; 0
+    /// until one is installed.
+    errno: u32,
+    kill_denied: [u64; SYSCALL_FILTER_WORDS],
+    errno_denied: [u64; SYSCALL_FILTER_WORDS],
+}
+
+impl TaskSyscallFilter {
+    pub const fn new() -> Self {
+        Self {
+            active: false,
+            errno: 0,
+            kill_denied: [0; SYSCALL_FILTER_WORDS],
+            errno_denied: [0; SYSCALL_FILTER_WORDS],
+        }
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Stack `filter` on top of what is already installed.
+    pub fn install(&mut self, filter: &SyscallFilter) -> Result<(), FilterError> {
+        if filter.reserved != 0 {
+            return Err(FilterError::Reserved);
+        }
+        if filter.errno > FILTER_ERRNO_MAX {
+            return Err(FilterError::ErrnoRange);
+        }
+        let kill = match filter.action {
+            SYSCALL_FILTER_ACTION_ERRNO => false,
+            SYSCALL_FILTER_ACTION_KILL => true,
+            _ => return Err(FilterError::UnknownAction),
+        };
+        // Denied bits are the complement of an allowlist.
+        let invert = match filter.mode {
+            SYSCALL_FILTER_MODE_ALLOW => u64::MAX,
+            SYSCALL_FILTER_MODE_DENY => 0,
+            _ => return Err(FilterError::UnknownMode),
+        };
+
+        let denied = if kill {
+            &mut self.kill_denied
+        } else {
+            &mut self.errno_denied
+        };
+        for (denied, &bits) in denied.iter_mut().zip(filter.bits.iter()) {
+            *denied |= bits ^ invert;
+        }
+        self.active = true;
+        if !kill && self.errno == 0 {
+            self.errno = if filter.errno == 0 {
+                ERRNO_EPERM_POSITIVE
+            } else {
+                filter.errno
+            };
+        }
+        Ok(())
+    }
+
+    pub fn verdict(&self, sysno: u64) -> FilterVerdict {
+        if !self.active || sysno == SYSCALL_EXIT || sysno == SYSCALL_RT_SIGRETURN {
+            return FilterVerdict::Allow;
+        }
+        // Numbers past the bitmap are not syscalls; let the lookup refuse them.
+        if sysno as usize >= SYSCALL_FILTER_MAX {
+            return FilterVerdict::Allow;
+        }
+        let (word, bit) = (sysno as usize / 64, 1 << (sysno % 64));
+        if self.kill_denied[word] & bit != 0 {
+            FilterVerdict::Kill
+        } else if self.errno_denied[word] & bit != 0 {
+            FilterVerdict::Errno((self.errno as i64).wrapping_neg() as u64)
+        } else {
+            FilterVerdict::Allow
+        }
+    }
+}
+
+impl Default for TaskSyscallFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Terminate the current task for making a call its filter kills on.
+pub fn kill_filtered_task(task: &mut Task, frame: &InterruptFrame, sysno: u64) {
+    let task_id = task.task_id;
+    klog_info!(
+        "SYSCALL: task {} ('{}') killed by syscall filter on syscall {}",
+        task_id,
+        slopos_lib::string::bytes_as_str(&task.name),
+        sysno
+    );
+    crash::crash_capture(task, frame, 0, TaskFaultReason::SyscallFilter);
+    task.exit_reason = TaskExitReason::UserFault;
+    task.fault_reason = TaskFaultReason::SyscallFilter;
+    task.exit_code = 128 + SIGSYS as u32;
+    task_terminate(task_id);
+    clear_scheduler_current_task();
+    schedule();
+}
@@ -4,10 +4,11 @@ pub mod poll_ioctl_handlers;
 
 pub use fd_handlers::{
     syscall_dup, syscall_dup2, syscall_dup3, syscall_fcntl, syscall_fstat, syscall_lseek,
-    syscall_pipe, syscall_pipe2,
+    syscall_openpty, syscall_pipe, syscall_pipe2,
 };
 pub use path_handlers::{
-    syscall_fs_close, syscall_fs_list, syscall_fs_mkdir, syscall_fs_open, syscall_fs_read,
-    syscall_fs_stat, syscall_fs_unlink, syscall_fs_write, syscall_rename,
+    syscall_chmod, syscall_chown, syscall_fs_close, syscall_fs_list, syscall_fs_mkdir,
+    syscall_fs_open, syscall_fs_read, syscall_fs_stat, syscall_fs_unlink, syscall_fs_write,
+    syscall_rename,
 };
 pub use poll_ioctl_handlers::{syscall_ioctl, syscall_poll, syscall_select};
@@ -4,8 +4,8 @@ use slopos_abi::UserFsStat;
 use slopos_abi::syscall::{O_CLOEXEC, O_NONBLOCK};
 
 use slopos_fs::fileio::{
-    file_dup_fd, file_dup2_fd, file_dup3_fd, file_fcntl_fd, file_fstat_fd, file_pipe_create,
-    file_seek_fd,
+    file_dup_fd, file_dup2_fd, file_dup3_fd, file_fcntl_fd, file_fstat_fd, file_openpty,
+    file_pipe_create, file_seek_fd,
 };
 
 use slopos_mm::user_copy::copy_to_user;
@@ -72,3 +72,18 @@ define_syscall!(syscall_pipe2(ctx, args) requires(let pid: process_id) {
     try_or_err!(ctx, copy_to_user(out_fds, &pair));
     ctx.ok(0)
 });
+
+define_syscall!(syscall_openpty(ctx, args) requires(let pid: process_id) {
+    require_nonzero!(ctx, args.arg0);
+    let flags = args.arg1 as u32;
+    if (flags & !(O_CLOEXEC as u32 | O_NONBLOCK as u32)) != 0 {
+        return ctx.err();
+    }
+    let out_fds = try_or_err!(ctx, UserPtr::<[i32; 2]>::try_new(args.arg0));
+    let mut master_fd: c_int = -1;
+    let mut slave_fd: c_int = -1;
+    check_result!(ctx, file_openpty(pid, flags, &mut master_fd, &mut slave_fd));
+    let pair = [master_fd, slave_fd];
+    try_or_err!(ctx, copy_to_user(out_fds, &pair));
+    ctx.ok(0)
+});
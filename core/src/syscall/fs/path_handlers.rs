@@ -1,6 +1,10 @@
 use core::ffi::{c_char, c_int, c_void};
 use core::mem;
 
+use slopos_abi::syscall::{
+    ERRNO_EACCES, ERRNO_EINVAL, ERRNO_EIO, ERRNO_ENOENT, ERRNO_ENOTDIR, ERRNO_EOPNOTSUPP,
+    ERRNO_EPERM,
+};
 use slopos_abi::{USER_FS_MAX_ENTRIES, UserFsEntry, UserFsList, UserFsStat};
 
 use crate::syscall::common::{
@@ -13,6 +17,7 @@ use slopos_fs::fileio::{
     file_stat_path, file_unlink_path, file_write_fd,
 };
 
+use slopos_fs::vfs::{VfsError, current_credentials, vfs_chmod_as, vfs_chown_as, vfs_rename_as};
 use slopos_mm::kernel_heap::{kfree, kmalloc};
 use slopos_mm::user_copy::{copy_bytes_to_user, copy_from_user, copy_to_user};
 use slopos_mm::user_ptr::{UserBytes, UserPtr};
@@ -168,8 +173,57 @@ define_syscall!(syscall_rename(ctx, args) {
         .position(|&b| b == 0)
         .unwrap_or(new_path.len());
 
-    match slopos_fs::vfs::ops::vfs_rename(&old_path[..old_len], &new_path[..new_len]) {
+    let creds = current_credentials();
+    match vfs_rename_as(&old_path[..old_len], &new_path[..new_len], &creds) {
         Ok(()) => ctx.ok(0),
         Err(_) => ctx.err(),
     }
 });
+
+fn vfs_errno(err: VfsError) -> u64 {
+    match err {
+        VfsError::NotFound => ERRNO_ENOENT,
+        VfsError::NotDirectory => ERRNO_ENOTDIR,
+        VfsError::PermissionDenied => ERRNO_EACCES,
+        VfsError::NotPermitted => ERRNO_EPERM,
+        VfsError::NotSupported | VfsError::ReadOnly => ERRNO_EOPNOTSUPP,
+        VfsError::IoError => ERRNO_EIO,
+        _ => ERRNO_EINVAL,
+    }
+}
+
+define_syscall!(syscall_chmod(ctx, args) {
+    let mut path = [0u8; USER_PATH_MAX];
+    let Ok(path_len) = copy_path_arg(&mut path, args.arg0) else {
+        return ctx.bad_address();
+    };
+    let mode = (args.arg1 & 0o7777) as u16;
+    let creds = current_credentials();
+    match vfs_chmod_as(&path[..path_len], mode, &creds) {
+        Ok(()) => ctx.ok(0),
+        Err(err) => ctx.err_with(vfs_errno(err)),
+    }
+});
+
+define_syscall!(syscall_chown(ctx, args) {
+    let mut path = [0u8; USER_PATH_MAX];
+    let Ok(path_len) = copy_path_arg(&mut path, args.arg0) else {
+        return ctx.bad_address();
+    };
+    let uid = (args.arg1 as u32 != u32::MAX).then_some(args.arg1 as u32);
+    let gid = (args.arg2 as u32 != u32::MAX).then_some(args.arg2 as u32);
+    let creds = current_credentials();
+    match vfs_chown_as(&path[..path_len], uid, gid, &creds) {
+        Ok(()) => ctx.ok(0),
+        Err(err) => ctx.err_with(vfs_errno(err)),
+    }
+});
+
+/// Copy a NUL-terminated user path; its length without the NUL.
+fn copy_path_arg(buf: &mut [u8], user_ptr: u64) -> Result<usize, ()> {
+    if user_ptr == 0 {
+        return Err(());
+    }
+    syscall_copy_user_str(buf, user_ptr).map_err(|_| ())?;
+    Ok(buf.iter().position(|&b| b == 0).unwrap_or(buf.len()))
+}
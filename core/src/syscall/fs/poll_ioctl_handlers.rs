@@ -7,7 +7,7 @@ use slopos_abi::syscall::{
     TIOCGWINSZ, TIOCSCTTY, TIOCSETD, TIOCSPGRP, UserPollFd, UserTermios, UserTimeval, UserWinsize,
 };
 
-use slopos_fs::fileio::{file_get_tty_index, file_poll_fd};
+use slopos_fs::fileio::{file_get_tty_index, file_ioctl_fd, file_poll_fd};
 
 use slopos_lib::kernel_services::syscall_services::tty;
 use slopos_mm::user_copy::{
@@ -260,11 +260,14 @@ define_syscall!(syscall_ioctl(ctx, args) requires(let task_id, let pid: process_
     let cmd = args.arg1;
     let arg = args.arg2;
 
-    // Resolve the TTY index from the file descriptor.  If the FD is not a TTY,
-    // ioctl is not supported.
+    // Resolve the TTY index from the file descriptor.  Non-TTY FDs are
+    // handed to their filesystem (e.g. devfs `/dev/fb0`).
     let tty_idx = match file_get_tty_index(pid, fd) {
         Some(idx) => idx,
-        None => return ctx.err(), // ENOTTY
+        None => {
+            let rc = file_ioctl_fd(pid, fd, cmd, arg);
+            return if rc < 0 { ctx.err() } else { ctx.ok(rc as u64) };
+        }
     };
 
     match cmd {
@@ -3,17 +3,23 @@ use core::ptr;
 
 use slopos_abi::syscall::*;
 
+pub use crate::syscall::audio_handlers::{syscall_audio_ctl, syscall_audio_write};
 use crate::syscall::common::SyscallEntry;
 pub use crate::syscall::core_handlers::{
-    syscall_clock_gettime, syscall_exit, syscall_get_time_ms, syscall_halt, syscall_net_info,
-    syscall_net_scan, syscall_reboot, syscall_sleep_ms, syscall_sys_info, syscall_user_read,
+    syscall_audit_ctl, syscall_clock_gettime, syscall_cpu_usage, syscall_crash_notice,
+    syscall_exit, syscall_get_time_ms, syscall_halt, syscall_hw_inventory, syscall_klog_level,
+    syscall_ktrace, syscall_kwarn_stats, syscall_lock_stats, syscall_net_filter, syscall_net_info,
+    syscall_net_lease, syscall_net_ping, syscall_net_route, syscall_net_scan, syscall_net_stat,
+    syscall_perf_close, syscall_perf_open, syscall_perf_read, syscall_reboot, syscall_sleep_ms,
+    syscall_sys_info, syscall_task_snapshot, syscall_task_stack_usage, syscall_user_read,
     syscall_user_write, syscall_yield,
 };
 use crate::syscall::fs::{
-    syscall_dup, syscall_dup2, syscall_dup3, syscall_fcntl, syscall_fs_close, syscall_fs_list,
-    syscall_fs_mkdir, syscall_fs_open, syscall_fs_read, syscall_fs_stat, syscall_fs_unlink,
-    syscall_fs_write, syscall_fstat, syscall_ioctl, syscall_lseek, syscall_pipe, syscall_pipe2,
-    syscall_poll, syscall_rename, syscall_select,
+    syscall_chmod, syscall_chown, syscall_dup, syscall_dup2, syscall_dup3, syscall_fcntl,
+    syscall_fs_close, syscall_fs_list, syscall_fs_mkdir, syscall_fs_open, syscall_fs_read,
+    syscall_fs_stat, syscall_fs_unlink, syscall_fs_write, syscall_fstat, syscall_ioctl,
+    syscall_lseek, syscall_openpty, syscall_pipe, syscall_pipe2, syscall_poll, syscall_rename,
+    syscall_select,
 };
 pub use crate::syscall::memory_handlers::{
     syscall_brk, syscall_mmap, syscall_mprotect, syscall_munmap,
@@ -27,26 +33,32 @@ pub use crate::syscall::process_handlers::{
     syscall_arch_prctl, syscall_chdir, syscall_clone, syscall_exec, syscall_fork, syscall_futex,
     syscall_get_cpu_affinity, syscall_get_cpu_count, syscall_get_current_cpu, syscall_getcwd,
     syscall_getegid, syscall_geteuid, syscall_getgid, syscall_getpgid, syscall_getpid,
-    syscall_getppid, syscall_getuid, syscall_set_cpu_affinity, syscall_setpgid, syscall_setsid,
-    syscall_spawn_path, syscall_terminate_task, syscall_waitpid,
+    syscall_getppid, syscall_getrlimit, syscall_getuid, syscall_set_cpu_affinity,
+    syscall_set_syscall_filter, syscall_setgid, syscall_setpgid, syscall_setrlimit, syscall_setsid,
+    syscall_setuid, syscall_spawn_path, syscall_spawnve, syscall_terminate_task, syscall_waitpid,
 };
 use crate::syscall::signal::{
     syscall_kill, syscall_rt_sigaction, syscall_rt_sigprocmask, syscall_rt_sigreturn,
 };
 pub use crate::syscall::ui_handlers::{
-    syscall_buffer_age, syscall_clipboard_copy, syscall_clipboard_paste, syscall_drain_queue,
-    syscall_enumerate_windows, syscall_fb_flip, syscall_fb_info, syscall_input_get_button_state,
+    syscall_buffer_age, syscall_clipboard_copy, syscall_clipboard_get, syscall_clipboard_paste,
+    syscall_clipboard_set, syscall_compositor_wait, syscall_cursor_move, syscall_cursor_set_image,
+    syscall_drain_queue, syscall_enumerate_windows, syscall_fb_flip, syscall_fb_flush,
+    syscall_fb_info, syscall_getrandom, syscall_input_get_button_state,
     syscall_input_get_pointer_pos, syscall_input_has_events, syscall_input_poll,
-    syscall_input_poll_batch, syscall_input_request_close, syscall_input_set_focus,
-    syscall_input_set_focus_with_offset, syscall_mark_frames_done, syscall_poll_frame_done,
-    syscall_raise_window, syscall_random_next, syscall_roulette_draw, syscall_roulette_result,
-    syscall_roulette_spin, syscall_set_cursor_shape, syscall_set_window_position,
-    syscall_set_window_state, syscall_shm_acquire, syscall_shm_create,
+    syscall_input_poll_batch, syscall_input_request_close, syscall_input_request_configure,
+    syscall_input_set_focus, syscall_input_set_focus_with_offset, syscall_mark_frames_done,
+    syscall_output_configure, syscall_output_enumerate, syscall_poll_frame_done,
+    syscall_present_feedback, syscall_raise_window, syscall_random_next, syscall_roulette_draw,
+    syscall_roulette_result, syscall_roulette_spin, syscall_set_cursor_shape, syscall_set_keymap,
+    syscall_set_window_position, syscall_set_window_state, syscall_shm_acquire, syscall_shm_create,
     syscall_shm_create_with_format, syscall_shm_destroy, syscall_shm_get_formats, syscall_shm_map,
     syscall_shm_poll_released, syscall_shm_release, syscall_shm_unmap, syscall_surface_attach,
-    syscall_surface_commit, syscall_surface_damage, syscall_surface_frame,
-    syscall_surface_set_parent, syscall_surface_set_rel_pos, syscall_surface_set_role,
-    syscall_surface_set_title, syscall_tty_set_focus,
+    syscall_surface_commit, syscall_surface_damage, syscall_surface_frame, syscall_surface_present,
+    syscall_surface_set_buffer_scale, syscall_surface_set_opacity, syscall_surface_set_parent,
+    syscall_surface_set_rel_pos, syscall_surface_set_role, syscall_surface_set_title,
+    syscall_surface_set_window_flags, syscall_surface_thumbnail, syscall_tty_set_focus,
+    syscall_wait_vblank, syscall_wallpaper_reload, syscall_wallpaper_take_reload,
 };
 
 /// Build the static syscall dispatch table from a compact registration list.
@@ -83,15 +95,39 @@ static SYSCALL_TABLE: [SyscallEntry; SYSCALL_TABLE_SIZE] = syscall_table! {
     [SYSCALL_SYS_INFO]       => syscall_sys_info,       "sys_info";
     [SYSCALL_NET_SCAN]       => syscall_net_scan,       "net_scan";
     [SYSCALL_NET_INFO]       => syscall_net_info,       "net_info";
+    [SYSCALL_NET_FILTER]     => syscall_net_filter,     "net_filter";
+    [SYSCALL_NET_PING]       => syscall_net_ping,       "net_ping";
+    [SYSCALL_NET_ROUTE]      => syscall_net_route,      "net_route";
+    [SYSCALL_NET_LEASE]      => syscall_net_lease,      "net_lease";
+    [SYSCALL_NET_STAT]       => syscall_net_stat,       "net_stat";
     [SYSCALL_HALT]           => syscall_halt,            "halt";
     [SYSCALL_REBOOT]         => syscall_reboot,          "reboot";
     [SYSCALL_CLOCK_GETTIME]  => syscall_clock_gettime,  "clock_gettime";
+    [SYSCALL_KWARN_STATS]    => syscall_kwarn_stats,    "kwarn_stats";
+    [SYSCALL_KLOG_LEVEL]     => syscall_klog_level,     "klog_level";
+    [SYSCALL_KTRACE]         => syscall_ktrace,         "ktrace";
+    [SYSCALL_LOCK_STATS]     => syscall_lock_stats,     "lock_stats";
+    [SYSCALL_TASK_STACK_USAGE] => syscall_task_stack_usage, "task_stack_usage";
+    [SYSCALL_CPU_USAGE]      => syscall_cpu_usage,      "cpu_usage";
+    [SYSCALL_TASK_SNAPSHOT]  => syscall_task_snapshot,  "task_snapshot";
+    [SYSCALL_HW_INVENTORY]   => syscall_hw_inventory,   "hw_inventory";
+    [SYSCALL_AUDIT_CTL]      => syscall_audit_ctl,      "audit_ctl";
+    [SYSCALL_CRASH_NOTICE]   => syscall_crash_notice,   "crash_notice";
+    [SYSCALL_PERF_OPEN]      => syscall_perf_open,      "perf_open";
+    [SYSCALL_PERF_READ]      => syscall_perf_read,      "perf_read";
+    [SYSCALL_PERF_CLOSE]     => syscall_perf_close,     "perf_close";
 
     // Random / Roulette
     [SYSCALL_RANDOM_NEXT]     => syscall_random_next,     "random_next";
+    [SYSCALL_GETRANDOM]       => syscall_getrandom,       "getrandom";
     [SYSCALL_ROULETTE]        => syscall_roulette_spin,   "roulette";
     [SYSCALL_ROULETTE_RESULT] => syscall_roulette_result, "roulette_result";
     [SYSCALL_ROULETTE_DRAW]   => syscall_roulette_draw,   "roulette_draw";
+    [SYSCALL_FB_FLUSH]        => syscall_fb_flush,        "fb_flush";
+
+    // Audio
+    [SYSCALL_AUDIO_WRITE] => syscall_audio_write, "audio_write";
+    [SYSCALL_AUDIO_CTL]   => syscall_audio_ctl,   "audio_ctl";
 
     // Filesystem
     [SYSCALL_FS_OPEN]   => syscall_fs_open,   "fs_open";
@@ -103,6 +139,8 @@ static SYSCALL_TABLE: [SyscallEntry; SYSCALL_TABLE_SIZE] = syscall_table! {
     [SYSCALL_FS_UNLINK] => syscall_fs_unlink, "fs_unlink";
     [SYSCALL_FS_LIST]   => syscall_fs_list,   "fs_list";
     [SYSCALL_RENAME]    => syscall_rename,    "rename";
+    [SYSCALL_CHMOD]     => syscall_chmod,     "chmod";
+    [SYSCALL_CHOWN]     => syscall_chown,     "chown";
 
     [SYSCALL_SOCKET]  => syscall_socket,  "socket";
     [SYSCALL_BIND]    => syscall_bind,    "bind";
@@ -140,8 +178,22 @@ static SYSCALL_TABLE: [SyscallEntry; SYSCALL_TABLE_SIZE] = syscall_table! {
     [SYSCALL_SURFACE_SET_PARENT]  => syscall_surface_set_parent,  "surface_set_parent";
     [SYSCALL_SURFACE_SET_REL_POS] => syscall_surface_set_rel_pos, "surface_set_rel_pos";
     [SYSCALL_SURFACE_SET_TITLE]   => syscall_surface_set_title,   "surface_set_title";
+    [SYSCALL_SURFACE_SET_OPACITY] => syscall_surface_set_opacity, "surface_set_opacity";
+    [SYSCALL_SURFACE_THUMBNAIL]   => syscall_surface_thumbnail,   "surface_thumbnail";
+    [SYSCALL_SURFACE_SET_WINDOW_FLAGS] => syscall_surface_set_window_flags, "surface_set_window_flags";
+    [SYSCALL_SURFACE_SET_BUFFER_SCALE] => syscall_surface_set_buffer_scale, "surface_set_buffer_scale";
+    [SYSCALL_WAIT_VBLANK]         => syscall_wait_vblank,         "wait_vblank";
+    [SYSCALL_CURSOR_SET_IMAGE]    => syscall_cursor_set_image,    "cursor_set_image";
+    [SYSCALL_CURSOR_MOVE]         => syscall_cursor_move,         "cursor_move";
+    [SYSCALL_OUTPUT_ENUMERATE]    => syscall_output_enumerate,    "output_enumerate";
+    [SYSCALL_OUTPUT_CONFIGURE]    => syscall_output_configure,    "output_configure";
     [SYSCALL_FB_FLIP]             => syscall_fb_flip,             "fb_flip";
     [SYSCALL_DRAIN_QUEUE]         => syscall_drain_queue,         "drain_queue";
+    [SYSCALL_COMPOSITOR_WAIT]     => syscall_compositor_wait,     "compositor_wait";
+    [SYSCALL_WALLPAPER_RELOAD]    => syscall_wallpaper_reload,    "wallpaper_reload";
+    [SYSCALL_WALLPAPER_TAKE_RELOAD] => syscall_wallpaper_take_reload, "wallpaper_take_reload";
+    [SYSCALL_SURFACE_PRESENT]     => syscall_surface_present,     "surface_present";
+    [SYSCALL_PRESENT_FEEDBACK]    => syscall_present_feedback,    "present_feedback";
 
     // Shared memory
     [SYSCALL_SHM_CREATE]             => syscall_shm_create,             "shm_create";
@@ -163,11 +215,16 @@ static SYSCALL_TABLE: [SyscallEntry; SYSCALL_TABLE_SIZE] = syscall_table! {
     [SYSCALL_INPUT_GET_POINTER_POS]      => syscall_input_get_pointer_pos,      "input_get_pointer_pos";
     [SYSCALL_INPUT_GET_BUTTON_STATE]     => syscall_input_get_button_state,     "input_get_button_state";
     [SYSCALL_INPUT_REQUEST_CLOSE]        => syscall_input_request_close,        "input_request_close";
+    [SYSCALL_INPUT_REQUEST_CONFIGURE]    => syscall_input_request_configure,    "input_request_configure";
+    [SYSCALL_SET_KEYMAP]                 => syscall_set_keymap,                 "set_keymap";
     [SYSCALL_CLIPBOARD_COPY]             => syscall_clipboard_copy,             "clipboard_copy";
     [SYSCALL_CLIPBOARD_PASTE]            => syscall_clipboard_paste,            "clipboard_paste";
+    [SYSCALL_CLIPBOARD_SET]              => syscall_clipboard_set,              "clipboard_set";
+    [SYSCALL_CLIPBOARD_GET]              => syscall_clipboard_get,              "clipboard_get";
 
     // Task management
     [SYSCALL_SPAWN_PATH]     => syscall_spawn_path,     "spawn_path";
+    [SYSCALL_SPAWNVE]        => syscall_spawnve,        "spawnve";
     [SYSCALL_WAITPID]        => syscall_waitpid,        "waitpid";
     [SYSCALL_TERMINATE_TASK] => syscall_terminate_task,  "terminate_task";
     [SYSCALL_EXEC]           => syscall_exec,            "exec";
@@ -195,8 +252,13 @@ static SYSCALL_TABLE: [SyscallEntry; SYSCALL_TABLE_SIZE] = syscall_table! {
     [SYSCALL_GETGID]  => syscall_getgid,  "getgid";
     [SYSCALL_GETEUID] => syscall_geteuid, "geteuid";
     [SYSCALL_GETEGID] => syscall_getegid, "getegid";
+    [SYSCALL_SETUID]  => syscall_setuid,  "setuid";
+    [SYSCALL_SETGID]  => syscall_setgid,  "setgid";
     [SYSCALL_CHDIR]   => syscall_chdir,   "chdir";
     [SYSCALL_GETCWD]  => syscall_getcwd,  "getcwd";
+    [SYSCALL_SET_SYSCALL_FILTER] => syscall_set_syscall_filter, "set_syscall_filter";
+    [SYSCALL_GETRLIMIT] => syscall_getrlimit, "getrlimit";
+    [SYSCALL_SETRLIMIT] => syscall_setrlimit, "setrlimit";
 
     [SYSCALL_RT_SIGACTION]   => syscall_rt_sigaction,   "rt_sigaction";
     [SYSCALL_RT_SIGPROCMASK] => syscall_rt_sigprocmask, "rt_sigprocmask";
@@ -214,6 +276,7 @@ static SYSCALL_TABLE: [SyscallEntry; SYSCALL_TABLE_SIZE] = syscall_table! {
     [SYSCALL_SELECT] => syscall_select, "select";
     [SYSCALL_PIPE] => syscall_pipe, "pipe";
     [SYSCALL_PIPE2] => syscall_pipe2, "pipe2";
+    [SYSCALL_OPENPTY] => syscall_openpty, "openpty";
     [SYSCALL_IOCTL] => syscall_ioctl, "ioctl";
     [SYSCALL_SETPGID] => syscall_setpgid, "setpgid";
     [SYSCALL_GETPGID] => syscall_getpgid, "getpgid";
@@ -1,3 +1,8 @@
+use slopos_abi::syscall::{MAP_FIXED, MAP_FRAMEBUFFER};
+use slopos_lib::kernel_services::syscall_services::video;
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+use slopos_mm::pat::MEM_TYPE_WC;
+
 define_syscall!(syscall_brk(ctx, args) requires(let process_id) {
     let new_brk = args.arg0;
     let result = slopos_mm::process_vm::process_vm_brk(process_id, new_brk);
@@ -11,6 +16,27 @@ define_syscall!(syscall_mmap(ctx, args) requires(let process_id) {
     let flags = args.arg3;
     let fd = args.arg4 as i64;
     let offset = args.arg5;
+    if flags & MAP_FRAMEBUFFER != 0 {
+        if let Err(disp) = ctx.require_display_exclusive() {
+            return disp;
+        }
+        if flags & MAP_FIXED != 0 || fd != -1 || offset & (PAGE_SIZE_4KB - 1) != 0 {
+            return ctx.invalid_arg();
+        }
+        let (phys, size) = some_or_err!(ctx, video::scanout_region());
+        let end = some_or_err!(ctx, offset.checked_add(length));
+        if length == 0 || end > size as u64 {
+            return ctx.invalid_arg();
+        }
+        let result = slopos_mm::process_vm::process_vm_map_phys(
+            process_id,
+            phys.offset(offset),
+            length,
+            prot,
+            MEM_TYPE_WC,
+        );
+        return ctx.from_nonzero(result);
+    }
     let result = slopos_mm::process_vm::process_vm_mmap(
         process_id, addr, length, prot, flags, fd, offset,
     );
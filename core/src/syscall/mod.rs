@@ -1,9 +1,11 @@
 #[macro_use]
 pub mod macros;
+pub mod audio_handlers;
 pub mod common;
 pub mod context;
 pub mod core_handlers;
 pub mod dispatch;
+pub mod filter;
 pub mod fs;
 pub mod handlers;
 pub mod memory_handlers;
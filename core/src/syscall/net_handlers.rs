@@ -1,7 +1,10 @@
 use crate::syscall::common::SyscallDisposition;
 use crate::syscall::context::SyscallContext;
-use slopos_abi::net::{AF_INET, INVALID_SOCKET_IDX, SOCK_DGRAM, SOCK_STREAM, SockAddrIn};
+use slopos_abi::net::{
+    AF_INET, NET_RESOLVE_NONBLOCK, SOCK_DGRAM, SOCK_RAW, SOCK_STREAM, SockAddrIn,
+};
 use slopos_abi::syscall::*;
+use slopos_abi::task::TASK_FLAG_NET_RAW;
 use slopos_lib::kernel_services::syscall_services::socket;
 use slopos_mm::user_copy::{
     copy_bytes_from_user, copy_bytes_to_user, copy_from_user, copy_to_user,
@@ -29,12 +32,7 @@ fn rc_i64(ctx: &SyscallContext, rc: i64) -> SyscallDisposition {
 }
 
 fn socket_idx_for_fd(process_id: u32, fd: i32) -> Result<u32, u64> {
-    let idx = slopos_fs::fileio_get_socket_idx(process_id, fd).unwrap_or(INVALID_SOCKET_IDX);
-    if idx == INVALID_SOCKET_IDX {
-        Err(ERRNO_ENOTSOCK)
-    } else {
-        Ok(idx)
-    }
+    slopos_fs::fileio_get_socket_idx(process_id, fd).ok_or(ERRNO_ENOTSOCK)
 }
 
 define_syscall!(syscall_socket(ctx, args) requires(let process_id) {
@@ -45,9 +43,12 @@ define_syscall!(syscall_socket(ctx, args) requires(let process_id) {
     if domain != AF_INET {
         return ctx.err_with(ERRNO_EAFNOSUPPORT);
     }
-    if sock_type != SOCK_STREAM && sock_type != SOCK_DGRAM {
+    if sock_type != SOCK_STREAM && sock_type != SOCK_DGRAM && sock_type != SOCK_RAW {
         return ctx.err_with(ERRNO_EPROTONOSUPPORT);
     }
+    if sock_type == SOCK_RAW && !ctx.has_flag(TASK_FLAG_NET_RAW) {
+        return ctx.err_with(ERRNO_EPERM);
+    }
 
     let sock_idx = socket::create(domain, sock_type, protocol);
     if sock_idx < 0 {
@@ -403,10 +404,14 @@ define_syscall!(syscall_shutdown(ctx, args) requires(let process_id) {
 });
 
 define_syscall!(syscall_resolve(ctx, args) requires(let process_id) {
-    // arg0 = hostname pointer, arg1 = hostname length, arg2 = result pointer
+    // arg0 = hostname pointer, arg1 = hostname length, arg2 = result pointer,
+    // arg3 = flags
     if args.arg0 == 0 || args.arg2 == 0 {
         return ctx.err_with(ERRNO_EFAULT);
     }
+    if args.arg3 & !NET_RESOLVE_NONBLOCK != 0 {
+        return ctx.err_with(ERRNO_EINVAL);
+    }
 
     let hostname_len = args.arg1_usize();
     if hostname_len == 0 || hostname_len > 253 {
@@ -427,11 +432,12 @@ define_syscall!(syscall_resolve(ctx, args) requires(let process_id) {
     let rc = dns::resolve(
         hostname_buf[..hostname_len].as_ptr(),
         hostname_len,
+        args.arg3 & NET_RESOLVE_NONBLOCK != 0,
         &mut result_addr as *mut [u8; 4],
     );
 
     if rc < 0 {
-        return ctx.err_with(ERRNO_EHOSTUNREACH);
+        return ctx.err_with(errno_i32(rc));
     }
 
     // Copy result to user memory
@@ -10,8 +10,10 @@ use crate::syscall::common::{
 };
 use crate::syscall::context::SyscallContext;
 use slopos_abi::fs::FS_TYPE_DIRECTORY;
+use slopos_abi::rlimit::{RLIMIT_CORE, RLimit};
 use slopos_abi::syscall::*;
-use slopos_abi::task::{INVALID_TASK_ID, TaskExitRecord};
+use slopos_abi::syscall_filter::SyscallFilter;
+use slopos_abi::task::{INVALID_TASK_ID, TASK_FLAG_NET_RAW, TaskExitRecord};
 use slopos_fs::vfs::traits::VfsError;
 
 use slopos_lib::InterruptFrame;
@@ -20,13 +22,14 @@ use slopos_mm::user_ptr::UserPtr;
 
 use crate::task::task_get_exit_record;
 
+/// Read up to `max_count` pointers followed by a null terminator.
 fn read_user_ptr_array_terminated(base_ptr: u64, max_count: usize) -> Result<Vec<u64>, ()> {
     let mut out = Vec::new();
     if out.try_reserve(max_count).is_err() {
         return Err(());
     }
 
-    for idx in 0..max_count {
+    for idx in 0..=max_count {
         let slot_addr = base_ptr
             .checked_add((idx * core::mem::size_of::<u64>()) as u64)
             .ok_or(())?;
@@ -35,6 +38,9 @@ fn read_user_ptr_array_terminated(base_ptr: u64, max_count: usize) -> Result<Vec
         if value == 0 {
             return Ok(out);
         }
+        if idx == max_count {
+            break;
+        }
         out.push(value);
     }
 
@@ -92,6 +98,22 @@ fn read_user_cstr_list(ptrs: &[u64]) -> Result<Vec<Vec<u8>>, ()> {
     Ok(out)
 }
 
+/// Copy a null-terminated array of C strings such as argv or envp;
+/// `None` when `base_ptr` is 0.
+fn read_user_cstr_array(base_ptr: u64, max_count: usize) -> Result<Option<Vec<Vec<u8>>>, ()> {
+    if base_ptr == 0 {
+        return Ok(None);
+    }
+    let ptrs = read_user_ptr_array_terminated(base_ptr, max_count)?;
+    read_user_cstr_list(ptrs.as_slice()).map(Some)
+}
+
+fn cstr_refs(values: &Option<Vec<Vec<u8>>>) -> Option<Vec<&[u8]>> {
+    values
+        .as_ref()
+        .map(|values| values.iter().map(|v| v.as_slice()).collect())
+}
+
 define_syscall!(syscall_spawn_path(ctx, args) {
     let path_ptr = args.arg0 as *const u8;
     let path_len = args.arg1 as usize;
@@ -130,22 +152,68 @@ define_syscall!(syscall_spawn_path(ctx, args) {
         None
     };
 
-    let argv_refs = argv_storage
-        .as_ref()
-        .map(|values| values.iter().map(|v| v.as_slice()).collect::<Vec<&[u8]>>());
-
-    let parent_pid = ctx.process_id().unwrap_or(slopos_abi::task::INVALID_PROCESS_ID);
-    match exec::spawn_program_with_attrs(
+    let argv_refs = cstr_refs(&argv_storage);
+    spawn_for_caller(
+        &ctx,
         &path_buf[..copied_len],
         argv_refs.as_deref(),
+        None,
         priority,
         flags,
-        parent_pid,
-    ) {
+    )
+});
+
+define_syscall!(syscall_spawnve(ctx, args) {
+    let mut path_buf = [0u8; exec::EXEC_MAX_PATH];
+    if args.arg0 == 0 || syscall_copy_user_str(&mut path_buf, args.arg0).is_err() {
+        return ctx.err();
+    }
+    let path_len = path_buf
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(path_buf.len());
+
+    let Ok(argv_storage) = read_user_cstr_array(args.arg1, exec::EXEC_MAX_ARGS) else {
+        return ctx.err();
+    };
+    let Ok(envp_storage) = read_user_cstr_array(args.arg2, exec::EXEC_MAX_ENVS) else {
+        return ctx.err();
+    };
+    let argv_refs = cstr_refs(&argv_storage);
+    let envp_refs = cstr_refs(&envp_storage);
+
+    spawn_for_caller(
+        &ctx,
+        &path_buf[..path_len],
+        argv_refs.as_deref(),
+        envp_refs.as_deref(),
+        args.arg3 as u8,
+        args.arg4 as u16,
+    )
+});
+
+/// Spawn `path` as a child of the calling task, inheriting its descriptors.
+fn spawn_for_caller(
+    ctx: &SyscallContext,
+    path: &[u8],
+    argv: Option<&[&[u8]]>,
+    envp: Option<&[&[u8]]>,
+    priority: u8,
+    mut flags: u16,
+) -> SyscallDisposition {
+    // Raw socket access is only passed down, never granted.
+    if !ctx.has_flag(TASK_FLAG_NET_RAW) {
+        flags &= !TASK_FLAG_NET_RAW;
+    }
+
+    let parent_pid = ctx
+        .process_id()
+        .unwrap_or(slopos_abi::task::INVALID_PROCESS_ID);
+    match exec::spawn_program_with_attrs(path, argv, envp, priority, flags, parent_pid) {
         Ok(task_id) => ctx.ok(task_id as u64),
         Err(err) => ctx.ok(err as i32 as u64),
     }
-});
+}
 
 define_syscall!(syscall_waitpid(ctx, args) {
     let target_id = args.arg0 as u32;
@@ -221,38 +289,14 @@ pub fn syscall_exec(task: *mut Task, frame: *mut InterruptFrame) -> SyscallDispo
         .unwrap_or(path_buf.len());
     let path = &path_buf[..path_len];
 
-    let argv_storage = if argv_ptr != 0 {
-        let argv_ptrs = match read_user_ptr_array_terminated(argv_ptr, exec::EXEC_MAX_ARGS) {
-            Ok(ptrs) => ptrs,
-            Err(_) => return ctx.err(),
-        };
-        match read_user_cstr_list(argv_ptrs.as_slice()) {
-            Ok(values) => Some(values),
-            Err(_) => return ctx.err(),
-        }
-    } else {
-        None
+    let Ok(argv_storage) = read_user_cstr_array(argv_ptr, exec::EXEC_MAX_ARGS) else {
+        return ctx.err();
     };
-
-    let envp_storage = if envp_ptr != 0 {
-        let envp_ptrs = match read_user_ptr_array_terminated(envp_ptr, exec::EXEC_MAX_ENVS) {
-            Ok(ptrs) => ptrs,
-            Err(_) => return ctx.err(),
-        };
-        match read_user_cstr_list(envp_ptrs.as_slice()) {
-            Ok(values) => Some(values),
-            Err(_) => return ctx.err(),
-        }
-    } else {
-        None
+    let Ok(envp_storage) = read_user_cstr_array(envp_ptr, exec::EXEC_MAX_ENVS) else {
+        return ctx.err();
     };
-
-    let argv_refs = argv_storage
-        .as_ref()
-        .map(|values| values.iter().map(|v| v.as_slice()).collect::<Vec<&[u8]>>());
-    let envp_refs = envp_storage
-        .as_ref()
-        .map(|values| values.iter().map(|v| v.as_slice()).collect::<Vec<&[u8]>>());
+    let argv_refs = cstr_refs(&argv_storage);
+    let envp_refs = cstr_refs(&envp_storage);
 
     let mut entry_point = 0u64;
     let mut stack_ptr = 0u64;
@@ -415,21 +459,47 @@ define_syscall!(syscall_setsid(ctx, args) requires(let task_id) {
 
 define_syscall!(syscall_getuid(ctx, args) {
     let _ = args;
-    ctx.ok(0)
+    let task = some_or_err!(ctx, ctx.task_mut());
+    ctx.ok(task.creds.uid as u64)
 });
 
 define_syscall!(syscall_getgid(ctx, args) {
     let _ = args;
-    ctx.ok(0)
+    let task = some_or_err!(ctx, ctx.task_mut());
+    ctx.ok(task.creds.gid as u64)
 });
 
 define_syscall!(syscall_geteuid(ctx, args) {
     let _ = args;
-    ctx.ok(0)
+    let task = some_or_err!(ctx, ctx.task_mut());
+    ctx.ok(task.creds.euid as u64)
 });
 
 define_syscall!(syscall_getegid(ctx, args) {
     let _ = args;
+    let task = some_or_err!(ctx, ctx.task_mut());
+    ctx.ok(task.creds.egid as u64)
+});
+
+define_syscall!(syscall_setuid(ctx, args) {
+    let Ok(uid) = u32::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    let task = some_or_err!(ctx, ctx.task_mut());
+    if !task.creds.set_uid(uid) {
+        return ctx.err_with(ERRNO_EPERM);
+    }
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_setgid(ctx, args) {
+    let Ok(gid) = u32::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    let task = some_or_err!(ctx, ctx.task_mut());
+    if !task.creds.set_gid(gid) {
+        return ctx.err_with(ERRNO_EPERM);
+    }
     ctx.ok(0)
 });
 
@@ -494,6 +564,54 @@ define_syscall!(syscall_getcwd(ctx, args) {
     ctx.ok(needed as u64)
 });
 
+define_syscall!(syscall_set_syscall_filter(ctx, args) {
+    require_nonzero!(ctx, args.arg0);
+    let user_ptr = try_or_err!(ctx, UserPtr::<SyscallFilter>::try_new(args.arg0));
+    let filter = match copy_from_user(user_ptr) {
+        Ok(filter) => filter,
+        Err(_) => return ctx.bad_address(),
+    };
+    let task = some_or_err!(ctx, ctx.task_mut());
+    if task.syscall_filter.install(&filter).is_err() {
+        return ctx.invalid_arg();
+    }
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_getrlimit(ctx, args) {
+    if args.arg0 != RLIMIT_CORE as u64 {
+        return ctx.invalid_arg();
+    }
+    require_nonzero!(ctx, args.arg1);
+    let user_ptr = try_or_err!(ctx, UserPtr::<RLimit>::try_new(args.arg1));
+    let task = some_or_err!(ctx, ctx.task_mut());
+    if copy_to_user(user_ptr, &task.core_limit).is_err() {
+        return ctx.bad_address();
+    }
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_setrlimit(ctx, args) {
+    if args.arg0 != RLIMIT_CORE as u64 {
+        return ctx.invalid_arg();
+    }
+    require_nonzero!(ctx, args.arg1);
+    let user_ptr = try_or_err!(ctx, UserPtr::<RLimit>::try_new(args.arg1));
+    let limit = match copy_from_user(user_ptr) {
+        Ok(limit) => limit,
+        Err(_) => return ctx.bad_address(),
+    };
+    let task = some_or_err!(ctx, ctx.task_mut());
+    if limit.cur > limit.max {
+        return ctx.invalid_arg();
+    }
+    if !task.core_limit.may_set(&limit, task.creds.is_root()) {
+        return ctx.err_with(ERRNO_EPERM);
+    }
+    task.core_limit = limit;
+    ctx.ok(0)
+});
+
 pub fn syscall_arch_prctl(task: *mut Task, frame: *mut InterruptFrame) -> SyscallDisposition {
     let Some(ctx) = SyscallContext::new(task, frame) else {
         return syscall_return_err(frame, ERRNO_EINVAL);
@@ -5,9 +5,9 @@ use slopos_abi::signal::{
     NSIG, SA_NODEFER, SIG_DFL, SIG_IGN, SIG_SETMASK, SIG_UNBLOCK, SIG_UNCATCHABLE, SIGKILL,
     SigDefault, SigSet, SignalFrame, UserSigaction, sig_bit, sig_default_action,
 };
-use slopos_abi::syscall::{ERRNO_EFAULT, ERRNO_EINVAL, ERRNO_ESRCH};
+use slopos_abi::syscall::{ERRNO_EFAULT, ERRNO_EINVAL, ERRNO_EPERM, ERRNO_ESRCH};
 use slopos_abi::task::{
-    INVALID_TASK_ID, MAX_TASKS, TASK_FLAG_USER_MODE, TaskExitReason, TaskFaultReason,
+    Credentials, INVALID_TASK_ID, MAX_TASKS, TASK_FLAG_USER_MODE, TaskExitReason, TaskFaultReason,
 };
 use slopos_lib::InterruptFrame;
 use slopos_mm::user_copy::{copy_from_user, copy_to_user};
@@ -288,6 +288,17 @@ pub fn syscall_kill(task: *mut Task, frame: *mut InterruptFrame) -> SyscallDispo
         return ctx.err_with(ERRNO_ESRCH);
     }
 
+    // Non-root callers may only signal tasks running under their uid.
+    let creds = ctx.task_mut().map_or(Credentials::ROOT, |t| t.creds);
+    let permitted =
+        |target: *mut Task| !target.is_null() && creds.may_signal(unsafe { &(*target).creds });
+    if !targets.ids[..targets.len]
+        .iter()
+        .any(|&id| permitted(task_find_by_id(id)))
+    {
+        return ctx.err_with(ERRNO_EPERM);
+    }
+
     if args.arg1 == 0 {
         return ctx.ok(0);
     }
@@ -301,7 +312,7 @@ pub fn syscall_kill(task: *mut Task, frame: *mut InterruptFrame) -> SyscallDispo
 
     for target_id in &targets.ids[..targets.len] {
         let target = task_find_by_id(*target_id);
-        if target.is_null() {
+        if !permitted(target) {
             continue;
         }
 
@@ -8,9 +8,11 @@ use core::ptr;
 use core::sync::atomic::Ordering;
 
 use crate::scheduler::task_struct::Task;
+use crate::syscall::filter::{FilterError, FilterVerdict, TaskSyscallFilter};
 use crate::syscall::fs::syscall_ioctl;
 use crate::syscall::handlers::{
-    syscall_arch_prctl, syscall_futex, syscall_getpgid, syscall_setpgid, syscall_setsid,
+    syscall_arch_prctl, syscall_futex, syscall_getpgid, syscall_getuid, syscall_kwarn_stats,
+    syscall_set_syscall_filter, syscall_setpgid, syscall_setsid, syscall_setuid,
 };
 use crate::syscall::signal::{
     deliver_pending_signal, syscall_kill, syscall_rt_sigaction, syscall_rt_sigprocmask,
@@ -23,15 +25,26 @@ use slopos_abi::signal::{
 };
 use slopos_abi::syscall::{
     ARCH_GET_FS, ARCH_SET_FS, CLONE_SETTLS, CLONE_SIGHAND, CLONE_THREAD, CLONE_VM, ERRNO_EAGAIN,
-    F_GETFL, FUTEX_WAIT, FUTEX_WAKE, MAP_ANONYMOUS, MAP_PRIVATE, O_NOCTTY, O_NONBLOCK, POLLIN,
-    SYSCALL_ARCH_PRCTL, SYSCALL_CLONE, SYSCALL_FUTEX, SYSCALL_GETPGID, SYSCALL_IOCTL, SYSCALL_KILL,
-    SYSCALL_NET_SCAN, SYSCALL_PIPE, SYSCALL_PIPE2, SYSCALL_POLL, SYSCALL_RT_SIGACTION,
-    SYSCALL_RT_SIGPROCMASK, SYSCALL_RT_SIGRETURN, SYSCALL_SELECT, SYSCALL_SETPGID, SYSCALL_SETSID,
+    ERRNO_EINVAL, ERRNO_EPERM, F_DUPFD_CLOEXEC, F_GETFD, F_GETFL, FD_CLOEXEC, FUTEX_WAIT,
+    FUTEX_WAKE, KWARN_PANIC_ON, KwarnStats, MAP_ANONYMOUS, MAP_PRIVATE, O_CLOEXEC, O_NOCTTY,
+    O_NONBLOCK, POLLIN, SYSCALL_ARCH_PRCTL, SYSCALL_CLONE, SYSCALL_EXIT, SYSCALL_FS_OPEN,
+    SYSCALL_FS_READ, SYSCALL_FUTEX, SYSCALL_GETPGID, SYSCALL_IOCTL, SYSCALL_KILL, SYSCALL_NET_SCAN,
+    SYSCALL_PIPE, SYSCALL_PIPE2, SYSCALL_POLL, SYSCALL_RT_SIGACTION, SYSCALL_RT_SIGPROCMASK,
+    SYSCALL_RT_SIGRETURN, SYSCALL_SELECT, SYSCALL_SETPGID, SYSCALL_SETSID, SYSCALL_SOCKET,
     SYSCALL_TABLE_SIZE, TIOCSCTTY, TtyIndex,
 };
-use slopos_abi::task::{INVALID_TASK_ID, TASK_FLAG_KERNEL_MODE, TASK_FLAG_USER_MODE, TaskStatus};
+use slopos_abi::syscall_filter::{
+    SYSCALL_FILTER_ACTION_ERRNO, SYSCALL_FILTER_ACTION_KILL, SYSCALL_FILTER_FS,
+    SYSCALL_FILTER_MODE_DENY, SYSCALL_FILTER_NET, SyscallFilter,
+};
+use slopos_abi::task::{
+    Credentials, INVALID_TASK_ID, TASK_FLAG_KERNEL_MODE, TASK_FLAG_USER_MODE, TaskStatus,
+};
 use slopos_lib::InterruptFrame;
-use slopos_lib::{assert_eq_test, assert_not_null, assert_test, klog_info, testing::TestResult};
+use slopos_lib::{
+    assert_eq_test, assert_not_null, assert_test, kbug, klog_info, kwarn, kwarn_once,
+    testing::TestResult,
+};
 use slopos_mm::page_alloc::{ALLOC_FLAG_ZERO, alloc_page_frame};
 use slopos_mm::paging::map_page_4kb_in_dir;
 use slopos_mm::paging_defs::PageFlags;
@@ -47,8 +60,10 @@ use crate::scheduler::task::{
 use crate::scheduler::{per_cpu, task};
 use crate::syscall::handlers::syscall_lookup;
 use slopos_fs::fileio::{
-    file_close_fd, file_fcntl_fd, file_open_for_process, file_pipe_create, file_poll_fd,
-    file_read_fd, file_write_fd, fileio_clone_table_for_process, fileio_destroy_table_for_process,
+    file_close_fd, file_dup_fd, file_dup2_fd, file_fcntl_fd, file_open_for_process, file_openpty,
+    file_pipe_create, file_poll_fd, file_read_fd, file_write_fd, fileio_clone_table_for_process,
+    fileio_destroy_table_for_process, fileio_get_socket_idx, fileio_inherit_table_for_exec,
+    fileio_open_socket_fd,
 };
 use slopos_mm::memory_layout_defs::PROCESS_CODE_START_VA;
 
@@ -760,6 +775,48 @@ pub fn test_pts_open_with_o_noctty_skips_controlling_tty_acquire() -> TestResult
     TestResult::Pass
 }
 
+pub fn test_openpty_opens_both_ends_without_controlling_tty() -> TestResult {
+    let _fixture = SyscallFixture::new();
+
+    let task_id = create_test_user_task();
+    assert_test!(task_id != INVALID_TASK_ID, "failed to create task");
+    let task_ptr = task_find_by_id(task_id);
+    assert_not_null!(task_ptr, "task lookup failed");
+    let pid = unsafe { (*task_ptr).process_id };
+
+    let mut master_fd = -1;
+    let mut slave_fd = -1;
+    let rc = file_openpty(pid, O_CLOEXEC as u32, &mut master_fd, &mut slave_fd);
+    assert_eq_test!(rc, 0, "openpty failed");
+    assert_test!(
+        master_fd >= 0 && slave_fd >= 0 && master_fd != slave_fd,
+        "openpty returned bad fds"
+    );
+
+    let slave_flags = file_fcntl_fd(pid, slave_fd, F_GETFL, 0);
+    let slave_fd_flags = file_fcntl_fd(pid, slave_fd, F_GETFD, 0);
+    let ctty = unsafe { (*task_ptr).controlling_tty };
+    let (mut unused_master, mut unused_slave) = (-1, -1);
+    let bad_rc = file_openpty(pid, O_NOCTTY as u32, &mut unused_master, &mut unused_slave);
+    let _ = file_close_fd(pid, slave_fd);
+    let _ = file_close_fd(pid, master_fd);
+    task_terminate(task_id);
+
+    assert_test!(
+        slave_flags >= 0 && (slave_flags as u64 & O_NOCTTY) != 0,
+        "openpty slave should be O_NOCTTY"
+    );
+    assert_eq_test!(
+        slave_fd_flags as u64,
+        FD_CLOEXEC,
+        "openpty should apply O_CLOEXEC"
+    );
+    assert_eq_test!(ctty, None, "openpty should not acquire a ctty");
+    assert_test!(bad_rc != 0, "openpty should reject unknown flags");
+
+    TestResult::Pass
+}
+
 pub fn test_vm_mmap_munmap_stress_baseline() -> TestResult {
     let _fixture = SyscallFixture::new();
 
@@ -1045,6 +1102,26 @@ pub fn test_shm_create_boundaries() -> TestResult {
     TestResult::Pass
 }
 
+/// A destroyed buffer's token must stay dead even when its registry slot is
+/// handed to the next buffer.
+pub fn test_shm_stale_token_rejected() -> TestResult {
+    use slopos_mm::shared_memory::{shm_create, shm_destroy, shm_get_size};
+
+    let old = shm_create(1, 4096, 0);
+    assert_test!(old != 0, "shm_create failed");
+    assert_eq_test!(shm_destroy(1, old), 0, "shm_destroy failed");
+
+    let new = shm_create(1, 4096, 0);
+    assert_test!(new != 0, "second shm_create failed");
+    assert_test!(new != old, "token reused after destroy");
+    assert_eq_test!(shm_get_size(old), 0, "stale token still resolves");
+    assert_eq_test!(shm_destroy(1, old), -1, "stale token destroyed a buffer");
+    assert_eq_test!(shm_get_size(new), 4096, "live buffer lost");
+
+    assert_eq_test!(shm_destroy(1, new), 0, "cleanup destroy failed");
+    TestResult::Pass
+}
+
 // =============================================================================
 // Task State Corruption Tests
 // =============================================================================
@@ -1778,6 +1855,282 @@ pub fn test_arch_prctl_set_get_fs_roundtrip() -> TestResult {
     TestResult::Pass
 }
 
+// =============================================================================
+// Kernel Warning Tests
+// =============================================================================
+
+/// kwarn!/kwarn_once! count every hit but log each site only once in a
+/// burst; a false condition is free.
+pub fn test_kwarn_counts_and_rate_limits() -> TestResult {
+    let panic_on_warn = kwarn::kwarn_panic_on_warn();
+    kwarn::kwarn_set_panic_on_warn(false);
+
+    let before = kwarn::kwarn_stats();
+    let quiet = kwarn!(false, "never fires");
+    let mut values = [false; 6];
+    for value in &mut values[..3] {
+        *value = kwarn!(true, "kwarn rate-limit test");
+    }
+    for value in &mut values[3..] {
+        *value = kwarn_once!(true);
+    }
+    kbug!(false, "never fires");
+
+    let after = kwarn::kwarn_stats();
+    kwarn::kwarn_set_panic_on_warn(panic_on_warn);
+    assert_test!(!quiet, "false condition reported");
+    assert_test!(
+        values.iter().all(|&v| v),
+        "macros should evaluate to the condition"
+    );
+    assert_eq_test!(after.warnings - before.warnings, 6, "expected six hits");
+    assert_eq_test!(
+        after.suppressed - before.suppressed,
+        4,
+        "repeat hits within the window should be suppressed"
+    );
+    assert_eq_test!(after.bugs, before.bugs, "false kbug! counted");
+    TestResult::Pass
+}
+
+pub fn test_kwarn_stats_syscall_roundtrip() -> TestResult {
+    let _fixture = SyscallFixture::new();
+    let panic_on_warn = kwarn::kwarn_panic_on_warn();
+
+    let task_id = create_test_user_task();
+    assert_test!(task_id != INVALID_TASK_ID, "failed to create user task");
+    let task_ptr = task_find_by_id(task_id);
+    assert_not_null!(task_ptr, "task lookup failed");
+    let pid = unsafe { (*task_ptr).process_id };
+
+    let Some(out_addr) = map_user_rw_page(pid) else {
+        task_terminate(task_id);
+        return TestResult::Fail;
+    };
+
+    let mut frame = zero_frame();
+    frame.rdi = out_addr;
+    frame.rsi = KWARN_PANIC_ON;
+    let _ = with_user_process_context(pid, || syscall_kwarn_stats(task_ptr, &mut frame));
+    let stats: Option<KwarnStats> = user_copy_in(pid, out_addr);
+    let enabled = kwarn::kwarn_panic_on_warn();
+
+    let mut bad_frame = zero_frame();
+    bad_frame.rdi = out_addr;
+    bad_frame.rsi = 7;
+    let _ = with_user_process_context(pid, || syscall_kwarn_stats(task_ptr, &mut bad_frame));
+
+    kwarn::kwarn_set_panic_on_warn(panic_on_warn);
+    task_terminate(task_id);
+
+    assert_eq_test!(frame.rax, 0, "kwarn_stats failed");
+    assert_test!(enabled, "KWARN_PANIC_ON did not set the knob");
+    let Some(stats) = stats else {
+        return TestResult::Fail;
+    };
+    assert_eq_test!(stats.panic_on_warn, 1, "stats should reflect the new knob");
+    assert_test!(
+        stats.warnings >= stats.suppressed,
+        "suppressed hits exceed total warnings"
+    );
+    assert_eq_test!(bad_frame.rax, ERRNO_EINVAL, "unknown knob value accepted");
+    TestResult::Pass
+}
+
+// =============================================================================
+// Syscall Filter Tests
+// =============================================================================
+
+pub fn test_syscall_filter_stacking() -> TestResult {
+    let mut filter = TaskSyscallFilter::new();
+    assert_test!(!filter.is_active());
+    assert_eq_test!(filter.verdict(SYSCALL_SOCKET), FilterVerdict::Allow);
+
+    let deny_net = SyscallFilter::deny(SYSCALL_FILTER_NET, SYSCALL_FILTER_ACTION_ERRNO);
+    assert_test!(filter.install(&deny_net).is_ok(), "deny filter rejected");
+    assert_eq_test!(
+        filter.verdict(SYSCALL_SOCKET),
+        FilterVerdict::Errno(ERRNO_EPERM)
+    );
+    assert_eq_test!(filter.verdict(SYSCALL_FS_OPEN), FilterVerdict::Allow);
+
+    // An allowlist that names socket cannot bring it back.
+    let mut allow = SyscallFilter::allow_only(
+        &[SYSCALL_SOCKET, SYSCALL_FS_READ],
+        SYSCALL_FILTER_ACTION_KILL,
+    );
+    allow.errno = 13;
+    assert_test!(filter.install(&allow).is_ok(), "allow filter rejected");
+    assert_eq_test!(
+        filter.verdict(SYSCALL_SOCKET),
+        FilterVerdict::Errno(ERRNO_EPERM)
+    );
+    assert_eq_test!(filter.verdict(SYSCALL_FS_READ), FilterVerdict::Allow);
+    assert_eq_test!(filter.verdict(SYSCALL_FS_OPEN), FilterVerdict::Kill);
+
+    // A later errno filter refuses with the first errno filter's errno and
+    // does not turn into a kill; calls a kill filter refuses stay kills.
+    let mut deny_read = SyscallFilter::deny(&[SYSCALL_FS_READ], SYSCALL_FILTER_ACTION_ERRNO);
+    deny_read.errno = 13;
+    assert_test!(filter.install(&deny_read).is_ok());
+    assert_eq_test!(
+        filter.verdict(SYSCALL_FS_READ),
+        FilterVerdict::Errno(ERRNO_EPERM)
+    );
+    assert_eq_test!(filter.verdict(SYSCALL_FS_OPEN), FilterVerdict::Kill);
+
+    // Exit and sigreturn always get through.
+    assert_eq_test!(filter.verdict(SYSCALL_EXIT), FilterVerdict::Allow);
+    assert_eq_test!(filter.verdict(SYSCALL_RT_SIGRETURN), FilterVerdict::Allow);
+
+    let mut bad = SyscallFilter::new(7, SYSCALL_FILTER_ACTION_ERRNO);
+    assert_eq_test!(
+        filter.install(&bad),
+        Err(FilterError::UnknownMode),
+        "unknown mode accepted"
+    );
+    bad.mode = SYSCALL_FILTER_MODE_DENY;
+    bad.action = 9;
+    assert_eq_test!(
+        filter.install(&bad),
+        Err(FilterError::UnknownAction),
+        "unknown action accepted"
+    );
+    TestResult::Pass
+}
+
+pub fn test_syscall_filter_syscall_and_fork() -> TestResult {
+    let _fixture = SyscallFixture::new();
+
+    let task_id = create_test_user_task();
+    assert_test!(task_id != INVALID_TASK_ID, "failed to create user task");
+    let task_ptr = task_find_by_id(task_id);
+    assert_not_null!(task_ptr, "task lookup failed");
+    let pid = unsafe { (*task_ptr).process_id };
+
+    let Some(addr) = map_user_rw_page(pid) else {
+        task_terminate(task_id);
+        return TestResult::Fail;
+    };
+    let mut filter = SyscallFilter::deny(SYSCALL_FILTER_FS, SYSCALL_FILTER_ACTION_ERRNO);
+    filter.errno = 13;
+    if !user_copy_out(pid, addr, &filter) {
+        task_terminate(task_id);
+        return TestResult::Fail;
+    }
+
+    let mut frame = zero_frame();
+    frame.rdi = addr;
+    let _ = with_user_process_context(pid, || syscall_set_syscall_filter(task_ptr, &mut frame));
+
+    let mut bad = SyscallFilter::new(SYSCALL_FILTER_MODE_DENY, SYSCALL_FILTER_ACTION_ERRNO);
+    bad.reserved = 1;
+    let _ = user_copy_out(pid, addr, &bad);
+    let mut bad_frame = zero_frame();
+    bad_frame.rdi = addr;
+    let _ = with_user_process_context(pid, || syscall_set_syscall_filter(task_ptr, &mut bad_frame));
+
+    let child_id = task_fork(task_ptr, core::ptr::null());
+    let child_ptr = task_find_by_id(child_id);
+    let child_verdict = (!child_ptr.is_null())
+        .then(|| unsafe { (*child_ptr).syscall_filter.verdict(SYSCALL_FS_OPEN) });
+    let verdict = unsafe { (*task_ptr).syscall_filter.verdict(SYSCALL_FS_OPEN) };
+    let read_verdict = unsafe { (*task_ptr).syscall_filter.verdict(SYSCALL_FS_READ) };
+
+    if child_id != INVALID_TASK_ID {
+        task_terminate(child_id);
+    }
+    task_terminate(task_id);
+
+    assert_eq_test!(frame.rax, 0, "set_syscall_filter failed");
+    assert_eq_test!(bad_frame.rax, ERRNO_EINVAL, "reserved field accepted");
+    assert_eq_test!(verdict, FilterVerdict::Errno((-13i64) as u64));
+    assert_eq_test!(read_verdict, FilterVerdict::Allow);
+    assert_eq_test!(
+        child_verdict,
+        Some(FilterVerdict::Errno((-13i64) as u64)),
+        "fork did not inherit the filter"
+    );
+    TestResult::Pass
+}
+
+pub fn test_setuid_rules_and_fork_inherit() -> TestResult {
+    let _fixture = SyscallFixture::new();
+
+    let task_id = create_test_user_task();
+    assert_test!(task_id != INVALID_TASK_ID, "failed to create user task");
+    let task_ptr = task_find_by_id(task_id);
+    assert_not_null!(task_ptr, "task lookup failed");
+
+    let mut drop_frame = zero_frame();
+    drop_frame.rdi = 1000;
+    let _ = syscall_setuid(task_ptr, &mut drop_frame);
+    let mut getuid_frame = zero_frame();
+    let _ = syscall_getuid(task_ptr, &mut getuid_frame);
+    let mut regain_frame = zero_frame();
+    regain_frame.rdi = 0;
+    let _ = syscall_setuid(task_ptr, &mut regain_frame);
+
+    let child_id = task_fork(task_ptr, core::ptr::null());
+    let child_ptr = task_find_by_id(child_id);
+    let child_creds = (!child_ptr.is_null()).then(|| unsafe { (*child_ptr).creds });
+
+    if child_id != INVALID_TASK_ID {
+        task_terminate(child_id);
+    }
+    task_terminate(task_id);
+
+    assert_eq_test!(drop_frame.rax, 0, "root could not drop to uid 1000");
+    assert_eq_test!(getuid_frame.rax, 1000, "getuid after setuid");
+    assert_eq_test!(regain_frame.rax, ERRNO_EPERM, "uid 1000 regained root");
+    assert_eq_test!(
+        child_creds,
+        Some(Credentials::new(1000, 0)),
+        "fork did not inherit credentials"
+    );
+    TestResult::Pass
+}
+
+pub fn test_kill_requires_matching_uid() -> TestResult {
+    let _fixture = SyscallFixture::new();
+
+    let user_id = create_test_user_task();
+    let other_id = create_test_user_task();
+    assert_test!(
+        user_id != INVALID_TASK_ID && other_id != INVALID_TASK_ID,
+        "failed to create user tasks"
+    );
+    let user_ptr = task_find_by_id(user_id);
+    let other_ptr = task_find_by_id(other_id);
+    assert_not_null!(user_ptr, "user lookup failed");
+    assert_not_null!(other_ptr, "other lookup failed");
+    unsafe {
+        (*user_ptr).creds = Credentials::new(1000, 1000);
+        (*other_ptr).creds = Credentials::new(1001, 1001);
+    }
+    let user_pid = unsafe { (*user_ptr).process_id };
+
+    let mut denied_frame = zero_frame();
+    denied_frame.rdi = other_id as u64;
+    denied_frame.rsi = SIGUSR1 as u64;
+    let _ = with_user_process_context(user_pid, || syscall_kill(user_ptr, &mut denied_frame));
+    let pending = unsafe { (*other_ptr).signal_pending.load(Ordering::Acquire) };
+
+    unsafe { (*other_ptr).creds = Credentials::new(1000, 1001) };
+    let mut allowed_frame = zero_frame();
+    allowed_frame.rdi = other_id as u64;
+    allowed_frame.rsi = 0;
+    let _ = with_user_process_context(user_pid, || syscall_kill(user_ptr, &mut allowed_frame));
+
+    task_terminate(other_id);
+    task_terminate(user_id);
+
+    assert_eq_test!(denied_frame.rax, ERRNO_EPERM, "kill across uids");
+    assert_eq_test!(pending & sig_bit(SIGUSR1), 0, "signal delivered anyway");
+    assert_eq_test!(allowed_frame.rax, 0, "kill probe for same uid");
+    TestResult::Pass
+}
+
 // =============================================================================
 // Pipe Blocking & EOF Tests
 // =============================================================================
@@ -2055,6 +2408,52 @@ pub fn test_pipe_buffer_full() -> TestResult {
     TestResult::Pass
 }
 
+/// A dup'd socket fd shares the socket: closing one fd must not close the
+/// socket under the other, and the last close must release it.
+pub fn test_socket_fd_dup_shares_socket() -> TestResult {
+    use slopos_abi::net::{AF_INET, SOCK_DGRAM};
+    use slopos_lib::kernel_services::syscall_services::socket;
+
+    let _fixture = SyscallFixture::new();
+
+    let task_id = create_test_user_task();
+    assert_test!(task_id != INVALID_TASK_ID, "failed to create user task");
+    let task_ptr = task_find_by_id(task_id);
+    assert_not_null!(task_ptr, "task lookup failed");
+    let pid = unsafe { (*task_ptr).process_id };
+
+    let sock_idx = socket::create(AF_INET, SOCK_DGRAM, 0);
+    if sock_idx < 0 {
+        task_terminate(task_id);
+        return TestResult::Skipped;
+    }
+    let fd = fileio_open_socket_fd(pid, sock_idx as u32);
+    assert_test!(fd >= 0, "socket fd install failed");
+
+    let dup = file_dup_fd(pid, fd);
+    assert_test!(dup >= 0 && dup != fd, "dup failed");
+    assert_eq_test!(file_close_fd(pid, fd), 0, "close original failed");
+    assert_eq_test!(
+        fileio_get_socket_idx(pid, dup),
+        Some(sock_idx as u32),
+        "dup lost its socket when the original closed"
+    );
+
+    assert_eq_test!(file_close_fd(pid, dup), 0, "close dup failed");
+    assert_eq_test!(
+        fileio_get_socket_idx(pid, dup),
+        None,
+        "closed fd still resolves to a socket"
+    );
+    assert_test!(
+        socket::bind(sock_idx as u32, [0, 0, 0, 0], 40000) < 0,
+        "socket still open after last fd closed"
+    );
+
+    task_terminate(task_id);
+    TestResult::Pass
+}
+
 /// Regression: when the current task exits, its file table must be destroyed
 /// so pipe writer refs are released and peer readers observe EOF.
 pub fn test_exit_current_task_releases_pipe_refs() -> TestResult {
@@ -2112,6 +2511,153 @@ pub fn test_exit_current_task_releases_pipe_refs() -> TestResult {
     TestResult::Pass
 }
 
+/// Model the shell capturing a spawned program's stdout: the pipe and the
+/// saved stdout are close-on-exec, so the spawned child inherits only the
+/// pipe write end the shell dup2()ed onto fd 1.  A plain fork keeps every
+/// descriptor and its FD_CLOEXEC flag.
+pub fn test_spawn_inherit_drops_cloexec_fds() -> TestResult {
+    let _fixture = SyscallFixture::new();
+
+    let t1 = create_test_user_task();
+    let t2 = create_test_user_task();
+    let t3 = create_test_user_task();
+    assert_test!(
+        t1 != INVALID_TASK_ID && t2 != INVALID_TASK_ID && t3 != INVALID_TASK_ID,
+        "failed to create tasks"
+    );
+    let pid1 = unsafe { (*task_find_by_id(t1)).process_id };
+    let pid2 = unsafe { (*task_find_by_id(t2)).process_id };
+    let pid3 = unsafe { (*task_find_by_id(t3)).process_id };
+
+    let mut read_fd = -1;
+    let mut write_fd = -1;
+    assert_eq_test!(
+        file_pipe_create(
+            pid1,
+            (O_CLOEXEC | O_NONBLOCK) as u32,
+            &mut read_fd,
+            &mut write_fd
+        ),
+        0,
+        "pipe create failed"
+    );
+    let backup_fd = file_fcntl_fd(pid1, 1, F_DUPFD_CLOEXEC, 0) as i32;
+    assert_test!(backup_fd >= 0, "F_DUPFD_CLOEXEC failed");
+    assert_eq_test!(
+        file_dup2_fd(pid1, write_fd, 1),
+        1,
+        "dup2 onto stdout failed"
+    );
+    assert_eq_test!(file_close_fd(pid1, write_fd), 0, "close write end failed");
+
+    // fork: a straight copy, flags included.
+    fileio_destroy_table_for_process(pid3);
+    assert_eq_test!(
+        fileio_clone_table_for_process(pid1, pid3),
+        0,
+        "fork table clone failed"
+    );
+    let forked_flags = file_fcntl_fd(pid3, read_fd, F_GETFD, 0);
+
+    // spawn: the copy minus FD_CLOEXEC descriptors.
+    assert_eq_test!(
+        fileio_inherit_table_for_exec(pid1, pid2),
+        0,
+        "spawn table inherit failed"
+    );
+    let child_stdout = file_fcntl_fd(pid2, 1, F_GETFD, 0);
+    let child_read = file_fcntl_fd(pid2, read_fd, F_GETFD, 0);
+    let child_backup = file_fcntl_fd(pid2, backup_fd, F_GETFD, 0);
+
+    let payload = b"out";
+    let written = file_write_fd(pid2, 1, payload.as_ptr() as *const c_char, payload.len());
+
+    // Restore the parent's stdout; after both children drop fd 1 the
+    // parent's read end must see EOF.
+    assert_eq_test!(file_dup2_fd(pid1, backup_fd, 1), 1, "restore stdout failed");
+    let _ = file_close_fd(pid1, backup_fd);
+    task_terminate(t3);
+    task_terminate(t2);
+    let mut buf = [0u8; 8];
+    let first = file_read_fd(pid1, read_fd, buf.as_mut_ptr() as *mut c_char, buf.len());
+    let second = file_read_fd(pid1, read_fd, buf.as_mut_ptr() as *mut c_char, buf.len());
+    let _ = file_close_fd(pid1, read_fd);
+    task_terminate(t1);
+
+    assert_eq_test!(forked_flags, FD_CLOEXEC as i64, "fork lost FD_CLOEXEC");
+    assert_eq_test!(child_stdout, 0, "spawned child lost stdout");
+    assert_eq_test!(child_read, -1, "spawned child inherited the read end");
+    assert_eq_test!(child_backup, -1, "spawned child inherited the backup fd");
+    assert_eq_test!(written, payload.len() as isize, "child write failed");
+    assert_eq_test!(
+        first,
+        payload.len() as isize,
+        "parent did not read child output"
+    );
+    assert_eq_test!(second, 0, "pipe not at EOF after children exited");
+    TestResult::Pass
+}
+
+/// `SYSCALL_SPAWNVE` copies null-terminated argv/envp arrays of up to
+/// `EXEC_MAX_ARGS`/`EXEC_MAX_ENVS` entries and refuses longer ones before
+/// touching the filesystem.
+pub fn test_spawnve_copies_argv_and_envp() -> TestResult {
+    use crate::syscall::handlers::syscall_spawnve;
+    use slopos_abi::syscall::{ERRNO_EINVAL, EXEC_MAX_ENVS};
+
+    let _fixture = SyscallFixture::new();
+
+    let task_id = create_test_user_task();
+    assert_test!(task_id != INVALID_TASK_ID, "failed to create user task");
+    let task_ptr = task_find_by_id(task_id);
+    assert_not_null!(task_ptr, "task lookup failed");
+    let pid = unsafe { (*task_ptr).process_id };
+
+    let Some(page) = map_user_rw_page(pid) else {
+        task_terminate(task_id);
+        return TestResult::Fail;
+    };
+    let path_ptr = page;
+    let var_ptr = page + 64;
+    let argv_ptr = page + 128;
+    let envp_ptr = page + 256;
+
+    let mut argv = [0u64; 2];
+    argv[0] = path_ptr;
+    let mut envp = [0u64; EXEC_MAX_ENVS + 2];
+    envp[..EXEC_MAX_ENVS].fill(var_ptr);
+    let mut too_many = envp;
+    too_many[EXEC_MAX_ENVS] = var_ptr;
+    if !user_copy_out(pid, path_ptr, b"/bin/noent\0")
+        || !user_copy_out(pid, var_ptr, b"HOME=/\0")
+        || !user_copy_out(pid, argv_ptr, &argv)
+        || !user_copy_out(pid, envp_ptr, &envp)
+    {
+        task_terminate(task_id);
+        return TestResult::Fail;
+    }
+
+    let spawn = |frame: &mut InterruptFrame| {
+        frame.rdi = path_ptr;
+        frame.rsi = argv_ptr;
+        frame.rdx = envp_ptr;
+        frame.r10 = 1;
+        let _ = with_user_process_context(pid, || syscall_spawnve(task_ptr, frame));
+    };
+    let mut full = zero_frame();
+    spawn(&mut full);
+    let _ = user_copy_out(pid, envp_ptr, &too_many);
+    let mut over = zero_frame();
+    spawn(&mut over);
+
+    task_terminate(task_id);
+
+    // ExecError::NoEntry: both arrays were accepted and the lookup ran.
+    assert_eq_test!(full.rax, (-2i32) as u64, "full envp rejected");
+    assert_eq_test!(over.rax, ERRNO_EINVAL, "oversized envp accepted");
+    TestResult::Pass
+}
+
 /// Regression test for the stale-argv spawn bug (compositor spawn failure).
 ///
 /// Before the fix, `spawn_path_with_attrs()` used `syscall4` which left r8/r9
@@ -2215,6 +2761,7 @@ slopos_lib::define_test_suite!(
         test_user_ptr_overflow_boundary,
         test_brk_extreme_values,
         test_shm_create_boundaries,
+        test_shm_stale_token_rejected,
         test_terminate_already_terminated,
         test_operations_on_terminated_task,
         test_fork_memory_pressure,
@@ -2228,6 +2775,12 @@ slopos_lib::define_test_suite!(
         test_sigprocmask_block_then_unblock_delivery,
         test_sigchld_and_wait_interaction,
         test_arch_prctl_set_get_fs_roundtrip,
+        test_kwarn_counts_and_rate_limits,
+        test_kwarn_stats_syscall_roundtrip,
+        test_syscall_filter_stacking,
+        test_syscall_filter_syscall_and_fork,
+        test_setuid_rules_and_fork_inherit,
+        test_kill_requires_matching_uid,
         test_pipe_poll_eof_baseline,
         test_pipe_write_read_basic,
         test_pipe_eof_returns_zero,
@@ -2235,7 +2788,9 @@ slopos_lib::define_test_suite!(
         test_pipe_multi_write_read,
         test_pipe_partial_read,
         test_pipe_buffer_full,
+        test_socket_fd_dup_shares_socket,
         test_exit_current_task_releases_pipe_refs,
+        test_spawn_inherit_drops_cloexec_fds,
         test_process_group_session_syscalls_baseline,
         test_kill_process_group_semantics,
         test_tiocsctty_session_leader_acquires_ctty,
@@ -2245,8 +2800,10 @@ slopos_lib::define_test_suite!(
         test_hangup_clears_all_session_controlling_ttys,
         test_pts_open_acquires_controlling_tty_without_o_noctty,
         test_pts_open_with_o_noctty_skips_controlling_tty_acquire,
+        test_openpty_opens_both_ends_without_controlling_tty,
         test_vm_mmap_munmap_stress_baseline,
         test_spawn_path_stale_argv_regression,
+        test_spawnve_copies_argv_and_envp,
     ]
 );
 
@@ -2273,6 +2830,7 @@ slopos_lib::define_test_suite!(
         test_hangup_clears_all_session_controlling_ttys,
         test_pts_open_acquires_controlling_tty_without_o_noctty,
         test_pts_open_with_o_noctty_skips_controlling_tty_acquire,
+        test_openpty_opens_both_ends_without_controlling_tty,
         test_sigchld_and_wait_interaction,
         test_clone_thread_tls_isolation,
         test_futex_wait_mismatch_and_wake_no_waiters,
@@ -1,16 +1,32 @@
+use alloc::vec;
+
 use slopos_abi::damage::{DamageRect, MAX_DAMAGE_REGIONS};
+use slopos_abi::display::MAX_OUTPUTS;
 use slopos_abi::fate::FateResult;
-use slopos_abi::task::INVALID_TASK_ID;
-use slopos_abi::{DisplayInfo, InputEvent, WindowInfo};
-
-use crate::fate_api::{fate_apply_outcome, fate_set_pending, fate_spin, fate_take_pending};
+use slopos_abi::input::{
+    CLIPBOARD_MAX_SIZE, CLIPBOARD_MIME_MAX, ClipboardError, INPUT_FOCUS_KEYBOARD, INPUT_FOCUS_KEYS,
+    INPUT_FOCUS_POINTER, INPUT_FOCUS_SHORTCUTS, KEYMAP_QUERY,
+};
+use slopos_abi::present::PresentFeedback;
+use slopos_abi::syscall::ERRNO_ENODEV;
+use slopos_abi::task::{INVALID_TASK_ID, MAX_TASKS};
+use slopos_abi::window::{CURSOR_IMAGE_BYTES, CURSOR_IMAGE_SIZE};
+use slopos_abi::{DisplayInfo, InputEvent, OutputInfo, WindowInfo};
+
+use crate::fate_api::{
+    fate_apply_outcome, fate_loss_reboots, fate_set_pending, fate_spin, fate_take_pending,
+};
 use crate::platform;
+use crate::syscall::common::syscall_bounded_from_user;
 use slopos_lib::kernel_services::syscall_services::{input, tty, video};
 
 use slopos_mm::paging::{paging_get_kernel_directory, switch_page_directory};
 use slopos_mm::process_vm::process_vm_get_page_dir;
 use slopos_mm::user_copy::{copy_bytes_from_user, copy_bytes_to_user, copy_to_user};
-use slopos_mm::user_ptr::{UserBytes, UserPtr};
+use slopos_mm::user_ptr::{UserBytes, UserPtr, UserSlice};
+
+/// Most events one `input_poll_batch` call hands out.
+const INPUT_POLL_BATCH_MAX: usize = 16;
 
 define_syscall!(syscall_random_next(ctx, args) {
     let _ = args;
@@ -18,6 +34,38 @@ define_syscall!(syscall_random_next(ctx, args) {
     ctx.ok(value)
 });
 
+define_syscall!(syscall_getrandom(ctx, args) {
+    use slopos_abi::syscall::{ERRNO_EAGAIN, GETRANDOM_MAX, GRND_NONBLOCK, GRND_RANDOM};
+
+    let flags = args.arg2;
+    if flags & !(GRND_NONBLOCK | GRND_RANDOM) != 0 {
+        return ctx.invalid_arg();
+    }
+
+    let len = args.arg1_usize().min(GETRANDOM_MAX);
+    if len == 0 {
+        return ctx.ok(0);
+    }
+    // Validate the whole range before possibly sleeping.
+    try_or_err!(ctx, UserBytes::try_new(args.arg0, len));
+
+    if !platform::rng_wait_seeded(flags & GRND_NONBLOCK != 0) {
+        return ctx.err_with(ERRNO_EAGAIN);
+    }
+
+    let mut chunk = [0u8; 256];
+    let mut done = 0;
+    while done < len {
+        let take = (len - done).min(chunk.len());
+        platform::rng_fill(&mut chunk[..take]);
+        let dst = try_or_err!(ctx, UserBytes::try_new(args.arg0 + done as u64, take));
+        try_or_err!(ctx, copy_bytes_to_user(dst, &chunk[..take]));
+        done += take;
+    }
+    chunk.fill(0);
+    ctx.ok(len as u64)
+});
+
 define_syscall!(syscall_shm_get_formats(ctx, args) {
     let _ = args;
     let formats = slopos_mm::shared_memory::shm_get_formats();
@@ -37,6 +85,10 @@ define_syscall!(syscall_poll_frame_done(ctx, args) requires(let task_id) {
     ctx.ok(timestamp)
 });
 
+define_syscall!(syscall_wait_vblank(ctx, args) {
+    ctx.ok(video::wait_vblank(args.arg0))
+});
+
 define_syscall!(syscall_buffer_age(ctx, args) requires(let task_id) {
     let age = video::surface_get_buffer_age(task_id);
     ctx.ok(age as u64)
@@ -102,6 +154,23 @@ define_syscall!(syscall_shm_create_with_format(ctx, args) requires(let task_id)
     ctx.from_token(slopos_mm::shared_memory::shm_create_with_format(task_id, size, format))
 });
 
+define_syscall!(syscall_surface_present(ctx, args) requires(let task_id, let process_id) {
+    let token = args.arg0_u32();
+    let target_ns = args.arg1;
+    let flags = args.arg2_u32();
+    let exclusive = ctx.is_display_exclusive();
+    match video::surface_present(task_id, process_id, token, target_ns, flags, exclusive) {
+        Ok(seq) => ctx.ok(seq),
+        Err(err) => ctx.err_with(err.errno()),
+    }
+});
+
+define_syscall!(syscall_present_feedback(ctx, args) requires(let task_id) {
+    let user_ptr = try_or_err!(ctx, UserPtr::<PresentFeedback>::try_new(args.arg0));
+    try_or_err!(ctx, copy_to_user(user_ptr, &video::present_feedback(task_id)));
+    ctx.ok(0)
+});
+
 define_syscall!(syscall_surface_set_role(ctx, args) requires(let task_id) {
     let role = args.arg0 as u8;
     ctx.from_result(video::surface_set_role(task_id, role))
@@ -118,24 +187,54 @@ define_syscall!(syscall_surface_set_rel_pos(ctx, args) requires(let task_id) {
     ctx.from_result(video::surface_set_relative_position(task_id, rel_x, rel_y))
 });
 
-define_syscall!(syscall_surface_set_title(ctx, args) requires(let task_id) {
-    let title_ptr = args.arg0_const_ptr::<u8>();
-    let title_len = args.arg1_usize();
+define_syscall!(syscall_surface_thumbnail(ctx, args) requires(let process_id) {
+    let target_task_id = args.arg0_u32();
+    let token = args.arg1_u32();
+    let max_width = args.arg2_u32();
+    let max_height = args.arg3_u32();
+    ctx.from_result_map(
+        video::surface_render_thumbnail(target_task_id, process_id, token, max_width, max_height),
+        |(width, height)| ((width as u64) << 32) | height as u64,
+    )
+});
+
+define_syscall!(syscall_surface_set_opacity(ctx, args) requires(let task_id) {
+    let Ok(opacity) = u8::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    let Ok(flags) = u8::try_from(args.arg1) else {
+        return ctx.invalid_arg();
+    };
+    ctx.from_result(video::surface_set_opacity(task_id, opacity, flags))
+});
 
-    if title_ptr.is_null() || title_len == 0 {
-        return ctx.err();
-    }
+define_syscall!(syscall_surface_set_window_flags(ctx, args) requires(let task_id) {
+    let Ok(flags) = u8::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    ctx.from_result(video::surface_set_window_flags(task_id, flags))
+});
+
+define_syscall!(syscall_surface_set_buffer_scale(ctx, args) requires(let task_id) {
+    let Ok(scale) = u32::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    ctx.from_result(video::surface_set_buffer_scale(task_id, scale))
+});
 
-    let copy_len = title_len.min(31);
-    let title_slice = unsafe { core::slice::from_raw_parts(title_ptr, copy_len) };
-    ctx.from_result(video::surface_set_title(task_id, title_slice))
+define_syscall!(syscall_surface_set_title(ctx, args) requires(let task_id) {
+    let mut title = [0u8; 31];
+    let len = try_or_err!(
+        ctx,
+        syscall_bounded_from_user(&mut title, args.arg0, args.arg1, title.len())
+    );
+    ctx.from_result(video::surface_set_title(task_id, &title[..len]))
 });
 
 define_syscall!(syscall_input_poll(ctx, args) requires(let task_id) {
-    let event_ptr = args.arg0_ptr::<InputEvent>();
-    if event_ptr.is_null() {
+    let Ok(event_ptr) = UserPtr::<InputEvent>::try_new(args.arg0) else {
         return ctx.ok_i64(-1);
-    }
+    };
 
     if ctx.is_compositor() && input::get_pointer_focus() == 0 {
         input::set_pointer_focus(task_id, 0);
@@ -143,7 +242,7 @@ define_syscall!(syscall_input_poll(ctx, args) requires(let task_id) {
 
     match input::poll(task_id) {
         Some(event) => {
-            unsafe { *event_ptr = event; }
+            try_or_err!(ctx, copy_to_user(event_ptr, &event));
             ctx.ok(1)
         }
         None => ctx.ok(0),
@@ -151,18 +250,25 @@ define_syscall!(syscall_input_poll(ctx, args) requires(let task_id) {
 });
 
 define_syscall!(syscall_input_poll_batch(ctx, args) requires(let task_id) {
-    let buffer_ptr = args.arg0_ptr::<InputEvent>();
-    let max_count = args.arg1_usize();
-
-    if buffer_ptr.is_null() || max_count == 0 {
+    let mut events = [InputEvent::default(); INPUT_POLL_BATCH_MAX];
+    let max = args.arg1_usize().min(events.len());
+    if args.arg0 == 0 || max == 0 {
         return ctx.ok(0);
     }
+    // Check the whole buffer up front so no event is drained and then lost.
+    try_or_err!(ctx, UserSlice::<InputEvent>::try_new(args.arg0, max));
 
     if ctx.is_compositor() && input::get_pointer_focus() == 0 {
         input::set_pointer_focus(task_id, 0);
     }
 
-    ctx.ok(input::drain_batch(task_id, buffer_ptr, max_count) as u64)
+    let count = input::drain_batch(task_id, &mut events[..max]);
+    for (i, event) in events[..count].iter().enumerate() {
+        let addr = args.arg0 + (i * core::mem::size_of::<InputEvent>()) as u64;
+        let user_ptr = try_or_err!(ctx, UserPtr::<InputEvent>::try_new(addr));
+        try_or_err!(ctx, copy_to_user(user_ptr, event));
+    }
+    ctx.ok(count as u64)
 });
 
 define_syscall!(syscall_input_has_events(ctx, args) requires(let task_id) {
@@ -171,14 +277,17 @@ define_syscall!(syscall_input_has_events(ctx, args) requires(let task_id) {
 });
 
 define_syscall!(syscall_input_set_focus(ctx, args) requires(let task_id) {
-    let _ = task_id;
     let target_task_id = args.arg0_u32();
     let focus_type = args.arg1_u32();
     let timestamp_ms = platform::get_time_ms();
 
     match focus_type {
-        0 => input::set_keyboard_focus(target_task_id),
-        1 => input::set_pointer_focus(target_task_id, timestamp_ms),
+        INPUT_FOCUS_KEYBOARD => input::set_keyboard_focus(target_task_id),
+        INPUT_FOCUS_POINTER => input::set_pointer_focus(target_task_id, timestamp_ms),
+        INPUT_FOCUS_SHORTCUTS if ctx.is_compositor() => {
+            input::set_shortcut_focus(target_task_id)
+        }
+        INPUT_FOCUS_KEYS => input::take_keys(task_id),
         _ => return ctx.ok_i64(-1),
     }
     ctx.ok(0)
@@ -188,8 +297,9 @@ define_syscall!(syscall_input_set_focus_with_offset(ctx, args) requires(composit
     let target_task_id = args.arg0_u32();
     let offset_x = args.arg1 as i32;
     let offset_y = args.arg2 as i32;
+    let scale = args.arg3_u32();
     let timestamp_ms = platform::get_time_ms();
-    input::set_pointer_focus_with_offset(target_task_id, offset_x, offset_y, timestamp_ms);
+    input::set_pointer_focus_with_offset(target_task_id, offset_x, offset_y, scale, timestamp_ms);
     ctx.ok(0)
 });
 
@@ -218,6 +328,38 @@ define_syscall!(syscall_input_request_close(ctx, args) requires(compositor) {
     ctx.ok(0)
 });
 
+define_syscall!(syscall_input_request_configure(ctx, args) requires(compositor) {
+    let target_task_id = args.arg0_u32();
+    if target_task_id == 0 || target_task_id == INVALID_TASK_ID {
+        return ctx.err();
+    }
+    let width = args.arg1_u32();
+    let height = args.arg2_u32();
+    if width == 0 || height == 0 {
+        return ctx.err();
+    }
+
+    let timestamp_ms = platform::get_time_ms();
+    if input::request_configure(target_task_id, width, height, timestamp_ms) != 0 {
+        return ctx.err();
+    }
+
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_set_keymap(ctx, args) {
+    if args.arg0 == KEYMAP_QUERY {
+        return ctx.ok(input::keymap() as u64);
+    }
+    let Ok(id) = u32::try_from(args.arg0) else {
+        return ctx.invalid_arg();
+    };
+    match input::set_keymap(id) {
+        Some(prev) => ctx.ok(prev as u64),
+        None => ctx.invalid_arg(),
+    }
+});
+
 define_syscall!(syscall_clipboard_copy(ctx, args) requires(let task_id) {
     let _ = task_id;
     let src_ptr = args.arg0;
@@ -256,22 +398,137 @@ define_syscall!(syscall_clipboard_paste(ctx, args) requires(let task_id) {
     ctx.ok(write_len as u64)
 });
 
+/// Copy a clipboard MIME type in from user memory.
+fn read_clipboard_mime(
+    ptr: u64,
+    len: usize,
+    buf: &mut [u8; CLIPBOARD_MIME_MAX],
+) -> Result<usize, ClipboardError> {
+    if ptr == 0 || len == 0 || len > CLIPBOARD_MIME_MAX {
+        return Err(ClipboardError::BadMime);
+    }
+    let user = UserBytes::try_new(ptr, len).map_err(|_| ClipboardError::BadMime)?;
+    copy_bytes_from_user(user, &mut buf[..len]).map_err(|_| ClipboardError::BadMime)?;
+    Ok(len)
+}
+
+define_syscall!(syscall_clipboard_set(ctx, args) requires(let task_id) {
+    let _ = task_id;
+    let mut mime = [0u8; CLIPBOARD_MIME_MAX];
+    let mime_len = match read_clipboard_mime(args.arg0, args.arg1_usize(), &mut mime) {
+        Ok(len) => len,
+        Err(err) => return ctx.err_with(err.errno()),
+    };
+
+    let data_len = args.arg3_usize();
+    if data_len > CLIPBOARD_MAX_SIZE {
+        return ctx.err_with(ClipboardError::TooLarge.errno());
+    }
+    let mut buf = [0u8; CLIPBOARD_MAX_SIZE];
+    if data_len > 0 {
+        let user_bytes = try_or_err!(ctx, UserBytes::try_new(args.arg2, data_len));
+        try_or_err!(ctx, copy_bytes_from_user(user_bytes, &mut buf[..data_len]));
+    }
+
+    match input::clipboard_set(&mime[..mime_len], &buf[..data_len]) {
+        Ok(stored) => ctx.ok(stored as u64),
+        Err(err) => ctx.err_with(err.errno()),
+    }
+});
+
+define_syscall!(syscall_clipboard_get(ctx, args) requires(let task_id) {
+    let _ = task_id;
+    let mut mime = [0u8; CLIPBOARD_MIME_MAX];
+    let mime_len = match read_clipboard_mime(args.arg0, args.arg1_usize(), &mut mime) {
+        Ok(len) => len,
+        Err(err) => return ctx.err_with(err.errno()),
+    };
+
+    let mut buf = [0u8; CLIPBOARD_MAX_SIZE];
+    let total = match input::clipboard_get(&mime[..mime_len], &mut buf) {
+        Ok(total) => total,
+        Err(err) => return ctx.err_with(err.errno()),
+    };
+
+    let write_len = total.min(args.arg3_usize());
+    if args.arg2 != 0 && write_len > 0 {
+        let user_ptr = try_or_err!(ctx, UserBytes::try_new(args.arg2, write_len));
+        try_or_err!(ctx, copy_bytes_to_user(user_ptr, &buf[..write_len]));
+    }
+    ctx.ok(total as u64)
+});
+
 define_syscall!(syscall_set_cursor_shape(ctx, args) requires(let task_id) {
     let shape = args.arg0 as u8;
     ctx.from_result(video::surface_set_cursor_shape(task_id, shape))
 });
 
+define_syscall!(syscall_cursor_set_image(ctx, args) requires(compositor) {
+    let hot_x = args.arg1_u32();
+    let hot_y = args.arg2_u32();
+    let shown = if args.arg0 == 0 {
+        video::surface_set_cursor_image(&[], 0, 0)
+    } else {
+        if hot_x >= CURSOR_IMAGE_SIZE || hot_y >= CURSOR_IMAGE_SIZE {
+            return ctx.invalid_arg();
+        }
+        let user_image = try_or_err!(ctx, UserBytes::try_new(args.arg0, CURSOR_IMAGE_BYTES));
+        let mut image = vec![0u8; CURSOR_IMAGE_BYTES];
+        try_or_err!(ctx, copy_bytes_from_user(user_image, &mut image));
+        video::surface_set_cursor_image(&image, hot_x, hot_y)
+    };
+    if shown {
+        ctx.ok(0)
+    } else {
+        ctx.err_with(ERRNO_ENODEV)
+    }
+});
+
+define_syscall!(syscall_cursor_move(ctx, args) requires(compositor) {
+    video::surface_move_cursor(args.arg0_i32(), args.arg1_i32());
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_output_enumerate(ctx, args) {
+    let capacity = args.arg1_usize().min(MAX_OUTPUTS);
+    let mut outputs = [OutputInfo::default(); MAX_OUTPUTS];
+    let count = video::output_enumerate(&mut outputs);
+    for (i, output) in outputs[..count.min(capacity)].iter().enumerate() {
+        let addr = args.arg0 + (i * core::mem::size_of::<OutputInfo>()) as u64;
+        let user_ptr = try_or_err!(ctx, UserPtr::<OutputInfo>::try_new(addr));
+        try_or_err!(ctx, copy_to_user(user_ptr, output));
+    }
+    ctx.ok(count as u64)
+});
+
+define_syscall!(syscall_output_configure(ctx, args) {
+    ctx.from_result(video::output_configure(
+        args.arg0_u32(),
+        args.arg1_i32(),
+        args.arg2_i32(),
+        args.arg3_u32(),
+        args.arg4_u32(),
+        args.arg5_u32(),
+    ))
+});
+
 define_syscall!(syscall_tty_set_focus(ctx, args) requires(compositor) {
     let target = args.arg0_u32();
     ctx.from_bool_value(tty::set_compositor_focus(target) == 0, tty::get_compositor_focus() as u64)
 });
 
 define_syscall!(syscall_enumerate_windows(ctx, args) requires(compositor) {
-    let out_buffer = args.arg0_ptr::<WindowInfo>();
-    let max_count = args.arg1_u32();
-    require_nonnull!(ctx, out_buffer);
+    let max_count = args.arg1_usize().min(MAX_TASKS);
+    require_nonzero!(ctx, args.arg0);
     require_nonzero!(ctx, max_count);
-    ctx.ok(video::surface_enumerate_windows(out_buffer, max_count) as u64)
+    let mut windows = vec![WindowInfo::default(); max_count];
+    let count = video::surface_enumerate_windows(&mut windows) as usize;
+    for (i, window) in windows[..count].iter().enumerate() {
+        let addr = args.arg0 + (i * core::mem::size_of::<WindowInfo>()) as u64;
+        let user_ptr = try_or_err!(ctx, UserPtr::<WindowInfo>::try_new(addr));
+        try_or_err!(ctx, copy_to_user(user_ptr, window));
+    }
+    ctx.ok(count as u64)
 });
 
 define_syscall!(syscall_set_window_position(ctx, args) requires(compositor) {
@@ -341,6 +598,22 @@ define_syscall!(syscall_drain_queue(ctx, args) requires(compositor) {
     ctx.ok(0)
 });
 
+define_syscall!(syscall_compositor_wait(ctx, args) requires(compositor) {
+    let woken = video::compositor_wait(args.arg0);
+    ctx.ok(woken as u64)
+});
+
+define_syscall!(syscall_wallpaper_reload(ctx, args) {
+    let _ = args;
+    video::wallpaper_request_reload();
+    ctx.ok(0)
+});
+
+define_syscall!(syscall_wallpaper_take_reload(ctx, args) requires(compositor) {
+    let _ = args;
+    ctx.ok(video::wallpaper_take_reload() as u64)
+});
+
 define_syscall!(syscall_shm_acquire(ctx, args) requires(compositor) {
     let token = args.arg0_u32();
     let result = slopos_mm::shared_memory::shm_acquire(token);
@@ -383,6 +656,12 @@ define_syscall!(syscall_roulette_draw(ctx, args) requires(display_exclusive) {
     disp
 });
 
+define_syscall!(syscall_fb_flush(ctx, args) requires(display_exclusive) {
+    let _ = args;
+    check_result!(ctx, video::fb_flush());
+    ctx.ok(0)
+});
+
 define_syscall!(syscall_roulette_spin(ctx, args) requires(let task_id) {
     let _ = args;
     let res = fate_spin();
@@ -407,6 +686,9 @@ define_syscall!(syscall_roulette_result(ctx, args) requires(let task_id) {
         ctx.ok(0)
     } else {
         fate_apply_outcome(&stored as *const FateResult, 0, false);
+        if !fate_loss_reboots() {
+            return ctx.ok(1);
+        }
         platform::kernel_reboot(b"Roulette loss - spinning again\0".as_ptr() as *const i8);
     }
 });
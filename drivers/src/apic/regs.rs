@@ -43,6 +43,7 @@ pub(crate) const LAPIC_LVT_DELIVERY_MODE_EXTINT: u32 = 0x7 << 8;
 
 pub(crate) const LAPIC_TIMER_ONESHOT: u32 = 0x0000_0000;
 pub(crate) const LAPIC_TIMER_PERIODIC: u32 = 0x0002_0000;
+pub(crate) const LAPIC_TIMER_TSC_DEADLINE: u32 = 0x0004_0000;
 pub(crate) const LAPIC_TIMER_DIV_16: u32 = 0x3;
 
 // =============================================================================
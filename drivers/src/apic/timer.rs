@@ -10,10 +10,18 @@
 //! measure the actual tick rate once at boot.  After calibration,
 //! [`set_periodic_ms`] converts a desired millisecond interval to the
 //! correct initial count.
+//!
+//! Once the scheduler runs tickless, [`enable_oneshot`] switches each CPU
+//! to one-shot operation and the scheduler re-arms the timer with
+//! [`arm_ns`] for just the next event.  TSC-deadline mode is used where the
+//! CPU advertises it and the TSC has been calibrated; otherwise the LAPIC
+//! count-down one-shot mode stands in.
 
-use core::sync::atomic::{AtomicU64, Ordering};
+use core::sync::atomic::{AtomicU8, AtomicU64, Ordering};
 
-use slopos_lib::{klog_debug, klog_info};
+use slopos_lib::cpu::cpuid::{CPUID_FEAT_ECX_TSC_DEADLINE, CPUID_LEAF_FEATURES};
+use slopos_lib::cpu::msr::{Msr, read_msr, write_msr};
+use slopos_lib::{cpu, klog_debug, klog_info, tsc};
 
 use super::regs::*;
 use super::{
@@ -27,6 +35,21 @@ use super::{
 /// Calibrated LAPIC timer frequency in Hz (ticks per second at divisor 16).
 static LAPIC_TIMER_FREQ_HZ: AtomicU64 = AtomicU64::new(0);
 
+/// How [`arm_ns`] programs the timer.  Every CPU runs the same mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum OneshotMode {
+    /// One-shot not enabled; the timer stays in whatever mode it was set to.
+    Off = 0,
+    /// LAPIC initial-count one-shot (bus clock / 16).
+    Count = 1,
+    /// Absolute TSC deadline via `IA32_TSC_DEADLINE`.
+    TscDeadline = 2,
+}
+
+static ONESHOT_MODE: AtomicU8 = AtomicU8::new(OneshotMode::Off as u8);
+static ONESHOT_VECTOR: AtomicU8 = AtomicU8::new(0);
+
 // ---------------------------------------------------------------------------
 // Calibration tunables
 // ---------------------------------------------------------------------------
@@ -168,6 +191,9 @@ pub fn mask() {
 }
 
 /// Unmask the LAPIC timer LVT entry (resume delivering interrupts).
+///
+/// A one-shot that expired while masked is lost rather than left pending,
+/// and nothing would ever re-arm it; deliver it now instead.
 #[inline]
 pub fn unmask() {
     if !is_enabled() {
@@ -175,6 +201,123 @@ pub fn unmask() {
     }
     let lvt = read_register(LAPIC_LVT_TIMER);
     write_register(LAPIC_LVT_TIMER, lvt & !LAPIC_LVT_MASKED);
+    if lvt & LAPIC_LVT_MASKED != 0 && oneshot_expired() {
+        arm_ns(0);
+    }
+}
+
+fn oneshot_expired() -> bool {
+    match oneshot_mode() {
+        OneshotMode::Off => false,
+        OneshotMode::TscDeadline => read_msr(Msr::TSC_DEADLINE) == 0,
+        OneshotMode::Count => timer_get_current_count() == 0,
+    }
+}
+
+/// Whether this CPU's LAPIC timer supports TSC-deadline mode.
+pub fn tsc_deadline_supported() -> bool {
+    let (_, _, ecx, _) = cpu::cpuid(CPUID_LEAF_FEATURES);
+    ecx & CPUID_FEAT_ECX_TSC_DEADLINE != 0
+}
+
+/// Currently selected one-shot mode.
+#[inline]
+pub fn oneshot_mode() -> OneshotMode {
+    match ONESHOT_MODE.load(Ordering::Acquire) {
+        1 => OneshotMode::Count,
+        2 => OneshotMode::TscDeadline,
+        _ => OneshotMode::Off,
+    }
+}
+
+/// Switch the calling CPU's timer to one-shot mode on `vector`, disarmed.
+///
+/// The first caller picks the mode for every CPU: TSC-deadline when
+/// supported and the TSC is calibrated, count-down otherwise.  Call on each
+/// CPU; the LVT entry is per-LAPIC.  Returns `false` if the LAPIC timer is
+/// not usable.
+pub fn enable_oneshot(vector: u8) -> bool {
+    if !is_enabled() {
+        klog_info!("APIC TIMER: Cannot enable one-shot — LAPIC not enabled");
+        return false;
+    }
+    if !is_calibrated() {
+        klog_info!("APIC TIMER: Cannot enable one-shot — not calibrated");
+        return false;
+    }
+
+    if oneshot_mode() == OneshotMode::Off {
+        let mode = if tsc_deadline_supported() && tsc::calibrated_cycles_per_ms() != 0 {
+            OneshotMode::TscDeadline
+        } else {
+            OneshotMode::Count
+        };
+        ONESHOT_VECTOR.store(vector, Ordering::Release);
+        ONESHOT_MODE.store(mode as u8, Ordering::Release);
+        klog_info!(
+            "APIC TIMER: One-shot mode {:?}, vector 0x{:x}",
+            mode,
+            vector
+        );
+    }
+
+    timer_set_divisor(LAPIC_TIMER_DIV_16);
+    disarm();
+    true
+}
+
+/// Rewrite the LVT for the one-shot mode, preserving the mask bit.
+///
+/// Done on every arm so a timer left periodic (e.g. by a test) comes back.
+fn program_oneshot_lvt(mode: OneshotMode) {
+    let masked = read_register(LAPIC_LVT_TIMER) & LAPIC_LVT_MASKED;
+    let mode_bits = match mode {
+        OneshotMode::TscDeadline => LAPIC_TIMER_TSC_DEADLINE,
+        _ => LAPIC_TIMER_ONESHOT,
+    };
+    let vector = ONESHOT_VECTOR.load(Ordering::Acquire) as u32;
+    write_register(LAPIC_LVT_TIMER, vector | mode_bits | masked);
+}
+
+/// Fire the calling CPU's timer once, `delay_ns` from now.
+///
+/// Replaces any pending deadline.  A no-op until [`enable_oneshot`] ran.
+/// Count mode clamps long delays to the 32-bit counter range; the caller
+/// simply re-arms when it fires early.
+pub fn arm_ns(delay_ns: u64) {
+    let mode = oneshot_mode();
+    if mode == OneshotMode::Off {
+        return;
+    }
+    program_oneshot_lvt(mode);
+
+    match mode {
+        OneshotMode::TscDeadline => {
+            let per_ms = tsc::calibrated_cycles_per_ms();
+            let cycles = (delay_ns as u128 * per_ms as u128 / 1_000_000) as u64;
+            let deadline = tsc::rdtsc().saturating_add(cycles.max(1));
+            write_msr(Msr::TSC_DEADLINE, deadline);
+        }
+        _ => {
+            let freq = frequency_hz();
+            let count = (delay_ns as u128 * freq as u128 / 1_000_000_000) as u64;
+            write_register(LAPIC_TIMER_ICR, count.clamp(1, u32::MAX as u64) as u32);
+        }
+    }
+}
+
+/// Cancel the calling CPU's pending one-shot, if any.
+pub fn disarm() {
+    let mode = oneshot_mode();
+    if mode == OneshotMode::Off {
+        return;
+    }
+    program_oneshot_lvt(mode);
+
+    match mode {
+        OneshotMode::TscDeadline => write_msr(Msr::TSC_DEADLINE, 0),
+        _ => write_register(LAPIC_TIMER_ICR, 0),
+    }
 }
 
 // ---------------------------------------------------------------------------
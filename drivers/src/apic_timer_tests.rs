@@ -347,6 +347,74 @@ pub fn test_lapic_timer_tick_rate_reasonable() -> TestResult {
     TestResult::Pass
 }
 
+// ---------------------------------------------------------------------------
+// One-shot (tickless) mode
+// ---------------------------------------------------------------------------
+
+/// TSC-deadline mode must be chosen exactly when the CPU supports it and
+/// the TSC has been calibrated.
+pub fn test_lapic_timer_oneshot_mode_selected() -> TestResult {
+    let mode = apic::timer::oneshot_mode();
+    let expected = if apic::timer::tsc_deadline_supported()
+        && slopos_lib::tsc::calibrated_cycles_per_ms() != 0
+    {
+        apic::timer::OneshotMode::TscDeadline
+    } else {
+        apic::timer::OneshotMode::Count
+    };
+    if mode != expected {
+        klog_info!(
+            "LAPIC_TIMER_TEST: BUG - one-shot mode {:?}, expected {:?}",
+            mode,
+            expected,
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+/// A disarmed one-shot timer must stay silent; arming it once must bring
+/// the self-re-arming scheduler tick back.
+///
+/// Runs last: it leaves the timer in the tickless one-shot state the rest
+/// of the kernel expects after the periodic tests above.
+pub fn test_lapic_timer_oneshot_disarm_and_rearm() -> TestResult {
+    if !apic::is_enabled() || !apic::timer::is_calibrated() {
+        klog_info!("LAPIC_TIMER_TEST: SKIP - timer not usable");
+        return TestResult::Skipped;
+    }
+
+    if !apic::timer::enable_oneshot(LAPIC_TIMER_VECTOR) {
+        klog_info!("LAPIC_TIMER_TEST: BUG - enable_oneshot failed");
+        return TestResult::Fail;
+    }
+
+    let ticks_before = irq_get_timer_ticks();
+    crate::hpet::delay_ms(50);
+    let delta_disarmed = irq_get_timer_ticks().saturating_sub(ticks_before);
+
+    apic::timer::arm_ns(10_000_000);
+    let ticks_armed = irq_get_timer_ticks();
+    crate::hpet::delay_ms(50);
+    let delta_armed = irq_get_timer_ticks().saturating_sub(ticks_armed);
+
+    if delta_disarmed > 1 {
+        klog_info!(
+            "LAPIC_TIMER_TEST: BUG - disarmed timer still ticked (delta={})",
+            delta_disarmed,
+        );
+        return TestResult::Fail;
+    }
+    if delta_armed < 2 {
+        klog_info!(
+            "LAPIC_TIMER_TEST: BUG - re-armed timer did not keep ticking (delta={})",
+            delta_armed,
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
 slopos_lib::define_test_suite!(
     apic_timer,
     [
@@ -361,5 +429,7 @@ slopos_lib::define_test_suite!(
         test_lapic_timer_mask_suppresses_ticks,
         test_lapic_timer_idt_gate_installed,
         test_lapic_timer_tick_rate_reasonable,
+        test_lapic_timer_oneshot_mode_selected,
+        test_lapic_timer_oneshot_disarm_and_rearm,
     ]
 );
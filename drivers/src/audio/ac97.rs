@@ -0,0 +1,282 @@
+//! Intel AC'97 audio controller (PCI class 04:01), the fallback when no HDA
+//! controller exists.
+//!
+//! BAR0 is the mixer (NAM) and BAR1 the bus master (NABM), both I/O space.
+//! The PCM-out box walks a 32-entry buffer descriptor list and halts after
+//! the "last valid index"; every position read pushes LVI ahead of the
+//! current index so DMA loops over the ring like HDA does.  The 32 entries
+//! map onto the ring's pages twice over.
+
+use core::ffi::c_int;
+use core::ptr;
+
+use slopos_abi::audio::AUDIO_BACKEND_AC97;
+use slopos_lib::io::Port;
+use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_info};
+use slopos_mm::page_alloc::OwnedPageFrame;
+
+use super::hda::{PCI_CLASS_MULTIMEDIA, PCI_SUBCLASS_HDA};
+use super::{AudioBackendOps, RING_PAGES, alloc_ring, audio_present, audio_register_backend};
+use crate::hpet;
+use crate::pci::{
+    PciDeviceInfo, PciDriver, pci_config_read16, pci_config_write16, pci_get_device,
+    pci_get_device_count, pci_register_driver,
+};
+use crate::pci_defs::{PCI_COMMAND_BUS_MASTER, PCI_COMMAND_IO_SPACE, PCI_COMMAND_OFFSET};
+
+pub const PCI_SUBCLASS_AC97: u8 = 0x01;
+
+// Mixer registers (AC'97 2.3, section 5.7).
+const NAM_RESET: u16 = 0x00;
+const NAM_MASTER_VOLUME: u16 = 0x02;
+const NAM_PCM_OUT_VOLUME: u16 = 0x18;
+
+/// 0 dB on both channels, unmuted.
+const PCM_OUT_0DB: u16 = 0x0808;
+
+// Bus master registers, PCM out box.
+const NABM_PO_BDBAR: u16 = 0x10;
+const NABM_PO_CIV: u16 = 0x14;
+const NABM_PO_LVI: u16 = 0x15;
+const NABM_PO_SR: u16 = 0x16;
+const NABM_PO_PICB: u16 = 0x18;
+const NABM_PO_CR: u16 = 0x1B;
+const NABM_GLOB_CNT: u16 = 0x2C;
+const NABM_GLOB_STA: u16 = 0x30;
+
+const CR_RPBM: u8 = 1 << 0;
+const CR_RR: u8 = 1 << 1;
+/// LVBCI | BCIS | FIFOE, write-1-to-clear.
+const SR_CLEAR: u16 = 0x1C;
+/// Deassert AC-link cold reset.
+const GLOB_CNT_COLD_RESET: u32 = 1 << 1;
+const GLOB_STA_PRIMARY_READY: u32 = 1 << 8;
+
+const BDL_ENTRIES: usize = 32;
+/// Descriptor lengths count 16-bit samples, not bytes.
+const SAMPLES_PER_PAGE: u16 = 2048;
+const PAGE_SIZE: usize = 4096;
+
+/// Buffer descriptor list entry.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BdlEntry {
+    addr: u32,
+    samples: u16,
+    flags: u16,
+}
+
+struct Ac97 {
+    nabm: u16,
+    bdl_phys: u32,
+}
+
+static DEVICE_CLAIMED: InitFlag = InitFlag::new();
+static DEVICE: IrqMutex<Option<Ac97>> = IrqMutex::new(None);
+static BDL_PAGE: IrqMutex<Option<OwnedPageFrame>> = IrqMutex::new(None);
+
+impl Ac97 {
+    fn read8(&self, reg: u16) -> u8 {
+        // SAFETY: `nabm` is the device's bus master I/O BAR.
+        unsafe { Port::<u8>::new(self.nabm + reg).read() }
+    }
+
+    fn write8(&self, reg: u16, value: u8) {
+        // SAFETY: as above.
+        unsafe { Port::<u8>::new(self.nabm + reg).write(value) }
+    }
+
+    fn read16(&self, reg: u16) -> u16 {
+        // SAFETY: as above.
+        unsafe { Port::<u16>::new(self.nabm + reg).read() }
+    }
+
+    fn write16(&self, reg: u16, value: u16) {
+        // SAFETY: as above.
+        unsafe { Port::<u16>::new(self.nabm + reg).write(value) }
+    }
+
+    fn write32(&self, reg: u16, value: u32) {
+        // SAFETY: as above.
+        unsafe { Port::<u32>::new(self.nabm + reg).write(value) }
+    }
+
+    /// Halt PCM out, reset the box and reload the BDL.
+    fn reset_stream(&self) {
+        self.write8(NABM_PO_CR, 0);
+        self.write8(NABM_PO_CR, CR_RR);
+        for _ in 0..1000 {
+            if self.read8(NABM_PO_CR) & CR_RR == 0 {
+                break;
+            }
+            hpet::delay_ns(10_000);
+        }
+        self.write16(NABM_PO_SR, SR_CLEAR);
+        self.write32(NABM_PO_BDBAR, self.bdl_phys);
+        self.write8(NABM_PO_LVI, (BDL_ENTRIES - 1) as u8);
+    }
+}
+
+fn ac97_match(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> bool {
+    if info.is_null() {
+        return false;
+    }
+    let info = unsafe { &*info };
+    info.class_code == PCI_CLASS_MULTIMEDIA && info.subclass == PCI_SUBCLASS_AC97
+}
+
+/// Whether enumeration found an HDA controller, which takes precedence.
+fn hda_controller_present() -> bool {
+    (0..pci_get_device_count()).any(|i| {
+        pci_get_device(i)
+            .is_some_and(|d| d.class_code == PCI_CLASS_MULTIMEDIA && d.subclass == PCI_SUBCLASS_HDA)
+    })
+}
+
+fn ac97_start() {
+    if let Some(dev) = DEVICE.lock().as_ref() {
+        dev.write8(NABM_PO_CR, CR_RPBM);
+    }
+}
+
+fn ac97_stop() {
+    if let Some(dev) = DEVICE.lock().as_ref() {
+        dev.reset_stream();
+    }
+}
+
+fn ac97_position() -> usize {
+    let guard = DEVICE.lock();
+    let Some(dev) = guard.as_ref() else {
+        return 0;
+    };
+    // Re-read if the index moved under us so PICB belongs to `civ`.
+    let (civ, picb) = loop {
+        let civ = dev.read8(NABM_PO_CIV);
+        let picb = dev.read16(NABM_PO_PICB);
+        if dev.read8(NABM_PO_CIV) == civ {
+            break (civ as usize % BDL_ENTRIES, picb as usize);
+        }
+    };
+    dev.write8(NABM_PO_LVI, ((civ + BDL_ENTRIES - 1) % BDL_ENTRIES) as u8);
+    let done = PAGE_SIZE - (picb * 2).min(PAGE_SIZE);
+    (civ % RING_PAGES) * PAGE_SIZE + done
+}
+
+static AC97_OPS: AudioBackendOps = AudioBackendOps {
+    name: "AC97",
+    kind: AUDIO_BACKEND_AC97,
+    start: ac97_start,
+    stop: ac97_stop,
+    position: ac97_position,
+};
+
+fn ac97_bring_up(info: &PciDeviceInfo) -> Result<(), &'static str> {
+    if audio_present() || hda_controller_present() {
+        return Err("deferring to HDA");
+    }
+    let (nam_bar, nabm_bar) = (&info.bars[0], &info.bars[1]);
+    if nam_bar.is_io == 0 || nabm_bar.is_io == 0 || nam_bar.base == 0 || nabm_bar.base == 0 {
+        return Err("mixer/bus master BARs are not I/O");
+    }
+    let nam = nam_bar.base as u16;
+    let nabm = nabm_bar.base as u16;
+
+    let cmd = pci_config_read16(info.bus, info.device, info.function, PCI_COMMAND_OFFSET);
+    pci_config_write16(
+        info.bus,
+        info.device,
+        info.function,
+        PCI_COMMAND_OFFSET,
+        cmd | PCI_COMMAND_IO_SPACE | PCI_COMMAND_BUS_MASTER,
+    );
+
+    let glob_cnt = Port::<u32>::new(nabm + NABM_GLOB_CNT);
+    let glob_sta = Port::<u32>::new(nabm + NABM_GLOB_STA);
+    // SAFETY: BAR1 is this device's bus master I/O window.
+    unsafe { glob_cnt.write(GLOB_CNT_COLD_RESET) };
+    let mut ready = false;
+    for _ in 0..100 {
+        // SAFETY: as above.
+        if unsafe { glob_sta.read() } & GLOB_STA_PRIMARY_READY != 0 {
+            ready = true;
+            break;
+        }
+        hpet::delay_ms(1);
+    }
+    if !ready {
+        return Err("codec not ready");
+    }
+
+    // SAFETY: BAR0 is this device's mixer I/O window.
+    unsafe {
+        Port::<u16>::new(nam + NAM_RESET).write(0);
+        Port::<u16>::new(nam + NAM_MASTER_VOLUME).write(0);
+        Port::<u16>::new(nam + NAM_PCM_OUT_VOLUME).write(PCM_OUT_0DB);
+    }
+
+    // Descriptors and buffers are 32-bit.
+    let ring = alloc_ring(true).ok_or("out of memory for ring")?;
+    let bdl_page = OwnedPageFrame::alloc_dma().ok_or("out of memory for BDL")?;
+    let bdl = bdl_page.as_mut_ptr::<BdlEntry>();
+    for i in 0..BDL_ENTRIES {
+        let entry = BdlEntry {
+            addr: ring[i % RING_PAGES].phys_u64() as u32,
+            samples: SAMPLES_PER_PAGE,
+            flags: 0,
+        };
+        // SAFETY: 32 entries of 8 bytes fit in the page.
+        unsafe { ptr::write_volatile(bdl.add(i), entry) };
+    }
+
+    let dev = Ac97 {
+        nabm,
+        bdl_phys: bdl_page.phys_u64() as u32,
+    };
+    dev.reset_stream();
+    *DEVICE.lock() = Some(dev);
+    *BDL_PAGE.lock() = Some(bdl_page);
+
+    if !audio_register_backend(&AC97_OPS, ring) {
+        return Err("another audio backend is active");
+    }
+    klog_info!(
+        "ac97: {:02x}:{:02x}.{} nam {:#x} nabm {:#x}",
+        info.bus,
+        info.device,
+        info.function,
+        nam,
+        nabm
+    );
+    Ok(())
+}
+
+fn ac97_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> c_int {
+    if !DEVICE_CLAIMED.claim() {
+        klog_debug!("ac97: already claimed");
+        return -1;
+    }
+    let info = unsafe { &*info };
+    match ac97_bring_up(info) {
+        Ok(()) => 0,
+        Err(msg) => {
+            klog_info!("ac97: {}", msg);
+            *DEVICE.lock() = None;
+            DEVICE_CLAIMED.reset();
+            -1
+        }
+    }
+}
+
+static AC97_DRIVER: PciDriver = PciDriver {
+    name: c"ac97".as_ptr().cast(),
+    match_fn: Some(ac97_match),
+    probe: Some(ac97_probe),
+    context: ptr::null_mut(),
+};
+
+pub fn ac97_register_driver() {
+    if pci_register_driver(&AC97_DRIVER) != 0 {
+        klog_info!("ac97: driver registration failed");
+    }
+}
@@ -0,0 +1,584 @@
+//! Intel High Definition Audio controller (PCI class 04:03).
+//!
+//! Resets the controller, brings up CORB/RIRB to talk to the first codec,
+//! and walks its widget graph for a path from an audio output converter
+//! (DAC) to an output-capable pin.  Playback uses the first output stream
+//! descriptor looping over the shared ring; codec verbs are polled and only
+//! issued during probe, so the controller never raises an interrupt.
+
+use core::ffi::c_int;
+use core::ptr;
+
+use slopos_abi::addr::PhysAddr;
+use slopos_abi::audio::AUDIO_BACKEND_HDA;
+use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_info};
+use slopos_mm::mmio::MmioRegion;
+use slopos_mm::page_alloc::OwnedPageFrame;
+
+use super::{AudioBackendOps, RING_BYTES, RING_PAGES, alloc_ring, audio_register_backend};
+use crate::hpet;
+use crate::pci::{PciDeviceInfo, PciDriver, pci_register_driver};
+use crate::virtio::pci::enable_bus_master;
+
+pub const PCI_CLASS_MULTIMEDIA: u8 = 0x04;
+pub const PCI_SUBCLASS_HDA: u8 = 0x03;
+
+const HDA_MMIO_SIZE: usize = 0x4000;
+
+// Controller registers (HDA 1.0a, section 3.3).
+const REG_GCAP: usize = 0x00;
+const REG_GCTL: usize = 0x08;
+const REG_STATESTS: usize = 0x0E;
+const REG_INTCTL: usize = 0x20;
+const REG_CORBLBASE: usize = 0x40;
+const REG_CORBUBASE: usize = 0x44;
+const REG_CORBWP: usize = 0x48;
+const REG_CORBRP: usize = 0x4A;
+const REG_CORBCTL: usize = 0x4C;
+const REG_CORBSIZE: usize = 0x4E;
+const REG_RIRBLBASE: usize = 0x50;
+const REG_RIRBUBASE: usize = 0x54;
+const REG_RIRBWP: usize = 0x58;
+const REG_RINTCNT: usize = 0x5A;
+const REG_RIRBCTL: usize = 0x5C;
+const REG_RIRBSIZE: usize = 0x5E;
+
+const GCTL_CRST: u32 = 1 << 0;
+const CORBRP_RST: u16 = 1 << 15;
+const RIRBWP_RST: u16 = 1 << 15;
+const CORBCTL_RUN: u8 = 1 << 1;
+const RIRBCTL_DMAEN: u8 = 1 << 1;
+
+// Stream descriptors start at 0x80, input streams first.
+const SD_BASE: usize = 0x80;
+const SD_STRIDE: usize = 0x20;
+const SD_CTL: usize = 0x00;
+const SD_STS: usize = 0x03;
+const SD_LPIB: usize = 0x04;
+const SD_CBL: usize = 0x08;
+const SD_LVI: usize = 0x0C;
+const SD_FMT: usize = 0x12;
+const SD_BDPL: usize = 0x18;
+const SD_BDPU: usize = 0x1C;
+
+const SD_CTL_SRST: u32 = 1 << 0;
+const SD_CTL_RUN: u32 = 1 << 1;
+const SD_CTL_STRM_SHIFT: u32 = 20;
+/// BCIS | FIFOE | DESE, write-1-to-clear.
+const SD_STS_CLEAR: u8 = 0x1C;
+
+/// Stream tag shared by the descriptor and the DAC.  Tag 0 is reserved.
+const STREAM_TAG: u32 = 1;
+/// 48 kHz base, x1, /1, 16 bits, 2 channels.
+const STREAM_FORMAT: u16 = (0b001 << 4) | 0x1;
+
+// Codec verbs (HDA 1.0a, section 7.3).
+const VERB_GET_PARAMETER: u32 = 0xF00;
+const VERB_GET_CONN_LIST: u32 = 0xF02;
+const VERB_GET_CONFIG_DEFAULT: u32 = 0xF1C;
+const VERB_SET_CONN_SELECT: u32 = 0x701;
+const VERB_SET_POWER_STATE: u32 = 0x705;
+const VERB_SET_STREAM_CHANNEL: u32 = 0x706;
+const VERB_SET_PIN_CONTROL: u32 = 0x707;
+const VERB_SET_EAPD: u32 = 0x70C;
+// 4-bit verbs with a 16-bit payload.
+const VERB_SET_FORMAT: u32 = 0x2;
+const VERB_SET_AMP_GAIN_MUTE: u32 = 0x3;
+
+const PARAM_NODE_COUNT: u32 = 0x04;
+const PARAM_FUNCTION_TYPE: u32 = 0x05;
+const PARAM_WIDGET_CAP: u32 = 0x09;
+const PARAM_PIN_CAP: u32 = 0x0C;
+const PARAM_CONN_LIST_LEN: u32 = 0x0E;
+const PARAM_OUT_AMP_CAP: u32 = 0x12;
+
+const FUNCTION_TYPE_AUDIO: u32 = 0x01;
+
+const WIDGET_TYPE_OUTPUT: u32 = 0x0;
+const WIDGET_TYPE_MIXER: u32 = 0x2;
+const WIDGET_TYPE_SELECTOR: u32 = 0x3;
+const WIDGET_TYPE_PIN: u32 = 0x4;
+const WIDGET_CAP_IN_AMP: u32 = 1 << 1;
+const WIDGET_CAP_OUT_AMP: u32 = 1 << 2;
+
+const PIN_CAP_OUTPUT: u32 = 1 << 4;
+const PIN_CAP_EAPD: u32 = 1 << 16;
+const PIN_CTL_OUT_EN: u32 = 1 << 6;
+const PIN_CTL_HP_EN: u32 = 1 << 7;
+const EAPD_ENABLE: u32 = 1 << 1;
+
+/// Config default port connectivity: jack absent.
+const CONFIG_PORT_NONE: u32 = 0x1;
+
+const AMP_SET_OUTPUT: u32 = 1 << 15;
+const AMP_SET_INPUT: u32 = 1 << 14;
+const AMP_SET_LEFT: u32 = 1 << 13;
+const AMP_SET_RIGHT: u32 = 1 << 12;
+
+/// DAC→pin paths longer than this (pin, mixers/selectors, DAC) are ignored.
+const MAX_PATH: usize = 4;
+
+const VERB_TIMEOUT_MS: u32 = 100;
+
+/// CORB at the start of the command page, RIRB in its second half.
+const RIRB_OFFSET: usize = 2048;
+
+/// Buffer descriptor list entry (HDA 1.0a, section 3.6.3).
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct BdlEntry {
+    addr: u64,
+    len: u32,
+    flags: u32,
+}
+
+struct HdaStream {
+    regs: MmioRegion,
+    /// Offset of the output stream descriptor.
+    sd: usize,
+    bdl_phys: u64,
+}
+
+static DEVICE_CLAIMED: InitFlag = InitFlag::new();
+static STREAM: IrqMutex<Option<HdaStream>> = IrqMutex::new(None);
+/// Command rings stay allocated for the controller's lifetime.
+static COMMAND_PAGE: IrqMutex<Option<OwnedPageFrame>> = IrqMutex::new(None);
+static BDL_PAGE: IrqMutex<Option<OwnedPageFrame>> = IrqMutex::new(None);
+
+fn hda_match(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> bool {
+    if info.is_null() {
+        return false;
+    }
+    let info = unsafe { &*info };
+    info.class_code == PCI_CLASS_MULTIMEDIA && info.subclass == PCI_SUBCLASS_HDA
+}
+
+/// Poll `cond` once per 10 µs for up to `timeout_ms`.
+fn poll(timeout_ms: u32, mut cond: impl FnMut() -> bool) -> bool {
+    for _ in 0..timeout_ms * 100 {
+        if cond() {
+            return true;
+        }
+        hpet::delay_ns(10_000);
+    }
+    cond()
+}
+
+/// CORB/RIRB command channel to one codec.
+struct Codec<'a> {
+    regs: &'a MmioRegion,
+    corb: *mut u32,
+    rirb: *mut u64,
+    corb_entries: u16,
+    rirb_entries: u16,
+    rirb_rp: u16,
+    cad: u32,
+}
+
+impl Codec<'_> {
+    fn command(&mut self, verb: u32) -> Option<u32> {
+        let wp = (self.regs.read::<u16>(REG_CORBWP) + 1) % self.corb_entries;
+        // SAFETY: `wp` is within the CORB, which lives in the command page.
+        unsafe { ptr::write_volatile(self.corb.add(wp as usize), verb) };
+        self.regs.write::<u16>(REG_CORBWP, wp);
+
+        let want = (self.rirb_rp + 1) % self.rirb_entries;
+        let regs = self.regs;
+        if !poll(VERB_TIMEOUT_MS, || {
+            regs.read::<u16>(REG_RIRBWP) & 0xFF == want & 0xFF
+        }) {
+            return None;
+        }
+        self.rirb_rp = want;
+        // SAFETY: `want` is within the RIRB.
+        let entry = unsafe { ptr::read_volatile(self.rirb.add(want as usize)) };
+        Some(entry as u32)
+    }
+
+    /// 12-bit verb with an 8-bit payload.
+    fn verb(&mut self, nid: u32, verb: u32, payload: u32) -> u32 {
+        self.command((self.cad << 28) | (nid << 20) | (verb << 8) | (payload & 0xFF))
+            .unwrap_or(0)
+    }
+
+    /// 4-bit verb with a 16-bit payload.
+    fn verb16(&mut self, nid: u32, verb: u32, payload: u32) {
+        let _ = self.command((self.cad << 28) | (nid << 20) | (verb << 16) | (payload & 0xFFFF));
+    }
+
+    fn param(&mut self, nid: u32, param: u32) -> u32 {
+        self.verb(nid, VERB_GET_PARAMETER, param)
+    }
+
+    fn widget_type(&mut self, nid: u32) -> u32 {
+        (self.param(nid, PARAM_WIDGET_CAP) >> 20) & 0xF
+    }
+
+    /// Connection list entry `index` of `nid`.  Range entries are treated
+    /// as their endpoint, which is all simple codecs use.
+    fn connection(&mut self, nid: u32, index: u32) -> u32 {
+        let len = self.param(nid, PARAM_CONN_LIST_LEN);
+        if len & 0x80 != 0 {
+            let resp = self.verb(nid, VERB_GET_CONN_LIST, index & !1);
+            (resp >> ((index & 1) * 16)) & 0x7FFF
+        } else {
+            let resp = self.verb(nid, VERB_GET_CONN_LIST, index & !3);
+            (resp >> ((index & 3) * 8)) & 0x7F
+        }
+    }
+
+    fn connection_count(&mut self, nid: u32) -> u32 {
+        self.param(nid, PARAM_CONN_LIST_LEN) & 0x7F
+    }
+
+    /// Depth-first search from `nid` to a DAC.  `path` holds
+    /// `(node, connection index taken)` from the pin inwards.
+    fn find_dac(
+        &mut self,
+        nid: u32,
+        path: &mut [(u32, u32); MAX_PATH],
+        depth: usize,
+    ) -> Option<usize> {
+        if depth >= MAX_PATH {
+            return None;
+        }
+        let ty = self.widget_type(nid);
+        path[depth] = (nid, 0);
+        if ty == WIDGET_TYPE_OUTPUT {
+            return Some(depth + 1);
+        }
+        if depth > 0 && ty != WIDGET_TYPE_MIXER && ty != WIDGET_TYPE_SELECTOR {
+            return None;
+        }
+        for i in 0..self.connection_count(nid) {
+            let next = self.connection(nid, i);
+            if next == 0 || path[..depth].iter().any(|&(n, _)| n == next) {
+                continue;
+            }
+            path[depth].1 = i;
+            if let Some(len) = self.find_dac(next, path, depth + 1) {
+                return Some(len);
+            }
+        }
+        None
+    }
+
+    /// Unmute every amp on the path and route selectors towards the DAC.
+    /// Widgets without their own amp capabilities inherit the `afg`'s.
+    fn enable_path(&mut self, afg: u32, path: &[(u32, u32)]) {
+        for &(nid, conn) in path {
+            self.verb(nid, VERB_SET_POWER_STATE, 0);
+            let caps = self.param(nid, PARAM_WIDGET_CAP);
+            if caps & WIDGET_CAP_OUT_AMP != 0 {
+                let mut amp_caps = self.param(nid, PARAM_OUT_AMP_CAP);
+                if amp_caps == 0 {
+                    amp_caps = self.param(afg, PARAM_OUT_AMP_CAP);
+                }
+                // Offset is the 0 dB step.
+                let gain = amp_caps & 0x7F;
+                self.verb16(
+                    nid,
+                    VERB_SET_AMP_GAIN_MUTE,
+                    AMP_SET_OUTPUT | AMP_SET_LEFT | AMP_SET_RIGHT | gain,
+                );
+            }
+            if caps & WIDGET_CAP_IN_AMP != 0 {
+                self.verb16(
+                    nid,
+                    VERB_SET_AMP_GAIN_MUTE,
+                    AMP_SET_INPUT | AMP_SET_LEFT | AMP_SET_RIGHT | (conn << 8),
+                );
+            }
+            if self.connection_count(nid) > 1 {
+                self.verb(nid, VERB_SET_CONN_SELECT, conn);
+            }
+        }
+    }
+
+    /// Find and program an output path.  Returns the DAC's node id.
+    fn configure_output(&mut self) -> Option<u32> {
+        let root = self.param(0, PARAM_NODE_COUNT);
+        let (fg_start, fg_count) = ((root >> 16) & 0xFF, root & 0xFF);
+        let afg = (fg_start..fg_start + fg_count)
+            .find(|&nid| self.param(nid, PARAM_FUNCTION_TYPE) & 0xFF == FUNCTION_TYPE_AUDIO)?;
+        self.verb(afg, VERB_SET_POWER_STATE, 0);
+
+        let nodes = self.param(afg, PARAM_NODE_COUNT);
+        let (start, count) = ((nodes >> 16) & 0xFF, nodes & 0xFF);
+        let mut path = [(0u32, 0u32); MAX_PATH];
+        for nid in start..start + count {
+            if self.widget_type(nid) != WIDGET_TYPE_PIN {
+                continue;
+            }
+            let pin_caps = self.param(nid, PARAM_PIN_CAP);
+            if pin_caps & PIN_CAP_OUTPUT == 0 {
+                continue;
+            }
+            if self.verb(nid, VERB_GET_CONFIG_DEFAULT, 0) >> 30 == CONFIG_PORT_NONE {
+                continue;
+            }
+            let Some(len) = self.find_dac(nid, &mut path, 0) else {
+                continue;
+            };
+            self.enable_path(afg, &path[..len]);
+            self.verb(nid, VERB_SET_PIN_CONTROL, PIN_CTL_OUT_EN | PIN_CTL_HP_EN);
+            if pin_caps & PIN_CAP_EAPD != 0 {
+                self.verb(nid, VERB_SET_EAPD, EAPD_ENABLE);
+            }
+            let dac = path[len - 1].0;
+            klog_debug!(
+                "hda: codec {} pin {} -> dac {} ({} hops)",
+                self.cad,
+                nid,
+                dac,
+                len - 1
+            );
+            return Some(dac);
+        }
+        None
+    }
+}
+
+/// Pick the largest ring size advertised in a CORBSIZE/RIRBSIZE register.
+fn ring_size(regs: &MmioRegion, reg: usize) -> (u8, u16) {
+    let cap = regs.read::<u8>(reg) >> 4;
+    if cap & 0b100 != 0 {
+        (0b10, 256)
+    } else if cap & 0b010 != 0 {
+        (0b01, 16)
+    } else {
+        (0b00, 2)
+    }
+}
+
+fn reset_controller(regs: &MmioRegion) -> Result<(), &'static str> {
+    regs.write::<u32>(REG_GCTL, regs.read::<u32>(REG_GCTL) & !GCTL_CRST);
+    if !poll(100, || regs.read::<u32>(REG_GCTL) & GCTL_CRST == 0) {
+        return Err("controller stuck in reset entry");
+    }
+    hpet::delay_ms(1);
+    regs.write::<u32>(REG_GCTL, regs.read::<u32>(REG_GCTL) | GCTL_CRST);
+    if !poll(100, || regs.read::<u32>(REG_GCTL) & GCTL_CRST != 0) {
+        return Err("controller did not leave reset");
+    }
+    // Codecs get 521 µs after reset to request a state change.
+    hpet::delay_ms(1);
+    Ok(())
+}
+
+fn start_command_rings<'a>(
+    regs: &'a MmioRegion,
+    page: &OwnedPageFrame,
+    cad: u32,
+) -> Result<Codec<'a>, &'static str> {
+    regs.write::<u8>(REG_CORBCTL, 0);
+    regs.write::<u8>(REG_RIRBCTL, 0);
+    if !poll(10, || {
+        regs.read::<u8>(REG_CORBCTL) & CORBCTL_RUN == 0
+            && regs.read::<u8>(REG_RIRBCTL) & RIRBCTL_DMAEN == 0
+    }) {
+        return Err("command DMA did not stop");
+    }
+
+    let corb_phys = page.phys_u64();
+    let rirb_phys = corb_phys + RIRB_OFFSET as u64;
+    let (corb_sel, corb_entries) = ring_size(regs, REG_CORBSIZE);
+    let (rirb_sel, rirb_entries) = ring_size(regs, REG_RIRBSIZE);
+
+    regs.write::<u32>(REG_CORBLBASE, corb_phys as u32);
+    regs.write::<u32>(REG_CORBUBASE, (corb_phys >> 32) as u32);
+    regs.write::<u8>(REG_CORBSIZE, corb_sel);
+    regs.write::<u16>(REG_CORBWP, 0);
+    // Some controllers never reflect the reset bit; don't insist on it.
+    regs.write::<u16>(REG_CORBRP, CORBRP_RST);
+    poll(1, || regs.read::<u16>(REG_CORBRP) & CORBRP_RST != 0);
+    regs.write::<u16>(REG_CORBRP, 0);
+    if !poll(10, || regs.read::<u16>(REG_CORBRP) & CORBRP_RST == 0) {
+        return Err("CORB read pointer stuck in reset");
+    }
+
+    regs.write::<u32>(REG_RIRBLBASE, rirb_phys as u32);
+    regs.write::<u32>(REG_RIRBUBASE, (rirb_phys >> 32) as u32);
+    regs.write::<u8>(REG_RIRBSIZE, rirb_sel);
+    regs.write::<u16>(REG_RIRBWP, RIRBWP_RST);
+    regs.write::<u16>(REG_RINTCNT, 1);
+
+    regs.write::<u8>(REG_CORBCTL, CORBCTL_RUN);
+    regs.write::<u8>(REG_RIRBCTL, RIRBCTL_DMAEN);
+
+    Ok(Codec {
+        regs,
+        corb: page.as_mut_ptr::<u32>(),
+        // SAFETY: the RIRB sits inside the same page.
+        rirb: unsafe { page.as_mut_ptr::<u8>().add(RIRB_OFFSET) as *mut u64 },
+        corb_entries,
+        rirb_entries,
+        rirb_rp: 0,
+        cad,
+    })
+}
+
+fn sd_read(s: &HdaStream, off: usize) -> u32 {
+    s.regs.read::<u32>(s.sd + off)
+}
+
+fn sd_write(s: &HdaStream, off: usize, value: u32) {
+    s.regs.write::<u32>(s.sd + off, value);
+}
+
+/// Reset the stream descriptor and load the ring's BDL.  Leaves it stopped
+/// at LPIB 0.
+fn stream_reset(s: &HdaStream) {
+    let ctl = sd_read(s, SD_CTL) & !SD_CTL_RUN;
+    sd_write(s, SD_CTL, ctl);
+    poll(10, || sd_read(s, SD_CTL) & SD_CTL_RUN == 0);
+    sd_write(s, SD_CTL, ctl | SD_CTL_SRST);
+    poll(10, || sd_read(s, SD_CTL) & SD_CTL_SRST != 0);
+    sd_write(s, SD_CTL, ctl & !SD_CTL_SRST);
+    poll(10, || sd_read(s, SD_CTL) & SD_CTL_SRST == 0);
+
+    s.regs.write::<u8>(s.sd + SD_STS, SD_STS_CLEAR);
+    sd_write(s, SD_CBL, RING_BYTES as u32);
+    s.regs.write::<u16>(s.sd + SD_LVI, (RING_PAGES - 1) as u16);
+    s.regs.write::<u16>(s.sd + SD_FMT, STREAM_FORMAT);
+    sd_write(s, SD_BDPL, s.bdl_phys as u32);
+    sd_write(s, SD_BDPU, (s.bdl_phys >> 32) as u32);
+    let ctl = sd_read(s, SD_CTL) & !(0xF << SD_CTL_STRM_SHIFT);
+    sd_write(s, SD_CTL, ctl | (STREAM_TAG << SD_CTL_STRM_SHIFT));
+}
+
+fn hda_start() {
+    if let Some(s) = STREAM.lock().as_ref() {
+        sd_write(s, SD_CTL, sd_read(s, SD_CTL) | SD_CTL_RUN);
+    }
+}
+
+fn hda_stop() {
+    if let Some(s) = STREAM.lock().as_ref() {
+        stream_reset(s);
+    }
+}
+
+fn hda_position() -> usize {
+    STREAM
+        .lock()
+        .as_ref()
+        .map_or(0, |s| sd_read(s, SD_LPIB) as usize)
+}
+
+static HDA_OPS: AudioBackendOps = AudioBackendOps {
+    name: "HDA",
+    kind: AUDIO_BACKEND_HDA,
+    start: hda_start,
+    stop: hda_stop,
+    position: hda_position,
+};
+
+fn hda_bring_up(info: &PciDeviceInfo) -> Result<(), &'static str> {
+    if super::audio_present() {
+        return Err("another audio backend is active");
+    }
+    let bar = &info.bars[0];
+    if bar.base == 0 || bar.is_io != 0 {
+        return Err("BAR0 is not a memory BAR");
+    }
+    let regs =
+        MmioRegion::map(PhysAddr::new(bar.base), HDA_MMIO_SIZE).ok_or("failed to map registers")?;
+    enable_bus_master(info);
+
+    reset_controller(&regs)?;
+    regs.write::<u32>(REG_INTCTL, 0);
+    let codecs = regs.read::<u16>(REG_STATESTS);
+    if codecs == 0 {
+        return Err("no codec present");
+    }
+    let cad = codecs.trailing_zeros();
+
+    let gcap = regs.read::<u16>(REG_GCAP);
+    let input_streams = ((gcap >> 8) & 0xF) as usize;
+    let output_streams = ((gcap >> 12) & 0xF) as usize;
+    let addr64 = gcap & 1 != 0;
+    if output_streams == 0 {
+        return Err("no output streams");
+    }
+
+    let command_page = OwnedPageFrame::alloc_zeroed().ok_or("out of memory")?;
+    let mut codec = start_command_rings(&regs, &command_page, cad)?;
+    let dac = codec
+        .configure_output()
+        .ok_or("no DAC-to-pin output path")?;
+    codec.verb(dac, VERB_SET_STREAM_CHANNEL, STREAM_TAG << 4);
+    codec.verb16(dac, VERB_SET_FORMAT, STREAM_FORMAT as u32);
+
+    let ring = alloc_ring(!addr64).ok_or("out of memory for ring")?;
+    let bdl_page = if addr64 {
+        OwnedPageFrame::alloc_zeroed()
+    } else {
+        OwnedPageFrame::alloc_dma()
+    }
+    .ok_or("out of memory for BDL")?;
+    let bdl = bdl_page.as_mut_ptr::<BdlEntry>();
+    for (i, page) in ring.iter().enumerate() {
+        let entry = BdlEntry {
+            addr: page.phys_u64(),
+            len: 4096,
+            flags: 0,
+        };
+        // SAFETY: RING_PAGES entries of 16 bytes fit in the page.
+        unsafe { ptr::write_volatile(bdl.add(i), entry) };
+    }
+
+    let stream = HdaStream {
+        regs,
+        sd: SD_BASE + input_streams * SD_STRIDE,
+        bdl_phys: bdl_page.phys_u64(),
+    };
+    stream_reset(&stream);
+    *STREAM.lock() = Some(stream);
+    *COMMAND_PAGE.lock() = Some(command_page);
+    *BDL_PAGE.lock() = Some(bdl_page);
+
+    if !audio_register_backend(&HDA_OPS, ring) {
+        return Err("another audio backend is active");
+    }
+    klog_info!(
+        "hda: {:02x}:{:02x}.{} codec {} dac {}, {} output streams",
+        info.bus,
+        info.device,
+        info.function,
+        cad,
+        dac,
+        output_streams
+    );
+    Ok(())
+}
+
+fn hda_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> c_int {
+    if !DEVICE_CLAIMED.claim() {
+        klog_debug!("hda: already claimed");
+        return -1;
+    }
+    let info = unsafe { &*info };
+    match hda_bring_up(info) {
+        Ok(()) => 0,
+        Err(msg) => {
+            klog_info!("hda: {}", msg);
+            *STREAM.lock() = None;
+            DEVICE_CLAIMED.reset();
+            -1
+        }
+    }
+}
+
+static HDA_DRIVER: PciDriver = PciDriver {
+    name: c"hda".as_ptr().cast(),
+    match_fn: Some(hda_match),
+    probe: Some(hda_probe),
+    context: ptr::null_mut(),
+};
+
+pub fn hda_register_driver() {
+    if pci_register_driver(&HDA_DRIVER) != 0 {
+        klog_info!("hda: driver registration failed");
+    }
+}
@@ -0,0 +1,335 @@
+//! PCM audio output.
+//!
+//! One playback stream in the fixed `slopos_abi::audio` format.  A backend
+//! (Intel HDA, or AC97 when no HDA controller exists) loops DMA over a ring
+//! of [`RING_PAGES`] page-sized buffers; the bookkeeping lives here:
+//!
+//! - writers copy PCM in behind the hardware's read position and block
+//!   while the ring is full;
+//! - a pump kthread, spawned by the first write, samples the position every
+//!   [`PUMP_INTERVAL_MS`], zeroes what has played so an underrun repeats
+//!   silence instead of stale audio, and halts DMA once the ring drains.
+//!   It parks on a wait queue while the stream is stopped, so an idle sound
+//!   card costs no wakeups.
+
+pub mod ac97;
+pub mod hda;
+
+use core::ffi::c_void;
+use core::ptr;
+
+use slopos_abi::audio::{
+    AUDIO_BACKEND_NONE, AUDIO_BITS_PER_SAMPLE, AUDIO_CHANNELS, AUDIO_FRAME_BYTES,
+    AUDIO_SAMPLE_RATE, AUDIO_VOLUME_MAX, AudioInfo,
+};
+use slopos_abi::task::INVALID_TASK_ID;
+use slopos_core::kthread::kthread_spawn;
+use slopos_core::scheduler::sleep::sleep_current_task_ms;
+use slopos_lib::{InitFlag, IrqMutex, WaitQueue, klog_info};
+use slopos_mm::page_alloc::OwnedPageFrame;
+
+const PAGE_SIZE: usize = 4096;
+
+/// Ring size in pages; each page is one buffer descriptor.
+pub const RING_PAGES: usize = 16;
+/// 64 KiB, about 340 ms of audio.
+pub const RING_BYTES: usize = RING_PAGES * PAGE_SIZE;
+
+/// Pump period.  Must stay well under the ring's playback time so a lap is
+/// never missed.
+const PUMP_INTERVAL_MS: u32 = 10;
+
+/// A blocked writer gives up after this long without the ring draining.
+const WRITE_STALL_MS: u64 = 500;
+
+/// Hardware hooks supplied by a backend.
+pub struct AudioBackendOps {
+    pub name: &'static str,
+    /// `AUDIO_BACKEND_*`.
+    pub kind: u32,
+    /// Start DMA from ring offset 0.
+    pub start: fn(),
+    /// Halt DMA and rewind so the next `start` begins at offset 0.
+    pub stop: fn(),
+    /// Byte offset in the ring the hardware is currently reading.
+    pub position: fn() -> usize,
+}
+
+/// DMA buffers for the ring.  `dma32` keeps them below 4 GiB for
+/// controllers with 32-bit descriptors.
+pub(crate) fn alloc_ring(dma32: bool) -> Option<[OwnedPageFrame; RING_PAGES]> {
+    let mut pages: [Option<OwnedPageFrame>; RING_PAGES] = [const { None }; RING_PAGES];
+    for slot in pages.iter_mut() {
+        let page = if dma32 {
+            OwnedPageFrame::alloc_dma()?
+        } else {
+            OwnedPageFrame::alloc_zeroed()?
+        };
+        *slot = Some(page);
+    }
+    Some(pages.map(|p| p.expect("ring page allocated")))
+}
+
+struct AudioState {
+    backend: Option<&'static AudioBackendOps>,
+    ring: Option<[OwnedPageFrame; RING_PAGES]>,
+    running: bool,
+    /// Bytes written and played since the stream last started.  Both are
+    /// absolute; ring offsets are taken modulo [`RING_BYTES`].
+    written: usize,
+    played: usize,
+    /// Hardware position seen by the previous pump pass.
+    last_pos: usize,
+    volume: u32,
+}
+
+impl AudioState {
+    const fn new() -> Self {
+        Self {
+            backend: None,
+            ring: None,
+            running: false,
+            written: 0,
+            played: 0,
+            last_pos: 0,
+            volume: AUDIO_VOLUME_MAX,
+        }
+    }
+
+    #[inline]
+    fn queued(&self) -> usize {
+        self.written - self.played
+    }
+
+    #[inline]
+    fn space(&self) -> usize {
+        RING_BYTES - self.queued()
+    }
+
+    /// Pointer to ring offset `off` and the bytes left in that page.
+    fn ring_ptr(&self, off: usize) -> (*mut u8, usize) {
+        let ring = self.ring.as_ref().expect("audio ring");
+        let page = &ring[off / PAGE_SIZE];
+        let in_page = off % PAGE_SIZE;
+        // SAFETY: `in_page` is inside the page, which lives as long as `self.ring`.
+        let p = unsafe { page.as_mut_ptr::<u8>().add(in_page) };
+        (p, PAGE_SIZE - in_page)
+    }
+
+    /// Zero `len` bytes of ring starting at offset `off`, wrapping.
+    fn zero(&self, mut off: usize, mut len: usize) {
+        while len > 0 {
+            let (p, room) = self.ring_ptr(off % RING_BYTES);
+            let n = room.min(len);
+            // SAFETY: `n` bytes fit in the page behind `p`.
+            unsafe { ptr::write_bytes(p, 0, n) };
+            off += n;
+            len -= n;
+        }
+    }
+
+    /// Copy as much of `data` as fits, applying the volume.  Returns bytes
+    /// taken, always whole frames.
+    fn push(&mut self, data: &[u8]) -> usize {
+        let take = data.len().min(self.space()) / AUDIO_FRAME_BYTES * AUDIO_FRAME_BYTES;
+        let mut done = 0;
+        while done < take {
+            let (p, room) = self.ring_ptr((self.written + done) % RING_BYTES);
+            let n = room.min(take - done);
+            let src = &data[done..done + n];
+            if self.volume >= AUDIO_VOLUME_MAX {
+                // SAFETY: `n` bytes fit in the page behind `p`.
+                unsafe { ptr::copy_nonoverlapping(src.as_ptr(), p, n) };
+            } else {
+                for (i, s) in src.chunks_exact(2).enumerate() {
+                    let sample = i16::from_le_bytes([s[0], s[1]]) as i32;
+                    let scaled = (sample * self.volume as i32 / AUDIO_VOLUME_MAX as i32) as i16;
+                    // SAFETY: `2 * i + 1 < n`, inside the page.
+                    unsafe { (p.add(2 * i) as *mut [u8; 2]).write(scaled.to_le_bytes()) };
+                }
+            }
+            done += n;
+        }
+        self.written += take;
+        take
+    }
+
+    fn start(&mut self) {
+        let Some(ops) = self.backend else {
+            return;
+        };
+        (ops.start)();
+        self.running = true;
+    }
+
+    /// Halt DMA and forget everything queued.
+    fn stop(&mut self) {
+        if let Some(ops) = self.backend {
+            (ops.stop)();
+        }
+        if self.ring.is_some() {
+            self.zero(0, RING_BYTES);
+        }
+        self.running = false;
+        self.written = 0;
+        self.played = 0;
+        self.last_pos = 0;
+    }
+
+    /// Account for what the hardware played since the last pass.  Returns
+    /// `true` while the stream is still running.
+    fn advance(&mut self) -> bool {
+        let Some(ops) = self.backend else {
+            return false;
+        };
+        if !self.running {
+            return false;
+        }
+        let pos = (ops.position)() % RING_BYTES;
+        let delta = (pos + RING_BYTES - self.last_pos) % RING_BYTES;
+        if delta > 0 {
+            self.zero(self.last_pos, delta);
+            self.played += delta;
+            self.last_pos = pos;
+        }
+        if self.played >= self.written {
+            // Drained, or underran into silence: either way nothing is left.
+            self.stop();
+            return false;
+        }
+        true
+    }
+}
+
+static AUDIO: IrqMutex<AudioState> = IrqMutex::new(AudioState::new());
+/// Writers waiting for ring space.
+static SPACE_WAITERS: WaitQueue = WaitQueue::new();
+/// The pump, while the stream is stopped.
+static PUMP_WAITERS: WaitQueue = WaitQueue::new();
+static PUMP_SPAWNED: InitFlag = InitFlag::new();
+
+/// Install the output backend and its DMA ring.  The first caller wins.
+pub(crate) fn audio_register_backend(
+    ops: &'static AudioBackendOps,
+    ring: [OwnedPageFrame; RING_PAGES],
+) -> bool {
+    let mut st = AUDIO.lock();
+    if st.backend.is_some() {
+        return false;
+    }
+    st.backend = Some(ops);
+    st.ring = Some(ring);
+    drop(st);
+    klog_info!(
+        "audio: {} output, {} Hz {}-bit {}ch, {} KiB ring",
+        ops.name,
+        AUDIO_SAMPLE_RATE,
+        AUDIO_BITS_PER_SAMPLE,
+        AUDIO_CHANNELS,
+        RING_BYTES / 1024
+    );
+    true
+}
+
+pub fn audio_present() -> bool {
+    AUDIO.lock().backend.is_some()
+}
+
+fn stream_running() -> bool {
+    AUDIO.lock().running
+}
+
+fn ring_has_space() -> bool {
+    let st = AUDIO.lock();
+    st.backend.is_none() || st.space() >= AUDIO_FRAME_BYTES
+}
+
+/// One pump pass; exposed so tests can drive the ring without the kthread.
+pub fn audio_pump_once() -> bool {
+    let running = AUDIO.lock().advance();
+    SPACE_WAITERS.wake_all();
+    running
+}
+
+fn audio_pump_task(_arg: *mut c_void) {
+    loop {
+        PUMP_WAITERS.wait_event(stream_running);
+        while audio_pump_once() {
+            sleep_current_task_ms(PUMP_INTERVAL_MS);
+        }
+    }
+}
+
+fn ensure_pump() {
+    if !PUMP_SPAWNED.claim() {
+        return;
+    }
+    let task_id = kthread_spawn(
+        c"audio_pump".as_ptr(),
+        Some(audio_pump_task),
+        ptr::null_mut(),
+    );
+    if task_id == INVALID_TASK_ID {
+        klog_info!("audio: failed to spawn pump thread");
+        PUMP_SPAWNED.reset();
+    }
+}
+
+/// Queue frames for playback, blocking while the ring is full.  Returns
+/// the bytes queued.
+pub fn audio_write(data: &[u8]) -> usize {
+    let mut done = 0;
+    while data.len() - done >= AUDIO_FRAME_BYTES {
+        let (taken, started) = {
+            let mut st = AUDIO.lock();
+            if st.backend.is_none() {
+                break;
+            }
+            let taken = st.push(&data[done..]);
+            let started = taken > 0 && !st.running;
+            if started {
+                st.start();
+            }
+            (taken, started)
+        };
+        if started {
+            ensure_pump();
+            PUMP_WAITERS.wake_one();
+        }
+        done += taken;
+        if taken == 0 && !SPACE_WAITERS.wait_event_timeout(ring_has_space, WRITE_STALL_MS) {
+            break;
+        }
+    }
+    done
+}
+
+pub fn audio_info() -> AudioInfo {
+    let st = AUDIO.lock();
+    let Some(ops) = st.backend else {
+        return AudioInfo {
+            backend: AUDIO_BACKEND_NONE,
+            volume: st.volume,
+            ..AudioInfo::default()
+        };
+    };
+    AudioInfo {
+        backend: ops.kind,
+        sample_rate: AUDIO_SAMPLE_RATE,
+        channels: AUDIO_CHANNELS,
+        bits_per_sample: AUDIO_BITS_PER_SAMPLE,
+        ring_bytes: RING_BYTES as u32,
+        queued_bytes: st.queued() as u32,
+        volume: st.volume,
+        _reserved: 0,
+    }
+}
+
+pub fn audio_set_volume(volume: u32) {
+    AUDIO.lock().volume = volume.min(AUDIO_VOLUME_MAX);
+}
+
+pub fn audio_stop() {
+    AUDIO.lock().stop();
+    SPACE_WAITERS.wake_all();
+}
@@ -0,0 +1,129 @@
+//! Audio output tests.
+//!
+//! The test harness boots QEMU with an HDA controller and a silent audio
+//! backend, so DMA runs but nothing is heard.  Without a sound device every
+//! test is skipped.
+
+use slopos_abi::audio::{AUDIO_FRAME_BYTES, AUDIO_SAMPLE_RATE, AUDIO_VOLUME_MAX};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+
+use crate::audio;
+use crate::hpet;
+
+fn skip_without_audio() -> Option<TestResult> {
+    if audio::audio_present() {
+        None
+    } else {
+        Some(TestResult::Skipped)
+    }
+}
+
+/// `ms` of a quiet 1 kHz square wave.
+fn queue_tone(ms: usize) -> usize {
+    let mut buf = [0u8; 4096];
+    let period = AUDIO_SAMPLE_RATE as usize / 1000;
+    for (i, frame) in buf.chunks_exact_mut(AUDIO_FRAME_BYTES).enumerate() {
+        let s: i16 = if i % period < period / 2 { 2000 } else { -2000 };
+        frame[..2].copy_from_slice(&s.to_le_bytes());
+        frame[2..].copy_from_slice(&s.to_le_bytes());
+    }
+    let total = AUDIO_SAMPLE_RATE as usize * ms / 1000 * AUDIO_FRAME_BYTES;
+    let mut done = 0;
+    while done < total {
+        let n = (total - done).min(buf.len());
+        let queued = audio::audio_write(&buf[..n]);
+        done += queued;
+        if queued < n {
+            break;
+        }
+    }
+    done
+}
+
+pub fn test_audio_info_consistent() -> TestResult {
+    if let Some(skip) = skip_without_audio() {
+        return skip;
+    }
+    let info = audio::audio_info();
+    assert_test!(info.is_present(), "backend registered but info says absent");
+    assert_eq_test!(info.sample_rate, AUDIO_SAMPLE_RATE, "sample rate");
+    assert_eq_test!(info.ring_bytes as usize, audio::RING_BYTES, "ring size");
+    assert_test!(
+        info.queued_bytes as usize <= audio::RING_BYTES,
+        "queued {} exceeds the ring",
+        info.queued_bytes
+    );
+    pass!()
+}
+
+pub fn test_audio_dma_advances() -> TestResult {
+    if let Some(skip) = skip_without_audio() {
+        return skip;
+    }
+    audio::audio_stop();
+    let queued = queue_tone(200);
+    assert_test!(queued > 0, "nothing queued");
+    hpet::delay_ms(50);
+    audio::audio_pump_once();
+    let left = audio::audio_info().queued_bytes as usize;
+    audio::audio_stop();
+    assert_test!(
+        left < queued,
+        "hardware did not consume anything ({} of {} left)",
+        left,
+        queued
+    );
+    pass!()
+}
+
+pub fn test_audio_stream_stops_when_drained() -> TestResult {
+    if let Some(skip) = skip_without_audio() {
+        return skip;
+    }
+    audio::audio_stop();
+    assert_test!(queue_tone(20) > 0, "nothing queued");
+    hpet::delay_ms(80);
+    let running = audio::audio_pump_once();
+    let left = audio::audio_info().queued_bytes;
+    audio::audio_stop();
+    assert_test!(!running, "stream kept running after draining");
+    assert_eq_test!(left, 0, "drained stream reports queued audio");
+    pass!()
+}
+
+pub fn test_audio_stop_drops_queue() -> TestResult {
+    if let Some(skip) = skip_without_audio() {
+        return skip;
+    }
+    assert_test!(queue_tone(100) > 0, "nothing queued");
+    audio::audio_stop();
+    assert_eq_test!(
+        audio::audio_info().queued_bytes,
+        0,
+        "stop left audio queued"
+    );
+    pass!()
+}
+
+pub fn test_audio_volume_clamped() -> TestResult {
+    if let Some(skip) = skip_without_audio() {
+        return skip;
+    }
+    audio::audio_set_volume(AUDIO_VOLUME_MAX + 50);
+    let clamped = audio::audio_info().volume;
+    audio::audio_set_volume(AUDIO_VOLUME_MAX);
+    assert_eq_test!(clamped, AUDIO_VOLUME_MAX, "volume not clamped");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    audio,
+    [
+        test_audio_info_consistent,
+        test_audio_dma_advances,
+        test_audio_stream_stops_when_drained,
+        test_audio_stop_drops_queue,
+        test_audio_volume_clamped,
+    ]
+);
@@ -0,0 +1,140 @@
+//! Clipboard tests: MIME validation, typed set/get, and the plain-text
+//! copy/paste shorthands.
+
+use slopos_abi::input::{CLIPBOARD_MAX_SIZE, CLIPBOARD_MIME_TEXT, clipboard_mime_valid};
+use slopos_abi::syscall::{ERRNO_EINVAL, ERRNO_ENOENT, ERRNO_ERANGE};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+
+use crate::input_event::{
+    ClipboardError, clipboard_clear, clipboard_copy, clipboard_get, clipboard_paste, clipboard_set,
+};
+
+pub fn test_clipboard_mime_validation() -> TestResult {
+    assert_test!(clipboard_mime_valid(b"text/plain"), "text/plain");
+    assert_test!(
+        clipboard_mime_valid(b"text/plain;charset=utf-8"),
+        "parameters allowed"
+    );
+    assert_test!(
+        clipboard_mime_valid(b"image/x-portable-pixmap"),
+        "x- subtype"
+    );
+    assert_test!(!clipboard_mime_valid(b""), "empty");
+    assert_test!(!clipboard_mime_valid(b"text"), "no subtype");
+    assert_test!(!clipboard_mime_valid(b"text/"), "empty subtype");
+    assert_test!(!clipboard_mime_valid(b"text/plain/extra"), "two slashes");
+    assert_test!(!clipboard_mime_valid(b"te xt/plain"), "space in type");
+    assert_test!(!clipboard_mime_valid(b"text/pl\x01ain"), "control byte");
+
+    let mut long = [b'a'; 80];
+    long[1] = b'/';
+    assert_test!(
+        !clipboard_mime_valid(&long),
+        "longer than CLIPBOARD_MIME_MAX"
+    );
+    pass!()
+}
+
+pub fn test_clipboard_typed_roundtrip() -> TestResult {
+    clipboard_clear();
+    let mut out = [0u8; 16];
+    assert_eq_test!(
+        clipboard_get(CLIPBOARD_MIME_TEXT, &mut out),
+        Err(ClipboardError::NoMatch),
+        "empty clipboard"
+    );
+
+    assert_eq_test!(
+        clipboard_set(b"application/x-slop", b"\x00\x01\x02"),
+        Ok(3),
+        "set binary"
+    );
+    assert_eq_test!(
+        clipboard_get(b"application/x-slop", &mut out),
+        Ok(3),
+        "get same type"
+    );
+    assert_eq_test!(&out[..3], b"\x00\x01\x02", "binary data");
+    assert_eq_test!(
+        clipboard_get(CLIPBOARD_MIME_TEXT, &mut out),
+        Err(ClipboardError::NoMatch),
+        "other type"
+    );
+    assert_eq_test!(clipboard_paste(&mut out), 0, "paste sees no text");
+
+    clipboard_clear();
+    pass!()
+}
+
+pub fn test_clipboard_mime_match_ignores_case_and_params() -> TestResult {
+    clipboard_clear();
+    assert_eq_test!(
+        clipboard_set(b"text/plain;charset=utf-8", b"hi"),
+        Ok(2),
+        "set with charset"
+    );
+    let mut out = [0u8; 4];
+    assert_eq_test!(clipboard_get(b"TEXT/Plain", &mut out), Ok(2), "case");
+    assert_eq_test!(clipboard_paste(&mut out), 2, "paste as text/plain");
+    assert_eq_test!(&out[..2], b"hi", "pasted bytes");
+
+    clipboard_clear();
+    pass!()
+}
+
+pub fn test_clipboard_short_buffer_reports_full_length() -> TestResult {
+    clipboard_clear();
+    assert_eq_test!(clipboard_copy(b"hello world"), 11, "copy");
+
+    let mut out = [0u8; 5];
+    assert_eq_test!(
+        clipboard_get(CLIPBOARD_MIME_TEXT, &mut out),
+        Ok(11),
+        "full length"
+    );
+    assert_eq_test!(&out, b"hello", "truncated copy");
+    assert_eq_test!(clipboard_paste(&mut out), 5, "paste clamps");
+
+    clipboard_clear();
+    pass!()
+}
+
+pub fn test_clipboard_rejects_bad_input() -> TestResult {
+    clipboard_clear();
+    assert_eq_test!(
+        clipboard_set(b"notamime", b"x"),
+        Err(ClipboardError::BadMime),
+        "bad mime"
+    );
+
+    let big = [b'x'; CLIPBOARD_MAX_SIZE + 1];
+    assert_eq_test!(
+        clipboard_set(CLIPBOARD_MIME_TEXT, &big),
+        Err(ClipboardError::TooLarge),
+        "too large"
+    );
+    assert_eq_test!(
+        clipboard_copy(&big),
+        CLIPBOARD_MAX_SIZE,
+        "copy truncates instead"
+    );
+
+    assert_eq_test!(ClipboardError::BadMime.errno(), ERRNO_EINVAL, "EINVAL");
+    assert_eq_test!(ClipboardError::TooLarge.errno(), ERRNO_ERANGE, "ERANGE");
+    assert_eq_test!(ClipboardError::NoMatch.errno(), ERRNO_ENOENT, "ENOENT");
+
+    clipboard_clear();
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    clipboard,
+    [
+        test_clipboard_mime_validation,
+        test_clipboard_typed_roundtrip,
+        test_clipboard_mime_match_ignores_case_and_params,
+        test_clipboard_short_buffer_reports_full_length,
+        test_clipboard_rejects_bad_input,
+    ]
+);
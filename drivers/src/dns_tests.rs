@@ -1,9 +1,9 @@
 //! DNS client test suite (Phase 5F).
 
 use slopos_lib::testing::TestResult;
-use slopos_lib::{assert_eq_test, assert_test, pass};
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
 
-use crate::net::dns;
+use crate::net::dns::{self, DnsAnswer, DnsLookup};
 
 // =============================================================================
 // 5F.T1 — DNS name encoding
@@ -375,6 +375,169 @@ pub fn test_dns_t8_regression_network_stack() -> TestResult {
     pass!()
 }
 
+// =============================================================================
+// Negative answers, TTL-aware caching and non-blocking lookups
+// =============================================================================
+
+/// Build a reply to an `example.com` A query with no answers and, when
+/// `soa` is `Some((ttl, minimum))`, an SOA record in the authority section.
+fn build_empty_reply(id: u16, flags: u16, soa: Option<(u32, u32)>, packet: &mut [u8]) -> usize {
+    packet[0..2].copy_from_slice(&id.to_be_bytes());
+    packet[2..4].copy_from_slice(&flags.to_be_bytes());
+    packet[4..6].copy_from_slice(&1u16.to_be_bytes()); // QDCOUNT=1
+    packet[6..8].copy_from_slice(&0u16.to_be_bytes()); // ANCOUNT=0
+    packet[8..10].copy_from_slice(&(soa.is_some() as u16).to_be_bytes()); // NSCOUNT
+    packet[10..12].copy_from_slice(&0u16.to_be_bytes()); // ARCOUNT=0
+    let mut pos = 12;
+
+    let name_wire: &[u8] = &[
+        7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0,
+    ];
+    packet[pos..pos + 13].copy_from_slice(name_wire);
+    pos += 13;
+    packet[pos..pos + 4].copy_from_slice(&[0, 1, 0, 1]); // QTYPE=A, QCLASS=IN
+    pos += 4;
+
+    if let Some((ttl, minimum)) = soa {
+        packet[pos..pos + 2].copy_from_slice(&[0xC0, 0x0C]); // owner: example.com
+        pos += 2;
+        packet[pos..pos + 4].copy_from_slice(&[0, 6, 0, 1]); // TYPE=SOA, CLASS=IN
+        pos += 4;
+        packet[pos..pos + 4].copy_from_slice(&ttl.to_be_bytes());
+        pos += 4;
+        packet[pos..pos + 2].copy_from_slice(&22u16.to_be_bytes()); // RDLENGTH
+        pos += 2;
+        packet[pos] = 0; // MNAME: root
+        packet[pos + 1] = 0; // RNAME: root
+        pos += 2;
+        // SERIAL, REFRESH, RETRY, EXPIRE
+        packet[pos..pos + 16].fill(0);
+        pos += 16;
+        packet[pos..pos + 4].copy_from_slice(&minimum.to_be_bytes());
+        pos += 4;
+    }
+    pos
+}
+
+pub fn test_dns_t9_negative_answers() -> TestResult {
+    let id = 0x5150;
+    let mut packet = [0u8; 128];
+
+    // NXDOMAIN: the negative TTL is the smaller of the SOA TTL and MINIMUM.
+    let len = build_empty_reply(id, 0x8183, Some((3600, 120)), &mut packet);
+    assert_eq_test!(
+        dns::dns_parse_answer(&packet[..len], id),
+        Some(DnsAnswer::Negative { ttl: Some(120) }),
+        "NXDOMAIN with SOA minimum"
+    );
+    assert_test!(
+        dns::dns_parse_response(&packet[..len], id).is_none(),
+        "NXDOMAIN has no address"
+    );
+    let len = build_empty_reply(id, 0x8183, Some((60, 900)), &mut packet);
+    assert_eq_test!(
+        dns::dns_parse_answer(&packet[..len], id),
+        Some(DnsAnswer::Negative { ttl: Some(60) }),
+        "NXDOMAIN with short SOA TTL"
+    );
+
+    // NODATA: NOERROR without an A record, here without an SOA either.
+    let len = build_empty_reply(id, 0x8180, None, &mut packet);
+    assert_eq_test!(
+        dns::dns_parse_answer(&packet[..len], id),
+        Some(DnsAnswer::Negative { ttl: None }),
+        "NODATA without SOA"
+    );
+
+    // SERVFAIL and REFUSED send the resolver to the next server.
+    let len = build_empty_reply(id, 0x8182, None, &mut packet);
+    assert_eq_test!(
+        dns::dns_parse_answer(&packet[..len], id),
+        Some(DnsAnswer::ServerFailure),
+        "SERVFAIL"
+    );
+    let len = build_empty_reply(id, 0x8185, None, &mut packet);
+    assert_eq_test!(
+        dns::dns_parse_answer(&packet[..len], id),
+        Some(DnsAnswer::ServerFailure),
+        "REFUSED"
+    );
+    assert_test!(
+        dns::dns_parse_answer(&packet[..len], id.wrapping_add(1)).is_none(),
+        "reply to another query"
+    );
+
+    pass!()
+}
+
+pub fn test_dns_t10_ttl_cache() -> TestResult {
+    dns::dns_cache_flush();
+
+    // A negative entry answers lookups without touching the network.
+    dns::dns_cache_insert_negative(b"gone.test", Some(120));
+    assert_test!(
+        dns::dns_cache_lookup(b"gone.test").is_none(),
+        "negative entry has no address"
+    );
+    assert_eq_test!(
+        dns::dns_lookup(b"GONE.test", true),
+        DnsLookup::NotFound,
+        "negative entry, any case"
+    );
+
+    // A zero TTL means the answer must not be reused.
+    dns::dns_cache_insert(b"zero.test", [10, 0, 0, 1], 0);
+    assert_test!(
+        dns::dns_cache_lookup(b"zero.test").is_none(),
+        "zero TTL cached"
+    );
+
+    // Entries are keyed by the whole name, not a prefix or its hash alone.
+    dns::dns_cache_insert(b"host.test", [10, 0, 0, 2], 60);
+    assert_test!(
+        dns::dns_cache_lookup(b"host.tes").is_none(),
+        "prefix matched"
+    );
+    assert_eq_test!(
+        dns::dns_lookup(b"Host.Test", true),
+        DnsLookup::Found([10, 0, 0, 2]),
+        "case-insensitive hit"
+    );
+
+    dns::dns_cache_flush();
+    pass!()
+}
+
+pub fn test_dns_t11_nonblocking_lookup() -> TestResult {
+    // Skip if network is not ready
+    if !crate::virtio_net::virtio_net_is_ready() {
+        return pass!();
+    }
+    dns::dns_cache_flush();
+
+    let first = dns::dns_lookup(b"dns.google", true);
+    assert_eq_test!(first, DnsLookup::Pending, "uncached lookup returned early");
+
+    // A blocking lookup for another name proceeds alongside it.
+    let missing = dns::dns_resolve(b"this-does-not-exist.invalid");
+    assert_test!(missing.is_none(), "non-existent hostname resolved");
+    let again = dns::dns_lookup(b"this-does-not-exist.invalid", true);
+    assert_test!(again != DnsLookup::Pending, "failure was not remembered");
+
+    let start = slopos_lib::clock::uptime_ms();
+    let mut result = dns::dns_lookup(b"dns.google", true);
+    while result == DnsLookup::Pending && slopos_lib::clock::uptime_ms() - start < 10_000 {
+        crate::virtio_net::net_rx_wait(50);
+        result = dns::dns_lookup(b"dns.google", true);
+    }
+    let DnsLookup::Found(addr) = result else {
+        return fail!("dns.google: {:?}", result);
+    };
+    assert_test!(addr[0] == 8 && addr[1] == 8, "dns.google starts with 8.8");
+
+    pass!()
+}
+
 slopos_lib::define_test_suite!(
     dns,
     [
@@ -386,5 +549,8 @@ slopos_lib::define_test_suite!(
         test_dns_t6_resolver_integration,
         test_dns_t7_resolver_timeout,
         test_dns_t8_regression_network_stack,
+        test_dns_t9_negative_answers,
+        test_dns_t10_ttl_cache,
+        test_dns_t11_nonblocking_lookup,
     ]
 );
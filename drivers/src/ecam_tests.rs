@@ -20,6 +20,7 @@
 //! - Extended config space (offset >= 0x100) is accessible through ECAM
 //! - Misaligned and out-of-range ECAM reads correctly return None
 //! - Write-readback through ECAM produces consistent results
+//! - AER and SR-IOV readers find the same headers as the capability walk
 //!
 //! All tests run after PCI enumeration in the test harness.  QEMU q35 exposes
 //! a single MCFG entry for segment 0, buses 0–255, at physical address
@@ -33,6 +34,7 @@ use crate::pci::{
     pci_ecam_mapped_region, pci_ecam_primary_virt, pci_ecam_read8, pci_ecam_read16,
     pci_ecam_read32, pci_get_device, pci_get_device_count,
 };
+use crate::pci_defs::{PCI_EXT_CAP_ID_AER, PCI_EXT_CAP_ID_SRIOV, PciAerStatus};
 
 // =============================================================================
 // 1. MCFG discovery sanity
@@ -742,6 +744,76 @@ pub fn test_ecam_sweep_all_devices() -> TestResult {
     pass!()
 }
 
+// =============================================================================
+// 15. AER / SR-IOV headers
+// =============================================================================
+
+pub fn test_aer_sriov_readers_match_capability_walk() -> TestResult {
+    for i in 0..pci_get_device_count() {
+        let Some(dev) = pci_get_device(i) else {
+            continue;
+        };
+        let aer_cap = dev.find_ext_capability(PCI_EXT_CAP_ID_AER);
+        let aer = dev.aer_status();
+        if aer.map(|a| a.offset) != aer_cap {
+            return fail!(
+                "{}.{}.{}: AER reader at {:?}, capability walk at {:?}",
+                dev.bus,
+                dev.device,
+                dev.function,
+                aer.map(|a| a.offset),
+                aer_cap
+            );
+        }
+        if let Some(aer) = aer
+            && aer.fatal() & !aer.uncorrectable != 0
+        {
+            return fail!("AER fatal bits outside the uncorrectable status");
+        }
+
+        let sriov_cap = dev.find_ext_capability(PCI_EXT_CAP_ID_SRIOV);
+        let sriov = dev.sriov();
+        if sriov.map(|s| s.offset) != sriov_cap {
+            return fail!(
+                "{}.{}.{}: SR-IOV reader at {:?}, capability walk at {:?}",
+                dev.bus,
+                dev.device,
+                dev.function,
+                sriov.map(|s| s.offset),
+                sriov_cap
+            );
+        }
+        if let Some(sriov) = sriov
+            && (sriov.initial_vfs > sriov.total_vfs || sriov.num_vfs > sriov.total_vfs)
+        {
+            return fail!("SR-IOV VF counts exceed TotalVFs: {:?}", sriov);
+        }
+    }
+    pass!()
+}
+
+pub fn test_aer_status_ignores_masked_errors() -> TestResult {
+    let masked = PciAerStatus {
+        offset: 0x100,
+        uncorrectable: 1 << 4,
+        uncorrectable_mask: 1 << 4,
+        uncorrectable_severity: 1 << 4,
+        correctable: 1 << 6,
+        correctable_mask: 1 << 6,
+    };
+    if masked.has_errors() || masked.fatal() != 0 {
+        return fail!("masked AER errors reported");
+    }
+    let live = PciAerStatus {
+        uncorrectable_mask: 0,
+        ..masked
+    };
+    if !live.has_errors() || live.fatal() != 1 << 4 {
+        return fail!("unmasked fatal AER error not reported");
+    }
+    pass!()
+}
+
 // =============================================================================
 // Suite registration
 // =============================================================================
@@ -802,5 +874,8 @@ slopos_lib::define_test_suite!(
         test_ecam_reads_deterministic,
         // All-device sweep
         test_ecam_sweep_all_devices,
+        // AER / SR-IOV headers
+        test_aer_sriov_readers_match_capability_walk,
+        test_aer_status_ignores_masked_errors,
     ]
 );
@@ -1,7 +1,9 @@
 //! HPET (High Precision Event Timer) driver.
 //!
 //! Primary monotonic time source for SlopOS.  Provides LAPIC timer
-//! calibration reference and nanosecond-precision polled delays.
+//! calibration reference, nanosecond-precision polled delays, the TSC
+//! frequency used by [`slopos_lib::testing::measure_elapsed_ms`], and
+//! comparator-based one-shot interrupts.
 //!
 //! HPET is mandatory; the kernel panics at boot if
 //! the ACPI HPET table is missing or the hardware is unavailable.
@@ -10,16 +12,26 @@
 //! CPU without synchronization.  Init is guarded by [`InitFlag`] +
 //! [`StateFlag`] for SMP safety.
 
-use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicU8, AtomicU32, AtomicU64, Ordering};
 
 use slopos_abi::addr::PhysAddr;
 use slopos_acpi::hpet::Hpet;
 use slopos_acpi::tables::{AcpiTables, Rsdp};
+use slopos_lib::kernel_services::driver_runtime::{
+    LEGACY_IRQ_COM1, LEGACY_IRQ_KEYBOARD, LEGACY_IRQ_MOUSE,
+};
 use slopos_lib::kernel_services::platform;
-use slopos_lib::{InitFlag, StateFlag, klog_debug, klog_info};
+use slopos_lib::{InitFlag, InterruptFrame, IrqMutex, StateFlag, klog_debug, klog_info, tsc};
 use slopos_mm::hhdm;
 use slopos_mm::mmio::MmioRegion;
 
+use crate::ioapic::regs::{
+    IOAPIC_FLAG_DELIVERY_FIXED, IOAPIC_FLAG_DEST_PHYSICAL, IOAPIC_FLAG_POLARITY_HIGH,
+    IOAPIC_FLAG_TRIGGER_EDGE,
+};
+use crate::{apic, ioapic};
+
 /// General Capabilities and ID (64-bit RO).
 /// [63:32] CLK_PERIOD (fs), [15] LEG_RT_CAP, [13] COUNT_SIZE_CAP,
 /// [12:8] NUM_TIM_CAP (timers-1), [7:0] REV_ID.
@@ -31,12 +43,42 @@ const REG_GENERAL_CONFIG: usize = 0x010;
 /// Main Counter Value (64-bit RW). Monotonic; writes require counter halted.
 const REG_MAIN_COUNTER: usize = 0x0F0;
 
+/// Timer N Configuration and Capability (64-bit). [63:32] INT_ROUTE_CAP,
+/// [13:9] INT_ROUTE_CNF, [8] 32MODE_CNF, [3] TYPE_CNF, [2] INT_ENB_CNF,
+/// [1] INT_TYPE_CNF.
+const REG_TIMER_CONFIG_BASE: usize = 0x100;
+/// Timer N Comparator Value (64-bit, or 32-bit when the timer is narrow).
+const REG_TIMER_COMPARATOR_BASE: usize = 0x108;
+/// Register stride between consecutive timer blocks.
+const TIMER_STRIDE: usize = 0x20;
+
 const CONFIG_ENABLE: u64 = 1 << 0;
 /// Legacy replacement routing — disabled to avoid IOAPIC conflicts.
 const CONFIG_LEGACY_REPLACE: u64 = 1 << 1;
 
+/// Level-triggered interrupt (clear = edge).
+const TIMER_INT_LEVEL: u64 = 1 << 1;
+const TIMER_INT_ENABLE: u64 = 1 << 2;
+const TIMER_PERIODIC: u64 = 1 << 3;
+const TIMER_FSB_ENABLE: u64 = 1 << 14;
+const TIMER_ROUTE_SHIFT: u32 = 9;
+const TIMER_ROUTE_MASK: u64 = 0x1F << TIMER_ROUTE_SHIFT;
+
 const HPET_REGION_SIZE: usize = 0x400;
 
+/// Comparator used for one-shot interrupts.
+const ONESHOT_TIMER: usize = 0;
+
+/// GSIs below this are ISA lines; prefer routes above them.
+const FIRST_NON_ISA_GSI: u32 = 16;
+
+/// Shortest one-shot delay.  A comparator written at or behind the main
+/// counter only fires after a full wrap, so deadlines are kept clear of it.
+const ONESHOT_MIN_NS: u64 = 10_000;
+
+/// Length of the HPET window the TSC is measured over.
+const TSC_CALIBRATION_MS: u64 = 10;
+
 /// Max valid CLK_PERIOD per HPET spec: ≤ 100 ns (0x05F5_E100 fs).
 const MAX_VALID_PERIOD_FS: u32 = 0x05F5_E100;
 
@@ -49,6 +91,11 @@ static PERIOD_FS: AtomicU32 = AtomicU32::new(0);
 /// MMIO virtual base — cached for hot-path counter reads without Option.
 static MMIO_VIRT_BASE: AtomicU64 = AtomicU64::new(0);
 
+/// Vector of the one-shot comparator interrupt, 0 if not routed.
+static ONESHOT_VECTOR: AtomicU8 = AtomicU8::new(0);
+static ONESHOT_FIRED: AtomicU64 = AtomicU64::new(0);
+static ONESHOT_CALLBACK: IrqMutex<Option<fn()>> = IrqMutex::new(None);
+
 /// Initialize the HPET from ACPI tables.
 ///
 /// Returns `0` on success, `-1` on failure.  The boot sequence treats
@@ -71,16 +118,41 @@ pub fn init() -> i32 {
     result
 }
 
-/// Read the HPET main counter (64-bit monotonic). Returns `0` if not init'd.
 #[inline]
-pub fn read_counter() -> u64 {
+fn reg_read(offset: usize) -> u64 {
     let base = MMIO_VIRT_BASE.load(Ordering::Relaxed);
     if base == 0 {
         return 0;
     }
-    // SAFETY: base was validated during init and points to a mapped MMIO page.
-    // The main counter register is read-only and safe from any CPU.
-    unsafe { core::ptr::read_volatile((base + REG_MAIN_COUNTER as u64) as *const u64) }
+    // SAFETY: base was validated during init and points to a mapped MMIO
+    // region of HPET_REGION_SIZE bytes; every caller passes an offset in it.
+    unsafe { core::ptr::read_volatile((base + offset as u64) as *const u64) }
+}
+
+#[inline]
+fn reg_write(offset: usize, value: u64) {
+    let base = MMIO_VIRT_BASE.load(Ordering::Relaxed);
+    if base == 0 {
+        return;
+    }
+    // SAFETY: as for reg_read.
+    unsafe { core::ptr::write_volatile((base + offset as u64) as *mut u64, value) }
+}
+
+#[inline]
+const fn timer_config_reg(timer: usize) -> usize {
+    REG_TIMER_CONFIG_BASE + timer * TIMER_STRIDE
+}
+
+#[inline]
+const fn timer_comparator_reg(timer: usize) -> usize {
+    REG_TIMER_COMPARATOR_BASE + timer * TIMER_STRIDE
+}
+
+/// Read the HPET main counter (64-bit monotonic). Returns `0` if not init'd.
+#[inline]
+pub fn read_counter() -> u64 {
+    reg_read(REG_MAIN_COUNTER)
 }
 
 /// Convert ticks to nanoseconds: `ns = ticks × period_fs / 1_000_000`.
@@ -94,13 +166,22 @@ pub fn nanoseconds(ticks: u64) -> u64 {
     ((ticks as u128 * period as u128) / 1_000_000) as u64
 }
 
-/// Spin-wait for the specified nanoseconds.
-pub fn delay_ns(ns: u64) {
+/// Convert nanoseconds to ticks (rounding down).  Returns `0` if not init'd.
+#[inline]
+pub fn ticks_for_ns(ns: u64) -> u64 {
     let period = PERIOD_FS.load(Ordering::Relaxed) as u64;
     if period == 0 {
+        return 0;
+    }
+    ((ns as u128 * 1_000_000) / period as u128) as u64
+}
+
+/// Spin-wait for the specified nanoseconds.
+pub fn delay_ns(ns: u64) {
+    if PERIOD_FS.load(Ordering::Relaxed) == 0 {
         return;
     }
-    let ticks_needed = ((ns as u128 * 1_000_000) / period as u128) as u64;
+    let ticks_needed = ticks_for_ns(ns);
     let start = read_counter();
     while read_counter().wrapping_sub(start) < ticks_needed {
         core::hint::spin_loop();
@@ -130,6 +211,176 @@ pub fn period_fs() -> u32 {
     PERIOD_FS.load(Ordering::Relaxed)
 }
 
+/// Measure the TSC against the HPET main counter and publish the result via
+/// [`slopos_lib::tsc::set_cycles_per_ms`].  Returns cycles per millisecond.
+pub fn calibrate_tsc() -> Option<u64> {
+    if !is_available() {
+        return None;
+    }
+
+    let window = ticks_for_ns(TSC_CALIBRATION_MS * 1_000_000);
+    let hpet_start = read_counter();
+    let tsc_start = tsc::rdtsc();
+    let mut hpet_end = hpet_start;
+    while hpet_end.wrapping_sub(hpet_start) < window {
+        core::hint::spin_loop();
+        hpet_end = read_counter();
+    }
+    let tsc_end = tsc::rdtsc();
+
+    let elapsed_ns = nanoseconds(hpet_end.wrapping_sub(hpet_start));
+    if elapsed_ns == 0 {
+        return None;
+    }
+    let cycles = tsc_end.wrapping_sub(tsc_start);
+    let cycles_per_ms = ((cycles as u128 * 1_000_000) / elapsed_ns as u128) as u64;
+    if cycles_per_ms == 0 {
+        return None;
+    }
+
+    tsc::set_cycles_per_ms(cycles_per_ms);
+    klog_info!(
+        "HPET: TSC calibrated at {} cycles/ms (~{} MHz)",
+        cycles_per_ms,
+        cycles_per_ms / 1_000
+    );
+    Some(cycles_per_ms)
+}
+
+/// Whether the one-shot comparator has an interrupt route.
+#[inline]
+pub fn oneshot_available() -> bool {
+    ONESHOT_VECTOR.load(Ordering::Acquire) != 0
+}
+
+/// Number of one-shot interrupts delivered since boot.
+#[inline]
+pub fn oneshot_fired_count() -> u64 {
+    ONESHOT_FIRED.load(Ordering::Acquire)
+}
+
+/// Arm the one-shot comparator to interrupt after `delay_ns`, replacing
+/// any pending deadline.  `callback` runs in interrupt context.
+///
+/// Returns `false` if one-shot interrupts are unavailable.
+pub fn oneshot_arm(delay_ns: u64, callback: Option<fn()>) -> bool {
+    if !oneshot_available() {
+        return false;
+    }
+
+    let ticks = ticks_for_ns(delay_ns.max(ONESHOT_MIN_NS)).max(1);
+    let config_reg = timer_config_reg(ONESHOT_TIMER);
+    let config = reg_read(config_reg) & !TIMER_INT_ENABLE;
+
+    reg_write(config_reg, config);
+    *ONESHOT_CALLBACK.lock() = callback;
+    reg_write(
+        timer_comparator_reg(ONESHOT_TIMER),
+        read_counter().wrapping_add(ticks),
+    );
+    reg_write(config_reg, config | TIMER_INT_ENABLE);
+    true
+}
+
+/// Disarm a pending one-shot deadline without running its callback.
+pub fn oneshot_cancel() {
+    if !oneshot_available() {
+        return;
+    }
+    let config_reg = timer_config_reg(ONESHOT_TIMER);
+    reg_write(config_reg, reg_read(config_reg) & !TIMER_INT_ENABLE);
+    *ONESHOT_CALLBACK.lock() = None;
+}
+
+extern "C" fn oneshot_irq_handler(_vector: u8, _frame: *mut InterruptFrame, _ctx: *mut c_void) {
+    // Disable the comparator so a 32-bit timer can't re-fire on wrap.
+    let config_reg = timer_config_reg(ONESHOT_TIMER);
+    reg_write(config_reg, reg_read(config_reg) & !TIMER_INT_ENABLE);
+    ONESHOT_FIRED.fetch_add(1, Ordering::AcqRel);
+
+    let callback = ONESHOT_CALLBACK.lock().take();
+    if let Some(callback) = callback {
+        callback();
+    }
+}
+
+/// GSIs already claimed by the legacy device routes in [`crate::irq`].
+fn legacy_route_gsis() -> [u32; 3] {
+    [LEGACY_IRQ_KEYBOARD, LEGACY_IRQ_MOUSE, LEGACY_IRQ_COM1].map(|irq| {
+        let mut gsi = irq as u32;
+        let mut flags = 0u32;
+        let _ = ioapic::legacy_irq_info(irq, &mut gsi, &mut flags);
+        gsi
+    })
+}
+
+/// Route the one-shot comparator through the IOAPIC to an MSI-range vector.
+///
+/// Non-fatal: without a usable route the driver still serves as the
+/// clocksource and delays just can't be interrupt-driven.
+fn setup_oneshot() {
+    if !apic::is_enabled() || ioapic::is_ready() == 0 {
+        klog_info!("HPET: APIC/IOAPIC not ready, one-shot interrupts disabled");
+        return;
+    }
+
+    let config_reg = timer_config_reg(ONESHOT_TIMER);
+    let config = reg_read(config_reg);
+    let route_cap = (config >> 32) as u32;
+    let reserved = legacy_route_gsis();
+
+    // Highest GSI first: the PCI lines above the ISA range are free on
+    // MSI-only systems, and QEMU's i440fx only offers GSI 2 anyway.
+    let Some(gsi) = (0..32u32)
+        .rev()
+        .filter(|gsi| route_cap & (1 << gsi) != 0 && !reserved.contains(gsi))
+        .min_by_key(|gsi| *gsi < FIRST_NON_ISA_GSI)
+    else {
+        klog_info!(
+            "HPET: timer {} has no usable IOAPIC route (cap 0x{:08x})",
+            ONESHOT_TIMER,
+            route_cap
+        );
+        return;
+    };
+
+    let Some(vector) = slopos_core::irq::msi_alloc_vector() else {
+        klog_info!("HPET: no free vector for one-shot interrupts");
+        return;
+    };
+
+    let flags = IOAPIC_FLAG_DELIVERY_FIXED
+        | IOAPIC_FLAG_DEST_PHYSICAL
+        | IOAPIC_FLAG_POLARITY_HIGH
+        | IOAPIC_FLAG_TRIGGER_EDGE;
+    if ioapic::config_irq(gsi, vector, apic::get_id() as u8, flags) != 0 {
+        slopos_core::irq::msi_free_vector(vector);
+        klog_info!(
+            "HPET: failed to route timer {} to GSI {}",
+            ONESHOT_TIMER,
+            gsi
+        );
+        return;
+    }
+    slopos_core::irq::msi_register_handler(vector, oneshot_irq_handler, core::ptr::null_mut(), 0);
+
+    // Edge-triggered, non-periodic, IOAPIC-routed, interrupt off until armed.
+    let config = (config
+        & !(TIMER_INT_LEVEL | TIMER_INT_ENABLE | TIMER_PERIODIC | TIMER_FSB_ENABLE))
+        & !TIMER_ROUTE_MASK
+        | ((gsi as u64) << TIMER_ROUTE_SHIFT);
+    reg_write(config_reg, config);
+    let _ = ioapic::unmask_gsi(gsi);
+
+    ONESHOT_VECTOR.store(vector, Ordering::Release);
+    klog_info!(
+        "HPET: timer {} one-shot via GSI {} -> vector 0x{:x}",
+        ONESHOT_TIMER,
+        gsi,
+        vector
+    );
+}
+
 fn init_inner() -> i32 {
     if !hhdm::is_available() {
         klog_info!("HPET: HHDM unavailable, cannot map MMIO registers");
@@ -191,13 +442,19 @@ fn init_inner() -> i32 {
     let freq_mhz = 1_000_000_000_000_000u64 / period_fs as u64 / 1_000_000;
 
     klog_info!(
-        "HPET: base 0x{:x}, period {} fs (~{} MHz), {} comparators, {}-bit counter, rev {}",
+        "HPET: base 0x{:x}, vendor 0x{:04x}, period {} fs (~{} MHz), {} comparators, {}-bit counter, rev {}{}",
         info.base_phys,
+        info.pci_vendor_id,
         period_fs,
         freq_mhz,
         num_timers,
         if counter_64bit { 64 } else { 32 },
         rev_id,
+        if info.legacy_replacement {
+            ", legacy-capable"
+        } else {
+            ""
+        },
     );
 
     let c1 = read_counter();
@@ -220,5 +477,7 @@ fn init_inner() -> i32 {
 
     HPET_READY.mark_set();
     HPET_INIT_IN_PROGRESS.leave();
+
+    setup_oneshot();
     0
 }
@@ -1,5 +1,7 @@
-use slopos_lib::testing::TestResult;
-use slopos_lib::{klog_info, testing::measure_elapsed_ms, tsc::rdtsc};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_lib::testing::{TestResult, estimate_cycles_per_ms};
+use slopos_lib::{cpu, klog_info, testing::measure_elapsed_ms, tsc, tsc::rdtsc};
 
 use crate::hpet;
 
@@ -245,6 +247,122 @@ pub fn test_hpet_period_valid() -> TestResult {
     TestResult::Pass
 }
 
+// ---------------------------------------------------------------------------
+// TSC calibration
+// ---------------------------------------------------------------------------
+
+/// Verify boot calibrated the TSC and the test harness uses that figure.
+pub fn test_hpet_tsc_calibrated() -> TestResult {
+    let calibrated = tsc::calibrated_cycles_per_ms();
+    if calibrated == 0 {
+        klog_info!("HPET_TEST: BUG - TSC never calibrated against HPET");
+        return TestResult::Fail;
+    }
+    if estimate_cycles_per_ms() != calibrated {
+        klog_info!(
+            "HPET_TEST: BUG - harness uses {} cycles/ms, calibration says {}",
+            estimate_cycles_per_ms(),
+            calibrated
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+/// Verify a fresh calibration agrees with the boot one to within 10%.
+pub fn test_hpet_tsc_recalibration_stable() -> TestResult {
+    let boot = tsc::calibrated_cycles_per_ms();
+    let Some(again) = hpet::calibrate_tsc() else {
+        klog_info!("HPET_TEST: BUG - calibrate_tsc() failed");
+        return TestResult::Fail;
+    };
+    if boot != 0 && again.abs_diff(boot) > boot / 10 {
+        klog_info!(
+            "HPET_TEST: BUG - TSC calibration drifted {} -> {} cycles/ms",
+            boot,
+            again
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+// ---------------------------------------------------------------------------
+// One-shot comparator interrupts
+// ---------------------------------------------------------------------------
+
+static ONESHOT_CALLBACK_RAN: AtomicBool = AtomicBool::new(false);
+
+fn oneshot_test_callback() {
+    ONESHOT_CALLBACK_RAN.store(true, Ordering::Release);
+}
+
+/// Poll up to `limit_ms` for the fired counter to move past `before`.
+fn wait_for_oneshot(before: u64, limit_ms: u64) -> bool {
+    let deadline = hpet::read_counter().wrapping_add(hpet::ticks_for_ns(limit_ms * 1_000_000));
+    while hpet::oneshot_fired_count() == before {
+        if hpet::read_counter().wrapping_sub(deadline) as i64 >= 0 {
+            return false;
+        }
+        core::hint::spin_loop();
+    }
+    true
+}
+
+/// Verify an armed comparator interrupts and runs its callback once.
+pub fn test_hpet_oneshot_fires() -> TestResult {
+    if !hpet::oneshot_available() {
+        klog_info!("HPET_TEST: SKIP - no one-shot interrupt route");
+        return TestResult::Skipped;
+    }
+    if !cpu::are_interrupts_enabled() {
+        klog_info!("HPET_TEST: SKIP - interrupts disabled");
+        return TestResult::Skipped;
+    }
+
+    ONESHOT_CALLBACK_RAN.store(false, Ordering::Release);
+    let before = hpet::oneshot_fired_count();
+    if !hpet::oneshot_arm(1_000_000, Some(oneshot_test_callback)) {
+        klog_info!("HPET_TEST: BUG - oneshot_arm refused with a route present");
+        return TestResult::Fail;
+    }
+    if !wait_for_oneshot(before, 100) {
+        klog_info!("HPET_TEST: BUG - 1 ms one-shot not delivered within 100 ms");
+        return TestResult::Fail;
+    }
+    if !ONESHOT_CALLBACK_RAN.load(Ordering::Acquire) {
+        klog_info!("HPET_TEST: BUG - one-shot fired without running callback");
+        return TestResult::Fail;
+    }
+
+    // One-shot means one: nothing further within another 20 ms.
+    let after = hpet::oneshot_fired_count();
+    hpet::delay_ms(20);
+    if hpet::oneshot_fired_count() != after {
+        klog_info!("HPET_TEST: BUG - one-shot comparator fired again");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+/// Verify a cancelled deadline never fires.
+pub fn test_hpet_oneshot_cancel() -> TestResult {
+    if !hpet::oneshot_available() {
+        klog_info!("HPET_TEST: SKIP - no one-shot interrupt route");
+        return TestResult::Skipped;
+    }
+
+    let before = hpet::oneshot_fired_count();
+    hpet::oneshot_arm(5_000_000, None);
+    hpet::oneshot_cancel();
+    hpet::delay_ms(20);
+    if hpet::oneshot_fired_count() != before {
+        klog_info!("HPET_TEST: BUG - cancelled one-shot still fired");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
 slopos_lib::define_test_suite!(
     hpet,
     [
@@ -258,5 +376,9 @@ slopos_lib::define_test_suite!(
         test_hpet_counter_monotonic,
         test_hpet_delay_zero,
         test_hpet_delay_accuracy,
+        test_hpet_tsc_calibrated,
+        test_hpet_tsc_recalibration_stable,
+        test_hpet_oneshot_fires,
+        test_hpet_oneshot_cancel,
     ]
 );
@@ -0,0 +1,335 @@
+//! Hardware inventory.
+//!
+//! [`hwinfo_assemble`] runs once at boot, after PCI drivers have probed and
+//! the display is up.  It walks the CPUs, the memory map, the PCI bus and
+//! the block, network, display and input drivers and records one
+//! [`HwDevice`] per device, linking each device to the PCI function it sits
+//! behind.  `SYSCALL_HW_INVENTORY` copies the table out, so `lspci`, `lsblk`
+//! and `sysinfo` all read the same snapshot instead of asking each driver.
+
+use alloc::format;
+
+use slopos_abi::DisplayInfo;
+use slopos_abi::hw::{
+    HW_CLASS_BLOCK, HW_CLASS_CPU, HW_CLASS_DISPLAY, HW_CLASS_INPUT, HW_CLASS_MEMORY, HW_CLASS_NET,
+    HW_CLASS_PCI, HW_FLAG_BOUND, HW_FLAG_BSP, HW_INPUT_KEYBOARD, HW_INPUT_POINTER, HW_MAX_DEVICES,
+    HW_NAME_LEN, HW_NO_PARENT, HwDevice, hw_pci_bdf,
+};
+use slopos_abi::net::UserNetInfo;
+use slopos_lib::cpu::{CPUID_LEAF_FEATURES, cpuid};
+use slopos_lib::{IrqMutex, klog_info, pcr};
+use slopos_mm::memmap::{MemMapKind, memmap_for_each};
+
+use crate::pci::{
+    pci_get_device, pci_get_device_count, pci_get_device_driver, pci_get_primary_gpu,
+};
+use crate::ps2::{keyboard, mouse};
+use crate::{nvme, virtio_blk, virtio_net};
+
+/// The display the video subsystem came up on.  Boot hands it in because
+/// the video crate sits above the drivers.
+#[derive(Clone, Copy, Debug)]
+pub struct HwDisplay {
+    pub info: DisplayInfo,
+    pub driver: &'static str,
+}
+
+pub(crate) struct Inventory {
+    devices: [HwDevice; HW_MAX_DEVICES],
+    count: usize,
+    /// Devices that did not fit.
+    dropped: usize,
+}
+
+impl Inventory {
+    pub(crate) const fn new() -> Self {
+        Self {
+            devices: [HwDevice {
+                id: 0,
+                parent: HW_NO_PARENT,
+                class: 0,
+                flags: 0,
+                _pad: [0; 2],
+                name: [0; HW_NAME_LEN],
+                driver: [0; HW_NAME_LEN],
+                attrs: [0; 4],
+            }; HW_MAX_DEVICES],
+            count: 0,
+            dropped: 0,
+        }
+    }
+
+    pub(crate) fn devices(&self) -> &[HwDevice] {
+        &self.devices[..self.count]
+    }
+
+    pub(crate) fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Append a device and return its id, or [`HW_NO_PARENT`] once the
+    /// table is full.  A non-empty `driver` marks the device bound.
+    pub(crate) fn push(
+        &mut self,
+        class: u8,
+        parent: u16,
+        flags: u8,
+        name: &str,
+        driver: &str,
+        attrs: [u64; 4],
+    ) -> u16 {
+        if self.count == HW_MAX_DEVICES {
+            self.dropped += 1;
+            return HW_NO_PARENT;
+        }
+        let id = self.count as u16;
+        let mut device = HwDevice {
+            id,
+            parent,
+            class,
+            flags,
+            attrs,
+            ..HwDevice::default()
+        };
+        if !driver.is_empty() {
+            device.flags |= HW_FLAG_BOUND;
+        }
+        copy_name(&mut device.name, name);
+        copy_name(&mut device.driver, driver);
+        self.devices[self.count] = device;
+        self.count += 1;
+        id
+    }
+
+    /// Id of the PCI function `driver` claimed, for parent links.
+    pub(crate) fn pci_bound_to(&self, driver: &str) -> u16 {
+        self.devices()
+            .iter()
+            .find(|d| d.class == HW_CLASS_PCI && d.driver_str() == driver)
+            .map_or(HW_NO_PARENT, |d| d.id)
+    }
+
+    fn pci_at(&self, bdf: u64) -> u16 {
+        self.devices()
+            .iter()
+            .find(|d| d.class == HW_CLASS_PCI && d.attrs[0] == bdf)
+            .map_or(HW_NO_PARENT, |d| d.id)
+    }
+}
+
+static INVENTORY: IrqMutex<Inventory> = IrqMutex::new(Inventory::new());
+
+fn copy_name(dst: &mut [u8; HW_NAME_LEN], src: &str) {
+    let len = src.len().min(HW_NAME_LEN - 1);
+    dst[..len].copy_from_slice(&src.as_bytes()[..len]);
+}
+
+/// `family << 16 | model << 8 | stepping`, with the extended fields folded
+/// in the way the SDM describes.
+fn cpu_signature() -> u64 {
+    let (eax, _, _, _) = cpuid(CPUID_LEAF_FEATURES);
+    let mut family = (eax >> 8) & 0xF;
+    let mut model = (eax >> 4) & 0xF;
+    if family == 0xF {
+        family += (eax >> 20) & 0xFF;
+    }
+    if family == 0x6 || family >= 0xF {
+        model |= ((eax >> 16) & 0xF) << 4;
+    }
+    ((family as u64) << 16) | ((model as u64) << 8) | (eax & 0xF) as u64
+}
+
+fn collect_cpus(inv: &mut Inventory) {
+    let signature = cpu_signature();
+    for cpu in 0..pcr::get_cpu_count() {
+        let Some(apic_id) = pcr::apic_id_from_cpu_index(cpu) else {
+            continue;
+        };
+        let flags = if cpu == 0 { HW_FLAG_BSP } else { 0 };
+        inv.push(
+            HW_CLASS_CPU,
+            HW_NO_PARENT,
+            flags,
+            &format!("cpu{}", cpu),
+            "",
+            [apic_id as u64, cpu as u64, signature, 0],
+        );
+    }
+}
+
+/// Without SRAT every CPU sees one node spanning all usable memory.
+fn collect_memory(inv: &mut Inventory) {
+    let mut base = u64::MAX;
+    let mut end = 0u64;
+    let mut usable = 0u64;
+    memmap_for_each(|entry| {
+        if entry.kind == MemMapKind::Usable {
+            base = base.min(entry.phys_base);
+            end = end.max(entry.end());
+            usable += entry.length;
+        }
+    });
+    if usable != 0 {
+        inv.push(
+            HW_CLASS_MEMORY,
+            HW_NO_PARENT,
+            0,
+            "node0",
+            "",
+            [base, end, usable, 0],
+        );
+    }
+}
+
+fn collect_pci(inv: &mut Inventory) {
+    for index in 0..pci_get_device_count() {
+        let Some(dev) = pci_get_device(index) else {
+            continue;
+        };
+        inv.push(
+            HW_CLASS_PCI,
+            HW_NO_PARENT,
+            0,
+            &format!("{:02x}:{:02x}.{}", dev.bus, dev.device, dev.function),
+            pci_get_device_driver(index).unwrap_or(""),
+            [
+                hw_pci_bdf(dev.bus, dev.device, dev.function),
+                ((dev.vendor_id as u64) << 16) | dev.device_id as u64,
+                ((dev.class_code as u64) << 16) | ((dev.subclass as u64) << 8) | dev.prog_if as u64,
+                ((dev.revision as u64) << 8) | dev.irq_line as u64,
+            ],
+        );
+    }
+}
+
+fn collect_block(inv: &mut Inventory) {
+    if virtio_blk::virtio_blk_is_ready() {
+        let parent = inv.pci_bound_to("virtio-blk");
+        inv.push(
+            HW_CLASS_BLOCK,
+            parent,
+            0,
+            "vda",
+            "virtio-blk",
+            [
+                virtio_blk::virtio_blk_capacity(),
+                virtio_blk::virtio_blk_block_size() as u64,
+                0,
+                0,
+            ],
+        );
+    }
+    if nvme::nvme_is_ready() {
+        let parent = inv.pci_bound_to("nvme");
+        inv.push(
+            HW_CLASS_BLOCK,
+            parent,
+            0,
+            "nvme0n1",
+            "nvme",
+            [nvme::nvme_capacity(), nvme::nvme_block_size() as u64, 0, 0],
+        );
+    }
+}
+
+fn collect_net(inv: &mut Inventory) {
+    if !virtio_net::virtio_net_is_ready() {
+        return;
+    }
+    let mut info = UserNetInfo::default();
+    virtio_net::virtio_net_get_info(&mut info);
+    let mac = info.mac.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    let parent = inv.pci_bound_to("virtio-net");
+    inv.push(
+        HW_CLASS_NET,
+        parent,
+        0,
+        "eth0",
+        "virtio-net",
+        [mac, info.mtu as u64, 0, 0],
+    );
+}
+
+fn collect_display(inv: &mut Inventory, display: Option<HwDisplay>) {
+    let Some(display) = display else {
+        return;
+    };
+    let gpu = pci_get_primary_gpu();
+    let parent = if gpu.present != 0 {
+        inv.pci_at(hw_pci_bdf(
+            gpu.device.bus,
+            gpu.device.device,
+            gpu.device.function,
+        ))
+    } else {
+        HW_NO_PARENT
+    };
+    let info = display.info;
+    inv.push(
+        HW_CLASS_DISPLAY,
+        parent,
+        0,
+        "fb0",
+        display.driver,
+        [
+            info.width as u64,
+            info.height as u64,
+            info.pitch as u64,
+            (info.bytes_per_pixel() * 8) as u64,
+        ],
+    );
+}
+
+fn collect_input(inv: &mut Inventory) {
+    if keyboard::is_present() {
+        inv.push(
+            HW_CLASS_INPUT,
+            HW_NO_PARENT,
+            0,
+            "kbd0",
+            "ps2-keyboard",
+            [HW_INPUT_KEYBOARD, 0, 0, 0],
+        );
+    }
+    if mouse::is_present() {
+        inv.push(
+            HW_CLASS_INPUT,
+            HW_NO_PARENT,
+            0,
+            "mouse0",
+            "ps2-mouse",
+            [HW_INPUT_POINTER, mouse::device_id() as u64, 0, 0],
+        );
+    }
+}
+
+/// Build the inventory from the current driver state and publish it.
+/// Returns the number of devices recorded.
+pub fn hwinfo_assemble(display: Option<HwDisplay>) -> usize {
+    let mut inv = Inventory::new();
+    collect_cpus(&mut inv);
+    collect_memory(&mut inv);
+    collect_pci(&mut inv);
+    collect_block(&mut inv);
+    collect_net(&mut inv);
+    collect_display(&mut inv, display);
+    collect_input(&mut inv);
+
+    let count = inv.devices().len();
+    if inv.dropped() != 0 {
+        klog_info!("HW: inventory full, {} devices not recorded", inv.dropped());
+    }
+    *INVENTORY.lock() = inv;
+    klog_info!("HW: inventory of {} devices", count);
+    count
+}
+
+/// Number of devices in the published inventory.
+pub fn hwinfo_count() -> usize {
+    INVENTORY.lock().count
+}
+
+/// Inventory entry `index`, or `None` past the end.
+pub fn hwinfo_device(index: usize) -> Option<HwDevice> {
+    INVENTORY.lock().devices().get(index).copied()
+}
@@ -0,0 +1,170 @@
+//! Hardware inventory tests: the boot snapshot agrees with the subsystems
+//! it was assembled from, and the table stays well-formed.
+
+use alloc::vec::Vec;
+
+use slopos_abi::hw::{
+    HW_CLASS_BLOCK, HW_CLASS_CPU, HW_CLASS_MEMORY, HW_CLASS_NET, HW_CLASS_PCI, HW_FLAG_BOUND,
+    HW_FLAG_BSP, HW_MAX_DEVICES, HW_NO_PARENT, HwDevice, hw_pci_bdf,
+};
+use slopos_lib::pcr;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::hwinfo::{Inventory, hwinfo_count, hwinfo_device};
+use crate::pci::{pci_get_device, pci_get_device_count, pci_get_device_driver};
+use crate::{virtio_blk, virtio_net};
+
+fn snapshot() -> Vec<HwDevice> {
+    let mut devices = Vec::new();
+    while let Some(device) = hwinfo_device(devices.len()) {
+        devices.push(device);
+    }
+    devices
+}
+
+fn of_class(devices: &[HwDevice], class: u8) -> impl Iterator<Item = &HwDevice> {
+    devices.iter().filter(move |d| d.class == class)
+}
+
+pub fn test_hwinfo_ids_and_parents_are_consistent() -> TestResult {
+    let devices = snapshot();
+    assert_eq_test!(devices.len(), hwinfo_count());
+    for (index, device) in devices.iter().enumerate() {
+        assert_eq_test!(device.id as usize, index, "id is not the position");
+        assert_test!(
+            device.parent == HW_NO_PARENT || device.parent < device.id,
+            "parent recorded after its child"
+        );
+        assert_eq_test!(
+            device.flags & HW_FLAG_BOUND != 0,
+            !device.driver_str().is_empty(),
+            "bound flag disagrees with driver name"
+        );
+    }
+    pass!()
+}
+
+pub fn test_hwinfo_lists_every_cpu() -> TestResult {
+    let devices = snapshot();
+    let cpus: Vec<&HwDevice> = of_class(&devices, HW_CLASS_CPU).collect();
+    assert_eq_test!(cpus.len(), pcr::get_cpu_count());
+    assert_eq_test!(
+        cpus.iter().filter(|c| c.flags & HW_FLAG_BSP != 0).count(),
+        1,
+        "expected exactly one BSP"
+    );
+    for cpu in cpus {
+        assert_eq_test!(
+            pcr::apic_id_from_cpu_index(cpu.attrs[1] as usize),
+            Some(cpu.attrs[0] as u32),
+            "APIC id mismatch"
+        );
+    }
+    pass!()
+}
+
+pub fn test_hwinfo_memory_node_covers_usable_memory() -> TestResult {
+    let devices = snapshot();
+    let nodes: Vec<&HwDevice> = of_class(&devices, HW_CLASS_MEMORY).collect();
+    assert_eq_test!(nodes.len(), 1, "expected a single memory node");
+    let [base, end, usable, _] = nodes[0].attrs;
+    assert_test!(usable > 0, "no usable memory");
+    assert_test!(end > base && end - base >= usable, "node span too small");
+    pass!()
+}
+
+pub fn test_hwinfo_pci_matches_enumeration() -> TestResult {
+    let devices = snapshot();
+    let pci: Vec<&HwDevice> = of_class(&devices, HW_CLASS_PCI).collect();
+    assert_eq_test!(pci.len(), pci_get_device_count());
+    for (index, entry) in pci.iter().enumerate() {
+        let Some(dev) = pci_get_device(index) else {
+            return fail!("PCI device vanished");
+        };
+        assert_eq_test!(
+            entry.attrs[0],
+            hw_pci_bdf(dev.bus, dev.device, dev.function)
+        );
+        assert_eq_test!(entry.attrs[1] >> 16, dev.vendor_id as u64);
+        assert_eq_test!(
+            entry.driver_str(),
+            pci_get_device_driver(index).unwrap_or("")
+        );
+    }
+    pass!()
+}
+
+pub fn test_hwinfo_devices_link_to_their_pci_function() -> TestResult {
+    let devices = snapshot();
+    for device in of_class(&devices, HW_CLASS_BLOCK).chain(of_class(&devices, HW_CLASS_NET)) {
+        assert_test!(device.parent != HW_NO_PARENT, "device has no PCI parent");
+        let parent = &devices[device.parent as usize];
+        assert_eq_test!(parent.class, HW_CLASS_PCI);
+        assert_eq_test!(parent.driver_str(), device.driver_str());
+    }
+
+    let vda = devices.iter().find(|d| d.name_str() == "vda");
+    assert_eq_test!(vda.is_some(), virtio_blk::virtio_blk_is_ready());
+    if let Some(vda) = vda {
+        assert_eq_test!(vda.attrs[0], virtio_blk::virtio_blk_capacity());
+    }
+    assert_eq_test!(
+        of_class(&devices, HW_CLASS_NET).count(),
+        usize::from(virtio_net::virtio_net_is_ready())
+    );
+    pass!()
+}
+
+pub fn test_hwinfo_push_drops_past_capacity() -> TestResult {
+    let mut inv = Inventory::new();
+    for _ in 0..HW_MAX_DEVICES {
+        assert_test!(inv.push(HW_CLASS_CPU, HW_NO_PARENT, 0, "cpu", "", [0; 4]) != HW_NO_PARENT);
+    }
+    assert_eq_test!(
+        inv.push(HW_CLASS_CPU, HW_NO_PARENT, 0, "extra", "", [0; 4]),
+        HW_NO_PARENT
+    );
+    assert_eq_test!(inv.devices().len(), HW_MAX_DEVICES);
+    assert_eq_test!(inv.dropped(), 1);
+    pass!()
+}
+
+pub fn test_hwinfo_push_truncates_names() -> TestResult {
+    let mut inv = Inventory::new();
+    let id = inv.push(
+        HW_CLASS_PCI,
+        HW_NO_PARENT,
+        0,
+        "a-rather-long-device-name",
+        "some-long-driver-name",
+        [0; 4],
+    );
+    let device = inv.devices()[id as usize];
+    assert_eq_test!(device.name_str(), "a-rather-long-d");
+    assert_eq_test!(device.driver_str(), "some-long-drive");
+    assert_test!(device.flags & HW_FLAG_BOUND != 0);
+    assert_eq_test!(inv.pci_bound_to("some-long-drive"), id);
+    assert_eq_test!(inv.pci_bound_to("virtio-blk"), HW_NO_PARENT);
+    pass!()
+}
+
+pub fn test_hwinfo_device_past_end() -> TestResult {
+    assert_test!(hwinfo_device(hwinfo_count()).is_none());
+    assert_test!(hwinfo_device(usize::MAX).is_none());
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    hwinfo,
+    [
+        test_hwinfo_ids_and_parents_are_consistent,
+        test_hwinfo_lists_every_cpu,
+        test_hwinfo_memory_node_covers_usable_memory,
+        test_hwinfo_pci_matches_enumeration,
+        test_hwinfo_devices_link_to_their_pci_function,
+        test_hwinfo_push_drops_past_capacity,
+        test_hwinfo_push_truncates_names,
+        test_hwinfo_device_past_end,
+    ]
+);
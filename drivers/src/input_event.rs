@@ -7,8 +7,11 @@
 //!
 //! Events are routed to the focused task for each input type.
 
+use slopos_lib::compositor_wake::compositor_wake;
 use slopos_lib::{IrqMutex, RingBuffer};
 
+use crate::random;
+
 /// Monotonic millisecond timestamp for input events.
 ///
 /// Uses the HPET hardware counter for
@@ -20,7 +23,8 @@ pub fn get_timestamp_ms() -> u64 {
 // Re-export ABI types and constants for consumers.
 // All construction and accessor methods live on `InputEvent` in `slopos_abi::input`.
 pub use slopos_abi::{
-    InputEvent, InputEventData, InputEventType, MAX_EVENTS_PER_TASK, MAX_INPUT_TASKS,
+    ClipboardError, InputEvent, InputEventData, InputEventType, MAX_EVENTS_PER_TASK,
+    MAX_INPUT_TASKS,
 };
 
 // =============================================================================
@@ -30,20 +34,26 @@ pub use slopos_abi::{
 struct TaskEventQueue {
     task_id: u32,
     active: bool,
+    /// Key presses go here rather than to the TTY while the task has
+    /// keyboard focus.
+    takes_keys: bool,
     events: RingBuffer<InputEvent, MAX_EVENTS_PER_TASK>,
 }
 
+const EMPTY_EVENT: InputEvent = InputEvent {
+    event_type: InputEventType::KeyPress,
+    _padding: [0; 3],
+    timestamp_ms: 0,
+    data: InputEventData { data0: 0, data1: 0 },
+};
+
 impl TaskEventQueue {
     const fn new() -> Self {
         Self {
             task_id: 0,
             active: false,
-            events: RingBuffer::new_with(InputEvent {
-                event_type: InputEventType::KeyPress,
-                _padding: [0; 3],
-                timestamp_ms: 0,
-                data: InputEventData { data0: 0, data1: 0 },
-            }),
+            takes_keys: false,
+            events: RingBuffer::new_with(EMPTY_EVENT),
         }
     }
 }
@@ -59,6 +69,8 @@ struct InputManager {
     keyboard_focus: u32,
     /// Task ID with pointer focus (0 = no focus)
     pointer_focus: u32,
+    /// Task ID receiving Super+key shortcuts (0 = none, keys go to the TTY)
+    shortcut_focus: u32,
     /// Current pointer position (screen coordinates)
     pointer_x: i32,
     pointer_y: i32,
@@ -68,6 +80,12 @@ struct InputManager {
     /// Pointer events will be translated from screen coords to window-local coords
     window_offset_x: i32,
     window_offset_y: i32,
+    /// Desktop pixels per pixel of the focused window's buffer; local
+    /// coordinates are divided by it
+    window_scale: i32,
+    /// Focus-independent copy of every event (screen coordinates), drained
+    /// by `/dev/input/event0` readers. Oldest events are dropped when full.
+    device_events: RingBuffer<InputEvent, MAX_EVENTS_PER_TASK>,
 }
 
 impl InputManager {
@@ -76,14 +94,25 @@ impl InputManager {
             queues: [const { TaskEventQueue::new() }; MAX_INPUT_TASKS],
             keyboard_focus: 0,
             pointer_focus: 0,
+            shortcut_focus: 0,
             pointer_x: 0,
             pointer_y: 0,
             pointer_buttons: 0,
             window_offset_x: 0,
             window_offset_y: 0,
+            window_scale: 1,
+            device_events: RingBuffer::new_with(EMPTY_EVENT),
         }
     }
 
+    /// Screen point `x, y` in the pointer-focused window's coordinates.
+    fn window_local(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            (x - self.window_offset_x).div_euclid(self.window_scale),
+            (y - self.window_offset_y).div_euclid(self.window_scale),
+        )
+    }
+
     fn find_queue(&self, task_id: u32) -> Option<usize> {
         for (i, queue) in self.queues.iter().enumerate() {
             if queue.active && queue.task_id == task_id {
@@ -102,6 +131,7 @@ impl InputManager {
             if !queue.active {
                 queue.task_id = task_id;
                 queue.active = true;
+                queue.takes_keys = false;
                 queue.events.reset();
                 return Some(i);
             }
@@ -122,19 +152,35 @@ pub fn input_set_keyboard_focus(task_id: u32) {
     INPUT_MANAGER.lock().keyboard_focus = task_id;
 }
 
+/// Have key presses delivered to `task_id`'s queue instead of the TTY while
+/// it has keyboard focus.  Lasts until the task exits.
+pub fn input_take_keys(task_id: u32) {
+    let mut mgr = INPUT_MANAGER.lock();
+    if let Some(idx) = mgr.find_or_create_queue(task_id) {
+        mgr.queues[idx].takes_keys = true;
+    }
+}
+
+/// Set the task that receives Super+key shortcuts (called by compositor)
+pub fn input_set_shortcut_focus(task_id: u32) {
+    INPUT_MANAGER.lock().shortcut_focus = task_id;
+}
+
 /// Set pointer focus to a task (called by compositor)
 /// Also sends enter/leave events. Uses offset (0, 0) for backwards compatibility.
 pub fn input_set_pointer_focus(task_id: u32, timestamp_ms: u64) {
-    input_set_pointer_focus_with_offset(task_id, 0, 0, timestamp_ms);
+    input_set_pointer_focus_with_offset(task_id, 0, 0, 1, timestamp_ms);
 }
 
 /// Set pointer focus to a task with window offset for coordinate translation
 /// The offset is subtracted from screen coordinates to get window-local coordinates.
 /// For a window at screen position (100, 50), pass offset_x=100, offset_y=50.
+/// A window magnified `scale` times gets coordinates in its buffer's pixels.
 pub fn input_set_pointer_focus_with_offset(
     task_id: u32,
     offset_x: i32,
     offset_y: i32,
+    scale: u32,
     timestamp_ms: u64,
 ) {
     let mut mgr = INPUT_MANAGER.lock();
@@ -145,6 +191,7 @@ pub fn input_set_pointer_focus_with_offset(
     // Update window offset for coordinate translation
     mgr.window_offset_x = offset_x;
     mgr.window_offset_y = offset_y;
+    mgr.window_scale = scale.max(1) as i32;
 
     if old_focus == task_id {
         return;
@@ -164,8 +211,7 @@ pub fn input_set_pointer_focus_with_offset(
     // Send enter event to new focus (with new offset - translated coords)
     if task_id != 0 {
         if let Some(idx) = mgr.find_or_create_queue(task_id) {
-            let local_x = x - offset_x;
-            let local_y = y - offset_y;
+            let (local_x, local_y) = mgr.window_local(x, y);
             mgr.queues[idx]
                 .events
                 .push_overwrite(InputEvent::pointer_enter_leave(
@@ -196,6 +242,24 @@ pub fn input_request_close(task_id: u32, timestamp_ms: u64) -> bool {
     }
 }
 
+/// Enqueue a configure event asking a task to resize its window.
+/// Called by compositor syscall path when the user finishes a drag-resize.
+pub fn input_request_configure(task_id: u32, width: u32, height: u32, timestamp_ms: u64) -> bool {
+    if task_id == 0 {
+        return false;
+    }
+
+    let mut mgr = INPUT_MANAGER.lock();
+    if let Some(idx) = mgr.find_or_create_queue(task_id) {
+        mgr.queues[idx]
+            .events
+            .push_overwrite(InputEvent::configure(width, height, timestamp_ms));
+        true
+    } else {
+        false
+    }
+}
+
 /// Get current keyboard focus task ID
 pub fn input_get_keyboard_focus() -> u32 {
     INPUT_MANAGER.lock().keyboard_focus
@@ -223,57 +287,100 @@ pub fn input_get_button_state() -> u8 {
 // Public API - Event Routing (Called from IRQ handlers)
 // =============================================================================
 
-/// Route a keyboard event to the focused task
+/// Route a keyboard event to the focused task if it takes keys (see
+/// [`input_take_keys`]).  Returns false if it does not, in which case the
+/// key goes to the TTY as usual.
 ///
 /// Called from IRQ context (keyboard interrupt handler). IrqMutex handles
 /// interrupt safety automatically.
-pub fn input_route_key_event(scancode: u8, ascii: u8, pressed: bool, timestamp_ms: u64) {
+pub fn input_route_key_event(
+    scancode: u8,
+    ascii: u8,
+    modifiers: u8,
+    pressed: bool,
+    timestamp_ms: u64,
+) -> bool {
     let mut mgr = INPUT_MANAGER.lock();
-    let focus = mgr.keyboard_focus;
+    let event_type = if pressed {
+        InputEventType::KeyPress
+    } else {
+        InputEventType::KeyRelease
+    };
+    let event =
+        InputEvent::key_with_modifiers(event_type, scancode, ascii, modifiers, timestamp_ms);
+    mgr.device_events.push_overwrite(event);
 
+    let focus = mgr.keyboard_focus;
     if focus == 0 {
-        return;
+        return false;
+    }
+    let Some(idx) = mgr.find_queue(focus) else {
+        return false;
+    };
+    if !mgr.queues[idx].takes_keys {
+        return false;
     }
+    mgr.queues[idx].events.push_overwrite(event);
+    true
+}
 
-    if let Some(idx) = mgr.find_or_create_queue(focus) {
-        let event_type = if pressed {
-            InputEventType::KeyPress
-        } else {
-            InputEventType::KeyRelease
-        };
-        mgr.queues[idx].events.push_overwrite(InputEvent::key(
-            event_type,
+/// Route a key pressed with Super held to the shortcut task, waking the
+/// compositor.  Returns false if no task takes shortcuts, in which case the
+/// key is typed as usual.
+///
+/// Called from IRQ context (keyboard interrupt handler).
+pub fn input_route_shortcut(scancode: u8, ascii: u8, timestamp_ms: u64) -> bool {
+    let mut mgr = INPUT_MANAGER.lock();
+    let focus = mgr.shortcut_focus;
+    if focus == 0 {
+        return false;
+    }
+    let Some(idx) = mgr.find_or_create_queue(focus) else {
+        return false;
+    };
+    mgr.queues[idx]
+        .events
+        .push_overwrite(InputEvent::key_with_modifiers(
+            InputEventType::KeyPress,
             scancode,
             ascii,
+            slopos_abi::KEY_MOD_SUPER,
             timestamp_ms,
         ));
-    }
+    drop(mgr);
+    compositor_wake();
+    true
 }
 
 /// Route a pointer motion event to the focused task (called from mouse IRQ).
 /// Coordinates are translated from screen coords to window-local coords.
+/// Wakes the compositor, which draws the cursor.
 pub fn input_route_pointer_motion(x: i32, y: i32, timestamp_ms: u64) {
     let mut mgr = INPUT_MANAGER.lock();
     mgr.pointer_x = x;
     mgr.pointer_y = y;
+    mgr.device_events
+        .push_overwrite(InputEvent::pointer_motion(x, y, timestamp_ms));
 
     let focus = mgr.pointer_focus;
-    if focus == 0 {
-        return;
-    }
+    let (local_x, local_y) = mgr.window_local(x, y);
 
-    let local_x = x - mgr.window_offset_x;
-    let local_y = y - mgr.window_offset_y;
-
-    if let Some(idx) = mgr.find_or_create_queue(focus) {
+    if focus != 0
+        && let Some(idx) = mgr.find_or_create_queue(focus)
+    {
         mgr.queues[idx]
             .events
             .push_overwrite(InputEvent::pointer_motion(local_x, local_y, timestamp_ms));
     }
+    drop(mgr);
+    compositor_wake();
 }
 
 /// Route a pointer button event to the focused task (called from mouse IRQ).
+/// Wakes the compositor, which handles clicks on decorations and the taskbar.
 pub fn input_route_pointer_button(button: u8, pressed: bool, timestamp_ms: u64) {
+    random::random_add_interrupt_timing(button as u64 | (pressed as u64) << 8);
+
     let mut mgr = INPUT_MANAGER.lock();
 
     if pressed {
@@ -281,6 +388,24 @@ pub fn input_route_pointer_button(button: u8, pressed: bool, timestamp_ms: u64)
     } else {
         mgr.pointer_buttons &= !button;
     }
+    let event = InputEvent::pointer_button(pressed, button, timestamp_ms);
+    mgr.device_events.push_overwrite(event);
+
+    let focus = mgr.pointer_focus;
+    if focus != 0
+        && let Some(idx) = mgr.find_or_create_queue(focus)
+    {
+        mgr.queues[idx].events.push_overwrite(event);
+    }
+    drop(mgr);
+    compositor_wake();
+}
+
+/// Route a scroll wheel event to the focused task (called from mouse IRQ).
+pub fn input_route_pointer_scroll(dx: i32, dy: i32, timestamp_ms: u64) {
+    let mut mgr = INPUT_MANAGER.lock();
+    let event = InputEvent::pointer_scroll(dx, dy, timestamp_ms);
+    mgr.device_events.push_overwrite(event);
 
     let focus = mgr.pointer_focus;
     if focus == 0 {
@@ -288,9 +413,7 @@ pub fn input_route_pointer_button(button: u8, pressed: bool, timestamp_ms: u64)
     }
 
     if let Some(idx) = mgr.find_or_create_queue(focus) {
-        mgr.queues[idx]
-            .events
-            .push_overwrite(InputEvent::pointer_button(pressed, button, timestamp_ms));
+        mgr.queues[idx].events.push_overwrite(event);
     }
 }
 
@@ -323,8 +446,8 @@ pub fn input_poll(task_id: u32) -> Option<InputEvent> {
 ///
 /// # Safety
 /// Caller must ensure out_buffer points to valid memory for max_count InputEvents.
-pub fn input_drain_batch(task_id: u32, out_buffer: *mut InputEvent, max_count: usize) -> usize {
-    if out_buffer.is_null() || max_count == 0 {
+pub fn input_drain_batch(task_id: u32, dst: &mut [InputEvent]) -> usize {
+    if dst.is_empty() {
         return 0;
     }
 
@@ -335,11 +458,9 @@ pub fn input_drain_batch(task_id: u32, out_buffer: *mut InputEvent, max_count: u
     };
 
     let mut count = 0;
-    while count < max_count {
+    while count < dst.len() {
         if let Some(event) = mgr.queues[idx].events.try_pop() {
-            unsafe {
-                out_buffer.add(count).write(event);
-            }
+            dst[count] = event;
             count += 1;
         } else {
             break;
@@ -348,6 +469,22 @@ pub fn input_drain_batch(task_id: u32, out_buffer: *mut InputEvent, max_count: u
     count
 }
 
+/// Drain raw device events (independent of focus) into `dst`.
+///
+/// Backs `/dev/input/event0`. Non-blocking: returns 0 when nothing is queued.
+pub fn input_device_read(dst: &mut [InputEvent]) -> usize {
+    let mut mgr = INPUT_MANAGER.lock();
+    let mut count = 0;
+    while count < dst.len() {
+        let Some(event) = mgr.device_events.try_pop() else {
+            break;
+        };
+        dst[count] = event;
+        count += 1;
+    }
+    count
+}
+
 /// Peek at the next input event without removing it
 pub fn input_peek(task_id: u32) -> Option<InputEvent> {
     let mgr = INPUT_MANAGER.lock();
@@ -378,7 +515,14 @@ pub fn input_event_count(task_id: u32) -> u32 {
     }
 }
 
+// =============================================================================
+// Clipboard
+// =============================================================================
+
+/// One MIME-typed buffer shared by every task.  The last `set` wins.
 struct ClipboardState {
+    mime: [u8; slopos_abi::CLIPBOARD_MIME_MAX],
+    mime_len: usize,
     data: [u8; slopos_abi::CLIPBOARD_MAX_SIZE],
     len: usize,
 }
@@ -386,31 +530,75 @@ struct ClipboardState {
 impl ClipboardState {
     const fn new() -> Self {
         Self {
+            mime: [0u8; slopos_abi::CLIPBOARD_MIME_MAX],
+            mime_len: 0,
             data: [0u8; slopos_abi::CLIPBOARD_MAX_SIZE],
             len: 0,
         }
     }
+
+    fn holds(&self, mime: &[u8]) -> bool {
+        self.mime_len != 0 && mime_essence_eq(&self.mime[..self.mime_len], mime)
+    }
+}
+
+/// Compare two MIME types by their `type/subtype`, ignoring case and any
+/// parameters, so `text/plain;charset=utf-8` matches `TEXT/Plain`.
+fn mime_essence_eq(a: &[u8], b: &[u8]) -> bool {
+    let essence = |m: &[u8]| -> usize { m.iter().position(|&c| c == b';').unwrap_or(m.len()) };
+    let (a, b) = (&a[..essence(a)], &b[..essence(b)]);
+    a.trim_ascii().eq_ignore_ascii_case(b.trim_ascii())
 }
 
 static CLIPBOARD: IrqMutex<ClipboardState> = IrqMutex::new(ClipboardState::new());
 
-pub fn clipboard_copy(src: &[u8]) -> usize {
+/// Replace the clipboard with `data` of type `mime`.  Returns the bytes stored.
+pub fn clipboard_set(mime: &[u8], data: &[u8]) -> Result<usize, ClipboardError> {
+    if !slopos_abi::clipboard_mime_valid(mime) {
+        return Err(ClipboardError::BadMime);
+    }
+    if data.len() > slopos_abi::CLIPBOARD_MAX_SIZE {
+        return Err(ClipboardError::TooLarge);
+    }
     let mut clip = CLIPBOARD.lock();
-    let copy_len = src.len().min(slopos_abi::CLIPBOARD_MAX_SIZE);
-    clip.data[..copy_len].copy_from_slice(&src[..copy_len]);
-    clip.len = copy_len;
-    copy_len
+    clip.mime[..mime.len()].copy_from_slice(mime);
+    clip.mime_len = mime.len();
+    clip.data[..data.len()].copy_from_slice(data);
+    clip.len = data.len();
+    Ok(data.len())
 }
 
-pub fn clipboard_paste(dst: &mut [u8]) -> usize {
+/// Copy the clipboard into `dst` if it holds `mime`.  Returns the full data
+/// length, which may be larger than `dst`.
+pub fn clipboard_get(mime: &[u8], dst: &mut [u8]) -> Result<usize, ClipboardError> {
     let clip = CLIPBOARD.lock();
-    if clip.len == 0 {
-        return 0;
+    if !clip.holds(mime) {
+        return Err(ClipboardError::NoMatch);
     }
-
     let copy_len = clip.len.min(dst.len());
     dst[..copy_len].copy_from_slice(&clip.data[..copy_len]);
-    copy_len
+    Ok(clip.len)
+}
+
+/// Empty the clipboard.
+pub fn clipboard_clear() {
+    let mut clip = CLIPBOARD.lock();
+    clip.mime_len = 0;
+    clip.len = 0;
+}
+
+/// Put plain text on the clipboard, truncated to `CLIPBOARD_MAX_SIZE`.
+pub fn clipboard_copy(src: &[u8]) -> usize {
+    let copy_len = src.len().min(slopos_abi::CLIPBOARD_MAX_SIZE);
+    clipboard_set(slopos_abi::CLIPBOARD_MIME_TEXT, &src[..copy_len]).unwrap_or(0)
+}
+
+/// Read plain text from the clipboard; 0 when it is empty or holds another type.
+pub fn clipboard_paste(dst: &mut [u8]) -> usize {
+    match clipboard_get(slopos_abi::CLIPBOARD_MIME_TEXT, dst) {
+        Ok(len) => len.min(dst.len()),
+        Err(_) => 0,
+    }
 }
 
 // =============================================================================
@@ -428,10 +616,14 @@ pub fn input_cleanup_task(task_id: u32) {
     if mgr.pointer_focus == task_id {
         mgr.pointer_focus = 0;
     }
+    if mgr.shortcut_focus == task_id {
+        mgr.shortcut_focus = 0;
+    }
 
     if let Some(idx) = mgr.find_queue(task_id) {
         mgr.queues[idx].active = false;
         mgr.queues[idx].task_id = 0;
+        mgr.queues[idx].takes_keys = false;
         mgr.queues[idx].events.reset();
     }
 }
@@ -9,9 +9,10 @@ use slopos_lib::kernel_services::driver_runtime::{
     IRQ_LINES, LEGACY_IRQ_COM1, LEGACY_IRQ_KEYBOARD, LEGACY_IRQ_MOUSE,
     irq_increment_keyboard_events, irq_init, irq_is_masked, irq_register_handler, irq_set_route,
 };
+use slopos_lib::ports::COM1;
 use slopos_lib::{InterruptFrame, cpu, klog_info};
 
-use crate::{apic, ioapic, ps2};
+use crate::{apic, ioapic, ps2, serial, tty};
 
 // PIT timer IRQ handler and fallback have been removed.
 // Scheduler preemption is driven exclusively by the per-CPU LAPIC timer
@@ -43,6 +44,13 @@ extern "C" fn ps2_irq_handler(_irq: u8, _frame: *mut InterruptFrame, _ctx: *mut
     }
 }
 
+extern "C" fn com1_irq_handler(_irq: u8, _frame: *mut InterruptFrame, _ctx: *mut c_void) {
+    // Emptying the receive FIFO is what deasserts the interrupt, so do it
+    // even if the TTY table is not up yet; the bytes wait in INPUT_BUFFER.
+    serial::serial_poll_receive(COM1.address());
+    tty::service_hw_input(tty::SERIAL_CONSOLE_TTY);
+}
+
 fn program_ioapic_route(irq_line: u8) {
     if irq_line as usize >= IRQ_LINES {
         return;
@@ -139,6 +147,13 @@ pub fn init() {
         core::ptr::null_mut(),
         core::ptr::null(),
     );
+    let _ = irq_register_handler(
+        LEGACY_IRQ_COM1,
+        Some(com1_irq_handler),
+        core::ptr::null_mut(),
+        core::ptr::null(),
+    );
+    serial::serial_enable_rx_interrupt();
 
     cpu::enable_interrupts();
 }
@@ -6,6 +6,11 @@ extern crate alloc;
 pub mod apic;
 #[cfg(feature = "itests")]
 pub mod apic_timer_tests;
+pub mod audio;
+#[cfg(feature = "itests")]
+pub mod audio_tests;
+#[cfg(feature = "itests")]
+pub mod clipboard_tests;
 #[cfg(feature = "itests")]
 pub mod dns_tests;
 #[cfg(feature = "itests")]
@@ -13,6 +18,9 @@ pub mod ecam_tests;
 pub mod hpet;
 #[cfg(feature = "itests")]
 pub mod hpet_tests;
+pub mod hwinfo;
+#[cfg(feature = "itests")]
+pub mod hwinfo_tests;
 #[cfg(feature = "itests")]
 pub mod ingress_tests;
 pub mod input_event;
@@ -37,6 +45,9 @@ pub mod net_types_tests;
 pub mod netdev_tests;
 #[cfg(feature = "itests")]
 pub mod netstack_tests;
+pub mod nvme;
+#[cfg(feature = "itests")]
+pub mod nvme_tests;
 #[cfg(feature = "itests")]
 pub mod packetbuf_tests;
 pub mod pci;
@@ -46,9 +57,18 @@ pub mod pci_defs;
 pub mod pic;
 pub mod pit;
 pub mod ps2;
+#[cfg(feature = "itests")]
+pub mod ps2_keymap_tests;
+#[cfg(feature = "itests")]
+pub mod ps2_mouse_tests;
 pub mod random;
 #[cfg(feature = "itests")]
+pub mod random_tests;
+#[cfg(feature = "itests")]
 pub mod route_tests;
+pub mod rtc;
+#[cfg(feature = "itests")]
+pub mod rtc_tests;
 pub mod serial;
 #[cfg(feature = "itests")]
 pub mod socket_tests;
@@ -56,6 +76,8 @@ pub mod syscall_services_init;
 #[cfg(feature = "itests")]
 pub mod tcp_data_tests;
 #[cfg(feature = "itests")]
+pub mod tcp_recovery_tests;
+#[cfg(feature = "itests")]
 pub mod tcp_tests;
 #[cfg(feature = "itests")]
 pub mod timer_tests;
@@ -64,10 +86,16 @@ pub mod tty;
 pub mod tty_tests;
 #[cfg(feature = "itests")]
 pub mod udp_socket_tests;
+pub mod usb;
+#[cfg(feature = "itests")]
+pub mod usb_tests;
 pub mod virtio;
 pub mod virtio_blk;
 #[cfg(feature = "itests")]
 pub mod virtio_completion_tests;
+pub mod virtio_gpu;
+#[cfg(feature = "itests")]
+pub mod virtio_gpu_tests;
 #[cfg(feature = "itests")]
 pub mod virtio_msix_tests;
 pub mod virtio_net;
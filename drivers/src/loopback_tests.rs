@@ -4,18 +4,32 @@
 //! - 3.T6: Loopback device tx/poll_rx delivery without VirtIO
 //! - 3.T7: DHCP lease (NetStack::configure) populates route table correctly
 //! - 3.T8: IfaceConfig readable via NetStack after configure
+//! - Local delivery: UDP and TCP between two sockets over `127.0.0.1`
+//! - Listen backlog: SYN admission, accept order and peers, and resetting
+//!   connections a closed listener never accepted
 
 extern crate alloc;
 
+use slopos_abi::net::{
+    AF_INET, NET_SOCK_BOUND, NET_SOCK_ESTABLISHED, NET_SOCK_LISTEN, NET_SOCK_PROTO_TCP,
+    NET_SOCK_PROTO_UDP, NetSocketStat, SOCK_DGRAM, SOCK_STREAM,
+};
 use slopos_lib::testing::TestResult;
-use slopos_lib::{assert_eq_test, assert_test, pass};
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
 
-use crate::net::loopback::LoopbackDev;
+use crate::net::loopback::{LoopbackDev, is_local_destination, loopback_flush};
 use crate::net::netdev::{NetDevice, NetDeviceFeatures};
+use crate::net::netstack::NET_STACK;
 use crate::net::netstack::NetStack;
 use crate::net::packetbuf::PacketBuf;
 use crate::net::pool::PACKET_POOL;
 use crate::net::route::RouteTable;
+use crate::net::socket::{
+    socket_accept, socket_bind, socket_close, socket_connect, socket_create, socket_listen,
+    socket_lookup_tcp_idx, socket_recv, socket_recvfrom, socket_reset_all, socket_send,
+    socket_sendto, socket_set_nonblocking, socket_stat,
+};
+use crate::net::tcp::{self, TcpState, TcpTuple};
 use crate::net::types::{DevIndex, Ipv4Addr};
 
 // =============================================================================
@@ -398,6 +412,367 @@ pub fn test_iface_config_multiple_interfaces() -> TestResult {
     pass!()
 }
 
+// =============================================================================
+// Local delivery between sockets
+// =============================================================================
+
+const LO_UDP_PORT: u16 = 47011;
+const LO_TCP_PORT: u16 = 47012;
+const LO_STAT_UDP_PORT: u16 = 47013;
+const LO_STAT_TCP_PORT: u16 = 47014;
+const LO_BACKLOG_TCP_PORT: u16 = 47015;
+const LO_ORPHAN_TCP_PORT: u16 = 47016;
+
+/// Clear the socket tables and drop whatever earlier tests left on `lo`.
+fn reset_local() {
+    socket_reset_all();
+    loopback_flush();
+}
+
+pub fn test_loopback_local_destinations() -> TestResult {
+    assert_test!(is_local_destination(Ipv4Addr::LOCALHOST));
+    assert_test!(
+        is_local_destination(Ipv4Addr([127, 1, 2, 3])),
+        "all of 127/8"
+    );
+    assert_test!(!is_local_destination(Ipv4Addr([8, 8, 8, 8])));
+    if let Some(ip) = NET_STACK.first_ipv4() {
+        assert_test!(!ip.is_loopback(), "first_ipv4 picked lo");
+        assert_test!(is_local_destination(ip), "own NIC address");
+    }
+    assert_eq_test!(
+        NET_STACK.source_addr_for(Ipv4Addr([127, 0, 0, 9])),
+        Ipv4Addr::LOCALHOST,
+        "source for loopback"
+    );
+    pass!()
+}
+
+pub fn test_loopback_udp_between_sockets() -> TestResult {
+    reset_local();
+    let server = socket_create(AF_INET, SOCK_DGRAM, 0);
+    let client = socket_create(AF_INET, SOCK_DGRAM, 0);
+    if server < 0 || client < 0 {
+        return fail!("socket_create failed");
+    }
+    let (server, client) = (server as u32, client as u32);
+    assert_eq_test!(socket_bind(server, Ipv4Addr::LOCALHOST.0, LO_UDP_PORT), 0);
+
+    let payload = b"hello lo";
+    let sent = socket_sendto(
+        client,
+        payload.as_ptr(),
+        payload.len(),
+        Ipv4Addr::LOCALHOST.0,
+        LO_UDP_PORT,
+    );
+    assert_eq_test!(sent, payload.len() as i64, "sendto");
+    assert_eq_test!(loopback_flush(), 1, "datagram not queued on lo");
+
+    let mut out = [0u8; 16];
+    let mut src_ip = [0u8; 4];
+    let mut src_port = 0u16;
+    let got = socket_recvfrom(
+        server,
+        out.as_mut_ptr(),
+        out.len(),
+        &mut src_ip as *mut [u8; 4],
+        &mut src_port as *mut u16,
+    );
+    assert_eq_test!(got, payload.len() as i64, "recvfrom");
+    assert_eq_test!(&out[..payload.len()], payload);
+    assert_eq_test!(src_ip, Ipv4Addr::LOCALHOST.0, "source address");
+    assert_test!(src_port != 0, "client not auto-bound");
+
+    socket_close(client);
+    socket_close(server);
+    pass!()
+}
+
+pub fn test_loopback_tcp_connect_accept_send() -> TestResult {
+    reset_local();
+    let server = socket_create(AF_INET, SOCK_STREAM, 0);
+    let client = socket_create(AF_INET, SOCK_STREAM, 0);
+    if server < 0 || client < 0 {
+        return fail!("socket_create failed");
+    }
+    let (server, client) = (server as u32, client as u32);
+    assert_eq_test!(socket_bind(server, [0; 4], LO_TCP_PORT), 0);
+    assert_eq_test!(socket_listen(server, 4), 0);
+    socket_set_nonblocking(server, true);
+
+    // SYN, SYN-ACK and ACK all cross lo in one flush.
+    assert_eq_test!(
+        socket_connect(client, Ipv4Addr::LOCALHOST.0, LO_TCP_PORT),
+        0
+    );
+    assert_test!(loopback_flush() >= 3, "handshake not delivered");
+    let Some(client_tcp) = socket_lookup_tcp_idx(client) else {
+        return fail!("client has no connection");
+    };
+    assert_eq_test!(
+        tcp::tcp_get_state(client_tcp),
+        Some(TcpState::Established),
+        "client state"
+    );
+
+    let mut peer_ip = [0u8; 4];
+    let mut peer_port = 0u16;
+    let conn = socket_accept(server, &mut peer_ip, &mut peer_port);
+    if conn < 0 {
+        return fail!("accept failed: {}", conn);
+    }
+    let conn = conn as u32;
+    assert_eq_test!(peer_ip, Ipv4Addr::LOCALHOST.0, "peer address");
+    socket_set_nonblocking(conn, true);
+
+    let payload = b"ping";
+    assert_eq_test!(
+        socket_send(client, payload.as_ptr(), payload.len()),
+        payload.len() as i64
+    );
+    assert_test!(loopback_flush() >= 1, "data not delivered");
+    let mut out = [0u8; 8];
+    assert_eq_test!(
+        socket_recv(conn, out.as_mut_ptr(), out.len()),
+        payload.len() as i64
+    );
+    assert_eq_test!(&out[..payload.len()], payload);
+
+    socket_close(conn);
+    socket_close(client);
+    socket_close(server);
+    reset_local();
+    pass!()
+}
+
+/// The local port of a TCP socket's connection.
+fn tcp_local_port(sock: u32) -> Option<u16> {
+    let idx = socket_lookup_tcp_idx(sock)?;
+    Some(tcp::tcp_get_connection(idx)?.tuple.local_port)
+}
+
+pub fn test_loopback_tcp_backlog_and_accept() -> TestResult {
+    reset_local();
+    let server = socket_create(AF_INET, SOCK_STREAM, 0);
+    if server < 0 {
+        return fail!("socket_create failed");
+    }
+    let server = server as u32;
+    assert_eq_test!(socket_bind(server, [0; 4], LO_BACKLOG_TCP_PORT), 0);
+    assert_eq_test!(socket_listen(server, 2), 0);
+    socket_set_nonblocking(server, true);
+
+    let mut clients = [0u32; 3];
+    for client in clients.iter_mut() {
+        let sock = socket_create(AF_INET, SOCK_STREAM, 0);
+        if sock < 0 {
+            return fail!("socket_create failed");
+        }
+        *client = sock as u32;
+        assert_eq_test!(
+            socket_connect(*client, Ipv4Addr::LOCALHOST.0, LO_BACKLOG_TCP_PORT),
+            0
+        );
+    }
+    loopback_flush();
+
+    // The third SYN found the backlog full and was dropped.
+    let state = |sock| socket_lookup_tcp_idx(sock).and_then(tcp::tcp_get_state);
+    assert_eq_test!(state(clients[0]), Some(TcpState::Established));
+    assert_eq_test!(state(clients[1]), Some(TcpState::Established));
+    assert_eq_test!(state(clients[2]), Some(TcpState::SynSent), "third client");
+    assert_eq_test!(stat_of(server).map(|stat| stat.recv_queue), Some(2));
+
+    // Accept hands back connections in order, with their peers.
+    for &client in &clients[..2] {
+        let mut peer_ip = [0u8; 4];
+        let mut peer_port = 0u16;
+        let conn = socket_accept(server, &mut peer_ip, &mut peer_port);
+        if conn < 0 {
+            return fail!("accept failed: {}", conn);
+        }
+        assert_eq_test!(peer_ip, Ipv4Addr::LOCALHOST.0, "peer address");
+        assert_eq_test!(Some(peer_port), tcp_local_port(client), "peer port");
+        socket_close(conn as u32);
+    }
+    assert_test!(
+        socket_accept(server, core::ptr::null_mut(), core::ptr::null_mut()) < 0,
+        "accept queue not empty"
+    );
+
+    for client in clients {
+        socket_close(client);
+    }
+    socket_close(server);
+    reset_local();
+    pass!()
+}
+
+pub fn test_loopback_tcp_listener_close_resets_unaccepted() -> TestResult {
+    reset_local();
+    let server = socket_create(AF_INET, SOCK_STREAM, 0);
+    let client = socket_create(AF_INET, SOCK_STREAM, 0);
+    if server < 0 || client < 0 {
+        return fail!("socket_create failed");
+    }
+    let (server, client) = (server as u32, client as u32);
+    assert_eq_test!(socket_bind(server, [0; 4], LO_ORPHAN_TCP_PORT), 0);
+    assert_eq_test!(socket_listen(server, 4), 0);
+    // Listening again only changes the backlog.
+    assert_eq_test!(socket_listen(server, 1), 0);
+    assert_eq_test!(stat_of(server).map(|stat| stat.backlog), Some(1));
+
+    assert_eq_test!(
+        socket_connect(client, Ipv4Addr::LOCALHOST.0, LO_ORPHAN_TCP_PORT),
+        0
+    );
+    loopback_flush();
+    let Some(client_tcp) = socket_lookup_tcp_idx(client) else {
+        return fail!("client has no connection");
+    };
+    let Some(client_conn) = tcp::tcp_get_connection(client_tcp) else {
+        return fail!("client connection missing");
+    };
+    assert_eq_test!(client_conn.state, TcpState::Established);
+    let child = TcpTuple {
+        local_ip: client_conn.tuple.remote_ip,
+        local_port: client_conn.tuple.remote_port,
+        remote_ip: client_conn.tuple.local_ip,
+        remote_port: client_conn.tuple.local_port,
+    };
+    assert_test!(tcp::tcp_find(&child).is_some(), "no server-side child");
+
+    // The connection nobody accepted is reset, not left behind.
+    socket_close(server);
+    loopback_flush();
+    assert_test!(tcp::tcp_find(&child).is_none(), "child left behind");
+    assert_eq_test!(tcp::tcp_get_state(client_tcp), None, "client not reset");
+
+    socket_close(client);
+    reset_local();
+    pass!()
+}
+
+/// The `SYSCALL_NET_STAT` record for one socket.
+fn stat_of(sock: u32) -> Option<NetSocketStat> {
+    socket_stat(sock as usize).filter(|stat| stat.index == sock)
+}
+
+pub fn test_socket_stat_udp_queue_and_counters() -> TestResult {
+    reset_local();
+    let server = socket_create(AF_INET, SOCK_DGRAM, 0);
+    let client = socket_create(AF_INET, SOCK_DGRAM, 0);
+    if server < 0 || client < 0 {
+        return fail!("socket_create failed");
+    }
+    let (server, client) = (server as u32, client as u32);
+    assert_eq_test!(
+        socket_bind(server, Ipv4Addr::LOCALHOST.0, LO_STAT_UDP_PORT),
+        0
+    );
+
+    let payload = b"queued";
+    for _ in 0..2 {
+        socket_sendto(
+            client,
+            payload.as_ptr(),
+            payload.len(),
+            Ipv4Addr::LOCALHOST.0,
+            LO_STAT_UDP_PORT,
+        );
+    }
+    loopback_flush();
+
+    let Some(stat) = stat_of(server) else {
+        return fail!("server socket not listed");
+    };
+    assert_eq_test!(stat.proto, NET_SOCK_PROTO_UDP);
+    assert_eq_test!(stat.state, NET_SOCK_BOUND);
+    assert_eq_test!(stat.local_addr, Ipv4Addr::LOCALHOST.0);
+    assert_eq_test!(stat.local_port, LO_STAT_UDP_PORT);
+    assert_eq_test!(stat.recv_queue, 2 * payload.len() as u32, "queued bytes");
+    assert_eq_test!(stat.rx_bytes, 0);
+    let sent = stat_of(client).map(|stat| stat.tx_bytes);
+    assert_eq_test!(sent, Some(2 * payload.len() as u64), "client tx_bytes");
+
+    let mut out = [0u8; 16];
+    socket_recv(server, out.as_mut_ptr(), out.len());
+    let stat = stat_of(server).unwrap_or_default();
+    assert_eq_test!(stat.recv_queue, payload.len() as u32, "after recv");
+    assert_eq_test!(stat.rx_bytes, payload.len() as u64);
+
+    socket_close(client);
+    socket_close(server);
+    assert_test!(stat_of(server).is_none(), "closed socket still listed");
+    pass!()
+}
+
+pub fn test_socket_stat_tcp_listener_and_connection() -> TestResult {
+    reset_local();
+    let server = socket_create(AF_INET, SOCK_STREAM, 0);
+    let client = socket_create(AF_INET, SOCK_STREAM, 0);
+    if server < 0 || client < 0 {
+        return fail!("socket_create failed");
+    }
+    let (server, client) = (server as u32, client as u32);
+    assert_eq_test!(socket_bind(server, [0; 4], LO_STAT_TCP_PORT), 0);
+    assert_eq_test!(socket_listen(server, 4), 0);
+    socket_set_nonblocking(server, true);
+    assert_eq_test!(
+        socket_connect(client, Ipv4Addr::LOCALHOST.0, LO_STAT_TCP_PORT),
+        0
+    );
+    loopback_flush();
+
+    let Some(listener) = stat_of(server) else {
+        return fail!("listener not listed");
+    };
+    assert_eq_test!(listener.proto, NET_SOCK_PROTO_TCP);
+    assert_eq_test!(listener.state, NET_SOCK_LISTEN);
+    assert_eq_test!(listener.backlog, 4);
+    assert_eq_test!(listener.recv_queue, 1, "connection awaiting accept");
+
+    let Some(conn_stat) = stat_of(client) else {
+        return fail!("client not listed");
+    };
+    assert_eq_test!(conn_stat.state, NET_SOCK_ESTABLISHED);
+    assert_eq_test!(conn_stat.remote_addr, Ipv4Addr::LOCALHOST.0);
+    assert_eq_test!(conn_stat.remote_port, LO_STAT_TCP_PORT);
+
+    let conn = socket_accept(server, core::ptr::null_mut(), core::ptr::null_mut());
+    if conn < 0 {
+        return fail!("accept failed: {}", conn);
+    }
+    let conn = conn as u32;
+    socket_set_nonblocking(conn, true);
+    assert_eq_test!(stat_of(server).map(|stat| stat.recv_queue), Some(0));
+
+    let payload = b"netstat";
+    socket_send(client, payload.as_ptr(), payload.len());
+    loopback_flush();
+    let accepted = stat_of(conn).unwrap_or_default();
+    assert_eq_test!(accepted.state, NET_SOCK_ESTABLISHED);
+    assert_eq_test!(accepted.local_port, LO_STAT_TCP_PORT);
+    assert_eq_test!(accepted.recv_queue, payload.len() as u32, "unread data");
+    assert_eq_test!(
+        stat_of(client).map(|stat| stat.tx_bytes),
+        Some(payload.len() as u64)
+    );
+
+    let mut out = [0u8; 16];
+    socket_recv(conn, out.as_mut_ptr(), out.len());
+    let accepted = stat_of(conn).unwrap_or_default();
+    assert_eq_test!(accepted.recv_queue, 0);
+    assert_eq_test!(accepted.rx_bytes, payload.len() as u64);
+
+    socket_close(conn);
+    socket_close(client);
+    socket_close(server);
+    reset_local();
+    pass!()
+}
+
 // =============================================================================
 // Test suite registration
 // =============================================================================
@@ -417,5 +792,14 @@ slopos_lib::define_test_suite!(
         // 3.T8 — ifconfig reads IfaceConfig
         test_iface_config_readable,
         test_iface_config_multiple_interfaces,
+        // Local delivery
+        test_loopback_local_destinations,
+        test_loopback_udp_between_sockets,
+        test_loopback_tcp_connect_accept_send,
+        test_loopback_tcp_backlog_and_accept,
+        test_loopback_tcp_listener_close_resets_unaccepted,
+        // Socket listing
+        test_socket_stat_udp_queue_and_counters,
+        test_socket_stat_tcp_listener_and_connection,
     ]
 );
@@ -8,18 +8,22 @@
 //!
 //! ## Usage
 //!
+//! Drivers go through an [`MsixDomain`], which owns the mapped table and the
+//! IDT vectors bound to its entries:
+//!
 //! ```ignore
-//! use slopos_core::irq::{msi_alloc_vector, msi_register_handler};
-//! use slopos_drivers::msix;
+//! use slopos_drivers::msix::MsixDomain;
 //!
-//! let cap = msix::msix_read_capability(bus, dev, func, cap_offset);
-//! let table = msix::msix_map_table(&device_info, &cap).expect("MSI-X map failed");
-//! let vector = msi_alloc_vector().expect("MSI vector exhausted");
-//! msi_register_handler(vector, my_handler, ctx, bdf);
-//! msix::msix_configure(&table, 0, vector, apic_id).unwrap();
-//! msix::msix_enable(bus, dev, func, &cap);
+//! let mut domain = MsixDomain::open(&device_info)?;
+//! domain.alloc_entries(num_queues)?;          // entries 0..n, aimed at the BSP
+//! domain.register_handler(0, my_handler, ctx)?;
+//! domain.enable();
+//! domain.retarget(1, other_apic_id)?;          // move entry 1 to another CPU
 //! ```
 //!
+//! The free functions below are the raw register interface the domain is
+//! built on.
+//!
 //! ## Register layout reference (PCI Local Bus Spec §6.8.2)
 //!
 //! ```text
@@ -37,10 +41,13 @@
 //! +0x0C   32    Vector Control (bit 0 = mask)
 //! ```
 
+use core::ffi::c_void;
+
 use crate::pci::{PciDeviceInfo, pci_config_read16, pci_config_read32, pci_config_write16};
 use crate::pci_defs::{PCI_COMMAND_INTX_DISABLE, PCI_COMMAND_OFFSET, PCI_MAX_BARS};
 use slopos_abi::addr::PhysAddr;
-use slopos_lib::klog_info;
+use slopos_core::irq::{self, MsiHandler};
+use slopos_lib::{IrqMutex, klog_debug, klog_info};
 use slopos_mm::mmio::MmioRegion;
 
 // =============================================================================
@@ -246,6 +253,12 @@ pub enum MsixError {
     MappingFailed,
     /// The MSI-X table has not been mapped yet.
     TableNotMapped,
+    /// The device has no MSI-X capability.
+    NoCapability,
+    /// The shared MSI vector range has no room left.
+    VectorsExhausted,
+    /// The table entry has no vector bound to it.
+    EntryNotAllocated,
 }
 
 // =============================================================================
@@ -532,3 +545,197 @@ pub fn msix_clear_function_mask(bus: u8, dev: u8, func: u8, cap: &MsixCapability
 pub fn msix_refresh_control(bus: u8, dev: u8, func: u8, cap: &mut MsixCapability) {
     cap.control = pci_config_read16(bus, dev, func, cap.cap_offset + MSIX_REG_CONTROL);
 }
+
+// =============================================================================
+// Per-device interrupt domain
+// =============================================================================
+
+/// Table entries a single [`MsixDomain`] can bind vectors to.
+pub const MSIX_DOMAIN_MAX_ENTRIES: usize = 16;
+
+/// Devices whose MSI-X domains can be enabled at once.
+const MSIX_MAX_DOMAINS: usize = 16;
+
+/// One device's MSI-X table and the IDT vectors bound to its entries.
+///
+/// Vectors come from the shared MSI range in `slopos_core::irq`, which also
+/// records each vector's owner and target CPU.  The domain is `Copy` so
+/// drivers can keep it in their state struct; only one copy should call
+/// [`release`](Self::release).
+#[derive(Debug, Clone, Copy)]
+pub struct MsixDomain {
+    bus: u8,
+    device: u8,
+    function: u8,
+    cap: MsixCapability,
+    table: MsixTable,
+    /// Vector bound to each table entry (0 = none).
+    vectors: [u8; MSIX_DOMAIN_MAX_ENTRIES],
+}
+
+/// Enabled domains, for diagnostics and for finding a device's vectors.
+static MSIX_DOMAINS: IrqMutex<[Option<MsixDomain>; MSIX_MAX_DOMAINS]> =
+    IrqMutex::new([None; MSIX_MAX_DOMAINS]);
+
+impl MsixDomain {
+    /// Parse the MSI-X capability of `info` and map its table.  No vectors
+    /// are allocated and MSI-X stays disabled.
+    pub fn open(info: &PciDeviceInfo) -> Result<Self, MsixError> {
+        let cap_offset = info.msix_cap_offset.ok_or(MsixError::NoCapability)?;
+        let cap = msix_read_capability(info.bus, info.device, info.function, cap_offset);
+        let table = msix_map_table(info, &cap)?;
+        Ok(Self {
+            bus: info.bus,
+            device: info.device,
+            function: info.function,
+            cap,
+            table,
+            vectors: [0; MSIX_DOMAIN_MAX_ENTRIES],
+        })
+    }
+
+    /// `(bus << 16) | (dev << 8) | func`, as recorded against each vector.
+    #[inline]
+    pub const fn bdf(&self) -> u32 {
+        ((self.bus as u32) << 16) | ((self.device as u32) << 8) | (self.function as u32)
+    }
+
+    #[inline]
+    pub const fn capability(&self) -> &MsixCapability {
+        &self.cap
+    }
+
+    #[inline]
+    pub const fn table(&self) -> &MsixTable {
+        &self.table
+    }
+
+    /// Entries usable through this domain: the table size, capped at
+    /// [`MSIX_DOMAIN_MAX_ENTRIES`].
+    #[inline]
+    pub fn entry_limit(&self) -> u16 {
+        self.cap.table_size.min(MSIX_DOMAIN_MAX_ENTRIES as u16)
+    }
+
+    /// IDT vector bound to `entry`, if any.
+    #[inline]
+    pub fn vector(&self, entry: u16) -> Option<u8> {
+        self.vectors
+            .get(entry as usize)
+            .copied()
+            .filter(|&v| v != 0)
+    }
+
+    /// Bind a fresh vector to `entry` and program it to `apic_id`.
+    pub fn alloc_entry(&mut self, entry: u16, apic_id: u8) -> Result<u8, MsixError> {
+        if entry >= self.entry_limit() || self.vectors[entry as usize] != 0 {
+            return Err(MsixError::InvalidEntry);
+        }
+        let vector = irq::msi_alloc_vector().ok_or(MsixError::VectorsExhausted)?;
+        if let Err(err) = msix_configure(&self.table, entry, vector, apic_id) {
+            irq::msi_free_vector(vector);
+            return Err(err);
+        }
+        irq::msi_set_affinity(vector, apic_id);
+        self.vectors[entry as usize] = vector;
+        Ok(vector)
+    }
+
+    /// Bind vectors to entries `0..count`, all aimed at the BSP.  Either
+    /// every entry gets a vector or none does.
+    pub fn alloc_entries(&mut self, count: u16) -> Result<(), MsixError> {
+        if count > self.entry_limit() {
+            return Err(MsixError::InvalidEntry);
+        }
+        for entry in 0..count {
+            if let Err(err) = self.alloc_entry(entry, 0) {
+                for done in 0..entry {
+                    self.free_entry(done);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Mask `entry`, unregister its handler and give its vector back.
+    pub fn free_entry(&mut self, entry: u16) {
+        let Some(vector) = self.vector(entry) else {
+            return;
+        };
+        msix_mask_entry(&self.table, entry);
+        irq::msi_unregister_handler(vector);
+        irq::msi_free_vector(vector);
+        self.vectors[entry as usize] = 0;
+    }
+
+    /// Route `entry`'s vector to `handler`.  `context` is passed back on
+    /// every interrupt.
+    pub fn register_handler(
+        &self,
+        entry: u16,
+        handler: MsiHandler,
+        context: *mut c_void,
+    ) -> Result<u8, MsixError> {
+        let vector = self.vector(entry).ok_or(MsixError::EntryNotAllocated)?;
+        irq::msi_register_handler(vector, handler, context, self.bdf());
+        Ok(vector)
+    }
+
+    pub fn mask(&self, entry: u16) -> bool {
+        self.vector(entry).is_some() && msix_mask_entry(&self.table, entry)
+    }
+
+    pub fn unmask(&self, entry: u16) -> bool {
+        self.vector(entry).is_some() && msix_unmask_entry(&self.table, entry)
+    }
+
+    /// Deliver `entry` to `apic_id` instead, keeping its vector.  The entry
+    /// is masked while its message address is rewritten.
+    pub fn retarget(&self, entry: u16, apic_id: u8) -> Result<(), MsixError> {
+        let vector = self.vector(entry).ok_or(MsixError::EntryNotAllocated)?;
+        msix_configure(&self.table, entry, vector, apic_id)?;
+        irq::msi_set_affinity(vector, apic_id);
+        Ok(())
+    }
+
+    /// Turn MSI-X on for the device and list the domain in the registry.
+    pub fn enable(&self) {
+        msix_enable(self.bus, self.device, self.function, &self.cap);
+        let mut domains = MSIX_DOMAINS.lock();
+        let bdf = self.bdf();
+        let slot = domains
+            .iter()
+            .position(|d| d.is_some_and(|d| d.bdf() == bdf))
+            .or_else(|| domains.iter().position(Option::is_none));
+        match slot {
+            Some(idx) => domains[idx] = Some(*self),
+            None => klog_debug!("MSI-X: domain registry full, {:06x} not listed", bdf),
+        }
+    }
+
+    /// Disable MSI-X on the device, free every vector and drop the domain
+    /// from the registry.
+    pub fn release(&mut self) {
+        msix_disable(self.bus, self.device, self.function, &self.cap);
+        for entry in 0..MSIX_DOMAIN_MAX_ENTRIES as u16 {
+            self.free_entry(entry);
+        }
+        let bdf = self.bdf();
+        for slot in MSIX_DOMAINS.lock().iter_mut() {
+            if slot.is_some_and(|d| d.bdf() == bdf) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// The enabled MSI-X domain of device `bdf`, if any.
+pub fn msix_domain_lookup(bdf: u32) -> Option<MsixDomain> {
+    MSIX_DOMAINS
+        .lock()
+        .iter()
+        .flatten()
+        .find(|d| d.bdf() == bdf)
+        .copied()
+}
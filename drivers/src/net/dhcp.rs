@@ -1,4 +1,28 @@
-//! DHCP client packet construction and parsing.
+//! DHCP client: packet construction and parsing, and the lease state machine.
+//!
+//! The boot path in `virtio_net` acquires the first lease synchronously on
+//! queue pair 0.  From then on [`DhcpClient`] keeps it alive: it renews
+//! with the granting server at T1, rebinds with any server at T2, drops the
+//! address when the lease runs out, and starts over with DISCOVER whenever
+//! the NIC reports a link transition.  Its timeouts run on the network
+//! timer wheel and replies arrive through the UDP receive path, so the
+//! client never blocks.
+//!
+//! The client itself is pure: each event returns a [`DhcpStep`] saying what
+//! to transmit and how the interface configuration changes, and the global
+//! wrappers at the bottom of this file carry it out with the lock dropped.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use slopos_abi::net::{
+    NET_LEASE_BOUND, NET_LEASE_INFINITE, NET_LEASE_INIT, NET_LEASE_NONE, NET_LEASE_REBINDING,
+    NET_LEASE_RENEWING, NET_LEASE_REQUESTING, NET_LEASE_SELECTING, NetLease,
+};
+use slopos_lib::{IrqMutex, klog_info};
+
+use super::netstack::NET_STACK;
+use super::timer::{NET_TIMER_WHEEL, TimerKind, TimerToken};
+use super::types::{DevIndex, Ipv4Addr};
 
 pub const UDP_PORT_SERVER: u16 = 67;
 pub const UDP_PORT_CLIENT: u16 = 68;
@@ -13,15 +37,19 @@ const OPTION_SUBNET_MASK: u8 = 1;
 const OPTION_ROUTER: u8 = 3;
 const OPTION_DNS: u8 = 6;
 const OPTION_REQUESTED_IP: u8 = 50;
+const OPTION_LEASE_TIME: u8 = 51;
 const OPTION_MSG_TYPE: u8 = 53;
 const OPTION_SERVER_ID: u8 = 54;
 const OPTION_PARAM_REQ_LIST: u8 = 55;
+const OPTION_RENEWAL_TIME: u8 = 58;
+const OPTION_REBINDING_TIME: u8 = 59;
 const OPTION_END: u8 = 255;
 
 pub const MSG_DISCOVER: u8 = 1;
 pub const MSG_OFFER: u8 = 2;
 pub const MSG_REQUEST: u8 = 3;
 pub const MSG_ACK: u8 = 5;
+pub const MSG_NAK: u8 = 6;
 
 pub const BOOTP_HEADER_LEN: usize = 240;
 
@@ -32,20 +60,89 @@ struct DhcpOptions {
     subnet_mask: [u8; 4],
     router: [u8; 4],
     dns: [u8; 4],
+    dns_secondary: [u8; 4],
+    lease_secs: u32,
+    renewal_secs: u32,
+    rebinding_secs: u32,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
 pub struct DhcpLease {
     pub ipv4: [u8; 4],
     pub subnet_mask: [u8; 4],
     pub router: [u8; 4],
     pub dns: [u8; 4],
+    /// Second server from option 6, or zero if the server sent only one.
+    pub dns_secondary: [u8; 4],
+    /// Server that granted the lease; renewals are unicast to it.
+    pub server_id: [u8; 4],
+    /// Lease length, or [`NET_LEASE_INFINITE`].
+    pub lease_secs: u32,
+    /// Renewal (T1) and rebinding (T2) times, `t1 <= t2 <= lease`.
+    pub t1_secs: u32,
+    pub t2_secs: u32,
+}
+
+/// Use the preferred value unless it's zeroed, in which case fall back.
+fn or_fallback(preferred: [u8; 4], fallback: [u8; 4]) -> [u8; 4] {
+    if preferred != [0; 4] {
+        preferred
+    } else {
+        fallback
+    }
 }
 
 impl DhcpLease {
+    /// The lease an ACK grants.  Fields the ACK leaves out are taken from
+    /// `fallback`: the OFFER's parameters, or the lease being renewed.
+    /// Without a lease time in either, the lease never expires.
+    pub fn from_ack(ack: &DhcpOffer, fallback: Option<&DhcpLease>) -> Self {
+        let prev = fallback.copied().unwrap_or_default();
+        let lease_secs = match ack.lease_secs {
+            0 if prev.lease_secs != 0 => prev.lease_secs,
+            0 => NET_LEASE_INFINITE,
+            secs => secs,
+        };
+        let (t1_secs, t2_secs) = lease_timers(lease_secs, ack.renewal_secs, ack.rebinding_secs);
+        Self {
+            ipv4: ack.yiaddr,
+            subnet_mask: or_fallback(ack.subnet_mask, prev.subnet_mask),
+            router: or_fallback(ack.router, prev.router),
+            dns: or_fallback(ack.dns, prev.dns),
+            dns_secondary: or_fallback(ack.dns_secondary, prev.dns_secondary),
+            server_id: or_fallback(ack.server_id, prev.server_id),
+            lease_secs,
+            t1_secs,
+            t2_secs,
+        }
+    }
+
     pub fn is_valid(&self) -> bool {
         self.ipv4 != [0; 4]
     }
+
+    pub fn is_infinite(&self) -> bool {
+        self.lease_secs == NET_LEASE_INFINITE
+    }
+}
+
+/// T1 and T2 for a lease, defaulting to 50% and 87.5% of it (RFC 2131
+/// 4.4.5) when the server sent none or sent values out of order.
+fn lease_timers(lease_secs: u32, renewal_secs: u32, rebinding_secs: u32) -> (u32, u32) {
+    if lease_secs == NET_LEASE_INFINITE {
+        return (NET_LEASE_INFINITE, NET_LEASE_INFINITE);
+    }
+    let t2 = if rebinding_secs != 0 && rebinding_secs <= lease_secs {
+        rebinding_secs
+    } else {
+        (lease_secs as u64 * 7 / 8) as u32
+    };
+    let t1 = if renewal_secs != 0 && renewal_secs <= t2 {
+        renewal_secs
+    } else {
+        (lease_secs / 2).min(t2)
+    };
+    (t1, t2)
 }
 
 #[derive(Clone, Copy)]
@@ -55,6 +152,12 @@ pub struct DhcpOffer {
     pub subnet_mask: [u8; 4],
     pub router: [u8; 4],
     pub dns: [u8; 4],
+    /// Second server from option 6, or zero if the server sent only one.
+    pub dns_secondary: [u8; 4],
+    /// Options 51, 58 and 59 in seconds, or zero if absent.
+    pub lease_secs: u32,
+    pub renewal_secs: u32,
+    pub rebinding_secs: u32,
 }
 
 // =============================================================================
@@ -121,10 +224,30 @@ pub fn build_request(mac: [u8; 6], xid: u32, offer: DhcpOffer, out: &mut [u8; 32
     finish_options(out, i)
 }
 
+/// REQUEST extending the lease on `ciaddr`, for RENEWING and REBINDING.
+/// Unlike the REQUEST answering an OFFER it names neither the address nor
+/// the server, and the reply comes back unicast.
+pub fn build_renew(mac: [u8; 6], xid: u32, ciaddr: [u8; 4], out: &mut [u8; 320]) -> usize {
+    let mut i = write_bootp_header(out, mac, xid);
+    out[10..12].fill(0);
+    out[12..16].copy_from_slice(&ciaddr);
+
+    out[i] = OPTION_MSG_TYPE;
+    out[i + 1] = 1;
+    out[i + 2] = MSG_REQUEST;
+    i += 3;
+
+    finish_options(out, i)
+}
+
 // =============================================================================
 // Parsing
 // =============================================================================
 
+fn read_secs(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
 fn parse_options(options: &[u8]) -> DhcpOptions {
     let mut opts = DhcpOptions::default();
     let mut i = 0usize;
@@ -151,7 +274,15 @@ fn parse_options(options: &[u8]) -> DhcpOptions {
             OPTION_SERVER_ID if len >= 4 => opts.server_id.copy_from_slice(&data[..4]),
             OPTION_SUBNET_MASK if len >= 4 => opts.subnet_mask.copy_from_slice(&data[..4]),
             OPTION_ROUTER if len >= 4 => opts.router.copy_from_slice(&data[..4]),
-            OPTION_DNS if len >= 4 => opts.dns.copy_from_slice(&data[..4]),
+            OPTION_LEASE_TIME if len >= 4 => opts.lease_secs = read_secs(data),
+            OPTION_RENEWAL_TIME if len >= 4 => opts.renewal_secs = read_secs(data),
+            OPTION_REBINDING_TIME if len >= 4 => opts.rebinding_secs = read_secs(data),
+            OPTION_DNS if len >= 4 => {
+                opts.dns.copy_from_slice(&data[..4]);
+                if len >= 8 {
+                    opts.dns_secondary.copy_from_slice(&data[4..8]);
+                }
+            }
             _ => {}
         }
 
@@ -162,6 +293,17 @@ fn parse_options(options: &[u8]) -> DhcpOptions {
 }
 
 pub fn parse_bootp_reply(payload: &[u8], xid: u32, expected_type: u8) -> Option<DhcpOffer> {
+    let (message_type, reply) = parse_reply(payload, xid)?;
+    if message_type != expected_type {
+        return None;
+    }
+    Some(reply)
+}
+
+/// Parse a reply of any message type, returned alongside it.  An OFFER
+/// without a server identifier is rejected, as nothing could be requested
+/// from it.
+fn parse_reply(payload: &[u8], xid: u32) -> Option<(u8, DhcpOffer)> {
     if payload.len() < BOOTP_HEADER_LEN {
         return None;
     }
@@ -177,18 +319,616 @@ pub fn parse_bootp_reply(payload: &[u8], xid: u32, expected_type: u8) -> Option<
 
     let options = parse_options(&payload[BOOTP_HEADER_LEN..]);
 
-    if options.message_type != expected_type {
-        return None;
-    }
-    if expected_type == MSG_OFFER && options.server_id == [0; 4] {
+    if options.message_type == MSG_OFFER && options.server_id == [0; 4] {
         return None;
     }
 
-    Some(DhcpOffer {
+    let reply = DhcpOffer {
         yiaddr: [payload[16], payload[17], payload[18], payload[19]],
         server_id: options.server_id,
         subnet_mask: options.subnet_mask,
         router: options.router,
         dns: options.dns,
-    })
+        dns_secondary: options.dns_secondary,
+        lease_secs: options.lease_secs,
+        renewal_secs: options.renewal_secs,
+        rebinding_secs: options.rebinding_secs,
+    };
+    Some((options.message_type, reply))
+}
+
+// =============================================================================
+// Lease state machine
+// =============================================================================
+
+/// First DISCOVER / REQUEST retransmission delay; doubles per retry.
+const RETRANSMIT_INITIAL_MS: u64 = 4_000;
+const RETRANSMIT_MAX_MS: u64 = 64_000;
+/// Floor on the wait between RENEWING and REBINDING retransmissions
+/// (RFC 2131 4.4.5).
+const RENEW_RETRANSMIT_MIN_MS: u64 = 60_000;
+/// REQUESTs sent for one OFFER before going back to DISCOVER.
+const REQUEST_MAX_TRIES: u8 = 4;
+const DHCP_TTL: u8 = 64;
+
+/// Wheel keys for client timeouts.  Each arm takes a fresh key, so a
+/// timeout that fires after the client re-armed is recognised as stale.
+static DHCP_TIMER_KEY: AtomicU32 = AtomicU32::new(0);
+
+fn secs_to_ms(secs: u32) -> u64 {
+    secs as u64 * 1000
+}
+
+fn ms_to_ticks(ms: u64) -> u64 {
+    (ms / 10).max(1)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DhcpState {
+    /// No client: no NIC yet, or the address is static.
+    Stopped,
+    /// Waiting for the link to come up.
+    Init,
+    Selecting,
+    Requesting,
+    Bound,
+    Renewing,
+    Rebinding,
+}
+
+impl DhcpState {
+    fn abi(self) -> u8 {
+        match self {
+            Self::Stopped => NET_LEASE_NONE,
+            Self::Init => NET_LEASE_INIT,
+            Self::Selecting => NET_LEASE_SELECTING,
+            Self::Requesting => NET_LEASE_REQUESTING,
+            Self::Bound => NET_LEASE_BOUND,
+            Self::Renewing => NET_LEASE_RENEWING,
+            Self::Rebinding => NET_LEASE_REBINDING,
+        }
+    }
+}
+
+/// A DHCP message to send.  Broadcasts go out raw on the NIC since their
+/// source may be `0.0.0.0`; a renewal is routed to its server like any
+/// other datagram.
+#[derive(Clone, Copy)]
+pub struct DhcpTx {
+    pub src: [u8; 4],
+    pub dst: [u8; 4],
+    len: usize,
+    packet: [u8; 320],
+}
+
+impl DhcpTx {
+    fn new(src: [u8; 4], dst: [u8; 4]) -> Self {
+        Self {
+            src,
+            dst,
+            len: 0,
+            packet: [0; 320],
+        }
+    }
+
+    pub fn message(&self) -> &[u8] {
+        &self.packet[..self.len]
+    }
+
+    fn send(&self) -> bool {
+        if Ipv4Addr(self.dst).is_broadcast() {
+            crate::virtio_net::transmit_udp_packet(
+                self.src,
+                self.dst,
+                UDP_PORT_CLIENT,
+                UDP_PORT_SERVER,
+                self.message(),
+            )
+        } else {
+            super::udp::udp_sendto(
+                self.src,
+                self.dst,
+                UDP_PORT_CLIENT,
+                UDP_PORT_SERVER,
+                self.message(),
+                DHCP_TTL,
+            )
+            .is_ok()
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DhcpConfig {
+    /// Configure the interface from a new or changed lease.
+    Apply(DhcpLease),
+    /// The lease is gone; unconfigure the interface.
+    Release,
+}
+
+/// What to do after a client event.
+#[derive(Clone, Copy, Default)]
+pub struct DhcpStep {
+    pub tx: Option<DhcpTx>,
+    pub config: Option<DhcpConfig>,
+}
+
+impl DhcpStep {
+    fn send(tx: DhcpTx) -> Self {
+        Self {
+            tx: Some(tx),
+            config: None,
+        }
+    }
+}
+
+/// RFC 2131 client state for one interface.
+pub struct DhcpClient {
+    state: DhcpState,
+    dev: DevIndex,
+    mac: [u8; 6],
+    xid: u32,
+    link_up: bool,
+    /// The OFFER being requested in `Requesting`.
+    offer: Option<DhcpOffer>,
+    /// The lease held.  It outlives link changes and a restarted DISCOVER,
+    /// and is only dropped when it expires or a server NAKs it.
+    lease: DhcpLease,
+    /// Uptime at which `lease` started.
+    bound_ms: u64,
+    retransmit_ms: u64,
+    tries: u8,
+    renewals: u32,
+    timer: TimerToken,
+    timer_key: u32,
+}
+
+impl Default for DhcpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DhcpClient {
+    pub const fn new() -> Self {
+        Self {
+            state: DhcpState::Stopped,
+            dev: DevIndex(0),
+            mac: [0; 6],
+            xid: 0,
+            link_up: false,
+            offer: None,
+            lease: DhcpLease {
+                ipv4: [0; 4],
+                subnet_mask: [0; 4],
+                router: [0; 4],
+                dns: [0; 4],
+                dns_secondary: [0; 4],
+                server_id: [0; 4],
+                lease_secs: 0,
+                t1_secs: 0,
+                t2_secs: 0,
+            },
+            bound_ms: 0,
+            retransmit_ms: RETRANSMIT_INITIAL_MS,
+            tries: 0,
+            renewals: 0,
+            timer: TimerToken::INVALID,
+            timer_key: 0,
+        }
+    }
+
+    pub fn state(&self) -> DhcpState {
+        self.state
+    }
+
+    pub fn xid(&self) -> u32 {
+        self.xid
+    }
+
+    /// Key of the armed timeout, or zero if none is armed.
+    pub fn timer_key(&self) -> u32 {
+        self.timer_key
+    }
+
+    pub fn lease(&self) -> Option<&DhcpLease> {
+        self.lease.is_valid().then_some(&self.lease)
+    }
+
+    /// Take over from the boot-time exchange, which used `xid` and got
+    /// `lease` if it succeeded.  The caller has already configured the
+    /// interface from it.
+    pub fn start(
+        &mut self,
+        dev: DevIndex,
+        mac: [u8; 6],
+        xid: u32,
+        link_up: bool,
+        lease: Option<DhcpLease>,
+        now: u64,
+    ) -> DhcpStep {
+        self.dev = dev;
+        self.mac = mac;
+        self.xid = xid;
+        self.link_up = link_up;
+        self.renewals = 0;
+        match lease {
+            Some(lease) if lease.is_valid() => {
+                self.lease = lease;
+                self.bound_ms = now;
+                self.state = DhcpState::Bound;
+                self.arm_lease_timer(now);
+                DhcpStep::default()
+            }
+            _ if link_up => self.discover(),
+            _ => {
+                self.state = DhcpState::Init;
+                DhcpStep::default()
+            }
+        }
+    }
+
+    /// Cancel any timeout and stop.  The interface keeps its address.
+    pub fn stop(&mut self) {
+        self.disarm();
+        self.state = DhcpState::Stopped;
+    }
+
+    /// A client timeout fired.  `key` is the one it was armed with.
+    pub fn on_timeout(&mut self, key: u32, now: u64) -> DhcpStep {
+        if key == 0 || key != self.timer_key {
+            return DhcpStep::default();
+        }
+        self.timer = TimerToken::INVALID;
+        self.timer_key = 0;
+
+        let mut step = DhcpStep::default();
+        if self.lease_expired(now) {
+            klog_info!("dhcp: lease on {} expired", Ipv4Addr(self.lease.ipv4));
+            self.lease = DhcpLease::default();
+            step.config = Some(DhcpConfig::Release);
+            if matches!(
+                self.state,
+                DhcpState::Bound | DhcpState::Renewing | DhcpState::Rebinding
+            ) {
+                step.tx = self.discover().tx;
+                return step;
+            }
+        }
+
+        match self.state {
+            DhcpState::Stopped | DhcpState::Init => {}
+            DhcpState::Selecting => {
+                self.retransmit_ms = (self.retransmit_ms * 2).min(RETRANSMIT_MAX_MS);
+                step.tx = Some(self.discover_tx());
+                self.arm(self.retransmit_ms);
+            }
+            DhcpState::Requesting => {
+                self.tries += 1;
+                match self.offer {
+                    Some(offer) if self.tries < REQUEST_MAX_TRIES => {
+                        self.retransmit_ms = (self.retransmit_ms * 2).min(RETRANSMIT_MAX_MS);
+                        step.tx = Some(self.request_tx(&offer));
+                        self.arm(self.retransmit_ms);
+                    }
+                    _ => step.tx = self.discover().tx,
+                }
+            }
+            DhcpState::Bound | DhcpState::Renewing | DhcpState::Rebinding => {
+                step.tx = self.renew_tick(now);
+            }
+        }
+        step
+    }
+
+    /// A datagram arrived on the client port.
+    pub fn on_reply(&mut self, payload: &[u8], now: u64) -> DhcpStep {
+        let Some((message_type, reply)) = parse_reply(payload, self.xid) else {
+            return DhcpStep::default();
+        };
+
+        match (self.state, message_type) {
+            (DhcpState::Selecting, MSG_OFFER) => {
+                self.offer = Some(reply);
+                self.state = DhcpState::Requesting;
+                self.tries = 0;
+                self.retransmit_ms = RETRANSMIT_INITIAL_MS;
+                let tx = self.request_tx(&reply);
+                self.arm(self.retransmit_ms);
+                DhcpStep::send(tx)
+            }
+            (DhcpState::Requesting, MSG_ACK) => {
+                let offered = self.offer.map(|offer| DhcpLease::from_ack(&offer, None));
+                self.bind(DhcpLease::from_ack(&reply, offered.as_ref()), now)
+            }
+            (DhcpState::Renewing | DhcpState::Rebinding, MSG_ACK) => {
+                let step = self.bind(DhcpLease::from_ack(&reply, Some(&self.lease)), now);
+                if self.state == DhcpState::Bound {
+                    self.renewals += 1;
+                }
+                step
+            }
+            (DhcpState::Requesting | DhcpState::Renewing | DhcpState::Rebinding, MSG_NAK) => {
+                klog_info!(
+                    "dhcp: server {} refused the lease",
+                    Ipv4Addr(reply.server_id)
+                );
+                let mut step = self.discover();
+                if self.lease.is_valid() {
+                    self.lease = DhcpLease::default();
+                    step.config = Some(DhcpConfig::Release);
+                }
+                step
+            }
+            _ => DhcpStep::default(),
+        }
+    }
+
+    /// The NIC reported a link transition.  Coming up starts over with
+    /// DISCOVER, as the link may now lead to another network; going down
+    /// waits, keeping the lease until it expires.
+    pub fn on_link(&mut self, up: bool, now: u64) -> DhcpStep {
+        if self.state == DhcpState::Stopped || up == self.link_up {
+            return DhcpStep::default();
+        }
+        self.link_up = up;
+        if up {
+            return self.discover();
+        }
+
+        self.offer = None;
+        self.state = DhcpState::Init;
+        if self.lease.is_valid() {
+            self.arm_lease_timer(now);
+        } else {
+            self.disarm();
+        }
+        DhcpStep::default()
+    }
+
+    /// Lease state for `SYSCALL_NET_LEASE`.
+    pub fn info(&self, now: u64) -> NetLease {
+        let mut info = NetLease {
+            state: self.state.abi(),
+            link_up: u8::from(self.link_up),
+            renewals: self.renewals,
+            ..NetLease::default()
+        };
+        if !self.lease.is_valid() {
+            return info;
+        }
+
+        let lease = &self.lease;
+        info.ipv4 = lease.ipv4;
+        info.subnet_mask = lease.subnet_mask;
+        info.router = lease.router;
+        info.dns = lease.dns;
+        info.dns_secondary = lease.dns_secondary;
+        info.server = lease.server_id;
+        info.lease_secs = lease.lease_secs;
+        info.t1_secs = lease.t1_secs;
+        info.t2_secs = lease.t2_secs;
+        if lease.is_infinite() {
+            info.expires_in = NET_LEASE_INFINITE;
+            info.renew_in = NET_LEASE_INFINITE;
+            info.rebind_in = NET_LEASE_INFINITE;
+        } else {
+            let elapsed = now.saturating_sub(self.bound_ms);
+            let left = |secs: u32| (secs_to_ms(secs).saturating_sub(elapsed) / 1000) as u32;
+            info.expires_in = left(lease.lease_secs);
+            info.renew_in = left(lease.t1_secs);
+            info.rebind_in = left(lease.t2_secs);
+        }
+        info
+    }
+
+    fn lease_expired(&self, now: u64) -> bool {
+        self.lease.is_valid()
+            && !self.lease.is_infinite()
+            && now.saturating_sub(self.bound_ms) >= secs_to_ms(self.lease.lease_secs)
+    }
+
+    fn bind(&mut self, lease: DhcpLease, now: u64) -> DhcpStep {
+        if !lease.is_valid() {
+            return DhcpStep::default();
+        }
+        let changed = (
+            lease.ipv4,
+            lease.subnet_mask,
+            lease.router,
+            lease.dns,
+            lease.dns_secondary,
+        ) != (
+            self.lease.ipv4,
+            self.lease.subnet_mask,
+            self.lease.router,
+            self.lease.dns,
+            self.lease.dns_secondary,
+        );
+
+        self.offer = None;
+        self.lease = lease;
+        self.bound_ms = now;
+        self.state = DhcpState::Bound;
+        self.arm_lease_timer(now);
+        DhcpStep {
+            tx: None,
+            config: changed.then_some(DhcpConfig::Apply(lease)),
+        }
+    }
+
+    fn discover(&mut self) -> DhcpStep {
+        self.state = DhcpState::Selecting;
+        self.offer = None;
+        self.tries = 0;
+        self.retransmit_ms = RETRANSMIT_INITIAL_MS;
+        self.xid = self.xid.wrapping_add(1);
+        let tx = self.discover_tx();
+        self.arm(self.retransmit_ms);
+        DhcpStep::send(tx)
+    }
+
+    /// Move through RENEWING and REBINDING as T1 and T2 pass, sending the
+    /// REQUEST of the state reached.
+    fn renew_tick(&mut self, now: u64) -> Option<DhcpTx> {
+        let elapsed = now.saturating_sub(self.bound_ms);
+        if elapsed >= secs_to_ms(self.lease.t2_secs) {
+            self.state = DhcpState::Rebinding;
+        } else if elapsed >= secs_to_ms(self.lease.t1_secs) {
+            self.state = DhcpState::Renewing;
+        }
+        if self.state == DhcpState::Bound {
+            self.arm_lease_timer(now);
+            return None;
+        }
+
+        self.xid = self.xid.wrapping_add(1);
+        let tx = self.renew_tx();
+        self.arm_lease_timer(now);
+        Some(tx)
+    }
+
+    /// Arm the timeout for the next lease event: T1 when bound, the next
+    /// retransmission (half the time left, but no less than a minute) while
+    /// renewing or rebinding, and expiry otherwise.
+    fn arm_lease_timer(&mut self, now: u64) {
+        if self.lease.is_infinite() {
+            self.disarm();
+            return;
+        }
+        let boundary = match self.state {
+            DhcpState::Bound => self.lease.t1_secs,
+            DhcpState::Renewing => self.lease.t2_secs,
+            _ => self.lease.lease_secs,
+        };
+        let left = secs_to_ms(boundary).saturating_sub(now.saturating_sub(self.bound_ms));
+        let wait = match self.state {
+            DhcpState::Renewing | DhcpState::Rebinding => {
+                (left / 2).max(RENEW_RETRANSMIT_MIN_MS).min(left)
+            }
+            _ => left,
+        };
+        self.arm(wait);
+    }
+
+    fn arm(&mut self, delay_ms: u64) {
+        self.disarm();
+        self.timer_key = DHCP_TIMER_KEY
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1);
+        self.timer = NET_TIMER_WHEEL.schedule(
+            ms_to_ticks(delay_ms),
+            TimerKind::DhcpTimeout,
+            self.timer_key,
+        );
+    }
+
+    fn disarm(&mut self) {
+        if self.timer != TimerToken::INVALID {
+            NET_TIMER_WHEEL.cancel(self.timer);
+            self.timer = TimerToken::INVALID;
+        }
+        self.timer_key = 0;
+    }
+
+    fn discover_tx(&self) -> DhcpTx {
+        let mut tx = DhcpTx::new([0; 4], super::IPV4_BROADCAST);
+        tx.len = build_discover(self.mac, self.xid, &mut tx.packet);
+        tx
+    }
+
+    fn request_tx(&self, offer: &DhcpOffer) -> DhcpTx {
+        let mut tx = DhcpTx::new([0; 4], super::IPV4_BROADCAST);
+        tx.len = build_request(self.mac, self.xid, *offer, &mut tx.packet);
+        tx
+    }
+
+    /// RENEWING asks the granting server directly; REBINDING asks anyone.
+    fn renew_tx(&self) -> DhcpTx {
+        let dst = if self.state == DhcpState::Renewing && self.lease.server_id != [0; 4] {
+            self.lease.server_id
+        } else {
+            super::IPV4_BROADCAST
+        };
+        let mut tx = DhcpTx::new(self.lease.ipv4, dst);
+        tx.len = build_renew(self.mac, self.xid, self.lease.ipv4, &mut tx.packet);
+        tx
+    }
+}
+
+// =============================================================================
+// Global client
+// =============================================================================
+
+static DHCP_CLIENT: IrqMutex<DhcpClient> = IrqMutex::new(DhcpClient::new());
+
+/// Run one client event, then carry out its step with the client unlocked.
+fn drive(event: impl FnOnce(&mut DhcpClient, u64) -> DhcpStep) {
+    let now = slopos_lib::clock::uptime_ms();
+    let (dev, step) = {
+        let mut client = DHCP_CLIENT.lock();
+        let step = event(&mut client, now);
+        (client.dev, step)
+    };
+
+    match step.config {
+        Some(DhcpConfig::Apply(lease)) => {
+            klog_info!(
+                "dhcp: bound {} netmask {} gw {} lease {}s",
+                Ipv4Addr(lease.ipv4),
+                Ipv4Addr(lease.subnet_mask),
+                Ipv4Addr(lease.router),
+                lease.lease_secs
+            );
+            NET_STACK.configure(
+                dev,
+                Ipv4Addr(lease.ipv4),
+                Ipv4Addr(lease.subnet_mask),
+                Ipv4Addr(lease.router),
+                [Ipv4Addr(lease.dns), Ipv4Addr(lease.dns_secondary)],
+            );
+            crate::virtio_net::virtio_net_set_dhcp_config(Some(&lease));
+        }
+        Some(DhcpConfig::Release) => {
+            NET_STACK.deconfigure(dev);
+            crate::virtio_net::virtio_net_set_dhcp_config(None);
+        }
+        None => {}
+    }
+
+    // A lost message is covered by the retransmission timeout.
+    if let Some(tx) = step.tx {
+        let _ = tx.send();
+    }
+}
+
+/// Start maintaining the lease of `dev` (see [`DhcpClient::start`]).
+pub fn dhcp_client_start(
+    dev: DevIndex,
+    mac: [u8; 6],
+    xid: u32,
+    link_up: bool,
+    lease: Option<DhcpLease>,
+) {
+    drive(|client, now| client.start(dev, mac, xid, link_up, lease, now));
+}
+
+/// Dispatch for [`TimerKind::DhcpTimeout`].
+pub fn dhcp_on_timeout(key: u32) {
+    drive(|client, now| client.on_timeout(key, now));
+}
+
+/// A UDP datagram from the server port to the client port.
+pub fn dhcp_handle_rx(payload: &[u8]) {
+    drive(|client, now| client.on_reply(payload, now));
+}
+
+/// The NIC link went up or down.
+pub fn dhcp_link_changed(up: bool) {
+    drive(|client, now| client.on_link(up, now));
+}
+
+pub fn dhcp_lease_info() -> NetLease {
+    let now = slopos_lib::clock::uptime_ms();
+    DHCP_CLIENT.lock().info(now)
 }
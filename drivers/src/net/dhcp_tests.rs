@@ -0,0 +1,315 @@
+//! DHCP tests: lease options and timers, the renewal REQUEST, and the client
+//! state machine driven with synthetic replies and clock values.
+
+use slopos_abi::net::{NET_LEASE_BOUND, NET_LEASE_INFINITE, NET_LEASE_NONE};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::IPV4_BROADCAST;
+use super::dhcp::{
+    self, BOOTP_HEADER_LEN, DhcpClient, DhcpConfig, DhcpLease, DhcpState, MSG_ACK, MSG_NAK,
+    MSG_OFFER, MSG_REQUEST,
+};
+use super::types::DevIndex;
+
+const MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+const SERVER: [u8; 4] = [10, 0, 2, 2];
+const ADDR: [u8; 4] = [10, 0, 2, 15];
+const OTHER_ADDR: [u8; 4] = [10, 0, 2, 16];
+const ROUTER: [u8; 4] = [10, 0, 2, 1];
+const START_MS: u64 = 1_000;
+
+/// A BOOTREPLY of `message_type` from [`SERVER`], with `extra` options
+/// before the end marker.
+fn reply(xid: u32, message_type: u8, yiaddr: [u8; 4], extra: &[u8]) -> [u8; 320] {
+    let mut p = [0u8; 320];
+    p[0] = 2;
+    p[4..8].copy_from_slice(&xid.to_be_bytes());
+    p[16..20].copy_from_slice(&yiaddr);
+    p[236..240].copy_from_slice(&[0x63, 0x82, 0x53, 0x63]);
+
+    let mut i = BOOTP_HEADER_LEN;
+    for option in [
+        &[53, 1, message_type][..],
+        &[54, 4, SERVER[0], SERVER[1], SERVER[2], SERVER[3]],
+        &[1, 4, 255, 255, 255, 0],
+        &[3, 4, ROUTER[0], ROUTER[1], ROUTER[2], ROUTER[3]],
+        extra,
+    ] {
+        p[i..i + option.len()].copy_from_slice(option);
+        i += option.len();
+    }
+    p[i] = 255;
+    p
+}
+
+fn lease_time(secs: u32) -> [u8; 6] {
+    let b = secs.to_be_bytes();
+    [51, 4, b[0], b[1], b[2], b[3]]
+}
+
+fn bound_lease(lease_secs: u32) -> DhcpLease {
+    let ack = reply(1, MSG_ACK, ADDR, &lease_time(lease_secs));
+    match dhcp::parse_bootp_reply(&ack, 1, MSG_ACK) {
+        Some(ack) => DhcpLease::from_ack(&ack, None),
+        None => DhcpLease::default(),
+    }
+}
+
+/// A client bound at [`START_MS`] to a lease of `lease_secs`.
+fn bound_client(lease_secs: u32) -> DhcpClient {
+    let mut client = DhcpClient::new();
+    let _ = client.start(
+        DevIndex(1),
+        MAC,
+        1,
+        true,
+        Some(bound_lease(lease_secs)),
+        START_MS,
+    );
+    client
+}
+
+fn option_present(message: &[u8], code: u8) -> bool {
+    let mut i = BOOTP_HEADER_LEN;
+    while i + 1 < message.len() && message[i] != 255 {
+        if message[i] == code {
+            return true;
+        }
+        i += 2 + message[i + 1] as usize;
+    }
+    false
+}
+
+pub fn test_reply_parses_lease_times() -> TestResult {
+    let extra = [
+        51, 4, 0, 0, 0x0e, 0x10, // 3600 s
+        58, 4, 0, 0, 0x03, 0x84, // 900 s
+        59, 4, 0, 0, 0x07, 0x08, // 1800 s
+    ];
+    let packet = reply(7, MSG_ACK, ADDR, &extra);
+    let Some(ack) = dhcp::parse_bootp_reply(&packet, 7, MSG_ACK) else {
+        return fail!("ACK rejected");
+    };
+    assert_eq_test!(ack.lease_secs, 3600);
+    assert_eq_test!(ack.renewal_secs, 900);
+    assert_eq_test!(ack.rebinding_secs, 1800);
+
+    let lease = DhcpLease::from_ack(&ack, None);
+    assert_eq_test!(lease.server_id, SERVER);
+    assert_eq_test!((lease.t1_secs, lease.t2_secs), (900, 1800));
+    assert_test!(
+        dhcp::parse_bootp_reply(&packet, 8, MSG_ACK).is_none(),
+        "wrong xid"
+    );
+    assert_test!(
+        dhcp::parse_bootp_reply(&packet, 7, MSG_NAK).is_none(),
+        "wrong type"
+    );
+    pass!()
+}
+
+pub fn test_lease_timers_default_and_clamp() -> TestResult {
+    let lease = bound_lease(3600);
+    assert_eq_test!((lease.t1_secs, lease.t2_secs), (1800, 3150), "RFC defaults");
+
+    // T1 past T2 and T2 past the lease are ignored.
+    let mut extra = [0u8; 18];
+    extra[..6].copy_from_slice(&lease_time(100));
+    extra[6..12].copy_from_slice(&[58, 4, 0, 0, 0, 95]);
+    extra[12..].copy_from_slice(&[59, 4, 0, 0, 0, 200]);
+    let packet = reply(1, MSG_ACK, ADDR, &extra);
+    let Some(ack) = dhcp::parse_bootp_reply(&packet, 1, MSG_ACK) else {
+        return fail!("ACK rejected");
+    };
+    let lease = DhcpLease::from_ack(&ack, None);
+    assert_eq_test!((lease.t1_secs, lease.t2_secs), (50, 87));
+
+    // No lease time at all: forever, unless renewing a timed lease.
+    let packet = reply(1, MSG_ACK, ADDR, &[]);
+    let Some(ack) = dhcp::parse_bootp_reply(&packet, 1, MSG_ACK) else {
+        return fail!("ACK rejected");
+    };
+    let forever = DhcpLease::from_ack(&ack, None);
+    assert_test!(forever.is_infinite());
+    assert_eq_test!(forever.t1_secs, NET_LEASE_INFINITE);
+    let renewed = DhcpLease::from_ack(&ack, Some(&bound_lease(600)));
+    assert_eq_test!(renewed.lease_secs, 600);
+    pass!()
+}
+
+pub fn test_renew_request_names_current_address() -> TestResult {
+    let mut out = [0u8; 320];
+    let len = dhcp::build_renew(MAC, 0x1234, ADDR, &mut out);
+    let message = &out[..len];
+    assert_eq_test!(message[0], 1, "not a BOOTREQUEST");
+    assert_eq_test!(&message[12..16], &ADDR[..], "ciaddr");
+    assert_eq_test!(&message[10..12], &[0u8, 0][..], "broadcast flag set");
+    assert_eq_test!(&message[28..34], &MAC[..]);
+    assert_eq_test!(
+        &message[BOOTP_HEADER_LEN..BOOTP_HEADER_LEN + 3],
+        &[53u8, 1, MSG_REQUEST][..]
+    );
+    assert_test!(!option_present(message, 50), "requested IP in a renewal");
+    assert_test!(!option_present(message, 54), "server ID in a renewal");
+    pass!()
+}
+
+pub fn test_client_renews_then_rebinds() -> TestResult {
+    let mut client = bound_client(100);
+    assert_eq_test!(client.state(), DhcpState::Bound);
+    assert_test!(client.timer_key() != 0, "T1 not armed");
+
+    // T1: unicast to the server that granted the lease.
+    let step = client.on_timeout(client.timer_key(), START_MS + 50_000);
+    assert_eq_test!(client.state(), DhcpState::Renewing);
+    let Some(tx) = step.tx else {
+        client.stop();
+        return fail!("no renewal sent");
+    };
+    assert_eq_test!((tx.src, tx.dst), (ADDR, SERVER));
+    assert_eq_test!(&tx.message()[12..16], &ADDR[..]);
+
+    // T2: broadcast to any server.
+    let step = client.on_timeout(client.timer_key(), START_MS + 87_000);
+    assert_eq_test!(client.state(), DhcpState::Rebinding);
+    let dst = step.tx.map(|tx| tx.dst);
+    assert_test!(dst == Some(IPV4_BROADCAST), "rebind not broadcast");
+
+    let ack = reply(client.xid(), MSG_ACK, ADDR, &lease_time(100));
+    let step = client.on_reply(&ack, START_MS + 90_000);
+    assert_eq_test!(client.state(), DhcpState::Bound);
+    assert_test!(step.config.is_none(), "unchanged lease reconfigured");
+
+    let info = client.info(START_MS + 100_000);
+    client.stop();
+    assert_eq_test!(info.state, NET_LEASE_BOUND);
+    assert_eq_test!(info.renewals, 1);
+    assert_eq_test!(info.expires_in, 90, "lease not restarted by the ACK");
+    assert_eq_test!(info.renew_in, 40);
+    pass!()
+}
+
+pub fn test_client_expired_lease_restarts_discover() -> TestResult {
+    let mut client = bound_client(100);
+    let _ = client.on_timeout(client.timer_key(), START_MS + 50_000);
+    let _ = client.on_timeout(client.timer_key(), START_MS + 87_000);
+    let step = client.on_timeout(client.timer_key(), START_MS + 100_000);
+    let state = client.state();
+    let lease = client.lease().copied();
+    client.stop();
+
+    assert_eq_test!(state, DhcpState::Selecting);
+    assert_test!(lease.is_none(), "expired lease kept");
+    assert_test!(
+        step.config == Some(DhcpConfig::Release),
+        "address not released"
+    );
+    let Some(tx) = step.tx else {
+        return fail!("no DISCOVER sent");
+    };
+    assert_eq_test!((tx.src, tx.dst), ([0u8; 4], IPV4_BROADCAST));
+    pass!()
+}
+
+pub fn test_client_link_bounce_rediscovers() -> TestResult {
+    let mut client = bound_client(3600);
+
+    let step = client.on_link(false, START_MS + 1_000);
+    assert_eq_test!(client.state(), DhcpState::Init);
+    assert_test!(step.tx.is_none() && step.config.is_none());
+    assert_test!(client.lease().is_some(), "lease dropped with the link");
+
+    let step = client.on_link(true, START_MS + 2_000);
+    assert_eq_test!(client.state(), DhcpState::Selecting);
+    assert_test!(step.tx.is_some(), "no DISCOVER on link up");
+
+    // A new network offers a different address.
+    let offer = reply(client.xid(), MSG_OFFER, OTHER_ADDR, &lease_time(3600));
+    let step = client.on_reply(&offer, START_MS + 2_100);
+    assert_eq_test!(client.state(), DhcpState::Requesting);
+    assert_test!(step.tx.is_some(), "no REQUEST for the offer");
+
+    let ack = reply(client.xid(), MSG_ACK, OTHER_ADDR, &lease_time(3600));
+    let step = client.on_reply(&ack, START_MS + 2_200);
+    let state = client.state();
+    client.stop();
+    assert_eq_test!(state, DhcpState::Bound);
+    match step.config {
+        Some(DhcpConfig::Apply(lease)) => assert_eq_test!(lease.ipv4, OTHER_ADDR),
+        _ => return fail!("new address not applied"),
+    }
+    pass!()
+}
+
+pub fn test_client_nak_releases_lease() -> TestResult {
+    let mut client = bound_client(100);
+    let _ = client.on_timeout(client.timer_key(), START_MS + 50_000);
+
+    // A stale key and a reply to another transaction change nothing.
+    let stale = client.on_timeout(client.timer_key().wrapping_add(1), START_MS + 51_000);
+    let foreign = reply(client.xid().wrapping_add(1), MSG_NAK, [0; 4], &[]);
+    let ignored = client.on_reply(&foreign, START_MS + 51_000);
+    assert_test!(stale.tx.is_none() && ignored.config.is_none());
+    assert_eq_test!(client.state(), DhcpState::Renewing);
+
+    let nak = reply(client.xid(), MSG_NAK, [0; 4], &[]);
+    let step = client.on_reply(&nak, START_MS + 52_000);
+    let state = client.state();
+    client.stop();
+    assert_eq_test!(state, DhcpState::Selecting);
+    assert_test!(step.config == Some(DhcpConfig::Release), "NAKed lease kept");
+    assert_test!(step.tx.is_some(), "no DISCOVER after NAK");
+    pass!()
+}
+
+pub fn test_client_info_infinite_lease() -> TestResult {
+    let mut client = DhcpClient::new();
+    assert_eq_test!(client.info(0).state, NET_LEASE_NONE);
+
+    let packet = reply(1, MSG_ACK, ADDR, &[]);
+    let Some(ack) = dhcp::parse_bootp_reply(&packet, 1, MSG_ACK) else {
+        return fail!("ACK rejected");
+    };
+    let lease = DhcpLease::from_ack(&ack, None);
+    let _ = client.start(DevIndex(1), MAC, 1, true, Some(lease), START_MS);
+    let armed = client.timer_key();
+    let info = client.info(START_MS + 1_000_000);
+    client.stop();
+
+    assert_eq_test!(armed, 0, "timer armed for a lease that never ends");
+    assert_eq_test!(info.ipv4, ADDR);
+    assert_eq_test!(info.server, SERVER);
+    assert_eq_test!(info.expires_in, NET_LEASE_INFINITE);
+    assert_eq_test!(info.rebind_in, NET_LEASE_INFINITE);
+    pass!()
+}
+
+pub fn test_nic_lease_client_running() -> TestResult {
+    if !crate::virtio_net::virtio_net_is_ready() || super::NET_STACK.static_config().is_some() {
+        return pass!();
+    }
+    let info = dhcp::dhcp_lease_info();
+    assert_test!(info.state != NET_LEASE_NONE, "DHCP client not started");
+    if info.state == NET_LEASE_BOUND {
+        assert_test!(info.ipv4 != [0; 4]);
+        assert_test!(info.expires_in >= info.rebind_in);
+        assert_test!(info.rebind_in >= info.renew_in);
+    }
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    dhcp,
+    [
+        test_reply_parses_lease_times,
+        test_lease_timers_default_and_clamp,
+        test_renew_request_names_current_address,
+        test_client_renews_then_rebinds,
+        test_client_expired_lease_restarts_discover,
+        test_client_link_bounce_rediscovers,
+        test_client_nak_releases_lease,
+        test_client_info_infinite_lease,
+        test_nic_lease_client_running,
+    ]
+);
@@ -1,14 +1,27 @@
 //! DNS client: wire protocol, cache, and resolver.
 //!
 //! Implements a minimal DNS stub resolver for A-record lookups over UDP.
-//! The resolver lives in-kernel (matching the DHCP client pattern) with a
-//! synchronous `dns_resolve()` entry point called from the `SYSCALL_RESOLVE`
-//! handler.
+//! The resolver lives in-kernel (matching the DHCP client pattern) and backs
+//! the `SYSCALL_RESOLVE` handler.
+//!
+//! Lookups are queries in a small in-flight table, matched to replies by
+//! query ID, so several tasks can resolve at once.  Each query asks the
+//! primary server, then the secondary, for two rounds; a
+//! [`TimerKind::DnsRetransmit`] timer moves it on when a server stays silent.
+//! Answers are cached for their TTL, NXDOMAIN/NODATA for the SOA negative
+//! TTL (RFC 2308), and lookups nobody answered for a few seconds, so a dead
+//! name or server fails fast the second time.
+//!
+//! [`dns_resolve`] blocks until the query finishes.  [`dns_lookup`] can
+//! instead start the query and return [`DnsLookup::Pending`]; calling it
+//! again for the same name collects the answer.
 
 use core::sync::atomic::{AtomicU16, Ordering};
 
 use slopos_lib::{IrqMutex, klog_debug};
 
+use super::timer::{NET_TIMER_WHEEL, TimerKind, TimerToken};
+
 // =============================================================================
 // Constants
 // =============================================================================
@@ -27,12 +40,30 @@ const DNS_MAX_RESPONSE: usize = 512;
 const MAX_CNAME_HOPS: usize = 8;
 /// Maximum compression pointer follows (loop detection).
 const MAX_POINTER_FOLLOWS: usize = 16;
-/// DNS resolve timeout per attempt (ms).
-const DNS_TIMEOUT_MS: u32 = 3000;
-/// Number of resolve attempts.
-const DNS_MAX_RETRIES: usize = 2;
+/// Ticks a server gets to answer in the first round (~1 s at 100 Hz).
+/// The second round doubles it.
+const DNS_TIMEOUT_TICKS: u64 = 100;
+/// Rounds over the configured servers before a lookup fails.
+const DNS_MAX_ROUNDS: u8 = 2;
+/// Upper bound on a blocking lookup, in case the timers stall (ms).
+const DNS_LOOKUP_BUDGET_MS: u64 = 10_000;
+/// How long a blocking lookup sleeps between checks (ms).
+const DNS_WAIT_SLICE_MS: u32 = 50;
 /// DNS cache size.
-const DNS_CACHE_SIZE: usize = 16;
+const DNS_CACHE_SIZE: usize = 32;
+/// Longest an answer is cached, whatever its TTL (1 day).
+const DNS_MAX_TTL_SECS: u32 = 86_400;
+/// Negative TTL when an NXDOMAIN/NODATA reply carries no SOA record.
+const DNS_DEFAULT_NEGATIVE_TTL_SECS: u32 = 30;
+/// Longest a negative answer is cached, so a newly added name shows up
+/// within minutes.
+const DNS_MAX_NEGATIVE_TTL_SECS: u32 = 300;
+/// How long a lookup no server answered is remembered as failed.
+const DNS_FAILURE_TTL_SECS: u32 = 5;
+/// Lookups that can be in flight at once.
+const DNS_MAX_QUERIES: usize = 8;
+/// How long a finished query lingers so every waiter sees its result (ms).
+const DNS_RESULT_LINGER_MS: u64 = 1000;
 
 /// Monotonically increasing query ID.
 static QUERY_ID: AtomicU16 = AtomicU16::new(0x4242);
@@ -46,6 +77,7 @@ static QUERY_ID: AtomicU16 = AtomicU16::new(0x4242);
 pub enum DnsType {
     A = 1,
     CNAME = 5,
+    SOA = 6,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -123,12 +155,37 @@ impl DnsHeader {
 }
 
 /// Successful DNS resolution result.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct DnsResponse {
     pub addr: [u8; 4],
     pub ttl: u32,
 }
 
+/// What a DNS reply says about the queried name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DnsAnswer {
+    /// An A record, possibly at the end of a CNAME chain.
+    Address(DnsResponse),
+    /// NXDOMAIN, or NOERROR without an A record (NODATA).  `ttl` is the
+    /// negative caching time from the authority SOA, if there was one.
+    Negative { ttl: Option<u32> },
+    /// SERVFAIL, REFUSED or another error: ask the next server.
+    ServerFailure,
+}
+
+/// Outcome of [`dns_lookup`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DnsLookup {
+    /// The name resolved to this address.
+    Found([u8; 4]),
+    /// A server answered that the name has no A record.
+    NotFound,
+    /// No server gave a usable answer in time, or none is configured.
+    Failed,
+    /// The query is still in flight (non-blocking lookups only).
+    Pending,
+}
+
 // =============================================================================
 // DNS name encoding
 // =============================================================================
@@ -328,8 +385,20 @@ pub fn dns_decode_name(packet: &[u8], offset: usize, out: &mut [u8]) -> Option<(
 
 /// Parse a DNS response packet and extract the first A record.
 ///
-/// Chases CNAME records up to `MAX_CNAME_HOPS` deep.
+/// Chases CNAME records up to `MAX_CNAME_HOPS` deep.  Negative and error
+/// replies yield `None`; see [`dns_parse_answer`] to tell them apart.
 pub fn dns_parse_response(packet: &[u8], expected_id: u16) -> Option<DnsResponse> {
+    match dns_parse_answer(packet, expected_id)? {
+        DnsAnswer::Address(response) => Some(response),
+        _ => None,
+    }
+}
+
+/// Classify a DNS response packet to the query `expected_id`.
+///
+/// Returns `None` for packets that are not a well-formed reply to that
+/// query, which the resolver ignores.
+pub fn dns_parse_answer(packet: &[u8], expected_id: u16) -> Option<DnsAnswer> {
     let header = DnsHeader::from_bytes(packet)?;
 
     // Validate response
@@ -340,8 +409,8 @@ pub fn dns_parse_response(packet: &[u8], expected_id: u16) -> Option<DnsResponse
         return None; // ID mismatch
     }
     let rcode = header.rcode();
-    if rcode != DnsRcode::NoError as u8 {
-        return None; // Error response
+    if rcode != DnsRcode::NoError as u8 && rcode != DnsRcode::NXDomain as u8 {
+        return Some(DnsAnswer::ServerFailure);
     }
 
     // Skip question section
@@ -358,44 +427,22 @@ pub fn dns_parse_response(packet: &[u8], expected_id: u16) -> Option<DnsResponse
 
     // Parse answer section, chasing CNAMEs
     let mut a_addr: Option<([u8; 4], u32)> = None;
-    let mut _cname_hops = 0usize;
+    let mut cname_hops = 0usize;
 
     for _ in 0..header.ancount {
         if pos >= packet.len() {
             break;
         }
 
-        // Skip RR name
-        let name_end = skip_dns_name(packet, pos)?;
-        pos = name_end;
-
-        // Read TYPE, CLASS, TTL, RDLENGTH
-        if pos + 10 > packet.len() {
-            return None;
-        }
-        let rr_type = u16::from_be_bytes([packet[pos], packet[pos + 1]]);
-        let _rr_class = u16::from_be_bytes([packet[pos + 2], packet[pos + 3]]);
-        let ttl = u32::from_be_bytes([
-            packet[pos + 4],
-            packet[pos + 5],
-            packet[pos + 6],
-            packet[pos + 7],
-        ]);
-        let rdlength = u16::from_be_bytes([packet[pos + 8], packet[pos + 9]]) as usize;
-        pos += 10;
-
-        if pos + rdlength > packet.len() {
-            return None;
-        }
-
-        if rr_type == DnsType::A as u16 && rdlength == 4 {
+        let rr = read_rr(packet, pos)?;
+        if rr.rr_type == DnsType::A as u16 && rr.rdlength == 4 {
             let mut addr = [0u8; 4];
-            addr.copy_from_slice(&packet[pos..pos + 4]);
-            a_addr = Some((addr, ttl));
+            addr.copy_from_slice(&packet[rr.rdata..rr.rdata + 4]);
+            a_addr = Some((addr, rr.ttl));
             // Don't break — continue to find the best answer
-        } else if rr_type == DnsType::CNAME as u16 {
-            _cname_hops += 1;
-            if _cname_hops > MAX_CNAME_HOPS {
+        } else if rr.rr_type == DnsType::CNAME as u16 {
+            cname_hops += 1;
+            if cname_hops > MAX_CNAME_HOPS {
                 return None;
             }
             // CNAME: the A record should follow for the canonical name.
@@ -403,10 +450,72 @@ pub fn dns_parse_response(packet: &[u8], expected_id: u16) -> Option<DnsResponse
             // target typically appears later in the answer section.
         }
 
-        pos += rdlength;
+        pos = rr.rdata + rr.rdlength;
+    }
+
+    if rcode == DnsRcode::NoError as u8
+        && let Some((addr, ttl)) = a_addr
+    {
+        return Some(DnsAnswer::Address(DnsResponse { addr, ttl }));
     }
 
-    a_addr.map(|(addr, ttl)| DnsResponse { addr, ttl })
+    // Negative answer: the SOA in the authority section bounds how long it
+    // may be cached (RFC 2308 §5).  A malformed authority section only
+    // loses the TTL, not the answer.
+    let mut ttl = None;
+    for _ in 0..header.nscount {
+        let Some(rr) = read_rr(packet, pos) else {
+            break;
+        };
+        // SOA RDATA ends with SERIAL, REFRESH, RETRY, EXPIRE, MINIMUM.
+        if rr.rr_type == DnsType::SOA as u16 && rr.rdlength >= 22 {
+            let min = &packet[rr.rdata + rr.rdlength - 4..rr.rdata + rr.rdlength];
+            let minimum = u32::from_be_bytes([min[0], min[1], min[2], min[3]]);
+            ttl = Some(rr.ttl.min(minimum));
+            break;
+        }
+        pos = rr.rdata + rr.rdlength;
+    }
+    Some(DnsAnswer::Negative { ttl })
+}
+
+/// Fixed fields of a resource record, located by [`read_rr`].
+struct ResourceRecord {
+    rr_type: u16,
+    ttl: u32,
+    /// Offset of the RDATA in the packet.
+    rdata: usize,
+    rdlength: usize,
+}
+
+/// Read the resource record at `pos`, checking its RDATA fits the packet.
+fn read_rr(packet: &[u8], pos: usize) -> Option<ResourceRecord> {
+    // Skip RR name
+    let pos = skip_dns_name(packet, pos)?;
+
+    // Read TYPE, CLASS, TTL, RDLENGTH
+    if pos + 10 > packet.len() {
+        return None;
+    }
+    let rr_type = u16::from_be_bytes([packet[pos], packet[pos + 1]]);
+    let ttl = u32::from_be_bytes([
+        packet[pos + 4],
+        packet[pos + 5],
+        packet[pos + 6],
+        packet[pos + 7],
+    ]);
+    let rdlength = u16::from_be_bytes([packet[pos + 8], packet[pos + 9]]) as usize;
+    let rdata = pos + 10;
+
+    if rdata + rdlength > packet.len() {
+        return None;
+    }
+    Some(ResourceRecord {
+        rr_type,
+        ttl,
+        rdata,
+        rdlength,
+    })
 }
 
 /// Skip a DNS name in wire format, returning the offset after it.
@@ -447,8 +556,11 @@ fn skip_dns_name(packet: &[u8], mut pos: usize) -> Option<usize> {
 struct DnsCacheEntry {
     /// FNV-1a hash of the hostname for fast comparison.
     hostname_hash: u32,
-    /// Resolved IPv4 address.
-    addr: [u8; 4],
+    /// The hostname itself; the hash only narrows the search.
+    hostname: [u8; DNS_NAME_MAX],
+    hostname_len: usize,
+    /// `Found`, `NotFound` or `Failed`; never `Pending`.
+    result: DnsLookup,
     /// Absolute expiry time in ms (from `clock::uptime_ms()`).
     expiry_ms: u64,
     /// Last-used timestamp for LRU eviction.
@@ -461,12 +573,20 @@ impl DnsCacheEntry {
     const fn empty() -> Self {
         Self {
             hostname_hash: 0,
-            addr: [0; 4],
+            hostname: [0; DNS_NAME_MAX],
+            hostname_len: 0,
+            result: DnsLookup::Failed,
             expiry_ms: 0,
             last_used_ms: 0,
             valid: false,
         }
     }
+
+    fn matches(&self, hash: u32, hostname: &[u8]) -> bool {
+        self.valid
+            && self.hostname_hash == hash
+            && self.hostname[..self.hostname_len].eq_ignore_ascii_case(hostname)
+    }
 }
 
 struct DnsCache {
@@ -480,71 +600,64 @@ impl DnsCache {
         }
     }
 
-    fn lookup(&mut self, hostname: &[u8]) -> Option<[u8; 4]> {
+    fn lookup(&mut self, hostname: &[u8]) -> Option<DnsLookup> {
         let hash = fnv1a_hash(hostname);
         let now = slopos_lib::clock::uptime_ms();
 
-        for entry in self.entries.iter_mut() {
-            if entry.valid && entry.hostname_hash == hash {
-                if now < entry.expiry_ms {
-                    entry.last_used_ms = now;
-                    return Some(entry.addr);
-                } else {
-                    // TTL expired
-                    entry.valid = false;
-                    return None;
-                }
-            }
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|e| e.matches(hash, hostname))?;
+        if now >= entry.expiry_ms {
+            // TTL expired
+            entry.valid = false;
+            return None;
         }
-        None
+        entry.last_used_ms = now;
+        Some(entry.result)
     }
 
-    fn insert(&mut self, hostname: &[u8], addr: [u8; 4], ttl_secs: u32) {
+    /// Cache `result` for `ttl_secs`.  A zero TTL means "do not cache".
+    fn insert(&mut self, hostname: &[u8], result: DnsLookup, ttl_secs: u32) {
+        if ttl_secs == 0 || hostname.len() > DNS_NAME_MAX {
+            return;
+        }
         let hash = fnv1a_hash(hostname);
         let now = slopos_lib::clock::uptime_ms();
-        // Minimum TTL of 60s to avoid thrashing
-        let ttl_ms = (ttl_secs.max(60) as u64) * 1000;
-
-        // Check if already cached — update in place
-        for entry in self.entries.iter_mut() {
-            if entry.valid && entry.hostname_hash == hash {
-                entry.addr = addr;
-                entry.expiry_ms = now + ttl_ms;
-                entry.last_used_ms = now;
-                return;
-            }
-        }
-
-        // Find a free slot
-        for entry in self.entries.iter_mut() {
-            if !entry.valid {
-                *entry = DnsCacheEntry {
-                    hostname_hash: hash,
-                    addr,
-                    expiry_ms: now + ttl_ms,
-                    last_used_ms: now,
-                    valid: true,
-                };
-                return;
-            }
-        }
-
-        // Evict LRU entry
-        let mut lru_idx = 0usize;
-        let mut lru_time = u64::MAX;
-        for (i, entry) in self.entries.iter().enumerate() {
-            if entry.last_used_ms < lru_time {
-                lru_time = entry.last_used_ms;
-                lru_idx = i;
-            }
-        }
-        self.entries[lru_idx] = DnsCacheEntry {
+        let mut entry = DnsCacheEntry {
             hostname_hash: hash,
-            addr,
-            expiry_ms: now + ttl_ms,
+            hostname_len: hostname.len(),
+            result,
+            expiry_ms: now + ttl_secs as u64 * 1000,
             last_used_ms: now,
             valid: true,
+            ..DnsCacheEntry::empty()
         };
+        entry.hostname[..hostname.len()].copy_from_slice(hostname);
+
+        // Update in place, else take a free or expired slot, else evict the
+        // least recently used entry.
+        let idx = self
+            .entries
+            .iter()
+            .position(|e| e.matches(hash, hostname))
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .position(|e| !e.valid || now >= e.expiry_ms)
+            })
+            .unwrap_or_else(|| {
+                let mut lru_idx = 0usize;
+                let mut lru_time = u64::MAX;
+                for (i, e) in self.entries.iter().enumerate() {
+                    if e.last_used_ms < lru_time {
+                        lru_time = e.last_used_ms;
+                        lru_idx = i;
+                    }
+                }
+                lru_idx
+            });
+        self.entries[idx] = entry;
     }
 
     fn flush(&mut self) {
@@ -572,14 +685,32 @@ fn fnv1a_hash(data: &[u8]) -> u32 {
 
 static DNS_CACHE: IrqMutex<DnsCache> = IrqMutex::new(DnsCache::new());
 
-/// Look up a hostname in the DNS cache.
+/// Look up a hostname's address in the DNS cache.  Cached negative answers
+/// also return `None`.
 pub fn dns_cache_lookup(hostname: &[u8]) -> Option<[u8; 4]> {
-    DNS_CACHE.lock().lookup(hostname)
+    match DNS_CACHE.lock().lookup(hostname)? {
+        DnsLookup::Found(addr) => Some(addr),
+        _ => None,
+    }
 }
 
-/// Insert a resolved address into the DNS cache.
+/// Insert a resolved address into the DNS cache.  The TTL is capped at one
+/// day; a zero TTL is not cached.
 pub fn dns_cache_insert(hostname: &[u8], addr: [u8; 4], ttl_secs: u32) {
-    DNS_CACHE.lock().insert(hostname, addr, ttl_secs);
+    DNS_CACHE.lock().insert(
+        hostname,
+        DnsLookup::Found(addr),
+        ttl_secs.min(DNS_MAX_TTL_SECS),
+    );
+}
+
+/// Remember that `hostname` does not exist, for the negative TTL from the
+/// server's SOA (`None` uses a 30 s default).
+pub fn dns_cache_insert_negative(hostname: &[u8], ttl_secs: Option<u32>) {
+    let ttl = ttl_secs
+        .unwrap_or(DNS_DEFAULT_NEGATIVE_TTL_SECS)
+        .min(DNS_MAX_NEGATIVE_TTL_SECS);
+    DNS_CACHE.lock().insert(hostname, DnsLookup::NotFound, ttl);
 }
 
 /// Flush the entire DNS cache.
@@ -588,110 +719,339 @@ pub fn dns_cache_flush() {
 }
 
 // =============================================================================
-// Resolver
+// In-flight queries
 // =============================================================================
 
-/// Resolve a hostname to an IPv4 address using the kernel's DNS client.
-///
-/// 1. Checks the DNS cache
-/// 2. Sends a DNS query to the DHCP-provided DNS server
-/// 3. Waits for a response with timeout
-/// 4. Parses and caches the result
-/// 5. Retries once on timeout
-pub fn dns_resolve(hostname: &[u8]) -> Option<[u8; 4]> {
-    // Shortcut: if it looks like an IP literal, parse it
-    if let Some(addr) = parse_ip_literal(hostname) {
-        return Some(addr);
-    }
-
-    // Check cache first
-    if let Some(addr) = dns_cache_lookup(hostname) {
-        klog_debug!(
-            "dns: cache hit for {:?} -> {}.{}.{}.{}",
-            core::str::from_utf8(hostname).unwrap_or("?"),
-            addr[0],
-            addr[1],
-            addr[2],
-            addr[3]
-        );
-        return Some(addr);
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum QueryState {
+    Free,
+    Pending,
+    /// Finished at the given uptime (ms).  Kept for `DNS_RESULT_LINGER_MS`
+    /// so every waiter sees the result, even one too short-lived to cache.
+    Done(DnsLookup, u64),
+}
+
+#[derive(Clone, Copy)]
+struct DnsQuery {
+    state: QueryState,
+    id: u16,
+    hostname: [u8; DNS_NAME_MAX],
+    hostname_len: usize,
+    servers: [[u8; 4]; 2],
+    server_count: u8,
+    /// Transmissions so far; attempt `n` goes to `servers[n % server_count]`.
+    attempt: u8,
+    timer: TimerToken,
+}
+
+impl DnsQuery {
+    const fn empty() -> Self {
+        Self {
+            state: QueryState::Free,
+            id: 0,
+            hostname: [0; DNS_NAME_MAX],
+            hostname_len: 0,
+            servers: [[0; 4]; 2],
+            server_count: 0,
+            attempt: 0,
+            timer: TimerToken::INVALID,
+        }
     }
 
-    // Get DNS server IP from DHCP lease
-    let dns_server = crate::virtio_net::virtio_net_dns()?;
-    if dns_server == [0; 4] {
-        klog_debug!("dns: no DNS server configured");
-        return None;
+    fn hostname(&self) -> &[u8] {
+        &self.hostname[..self.hostname_len]
     }
 
-    // Get our IP for source address
-    let src_ip = crate::net::netstack::NET_STACK
-        .first_ipv4()
-        .map(|ip| ip.0)
-        .unwrap_or([0; 4]);
+    fn max_attempts(&self) -> u8 {
+        self.server_count * DNS_MAX_ROUNDS
+    }
+
+    /// Record the query's result and cache it.
+    fn complete(&mut self, result: DnsLookup, ttl: Option<u32>) {
+        NET_TIMER_WHEEL.cancel(self.timer);
+        self.timer = TimerToken::INVALID;
+        self.state = QueryState::Done(result, slopos_lib::clock::uptime_ms());
+        let hostname = self.hostname();
+        match result {
+            DnsLookup::Found(addr) => dns_cache_insert(hostname, addr, ttl.unwrap_or(0)),
+            DnsLookup::NotFound => dns_cache_insert_negative(hostname, ttl),
+            DnsLookup::Failed => {
+                DNS_CACHE
+                    .lock()
+                    .insert(hostname, DnsLookup::Failed, DNS_FAILURE_TTL_SECS)
+            }
+            DnsLookup::Pending => {}
+        }
+    }
+}
+
+struct DnsQueryTable {
+    queries: [DnsQuery; DNS_MAX_QUERIES],
+}
 
-    for attempt in 0..DNS_MAX_RETRIES {
-        let id = QUERY_ID.fetch_add(1, Ordering::Relaxed);
+impl DnsQueryTable {
+    const fn new() -> Self {
+        Self {
+            queries: [DnsQuery::empty(); DNS_MAX_QUERIES],
+        }
+    }
 
-        // Build query
-        let mut query_buf = [0u8; 512];
-        let query_len = dns_build_query(id, hostname, DnsType::A, &mut query_buf)?;
+    /// The pending query for `hostname`, or one that finished recently.
+    fn find(&self, hostname: &[u8], now: u64) -> Option<&DnsQuery> {
+        self.queries.iter().find(|q| {
+            let live = match q.state {
+                QueryState::Free => false,
+                QueryState::Pending => true,
+                QueryState::Done(_, done_ms) => now.saturating_sub(done_ms) < DNS_RESULT_LINGER_MS,
+            };
+            live && q.hostname().eq_ignore_ascii_case(hostname)
+        })
+    }
 
-        // Use an ephemeral source port
-        let src_port = 49152 + (id % 16384);
+    fn pending_by_id(&mut self, id: u16) -> Option<&mut DnsQuery> {
+        self.queries
+            .iter_mut()
+            .find(|q| q.state == QueryState::Pending && q.id == id)
+    }
+}
 
-        // Clear any stale RX data
-        crate::virtio_net::dns_rx_clear();
+static DNS_QUERIES: IrqMutex<DnsQueryTable> = IrqMutex::new(DnsQueryTable::new());
 
-        // Send query
-        if !crate::virtio_net::transmit_udp_packet(
-            src_ip,
-            dns_server,
-            src_port,
-            DNS_PORT,
-            &query_buf[..query_len],
-        ) {
-            klog_debug!("dns: transmit failed (attempt {})", attempt);
-            continue;
+/// Configured DNS servers, primary first, and how many there are.
+fn dns_servers() -> ([[u8; 4]; 2], u8) {
+    let mut servers = [[0u8; 4]; 2];
+    let mut count = 0usize;
+    for server in super::netstack::NET_STACK.dns_servers() {
+        if !server.is_unspecified() && !servers[..count].contains(&server.0) {
+            servers[count] = server.0;
+            count += 1;
         }
+    }
+    if count == 0
+        && let Some(server) = crate::virtio_net::virtio_net_dns()
+    {
+        servers[0] = server;
+        count = 1;
+    }
+    (servers, count as u8)
+}
 
-        // Wait for response
-        if !crate::virtio_net::dns_rx_wait(DNS_TIMEOUT_MS) {
-            klog_debug!("dns: timeout (attempt {})", attempt);
-            continue;
+/// Start a query for `hostname`.  `Pending` once it is on the wire, or
+/// while the table is full; `Failed` if it cannot be asked at all.
+fn dns_start_query(hostname: &[u8]) -> DnsLookup {
+    let (servers, server_count) = dns_servers();
+    if server_count == 0 {
+        klog_debug!("dns: no DNS server configured");
+        return DnsLookup::Failed;
+    }
+    // Reject names that cannot be encoded before they take a slot.
+    let mut wire = [0u8; DNS_MAX_RESPONSE];
+    if dns_build_query(0, hostname, DnsType::A, &mut wire).is_none() {
+        return DnsLookup::Failed;
+    }
+
+    let id = QUERY_ID.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut table = DNS_QUERIES.lock();
+        let now = slopos_lib::clock::uptime_ms();
+        // Another task may have started the same lookup meanwhile.
+        if table.find(hostname, now).is_some() {
+            return DnsLookup::Pending;
         }
+        // Prefer a free slot over a finished query's lingering result.
+        let slot = table
+            .queries
+            .iter()
+            .position(|q| q.state == QueryState::Free)
+            .or_else(|| {
+                table
+                    .queries
+                    .iter()
+                    .position(|q| q.state != QueryState::Pending)
+            });
+        let Some(query) = slot.map(|idx| &mut table.queries[idx]) else {
+            klog_debug!("dns: too many lookups in flight");
+            return DnsLookup::Pending;
+        };
+        *query = DnsQuery {
+            state: QueryState::Pending,
+            id,
+            hostname_len: hostname.len(),
+            servers,
+            server_count,
+            ..DnsQuery::empty()
+        };
+        query.hostname[..hostname.len()].copy_from_slice(hostname);
+    }
+    dns_transmit(id);
+    DnsLookup::Pending
+}
 
-        // Read response
-        let mut resp_buf = [0u8; DNS_MAX_RESPONSE];
-        let resp_len = crate::virtio_net::dns_rx_read(&mut resp_buf);
-        if resp_len == 0 {
-            klog_debug!("dns: empty response (attempt {})", attempt);
-            continue;
+/// Send the current attempt of query `id` and arm its timeout.
+fn dns_transmit(id: u16) {
+    let mut query_buf = [0u8; DNS_MAX_RESPONSE];
+    let (query_len, server, attempt) = {
+        let mut table = DNS_QUERIES.lock();
+        let Some(query) = table.pending_by_id(id) else {
+            return;
+        };
+        let Some(len) = dns_build_query(id, query.hostname(), DnsType::A, &mut query_buf) else {
+            return;
+        };
+        let round = query.attempt / query.server_count;
+        let server = query.servers[(query.attempt % query.server_count) as usize];
+        query.timer = NET_TIMER_WHEEL.schedule(
+            DNS_TIMEOUT_TICKS << round,
+            TimerKind::DnsRetransmit,
+            id as u32,
+        );
+        (len, server, query.attempt)
+    };
+
+    let src_ip = super::netstack::NET_STACK
+        .first_ipv4()
+        .map(|ip| ip.0)
+        .unwrap_or([0; 4]);
+    // Use an ephemeral source port
+    let src_port = 49152 + (id % 16384);
+    // A failed send is retried by the timer like a lost one.
+    if !crate::virtio_net::transmit_udp_packet(
+        src_ip,
+        server,
+        src_port,
+        DNS_PORT,
+        &query_buf[..query_len],
+    ) {
+        klog_debug!("dns: transmit failed (attempt {})", attempt);
+    }
+}
+
+/// [`TimerKind::DnsRetransmit`] fired: the server did not answer query `key`
+/// in time.  Ask the next server, or give up once every round is spent.
+pub fn dns_on_timeout(key: u32) {
+    let id = key as u16;
+    {
+        let mut table = DNS_QUERIES.lock();
+        let Some(query) = table.pending_by_id(id) else {
+            return;
+        };
+        query.attempt += 1;
+        if query.attempt >= query.max_attempts() {
+            klog_debug!(
+                "dns: no answer for {:?}",
+                core::str::from_utf8(query.hostname()).unwrap_or("?")
+            );
+            query.complete(DnsLookup::Failed, None);
+            return;
         }
+    }
+    dns_transmit(id);
+}
 
-        // Parse response
-        if let Some(response) = dns_parse_response(&resp_buf[..resp_len], id) {
+/// Feed a UDP payload from port 53 to the query it answers.
+///
+/// Runs on the receive path, so it never transmits: a server failure only
+/// pulls the query's timer in to the next tick.
+pub fn dns_handle_response(src_ip: [u8; 4], payload: &[u8]) {
+    let Some(header) = DnsHeader::from_bytes(payload) else {
+        return;
+    };
+    let mut table = DNS_QUERIES.lock();
+    let Some(query) = table.pending_by_id(header.id) else {
+        return;
+    };
+    if !query.servers[..query.server_count as usize].contains(&src_ip) {
+        return;
+    }
+    let Some(answer) = dns_parse_answer(payload, header.id) else {
+        return;
+    };
+    let name = core::str::from_utf8(query.hostname()).unwrap_or("?");
+    match answer {
+        DnsAnswer::Address(response) => {
             klog_debug!(
                 "dns: resolved {:?} -> {}.{}.{}.{} (ttl={}s)",
-                core::str::from_utf8(hostname).unwrap_or("?"),
+                name,
                 response.addr[0],
                 response.addr[1],
                 response.addr[2],
                 response.addr[3],
                 response.ttl
             );
-            dns_cache_insert(hostname, response.addr, response.ttl);
-            return Some(response.addr);
+            query.complete(DnsLookup::Found(response.addr), Some(response.ttl));
+        }
+        DnsAnswer::Negative { ttl } => {
+            klog_debug!("dns: {:?} does not exist", name);
+            query.complete(DnsLookup::NotFound, ttl);
+        }
+        DnsAnswer::ServerFailure => {
+            klog_debug!("dns: server error for {:?}", name);
+            NET_TIMER_WHEEL.cancel(query.timer);
+            query.timer = NET_TIMER_WHEEL.schedule(1, TimerKind::DnsRetransmit, header.id as u32);
         }
+    }
+}
+
+// =============================================================================
+// Resolver
+// =============================================================================
 
-        klog_debug!("dns: parse failed (attempt {})", attempt);
+/// One step of a lookup: the cached result, the state of the query for
+/// `hostname`, or a newly started query.
+fn dns_lookup_step(hostname: &[u8]) -> DnsLookup {
+    if let Some(result) = DNS_CACHE.lock().lookup(hostname) {
+        return result;
     }
+    let now = slopos_lib::clock::uptime_ms();
+    if let Some(query) = DNS_QUERIES.lock().find(hostname, now) {
+        return match query.state {
+            QueryState::Done(result, _) => result,
+            _ => DnsLookup::Pending,
+        };
+    }
+    dns_start_query(hostname)
+}
 
-    None
+/// Resolve a hostname to an IPv4 address.
+///
+/// IP literals resolve directly.  Otherwise the cache answers, or a query
+/// is started (or joined, if another task is already asking).  With
+/// `nonblocking` the call then returns [`DnsLookup::Pending`]; call again
+/// later for the result.  Without it the call waits until the query
+/// finishes.
+pub fn dns_lookup(hostname: &[u8], nonblocking: bool) -> DnsLookup {
+    // Shortcut: if it looks like an IP literal, parse it
+    if let Some(addr) = parse_ip_literal(hostname) {
+        return DnsLookup::Found(addr);
+    }
+    if hostname.is_empty() || hostname.len() > DNS_NAME_MAX {
+        return DnsLookup::Failed;
+    }
+
+    let deadline = slopos_lib::clock::uptime_ms() + DNS_LOOKUP_BUDGET_MS;
+    loop {
+        let result = dns_lookup_step(hostname);
+        if result != DnsLookup::Pending || nonblocking {
+            return result;
+        }
+        if slopos_lib::clock::uptime_ms() >= deadline {
+            return DnsLookup::Failed;
+        }
+        // Runs NAPI, which delivers replies and fires the retry timers.
+        crate::virtio_net::net_rx_wait(DNS_WAIT_SLICE_MS);
+    }
+}
+
+/// Resolve a hostname to an IPv4 address, waiting for the answer.
+pub fn dns_resolve(hostname: &[u8]) -> Option<[u8; 4]> {
+    match dns_lookup(hostname, false) {
+        DnsLookup::Found(addr) => Some(addr),
+        _ => None,
+    }
 }
 
 /// Try to parse a dotted-decimal IPv4 literal (e.g., `"10.0.2.3"`).
-fn parse_ip_literal(s: &[u8]) -> Option<[u8; 4]> {
+pub(crate) fn parse_ip_literal(s: &[u8]) -> Option<[u8; 4]> {
     let mut octets = [0u8; 4];
     let mut octet_idx = 0usize;
     let mut current: u16 = 0;
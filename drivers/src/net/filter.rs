@@ -0,0 +1,283 @@
+//! IPv4 packet filter.
+//!
+//! An ordered rule table consulted once per packet in the IP layer:
+//! [`ipv4::handle_rx`] checks input before protocol dispatch, and
+//! [`ipv4::send`] plus the TCP segment path check output before the frame
+//! reaches a device.  The first rule that matches decides; packets no rule
+//! matches get the direction's default policy, which starts out as accept.
+//!
+//! Rules match on protocol, a CIDR block for the remote address and an
+//! inclusive destination port range.  With no rules and both policies at
+//! accept the filter is idle and a packet costs one atomic load.
+//!
+//! [`ipv4::handle_rx`]: super::ipv4::handle_rx
+//! [`ipv4::send`]: super::ipv4::send
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_abi::net::{
+    NET_FILTER_ACCEPT, NET_FILTER_DROP, NET_FILTER_IN, NET_FILTER_MAX_RULES, NET_FILTER_OUT,
+    NET_FILTER_PROTO_ANY, NetFilterRule, NetFilterStatus,
+};
+use slopos_lib::IrqMutex;
+
+use super::types::{Ipv4Addr, NetError};
+
+/// The parts of a packet rules look at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FilterPacket {
+    pub proto: u8,
+    /// Source address on input, destination address on output.
+    pub remote: Ipv4Addr,
+    /// Destination port for TCP and UDP, 0 otherwise.
+    pub dst_port: u16,
+}
+
+impl FilterPacket {
+    /// Describe a packet from its protocol, remote address and L4 bytes.
+    pub fn new(proto: u8, remote: Ipv4Addr, l4: &[u8]) -> Self {
+        let dst_port = match proto {
+            super::IPPROTO_TCP | super::IPPROTO_UDP if l4.len() >= 4 => {
+                u16::from_be_bytes([l4[2], l4[3]])
+            }
+            _ => 0,
+        };
+        Self {
+            proto,
+            remote,
+            dst_port,
+        }
+    }
+}
+
+const fn prefix_mask(prefix_len: u8) -> u32 {
+    if prefix_len == 0 {
+        0
+    } else {
+        u32::MAX << (32 - prefix_len as u32)
+    }
+}
+
+/// Check a rule from userland and mask its address to the prefix.
+fn normalize(mut rule: NetFilterRule) -> Result<NetFilterRule, NetError> {
+    let dirs = NET_FILTER_IN | NET_FILTER_OUT;
+    if rule.direction == 0 || rule.direction & !dirs != 0 {
+        return Err(NetError::InvalidArgument);
+    }
+    if !matches!(rule.action, NET_FILTER_ACCEPT | NET_FILTER_DROP) || rule.prefix_len > 32 {
+        return Err(NetError::InvalidArgument);
+    }
+    let any_port = rule.port_min == 0 && rule.port_max == 0;
+    if !any_port {
+        if rule.port_min > rule.port_max {
+            return Err(NetError::InvalidArgument);
+        }
+        if !matches!(rule.proto, super::IPPROTO_TCP | super::IPPROTO_UDP) {
+            return Err(NetError::InvalidArgument);
+        }
+    }
+    let addr = u32::from_be_bytes(rule.addr) & prefix_mask(rule.prefix_len);
+    rule.addr = addr.to_be_bytes();
+    rule._pad = [0; 4];
+    rule.hits = 0;
+    Ok(rule)
+}
+
+fn rule_matches(rule: &NetFilterRule, direction: u8, pkt: &FilterPacket) -> bool {
+    if rule.direction & direction == 0 {
+        return false;
+    }
+    if rule.proto != NET_FILTER_PROTO_ANY && rule.proto != pkt.proto {
+        return false;
+    }
+    let mask = prefix_mask(rule.prefix_len);
+    if u32::from_be_bytes(pkt.remote.0) & mask != u32::from_be_bytes(rule.addr) {
+        return false;
+    }
+    let any_port = rule.port_min == 0 && rule.port_max == 0;
+    any_port || (rule.port_min..=rule.port_max).contains(&pkt.dst_port)
+}
+
+pub struct FilterTable {
+    rules: [NetFilterRule; NET_FILTER_MAX_RULES],
+    len: usize,
+    policy_in: u8,
+    policy_out: u8,
+    dropped_in: u64,
+    dropped_out: u64,
+}
+
+impl FilterTable {
+    pub const fn new() -> Self {
+        Self {
+            rules: [NetFilterRule {
+                direction: 0,
+                action: 0,
+                proto: 0,
+                prefix_len: 0,
+                addr: [0; 4],
+                port_min: 0,
+                port_max: 0,
+                _pad: [0; 4],
+                hits: 0,
+            }; NET_FILTER_MAX_RULES],
+            len: 0,
+            policy_in: NET_FILTER_ACCEPT,
+            policy_out: NET_FILTER_ACCEPT,
+            dropped_in: 0,
+            dropped_out: 0,
+        }
+    }
+
+    /// Add a rule at the end.  Returns its index.
+    pub fn append(&mut self, rule: NetFilterRule) -> Result<usize, NetError> {
+        let rule = normalize(rule)?;
+        if self.len == NET_FILTER_MAX_RULES {
+            return Err(NetError::NoBufferSpace);
+        }
+        self.rules[self.len] = rule;
+        self.len += 1;
+        Ok(self.len - 1)
+    }
+
+    /// Remove the rule at `idx`; later rules move up by one.
+    pub fn delete(&mut self, idx: usize) -> Result<(), NetError> {
+        if idx >= self.len {
+            return Err(NetError::InvalidArgument);
+        }
+        self.rules.copy_within(idx + 1..self.len, idx);
+        self.len -= 1;
+        Ok(())
+    }
+
+    /// Remove every rule and zero the drop counters.  Policies are kept.
+    pub fn flush(&mut self) {
+        self.len = 0;
+        self.dropped_in = 0;
+        self.dropped_out = 0;
+    }
+
+    /// Set the default verdict for one or both directions.
+    pub fn set_policy(&mut self, direction: u8, action: u8) -> Result<(), NetError> {
+        let dirs = NET_FILTER_IN | NET_FILTER_OUT;
+        if direction == 0 || direction & !dirs != 0 {
+            return Err(NetError::InvalidArgument);
+        }
+        if !matches!(action, NET_FILTER_ACCEPT | NET_FILTER_DROP) {
+            return Err(NetError::InvalidArgument);
+        }
+        if direction & NET_FILTER_IN != 0 {
+            self.policy_in = action;
+        }
+        if direction & NET_FILTER_OUT != 0 {
+            self.policy_out = action;
+        }
+        Ok(())
+    }
+
+    pub fn rules(&self) -> &[NetFilterRule] {
+        &self.rules[..self.len]
+    }
+
+    pub fn status(&self) -> NetFilterStatus {
+        NetFilterStatus {
+            rule_count: self.len as u32,
+            policy_in: self.policy_in,
+            policy_out: self.policy_out,
+            _pad: [0; 2],
+            dropped_in: self.dropped_in,
+            dropped_out: self.dropped_out,
+        }
+    }
+
+    /// Whether every packet would be accepted without looking at it.
+    pub fn is_idle(&self) -> bool {
+        self.len == 0 && self.policy_in == NET_FILTER_ACCEPT && self.policy_out == NET_FILTER_ACCEPT
+    }
+
+    /// Decide on one packet travelling in `direction` (`NET_FILTER_IN` or
+    /// `NET_FILTER_OUT`), counting the hit and any drop.
+    pub fn evaluate(&mut self, direction: u8, pkt: &FilterPacket) -> u8 {
+        let verdict = match self.rules[..self.len]
+            .iter_mut()
+            .find(|rule| rule_matches(rule, direction, pkt))
+        {
+            Some(rule) => {
+                rule.hits += 1;
+                rule.action
+            }
+            None if direction == NET_FILTER_IN => self.policy_in,
+            None => self.policy_out,
+        };
+        if verdict == NET_FILTER_DROP {
+            if direction == NET_FILTER_IN {
+                self.dropped_in += 1;
+            } else {
+                self.dropped_out += 1;
+            }
+        }
+        verdict
+    }
+}
+
+impl Default for FilterTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub static NET_FILTER: IrqMutex<FilterTable> = IrqMutex::new(FilterTable::new());
+
+/// Mirrors `!NET_FILTER.is_idle()` so idle packets skip the lock.
+static FILTER_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn update<R>(f: impl FnOnce(&mut FilterTable) -> R) -> R {
+    let mut table = NET_FILTER.lock();
+    let result = f(&mut table);
+    FILTER_ACTIVE.store(!table.is_idle(), Ordering::Release);
+    result
+}
+
+pub fn append(rule: NetFilterRule) -> Result<usize, NetError> {
+    update(|table| table.append(rule))
+}
+
+pub fn delete(idx: usize) -> Result<(), NetError> {
+    update(|table| table.delete(idx))
+}
+
+pub fn flush() {
+    update(FilterTable::flush);
+}
+
+pub fn set_policy(direction: u8, action: u8) -> Result<(), NetError> {
+    update(|table| table.set_policy(direction, action))
+}
+
+/// Copy rules (with hit counts) into `out`.  Returns how many were copied.
+pub fn list(out: &mut [NetFilterRule]) -> usize {
+    let table = NET_FILTER.lock();
+    let rules = table.rules();
+    let count = rules.len().min(out.len());
+    out[..count].copy_from_slice(&rules[..count]);
+    count
+}
+
+pub fn status() -> NetFilterStatus {
+    NET_FILTER.lock().status()
+}
+
+/// Remove all rules and restore the accept policies.
+pub fn reset() {
+    update(|table| *table = FilterTable::new());
+}
+
+/// Whether a packet may pass.  `l4` starts at the TCP/UDP header.
+#[inline]
+pub fn allows(direction: u8, proto: u8, remote: Ipv4Addr, l4: &[u8]) -> bool {
+    if !FILTER_ACTIVE.load(Ordering::Acquire) {
+        return true;
+    }
+    let pkt = FilterPacket::new(proto, remote, l4);
+    NET_FILTER.lock().evaluate(direction, &pkt) == NET_FILTER_ACCEPT
+}
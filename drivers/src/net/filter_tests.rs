@@ -0,0 +1,292 @@
+//! Packet filter tests: rule validation and matching on a private table,
+//! then the input and output hooks end to end.
+
+use slopos_abi::net::{
+    AF_INET, NET_FILTER_ACCEPT, NET_FILTER_DROP, NET_FILTER_IN, NET_FILTER_MAX_RULES,
+    NET_FILTER_OUT, NetFilterRule, SOCK_DGRAM,
+};
+use slopos_abi::syscall::ERRNO_EPERM;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::filter::{self, FilterPacket, FilterTable};
+use super::packetbuf::PacketBuf;
+use super::socket::*;
+use super::types::{DevIndex, Ipv4Addr, NetError};
+use super::{IPPROTO_ICMP, IPPROTO_TCP, IPPROTO_UDP};
+
+const REMOTE: Ipv4Addr = Ipv4Addr([10, 0, 0, 9]);
+const OTHER: Ipv4Addr = Ipv4Addr([192, 168, 1, 5]);
+
+const EPERM: i64 = ERRNO_EPERM as i64;
+
+fn rule(direction: u8, action: u8, proto: u8) -> NetFilterRule {
+    NetFilterRule {
+        direction,
+        action,
+        proto,
+        ..NetFilterRule::default()
+    }
+}
+
+fn packet(proto: u8, remote: Ipv4Addr, dst_port: u16) -> FilterPacket {
+    FilterPacket {
+        proto,
+        remote,
+        dst_port,
+    }
+}
+
+fn queued(sock: u32) -> usize {
+    NEW_SOCKET_TABLE
+        .lock()
+        .get(sock as usize)
+        .map_or(0, |s| s.recv_queue.len())
+}
+
+/// Run a UDP datagram from `REMOTE` through the full IPv4 receive path.
+fn receive_ipv4_udp(dst_port: u16, payload: &[u8]) -> bool {
+    let Some(mut pkt) = PacketBuf::alloc() else {
+        return false;
+    };
+    let udp_len = 8 + payload.len();
+    let mut hdr = [0u8; super::IPV4_HEADER_LEN + 8];
+    hdr[0] = 0x45;
+    hdr[2..4].copy_from_slice(&((super::IPV4_HEADER_LEN + udp_len) as u16).to_be_bytes());
+    hdr[8] = 64;
+    hdr[9] = IPPROTO_UDP;
+    hdr[12..16].copy_from_slice(&REMOTE.0);
+    hdr[16..20].copy_from_slice(&[127, 0, 0, 1]);
+    let checksum = super::ipv4_header_checksum(&hdr[..super::IPV4_HEADER_LEN]);
+    hdr[10..12].copy_from_slice(&checksum.to_be_bytes());
+    let udp = &mut hdr[super::IPV4_HEADER_LEN..];
+    udp[0..2].copy_from_slice(&4000u16.to_be_bytes());
+    udp[2..4].copy_from_slice(&dst_port.to_be_bytes());
+    udp[4..6].copy_from_slice(&(udp_len as u16).to_be_bytes());
+    if pkt.append(&hdr).is_err() || pkt.append(payload).is_err() {
+        return false;
+    }
+    super::ipv4::handle_rx(DevIndex(0), pkt, false);
+    true
+}
+
+pub fn test_filter_rule_validation() -> TestResult {
+    let mut table = FilterTable::new();
+    let invalid = Err(NetError::InvalidArgument);
+    assert_eq_test!(
+        table.append(rule(0, NET_FILTER_DROP, 0)),
+        invalid,
+        "no direction"
+    );
+    assert_eq_test!(
+        table.append(rule(4, NET_FILTER_DROP, 0)),
+        invalid,
+        "bad direction"
+    );
+    assert_eq_test!(
+        table.append(rule(NET_FILTER_IN, 7, 0)),
+        invalid,
+        "bad action"
+    );
+
+    let mut wide = rule(NET_FILTER_IN, NET_FILTER_DROP, 0);
+    wide.prefix_len = 33;
+    assert_eq_test!(table.append(wide), invalid, "prefix over 32");
+
+    let mut ports = rule(NET_FILTER_IN, NET_FILTER_DROP, IPPROTO_ICMP);
+    ports.port_min = 22;
+    ports.port_max = 22;
+    assert_eq_test!(table.append(ports), invalid, "ports on ICMP");
+    ports.proto = IPPROTO_TCP;
+    ports.port_min = 80;
+    assert_eq_test!(table.append(ports), invalid, "reversed port range");
+    assert_eq_test!(table.rules().len(), 0, "invalid rule stored");
+
+    let mut net = rule(NET_FILTER_IN, NET_FILTER_DROP, 0);
+    net.addr = [10, 1, 2, 3];
+    net.prefix_len = 8;
+    net.hits = 99;
+    assert_eq_test!(table.append(net), Ok(0));
+    let stored = table.rules()[0];
+    assert_eq_test!(stored.addr, [10, 0, 0, 0], "address not masked");
+    assert_eq_test!(stored.hits, 0, "hit count taken from caller");
+    pass!()
+}
+
+pub fn test_filter_first_match_wins() -> TestResult {
+    let mut table = FilterTable::new();
+    let mut ssh = rule(NET_FILTER_IN, NET_FILTER_ACCEPT, IPPROTO_TCP);
+    ssh.port_min = 22;
+    ssh.port_max = 22;
+    let _ = table.append(ssh);
+    let _ = table.append(rule(NET_FILTER_IN, NET_FILTER_DROP, IPPROTO_TCP));
+
+    let to_ssh = packet(IPPROTO_TCP, REMOTE, 22);
+    let to_http = packet(IPPROTO_TCP, REMOTE, 80);
+    assert_eq_test!(table.evaluate(NET_FILTER_IN, &to_ssh), NET_FILTER_ACCEPT);
+    assert_eq_test!(table.evaluate(NET_FILTER_IN, &to_http), NET_FILTER_DROP);
+    assert_eq_test!(table.evaluate(NET_FILTER_IN, &to_http), NET_FILTER_DROP);
+    assert_eq_test!(
+        table.evaluate(NET_FILTER_OUT, &to_http),
+        NET_FILTER_ACCEPT,
+        "input rule applied to output"
+    );
+
+    let hits = [table.rules()[0].hits, table.rules()[1].hits];
+    assert_eq_test!(hits, [1, 2], "hit counters");
+    let status = table.status();
+    assert_eq_test!((status.dropped_in, status.dropped_out), (2, 0));
+    pass!()
+}
+
+pub fn test_filter_cidr_and_port_range() -> TestResult {
+    let mut table = FilterTable::new();
+    let mut lan = rule(NET_FILTER_IN | NET_FILTER_OUT, NET_FILTER_DROP, IPPROTO_UDP);
+    lan.addr = [192, 168, 0, 0];
+    lan.prefix_len = 16;
+    lan.port_min = 5000;
+    lan.port_max = 5010;
+    let _ = table.append(lan);
+
+    let cases = [
+        (packet(IPPROTO_UDP, OTHER, 5000), NET_FILTER_DROP),
+        (packet(IPPROTO_UDP, OTHER, 5010), NET_FILTER_DROP),
+        (packet(IPPROTO_UDP, OTHER, 5011), NET_FILTER_ACCEPT),
+        (packet(IPPROTO_UDP, REMOTE, 5005), NET_FILTER_ACCEPT),
+        (packet(IPPROTO_TCP, OTHER, 5005), NET_FILTER_ACCEPT),
+    ];
+    for (pkt, verdict) in cases {
+        assert_eq_test!(table.evaluate(NET_FILTER_OUT, &pkt), verdict);
+    }
+    pass!()
+}
+
+pub fn test_filter_policy_and_idle() -> TestResult {
+    let mut table = FilterTable::new();
+    assert_test!(table.is_idle(), "new table not idle");
+    assert_eq_test!(
+        table.set_policy(NET_FILTER_IN, 2),
+        Err(NetError::InvalidArgument)
+    );
+    assert_eq_test!(table.set_policy(NET_FILTER_IN, NET_FILTER_DROP), Ok(()));
+    assert_test!(!table.is_idle(), "drop policy left the table idle");
+
+    let ping = packet(IPPROTO_ICMP, REMOTE, 0);
+    assert_eq_test!(table.evaluate(NET_FILTER_IN, &ping), NET_FILTER_DROP);
+    assert_eq_test!(table.evaluate(NET_FILTER_OUT, &ping), NET_FILTER_ACCEPT);
+
+    let mut allow = rule(NET_FILTER_IN, NET_FILTER_ACCEPT, IPPROTO_ICMP);
+    allow.addr = REMOTE.0;
+    allow.prefix_len = 32;
+    let _ = table.append(allow);
+    assert_eq_test!(table.evaluate(NET_FILTER_IN, &ping), NET_FILTER_ACCEPT);
+    let other = packet(IPPROTO_ICMP, OTHER, 0);
+    assert_eq_test!(table.evaluate(NET_FILTER_IN, &other), NET_FILTER_DROP);
+
+    table.flush();
+    let status = table.status();
+    assert_eq_test!(status.rule_count, 0, "flush kept rules");
+    assert_eq_test!(status.dropped_in, 0, "flush kept counters");
+    assert_eq_test!(status.policy_in, NET_FILTER_DROP, "flush reset policy");
+    pass!()
+}
+
+pub fn test_filter_table_full_and_delete() -> TestResult {
+    let mut table = FilterTable::new();
+    for proto in 0..NET_FILTER_MAX_RULES {
+        if table
+            .append(rule(NET_FILTER_IN, NET_FILTER_DROP, proto as u8))
+            .is_err()
+        {
+            return fail!("append {} failed", proto);
+        }
+    }
+    assert_eq_test!(
+        table.append(rule(NET_FILTER_IN, NET_FILTER_DROP, 0)),
+        Err(NetError::NoBufferSpace)
+    );
+    assert_eq_test!(
+        table.delete(NET_FILTER_MAX_RULES),
+        Err(NetError::InvalidArgument)
+    );
+    assert_eq_test!(table.delete(1), Ok(()));
+    assert_eq_test!(table.rules().len(), NET_FILTER_MAX_RULES - 1);
+    let protos = [table.rules()[0].proto, table.rules()[1].proto];
+    assert_eq_test!(protos, [0, 2], "later rules did not move up");
+    pass!()
+}
+
+pub fn test_filter_packet_ports() -> TestResult {
+    let l4 = [0x0f, 0xa0, 0x00, 0x35, 0, 8, 0, 0];
+    let udp = FilterPacket::new(IPPROTO_UDP, REMOTE, &l4);
+    assert_eq_test!(udp.dst_port, 53, "UDP destination port");
+    let icmp = FilterPacket::new(IPPROTO_ICMP, REMOTE, &l4);
+    assert_eq_test!(icmp.dst_port, 0, "port read from ICMP");
+    let short = FilterPacket::new(IPPROTO_TCP, REMOTE, &l4[..3]);
+    assert_eq_test!(short.dst_port, 0, "port read past the end");
+    pass!()
+}
+
+pub fn test_filter_drops_input() -> TestResult {
+    socket_reset_all();
+    filter::reset();
+    let sock = socket_create(AF_INET, SOCK_DGRAM, 0);
+    if sock < 0 {
+        return fail!("socket_create failed");
+    }
+    let sock = sock as u32;
+    assert_eq_test!(socket_bind(sock, [0, 0, 0, 0], 6100), 0, "bind");
+
+    let mut block = rule(NET_FILTER_IN, NET_FILTER_DROP, IPPROTO_UDP);
+    block.port_min = 6100;
+    block.port_max = 6100;
+    assert_eq_test!(filter::append(block), Ok(0));
+
+    assert_test!(receive_ipv4_udp(6100, b"blocked"), "packet alloc");
+    let blocked = queued(sock);
+    let dropped = filter::status().dropped_in;
+    filter::reset();
+    assert_test!(receive_ipv4_udp(6100, b"allowed"), "packet alloc");
+    let allowed = queued(sock);
+
+    assert_eq_test!(blocked, 0, "filtered datagram delivered");
+    assert_eq_test!(dropped, 1, "drop not counted");
+    assert_eq_test!(allowed, 1, "datagram lost after reset");
+    pass!()
+}
+
+pub fn test_filter_output_is_eperm() -> TestResult {
+    socket_reset_all();
+    filter::reset();
+    let sock = socket_create(AF_INET, SOCK_DGRAM, 0);
+    if sock < 0 {
+        return fail!("socket_create failed");
+    }
+    let mut block = rule(NET_FILTER_OUT, NET_FILTER_DROP, IPPROTO_UDP);
+    block.addr = REMOTE.0;
+    block.prefix_len = 32;
+    let _ = filter::append(block);
+
+    let data = b"hello";
+    let rc = socket_sendto(sock as u32, data.as_ptr(), data.len(), REMOTE.0, 9);
+    let dropped = filter::status().dropped_out;
+    filter::reset();
+
+    assert_eq_test!(rc, EPERM, "filtered send");
+    assert_eq_test!(dropped, 1, "drop not counted");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    net_filter,
+    [
+        test_filter_rule_validation,
+        test_filter_first_match_wins,
+        test_filter_cidr_and_port_range,
+        test_filter_policy_and_idle,
+        test_filter_table_full_and_delete,
+        test_filter_packet_ports,
+        test_filter_drops_input,
+        test_filter_output_is_eperm,
+    ]
+);
@@ -0,0 +1,345 @@
+//! ICMP echo (RFC 792): answering pings and sending our own.
+//!
+//! Echo requests addressed to one of our unicast addresses are answered with
+//! a reply carrying the same identifier, sequence number and data.  Requests
+//! sent to a broadcast or multicast address are ignored, as most hosts do.
+//!
+//! [`ping`] sends one echo request and waits for the matching reply.  The
+//! round-trip time is taken when the reply is processed on the receive path,
+//! so it does not include however long the waiting task takes to be
+//! scheduled again.  Other ICMP types are logged and dropped.
+
+use core::sync::atomic::{AtomicU16, Ordering};
+
+use slopos_abi::net::NET_PING_MAX_PAYLOAD;
+use slopos_lib::{IrqMutex, clock, klog_debug};
+
+use super::packetbuf::PacketBuf;
+use super::route::ROUTE_TABLE;
+use super::types::{Ipv4Addr, NetError};
+
+pub const ICMP_HEADER_LEN: usize = 8;
+
+pub const ICMP_ECHO_REPLY: u8 = 0;
+pub const ICMP_DEST_UNREACHABLE: u8 = 3;
+pub const ICMP_ECHO_REQUEST: u8 = 8;
+pub const ICMP_TIME_EXCEEDED: u8 = 11;
+
+/// Largest echo payload that fits an unfragmented 1500-byte frame.
+pub const ICMP_ECHO_MAX_PAYLOAD: usize = NET_PING_MAX_PAYLOAD as usize;
+const _: () = assert!(ICMP_ECHO_MAX_PAYLOAD == 1500 - super::IPV4_HEADER_LEN - ICMP_HEADER_LEN);
+
+/// TTL of the datagrams we send.
+const ICMP_TTL: u8 = 64;
+
+/// Pings in flight across all callers.
+pub const MAX_PENDING_PINGS: usize = 8;
+
+/// Longest a caller may wait in [`ping`].
+pub const PING_MAX_TIMEOUT_MS: u32 = 10_000;
+
+/// Identifier for kernel-originated pings without one of their own.
+static NEXT_IDENT: AtomicU16 = AtomicU16::new(0x5100);
+
+/// Identifier, sequence number and type of an echo message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EchoHeader {
+    pub kind: u8,
+    pub ident: u16,
+    pub seq: u16,
+}
+
+/// What came back for one ping.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PingReply {
+    pub rtt_us: u64,
+    /// TTL of the reply datagram.
+    pub ttl: u8,
+    /// Echo data bytes in the reply.
+    pub len: u16,
+}
+
+/// Fill in the echo header at the front of `msg`, whose data is already in
+/// place after it, and checksum the whole message.
+fn finish_echo(msg: &mut [u8], kind: u8, ident: u16, seq: u16) {
+    msg[0] = kind;
+    msg[1] = 0;
+    msg[2..4].copy_from_slice(&0u16.to_be_bytes());
+    msg[4..6].copy_from_slice(&ident.to_be_bytes());
+    msg[6..8].copy_from_slice(&seq.to_be_bytes());
+    let checksum = super::ipv4_header_checksum(msg);
+    msg[2..4].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// Write an echo message (`kind` is request or reply) into `out`, checksum
+/// included.  Returns the message length, or `None` if `out` is too small.
+pub fn build_echo(out: &mut [u8], kind: u8, ident: u16, seq: u16, data: &[u8]) -> Option<usize> {
+    let msg = out.get_mut(..ICMP_HEADER_LEN + data.len())?;
+    msg[ICMP_HEADER_LEN..].copy_from_slice(data);
+    finish_echo(msg, kind, ident, seq);
+    Some(msg.len())
+}
+
+/// Parse an echo request or reply, verifying the checksum.
+pub fn parse_echo(msg: &[u8]) -> Option<EchoHeader> {
+    if msg.len() < ICMP_HEADER_LEN || super::ipv4_header_checksum(msg) != 0 {
+        return None;
+    }
+    let kind = msg[0];
+    if !matches!(kind, ICMP_ECHO_REQUEST | ICMP_ECHO_REPLY) || msg[1] != 0 {
+        return None;
+    }
+    Some(EchoHeader {
+        kind,
+        ident: u16::from_be_bytes([msg[4], msg[5]]),
+        seq: u16::from_be_bytes([msg[6], msg[7]]),
+    })
+}
+
+#[derive(Clone, Copy)]
+struct PendingPing {
+    dst: Ipv4Addr,
+    ident: u16,
+    seq: u16,
+    sent_ns: u64,
+    reply: Option<PingReply>,
+}
+
+/// Echo requests waiting for their reply.
+pub struct PingTable {
+    slots: [Option<PendingPing>; MAX_PENDING_PINGS],
+}
+
+impl PingTable {
+    pub const fn new() -> Self {
+        Self {
+            slots: [None; MAX_PENDING_PINGS],
+        }
+    }
+
+    /// Start waiting for the reply to `(ident, seq)` from `dst`.  Returns the
+    /// slot to poll.
+    pub fn begin(
+        &mut self,
+        dst: Ipv4Addr,
+        ident: u16,
+        seq: u16,
+        now_ns: u64,
+    ) -> Result<usize, NetError> {
+        let idx = self
+            .slots
+            .iter()
+            .position(Option::is_none)
+            .ok_or(NetError::NoBufferSpace)?;
+        self.slots[idx] = Some(PendingPing {
+            dst,
+            ident,
+            seq,
+            sent_ns: now_ns,
+            reply: None,
+        });
+        Ok(idx)
+    }
+
+    /// Record an echo reply.  Returns `false` if nobody was waiting for it.
+    pub fn complete(
+        &mut self,
+        src: Ipv4Addr,
+        hdr: EchoHeader,
+        ttl: u8,
+        len: u16,
+        now_ns: u64,
+    ) -> bool {
+        let Some(pending) = self.slots.iter_mut().flatten().find(|p| {
+            p.reply.is_none() && p.dst == src && p.ident == hdr.ident && p.seq == hdr.seq
+        }) else {
+            return false;
+        };
+        pending.reply = Some(PingReply {
+            rtt_us: now_ns.saturating_sub(pending.sent_ns) / 1_000,
+            ttl,
+            len,
+        });
+        true
+    }
+
+    /// The reply for `slot`, if it has arrived.  Frees the slot when it has.
+    pub fn take(&mut self, slot: usize) -> Option<PingReply> {
+        let reply = self.slots.get(slot)?.as_ref()?.reply?;
+        self.slots[slot] = None;
+        Some(reply)
+    }
+
+    /// Give up on `slot`.
+    pub fn cancel(&mut self, slot: usize) {
+        if let Some(entry) = self.slots.get_mut(slot) {
+            *entry = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.slots = [None; MAX_PENDING_PINGS];
+    }
+}
+
+impl Default for PingTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub static PING_TABLE: IrqMutex<PingTable> = IrqMutex::new(PingTable::new());
+
+/// Handle an ICMP message addressed to us.  `ttl` is from the IP header.
+pub fn handle_rx(src_ip: [u8; 4], dst_ip: [u8; 4], ttl: u8, msg: &[u8]) {
+    let src = Ipv4Addr(src_ip);
+    let dst = Ipv4Addr(dst_ip);
+    let Some(&kind) = msg.first() else {
+        return;
+    };
+    if !matches!(kind, ICMP_ECHO_REQUEST | ICMP_ECHO_REPLY) {
+        klog_debug!("icmp: type {} from {}, dropping", kind, src);
+        return;
+    }
+    let Some(hdr) = parse_echo(msg) else {
+        klog_debug!("icmp: malformed echo from {}", src);
+        return;
+    };
+
+    if hdr.kind == ICMP_ECHO_REPLY {
+        let len = (msg.len() - ICMP_HEADER_LEN) as u16;
+        let now_ns = clock::monotonic_ns();
+        if !PING_TABLE.lock().complete(src, hdr, ttl, len, now_ns) {
+            klog_debug!("icmp: unexpected echo reply from {} seq {}", src, hdr.seq);
+        }
+        return;
+    }
+
+    if dst.is_multicast() || dst.is_broadcast() || super::NET_STACK.is_broadcast_addr(dst) {
+        return;
+    }
+    let data = &msg[ICMP_HEADER_LEN..];
+    let result = build_packet(ICMP_ECHO_REPLY, hdr.ident, hdr.seq, data, dst, src)
+        .and_then(|pkt| super::ipv4::send(src, pkt));
+    if let Err(err) = result {
+        klog_debug!("icmp: echo reply to {} not sent: {}", src, err);
+    }
+}
+
+/// Pick the source address for a datagram to `dst`: the address of the
+/// interface the route goes out of.
+fn source_for(dst: Ipv4Addr) -> Ipv4Addr {
+    ROUTE_TABLE
+        .lookup(dst)
+        .and_then(|(dev, _)| super::NET_STACK.our_ip(dev))
+        .or_else(|| super::NET_STACK.first_ipv4())
+        .unwrap_or(Ipv4Addr::UNSPECIFIED)
+}
+
+/// Send one echo request to `dst` with `payload_len` bytes of data and wait
+/// up to `timeout_ms` for the reply.  `ident` 0 picks a kernel identifier.
+pub fn ping(
+    dst: Ipv4Addr,
+    ident: u16,
+    seq: u16,
+    payload_len: usize,
+    timeout_ms: u32,
+) -> Result<PingReply, NetError> {
+    if payload_len > ICMP_ECHO_MAX_PAYLOAD || dst.is_unspecified() || timeout_ms == 0 {
+        return Err(NetError::InvalidArgument);
+    }
+    let timeout_ms = timeout_ms.min(PING_MAX_TIMEOUT_MS);
+    let ident = if ident == 0 {
+        NEXT_IDENT.fetch_add(1, Ordering::Relaxed)
+    } else {
+        ident
+    };
+
+    // The classic pattern: each data byte is its offset.
+    let mut data = [0u8; ICMP_ECHO_MAX_PAYLOAD];
+    for (i, byte) in data[..payload_len].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    let pkt = build_packet(
+        ICMP_ECHO_REQUEST,
+        ident,
+        seq,
+        &data[..payload_len],
+        source_for(dst),
+        dst,
+    )?;
+
+    // Register before sending: on loopback the reply can be processed
+    // before `send` returns.
+    let slot = PING_TABLE
+        .lock()
+        .begin(dst, ident, seq, clock::monotonic_ns())?;
+    if let Err(err) = super::ipv4::send(dst, pkt) {
+        PING_TABLE.lock().cancel(slot);
+        return Err(err);
+    }
+
+    let start_ms = clock::uptime_ms();
+    loop {
+        if let Some(reply) = PING_TABLE.lock().take(slot) {
+            return Ok(reply);
+        }
+        let elapsed = clock::uptime_ms().saturating_sub(start_ms);
+        if elapsed >= timeout_ms as u64 {
+            PING_TABLE.lock().cancel(slot);
+            return Err(NetError::TimedOut);
+        }
+        let remaining = timeout_ms - elapsed as u32;
+        crate::virtio_net::net_rx_wait(remaining.min(100));
+    }
+}
+
+/// Forget every ping in flight.
+pub fn reset() {
+    PING_TABLE.lock().clear();
+}
+
+fn build_packet(
+    kind: u8,
+    ident: u16,
+    seq: u16,
+    data: &[u8],
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+) -> Result<PacketBuf, NetError> {
+    let msg_len = ICMP_HEADER_LEN + data.len();
+    let mut pkt = PacketBuf::alloc().ok_or(NetError::NoBufferSpace)?;
+    pkt.append(&[0; ICMP_HEADER_LEN])?;
+    pkt.append(data)?;
+    finish_echo(pkt.payload_mut(), kind, ident, seq);
+
+    let total_len = (super::IPV4_HEADER_LEN + msg_len) as u16;
+    {
+        let ip_hdr = pkt.push_header(super::IPV4_HEADER_LEN)?;
+        ip_hdr[0] = 0x45;
+        ip_hdr[1] = 0;
+        ip_hdr[2..4].copy_from_slice(&total_len.to_be_bytes());
+        ip_hdr[4..6].copy_from_slice(&seq.to_be_bytes());
+        ip_hdr[6..8].copy_from_slice(&0u16.to_be_bytes());
+        ip_hdr[8] = ICMP_TTL;
+        ip_hdr[9] = super::IPPROTO_ICMP;
+        ip_hdr[10..12].copy_from_slice(&0u16.to_be_bytes());
+        ip_hdr[12..16].copy_from_slice(&src.0);
+        ip_hdr[16..20].copy_from_slice(&dst.0);
+        let checksum = super::ipv4_header_checksum(ip_hdr);
+        ip_hdr[10..12].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    {
+        let eth_hdr = pkt.push_header(super::ETH_HEADER_LEN)?;
+        eth_hdr[0..6].copy_from_slice(&super::ETH_BROADCAST);
+        eth_hdr[6..12].copy_from_slice(&crate::virtio_net::virtio_net_mac().unwrap_or([0; 6]));
+        eth_hdr[12..14].copy_from_slice(&super::ETHERTYPE_IPV4.to_be_bytes());
+    }
+
+    let head = pkt.head();
+    pkt.set_l2(head);
+    pkt.set_l3(head + super::ETH_HEADER_LEN as u16);
+    pkt.set_l4(head + (super::ETH_HEADER_LEN + super::IPV4_HEADER_LEN) as u16);
+    Ok(pkt)
+}
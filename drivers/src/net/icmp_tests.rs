@@ -0,0 +1,193 @@
+//! ICMP echo tests: message format, the pending-ping table, reply matching
+//! on the receive path and the argument checks in `ping`.
+
+use slopos_abi::net::{NET_FILTER_DROP, NET_FILTER_OUT, NetFilterRule};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::IPPROTO_ICMP;
+use super::filter;
+use super::icmp::{
+    self, EchoHeader, ICMP_DEST_UNREACHABLE, ICMP_ECHO_MAX_PAYLOAD, ICMP_ECHO_REPLY,
+    ICMP_ECHO_REQUEST, ICMP_HEADER_LEN, MAX_PENDING_PINGS, PING_TABLE, PingTable,
+};
+use super::types::{IpProtocol, Ipv4Addr, NetError};
+
+const REMOTE: Ipv4Addr = Ipv4Addr([10, 0, 0, 9]);
+const OTHER: Ipv4Addr = Ipv4Addr([10, 0, 0, 10]);
+const LOCAL: [u8; 4] = [10, 0, 0, 2];
+
+fn echo_header(kind: u8, ident: u16, seq: u16) -> EchoHeader {
+    EchoHeader { kind, ident, seq }
+}
+
+pub fn test_echo_message_round_trip() -> TestResult {
+    let mut msg = [0u8; 64];
+    let data = *b"abcdefgh";
+    let Some(len) = icmp::build_echo(&mut msg, ICMP_ECHO_REQUEST, 0x1234, 7, &data) else {
+        return fail!("build_echo refused a 64-byte buffer");
+    };
+    assert_eq_test!(len, ICMP_HEADER_LEN + data.len());
+    assert_eq_test!(msg[0], 8, "echo request type");
+    assert_eq_test!(&msg[ICMP_HEADER_LEN..len], &data[..], "echo data");
+    assert_eq_test!(
+        icmp::parse_echo(&msg[..len]),
+        Some(echo_header(ICMP_ECHO_REQUEST, 0x1234, 7)),
+        "round trip"
+    );
+
+    msg[len - 1] ^= 0x40;
+    assert_test!(icmp::parse_echo(&msg[..len]).is_none(), "bad checksum");
+    assert_test!(icmp::parse_echo(&msg[..4]).is_none(), "short message");
+    let _ = icmp::build_echo(&mut msg, ICMP_DEST_UNREACHABLE, 0, 0, &[]);
+    assert_test!(
+        icmp::parse_echo(&msg[..ICMP_HEADER_LEN]).is_none(),
+        "non-echo type parsed"
+    );
+    assert_test!(
+        icmp::build_echo(&mut msg[..10], ICMP_ECHO_REPLY, 0, 0, &data).is_none(),
+        "overflowed the buffer"
+    );
+    assert_eq_test!(IpProtocol::from_u8(IPPROTO_ICMP), Some(IpProtocol::Icmp));
+    pass!()
+}
+
+pub fn test_ping_table_matches_reply() -> TestResult {
+    let mut table = PingTable::new();
+    let Ok(slot) = table.begin(REMOTE, 0x42, 3, 1_000_000) else {
+        return fail!("begin failed on an empty table");
+    };
+    let reply = echo_header(ICMP_ECHO_REPLY, 0x42, 3);
+
+    assert_test!(
+        !table.complete(OTHER, reply, 64, 56, 2_000_000),
+        "reply from another host matched"
+    );
+    assert_test!(
+        !table.complete(
+            REMOTE,
+            echo_header(ICMP_ECHO_REPLY, 0x42, 4),
+            64,
+            56,
+            2_000_000
+        ),
+        "wrong sequence number matched"
+    );
+    assert_test!(table.take(slot).is_none(), "reply before it arrived");
+
+    assert_test!(table.complete(REMOTE, reply, 63, 56, 3_500_000));
+    assert_test!(
+        !table.complete(REMOTE, reply, 63, 56, 4_000_000),
+        "duplicate reply matched"
+    );
+    let Some(pong) = table.take(slot) else {
+        return fail!("reply not recorded");
+    };
+    assert_eq_test!(pong.rtt_us, 2_500, "rtt");
+    assert_eq_test!(pong.ttl, 63, "ttl");
+    assert_eq_test!(pong.len, 56, "length");
+    assert_test!(table.take(slot).is_none(), "slot not freed");
+    pass!()
+}
+
+pub fn test_ping_table_full_and_cancel() -> TestResult {
+    let mut table = PingTable::new();
+    for seq in 0..MAX_PENDING_PINGS as u16 {
+        if table.begin(REMOTE, 1, seq, 0).is_err() {
+            return fail!("begin {} failed", seq);
+        }
+    }
+    assert_eq_test!(
+        table.begin(REMOTE, 1, 99, 0),
+        Err(NetError::NoBufferSpace),
+        "table overfilled"
+    );
+    table.cancel(2);
+    assert_eq_test!(
+        table.begin(REMOTE, 1, 99, 0),
+        Ok(2),
+        "cancelled slot reused"
+    );
+    pass!()
+}
+
+pub fn test_echo_reply_completes_pending_ping() -> TestResult {
+    icmp::reset();
+    let Ok(slot) = PING_TABLE.lock().begin(REMOTE, 0x77, 9, 0) else {
+        return fail!("begin failed");
+    };
+
+    let mut msg = [0u8; ICMP_HEADER_LEN + 16];
+    let Some(len) = icmp::build_echo(&mut msg, ICMP_ECHO_REPLY, 0x77, 9, &[0xAB; 16]) else {
+        return fail!("build_echo failed");
+    };
+    icmp::handle_rx(REMOTE.0, LOCAL, 57, &msg[..len]);
+
+    let pong = PING_TABLE.lock().take(slot);
+    icmp::reset();
+    let Some(pong) = pong else {
+        return fail!("echo reply not matched");
+    };
+    assert_eq_test!(pong.ttl, 57, "ttl");
+    assert_eq_test!(pong.len, 16, "length");
+    pass!()
+}
+
+pub fn test_ping_argument_checks() -> TestResult {
+    icmp::reset();
+    assert_eq_test!(
+        icmp::ping(REMOTE, 1, 1, ICMP_ECHO_MAX_PAYLOAD + 1, 100),
+        Err(NetError::InvalidArgument),
+        "oversized payload"
+    );
+    assert_eq_test!(
+        icmp::ping(Ipv4Addr::UNSPECIFIED, 1, 1, 56, 100),
+        Err(NetError::InvalidArgument),
+        "unspecified address"
+    );
+    assert_eq_test!(
+        icmp::ping(REMOTE, 1, 1, 56, 0),
+        Err(NetError::InvalidArgument),
+        "zero timeout"
+    );
+    pass!()
+}
+
+pub fn test_ping_blocked_by_filter_frees_slot() -> TestResult {
+    icmp::reset();
+    filter::reset();
+    let rule = NetFilterRule {
+        direction: NET_FILTER_OUT,
+        action: NET_FILTER_DROP,
+        proto: IPPROTO_ICMP,
+        ..NetFilterRule::default()
+    };
+    if filter::append(rule).is_err() {
+        return fail!("filter rule rejected");
+    }
+    let result = icmp::ping(REMOTE, 5, 1, 56, 100);
+    filter::reset();
+    assert_eq_test!(result, Err(NetError::PermissionDenied), "filtered ping");
+
+    let mut free = 0;
+    let mut table = PING_TABLE.lock();
+    for seq in 0..MAX_PENDING_PINGS as u16 {
+        free += table.begin(REMOTE, 6, seq, 0).is_ok() as usize;
+    }
+    table.clear();
+    drop(table);
+    assert_eq_test!(free, MAX_PENDING_PINGS, "failed send leaked a slot");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    net_icmp,
+    [
+        test_echo_message_round_trip,
+        test_ping_table_matches_reply,
+        test_ping_table_full_and_cancel,
+        test_echo_reply_completes_pending_ping,
+        test_ping_argument_checks,
+        test_ping_blocked_by_filter_frees_slot,
+    ]
+);
@@ -0,0 +1,277 @@
+//! IGMPv2 host side (RFC 2236) and the multicast group table.
+//!
+//! Sockets join groups with `IP_ADD_MEMBERSHIP`.  The first socket to join a
+//! group sends an unsolicited membership report; the last one to leave sends
+//! a leave message to the all-routers group.  Queries are answered with a
+//! report for every group we are in, immediately rather than after a random
+//! delay: with a handful of groups on one link the burst is harmless.
+//!
+//! The table also drives the receive filter: [`ipv4::handle_rx`] drops
+//! multicast datagrams for groups no socket has joined.  The all-hosts group
+//! `224.0.0.1` is always accepted and never reported.
+//!
+//! [`ipv4::handle_rx`]: super::ipv4::handle_rx
+
+use slopos_abi::net::MAX_SOCKETS;
+use slopos_lib::{IrqMutex, klog_debug};
+
+use super::packetbuf::PacketBuf;
+use super::types::{Ipv4Addr, NetError};
+
+pub const IGMP_MSG_LEN: usize = 8;
+
+pub const IGMP_MEMBERSHIP_QUERY: u8 = 0x11;
+pub const IGMP_V1_MEMBERSHIP_REPORT: u8 = 0x12;
+pub const IGMP_V2_MEMBERSHIP_REPORT: u8 = 0x16;
+pub const IGMP_LEAVE_GROUP: u8 = 0x17;
+
+/// `224.0.0.1`: every multicast-capable host on the link.
+pub const ALL_HOSTS: Ipv4Addr = Ipv4Addr([224, 0, 0, 1]);
+/// `224.0.0.2`: destination of leave messages.
+pub const ALL_ROUTERS: Ipv4Addr = Ipv4Addr([224, 0, 0, 2]);
+
+/// IPv4 Router Alert option (RFC 2113), required on IGMPv2 messages.
+const ROUTER_ALERT: [u8; 4] = [0x94, 0x04, 0x00, 0x00];
+const IGMP_IP_HEADER_LEN: usize = super::IPV4_HEADER_LEN + ROUTER_ALERT.len();
+
+/// Memberships across all sockets.  One socket may be in several groups.
+const MAX_MEMBERSHIPS: usize = MAX_SOCKETS;
+
+#[derive(Clone, Copy)]
+struct Membership {
+    group: Ipv4Addr,
+    sock_idx: u32,
+}
+
+pub struct MembershipTable {
+    entries: [Option<Membership>; MAX_MEMBERSHIPS],
+}
+
+impl MembershipTable {
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; MAX_MEMBERSHIPS],
+        }
+    }
+
+    /// Add `sock_idx` to `group`.  Returns `true` if it is the group's first
+    /// member.
+    pub fn join(&mut self, group: Ipv4Addr, sock_idx: u32) -> Result<bool, NetError> {
+        if self.is_member(group, sock_idx) {
+            return Err(NetError::AddressInUse);
+        }
+        let first = !self.is_joined(group);
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(NetError::NoBufferSpace)?;
+        *slot = Some(Membership { group, sock_idx });
+        Ok(first)
+    }
+
+    /// Remove `sock_idx` from `group`.  Returns `true` if it was the group's
+    /// last member.
+    pub fn leave(&mut self, group: Ipv4Addr, sock_idx: u32) -> Result<bool, NetError> {
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|slot| matches!(slot, Some(m) if m.group == group && m.sock_idx == sock_idx))
+            .ok_or(NetError::AddressNotAvailable)?;
+        *slot = None;
+        Ok(!self.is_joined(group))
+    }
+
+    /// Remove one membership of `sock_idx`.  Returns the group and whether
+    /// it is now empty, or `None` once the socket is in no group.
+    pub fn leave_any(&mut self, sock_idx: u32) -> Option<(Ipv4Addr, bool)> {
+        let slot = self
+            .entries
+            .iter_mut()
+            .find(|slot| matches!(slot, Some(m) if m.sock_idx == sock_idx))?;
+        let group = slot.take()?.group;
+        Some((group, !self.is_joined(group)))
+    }
+
+    pub fn is_member(&self, group: Ipv4Addr, sock_idx: u32) -> bool {
+        self.entries
+            .iter()
+            .flatten()
+            .any(|m| m.group == group && m.sock_idx == sock_idx)
+    }
+
+    /// Whether any socket is in `group`.
+    pub fn is_joined(&self, group: Ipv4Addr) -> bool {
+        self.entries.iter().flatten().any(|m| m.group == group)
+    }
+
+    /// Distinct joined groups, in table order.
+    pub fn groups(&self, out: &mut [Ipv4Addr; MAX_MEMBERSHIPS]) -> usize {
+        let mut count = 0;
+        for m in self.entries.iter().flatten() {
+            if !out[..count].contains(&m.group) {
+                out[count] = m.group;
+                count += 1;
+            }
+        }
+        count
+    }
+
+    pub fn clear(&mut self) {
+        self.entries = [None; MAX_MEMBERSHIPS];
+    }
+}
+
+impl Default for MembershipTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub static IGMP_GROUPS: IrqMutex<MembershipTable> = IrqMutex::new(MembershipTable::new());
+
+/// Join `group` on behalf of a socket, reporting it if it is new to us.
+pub fn join(group: Ipv4Addr, sock_idx: u32) -> Result<(), NetError> {
+    if !group.is_multicast() {
+        return Err(NetError::InvalidArgument);
+    }
+    if IGMP_GROUPS.lock().join(group, sock_idx)? && group != ALL_HOSTS {
+        send_message(IGMP_V2_MEMBERSHIP_REPORT, group, group);
+    }
+    Ok(())
+}
+
+/// Leave `group` on behalf of a socket, announcing it if nobody is left.
+pub fn leave(group: Ipv4Addr, sock_idx: u32) -> Result<(), NetError> {
+    if IGMP_GROUPS.lock().leave(group, sock_idx)? && group != ALL_HOSTS {
+        send_message(IGMP_LEAVE_GROUP, group, ALL_ROUTERS);
+    }
+    Ok(())
+}
+
+/// Drop every membership of a closing socket.
+pub fn socket_closed(sock_idx: u32) {
+    loop {
+        let Some((group, last)) = IGMP_GROUPS.lock().leave_any(sock_idx) else {
+            return;
+        };
+        if last && group != ALL_HOSTS {
+            send_message(IGMP_LEAVE_GROUP, group, ALL_ROUTERS);
+        }
+    }
+}
+
+/// Whether datagrams sent to `group` should be received at all.
+pub fn accepts(group: Ipv4Addr) -> bool {
+    group == ALL_HOSTS || IGMP_GROUPS.lock().is_joined(group)
+}
+
+/// Forget all memberships without sending leave messages.
+pub fn reset() {
+    IGMP_GROUPS.lock().clear();
+}
+
+/// Fill `out` with an IGMPv2 message, checksum included.
+pub fn build_message(out: &mut [u8; IGMP_MSG_LEN], kind: u8, group: Ipv4Addr) {
+    out[0] = kind;
+    // Max response time only means something in queries.
+    out[1] = 0;
+    out[2..4].copy_from_slice(&0u16.to_be_bytes());
+    out[4..8].copy_from_slice(&group.0);
+    let checksum = super::ipv4_header_checksum(out);
+    out[2..4].copy_from_slice(&checksum.to_be_bytes());
+}
+
+/// Parse an IGMP message into `(type, group)`, verifying the checksum.
+pub fn parse_message(payload: &[u8]) -> Option<(u8, Ipv4Addr)> {
+    if payload.len() < IGMP_MSG_LEN || super::ipv4_header_checksum(payload) != 0 {
+        return None;
+    }
+    let group = Ipv4Addr([payload[4], payload[5], payload[6], payload[7]]);
+    Some((payload[0], group))
+}
+
+/// Handle an IGMP message addressed to us.
+pub fn handle_rx(src_ip: [u8; 4], pkt: &PacketBuf) {
+    let Some((kind, group)) = parse_message(pkt.payload()) else {
+        klog_debug!("igmp: malformed message from {}", Ipv4Addr(src_ip));
+        return;
+    };
+
+    match kind {
+        IGMP_MEMBERSHIP_QUERY if group.is_unspecified() => {
+            let mut groups = [Ipv4Addr::UNSPECIFIED; MAX_MEMBERSHIPS];
+            let count = IGMP_GROUPS.lock().groups(&mut groups);
+            for &joined in &groups[..count] {
+                if joined != ALL_HOSTS {
+                    send_message(IGMP_V2_MEMBERSHIP_REPORT, joined, joined);
+                }
+            }
+        }
+        IGMP_MEMBERSHIP_QUERY => {
+            if group != ALL_HOSTS && IGMP_GROUPS.lock().is_joined(group) {
+                send_message(IGMP_V2_MEMBERSHIP_REPORT, group, group);
+            }
+        }
+        // Another member's report; we answer queries at once, so there is
+        // no pending report of ours to suppress.
+        IGMP_V1_MEMBERSHIP_REPORT | IGMP_V2_MEMBERSHIP_REPORT | IGMP_LEAVE_GROUP => {}
+        _ => klog_debug!("igmp: unknown type {:#x} from {}", kind, Ipv4Addr(src_ip)),
+    }
+}
+
+fn send_message(kind: u8, group: Ipv4Addr, dst: Ipv4Addr) {
+    let src = super::NET_STACK
+        .first_ipv4()
+        .unwrap_or(Ipv4Addr::UNSPECIFIED);
+    let result = build_packet(kind, group, src, dst)
+        .and_then(|pkt| super::ipv4::send(dst, pkt).map_err(|_| NetError::NetworkUnreachable));
+    if let Err(err) = result {
+        klog_debug!("igmp: type {:#x} for {} not sent: {}", kind, group, err);
+    }
+}
+
+fn build_packet(
+    kind: u8,
+    group: Ipv4Addr,
+    src: Ipv4Addr,
+    dst: Ipv4Addr,
+) -> Result<PacketBuf, NetError> {
+    let mut msg = [0u8; IGMP_MSG_LEN];
+    build_message(&mut msg, kind, group);
+
+    let mut pkt = PacketBuf::alloc().ok_or(NetError::NoBufferSpace)?;
+    pkt.append(&msg)?;
+
+    let total_len = (IGMP_IP_HEADER_LEN + IGMP_MSG_LEN) as u16;
+    {
+        let ip_hdr = pkt.push_header(IGMP_IP_HEADER_LEN)?;
+        ip_hdr[0] = 0x40 | (IGMP_IP_HEADER_LEN / 4) as u8;
+        ip_hdr[1] = 0;
+        ip_hdr[2..4].copy_from_slice(&total_len.to_be_bytes());
+        ip_hdr[4..6].copy_from_slice(&0u16.to_be_bytes());
+        ip_hdr[6..8].copy_from_slice(&0u16.to_be_bytes());
+        // Link-local only: routers must not forward IGMP.
+        ip_hdr[8] = 1;
+        ip_hdr[9] = super::IPPROTO_IGMP;
+        ip_hdr[10..12].copy_from_slice(&0u16.to_be_bytes());
+        ip_hdr[12..16].copy_from_slice(&src.0);
+        ip_hdr[16..20].copy_from_slice(&dst.0);
+        ip_hdr[20..24].copy_from_slice(&ROUTER_ALERT);
+        let checksum = super::ipv4_header_checksum(ip_hdr);
+        ip_hdr[10..12].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    {
+        let eth_hdr = pkt.push_header(super::ETH_HEADER_LEN)?;
+        eth_hdr[0..6].copy_from_slice(&super::ETH_BROADCAST);
+        eth_hdr[6..12].copy_from_slice(&crate::virtio_net::virtio_net_mac().unwrap_or([0; 6]));
+        eth_hdr[12..14].copy_from_slice(&super::ETHERTYPE_IPV4.to_be_bytes());
+    }
+
+    let head = pkt.head();
+    pkt.set_l2(head);
+    pkt.set_l3(head + super::ETH_HEADER_LEN as u16);
+    pkt.set_l4(head + (super::ETH_HEADER_LEN + IGMP_IP_HEADER_LEN) as u16);
+    Ok(pkt)
+}
@@ -0,0 +1,308 @@
+//! Multicast tests: the IGMP membership table and message format, group
+//! filtering and delivery, and the SO_BROADCAST / IP_* socket options.
+
+use slopos_abi::net::{AF_INET, SOCK_DGRAM, SOCK_STREAM};
+use slopos_abi::syscall::*;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::igmp::{
+    self, ALL_HOSTS, IGMP_LEAVE_GROUP, IGMP_MSG_LEN, IGMP_V2_MEMBERSHIP_REPORT, MembershipTable,
+};
+use super::packetbuf::PacketBuf;
+use super::socket::*;
+use super::types::{IpProtocol, Ipv4Addr, MacAddr, NetError};
+
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr([224, 0, 0, 251]);
+const OTHER_GROUP: Ipv4Addr = Ipv4Addr([239, 1, 2, 3]);
+const REMOTE_IP: [u8; 4] = [10, 0, 0, 9];
+
+const EACCES: i64 = ERRNO_EACCES as i64;
+const EADDRINUSE: i32 = ERRNO_EADDRINUSE as i64 as i32;
+const EADDRNOTAVAIL: i32 = ERRNO_EADDRNOTAVAIL as i64 as i32;
+const EINVAL: i32 = ERRNO_EINVAL as i64 as i32;
+const EPROTONOSUPPORT: i32 = ERRNO_EPROTONOSUPPORT as i64 as i32;
+
+fn reset() {
+    socket_reset_all();
+}
+
+fn udp_socket() -> Option<u32> {
+    let sock = socket_create(AF_INET, SOCK_DGRAM, 0);
+    (sock >= 0).then_some(sock as u32)
+}
+
+fn membership(sock: u32, optname: i32, group: Ipv4Addr) -> i32 {
+    let mut mreq = [0u8; 8];
+    mreq[..4].copy_from_slice(&group.0);
+    socket_setsockopt(sock, IPPROTO_IP, optname, &mreq)
+}
+
+fn queued(sock: u32) -> usize {
+    NEW_SOCKET_TABLE
+        .lock()
+        .get(sock as usize)
+        .map_or(0, |s| s.recv_queue.len())
+}
+
+/// Run a UDP datagram through the receive path as if it came off the wire.
+fn receive_udp(dst: Ipv4Addr, dst_port: u16, payload: &[u8]) -> bool {
+    let Some(mut pkt) = PacketBuf::alloc() else {
+        return false;
+    };
+    let mut hdr = [0u8; 8];
+    hdr[0..2].copy_from_slice(&5353u16.to_be_bytes());
+    hdr[2..4].copy_from_slice(&dst_port.to_be_bytes());
+    hdr[4..6].copy_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+    if pkt.append(&hdr).is_err() || pkt.append(payload).is_err() {
+        return false;
+    }
+    super::udp::handle_rx(REMOTE_IP, dst.0, &pkt);
+    true
+}
+
+pub fn test_membership_table_first_and_last() -> TestResult {
+    let mut table = MembershipTable::new();
+    assert_eq_test!(table.join(MDNS_GROUP, 1), Ok(true), "first member");
+    assert_eq_test!(table.join(MDNS_GROUP, 2), Ok(false), "second member");
+    assert_eq_test!(
+        table.join(MDNS_GROUP, 1),
+        Err(NetError::AddressInUse),
+        "duplicate join"
+    );
+    assert_eq_test!(table.leave(MDNS_GROUP, 1), Ok(false), "one member left");
+    assert_test!(table.is_joined(MDNS_GROUP), "group dropped early");
+    assert_eq_test!(
+        table.leave(MDNS_GROUP, 1),
+        Err(NetError::AddressNotAvailable),
+        "leave twice"
+    );
+    assert_eq_test!(table.leave(MDNS_GROUP, 2), Ok(true), "last member");
+    assert_test!(!table.is_joined(MDNS_GROUP), "group still joined");
+    pass!()
+}
+
+pub fn test_membership_table_leave_any() -> TestResult {
+    let mut table = MembershipTable::new();
+    let _ = table.join(MDNS_GROUP, 4);
+    let _ = table.join(OTHER_GROUP, 4);
+    let _ = table.join(OTHER_GROUP, 5);
+
+    let mut emptied = 0;
+    let mut dropped = 0;
+    while let Some((_, last)) = table.leave_any(4) {
+        dropped += 1;
+        emptied += last as usize;
+    }
+    assert_eq_test!(dropped, 2, "memberships dropped");
+    assert_eq_test!(emptied, 1, "only the MDNS group emptied");
+    assert_test!(
+        table.is_member(OTHER_GROUP, 5),
+        "other socket lost its group"
+    );
+    pass!()
+}
+
+pub fn test_igmp_message_format() -> TestResult {
+    let mut msg = [0u8; IGMP_MSG_LEN];
+    igmp::build_message(&mut msg, IGMP_V2_MEMBERSHIP_REPORT, MDNS_GROUP);
+    assert_eq_test!(msg[0], 0x16, "v2 report type");
+    assert_eq_test!(&msg[4..8], &MDNS_GROUP.0[..], "group address");
+    assert_eq_test!(
+        igmp::parse_message(&msg),
+        Some((IGMP_V2_MEMBERSHIP_REPORT, MDNS_GROUP)),
+        "round trip"
+    );
+
+    igmp::build_message(&mut msg, IGMP_LEAVE_GROUP, MDNS_GROUP);
+    assert_eq_test!(msg[0], 0x17, "leave type");
+    msg[7] ^= 1;
+    assert_test!(igmp::parse_message(&msg).is_none(), "bad checksum accepted");
+    assert_test!(igmp::parse_message(&msg[..4]).is_none(), "short message");
+    assert_eq_test!(IpProtocol::from_u8(2), Some(IpProtocol::Igmp));
+    pass!()
+}
+
+pub fn test_multicast_mac_mapping() -> TestResult {
+    assert_eq_test!(
+        MacAddr::from_ipv4_multicast(MDNS_GROUP).0,
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb]
+    );
+    // Bit 24 of the group does not fit in the MAC.
+    assert_eq_test!(
+        MacAddr::from_ipv4_multicast(Ipv4Addr([239, 255, 0, 1])).0,
+        [0x01, 0x00, 0x5e, 0x7f, 0x00, 0x01]
+    );
+    pass!()
+}
+
+pub fn test_group_filter_follows_membership() -> TestResult {
+    reset();
+    let Some(sock) = udp_socket() else {
+        return fail!("socket_create failed");
+    };
+    assert_test!(igmp::accepts(ALL_HOSTS), "all-hosts group filtered");
+    assert_test!(!igmp::accepts(MDNS_GROUP), "unjoined group accepted");
+
+    assert_eq_test!(membership(sock, IP_ADD_MEMBERSHIP, MDNS_GROUP), 0, "join");
+    assert_test!(igmp::accepts(MDNS_GROUP), "joined group filtered");
+    assert_eq_test!(
+        membership(sock, IP_ADD_MEMBERSHIP, MDNS_GROUP),
+        EADDRINUSE,
+        "duplicate join"
+    );
+    assert_eq_test!(membership(sock, IP_DROP_MEMBERSHIP, MDNS_GROUP), 0, "leave");
+    assert_test!(!igmp::accepts(MDNS_GROUP), "left group accepted");
+    assert_eq_test!(
+        membership(sock, IP_DROP_MEMBERSHIP, MDNS_GROUP),
+        EADDRNOTAVAIL,
+        "leave unjoined group"
+    );
+
+    assert_eq_test!(
+        membership(sock, IP_ADD_MEMBERSHIP, OTHER_GROUP),
+        0,
+        "rejoin"
+    );
+    assert_eq_test!(socket_close(sock), 0, "close");
+    assert_test!(!igmp::accepts(OTHER_GROUP), "close kept the membership");
+    pass!()
+}
+
+pub fn test_membership_option_validation() -> TestResult {
+    reset();
+    let Some(udp) = udp_socket() else {
+        return fail!("socket_create failed");
+    };
+    assert_eq_test!(
+        membership(udp, IP_ADD_MEMBERSHIP, Ipv4Addr(REMOTE_IP)),
+        EINVAL,
+        "unicast group"
+    );
+    assert_eq_test!(
+        socket_setsockopt(udp, IPPROTO_IP, IP_ADD_MEMBERSHIP, &MDNS_GROUP.0),
+        EINVAL,
+        "short ip_mreq"
+    );
+    let mut mreq = [0u8; 8];
+    mreq[..4].copy_from_slice(&MDNS_GROUP.0);
+    mreq[4..].copy_from_slice(&REMOTE_IP);
+    assert_eq_test!(
+        socket_setsockopt(udp, IPPROTO_IP, IP_ADD_MEMBERSHIP, &mreq),
+        EADDRNOTAVAIL,
+        "foreign interface address"
+    );
+
+    let tcp = socket_create(AF_INET, SOCK_STREAM, 0);
+    if tcp < 0 {
+        return fail!("socket_create failed");
+    }
+    assert_eq_test!(
+        membership(tcp as u32, IP_ADD_MEMBERSHIP, MDNS_GROUP),
+        EPROTONOSUPPORT,
+        "join on TCP"
+    );
+    pass!()
+}
+
+pub fn test_multicast_delivered_to_every_bound_socket() -> TestResult {
+    reset();
+    let (Some(any), Some(group)) = (udp_socket(), udp_socket()) else {
+        return fail!("socket_create failed");
+    };
+    let on = 1i32.to_ne_bytes();
+    for sock in [any, group] {
+        assert_eq_test!(
+            socket_setsockopt(sock, SOL_SOCKET, SO_REUSEADDR, &on),
+            0,
+            "SO_REUSEADDR"
+        );
+    }
+    assert_eq_test!(socket_bind(any, [0, 0, 0, 0], 5353), 0, "bind wildcard");
+    assert_eq_test!(socket_bind(group, MDNS_GROUP.0, 5353), 0, "bind group");
+    assert_eq_test!(membership(any, IP_ADD_MEMBERSHIP, MDNS_GROUP), 0, "join");
+
+    assert_test!(receive_udp(MDNS_GROUP, 5353, b"query"), "packet alloc");
+    assert_eq_test!(queued(any), 1, "wildcard socket missed the datagram");
+    assert_eq_test!(queued(group), 1, "group socket missed the datagram");
+
+    assert_test!(receive_udp(MDNS_GROUP, 5354, b"query"), "packet alloc");
+    assert_eq_test!(queued(any), 1, "datagram for another port delivered");
+    pass!()
+}
+
+pub fn test_broadcast_needs_so_broadcast() -> TestResult {
+    reset();
+    let Some(sock) = udp_socket() else {
+        return fail!("socket_create failed");
+    };
+    let data = b"hello";
+    let send = || socket_sendto(sock, data.as_ptr(), data.len(), [255; 4], 9);
+    assert_eq_test!(send(), EACCES, "broadcast without SO_BROADCAST");
+
+    let mut out = [0u8; 4];
+    assert_eq_test!(
+        socket_getsockopt(sock, SOL_SOCKET, SO_BROADCAST, &mut out),
+        4
+    );
+    assert_eq_test!(i32::from_ne_bytes(out), 0, "SO_BROADCAST default");
+    assert_eq_test!(
+        socket_setsockopt(sock, SOL_SOCKET, SO_BROADCAST, &1i32.to_ne_bytes()),
+        0,
+        "set SO_BROADCAST"
+    );
+    let _ = socket_getsockopt(sock, SOL_SOCKET, SO_BROADCAST, &mut out);
+    assert_eq_test!(i32::from_ne_bytes(out), 1, "SO_BROADCAST set");
+    // Without a route the send itself may still fail, just not with EACCES.
+    assert_test!(send() != EACCES, "SO_BROADCAST ignored");
+    pass!()
+}
+
+pub fn test_multicast_ttl_option() -> TestResult {
+    reset();
+    let Some(sock) = udp_socket() else {
+        return fail!("socket_create failed");
+    };
+    let mut out = [0u8; 4];
+    assert_eq_test!(
+        socket_getsockopt(sock, IPPROTO_IP, IP_MULTICAST_TTL, &mut out),
+        4
+    );
+    assert_eq_test!(i32::from_ne_bytes(out), 1, "link-local by default");
+    assert_eq_test!(
+        socket_setsockopt(sock, IPPROTO_IP, IP_MULTICAST_TTL, &8i32.to_ne_bytes()),
+        0
+    );
+    assert_eq_test!(
+        socket_setsockopt(sock, IPPROTO_IP, IP_MULTICAST_TTL, &256i32.to_ne_bytes()),
+        EINVAL,
+        "TTL above 255"
+    );
+
+    let table = NEW_SOCKET_TABLE.lock();
+    let Some(s) = table.get(sock as usize) else {
+        return fail!("socket missing");
+    };
+    assert_eq_test!(s.options.ttl_for(MDNS_GROUP), 8, "multicast TTL");
+    assert_eq_test!(
+        s.options.ttl_for(Ipv4Addr(REMOTE_IP)),
+        SocketOptions::UNICAST_TTL,
+        "unicast TTL"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    igmp,
+    [
+        test_membership_table_first_and_last,
+        test_membership_table_leave_any,
+        test_igmp_message_format,
+        test_multicast_mac_mapping,
+        test_group_filter_follows_membership,
+        test_membership_option_validation,
+        test_multicast_delivered_to_every_bound_socket,
+        test_broadcast_needs_so_broadcast,
+        test_multicast_ttl_option,
+    ]
+);
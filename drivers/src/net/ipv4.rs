@@ -5,14 +5,19 @@
 //! [`handle_rx`] is the single entry point for all received IPv4 packets after
 //! Ethernet demux.  It validates the IP header (version, length, checksum, TTL),
 //! sets the L4 layer offset on the [`PacketBuf`], and dispatches to the
-//! appropriate protocol handler (TCP, UDP, ICMP).
+//! appropriate protocol handler (TCP, UDP, ICMP, IGMP).  Multicast
+//! datagrams for groups no socket has joined are dropped here, as is
+//! anything the input side of the packet [`filter`](super::filter) rejects.
+//! Whatever passes is copied to matching raw sockets first.
 //!
 //! # Egress (Phase 3B)
 //!
 //! [`send`] is the route-aware egress entry point.  It performs a routing table
 //! lookup to determine the outgoing device and next hop, then either transmits
 //! directly (broadcast/multicast/loopback) or delegates to the neighbor cache
-//! for ARP resolution.
+//! for ARP resolution.  Multicast frames get the group's `01:00:5e` MAC.
+//! Packets the output side of the filter rejects fail with
+//! [`NetError::PermissionDenied`].
 //!
 //! [`send_via`] is the lower-level egress path for callers that already have a
 //! [`DeviceHandle`] and know the next hop (e.g., timer-driven retransmits).
@@ -22,13 +27,14 @@
 //! - Full IPv4 header validation
 //! - Protocol dispatch to existing TCP/UDP handlers via the socket layer
 //! - DNS response interception for the in-kernel resolver
-//! - ICMP stub (logs and drops)
+//! - ICMP echo via [`icmp`](super::icmp)
 
+use slopos_abi::net::{NET_FILTER_IN, NET_FILTER_OUT};
 use slopos_lib::klog_debug;
 
 use super::socket;
 use super::tcp;
-use super::types::{DevIndex, IpProtocol};
+use super::types::{DevIndex, IpProtocol, Ipv4Addr, MacAddr};
 use crate::net::{self as net, NetError, packetbuf::PacketBuf};
 
 /// Handle an incoming IPv4 packet.
@@ -49,7 +55,7 @@ use crate::net::{self as net, NetError, packetbuf::PacketBuf};
 pub fn handle_rx(dev: DevIndex, mut pkt: PacketBuf, checksum_rx: bool) {
     // Extract all fields we need while borrowing the payload immutably.
     // We must drop this borrow before calling pkt.set_l4() / pkt.pull_header().
-    let (proto, src_ip, dst_ip, ihl) = {
+    let (proto, src_ip, dst_ip, ihl, ttl, total_len) = {
         let ip_data = pkt.payload();
         if ip_data.len() < net::IPV4_HEADER_LEN {
             klog_debug!(
@@ -102,10 +108,36 @@ pub fn handle_rx(dev: DevIndex, mut pkt: PacketBuf, checksum_rx: bool) {
         let src_ip: [u8; 4] = ip_data[12..16].try_into().unwrap_or([0; 4]);
         let dst_ip: [u8; 4] = ip_data[16..20].try_into().unwrap_or([0; 4]);
 
-        (proto, src_ip, dst_ip, ihl)
+        (proto, src_ip, dst_ip, ihl, ttl, total_len)
     };
     // Immutable borrow of pkt dropped here.
 
+    // Multicast filter: IGMP itself always gets through (queries for groups
+    // we are not in still need an answer of "no").
+    let group = Ipv4Addr(dst_ip);
+    if group.is_multicast() && proto != net::IPPROTO_IGMP && !super::igmp::accepts(group) {
+        klog_debug!("ipv4: drop multicast to unjoined group {}", group);
+        return;
+    }
+
+    if !super::filter::allows(
+        NET_FILTER_IN,
+        proto,
+        Ipv4Addr(src_ip),
+        &pkt.payload()[ihl..],
+    ) {
+        klog_debug!(
+            "ipv4: filter dropped proto {} from {}",
+            proto,
+            Ipv4Addr(src_ip)
+        );
+        return;
+    }
+
+    // Raw sockets get a copy of the whole datagram, header included, before
+    // the protocol handler consumes it.
+    socket::socket_deliver_raw(proto, src_ip, dst_ip, &pkt.payload()[..total_len]);
+
     // Set L4 offset (absolute position: current head + IHL).
     pkt.set_l4(pkt.head() + ihl as u16);
 
@@ -118,17 +150,15 @@ pub fn handle_rx(dev: DevIndex, mut pkt: PacketBuf, checksum_rx: bool) {
     match IpProtocol::from_u8(proto) {
         Some(IpProtocol::Tcp) => dispatch_tcp(src_ip, dst_ip, &pkt),
         Some(IpProtocol::Udp) => dispatch_udp(src_ip, dst_ip, &pkt),
+        Some(IpProtocol::Igmp) => super::igmp::handle_rx(src_ip, &pkt),
         Some(IpProtocol::Icmp) => {
-            klog_debug!(
-                "ipv4: ICMP from {}.{}.{}.{} — stub, dropping",
-                src_ip[0],
-                src_ip[1],
-                src_ip[2],
-                src_ip[3]
-            );
+            // Short frames arrive with Ethernet padding past the datagram.
+            let msg = pkt.payload();
+            let msg = &msg[..total_len.saturating_sub(ihl).min(msg.len())];
+            super::icmp::handle_rx(src_ip, dst_ip, ttl, msg);
         }
         None => {
-            klog_debug!("ipv4: unknown protocol {}, dropping", proto);
+            klog_debug!("ipv4: no handler for protocol {}", proto);
         }
     }
 
@@ -218,22 +248,34 @@ fn dispatch_udp(src_ip: [u8; 4], dst_ip: [u8; 4], pkt: &PacketBuf) {
 ///
 /// This is the primary egress entry point for the socket layer (Phase 4+).
 /// For callers that already hold a [`DeviceHandle`], use [`send_via`] instead.
-pub fn send(dst_ip: super::types::Ipv4Addr, pkt: PacketBuf) -> Result<(), NetError> {
+pub fn send(dst_ip: Ipv4Addr, mut pkt: PacketBuf) -> Result<(), NetError> {
+    use super::arp;
     use super::netdev::DEVICE_REGISTRY;
     use super::route::ROUTE_TABLE;
 
+    let proto = pkt.l3_header().get(9).copied().unwrap_or(0);
+    if !super::filter::allows(NET_FILTER_OUT, proto, dst_ip, pkt.l4_header()) {
+        klog_debug!("ipv4::send: filter dropped proto {} to {}", proto, dst_ip);
+        return Err(NetError::PermissionDenied);
+    }
+
+    // Local destinations, our own interface addresses included, go to lo;
+    // no neighbor resolution there.
+    if super::loopback::is_local_destination(dst_ip) {
+        return DEVICE_REGISTRY.tx_by_index(super::loopback::LOOPBACK_DEV, pkt);
+    }
+
     let (dev, next_hop) = ROUTE_TABLE.lookup(dst_ip).ok_or_else(|| {
         klog_debug!("ipv4::send: no route to {}", dst_ip);
         NetError::NetworkUnreachable
     })?;
 
-    // Loopback: skip neighbor resolution entirely — no ARP on lo.
-    if next_hop.is_loopback() || dst_ip.is_loopback() {
+    // Broadcast/multicast: skip neighbor resolution, TX directly.
+    if dst_ip.is_multicast() {
+        arp::set_dst_mac_in_eth_header(&mut pkt, MacAddr::from_ipv4_multicast(dst_ip));
         return DEVICE_REGISTRY.tx_by_index(dev, pkt);
     }
-
-    // Broadcast/multicast: skip neighbor resolution, TX directly.
-    if dst_ip.is_broadcast() || dst_ip.is_multicast() {
+    if dst_ip.is_broadcast() {
         return DEVICE_REGISTRY.tx_by_index(dev, pkt);
     }
 
@@ -11,6 +11,16 @@
 //! any physical NIC.  It is configured with `127.0.0.1/8` and a connected
 //! route for `127.0.0.0/8`.
 //!
+//! # Local delivery
+//!
+//! Everything addressed to this host — `127.0.0.0/8` or the address of any
+//! configured interface — goes through `lo`, TCP segments included, so two
+//! local sockets can talk without a NIC.  Senders often hold socket locks,
+//! so nothing is delivered from inside `tx()`; [`loopback_flush`] drains the
+//! queue instead.  The socket layer calls it before a task sleeps and when a
+//! socket syscall returns, and the NAPI loop calls it for packets queued by
+//! timers.
+//!
 //! # Concurrency
 //!
 //! The internal queue is protected by an [`IrqMutex`] since both `tx()` (from
@@ -26,11 +36,24 @@ use slopos_lib::IrqMutex;
 use super::netdev::{NetDevice, NetDeviceFeatures, NetDeviceStats};
 use super::packetbuf::PacketBuf;
 use super::pool::PacketPool;
-use super::types::{MacAddr, NetError};
+use super::types::{DevIndex, Ipv4Addr, MacAddr, NetError};
 
 /// Maximum number of packets queued in the loopback device.
 const LOOPBACK_QUEUE_CAPACITY: usize = 256;
 
+/// Device index of `lo`.
+pub const LOOPBACK_DEV: DevIndex = DevIndex(0);
+
+/// Packets taken from the queue per poll.
+const LOOPBACK_POLL_BUDGET: usize = 32;
+
+/// Polls per [`loopback_flush`].  Every delivered segment can queue a reply,
+/// so the bound keeps two chatty sockets from pinning the caller.
+const LOOPBACK_FLUSH_ROUNDS: usize = 64;
+
+/// Serializes draining so packets reach ingress in the order they were sent.
+static LOOPBACK_DRAIN: IrqMutex<()> = IrqMutex::new(());
+
 /// Inner state of the loopback device, behind [`IrqMutex`].
 struct LoopbackInner {
     /// Packets waiting to be "received" by the ingress pipeline.
@@ -118,6 +141,59 @@ impl NetDevice for LoopbackDev {
     }
 }
 
+// =============================================================================
+// Local delivery
+// =============================================================================
+
+/// Whether traffic to `ip` stays on this host and belongs on `lo`.
+pub fn is_local_destination(ip: Ipv4Addr) -> bool {
+    ip.is_loopback() || super::netstack::NET_STACK.is_our_addr(ip)
+}
+
+/// Feed one frame from `lo` to the IPv4 input path.  Loopback frames carry
+/// an Ethernet header but skip MAC filtering and checksum verification.
+fn deliver(mut pkt: PacketBuf) {
+    let data = pkt.payload();
+    if data.len() < super::ETH_HEADER_LEN {
+        return;
+    }
+    let ethertype = u16::from_be_bytes([data[12], data[13]]);
+    // Loopback only handles IPv4 for now.
+    if super::EtherType::from_u16(ethertype) != Some(super::EtherType::Ipv4) {
+        return;
+    }
+    pkt.set_l2(pkt.head());
+    pkt.set_l3(pkt.head() + super::ETH_HEADER_LEN as u16);
+    if pkt.pull_header(super::ETH_HEADER_LEN).is_ok() {
+        super::ipv4::handle_rx(LOOPBACK_DEV, pkt, true);
+    }
+}
+
+/// Deliver the packets queued on `lo`, including replies they provoke.
+/// Returns the number delivered.
+///
+/// Must be called without socket or TCP locks held: delivery runs the TCP
+/// state machine and the socket wakeups.
+pub fn loopback_flush() -> usize {
+    use super::netdev::DEVICE_REGISTRY;
+    use super::pool::PACKET_POOL;
+
+    let _drain = LOOPBACK_DRAIN.lock();
+    let mut delivered = 0;
+    for _ in 0..LOOPBACK_FLUSH_ROUNDS {
+        let packets =
+            DEVICE_REGISTRY.poll_rx_by_index(LOOPBACK_DEV, LOOPBACK_POLL_BUDGET, &PACKET_POOL);
+        if packets.is_empty() {
+            break;
+        }
+        delivered += packets.len();
+        for pkt in packets {
+            deliver(pkt);
+        }
+    }
+    delivered
+}
+
 // =============================================================================
 // 3C.2 — Loopback registration
 // =============================================================================
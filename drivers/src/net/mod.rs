@@ -1,7 +1,7 @@
 //! Network subsystem.
 //!
 //! Core abstractions (types, pool, packet buffers, device trait) and protocol
-//! modules (DHCP, DNS, TCP, UDP) shared across network drivers.
+//! modules (DHCP, DNS, ICMP, IGMP, raw IP, TCP, UDP) shared across network drivers.
 pub mod netdev;
 pub mod packetbuf;
 pub mod pool;
@@ -9,7 +9,18 @@ pub mod types;
 
 pub mod arp;
 pub mod dhcp;
+#[cfg(feature = "itests")]
+pub mod dhcp_tests;
 pub mod dns;
+pub mod filter;
+#[cfg(feature = "itests")]
+pub mod filter_tests;
+pub mod icmp;
+#[cfg(feature = "itests")]
+pub mod icmp_tests;
+pub mod igmp;
+#[cfg(feature = "itests")]
+pub mod igmp_tests;
 pub mod ingress;
 pub mod ipv4;
 pub mod loopback;
@@ -18,10 +29,16 @@ pub mod neighbor;
 pub mod netstack;
 #[cfg(feature = "itests")]
 pub mod phase4d_tests;
+pub mod raw;
+#[cfg(feature = "itests")]
+pub mod raw_tests;
 pub mod route;
 pub mod socket;
 #[cfg(feature = "itests")]
 pub mod socket_option_tests;
+pub mod sockmem;
+#[cfg(feature = "itests")]
+pub mod sockmem_tests;
 pub mod tcp;
 pub mod tcp_socket;
 #[cfg(feature = "itests")]
@@ -74,6 +91,7 @@ pub const IPV4_BROADCAST: [u8; 4] = [255, 255, 255, 255];
 pub const IPPROTO_TCP: u8 = 6;
 pub const IPPROTO_UDP: u8 = 17;
 pub const IPPROTO_ICMP: u8 = 1;
+pub const IPPROTO_IGMP: u8 = 2;
 
 pub fn parse_udp_header(payload: &[u8]) -> Option<(u16, u16, &[u8])> {
     if payload.len() < 8 {
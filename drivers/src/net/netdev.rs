@@ -262,17 +262,31 @@ pub struct DeviceHandle {
 unsafe impl Send for DeviceHandle {}
 unsafe impl Sync for DeviceHandle {}
 
+/// Hand `pkt` to `dev`, finishing a partial L4 checksum in software when the
+/// device cannot offload it.
+fn tx_with_offload(
+    dev: &(dyn NetDevice + Send + Sync),
+    mut pkt: PacketBuf,
+) -> Result<(), NetError> {
+    if pkt.checksum_partial().is_some() && !dev.features().contains(NetDeviceFeatures::CHECKSUM_TX)
+    {
+        pkt.finish_checksum();
+    }
+    dev.tx(pkt)
+}
+
 impl DeviceHandle {
     /// Transmit a packet through this device.
     ///
     /// Acquires the per-device TX lock (**not** the registry lock).  Multiple
-    /// callers (socket TX paths) are serialized by this lock.
+    /// callers (socket TX paths) are serialized by this lock.  A partial L4
+    /// checksum is finished in software unless the device has `CHECKSUM_TX`.
     pub fn tx(&self, pkt: PacketBuf) -> Result<(), NetError> {
         let _guard = self.tx_lock.lock();
         // SAFETY: The pointer is valid for the device's registered lifetime.
         // The trait method takes `&self`, so no mutable aliasing issues.
         let dev = unsafe { &*self.dev };
-        dev.tx(pkt)
+        tx_with_offload(dev, pkt)
     }
 
     /// Poll for received packets.
@@ -472,7 +486,7 @@ impl NetDeviceRegistry {
     pub fn tx_by_index(&self, index: DevIndex, pkt: PacketBuf) -> Result<(), NetError> {
         let inner = self.inner.lock();
         match inner.slots.get(index.0) {
-            Some(Some(dev)) => dev.tx(pkt),
+            Some(Some(dev)) => tx_with_offload(dev.as_ref(), pkt),
             _ => Err(NetError::NetworkUnreachable),
         }
     }
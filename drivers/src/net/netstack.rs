@@ -16,6 +16,9 @@
 //! # Integration
 //!
 //! - **DHCP**: calls [`NetStack::configure`] when a lease is obtained.
+//! - **Static config**: an `ip=` kernel cmdline option is parsed into a
+//!   [`StaticNetConfig`] at boot; the NIC driver applies it instead of
+//!   running DHCP.
 //! - **ARP**: calls [`NetStack::our_ip`] to decide whether to respond to
 //!   requests.
 //! - **Socket layer**: calls [`NetStack::our_ip`] for source address selection.
@@ -112,6 +115,95 @@ impl fmt::Display for IfaceConfig {
     }
 }
 
+// =============================================================================
+// Static configuration (no DHCP)
+// =============================================================================
+
+/// Address configuration given on the kernel cmdline, for environments
+/// without a DHCP server (or tests that must not depend on one):
+///
+/// ```text
+/// ip=10.0.2.15/24,gw=10.0.2.2,dns=10.0.2.3
+/// ```
+///
+/// The prefix length defaults to /24; `gw` and `dns` are optional, and
+/// `dns` may be given twice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StaticNetConfig {
+    pub addr: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    pub gateway: Ipv4Addr,
+    pub dns: [Ipv4Addr; 2],
+}
+
+impl StaticNetConfig {
+    /// Cmdline key that introduces a static configuration.
+    pub const CMDLINE_KEY: &'static str = "ip=";
+
+    /// Find and parse the `ip=` option in a full kernel cmdline.
+    pub fn from_cmdline(cmdline: &str) -> Option<Self> {
+        cmdline
+            .split_whitespace()
+            .find(|token| token.starts_with(Self::CMDLINE_KEY))
+            .and_then(Self::parse)
+    }
+
+    /// Parse a single `ip=ADDR[/PREFIX][,gw=ADDR][,dns=ADDR]...` option.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut config = Self {
+            addr: Ipv4Addr::UNSPECIFIED,
+            netmask: netmask_from_prefix(24),
+            gateway: Ipv4Addr::UNSPECIFIED,
+            dns: [Ipv4Addr::UNSPECIFIED; 2],
+        };
+        let mut dns_count = 0;
+
+        for field in spec.split(',') {
+            let (key, value) = field.split_once('=')?;
+            match key {
+                "ip" => {
+                    let (addr, prefix) = match value.split_once('/') {
+                        Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+                        None => (value, None),
+                    };
+                    config.addr = parse_addr(addr)?;
+                    if let Some(prefix) = prefix {
+                        if prefix > 32 {
+                            return None;
+                        }
+                        config.netmask = netmask_from_prefix(prefix);
+                    }
+                }
+                "gw" => config.gateway = parse_addr(value)?,
+                "dns" if dns_count < config.dns.len() => {
+                    config.dns[dns_count] = parse_addr(value)?;
+                    dns_count += 1;
+                }
+                _ => return None,
+            }
+        }
+
+        if config.addr.is_unspecified() {
+            return None;
+        }
+        if !config.gateway.is_unspecified()
+            && !Ipv4Addr::in_subnet(config.gateway, config.addr, config.netmask)
+        {
+            return None;
+        }
+        Some(config)
+    }
+}
+
+fn parse_addr(s: &str) -> Option<Ipv4Addr> {
+    super::dns::parse_ip_literal(s.as_bytes()).map(Ipv4Addr::from_bytes)
+}
+
+fn netmask_from_prefix(prefix: u8) -> Ipv4Addr {
+    let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+    Ipv4Addr::from_u32_be(mask)
+}
+
 // =============================================================================
 // 3A.2 — NetStack
 // =============================================================================
@@ -120,6 +212,8 @@ impl fmt::Display for IfaceConfig {
 struct NetStackInner {
     /// Per-interface configurations.  One entry per configured device.
     ifaces: Vec<IfaceConfig>,
+    /// Static configuration from the cmdline, applied in place of DHCP.
+    static_config: Option<StaticNetConfig>,
 }
 
 /// Centralised network stack state — the single source of truth for per-interface
@@ -152,7 +246,10 @@ impl NetStack {
     /// Create an empty network stack.
     pub const fn new() -> Self {
         Self {
-            inner: IrqMutex::new(NetStackInner { ifaces: Vec::new() }),
+            inner: IrqMutex::new(NetStackInner {
+                ifaces: Vec::new(),
+                static_config: None,
+            }),
         }
     }
 
@@ -241,6 +338,33 @@ impl NetStack {
             });
         }
     }
+
+    /// Remove a device's interface configuration and its routes, as when
+    /// its DHCP lease expires.
+    pub fn deconfigure(&self, dev: DevIndex) {
+        let removed = {
+            let mut inner = self.inner.lock();
+            let before = inner.ifaces.len();
+            inner.ifaces.retain(|c| c.dev_index != dev);
+            inner.ifaces.len() != before
+        };
+        if removed {
+            klog_debug!("netstack: deconfigured dev {}", dev);
+            super::route::ROUTE_TABLE.remove_device_routes(dev);
+        }
+    }
+
+    /// Record a static configuration for the NIC driver to apply instead of
+    /// DHCP.  Must be set before the NIC is probed to take effect.
+    pub fn set_static_config(&self, config: Option<StaticNetConfig>) {
+        self.inner.lock().static_config = config;
+    }
+
+    /// The static configuration given at boot, if any.
+    pub fn static_config(&self) -> Option<StaticNetConfig> {
+        self.inner.lock().static_config
+    }
+
     /// Look up the interface configuration for a device.
     ///
     /// Returns `None` if the device has not been configured.
@@ -264,25 +388,66 @@ impl NetStack {
         inner.ifaces.iter().any(|c| c.ipv4_addr == ip && c.up)
     }
 
+    /// Check if `ip` is a broadcast address: the limited broadcast
+    /// `255.255.255.255` or the subnet broadcast of a configured interface.
+    pub fn is_broadcast_addr(&self, ip: Ipv4Addr) -> bool {
+        if ip.is_broadcast() {
+            return true;
+        }
+        let inner = self.inner.lock();
+        inner
+            .ifaces
+            .iter()
+            .any(|c| c.up && c.netmask.to_u32_be() != u32::MAX && c.broadcast() == ip)
+    }
+
     /// Return the first configured interface's IPv4 address.
     ///
-    /// Convenience for the common single-NIC case.  Returns `None` if no
-    /// interface has been configured.
+    /// Convenience for the common single-NIC case, so loopback is skipped.
+    /// Returns `None` if no other interface has been configured.
     pub fn first_ipv4(&self) -> Option<Ipv4Addr> {
         let inner = self.inner.lock();
         inner
             .ifaces
             .iter()
-            .find(|c| c.up && !c.ipv4_addr.is_unspecified())
+            .find(|c| c.up && !c.ipv4_addr.is_unspecified() && !c.ipv4_addr.is_loopback())
             .map(|c| c.ipv4_addr)
     }
 
-    /// Return the first configured interface's full config.
+    /// Return the first configured interface's full config, loopback
+    /// skipped.
     ///
     /// Convenience for DHCP info queries.
     pub fn first_iface(&self) -> Option<IfaceConfig> {
         let inner = self.inner.lock();
-        inner.ifaces.iter().find(|c| c.up).copied()
+        inner
+            .ifaces
+            .iter()
+            .find(|c| c.up && !c.ipv4_addr.is_loopback())
+            .copied()
+    }
+
+    /// Source address for traffic to `dst`: the address of the interface
+    /// the route to `dst` leaves by, so `127.0.0.1` for loopback
+    /// destinations.  Falls back to [`first_ipv4`](Self::first_ipv4), then
+    /// to `0.0.0.0`.
+    pub fn source_addr_for(&self, dst: Ipv4Addr) -> Ipv4Addr {
+        super::route::ROUTE_TABLE
+            .lookup(dst)
+            .and_then(|(dev, _)| self.our_ip(dev))
+            .filter(|ip| !ip.is_unspecified())
+            .or_else(|| self.first_ipv4())
+            .unwrap_or(Ipv4Addr::UNSPECIFIED)
+    }
+
+    /// DNS servers of the first up interface that has any, primary first.
+    pub fn dns_servers(&self) -> [Ipv4Addr; 2] {
+        let inner = self.inner.lock();
+        inner
+            .ifaces
+            .iter()
+            .find(|c| c.up && c.dns.iter().any(|d| !d.is_unspecified()))
+            .map_or([Ipv4Addr::UNSPECIFIED; 2], |c| c.dns)
     }
 
     /// Number of configured interfaces (diagnostic).
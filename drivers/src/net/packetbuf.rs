@@ -73,6 +73,9 @@ pub struct PacketBuf {
     l3_offset: u16,
     /// Byte offset of the L4 (TCP/UDP) header within the backing buffer.
     l4_offset: u16,
+    /// Offset of the L4 checksum field from `l4_offset` when the checksum is
+    /// left for the device to finish; 0 when the checksum is complete.
+    csum_offset: u16,
 }
 
 // -- Drop: return pooled buffers automatically --------------------------------
@@ -100,13 +103,14 @@ impl fmt::Debug for PacketBuf {
         }
         write!(
             f,
-            " {{ head={}, tail={}, len={}, l2={}, l3={}, l4={} }}",
+            " {{ head={}, tail={}, len={}, l2={}, l3={}, l4={}, csum_offset={} }}",
             self.head,
             self.tail,
             self.len(),
             self.l2_offset,
             self.l3_offset,
-            self.l4_offset
+            self.l4_offset,
+            self.csum_offset
         )
     }
 }
@@ -135,6 +139,7 @@ impl PacketBuf {
             l2_offset: 0,
             l3_offset: 0,
             l4_offset: 0,
+            csum_offset: 0,
         })
     }
 
@@ -165,6 +170,7 @@ impl PacketBuf {
             l2_offset: 0,
             l3_offset: 0,
             l4_offset: 0,
+            csum_offset: 0,
         })
     }
 
@@ -182,6 +188,7 @@ impl PacketBuf {
             l2_offset: 0,
             l3_offset: 0,
             l4_offset: 0,
+            csum_offset: 0,
         }
     }
 }
@@ -470,9 +477,8 @@ impl PacketBuf {
     /// The checksum field at TCP header bytes 16–17 is treated as zero.
     /// The L4 segment includes both the TCP header and its payload.
     ///
-    /// Software checksum is always computed.  If `NetDeviceFeatures::CHECKSUM_TX`
-    /// is set, the driver may offload — but the stack does not skip computation
-    /// (simplicity over performance for now).
+    /// Computes the full checksum in software; TX paths that can defer it to
+    /// the device use [`set_checksum_partial`](Self::set_checksum_partial).
     pub fn compute_tcp_checksum(&self, src: Ipv4Addr, dst: Ipv4Addr) -> u16 {
         let segment = self.l4_header();
         if segment.len() < 20 {
@@ -516,3 +522,68 @@ impl PacketBuf {
         if csum == 0 { 0xFFFF } else { csum }
     }
 }
+
+// =============================================================================
+// Checksum offload
+// =============================================================================
+
+/// Offset of the checksum field within a TCP header.
+pub const TCP_CSUM_OFFSET: u16 = 16;
+/// Offset of the checksum field within a UDP header.
+pub const UDP_CSUM_OFFSET: u16 = 6;
+
+impl PacketBuf {
+    /// Defer the L4 checksum to the egress device.
+    ///
+    /// Stores the folded pseudo-header sum in the checksum field (at
+    /// `csum_offset` bytes into the L4 header) and marks the packet partial.
+    /// A device with `NetDeviceFeatures::CHECKSUM_TX` sums from the L4 header
+    /// to the end of the frame and writes the result; otherwise
+    /// [`DeviceHandle::tx`](super::netdev::DeviceHandle::tx) calls
+    /// [`finish_checksum`](Self::finish_checksum) first.
+    ///
+    /// Requires `l4_offset` to be set.
+    pub fn set_checksum_partial(
+        &mut self,
+        src: Ipv4Addr,
+        dst: Ipv4Addr,
+        protocol: u8,
+        csum_offset: u16,
+    ) {
+        let l4_len = self.l4_header().len();
+        if self.l4_offset == 0 || csum_offset as usize + 2 > l4_len {
+            return;
+        }
+        let field = (self.l4_offset + csum_offset) as usize;
+        let mut sum = 0u32;
+        add_pseudo_header(&mut sum, &src, &dst, protocol, l4_len);
+        let partial = !fold_checksum(sum);
+        self.data_mut()[field..field + 2].copy_from_slice(&partial.to_be_bytes());
+        self.csum_offset = csum_offset;
+    }
+
+    /// `(csum_start, csum_offset)` if the L4 checksum still has to be
+    /// finished, with `csum_start` relative to the start of the payload.
+    #[inline]
+    pub fn checksum_partial(&self) -> Option<(u16, u16)> {
+        if self.csum_offset == 0 {
+            return None;
+        }
+        Some((self.l4_offset - self.head, self.csum_offset))
+    }
+
+    /// Complete a partial checksum in software.
+    pub fn finish_checksum(&mut self) {
+        let Some((_, csum_offset)) = self.checksum_partial() else {
+            return;
+        };
+        let mut csum = fold_checksum(ones_complement_sum(self.l4_header()));
+        // RFC 768: a zero UDP checksum means "none"; send 0xFFFF instead.
+        if csum == 0 && csum_offset == UDP_CSUM_OFFSET {
+            csum = 0xFFFF;
+        }
+        let field = (self.l4_offset + csum_offset) as usize;
+        self.data_mut()[field..field + 2].copy_from_slice(&csum.to_be_bytes());
+        self.csum_offset = 0;
+    }
+}
@@ -0,0 +1,102 @@
+//! Raw IPv4 sockets (`SOCK_RAW`).
+//!
+//! A raw socket is tied to one IP protocol number.  Each inbound datagram of
+//! that protocol which passes the input filter is copied, IP header
+//! included, to every matching raw socket before the kernel's own protocol
+//! handler sees it (see [`socket_deliver_raw`](super::socket::socket_deliver_raw)).
+//!
+//! [`raw_sendto`] sends the other way: it either prepends an IPv4 header
+//! built for the socket or, with `IP_HDRINCL`, sends the caller's header
+//! after filling in the total length, the checksum and a zero source
+//! address.  Nothing is fragmented, so a datagram must fit the MTU.
+
+use super::types::{Ipv4Addr, NetError};
+use super::{ETH_HEADER_LEN, ETHERTYPE_IPV4, IPV4_HEADER_LEN, NET_STACK, PacketBuf};
+
+/// Largest datagram, IP header included, a raw socket can send.
+pub const RAW_MAX_DATAGRAM: usize = 1500;
+
+/// Send `data` as one IPv4 datagram routed to `dst_ip`.
+///
+/// Without `hdrincl`, `data` is the payload of a datagram of `protocol`
+/// from `local_ip` (or the outgoing interface's address when unspecified)
+/// with the given TTL.  With `hdrincl`, `data` starts with a complete IPv4
+/// header that is sent as written apart from the fields above.  Returns
+/// the number of bytes of `data` sent.
+pub fn raw_sendto(
+    local_ip: Ipv4Addr,
+    dst_ip: Ipv4Addr,
+    protocol: u8,
+    hdrincl: bool,
+    data: &[u8],
+    ttl: u8,
+) -> Result<usize, NetError> {
+    let max = if hdrincl {
+        RAW_MAX_DATAGRAM
+    } else {
+        RAW_MAX_DATAGRAM - IPV4_HEADER_LEN
+    };
+    if data.len() > max {
+        return Err(NetError::InvalidArgument);
+    }
+    let src_ip = if local_ip.is_unspecified() {
+        NET_STACK.source_addr_for(dst_ip)
+    } else {
+        local_ip
+    };
+
+    let mut pkt = PacketBuf::alloc().ok_or(NetError::NoBufferSpace)?;
+    pkt.append(data)?;
+
+    let ihl = if hdrincl {
+        let ihl = data.first().map_or(0, |b| (b & 0x0F) as usize * 4);
+        if data.len() < IPV4_HEADER_LEN || data[0] >> 4 != 4 || ihl < IPV4_HEADER_LEN {
+            return Err(NetError::InvalidArgument);
+        }
+        if ihl > data.len() {
+            return Err(NetError::InvalidArgument);
+        }
+        let ip_hdr = &mut pkt.payload_mut()[..ihl];
+        ip_hdr[2..4].copy_from_slice(&(data.len() as u16).to_be_bytes());
+        if ip_hdr[12..16] == [0; 4] {
+            ip_hdr[12..16].copy_from_slice(&src_ip.0);
+        }
+        ip_hdr[10..12].copy_from_slice(&0u16.to_be_bytes());
+        let checksum = super::ipv4_header_checksum(ip_hdr);
+        ip_hdr[10..12].copy_from_slice(&checksum.to_be_bytes());
+        ihl
+    } else {
+        let total_len = (IPV4_HEADER_LEN + data.len()) as u16;
+        let ip_hdr = pkt.push_header(IPV4_HEADER_LEN)?;
+        ip_hdr[0] = 0x45;
+        ip_hdr[1] = 0;
+        ip_hdr[2..4].copy_from_slice(&total_len.to_be_bytes());
+        ip_hdr[4..8].copy_from_slice(&[0; 4]);
+        ip_hdr[8] = ttl;
+        ip_hdr[9] = protocol;
+        ip_hdr[10..12].copy_from_slice(&0u16.to_be_bytes());
+        ip_hdr[12..16].copy_from_slice(&src_ip.0);
+        ip_hdr[16..20].copy_from_slice(&dst_ip.0);
+        let checksum = super::ipv4_header_checksum(ip_hdr);
+        ip_hdr[10..12].copy_from_slice(&checksum.to_be_bytes());
+        IPV4_HEADER_LEN
+    };
+
+    {
+        let eth_hdr = pkt.push_header(ETH_HEADER_LEN)?;
+        eth_hdr[0..6].copy_from_slice(&[0xff; 6]);
+        eth_hdr[6..12].copy_from_slice(&crate::virtio_net::virtio_net_mac().unwrap_or([0; 6]));
+        eth_hdr[12..14].copy_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+    }
+
+    let head = pkt.head();
+    pkt.set_l2(head);
+    pkt.set_l3(head + ETH_HEADER_LEN as u16);
+    pkt.set_l4(head + (ETH_HEADER_LEN + ihl) as u16);
+
+    super::ipv4::send(dst_ip, pkt).map_err(|err| match err {
+        NetError::PermissionDenied => err,
+        _ => NetError::NetworkUnreachable,
+    })?;
+    Ok(data.len())
+}
@@ -0,0 +1,197 @@
+//! Raw socket tests: protocol checks at creation, delivery of inbound
+//! datagrams with their header, and both ways of sending, all over `lo`.
+
+use slopos_abi::net::{AF_INET, IPPROTO_RAW, SOCK_RAW};
+use slopos_abi::syscall::{
+    ERRNO_EAGAIN, ERRNO_EINVAL, ERRNO_EPROTONOSUPPORT, IP_HDRINCL, IPPROTO_IP,
+};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::loopback::loopback_flush;
+use super::socket::{
+    socket_close, socket_connect, socket_create, socket_getsockopt, socket_recvfrom,
+    socket_reset_all, socket_sendto, socket_setsockopt,
+};
+use super::types::Ipv4Addr;
+use super::{IPV4_HEADER_LEN, ipv4_header_checksum};
+
+const EAGAIN: i64 = ERRNO_EAGAIN as i64;
+const EINVAL: i64 = ERRNO_EINVAL as i64;
+const EPROTONOSUPPORT: i32 = ERRNO_EPROTONOSUPPORT as i64 as i32;
+
+/// Experimental protocol numbers (RFC 3692) the kernel has no handler for.
+const PROTO_A: u16 = 253;
+const PROTO_B: u16 = 254;
+
+fn reset() {
+    socket_reset_all();
+    loopback_flush();
+}
+
+fn raw_socket(protocol: u16) -> Option<u32> {
+    let fd = socket_create(AF_INET, SOCK_RAW, protocol);
+    (fd >= 0).then_some(fd as u32)
+}
+
+fn send_to_lo(sock: u32, data: &[u8]) -> i64 {
+    let rc = socket_sendto(sock, data.as_ptr(), data.len(), Ipv4Addr::LOCALHOST.0, 0);
+    loopback_flush();
+    rc
+}
+
+fn recv(sock: u32, buf: &mut [u8]) -> (i64, [u8; 4]) {
+    let mut src = [0u8; 4];
+    let rc = socket_recvfrom(
+        sock,
+        buf.as_mut_ptr(),
+        buf.len(),
+        &mut src,
+        core::ptr::null_mut(),
+    );
+    (rc, src)
+}
+
+fn hdrincl(sock: u32) -> i32 {
+    let mut out = [0u8; 4];
+    socket_getsockopt(sock, IPPROTO_IP, IP_HDRINCL, &mut out);
+    i32::from_ne_bytes(out)
+}
+
+pub fn test_raw_create_checks_protocol() -> TestResult {
+    reset();
+    assert_eq_test!(socket_create(AF_INET, SOCK_RAW, 0), EPROTONOSUPPORT);
+    assert_eq_test!(socket_create(AF_INET, SOCK_RAW, 256), EPROTONOSUPPORT);
+    let Some(sock) = raw_socket(1) else {
+        return fail!("raw ICMP socket refused");
+    };
+    assert_eq_test!(hdrincl(sock), 0, "IP_HDRINCL default");
+    socket_close(sock);
+    pass!()
+}
+
+pub fn test_raw_receives_datagram_with_header() -> TestResult {
+    reset();
+    let (Some(tx), Some(rx), Some(other)) = (
+        raw_socket(PROTO_A),
+        raw_socket(PROTO_A),
+        raw_socket(PROTO_B),
+    ) else {
+        return fail!("socket_create failed");
+    };
+
+    let payload = b"probe";
+    assert_eq_test!(send_to_lo(tx, payload), payload.len() as i64);
+
+    let mut buf = [0u8; 64];
+    let (len, src) = recv(rx, &mut buf);
+    assert_eq_test!(len, (IPV4_HEADER_LEN + payload.len()) as i64);
+    let (hdr, data) = buf[..len as usize].split_at(IPV4_HEADER_LEN);
+    assert_eq_test!(hdr[0], 0x45, "version and IHL");
+    assert_eq_test!(u16::from_be_bytes([hdr[2], hdr[3]]) as i64, len);
+    assert_eq_test!(hdr[9], PROTO_A as u8, "protocol");
+    assert_eq_test!(&hdr[12..16], &Ipv4Addr::LOCALHOST.0, "source");
+    assert_eq_test!(&hdr[16..20], &Ipv4Addr::LOCALHOST.0, "destination");
+    assert_eq_test!(ipv4_header_checksum(hdr), 0, "header checksum");
+    assert_eq_test!(data, &payload[..]);
+    assert_eq_test!(src, Ipv4Addr::LOCALHOST.0);
+
+    assert_eq_test!(recv(other, &mut buf).0, EAGAIN, "other protocol got it");
+    socket_close(other);
+    socket_close(rx);
+    socket_close(tx);
+    pass!()
+}
+
+pub fn test_raw_hdrincl_fills_length_and_checksum() -> TestResult {
+    reset();
+    let (Some(tx), Some(rx)) = (raw_socket(PROTO_A), raw_socket(PROTO_A)) else {
+        return fail!("socket_create failed");
+    };
+    let on = 1i32.to_ne_bytes();
+    assert_eq_test!(socket_setsockopt(tx, IPPROTO_IP, IP_HDRINCL, &on), 0);
+    assert_eq_test!(hdrincl(tx), 1);
+
+    let mut dgram = [0u8; IPV4_HEADER_LEN + 4];
+    dgram[0] = 0x45;
+    dgram[8] = 9;
+    dgram[9] = PROTO_A as u8;
+    dgram[16..20].copy_from_slice(&Ipv4Addr::LOCALHOST.0);
+    dgram[IPV4_HEADER_LEN..].copy_from_slice(b"data");
+    assert_eq_test!(send_to_lo(tx, &dgram), dgram.len() as i64);
+
+    let mut buf = [0u8; 64];
+    let (len, _) = recv(rx, &mut buf);
+    assert_eq_test!(len, dgram.len() as i64);
+    let hdr = &buf[..IPV4_HEADER_LEN];
+    assert_eq_test!(u16::from_be_bytes([hdr[2], hdr[3]]) as usize, dgram.len());
+    assert_eq_test!(hdr[8], 9, "caller's TTL kept");
+    assert_eq_test!(&hdr[12..16], &Ipv4Addr::LOCALHOST.0, "source filled in");
+    assert_eq_test!(ipv4_header_checksum(hdr), 0, "header checksum");
+
+    dgram[0] = 0x65;
+    assert_eq_test!(send_to_lo(tx, &dgram), EINVAL, "IPv6 version accepted");
+    dgram[0] = 0x4f;
+    assert_eq_test!(send_to_lo(tx, &dgram), EINVAL, "IHL past the data");
+    socket_close(rx);
+    socket_close(tx);
+    pass!()
+}
+
+pub fn test_raw_connected_socket_filters_source() -> TestResult {
+    reset();
+    let (Some(tx), Some(near), Some(far)) = (
+        raw_socket(PROTO_A),
+        raw_socket(PROTO_A),
+        raw_socket(PROTO_A),
+    ) else {
+        return fail!("socket_create failed");
+    };
+    assert_eq_test!(socket_connect(near, Ipv4Addr::LOCALHOST.0, 0), 0);
+    assert_eq_test!(socket_connect(far, [10, 9, 9, 9], 0), 0);
+    send_to_lo(tx, b"x");
+
+    let mut buf = [0u8; 64];
+    assert_test!(recv(near, &mut buf).0 > 0, "connected peer filtered out");
+    assert_eq_test!(recv(far, &mut buf).0, EAGAIN, "other peer delivered");
+    socket_close(far);
+    socket_close(near);
+    socket_close(tx);
+    pass!()
+}
+
+pub fn test_ipproto_raw_sends_only() -> TestResult {
+    reset();
+    let (Some(tx), Some(rx)) = (raw_socket(IPPROTO_RAW), raw_socket(PROTO_A)) else {
+        return fail!("socket_create failed");
+    };
+    assert_eq_test!(hdrincl(tx), 1, "IPPROTO_RAW without IP_HDRINCL");
+    let off = 0i32.to_ne_bytes();
+    socket_setsockopt(tx, IPPROTO_IP, IP_HDRINCL, &off);
+    assert_eq_test!(hdrincl(tx), 1, "IP_HDRINCL cleared");
+
+    let mut dgram = [0u8; IPV4_HEADER_LEN];
+    dgram[0] = 0x45;
+    dgram[8] = 64;
+    dgram[9] = PROTO_A as u8;
+    dgram[16..20].copy_from_slice(&Ipv4Addr::LOCALHOST.0);
+    assert_eq_test!(send_to_lo(tx, &dgram), dgram.len() as i64);
+
+    let mut buf = [0u8; 64];
+    assert_eq_test!(recv(rx, &mut buf).0, IPV4_HEADER_LEN as i64);
+    assert_eq_test!(recv(tx, &mut buf).0, EAGAIN, "IPPROTO_RAW received");
+    socket_close(rx);
+    socket_close(tx);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    net_raw,
+    [
+        test_raw_create_checks_protocol,
+        test_raw_receives_datagram_with_header,
+        test_raw_hdrincl_fills_length_and_checksum,
+        test_raw_connected_socket_filters_source,
+        test_ipproto_raw_sends_only,
+    ]
+);
@@ -24,23 +24,28 @@
 //! - **IPv4 egress**: calls [`RouteTable::lookup`] to determine the outgoing
 //!   device and next-hop address for each packet.
 //! - **Loopback**: the `127.0.0.0/8` connected route is added at kernel init.
+//! - **Management**: `SYSCALL_NET_ROUTE` adds, deletes and lists routes
+//!   through [`add_route`], [`del_route`] and [`list_routes`], which check
+//!   user input before it reaches the table.
 
 extern crate alloc;
 
 use alloc::vec::Vec;
 use core::fmt;
 
+use slopos_abi::net::{NET_ROUTE_DEV_AUTO, NetRoute};
 use slopos_lib::IrqMutex;
 use slopos_lib::klog_debug;
 
-use super::types::{DevIndex, Ipv4Addr};
+use super::netdev::DEVICE_REGISTRY;
+use super::types::{DevIndex, Ipv4Addr, NetError};
 
 // =============================================================================
 // 3B.1 — RouteEntry
 // =============================================================================
 
 /// Maximum number of routes per prefix-length bucket.
-const MAX_ROUTES_PER_BUCKET: usize = 16;
+pub(crate) const MAX_ROUTES_PER_BUCKET: usize = 16;
 
 /// A single entry in the routing table.
 ///
@@ -166,8 +171,14 @@ impl RouteTable {
     /// already exists, it is replaced.
     ///
     /// Returns `true` if a new route was added, `false` if an existing route
-    /// was updated.
+    /// was updated or the bucket is full.
     pub fn add(&self, entry: RouteEntry) -> bool {
+        self.try_add(entry).unwrap_or(false)
+    }
+
+    /// [`add`](Self::add), reporting a full bucket as
+    /// [`NetError::NoBufferSpace`] instead of folding it into `false`.
+    pub fn try_add(&self, entry: RouteEntry) -> Result<bool, NetError> {
         let mut inner = self.inner.lock();
         let bucket = &mut inner.buckets[entry.prefix_len as usize];
 
@@ -184,7 +195,7 @@ impl RouteTable {
                 existing.metric = entry.metric;
                 // Re-sort by metric after update.
                 bucket.sort_by_key(|r| r.metric);
-                return false;
+                return Ok(false);
             }
         }
 
@@ -195,7 +206,7 @@ impl RouteTable {
                 entry.prefix_len,
                 bucket.len(),
             );
-            return false;
+            return Err(NetError::NoBufferSpace);
         }
 
         klog_debug!("route: added {:?}", entry);
@@ -203,7 +214,7 @@ impl RouteTable {
         // Insert sorted by metric.
         let pos = bucket.partition_point(|r| r.metric <= entry.metric);
         bucket.insert(pos, entry);
-        true
+        Ok(true)
     }
 
     /// Remove a route matching `(prefix, prefix_len)`.
@@ -211,9 +222,20 @@ impl RouteTable {
     /// If multiple routes match (different devices/metrics), removes the first
     /// match.  Returns `true` if a route was removed.
     pub fn remove(&self, prefix: Ipv4Addr, prefix_len: u8) -> bool {
+        self.remove_on(prefix, prefix_len, None)
+    }
+
+    /// Remove the route for `(prefix, prefix_len)`, restricted to `dev` when
+    /// one is given.  Returns `true` if a route was removed.
+    pub fn remove_on(&self, prefix: Ipv4Addr, prefix_len: u8, dev: Option<DevIndex>) -> bool {
         let mut inner = self.inner.lock();
-        let bucket = &mut inner.buckets[prefix_len as usize];
-        if let Some(pos) = bucket.iter().position(|r| r.prefix == prefix) {
+        let Some(bucket) = inner.buckets.get_mut(prefix_len as usize) else {
+            return false;
+        };
+        let pos = bucket
+            .iter()
+            .position(|r| r.prefix == prefix && dev.is_none_or(|dev| r.dev == dev));
+        if let Some(pos) = pos {
             let removed = bucket.remove(pos);
             klog_debug!("route: removed {:?}", removed);
             true
@@ -284,6 +306,100 @@ impl RouteTable {
     }
 }
 
+// =============================================================================
+// Route management (SYSCALL_NET_ROUTE)
+// =============================================================================
+
+/// Check a user-supplied prefix: at most /32, no host bits set.
+fn checked_prefix(route: &NetRoute) -> Result<(Ipv4Addr, u8), NetError> {
+    if route.prefix_len > 32 {
+        return Err(NetError::InvalidArgument);
+    }
+    let prefix = Ipv4Addr(route.prefix);
+    if prefix.to_u32_be() & !prefix_len_to_mask(route.prefix_len) != 0 {
+        return Err(NetError::InvalidArgument);
+    }
+    Ok((prefix, route.prefix_len))
+}
+
+/// The interface a user route names, `None` for [`NET_ROUTE_DEV_AUTO`].
+fn requested_dev(route: &NetRoute) -> Result<Option<DevIndex>, NetError> {
+    if route.dev == NET_ROUTE_DEV_AUTO {
+        return Ok(None);
+    }
+    let dev = DevIndex(route.dev as usize);
+    match DEVICE_REGISTRY.mac_by_index(dev) {
+        Some(_) => Ok(Some(dev)),
+        None => Err(NetError::InvalidArgument),
+    }
+}
+
+/// Validate a user-supplied route and add it to `table`.
+///
+/// A gateway must sit on a connected subnet (of the named interface, when
+/// there is one); with [`NET_ROUTE_DEV_AUTO`] the interface is taken from
+/// that connected route.  A route without a gateway needs an interface.
+pub fn add_route(table: &RouteTable, route: &NetRoute) -> Result<(), NetError> {
+    let (prefix, prefix_len) = checked_prefix(route)?;
+    let requested = requested_dev(route)?;
+    let gateway = Ipv4Addr(route.gateway);
+
+    let dev = if gateway.is_unspecified() {
+        requested.ok_or(NetError::InvalidArgument)?
+    } else if gateway.is_broadcast() || gateway.is_multicast() {
+        return Err(NetError::InvalidArgument);
+    } else {
+        match table.lookup(gateway) {
+            Some((dev, next_hop))
+                if next_hop == gateway && requested.is_none_or(|want| want == dev) =>
+            {
+                dev
+            }
+            _ => return Err(NetError::NetworkUnreachable),
+        }
+    };
+
+    table
+        .try_add(RouteEntry {
+            prefix,
+            prefix_len,
+            gateway,
+            dev,
+            metric: route.metric,
+        })
+        .map(|_| ())
+}
+
+/// Remove the route for a user-supplied prefix, on its interface unless
+/// that is [`NET_ROUTE_DEV_AUTO`].
+pub fn del_route(table: &RouteTable, route: &NetRoute) -> Result<(), NetError> {
+    let (prefix, prefix_len) = checked_prefix(route)?;
+    let dev = (route.dev != NET_ROUTE_DEV_AUTO).then_some(DevIndex(route.dev as usize));
+    if table.remove_on(prefix, prefix_len, dev) {
+        Ok(())
+    } else {
+        Err(NetError::InvalidArgument)
+    }
+}
+
+/// Copy routes into `out`, default routes first.  Returns how many were
+/// copied.
+pub fn list_routes(table: &RouteTable, out: &mut [NetRoute]) -> usize {
+    let routes = table.all_routes();
+    let count = routes.len().min(out.len());
+    for (slot, route) in out.iter_mut().zip(&routes) {
+        *slot = NetRoute {
+            prefix: route.prefix.0,
+            gateway: route.gateway.0,
+            metric: route.metric,
+            prefix_len: route.prefix_len,
+            dev: route.dev.0 as u8,
+            _pad: [0; 2],
+        };
+    }
+    count
+}
+
 // =============================================================================
 // Helper: prefix length → mask
 // =============================================================================
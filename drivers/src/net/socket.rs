@@ -4,7 +4,8 @@ use alloc::vec::Vec;
 use core::fmt;
 use core::sync::atomic::{AtomicU16, Ordering};
 
-use crate::net::packetbuf::PacketBuf;
+use crate::net::packetbuf::{self, PacketBuf};
+use crate::net::sockmem::NET_RMEM;
 use crate::net::tcp_socket;
 use crate::net::types::{Ipv4Addr, NetError, Port, SockAddr};
 
@@ -17,7 +18,7 @@ pub enum SocketInner {
     Udp(UdpSocketInner),
     /// TCP socket state placeholder (expanded in Phase 5).
     Tcp(TcpSocketInner),
-    /// Raw socket state placeholder (expanded in Phase 9).
+    /// Raw IPv4 socket bound to one IP protocol number.
     Raw(RawSocketInner),
 }
 
@@ -37,10 +38,25 @@ pub struct TcpSocketInner {
     pub listen: Option<tcp_socket::TcpListenState>,
 }
 
-/// Raw socket protocol-specific state placeholder.
+/// Raw IPv4 socket state.
 ///
-/// This remains empty in Phase 4A and is expanded in Phase 9.
-pub struct RawSocketInner;
+/// `protocol` selects both the inbound datagrams the socket receives and the
+/// protocol field of the headers it builds.  With `hdrincl` (`IP_HDRINCL`)
+/// the caller writes the IP header itself; [`IPPROTO_RAW`] sockets always do
+/// and receive nothing.
+pub struct RawSocketInner {
+    pub protocol: u8,
+    pub hdrincl: bool,
+}
+
+impl RawSocketInner {
+    pub const fn new(protocol: u8) -> Self {
+        Self {
+            protocol,
+            hdrincl: protocol as u16 == IPPROTO_RAW,
+        }
+    }
+}
 
 /// Socket status and mode flags.
 ///
@@ -105,8 +121,17 @@ pub struct SocketOptions {
     pub send_timeout: Option<u64>,
     /// Enable keepalive (TCP-only behavior in later phases).
     pub keepalive: bool,
-    /// Disable Nagle algorithm (TCP-only behavior in later phases).
+    /// Disable Nagle's algorithm (TCP only).
     pub tcp_nodelay: bool,
+    /// `SO_LINGER` timeout in seconds (`None` means off).
+    ///
+    /// `Some(0)` makes close reset the connection; a longer timeout makes a
+    /// blocking close wait for the peer to acknowledge our FIN.
+    pub linger: Option<u32>,
+    /// Allow sending to broadcast addresses (`SO_BROADCAST`).
+    pub broadcast: bool,
+    /// TTL of outgoing multicast datagrams (`IP_MULTICAST_TTL`).
+    pub multicast_ttl: u8,
 }
 
 impl SocketOptions {
@@ -122,6 +147,10 @@ impl SocketOptions {
     pub const SEND_BUF_MIN: usize = 256;
     /// Maximum allowed send buffer size in bytes.
     pub const SEND_BUF_MAX: usize = 262_144;
+    /// TTL of outgoing unicast and broadcast datagrams.
+    pub const UNICAST_TTL: u8 = 64;
+    /// Default multicast TTL: stay on the local link.
+    pub const MULTICAST_TTL_DEFAULT: u8 = 1;
 
     /// Construct options with Phase 4A defaults.
     pub const fn new() -> Self {
@@ -133,6 +162,18 @@ impl SocketOptions {
             send_timeout: None,
             keepalive: false,
             tcp_nodelay: false,
+            linger: None,
+            broadcast: false,
+            multicast_ttl: Self::MULTICAST_TTL_DEFAULT,
+        }
+    }
+
+    /// TTL for a datagram sent to `dst`.
+    pub const fn ttl_for(&self, dst: Ipv4Addr) -> u8 {
+        if dst.is_multicast() {
+            self.multicast_ttl
+        } else {
+            Self::UNICAST_TTL
         }
     }
 
@@ -278,6 +319,17 @@ impl<T> fmt::Debug for BoundedQueue<T> {
     }
 }
 
+/// Traffic through one socket, reported by `SYSCALL_NET_STAT`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SocketCounters {
+    /// Payload bytes read by the application.
+    pub rx_bytes: u64,
+    /// Payload bytes accepted from the application.
+    pub tx_bytes: u64,
+    /// Datagrams discarded because the receive queue was full.
+    pub rx_dropped: u64,
+}
+
 /// Next wait-queue hint used by `Socket::new` placeholders.
 ///
 /// Phase 6 replaces these indices with real queue registrations.
@@ -302,8 +354,12 @@ pub struct Socket {
     pub remote_addr: Option<SockAddr>,
     /// Receive queue of `(packet, source address)` tuples.
     pub recv_queue: BoundedQueue<(PacketBuf, SockAddr)>,
+    /// Payload bytes in `recv_queue`, charged to [`NET_RMEM`].
+    pub recv_queued_bytes: usize,
     /// Deferred error reported on next operation.
     pub pending_error: Option<NetError>,
+    /// Bytes and drops since the socket was created.
+    pub counters: SocketCounters,
     /// Owning process identifier.
     pub process_id: u32,
     /// Placeholder receive wait queue index (Phase 6 replacement planned).
@@ -329,7 +385,9 @@ impl Socket {
             local_addr: None,
             remote_addr: None,
             recv_queue: BoundedQueue::new(Self::RECV_QUEUE_DEFAULT_CAPACITY),
+            recv_queued_bytes: 0,
             pending_error: None,
+            counters: SocketCounters::default(),
             process_id: 0,
             recv_wq_idx: wq_idx,
             accept_wq_idx: wq_idx,
@@ -337,6 +395,58 @@ impl Socket {
         }
     }
 
+    /// Queue a received datagram.  Fails when the queue is full, when the
+    /// payload would push the queued bytes past `SO_RCVBUF`, or when global
+    /// receive memory is exhausted.
+    pub fn enqueue_datagram(&mut self, packet: PacketBuf, src: SockAddr) -> bool {
+        let len = packet.payload().len();
+        if self.recv_queue.is_full()
+            || self.recv_queued_bytes + len > self.options.recv_buf_size
+            || !NET_RMEM.try_charge(len)
+        {
+            self.counters.rx_dropped += 1;
+            return false;
+        }
+        if !self.recv_queue.push((packet, src)) {
+            NET_RMEM.uncharge(len);
+            self.counters.rx_dropped += 1;
+            return false;
+        }
+        self.recv_queued_bytes += len;
+        true
+    }
+
+    /// Pop the oldest queued datagram, releasing its memory charge.
+    pub fn dequeue_datagram(&mut self) -> Option<(PacketBuf, SockAddr)> {
+        let (packet, src) = self.recv_queue.pop()?;
+        let len = packet.payload().len();
+        self.recv_queued_bytes = self.recv_queued_bytes.saturating_sub(len);
+        NET_RMEM.uncharge(len);
+        Some((packet, src))
+    }
+
+    /// Resize the receive queue to `capacity` datagrams.  Queued datagrams
+    /// that no longer fit, by count or by `SO_RCVBUF`, are dropped.
+    pub fn resize_recv_queue(&mut self, capacity: usize) {
+        let mut queued = Vec::with_capacity(self.recv_queue.len());
+        while let Some(entry) = self.dequeue_datagram() {
+            queued.push(entry);
+        }
+        self.recv_queue.resize(capacity);
+        for (packet, src) in queued {
+            if !self.enqueue_datagram(packet, src) {
+                break;
+            }
+        }
+    }
+
+    /// Drop every queued datagram, releasing their memory charge.
+    pub fn clear_recv_queue(&mut self) {
+        self.recv_queue.clear();
+        NET_RMEM.uncharge(self.recv_queued_bytes);
+        self.recv_queued_bytes = 0;
+    }
+
     /// Return `true` if non-blocking mode is enabled.
     pub fn is_nonblocking(&self) -> bool {
         self.flags.contains(SocketFlags::O_NONBLOCK)
@@ -556,6 +666,34 @@ impl EphemeralPortAllocator {
         None
     }
 
+    /// Allocate one ephemeral port, searching from a random point in the
+    /// range (`seed`) and skipping any port `in_use` reports as bound.
+    ///
+    /// Returns `None` if no ephemeral port is both unallocated and unused.
+    pub fn alloc_random(
+        &mut self,
+        seed: u64,
+        mut in_use: impl FnMut(Port) -> bool,
+    ) -> Option<Port> {
+        if self.allocated_count >= Self::EPHEMERAL_PORT_COUNT {
+            return None;
+        }
+
+        let start = (seed % Self::EPHEMERAL_PORT_COUNT as u64) as usize;
+        for offset in 0..Self::EPHEMERAL_PORT_COUNT {
+            let candidate =
+                Self::EPHEMERAL_PORT_START + ((start + offset) % Self::EPHEMERAL_PORT_COUNT) as u16;
+            if self.is_allocated(candidate) || in_use(Port(candidate)) {
+                continue;
+            }
+            self.set_allocated(candidate);
+            self.allocated_count += 1;
+            return Some(Port(candidate));
+        }
+
+        None
+    }
+
     /// Release a previously allocated ephemeral port.
     pub fn release(&mut self, port: Port) {
         let p = port.0;
@@ -636,15 +774,24 @@ pub static EPHEMERAL_PORTS: slopos_lib::IrqMutex<EphemeralPortAllocator> =
 
 use core::cmp;
 
-use slopos_abi::net::{AF_INET, MAX_SOCKETS, SOCK_DGRAM, SOCK_STREAM};
+use slopos_abi::net::{
+    AF_INET, IPPROTO_RAW, MAX_SOCKETS, NET_FILTER_OUT, NET_SOCK_BOUND, NET_SOCK_CLOSE_WAIT,
+    NET_SOCK_CLOSED, NET_SOCK_CLOSING, NET_SOCK_ESTABLISHED, NET_SOCK_FIN_WAIT1,
+    NET_SOCK_FIN_WAIT2, NET_SOCK_FLAG_NONBLOCK, NET_SOCK_FLAG_SHUT_RD, NET_SOCK_FLAG_SHUT_WR,
+    NET_SOCK_LAST_ACK, NET_SOCK_LISTEN, NET_SOCK_PROTO_RAW, NET_SOCK_PROTO_TCP, NET_SOCK_PROTO_UDP,
+    NET_SOCK_SYN_RECEIVED, NET_SOCK_SYN_SENT, NET_SOCK_TIME_WAIT, NET_SOCK_UNBOUND, NetSocketStat,
+    SOCK_DGRAM, SOCK_RAW, SOCK_STREAM,
+};
 use slopos_abi::syscall::{
-    ERRNO_EADDRINUSE, ERRNO_EAFNOSUPPORT, ERRNO_EAGAIN, ERRNO_ECONNREFUSED, ERRNO_EDESTADDRREQ,
-    ERRNO_EFAULT, ERRNO_EINVAL, ERRNO_EISCONN, ERRNO_ENETUNREACH, ERRNO_ENOMEM, ERRNO_ENOTCONN,
-    ERRNO_ENOTSOCK, ERRNO_EPIPE, ERRNO_EPROTONOSUPPORT, POLLERR, POLLHUP, POLLIN, POLLOUT,
+    ERRNO_EACCES, ERRNO_EADDRINUSE, ERRNO_EADDRNOTAVAIL, ERRNO_EAFNOSUPPORT, ERRNO_EAGAIN,
+    ERRNO_ECONNREFUSED, ERRNO_EDESTADDRREQ, ERRNO_EFAULT, ERRNO_EINVAL, ERRNO_EISCONN,
+    ERRNO_ENETUNREACH, ERRNO_ENOMEM, ERRNO_ENOTCONN, ERRNO_ENOTSOCK, ERRNO_EPERM, ERRNO_EPIPE,
+    ERRNO_EPROTONOSUPPORT, POLLERR, POLLHUP, POLLIN, POLLOUT,
 };
 use slopos_lib::{IrqMutex, WaitQueue};
 
 use crate::net;
+use crate::net::loopback::{self, loopback_flush};
 use crate::net::tcp::{self, TCP_HEADER_LEN, TcpError, TcpOutSegment, TcpState};
 use crate::virtio_net;
 
@@ -772,6 +919,7 @@ fn map_tcp_err_i64(err: TcpError) -> i64 {
 fn map_net_err(err: NetError) -> i32 {
     match err {
         NetError::AddressInUse => errno_i32(ERRNO_EADDRINUSE),
+        NetError::AddressNotAvailable => errno_i32(ERRNO_EADDRNOTAVAIL),
         NetError::AddressFamilyNotSupported => errno_i32(ERRNO_EAFNOSUPPORT),
         NetError::WouldBlock => errno_i32(ERRNO_EAGAIN),
         NetError::NotConnected => errno_i32(ERRNO_ENOTCONN),
@@ -780,19 +928,109 @@ fn map_net_err(err: NetError) -> i32 {
         NetError::NetworkUnreachable | NetError::HostUnreachable => errno_i32(ERRNO_ENETUNREACH),
         NetError::NoBufferSpace => errno_i32(ERRNO_ENOMEM),
         NetError::Shutdown => errno_i32(ERRNO_EPIPE),
+        NetError::PermissionDenied => errno_i32(ERRNO_EPERM),
         _ => errno_i32(ERRNO_EINVAL),
     }
 }
 
-fn alloc_ephemeral_port() -> Option<Port> {
-    EPHEMERAL_PORTS.lock().alloc()
+/// Broadcast destinations need `SO_BROADCAST`; sending there without it is
+/// `EACCES`, as on other systems.
+fn udp_check_dst(options: &SocketOptions, dst: Ipv4Addr) -> Result<u8, i64> {
+    if !options.broadcast && net::NET_STACK.is_broadcast_addr(dst) {
+        return Err(errno_i32(ERRNO_EACCES) as i64);
+    }
+    Ok(options.ttl_for(dst))
+}
+
+/// Whether two local addresses overlap: equal, or either is the wildcard.
+fn local_ips_overlap(a: Ipv4Addr, b: Ipv4Addr) -> bool {
+    a == b || a == Ipv4Addr::UNSPECIFIED || b == Ipv4Addr::UNSPECIFIED
+}
+
+/// Whether `local` is already taken for socket `sock_idx`.
+///
+/// TCP and UDP ports are separate.  A socket of the same protocol on an
+/// overlapping address (equal, or either the wildcard) clashes unless the
+/// new binding sets `SO_REUSEADDR`; a listener clashes regardless.  A TCP
+/// port is also held by connections whose socket has closed (FIN in flight,
+/// TIME_WAIT), the same way.
+fn local_addr_in_use(
+    table: &SlabSocketTable,
+    sock_idx: usize,
+    local: SockAddr,
+    reuse_addr: bool,
+) -> bool {
+    let Some(sock) = table.get(sock_idx) else {
+        return false;
+    };
+    let kind = core::mem::discriminant(&sock.inner);
+    let clash = table.slots.iter().enumerate().any(|(idx, slot)| {
+        let Some(other) = slot else {
+            return false;
+        };
+        idx != sock_idx
+            && core::mem::discriminant(&other.inner) == kind
+            && other
+                .local_addr
+                .is_some_and(|a| a.port == local.port && local_ips_overlap(a.ip, local.ip))
+            && (!reuse_addr || other.state == SocketState::Listening)
+    });
+    clash
+        || (matches!(sock.inner, SocketInner::Tcp(_))
+            && tcp::tcp_port_in_use(local.ip.0, local.port.0, reuse_addr))
+}
+
+/// Pick a local port for `sock_idx` on `ip`: random within the ephemeral
+/// range and clear of every existing binding.
+fn alloc_ephemeral_port(table: &SlabSocketTable, sock_idx: usize, ip: Ipv4Addr) -> Option<Port> {
+    let seed = crate::random::random_next();
+    EPHEMERAL_PORTS.lock().alloc_random(seed, |port| {
+        local_addr_in_use(table, sock_idx, SockAddr::new(ip, port), false)
+    })
+}
+
+/// Give a UDP socket without a port an ephemeral one on the wildcard
+/// address, as its first send does implicitly; each datagram then takes
+/// the source address of the interface it leaves by.  Returns the new
+/// address and the socket's `SO_REUSEADDR` for the demux registration, or
+/// `None` if already bound.
+fn udp_autobind(
+    table: &mut SlabSocketTable,
+    sock_idx: usize,
+) -> Result<Option<(SockAddr, bool)>, i32> {
+    let Some(sock) = table.get(sock_idx) else {
+        return Err(errno_i32(ERRNO_ENOTSOCK));
+    };
+    if sock.local_addr.is_some_and(|a| a.port.0 != 0) {
+        return Ok(None);
+    }
+    let local_ip = Ipv4Addr::UNSPECIFIED;
+    let Some(port) = alloc_ephemeral_port(table, sock_idx, local_ip) else {
+        return Err(errno_i32(ERRNO_ENOMEM));
+    };
+    let Some(sock) = table.get_mut(sock_idx) else {
+        return Err(errno_i32(ERRNO_ENOTSOCK));
+    };
+    let bind_addr = SockAddr::new(local_ip, port);
+    sock.local_addr = Some(bind_addr);
+    if sock.state == SocketState::Unbound {
+        sock.state = SocketState::Bound;
+    }
+    Ok(Some((bind_addr, sock.options.reuse_addr)))
 }
 
 fn be_port(port: u16) -> [u8; 2] {
     port.to_be_bytes()
 }
 
-fn write_tcp_segment(seg: &TcpOutSegment, payload: &[u8], out: &mut [u8]) -> Option<usize> {
+/// Serialize `seg` into `out`.  With `csum_offload` only the pseudo-header
+/// sum is written and the device finishes the checksum.
+fn write_tcp_segment(
+    seg: &TcpOutSegment,
+    payload: &[u8],
+    out: &mut [u8],
+    csum_offload: bool,
+) -> Option<usize> {
     let opt_len = if seg.mss != 0 { 4usize } else { 0usize };
     let data_offset_words = ((TCP_HEADER_LEN + opt_len) / 4) as u8;
     let tcp_len = TCP_HEADER_LEN + opt_len + payload.len();
@@ -820,7 +1058,11 @@ fn write_tcp_segment(seg: &TcpOutSegment, payload: &[u8], out: &mut [u8]) -> Opt
 
     out[hdr_len..hdr_len + payload.len()].copy_from_slice(payload);
 
-    let checksum = tcp::tcp_checksum(seg.tuple.local_ip, seg.tuple.remote_ip, &out[..tcp_len]);
+    let checksum = if csum_offload {
+        tcp::tcp_pseudo_header_sum(seg.tuple.local_ip, seg.tuple.remote_ip, tcp_len as u16)
+    } else {
+        tcp::tcp_checksum(seg.tuple.local_ip, seg.tuple.remote_ip, &out[..tcp_len])
+    };
     out[16..18].copy_from_slice(&checksum.to_be_bytes());
     Some(tcp_len)
 }
@@ -856,21 +1098,46 @@ pub(crate) fn socket_send_tcp_segment(seg: &TcpOutSegment, payload: &[u8]) -> i3
     frame[ip + 10..ip + 12].copy_from_slice(&ip_csum.to_be_bytes());
 
     let tcp_start = ip + net::IPV4_HEADER_LEN;
-    let tcp_len = match write_tcp_segment(seg, payload, &mut frame[tcp_start..]) {
+    let remote = Ipv4Addr(seg.tuple.remote_ip);
+    let local = loopback::is_local_destination(remote);
+    let csum_offload = !local && virtio_net::virtio_net_checksum_offload();
+    let tcp_len = match write_tcp_segment(seg, payload, &mut frame[tcp_start..], csum_offload) {
         Some(n) => n,
         None => return errno_i32(ERRNO_EINVAL),
     };
 
     let total = net::ETH_HEADER_LEN + net::IPV4_HEADER_LEN + tcp_len;
+    // Treated like a lost segment: retransmission gives up eventually.
+    if !net::filter::allows(
+        NET_FILTER_OUT,
+        net::IPPROTO_TCP,
+        remote,
+        &frame[tcp_start..],
+    ) {
+        return 0;
+    }
+    if local {
+        // Delivered by the next loopback flush; a full queue is a lost
+        // segment like any other.
+        if let Some(pkt) = PacketBuf::from_raw_copy(&frame[..total]) {
+            let _ = net::netdev::DEVICE_REGISTRY.tx_by_index(loopback::LOOPBACK_DEV, pkt);
+        }
+        return 0;
+    }
     if !virtio_net::virtio_net_is_ready() {
         return 0;
     }
 
-    if virtio_net::virtio_net_transmit(&frame[..total]) {
-        0
+    let _ = if csum_offload {
+        virtio_net::virtio_net_transmit_partial(
+            &frame[..total],
+            tcp_start as u16,
+            packetbuf::TCP_CSUM_OFFSET,
+        )
     } else {
-        0
-    }
+        virtio_net::virtio_net_transmit(&frame[..total])
+    };
+    0
 }
 
 fn wq_slot(hint: u8) -> usize {
@@ -900,6 +1167,12 @@ fn socket_is_udp(sock: &Socket) -> bool {
     matches!(sock.inner, SocketInner::Udp(_))
 }
 
+/// UDP and raw sockets: message boundaries kept, received through the
+/// socket's own datagram queue.
+fn socket_is_datagram(sock: &Socket) -> bool {
+    matches!(sock.inner, SocketInner::Udp(_) | SocketInner::Raw(_))
+}
+
 fn socket_notify_tcp_idx_waiters(tcp_idx: usize) {
     let table = NEW_SOCKET_TABLE.lock();
     for slot in table.slots.iter().flatten() {
@@ -944,8 +1217,23 @@ fn socket_notify_accept_waiters() {
     }
 }
 
+/// Reset a connection a listener created that no socket has accepted.
+fn socket_reset_unaccepted(tcp_idx: usize) {
+    tcp_socket::TCP_DEMUX
+        .lock()
+        .unregister_established(tcp_idx as u32);
+    if let Ok(Some(seg)) = tcp::tcp_abort(tcp_idx) {
+        let _ = socket_send_tcp_segment(&seg, &[]);
+    }
+}
+
 pub fn socket_notify_tcp_activity(result: &tcp::TcpInputResult) {
     if let Some(tcp_idx) = result.conn_idx {
+        // An ACK may have opened the peer's or the congestion window on
+        // data still queued behind it.
+        if tcp::tcp_has_pending_data(tcp_idx) {
+            let _ = socket_flush_tcp(tcp_idx);
+        }
         socket_notify_tcp_idx_waiters(tcp_idx);
 
         // Phase 5B: When a connection transitions to Established, register it
@@ -968,7 +1256,7 @@ pub fn socket_notify_tcp_activity(result: &tcp::TcpInputResult) {
                     .lookup_listener(Ipv4Addr(conn.tuple.local_ip), Port(conn.tuple.local_port));
                 if let Some(listener_idx) = listener_sock_idx {
                     let mut table = NEW_SOCKET_TABLE.lock();
-                    if let Some(listener_sock) = table.get_mut(listener_idx as usize)
+                    let queued = if let Some(listener_sock) = table.get_mut(listener_idx as usize)
                         && listener_sock.state == SocketState::Listening
                         && let SocketInner::Tcp(ref mut tcp_inner) = listener_sock.inner
                         && let Some(ref mut listen_state) = tcp_inner.listen
@@ -979,7 +1267,17 @@ pub fn socket_notify_tcp_activity(result: &tcp::TcpInputResult) {
                             irs: conn.irs,
                             peer_mss: conn.peer_mss,
                         };
-                        listen_state.push_accepted(accepted);
+                        listen_state.push_accepted(accepted)
+                    } else {
+                        true
+                    };
+                    drop(table);
+                    // SYN admission keeps the queue within the backlog, but
+                    // a smaller backlog from a second `listen` can still
+                    // overflow it.  Reset rather than leave a connection
+                    // that nothing will accept.
+                    if !queued {
+                        socket_reset_unaccepted(tcp_idx);
                     }
                 }
             }
@@ -1022,7 +1320,7 @@ pub fn socket_deliver_udp(sock_idx: u32, src_ip: [u8; 4], src_port: u16, payload
             return;
         }
         let src = SockAddr::new(Ipv4Addr(src_ip), Port(src_port));
-        if sock.recv_queue.push((packet, src)) {
+        if sock.enqueue_datagram(packet, src) {
             wake_hint = Some(sock.recv_wq_idx);
         }
     }
@@ -1075,7 +1373,47 @@ pub fn socket_deliver_udp_from_dispatch(
     }
 }
 
-pub fn socket_create(domain: u16, sock_type: u16, _protocol: u16) -> i32 {
+/// Copy an inbound IPv4 datagram of protocol `proto`, header included, to
+/// every raw socket that wants it: same protocol, bound to `dst` or the
+/// wildcard, and if connected, connected to `src`.
+pub fn socket_deliver_raw(proto: u8, src: [u8; 4], dst: [u8; 4], datagram: &[u8]) {
+    let mut wake = [0u8; MAX_SOCKETS];
+    let mut woken = 0;
+    {
+        let mut table = NEW_SOCKET_TABLE.lock();
+        for sock in table.slots.iter_mut().flatten() {
+            let SocketInner::Raw(ref raw) = sock.inner else {
+                continue;
+            };
+            if raw.protocol != proto || raw.protocol as u16 == IPPROTO_RAW {
+                continue;
+            }
+            if sock.is_read_shutdown()
+                || sock
+                    .local_addr
+                    .is_some_and(|a| !a.ip.is_unspecified() && a.ip.0 != dst)
+                || sock.remote_addr.is_some_and(|a| a.ip.0 != src)
+            {
+                continue;
+            }
+            let Some(packet) = PacketBuf::from_raw_copy(datagram) else {
+                break;
+            };
+            if sock.enqueue_datagram(packet, SockAddr::new(Ipv4Addr(src), Port(0)))
+                && woken < wake.len()
+            {
+                wake[woken] = sock.recv_wq_idx;
+                woken += 1;
+            }
+        }
+    }
+
+    for &hint in &wake[..woken] {
+        socket_wake_recv_hint(hint);
+    }
+}
+
+pub fn socket_create(domain: u16, sock_type: u16, protocol: u16) -> i32 {
     if domain != AF_INET {
         return errno_i32(ERRNO_EAFNOSUPPORT);
     }
@@ -1086,6 +1424,9 @@ pub fn socket_create(domain: u16, sock_type: u16, _protocol: u16) -> i32 {
             conn_id: None,
             listen: None,
         }),
+        SOCK_RAW if (1..=255).contains(&protocol) => {
+            SocketInner::Raw(RawSocketInner::new(protocol as u8))
+        }
         _ => return errno_i32(ERRNO_EPROTONOSUPPORT),
     };
 
@@ -1094,12 +1435,58 @@ pub fn socket_create(domain: u16, sock_type: u16, _protocol: u16) -> i32 {
         return errno_i32(ERRNO_ENOMEM);
     };
     if let Some(sock) = table.get_mut(idx) {
-        sock.recv_queue.clear();
+        sock.clear_recv_queue();
         sock.set_nonblocking(true);
     }
     idx as i32
 }
 
+fn socket_is_raw(sock_idx: u32) -> bool {
+    NEW_SOCKET_TABLE
+        .lock()
+        .get(sock_idx as usize)
+        .is_some_and(|sock| matches!(sock.inner, SocketInner::Raw(_)))
+}
+
+/// Send the `len` bytes at `data` as one datagram from raw socket
+/// `sock_idx` to `dst`, or to its connected address when `dst` is `None`.
+fn socket_raw_send(sock_idx: u32, data: *const u8, len: usize, dst: Option<Ipv4Addr>) -> i64 {
+    let (local_ip, dst, protocol, hdrincl, ttl) = {
+        let table = NEW_SOCKET_TABLE.lock();
+        let Some(sock) = table.get(sock_idx as usize) else {
+            return errno_i32(ERRNO_ENOTSOCK) as i64;
+        };
+        let SocketInner::Raw(ref raw) = sock.inner else {
+            return errno_i32(ERRNO_EPROTONOSUPPORT) as i64;
+        };
+        if sock.is_write_shutdown() {
+            return errno_i32(ERRNO_EPIPE) as i64;
+        }
+        let Some(dst) = dst.or(sock.remote_addr.map(|a| a.ip)) else {
+            return errno_i32(ERRNO_EDESTADDRREQ) as i64;
+        };
+        let ttl = match udp_check_dst(&sock.options, dst) {
+            Ok(ttl) => ttl,
+            Err(err) => return err,
+        };
+        let local_ip = sock.local_addr.map_or(Ipv4Addr::UNSPECIFIED, |a| a.ip);
+        (local_ip, dst, raw.protocol, raw.hdrincl, ttl)
+    };
+
+    let payload = if len == 0 {
+        &[][..]
+    } else {
+        unsafe { core::slice::from_raw_parts(data, len) }
+    };
+    match net::raw::raw_sendto(local_ip, dst, protocol, hdrincl, payload, ttl) {
+        Ok(n) => {
+            socket_account(sock_idx, 0, n);
+            n as i64
+        }
+        Err(err) => map_net_err(err) as i64,
+    }
+}
+
 pub fn socket_sendto(
     sock_idx: u32,
     data: *const u8,
@@ -1110,6 +1497,9 @@ pub fn socket_sendto(
     if data.is_null() && len != 0 {
         return errno_i32(ERRNO_EFAULT) as i64;
     }
+    if socket_is_raw(sock_idx) {
+        return socket_raw_send(sock_idx, data, len, Some(Ipv4Addr(dst_ip)));
+    }
     if dst_port == 0 {
         return errno_i32(ERRNO_EDESTADDRREQ) as i64;
     }
@@ -1117,10 +1507,10 @@ pub fn socket_sendto(
         return errno_i32(ERRNO_EINVAL) as i64;
     }
 
-    let mut auto_bind: Option<(SockAddr, bool)> = None;
-    let local = {
+    let auto_bind: Option<(SockAddr, bool)>;
+    let (local, ttl) = {
         let mut table = NEW_SOCKET_TABLE.lock();
-        let Some(sock) = table.get_mut(sock_idx as usize) else {
+        let Some(sock) = table.get(sock_idx as usize) else {
             return errno_i32(ERRNO_ENOTSOCK) as i64;
         };
         if !socket_is_udp(sock) {
@@ -1129,24 +1519,21 @@ pub fn socket_sendto(
         if sock.is_write_shutdown() {
             return errno_i32(ERRNO_EPIPE) as i64;
         }
+        let ttl = match udp_check_dst(&sock.options, Ipv4Addr(dst_ip)) {
+            Ok(ttl) => ttl,
+            Err(err) => return err,
+        };
 
-        if sock.local_addr.is_none() || sock.local_addr.map(|a| a.port.0 == 0).unwrap_or(true) {
-            let Some(port) = alloc_ephemeral_port() else {
-                return errno_i32(ERRNO_ENOMEM) as i64;
-            };
-            let local_ip = crate::net::netstack::NET_STACK
-                .first_ipv4()
-                .map(|ip| ip.0)
-                .unwrap_or([0; 4]);
-            let bind_addr = SockAddr::new(Ipv4Addr(local_ip), port);
-            sock.local_addr = Some(bind_addr);
-            if sock.state == SocketState::Unbound {
-                sock.state = SocketState::Bound;
-            }
-            auto_bind = Some((bind_addr, sock.options.reuse_addr));
-            bind_addr
-        } else {
-            sock.local_addr.unwrap()
+        auto_bind = match udp_autobind(&mut table, sock_idx as usize) {
+            Ok(bound) => bound,
+            Err(err) => return err as i64,
+        };
+        match table
+            .get(sock_idx as usize)
+            .and_then(|sock| sock.local_addr)
+        {
+            Some(local) => (local, ttl),
+            None => return errno_i32(ERRNO_ENOTSOCK) as i64,
         }
     };
 
@@ -1173,8 +1560,11 @@ pub fn socket_sendto(
         unsafe { core::slice::from_raw_parts(data, len) }
     };
 
-    match crate::net::udp::udp_sendto(local.ip.0, dst_ip, local.port.0, dst_port, payload) {
-        Ok(n) => n as i64,
+    match crate::net::udp::udp_sendto(local.ip.0, dst_ip, local.port.0, dst_port, payload, ttl) {
+        Ok(n) => {
+            socket_account(sock_idx, 0, n);
+            n as i64
+        }
         Err(err) => map_net_err(err) as i64,
     }
 }
@@ -1201,7 +1591,7 @@ pub fn socket_recvfrom(
         let Some(sock) = table.get(sock_idx as usize) else {
             return errno_i32(ERRNO_ENOTSOCK) as i64;
         };
-        if !socket_is_udp(sock) {
+        if !socket_is_datagram(sock) {
             return errno_i32(ERRNO_EPROTONOSUPPORT) as i64;
         }
         if sock.is_read_shutdown() {
@@ -1220,7 +1610,7 @@ pub fn socket_recvfrom(
             let Some(sock) = table.get_mut(sock_idx as usize) else {
                 return errno_i32(ERRNO_ENOTSOCK) as i64;
             };
-            sock.recv_queue.pop()
+            sock.dequeue_datagram()
         };
 
         if let Some((pkt, src)) = packet {
@@ -1238,6 +1628,7 @@ pub fn socket_recvfrom(
                     *src_port = src.port.0;
                 }
             }
+            socket_account(sock_idx, copy_len, 0);
             return copy_len as i64;
         }
 
@@ -1245,6 +1636,7 @@ pub fn socket_recvfrom(
             return errno_i32(ERRNO_EAGAIN) as i64;
         }
 
+        loopback_flush();
         let wait_ok = if timeout_ms > 0 {
             RECV_WQS[wq_slot(recv_hint)].wait_event_timeout(
                 || {
@@ -1272,11 +1664,15 @@ pub fn socket_recvfrom(
     }
 }
 
+/// Bind a socket to `addr:port`.  Port 0 picks a free ephemeral port; an
+/// explicit port already bound by another socket fails with `EADDRINUSE`
+/// (see [`local_addr_in_use`] for what counts).
 pub fn socket_bind(sock_idx: u32, addr: [u8; 4], port: u16) -> i32 {
     let mut udp_bind_args: Option<(SockAddr, bool)> = None;
     {
         let mut table = NEW_SOCKET_TABLE.lock();
-        let Some(sock) = table.get_mut(sock_idx as usize) else {
+        let idx = sock_idx as usize;
+        let Some(sock) = table.get(idx) else {
             return errno_i32(ERRNO_ENOTSOCK);
         };
 
@@ -1284,12 +1680,32 @@ pub fn socket_bind(sock_idx: u32, addr: [u8; 4], port: u16) -> i32 {
             return errno_i32(ERRNO_EINVAL);
         }
 
-        let local = SockAddr::new(Ipv4Addr(addr), Port(port));
+        let reuse_addr = sock.options.reuse_addr;
+        let ip = Ipv4Addr(addr);
+        // Raw sockets have no ports; binding only picks the address.
+        let local = if matches!(sock.inner, SocketInner::Raw(_)) {
+            SockAddr::new(ip, Port(0))
+        } else if port == 0 {
+            match alloc_ephemeral_port(&table, idx, ip) {
+                Some(port) => SockAddr::new(ip, port),
+                None => return errno_i32(ERRNO_EADDRINUSE),
+            }
+        } else {
+            let local = SockAddr::new(ip, Port(port));
+            if local_addr_in_use(&table, idx, local, reuse_addr) {
+                return errno_i32(ERRNO_EADDRINUSE);
+            }
+            local
+        };
+
+        let Some(sock) = table.get_mut(idx) else {
+            return errno_i32(ERRNO_ENOTSOCK);
+        };
         sock.local_addr = Some(local);
         sock.state = SocketState::Bound;
 
         if socket_is_udp(sock) {
-            udp_bind_args = Some((local, sock.options.reuse_addr));
+            udp_bind_args = Some((local, reuse_addr));
         }
     }
 
@@ -1305,6 +1721,9 @@ pub fn socket_bind(sock_idx: u32, addr: [u8; 4], port: u16) -> i32 {
             sock.local_addr = None;
             sock.state = SocketState::Unbound;
         }
+        if port == 0 {
+            EPHEMERAL_PORTS.lock().release(local.port);
+        }
         return map_net_err(err);
     }
 
@@ -1324,12 +1743,34 @@ pub fn socket_listen(sock_idx: u32, backlog: u32) -> i32 {
     if !matches!(sock.inner, SocketInner::Tcp(_)) {
         return errno_i32(ERRNO_EPROTONOSUPPORT);
     }
+    if sock.state == SocketState::Listening {
+        // Listening again only changes the backlog.
+        let tcp_idx = socket_tcp_conn_id(sock);
+        if let SocketInner::Tcp(tcp_inner) = &mut sock.inner
+            && let Some(listen) = &mut tcp_inner.listen
+        {
+            listen.set_backlog(backlog as usize);
+        }
+        if let Some(tcp_idx) = tcp_idx {
+            tcp::tcp_set_backlog(tcp_idx, backlog as usize);
+        }
+        return 0;
+    }
     if sock.state != SocketState::Bound {
         return errno_i32(ERRNO_EINVAL);
     }
 
-    match tcp::tcp_listen(local.ip.0, local.port.0) {
+    match tcp::tcp_listen(local.ip.0, local.port.0, sock.options.reuse_addr) {
         Ok(tcp_idx) => {
+            // Accepted connections inherit the listener's buffer limits
+            // and Nagle setting.
+            tcp::tcp_set_buffer_limits(
+                tcp_idx,
+                sock.options.recv_buf_size,
+                sock.options.send_buf_size,
+            );
+            tcp::tcp_set_nagle(tcp_idx, !sock.options.tcp_nodelay);
+            tcp::tcp_set_backlog(tcp_idx, backlog as usize);
             if let SocketInner::Tcp(tcp_inner) = &mut sock.inner {
                 tcp_inner.conn_id = Some(tcp_idx as u32);
                 // Phase 5C: Create TcpListenState with two-queue model.
@@ -1381,6 +1822,9 @@ pub fn socket_accept(sock_idx: u32, peer_addr: *mut [u8; 4], peer_port: *mut u16
                 send_timeout: listen_sock.options.send_timeout,
                 keepalive: listen_sock.options.keepalive,
                 tcp_nodelay: listen_sock.options.tcp_nodelay,
+                linger: listen_sock.options.linger,
+                broadcast: listen_sock.options.broadcast,
+                multicast_ttl: listen_sock.options.multicast_ttl,
             };
             let is_nonblocking = listen_sock.is_nonblocking();
 
@@ -1442,6 +1886,7 @@ pub fn socket_accept(sock_idx: u32, peer_addr: *mut [u8; 4], peer_port: *mut u16
         }
 
         // Phase 5C: Wait for accept queue to become non-empty.
+        loopback_flush();
         let wait_ok = if timeout_ms > 0 {
             ACCEPT_WQS[wq_slot(accept_hint)].wait_event_timeout(
                 || {
@@ -1487,28 +1932,57 @@ pub fn socket_accept(sock_idx: u32, peer_addr: *mut [u8; 4], peer_port: *mut u16
 
 pub fn socket_connect(sock_idx: u32, addr: [u8; 4], port: u16) -> i32 {
     let mut table = NEW_SOCKET_TABLE.lock();
-    let Some(sock) = table.get_mut(sock_idx as usize) else {
+    let idx = sock_idx as usize;
+    let Some(sock) = table.get(idx) else {
         return errno_i32(ERRNO_ENOTSOCK);
     };
 
+    // A TCP socket connects from its bound port (`nc -p`), or else from a
+    // fresh ephemeral one that is handed back if the connect fails.
+    let mut tcp_local = None;
+    if matches!(sock.inner, SocketInner::Tcp(_))
+        && !matches!(sock.state, SocketState::Connected | SocketState::Connecting)
+    {
+        let local_ip = sock
+            .local_addr
+            .map(|a| a.ip)
+            .filter(|ip| *ip != Ipv4Addr::UNSPECIFIED)
+            .unwrap_or_else(|| net::netstack::NET_STACK.source_addr_for(Ipv4Addr(addr)));
+        tcp_local = Some(match sock.local_addr {
+            Some(bound) if bound.port.0 != 0 => (SockAddr::new(local_ip, bound.port), false),
+            _ => match alloc_ephemeral_port(&table, idx, local_ip) {
+                Some(port) => (SockAddr::new(local_ip, port), true),
+                None => return errno_i32(ERRNO_EADDRINUSE),
+            },
+        });
+    }
+
+    let Some(sock) = table.get_mut(idx) else {
+        return errno_i32(ERRNO_ENOTSOCK);
+    };
     match &mut sock.inner {
         SocketInner::Tcp(tcp_inner) => {
-            if matches!(sock.state, SocketState::Connected | SocketState::Connecting) {
+            let Some((local, ephemeral)) = tcp_local else {
                 return errno_i32(ERRNO_EISCONN);
-            }
-
-            let local_ip = sock.local_addr.map(|a| a.ip.0).unwrap_or_else(|| {
-                crate::net::netstack::NET_STACK
-                    .first_ipv4()
-                    .map(|ip| ip.0)
-                    .unwrap_or([0; 4])
-            });
+            };
+            let release_port = || {
+                if ephemeral {
+                    EPHEMERAL_PORTS.lock().release(local.port);
+                }
+            };
 
-            match tcp::tcp_connect(local_ip, addr, port) {
-                Ok((tcp_idx, syn)) => {
+            match tcp::tcp_connect(local.ip.0, local.port.0, addr, port) {
+                Ok((tcp_idx, mut syn)) => {
+                    syn.window_size = tcp::tcp_set_buffer_limits(
+                        tcp_idx,
+                        sock.options.recv_buf_size,
+                        sock.options.send_buf_size,
+                    );
+                    tcp::tcp_set_nagle(tcp_idx, !sock.options.tcp_nodelay);
                     let send_rc = socket_send_tcp_segment(&syn, &[]);
                     if send_rc != 0 {
                         let _ = tcp::tcp_abort(tcp_idx);
+                        release_port();
                         return send_rc;
                     }
 
@@ -1524,7 +1998,10 @@ pub fn socket_connect(sock_idx: u32, addr: [u8; 4], port: u16) -> i32 {
                     tcp::tcp_set_socket_idx(tcp_idx, Some(sock_idx as usize));
                     0
                 }
-                Err(e) => map_tcp_err(e),
+                Err(e) => {
+                    release_port();
+                    map_tcp_err(e)
+                }
             }
         }
         SocketInner::Udp(_) => {
@@ -1532,7 +2009,11 @@ pub fn socket_connect(sock_idx: u32, addr: [u8; 4], port: u16) -> i32 {
             sock.state = SocketState::Connected;
             0
         }
-        SocketInner::Raw(_) => errno_i32(ERRNO_EPROTONOSUPPORT),
+        SocketInner::Raw(_) => {
+            sock.remote_addr = Some(SockAddr::new(Ipv4Addr(addr), Port(0)));
+            sock.state = SocketState::Connected;
+            0
+        }
     }
 }
 
@@ -1541,7 +2022,7 @@ pub fn socket_send(sock_idx: u32, data: *const u8, len: usize) -> i64 {
         return errno_i32(ERRNO_EFAULT) as i64;
     }
 
-    let is_udp = {
+    let (is_udp, is_raw) = {
         let table = NEW_SOCKET_TABLE.lock();
         let Some(sock) = table.get(sock_idx as usize) else {
             return errno_i32(ERRNO_ENOTSOCK) as i64;
@@ -1549,37 +2030,32 @@ pub fn socket_send(sock_idx: u32, data: *const u8, len: usize) -> i64 {
         if sock.is_write_shutdown() {
             return errno_i32(ERRNO_EPIPE) as i64;
         }
-        socket_is_udp(sock)
+        (
+            socket_is_udp(sock),
+            matches!(sock.inner, SocketInner::Raw(_)),
+        )
     };
 
+    if is_raw {
+        return socket_raw_send(sock_idx, data, len, None);
+    }
+
     if is_udp {
         if len > UDP_DGRAM_MAX_PAYLOAD {
             return errno_i32(ERRNO_EINVAL) as i64;
         }
 
-        let mut auto_bind: Option<(SockAddr, bool)> = None;
-        let (local, remote, state) = {
+        let auto_bind: Option<(SockAddr, bool)>;
+        let (local, remote, state, ttl) = {
             let mut table = NEW_SOCKET_TABLE.lock();
-            let Some(sock) = table.get_mut(sock_idx as usize) else {
+            auto_bind = match udp_autobind(&mut table, sock_idx as usize) {
+                Ok(bound) => bound,
+                Err(err) => return err as i64,
+            };
+            let Some(sock) = table.get(sock_idx as usize) else {
                 return errno_i32(ERRNO_ENOTSOCK) as i64;
             };
 
-            if sock.local_addr.is_none() || sock.local_addr.map(|a| a.port.0 == 0).unwrap_or(true) {
-                let Some(port) = alloc_ephemeral_port() else {
-                    return errno_i32(ERRNO_ENOMEM) as i64;
-                };
-                let local_ip = crate::net::netstack::NET_STACK
-                    .first_ipv4()
-                    .map(|ip| ip.0)
-                    .unwrap_or([0; 4]);
-                let local = SockAddr::new(Ipv4Addr(local_ip), port);
-                sock.local_addr = Some(local);
-                if sock.state == SocketState::Unbound {
-                    sock.state = SocketState::Bound;
-                }
-                auto_bind = Some((local, sock.options.reuse_addr));
-            }
-
             let local = match sock.local_addr {
                 Some(v) => v,
                 None => return errno_i32(ERRNO_ENOTCONN) as i64,
@@ -1588,7 +2064,11 @@ pub fn socket_send(sock_idx: u32, data: *const u8, len: usize) -> i64 {
                 Some(v) => v,
                 None => return errno_i32(ERRNO_ENOTCONN) as i64,
             };
-            (local, remote, sock.state)
+            let ttl = match udp_check_dst(&sock.options, remote.ip) {
+                Ok(ttl) => ttl,
+                Err(err) => return err,
+            };
+            (local, remote, sock.state, ttl)
         };
 
         if let Some((bind_addr, reuse_addr)) = auto_bind
@@ -1624,8 +2104,12 @@ pub fn socket_send(sock_idx: u32, data: *const u8, len: usize) -> i64 {
             local.port.0,
             remote.port.0,
             payload,
+            ttl,
         ) {
-            Ok(n) => n as i64,
+            Ok(n) => {
+                socket_account(sock_idx, 0, n);
+                n as i64
+            }
             Err(err) => map_net_err(err) as i64,
         };
     }
@@ -1668,6 +2152,7 @@ pub fn socket_send(sock_idx: u32, data: *const u8, len: usize) -> i64 {
             if nonblocking {
                 return errno_i32(ERRNO_EAGAIN) as i64;
             }
+            loopback_flush();
             let wait_ok = if timeout_ms > 0 {
                 SEND_WQS[wq_slot(send_hint)]
                     .wait_event_timeout(|| tcp::tcp_send_buffer_space(tcp_idx) > 0, timeout_ms)
@@ -1700,6 +2185,7 @@ pub fn socket_send(sock_idx: u32, data: *const u8, len: usize) -> i64 {
             if nonblocking {
                 return errno_i32(ERRNO_EAGAIN) as i64;
             }
+            loopback_flush();
             let wait_ok = if timeout_ms > 0 {
                 SEND_WQS[wq_slot(send_hint)]
                     .wait_event_timeout(|| tcp::tcp_send_buffer_space(tcp_idx) > 0, timeout_ms)
@@ -1713,6 +2199,7 @@ pub fn socket_send(sock_idx: u32, data: *const u8, len: usize) -> i64 {
         }
         total_wrote += wrote;
     }
+    socket_account(sock_idx, 0, total_wrote);
 
     let mut tx_payload = [0u8; TCP_TX_MAX];
     let now_ms = slopos_lib::clock::uptime_ms();
@@ -1745,7 +2232,7 @@ pub fn socket_recv(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
         let Some(sock) = table.get(sock_idx as usize) else {
             return errno_i32(ERRNO_ENOTSOCK) as i64;
         };
-        (socket_is_udp(sock), sock.is_read_shutdown())
+        (socket_is_datagram(sock), sock.is_read_shutdown())
     };
 
     if is_shut_rd {
@@ -1780,7 +2267,7 @@ pub fn socket_recv(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
                 };
 
                 let mut found = None;
-                while let Some((pkt, src)) = sock.recv_queue.pop() {
+                while let Some((pkt, src)) = sock.dequeue_datagram() {
                     if let Some(peer) = peer_filter
                         && src != peer
                     {
@@ -1796,6 +2283,7 @@ pub fn socket_recv(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
                 let payload = pkt.payload();
                 let copy_len = cmp::min(out.len(), payload.len());
                 out[..copy_len].copy_from_slice(&payload[..copy_len]);
+                socket_account(sock_idx, copy_len, 0);
                 return copy_len as i64;
             }
 
@@ -1803,6 +2291,7 @@ pub fn socket_recv(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
                 return errno_i32(ERRNO_EAGAIN) as i64;
             }
 
+            loopback_flush();
             let wait_ok = if timeout_ms > 0 {
                 RECV_WQS[wq_slot(recv_hint)].wait_event_timeout(
                     || {
@@ -1857,6 +2346,7 @@ pub fn socket_recv(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
         match tcp::tcp_recv(tcp_idx, out) {
             Ok(n) => {
                 if n > 0 {
+                    socket_account(sock_idx, n, 0);
                     return n as i64;
                 }
 
@@ -1881,6 +2371,7 @@ pub fn socket_recv(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
                     return errno_i32(ERRNO_EAGAIN) as i64;
                 }
 
+                loopback_flush();
                 let wait_ok = if timeout_ms > 0 {
                     RECV_WQS[wq_slot(recv_hint)].wait_event_timeout(
                         || {
@@ -1923,14 +2414,59 @@ pub fn socket_recv(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
     }
 }
 
+/// `SO_LINGER` with a timeout: send our FIN behind any queued data and block
+/// until the peer acknowledges it or `secs` run out.  The socket is still in
+/// the table, so ACK processing wakes us through its send queue.
+fn socket_linger(tcp_idx: usize, send_hint: u8, secs: u32) {
+    match tcp::tcp_close(tcp_idx) {
+        Ok(Some(seg)) => {
+            let _ = socket_send_tcp_segment(&seg, &[]);
+        }
+        Ok(None) => {}
+        Err(_) => return,
+    }
+    let _ = socket_flush_tcp(tcp_idx);
+    loopback_flush();
+    SEND_WQS[wq_slot(send_hint)]
+        .wait_event_timeout(|| tcp::tcp_fin_acked(tcp_idx), secs as u64 * 1000);
+}
+
 pub fn socket_close(sock_idx: u32) -> i32 {
-    let (tcp_idx, udp_unbind, recv_hint, send_hint, accept_hint, was_listener) = {
+    let linger = {
+        let table = NEW_SOCKET_TABLE.lock();
+        let Some(sock) = table.get(sock_idx as usize) else {
+            return errno_i32(ERRNO_ENOTSOCK);
+        };
+        // A listener has nothing to linger over, and its connection must
+        // outlive the reset of its unaccepted children below.
+        socket_tcp_conn_id(sock)
+            .filter(|_| sock.state != SocketState::Listening)
+            .zip(sock.options.linger)
+            .map(|(tcp_idx, secs)| (tcp_idx, secs, sock.is_nonblocking(), sock.send_wq_idx))
+    };
+    // Whether the linger handling below already closed the connection.
+    let mut tcp_closed = false;
+    if let Some((tcp_idx, secs, nonblocking, send_hint)) = linger {
+        if secs == 0 {
+            // Zero linger: drop unsent data and reset instead of a FIN.
+            if let Ok(Some(seg)) = tcp::tcp_abort(tcp_idx) {
+                let _ = socket_send_tcp_segment(&seg, &[]);
+            }
+            tcp_closed = true;
+        } else if !nonblocking {
+            socket_linger(tcp_idx, send_hint, secs);
+            tcp_closed = true;
+        }
+    }
+
+    let (tcp_idx, local_port, udp_unbind, recv_hint, send_hint, accept_hint, was_listener) = {
         let mut table = NEW_SOCKET_TABLE.lock();
         let Some(sock) = table.get_mut(sock_idx as usize) else {
             return errno_i32(ERRNO_ENOTSOCK);
         };
 
         let tcp_idx = socket_tcp_conn_id(sock);
+        let local_port = sock.local_addr.map(|a| a.port);
         let udp_unbind = if socket_is_udp(sock) {
             sock.local_addr
         } else {
@@ -1940,7 +2476,7 @@ pub fn socket_close(sock_idx: u32) -> i32 {
         let recv_hint = sock.recv_wq_idx;
         let send_hint = sock.send_wq_idx;
         let accept_hint = sock.accept_wq_idx;
-        sock.recv_queue.clear();
+        sock.clear_recv_queue();
 
         // Phase 5C: Clean up TcpListenState (cancels SYN-ACK retransmit timers).
         if let SocketInner::Tcp(ref mut tcp_inner) = sock.inner {
@@ -1953,6 +2489,7 @@ pub fn socket_close(sock_idx: u32) -> i32 {
         table.free(sock_idx as usize);
         (
             tcp_idx,
+            local_port,
             udp_unbind,
             recv_hint,
             send_hint,
@@ -1964,6 +2501,12 @@ pub fn socket_close(sock_idx: u32) -> i32 {
     // Phase 5B: Unregister from TCP demux table.
     if was_listener {
         tcp_socket::TCP_DEMUX.lock().unregister_listener(sock_idx);
+        // Connections no one accepted go with the listener.
+        if let Some(tcp_idx) = tcp_idx {
+            while let Some(child) = tcp::tcp_unaccepted_child(tcp_idx) {
+                socket_reset_unaccepted(child);
+            }
+        }
     }
     if let Some(tcp_idx) = tcp_idx {
         tcp_socket::TCP_DEMUX
@@ -1975,21 +2518,31 @@ pub fn socket_close(sock_idx: u32) -> i32 {
 
     if let Some(local) = udp_unbind {
         crate::net::udp::udp_unbind(sock_idx, local.ip, local.port);
-        EPHEMERAL_PORTS.lock().release(local.port);
+    }
+    crate::net::igmp::socket_closed(sock_idx);
+    // A TCP port stays held by its connection until TIME_WAIT ends; the
+    // bind checks see that through the connection table.
+    if let Some(port) = local_port {
+        EPHEMERAL_PORTS.lock().release(port);
     }
 
     socket_wake_recv_hint(recv_hint);
     socket_wake_send_hint(send_hint);
     socket_wake_accept_hint(accept_hint);
 
-    if let Some(tcp_idx) = tcp_idx {
+    if let Some(tcp_idx) = tcp_idx
+        && !tcp_closed
+    {
         match tcp::tcp_close(tcp_idx) {
-            Ok(Some(seg)) => {
-                let _ = socket_send_tcp_segment(&seg, &[]);
+            Ok(seg) => {
+                if let Some(seg) = seg {
+                    let _ = socket_send_tcp_segment(&seg, &[]);
+                }
+                // Queued data, then a FIN that was waiting behind it.
+                let _ = socket_flush_tcp(tcp_idx);
                 socket_notify_tcp_idx_waiters(tcp_idx);
                 0
             }
-            Ok(None) => 0,
             Err(e) => map_tcp_err(e),
         }
     } else {
@@ -2006,7 +2559,7 @@ pub fn socket_poll_readable(sock_idx: u32) -> u32 {
         sync_socket_state(sock);
         (
             sock.state,
-            socket_is_udp(sock),
+            socket_is_datagram(sock),
             socket_tcp_conn_id(sock),
             !sock.recv_queue.is_empty(),
         )
@@ -2073,7 +2626,11 @@ pub fn socket_poll_writable(sock_idx: u32) -> u32 {
             return 0;
         };
         sync_socket_state(sock);
-        (socket_is_udp(sock), socket_tcp_conn_id(sock), sock.state)
+        (
+            socket_is_datagram(sock),
+            socket_tcp_conn_id(sock),
+            sock.state,
+        )
     };
 
     if is_udp {
@@ -2149,7 +2706,8 @@ pub fn socket_reset_all() {
         table.init_if_needed();
         let cap = table.capacity();
         for idx in 0..cap {
-            if let Some(sock) = table.get(idx) {
+            if let Some(sock) = table.get_mut(idx) {
+                sock.clear_recv_queue();
                 socket_wake_recv_hint(sock.recv_wq_idx);
                 socket_wake_accept_hint(sock.accept_wq_idx);
                 socket_wake_send_hint(sock.send_wq_idx);
@@ -2166,7 +2724,10 @@ pub fn socket_reset_all() {
 
     *EPHEMERAL_PORTS.lock() = EphemeralPortAllocator::new();
     crate::net::udp::UDP_DEMUX.lock().clear();
+    crate::net::igmp::reset();
     tcp::tcp_reset_all();
+    crate::net::sockmem::NET_RMEM.reset();
+    crate::net::sockmem::NET_WMEM.reset();
 }
 
 #[derive(Clone, Copy)]
@@ -2198,6 +2759,99 @@ pub fn socket_snapshot(sock_idx: u32) -> Option<SocketSnapshot> {
     })
 }
 
+/// Charge completed application reads and writes to a socket's counters.
+fn socket_account(sock_idx: u32, rx: usize, tx: usize) {
+    if let Some(sock) = NEW_SOCKET_TABLE.lock().get_mut(sock_idx as usize) {
+        sock.counters.rx_bytes += rx as u64;
+        sock.counters.tx_bytes += tx as u64;
+    }
+}
+
+fn tcp_state_code(state: TcpState) -> u8 {
+    match state {
+        TcpState::Closed => NET_SOCK_CLOSED,
+        TcpState::Listen => NET_SOCK_LISTEN,
+        TcpState::SynSent => NET_SOCK_SYN_SENT,
+        TcpState::SynReceived => NET_SOCK_SYN_RECEIVED,
+        TcpState::Established => NET_SOCK_ESTABLISHED,
+        TcpState::FinWait1 => NET_SOCK_FIN_WAIT1,
+        TcpState::FinWait2 => NET_SOCK_FIN_WAIT2,
+        TcpState::CloseWait => NET_SOCK_CLOSE_WAIT,
+        TcpState::Closing => NET_SOCK_CLOSING,
+        TcpState::LastAck => NET_SOCK_LAST_ACK,
+        TcpState::TimeWait => NET_SOCK_TIME_WAIT,
+    }
+}
+
+fn socket_stat_of(idx: usize, sock: &Socket) -> NetSocketStat {
+    let local = sock
+        .local_addr
+        .unwrap_or(SockAddr::new(Ipv4Addr::UNSPECIFIED, Port(0)));
+    let remote = sock
+        .remote_addr
+        .unwrap_or(SockAddr::new(Ipv4Addr::UNSPECIFIED, Port(0)));
+    let mut stat = NetSocketStat {
+        index: idx as u32,
+        state: match sock.state {
+            SocketState::Unbound => NET_SOCK_UNBOUND,
+            SocketState::Bound => NET_SOCK_BOUND,
+            SocketState::Listening => NET_SOCK_LISTEN,
+            SocketState::Connecting => NET_SOCK_SYN_SENT,
+            SocketState::Connected => NET_SOCK_ESTABLISHED,
+            SocketState::Closed => NET_SOCK_CLOSED,
+        },
+        local_addr: local.ip.0,
+        remote_addr: remote.ip.0,
+        local_port: local.port.0,
+        remote_port: remote.port.0,
+        rx_bytes: sock.counters.rx_bytes,
+        tx_bytes: sock.counters.tx_bytes,
+        rx_dropped: sock.counters.rx_dropped,
+        ..NetSocketStat::default()
+    };
+    for (set, bit) in [
+        (sock.is_nonblocking(), NET_SOCK_FLAG_NONBLOCK),
+        (sock.is_read_shutdown(), NET_SOCK_FLAG_SHUT_RD),
+        (sock.is_write_shutdown(), NET_SOCK_FLAG_SHUT_WR),
+    ] {
+        if set {
+            stat.flags |= bit;
+        }
+    }
+    match &sock.inner {
+        SocketInner::Udp(_) => {
+            stat.proto = NET_SOCK_PROTO_UDP;
+            stat.recv_queue = sock.recv_queued_bytes as u32;
+        }
+        SocketInner::Raw(_) => {
+            stat.proto = NET_SOCK_PROTO_RAW;
+            stat.recv_queue = sock.recv_queued_bytes as u32;
+        }
+        SocketInner::Tcp(tcp_inner) => {
+            stat.proto = NET_SOCK_PROTO_TCP;
+            if let Some(listen) = &tcp_inner.listen {
+                stat.recv_queue = listen.accept_queue_len() as u32;
+                stat.backlog = listen.backlog() as u32;
+            } else if let Some(conn) = tcp_inner.conn_id.map(|id| id as usize) {
+                if let Some(state) = tcp::tcp_get_state(conn) {
+                    stat.state = tcp_state_code(state);
+                }
+                stat.recv_queue = tcp::tcp_recv_available(conn) as u32;
+                stat.send_queue = tcp::tcp_send_buffered(conn) as u32;
+            }
+        }
+    }
+    stat
+}
+
+/// Describe the first open socket at index `start` or above, for
+/// `SYSCALL_NET_STAT` and `/proc/net/sockets`.  Continue from
+/// `stat.index + 1`.
+pub fn socket_stat(start: usize) -> Option<NetSocketStat> {
+    let table = NEW_SOCKET_TABLE.lock();
+    (start..table.capacity()).find_map(|idx| table.get(idx).map(|sock| socket_stat_of(idx, sock)))
+}
+
 pub fn socket_lookup_tcp_idx(sock_idx: u32) -> Option<usize> {
     NEW_SOCKET_TABLE
         .lock()
@@ -2236,7 +2890,10 @@ pub fn socket_setsockopt(sock_idx: u32, level: i32, optname: i32, val: &[u8]) ->
                     return errno_i32(ERRNO_EINVAL);
                 };
                 sock.options.recv_buf_size = size;
-                sock.recv_queue.resize(size);
+                sock.resize_recv_queue(size);
+                if let Some(tcp_idx) = socket_tcp_conn_id(sock) {
+                    tcp::tcp_set_buffer_limits(tcp_idx, size, sock.options.send_buf_size);
+                }
                 0
             }
             SO_SNDBUF => {
@@ -2248,6 +2905,9 @@ pub fn socket_setsockopt(sock_idx: u32, level: i32, optname: i32, val: &[u8]) ->
                     return errno_i32(ERRNO_EINVAL);
                 };
                 sock.options.send_buf_size = size;
+                if let Some(tcp_idx) = socket_tcp_conn_id(sock) {
+                    tcp::tcp_set_buffer_limits(tcp_idx, sock.options.recv_buf_size, size);
+                }
                 0
             }
             SO_RCVTIMEO => {
@@ -2278,6 +2938,71 @@ pub fn socket_setsockopt(sock_idx: u32, level: i32, optname: i32, val: &[u8]) ->
                 sock.options.keepalive = v != 0;
                 0
             }
+            SO_LINGER => {
+                if val.len() < 8 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let onoff = i32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
+                let secs = i32::from_ne_bytes([val[4], val[5], val[6], val[7]]);
+                if secs < 0 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                sock.options.linger = (onoff != 0).then_some(secs as u32);
+                0
+            }
+            SO_BROADCAST => {
+                if val.len() < 4 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let v = i32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
+                sock.options.broadcast = v != 0;
+                0
+            }
+            _ => errno_i32(ERRNO_EINVAL),
+        },
+        IPPROTO_IP => match optname {
+            IP_HDRINCL => {
+                if val.len() < 4 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let SocketInner::Raw(ref mut raw) = sock.inner else {
+                    return errno_i32(ERRNO_EPROTONOSUPPORT);
+                };
+                let v = i32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
+                raw.hdrincl = v != 0 || raw.protocol as u16 == IPPROTO_RAW;
+                0
+            }
+            IP_MULTICAST_TTL => {
+                if val.len() < 4 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let v = i32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
+                let Ok(ttl) = u8::try_from(v) else {
+                    return errno_i32(ERRNO_EINVAL);
+                };
+                sock.options.multicast_ttl = ttl;
+                0
+            }
+            IP_ADD_MEMBERSHIP | IP_DROP_MEMBERSHIP => {
+                if val.len() < 8 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                if !socket_is_udp(sock) {
+                    return errno_i32(ERRNO_EPROTONOSUPPORT);
+                }
+                drop(table);
+                let group = Ipv4Addr([val[0], val[1], val[2], val[3]]);
+                let iface = Ipv4Addr([val[4], val[5], val[6], val[7]]);
+                if !iface.is_unspecified() && !net::NET_STACK.is_our_addr(iface) {
+                    return errno_i32(ERRNO_EADDRNOTAVAIL);
+                }
+                let result = if optname == IP_ADD_MEMBERSHIP {
+                    net::igmp::join(group, sock_idx)
+                } else {
+                    net::igmp::leave(group, sock_idx)
+                };
+                result.map_or_else(map_net_err, |()| 0)
+            }
             _ => errno_i32(ERRNO_EINVAL),
         },
         IPPROTO_TCP => match optname {
@@ -2287,6 +3012,9 @@ pub fn socket_setsockopt(sock_idx: u32, level: i32, optname: i32, val: &[u8]) ->
                 }
                 let v = i32::from_ne_bytes([val[0], val[1], val[2], val[3]]);
                 sock.options.tcp_nodelay = v != 0;
+                if let Some(tcp_idx) = socket_tcp_conn_id(sock) {
+                    tcp::tcp_set_nagle(tcp_idx, !sock.options.tcp_nodelay);
+                }
                 0
             }
             _ => errno_i32(ERRNO_EINVAL),
@@ -2361,6 +3089,46 @@ pub fn socket_getsockopt(sock_idx: u32, level: i32, optname: i32, out: &mut [u8]
                 out[..4].copy_from_slice(&v.to_ne_bytes());
                 4
             }
+            SO_LINGER => {
+                if out.len() < 8 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let onoff = sock.options.linger.is_some() as i32;
+                let secs = sock.options.linger.unwrap_or(0) as i32;
+                out[..4].copy_from_slice(&onoff.to_ne_bytes());
+                out[4..8].copy_from_slice(&secs.to_ne_bytes());
+                8
+            }
+            SO_BROADCAST => {
+                if out.len() < 4 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let v: i32 = if sock.options.broadcast { 1 } else { 0 };
+                out[..4].copy_from_slice(&v.to_ne_bytes());
+                4
+            }
+            _ => errno_i32(ERRNO_EINVAL),
+        },
+        IPPROTO_IP => match optname {
+            IP_HDRINCL => {
+                if out.len() < 4 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let SocketInner::Raw(ref raw) = sock.inner else {
+                    return errno_i32(ERRNO_EPROTONOSUPPORT);
+                };
+                let v = raw.hdrincl as i32;
+                out[..4].copy_from_slice(&v.to_ne_bytes());
+                4
+            }
+            IP_MULTICAST_TTL => {
+                if out.len() < 4 {
+                    return errno_i32(ERRNO_EINVAL);
+                }
+                let v = sock.options.multicast_ttl as i32;
+                out[..4].copy_from_slice(&v.to_ne_bytes());
+                4
+            }
             _ => errno_i32(ERRNO_EINVAL),
         },
         IPPROTO_TCP => match optname {
@@ -2393,8 +3161,8 @@ pub fn socket_shutdown(sock_idx: u32, how: i32) -> i32 {
         match how {
             SHUT_RD => {
                 sock.flags.set(SocketFlags::SHUT_RD);
-                if socket_is_udp(sock) {
-                    sock.recv_queue.clear();
+                if socket_is_datagram(sock) {
+                    sock.clear_recv_queue();
                 }
             }
             SHUT_WR => {
@@ -2403,8 +3171,8 @@ pub fn socket_shutdown(sock_idx: u32, how: i32) -> i32 {
             SHUT_RDWR => {
                 sock.flags.set(SocketFlags::SHUT_RD);
                 sock.flags.set(SocketFlags::SHUT_WR);
-                if socket_is_udp(sock) {
-                    sock.recv_queue.clear();
+                if socket_is_datagram(sock) {
+                    sock.clear_recv_queue();
                 }
             }
             _ => return errno_i32(ERRNO_EINVAL),
@@ -2422,6 +3190,8 @@ pub fn socket_shutdown(sock_idx: u32, how: i32) -> i32 {
             if let Ok(Some(seg)) = tcp::tcp_shutdown_write(tcp_idx) {
                 let _ = socket_send_tcp_segment(&seg, &[]);
             }
+            // The FIN goes out behind any data still queued.
+            let _ = socket_flush_tcp(tcp_idx);
         }
 
         if shut_rd {
@@ -2435,11 +3205,15 @@ pub fn socket_shutdown(sock_idx: u32, how: i32) -> i32 {
 }
 
 pub fn socket_send_queued(sock_idx: u32) -> i32 {
-    let tcp_idx = match socket_lookup_tcp_idx(sock_idx) {
-        Some(i) => i,
-        None => return errno_i32(ERRNO_ENOTCONN),
-    };
+    match socket_lookup_tcp_idx(sock_idx) {
+        Some(tcp_idx) => socket_flush_tcp(tcp_idx),
+        None => errno_i32(ERRNO_ENOTCONN),
+    }
+}
 
+/// Transmit everything a connection has queued, including a pending FIN.
+/// Works on connections whose socket is already closed.
+pub fn socket_flush_tcp(tcp_idx: usize) -> i32 {
     let mut tx_payload = [0u8; TCP_TX_MAX];
     let now_ms = slopos_lib::clock::uptime_ms();
     loop {
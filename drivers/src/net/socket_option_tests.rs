@@ -209,6 +209,50 @@ pub fn test_shutdown_write() -> TestResult {
     pass!()
 }
 
+pub fn test_so_linger_roundtrip() -> TestResult {
+    reset();
+    let idx = socket_create(AF_INET, SOCK_STREAM, 0);
+    if idx < 0 {
+        return fail!("socket_create failed");
+    }
+    let sock_idx = idx as u32;
+
+    let linger = |onoff: i32, secs: i32| {
+        let mut val = [0u8; 8];
+        val[..4].copy_from_slice(&onoff.to_ne_bytes());
+        val[4..].copy_from_slice(&secs.to_ne_bytes());
+        val
+    };
+    let mut buf = [0u8; 8];
+    assert_eq_test!(
+        socket_getsockopt(sock_idx, SOL_SOCKET, SO_LINGER, &mut buf),
+        8
+    );
+    assert_eq_test!(buf, linger(0, 0), "default SO_LINGER");
+
+    assert_eq_test!(
+        socket_setsockopt(sock_idx, SOL_SOCKET, SO_LINGER, &linger(1, 5)),
+        0
+    );
+    assert_eq_test!(
+        socket_getsockopt(sock_idx, SOL_SOCKET, SO_LINGER, &mut buf),
+        8
+    );
+    assert_eq_test!(buf, linger(1, 5), "SO_LINGER after set");
+
+    assert_test!(
+        socket_setsockopt(sock_idx, SOL_SOCKET, SO_LINGER, &linger(1, -1)) < 0,
+        "negative linger accepted"
+    );
+    assert_test!(
+        socket_setsockopt(sock_idx, SOL_SOCKET, SO_LINGER, &linger(1, 5)[..4]) < 0,
+        "short SO_LINGER accepted"
+    );
+
+    let _ = socket_close(sock_idx);
+    pass!()
+}
+
 pub fn test_unknown_option_returns_einval() -> TestResult {
     reset();
     let idx = socket_create(AF_INET, SOCK_DGRAM, 0);
@@ -236,6 +280,7 @@ slopos_lib::define_test_suite!(
         test_so_error_clear_on_read,
         test_shutdown_read,
         test_shutdown_write,
+        test_so_linger_roundtrip,
         test_unknown_option_returns_einval,
     ]
 );
@@ -0,0 +1,103 @@
+//! Global socket buffer accounting.
+//!
+//! Every byte sitting in a socket buffer is charged to one of two counters:
+//! [`NET_RMEM`] for data received but not yet read by the application, and
+//! [`NET_WMEM`] for data written but not yet acknowledged by the peer.
+//! Per-socket caps come from `SO_RCVBUF`/`SO_SNDBUF`; these counters bound
+//! the total, so many slow readers together cannot pin down the packet pool
+//! and the kernel heap.
+//!
+//! When receive memory runs short, UDP datagrams are dropped and TCP
+//! advertises a shrinking (eventually zero) window, pushing the backpressure
+//! onto the sender instead of buffering without bound.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// Limit on bytes queued for reading across all sockets.
+pub const NET_RMEM_LIMIT: usize = 1024 * 1024;
+
+/// Limit on bytes queued for sending across all sockets.
+pub const NET_WMEM_LIMIT: usize = 1024 * 1024;
+
+/// Receive-side memory charged by all sockets.
+pub static NET_RMEM: NetMemCounter = NetMemCounter::new(NET_RMEM_LIMIT);
+
+/// Send-side memory charged by all sockets.
+pub static NET_WMEM: NetMemCounter = NetMemCounter::new(NET_WMEM_LIMIT);
+
+/// A byte counter with a hard limit.
+pub struct NetMemCounter {
+    used: AtomicUsize,
+    limit: usize,
+}
+
+impl NetMemCounter {
+    pub const fn new(limit: usize) -> Self {
+        Self {
+            used: AtomicUsize::new(0),
+            limit,
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    pub const fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Bytes that can still be charged before the limit is reached.
+    pub fn headroom(&self) -> usize {
+        self.limit.saturating_sub(self.used())
+    }
+
+    /// Charge exactly `bytes`, or nothing if that would exceed the limit.
+    pub fn try_charge(&self, bytes: usize) -> bool {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |used| {
+                used.checked_add(bytes).filter(|&total| total <= self.limit)
+            })
+            .is_ok()
+    }
+
+    /// Charge as much of `bytes` as fits and return the amount charged.
+    pub fn charge_up_to(&self, bytes: usize) -> usize {
+        let mut charged = 0;
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |used| {
+                charged = core::cmp::min(bytes, self.limit.saturating_sub(used));
+                Some(used + charged)
+            });
+        charged
+    }
+
+    /// Return `bytes` previously charged.
+    pub fn uncharge(&self, bytes: usize) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Relaxed, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// Drop every charge (for testing, once all buffers have been released).
+    pub fn reset(&self) {
+        self.used.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Snapshot of both counters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NetMemUsage {
+    pub rmem: usize,
+    pub wmem: usize,
+}
+
+pub fn net_mem_usage() -> NetMemUsage {
+    NetMemUsage {
+        rmem: NET_RMEM.used(),
+        wmem: NET_WMEM.used(),
+    }
+}
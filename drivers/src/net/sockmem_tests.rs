@@ -0,0 +1,224 @@
+//! Socket memory accounting tests: per-socket caps, global limits, and the
+//! TCP window backpressure they produce.
+
+use slopos_abi::net::{AF_INET, SOCK_DGRAM, SOCK_STREAM};
+use slopos_abi::syscall::*;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use super::socket::*;
+use super::sockmem::{NET_RMEM, NetMemCounter, NetMemUsage, net_mem_usage};
+use super::tcp::{self, TCP_BUFFER_SIZE, TCP_FLAG_ACK, TCP_FLAG_PSH, TCP_FLAG_SYN, TcpHeader};
+
+const LOCAL_IP: [u8; 4] = [10, 0, 0, 1];
+const REMOTE_IP: [u8; 4] = [10, 0, 0, 2];
+const CLIENT_PORT: u16 = 49200;
+const SERVER_ISS: u32 = 9000;
+
+fn reset() {
+    socket_reset_all();
+}
+
+/// Open a connection straight on the TCP layer and complete the handshake.
+fn establish() -> Option<usize> {
+    let (idx, syn) = tcp::tcp_connect(LOCAL_IP, CLIENT_PORT, REMOTE_IP, 80).ok()?;
+    let syn_ack = TcpHeader {
+        src_port: 80,
+        dst_port: CLIENT_PORT,
+        seq_num: SERVER_ISS,
+        ack_num: syn.seq_num.wrapping_add(1),
+        data_offset: 5,
+        flags: TCP_FLAG_SYN | TCP_FLAG_ACK,
+        window_size: 32768,
+        checksum: 0,
+        urgent_ptr: 0,
+    };
+    let _ = tcp::tcp_input(REMOTE_IP, LOCAL_IP, &syn_ack, &[], &[], 0);
+    Some(idx)
+}
+
+fn inject(idx: usize, data: &[u8]) -> tcp::TcpInputResult {
+    let conn = tcp::tcp_get_connection(idx).unwrap_or(tcp::TcpConnection::empty());
+    let hdr = TcpHeader {
+        src_port: 80,
+        dst_port: CLIENT_PORT,
+        seq_num: conn.rcv_nxt,
+        ack_num: conn.snd_nxt,
+        data_offset: 5,
+        flags: TCP_FLAG_ACK | TCP_FLAG_PSH,
+        window_size: 32768,
+        checksum: 0,
+        urgent_ptr: 0,
+    };
+    tcp::tcp_input(REMOTE_IP, LOCAL_IP, &hdr, &[], data, 0)
+}
+
+fn rcv_wnd(idx: usize) -> Option<u16> {
+    tcp::tcp_get_connection(idx).map(|c| c.rcv_wnd)
+}
+
+fn set_opt(sock: u32, opt: i32, value: u32) -> i32 {
+    socket_setsockopt(sock, SOL_SOCKET, opt, &value.to_ne_bytes())
+}
+
+pub fn test_counter_charges_within_limit() -> TestResult {
+    let counter = NetMemCounter::new(100);
+    assert_test!(counter.try_charge(60), "charge under the limit");
+    assert_test!(!counter.try_charge(41), "charge past the limit accepted");
+    assert_eq_test!(counter.used(), 60, "failed charge left a residue");
+    assert_eq_test!(counter.charge_up_to(70), 40, "partial charge");
+    assert_eq_test!(counter.headroom(), 0, "headroom at the limit");
+    counter.uncharge(30);
+    assert_eq_test!(counter.used(), 70, "uncharge");
+    counter.uncharge(1000);
+    assert_eq_test!(counter.used(), 0, "uncharge must not underflow");
+    pass!()
+}
+
+pub fn test_udp_queue_respects_rcvbuf() -> TestResult {
+    reset();
+    let sock = socket_create(AF_INET, SOCK_DGRAM, 0);
+    if sock < 0 {
+        return fail!("socket_create failed");
+    }
+    let sock = sock as u32;
+    assert_eq_test!(set_opt(sock, SO_RCVBUF, 256), 0, "SO_RCVBUF");
+
+    let payload = [0x5Au8; 200];
+    socket_deliver_udp(sock, REMOTE_IP, 5000, &payload);
+    socket_deliver_udp(sock, REMOTE_IP, 5000, &payload);
+    assert_eq_test!(net_mem_usage().rmem, 200, "second datagram past SO_RCVBUF");
+    {
+        let table = NEW_SOCKET_TABLE.lock();
+        let Some(s) = table.get(sock as usize) else {
+            return fail!("socket missing");
+        };
+        assert_eq_test!(s.recv_queue.len(), 1, "datagrams queued");
+        assert_eq_test!(s.recv_queued_bytes, 200, "queued bytes");
+    }
+
+    let mut out = [0u8; 256];
+    let n = socket_recvfrom(
+        sock,
+        out.as_mut_ptr(),
+        out.len(),
+        core::ptr::null_mut(),
+        core::ptr::null_mut(),
+    );
+    assert_eq_test!(n, 200, "recvfrom");
+    assert_eq_test!(net_mem_usage().rmem, 0, "read datagram still charged");
+
+    socket_deliver_udp(sock, REMOTE_IP, 5000, &payload);
+    assert_eq_test!(socket_close(sock), 0, "close");
+    assert_eq_test!(net_mem_usage().rmem, 0, "closed socket still charged");
+    pass!()
+}
+
+pub fn test_udp_drops_when_global_rmem_exhausted() -> TestResult {
+    reset();
+    let sock = socket_create(AF_INET, SOCK_DGRAM, 0);
+    if sock < 0 {
+        return fail!("socket_create failed");
+    }
+    let sock = sock as u32;
+
+    let hogged = NET_RMEM.charge_up_to(NET_RMEM.headroom() - 10);
+    socket_deliver_udp(sock, REMOTE_IP, 5000, &[1u8; 64]);
+    let queued = NEW_SOCKET_TABLE
+        .lock()
+        .get(sock as usize)
+        .map_or(usize::MAX, |s| s.recv_queue.len());
+    NET_RMEM.uncharge(hogged);
+    assert_eq_test!(queued, 0, "datagram queued past the global limit");
+    pass!()
+}
+
+pub fn test_tcp_buffer_limits_cap_window_and_send() -> TestResult {
+    reset();
+    let Some(idx) = establish() else {
+        return fail!("connect failed");
+    };
+    assert_eq_test!(
+        tcp::tcp_buffer_limits(idx),
+        Some((TCP_BUFFER_SIZE, TCP_BUFFER_SIZE)),
+        "default limits"
+    );
+
+    let window = tcp::tcp_set_buffer_limits(idx, 1024, 2048);
+    assert_eq_test!(window, 1024, "window follows SO_RCVBUF");
+    assert_eq_test!(tcp::tcp_send_buffer_space(idx), 2048, "send space");
+
+    let data = [0x33u8; 4096];
+    assert_eq_test!(tcp::tcp_send(idx, &data), Ok(2048), "send capped");
+    assert_eq_test!(net_mem_usage().wmem, 2048, "send bytes charged");
+
+    let _ = inject(idx, &data[..1500]);
+    assert_eq_test!(tcp::tcp_recv_available(idx), 1024, "receive capped");
+    assert_eq_test!(rcv_wnd(idx), Some(0), "full buffer advertises zero window");
+    assert_eq_test!(net_mem_usage().rmem, 1024, "receive bytes charged");
+
+    let mut out = [0u8; 512];
+    assert_eq_test!(tcp::tcp_recv(idx, &mut out), Ok(512), "read");
+    assert_eq_test!(net_mem_usage().rmem, 512, "read bytes uncharged");
+    assert_eq_test!(rcv_wnd(idx), Some(512), "window reopens after read");
+
+    tcp::tcp_reset_all();
+    assert_eq_test!(
+        net_mem_usage(),
+        NetMemUsage { rmem: 0, wmem: 0 },
+        "released connection still charged"
+    );
+    pass!()
+}
+
+pub fn test_tcp_window_closes_under_global_pressure() -> TestResult {
+    reset();
+    let Some(idx) = establish() else {
+        return fail!("connect failed");
+    };
+
+    let hogged = NET_RMEM.charge_up_to(NET_RMEM.headroom() - 100);
+    let _ = inject(idx, &[0x44u8; 300]);
+    let available = tcp::tcp_recv_available(idx);
+    let window = rcv_wnd(idx);
+    NET_RMEM.uncharge(hogged);
+
+    assert_eq_test!(available, 100, "accepted past global headroom");
+    assert_eq_test!(window, Some(0), "window not closed under pressure");
+    pass!()
+}
+
+pub fn test_sockopt_updates_tcp_limits() -> TestResult {
+    reset();
+    let sock = socket_create(AF_INET, SOCK_STREAM, 0);
+    if sock < 0 {
+        return fail!("socket_create failed");
+    }
+    let sock = sock as u32;
+    assert_eq_test!(socket_bind(sock, [0, 0, 0, 0], 7070), 0, "bind");
+    assert_eq_test!(socket_listen(sock, 4), 0, "listen");
+    assert_eq_test!(set_opt(sock, SO_RCVBUF, 4096), 0, "SO_RCVBUF");
+    assert_eq_test!(set_opt(sock, SO_SNDBUF, 8192), 0, "SO_SNDBUF");
+
+    let Some(tcp_idx) = socket_lookup_tcp_idx(sock) else {
+        return fail!("listener has no connection");
+    };
+    assert_eq_test!(
+        tcp::tcp_buffer_limits(tcp_idx),
+        Some((4096, 8192)),
+        "listener limits"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    sockmem,
+    [
+        test_counter_charges_within_limit,
+        test_udp_queue_respects_rcvbuf,
+        test_udp_drops_when_global_rmem_exhausted,
+        test_tcp_buffer_limits_cap_window_and_send,
+        test_tcp_window_closes_under_global_pressure,
+        test_sockopt_updates_tcp_limits,
+    ]
+);
@@ -4,14 +4,21 @@
 //! IPv4 pseudo-header, a full TCP state machine, connection table, three-way
 //! handshake (active and passive open), and connection teardown.
 //!
+//! Data transfer follows RFC 6298 for the retransmission timer (RTT samples
+//! under Karn's rule) and RFC 5681 for congestion control (slow start and
+//! congestion avoidance).  Segments arriving ahead of `rcv_nxt` are kept
+//! for reassembly, and a persist timer probes a peer's zero window.
+//!
 //! This module is purely protocol logic — it does **not** drive the NIC
 //! directly.  Higher layers (Phase 5B+) wire it into the VirtIO net driver
 //! for actual packet I/O.
 
-use core::sync::atomic::{AtomicU16, AtomicU32, Ordering};
+use core::sync::atomic::{AtomicU32, Ordering};
 
 use slopos_lib::{IrqMutex, klog_debug};
 
+use crate::net::sockmem::{NET_RMEM, NET_WMEM};
+use crate::net::tcp_socket::{BACKLOG_MAX, BACKLOG_MIN};
 use crate::net::timer::{NET_TIMER_WHEEL, TimerKind, TimerToken};
 
 // =============================================================================
@@ -39,9 +46,25 @@ pub const INITIAL_RTO_MS: u32 = 1000;
 /// Maximum retransmission timeout in milliseconds.
 pub const MAX_RTO_MS: u32 = 60_000;
 
+/// Lower bound on an RTO computed from RTT samples.  RFC 6298 asks for one
+/// second; like most stacks we go lower so a segment lost on a LAN does not
+/// stall the connection that long.
+pub const MIN_RTO_MS: u32 = 200;
+
+/// Clock granularity `G` of RFC 6298: one net timer wheel tick.
+const RTO_GRANULARITY_MS: u32 = 10;
+
 /// TIME_WAIT duration in milliseconds (2 × MSL, MSL = 30s).
 pub const TIME_WAIT_MS: u64 = 60_000;
 
+/// Most connections allowed to sit in TIME_WAIT at once.  Past this the
+/// oldest is recycled, so closed connections can never starve the table.
+pub const MAX_TIME_WAIT: usize = 16;
+
+/// How long a closed connection may wait for the peer to finish the
+/// teardown (FIN_WAIT_1/2, CLOSING, LAST_ACK) before it is dropped.
+pub const FIN_TIMEOUT_MS: u64 = 60_000;
+
 /// Maximum retransmission attempts before giving up.
 pub const MAX_RETRANSMITS: u8 = 8;
 
@@ -51,8 +74,12 @@ pub const TCP_BUFFER_SIZE: usize = 16384;
 pub const DELAYED_ACK_MS: u64 = 200;
 /// Send ACK after this many unacknowledged data segments.
 pub const DELAYED_ACK_SEGMENTS: u8 = 2;
-/// Zero-window probe interval in milliseconds.
+/// Longest interval between zero-window probes in milliseconds.  Probing
+/// starts at the current RTO and backs off up to this.
 pub const ZWP_INTERVAL_MS: u64 = 5000;
+/// Out-of-order byte ranges kept per connection until the segment that
+/// fills the hole before them arrives.
+pub const TCP_OOO_RANGES: usize = 4;
 
 // ---------------------------------------------------------------------------
 // TCP flag bits (in the flags byte of the header)
@@ -350,6 +377,23 @@ pub fn tcp_checksum(src_ip: [u8; 4], dst_ip: [u8; 4], tcp_segment: &[u8]) -> u16
     fold_checksum(sum)
 }
 
+/// Pseudo-header sum for a segment whose checksum the NIC will finish.
+///
+/// Written into the checksum field in place of [`tcp_checksum`] when the
+/// device offloads TX checksums: the device sums from the TCP header onward
+/// and folds this seed in.
+pub fn tcp_pseudo_header_sum(src_ip: [u8; 4], dst_ip: [u8; 4], tcp_len: u16) -> u16 {
+    let mut sum = 0u32;
+    sum = sum.wrapping_add(u16::from_be_bytes([src_ip[0], src_ip[1]]) as u32);
+    sum = sum.wrapping_add(u16::from_be_bytes([src_ip[2], src_ip[3]]) as u32);
+    sum = sum.wrapping_add(u16::from_be_bytes([dst_ip[0], dst_ip[1]]) as u32);
+    sum = sum.wrapping_add(u16::from_be_bytes([dst_ip[2], dst_ip[3]]) as u32);
+    sum = sum.wrapping_add(6u32);
+    sum = sum.wrapping_add(tcp_len as u32);
+
+    !fold_checksum(sum)
+}
+
 /// Verify a received TCP segment's checksum.
 ///
 /// Returns `true` if the checksum is valid (folds to 0).
@@ -426,6 +470,11 @@ impl TcpState {
                 | Self::TimeWait
         )
     }
+
+    /// Has this side closed and not yet had its FIN acknowledged?
+    pub const fn awaits_fin_ack(self) -> bool {
+        matches!(self, Self::FinWait1 | Self::Closing | Self::LastAck)
+    }
 }
 
 // =============================================================================
@@ -510,6 +559,9 @@ pub struct TcpConnection {
     pub snd_wnd: u16,
     /// Initial send sequence number.
     pub iss: u32,
+    /// Highest sequence number sent so far.  Data below it going out again
+    /// is a retransmission and is never timed (Karn's algorithm).
+    pub snd_max: u32,
 
     // --- Receive sequence variables ---
     /// Receive next.
@@ -527,6 +579,33 @@ pub struct TcpConnection {
     /// Retransmit counter.
     pub retransmits: u8,
 
+    // --- RTT estimation (RFC 6298) ---
+    /// Smoothed round-trip time (ms); `None` until the first sample.
+    pub srtt_ms: Option<u32>,
+    /// Round-trip time variation (ms).
+    pub rttvar_ms: u32,
+    /// Sequence number whose acknowledgement completes the RTT sample
+    /// being timed, if any.
+    pub rtt_seq: Option<u32>,
+    /// When the timed segment was sent.
+    pub rtt_start_ms: u64,
+
+    // --- Congestion control (RFC 5681) ---
+    /// Congestion window (bytes).
+    pub cwnd: u32,
+    /// Slow start threshold (bytes).
+    pub ssthresh: u32,
+
+    /// Timer token for the zero-window persist timer.
+    pub persist_timer_token: Option<TimerToken>,
+    /// Interval (ms) until the next zero-window probe; 0 while not probing.
+    pub persist_ms: u32,
+
+    /// Nagle's algorithm (RFC 896): hold back a short segment while
+    /// earlier data is unacknowledged.  The socket layer turns this on
+    /// unless `TCP_NODELAY` is set.
+    pub nagle: bool,
+
     /// Timer token for the pending retransmit timer (Phase 5E).
     pub retransmit_timer_token: Option<TimerToken>,
 
@@ -536,6 +615,24 @@ pub struct TcpConnection {
     /// Timer token for the TIME_WAIT 2×MSL timer (Phase 5E).
     pub time_wait_timer_token: Option<TimerToken>,
 
+    /// Our FIN is owed but waits behind unsent data; `tcp_poll_transmit`
+    /// emits it once the send buffer drains.
+    pub fin_pending: bool,
+
+    /// Timestamp (ms) of close/shutdown(SHUT_WR), for [`FIN_TIMEOUT_MS`].
+    pub close_start_ms: u64,
+
+    /// Read side shut down: further payload is acknowledged and dropped.
+    pub recv_shutdown: bool,
+
+    /// LISTEN only: how many connections that no socket has accepted yet,
+    /// half-open ones included, the listener holds before it drops SYNs.
+    pub backlog: u16,
+
+    /// The LISTEN connection this one was created from, until a socket
+    /// accepts it.
+    pub listener: Option<usize>,
+
     /// Whether the connection slot is in use.
     pub active: bool,
 
@@ -552,19 +649,89 @@ impl TcpConnection {
             snd_nxt: 0,
             snd_wnd: 0,
             iss: 0,
+            snd_max: 0,
             rcv_nxt: 0,
             rcv_wnd: DEFAULT_WINDOW_SIZE,
             irs: 0,
             peer_mss: DEFAULT_MSS,
             rto_ms: INITIAL_RTO_MS,
             retransmits: 0,
+            srtt_ms: None,
+            rttvar_ms: 0,
+            rtt_seq: None,
+            rtt_start_ms: 0,
+            cwnd: initial_cwnd(DEFAULT_MSS),
+            ssthresh: u32::MAX,
+            persist_timer_token: None,
+            persist_ms: 0,
+            nagle: false,
             retransmit_timer_token: None,
             time_wait_start_ms: 0,
             time_wait_timer_token: None,
+            fin_pending: false,
+            close_start_ms: 0,
+            recv_shutdown: false,
+            backlog: 0,
+            listener: None,
             active: false,
             socket_idx: None,
         }
     }
+
+    /// Feed one round-trip sample into SRTT, RTTVAR and the RTO
+    /// (RFC 6298 §2).
+    pub fn rtt_sample(&mut self, rtt_ms: u32) {
+        match self.srtt_ms {
+            None => {
+                self.srtt_ms = Some(rtt_ms);
+                self.rttvar_ms = rtt_ms / 2;
+            }
+            Some(srtt) => {
+                self.rttvar_ms = (3 * self.rttvar_ms + srtt.abs_diff(rtt_ms)) / 4;
+                self.srtt_ms = Some((7 * srtt + rtt_ms) / 8);
+            }
+        }
+        let srtt = self.srtt_ms.unwrap_or(rtt_ms);
+        let rto = srtt.saturating_add(core::cmp::max(RTO_GRANULARITY_MS, 4 * self.rttvar_ms));
+        self.rto_ms = rto.clamp(MIN_RTO_MS, MAX_RTO_MS);
+    }
+
+    /// Grow the congestion window for `acked` newly acknowledged bytes:
+    /// slow start below `ssthresh`, congestion avoidance above it.
+    pub fn cwnd_on_ack(&mut self, acked: u32) {
+        let mss = self.peer_mss as u32;
+        let grow = if self.cwnd < self.ssthresh {
+            core::cmp::min(acked, mss)
+        } else {
+            core::cmp::max(mss * mss / self.cwnd.max(1), 1)
+        };
+        self.cwnd = self.cwnd.saturating_add(grow);
+    }
+
+    /// Collapse the congestion window after a retransmission timeout
+    /// (RFC 5681 §3.1, equation 4).
+    pub fn cwnd_on_timeout(&mut self) {
+        let mss = self.peer_mss as u32;
+        let flight = self.snd_max.wrapping_sub(self.snd_una);
+        self.ssthresh = core::cmp::max(flight / 2, 2 * mss);
+        self.cwnd = mss;
+    }
+}
+
+/// Initial congestion window for a peer MSS (RFC 3390).
+pub const fn initial_cwnd(mss: u16) -> u32 {
+    let mss = mss as u32;
+    let upper = if 2 * mss > 4380 { 2 * mss } else { 4380 };
+    if 4 * mss < upper { 4 * mss } else { upper }
+}
+
+/// A run of bytes received ahead of `rcv_nxt`, in sequence space (`end`
+/// exclusive).  The bytes already sit in the receive ring past its readable
+/// end.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TcpOooRange {
+    pub start: u32,
+    pub end: u32,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -573,6 +740,8 @@ pub struct TcpRingBuffer {
     head: usize,
     tail: usize,
     len: usize,
+    /// Soft cap from `SO_RCVBUF`/`SO_SNDBUF`; 0 means the whole ring.
+    limit: usize,
 }
 
 impl TcpRingBuffer {
@@ -582,6 +751,7 @@ impl TcpRingBuffer {
             head: 0,
             tail: 0,
             len: 0,
+            limit: 0,
         }
     }
 
@@ -589,6 +759,21 @@ impl TcpRingBuffer {
         TCP_BUFFER_SIZE
     }
 
+    /// Bytes the owner may have buffered at once.
+    pub const fn limit(&self) -> usize {
+        if self.limit == 0 || self.limit > TCP_BUFFER_SIZE {
+            TCP_BUFFER_SIZE
+        } else {
+            self.limit
+        }
+    }
+
+    /// Cap the buffer at `limit` bytes.  Data already queued past a lowered
+    /// limit stays; only new writes are held back.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+    }
+
     pub const fn len(&self) -> usize {
         self.len
     }
@@ -598,7 +783,7 @@ impl TcpRingBuffer {
     }
 
     pub const fn free_space(&self) -> usize {
-        TCP_BUFFER_SIZE - self.len
+        self.limit().saturating_sub(self.len)
     }
 
     pub fn write(&mut self, data: &[u8]) -> usize {
@@ -615,6 +800,32 @@ impl TcpRingBuffer {
         written
     }
 
+    /// Copy `data` in `offset` bytes past the end of the buffered data,
+    /// within the free space, without making it readable; [`commit`]
+    /// does that once the gap in front of it is filled.  Returns the bytes
+    /// copied.
+    ///
+    /// [`commit`]: Self::commit
+    pub fn write_at(&mut self, offset: usize, data: &[u8]) -> usize {
+        let room = self.free_space().saturating_sub(offset);
+        let to_write = core::cmp::min(data.len(), room);
+        let mut idx = (self.head + offset) % TCP_BUFFER_SIZE;
+
+        for &byte in &data[..to_write] {
+            self.data[idx] = byte;
+            idx = (idx + 1) % TCP_BUFFER_SIZE;
+        }
+
+        to_write
+    }
+
+    /// Make `n` bytes stored by [`write_at`](Self::write_at) readable.
+    pub fn commit(&mut self, n: usize) {
+        let committed = core::cmp::min(n, TCP_BUFFER_SIZE - self.len);
+        self.head = (self.head + committed) % TCP_BUFFER_SIZE;
+        self.len += committed;
+    }
+
     pub fn read(&mut self, out: &mut [u8]) -> usize {
         let to_read = core::cmp::min(out.len(), self.len);
         let mut read = 0usize;
@@ -677,8 +888,12 @@ impl TcpSendState {
         }
     }
 
+    /// Queue as much of `data` as the buffer and the global send memory
+    /// allow.
     pub fn enqueue(&mut self, data: &[u8]) -> usize {
-        self.buf.write(data)
+        let room = core::cmp::min(data.len(), self.buf.free_space());
+        let charged = NET_WMEM.charge_up_to(room);
+        self.buf.write(&data[..charged])
     }
 
     pub fn unsent_len(&self) -> usize {
@@ -713,6 +928,7 @@ impl TcpSendState {
 
         let consumed = core::cmp::min(acked, self.buf.len());
         self.buf.consume(consumed);
+        NET_WMEM.uncharge(consumed);
         self.inflight = self.inflight.saturating_sub(consumed);
         if self.inflight == 0 {
             self.rto_deadline_ms = 0;
@@ -725,6 +941,7 @@ impl TcpSendState {
     }
 
     pub fn clear(&mut self) {
+        NET_WMEM.uncharge(self.buf.len());
         self.buf.clear();
         self.inflight = 0;
         self.rto_deadline_ms = 0;
@@ -738,6 +955,10 @@ pub struct TcpRecvState {
     pub segments_since_ack: u8,
     pub ack_pending: bool,
     pub delayed_ack_deadline_ms: u64,
+    /// Data held for reassembly, disjoint and not touching each other.
+    /// The ring's write position stays at `rcv_nxt`.
+    pub ooo: [TcpOooRange; TCP_OOO_RANGES],
+    pub ooo_len: usize,
 }
 
 impl TcpRecvState {
@@ -747,6 +968,8 @@ impl TcpRecvState {
             segments_since_ack: 0,
             ack_pending: false,
             delayed_ack_deadline_ms: 0,
+            ooo: [TcpOooRange { start: 0, end: 0 }; TCP_OOO_RANGES],
+            ooo_len: 0,
         }
     }
 
@@ -755,7 +978,9 @@ impl TcpRecvState {
             return 0;
         }
 
-        let wrote = self.buf.write(data);
+        let room = core::cmp::min(data.len(), self.buf.free_space());
+        let charged = NET_RMEM.charge_up_to(room);
+        let wrote = self.buf.write(&data[..charged]);
         if wrote > 0 {
             self.ack_pending = true;
             self.segments_since_ack = self.segments_since_ack.saturating_add(1);
@@ -766,16 +991,94 @@ impl TcpRecvState {
         wrote
     }
 
+    /// Keep a segment that starts at `seq`, ahead of `rcv_nxt`, for
+    /// reassembly.  Only the part inside the receive window is stored.
+    /// Returns false if the segment was dropped: outside the window, or
+    /// every range slot taken by data it does not touch.
+    pub fn insert_out_of_order(&mut self, rcv_nxt: u32, seq: u32, data: &[u8]) -> bool {
+        let offset = seq.wrapping_sub(rcv_nxt) as usize;
+        let room = self.buf.free_space();
+        if !seq_gt(seq, rcv_nxt) || offset >= room || data.is_empty() {
+            return false;
+        }
+        let len = core::cmp::min(data.len(), room - offset);
+        let mut start = seq;
+        let mut end = seq.wrapping_add(len as u32);
+        let touches = |r: &TcpOooRange| seq_le(r.start, end) && seq_le(start, r.end);
+        if self.ooo_len == TCP_OOO_RANGES && !self.ooo.iter().any(touches) {
+            return false;
+        }
+
+        self.buf.write_at(offset, &data[..len]);
+        let mut i = 0;
+        while i < self.ooo_len {
+            let r = self.ooo[i];
+            if seq_le(r.start, end) && seq_le(start, r.end) {
+                if seq_lt(r.start, start) {
+                    start = r.start;
+                }
+                if seq_gt(r.end, end) {
+                    end = r.end;
+                }
+                self.ooo_len -= 1;
+                self.ooo[i] = self.ooo[self.ooo_len];
+            } else {
+                i += 1;
+            }
+        }
+        self.ooo[self.ooo_len] = TcpOooRange { start, end };
+        self.ooo_len += 1;
+        true
+    }
+
+    /// Make stored out-of-order data that now follows `rcv_nxt` readable.
+    /// Returns how far `rcv_nxt` moves.
+    pub fn drain_out_of_order(&mut self, rcv_nxt: u32) -> usize {
+        let mut next = rcv_nxt;
+        let mut total = 0usize;
+        while let Some(i) = self.ooo[..self.ooo_len]
+            .iter()
+            .position(|r| seq_le(r.start, next))
+        {
+            let r = self.ooo[i];
+            self.ooo_len -= 1;
+            self.ooo[i] = self.ooo[self.ooo_len];
+            if !seq_gt(r.end, next) {
+                continue;
+            }
+            let want = r.end.wrapping_sub(next) as usize;
+            let charged = NET_RMEM.charge_up_to(want);
+            self.buf.commit(charged);
+            next = next.wrapping_add(charged as u32);
+            total += charged;
+            if charged < want {
+                // Out of receive memory: keep the rest for later.
+                self.ooo[self.ooo_len] = TcpOooRange {
+                    start: next,
+                    end: r.end,
+                };
+                self.ooo_len += 1;
+                break;
+            }
+        }
+        total
+    }
+
     pub fn dequeue(&mut self, out: &mut [u8]) -> usize {
-        self.buf.read(out)
+        let read = self.buf.read(out);
+        NET_RMEM.uncharge(read);
+        read
     }
 
     pub fn available(&self) -> usize {
         self.buf.len()
     }
 
+    /// Window to advertise: free buffer space, shrunk further when global
+    /// receive memory is running out.
     pub fn window(&self) -> u16 {
-        core::cmp::min(self.buf.free_space(), u16::MAX as usize) as u16
+        let room = core::cmp::min(self.buf.free_space(), NET_RMEM.headroom());
+        core::cmp::min(room, u16::MAX as usize) as u16
     }
 
     pub fn should_ack_now(&self, now_ms: u64) -> bool {
@@ -791,10 +1094,12 @@ impl TcpRecvState {
     }
 
     pub fn clear(&mut self) {
+        NET_RMEM.uncharge(self.buf.len());
         self.buf.clear();
         self.segments_since_ack = 0;
         self.ack_pending = false;
         self.delayed_ack_deadline_ms = 0;
+        self.ooo_len = 0;
     }
 }
 
@@ -812,9 +1117,13 @@ impl TcpBufferPair {
         }
     }
 
+    /// Drop all data and return the limits to the full ring, ready for the
+    /// slot's next connection.
     pub fn clear(&mut self) {
         self.send.clear();
         self.recv.clear();
+        self.send.buf.set_limit(0);
+        self.recv.buf.set_limit(0);
     }
 }
 
@@ -858,24 +1167,6 @@ pub(crate) fn generate_isn() -> u32 {
     ISN_COUNTER.fetch_add(64000, Ordering::Relaxed)
 }
 
-// =============================================================================
-// Ephemeral port allocator
-// =============================================================================
-
-static EPHEMERAL_PORT: AtomicU16 = AtomicU16::new(49152);
-
-/// Allocate the next ephemeral port (49152–65535).
-pub fn alloc_ephemeral_port() -> u16 {
-    loop {
-        let port = EPHEMERAL_PORT.fetch_add(1, Ordering::Relaxed);
-        if port >= 49152 {
-            return port;
-        }
-        // Wrapped around — reset.
-        EPHEMERAL_PORT.store(49152, Ordering::Relaxed);
-    }
-}
-
 // =============================================================================
 // Connection Table
 // =============================================================================
@@ -923,14 +1214,72 @@ impl TcpConnectionTable {
         None
     }
 
-    /// Find a free slot in the table.
-    fn alloc_slot(&self) -> Option<usize> {
+    /// Find a free slot in the table, recycling the oldest TIME_WAIT
+    /// connection if every slot is taken.
+    /// Connections created by the listener at `listen_idx` that no socket
+    /// has accepted yet.
+    fn unaccepted_children(&self, listen_idx: usize) -> usize {
+        self.connections
+            .iter()
+            .filter(|c| c.active && c.listener == Some(listen_idx))
+            .count()
+    }
+
+    fn alloc_slot(&mut self) -> Option<usize> {
         for (i, conn) in self.connections.iter().enumerate() {
             if !conn.active {
                 return Some(i);
             }
         }
-        None
+        let idx = self.oldest_time_wait()?;
+        klog_debug!("tcp: table full, recycling TIME_WAIT idx={}", idx);
+        self.release(idx);
+        Some(idx)
+    }
+
+    fn oldest_time_wait(&self) -> Option<usize> {
+        self.connections
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.active && c.state == TcpState::TimeWait)
+            .min_by_key(|(_, c)| c.time_wait_start_ms)
+            .map(|(i, _)| i)
+    }
+
+    /// Move `idx` into TIME_WAIT and arm its 2×MSL timer.  Keeps at most
+    /// [`MAX_TIME_WAIT`] connections there by dropping the oldest.
+    fn enter_time_wait(&mut self, idx: usize, now_ms: u64) {
+        let mut waiting = 0usize;
+        let mut oldest: Option<(usize, u64)> = None;
+        for (i, c) in self.connections.iter().enumerate() {
+            if i == idx || !c.active || c.state != TcpState::TimeWait {
+                continue;
+            }
+            waiting += 1;
+            if oldest.is_none_or(|(_, since)| c.time_wait_start_ms < since) {
+                oldest = Some((i, c.time_wait_start_ms));
+            }
+        }
+        if waiting >= MAX_TIME_WAIT
+            && let Some((oldest, _)) = oldest
+        {
+            klog_debug!("tcp: TIME_WAIT full, recycling idx={}", oldest);
+            self.release(oldest);
+        }
+
+        self.cancel_persist(idx);
+        let conn = &mut self.connections[idx];
+        conn.state = TcpState::TimeWait;
+        conn.time_wait_start_ms = now_ms;
+        if let Some(token) = conn.retransmit_timer_token.take() {
+            NET_TIMER_WHEEL.cancel(token);
+        }
+        if let Some(token) = conn.time_wait_timer_token.take() {
+            NET_TIMER_WHEEL.cancel(token);
+        }
+        let tw_delay_ticks = (TIME_WAIT_MS / 10).max(1);
+        let tw_token = NET_TIMER_WHEEL.schedule(tw_delay_ticks, TimerKind::TcpTimeWait, idx as u32);
+        conn.time_wait_timer_token = Some(tw_token);
     }
 
     /// Count of active connections.
@@ -938,10 +1287,15 @@ impl TcpConnectionTable {
         self.connections.iter().filter(|c| c.active).count()
     }
 
-    /// Check if a local port is already bound.
-    pub fn port_in_use(&self, local_ip: [u8; 4], local_port: u16) -> bool {
+    /// Check if a listener may not bind `local_ip:local_port`.
+    ///
+    /// Another listener always conflicts.  Connections on the port (accepted
+    /// children, or ones still tearing down or in TIME_WAIT after close)
+    /// conflict only when the new socket did not set `SO_REUSEADDR`.
+    pub fn port_in_use(&self, local_ip: [u8; 4], local_port: u16, reuse_addr: bool) -> bool {
         self.connections.iter().any(|c| {
             c.active
+                && (c.state == TcpState::Listen || !reuse_addr)
                 && c.tuple.local_port == local_port
                 && (c.tuple.local_ip == [0; 4]
                     || local_ip == [0; 4]
@@ -959,6 +1313,50 @@ impl TcpConnectionTable {
         self.connections.get_mut(idx).filter(|c| c.active)
     }
 
+    /// Go back to `snd_una` after a retransmit timeout.  A FIN already sent
+    /// lies past it, so it is queued again behind the resent data.
+    ///
+    /// The congestion window collapses to one segment, and a pending RTT
+    /// sample is abandoned: its ACK could be for either transmission.
+    fn rewind_for_retransmit(&mut self, idx: usize) {
+        self.buffers[idx].send.retransmit_timeout();
+        let conn = &mut self.connections[idx];
+        conn.cwnd_on_timeout();
+        conn.rtt_seq = None;
+        conn.snd_nxt = conn.snd_una;
+        if conn.state.awaits_fin_ack() {
+            conn.fin_pending = true;
+        }
+    }
+
+    /// Start probing a zero send window if data is waiting behind it and
+    /// nothing is in flight to draw a window update.
+    fn arm_persist(&mut self, idx: usize) {
+        let send = &self.buffers[idx].send;
+        let conn = &mut self.connections[idx];
+        if conn.snd_wnd != 0
+            || send.inflight != 0
+            || send.unsent_len() == 0
+            || conn.persist_timer_token.is_some()
+        {
+            return;
+        }
+        if conn.persist_ms == 0 {
+            conn.persist_ms = core::cmp::min(conn.rto_ms, ZWP_INTERVAL_MS as u32);
+        }
+        let delay_ticks = ((conn.persist_ms as u64) / 10).max(1);
+        let token = NET_TIMER_WHEEL.schedule(delay_ticks, TimerKind::TcpPersist, idx as u32);
+        conn.persist_timer_token = Some(token);
+    }
+
+    fn cancel_persist(&mut self, idx: usize) {
+        let conn = &mut self.connections[idx];
+        if let Some(token) = conn.persist_timer_token.take() {
+            NET_TIMER_WHEEL.cancel(token);
+        }
+        conn.persist_ms = 0;
+    }
+
     /// Release a connection slot.
     pub fn release(&mut self, idx: usize) {
         if let Some(conn) = self.connections.get_mut(idx) {
@@ -968,11 +1366,21 @@ impl TcpConnectionTable {
             if let Some(token) = conn.time_wait_timer_token.take() {
                 NET_TIMER_WHEEL.cancel(token);
             }
+            if let Some(token) = conn.persist_timer_token.take() {
+                NET_TIMER_WHEEL.cancel(token);
+            }
             *conn = TcpConnection::empty();
         }
         if let Some(bufs) = self.buffers.get_mut(idx) {
             bufs.clear();
         }
+        // Children of a released listener must not count against whatever
+        // listener reuses the slot.
+        for conn in self.connections.iter_mut() {
+            if conn.listener == Some(idx) {
+                conn.listener = None;
+            }
+        }
     }
 }
 
@@ -982,19 +1390,17 @@ impl TcpConnectionTable {
 
 /// Open an active connection (client: SYN → SYN_SENT).
 ///
+/// The socket layer picks `local_port`; the only check here is that the
+/// exact four-tuple is not still in use, e.g. by a connection in TIME_WAIT.
+///
 /// Returns `(connection_index, outgoing_SYN_segment)`.
 pub fn tcp_connect(
     local_ip: [u8; 4],
+    local_port: u16,
     remote_ip: [u8; 4],
     remote_port: u16,
 ) -> Result<(usize, TcpOutSegment), TcpError> {
-    let local_port = alloc_ephemeral_port();
     let iss = generate_isn();
-
-    let mut table = TCP_TABLE.lock();
-
-    let idx = table.alloc_slot().ok_or(TcpError::TableFull)?;
-
     let tuple = TcpTuple {
         local_ip,
         local_port,
@@ -1002,12 +1408,25 @@ pub fn tcp_connect(
         remote_port,
     };
 
+    let mut table = TCP_TABLE.lock();
+
+    if table
+        .connections
+        .iter()
+        .any(|c| c.active && c.tuple == tuple)
+    {
+        return Err(TcpError::AddrInUse);
+    }
+
+    let idx = table.alloc_slot().ok_or(TcpError::TableFull)?;
+
     let conn = &mut table.connections[idx];
     conn.tuple = tuple;
     conn.state = TcpState::SynSent;
     conn.iss = iss;
     conn.snd_una = iss;
     conn.snd_nxt = iss.wrapping_add(1); // SYN consumes one sequence number
+    conn.snd_max = conn.snd_nxt;
     conn.snd_wnd = 0;
     conn.rcv_wnd = DEFAULT_WINDOW_SIZE;
     conn.peer_mss = DEFAULT_MSS;
@@ -1037,13 +1456,23 @@ pub fn tcp_connect(
     Ok((idx, seg))
 }
 
+/// Whether `local_ip:local_port` is held by a listener or, without
+/// `reuse_addr`, by any connection (see [`TcpConnectionTable::port_in_use`]).
+pub fn tcp_port_in_use(local_ip: [u8; 4], local_port: u16, reuse_addr: bool) -> bool {
+    TCP_TABLE
+        .lock()
+        .port_in_use(local_ip, local_port, reuse_addr)
+}
+
 /// Open a passive connection (server: → LISTEN).
 ///
-/// Binds to `local_ip:local_port` and waits for incoming SYNs.
-pub fn tcp_listen(local_ip: [u8; 4], local_port: u16) -> Result<usize, TcpError> {
+/// Binds to `local_ip:local_port` and waits for incoming SYNs.  With
+/// `reuse_addr`, connections left on the port by an earlier listener do not
+/// block the bind (see [`TcpConnectionTable::port_in_use`]).
+pub fn tcp_listen(local_ip: [u8; 4], local_port: u16, reuse_addr: bool) -> Result<usize, TcpError> {
     let mut table = TCP_TABLE.lock();
 
-    if table.port_in_use(local_ip, local_port) {
+    if table.port_in_use(local_ip, local_port, reuse_addr) {
         return Err(TcpError::AddrInUse);
     }
 
@@ -1058,15 +1487,75 @@ pub fn tcp_listen(local_ip: [u8; 4], local_port: u16) -> Result<usize, TcpError>
     };
     conn.state = TcpState::Listen;
     conn.rcv_wnd = DEFAULT_WINDOW_SIZE;
+    conn.backlog = BACKLOG_MAX as u16;
     conn.active = true;
 
     klog_debug!("tcp: LISTEN on port {} idx={}", local_port, idx);
     Ok(idx)
 }
 
+/// Build our FIN at `snd_nxt` and arm the retransmit timer for it.
+fn emit_fin(table: &mut TcpConnectionTable, idx: usize) -> TcpOutSegment {
+    let conn = &mut table.connections[idx];
+    let seq = conn.snd_nxt;
+    conn.snd_nxt = seq.wrapping_add(1); // FIN consumes one sequence number
+    if seq_gt(conn.snd_nxt, conn.snd_max) {
+        conn.snd_max = conn.snd_nxt;
+    }
+    conn.fin_pending = false;
+    if conn.retransmit_timer_token.is_none() {
+        let delay_ticks = ((conn.rto_ms as u64) / 10).max(1);
+        let token = NET_TIMER_WHEEL.schedule(delay_ticks, TimerKind::TcpRetransmit, idx as u32);
+        conn.retransmit_timer_token = Some(token);
+    }
+    klog_debug!("tcp: FIN idx={} seq={}", idx, seq);
+
+    TcpOutSegment {
+        tuple: conn.tuple,
+        seq_num: seq,
+        ack_num: conn.rcv_nxt,
+        flags: TCP_FLAG_FIN | TCP_FLAG_ACK,
+        window_size: conn.rcv_wnd,
+        mss: 0,
+    }
+}
+
+/// Close our half of a synchronized connection: Established/SynReceived →
+/// FinWait1, CloseWait → LastAck.
+///
+/// The FIN is sequenced after everything already queued.  If the send
+/// buffer still holds unsent data it is only marked pending here and goes
+/// out from `tcp_poll_transmit` behind the last byte.
+fn close_send_half(table: &mut TcpConnectionTable, idx: usize, op: &str) -> Option<TcpOutSegment> {
+    let unsent = table.buffers[idx].send.unsent_len();
+    let conn = &mut table.connections[idx];
+    let prev = conn.state;
+    conn.state = if prev == TcpState::CloseWait {
+        TcpState::LastAck
+    } else {
+        TcpState::FinWait1
+    };
+    conn.close_start_ms = slopos_lib::clock::uptime_ms();
+    klog_debug!(
+        "tcp: {} idx={} {} -> {}, {} bytes before FIN",
+        op,
+        idx,
+        prev.name(),
+        conn.state.name(),
+        unsent
+    );
+
+    if unsent > 0 {
+        conn.fin_pending = true;
+        return None;
+    }
+    Some(emit_fin(table, idx))
+}
+
 /// Close a connection (initiate graceful teardown).
 ///
-/// Returns the outgoing FIN segment if one should be sent.
+/// Returns the outgoing FIN segment if one should be sent now; with data
+/// still queued the FIN follows it from `tcp_poll_transmit`.
 pub fn tcp_close(idx: usize) -> Result<Option<TcpOutSegment>, TcpError> {
     let mut table = TCP_TABLE.lock();
     let conn = table.get_mut(idx).ok_or(TcpError::NotFound)?;
@@ -1080,51 +1569,8 @@ pub fn tcp_close(idx: usize) -> Result<Option<TcpOutSegment>, TcpError> {
             klog_debug!("tcp: CLOSE idx={} from {} — released", idx, state.name());
             Ok(None)
         }
-        TcpState::SynReceived | TcpState::Established => {
-            // Send FIN, move to FIN_WAIT_1.
-            let seq = conn.snd_nxt;
-            conn.snd_nxt = seq.wrapping_add(1); // FIN consumes one sequence number
-            let prev = conn.state;
-            conn.state = TcpState::FinWait1;
-
-            let seg = TcpOutSegment {
-                tuple: conn.tuple,
-                seq_num: seq,
-                ack_num: conn.rcv_nxt,
-                flags: TCP_FLAG_FIN | TCP_FLAG_ACK,
-                window_size: conn.rcv_wnd,
-                mss: 0,
-            };
-
-            klog_debug!(
-                "tcp: CLOSE idx={} {} -> FIN_WAIT_1, FIN seq={}",
-                idx,
-                prev.name(),
-                seq
-            );
-            Ok(Some(seg))
-        }
-        TcpState::CloseWait => {
-            // Peer already sent FIN — send our FIN, move to LAST_ACK.
-            let seq = conn.snd_nxt;
-            conn.snd_nxt = seq.wrapping_add(1);
-            conn.state = TcpState::LastAck;
-
-            let seg = TcpOutSegment {
-                tuple: conn.tuple,
-                seq_num: seq,
-                ack_num: conn.rcv_nxt,
-                flags: TCP_FLAG_FIN | TCP_FLAG_ACK,
-                window_size: conn.rcv_wnd,
-                mss: 0,
-            };
-
-            klog_debug!(
-                "tcp: CLOSE idx={} CLOSE_WAIT -> LAST_ACK, FIN seq={}",
-                idx,
-                seq
-            );
-            Ok(Some(seg))
+        TcpState::SynReceived | TcpState::Established | TcpState::CloseWait => {
+            Ok(close_send_half(&mut table, idx, "CLOSE"))
         }
         // Already closing — ignore.
         TcpState::FinWait1
@@ -1174,49 +1620,8 @@ pub fn tcp_shutdown_write(idx: usize) -> Result<Option<TcpOutSegment>, TcpError>
     let conn = table.get_mut(idx).ok_or(TcpError::NotFound)?;
 
     match conn.state {
-        TcpState::Established | TcpState::SynReceived => {
-            let seq = conn.snd_nxt;
-            conn.snd_nxt = seq.wrapping_add(1);
-            let prev = conn.state;
-            conn.state = TcpState::FinWait1;
-
-            let seg = TcpOutSegment {
-                tuple: conn.tuple,
-                seq_num: seq,
-                ack_num: conn.rcv_nxt,
-                flags: TCP_FLAG_FIN | TCP_FLAG_ACK,
-                window_size: conn.rcv_wnd,
-                mss: 0,
-            };
-
-            klog_debug!(
-                "tcp: SHUTDOWN_WR idx={} {} -> FIN_WAIT_1, FIN seq={}",
-                idx,
-                prev.name(),
-                seq
-            );
-            Ok(Some(seg))
-        }
-        TcpState::CloseWait => {
-            let seq = conn.snd_nxt;
-            conn.snd_nxt = seq.wrapping_add(1);
-            conn.state = TcpState::LastAck;
-
-            let seg = TcpOutSegment {
-                tuple: conn.tuple,
-                seq_num: seq,
-                ack_num: conn.rcv_nxt,
-                flags: TCP_FLAG_FIN | TCP_FLAG_ACK,
-                window_size: conn.rcv_wnd,
-                mss: 0,
-            };
-
-            klog_debug!(
-                "tcp: SHUTDOWN_WR idx={} CLOSE_WAIT -> LAST_ACK, FIN seq={}",
-                idx,
-                seq
-            );
-            Ok(Some(seg))
+        TcpState::Established | TcpState::SynReceived | TcpState::CloseWait => {
+            Ok(close_send_half(&mut table, idx, "SHUTDOWN_WR"))
         }
         // Already sent FIN or not connected — no-op.
         TcpState::FinWait1
@@ -1236,14 +1641,32 @@ pub fn tcp_shutdown_write(idx: usize) -> Result<Option<TcpOutSegment>, TcpError>
 }
 
 /// Discard all data in the receive buffer (for SHUT_RD).
+///
+/// Payload arriving afterwards is still acknowledged, so the peer is not
+/// stalled on a window that will never reopen, but never buffered.
 pub fn tcp_recv_discard(idx: usize) {
     let mut table = TCP_TABLE.lock();
-    if table.get(idx).is_some() {
+    if let Some(conn) = table.get_mut(idx) {
+        conn.recv_shutdown = true;
         table.buffers[idx].recv.clear();
         klog_debug!("tcp: RECV_DISCARD idx={} — recv buffer cleared", idx);
     }
 }
 
+/// Whether a close has finished from our side: the connection is gone or
+/// the peer has acknowledged our FIN and everything before it.
+pub fn tcp_fin_acked(idx: usize) -> bool {
+    let table = TCP_TABLE.lock();
+    table
+        .get(idx)
+        .is_none_or(|c| matches!(c.state, TcpState::FinWait2 | TcpState::TimeWait))
+}
+
+/// Whether a FIN is queued behind unsent data.
+pub fn tcp_fin_pending(idx: usize) -> bool {
+    TCP_TABLE.lock().get(idx).is_some_and(|c| c.fin_pending)
+}
+
 /// Check whether the peer has closed their write half (sent FIN).
 ///
 /// Returns true when the connection is in CloseWait, LastAck, Closing,
@@ -1365,6 +1788,29 @@ pub fn tcp_input(
         }
     };
 
+    // RFC 1122 §4.2.2.13: a new SYN beyond the old incarnation's sequence
+    // space reopens a tuple still in TIME_WAIT, so a client reconnecting
+    // from the same port reaches the listener instead of being ignored.
+    let conn_idx = if table.connections[conn_idx].state == TcpState::TimeWait
+        && hdr.is_syn()
+        && !hdr.is_ack()
+        && seq_gt(hdr.seq_num, table.connections[conn_idx].rcv_nxt)
+    {
+        klog_debug!("tcp: SYN reopens TIME_WAIT idx={}", conn_idx);
+        table.release(conn_idx);
+        match table.find(&incoming_tuple) {
+            Some(idx) => idx,
+            None => {
+                return TcpInputResult {
+                    response: Some(build_rst_for(hdr, dst_ip, src_ip)),
+                    ..TcpInputResult::empty()
+                };
+            }
+        }
+    } else {
+        conn_idx
+    };
+
     let conn_state = table.connections[conn_idx].state;
 
     match conn_state {
@@ -1435,6 +1881,18 @@ fn process_listen(
         return TcpInputResult::empty();
     }
 
+    // Drop the SYN without a RST while the backlog is full; the peer
+    // retransmits it and gets in once `accept()` makes room.
+    let backlog = table.connections[listen_idx].backlog as usize;
+    if table.unaccepted_children(listen_idx) >= backlog {
+        klog_debug!(
+            "tcp: LISTEN idx={} backlog {} full, dropping SYN",
+            listen_idx,
+            backlog
+        );
+        return TcpInputResult::empty();
+    }
+
     let new_idx = match table.alloc_slot() {
         Some(i) => i,
         None => return TcpInputResult::empty(), // Table full, drop silently.
@@ -1443,19 +1901,32 @@ fn process_listen(
     let iss = generate_isn();
     let peer_mss = parse_mss_option(options).unwrap_or(DEFAULT_MSS);
 
+    // The child inherits the listener's buffer limits, as the accepted
+    // socket inherits its options.
+    let recv_limit = table.buffers[listen_idx].recv.buf.limit();
+    let send_limit = table.buffers[listen_idx].send.buf.limit();
+    table.buffers[new_idx].recv.buf.set_limit(recv_limit);
+    table.buffers[new_idx].send.buf.set_limit(send_limit);
+    let rcv_wnd = table.buffers[new_idx].recv.window();
+    let nagle = table.connections[listen_idx].nagle;
+
     let child = &mut table.connections[new_idx];
+    child.nagle = nagle;
     child.tuple = *incoming_tuple;
     child.state = TcpState::SynReceived;
     child.iss = iss;
     child.snd_una = iss;
     child.snd_nxt = iss.wrapping_add(1);
+    child.snd_max = child.snd_nxt;
     child.irs = hdr.seq_num;
     child.rcv_nxt = hdr.seq_num.wrapping_add(1);
     child.snd_wnd = hdr.window_size;
-    child.rcv_wnd = DEFAULT_WINDOW_SIZE;
+    child.rcv_wnd = rcv_wnd;
     child.peer_mss = peer_mss;
+    child.cwnd = initial_cwnd(peer_mss);
     child.rto_ms = INITIAL_RTO_MS;
     child.retransmits = 0;
+    child.listener = Some(listen_idx);
     child.active = true;
 
     klog_debug!(
@@ -1470,7 +1941,7 @@ fn process_listen(
         seq_num: iss,
         ack_num: child.rcv_nxt,
         flags: TCP_FLAG_SYN | TCP_FLAG_ACK,
-        window_size: DEFAULT_WINDOW_SIZE,
+        window_size: rcv_wnd,
         mss: DEFAULT_MSS,
     };
 
@@ -1546,6 +2017,7 @@ fn process_syn_sent(
     conn.rcv_nxt = hdr.seq_num.wrapping_add(1);
     conn.snd_wnd = hdr.window_size;
     conn.peer_mss = peer_mss;
+    conn.cwnd = initial_cwnd(peer_mss);
 
     if hdr.is_ack() {
         // SYN+ACK — our SYN was acknowledged.
@@ -1716,21 +2188,45 @@ fn process_established_and_closing(
         return TcpInputResult::empty();
     }
 
-    // Update snd_una / snd_wnd from the ACK.
+    // Update snd_una / snd_wnd from the ACK.  After a timeout rewound
+    // `snd_nxt`, the ACK may cover data sent before it, up to `snd_max`.
+    // A duplicate ACK still carries the peer's current window, which is
+    // how a zero window reopens.
     let old_snd_una = table.connections[idx].snd_una;
     let mut ack_advanced = false;
     {
         let conn = &mut table.connections[idx];
-        if seq_gt(hdr.ack_num, conn.snd_una) && seq_le(hdr.ack_num, conn.snd_nxt) {
+        if seq_gt(hdr.ack_num, conn.snd_una) && seq_le(hdr.ack_num, conn.snd_max) {
             conn.snd_una = hdr.ack_num;
             conn.snd_wnd = hdr.window_size;
+            if seq_gt(hdr.ack_num, conn.snd_nxt) {
+                conn.snd_nxt = hdr.ack_num;
+            }
             ack_advanced = true;
+        } else if hdr.ack_num == conn.snd_una {
+            conn.snd_wnd = hdr.window_size;
         }
     }
 
     if ack_advanced && seq_gt(hdr.ack_num, old_snd_una) {
         let acked = hdr.ack_num.wrapping_sub(old_snd_una) as usize;
+        let buffered = table.buffers[idx].send.buffered_len();
         table.buffers[idx].send.process_ack(acked);
+        {
+            let conn = &mut table.connections[idx];
+            if conn.fin_pending && conn.state.awaits_fin_ack() && acked > buffered {
+                // Also covers a FIN sent before the rewind.
+                conn.fin_pending = false;
+            }
+            if let Some(rtt_seq) = conn.rtt_seq
+                && seq_ge(hdr.ack_num, rtt_seq)
+            {
+                let rtt_ms = now_ms.saturating_sub(conn.rtt_start_ms);
+                conn.rtt_sample(core::cmp::min(rtt_ms, u32::MAX as u64) as u32);
+                conn.rtt_seq = None;
+            }
+            conn.cwnd_on_ack(acked as u32);
+        }
         if table.buffers[idx].send.inflight == 0 {
             if let Some(token) = table.connections[idx].retransmit_timer_token.take() {
                 NET_TIMER_WHEEL.cancel(token);
@@ -1751,6 +2247,12 @@ fn process_established_and_closing(
         }
     }
 
+    if table.connections[idx].snd_wnd == 0 {
+        table.arm_persist(idx);
+    } else {
+        table.cancel_persist(idx);
+    }
+
     let mut accepted_payload_len = 0usize;
     if !payload.is_empty()
         && matches!(
@@ -1760,6 +2262,13 @@ fn process_established_and_closing(
     {
         let expected_seq = table.connections[idx].rcv_nxt;
         if hdr.seq_num != expected_seq {
+            // Ahead of a hole: keep it for reassembly and send the
+            // duplicate ACK right away (RFC 5681 §4.2).
+            if !table.connections[idx].recv_shutdown {
+                table.buffers[idx]
+                    .recv
+                    .insert_out_of_order(expected_seq, hdr.seq_num, payload);
+            }
             let conn = &table.connections[idx];
             let seg = TcpOutSegment {
                 tuple: conn.tuple,
@@ -1778,16 +2287,31 @@ fn process_established_and_closing(
             };
         }
 
-        let wrote = table.buffers[idx].recv.enqueue(payload, now_ms);
+        let wrote = if table.connections[idx].recv_shutdown {
+            payload.len()
+        } else {
+            table.buffers[idx].recv.enqueue(payload, now_ms)
+        };
         accepted_payload_len = wrote;
+        let rcv_nxt = expected_seq.wrapping_add(wrote as u32);
+        let reassembled = if table.connections[idx].recv_shutdown {
+            0
+        } else {
+            table.buffers[idx].recv.drain_out_of_order(rcv_nxt)
+        };
         let recv_window = table.buffers[idx].recv.window();
         {
             let conn = &mut table.connections[idx];
-            conn.rcv_nxt = conn.rcv_nxt.wrapping_add(wrote as u32);
+            conn.rcv_nxt = rcv_nxt.wrapping_add(reassembled as u32);
             conn.rcv_wnd = recv_window;
         }
 
-        if table.buffers[idx].recv.should_ack_now(now_ms) {
+        // Discarded payload never reaches the delayed-ACK bookkeeping.  A
+        // segment that filled a hole is acknowledged at once.
+        if table.connections[idx].recv_shutdown
+            || reassembled > 0
+            || table.buffers[idx].recv.should_ack_now(now_ms)
+        {
             let conn = &table.connections[idx];
             let seg = TcpOutSegment {
                 tuple: conn.tuple,
@@ -1819,35 +2343,24 @@ fn process_established_and_closing(
         }
     }
 
-    // State-specific ACK processing.
+    // State-specific ACK processing.  A FIN still queued behind data has
+    // not been sent, so an ACK up to `snd_nxt` only covers the data.
+    let fin_acked =
+        !table.connections[idx].fin_pending && hdr.ack_num == table.connections[idx].snd_nxt;
     match current_state {
         TcpState::FinWait1 => {
             // If our FIN is acknowledged.
-            if hdr.ack_num == table.connections[idx].snd_nxt {
+            if fin_acked {
                 if hdr.is_fin() {
                     // Simultaneous close: FIN+ACK acks our FIN and carries theirs.
-                    let conn = &mut table.connections[idx];
-                    conn.rcv_nxt = hdr.seq_num.wrapping_add(1);
-                    conn.state = TcpState::TimeWait;
-                    conn.time_wait_start_ms = now_ms;
-                    if let Some(token) = conn.retransmit_timer_token.take() {
-                        NET_TIMER_WHEEL.cancel(token);
-                    }
-                    if let Some(token) = conn.time_wait_timer_token.take() {
-                        NET_TIMER_WHEEL.cancel(token);
-                    }
-                    let tw_delay_ticks = ((TIME_WAIT_MS as u64) / 10).max(1);
-                    let tw_token = NET_TIMER_WHEEL.schedule(
-                        tw_delay_ticks,
-                        TimerKind::TcpTimeWait,
-                        idx as u32,
-                    );
-                    conn.time_wait_timer_token = Some(tw_token);
+                    table.connections[idx].rcv_nxt = hdr.seq_num.wrapping_add(1);
+                    table.enter_time_wait(idx, now_ms);
                     klog_debug!(
                         "tcp: FIN_WAIT_1 -> TIME_WAIT idx={} (simultaneous close)",
                         idx
                     );
 
+                    let conn = &table.connections[idx];
                     let seg = TcpOutSegment {
                         tuple: conn.tuple,
                         seq_num: conn.snd_nxt,
@@ -1869,43 +2382,27 @@ fn process_established_and_closing(
                 klog_debug!("tcp: FIN_WAIT_1 -> FIN_WAIT_2 idx={}", idx);
             }
         }
-        TcpState::Closing => {
-            if hdr.ack_num == table.connections[idx].snd_nxt {
-                let conn = &mut table.connections[idx];
-                conn.state = TcpState::TimeWait;
-                conn.time_wait_start_ms = now_ms;
-                if let Some(token) = conn.retransmit_timer_token.take() {
-                    NET_TIMER_WHEEL.cancel(token);
-                }
-                if let Some(token) = conn.time_wait_timer_token.take() {
-                    NET_TIMER_WHEEL.cancel(token);
-                }
-                let tw_delay_ticks = ((TIME_WAIT_MS as u64) / 10).max(1);
-                let tw_token =
-                    NET_TIMER_WHEEL.schedule(tw_delay_ticks, TimerKind::TcpTimeWait, idx as u32);
-                conn.time_wait_timer_token = Some(tw_token);
-                klog_debug!("tcp: CLOSING -> TIME_WAIT idx={}", idx);
-                return TcpInputResult {
-                    response: None,
-                    conn_idx: Some(idx),
-                    new_state: Some(TcpState::TimeWait),
-                    accepted_idx: None,
-                    reset: false,
-                };
-            }
+        TcpState::Closing if fin_acked => {
+            table.enter_time_wait(idx, now_ms);
+            klog_debug!("tcp: CLOSING -> TIME_WAIT idx={}", idx);
+            return TcpInputResult {
+                response: None,
+                conn_idx: Some(idx),
+                new_state: Some(TcpState::TimeWait),
+                accepted_idx: None,
+                reset: false,
+            };
         }
-        TcpState::LastAck => {
-            if hdr.ack_num == table.connections[idx].snd_nxt {
-                klog_debug!("tcp: LAST_ACK -> CLOSED idx={}", idx);
-                table.release(idx);
-                return TcpInputResult {
-                    conn_idx: Some(idx),
-                    new_state: Some(TcpState::Closed),
-                    accepted_idx: None,
-                    reset: false,
-                    response: None,
-                };
-            }
+        TcpState::LastAck if fin_acked => {
+            klog_debug!("tcp: LAST_ACK -> CLOSED idx={}", idx);
+            table.release(idx);
+            return TcpInputResult {
+                conn_idx: Some(idx),
+                new_state: Some(TcpState::Closed),
+                accepted_idx: None,
+                reset: false,
+                response: None,
+            };
         }
         _ => {}
     }
@@ -1946,23 +2443,15 @@ fn process_established_and_closing(
                 TcpState::Closing
             }
             TcpState::FinWait2 => {
-                conn.state = TcpState::TimeWait;
-                conn.time_wait_start_ms = now_ms;
-                if let Some(token) = conn.retransmit_timer_token.take() {
-                    NET_TIMER_WHEEL.cancel(token);
-                }
-                if let Some(token) = conn.time_wait_timer_token.take() {
-                    NET_TIMER_WHEEL.cancel(token);
-                }
-                let tw_delay_ticks = ((TIME_WAIT_MS as u64) / 10).max(1);
-                let tw_token =
-                    NET_TIMER_WHEEL.schedule(tw_delay_ticks, TimerKind::TcpTimeWait, idx as u32);
-                conn.time_wait_timer_token = Some(tw_token);
                 klog_debug!("tcp: FIN_WAIT_2 -> TIME_WAIT idx={}", idx);
                 TcpState::TimeWait
             }
             other => other, // FIN in other states — just ACK.
         };
+        if new_state == TcpState::TimeWait {
+            table.enter_time_wait(idx, now_ms);
+        }
+        let conn = &table.connections[idx];
 
         let seg = TcpOutSegment {
             tuple: conn.tuple,
@@ -1982,10 +2471,24 @@ fn process_established_and_closing(
         };
     }
 
+    // An empty segment one below `rcv_nxt` is a window probe (ours are
+    // built the same way): answer with our current window.
+    let conn = &table.connections[idx];
+    let response = (payload.is_empty() && hdr.seq_num == conn.rcv_nxt.wrapping_sub(1)).then(|| {
+        TcpOutSegment {
+            tuple: conn.tuple,
+            seq_num: conn.snd_nxt,
+            ack_num: conn.rcv_nxt,
+            flags: TCP_FLAG_ACK,
+            window_size: table.buffers[idx].recv.window(),
+            mss: 0,
+        }
+    });
+
     TcpInputResult {
-        response: None,
+        response,
         conn_idx: Some(idx),
-        new_state: Some(table.connections[idx].state),
+        new_state: Some(conn.state),
         accepted_idx: None,
         reset: false,
     }
@@ -2020,14 +2523,7 @@ fn process_time_wait(
             window_size: conn.rcv_wnd,
             mss: 0,
         };
-        let conn = &mut table.connections[idx];
-        conn.time_wait_start_ms = now_ms;
-        if let Some(token) = conn.time_wait_timer_token.take() {
-            NET_TIMER_WHEEL.cancel(token);
-        }
-        let tw_delay_ticks = ((TIME_WAIT_MS as u64) / 10).max(1);
-        let tw_token = NET_TIMER_WHEEL.schedule(tw_delay_ticks, TimerKind::TcpTimeWait, idx as u32);
-        conn.time_wait_timer_token = Some(tw_token);
+        table.enter_time_wait(idx, now_ms);
 
         return TcpInputResult {
             response: Some(seg),
@@ -2045,7 +2541,8 @@ fn process_time_wait(
 // Timer-driven maintenance
 // =============================================================================
 
-/// Expire TIME_WAIT connections whose 2×MSL has elapsed.
+/// Expire TIME_WAIT connections whose 2×MSL has elapsed, then reap orphans
+/// as [`tcp_reap_orphans`] does.
 ///
 /// Call periodically from a timer context.  Returns the number of connections
 /// reaped.
@@ -2063,12 +2560,40 @@ pub fn tcp_timer_tick(now_ms: u64) -> usize {
             reaped += 1;
         }
     }
+    reaped + reap_orphans(&mut table, now_ms)
+}
+
+/// Release closed connections whose peer has left the teardown unfinished
+/// for [`FIN_TIMEOUT_MS`]: a FIN never acknowledged, or never answered with
+/// the peer's own.  TIME_WAIT has its own timer and is not touched here.
+///
+/// Only orphans are reaped; a socket that shut down its write half may keep
+/// reading from FIN_WAIT_2 for as long as it likes.
+pub fn tcp_reap_orphans(now_ms: u64) -> usize {
+    reap_orphans(&mut TCP_TABLE.lock(), now_ms)
+}
+
+fn reap_orphans(table: &mut TcpConnectionTable, now_ms: u64) -> usize {
+    let mut reaped = 0usize;
+    for i in 0..MAX_CONNECTIONS {
+        let conn = &table.connections[i];
+        if conn.active
+            && conn.socket_idx.is_none()
+            && (conn.state.awaits_fin_ack() || conn.state == TcpState::FinWait2)
+            && now_ms.saturating_sub(conn.close_start_ms) >= FIN_TIMEOUT_MS
+        {
+            klog_debug!("tcp: {} orphan timed out idx={}", conn.state.name(), i);
+            table.release(i);
+            reaped += 1;
+        }
+    }
     reaped
 }
 
 /// Handle a retransmit timer firing for connection `conn_id`.
 ///
-/// Validates the connection still exists and has unacknowledged in-flight data.
+/// Validates the connection still exists and has unacknowledged in-flight data
+/// or an unacknowledged FIN.
 /// If valid, updates retransmit state and schedules the next retransmit timer.
 /// Returns the connection index so the caller can drive retransmission send.
 pub fn tcp_on_retransmit(conn_id: u32) -> Option<usize> {
@@ -2088,7 +2613,8 @@ pub fn tcp_on_retransmit(conn_id: u32) -> Option<usize> {
             | TcpState::Closing
             | TcpState::LastAck
     );
-    if !conn.active || !send_state || table.buffers[idx].send.inflight == 0 {
+    let fin_in_flight = conn.state.awaits_fin_ack() && !conn.fin_pending;
+    if !conn.active || !send_state || (table.buffers[idx].send.inflight == 0 && !fin_in_flight) {
         return None;
     }
 
@@ -2104,8 +2630,7 @@ pub fn tcp_on_retransmit(conn_id: u32) -> Option<usize> {
         return None;
     }
 
-    table.buffers[idx].send.retransmit_timeout();
-    table.connections[idx].snd_nxt = table.connections[idx].snd_una;
+    table.rewind_for_retransmit(idx);
     table.connections[idx].rto_ms =
         core::cmp::min(table.connections[idx].rto_ms.saturating_mul(2), MAX_RTO_MS);
 
@@ -2181,13 +2706,70 @@ pub fn tcp_find(tuple: &TcpTuple) -> Option<usize> {
 }
 
 /// Set or clear the socket_idx on a connection (Phase 5B bidirectional link).
+/// Setting it marks a listener's child accepted, so it stops counting
+/// against the backlog.
 pub fn tcp_set_socket_idx(idx: usize, socket_idx: Option<usize>) {
     let mut table = TCP_TABLE.lock();
     if let Some(conn) = table.get_mut(idx) {
         conn.socket_idx = socket_idx;
+        if socket_idx.is_some() {
+            conn.listener = None;
+        }
+    }
+}
+
+/// Set how many unaccepted connections a LISTEN connection holds; see
+/// [`TcpConnection::backlog`].  Clamped to `BACKLOG_MIN..=BACKLOG_MAX`.
+pub fn tcp_set_backlog(idx: usize, backlog: usize) {
+    if let Some(conn) = TCP_TABLE.lock().get_mut(idx) {
+        conn.backlog = backlog.clamp(BACKLOG_MIN, BACKLOG_MAX) as u16;
     }
 }
 
+/// A connection created by the listener at `listen_idx` that no socket has
+/// accepted yet, if there is one.
+pub fn tcp_unaccepted_child(listen_idx: usize) -> Option<usize> {
+    TCP_TABLE
+        .lock()
+        .connections
+        .iter()
+        .position(|c| c.active && c.listener == Some(listen_idx))
+}
+
+/// Cap a connection's receive and send buffers (`SO_RCVBUF`/`SO_SNDBUF`).
+/// Limits above [`TCP_BUFFER_SIZE`] are clamped to it.  Returns the receive
+/// window now advertised.
+pub fn tcp_set_buffer_limits(idx: usize, recv_limit: usize, send_limit: usize) -> u16 {
+    let mut table = TCP_TABLE.lock();
+    if table.get(idx).is_none() {
+        return 0;
+    }
+    table.buffers[idx].recv.buf.set_limit(recv_limit);
+    table.buffers[idx].send.buf.set_limit(send_limit);
+    let recv_window = table.buffers[idx].recv.window();
+    if let Some(conn) = table.get_mut(idx) {
+        conn.rcv_wnd = recv_window;
+    }
+    recv_window
+}
+
+/// Turn Nagle's algorithm on or off for a connection.
+pub fn tcp_set_nagle(idx: usize, nagle: bool) {
+    if let Some(conn) = TCP_TABLE.lock().get_mut(idx) {
+        conn.nagle = nagle;
+    }
+}
+
+/// Current receive and send buffer limits of a connection.
+pub fn tcp_buffer_limits(idx: usize) -> Option<(usize, usize)> {
+    let table = TCP_TABLE.lock();
+    table.get(idx)?;
+    Some((
+        table.buffers[idx].recv.buf.limit(),
+        table.buffers[idx].send.buf.limit(),
+    ))
+}
+
 /// Write data into a connection's send buffer.
 /// Returns the number of bytes written (may be less than data.len() if buffer is full).
 pub fn tcp_send(idx: usize, data: &[u8]) -> Result<usize, TcpError> {
@@ -2217,7 +2799,11 @@ pub fn tcp_recv(idx: usize, out: &mut [u8]) -> Result<usize, TcpError> {
 
 /// Generate the next outgoing data segment for a connection.
 /// Fills `payload_buf` with payload data. Returns (header_info, payload_len) or None.
-/// Caller should call repeatedly until None.
+/// Caller should call repeatedly until None.  A pending FIN is returned as a
+/// bare segment once all queued data has gone out.
+///
+/// Data in flight is capped by the smaller of the peer's window and the
+/// congestion window.  A zero peer window arms the persist timer instead.
 pub fn tcp_poll_transmit(
     idx: usize,
     payload_buf: &mut [u8],
@@ -2233,13 +2819,17 @@ pub fn tcp_poll_transmit(
             conn.rcv_nxt,
             conn.rto_ms as u64,
             conn.peer_mss as usize,
-            conn.snd_wnd as usize,
+            core::cmp::min(conn.snd_wnd as usize, conn.cwnd as usize),
         )
     };
 
     if !matches!(
         state,
-        TcpState::Established | TcpState::CloseWait | TcpState::FinWait1
+        TcpState::Established
+            | TcpState::CloseWait
+            | TcpState::FinWait1
+            | TcpState::Closing
+            | TcpState::LastAck
     ) {
         return None;
     }
@@ -2247,11 +2837,24 @@ pub fn tcp_poll_transmit(
     let inflight = table.buffers[idx].send.inflight;
     let wnd_avail = snd_wnd.saturating_sub(inflight);
     let unsent = table.buffers[idx].send.unsent_len();
+    if unsent == 0 && table.connections[idx].fin_pending {
+        return Some((emit_fin(&mut table, idx), 0));
+    }
     let mut max_send = core::cmp::min(unsent, peer_mss);
     max_send = core::cmp::min(max_send, wnd_avail);
     max_send = core::cmp::min(max_send, payload_buf.len());
 
     if max_send == 0 {
+        table.arm_persist(idx);
+        return None;
+    }
+    // Nagle: a short segment waits for the ACK of what is in flight, unless
+    // a FIN is queued behind it.
+    if max_send < peer_mss
+        && inflight > 0
+        && table.connections[idx].nagle
+        && !table.connections[idx].fin_pending
+    {
         return None;
     }
 
@@ -2263,9 +2866,18 @@ pub fn tcp_poll_transmit(
     }
 
     table.buffers[idx].send.mark_sent(payload_len);
-    table.connections[idx].snd_nxt = table.connections[idx]
-        .snd_nxt
-        .wrapping_add(payload_len as u32);
+    {
+        let conn = &mut table.connections[idx];
+        conn.snd_nxt = conn.snd_nxt.wrapping_add(payload_len as u32);
+        // Karn: only time segments carrying new data.
+        if seq_ge(seq, conn.snd_max) && conn.rtt_seq.is_none() {
+            conn.rtt_seq = Some(conn.snd_nxt);
+            conn.rtt_start_ms = now_ms;
+        }
+        if seq_gt(conn.snd_nxt, conn.snd_max) {
+            conn.snd_max = conn.snd_nxt;
+        }
+    }
     if table.buffers[idx].send.rto_deadline_ms == 0 {
         table.buffers[idx].send.rto_deadline_ms = now_ms.saturating_add(rto_ms);
         if table.connections[idx].retransmit_timer_token.is_none() {
@@ -2318,10 +2930,9 @@ pub fn tcp_retransmit_check(now_ms: u64) -> Option<usize> {
             continue;
         }
 
-        table.buffers[idx].send.retransmit_timeout();
+        table.rewind_for_retransmit(idx);
         {
-            let conn = &mut table.connections[idx];
-            conn.snd_nxt = conn.snd_una;
+            let conn = &table.connections[idx];
             table.buffers[idx].send.rto_deadline_ms = now_ms.saturating_add(conn.rto_ms as u64);
         }
         return Some(idx);
@@ -2360,31 +2971,68 @@ pub fn tcp_delayed_ack_check(now_ms: u64) -> Option<(usize, TcpOutSegment)> {
 
 /// Generate a zero-window probe for a connection with snd_wnd == 0.
 /// Returns probe segment or None if window is not zero or no data to send.
+///
+/// The probe is an empty segment one below `snd_una`.  That sequence
+/// number is outside the peer's window, so the peer must answer it with an
+/// ACK carrying its current window, and nothing is consumed on our side.
 pub fn tcp_zero_window_probe(idx: usize, _now_ms: u64) -> Option<TcpOutSegment> {
     let table = TCP_TABLE.lock();
+    build_zero_window_probe(&table, idx)
+}
+
+fn build_zero_window_probe(table: &TcpConnectionTable, idx: usize) -> Option<TcpOutSegment> {
     let conn = table.get(idx)?;
     let send = &table.buffers[idx].send;
 
-    if conn.snd_wnd != 0 || send.buffered_len() == 0 {
-        return None;
-    }
-
-    let mut byte = [0u8; 1];
-    let peeked = send.peek_unsent(&mut byte);
-    if peeked == 0 {
+    if conn.snd_wnd != 0 || send.unsent_len() == 0 {
         return None;
     }
 
     Some(TcpOutSegment {
         tuple: conn.tuple,
-        seq_num: conn.snd_nxt,
+        seq_num: conn.snd_una.wrapping_sub(1),
         ack_num: conn.rcv_nxt,
-        flags: TCP_FLAG_ACK | TCP_FLAG_PSH,
+        flags: TCP_FLAG_ACK,
         window_size: table.buffers[idx].recv.window(),
         mss: 0,
     })
 }
 
+/// Handle the persist timer firing for connection `conn_id`.
+///
+/// While the peer's window stays shut and data waits to go out, returns a
+/// probe to send and re-arms the timer with a doubled interval, capped at
+/// [`ZWP_INTERVAL_MS`].  Probes are not counted as retransmissions: a peer
+/// that keeps answering them keeps the connection alive.
+pub fn tcp_on_persist(conn_id: u32) -> Option<TcpOutSegment> {
+    let mut table = TCP_TABLE.lock();
+    let idx = conn_id as usize;
+    if idx >= MAX_CONNECTIONS {
+        return None;
+    }
+    if let Some(token) = table.connections[idx].persist_timer_token.take() {
+        NET_TIMER_WHEEL.cancel(token);
+    }
+
+    let Some(probe) = build_zero_window_probe(&table, idx) else {
+        table.connections[idx].persist_ms = 0;
+        return None;
+    };
+
+    let conn = &mut table.connections[idx];
+    conn.persist_ms = core::cmp::min(conn.persist_ms.saturating_mul(2), ZWP_INTERVAL_MS as u32);
+    let delay_ticks = ((conn.persist_ms as u64) / 10).max(1);
+    let token = NET_TIMER_WHEEL.schedule(delay_ticks, TimerKind::TcpPersist, conn_id);
+    conn.persist_timer_token = Some(token);
+
+    klog_debug!(
+        "tcp: zero-window probe idx={} next in {}ms",
+        idx,
+        conn.persist_ms
+    );
+    Some(probe)
+}
+
 /// Available send buffer space for a connection.
 pub fn tcp_send_buffer_space(idx: usize) -> usize {
     let table = TCP_TABLE.lock();
@@ -2405,6 +3053,17 @@ pub fn tcp_recv_available(idx: usize) -> usize {
     }
 }
 
+/// Bytes in a connection's send buffer, sent or not, that the peer has
+/// not acknowledged yet.
+pub fn tcp_send_buffered(idx: usize) -> usize {
+    let table = TCP_TABLE.lock();
+    if table.get(idx).is_some() {
+        table.buffers[idx].send.buffered_len()
+    } else {
+        0
+    }
+}
+
 /// Whether a connection has data pending transmission.
 pub fn tcp_has_pending_data(idx: usize) -> bool {
     let table = TCP_TABLE.lock();
@@ -2428,10 +3087,12 @@ pub fn tcp_reset_all() {
         if let Some(token) = table.connections[i].time_wait_timer_token.take() {
             NET_TIMER_WHEEL.cancel(token);
         }
+        if let Some(token) = table.connections[i].persist_timer_token.take() {
+            NET_TIMER_WHEEL.cancel(token);
+        }
         table.connections[i] = TcpConnection::empty();
         table.buffers[i].clear();
     }
-    // Reset ISN counter and ephemeral port for deterministic tests.
+    // Reset ISN counter for deterministic tests.
     ISN_COUNTER.store(0x4F50_534C, Ordering::Relaxed);
-    EPHEMERAL_PORT.store(49152, Ordering::Relaxed);
 }
@@ -62,7 +62,7 @@ pub const SYN_ACK_BASE_DELAY_TICKS: u64 = 100;
 pub const BACKLOG_MIN: usize = 1;
 
 /// Maximum listen backlog.
-pub const BACKLOG_MAX: usize = 128;
+pub const BACKLOG_MAX: usize = slopos_abi::net::SOMAXCONN as usize;
 
 // =============================================================================
 // Key generator for timer dispatch
@@ -483,6 +483,13 @@ impl TcpListenState {
         self.backlog
     }
 
+    /// Change the backlog, clamped like [`new`](Self::new).  Connections
+    /// already queued stay queued even if there are more than the new
+    /// backlog.
+    pub fn set_backlog(&mut self, backlog: usize) {
+        self.backlog = backlog.clamp(BACKLOG_MIN, BACKLOG_MAX);
+    }
+
     /// The local address this listener is bound to.
     pub fn local_addr(&self) -> SockAddr {
         self.local
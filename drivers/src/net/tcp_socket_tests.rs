@@ -11,6 +11,9 @@ use super::tcp_socket::{
     SYN_QUEUE_MAX, SYN_RETRIES_MAX, TcpDemuxTable, TcpListenState, reset_syn_entry_keys,
 };
 use super::types::{Ipv4Addr, Port, SockAddr};
+/// Local port for active opens; the socket layer normally picks one.
+const CLIENT_PORT: u16 = 49152;
+
 /// Helper: create a local listening address.
 fn local_addr() -> SockAddr {
     SockAddr {
@@ -707,8 +710,8 @@ fn establish_connection() -> (usize, u16, u32) {
     let remote_ip = [192, 168, 1, 1];
     let remote_port: u16 = 80;
 
-    let (idx, syn_seg) =
-        tcp::tcp_connect(local_ip, remote_ip, remote_port).expect("tcp_connect should succeed");
+    let (idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, remote_port)
+        .expect("tcp_connect should succeed");
 
     let local_port = syn_seg.tuple.local_port;
     let iss = syn_seg.seq_num; // our ISS
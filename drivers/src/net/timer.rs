@@ -1,7 +1,8 @@
 //! Data-driven timer wheel for the SlopOS networking stack.
 //!
 //! All network timers (ARP aging, TCP retransmit, TCP delayed ACK, TCP keepalive,
-//! TCP TIME_WAIT, reassembly timeout) use this timer wheel with typed dispatch.
+//! TCP TIME_WAIT, reassembly timeout, DNS retransmit, DHCP lease events, link
+//! polling) use this timer wheel with typed dispatch.
 //! No bare `fn()` callbacks — timers carry a [`TimerKind`] discriminant and a
 //! `key` that identifies the specific resource (ARP entry ID, TCP connection ID,
 //! reassembly group ID, etc.).
@@ -38,10 +39,12 @@
 extern crate alloc;
 
 use alloc::vec::Vec;
+use core::ffi::c_void;
 use core::sync::atomic::{AtomicU64, Ordering};
 
-use slopos_lib::IrqMutex;
-use slopos_lib::klog_debug;
+use slopos_core::ktimer::{KtimerMode, ktimer_add};
+use slopos_lib::lockstat::LockClass;
+use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_warn};
 
 /// Number of slots in the timer wheel.
 const NUM_SLOTS: usize = 256;
@@ -77,8 +80,16 @@ pub enum TimerKind {
     TcpTimeWait,
     /// TCP keepalive probe.
     TcpKeepalive,
+    /// TCP persist timer: probe a peer's zero receive window.
+    TcpPersist,
     /// IP reassembly timeout for a fragment group.
     ReassemblyTimeout,
+    /// DNS query went unanswered; `key` is the query ID.
+    DnsRetransmit,
+    /// DHCP client retransmission or lease event; `key` is the arm key.
+    DhcpTimeout,
+    /// Periodic NIC link status poll.
+    LinkWatch,
 }
 
 // =============================================================================
@@ -165,6 +176,8 @@ struct TimerWheelInner {
 // NetTimerWheel
 // =============================================================================
 
+static NET_TIMER_LOCK_CLASS: LockClass = LockClass::new("net_timer");
+
 /// Data-driven timer wheel with 256 slots and typed dispatch.
 ///
 /// See [module documentation](self) for architecture and concurrency details.
@@ -205,10 +218,13 @@ impl NetTimerWheel {
     /// Create a new, empty timer wheel with `current_tick = 0`.
     pub const fn new() -> Self {
         Self {
-            inner: IrqMutex::new(TimerWheelInner {
-                slots: [const { Vec::new() }; NUM_SLOTS],
-                current_tick: 0,
-            }),
+            inner: IrqMutex::with_class(
+                &NET_TIMER_LOCK_CLASS,
+                TimerWheelInner {
+                    slots: [const { Vec::new() }; NUM_SLOTS],
+                    current_tick: 0,
+                },
+            ),
             next_token: AtomicU64::new(1),
         }
     }
@@ -433,6 +449,35 @@ pub fn net_timer_process() {
     }
 }
 
+/// How often orphaned TCP connections are checked against `FIN_TIMEOUT_MS`.
+const ORPHAN_REAP_INTERVAL_MS: u32 = 1000;
+
+static ORPHAN_REAPER_INIT: InitFlag = InitFlag::new();
+
+/// Arm the periodic kernel timers the stack needs outside the wheel.
+///
+/// Orphaned connections stuck half closed have no wheel timer of their own,
+/// so a periodic [`ktimer`](slopos_core::ktimer) sweeps them instead of
+/// every poll-loop pass.
+pub fn net_timer_init() {
+    if !ORPHAN_REAPER_INIT.init_once() {
+        return;
+    }
+    let armed = ktimer_add(
+        KtimerMode::Periodic,
+        ORPHAN_REAP_INTERVAL_MS,
+        reap_orphans_timer,
+        core::ptr::null_mut(),
+    );
+    if armed.is_none() {
+        klog_warn!("net_timer: no kernel timer left for the TCP orphan reaper");
+    }
+}
+
+fn reap_orphans_timer(_context: *mut c_void) {
+    super::tcp::tcp_reap_orphans(slopos_lib::clock::uptime_ms());
+}
+
 /// Dispatch a single fired timer to the appropriate subsystem.
 ///
 /// ARP timers (Phase 2B) call into the [`NeighborCache`] and execute any
@@ -474,10 +519,25 @@ fn dispatch_fired_timer(timer: &FiredTimer) {
             klog_debug!("net_timer: TCP keepalive fired, key={}", timer.key);
             // Phase 5: tcp_engine.on_keepalive(timer.key)
         }
+        TimerKind::TcpPersist => {
+            klog_debug!("net_timer: TCP persist fired, key={}", timer.key);
+            if let Some(probe) = super::tcp::tcp_on_persist(timer.key) {
+                let _ = super::socket::socket_send_tcp_segment(&probe, &[]);
+            }
+        }
         TimerKind::ReassemblyTimeout => {
             klog_debug!("net_timer: reassembly timeout fired, key={}", timer.key);
             // Phase 8: reassembly.on_timeout(timer.key)
         }
+        TimerKind::DnsRetransmit => {
+            klog_debug!("net_timer: DNS retransmit fired, key={}", timer.key);
+            super::dns::dns_on_timeout(timer.key);
+        }
+        TimerKind::DhcpTimeout => {
+            klog_debug!("net_timer: DHCP timeout fired, key={}", timer.key);
+            super::dhcp::dhcp_on_timeout(timer.key);
+        }
+        TimerKind::LinkWatch => crate::virtio_net::virtio_net_link_watch(),
     }
 }
 
@@ -495,7 +555,6 @@ fn dispatch_tcp_syn_ack_retransmit(key: u32) -> bool {
 fn dispatch_tcp_retransmit_send(idx: usize) {
     use super::socket;
 
-    if let Some(sock_idx) = socket::socket_from_tcp_idx_pub(idx) {
-        let _ = socket::socket_send_queued(sock_idx);
-    }
+    // Closed sockets still owe their peer resent data and FIN.
+    let _ = socket::socket_flush_tcp(idx);
 }
@@ -172,6 +172,13 @@ impl MacAddr {
     /// `00:00:00:00:00:00` — the zero / unset address.
     pub const ZERO: Self = Self([0; 6]);
 
+    /// Ethernet address an IPv4 multicast group maps to (RFC 1112):
+    /// `01:00:5e` followed by the low 23 bits of the group.
+    #[inline]
+    pub const fn from_ipv4_multicast(group: Ipv4Addr) -> Self {
+        Self([0x01, 0x00, 0x5e, group.0[1] & 0x7f, group.0[2], group.0[3]])
+    }
+
     /// `true` if the address is `ff:ff:ff:ff:ff:ff`.
     #[inline]
     pub const fn is_broadcast(&self) -> bool {
@@ -468,6 +475,8 @@ impl fmt::Display for EtherType {
 pub enum IpProtocol {
     /// ICMP (`1`).
     Icmp = 1,
+    /// IGMP (`2`).
+    Igmp = 2,
     /// TCP (`6`).
     Tcp = 6,
     /// UDP (`17`).
@@ -480,6 +489,7 @@ impl IpProtocol {
     pub const fn from_u8(val: u8) -> Option<Self> {
         match val {
             1 => Some(Self::Icmp),
+            2 => Some(Self::Igmp),
             6 => Some(Self::Tcp),
             17 => Some(Self::Udp),
             _ => None,
@@ -497,6 +507,7 @@ impl fmt::Display for IpProtocol {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Icmp => write!(f, "ICMP"),
+            Self::Igmp => write!(f, "IGMP"),
             Self::Tcp => write!(f, "TCP"),
             Self::Udp => write!(f, "UDP"),
         }
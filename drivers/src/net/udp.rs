@@ -1,7 +1,7 @@
 use slopos_abi::net::MAX_SOCKETS;
 use slopos_lib::{IrqMutex, klog_debug};
 
-use super::packetbuf::PacketBuf;
+use super::packetbuf::{PacketBuf, UDP_CSUM_OFFSET};
 use super::types::{Ipv4Addr, NetError, Port};
 
 #[derive(Clone, Copy)]
@@ -84,6 +84,26 @@ impl UdpDemuxTable {
         None
     }
 
+    /// Sockets bound to `dst_port` on the wildcard address or on `group`
+    /// itself.  Every one of them gets a copy of a multicast datagram.
+    pub fn lookup_multicast(
+        &self,
+        group: Ipv4Addr,
+        dst_port: Port,
+        out: &mut [u32; MAX_SOCKETS],
+    ) -> usize {
+        let mut count = 0;
+        for entry in self.entries.iter().flatten() {
+            if entry.local_port == dst_port
+                && (entry.local_ip == group || entry.local_ip == Ipv4Addr::UNSPECIFIED)
+            {
+                out[count] = entry.sock_idx;
+                count += 1;
+            }
+        }
+        count
+    }
+
     pub fn clear(&mut self) {
         self.entries = [None; MAX_SOCKETS];
     }
@@ -97,7 +117,23 @@ pub fn handle_rx(src_ip: [u8; 4], dst_ip: [u8; 4], pkt: &PacketBuf) {
     };
 
     if src_port == super::dns::DNS_PORT {
-        crate::virtio_net::dns_intercept_response(udp_payload);
+        super::dns::dns_handle_response(src_ip, udp_payload);
+    }
+
+    if src_port == super::dhcp::UDP_PORT_SERVER && dst_port == super::dhcp::UDP_PORT_CLIENT {
+        super::dhcp::dhcp_handle_rx(udp_payload);
+        return;
+    }
+
+    if Ipv4Addr(dst_ip).is_multicast() {
+        let mut socks = [0u32; MAX_SOCKETS];
+        let count = UDP_DEMUX
+            .lock()
+            .lookup_multicast(Ipv4Addr(dst_ip), Port(dst_port), &mut socks);
+        for &sock_idx in &socks[..count] {
+            super::socket::socket_deliver_udp(sock_idx, src_ip, src_port, udp_payload);
+        }
+        return;
     }
 
     let sock_idx = UDP_DEMUX.lock().lookup(Ipv4Addr(dst_ip), Port(dst_port));
@@ -131,17 +167,26 @@ pub fn udp_unbind(sock_idx: u32, local_ip: Ipv4Addr, local_port: Port) {
     UDP_DEMUX.lock().unregister(local_ip, local_port, sock_idx);
 }
 
+/// Send one datagram.  An unspecified `local_ip` goes out with the first
+/// configured interface address as the source.
 pub fn udp_sendto(
     local_ip: [u8; 4],
     dst_ip: [u8; 4],
     local_port: u16,
     dst_port: u16,
     payload: &[u8],
+    ttl: u8,
 ) -> Result<usize, NetError> {
     if payload.len() > 1472 {
         return Err(NetError::InvalidArgument);
     }
 
+    let local_ip = if Ipv4Addr(local_ip).is_unspecified() {
+        super::NET_STACK.source_addr_for(Ipv4Addr(dst_ip)).0
+    } else {
+        local_ip
+    };
+
     let mut pkt = PacketBuf::alloc().ok_or(NetError::NoBufferSpace)?;
     pkt.append(payload)?;
 
@@ -162,7 +207,7 @@ pub fn udp_sendto(
         ip_hdr[2..4].copy_from_slice(&total_len.to_be_bytes());
         ip_hdr[4..6].copy_from_slice(&0u16.to_be_bytes());
         ip_hdr[6..8].copy_from_slice(&0u16.to_be_bytes());
-        ip_hdr[8] = 64;
+        ip_hdr[8] = ttl;
         ip_hdr[9] = super::IPPROTO_UDP;
         ip_hdr[10..12].copy_from_slice(&0u16.to_be_bytes());
         ip_hdr[12..16].copy_from_slice(&local_ip);
@@ -183,12 +228,17 @@ pub fn udp_sendto(
     pkt.set_l3(head + super::ETH_HEADER_LEN as u16);
     pkt.set_l4(head + (super::ETH_HEADER_LEN + super::IPV4_HEADER_LEN) as u16);
 
-    let udp_checksum = pkt.compute_udp_checksum(Ipv4Addr(local_ip), Ipv4Addr(dst_ip));
-    let udp_start = super::ETH_HEADER_LEN + super::IPV4_HEADER_LEN;
-    let frame = pkt.payload_mut();
-    frame[udp_start + 6..udp_start + 8].copy_from_slice(&udp_checksum.to_be_bytes());
+    pkt.set_checksum_partial(
+        Ipv4Addr(local_ip),
+        Ipv4Addr(dst_ip),
+        super::IPPROTO_UDP,
+        UDP_CSUM_OFFSET,
+    );
 
-    super::ipv4::send(Ipv4Addr(dst_ip), pkt).map_err(|_| NetError::NetworkUnreachable)?;
+    super::ipv4::send(Ipv4Addr(dst_ip), pkt).map_err(|err| match err {
+        NetError::PermissionDenied => err,
+        _ => NetError::NetworkUnreachable,
+    })?;
     Ok(payload.len())
 }
 
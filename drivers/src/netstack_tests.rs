@@ -9,13 +9,14 @@
 //! - 3A.T6: `NetStack::iface_for_dev()` returns None for unknown device
 //! - 3A.T7: `NetStack::is_our_addr()` matches configured interfaces
 //! - 3A.T8: `NetStack::first_ipv4()` returns first up+configured address
+//! - `StaticNetConfig` parses the `ip=` cmdline option and rejects bad input
 
 extern crate alloc;
 
 use slopos_lib::testing::TestResult;
-use slopos_lib::{assert_eq_test, assert_test, pass};
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
 
-use crate::net::netstack::{IfaceConfig, NetStack};
+use crate::net::netstack::{IfaceConfig, NetStack, StaticNetConfig};
 use crate::net::types::{DevIndex, Ipv4Addr};
 
 // =============================================================================
@@ -418,6 +419,65 @@ pub fn test_netstack_first_iface() -> TestResult {
     pass!()
 }
 
+// =============================================================================
+// Static configuration (ip= cmdline option)
+// =============================================================================
+
+pub fn test_static_config_parse_full() -> TestResult {
+    let cfg = StaticNetConfig::from_cmdline(
+        "itests=on ip=10.0.2.15/24,gw=10.0.2.2,dns=10.0.2.3 boot.debug=on",
+    );
+    assert_eq_test!(
+        cfg,
+        Some(StaticNetConfig {
+            addr: Ipv4Addr([10, 0, 2, 15]),
+            netmask: Ipv4Addr([255, 255, 255, 0]),
+            gateway: Ipv4Addr([10, 0, 2, 2]),
+            dns: [Ipv4Addr([10, 0, 2, 3]), Ipv4Addr::UNSPECIFIED],
+        }),
+        "full ip= option"
+    );
+    pass!()
+}
+
+pub fn test_static_config_parse_defaults() -> TestResult {
+    let Some(cfg) = StaticNetConfig::parse("ip=192.168.7.9") else {
+        return fail!("bare address rejected");
+    };
+    assert_eq_test!(cfg.netmask.0, [255, 255, 255, 0], "default prefix is /24");
+    assert_test!(cfg.gateway.is_unspecified(), "gateway set without gw=");
+
+    let Some(cfg) = StaticNetConfig::parse("ip=172.16.0.5/12,dns=1.1.1.1,dns=8.8.8.8") else {
+        return fail!("two dns servers rejected");
+    };
+    assert_eq_test!(cfg.netmask.0, [255, 240, 0, 0], "/12 netmask");
+    assert_eq_test!(cfg.dns[1].0, [8, 8, 8, 8], "second dns server");
+    pass!()
+}
+
+pub fn test_static_config_rejects_malformed() -> TestResult {
+    for spec in [
+        "ip=",
+        "ip=10.0.2",
+        "ip=10.0.2.300/24",
+        "ip=10.0.2.15/33",
+        "ip=10.0.2.15,gw=192.168.0.1",
+        "ip=10.0.2.15,mtu=1500",
+        "ip=10.0.2.15,dns=1.1.1.1,dns=1.0.0.1,dns=8.8.8.8",
+    ] {
+        assert_test!(
+            StaticNetConfig::parse(spec).is_none(),
+            "accepted malformed spec {}",
+            spec
+        );
+    }
+    assert_test!(
+        StaticNetConfig::from_cmdline("itests=on boot.debug=on").is_none(),
+        "config found without ip="
+    );
+    pass!()
+}
+
 // =============================================================================
 // Test suite registration
 // =============================================================================
@@ -448,5 +508,9 @@ slopos_lib::define_test_suite!(
         // Edge cases
         test_netstack_multiple_devices,
         test_netstack_first_iface,
+        // Static configuration
+        test_static_config_parse_full,
+        test_static_config_parse_defaults,
+        test_static_config_rejects_malformed,
     ]
 );
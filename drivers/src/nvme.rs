@@ -0,0 +1,723 @@
+//! NVMe block driver (PCI class 01:08, prog-if 02).
+//!
+//! Brings up the admin queue, identifies the controller and namespace 1,
+//! then creates a single I/O submission/completion queue pair whose
+//! completions are delivered over MSI-X.  Admin commands are polled; they
+//! only run during probe.
+//!
+//! Requests are serialized like virtio-blk: one command in flight, staged
+//! through a page-sized bounce buffer owned by the I/O queue.
+
+use core::ffi::c_int;
+use core::mem::size_of;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_abi::addr::PhysAddr;
+use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_info};
+use slopos_mm::mmio::MmioRegion;
+use slopos_mm::page_alloc::OwnedPageFrame;
+
+use crate::hpet;
+use crate::msix::{MsixDomain, MsixError};
+use crate::pci::{PciDeviceInfo, PciDriver, pci_register_driver};
+use crate::virtio::{QueueEvent, pci::enable_bus_master};
+
+pub const PCI_CLASS_MASS_STORAGE: u8 = 0x01;
+pub const PCI_SUBCLASS_NVME: u8 = 0x08;
+pub const PCI_PROG_IF_NVME: u8 = 0x02;
+
+// Controller registers (NVMe 1.4, section 3.1).
+const REG_CAP: usize = 0x00;
+const REG_VS: usize = 0x08;
+const REG_CC: usize = 0x14;
+const REG_CSTS: usize = 0x1C;
+const REG_AQA: usize = 0x24;
+const REG_ASQ: usize = 0x28;
+const REG_ACQ: usize = 0x30;
+const REG_DOORBELL_BASE: usize = 0x1000;
+
+const CC_EN: u32 = 1 << 0;
+const CC_IOSQES_64: u32 = 6 << 16;
+const CC_IOCQES_16: u32 = 4 << 20;
+const CSTS_RDY: u32 = 1 << 0;
+const CSTS_CFS: u32 = 1 << 1;
+
+// Admin opcodes.
+const ADMIN_CREATE_IO_SQ: u8 = 0x01;
+const ADMIN_CREATE_IO_CQ: u8 = 0x05;
+const ADMIN_IDENTIFY: u8 = 0x06;
+const ADMIN_SET_FEATURES: u8 = 0x09;
+
+const IDENTIFY_CNS_NAMESPACE: u32 = 0x00;
+const IDENTIFY_CNS_CONTROLLER: u32 = 0x01;
+const FEATURE_NUM_QUEUES: u32 = 0x07;
+
+// NVM command set opcodes.
+const NVM_CMD_WRITE: u8 = 0x01;
+const NVM_CMD_READ: u8 = 0x02;
+
+const ADMIN_QID: u16 = 0;
+const IO_QID: u16 = 1;
+const NAMESPACE_ID: u32 = 1;
+
+/// Entries per queue.  64 submission entries fill exactly one page.
+const QUEUE_DEPTH: u16 = 64;
+const PAGE_SIZE: usize = 4096;
+
+/// Each command moves at most one page so PRP1 alone describes it.
+const MAX_TRANSFER: usize = PAGE_SIZE;
+
+const ADMIN_TIMEOUT_MS: u32 = 1000;
+const REQUEST_TIMEOUT_MS: u32 = 5000;
+
+/// MSI-X table entry for the I/O completion queue.  Entry 0 belongs to
+/// the admin queue, which we poll instead.
+const IO_MSIX_ENTRY: u16 = 1;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct NvmeCommand {
+    cdw0: u32,
+    nsid: u32,
+    cdw2: u32,
+    cdw3: u32,
+    mptr: u64,
+    prp1: u64,
+    prp2: u64,
+    cdw10: u32,
+    cdw11: u32,
+    cdw12: u32,
+    cdw13: u32,
+    cdw14: u32,
+    cdw15: u32,
+}
+
+const _: () = assert!(size_of::<NvmeCommand>() == 64);
+
+impl NvmeCommand {
+    fn new(opcode: u8, nsid: u32) -> Self {
+        Self {
+            cdw0: opcode as u32,
+            nsid,
+            ..Self::default()
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct NvmeCompletion {
+    dw0: u32,
+    dw1: u32,
+    sq_head: u16,
+    sq_id: u16,
+    cid: u16,
+    status: u16,
+}
+
+const _: () = assert!(size_of::<NvmeCompletion>() == 16);
+
+impl NvmeCompletion {
+    #[inline]
+    fn phase(&self) -> bool {
+        self.status & 1 != 0
+    }
+
+    /// Status code type and status code; zero means success.
+    #[inline]
+    fn status_field(&self) -> u16 {
+        self.status >> 1
+    }
+}
+
+/// Doorbell register offset for queue `qid`.  `dstrd` is CAP.DSTRD.
+#[inline]
+fn doorbell_offset(qid: u16, completion: bool, dstrd: u32) -> usize {
+    REG_DOORBELL_BASE + ((2 * qid as usize + completion as usize) << (2 + dstrd))
+}
+
+/// One submission/completion queue pair backed by a page each.
+struct NvmeQueue {
+    sq: OwnedPageFrame,
+    cq: OwnedPageFrame,
+    qid: u16,
+    sq_tail: u16,
+    cq_head: u16,
+    phase: bool,
+    next_cid: u16,
+}
+
+impl NvmeQueue {
+    fn allocate(qid: u16) -> Option<Self> {
+        Some(Self {
+            sq: OwnedPageFrame::alloc_zeroed()?,
+            cq: OwnedPageFrame::alloc_zeroed()?,
+            qid,
+            sq_tail: 0,
+            cq_head: 0,
+            phase: true,
+            next_cid: 0,
+        })
+    }
+
+    /// Copy `cmd` into the next SQ slot and ring the tail doorbell.
+    /// Returns the command identifier assigned to it.
+    fn submit(&mut self, regs: &MmioRegion, dstrd: u32, mut cmd: NvmeCommand) -> u16 {
+        let cid = self.next_cid;
+        self.next_cid = self.next_cid.wrapping_add(1);
+        cmd.cdw0 = (cmd.cdw0 & 0xFFFF) | ((cid as u32) << 16);
+
+        unsafe {
+            let slot = self
+                .sq
+                .as_mut_ptr::<NvmeCommand>()
+                .add(self.sq_tail as usize);
+            ptr::write_volatile(slot, cmd);
+        }
+        self.sq_tail = (self.sq_tail + 1) % QUEUE_DEPTH;
+        core::sync::atomic::fence(Ordering::SeqCst);
+        regs.write::<u32>(doorbell_offset(self.qid, false, dstrd), self.sq_tail as u32);
+        cid
+    }
+
+    /// Pop the next posted completion, if any, and ring the head doorbell.
+    fn reap(&mut self, regs: &MmioRegion, dstrd: u32) -> Option<NvmeCompletion> {
+        let entry = unsafe {
+            let slot = self
+                .cq
+                .as_mut_ptr::<NvmeCompletion>()
+                .add(self.cq_head as usize);
+            ptr::read_volatile(slot)
+        };
+        if entry.phase() != self.phase {
+            return None;
+        }
+        self.cq_head += 1;
+        if self.cq_head == QUEUE_DEPTH {
+            self.cq_head = 0;
+            self.phase = !self.phase;
+        }
+        regs.write::<u32>(doorbell_offset(self.qid, true, dstrd), self.cq_head as u32);
+        Some(entry)
+    }
+}
+
+struct NvmeState {
+    regs: MmioRegion,
+    dstrd: u32,
+    admin: Option<NvmeQueue>,
+    io: Option<NvmeQueue>,
+    bounce: Option<OwnedPageFrame>,
+    /// IDT vector for the I/O completion queue; `None` means polled.
+    msix_vector: Option<u8>,
+    namespace_blocks: u64,
+    block_size: u32,
+    ready: bool,
+}
+
+impl NvmeState {
+    const fn new() -> Self {
+        Self {
+            regs: MmioRegion::empty(),
+            dstrd: 0,
+            admin: None,
+            io: None,
+            bounce: None,
+            msix_vector: None,
+            namespace_blocks: 0,
+            block_size: 0,
+            ready: false,
+        }
+    }
+}
+
+static DEVICE_CLAIMED: InitFlag = InitFlag::new();
+static NVME_STATE: IrqMutex<NvmeState> = IrqMutex::new(NvmeState::new());
+static NVME_IO_EVENT: QueueEvent = QueueEvent::new();
+static NVME_REQUEST_IN_FLIGHT: AtomicBool = AtomicBool::new(false);
+
+struct RequestGuard;
+
+impl RequestGuard {
+    fn acquire(flag: &AtomicBool) -> Self {
+        while flag
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        Self
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        NVME_REQUEST_IN_FLIGHT.store(false, Ordering::Release);
+    }
+}
+
+fn nvme_match(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> bool {
+    if info.is_null() {
+        return false;
+    }
+    let info = unsafe { &*info };
+    info.class_code == PCI_CLASS_MASS_STORAGE
+        && info.subclass == PCI_SUBCLASS_NVME
+        && info.prog_if == PCI_PROG_IF_NVME
+}
+
+/// Poll CSTS until RDY matches `ready`.  CAP.TO is in 500 ms units.
+fn wait_ready(regs: &MmioRegion, ready: bool, timeout_ms: u32) -> bool {
+    for _ in 0..timeout_ms {
+        let csts = regs.read::<u32>(REG_CSTS);
+        if csts & CSTS_CFS != 0 {
+            klog_info!("nvme: controller fatal status");
+            return false;
+        }
+        if (csts & CSTS_RDY != 0) == ready {
+            return true;
+        }
+        hpet::delay_ms(1);
+    }
+    false
+}
+
+/// Submit an admin command and poll for its completion.
+fn admin_command(
+    regs: &MmioRegion,
+    dstrd: u32,
+    admin: &mut NvmeQueue,
+    cmd: NvmeCommand,
+) -> Option<NvmeCompletion> {
+    let opcode = cmd.cdw0 as u8;
+    let cid = admin.submit(regs, dstrd, cmd);
+    for _ in 0..ADMIN_TIMEOUT_MS * 100 {
+        if let Some(cqe) = admin.reap(regs, dstrd) {
+            if cqe.cid != cid {
+                continue;
+            }
+            if cqe.status_field() != 0 {
+                klog_info!(
+                    "nvme: admin opcode 0x{:02x} failed, status 0x{:x}",
+                    opcode,
+                    cqe.status_field()
+                );
+                return None;
+            }
+            return Some(cqe);
+        }
+        hpet::delay_ns(10_000);
+    }
+    klog_info!("nvme: admin opcode 0x{:02x} timed out", opcode);
+    None
+}
+
+fn identify(
+    regs: &MmioRegion,
+    dstrd: u32,
+    admin: &mut NvmeQueue,
+    cns: u32,
+    nsid: u32,
+    page: &OwnedPageFrame,
+) -> bool {
+    let mut cmd = NvmeCommand::new(ADMIN_IDENTIFY, nsid);
+    cmd.prp1 = page.phys_u64();
+    cmd.cdw10 = cns;
+    admin_command(regs, dstrd, admin, cmd).is_some()
+}
+
+/// Map BAR0, which holds the register file and doorbells.
+fn map_registers(info: &PciDeviceInfo) -> Option<MmioRegion> {
+    let bar = &info.bars[0];
+    if bar.base == 0 || bar.is_io != 0 {
+        return None;
+    }
+    let len = (bar.size as usize).max(2 * PAGE_SIZE);
+    MmioRegion::map(PhysAddr::new(bar.base), len)
+}
+
+/// Route the I/O completion queue to a freshly allocated vector on the BSP.
+fn setup_msix(info: &PciDeviceInfo) -> Option<u8> {
+    let mut domain = match MsixDomain::open(info) {
+        Ok(d) => d,
+        Err(MsixError::NoCapability) => return None,
+        Err(e) => {
+            klog_debug!("nvme: MSI-X table map failed: {:?}", e);
+            return None;
+        }
+    };
+    let vector = match domain.alloc_entry(IO_MSIX_ENTRY, 0) {
+        Ok(v) => v,
+        Err(e) => {
+            klog_debug!(
+                "nvme: MSI-X entry {} of {} unavailable: {:?}",
+                IO_MSIX_ENTRY,
+                domain.capability().table_size,
+                e
+            );
+            return None;
+        }
+    };
+    if domain
+        .register_handler(IO_MSIX_ENTRY, nvme_irq_handler, ptr::null_mut())
+        .is_err()
+    {
+        domain.free_entry(IO_MSIX_ENTRY);
+        return None;
+    }
+    domain.enable();
+    Some(vector)
+}
+
+/// MSI-X handler for the I/O completion queue.
+extern "C" fn nvme_irq_handler(
+    _vector: u8,
+    _frame: *mut slopos_lib::InterruptFrame,
+    _ctx: *mut core::ffi::c_void,
+) {
+    NVME_IO_EVENT.signal();
+}
+
+fn nvme_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> c_int {
+    if !DEVICE_CLAIMED.claim() {
+        klog_debug!("nvme: already claimed");
+        return -1;
+    }
+    let info = unsafe { &*info };
+    match nvme_bring_up(info) {
+        Ok(()) => 0,
+        Err(msg) => {
+            klog_info!("nvme: {}", msg);
+            DEVICE_CLAIMED.reset();
+            -1
+        }
+    }
+}
+
+fn nvme_bring_up(info: &PciDeviceInfo) -> Result<(), &'static str> {
+    klog_info!(
+        "nvme: probing {:04x}:{:04x} at {:02x}:{:02x}.{}",
+        info.vendor_id,
+        info.device_id,
+        info.bus,
+        info.device,
+        info.function
+    );
+
+    enable_bus_master(info);
+    let regs = map_registers(info).ok_or("BAR0 unavailable")?;
+
+    let cap = regs.read::<u64>(REG_CAP);
+    let mqes = (cap & 0xFFFF) as u16 + 1;
+    let dstrd = ((cap >> 32) & 0xF) as u32;
+    let ready_timeout_ms = (((cap >> 24) & 0xFF) as u32).max(1) * 500;
+    let depth = QUEUE_DEPTH.min(mqes);
+    if depth < QUEUE_DEPTH {
+        return Err("controller queues too shallow");
+    }
+    let vs = regs.read::<u32>(REG_VS);
+    klog_debug!(
+        "nvme: version {}.{}, mqes {}, dstrd {}",
+        vs >> 16,
+        (vs >> 8) & 0xFF,
+        mqes,
+        dstrd
+    );
+
+    // Reset the controller before reprogramming the admin queue.
+    let cc = regs.read::<u32>(REG_CC);
+    if cc & CC_EN != 0 {
+        regs.write::<u32>(REG_CC, cc & !CC_EN);
+    }
+    if !wait_ready(&regs, false, ready_timeout_ms) {
+        return Err("controller did not disable");
+    }
+
+    let mut admin = NvmeQueue::allocate(ADMIN_QID).ok_or("admin queue allocation failed")?;
+    let aqa = (QUEUE_DEPTH as u32 - 1) | ((QUEUE_DEPTH as u32 - 1) << 16);
+    regs.write::<u32>(REG_AQA, aqa);
+    regs.write::<u64>(REG_ASQ, admin.sq.phys_u64());
+    regs.write::<u64>(REG_ACQ, admin.cq.phys_u64());
+    regs.write::<u32>(REG_CC, CC_EN | CC_IOSQES_64 | CC_IOCQES_16);
+    if !wait_ready(&regs, true, ready_timeout_ms) {
+        return Err("controller did not become ready");
+    }
+
+    let ident = OwnedPageFrame::alloc_zeroed().ok_or("identify buffer allocation failed")?;
+    if !identify(&regs, dstrd, &mut admin, IDENTIFY_CNS_CONTROLLER, 0, &ident) {
+        return Err("identify controller failed");
+    }
+    let ctrl = ident.as_mut_ptr::<u8>();
+    let mut model = [0u8; 40];
+    unsafe { ptr::copy_nonoverlapping(ctrl.add(24), model.as_mut_ptr(), model.len()) };
+    let model = core::str::from_utf8(&model).unwrap_or("?").trim_end();
+
+    if !identify(
+        &regs,
+        dstrd,
+        &mut admin,
+        IDENTIFY_CNS_NAMESPACE,
+        NAMESPACE_ID,
+        &ident,
+    ) {
+        return Err("identify namespace failed");
+    }
+    let ns = ident.as_mut_ptr::<u8>();
+    let (namespace_blocks, block_size) = unsafe {
+        let nsze = ptr::read_unaligned(ns as *const u64);
+        let flbas = *ns.add(26) & 0xF;
+        let lbaf = ptr::read_unaligned(ns.add(128 + 4 * flbas as usize) as *const u32);
+        let lbads = (lbaf >> 16) & 0xFF;
+        (nsze, 1u32.checked_shl(lbads).unwrap_or(0))
+    };
+    if namespace_blocks == 0 || block_size < 512 || block_size as usize > MAX_TRANSFER {
+        return Err("namespace 1 unusable");
+    }
+
+    let mut features = NvmeCommand::new(ADMIN_SET_FEATURES, 0);
+    features.cdw10 = FEATURE_NUM_QUEUES;
+    features.cdw11 = 0; // one SQ and one CQ, both zero-based
+    admin_command(&regs, dstrd, &mut admin, features).ok_or("set queue count failed")?;
+
+    let msix_vector = setup_msix(info);
+    let io = NvmeQueue::allocate(IO_QID).ok_or("I/O queue allocation failed")?;
+    let bounce = OwnedPageFrame::alloc_zeroed().ok_or("bounce page allocation failed")?;
+
+    let mut create_cq = NvmeCommand::new(ADMIN_CREATE_IO_CQ, 0);
+    create_cq.prp1 = io.cq.phys_u64();
+    create_cq.cdw10 = ((QUEUE_DEPTH as u32 - 1) << 16) | IO_QID as u32;
+    // Physically contiguous, with interrupts only when MSI-X is wired up.
+    create_cq.cdw11 = match msix_vector {
+        Some(_) => ((IO_MSIX_ENTRY as u32) << 16) | 0b11,
+        None => 0b01,
+    };
+    admin_command(&regs, dstrd, &mut admin, create_cq).ok_or("create I/O CQ failed")?;
+
+    let mut create_sq = NvmeCommand::new(ADMIN_CREATE_IO_SQ, 0);
+    create_sq.prp1 = io.sq.phys_u64();
+    create_sq.cdw10 = ((QUEUE_DEPTH as u32 - 1) << 16) | IO_QID as u32;
+    create_sq.cdw11 = ((IO_QID as u32) << 16) | 0b01;
+    admin_command(&regs, dstrd, &mut admin, create_sq).ok_or("create I/O SQ failed")?;
+
+    {
+        let mut state = NVME_STATE.lock();
+        state.regs = regs;
+        state.dstrd = dstrd;
+        state.admin = Some(admin);
+        state.io = Some(io);
+        state.bounce = Some(bounce);
+        state.msix_vector = msix_vector;
+        state.namespace_blocks = namespace_blocks;
+        state.block_size = block_size;
+        state.ready = true;
+    }
+
+    klog_info!(
+        "nvme: {} ready, {} blocks of {} bytes ({} MB), msix vector {:?}",
+        model,
+        namespace_blocks,
+        block_size,
+        (namespace_blocks * block_size as u64) / (1024 * 1024),
+        msix_vector,
+    );
+    Ok(())
+}
+
+/// Move `blocks` blocks starting at `lba` between `data` and the device.
+/// `data` must be exactly `blocks * block_size` bytes.
+fn do_io(lba: u64, blocks: u32, data: &mut [u8], write: bool) -> bool {
+    let _request_guard = RequestGuard::acquire(&NVME_REQUEST_IN_FLIGHT);
+
+    let (cid, interrupts) = {
+        let mut state = NVME_STATE.lock();
+        let state = &mut *state;
+        let (Some(io), Some(bounce)) = (state.io.as_mut(), state.bounce.as_ref()) else {
+            return false;
+        };
+        if write {
+            unsafe {
+                ptr::copy_nonoverlapping(data.as_ptr(), bounce.as_mut_ptr::<u8>(), data.len())
+            };
+        }
+
+        let mut cmd = NvmeCommand::new(
+            if write { NVM_CMD_WRITE } else { NVM_CMD_READ },
+            NAMESPACE_ID,
+        );
+        cmd.prp1 = bounce.phys_u64();
+        cmd.cdw10 = lba as u32;
+        cmd.cdw11 = (lba >> 32) as u32;
+        cmd.cdw12 = blocks - 1;
+
+        NVME_IO_EVENT.reset();
+        (
+            io.submit(&state.regs, state.dstrd, cmd),
+            state.msix_vector.is_some(),
+        )
+    };
+
+    let deadline = slopos_lib::clock::uptime_ms() + REQUEST_TIMEOUT_MS as u64;
+    let cqe = loop {
+        {
+            let mut state = NVME_STATE.lock();
+            let state = &mut *state;
+            if let Some(cqe) = state
+                .io
+                .as_mut()
+                .and_then(|io| io.reap(&state.regs, state.dstrd))
+            {
+                if cqe.cid == cid {
+                    break cqe;
+                }
+                continue;
+            }
+        }
+        let now = slopos_lib::clock::uptime_ms();
+        if now >= deadline {
+            klog_info!("nvme: request timeout");
+            return false;
+        }
+        if interrupts {
+            NVME_IO_EVENT.wait_timeout_ms((deadline - now) as u32);
+        } else {
+            core::hint::spin_loop();
+        }
+    };
+
+    if cqe.status_field() != 0 {
+        klog_info!(
+            "nvme: I/O failed at lba {}, status 0x{:x}",
+            lba,
+            cqe.status_field()
+        );
+        return false;
+    }
+
+    if !write {
+        let state = NVME_STATE.lock();
+        let Some(bounce) = state.bounce.as_ref() else {
+            return false;
+        };
+        unsafe {
+            ptr::copy_nonoverlapping(bounce.as_mut_ptr::<u8>(), data.as_mut_ptr(), data.len())
+        };
+    }
+    true
+}
+
+static NVME_DRIVER: PciDriver = PciDriver {
+    name: c"nvme".as_ptr().cast(),
+    match_fn: Some(nvme_match),
+    probe: Some(nvme_probe),
+    context: ptr::null_mut(),
+};
+
+pub fn nvme_register_driver() {
+    if pci_register_driver(&NVME_DRIVER) != 0 {
+        klog_info!("nvme: driver registration failed");
+    }
+}
+
+pub fn nvme_is_ready() -> bool {
+    NVME_STATE.lock().ready
+}
+
+/// Logical block size of namespace 1, or 0 if no controller is ready.
+pub fn nvme_block_size() -> u32 {
+    NVME_STATE.lock().block_size
+}
+
+pub fn nvme_capacity() -> u64 {
+    let state = NVME_STATE.lock();
+    state.namespace_blocks * state.block_size as u64
+}
+
+fn in_bounds(offset: u64, len: usize) -> bool {
+    offset
+        .checked_add(len as u64)
+        .is_some_and(|end| end <= nvme_capacity())
+}
+
+pub fn nvme_read(offset: u64, buffer: &mut [u8]) -> bool {
+    if buffer.is_empty() {
+        return true;
+    }
+    if !nvme_is_ready() || !in_bounds(offset, buffer.len()) {
+        return false;
+    }
+
+    let block_size = nvme_block_size() as usize;
+    let mut scratch = [0u8; MAX_TRANSFER];
+    let mut done = 0usize;
+    while done < buffer.len() {
+        let pos = offset + done as u64;
+        let lba = pos / block_size as u64;
+        let skip = (pos % block_size as u64) as usize;
+        let blocks = (skip + buffer.len() - done)
+            .div_ceil(block_size)
+            .min(MAX_TRANSFER / block_size);
+        let span = blocks * block_size;
+        if !do_io(lba, blocks as u32, &mut scratch[..span], false) {
+            return false;
+        }
+        let n = (span - skip).min(buffer.len() - done);
+        buffer[done..done + n].copy_from_slice(&scratch[skip..skip + n]);
+        done += n;
+    }
+    true
+}
+
+pub fn nvme_write(offset: u64, buffer: &[u8]) -> bool {
+    if buffer.is_empty() {
+        return true;
+    }
+    if !nvme_is_ready() || !in_bounds(offset, buffer.len()) {
+        return false;
+    }
+
+    let block_size = nvme_block_size() as usize;
+    let mut scratch = [0u8; MAX_TRANSFER];
+    let mut done = 0usize;
+    while done < buffer.len() {
+        let pos = offset + done as u64;
+        let lba = pos / block_size as u64;
+        let skip = (pos % block_size as u64) as usize;
+        let blocks = (skip + buffer.len() - done)
+            .div_ceil(block_size)
+            .min(MAX_TRANSFER / block_size);
+        let span = blocks * block_size;
+        let n = (span - skip).min(buffer.len() - done);
+
+        // Partial blocks at either end need a read-modify-write.
+        if (skip != 0 || n != span) && !do_io(lba, blocks as u32, &mut scratch[..span], false) {
+            return false;
+        }
+        scratch[skip..skip + n].copy_from_slice(&buffer[done..done + n]);
+        if !do_io(lba, blocks as u32, &mut scratch[..span], true) {
+            return false;
+        }
+        done += n;
+    }
+    true
+}
+
+// =============================================================================
+// Test-only accessors
+// =============================================================================
+
+/// Number of blocks in namespace 1.
+#[cfg(feature = "itests")]
+pub fn nvme_namespace_blocks() -> u64 {
+    NVME_STATE.lock().namespace_blocks
+}
+
+/// IDT vector and MSI-X table entry serving the I/O completion queue, or
+/// `None` if the controller fell back to polling.
+#[cfg(feature = "itests")]
+pub fn nvme_io_msix() -> Option<(u8, u16)> {
+    NVME_STATE
+        .lock()
+        .msix_vector
+        .map(|vector| (vector, IO_MSIX_ENTRY))
+}
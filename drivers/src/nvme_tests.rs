@@ -0,0 +1,206 @@
+//! NVMe driver integration tests.
+//!
+//! The test harness boots QEMU with a blank scratch NVMe disk next to the
+//! virtio-blk root image, so these tests are free to overwrite it.  When no
+//! controller was probed (e.g. a custom QEMU command line) every test is
+//! skipped rather than failed.
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, klog_info, pass};
+
+use crate::nvme;
+
+/// MSI vector range: 48–223 (allocated by `msi_alloc_vector`).
+const MSI_VECTOR_BASE: u8 = 48;
+const MSI_VECTOR_MAX: u8 = 223;
+
+/// Bytes moved by each direction of the throughput test.
+const THROUGHPUT_BYTES: usize = 1024 * 1024;
+const THROUGHPUT_CHUNK: usize = 64 * 1024;
+
+/// Offset of the scratch area, clear of LBA 0 so the pattern tests and the
+/// throughput test don't overlap.
+const SCRATCH_OFFSET: u64 = 1024 * 1024;
+
+fn skip_without_nvme() -> Option<TestResult> {
+    if nvme::nvme_is_ready() {
+        None
+    } else {
+        Some(TestResult::Skipped)
+    }
+}
+
+fn fill_pattern(buf: &mut [u8], seed: u8) {
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = (i as u8).wrapping_mul(31).wrapping_add(seed);
+    }
+}
+
+// =============================================================================
+// Identification
+// =============================================================================
+
+pub fn test_nvme_namespace_identified() -> TestResult {
+    if let Some(skip) = skip_without_nvme() {
+        return skip;
+    }
+    let block_size = nvme::nvme_block_size();
+    assert_test!(
+        block_size >= 512 && block_size.is_power_of_two(),
+        "unexpected block size {}",
+        block_size
+    );
+    assert_test!(
+        nvme::nvme_namespace_blocks() > 0,
+        "namespace 1 reports no blocks"
+    );
+    assert_eq_test!(
+        nvme::nvme_capacity(),
+        nvme::nvme_namespace_blocks() * block_size as u64,
+        "capacity should be blocks * block size"
+    );
+    pass!()
+}
+
+pub fn test_nvme_io_queue_uses_msix() -> TestResult {
+    if let Some(skip) = skip_without_nvme() {
+        return skip;
+    }
+    let Some((vector, entry)) = nvme::nvme_io_msix() else {
+        return fail!("NVMe I/O queue fell back to polling");
+    };
+    assert_test!(
+        (MSI_VECTOR_BASE..=MSI_VECTOR_MAX).contains(&vector),
+        "I/O vector {} outside MSI range",
+        vector
+    );
+    assert_test!(entry != 0, "I/O queue should not share the admin entry");
+    pass!()
+}
+
+// =============================================================================
+// Data path
+// =============================================================================
+
+pub fn test_nvme_write_read_roundtrip() -> TestResult {
+    if let Some(skip) = skip_without_nvme() {
+        return skip;
+    }
+    let mut out = [0u8; 8192];
+    let mut back = [0u8; 8192];
+    fill_pattern(&mut out, 0x5A);
+    assert_test!(
+        nvme::nvme_write(SCRATCH_OFFSET, &out),
+        "write of two pages failed"
+    );
+    assert_test!(
+        nvme::nvme_read(SCRATCH_OFFSET, &mut back),
+        "read of two pages failed"
+    );
+    assert_test!(out == back, "read data differs from written data");
+    pass!()
+}
+
+/// Writes that start and end mid-block must preserve the surrounding bytes.
+pub fn test_nvme_unaligned_write() -> TestResult {
+    if let Some(skip) = skip_without_nvme() {
+        return skip;
+    }
+    let mut base = [0u8; 2048];
+    fill_pattern(&mut base, 0x11);
+    assert_test!(nvme::nvme_write(SCRATCH_OFFSET, &base), "base write failed");
+
+    let patch = [0xEEu8; 700];
+    assert_test!(
+        nvme::nvme_write(SCRATCH_OFFSET + 300, &patch),
+        "unaligned write failed"
+    );
+
+    let mut back = [0u8; 2048];
+    assert_test!(
+        nvme::nvme_read(SCRATCH_OFFSET, &mut back),
+        "read back failed"
+    );
+    base[300..1000].copy_from_slice(&patch);
+    assert_test!(base == back, "unaligned write clobbered neighbouring bytes");
+    pass!()
+}
+
+pub fn test_nvme_read_past_end_fails() -> TestResult {
+    if let Some(skip) = skip_without_nvme() {
+        return skip;
+    }
+    let mut buf = [0u8; 512];
+    assert_test!(
+        !nvme::nvme_read(nvme::nvme_capacity(), &mut buf),
+        "read beyond the namespace should fail"
+    );
+    pass!()
+}
+
+// =============================================================================
+// Throughput
+// =============================================================================
+
+fn throughput_kib_per_s(bytes: usize, elapsed_ms: u64) -> u64 {
+    (bytes as u64 / 1024) * 1000 / elapsed_ms.max(1)
+}
+
+/// Stream 1 MiB out and back in, logging the rate of each direction.
+pub fn test_nvme_throughput() -> TestResult {
+    if let Some(skip) = skip_without_nvme() {
+        return skip;
+    }
+    let mut chunk = alloc::vec![0u8; THROUGHPUT_CHUNK];
+    fill_pattern(&mut chunk, 0xA5);
+    let base = SCRATCH_OFFSET * 2;
+
+    let start = slopos_lib::clock::uptime_ms();
+    for i in 0..THROUGHPUT_BYTES / THROUGHPUT_CHUNK {
+        let offset = base + (i * THROUGHPUT_CHUNK) as u64;
+        assert_test!(
+            nvme::nvme_write(offset, &chunk),
+            "write failed at offset {}",
+            offset
+        );
+    }
+    let write_ms = slopos_lib::clock::uptime_ms() - start;
+
+    let mut back = alloc::vec![0u8; THROUGHPUT_CHUNK];
+    let start = slopos_lib::clock::uptime_ms();
+    for i in 0..THROUGHPUT_BYTES / THROUGHPUT_CHUNK {
+        let offset = base + (i * THROUGHPUT_CHUNK) as u64;
+        assert_test!(
+            nvme::nvme_read(offset, &mut back),
+            "read failed at offset {}",
+            offset
+        );
+    }
+    let read_ms = slopos_lib::clock::uptime_ms() - start;
+    assert_test!(back == chunk, "last chunk read back differs");
+
+    klog_info!(
+        "nvme: throughput write {} KiB/s ({} ms), read {} KiB/s ({} ms)",
+        throughput_kib_per_s(THROUGHPUT_BYTES, write_ms),
+        write_ms,
+        throughput_kib_per_s(THROUGHPUT_BYTES, read_ms),
+        read_ms
+    );
+    pass!()
+}
+
+// =============================================================================
+// Suite registration
+// =============================================================================
+
+slopos_lib::define_test_suite!(
+    nvme,
+    [
+        test_nvme_namespace_identified,
+        test_nvme_io_queue_uses_msix,
+        test_nvme_write_read_roundtrip,
+        test_nvme_unaligned_write,
+        test_nvme_read_past_end_fails,
+        test_nvme_throughput,
+    ]
+);
@@ -7,7 +7,7 @@
 use slopos_lib::testing::TestResult;
 use slopos_lib::{assert_eq_test, assert_test, pass};
 
-use crate::net::packetbuf::{HEADROOM, PacketBuf};
+use crate::net::packetbuf::{HEADROOM, PacketBuf, TCP_CSUM_OFFSET, UDP_CSUM_OFFSET};
 use crate::net::pool::{PACKET_POOL, POOL_SIZE};
 use crate::net::types::{Ipv4Addr, NetError};
 
@@ -544,6 +544,97 @@ pub fn test_tcp_checksum() -> TestResult {
     pass!()
 }
 
+/// A partial checksum finished in software must match a full computation.
+pub fn test_checksum_partial_tcp() -> TestResult {
+    ensure_pool_init();
+
+    let src = Ipv4Addr([10, 0, 2, 15]);
+    let dst = Ipv4Addr([10, 0, 2, 2]);
+
+    #[rustfmt::skip]
+    let tcp_header: [u8; 20] = [
+        0xC0, 0x01, // src_port = 49153
+        0x1F, 0x90, // dst_port = 8080
+        0x00, 0x00, 0x10, 0x00, // seq = 4096
+        0x00, 0x00, 0x00, 0x07, // ack = 7
+        0x50, 0x18, // data_offset=5, PSH|ACK
+        0x40, 0x00, // window = 16384
+        0x00, 0x00, // checksum = 0
+        0x00, 0x00, // urgent_ptr = 0
+    ];
+    let payload = b"odd";
+
+    let mut frame = [0u8; 57];
+    frame[34..54].copy_from_slice(&tcp_header);
+    frame[54..57].copy_from_slice(payload);
+
+    let mut pkt = match PacketBuf::from_raw_copy(&frame) {
+        Some(p) => p,
+        None => return slopos_lib::fail!("from_raw_copy failed"),
+    };
+    pkt.set_l2(0);
+    pkt.set_l3(14);
+    pkt.set_l4(34);
+    let expected = pkt.compute_tcp_checksum(src, dst);
+
+    pkt.set_checksum_partial(src, dst, crate::net::IPPROTO_TCP, TCP_CSUM_OFFSET);
+    assert_eq_test!(
+        pkt.checksum_partial(),
+        Some((34, TCP_CSUM_OFFSET)),
+        "csum_start is the L4 offset within the frame"
+    );
+
+    pkt.finish_checksum();
+    assert_test!(
+        pkt.checksum_partial().is_none(),
+        "finish_checksum clears the partial mark"
+    );
+    let l4 = pkt.l4_header();
+    let csum = u16::from_be_bytes([l4[16], l4[17]]);
+    assert_eq_test!(csum, expected, "finished checksum matches full computation");
+
+    pass!()
+}
+
+/// UDP partial checksums finish to the same value as `udp_checksum`.
+pub fn test_checksum_partial_udp() -> TestResult {
+    ensure_pool_init();
+
+    let src = Ipv4Addr([10, 0, 2, 15]);
+    let dst = Ipv4Addr([10, 0, 2, 3]);
+
+    #[rustfmt::skip]
+    let udp_header: [u8; 8] = [
+        0x30, 0x39, // src_port = 12345
+        0x00, 0x35, // dst_port = 53
+        0x00, 0x0C, // length = 12 (8 + 4)
+        0x00, 0x00, // checksum = 0
+    ];
+    let payload = b"ping";
+
+    let mut frame = [0u8; 46];
+    frame[34..42].copy_from_slice(&udp_header);
+    frame[42..46].copy_from_slice(payload);
+
+    let mut pkt = match PacketBuf::from_raw_copy(&frame) {
+        Some(p) => p,
+        None => return slopos_lib::fail!("from_raw_copy failed"),
+    };
+    pkt.set_l2(0);
+    pkt.set_l3(14);
+    pkt.set_l4(34);
+
+    pkt.set_checksum_partial(src, dst, crate::net::IPPROTO_UDP, UDP_CSUM_OFFSET);
+    pkt.finish_checksum();
+
+    let l4 = pkt.l4_header();
+    let csum = u16::from_be_bytes([l4[6], l4[7]]);
+    let expected = crate::net::udp_checksum(src.0, dst.0, 12345, 53, payload);
+    assert_eq_test!(csum, expected, "finished checksum matches udp_checksum");
+
+    pass!()
+}
+
 // =============================================================================
 // Test suite registration
 // =============================================================================
@@ -575,5 +666,7 @@ slopos_lib::define_test_suite!(
         test_ipv4_checksum,
         test_udp_checksum,
         test_tcp_checksum,
+        test_checksum_partial_tcp,
+        test_checksum_partial_udp,
     ]
 );
@@ -50,6 +50,8 @@ unsafe impl Sync for PciDriver {}
 struct PciEnumState {
     bus_visited: [u8; PCI_MAX_BUSES],
     devices: [PciDeviceInfo; PCI_MAX_DEVICES],
+    /// Driver whose probe claimed each device, parallel to `devices`.
+    bound: [Option<&'static PciDriver>; PCI_MAX_DEVICES],
     device_count: usize,
     primary_gpu: PciGpuInfo,
 }
@@ -59,6 +61,7 @@ impl PciEnumState {
         Self {
             bus_visited: [0; PCI_MAX_BUSES],
             devices: [PciDeviceInfo::zeroed(); PCI_MAX_DEVICES],
+            bound: [None; PCI_MAX_DEVICES],
             device_count: 0,
             primary_gpu: PciGpuInfo::zeroed(),
         }
@@ -564,6 +567,57 @@ pub fn pci_find_ext_capability(bus: u8, device: u8, function: u8, cap_id: u16) -
         .map(|cap| cap.offset)
 }
 
+/// Read the AER registers of a function, or `None` without an AER capability.
+pub fn pci_read_aer_status(bus: u8, device: u8, function: u8) -> Option<PciAerStatus> {
+    let offset = pci_find_ext_capability(bus, device, function, PCI_EXT_CAP_ID_AER)?;
+    let reg = |r: u16| pci_ecam_read32(bus, device, function, offset + r);
+    Some(PciAerStatus {
+        offset,
+        uncorrectable: reg(PCI_AER_UNCOR_STATUS)?,
+        uncorrectable_mask: reg(PCI_AER_UNCOR_MASK)?,
+        uncorrectable_severity: reg(PCI_AER_UNCOR_SEVERITY)?,
+        correctable: reg(PCI_AER_COR_STATUS)?,
+        correctable_mask: reg(PCI_AER_COR_MASK)?,
+    })
+}
+
+/// Clear the latched AER status bits in `status` (both registers are
+/// write-one-to-clear).
+pub fn pci_clear_aer_status(bus: u8, device: u8, function: u8, status: &PciAerStatus) {
+    let base = status.offset;
+    let _ = pci_ecam_write32(
+        bus,
+        device,
+        function,
+        base + PCI_AER_UNCOR_STATUS,
+        status.uncorrectable,
+    );
+    let _ = pci_ecam_write32(
+        bus,
+        device,
+        function,
+        base + PCI_AER_COR_STATUS,
+        status.correctable,
+    );
+}
+
+/// Read the SR-IOV capability of a physical function, or `None` if it has
+/// none.
+pub fn pci_read_sriov(bus: u8, device: u8, function: u8) -> Option<PciSriovInfo> {
+    let offset = pci_find_ext_capability(bus, device, function, PCI_EXT_CAP_ID_SRIOV)?;
+    let reg = |r: u16| pci_ecam_read16(bus, device, function, offset + r);
+    Some(PciSriovInfo {
+        offset,
+        initial_vfs: reg(PCI_SRIOV_INITIAL_VF)?,
+        total_vfs: reg(PCI_SRIOV_TOTAL_VF)?,
+        num_vfs: reg(PCI_SRIOV_NUM_VF)?,
+        vf_offset: reg(PCI_SRIOV_VF_OFFSET)?,
+        vf_stride: reg(PCI_SRIOV_VF_STRIDE)?,
+        vf_device_id: reg(PCI_SRIOV_VF_DID)?,
+        vf_enabled: reg(PCI_SRIOV_CTRL)? & PCI_SRIOV_CTRL_VFE != 0,
+    })
+}
+
 /// Convenience methods for PCI capability queries on a known device.
 impl PciDeviceInfo {
     /// Find the first standard capability with the given ID for this device.
@@ -589,6 +643,16 @@ impl PciDeviceInfo {
     pub fn ext_capabilities(&self) -> PciExtCapabilityIter {
         PciExtCapabilityIter::for_device(self)
     }
+
+    /// AER error state of this device, if it implements AER.
+    pub fn aer_status(&self) -> Option<PciAerStatus> {
+        pci_read_aer_status(self.bus, self.device, self.function)
+    }
+
+    /// SR-IOV layout of this device, if it is an SR-IOV physical function.
+    pub fn sriov(&self) -> Option<PciSriovInfo> {
+        pci_read_sriov(self.bus, self.device, self.function)
+    }
 }
 
 /// Human-readable name for a PCI capability ID (for boot log output).
@@ -797,6 +861,27 @@ fn pci_probe_device(state: &mut PciEnumState, bus: u8, device: u8, function: u8)
             ext_cap.offset
         );
     }
+    if let Some(aer) = info.aer_status()
+        && aer.has_errors()
+    {
+        klog_info!(
+            "    AER: uncorrectable 0x{:08x} (fatal 0x{:08x}), correctable 0x{:08x}",
+            aer.uncorrectable,
+            aer.fatal(),
+            aer.correctable
+        );
+    }
+    if let Some(sriov) = info.sriov() {
+        klog_info!(
+            "    SR-IOV: {} of {} VFs (device 0x{:04x}, offset {}, stride {}){}",
+            sriov.num_vfs,
+            sriov.total_vfs,
+            sriov.vf_device_id,
+            sriov.vf_offset,
+            sriov.vf_stride,
+            if sriov.vf_enabled { ", enabled" } else { "" }
+        );
+    }
 
     for (i, bar) in bars.iter().enumerate() {
         if bar.base != 0 || bar.size != 0 {
@@ -1083,6 +1168,13 @@ pub fn pci_get_device(index: usize) -> Option<PciDeviceInfo> {
     }
 }
 
+/// Name of the driver bound to device `index` by [`pci_probe_drivers`].
+pub fn pci_get_device_driver(index: usize) -> Option<&'static str> {
+    let drv = ENUM_STATE.lock().bound.get(index).copied().flatten()?;
+    // SAFETY: driver names are NUL-terminated static strings.
+    Some(unsafe { cstr_to_str(drv.name as *const c_char) })
+}
+
 pub fn pci_get_primary_gpu() -> PciGpuInfo {
     ENUM_STATE.lock().primary_gpu
 }
@@ -1102,17 +1194,19 @@ pub fn pci_register_driver(driver: &'static PciDriver) -> c_int {
 
 pub fn pci_probe_drivers() {
     let registry = DRIVER_REGISTRY.lock();
-    let state = ENUM_STATE.lock();
+    let mut state = ENUM_STATE.lock();
 
     for drv_idx in 0..registry.count {
         // SAFETY: pci_register_driver only accepts 'static PciDriver references
-        let drv = unsafe { &*registry.drivers[drv_idx] };
+        let drv: &'static PciDriver = unsafe { &*registry.drivers[drv_idx] };
         for dev_idx in 0..state.device_count {
             let dev = &state.devices[dev_idx];
             if let Some(mf) = drv.match_fn {
                 if mf(dev, drv.context) {
                     if let Some(probe) = drv.probe {
-                        let _ = probe(dev, drv.context);
+                        if probe(dev, drv.context) == 0 && state.bound[dev_idx].is_none() {
+                            state.bound[dev_idx] = Some(drv);
+                        }
                     }
                 }
             }
@@ -59,6 +59,9 @@ pub const PCI_STATUS_CAP_LIST: u16 = 0x10;
 // Command Register Bits
 // =============================================================================
 
+/// Enable I/O space access (bit 0).
+pub const PCI_COMMAND_IO_SPACE: u16 = 0x0001;
+
 /// Enable memory space access (bit 1).
 pub const PCI_COMMAND_MEMORY_SPACE: u16 = 0x0002;
 
@@ -145,6 +148,38 @@ pub const PCI_EXT_CAP_ID_DLF: u16 = 0x0025;
 
 /// PCIe Extended Capability ID: Physical Layer 16.0 GT/s.
 pub const PCI_EXT_CAP_ID_PL16G: u16 = 0x0026;
+
+// =============================================================================
+// AER and SR-IOV register offsets (relative to the extended capability header)
+// =============================================================================
+
+/// AER: Uncorrectable Error Status (RW1C).
+pub const PCI_AER_UNCOR_STATUS: u16 = 0x04;
+/// AER: Uncorrectable Error Mask.
+pub const PCI_AER_UNCOR_MASK: u16 = 0x08;
+/// AER: Uncorrectable Error Severity (1 = fatal).
+pub const PCI_AER_UNCOR_SEVERITY: u16 = 0x0C;
+/// AER: Correctable Error Status (RW1C).
+pub const PCI_AER_COR_STATUS: u16 = 0x10;
+/// AER: Correctable Error Mask.
+pub const PCI_AER_COR_MASK: u16 = 0x14;
+
+/// SR-IOV: Control register (16-bit); bit 0 is VF Enable.
+pub const PCI_SRIOV_CTRL: u16 = 0x08;
+/// SR-IOV Control: VF Enable.
+pub const PCI_SRIOV_CTRL_VFE: u16 = 1 << 0;
+/// SR-IOV: InitialVFs (16-bit).
+pub const PCI_SRIOV_INITIAL_VF: u16 = 0x0C;
+/// SR-IOV: TotalVFs (16-bit).
+pub const PCI_SRIOV_TOTAL_VF: u16 = 0x0E;
+/// SR-IOV: NumVFs (16-bit).
+pub const PCI_SRIOV_NUM_VF: u16 = 0x10;
+/// SR-IOV: First VF Offset (16-bit, in routing IDs).
+pub const PCI_SRIOV_VF_OFFSET: u16 = 0x14;
+/// SR-IOV: VF Stride (16-bit, in routing IDs).
+pub const PCI_SRIOV_VF_STRIDE: u16 = 0x16;
+/// SR-IOV: VF Device ID (16-bit).
+pub const PCI_SRIOV_VF_DID: u16 = 0x1A;
 // =============================================================================
 // Known Vendor IDs
 // =============================================================================
@@ -227,6 +262,51 @@ pub struct PciExtCapability {
     pub version: u8,
 }
 
+/// Error state read from a device's Advanced Error Reporting capability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PciAerStatus {
+    /// Byte offset of the AER capability header.
+    pub offset: u16,
+    pub uncorrectable: u32,
+    pub uncorrectable_mask: u32,
+    /// Set bits mark uncorrectable errors reported as fatal.
+    pub uncorrectable_severity: u32,
+    pub correctable: u32,
+    pub correctable_mask: u32,
+}
+
+impl PciAerStatus {
+    /// Whether any unmasked error is latched.
+    #[inline]
+    pub const fn has_errors(&self) -> bool {
+        (self.uncorrectable & !self.uncorrectable_mask) != 0
+            || (self.correctable & !self.correctable_mask) != 0
+    }
+
+    /// Unmasked uncorrectable errors flagged fatal.
+    #[inline]
+    pub const fn fatal(&self) -> u32 {
+        self.uncorrectable & !self.uncorrectable_mask & self.uncorrectable_severity
+    }
+}
+
+/// Virtual function layout from a physical function's SR-IOV capability.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PciSriovInfo {
+    /// Byte offset of the SR-IOV capability header.
+    pub offset: u16,
+    pub initial_vfs: u16,
+    pub total_vfs: u16,
+    /// VFs currently configured (meaningful once `vf_enabled`).
+    pub num_vfs: u16,
+    /// Routing-ID distance from the PF to VF 0.
+    pub vf_offset: u16,
+    /// Routing-ID distance between consecutive VFs.
+    pub vf_stride: u16,
+    pub vf_device_id: u16,
+    pub vf_enabled: bool,
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Default, Debug)]
 pub struct PciDeviceInfo {
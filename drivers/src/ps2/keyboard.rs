@@ -1,7 +1,11 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_abi::input::{KEY_MOD_CTRL, KEY_MOD_SHIFT};
 use slopos_lib::{IrqMutex, RingBuffer, klog_debug, klog_info, klog_warn};
 
-use crate::ps2;
+use super::keymap::{self, Composed, DeadKeyState, KeyLevel, KeySym};
 use crate::tty::{active_tty, push_input};
+use crate::{input_event, ps2, random};
 use slopos_lib::kernel_services::driver_runtime::request_reschedule_from_interrupt;
 
 const BUFFER_SIZE: usize = 256;
@@ -12,7 +16,15 @@ struct ModifierState {
     shift_left: bool,
     shift_right: bool,
     ctrl_left: bool,
+    ctrl_right: bool,
     alt_left: bool,
+    /// Right Alt (E0 38), the third-level shift on DE/FR layouts.
+    altgr: bool,
+    /// Left/right Super (E0 5B / E0 5C); held, keys become shortcuts.
+    super_left: bool,
+    super_right: bool,
+    /// SysRq (Alt+PrintScreen) held: the next key press is a sysrq command.
+    sysrq: bool,
     caps_lock: bool,
 }
 
@@ -22,7 +34,12 @@ impl ModifierState {
             shift_left: false,
             shift_right: false,
             ctrl_left: false,
+            ctrl_right: false,
             alt_left: false,
+            altgr: false,
+            super_left: false,
+            super_right: false,
+            sysrq: false,
             caps_lock: false,
         }
     }
@@ -30,12 +47,45 @@ impl ModifierState {
     fn is_shift(&self) -> bool {
         self.shift_left || self.shift_right
     }
+
+    fn is_ctrl(&self) -> bool {
+        self.ctrl_left || self.ctrl_right
+    }
+
+    fn is_super(&self) -> bool {
+        self.super_left || self.super_right
+    }
+
+    fn is_alt(&self) -> bool {
+        self.alt_left || self.altgr
+    }
+
+    /// `KEY_MOD_*` bits for key events sent to windows.
+    fn key_mods(&self) -> u8 {
+        let mut mods = 0;
+        if self.is_shift() {
+            mods |= KEY_MOD_SHIFT;
+        }
+        if self.is_ctrl() {
+            mods |= KEY_MOD_CTRL;
+        }
+        mods
+    }
+
+    fn level(&self) -> KeyLevel {
+        KeyLevel {
+            shift: self.is_shift(),
+            caps_lock: self.caps_lock,
+            altgr: self.altgr,
+        }
+    }
 }
 
 struct KeyboardState {
     modifiers: ModifierState,
     scancode_buffer: Buffer,
     extended_code: bool,
+    dead_key: DeadKeyState,
 }
 
 impl KeyboardState {
@@ -44,6 +94,7 @@ impl KeyboardState {
             modifiers: ModifierState::new(),
             scancode_buffer: Buffer::new_with(0),
             extended_code: false,
+            dead_key: DeadKeyState::new(),
         }
     }
 
@@ -51,10 +102,15 @@ impl KeyboardState {
         self.modifiers = ModifierState::new();
         self.scancode_buffer = Buffer::new_with(0);
         self.extended_code = false;
+        self.dead_key.cancel();
     }
 }
 
 static STATE: IrqMutex<KeyboardState> = IrqMutex::new(KeyboardState::new());
+/// Handles Alt+SysRq+<key>; see [`keyboard_register_sysrq`].
+static SYSRQ: IrqMutex<Option<SysrqFn>> = IrqMutex::new(None);
+/// Set when the keyboard acknowledged its reset at init.
+static PRESENT: AtomicBool = AtomicBool::new(false);
 
 const KEY_PAGE_UP: u8 = 0x80;
 const KEY_PAGE_DOWN: u8 = 0x81;
@@ -71,27 +127,18 @@ const KEY_SHIFT_RIGHT: u8 = 0x95;
 const KEY_SHIFT_HOME: u8 = 0x96;
 const KEY_SHIFT_END: u8 = 0x97;
 
-const SCANCODE_LETTERS: [u8; 0x80] = [
-    0x00, 0x00, 0x31, 0x32, 0x33, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x30, 0x2D, 0x3D, 0x00, 0x09,
-    0x71, 0x77, 0x65, 0x72, 0x74, 0x79, 0x75, 0x69, 0x6F, 0x70, 0x5B, 0x5D, 0x00, 0x00, 0x61, 0x73,
-    0x64, 0x66, 0x67, 0x68, 0x6A, 0x6B, 0x6C, 0x3B, 0x27, 0x60, 0x00, 0x5C, 0x7A, 0x78, 0x63, 0x76,
-    0x62, 0x6E, 0x6D, 0x2C, 0x2E, 0x2F, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
-
-const SCANCODE_SHIFTED: [u8; 0x80] = [
-    0x00, 0x00, 0x21, 0x40, 0x23, 0x24, 0x25, 0x5E, 0x26, 0x2A, 0x28, 0x29, 0x5F, 0x2B, 0x00, 0x00,
-    0x51, 0x57, 0x45, 0x52, 0x54, 0x59, 0x55, 0x49, 0x4F, 0x50, 0x7B, 0x7D, 0x00, 0x00, 0x41, 0x53,
-    0x44, 0x46, 0x47, 0x48, 0x4A, 0x4B, 0x4C, 0x3A, 0x22, 0x7E, 0x00, 0x7C, 0x5A, 0x58, 0x43, 0x56,
-    0x42, 0x4E, 0x4D, 0x3C, 0x3E, 0x3F, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-];
+/// What Alt+PrintScreen sends, without the 0xE0 prefix PrintScreen has.
+const SCANCODE_SYSRQ: u8 = 0x54;
+
+/// Called from the keyboard interrupt with the make code of a key pressed
+/// while Alt+SysRq is held.
+pub type SysrqFn = fn(u8);
+
+/// Install the handler for Alt+SysRq+<key>.  Without one those keys type
+/// as usual.
+pub fn keyboard_register_sysrq(handler: SysrqFn) {
+    *SYSRQ.lock() = Some(handler);
+}
 
 #[inline(always)]
 fn is_break_code(scancode: u8) -> bool {
@@ -103,64 +150,51 @@ fn get_make_code(scancode: u8) -> u8 {
     scancode & 0x7F
 }
 
-fn translate_letter(make_code: u8, modifiers: &ModifierState) -> u8 {
-    let shift = modifiers.is_shift();
-    let caps = modifiers.caps_lock;
-
-    if shift && (make_code as usize) < SCANCODE_SHIFTED.len() {
-        let shifted = SCANCODE_SHIFTED[make_code as usize];
-        if shifted != 0 {
-            return shifted;
-        }
-    }
-
-    if (make_code as usize) < SCANCODE_LETTERS.len() {
-        let base_char = SCANCODE_LETTERS[make_code as usize];
-        if base_char != 0 {
-            if (b'a'..=b'z').contains(&base_char) {
-                let should_uppercase = shift ^ caps;
-                if should_uppercase {
-                    return base_char - 0x20;
-                }
-            }
-            return base_char;
-        }
-    }
-    0
-}
-
-fn translate_scancode(scancode: u8, modifiers: &ModifierState) -> u8 {
-    let make_code = get_make_code(scancode);
-    match make_code {
-        0x1C => b'\n',
-        0x0E => b'\x08',
-        0x39 => b' ',
-        0x0F => b'\t',
-        0x01 => 0x1B,
-        _ => {
-            let ch = translate_letter(make_code, modifiers);
-            // Ctrl+letter → control code (0x01–0x1A)
-            if modifiers.ctrl_left && ch != 0 {
-                let lower = if (b'A'..=b'Z').contains(&ch) {
-                    ch + 0x20
-                } else {
-                    ch
-                };
-                if (b'a'..=b'z').contains(&lower) {
-                    return lower - b'a' + 1;
-                }
-            }
-            ch
+/// Translate a key press into the characters to type.
+///
+/// Control keys bypass the layout; Escape and Backspace also drop a pending
+/// dead key.  With Ctrl held, letters become control codes (0x01–0x1A)
+/// without going through dead-key composition.
+fn translate_press(
+    make_code: u8,
+    modifiers: &ModifierState,
+    dead_key: &mut DeadKeyState,
+) -> Composed {
+    let sym = match make_code {
+        0x1C => KeySym::Char('\n'),
+        0x0F => KeySym::Char('\t'),
+        0x39 => KeySym::Char(' '),
+        0x0E | 0x01 => {
+            dead_key.cancel();
+            return Composed::single(if make_code == 0x01 { '\x1B' } else { '\x08' });
         }
+        _ => match keymap::active_keymap().lookup(make_code, modifiers.level()) {
+            Some(sym) => sym,
+            None => return Composed::none(),
+        },
+    };
+
+    if modifiers.is_ctrl()
+        && let KeySym::Char(ch) = sym
+        && ch.is_ascii_alphabetic()
+    {
+        dead_key.cancel();
+        let lower = ch.to_ascii_lowercase() as u8;
+        return Composed::single((lower - b'a' + 1) as char);
     }
+    dead_key.feed(sym)
 }
 
-fn handle_modifier(modifiers: &mut ModifierState, make_code: u8, is_press: bool) {
+fn handle_modifier(modifiers: &mut ModifierState, make_code: u8, is_press: bool, extended: bool) {
     match make_code {
         0x2A => modifiers.shift_left = is_press,
         0x36 => modifiers.shift_right = is_press,
+        0x1D if extended => modifiers.ctrl_right = is_press,
         0x1D => modifiers.ctrl_left = is_press,
+        0x38 if extended => modifiers.altgr = is_press,
         0x38 => modifiers.alt_left = is_press,
+        0x5B if extended => modifiers.super_left = is_press,
+        0x5C if extended => modifiers.super_right = is_press,
         0x3A => {
             if is_press {
                 modifiers.caps_lock = !modifiers.caps_lock;
@@ -177,6 +211,7 @@ pub fn init() {
     if ps2::wait_data() {
         let response = ps2::read_data_nowait();
         if response == ps2::DEV_ACK {
+            PRESENT.store(true, Ordering::Relaxed);
             if ps2::wait_data() {
                 let test_result = ps2::read_data_nowait();
                 if test_result != ps2::DEV_SELF_TEST_PASS {
@@ -195,8 +230,14 @@ pub fn init() {
     klog_info!("PS/2 keyboard: initialised");
 }
 
+/// Whether a keyboard answered at init.
+pub fn is_present() -> bool {
+    PRESENT.load(Ordering::Relaxed)
+}
+
 pub fn handle_scancode(scancode: u8) {
     klog_debug!("[KBD] Scancode: 0x{:02x}", scancode);
+    random::random_add_interrupt_timing(scancode as u64);
 
     let mut state = STATE.lock();
 
@@ -217,18 +258,55 @@ pub fn handle_scancode(scancode: u8) {
     state.scancode_buffer.push_overwrite(scancode);
 
     // Modifier keys: update state and return (no character to deliver).
-    if matches!(make_code, 0x2A | 0x36 | 0x1D | 0x38 | 0x3A) {
-        handle_modifier(&mut state.modifiers, make_code, is_press);
+    if matches!(make_code, 0x2A | 0x36 | 0x1D | 0x38 | 0x3A)
+        || (state.extended_code && matches!(make_code, 0x5B | 0x5C))
+    {
+        let extended = core::mem::take(&mut state.extended_code);
+        handle_modifier(&mut state.modifiers, make_code, is_press, extended);
+        return;
+    }
+
+    if make_code == SCANCODE_SYSRQ && !state.extended_code {
+        state.modifiers.sysrq = is_press && state.modifiers.is_alt();
         return;
     }
+    if state.modifiers.sysrq && is_press && !state.extended_code {
+        drop(state);
+        let handler = *SYSRQ.lock();
+        if let Some(handler) = handler {
+            handler(make_code);
+            return;
+        }
+        state = STATE.lock();
+    }
+
+    // With Super held, presses go to the window manager instead of the TTY.
+    if state.modifiers.is_super() && is_press {
+        drop(state);
+        if input_event::input_route_shortcut(make_code, 0, input_event::get_timestamp_ms()) {
+            STATE.lock().extended_code = false;
+            request_reschedule_from_interrupt();
+            return;
+        }
+        state = STATE.lock();
+    }
 
     // Extended keys (preceded by 0xE0).
     if state.extended_code {
         state.extended_code = false;
-        if !is_press {
+        let mods = state.modifiers.key_mods();
+        drop(state);
+        if input_event::input_route_key_event(
+            make_code,
+            0,
+            mods,
+            is_press,
+            input_event::get_timestamp_ms(),
+        ) || !is_press
+        {
             return;
         }
-        let shift = state.modifiers.is_shift();
+        let shift = mods & KEY_MOD_SHIFT != 0;
         let extended_key = match make_code {
             0x48 => KEY_UP,
             0x50 => KEY_DOWN,
@@ -266,43 +344,89 @@ pub fn handle_scancode(scancode: u8) {
             _ => 0,
         };
         if extended_key != 0 {
-            drop(state);
             push_input(active_tty(), extended_key);
             request_reschedule_from_interrupt();
         }
         return;
     }
 
+    let modifiers = state.modifiers;
     // Only key-press events produce characters.
     if !is_press {
+        drop(state);
+        input_event::input_route_key_event(
+            make_code,
+            0,
+            modifiers.key_mods(),
+            false,
+            input_event::get_timestamp_ms(),
+        );
         return;
     }
 
-    let ascii = translate_scancode(scancode, &state.modifiers);
-    klog_debug!("[KBD] ASCII: 0x{:02x}", ascii);
+    let typed = translate_press(make_code, &modifiers, &mut state.dead_key);
+    drop(state);
 
-    if ascii != 0 {
-        klog_debug!("[KBD] Adding to buffer");
-        drop(state);
-        push_input(active_tty(), ascii);
+    // A window that takes keys gets the first character typed, if any.
+    let ascii = typed
+        .as_slice()
+        .first()
+        .and_then(|&ch| keymap::tty_byte(ch))
+        .unwrap_or(0);
+    if input_event::input_route_key_event(
+        make_code,
+        ascii,
+        modifiers.key_mods(),
+        true,
+        input_event::get_timestamp_ms(),
+    ) {
+        return;
+    }
+
+    let mut delivered = false;
+    for &ch in typed.as_slice() {
+        match keymap::tty_byte(ch) {
+            Some(byte) => {
+                klog_debug!("[KBD] Char: 0x{:02x}", byte);
+                push_input(active_tty(), byte);
+                delivered = true;
+            }
+            None => klog_debug!("[KBD] U+{:04X} has no TTY encoding", ch as u32),
+        }
+    }
+    if delivered {
         request_reschedule_from_interrupt();
     }
 }
 
+/// Forget a half-typed accent (the layout changed under it).
+pub(super) fn cancel_dead_key() {
+    STATE.lock().dead_key.cancel();
+}
+
 pub fn get_scancode() -> u8 {
     STATE.lock().scancode_buffer.try_pop().unwrap_or(0)
 }
 
+/// Read a keyboard byte straight from the controller, bypassing the IRQ
+/// path.  Mouse bytes are read and dropped.  For use with interrupts off,
+/// e.g. on the panic screen.
+pub fn poll_scancode() -> Option<u8> {
+    if !ps2::has_data() {
+        return None;
+    }
+    let mouse = ps2::is_mouse_data();
+    let byte = ps2::read_data_nowait();
+    (!mouse).then_some(byte)
+}
+
 pub fn poll_wait_enter() {
     use slopos_lib::cpu;
     const ENTER_MAKE_CODE: u8 = 0x1C;
 
     loop {
-        if ps2::has_data() {
-            let scancode = ps2::read_data_nowait();
-            if scancode == ENTER_MAKE_CODE {
-                break;
-            }
+        if poll_scancode() == Some(ENTER_MAKE_CODE) {
+            break;
         }
         cpu::pause();
     }
@@ -0,0 +1,370 @@
+//! Keyboard layouts for scancode set 1.
+//!
+//! A [`Keymap`] covers the printable block of the keyboard: the number row,
+//! the three letter rows and the extra ISO key next to left shift.  Each row
+//! is written out as strings per shift level, so a layout reads roughly like
+//! the keycaps it describes.  Control keys (Enter, Tab, Backspace, Escape,
+//! Space) and the E0-prefixed navigation keys are layout-independent and stay
+//! in [`super::keyboard`].
+//!
+//! Dead keys are written as the matching Unicode combining mark (U+0300
+//! grave, U+0301 acute, ...).  [`DeadKeyState`] holds a pending accent and
+//! combines it with the next character: `^` then `e` gives `ê`, `^` then
+//! space gives a plain `^`, and `^` then `x` gives `^x`.
+//!
+//! The TTY is byte-oriented and bytes 0x80–0x9F are already used for cursor
+//! keys, so characters reach it as Latin-1 ([`tty_byte`]).  Layouts only
+//! contain symbols that fit; the AltGr euro sign is left out for that reason.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use slopos_abi::input::{KEYMAP_COUNT, KEYMAP_DE, KEYMAP_FR, KEYMAP_US, keymap_name};
+
+/// Position of the ISO 102nd key (`<>` on DE/FR boards).
+const ISO_KEY: u8 = 0x56;
+
+/// Marks "no symbol" at a position in a shifted or AltGr row.
+const HOLE: char = ' ';
+
+/// One contiguous run of make codes.
+struct KeyRow {
+    first: u8,
+    plain: &'static str,
+    shift: &'static str,
+    /// May be shorter than `plain`; missing positions have no AltGr symbol.
+    altgr: &'static str,
+}
+
+/// Shift level selected by the current modifiers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyLevel {
+    pub shift: bool,
+    pub caps_lock: bool,
+    pub altgr: bool,
+}
+
+/// What a key produces at a given level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeySym {
+    Char(char),
+    Dead(DeadKey),
+}
+
+/// Accents that can be typed ahead of a letter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeadKey {
+    Grave,
+    Acute,
+    Circumflex,
+    Tilde,
+    Diaeresis,
+}
+
+impl DeadKey {
+    fn from_combining(ch: char) -> Option<Self> {
+        match ch {
+            '\u{300}' => Some(Self::Grave),
+            '\u{301}' => Some(Self::Acute),
+            '\u{302}' => Some(Self::Circumflex),
+            '\u{303}' => Some(Self::Tilde),
+            '\u{308}' => Some(Self::Diaeresis),
+            _ => None,
+        }
+    }
+
+    /// The accent on its own, typed as dead key + space.
+    pub const fn spacing(self) -> char {
+        match self {
+            Self::Grave => '`',
+            Self::Acute => '´',
+            Self::Circumflex => '^',
+            Self::Tilde => '~',
+            Self::Diaeresis => '¨',
+        }
+    }
+
+    /// Base letters and their accented forms, position for position.
+    const fn table(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Grave => ("aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+            Self::Acute => ("aeiouyAEIOUY", "áéíóúýÁÉÍÓÚÝ"),
+            Self::Circumflex => ("aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+            Self::Tilde => ("anoANO", "ãñõÃÑÕ"),
+            Self::Diaeresis => ("aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+        }
+    }
+
+    /// Accented form of `base`, if this accent applies to it.
+    pub fn compose(self, base: char) -> Option<char> {
+        let (bases, composed) = self.table();
+        let idx = bases.chars().position(|c| c == base)?;
+        composed.chars().nth(idx)
+    }
+}
+
+/// A keyboard layout.
+pub struct Keymap {
+    pub id: u32,
+    rows: &'static [KeyRow],
+}
+
+impl Keymap {
+    /// Symbol for `make_code` at `level`, or `None` if the key has nothing
+    /// there (or is not part of the printable block).
+    pub fn lookup(&self, make_code: u8, level: KeyLevel) -> Option<KeySym> {
+        let row = self.rows.iter().find(|row| {
+            make_code >= row.first && ((make_code - row.first) as usize) < row.plain.chars().count()
+        })?;
+        let offset = (make_code - row.first) as usize;
+        let plain = row.plain.chars().nth(offset)?;
+
+        let ch = if level.altgr {
+            row.altgr.chars().nth(offset)?
+        } else {
+            let shifted = row.shift.chars().nth(offset);
+            // Caps Lock only shifts keys whose two levels are both letters,
+            // so AZERTY's é/2 key still types é.
+            let caps = level.caps_lock
+                && plain.is_alphabetic()
+                && shifted.is_some_and(char::is_alphabetic);
+            if level.shift ^ caps { shifted? } else { plain }
+        };
+        if ch == HOLE {
+            return None;
+        }
+        Some(match DeadKey::from_combining(ch) {
+            Some(dead) => KeySym::Dead(dead),
+            None => KeySym::Char(ch),
+        })
+    }
+}
+
+/// Up to two characters produced by one key press.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Composed {
+    chars: [char; 2],
+    len: usize,
+}
+
+impl Composed {
+    pub const fn none() -> Self {
+        Self {
+            chars: ['\0'; 2],
+            len: 0,
+        }
+    }
+
+    pub const fn single(ch: char) -> Self {
+        Self {
+            chars: [ch, '\0'],
+            len: 1,
+        }
+    }
+
+    const fn two(first: char, second: char) -> Self {
+        Self {
+            chars: [first, second],
+            len: 2,
+        }
+    }
+
+    pub fn as_slice(&self) -> &[char] {
+        &self.chars[..self.len]
+    }
+}
+
+/// Pending dead key between two presses.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeadKeyState {
+    pending: Option<DeadKey>,
+}
+
+impl DeadKeyState {
+    pub const fn new() -> Self {
+        Self { pending: None }
+    }
+
+    pub fn pending(&self) -> Option<DeadKey> {
+        self.pending
+    }
+
+    /// Drop a pending accent without output (Escape, Backspace, layout change).
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    /// Feed one key symbol and return what should be typed.
+    pub fn feed(&mut self, sym: KeySym) -> Composed {
+        match (self.pending.take(), sym) {
+            (None, KeySym::Char(ch)) => Composed::single(ch),
+            (None, KeySym::Dead(dead)) => {
+                self.pending = Some(dead);
+                Composed::none()
+            }
+            // Pressing the same accent twice types it once.
+            (Some(prev), KeySym::Dead(dead)) if prev == dead => Composed::single(prev.spacing()),
+            (Some(prev), KeySym::Dead(dead)) => {
+                self.pending = Some(dead);
+                Composed::single(prev.spacing())
+            }
+            (Some(prev), KeySym::Char(' ')) => Composed::single(prev.spacing()),
+            (Some(prev), KeySym::Char(ch)) => match prev.compose(ch) {
+                Some(composed) => Composed::single(composed),
+                None => Composed::two(prev.spacing(), ch),
+            },
+        }
+    }
+}
+
+/// Encode a character for the TTY: ASCII and the printable Latin-1 range
+/// map to their code point; anything else cannot be typed.
+pub fn tty_byte(ch: char) -> Option<u8> {
+    match ch as u32 {
+        c @ (0x00..=0x7F | 0xA0..=0xFF) => Some(c as u8),
+        _ => None,
+    }
+}
+
+static KEYMAP_US_LAYOUT: Keymap = Keymap {
+    id: KEYMAP_US,
+    rows: &[
+        KeyRow {
+            first: 0x02,
+            plain: "1234567890-=",
+            shift: "!@#$%^&*()_+",
+            altgr: "",
+        },
+        KeyRow {
+            first: 0x10,
+            plain: "qwertyuiop[]",
+            shift: "QWERTYUIOP{}",
+            altgr: "",
+        },
+        KeyRow {
+            first: 0x1E,
+            plain: "asdfghjkl;'`",
+            shift: "ASDFGHJKL:\"~",
+            altgr: "",
+        },
+        KeyRow {
+            first: 0x2B,
+            plain: "\\zxcvbnm,./",
+            shift: "|ZXCVBNM<>?",
+            altgr: "",
+        },
+        KeyRow {
+            first: ISO_KEY,
+            plain: "\\",
+            shift: "|",
+            altgr: "",
+        },
+    ],
+};
+
+static KEYMAP_DE_LAYOUT: Keymap = Keymap {
+    id: KEYMAP_DE,
+    rows: &[
+        KeyRow {
+            first: 0x02,
+            plain: "1234567890ß\u{301}",
+            shift: "!\"§$%&/()=?\u{300}",
+            altgr: " ²³   {[]}\\",
+        },
+        KeyRow {
+            first: 0x10,
+            plain: "qwertzuiopü+",
+            shift: "QWERTZUIOPÜ*",
+            altgr: "@          ~",
+        },
+        KeyRow {
+            first: 0x1E,
+            plain: "asdfghjklöä\u{302}",
+            shift: "ASDFGHJKLÖÄ°",
+            altgr: "",
+        },
+        KeyRow {
+            first: 0x2B,
+            plain: "#yxcvbnm,.-",
+            shift: "'YXCVBNM;:_",
+            altgr: "       µ",
+        },
+        KeyRow {
+            first: ISO_KEY,
+            plain: "<",
+            shift: ">",
+            altgr: "|",
+        },
+    ],
+};
+
+static KEYMAP_FR_LAYOUT: Keymap = Keymap {
+    id: KEYMAP_FR,
+    rows: &[
+        KeyRow {
+            first: 0x02,
+            plain: "&é\"'(-è_çà)=",
+            shift: "1234567890°+",
+            altgr: " \u{303}#{[|\u{300}\\^@]}",
+        },
+        KeyRow {
+            first: 0x10,
+            plain: "azertyuiop\u{302}$",
+            shift: "AZERTYUIOP\u{308}£",
+            altgr: "",
+        },
+        KeyRow {
+            first: 0x1E,
+            plain: "qsdfghjklmù²",
+            shift: "QSDFGHJKLM%",
+            altgr: "",
+        },
+        KeyRow {
+            first: 0x2B,
+            plain: "*wxcvbn,;:!",
+            shift: "µWXCVBN?./§",
+            altgr: "",
+        },
+        KeyRow {
+            first: ISO_KEY,
+            plain: "<",
+            shift: ">",
+            altgr: "",
+        },
+    ],
+};
+
+static KEYMAPS: [&Keymap; KEYMAP_COUNT as usize] =
+    [&KEYMAP_US_LAYOUT, &KEYMAP_DE_LAYOUT, &KEYMAP_FR_LAYOUT];
+
+static ACTIVE_KEYMAP: AtomicU32 = AtomicU32::new(KEYMAP_US);
+
+/// Built-in layout by `KEYMAP_*` ID.
+pub fn keymap(id: u32) -> Option<&'static Keymap> {
+    KEYMAPS.get(id as usize).copied()
+}
+
+/// Layout used to translate key presses.
+pub fn active_keymap() -> &'static Keymap {
+    keymap(ACTIVE_KEYMAP.load(Ordering::Acquire)).unwrap_or(&KEYMAP_US_LAYOUT)
+}
+
+/// Switch layouts.  Returns the previously active ID, or `None` (and leaves
+/// the layout alone) if `id` is unknown.
+pub fn set_active_keymap(id: u32) -> Option<u32> {
+    let map = keymap(id)?;
+    let prev = ACTIVE_KEYMAP.swap(map.id, Ordering::AcqRel);
+    if prev != map.id {
+        super::keyboard::cancel_dead_key();
+        slopos_lib::klog_info!(
+            "PS/2 keyboard: layout {} -> {}",
+            keymap_name(prev).unwrap_or("?"),
+            keymap_name(map.id).unwrap_or("?")
+        );
+    }
+    Some(prev)
+}
+
+/// ID of the active layout.
+pub fn active_keymap_id() -> u32 {
+    ACTIVE_KEYMAP.load(Ordering::Acquire)
+}
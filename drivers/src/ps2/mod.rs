@@ -44,9 +44,10 @@
 //! | 6   | TMOE | Timeout error |
 //! | 7   | PARE | Parity error |
 pub mod keyboard;
+pub mod keymap;
 pub mod mouse;
-use slopos_lib::cpu;
 use slopos_lib::ports::{PS2_COMMAND, PS2_DATA, PS2_STATUS};
+use slopos_lib::{boot_watchdog, cpu};
 use slopos_lib::{klog_debug, klog_info, klog_warn};
 
 // =============================================================================
@@ -92,6 +93,10 @@ pub const DEV_CMD_RESET: u8 = 0xFF;
 /// Set device defaults
 pub const DEV_CMD_DEFAULTS: u8 = 0xF6;
 pub const DEV_CMD_ENABLE: u8 = 0xF4;
+/// Set sample rate (mouse); followed by the rate byte
+pub const DEV_CMD_SET_SAMPLE_RATE: u8 = 0xF3;
+/// Report device ID (mouse); the ID byte follows the ACK
+pub const DEV_CMD_GET_ID: u8 = 0xF2;
 pub const DEV_CMD_DISABLE: u8 = 0xF5;
 pub const DEV_ACK: u8 = 0xFA;
 pub const DEV_RESEND: u8 = 0xFE;
@@ -136,8 +141,14 @@ pub fn is_busy() -> bool {
     read_status() & STATUS_INPUT_FULL != 0
 }
 /// Poll the status register until `condition` returns `true`, or timeout.
+///
+/// Gives up immediately once the boot watchdog has flagged the running init
+/// step, so a wedged controller can't stall boot one timeout at a time.
 #[inline(always)]
 fn wait_for_status(condition: fn() -> bool) -> bool {
+    if boot_watchdog::boot_watchdog_poll() {
+        return false;
+    }
     for _ in 0..WAIT_ITERATIONS {
         if condition() {
             return true;
@@ -1,3 +1,5 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use slopos_lib::{IrqMutex, klog_info};
 
 use crate::input_event::{self, get_timestamp_ms};
@@ -6,13 +8,101 @@ use crate::ps2;
 pub const BUTTON_LEFT: u8 = 0x01;
 pub const BUTTON_RIGHT: u8 = 0x02;
 pub const BUTTON_MIDDLE: u8 = 0x04;
+/// Fourth (side/back) button, IntelliMouse Explorer only.
+pub const BUTTON_SIDE: u8 = 0x08;
+/// Fifth (extra/forward) button, IntelliMouse Explorer only.
+pub const BUTTON_EXTRA: u8 = 0x10;
+
+const ALL_BUTTONS: [u8; 5] = [
+    BUTTON_LEFT,
+    BUTTON_RIGHT,
+    BUTTON_MIDDLE,
+    BUTTON_SIDE,
+    BUTTON_EXTRA,
+];
+
+/// Device IDs reported by `DEV_CMD_GET_ID`.
+pub const MOUSE_ID_STANDARD: u8 = 0x00;
+/// IntelliMouse: scroll wheel, 4-byte packets.
+pub const MOUSE_ID_INTELLIMOUSE: u8 = 0x03;
+/// IntelliMouse Explorer: scroll wheel plus buttons 4/5, 4-byte packets.
+pub const MOUSE_ID_EXPLORER: u8 = 0x04;
+
+/// Sample-rate sequences that unlock the extended protocols.
+const INTELLIMOUSE_MAGIC: [u8; 3] = [200, 100, 80];
+const EXPLORER_MAGIC: [u8; 3] = [200, 200, 80];
+/// Sample rate restored after negotiation (the PS/2 default).
+const DEFAULT_SAMPLE_RATE: u8 = 100;
+
+/// One decoded movement packet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MousePacket {
+    pub dx: i16,
+    /// Screen-oriented: positive moves down.
+    pub dy: i16,
+    /// Wheel detents; positive scrolls down.
+    pub dz: i8,
+    pub buttons: u8,
+}
+
+/// Packet length for a device ID: 4 bytes once a wheel is negotiated.
+pub const fn packet_len(device_id: u8) -> usize {
+    match device_id {
+        MOUSE_ID_INTELLIMOUSE | MOUSE_ID_EXPLORER => 4,
+        _ => 3,
+    }
+}
+
+/// Decode a complete packet for the given device ID.
+///
+/// Returns `None` when the overflow bits are set; such packets carry no
+/// usable movement.
+pub fn decode_packet(packet: &[u8], device_id: u8) -> Option<MousePacket> {
+    let flags = packet[0];
+    if flags & 0xC0 != 0 {
+        return None;
+    }
+
+    let mut dx = packet[1] as i16;
+    if flags & 0x10 != 0 {
+        dx -= 256;
+    }
+    let mut dy = packet[2] as i16;
+    if flags & 0x20 != 0 {
+        dy -= 256;
+    }
+
+    let mut decoded = MousePacket {
+        dx,
+        dy: -dy,
+        dz: 0,
+        buttons: flags & 0x07,
+    };
+    match device_id {
+        MOUSE_ID_INTELLIMOUSE => decoded.dz = packet[3] as i8,
+        MOUSE_ID_EXPLORER => {
+            let extra = packet[3];
+            // Low nibble is a 4-bit two's complement wheel delta.
+            decoded.dz = ((extra << 4) as i8) >> 4;
+            if extra & 0x10 != 0 {
+                decoded.buttons |= BUTTON_SIDE;
+            }
+            if extra & 0x20 != 0 {
+                decoded.buttons |= BUTTON_EXTRA;
+            }
+        }
+        _ => {}
+    }
+    Some(decoded)
+}
 
 struct MouseState {
     x: i32,
     y: i32,
     buttons: u8,
+    device_id: u8,
     packet_byte: u8,
-    packet: [u8; 3],
+    packet: [u8; 4],
     max_x: i32,
     max_y: i32,
 }
@@ -23,8 +113,9 @@ impl MouseState {
             x: 0,
             y: 0,
             buttons: 0,
+            device_id: MOUSE_ID_STANDARD,
             packet_byte: 0,
-            packet: [0; 3],
+            packet: [0; 4],
             max_x: 1,
             max_y: 1,
         }
@@ -32,18 +123,61 @@ impl MouseState {
 }
 
 static STATE: IrqMutex<MouseState> = IrqMutex::new(MouseState::new());
+/// Set when the mouse acknowledged its defaults command at init.
+static PRESENT: AtomicBool = AtomicBool::new(false);
+
+fn set_sample_rate(rate: u8) -> bool {
+    ps2::write_aux_acked(ps2::DEV_CMD_SET_SAMPLE_RATE) && ps2::write_aux_acked(rate)
+}
+
+fn read_device_id() -> Option<u8> {
+    if !ps2::write_aux_acked(ps2::DEV_CMD_GET_ID) {
+        return None;
+    }
+    ps2::read_aux_data()
+}
+
+/// Play a sample-rate sequence and return the ID the mouse reports after it.
+fn knock(sequence: &[u8; 3]) -> Option<u8> {
+    for &rate in sequence {
+        if !set_sample_rate(rate) {
+            return None;
+        }
+    }
+    read_device_id()
+}
+
+/// Unlock the wheel (and, if present, buttons 4/5) with the IntelliMouse
+/// sample-rate sequences.  Mice that don't understand them keep reporting
+/// ID 0 and stay on 3-byte packets.
+fn negotiate_protocol() -> u8 {
+    let mut id = MOUSE_ID_STANDARD;
+    if knock(&INTELLIMOUSE_MAGIC) == Some(MOUSE_ID_INTELLIMOUSE) {
+        id = MOUSE_ID_INTELLIMOUSE;
+        if knock(&EXPLORER_MAGIC) == Some(MOUSE_ID_EXPLORER) {
+            id = MOUSE_ID_EXPLORER;
+        }
+    }
+    set_sample_rate(DEFAULT_SAMPLE_RATE);
+    id
+}
 
 /// Initialise the PS/2 mouse device.
 ///
 /// Expects that `ps2::init_controller()` has already run (ports enabled,
-/// clean config written with IRQs off).  Sends set-defaults and enable-
-/// reporting commands via the AUX-aware ACK path so we never accidentally
-/// consume a keyboard byte as a mouse ACK.
+/// clean config written with IRQs off).  Sends set-defaults, negotiates the
+/// IntelliMouse protocol, then enables reporting, all via the AUX-aware ACK
+/// path so we never accidentally consume a keyboard byte as a mouse ACK.
 pub fn init() {
     klog_info!("PS/2 mouse: initialising device");
 
     // Set defaults (sample rate, resolution, scaling)
-    ps2::write_aux_acked(ps2::DEV_CMD_DEFAULTS);
+    PRESENT.store(
+        ps2::write_aux_acked(ps2::DEV_CMD_DEFAULTS),
+        Ordering::Relaxed,
+    );
+
+    let device_id = negotiate_protocol();
 
     // Enable data reporting
     ps2::write_aux_acked(ps2::DEV_CMD_ENABLE);
@@ -55,13 +189,20 @@ pub fn init() {
         let mut state = STATE.lock();
         state.x = state.max_x / 2;
         state.y = state.max_y / 2;
+        state.device_id = device_id;
         state.packet_byte = 0;
         (state.x, state.y)
     };
 
     input_event::input_route_pointer_motion(x, y, 0);
 
-    klog_info!("PS/2 mouse: initialised at ({}, {})", x, y);
+    klog_info!(
+        "PS/2 mouse: initialised at ({}, {}), id {} ({}-byte packets)",
+        x,
+        y,
+        device_id,
+        packet_len(device_id)
+    );
 }
 
 pub fn set_bounds(width: i32, height: i32) {
@@ -78,12 +219,13 @@ pub fn set_bounds(width: i32, height: i32) {
 
 /// Process a single mouse data byte from the IRQ handler.
 ///
-/// The byte is accumulated into a 3-byte packet.  Byte 0 is validated:
-/// bit 3 must be set (PS/2 protocol), and overflow bits (6:7) must be clear.
-/// Invalid byte-0 values reset the state machine.
+/// The byte is accumulated into a 3- or 4-byte packet depending on the
+/// negotiated protocol.  Byte 0 is validated: bit 3 must be set (PS/2
+/// protocol).  Invalid byte-0 values are discarded until a valid one
+/// arrives; packets with the overflow bits (6:7) set are dropped.
 pub fn handle_irq(data: u8) {
     let mut state = STATE.lock();
-    let byte_num = state.packet_byte;
+    let byte_num = state.packet_byte as usize;
 
     // PS/2 mouse packet byte 0 always has bit 3 set (per protocol).
     // If we're expecting byte 0 and bit 3 is clear, this isn't a valid
@@ -92,39 +234,35 @@ pub fn handle_irq(data: u8) {
         return;
     }
 
-    state.packet[byte_num as usize] = data;
-    state.packet_byte = (byte_num + 1) % 3;
+    let len = packet_len(state.device_id);
+    state.packet[byte_num] = data;
+    state.packet_byte = ((byte_num + 1) % len) as u8;
 
     if state.packet_byte != 0 {
         return;
     }
 
-    let packet_flags = state.packet[0];
-    let dx_raw = state.packet[1];
-    let dy_raw = state.packet[2];
-
-    // Overflow bits set — discard entire packet
-    if packet_flags & 0xC0 != 0 {
+    let Some(packet) = decode_packet(&state.packet[..len], state.device_id) else {
         return;
-    }
-
-    let old_buttons = state.buttons;
-    state.buttons = packet_flags & 0x07;
+    };
 
-    let mut dx = dx_raw as i16;
-    if packet_flags & 0x10 != 0 {
-        dx -= 256;
-    }
+    drop(state);
 
-    let mut dy = dy_raw as i16;
-    if packet_flags & 0x20 != 0 {
-        dy -= 256;
-    }
+    report_motion(packet);
+}
 
-    dy = -dy;
+/// Apply one decoded movement report to the shared pointer and route the
+/// resulting motion, scroll and button events.
+///
+/// Used by the PS/2 packet decoder and by USB HID boot mice, so both drive
+/// the same cursor.
+pub fn report_motion(packet: MousePacket) {
+    let mut state = STATE.lock();
+    let old_buttons = state.buttons;
+    state.buttons = packet.buttons;
 
-    state.x += dx as i32;
-    state.y += dy as i32;
+    state.x += packet.dx as i32;
+    state.y += packet.dy as i32;
 
     state.x = state.x.clamp(0, state.max_x - 1);
     state.y = state.y.clamp(0, state.max_y - 1);
@@ -137,12 +275,16 @@ pub fn handle_irq(data: u8) {
 
     let timestamp_ms = get_timestamp_ms();
 
-    if dx != 0 || dy != 0 {
+    if packet.dx != 0 || packet.dy != 0 {
         input_event::input_route_pointer_motion(final_x, final_y, timestamp_ms);
     }
 
+    if packet.dz != 0 {
+        input_event::input_route_pointer_scroll(0, packet.dz as i32, timestamp_ms);
+    }
+
     let button_changes = old_buttons ^ final_buttons;
-    for button_bit in [BUTTON_LEFT, BUTTON_RIGHT, BUTTON_MIDDLE] {
+    for button_bit in ALL_BUTTONS {
         if button_changes & button_bit != 0 {
             let pressed = final_buttons & button_bit != 0;
             input_event::input_route_pointer_button(button_bit, pressed, timestamp_ms);
@@ -158,3 +300,13 @@ pub fn get_position() -> (i32, i32) {
 pub fn get_buttons() -> u8 {
     STATE.lock().buttons
 }
+
+/// Whether a mouse answered at init.
+pub fn is_present() -> bool {
+    PRESENT.load(Ordering::Relaxed)
+}
+
+/// Device ID negotiated at init (`MOUSE_ID_*`).
+pub fn device_id() -> u8 {
+    STATE.lock().device_id
+}
@@ -0,0 +1,218 @@
+//! Keyboard layout tests: per-layout lookups, dead-key composition, and
+//! layout switching end to end through the active TTY.
+
+use slopos_abi::input::{KEYMAP_COUNT, KEYMAP_DE, KEYMAP_FR, KEYMAP_US};
+use slopos_abi::syscall::ICANON;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::ps2::keyboard;
+use crate::ps2::keymap::{
+    DeadKey, DeadKeyState, KeyLevel, KeySym, active_keymap_id, keymap, set_active_keymap, tty_byte,
+};
+use crate::tty::{self, TtyIndex};
+
+const PLAIN: KeyLevel = KeyLevel {
+    shift: false,
+    caps_lock: false,
+    altgr: false,
+};
+const SHIFT: KeyLevel = KeyLevel {
+    shift: true,
+    ..PLAIN
+};
+const CAPS: KeyLevel = KeyLevel {
+    caps_lock: true,
+    ..PLAIN
+};
+const ALTGR: KeyLevel = KeyLevel {
+    altgr: true,
+    ..PLAIN
+};
+
+fn lookup(id: u32, make_code: u8, level: KeyLevel) -> Option<KeySym> {
+    keymap(id)?.lookup(make_code, level)
+}
+
+/// Type `scancodes` on TTY 0 in raw mode and return what it read.
+fn type_on_tty(scancodes: &[u8], out: &mut [u8]) -> usize {
+    tty::table::tty_table_init();
+    tty::set_active_tty(TtyIndex(0));
+    let mut scratch = [0u8; 64];
+    while matches!(tty::read(TtyIndex(0), &mut scratch, true), Ok(n) if n > 0) {}
+
+    let Ok(saved) = tty::get_termios(TtyIndex(0)) else {
+        return 0;
+    };
+    let mut raw = saved;
+    raw.c_lflag &= !ICANON;
+    let _ = tty::set_termios(TtyIndex(0), &raw);
+
+    for &code in scancodes {
+        keyboard::handle_scancode(code);
+        keyboard::handle_scancode(code | 0x80);
+    }
+    let n = tty::read(TtyIndex(0), out, true).unwrap_or(0);
+    let _ = tty::set_termios(TtyIndex(0), &saved);
+    n
+}
+
+pub fn test_keymap_layouts_differ() -> TestResult {
+    assert_eq_test!(lookup(KEYMAP_US, 0x15, PLAIN), Some(KeySym::Char('y')));
+    assert_eq_test!(lookup(KEYMAP_DE, 0x15, PLAIN), Some(KeySym::Char('z')));
+    assert_eq_test!(lookup(KEYMAP_FR, 0x10, PLAIN), Some(KeySym::Char('a')));
+    assert_eq_test!(lookup(KEYMAP_FR, 0x32, PLAIN), Some(KeySym::Char(',')));
+    assert_eq_test!(lookup(KEYMAP_DE, 0x03, SHIFT), Some(KeySym::Char('"')));
+    assert_eq_test!(lookup(KEYMAP_DE, 0x28, SHIFT), Some(KeySym::Char('Ä')));
+    assert_eq_test!(lookup(KEYMAP_DE, 0x56, PLAIN), Some(KeySym::Char('<')));
+    assert_test!(keymap(KEYMAP_COUNT).is_none(), "out-of-range layout");
+    pass!()
+}
+
+pub fn test_keymap_altgr_and_caps_lock() -> TestResult {
+    assert_eq_test!(lookup(KEYMAP_DE, 0x10, ALTGR), Some(KeySym::Char('@')));
+    assert_eq_test!(lookup(KEYMAP_DE, 0x0C, ALTGR), Some(KeySym::Char('\\')));
+    assert_eq_test!(lookup(KEYMAP_FR, 0x0B, ALTGR), Some(KeySym::Char('@')));
+    assert_eq_test!(lookup(KEYMAP_DE, 0x02, ALTGR), None, "AltGr hole");
+    assert_eq_test!(lookup(KEYMAP_US, 0x10, ALTGR), None, "US has no AltGr");
+
+    assert_eq_test!(lookup(KEYMAP_DE, 0x27, CAPS), Some(KeySym::Char('Ö')));
+    // AZERTY é/2: Caps Lock must not turn it into a digit.
+    assert_eq_test!(lookup(KEYMAP_FR, 0x03, CAPS), Some(KeySym::Char('é')));
+    assert_eq_test!(lookup(KEYMAP_US, 0x02, CAPS), Some(KeySym::Char('1')));
+    pass!()
+}
+
+pub fn test_keymap_dead_keys_in_layouts() -> TestResult {
+    assert_eq_test!(
+        lookup(KEYMAP_DE, 0x0D, PLAIN),
+        Some(KeySym::Dead(DeadKey::Acute))
+    );
+    assert_eq_test!(
+        lookup(KEYMAP_DE, 0x0D, SHIFT),
+        Some(KeySym::Dead(DeadKey::Grave))
+    );
+    assert_eq_test!(
+        lookup(KEYMAP_FR, 0x1A, PLAIN),
+        Some(KeySym::Dead(DeadKey::Circumflex))
+    );
+    assert_eq_test!(
+        lookup(KEYMAP_FR, 0x1A, SHIFT),
+        Some(KeySym::Dead(DeadKey::Diaeresis))
+    );
+    assert_eq_test!(
+        lookup(KEYMAP_FR, 0x03, ALTGR),
+        Some(KeySym::Dead(DeadKey::Tilde))
+    );
+    pass!()
+}
+
+/// Feed `sym` and check that exactly `expected` is typed.
+fn types(dead: &mut DeadKeyState, sym: KeySym, expected: &[char]) -> bool {
+    dead.feed(sym).as_slice() == expected
+}
+
+pub fn test_dead_key_composition() -> TestResult {
+    let mut dead = DeadKeyState::new();
+    assert_test!(
+        types(&mut dead, KeySym::Dead(DeadKey::Circumflex), &[]),
+        "dead key typed immediately"
+    );
+    assert_eq_test!(dead.pending(), Some(DeadKey::Circumflex));
+    assert_test!(types(&mut dead, KeySym::Char('e'), &['ê']), "^ e");
+    assert_eq_test!(dead.pending(), None, "accent not consumed");
+
+    dead.feed(KeySym::Dead(DeadKey::Diaeresis));
+    assert_test!(types(&mut dead, KeySym::Char('U'), &['Ü']), "¨ U");
+
+    dead.feed(KeySym::Dead(DeadKey::Acute));
+    assert_test!(types(&mut dead, KeySym::Char(' '), &['´']), "´ space");
+
+    dead.feed(KeySym::Dead(DeadKey::Circumflex));
+    assert_test!(
+        types(&mut dead, KeySym::Dead(DeadKey::Circumflex), &['^']),
+        "double press"
+    );
+
+    dead.feed(KeySym::Dead(DeadKey::Grave));
+    assert_test!(
+        types(&mut dead, KeySym::Char('x'), &['`', 'x']),
+        "non-composing letter"
+    );
+
+    dead.feed(KeySym::Dead(DeadKey::Tilde));
+    dead.cancel();
+    assert_test!(types(&mut dead, KeySym::Char('n'), &['n']), "cancelled");
+    pass!()
+}
+
+pub fn test_tty_byte_is_latin1() -> TestResult {
+    assert_eq_test!(tty_byte('a'), Some(b'a'));
+    assert_eq_test!(tty_byte('é'), Some(0xE9));
+    assert_eq_test!(tty_byte('ß'), Some(0xDF));
+    assert_eq_test!(tty_byte('€'), None, "outside Latin-1");
+    assert_eq_test!(tty_byte('\u{85}'), None, "C1 control");
+    pass!()
+}
+
+pub fn test_set_active_keymap_rejects_unknown() -> TestResult {
+    let start = active_keymap_id();
+    assert_eq_test!(set_active_keymap(KEYMAP_FR), Some(start));
+    assert_eq_test!(set_active_keymap(KEYMAP_COUNT), None);
+    assert_eq_test!(active_keymap_id(), KEYMAP_FR, "unknown ID changed layout");
+    assert_eq_test!(set_active_keymap(start), Some(KEYMAP_FR));
+    pass!()
+}
+
+pub fn test_layout_switch_reaches_tty() -> TestResult {
+    let Some(prev) = set_active_keymap(KEYMAP_DE) else {
+        return fail!("DE layout missing");
+    };
+    // z (QWERTZ), then dead acute + e.
+    let mut out = [0u8; 8];
+    let n = type_on_tty(&[0x15, 0x0D, 0x12], &mut out);
+    set_active_keymap(prev);
+
+    assert_eq_test!(n, 2, "bytes typed");
+    assert_eq_test!(out[0], b'z');
+    assert_eq_test!(out[1], 0xE9, "dead acute + e");
+    pass!()
+}
+
+pub fn test_altgr_needs_extended_prefix() -> TestResult {
+    let Some(prev) = set_active_keymap(KEYMAP_DE) else {
+        return fail!("DE layout missing");
+    };
+    let mut out = [0u8; 8];
+    // Right Alt (E0 38) + q types @; left Alt (38) + q stays q.
+    keyboard::handle_scancode(0xE0);
+    keyboard::handle_scancode(0x38);
+    let altgr = type_on_tty(&[0x10], &mut out);
+    let altgr_byte = out[0];
+    keyboard::handle_scancode(0xE0);
+    keyboard::handle_scancode(0xB8);
+
+    keyboard::handle_scancode(0x38);
+    let alt = type_on_tty(&[0x10], &mut out);
+    let alt_byte = out[0];
+    keyboard::handle_scancode(0xB8);
+    set_active_keymap(prev);
+
+    assert_eq_test!((altgr, altgr_byte), (1, b'@'), "AltGr+q");
+    assert_eq_test!((alt, alt_byte), (1, b'q'), "left Alt+q");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    ps2_keymap,
+    [
+        test_keymap_layouts_differ,
+        test_keymap_altgr_and_caps_lock,
+        test_keymap_dead_keys_in_layouts,
+        test_dead_key_composition,
+        test_tty_byte_is_latin1,
+        test_set_active_keymap_rejects_unknown,
+        test_layout_switch_reaches_tty,
+        test_altgr_needs_extended_prefix,
+    ]
+);
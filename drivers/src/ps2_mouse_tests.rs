@@ -0,0 +1,84 @@
+//! PS/2 mouse packet decoding tests (3-byte, IntelliMouse, Explorer).
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::input_event::{self, InputEvent, InputEventType};
+use crate::ps2::mouse::{
+    BUTTON_EXTRA, BUTTON_LEFT, BUTTON_SIDE, MOUSE_ID_EXPLORER, MOUSE_ID_INTELLIMOUSE,
+    MOUSE_ID_STANDARD, MousePacket, decode_packet, packet_len,
+};
+
+pub fn test_mouse_packet_len_follows_device_id() -> TestResult {
+    assert_eq_test!(packet_len(MOUSE_ID_STANDARD), 3);
+    assert_eq_test!(packet_len(MOUSE_ID_INTELLIMOUSE), 4);
+    assert_eq_test!(packet_len(MOUSE_ID_EXPLORER), 4);
+    pass!()
+}
+
+pub fn test_mouse_decode_standard_packet() -> TestResult {
+    // Left button, dx = -2 (X sign bit), dy = +5 (up on the device).
+    let decoded = decode_packet(&[0x19, 0xFE, 0x05], MOUSE_ID_STANDARD);
+    assert_eq_test!(
+        decoded,
+        Some(MousePacket {
+            dx: -2,
+            dy: -5,
+            dz: 0,
+            buttons: BUTTON_LEFT,
+        })
+    );
+    assert_test!(
+        decode_packet(&[0x48, 0x00, 0x00], MOUSE_ID_STANDARD).is_none(),
+        "overflow packet decoded"
+    );
+    pass!()
+}
+
+pub fn test_mouse_decode_intellimouse_wheel() -> TestResult {
+    let Some(down) = decode_packet(&[0x08, 0, 0, 0x01], MOUSE_ID_INTELLIMOUSE) else {
+        return fail!("wheel packet rejected");
+    };
+    assert_eq_test!(down.dz, 1, "wheel down");
+    let Some(up) = decode_packet(&[0x08, 0, 0, 0xFF], MOUSE_ID_INTELLIMOUSE) else {
+        return fail!("wheel packet rejected");
+    };
+    assert_eq_test!(up.dz, -1, "wheel up");
+    assert_eq_test!(up.buttons, 0, "fourth byte leaked into buttons");
+    pass!()
+}
+
+pub fn test_mouse_decode_explorer_buttons() -> TestResult {
+    // Wheel -2 in the low nibble, both extra buttons held.
+    let Some(decoded) = decode_packet(&[0x08, 0, 0, 0x3E], MOUSE_ID_EXPLORER) else {
+        return fail!("explorer packet rejected");
+    };
+    assert_eq_test!(decoded.dz, -2, "4-bit wheel delta");
+    assert_eq_test!(decoded.buttons, BUTTON_SIDE | BUTTON_EXTRA, "buttons 4/5");
+    pass!()
+}
+
+pub fn test_scroll_event_reaches_device_queue() -> TestResult {
+    let mut drain = [InputEvent::default(); 64];
+    while input_event::input_device_read(&mut drain) == drain.len() {}
+
+    input_event::input_route_pointer_scroll(0, 3, 42);
+    let mut out = [InputEvent::default(); 1];
+    assert_eq_test!(input_event::input_device_read(&mut out), 1, "no event");
+    assert_eq_test!(out[0].event_type, InputEventType::PointerScroll);
+    assert_eq_test!(out[0].scroll_dx(), 0);
+    assert_eq_test!(out[0].scroll_dy(), 3);
+    assert_eq_test!(out[0].timestamp_ms, 42);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    ps2_mouse,
+    [
+        test_mouse_packet_len_follows_device_id,
+        test_mouse_decode_standard_packet,
+        test_mouse_decode_intellimouse_wheel,
+        test_mouse_decode_explorer_buttons,
+        test_scroll_event_reaches_device_queue,
+    ]
+);
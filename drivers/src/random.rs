@@ -1,38 +1,283 @@
-use slopos_lib::tsc;
-use slopos_lib::{IrqMutex, OnceLock};
+//! Kernel random number generator.
+//!
+//! A ChaCha20 CSPRNG keyed from an entropy pool. Samples are mixed into the
+//! key as they arrive (RDSEED/RDRAND when CPUID reports them, boot-time
+//! TSC/HPET jitter, input interrupt timing, writes to `/dev/random`) and
+//! credited conservatively against a 256-bit pool. Once
+//! [`SEED_THRESHOLD_BITS`] have been credited the generator is considered
+//! seeded; it never becomes "unseeded" again.
+//!
+//! The CPU sources are never the only input: jitter is always gathered, so
+//! a machine without them (or with a backdoored one) still seeds from the
+//! timers, and every output request folds in one more RDRAND value without
+//! crediting it.
+//!
+//! Output uses fast key erasure: every request ends by replacing the key with
+//! fresh keystream, so a later key compromise cannot recover earlier output.
+//! Reads do not debit the entropy estimate — like modern Linux, a seeded
+//! ChaCha20 stream is as good as its key.
 
-const DEFAULT_LFSR_SEED: u64 = 0xACE1u64;
+use core::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Clone, Copy)]
-pub struct Lfsr64 {
-    state: u64,
+use slopos_lib::cpu::rand;
+use slopos_lib::kernel_services::platform::RngStats;
+use slopos_lib::{IrqMutex, WaitQueue, tsc};
+
+use crate::hpet;
+
+/// Size of the entropy pool, in bits (`/proc/sys/kernel/random/poolsize`).
+pub const POOL_BITS: u32 = 256;
+
+/// Credited entropy required before the generator counts as seeded.
+pub const SEED_THRESHOLD_BITS: u32 = 128;
+
+/// RDSEED (or, failing that, RDRAND) values gathered by [`random_init`].
+const ARCH_SEED_SAMPLES: u32 = 32;
+
+/// Bits credited per RDSEED value. The instruction promises full entropy;
+/// crediting a sixteenth of it leaves the timers to carry the rest.
+const RDSEED_BITS_PER_SAMPLE: u32 = 4;
+
+/// Bits credited per RDRAND value: DRBG output, so barely trusted.
+const RDRAND_BITS_PER_SAMPLE: u32 = 1;
+
+/// Number of timer-jitter samples gathered by [`random_init`].
+const BOOT_JITTER_SAMPLES: u32 = 512;
+
+/// Jitter samples per credited bit. Deliberately pessimistic: under
+/// emulation consecutive reads are far more predictable than on hardware.
+const JITTER_SAMPLES_PER_BIT: u32 = 4;
+
+const CHACHA_CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+#[inline(always)]
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
 }
 
-impl Lfsr64 {
-    pub fn with_seed(seed: u64) -> Self {
-        let s = if seed == 0 { DEFAULT_LFSR_SEED } else { seed };
-        Self { state: s }
+/// One 64-byte ChaCha20 block for `key` at block position `counter`.
+pub(crate) fn chacha20_block(key: &[u32; 8], counter: u64) -> [u8; 64] {
+    let mut input = [0u32; 16];
+    input[..4].copy_from_slice(&CHACHA_CONSTANTS);
+    input[4..12].copy_from_slice(key);
+    input[12] = counter as u32;
+    input[13] = (counter >> 32) as u32;
+
+    let mut state = input;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
     }
 
-    pub fn from_tsc() -> Self {
-        let seed = tsc::rdtsc() | 1;
-        Self::with_seed(seed)
+    let mut out = [0u8; 64];
+    for (i, word) in state.iter().enumerate() {
+        let word = word.wrapping_add(input[i]);
+        out[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
     }
+    out
+}
 
-    pub fn next(&mut self) -> u64 {
-        let mut x = self.state;
-        x ^= x << 13;
-        x ^= x >> 7;
-        x ^= x << 17;
-        self.state = if x == 0 { 0xfeedc0de } else { x };
-        self.state
+struct Crng {
+    key: [u32; 8],
+    counter: u64,
+    /// Next key word to receive mixed-in entropy.
+    mix_pos: usize,
+    entropy_bits: u32,
+    stats: RngStats,
+}
+
+impl Crng {
+    const fn new() -> Self {
+        Self {
+            key: [0; 8],
+            counter: 0,
+            mix_pos: 0,
+            entropy_bits: 0,
+            stats: RngStats {
+                seeded: false,
+                entropy_avail: 0,
+                poolsize: POOL_BITS,
+                bytes_generated: 0,
+                reseeds: 0,
+                entropy_added: 0,
+                unseeded_reads: 0,
+                arch_samples: 0,
+            },
+        }
+    }
+
+    fn next_block(&mut self) -> [u8; 64] {
+        let block = chacha20_block(&self.key, self.counter);
+        self.counter = self.counter.wrapping_add(1);
+        block
+    }
+
+    /// Replace the key with fresh keystream (fast key erasure).
+    fn rekey(&mut self) {
+        let block = self.next_block();
+        for (i, word) in self.key.iter_mut().enumerate() {
+            *word = u32::from_le_bytes([
+                block[i * 4],
+                block[i * 4 + 1],
+                block[i * 4 + 2],
+                block[i * 4 + 3],
+            ]);
+        }
+    }
+
+    /// Mix `sample` into the key and credit `bits` of entropy.
+    /// Returns `true` if this call completed initial seeding.
+    fn add_entropy(&mut self, sample: u64, bits: u32) -> bool {
+        let stamp = tsc::rdtsc();
+        self.key[self.mix_pos] ^= sample as u32 ^ (stamp >> 32) as u32;
+        self.key[self.mix_pos + 1] ^= (sample >> 32) as u32 ^ stamp as u32;
+        self.mix_pos = (self.mix_pos + 2) % self.key.len();
+        self.rekey();
+
+        self.entropy_bits = (self.entropy_bits + bits).min(POOL_BITS);
+        self.stats.entropy_added = self.stats.entropy_added.saturating_add(bits as u64);
+
+        if !self.stats.seeded && self.entropy_bits >= SEED_THRESHOLD_BITS {
+            self.stats.seeded = true;
+            self.stats.reseeds += 1;
+            return true;
+        }
+        false
+    }
+
+    fn fill(&mut self, dst: &mut [u8]) {
+        if !self.stats.seeded {
+            self.stats.unseeded_reads += 1;
+        }
+        if let Some(value) = rand::rdrand() {
+            self.add_entropy(value, 0);
+            self.stats.arch_samples += 1;
+        }
+        for chunk in dst.chunks_mut(64) {
+            let block = self.next_block();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.rekey();
+        self.stats.bytes_generated = self.stats.bytes_generated.saturating_add(dst.len() as u64);
     }
 }
 
-static RNG: OnceLock<IrqMutex<Lfsr64>> = OnceLock::new();
+static CRNG: IrqMutex<Crng> = IrqMutex::new(Crng::new());
+static SEEDED: AtomicBool = AtomicBool::new(false);
+static SEED_WAITERS: WaitQueue = WaitQueue::new();
+
+fn mark_seeded() {
+    SEEDED.store(true, Ordering::Release);
+    SEED_WAITERS.wake_all();
+}
+
+/// Mix an entropy sample into the pool, crediting `bits` of entropy.
+///
+/// Callers must not over-credit: a sample that an attacker could predict
+/// should be added with `bits == 0`.
+pub fn random_add_entropy(sample: u64, bits: u32) {
+    let completed = CRNG.lock().add_entropy(sample, bits);
+    if completed {
+        mark_seeded();
+    }
+}
+
+/// Feed the timestamp of a device interrupt (keyboard, mouse) to the pool.
+pub fn random_add_interrupt_timing(source: u64) {
+    random_add_entropy(tsc::rdtsc() ^ source.rotate_left(47), 1);
+}
+
+/// Mix RDSEED values into the pool, or RDRAND values if the CPU has no
+/// RDSEED. Returns the number of values mixed.
+fn add_arch_seed() -> u32 {
+    let (read, bits): (fn() -> Option<u64>, u32) = if rand::rdseed_supported() {
+        (rand::rdseed, RDSEED_BITS_PER_SAMPLE)
+    } else if rand::rdrand_supported() {
+        (rand::rdrand, RDRAND_BITS_PER_SAMPLE)
+    } else {
+        return 0;
+    };
+
+    let mut mixed = 0;
+    for _ in 0..ARCH_SEED_SAMPLES {
+        let Some(value) = read() else {
+            break;
+        };
+        random_add_entropy(value, bits);
+        mixed += 1;
+    }
+    CRNG.lock().stats.arch_samples += mixed as u64;
+    mixed
+}
+
+/// Seed the pool from the CPU's random instructions and from jitter between
+/// the TSC and the HPET main counter. Returns the number of CPU values
+/// mixed in.
+///
+/// Must run after HPET initialisation.
+pub fn random_init() -> u32 {
+    let arch = add_arch_seed();
+    let mut last = tsc::rdtsc();
+    let mut pending_bits = 0;
+    for i in 0..BOOT_JITTER_SAMPLES {
+        let now = tsc::rdtsc();
+        let sample = (now.wrapping_sub(last) << 32) ^ hpet::read_counter();
+        last = now;
+
+        pending_bits += 1;
+        let bits = if pending_bits == JITTER_SAMPLES_PER_BIT {
+            pending_bits = 0;
+            1
+        } else {
+            0
+        };
+        random_add_entropy(sample ^ i as u64, bits);
+    }
+    arch
+}
+
+/// Fill `dst` with CSPRNG output. Never blocks; output produced before
+/// seeding is counted in [`RngStats::unseeded_reads`].
+pub fn random_fill(dst: &mut [u8]) -> usize {
+    CRNG.lock().fill(dst);
+    dst.len()
+}
 
 pub fn random_next() -> u64 {
-    RNG.call_once(|| IrqMutex::new(Lfsr64::from_tsc()));
-    let rng = RNG.get().expect("RNG missing");
-    rng.lock().next()
+    let mut bytes = [0u8; 8];
+    random_fill(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+pub fn random_is_seeded() -> bool {
+    SEEDED.load(Ordering::Acquire)
+}
+
+/// Wait until the pool is seeded. With `nonblock`, only reports the state.
+pub fn random_wait_seeded(nonblock: bool) -> bool {
+    if random_is_seeded() || nonblock {
+        return random_is_seeded();
+    }
+    SEED_WAITERS.wait_event(random_is_seeded)
+}
+
+pub fn random_stats() -> RngStats {
+    let crng = CRNG.lock();
+    RngStats {
+        entropy_avail: crng.entropy_bits,
+        ..crng.stats
+    }
 }
@@ -0,0 +1,44 @@
+//! Kernel RNG tests: the ChaCha20 core against the RFC 8439 vector, and
+//! the CPU entropy sources feeding the pool when CPUID reports them.
+
+use slopos_lib::cpu::rand;
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+
+use crate::random::{self, chacha20_block};
+
+pub fn test_chacha20_rfc8439_vector() -> TestResult {
+    // RFC 8439 appendix A.1, test vector #1: all-zero key, nonce and counter.
+    const EXPECTED: [u8; 16] = [
+        0x76, 0xb8, 0xe0, 0xad, 0xa0, 0xf1, 0x3d, 0x90, 0x40, 0x5d, 0x6a, 0xe5, 0x53, 0x86, 0xbd,
+        0x28,
+    ];
+    let block = chacha20_block(&[0; 8], 0);
+    assert_eq_test!(&block[..16], &EXPECTED[..], "keystream mismatch");
+    pass!()
+}
+
+pub fn test_arch_sources_feed_pool() -> TestResult {
+    let before = random::random_stats().arch_samples;
+    let mut out = [0u8; 64];
+    random::random_fill(&mut out);
+    let after = random::random_stats().arch_samples;
+
+    if rand::rdrand_supported() {
+        assert_test!(rand::rdrand().is_some(), "RDRAND kept failing");
+        assert_test!(after > before, "output did not mix in RDRAND");
+    } else {
+        assert_eq_test!(after, before, "samples counted without RDRAND");
+    }
+    if rand::rdseed_supported() {
+        assert_test!(rand::rdseed().is_some(), "RDSEED kept failing");
+    }
+    assert_test!(random::random_is_seeded(), "pool not seeded after boot");
+    assert_test!(out.iter().any(|&b| b != 0), "all-zero output");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    random,
+    [test_chacha20_rfc8439_vector, test_arch_sources_feed_pool]
+);
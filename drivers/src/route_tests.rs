@@ -6,14 +6,18 @@
 //! - 3.T3: `RouteTable::lookup` with no routes returns `None`
 //! - 3.T4: Prefix-length bucketing: /24 beats /16 for matching address
 //! - 3.T5: Metric tie-breaking: lower metric wins within same prefix length
+//! - Route management: validation in `add_route`/`del_route` and listing
 
 extern crate alloc;
 
+use slopos_abi::net::{NET_ROUTE_DEV_AUTO, NetRoute};
 use slopos_lib::testing::TestResult;
 use slopos_lib::{assert_eq_test, assert_test, pass};
 
-use crate::net::route::{RouteEntry, RouteTable};
-use crate::net::types::{DevIndex, Ipv4Addr};
+use crate::net::route::{
+    MAX_ROUTES_PER_BUCKET, RouteEntry, RouteTable, add_route, del_route, list_routes,
+};
+use crate::net::types::{DevIndex, Ipv4Addr, NetError};
 
 // =============================================================================
 // Helpers
@@ -411,6 +415,169 @@ pub fn test_route_entry_next_hop() -> TestResult {
     pass!()
 }
 
+// =============================================================================
+// Route management (SYSCALL_NET_ROUTE)
+// =============================================================================
+
+/// Build a user route as the syscall would receive it.  Device 0 is the
+/// loopback interface, which is always registered.
+fn user_route(prefix: [u8; 4], prefix_len: u8, gateway: [u8; 4], dev: u8) -> NetRoute {
+    NetRoute {
+        prefix,
+        prefix_len,
+        gateway,
+        dev,
+        metric: 10,
+        ..NetRoute::default()
+    }
+}
+
+pub fn test_route_add_rejects_bad_routes() -> TestResult {
+    let table = fresh_table();
+    table.add(connected_route([10, 0, 0, 0], 24, 0, 0));
+
+    assert_eq_test!(
+        add_route(&table, &user_route([10, 1, 0, 1], 16, [0; 4], 0)),
+        Err(NetError::InvalidArgument),
+        "host bits set in prefix"
+    );
+    assert_eq_test!(
+        add_route(&table, &user_route([10, 1, 0, 0], 33, [0; 4], 0)),
+        Err(NetError::InvalidArgument),
+        "prefix longer than /32"
+    );
+    assert_eq_test!(
+        add_route(
+            &table,
+            &user_route([10, 1, 0, 0], 16, [0; 4], NET_ROUTE_DEV_AUTO)
+        ),
+        Err(NetError::InvalidArgument),
+        "connected route without an interface"
+    );
+    assert_eq_test!(
+        add_route(&table, &user_route([10, 1, 0, 0], 16, [0; 4], 200)),
+        Err(NetError::InvalidArgument),
+        "nonexistent interface"
+    );
+    assert_eq_test!(
+        add_route(
+            &table,
+            &user_route([0; 4], 0, [192, 168, 1, 1], NET_ROUTE_DEV_AUTO)
+        ),
+        Err(NetError::NetworkUnreachable),
+        "gateway off every connected subnet"
+    );
+    assert_eq_test!(
+        add_route(
+            &table,
+            &user_route([0; 4], 0, [255, 255, 255, 255], NET_ROUTE_DEV_AUTO)
+        ),
+        Err(NetError::InvalidArgument),
+        "broadcast gateway"
+    );
+    assert_eq_test!(table.route_count(), 1, "rejected routes were added");
+
+    pass!()
+}
+
+pub fn test_route_add_picks_gateway_interface() -> TestResult {
+    let table = fresh_table();
+    table.add(connected_route([10, 0, 0, 0], 24, 0, 0));
+
+    assert_eq_test!(
+        add_route(
+            &table,
+            &user_route([172, 16, 0, 0], 12, [10, 0, 0, 1], NET_ROUTE_DEV_AUTO)
+        ),
+        Ok(())
+    );
+    let result = table.lookup(Ipv4Addr([172, 20, 1, 2]));
+    assert_test!(result.is_some(), "added route not found");
+    let (dev, next_hop) = result.unwrap();
+    assert_eq_test!(dev, DevIndex(0), "interface taken from the connected route");
+    assert_eq_test!(next_hop.0, [10, 0, 0, 1], "next hop is the gateway");
+
+    // A gateway reached only through another gateway is not on-link.
+    assert_eq_test!(
+        add_route(
+            &table,
+            &user_route([192, 168, 0, 0], 16, [172, 16, 0, 9], NET_ROUTE_DEV_AUTO)
+        ),
+        Err(NetError::NetworkUnreachable),
+        "gateway behind a gateway"
+    );
+
+    pass!()
+}
+
+pub fn test_route_del_matches_interface() -> TestResult {
+    let table = fresh_table();
+    table.add(connected_route([10, 0, 0, 0], 24, 0, 0));
+    table.add(connected_route([10, 0, 0, 0], 24, 3, 5));
+
+    assert_eq_test!(
+        del_route(&table, &user_route([10, 0, 0, 0], 24, [0; 4], 1)),
+        Err(NetError::InvalidArgument),
+        "deleted a route on another interface"
+    );
+    assert_eq_test!(
+        del_route(&table, &user_route([10, 0, 0, 0], 24, [0; 4], 3)),
+        Ok(())
+    );
+    let result = table.lookup(Ipv4Addr([10, 0, 0, 7]));
+    assert_test!(result.is_some(), "remaining route lost");
+    assert_eq_test!(result.unwrap().0, DevIndex(0), "wrong route deleted");
+
+    assert_eq_test!(
+        del_route(
+            &table,
+            &user_route([10, 0, 0, 0], 24, [0; 4], NET_ROUTE_DEV_AUTO)
+        ),
+        Ok(())
+    );
+    assert_eq_test!(
+        del_route(
+            &table,
+            &user_route([10, 0, 0, 0], 24, [0; 4], NET_ROUTE_DEV_AUTO)
+        ),
+        Err(NetError::InvalidArgument),
+        "deleted a missing route"
+    );
+    assert_eq_test!(table.route_count(), 0);
+
+    pass!()
+}
+
+pub fn test_route_list_and_full_bucket() -> TestResult {
+    let table = fresh_table();
+    for i in 0..MAX_ROUTES_PER_BUCKET {
+        let entry = connected_route([10, i as u8, 0, 0], 16, 0, i as u32);
+        assert_eq_test!(table.try_add(entry), Ok(true));
+    }
+    assert_eq_test!(
+        table.try_add(connected_route([10, 99, 0, 0], 16, 0, 0)),
+        Err(NetError::NoBufferSpace),
+        "bucket overfilled"
+    );
+    assert_test!(
+        !table.add(connected_route([10, 99, 0, 0], 16, 0, 0)),
+        "add reported success on a full bucket"
+    );
+    table.add(gateway_route([0; 4], 0, [10, 0, 0, 1], 0, 7));
+
+    let mut out = [NetRoute::default(); 4];
+    assert_eq_test!(list_routes(&table, &mut out), 4, "truncated listing");
+    assert_eq_test!(out[0].prefix_len, 0, "default route listed first");
+    assert_eq_test!(out[0].gateway, [10, 0, 0, 1]);
+    assert_eq_test!(out[0].metric, 7);
+    assert_eq_test!(out[1].prefix, [10, 0, 0, 0], "lowest metric next");
+
+    let mut all = [NetRoute::default(); MAX_ROUTES_PER_BUCKET + 4];
+    assert_eq_test!(list_routes(&table, &mut all), MAX_ROUTES_PER_BUCKET + 1);
+
+    pass!()
+}
+
 // =============================================================================
 // Test suite registration
 // =============================================================================
@@ -438,5 +605,10 @@ slopos_lib::define_test_suite!(
         test_route_remove_device_routes,
         test_route_entry_matches,
         test_route_entry_next_hop,
+        // Route management
+        test_route_add_rejects_bad_routes,
+        test_route_add_picks_gateway_interface,
+        test_route_del_matches_interface,
+        test_route_list_and_full_bucket,
     ]
 );
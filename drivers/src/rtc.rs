@@ -0,0 +1,149 @@
+//! CMOS real-time clock (MC146818-compatible).
+//!
+//! Read once at boot to anchor [`slopos_lib::walltime`]; nothing here
+//! programs alarms or the periodic interrupt.  The RTC is assumed to keep
+//! UTC, which is what QEMU's default `-rtc base=utc` provides.
+
+use slopos_abi::time::CivilTime;
+use slopos_lib::ports::{CMOS_DATA, CMOS_INDEX};
+use slopos_lib::{klog_info, walltime};
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0A;
+const REG_STATUS_B: u8 = 0x0B;
+/// Century register on PC-compatible CMOS (ACPI FADT `century` is 0x32
+/// on every chipset SlopOS targets).
+const REG_CENTURY: u8 = 0x32;
+
+/// Status A: update in progress — registers are mid-rollover.
+const STATUS_A_UIP: u8 = 1 << 7;
+/// Status B: hours are 24-hour (clear means 12-hour with PM in bit 7).
+const STATUS_B_24H: u8 = 1 << 1;
+/// Status B: values are binary (clear means BCD).
+const STATUS_B_BINARY: u8 = 1 << 2;
+
+const HOUR_PM: u8 = 1 << 7;
+
+/// Upper bound on UIP polling; an update cycle lasts under 2 ms.
+const UIP_SPIN_LIMIT: u32 = 1_000_000;
+
+/// Disable NMI while the index register is latched.
+const CMOS_NMI_DISABLE: u8 = 1 << 7;
+
+fn cmos_read(reg: u8) -> u8 {
+    // SAFETY: ports 0x70/0x71 are the CMOS index/data pair on every PC.
+    unsafe {
+        CMOS_INDEX.write(CMOS_NMI_DISABLE | reg);
+        CMOS_DATA.read()
+    }
+}
+
+fn wait_update_done() -> bool {
+    for _ in 0..UIP_SPIN_LIMIT {
+        if cmos_read(REG_STATUS_A) & STATUS_A_UIP == 0 {
+            return true;
+        }
+        core::hint::spin_loop();
+    }
+    false
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct RawRegs {
+    second: u8,
+    minute: u8,
+    hour: u8,
+    day: u8,
+    month: u8,
+    year: u8,
+    century: u8,
+}
+
+fn read_raw() -> Option<RawRegs> {
+    if !wait_update_done() {
+        return None;
+    }
+    Some(RawRegs {
+        second: cmos_read(REG_SECONDS),
+        minute: cmos_read(REG_MINUTES),
+        hour: cmos_read(REG_HOURS),
+        day: cmos_read(REG_DAY),
+        month: cmos_read(REG_MONTH),
+        year: cmos_read(REG_YEAR),
+        century: cmos_read(REG_CENTURY),
+    })
+}
+
+const fn bcd_to_bin(v: u8) -> u8 {
+    (v & 0x0F) + (v >> 4) * 10
+}
+
+/// Normalise raw register values per status register B.
+fn decode(raw: RawRegs, status_b: u8) -> CivilTime {
+    let binary = status_b & STATUS_B_BINARY != 0;
+    let conv = |v: u8| if binary { v } else { bcd_to_bin(v) };
+
+    let pm = raw.hour & HOUR_PM != 0;
+    let mut hour = conv(raw.hour & !HOUR_PM);
+    if status_b & STATUS_B_24H == 0 {
+        // 12-hour mode: 12 AM is midnight, 12 PM is noon.
+        hour %= 12;
+        if pm {
+            hour += 12;
+        }
+    }
+
+    let century = match conv(raw.century) {
+        c @ 19..=21 => c as u32,
+        _ => 20,
+    };
+
+    CivilTime {
+        year: century * 100 + conv(raw.year) as u32,
+        month: conv(raw.month),
+        day: conv(raw.day),
+        hour,
+        minute: conv(raw.minute),
+        second: conv(raw.second),
+    }
+}
+
+/// Read the current time from the RTC.
+///
+/// Registers are read until two consecutive snapshots agree so a rollover
+/// between reads can't produce e.g. 12:59:00 from 12:59:59 -> 13:00:00.
+pub fn rtc_read() -> Option<CivilTime> {
+    let mut prev = read_raw()?;
+    for _ in 0..8 {
+        let next = read_raw()?;
+        if next == prev {
+            let time = decode(next, cmos_read(REG_STATUS_B));
+            return time.is_plausible().then_some(time);
+        }
+        prev = next;
+    }
+    None
+}
+
+/// Anchor kernel wall time from the RTC.  Returns the Unix time used.
+pub fn rtc_sync_walltime() -> Option<u64> {
+    let time = rtc_read()?;
+    let secs = time.to_unix_secs()?;
+    walltime::walltime_set(secs);
+    klog_info!(
+        "RTC: {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC (unix {})",
+        time.year,
+        time.month,
+        time.day,
+        time.hour,
+        time.minute,
+        time.second,
+        secs
+    );
+    Some(secs)
+}
@@ -0,0 +1,52 @@
+//! CMOS RTC and wall-clock tests.
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::walltime;
+use slopos_lib::{assert_test, fail, pass};
+
+use crate::{hpet, rtc};
+
+pub fn test_rtc_read_plausible() -> TestResult {
+    let Some(now) = rtc::rtc_read() else {
+        return fail!("RTC read failed");
+    };
+    assert_test!(now.year >= 2020, "implausible RTC year {}", now.year);
+    assert_test!(now.to_unix_secs().is_some(), "RTC time did not convert");
+    pass!()
+}
+
+pub fn test_walltime_tracks_rtc() -> TestResult {
+    assert_test!(walltime::walltime_is_set(), "boot never anchored walltime");
+    let Some(rtc_secs) = rtc::rtc_read().and_then(|t| t.to_unix_secs()) else {
+        return fail!("RTC read failed");
+    };
+    let wall = walltime::realtime_secs();
+    assert_test!(
+        wall.abs_diff(rtc_secs) <= 2,
+        "walltime {} drifted from RTC {}",
+        wall,
+        rtc_secs
+    );
+    pass!()
+}
+
+pub fn test_walltime_advances_with_monotonic() -> TestResult {
+    let before = walltime::realtime_ns();
+    hpet::delay_ms(20);
+    let after = walltime::realtime_ns();
+    assert_test!(
+        after.saturating_sub(before) >= 20_000_000,
+        "walltime advanced only {} ns over 20 ms",
+        after.saturating_sub(before)
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    rtc,
+    [
+        test_rtc_read_plausible,
+        test_walltime_tracks_rtc,
+        test_walltime_advances_with_monotonic,
+    ]
+);
@@ -5,8 +5,9 @@ use slopos_lib::RingBuffer;
 use slopos_lib::cpu;
 use slopos_lib::io::Port;
 use slopos_lib::ports::{
-    COM1, UART_FCR_14_BYTE_THRESHOLD as FCR_14_BYTE_THRESHOLD, UART_FCR_CLEAR_RX as FCR_CLEAR_RX,
-    UART_FCR_CLEAR_TX as FCR_CLEAR_TX, UART_FCR_ENABLE_FIFO as FCR_ENABLE_FIFO,
+    COM1, COM2, UART_FCR_14_BYTE_THRESHOLD as FCR_14_BYTE_THRESHOLD,
+    UART_FCR_CLEAR_RX as FCR_CLEAR_RX, UART_FCR_CLEAR_TX as FCR_CLEAR_TX,
+    UART_FCR_ENABLE_FIFO as FCR_ENABLE_FIFO, UART_IER_RX_AVAILABLE as IER_RX_AVAILABLE,
     UART_IIR_FIFO_ENABLED as IIR_FIFO_ENABLED, UART_IIR_FIFO_MASK as IIR_FIFO_MASK,
     UART_LCR_DLAB as LCR_DLAB, UART_LSR_DATA_READY as LSR_DATA_READY, UART_MCR_AUX2 as MCR_AUX2,
     UART_MCR_DTR as MCR_DTR, UART_MCR_RTS as MCR_RTS, UART_REG_IER as REG_IER,
@@ -92,11 +93,37 @@ pub fn init_port(base: u16) -> Result<UartCapabilities, ()> {
         let mut port = SERIAL.lock();
         unsafe { port.init() }
         Ok(port.capabilities())
+    } else if base == COM2.address() {
+        // COM2 is polled by its only user (the GDB stub), so nothing keeps
+        // its state.
+        let mut port = SerialPort::new(COM2);
+        unsafe { port.init() }
+        Ok(port.capabilities())
     } else {
         Err(())
     }
 }
 
+/// Whether a UART answers at `base`: its scratch register keeps what is
+/// written to it, where an empty slot reads back 0xFF.
+pub fn serial_port_present(base: u16) -> bool {
+    let scratch = Port::<u8>::new(base).offset(REG_SCR);
+    unsafe {
+        scratch.write(0x5A);
+        scratch.read() == 0x5A
+    }
+}
+
+/// Read a byte from the UART at `base` if one has arrived, without going
+/// through the input buffer.  For polled users with interrupts off.
+pub fn serial_poll_byte(base: u16) -> Option<u8> {
+    let port = Port::<u8>::new(base);
+    if unsafe { port.offset(REG_LSR).read() } & LSR_DATA_READY == 0 {
+        return None;
+    }
+    Some(unsafe { port.offset(REG_RBR).read() })
+}
+
 pub fn get_capabilities() -> UartCapabilities {
     SERIAL.lock().capabilities()
 }
@@ -130,6 +157,13 @@ pub fn serial_poll_receive(base: u16) {
     }
 }
 
+/// Raise IRQ 4 whenever COM1 has received data.  OUT2 is already set by
+/// `init`, so the interrupt reaches the IOAPIC once the line is unmasked.
+pub fn serial_enable_rx_interrupt() {
+    let port = SERIAL.lock();
+    unsafe { port.reg(REG_IER).write(IER_RX_AVAILABLE) };
+}
+
 pub fn serial_buffer_pending(port: u16) -> i32 {
     serial_poll_receive(port);
     let buf = INPUT_BUFFER.lock();
@@ -1,4 +1,5 @@
 use slopos_abi::net::{AF_INET, SOCK_DGRAM, SOCK_STREAM};
+use slopos_abi::syscall::{ERRNO_EADDRINUSE, IPPROTO_TCP, SO_REUSEADDR, SOL_SOCKET, TCP_NODELAY};
 use slopos_lib::testing::TestResult;
 use slopos_lib::{assert_eq_test, assert_test, fail, pass};
 
@@ -398,7 +399,9 @@ pub fn test_slab_socket_table_grows_and_enforces_max() -> TestResult {
 
     assert_eq_test!(table.capacity(), 4);
     assert_test!(
-        table.alloc(SocketInner::Raw(RawSocketInner)).is_none(),
+        table
+            .alloc(SocketInner::Raw(RawSocketInner::new(1)))
+            .is_none(),
         "allocation beyond max must fail"
     );
     pass!()
@@ -656,6 +659,64 @@ pub fn test_tcp_send_after_shutdown_wr_fails() -> TestResult {
     pass!()
 }
 
+pub fn test_tcp_shutdown_rd_acks_and_drops_data() -> TestResult {
+    reset();
+    let (sock, tcp_idx) = match connect_and_establish() {
+        Ok(v) => v,
+        Err(m) => return fail!("{}", m),
+    };
+    use slopos_abi::syscall::SHUT_RD;
+    assert_eq_test!(socket_shutdown(sock, SHUT_RD), 0);
+
+    let conn = tcp::tcp_get_connection(tcp_idx).unwrap();
+    let data_hdr = TcpHeader {
+        src_port: conn.tuple.remote_port,
+        dst_port: conn.tuple.local_port,
+        seq_num: conn.rcv_nxt,
+        ack_num: conn.snd_nxt,
+        data_offset: 5,
+        flags: TCP_FLAG_ACK,
+        window_size: 32768,
+        checksum: 0,
+        urgent_ptr: 0,
+    };
+    let result = tcp::tcp_input(
+        conn.tuple.remote_ip,
+        conn.tuple.local_ip,
+        &data_hdr,
+        &[],
+        b"ignored",
+        0,
+    );
+
+    assert_eq_test!(tcp::tcp_recv_available(tcp_idx), 0, "data buffered");
+    let Some(ack) = result.response else {
+        return fail!("discarded data not acknowledged");
+    };
+    assert_eq_test!(ack.ack_num, conn.rcv_nxt.wrapping_add(7), "ACK covers data");
+    pass!()
+}
+
+pub fn test_tcp_close_linger_zero_resets() -> TestResult {
+    reset();
+    let (sock, tcp_idx) = match connect_and_establish() {
+        Ok(v) => v,
+        Err(m) => return fail!("{}", m),
+    };
+    use slopos_abi::syscall::SO_LINGER;
+    let mut linger = [0u8; 8];
+    linger[..4].copy_from_slice(&1i32.to_ne_bytes());
+    assert_eq_test!(socket_setsockopt(sock, SOL_SOCKET, SO_LINGER, &linger), 0);
+
+    assert_eq_test!(socket_close(sock), 0);
+    assert_eq_test!(
+        tcp::tcp_get_state(tcp_idx),
+        None,
+        "zero linger should release the connection"
+    );
+    pass!()
+}
+
 pub fn test_tcp_listen_accept_incoming_syn() -> TestResult {
     reset();
     let listen_sock = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
@@ -718,6 +779,144 @@ pub fn test_tcp_listen_accept_incoming_syn() -> TestResult {
     pass!()
 }
 
+const EADDRINUSE: i32 = ERRNO_EADDRINUSE as i64 as i32;
+
+fn set_reuse_addr(sock: u32) -> i32 {
+    socket_setsockopt(sock, SOL_SOCKET, SO_REUSEADDR, &1i32.to_ne_bytes())
+}
+
+pub fn test_socket_bind_conflict_returns_eaddrinuse() -> TestResult {
+    reset();
+    let a = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    let b = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    let c = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    assert_eq_test!(socket_bind(a, [10, 0, 0, 1], 8080), 0);
+    assert_eq_test!(
+        socket_bind(b, [10, 0, 0, 1], 8080),
+        EADDRINUSE,
+        "same address"
+    );
+    assert_eq_test!(
+        socket_bind(c, [0, 0, 0, 0], 8080),
+        EADDRINUSE,
+        "wildcard over a specific address"
+    );
+    assert_eq_test!(
+        socket_bind(b, [10, 0, 0, 9], 8080),
+        0,
+        "distinct addresses share a port"
+    );
+    pass!()
+}
+
+pub fn test_socket_bind_reuse_addr_except_listener() -> TestResult {
+    reset();
+    let a = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    let b = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    let c = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    assert_eq_test!(socket_bind(a, [0, 0, 0, 0], 8080), 0);
+    assert_eq_test!(set_reuse_addr(b), 0);
+    assert_eq_test!(socket_bind(b, [0, 0, 0, 0], 8080), 0, "SO_REUSEADDR");
+
+    assert_eq_test!(socket_listen(a, 4), 0);
+    assert_eq_test!(set_reuse_addr(c), 0);
+    assert_eq_test!(
+        socket_bind(c, [0, 0, 0, 0], 8080),
+        EADDRINUSE,
+        "bound over a listener"
+    );
+    pass!()
+}
+
+pub fn test_socket_bind_ports_are_per_protocol() -> TestResult {
+    reset();
+    let tcp_sock = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    let udp_sock = socket_create(AF_INET, SOCK_DGRAM, 0) as u32;
+    let udp_dup = socket_create(AF_INET, SOCK_DGRAM, 0) as u32;
+    assert_eq_test!(socket_bind(tcp_sock, [0, 0, 0, 0], 5353), 0);
+    assert_eq_test!(
+        socket_bind(udp_sock, [0, 0, 0, 0], 5353),
+        0,
+        "UDP alongside TCP"
+    );
+    assert_eq_test!(
+        socket_bind(udp_dup, [10, 0, 0, 1], 5353),
+        EADDRINUSE,
+        "second UDP binding"
+    );
+    pass!()
+}
+
+pub fn test_socket_bind_port_zero_is_ephemeral() -> TestResult {
+    reset();
+    let a = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    let b = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    assert_eq_test!(socket_bind(a, [0, 0, 0, 0], 0), 0);
+    assert_eq_test!(socket_bind(b, [0, 0, 0, 0], 0), 0);
+    let (Some(sa), Some(sb)) = (socket_snapshot(a), socket_snapshot(b)) else {
+        return fail!("no snapshot");
+    };
+    let range =
+        EphemeralPortAllocator::EPHEMERAL_PORT_START..=EphemeralPortAllocator::EPHEMERAL_PORT_END;
+    assert_test!(
+        range.contains(&sa.local_port),
+        "port outside ephemeral range"
+    );
+    assert_test!(
+        range.contains(&sb.local_port),
+        "port outside ephemeral range"
+    );
+    assert_test!(
+        sa.local_port != sb.local_port,
+        "ephemeral port handed out twice"
+    );
+    pass!()
+}
+
+pub fn test_socket_connect_uses_bound_port() -> TestResult {
+    reset();
+    let sock = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    assert_eq_test!(socket_bind(sock, [0, 0, 0, 0], 4444), 0);
+    assert_eq_test!(socket_connect(sock, [10, 0, 0, 2], 80), 0);
+    let Some(conn) = socket_lookup_tcp_idx(sock).and_then(tcp::tcp_get_connection) else {
+        return fail!("no tcp conn");
+    };
+    assert_eq_test!(conn.tuple.local_port, 4444, "bound port ignored");
+
+    let other = socket_create(AF_INET, SOCK_STREAM, 0) as u32;
+    assert_eq_test!(
+        socket_bind(other, [0, 0, 0, 0], 4444),
+        EADDRINUSE,
+        "port of a connected socket"
+    );
+    pass!()
+}
+
+pub fn test_tcp_nodelay_controls_nagle() -> TestResult {
+    reset();
+    let (sock, tcp_idx) = match connect_and_establish() {
+        Ok(v) => v,
+        Err(m) => return fail!("{}", m),
+    };
+    let nagle = || tcp::tcp_get_connection(tcp_idx).is_some_and(|c| c.nagle);
+    assert_test!(nagle(), "connected socket without nagle");
+
+    let on: i32 = 1;
+    assert_eq_test!(
+        socket_setsockopt(sock, IPPROTO_TCP, TCP_NODELAY, &on.to_ne_bytes()),
+        0
+    );
+    assert_test!(!nagle(), "TCP_NODELAY left nagle on");
+
+    let off: i32 = 0;
+    assert_eq_test!(
+        socket_setsockopt(sock, IPPROTO_TCP, TCP_NODELAY, &off.to_ne_bytes()),
+        0
+    );
+    assert_test!(nagle(), "clearing TCP_NODELAY left nagle off");
+    pass!()
+}
+
 slopos_lib::define_test_suite!(
     socket,
     [
@@ -764,6 +963,14 @@ slopos_lib::define_test_suite!(
         test_tcp_shutdown_wr_transitions_to_fin_wait1,
         test_tcp_shutdown_wr_recv_still_works,
         test_tcp_send_after_shutdown_wr_fails,
+        test_tcp_shutdown_rd_acks_and_drops_data,
+        test_tcp_close_linger_zero_resets,
         test_tcp_listen_accept_incoming_syn,
+        test_socket_bind_conflict_returns_eaddrinuse,
+        test_socket_bind_reuse_addr_except_listener,
+        test_socket_bind_ports_are_per_protocol,
+        test_socket_bind_port_zero_is_ephemeral,
+        test_socket_connect_uses_bound_port,
+        test_tcp_nodelay_controls_nagle,
     ]
 );
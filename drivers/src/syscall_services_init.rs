@@ -1,4 +1,8 @@
+use slopos_lib::kernel_services::syscall_services::audio::{
+    AudioServices, register_audio_services,
+};
 use slopos_lib::kernel_services::syscall_services::dns::{DnsServices, register_dns_services};
+use slopos_lib::kernel_services::syscall_services::hw::{HwServices, register_hw_services};
 use slopos_lib::kernel_services::syscall_services::input::{
     InputServices, register_input_services,
 };
@@ -8,9 +12,12 @@ use slopos_lib::kernel_services::syscall_services::socket::{
 };
 use slopos_lib::kernel_services::syscall_services::tty::{TtyServices, register_tty_services};
 
+use slopos_abi::hw::HwDevice;
+
 use crate::{
-    input_event,
-    net::{dns, socket},
+    audio, hwinfo, input_event,
+    net::{dhcp, dns, filter, icmp, loopback, route, socket},
+    ps2::keymap,
     tty, virtio_net,
 };
 
@@ -18,7 +25,7 @@ use crate::{
 // Input services
 // =============================================================================
 //
-// Most fields point directly at the driver implementation.  The three adapters
+// Most fields point directly at the driver implementation.  The four adapters
 // below exist only because the driver returns a different type than the service
 // interface requires.
 
@@ -36,6 +43,20 @@ fn input_request_close_adapter(task_id: u32, timestamp_ms: u64) -> i32 {
     }
 }
 
+/// Adapter: driver returns `bool`, service expects `i32` (0 = ok, -1 = fail).
+fn input_request_configure_adapter(
+    task_id: u32,
+    width: u32,
+    height: u32,
+    timestamp_ms: u64,
+) -> i32 {
+    if input_event::input_request_configure(task_id, width, height, timestamp_ms) {
+        0
+    } else {
+        -1
+    }
+}
+
 /// Adapter: driver returns `u8`, service expects `u32`.
 fn input_get_button_state_adapter() -> u32 {
     input_event::input_get_button_state() as u32
@@ -46,14 +67,22 @@ static INPUT_SERVICES: InputServices = InputServices {
     drain_batch: input_event::input_drain_batch,
     event_count: input_event_count_adapter,
     set_keyboard_focus: input_event::input_set_keyboard_focus,
+    set_shortcut_focus: input_event::input_set_shortcut_focus,
+    take_keys: input_event::input_take_keys,
     set_pointer_focus: input_event::input_set_pointer_focus,
     set_pointer_focus_with_offset: input_event::input_set_pointer_focus_with_offset,
     request_close: input_request_close_adapter,
+    request_configure: input_request_configure_adapter,
     get_pointer_focus: input_event::input_get_pointer_focus,
     get_pointer_position: input_event::input_get_pointer_position,
     get_button_state: input_get_button_state_adapter,
     clipboard_copy: input_event::clipboard_copy,
     clipboard_paste: input_event::clipboard_paste,
+    clipboard_set: input_event::clipboard_set,
+    clipboard_get: input_event::clipboard_get,
+    device_read: input_event::input_device_read,
+    set_keymap: keymap::set_active_keymap,
+    keymap: keymap::active_keymap_id,
 };
 
 // =============================================================================
@@ -329,18 +358,87 @@ fn net_get_info_adapter(out: *mut slopos_abi::net::UserNetInfo) -> u32 {
     1
 }
 
+fn net_filter_append_adapter(rule: slopos_abi::net::NetFilterRule) -> i32 {
+    filter::append(rule).map_or_else(|err| err.to_errno(), |idx| idx as i32)
+}
+
+fn net_filter_delete_adapter(idx: usize) -> i32 {
+    filter::delete(idx).map_or_else(|err| err.to_errno(), |()| 0)
+}
+
+fn net_filter_set_policy_adapter(direction: u8, action: u8) -> i32 {
+    filter::set_policy(direction, action).map_or_else(|err| err.to_errno(), |()| 0)
+}
+
+fn net_ping_adapter(
+    request: slopos_abi::net::NetPingRequest,
+    reply: &mut slopos_abi::net::NetPingReply,
+) -> i32 {
+    let result = icmp::ping(
+        crate::net::Ipv4Addr(request.addr),
+        request.ident,
+        request.seq,
+        request.payload_len as usize,
+        request.timeout_ms,
+    );
+    match result {
+        Ok(pong) => {
+            reply.rtt_us = pong.rtt_us;
+            reply.payload_len = pong.len;
+            reply.ttl = pong.ttl;
+            0
+        }
+        Err(err) => err.to_errno(),
+    }
+}
+
+fn net_route_add_adapter(entry: slopos_abi::net::NetRoute) -> i32 {
+    route::add_route(&route::ROUTE_TABLE, &entry).map_or_else(|err| err.to_errno(), |()| 0)
+}
+
+fn net_route_del_adapter(entry: slopos_abi::net::NetRoute) -> i32 {
+    route::del_route(&route::ROUTE_TABLE, &entry).map_or_else(|err| err.to_errno(), |()| 0)
+}
+
+fn net_route_list_adapter(out: &mut [slopos_abi::net::NetRoute]) -> usize {
+    route::list_routes(&route::ROUTE_TABLE, out)
+}
+
 static NET_SERVICES: NetServices = NetServices {
     scan_members: net_scan_members_adapter,
     is_ready: net_is_ready_adapter,
     get_info: net_get_info_adapter,
+    filter_append: net_filter_append_adapter,
+    filter_delete: net_filter_delete_adapter,
+    filter_flush: filter::flush,
+    filter_list: filter::list,
+    filter_set_policy: net_filter_set_policy_adapter,
+    filter_status: filter::status,
+    ping: net_ping_adapter,
+    route_add: net_route_add_adapter,
+    route_del: net_route_del_adapter,
+    route_list: net_route_list_adapter,
+    lease_info: dhcp::dhcp_lease_info,
+    socket_stat: socket::socket_stat,
 };
 
+/// Deliver what a socket call queued on loopback before returning to the
+/// caller, so the local peer sees it without waiting for a NIC poll.
+fn flush_loopback<T>(rc: T) -> T {
+    loopback::loopback_flush();
+    rc
+}
+
+fn socket_connect_adapter(sock_idx: u32, addr: [u8; 4], port: u16) -> i32 {
+    flush_loopback(socket::socket_connect(sock_idx, addr, port))
+}
+
 fn socket_send_adapter(sock_idx: u32, data: *const u8, len: usize) -> i64 {
-    socket::socket_send(sock_idx, data, len)
+    flush_loopback(socket::socket_send(sock_idx, data, len))
 }
 
 fn socket_recv_adapter(sock_idx: u32, buf: *mut u8, len: usize) -> i64 {
-    socket::socket_recv(sock_idx, buf, len)
+    flush_loopback(socket::socket_recv(sock_idx, buf, len))
 }
 
 fn socket_sendto_adapter(
@@ -350,7 +448,7 @@ fn socket_sendto_adapter(
     dst_ip: [u8; 4],
     dst_port: u16,
 ) -> i64 {
-    socket::socket_sendto(sock_idx, data, len, dst_ip, dst_port)
+    flush_loopback(socket::socket_sendto(sock_idx, data, len, dst_ip, dst_port))
 }
 
 fn socket_recvfrom_adapter(
@@ -399,42 +497,57 @@ fn socket_getsockopt_adapter(
     socket::socket_getsockopt(sock_idx, level, optname, slice)
 }
 
+fn socket_close_adapter(sock_idx: u32) -> i32 {
+    flush_loopback(socket::socket_close(sock_idx))
+}
+
+fn socket_shutdown_adapter(sock_idx: u32, how: i32) -> i32 {
+    flush_loopback(socket::socket_shutdown(sock_idx, how))
+}
+
 static SOCKET_SERVICES: SocketServices = SocketServices {
     create: socket::socket_create,
     bind: socket::socket_bind,
     listen: socket::socket_listen,
     accept: socket::socket_accept,
-    connect: socket::socket_connect,
+    connect: socket_connect_adapter,
     send: socket_send_adapter,
     recv: socket_recv_adapter,
     sendto: socket_sendto_adapter,
     recvfrom: socket_recvfrom_adapter,
-    close: socket::socket_close,
+    close: socket_close_adapter,
     poll_readable: socket::socket_poll_readable,
     poll_writable: socket::socket_poll_writable,
     set_nonblocking: socket::socket_set_nonblocking,
     setsockopt: socket_setsockopt_adapter,
     getsockopt: socket_getsockopt_adapter,
-    shutdown: socket::socket_shutdown,
+    shutdown: socket_shutdown_adapter,
 };
 
 // =============================================================================
 // DNS services
 // =============================================================================
 
-fn dns_resolve_adapter(hostname: *const u8, hostname_len: usize, result: *mut [u8; 4]) -> i32 {
+fn dns_resolve_adapter(
+    hostname: *const u8,
+    hostname_len: usize,
+    nonblocking: bool,
+    result: *mut [u8; 4],
+) -> i32 {
     if hostname.is_null() || result.is_null() || hostname_len == 0 || hostname_len > 253 {
         return -22; // EINVAL
     }
     let hostname_slice = unsafe { core::slice::from_raw_parts(hostname, hostname_len) };
-    match dns::dns_resolve(hostname_slice) {
-        Some(addr) => {
+    match dns::dns_lookup(hostname_slice, nonblocking) {
+        dns::DnsLookup::Found(addr) => {
             unsafe {
                 *result = addr;
             }
             0
         }
-        None => -113, // EHOSTUNREACH
+        dns::DnsLookup::NotFound => -113, // EHOSTUNREACH
+        dns::DnsLookup::Failed => -110,   // ETIMEDOUT
+        dns::DnsLookup::Pending => -11,   // EAGAIN
     }
 }
 
@@ -442,10 +555,46 @@ static DNS_SERVICES: DnsServices = DnsServices {
     resolve: dns_resolve_adapter,
 };
 
+// =============================================================================
+// Audio services
+// =============================================================================
+
+static AUDIO_SERVICES: AudioServices = AudioServices {
+    write: audio::audio_write,
+    info: audio::audio_info,
+    set_volume: audio::audio_set_volume,
+    stop: audio::audio_stop,
+};
+
+// =============================================================================
+// Hardware inventory services
+// =============================================================================
+
+/// Adapter: the inventory hands back an `Option`, the service fills a
+/// caller-provided record.
+fn hw_device_adapter(index: usize, out: *mut HwDevice) -> bool {
+    let Some(device) = hwinfo::hwinfo_device(index) else {
+        return false;
+    };
+    if out.is_null() {
+        return false;
+    }
+    unsafe {
+        *out = device;
+    }
+    true
+}
+
+static HW_SERVICES: HwServices = HwServices {
+    device: hw_device_adapter,
+};
+
 pub fn init_syscall_services() {
     register_input_services(&INPUT_SERVICES);
     register_tty_services(&TTY_SERVICES);
     register_net_services(&NET_SERVICES);
     register_socket_services(&SOCKET_SERVICES);
     register_dns_services(&DNS_SERVICES);
+    register_audio_services(&AUDIO_SERVICES);
+    register_hw_services(&HW_SERVICES);
 }
@@ -1,8 +1,8 @@
 //! TCP data transfer regression tests (Phase 5B).
 //!
 //! Covers: ring buffer operations, send/receive buffers, data transfer through
-//! the TCP state machine, delayed ACK, retransmission, flow control, and
-//! zero-window probing.
+//! the TCP state machine, delayed ACK, retransmission, flow control,
+//! zero-window probing and Nagle's algorithm.
 
 use slopos_lib::testing::TestResult;
 use slopos_lib::{assert_eq_test, assert_test, fail, pass};
@@ -16,10 +16,13 @@ fn reset() {
     tcp::tcp_reset_all();
 }
 
+/// Local port for active opens; the socket layer normally picks one.
+const CLIENT_PORT: u16 = 49152;
+
 fn establish_connection() -> (usize, u32, u16) {
     let local_ip = [10, 0, 0, 1];
     let remote_ip = [10, 0, 0, 2];
-    let (idx, syn_seg) = tcp::tcp_connect(local_ip, remote_ip, 80).unwrap();
+    let (idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80).unwrap();
     let client_iss = syn_seg.seq_num;
     let client_port = syn_seg.tuple.local_port;
 
@@ -615,7 +618,7 @@ pub fn test_tcp_recv_in_established() -> TestResult {
 
 pub fn test_tcp_send_wrong_state() -> TestResult {
     reset();
-    let (idx, _) = tcp::tcp_connect([10, 0, 0, 1], [10, 0, 0, 2], 80).unwrap();
+    let (idx, _) = tcp::tcp_connect([10, 0, 0, 1], CLIENT_PORT, [10, 0, 0, 2], 80).unwrap();
     let err = tcp::tcp_send(idx, b"x").unwrap_err();
     assert_eq_test!(err, TcpError::InvalidState, "send in SYN_SENT rejected");
     pass!()
@@ -1037,6 +1040,74 @@ pub fn test_tcp_immediate_ack_for_fin() -> TestResult {
     pass!()
 }
 
+// =============================================================================
+// Nagle
+// =============================================================================
+
+pub fn test_tcp_nagle_holds_short_segment() -> TestResult {
+    reset();
+    let (idx, server_iss, client_port) = establish_connection();
+    tcp::tcp_set_nagle(idx, true);
+    let mut payload = [0u8; 2048];
+
+    let _ = tcp::tcp_send(idx, &[1u8; 100]).unwrap();
+    let Some((first, n)) = tcp::tcp_poll_transmit(idx, &mut payload, 0) else {
+        return fail!("nothing in flight, short segment held");
+    };
+    assert_eq_test!(n, 100);
+
+    let _ = tcp::tcp_send(idx, &[2u8; 100]).unwrap();
+    assert_test!(
+        tcp::tcp_poll_transmit(idx, &mut payload, 1).is_none(),
+        "short segment sent with data in flight"
+    );
+
+    let _ = inject_data_segment(
+        [10, 0, 0, 2],
+        [10, 0, 0, 1],
+        80,
+        client_port,
+        server_iss.wrapping_add(1),
+        first.seq_num.wrapping_add(100),
+        &[],
+        2,
+    );
+    let Some((second, n)) = tcp::tcp_poll_transmit(idx, &mut payload, 3) else {
+        return fail!("held segment not sent after ack");
+    };
+    assert_eq_test!(n, 100);
+    assert_eq_test!(second.seq_num, first.seq_num.wrapping_add(100));
+    pass!()
+}
+
+pub fn test_tcp_nagle_sends_full_segments() -> TestResult {
+    reset();
+    let (idx, _, _) = establish_connection();
+    tcp::tcp_set_nagle(idx, true);
+    let mut payload = [0u8; 2048];
+
+    let data = [0x42u8; 2 * DEFAULT_MSS as usize + 100];
+    let _ = tcp::tcp_send(idx, &data).unwrap();
+    for now in 0..2 {
+        let Some((_, n)) = tcp::tcp_poll_transmit(idx, &mut payload, now) else {
+            return fail!("full segment {} held", now);
+        };
+        assert_eq_test!(n, DEFAULT_MSS as usize);
+    }
+    assert_test!(
+        tcp::tcp_poll_transmit(idx, &mut payload, 2).is_none(),
+        "remainder sent with data in flight"
+    );
+
+    // Without Nagle the remainder goes straight out.
+    tcp::tcp_set_nagle(idx, false);
+    let Some((_, n)) = tcp::tcp_poll_transmit(idx, &mut payload, 3) else {
+        return fail!("remainder held without nagle");
+    };
+    assert_eq_test!(n, 100);
+    pass!()
+}
+
 slopos_lib::define_test_suite!(
     tcp_data,
     [
@@ -1078,5 +1149,7 @@ slopos_lib::define_test_suite!(
         test_tcp_delayed_ack_after_two_segments,
         test_tcp_delayed_ack_timeout,
         test_tcp_immediate_ack_for_fin,
+        test_tcp_nagle_holds_short_segment,
+        test_tcp_nagle_sends_full_segments,
     ]
 );
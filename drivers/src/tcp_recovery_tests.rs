@@ -0,0 +1,480 @@
+//! TCP loss recovery tests.
+//!
+//! Covers: RTT estimation and Karn's algorithm, slow start and congestion
+//! avoidance, out-of-order reassembly, and zero-window probing.  Both ends
+//! live in the connection table on 127.0.0.1, and each test carries the
+//! segments between them by hand, so it can delay, drop and reorder them.
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::net::tcp::{
+    self, DEFAULT_MSS, DELAYED_ACK_MS, TCP_OPT_MSS_LEN, TcpConnection, TcpHeader, TcpInputResult,
+    TcpOutSegment, TcpState,
+};
+
+const LO: [u8; 4] = [127, 0, 0, 1];
+const SERVER_PORT: u16 = 7070;
+const CLIENT_PORT: u16 = 49300;
+const MSS: usize = DEFAULT_MSS as usize;
+
+/// A segment on its way from one end to the other.
+struct Wire {
+    seg: TcpOutSegment,
+    payload: Vec<u8>,
+}
+
+/// Client and server ends of an established loopback connection.  Dropping
+/// it clears the table, timers included.
+struct Loopback {
+    client: usize,
+    server: usize,
+}
+
+impl Loopback {
+    /// Handshake over loopback.  `server_recv_limit` caps the server's
+    /// receive buffer (0 for the full ring) before the SYN arrives, so the
+    /// window in its SYN-ACK already reflects it.
+    fn open(server_recv_limit: usize) -> Option<Self> {
+        tcp::tcp_reset_all();
+        let listener = tcp::tcp_listen(LO, SERVER_PORT, false).ok()?;
+        tcp::tcp_set_buffer_limits(listener, server_recv_limit, 0);
+        let (client, syn) = tcp::tcp_connect(LO, CLIENT_PORT, LO, SERVER_PORT).ok()?;
+
+        let syn_ack = deliver(&syn, &[], 0);
+        let server = syn_ack.accepted_idx?;
+        let ack = deliver(&syn_ack.response?, &[], 0).response?;
+        deliver(&ack, &[], 0);
+        let both_up = tcp::tcp_get_state(client) == Some(TcpState::Established)
+            && tcp::tcp_get_state(server) == Some(TcpState::Established);
+        both_up.then_some(Self { client, server })
+    }
+
+    fn client(&self) -> TcpConnection {
+        tcp::tcp_get_connection(self.client).unwrap_or(TcpConnection::empty())
+    }
+}
+
+impl Drop for Loopback {
+    fn drop(&mut self) {
+        tcp::tcp_reset_all();
+    }
+}
+
+/// Hand `seg` to the other end, as the loopback device would.
+fn deliver(seg: &TcpOutSegment, payload: &[u8], now_ms: u64) -> TcpInputResult {
+    let mut options = [0u8; TCP_OPT_MSS_LEN as usize];
+    let options_len = if seg.mss != 0 {
+        tcp::write_mss_option(seg.mss, &mut options).unwrap_or(0)
+    } else {
+        0
+    };
+    let hdr = TcpHeader {
+        src_port: seg.tuple.local_port,
+        dst_port: seg.tuple.remote_port,
+        seq_num: seg.seq_num,
+        ack_num: seg.ack_num,
+        data_offset: 5 + (options_len / 4) as u8,
+        flags: seg.flags,
+        window_size: seg.window_size,
+        checksum: 0,
+        urgent_ptr: 0,
+    };
+    tcp::tcp_input(
+        seg.tuple.local_ip,
+        seg.tuple.remote_ip,
+        &hdr,
+        &options[..options_len],
+        payload,
+        now_ms,
+    )
+}
+
+fn deliver_wire(wire: &Wire, now_ms: u64) -> TcpInputResult {
+    deliver(&wire.seg, &wire.payload, now_ms)
+}
+
+/// Everything `idx` may send right now.
+fn transmit(idx: usize, now_ms: u64) -> Vec<Wire> {
+    let mut out = Vec::new();
+    let mut buf = [0u8; MSS];
+    while let Some((seg, n)) = tcp::tcp_poll_transmit(idx, &mut buf, now_ms) {
+        out.push(Wire {
+            seg,
+            payload: buf[..n].to_vec(),
+        });
+    }
+    out
+}
+
+/// Queue `len` bytes on `idx`; byte `i` is `i % 251`.
+fn queue_pattern(idx: usize, len: usize) -> bool {
+    let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+    tcp::tcp_send(idx, &data) == Ok(len)
+}
+
+// =============================================================================
+// RTT estimation and Karn's algorithm
+// =============================================================================
+
+pub fn test_rtt_samples_set_rto() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_eq_test!(lo.client().srtt_ms, None, "no sample before data");
+    assert_eq_test!(lo.client().rto_ms, tcp::INITIAL_RTO_MS);
+
+    // 300 ms round trip: SRTT = R, RTTVAR = R/2, RTO = SRTT + 4 * RTTVAR.
+    assert_test!(queue_pattern(lo.client, 100));
+    let sent = transmit(lo.client, 0);
+    assert_eq_test!(sent.len(), 1);
+    assert_test!(
+        deliver_wire(&sent[0], 150).response.is_none(),
+        "ack not delayed"
+    );
+    let Some((_, ack)) = tcp::tcp_delayed_ack_check(150 + DELAYED_ACK_MS) else {
+        return fail!("server never acknowledged");
+    };
+    deliver(&ack, &[], 300);
+    assert_eq_test!(lo.client().srtt_ms, Some(300), "first sample");
+    assert_eq_test!(lo.client().rttvar_ms, 150);
+    assert_eq_test!(lo.client().rto_ms, 900, "rto from first sample");
+
+    // 100 ms: RTTVAR = (3 * 150 + 200) / 4, SRTT = (7 * 300 + 100) / 8.
+    assert_test!(queue_pattern(lo.client, 100));
+    let sent = transmit(lo.client, 1000);
+    deliver_wire(&sent[0], 1050);
+    let Some((_, ack)) = tcp::tcp_delayed_ack_check(1050 + DELAYED_ACK_MS) else {
+        return fail!("server never acknowledged");
+    };
+    deliver(&ack, &[], 1100);
+    assert_eq_test!(lo.client().srtt_ms, Some(275), "smoothed rtt");
+    assert_eq_test!(lo.client().rttvar_ms, 162, "rtt variation");
+    assert_eq_test!(lo.client().rto_ms, 275 + 4 * 162);
+    pass!()
+}
+
+pub fn test_rto_floor() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_test!(queue_pattern(lo.client, 2 * MSS));
+    let sent = transmit(lo.client, 0);
+    assert_eq_test!(sent.len(), 2);
+    deliver_wire(&sent[0], 1);
+    let Some(ack) = deliver_wire(&sent[1], 1).response else {
+        return fail!("second segment not acknowledged at once");
+    };
+    deliver(&ack, &[], 2);
+    assert_eq_test!(lo.client().srtt_ms, Some(2));
+    assert_eq_test!(lo.client().rto_ms, tcp::MIN_RTO_MS, "rto clamped");
+    pass!()
+}
+
+pub fn test_karn_skips_retransmitted_segments() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_test!(queue_pattern(lo.client, 64));
+    let lost = transmit(lo.client, 0);
+    assert_eq_test!(lost.len(), 1);
+    assert_test!(lo.client().rtt_seq.is_some(), "new data not timed");
+
+    assert_eq_test!(tcp::tcp_retransmit_check(1000), Some(lo.client));
+    assert_eq_test!(lo.client().rto_ms, 2000, "backed off");
+    assert_test!(lo.client().rtt_seq.is_none(), "sample kept across timeout");
+
+    let resent = transmit(lo.client, 1001);
+    assert_eq_test!(resent.len(), 1);
+    assert_eq_test!(
+        resent[0].seg.seq_num,
+        lost[0].seg.seq_num,
+        "resent from snd_una"
+    );
+    assert_test!(lo.client().rtt_seq.is_none(), "retransmission timed");
+
+    // The ACK could be for either copy: no sample, and the backed-off RTO
+    // stays.
+    deliver_wire(&resent[0], 1400);
+    let Some((_, ack)) = tcp::tcp_delayed_ack_check(1400 + DELAYED_ACK_MS) else {
+        return fail!("server never acknowledged");
+    };
+    deliver(&ack, &[], 1700);
+    assert_eq_test!(lo.client().srtt_ms, None, "sampled a retransmission");
+    assert_eq_test!(lo.client().rto_ms, 2000);
+
+    // Fresh data is timed again.
+    assert_test!(queue_pattern(lo.client, 64));
+    let sent = transmit(lo.client, 2000);
+    deliver_wire(&sent[0], 2000);
+    let Some((_, ack)) = tcp::tcp_delayed_ack_check(2000 + DELAYED_ACK_MS) else {
+        return fail!("server never acknowledged");
+    };
+    deliver(&ack, &[], 2400);
+    assert_eq_test!(lo.client().srtt_ms, Some(400), "new data sampled");
+    assert_eq_test!(lo.client().rto_ms, 1200);
+    pass!()
+}
+
+pub fn test_ack_for_data_sent_before_timeout() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_test!(queue_pattern(lo.client, 3 * MSS));
+    let sent = transmit(lo.client, 0);
+    assert_eq_test!(sent.len(), 3);
+    for wire in &sent {
+        deliver_wire(wire, 10);
+    }
+    let Some((_, last_ack)) = tcp::tcp_delayed_ack_check(10 + DELAYED_ACK_MS) else {
+        return fail!("server never acknowledged");
+    };
+
+    // Every ACK was lost; the timeout rewinds to snd_una and resends one
+    // segment.  The peer's ACK for all three must still be taken.
+    assert_eq_test!(tcp::tcp_retransmit_check(1000), Some(lo.client));
+    assert_eq_test!(transmit(lo.client, 1000).len(), 1, "loss window");
+    deliver(&last_ack, &[], 1100);
+    let client = lo.client();
+    assert_eq_test!(client.snd_una, client.snd_max, "cumulative ack ignored");
+    assert_eq_test!(client.snd_nxt, client.snd_max);
+    assert_test!(!tcp::tcp_has_pending_data(lo.client), "data left unsent");
+    assert_test!(transmit(lo.client, 1100).is_empty(), "acked data resent");
+    pass!()
+}
+
+// =============================================================================
+// Congestion control
+// =============================================================================
+
+pub fn test_slow_start_limits_and_grows_cwnd() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    let initial = tcp::initial_cwnd(DEFAULT_MSS);
+    assert_eq_test!(initial, 3 * MSS as u32, "RFC 3390 window for 1460");
+    assert_eq_test!(lo.client().cwnd, initial);
+
+    assert_test!(queue_pattern(lo.client, 10 * MSS));
+    let first = transmit(lo.client, 0);
+    assert_eq_test!(first.len(), 3, "initial window in segments");
+    assert_test!(tcp::tcp_has_pending_data(lo.client), "window not binding");
+
+    // One ACK every two segments; each grows cwnd by at most one MSS.
+    let mut acks = Vec::new();
+    for wire in &first {
+        if let Some(ack) = deliver_wire(wire, 5).response {
+            acks.push(ack);
+        }
+    }
+    if let Some((_, ack)) = tcp::tcp_delayed_ack_check(5 + DELAYED_ACK_MS) {
+        acks.push(ack);
+    }
+    assert_eq_test!(acks.len(), 2);
+    for ack in &acks {
+        deliver(ack, &[], 10);
+    }
+    assert_eq_test!(lo.client().cwnd, initial + 2 * MSS as u32, "slow start");
+
+    let second = transmit(lo.client, 10);
+    assert_eq_test!(second.len(), 5, "grown window in segments");
+    pass!()
+}
+
+pub fn test_timeout_then_congestion_avoidance() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_test!(queue_pattern(lo.client, 8 * MSS));
+    assert_eq_test!(transmit(lo.client, 0).len(), 3);
+
+    // The whole flight is lost.
+    assert_eq_test!(tcp::tcp_retransmit_check(1000), Some(lo.client));
+    let client = lo.client();
+    assert_eq_test!(client.cwnd, MSS as u32, "loss window");
+    assert_eq_test!(client.ssthresh, 2 * MSS as u32, "half the flight, floored");
+
+    let resent = transmit(lo.client, 1000);
+    assert_eq_test!(resent.len(), 1, "one segment after timeout");
+    deliver_wire(&resent[0], 1010);
+    let Some((_, ack)) = tcp::tcp_delayed_ack_check(1010 + DELAYED_ACK_MS) else {
+        return fail!("server never acknowledged");
+    };
+    deliver(&ack, &[], 1300);
+    assert_eq_test!(lo.client().cwnd, 2 * MSS as u32, "slow start to ssthresh");
+
+    let pair = transmit(lo.client, 1300);
+    assert_eq_test!(pair.len(), 2);
+    deliver_wire(&pair[0], 1310);
+    let Some(ack) = deliver_wire(&pair[1], 1310).response else {
+        return fail!("second segment not acknowledged at once");
+    };
+    deliver(&ack, &[], 1320);
+    // At ssthresh: MSS * MSS / cwnd per ACK.
+    assert_eq_test!(lo.client().cwnd, 2 * MSS as u32 + MSS as u32 / 2);
+    assert_eq_test!(transmit(lo.client, 1320).len(), 3, "2.5 segments of window");
+    pass!()
+}
+
+// =============================================================================
+// Out-of-order reassembly
+// =============================================================================
+
+pub fn test_out_of_order_segments_reassembled() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_test!(queue_pattern(lo.client, 3 * MSS));
+    let sent = transmit(lo.client, 0);
+    assert_eq_test!(sent.len(), 3);
+    let rcv_nxt = sent[0].seg.seq_num;
+
+    // Last, then a duplicate of it, then the middle: each is held and
+    // answered with a duplicate ACK for the hole.
+    for (i, wire) in [&sent[2], &sent[2], &sent[1]].into_iter().enumerate() {
+        let Some(dup) = deliver_wire(wire, 1).response else {
+            return fail!("no duplicate ACK for segment {}", i);
+        };
+        assert_eq_test!(dup.ack_num, rcv_nxt, "dup ack points at the hole");
+        assert_eq_test!(tcp::tcp_recv_available(lo.server), 0, "held data readable");
+    }
+
+    let Some(ack) = deliver_wire(&sent[0], 2).response else {
+        return fail!("filling the hole not acknowledged at once");
+    };
+    let end = sent[2].seg.seq_num.wrapping_add(MSS as u32);
+    assert_eq_test!(ack.ack_num, end, "ack covers the reassembled data");
+    assert_eq_test!(tcp::tcp_recv_available(lo.server), 3 * MSS);
+
+    let mut data = [0u8; 3 * MSS];
+    assert_eq_test!(tcp::tcp_recv(lo.server, &mut data), Ok(3 * MSS));
+    assert_test!(
+        data.iter().enumerate().all(|(i, &b)| b == (i % 251) as u8),
+        "reassembled stream out of order"
+    );
+    pass!()
+}
+
+pub fn test_out_of_order_partial_fill() -> TestResult {
+    let Some(lo) = Loopback::open(0) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_test!(queue_pattern(lo.client, 4 * 100));
+    let mut segs = Vec::new();
+    let mut buf = [0u8; 100];
+    while let Some((seg, n)) = tcp::tcp_poll_transmit(lo.client, &mut buf, 0) {
+        segs.push(Wire {
+            seg,
+            payload: buf[..n].to_vec(),
+        });
+    }
+    assert_eq_test!(segs.len(), 4);
+
+    // Holes before segments 1 and 3.  Filling the first only releases
+    // segment 1; segment 3 waits for segment 2.
+    deliver_wire(&segs[3], 1);
+    deliver_wire(&segs[1], 1);
+    deliver_wire(&segs[0], 2);
+    assert_eq_test!(tcp::tcp_recv_available(lo.server), 200, "first hole");
+    let Some(ack) = deliver_wire(&segs[2], 3).response else {
+        return fail!("second hole not acknowledged at once");
+    };
+    assert_eq_test!(ack.ack_num, segs[3].seg.seq_num.wrapping_add(100));
+    assert_eq_test!(tcp::tcp_recv_available(lo.server), 400, "second hole");
+    pass!()
+}
+
+// =============================================================================
+// Zero-window probing
+// =============================================================================
+
+pub fn test_zero_window_probe_until_reopened() -> TestResult {
+    const WINDOW: usize = 2000;
+    let Some(lo) = Loopback::open(WINDOW) else {
+        return fail!("loopback handshake failed");
+    };
+    assert_eq_test!(lo.client().snd_wnd as usize, WINDOW, "window from SYN-ACK");
+
+    assert_test!(queue_pattern(lo.client, WINDOW + 1000));
+    let sent = transmit(lo.client, 0);
+    assert_eq_test!(sent.len(), 2, "window fills in two segments");
+    deliver_wire(&sent[0], 1);
+    let Some(ack) = deliver_wire(&sent[1], 1).response else {
+        return fail!("second segment not acknowledged at once");
+    };
+    assert_eq_test!(ack.window_size, 0, "server window not shut");
+    deliver(&ack, &[], 2);
+
+    let client = lo.client();
+    assert_test!(
+        client.persist_timer_token.is_some(),
+        "persist timer not armed"
+    );
+    let interval = client.persist_ms;
+    assert_eq_test!(interval, client.rto_ms, "first probe after one rto");
+    assert_test!(transmit(lo.client, 2).is_empty(), "sent into a zero window");
+
+    // Probes carry no data and one-below-snd_una sequence numbers; the
+    // server answers each with its window.
+    let Some(probe) = tcp::tcp_on_persist(lo.client as u32) else {
+        return fail!("no probe while the window is shut");
+    };
+    assert_eq_test!(probe.seq_num, client.snd_una.wrapping_sub(1));
+    assert_eq_test!(lo.client().persist_ms, 2 * interval, "probe backoff");
+    let Some(answer) = deliver(&probe, &[], 3).response else {
+        return fail!("probe not answered");
+    };
+    assert_eq_test!(answer.window_size, 0);
+    deliver(&answer, &[], 3);
+    assert_eq_test!(
+        lo.client().snd_nxt,
+        client.snd_nxt,
+        "probe consumed sequence"
+    );
+
+    let mut drain = [0u8; WINDOW];
+    assert_eq_test!(tcp::tcp_recv(lo.server, &mut drain), Ok(WINDOW));
+    let Some(probe) = tcp::tcp_on_persist(lo.client as u32) else {
+        return fail!("no second probe");
+    };
+    assert_eq_test!(lo.client().persist_ms, 4 * interval);
+    let Some(answer) = deliver(&probe, &[], 4).response else {
+        return fail!("probe not answered");
+    };
+    assert_eq_test!(answer.window_size as usize, WINDOW, "reopened window");
+    deliver(&answer, &[], 4);
+
+    let client = lo.client();
+    assert_test!(
+        client.persist_timer_token.is_none(),
+        "persist timer left armed"
+    );
+    assert_eq_test!(client.persist_ms, 0);
+    let rest = transmit(lo.client, 5);
+    let resumed: usize = rest.iter().map(|w| w.payload.len()).sum();
+    assert_eq_test!(resumed, 1000, "queued data after the window reopened");
+    assert_test!(
+        tcp::tcp_on_persist(lo.client as u32).is_none(),
+        "probe with open window"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    tcp_recovery,
+    [
+        test_rtt_samples_set_rto,
+        test_rto_floor,
+        test_karn_skips_retransmitted_segments,
+        test_ack_for_data_sent_before_timeout,
+        test_slow_start_limits_and_grows_cwnd,
+        test_timeout_then_congestion_avoidance,
+        test_out_of_order_segments_reassembled,
+        test_out_of_order_partial_fill,
+        test_zero_window_probe_until_reopened,
+    ]
+);
@@ -4,7 +4,9 @@
 //! sequence number arithmetic, state machine transitions, connection table
 //! management, three-way handshake (active open and passive open), connection
 //! teardown (active close, passive close, simultaneous close), RST handling,
-//! MSS option parsing, ephemeral port allocation, and TIME_WAIT expiry.
+//! MSS option parsing, connect tuple uniqueness, TIME_WAIT expiry, FIN
+//! sequencing and retransmission, TIME_WAIT bounds and port reuse, and the
+//! listen backlog.
 //!
 //! All tests run in-kernel during the integration test harness (`itests=on`).
 
@@ -12,9 +14,9 @@ use slopos_lib::testing::TestResult;
 use slopos_lib::{assert_eq_test, assert_test, fail, pass};
 
 use crate::net::tcp::{
-    self, DEFAULT_MSS, DEFAULT_WINDOW_SIZE, MAX_CONNECTIONS, TCP_FLAG_ACK, TCP_FLAG_FIN,
-    TCP_FLAG_PSH, TCP_FLAG_RST, TCP_FLAG_SYN, TCP_FLAG_URG, TIME_WAIT_MS, TcpConnection, TcpError,
-    TcpHeader, TcpState, TcpTuple,
+    self, DEFAULT_MSS, DEFAULT_WINDOW_SIZE, FIN_TIMEOUT_MS, MAX_CONNECTIONS, MAX_TIME_WAIT,
+    TCP_FLAG_ACK, TCP_FLAG_FIN, TCP_FLAG_PSH, TCP_FLAG_RST, TCP_FLAG_SYN, TCP_FLAG_URG,
+    TIME_WAIT_MS, TcpConnection, TcpError, TcpHeader, TcpState, TcpTuple,
 };
 
 // =============================================================================
@@ -25,6 +27,9 @@ fn reset() {
     tcp::tcp_reset_all();
 }
 
+/// Local port for active opens; the socket layer normally picks one.
+const CLIENT_PORT: u16 = 49152;
+
 // =============================================================================
 // 1. Header parsing
 // =============================================================================
@@ -444,7 +449,7 @@ pub fn test_tcp_table_initially_empty() -> TestResult {
 
 pub fn test_tcp_connect_creates_syn_sent() -> TestResult {
     reset();
-    let (idx, seg) = match tcp::tcp_connect([10, 0, 0, 1], [10, 0, 0, 2], 80) {
+    let (idx, seg) = match tcp::tcp_connect([10, 0, 0, 1], CLIENT_PORT, [10, 0, 0, 2], 80) {
         Ok(r) => r,
         Err(e) => return fail!("tcp_connect failed: {:?}", e),
     };
@@ -471,7 +476,7 @@ pub fn test_tcp_table_full_returns_error() -> TestResult {
     reset();
     // Fill all slots.
     for i in 0..MAX_CONNECTIONS {
-        match tcp::tcp_connect([10, 0, 0, 1], [10, 0, 0, 2], (80 + i) as u16) {
+        match tcp::tcp_connect([10, 0, 0, 1], CLIENT_PORT, [10, 0, 0, 2], (80 + i) as u16) {
             Ok(_) => {}
             Err(e) => return fail!("connect {} failed: {:?}", i, e),
         }
@@ -479,7 +484,7 @@ pub fn test_tcp_table_full_returns_error() -> TestResult {
     assert_eq_test!(tcp::tcp_active_count(), MAX_CONNECTIONS, "table full");
 
     // Next connect should fail.
-    match tcp::tcp_connect([10, 0, 0, 1], [10, 0, 0, 2], 9999) {
+    match tcp::tcp_connect([10, 0, 0, 1], CLIENT_PORT, [10, 0, 0, 2], 9999) {
         Err(TcpError::TableFull) => {}
         other => return fail!("expected TableFull, got {:?}", other),
     }
@@ -488,7 +493,7 @@ pub fn test_tcp_table_full_returns_error() -> TestResult {
 
 pub fn test_tcp_listen_creates_listen_state() -> TestResult {
     reset();
-    let idx = match tcp::tcp_listen([0; 4], 8080) {
+    let idx = match tcp::tcp_listen([0; 4], 8080, false) {
         Ok(i) => i,
         Err(e) => return fail!("tcp_listen failed: {:?}", e),
     };
@@ -504,9 +509,9 @@ pub fn test_tcp_listen_creates_listen_state() -> TestResult {
 
 pub fn test_tcp_listen_duplicate_port_fails() -> TestResult {
     reset();
-    tcp::tcp_listen([0; 4], 8080).unwrap();
+    tcp::tcp_listen([0; 4], 8080, false).unwrap();
 
-    match tcp::tcp_listen([0; 4], 8080) {
+    match tcp::tcp_listen([0; 4], 8080, false) {
         Err(TcpError::AddrInUse) => {}
         other => return fail!("expected AddrInUse, got {:?}", other),
     }
@@ -515,7 +520,7 @@ pub fn test_tcp_listen_duplicate_port_fails() -> TestResult {
 
 pub fn test_tcp_close_listen_releases_slot() -> TestResult {
     reset();
-    let idx = tcp::tcp_listen([0; 4], 8080).unwrap();
+    let idx = tcp::tcp_listen([0; 4], 8080, false).unwrap();
     assert_eq_test!(tcp::tcp_active_count(), 1, "one active");
 
     let result = tcp::tcp_close(idx);
@@ -527,7 +532,7 @@ pub fn test_tcp_close_listen_releases_slot() -> TestResult {
 
 pub fn test_tcp_close_syn_sent_releases_slot() -> TestResult {
     reset();
-    let (idx, _) = tcp::tcp_connect([10, 0, 0, 1], [10, 0, 0, 2], 80).unwrap();
+    let (idx, _) = tcp::tcp_connect([10, 0, 0, 1], CLIENT_PORT, [10, 0, 0, 2], 80).unwrap();
     assert_eq_test!(tcp::tcp_active_count(), 1, "one active");
 
     let result = tcp::tcp_close(idx).unwrap();
@@ -538,7 +543,7 @@ pub fn test_tcp_close_syn_sent_releases_slot() -> TestResult {
 
 pub fn test_tcp_abort_sends_rst() -> TestResult {
     reset();
-    let (idx, _) = tcp::tcp_connect([10, 0, 0, 1], [10, 0, 0, 2], 80).unwrap();
+    let (idx, _) = tcp::tcp_connect([10, 0, 0, 1], CLIENT_PORT, [10, 0, 0, 2], 80).unwrap();
     let result = tcp::tcp_abort(idx).unwrap();
     assert_test!(result.is_some(), "RST segment expected");
     let seg = result.unwrap();
@@ -549,7 +554,7 @@ pub fn test_tcp_abort_sends_rst() -> TestResult {
 
 pub fn test_tcp_abort_listen_no_rst() -> TestResult {
     reset();
-    let idx = tcp::tcp_listen([0; 4], 80).unwrap();
+    let idx = tcp::tcp_listen([0; 4], 80, false).unwrap();
     let result = tcp::tcp_abort(idx).unwrap();
     assert_test!(result.is_none(), "no RST for LISTEN");
     assert_eq_test!(tcp::tcp_active_count(), 0, "released");
@@ -575,7 +580,7 @@ pub fn test_tcp_active_handshake_complete() -> TestResult {
     let remote_ip = [10, 0, 0, 2];
 
     // Step 1: Client sends SYN.
-    let (idx, syn_seg) = tcp::tcp_connect(local_ip, remote_ip, 80).unwrap();
+    let (idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80).unwrap();
     assert_eq_test!(tcp::tcp_get_state(idx), Some(TcpState::SynSent), "SYN_SENT");
 
     let client_iss = syn_seg.seq_num;
@@ -617,7 +622,7 @@ pub fn test_tcp_active_rst_in_syn_sent() -> TestResult {
     let local_ip = [10, 0, 0, 1];
     let remote_ip = [10, 0, 0, 2];
 
-    let (_idx, syn_seg) = tcp::tcp_connect(local_ip, remote_ip, 80).unwrap();
+    let (_idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80).unwrap();
     let client_iss = syn_seg.seq_num;
     let client_port = syn_seg.tuple.local_port;
 
@@ -650,7 +655,7 @@ pub fn test_tcp_active_bad_ack_in_syn_sent() -> TestResult {
     let local_ip = [10, 0, 0, 1];
     let remote_ip = [10, 0, 0, 2];
 
-    let (idx, syn_seg) = tcp::tcp_connect(local_ip, remote_ip, 80).unwrap();
+    let (idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80).unwrap();
     let client_port = syn_seg.tuple.local_port;
 
     // Peer sends SYN+ACK with wrong ack_num.
@@ -686,7 +691,7 @@ pub fn test_tcp_active_mss_negotiation() -> TestResult {
     let local_ip = [10, 0, 0, 1];
     let remote_ip = [10, 0, 0, 2];
 
-    let (idx, syn_seg) = tcp::tcp_connect(local_ip, remote_ip, 80).unwrap();
+    let (idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80).unwrap();
     let client_port = syn_seg.tuple.local_port;
     let client_iss = syn_seg.seq_num;
 
@@ -722,7 +727,7 @@ pub fn test_tcp_passive_handshake_complete() -> TestResult {
     let client_ip = [10, 0, 0, 2];
 
     // Step 1: Server listens.
-    let listen_idx = tcp::tcp_listen(server_ip, 80).unwrap();
+    let listen_idx = tcp::tcp_listen(server_ip, 80, false).unwrap();
 
     // Step 2: Client sends SYN.
     let client_iss = 3000u32;
@@ -796,7 +801,7 @@ pub fn test_tcp_passive_rst_in_syn_received() -> TestResult {
     let server_ip = [10, 0, 0, 1];
     let client_ip = [10, 0, 0, 2];
 
-    tcp::tcp_listen(server_ip, 80).unwrap();
+    tcp::tcp_listen(server_ip, 80, false).unwrap();
 
     // Client SYN.
     let syn = TcpHeader {
@@ -834,9 +839,49 @@ pub fn test_tcp_passive_rst_in_syn_received() -> TestResult {
     pass!()
 }
 
+pub fn test_tcp_listen_backlog_drops_syn() -> TestResult {
+    reset();
+    let server_ip = [10, 0, 0, 1];
+    let client_ip = [10, 0, 0, 2];
+    let listen_idx = tcp::tcp_listen(server_ip, 80, false).unwrap();
+    tcp::tcp_set_backlog(listen_idx, 1);
+
+    let syn = |src_port| TcpHeader {
+        src_port,
+        dst_port: 80,
+        seq_num: 1000,
+        ack_num: 0,
+        data_offset: 5,
+        flags: TCP_FLAG_SYN,
+        window_size: 8192,
+        checksum: 0,
+        urgent_ptr: 0,
+    };
+    let result = tcp::tcp_input(client_ip, server_ip, &syn(50000), &[], &[], 0);
+    let Some(child_idx) = result.accepted_idx else {
+        return fail!("first SYN not admitted");
+    };
+    assert_eq_test!(tcp::tcp_unaccepted_child(listen_idx), Some(child_idx));
+
+    // A half-open connection fills a backlog of one.
+    let result = tcp::tcp_input(client_ip, server_ip, &syn(50001), &[], &[], 0);
+    assert_test!(
+        result.accepted_idx.is_none(),
+        "SYN past the backlog admitted"
+    );
+    assert_test!(result.response.is_none(), "dropped SYN answered");
+
+    // Accepting the child makes room.
+    tcp::tcp_set_socket_idx(child_idx, Some(7));
+    assert_eq_test!(tcp::tcp_unaccepted_child(listen_idx), None);
+    let result = tcp::tcp_input(client_ip, server_ip, &syn(50001), &[], &[], 0);
+    assert_test!(result.accepted_idx.is_some(), "SYN after accept dropped");
+    pass!()
+}
+
 pub fn test_tcp_passive_ack_to_listen_sends_rst() -> TestResult {
     reset();
-    tcp::tcp_listen([10, 0, 0, 1], 80).unwrap();
+    tcp::tcp_listen([10, 0, 0, 1], 80, false).unwrap();
 
     // Random ACK to a LISTEN socket → should get RST.
     let ack = TcpHeader {
@@ -867,7 +912,7 @@ fn establish_client_connection(
     remote_ip: [u8; 4],
     remote_port: u16,
 ) -> (usize, u32, u16) {
-    let (idx, syn_seg) = tcp::tcp_connect(local_ip, remote_ip, remote_port).unwrap();
+    let (idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, remote_port).unwrap();
     let client_iss = syn_seg.seq_num;
     let client_port = syn_seg.tuple.local_port;
 
@@ -1253,6 +1298,235 @@ pub fn test_tcp_time_wait_timer() -> TestResult {
     pass!()
 }
 
+/// Segment from the peer of a connection made by `establish_client_connection`.
+fn peer_segment(client_port: u16, seq: u32, ack: u32, flags: u8) -> TcpHeader {
+    TcpHeader {
+        src_port: 80,
+        dst_port: client_port,
+        seq_num: seq,
+        ack_num: ack,
+        data_offset: 5,
+        flags,
+        window_size: 32768,
+        checksum: 0,
+        urgent_ptr: 0,
+    }
+}
+
+/// Establish a client connection and walk it through an active close into
+/// TIME_WAIT at `now_ms`.  Returns (idx, server_iss, client_port).
+fn time_wait_connection(local_ip: [u8; 4], remote_ip: [u8; 4], now_ms: u64) -> (usize, u32, u16) {
+    let (idx, server_iss, client_port) = establish_client_connection(local_ip, remote_ip, 80);
+    let fin_seq = tcp::tcp_close(idx).unwrap().unwrap().seq_num;
+    let fin_ack = peer_segment(
+        client_port,
+        server_iss.wrapping_add(1),
+        fin_seq.wrapping_add(1),
+        TCP_FLAG_FIN | TCP_FLAG_ACK,
+    );
+    tcp::tcp_input(remote_ip, local_ip, &fin_ack, &[], &[], now_ms);
+    (idx, server_iss, client_port)
+}
+
+pub fn test_tcp_close_fin_follows_queued_data() -> TestResult {
+    reset();
+    let local_ip = [10, 0, 0, 1];
+    let remote_ip = [10, 0, 0, 2];
+    let (idx, server_iss, client_port) = establish_client_connection(local_ip, remote_ip, 80);
+
+    assert_eq_test!(tcp::tcp_send(idx, b"bye").unwrap(), 3, "queued");
+    let data_seq = tcp::tcp_get_connection(idx).unwrap().snd_nxt;
+    assert_test!(
+        tcp::tcp_close(idx).unwrap().is_none(),
+        "FIN sent ahead of data"
+    );
+    assert_eq_test!(
+        tcp::tcp_get_state(idx),
+        Some(TcpState::FinWait1),
+        "FIN_WAIT_1"
+    );
+    assert_test!(tcp::tcp_fin_pending(idx), "FIN pending");
+
+    let mut payload = [0u8; 64];
+    let Some((data, len)) = tcp::tcp_poll_transmit(idx, &mut payload, 0) else {
+        return fail!("queued data not sent after close");
+    };
+    assert_eq_test!(len, 3, "data length");
+    assert_eq_test!(data.seq_num, data_seq, "data seq");
+    assert_test!(data.flags & TCP_FLAG_FIN == 0, "FIN on data segment");
+
+    // An ACK covering the data alone must not count as acking the FIN.
+    let ack = peer_segment(
+        client_port,
+        server_iss.wrapping_add(1),
+        data_seq.wrapping_add(3),
+        TCP_FLAG_ACK,
+    );
+    tcp::tcp_input(remote_ip, local_ip, &ack, &[], &[], 0);
+    assert_eq_test!(
+        tcp::tcp_get_state(idx),
+        Some(TcpState::FinWait1),
+        "data ACK treated as FIN ACK"
+    );
+
+    let Some((fin, len)) = tcp::tcp_poll_transmit(idx, &mut payload, 0) else {
+        return fail!("FIN not sent after data");
+    };
+    assert_eq_test!(len, 0, "FIN carries no payload");
+    assert_test!(fin.flags & TCP_FLAG_FIN != 0, "FIN flag");
+    assert_eq_test!(fin.seq_num, data_seq.wrapping_add(3), "FIN seq");
+    assert_test!(
+        tcp::tcp_poll_transmit(idx, &mut payload, 0).is_none(),
+        "FIN sent twice"
+    );
+    pass!()
+}
+
+pub fn test_tcp_fin_retransmitted_on_timeout() -> TestResult {
+    reset();
+    let (idx, _server_iss, _client_port) =
+        establish_client_connection([10, 0, 0, 1], [10, 0, 0, 2], 80);
+    let fin_seq = tcp::tcp_close(idx).unwrap().unwrap().seq_num;
+
+    assert_eq_test!(
+        tcp::tcp_on_retransmit(idx as u32),
+        Some(idx),
+        "unacked FIN not retransmitted"
+    );
+    let mut payload = [0u8; 64];
+    let Some((fin, _)) = tcp::tcp_poll_transmit(idx, &mut payload, 0) else {
+        return fail!("no FIN after retransmit timeout");
+    };
+    assert_test!(fin.flags & TCP_FLAG_FIN != 0, "FIN flag");
+    assert_eq_test!(fin.seq_num, fin_seq, "FIN resent at original seq");
+    pass!()
+}
+
+pub fn test_tcp_orphan_fin_wait2_reaped() -> TestResult {
+    reset();
+    let local_ip = [10, 0, 0, 1];
+    let remote_ip = [10, 0, 0, 2];
+    let (idx, server_iss, client_port) = establish_client_connection(local_ip, remote_ip, 80);
+    let fin_seq = tcp::tcp_close(idx).unwrap().unwrap().seq_num;
+    let ack = peer_segment(
+        client_port,
+        server_iss.wrapping_add(1),
+        fin_seq.wrapping_add(1),
+        TCP_FLAG_ACK,
+    );
+    tcp::tcp_input(remote_ip, local_ip, &ack, &[], &[], 0);
+    assert_eq_test!(
+        tcp::tcp_get_state(idx),
+        Some(TcpState::FinWait2),
+        "FIN_WAIT_2"
+    );
+
+    // The peer never sends its FIN.
+    let closed_at = tcp::tcp_get_connection(idx).unwrap().close_start_ms;
+    assert_eq_test!(
+        tcp::tcp_reap_orphans(closed_at + FIN_TIMEOUT_MS - 1),
+        0,
+        "reaped early"
+    );
+    assert_eq_test!(
+        tcp::tcp_reap_orphans(closed_at + FIN_TIMEOUT_MS),
+        1,
+        "orphan kept"
+    );
+    assert_eq_test!(tcp::tcp_active_count(), 0, "released");
+    pass!()
+}
+
+pub fn test_tcp_time_wait_is_bounded() -> TestResult {
+    reset();
+    let local_ip = [10, 0, 0, 1];
+    let mut first = None;
+    for i in 0..=MAX_TIME_WAIT {
+        let remote_ip = [10, 0, 1, i as u8];
+        let (idx, _, _) = time_wait_connection(local_ip, remote_ip, 1000 + i as u64);
+        first.get_or_insert(idx);
+    }
+
+    let in_time_wait = (0..MAX_CONNECTIONS)
+        .filter(|&i| tcp::tcp_get_state(i) == Some(TcpState::TimeWait))
+        .count();
+    assert_eq_test!(in_time_wait, MAX_TIME_WAIT, "TIME_WAIT not capped");
+    assert_eq_test!(
+        tcp::tcp_get_state(first.unwrap()),
+        None,
+        "oldest TIME_WAIT not recycled"
+    );
+    pass!()
+}
+
+pub fn test_tcp_listen_reuse_addr_over_time_wait() -> TestResult {
+    reset();
+    let local_ip = [10, 0, 0, 1];
+    let (idx, _, client_port) = time_wait_connection(local_ip, [10, 0, 0, 2], 1000);
+    assert_eq_test!(
+        tcp::tcp_get_state(idx),
+        Some(TcpState::TimeWait),
+        "TIME_WAIT"
+    );
+
+    match tcp::tcp_listen(local_ip, client_port, false) {
+        Err(TcpError::AddrInUse) => {}
+        other => return fail!("expected AddrInUse, got {:?}", other),
+    }
+    let Ok(listen_idx) = tcp::tcp_listen(local_ip, client_port, true) else {
+        return fail!("SO_REUSEADDR listen blocked by TIME_WAIT");
+    };
+    match tcp::tcp_listen([0; 4], client_port, true) {
+        Err(TcpError::AddrInUse) => {}
+        other => return fail!("second listener allowed: {:?}", other),
+    }
+    assert_eq_test!(
+        tcp::tcp_get_state(listen_idx),
+        Some(TcpState::Listen),
+        "LISTEN"
+    );
+    pass!()
+}
+
+pub fn test_tcp_syn_reopens_time_wait() -> TestResult {
+    reset();
+    let local_ip = [10, 0, 0, 1];
+    let remote_ip = [10, 0, 0, 2];
+    let (idx, server_iss, client_port) = time_wait_connection(local_ip, remote_ip, 1000);
+    let listen_idx = tcp::tcp_listen([0; 4], client_port, true).unwrap();
+
+    // A stale SYN inside the old sequence space is ignored.
+    let stale = peer_segment(client_port, server_iss, 0, TCP_FLAG_SYN);
+    tcp::tcp_input(remote_ip, local_ip, &stale, &[], &[], 2000);
+    assert_eq_test!(
+        tcp::tcp_get_state(idx),
+        Some(TcpState::TimeWait),
+        "stale SYN ended TIME_WAIT"
+    );
+
+    let fresh = peer_segment(
+        client_port,
+        server_iss.wrapping_add(100_000),
+        0,
+        TCP_FLAG_SYN,
+    );
+    let result = tcp::tcp_input(remote_ip, local_ip, &fresh, &[], &[], 2000);
+    let Some(response) = result.response else {
+        return fail!("no reply to SYN on TIME_WAIT tuple");
+    };
+    assert_eq_test!(
+        response.flags,
+        TCP_FLAG_SYN | TCP_FLAG_ACK,
+        "listener did not answer"
+    );
+    assert_eq_test!(
+        tcp::tcp_get_state(listen_idx),
+        Some(TcpState::Listen),
+        "listener intact"
+    );
+    pass!()
+}
+
 // =============================================================================
 // 11. RST handling in various states
 // =============================================================================
@@ -1357,20 +1631,24 @@ pub fn test_tcp_segment_no_connection_sends_rst() -> TestResult {
 }
 
 // =============================================================================
-// 13. Ephemeral port allocation
+// 13. Local port selection
 // =============================================================================
 
-pub fn test_tcp_ephemeral_ports_unique() -> TestResult {
+pub fn test_tcp_connect_rejects_duplicate_tuple() -> TestResult {
     reset();
-    let p1 = tcp::alloc_ephemeral_port();
-    let p2 = tcp::alloc_ephemeral_port();
-    let p3 = tcp::alloc_ephemeral_port();
-    assert_test!(p1 >= 49152, "p1 in range");
-    assert_test!(p2 >= 49152, "p2 in range");
-    assert_test!(p3 >= 49152, "p3 in range");
-    assert_test!(p1 != p2, "p1 != p2");
-    assert_test!(p2 != p3, "p2 != p3");
-    assert_test!(p1 != p3, "p1 != p3");
+    let local_ip = [10, 0, 0, 1];
+    let remote_ip = [10, 0, 0, 2];
+    let (idx, syn) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80).unwrap();
+    assert_eq_test!(syn.tuple.local_port, CLIENT_PORT, "caller's port used");
+
+    match tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80) {
+        Err(TcpError::AddrInUse) => {}
+        other => return fail!("duplicate tuple allowed: {:?}", other),
+    }
+    let Ok((other, _)) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 81) else {
+        return fail!("distinct remote port rejected");
+    };
+    assert_test!(other != idx, "distinct slots");
     pass!()
 }
 
@@ -1426,7 +1704,7 @@ pub fn test_tcp_state_is_closing() -> TestResult {
 
 pub fn test_tcp_find_exact_match() -> TestResult {
     reset();
-    let (idx, syn_seg) = tcp::tcp_connect([10, 0, 0, 1], [10, 0, 0, 2], 80).unwrap();
+    let (idx, syn_seg) = tcp::tcp_connect([10, 0, 0, 1], CLIENT_PORT, [10, 0, 0, 2], 80).unwrap();
     let tuple = TcpTuple {
         local_ip: [10, 0, 0, 1],
         local_port: syn_seg.tuple.local_port,
@@ -1440,7 +1718,7 @@ pub fn test_tcp_find_exact_match() -> TestResult {
 
 pub fn test_tcp_find_wildcard_listen() -> TestResult {
     reset();
-    let listen_idx = tcp::tcp_listen([0; 4], 80).unwrap();
+    let listen_idx = tcp::tcp_listen([0; 4], 80, false).unwrap();
 
     // A connection from any IP to port 80 should match the wildcard listener.
     let tuple = TcpTuple {
@@ -1512,7 +1790,7 @@ pub fn test_tcp_simultaneous_open() -> TestResult {
     let local_ip = [10, 0, 0, 1];
     let remote_ip = [10, 0, 0, 2];
 
-    let (_idx, syn_seg) = tcp::tcp_connect(local_ip, remote_ip, 80).unwrap();
+    let (_idx, syn_seg) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote_ip, 80).unwrap();
     let client_port = syn_seg.tuple.local_port;
 
     // Peer also sends SYN (without ACK — simultaneous open).
@@ -1552,7 +1830,7 @@ pub fn test_tcp_multiple_connections() -> TestResult {
     let mut indices = [0usize; 10];
     for i in 0..10 {
         let remote = [10, 0, (i / 256) as u8, (i % 256 + 1) as u8];
-        let (idx, _) = tcp::tcp_connect(local_ip, remote, (80 + i) as u16).unwrap();
+        let (idx, _) = tcp::tcp_connect(local_ip, CLIENT_PORT, remote, (80 + i) as u16).unwrap();
         indices[i] = idx;
     }
     assert_eq_test!(tcp::tcp_active_count(), 10, "10 active connections");
@@ -1647,9 +1925,10 @@ slopos_lib::define_test_suite!(
         test_tcp_active_rst_in_syn_sent,
         test_tcp_active_bad_ack_in_syn_sent,
         test_tcp_active_mss_negotiation,
-        // Passive open handshake (3)
+        // Passive open handshake (4)
         test_tcp_passive_handshake_complete,
         test_tcp_passive_rst_in_syn_received,
+        test_tcp_listen_backlog_drops_syn,
         test_tcp_passive_ack_to_listen_sends_rst,
         // Connection teardown (3)
         test_tcp_active_close,
@@ -1660,6 +1939,13 @@ slopos_lib::define_test_suite!(
         test_tcp_time_wait_retransmitted_fin,
         test_tcp_retransmit_timer,
         test_tcp_time_wait_timer,
+        // Teardown hygiene (6)
+        test_tcp_close_fin_follows_queued_data,
+        test_tcp_fin_retransmitted_on_timeout,
+        test_tcp_orphan_fin_wait2_reaped,
+        test_tcp_time_wait_is_bounded,
+        test_tcp_listen_reuse_addr_over_time_wait,
+        test_tcp_syn_reopens_time_wait,
         // RST handling (3)
         test_tcp_rst_in_established,
         test_tcp_rst_to_unknown_ignored,
@@ -1667,7 +1953,7 @@ slopos_lib::define_test_suite!(
         // Misc (1)
         test_tcp_segment_no_connection_sends_rst,
         // Ephemeral ports (1)
-        test_tcp_ephemeral_ports_unique,
+        test_tcp_connect_rejects_duplicate_tuple,
         // State helpers (3)
         test_tcp_state_names,
         test_tcp_state_is_open,
@@ -12,7 +12,10 @@
 //! the driver identifier while holding the per-TTY lock, drops the lock, and
 //! then writes the processed output via `write_driver_unlocked`.
 
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use slopos_abi::syscall::{TtyIndex, UserTermios};
+use slopos_lib::IrqMutex;
 use slopos_lib::ports::COM1;
 
 use crate::serial;
@@ -159,6 +162,9 @@ pub fn write_driver_unlocked(driver: DriverId, data: &[u8]) {
             for &b in data {
                 serial::serial_putc_com1(b);
             }
+            if CAPTURE_ENABLED.load(Ordering::Relaxed) {
+                CONSOLE_CAPTURE.lock().append(data);
+            }
         }
         DriverId::PtyMaster { slave_idx } => {
             pty::master_write(slave_idx, data);
@@ -171,13 +177,71 @@ pub fn write_driver_unlocked(driver: DriverId, data: &[u8]) {
 }
 
 // ---------------------------------------------------------------------------
-// Serial console driver — wraps COM1 UART polling-based I/O
+// Console capture — a window of recent console output for scripted checks
+// ---------------------------------------------------------------------------
+
+/// Bytes of console output kept while a capture is running.
+const CAPTURE_LEN: usize = 4096;
+
+struct ConsoleCapture {
+    buf: [u8; CAPTURE_LEN],
+    len: usize,
+}
+
+impl ConsoleCapture {
+    const fn new() -> Self {
+        Self {
+            buf: [0; CAPTURE_LEN],
+            len: 0,
+        }
+    }
+
+    /// Append `data`, dropping the oldest bytes once the window is full.
+    fn append(&mut self, data: &[u8]) {
+        let data = &data[data.len().saturating_sub(CAPTURE_LEN)..];
+        let overflow = (self.len + data.len()).saturating_sub(CAPTURE_LEN);
+        if overflow > 0 {
+            self.buf.copy_within(overflow..self.len, 0);
+            self.len -= overflow;
+        }
+        self.buf[self.len..self.len + data.len()].copy_from_slice(data);
+        self.len += data.len();
+    }
+}
+
+static CAPTURE_ENABLED: AtomicBool = AtomicBool::new(false);
+static CONSOLE_CAPTURE: IrqMutex<ConsoleCapture> = IrqMutex::new(ConsoleCapture::new());
+
+/// Start recording console output, discarding anything captured before.
+pub fn console_capture_start() {
+    CONSOLE_CAPTURE.lock().len = 0;
+    CAPTURE_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stop recording console output.
+pub fn console_capture_stop() {
+    CAPTURE_ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Whether the console output captured since `console_capture_start`
+/// (within the last `CAPTURE_LEN` bytes) contains `needle`.
+pub fn console_capture_contains(needle: &[u8]) -> bool {
+    let capture = CONSOLE_CAPTURE.lock();
+    needle.is_empty()
+        || capture.buf[..capture.len]
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+// ---------------------------------------------------------------------------
+// Serial console driver — wraps COM1 UART I/O
 // ---------------------------------------------------------------------------
 
 /// Driver backend for COM1 serial console (TTY 0).
 ///
-/// Output goes through `serial_putc_com1`.  Input is polled from the serial
-/// UART's `INPUT_BUFFER` ring via `serial_poll_receive` + buffer drain.
+/// Output goes through `serial_putc_com1`.  Input is drained from the serial
+/// UART's `INPUT_BUFFER` ring, which the COM1 receive interrupt fills; the
+/// UART is also polled here in case the interrupt is not routed.
 pub struct SerialConsoleDriver;
 
 impl TtyDriver for SerialConsoleDriver {
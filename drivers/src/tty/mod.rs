@@ -52,6 +52,8 @@ use self::table::{TTY_INPUT_WAITERS, TTY_SLOTS};
 /// definition used across the entire kernel.
 pub use slopos_abi::syscall::TtyIndex;
 
+pub use self::driver::{console_capture_contains, console_capture_start, console_capture_stop};
+
 /// Maximum number of TTY instances.
 pub const MAX_TTYS: usize = 8;
 
@@ -165,6 +167,9 @@ impl Tty {
 // Active TTY tracking (for keyboard input routing)
 // ---------------------------------------------------------------------------
 
+/// The serial console on COM1, fed by its receive interrupt.
+pub const SERIAL_CONSOLE_TTY: TtyIndex = TtyIndex(0);
+
 /// The currently active TTY index (receives keyboard input).
 /// Defaults to 0 (serial console).
 static ACTIVE_TTY: AtomicU8 = AtomicU8::new(0);
@@ -784,9 +789,9 @@ pub fn set_foreground_pgrp_checked(
     }
 }
 
-/// Get window size for a specific TTY.
+/// Get window size for a specific TTY; a pty master reports its slave's.
 pub fn get_winsize(idx: TtyIndex) -> Result<UserWinsize, TtyError> {
-    let slot = idx.0 as usize;
+    let slot = pty::winsize_tty(idx).0 as usize;
     if slot >= MAX_TTYS {
         return Err(TtyError::InvalidIndex);
     }
@@ -797,12 +802,13 @@ pub fn get_winsize(idx: TtyIndex) -> Result<UserWinsize, TtyError> {
     }
 }
 
-/// Set window size for a specific TTY.
+/// Set window size for a specific TTY; setting it on a pty master sets
+/// the slave's.
 ///
 /// If the new size differs from the old size, sends SIGWINCH to the
 /// foreground process group so applications can re-query dimensions.
 pub fn set_winsize(idx: TtyIndex, ws: &UserWinsize) -> Result<(), TtyError> {
-    let slot = idx.0 as usize;
+    let slot = pty::winsize_tty(idx).0 as usize;
     if slot >= MAX_TTYS {
         return Err(TtyError::InvalidIndex);
     }
@@ -1102,6 +1108,36 @@ pub fn is_hung_up(idx: TtyIndex) -> bool {
 // Idle callback (Phase 8: iterates ALL active TTYs)
 // ---------------------------------------------------------------------------
 
+/// Drain a TTY's hardware input into its line discipline and wake readers.
+///
+/// Called from the COM1 receive interrupt and the idle loop.  A signal
+/// raised by the line discipline (Ctrl+C, Ctrl+Z, Ctrl+\) is delivered to
+/// the foreground process group once the per-TTY lock is dropped.
+///
+/// Returns whether cooked input is now available.
+pub fn service_hw_input(idx: TtyIndex) -> bool {
+    let slot = idx.0 as usize;
+    if slot >= MAX_TTYS {
+        return false;
+    }
+    let (deferred_signal, has_data) = {
+        let mut guard = TTY_SLOTS[slot].lock();
+        match guard.as_mut() {
+            Some(tty) if tty.active => (tty.drain_hw_input(), tty.ldisc.has_data()),
+            _ => return false,
+        }
+    };
+    if let Some((pgid, sig)) = deferred_signal
+        && pgid != 0
+    {
+        let _ = signal_process_group(pgid, sig);
+    }
+    if has_data {
+        notify_input_ready(idx);
+    }
+    has_data
+}
+
 /// Idle-loop callback: drain hardware input and wake blocked readers.
 ///
 /// Phase 8: now iterates all active TTYs instead of only TTY 0.  Each
@@ -1109,23 +1145,7 @@ pub fn is_hung_up(idx: TtyIndex) -> bool {
 fn input_available_cb() -> c_int {
     let mut any_data = false;
     for i in 0..MAX_TTYS {
-        let has_data = {
-            let mut guard = TTY_SLOTS[i].lock();
-            if let Some(tty) = guard.as_mut() {
-                if tty.active {
-                    let _ = tty.drain_hw_input();
-                    tty.ldisc.has_data()
-                } else {
-                    false
-                }
-            } else {
-                false
-            }
-        };
-        if has_data {
-            notify_input_ready(TtyIndex(i as u8));
-            any_data = true;
-        }
+        any_data |= service_hw_input(TtyIndex(i as u8));
     }
     any_data as c_int
 }
@@ -82,6 +82,22 @@ pub fn get_pty_number(idx: TtyIndex) -> Result<u32, TtyError> {
     }
 }
 
+/// The TTY that holds the window size for `idx`.  A master shares its
+/// slave's, so the program drawing the screen can resize the terminal and
+/// the slave's foreground job gets the SIGWINCH.
+pub fn winsize_tty(idx: TtyIndex) -> TtyIndex {
+    let slot = idx.0 as usize;
+    if slot >= MAX_TTYS {
+        return idx;
+    }
+
+    let guard = TTY_SLOTS[slot].lock();
+    match guard.as_ref().map(|tty| &tty.driver) {
+        Some(TtyDriverKind::PtyMaster { slave_idx }) => *slave_idx,
+        _ => idx,
+    }
+}
+
 pub fn mark_peer_closed(idx: TtyIndex) {
     let slot = idx.0 as usize;
     if slot >= MAX_TTYS {
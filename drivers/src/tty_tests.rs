@@ -9,6 +9,7 @@
 //! Phase 6 additions: compositor focus / fg_pgrp split, check_read() as sole
 //! read gate, TtyIndex type safety, signal constant verification.
 
+use slopos_abi::input::{InputEventType, KEY_MOD_SHIFT, SCANCODE_HOME};
 use slopos_abi::signal::{SIGCONT, SIGHUP, SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU, SIGWINCH};
 use slopos_lib::klog_info;
 use slopos_lib::testing::TestResult;
@@ -1458,6 +1459,57 @@ pub fn test_keyboard_no_input_event_delivery() -> TestResult {
     TestResult::Pass
 }
 
+/// A focused task that takes keys gets key presses as events, with the
+/// typed character and modifiers, and the TTY gets nothing.
+pub fn test_keyboard_delivered_to_key_taking_task() -> TestResult {
+    tty::table::tty_table_init();
+    tty::set_active_tty(TtyIndex(0));
+    drain_tty_nonblock(TtyIndex(0));
+    let saved = tty::get_termios(TtyIndex(0)).unwrap();
+    let mut raw = saved;
+    raw.c_lflag &= !slopos_abi::syscall::ICANON;
+    tty::set_termios(TtyIndex(0), &raw).unwrap();
+
+    let task: u32 = 9998;
+    crate::input_event::input_take_keys(task);
+    crate::input_event::input_set_keyboard_focus(task);
+
+    // Shift+A, then Home (E0 47).
+    crate::ps2::keyboard::handle_scancode(0x2A);
+    crate::ps2::keyboard::handle_scancode(0x1E);
+    crate::ps2::keyboard::handle_scancode(0xAA);
+    crate::ps2::keyboard::handle_scancode(0xE0);
+    crate::ps2::keyboard::handle_scancode(0x47);
+
+    let letter = crate::input_event::input_poll(task);
+    let home = crate::input_event::input_poll(task);
+    crate::input_event::input_set_keyboard_focus(0);
+    crate::input_event::input_cleanup_task(task);
+    let mut out = [0u8; 8];
+    let n = tty::read(TtyIndex(0), &mut out, true);
+    tty::set_termios(TtyIndex(0), &saved).unwrap();
+
+    let (Some(letter), Some(home)) = (letter, home) else {
+        klog_info!("TTY_TEST: BUG - key presses not queued for the task");
+        return TestResult::Fail;
+    };
+    if letter.event_type != InputEventType::KeyPress
+        || letter.key_scancode() != 0x1E
+        || letter.key_ascii() != b'A'
+        || letter.key_modifiers() & KEY_MOD_SHIFT == 0
+        || home.key_scancode() != SCANCODE_HOME
+        || home.key_ascii() != 0
+        || home.key_modifiers() != 0
+    {
+        return TestResult::Fail;
+    }
+    if matches!(n, Ok(v) if v > 0) {
+        klog_info!("TTY_TEST: BUG - key taken by a task also reached the TTY");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
 /// Phase 3: Break codes (key release) do not produce TTY input.
 pub fn test_keyboard_break_code_no_input() -> TestResult {
     tty::table::tty_table_init();
@@ -4072,6 +4124,112 @@ pub fn test_phase17_pty_canonical_editing_on_slave() -> TestResult {
     TestResult::Pass
 }
 
+pub fn test_phase17_master_winsize_is_slave_winsize() -> TestResult {
+    tty::table::tty_table_init();
+
+    let master = tty::pty_alloc().unwrap();
+    let slave = TtyIndex(tty::get_pty_number(master).unwrap() as u8);
+    tty::open_ref(master).unwrap();
+    tty::open_ref(slave).unwrap();
+
+    let ws = slopos_abi::syscall::UserWinsize {
+        ws_row: 30,
+        ws_col: 100,
+        ws_xpixel: 800,
+        ws_ypixel: 480,
+    };
+    let set_rc = tty::set_winsize(master, &ws);
+    let on_slave = tty::get_winsize(slave);
+    let on_master = tty::get_winsize(master);
+
+    let _ = tty::close_ref(slave);
+    let _ = tty::close_ref(master);
+
+    let matches = |got: Result<slopos_abi::syscall::UserWinsize, TtyError>| {
+        got.is_ok_and(|got| got.ws_row == 30 && got.ws_col == 100)
+    };
+    if set_rc.is_err() || !matches(on_slave) || !matches(on_master) {
+        klog_info!("TTY_TEST: BUG - winsize set on PTY master not seen through the slave");
+        return TestResult::Fail;
+    }
+
+    TestResult::Pass
+}
+
+// ===========================================================================
+// Serial console receive path
+// ===========================================================================
+
+/// Queue bytes as if the COM1 receive interrupt had pulled them off the UART.
+fn inject_serial_rx(bytes: &[u8]) {
+    let mut buf = crate::serial::input_buffer_lock();
+    for &b in bytes {
+        let _ = buf.try_push(b);
+    }
+}
+
+/// Serial input is cooked by the console's line discipline: CR ends the
+/// line and DEL erases, as sent by a terminal on `-serial stdio`.
+pub fn test_serial_rx_cooked_by_console_ldisc() -> TestResult {
+    tty::table::tty_table_init();
+    let console = tty::SERIAL_CONSOLE_TTY;
+    drain_tty_nonblock(console);
+
+    let saved = tty::get_termios(console).unwrap();
+    let mut no_echo = saved;
+    no_echo.c_lflag &= !slopos_abi::syscall::ECHO;
+    tty::set_termios(console, &no_echo).unwrap();
+
+    inject_serial_rx(b"lx\x7fs\r");
+    let ready = tty::service_hw_input(console);
+    let mut buf = [0u8; 16];
+    let got = tty::read(console, &mut buf, true);
+
+    tty::set_termios(console, &saved).unwrap();
+
+    if !ready || got != Ok(3) || &buf[..3] != b"ls\n" {
+        klog_info!(
+            "TTY_TEST: BUG - serial RX not cooked (ready={}, read={:?}, buf={:?})",
+            ready,
+            got,
+            &buf[..3]
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+/// Ctrl+C from the serial line is a signal, not data: the partial line is
+/// discarded and nothing becomes readable.
+pub fn test_serial_rx_ctrl_c_discards_line() -> TestResult {
+    tty::table::tty_table_init();
+    let console = tty::SERIAL_CONSOLE_TTY;
+    drain_tty_nonblock(console);
+
+    inject_serial_rx(b"sleep 9\x03");
+    let ready = tty::service_hw_input(console);
+    let has = tty::has_data(console);
+
+    if ready || has {
+        klog_info!(
+            "TTY_TEST: BUG - Ctrl+C left input pending (ready={}, has_data={})",
+            ready,
+            has
+        );
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+/// Servicing an out-of-range TTY is a no-op.
+pub fn test_service_hw_input_invalid_index() -> TestResult {
+    if tty::service_hw_input(TtyIndex(255)) {
+        klog_info!("TTY_TEST: BUG - service_hw_input(255) reported data");
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
 // ===========================================================================
 // Test suite registration
 // ===========================================================================
@@ -4155,6 +4313,7 @@ slopos_lib::define_test_suite!(
         test_tty_write_returns_input_len,
         // Phase 3: Input pipeline cleanup
         test_keyboard_no_input_event_delivery,
+        test_keyboard_delivered_to_key_taking_task,
         test_keyboard_break_code_no_input,
         test_keyboard_modifier_no_input,
         test_keyboard_press_release_single_char,
@@ -4268,5 +4427,9 @@ slopos_lib::define_test_suite!(
         test_phase17_master_close_hangs_up_slave,
         test_phase17_slave_close_returns_master_eof,
         test_phase17_pty_canonical_editing_on_slave,
+        test_phase17_master_winsize_is_slave_winsize,
+        test_serial_rx_cooked_by_console_ldisc,
+        test_serial_rx_ctrl_c_discards_line,
+        test_service_hw_input_invalid_index,
     ]
 );
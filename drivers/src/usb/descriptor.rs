@@ -0,0 +1,173 @@
+//! USB standard requests and descriptor parsing (USB 2.0, chapter 9).
+
+pub const DESC_DEVICE: u8 = 1;
+pub const DESC_CONFIGURATION: u8 = 2;
+pub const DESC_INTERFACE: u8 = 4;
+pub const DESC_ENDPOINT: u8 = 5;
+
+pub const DEVICE_DESC_LEN: usize = 18;
+pub const CONFIG_DESC_LEN: usize = 9;
+
+const REQ_GET_DESCRIPTOR: u8 = 0x06;
+const REQ_SET_CONFIGURATION: u8 = 0x09;
+const HID_REQ_SET_IDLE: u8 = 0x0A;
+const HID_REQ_SET_PROTOCOL: u8 = 0x0B;
+
+const REQTYPE_DEVICE_IN: u8 = 0x80;
+const REQTYPE_DEVICE_OUT: u8 = 0x00;
+const REQTYPE_CLASS_INTERFACE_OUT: u8 = 0x21;
+
+const CLASS_HID: u8 = 0x03;
+const HID_SUBCLASS_BOOT: u8 = 0x01;
+pub const HID_PROTOCOL_KEYBOARD: u8 = 0x01;
+pub const HID_PROTOCOL_MOUSE: u8 = 0x02;
+
+const ENDPOINT_DIR_IN: u8 = 0x80;
+const ENDPOINT_XFER_INTERRUPT: u8 = 0x03;
+
+/// The 8-byte setup stage of a control transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SetupPacket {
+    pub request_type: u8,
+    pub request: u8,
+    pub value: u16,
+    pub index: u16,
+    pub length: u16,
+}
+
+impl SetupPacket {
+    pub const fn get_descriptor(kind: u8, length: u16) -> Self {
+        Self {
+            request_type: REQTYPE_DEVICE_IN,
+            request: REQ_GET_DESCRIPTOR,
+            value: (kind as u16) << 8,
+            index: 0,
+            length,
+        }
+    }
+
+    pub const fn set_configuration(value: u8) -> Self {
+        Self {
+            request_type: REQTYPE_DEVICE_OUT,
+            request: REQ_SET_CONFIGURATION,
+            value: value as u16,
+            index: 0,
+            length: 0,
+        }
+    }
+
+    /// Switch a HID interface to the boot protocol.
+    pub const fn set_boot_protocol(interface: u8) -> Self {
+        Self {
+            request_type: REQTYPE_CLASS_INTERFACE_OUT,
+            request: HID_REQ_SET_PROTOCOL,
+            value: 0,
+            index: interface as u16,
+            length: 0,
+        }
+    }
+
+    /// Report only on change: no idle repeats.
+    pub const fn set_idle(interface: u8) -> Self {
+        Self {
+            request_type: REQTYPE_CLASS_INTERFACE_OUT,
+            request: HID_REQ_SET_IDLE,
+            value: 0,
+            index: interface as u16,
+            length: 0,
+        }
+    }
+
+    pub const fn is_in(&self) -> bool {
+        self.request_type & ENDPOINT_DIR_IN != 0
+    }
+
+    /// The packet as the immediate data of a setup TRB.
+    pub const fn to_u64(self) -> u64 {
+        self.request_type as u64
+            | (self.request as u64) << 8
+            | (self.value as u64) << 16
+            | (self.index as u64) << 32
+            | (self.length as u64) << 48
+    }
+}
+
+/// A HID boot interface and its interrupt IN endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BootInterface {
+    /// `bConfigurationValue` of the configuration it belongs to.
+    pub configuration: u8,
+    pub interface: u8,
+    /// `HID_PROTOCOL_KEYBOARD` or `HID_PROTOCOL_MOUSE`.
+    pub protocol: u8,
+    /// Endpoint number, without the direction bit.
+    pub endpoint: u8,
+    pub max_packet: u16,
+    pub interval: u8,
+}
+
+/// `bMaxPacketSize0` from the first 8 bytes of a device descriptor.
+pub fn device_max_packet0(desc: &[u8]) -> Option<u8> {
+    (desc.len() >= 8 && desc[1] == DESC_DEVICE).then(|| desc[7])
+}
+
+/// `wTotalLength` of a configuration descriptor header.
+pub fn config_total_length(desc: &[u8]) -> Option<u16> {
+    (desc.len() >= CONFIG_DESC_LEN && desc[1] == DESC_CONFIGURATION)
+        .then(|| u16::from_le_bytes([desc[2], desc[3]]))
+}
+
+/// Find the first keyboard or mouse boot interface (alternate setting 0)
+/// with an interrupt IN endpoint in a full configuration descriptor.
+pub fn find_boot_interface(config: &[u8]) -> Option<BootInterface> {
+    let total = config_total_length(config)? as usize;
+    let config = &config[..total.min(config.len())];
+    let configuration = config[5];
+
+    let mut found: Option<BootInterface> = None;
+    let mut offset = 0;
+    while offset + 2 <= config.len() {
+        let len = config[offset] as usize;
+        if len < 2 || offset + len > config.len() {
+            break;
+        }
+        let desc = &config[offset..offset + len];
+        offset += len;
+
+        match desc[1] {
+            DESC_INTERFACE if len >= 9 => {
+                // The next interface ends the search once one has its endpoint.
+                if found.is_some_and(|f| f.endpoint != 0) {
+                    break;
+                }
+                found = (desc[3] == 0
+                    && desc[5] == CLASS_HID
+                    && desc[6] == HID_SUBCLASS_BOOT
+                    && matches!(desc[7], HID_PROTOCOL_KEYBOARD | HID_PROTOCOL_MOUSE))
+                .then_some(BootInterface {
+                    configuration,
+                    interface: desc[2],
+                    protocol: desc[7],
+                    endpoint: 0,
+                    max_packet: 0,
+                    interval: 0,
+                });
+            }
+            DESC_ENDPOINT if len >= 7 => {
+                let Some(iface) = found.as_mut() else {
+                    continue;
+                };
+                if iface.endpoint == 0
+                    && desc[2] & ENDPOINT_DIR_IN != 0
+                    && desc[3] & 0x03 == ENDPOINT_XFER_INTERRUPT
+                {
+                    iface.endpoint = desc[2] & 0x0F;
+                    iface.max_packet = u16::from_le_bytes([desc[4], desc[5]]) & 0x07FF;
+                    iface.interval = desc[6];
+                }
+            }
+            _ => {}
+        }
+    }
+    found.filter(|f| f.endpoint != 0)
+}
@@ -0,0 +1,156 @@
+//! HID boot-protocol report translation.
+//!
+//! Keyboard reports are turned into PS/2 scancode set 1 bytes and fed
+//! through the PS/2 keyboard's decoder, so USB and PS/2 keyboards share
+//! modifier state, the keymap and the tty path.  Mouse reports drive the
+//! same pointer as the PS/2 mouse.
+
+/// Keyboard boot report: modifiers, reserved, six key usages.
+pub const KEYBOARD_REPORT_LEN: usize = 8;
+/// Mouse boot report: buttons, X, Y (devices may append more).
+pub const MOUSE_REPORT_LEN: usize = 3;
+
+/// Usage reported in every key slot when too many keys are held.
+const USAGE_ERROR_ROLL_OVER: u8 = 0x01;
+
+/// Set in a table entry for keys that need the `E0` prefix.
+const EXT: u8 = 0x80;
+const SCANCODE_PREFIX: u8 = 0xE0;
+const SCANCODE_RELEASE: u8 = 0x80;
+
+/// Set 1 make codes for keyboard page usages `0x00..=0x65`; 0 is unmapped.
+#[rustfmt::skip]
+const USAGE_TO_SET1: [u8; 0x66] = [
+    // 0x00: no event, rollover, POST fail, undefined
+    0, 0, 0, 0,
+    // 0x04: a-z
+    0x1E, 0x30, 0x2E, 0x20, 0x12, 0x21, 0x22, 0x23, 0x17, 0x24, 0x25, 0x26, 0x32,
+    0x31, 0x18, 0x19, 0x10, 0x13, 0x1F, 0x14, 0x16, 0x2F, 0x11, 0x2D, 0x15, 0x2C,
+    // 0x1E: 1-9, 0
+    0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0A, 0x0B,
+    // 0x28: enter, escape, backspace, tab, space, - = [ ] \ non-US-# ; ' ` , . /
+    0x1C, 0x01, 0x0E, 0x0F, 0x39, 0x0C, 0x0D, 0x1A, 0x1B, 0x2B, 0x2B, 0x27, 0x28,
+    0x29, 0x33, 0x34, 0x35,
+    // 0x39: caps lock, F1-F12
+    0x3A, 0x3B, 0x3C, 0x3D, 0x3E, 0x3F, 0x40, 0x41, 0x42, 0x43, 0x44, 0x57, 0x58,
+    // 0x46: print screen, scroll lock, pause (multi-byte, unmapped)
+    EXT | 0x37, 0x46, 0,
+    // 0x49: insert, home, page up, delete, end, page down
+    EXT | 0x52, EXT | 0x47, EXT | 0x49, EXT | 0x53, EXT | 0x4F, EXT | 0x51,
+    // 0x4F: right, left, down, up
+    EXT | 0x4D, EXT | 0x4B, EXT | 0x50, EXT | 0x48,
+    // 0x53: num lock, keypad / * - + enter
+    0x45, EXT | 0x35, 0x37, 0x4A, 0x4E, EXT | 0x1C,
+    // 0x59: keypad 1-9, 0, .
+    0x4F, 0x50, 0x51, 0x4B, 0x4C, 0x4D, 0x47, 0x48, 0x49, 0x52, 0x53,
+    // 0x64: non-US \, application
+    0x56, EXT | 0x5D,
+];
+
+/// Set 1 make codes for the modifier byte, bit 0 (left control) first.
+const MODIFIER_TO_SET1: [u8; 8] = [
+    0x1D,
+    0x2A,
+    0x38,
+    EXT | 0x5B,
+    EXT | 0x1D,
+    0x36,
+    EXT | 0x38,
+    EXT | 0x5C,
+];
+
+/// Set 1 table entry for a keyboard page usage, if the key has one.
+pub fn usage_to_scancode(usage: u8) -> Option<u8> {
+    USAGE_TO_SET1
+        .get(usage as usize)
+        .copied()
+        .filter(|&code| code != 0)
+}
+
+fn emit_key(entry: u8, pressed: bool, emit: &mut impl FnMut(u8)) {
+    if entry & EXT != 0 {
+        emit(SCANCODE_PREFIX);
+    }
+    let code = entry & !EXT;
+    emit(if pressed {
+        code
+    } else {
+        code | SCANCODE_RELEASE
+    });
+}
+
+/// Key state of one boot keyboard, diffed against each new report.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BootKeyboard {
+    last: [u8; KEYBOARD_REPORT_LEN],
+}
+
+impl BootKeyboard {
+    pub const fn new() -> Self {
+        Self {
+            last: [0; KEYBOARD_REPORT_LEN],
+        }
+    }
+
+    /// Emit the scancodes that take the keyboard from the previous report
+    /// to `report`.  Modifiers go down before and come up after the keys
+    /// that changed with them, so a report that adds shift and a letter
+    /// together types the shifted letter.  Rollover reports are ignored.
+    pub fn update(&mut self, report: &[u8], mut emit: impl FnMut(u8)) {
+        if report.len() < KEYBOARD_REPORT_LEN {
+            return;
+        }
+        let keys = &report[2..KEYBOARD_REPORT_LEN];
+        if keys.contains(&USAGE_ERROR_ROLL_OVER) {
+            return;
+        }
+        let (old_mods, new_mods) = (self.last[0], report[0]);
+        let old_keys = &self.last[2..];
+
+        for (bit, &entry) in MODIFIER_TO_SET1.iter().enumerate() {
+            if new_mods & !old_mods & (1 << bit) != 0 {
+                emit_key(entry, true, &mut emit);
+            }
+        }
+        for &usage in old_keys {
+            if usage != 0
+                && !keys.contains(&usage)
+                && let Some(entry) = usage_to_scancode(usage)
+            {
+                emit_key(entry, false, &mut emit);
+            }
+        }
+        for &usage in keys {
+            if usage != 0
+                && !old_keys.contains(&usage)
+                && let Some(entry) = usage_to_scancode(usage)
+            {
+                emit_key(entry, true, &mut emit);
+            }
+        }
+        for (bit, &entry) in MODIFIER_TO_SET1.iter().enumerate() {
+            if old_mods & !new_mods & (1 << bit) != 0 {
+                emit_key(entry, false, &mut emit);
+            }
+        }
+
+        self.last.copy_from_slice(&report[..KEYBOARD_REPORT_LEN]);
+    }
+}
+
+/// One boot mouse report.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MouseReport {
+    pub buttons: u8,
+    pub dx: i16,
+    /// Screen-oriented: positive moves down, as HID reports it.
+    pub dy: i16,
+}
+
+pub fn parse_mouse_report(report: &[u8]) -> Option<MouseReport> {
+    (report.len() >= MOUSE_REPORT_LEN).then(|| MouseReport {
+        buttons: report[0] & 0x07,
+        dx: report[1] as i8 as i16,
+        dy: report[2] as i8 as i16,
+    })
+}
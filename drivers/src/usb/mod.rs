@@ -0,0 +1,14 @@
+//! USB support: an xHCI host controller driver and HID boot-protocol
+//! keyboards and mice.
+//!
+//! USB input is translated into the same paths PS/2 devices use — keyboard
+//! reports become set 1 scancodes for the PS/2 keyboard decoder, mouse
+//! reports move the shared pointer — so both reach `input_event` and the
+//! tty the same way and can be used side by side.
+
+pub mod descriptor;
+pub mod hid;
+pub mod regs;
+pub mod xhci;
+
+pub use xhci::{xhci_hid_devices, xhci_interrupts_enabled, xhci_is_ready, xhci_register_driver};
@@ -0,0 +1,99 @@
+//! xHCI register offsets, bit fields and TRB encodings (xHCI 1.2).
+
+// Capability registers, from BAR0.
+pub const CAP_CAPLENGTH: usize = 0x00;
+pub const CAP_HCIVERSION: usize = 0x02;
+pub const CAP_HCSPARAMS1: usize = 0x04;
+pub const CAP_HCSPARAMS2: usize = 0x08;
+pub const CAP_HCCPARAMS1: usize = 0x10;
+pub const CAP_DBOFF: usize = 0x14;
+pub const CAP_RTSOFF: usize = 0x18;
+
+pub const HCCPARAMS1_CSZ: u32 = 1 << 2;
+
+// Operational registers, from BAR0 + CAPLENGTH.
+pub const OP_USBCMD: usize = 0x00;
+pub const OP_USBSTS: usize = 0x04;
+pub const OP_PAGESIZE: usize = 0x08;
+pub const OP_CRCR: usize = 0x18;
+pub const OP_DCBAAP: usize = 0x30;
+pub const OP_CONFIG: usize = 0x38;
+pub const OP_PORTSC_BASE: usize = 0x400;
+pub const OP_PORT_STRIDE: usize = 0x10;
+
+pub const USBCMD_RS: u32 = 1 << 0;
+pub const USBCMD_HCRST: u32 = 1 << 1;
+pub const USBCMD_INTE: u32 = 1 << 2;
+
+pub const USBSTS_HCH: u32 = 1 << 0;
+pub const USBSTS_HSE: u32 = 1 << 2;
+pub const USBSTS_EINT: u32 = 1 << 3;
+pub const USBSTS_CNR: u32 = 1 << 11;
+
+pub const CRCR_RCS: u64 = 1 << 0;
+
+pub const PORTSC_CCS: u32 = 1 << 0;
+pub const PORTSC_PED: u32 = 1 << 1;
+pub const PORTSC_PR: u32 = 1 << 4;
+pub const PORTSC_SPEED_SHIFT: u32 = 10;
+pub const PORTSC_SPEED_MASK: u32 = 0xF << PORTSC_SPEED_SHIFT;
+pub const PORTSC_PRC: u32 = 1 << 21;
+/// Status-change bits, cleared by writing 1 (CSC, PEC, WRC, OCC, PRC,
+/// PLC, CEC).
+pub const PORTSC_CHANGE_MASK: u32 = 0x7F << 17;
+
+// Runtime registers, from BAR0 + RTSOFF; interrupter 0 only.
+pub const RT_IR0: usize = 0x20;
+pub const IR_IMAN: usize = 0x00;
+pub const IR_IMOD: usize = 0x04;
+pub const IR_ERSTSZ: usize = 0x08;
+pub const IR_ERSTBA: usize = 0x10;
+pub const IR_ERDP: usize = 0x18;
+
+pub const IMAN_IP: u32 = 1 << 0;
+pub const IMAN_IE: u32 = 1 << 1;
+pub const ERDP_EHB: u64 = 1 << 3;
+
+// Extended capabilities.
+pub const XECP_ID_LEGACY: u32 = 1;
+pub const USBLEGSUP_BIOS_OWNED: u32 = 1 << 16;
+pub const USBLEGSUP_OS_OWNED: u32 = 1 << 24;
+
+// Port speeds (PORTSC and slot context).
+pub const SPEED_FULL: u8 = 1;
+pub const SPEED_LOW: u8 = 2;
+pub const SPEED_HIGH: u8 = 3;
+pub const SPEED_SUPER: u8 = 4;
+
+// TRB types.
+pub const TRB_NORMAL: u32 = 1;
+pub const TRB_SETUP: u32 = 2;
+pub const TRB_DATA: u32 = 3;
+pub const TRB_STATUS: u32 = 4;
+pub const TRB_LINK: u32 = 6;
+pub const TRB_ENABLE_SLOT: u32 = 9;
+pub const TRB_ADDRESS_DEVICE: u32 = 11;
+pub const TRB_CONFIGURE_ENDPOINT: u32 = 12;
+pub const TRB_EVALUATE_CONTEXT: u32 = 13;
+pub const TRB_TRANSFER_EVENT: u32 = 32;
+pub const TRB_COMMAND_COMPLETION: u32 = 33;
+pub const TRB_PORT_STATUS_CHANGE: u32 = 34;
+
+// TRB control fields.
+pub const TRB_CYCLE: u32 = 1 << 0;
+/// Toggle Cycle on a link TRB.
+pub const TRB_TC: u32 = 1 << 1;
+pub const TRB_ISP: u32 = 1 << 2;
+pub const TRB_IOC: u32 = 1 << 5;
+pub const TRB_IDT: u32 = 1 << 6;
+pub const TRB_TYPE_SHIFT: u32 = 10;
+pub const TRB_DIR_IN: u32 = 1 << 16;
+pub const TRB_TRT_IN: u32 = 3 << 16;
+pub const TRB_SLOT_SHIFT: u32 = 24;
+
+pub const COMPLETION_SUCCESS: u8 = 1;
+pub const COMPLETION_SHORT_PACKET: u8 = 13;
+
+// Endpoint context types.
+pub const EP_TYPE_CONTROL: u32 = 4;
+pub const EP_TYPE_INTERRUPT_IN: u32 = 7;
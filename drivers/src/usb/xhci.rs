@@ -0,0 +1,1120 @@
+//! xHCI host controller driver (PCI class 0C:03, prog-if 30).
+//!
+//! Takes the controller from firmware, resets it, and brings up a command
+//! ring and a single-segment event ring on interrupter 0.  Root ports with
+//! a device attached are reset and enumerated with polled commands during
+//! probe; HID boot keyboards and mice then get their interrupt IN endpoint
+//! configured and a few reports queued, whose completions arrive over
+//! MSI-X (or MSI).
+//!
+//! Limits: one controller, no hubs, no hot-plug after probe.
+
+use alloc::vec::Vec;
+use core::ffi::{c_int, c_void};
+use core::mem::size_of;
+use core::ptr;
+use core::sync::atomic::{Ordering, fence};
+
+use slopos_abi::addr::PhysAddr;
+use slopos_lib::kernel_services::driver_runtime::irq_increment_keyboard_events;
+use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_info};
+use slopos_mm::mmio::MmioRegion;
+use slopos_mm::page_alloc::OwnedPageFrame;
+
+use super::descriptor::{
+    self, BootInterface, DESC_CONFIGURATION, DESC_DEVICE, HID_PROTOCOL_KEYBOARD, SetupPacket,
+};
+use super::hid::{self, BootKeyboard};
+use super::regs::*;
+use crate::msix::{MsixDomain, MsixError};
+use crate::pci::{PciDeviceInfo, PciDriver, pci_register_driver};
+use crate::virtio::pci::enable_bus_master;
+use crate::{hpet, msi, ps2};
+
+pub const PCI_CLASS_SERIAL_BUS: u8 = 0x0C;
+pub const PCI_SUBCLASS_USB: u8 = 0x03;
+pub const PCI_PROG_IF_XHCI: u8 = 0x30;
+
+const PAGE_SIZE: usize = 4096;
+
+/// TRBs per ring page; the last one is the link back to the start.
+const RING_TRBS: usize = PAGE_SIZE / size_of::<Trb>();
+const EVENT_TRBS: usize = PAGE_SIZE / size_of::<Trb>();
+
+/// Device slots we enable; boot keyboards and mice only need a few.
+const MAX_SLOTS: u8 = 8;
+const MAX_HID_DEVICES: usize = 4;
+
+/// Interrupt IN transfers kept queued per device, each with its own
+/// buffer of `REPORT_STRIDE` bytes in the device's report page.
+const REPORTS_QUEUED: usize = 4;
+const REPORT_STRIDE: usize = 64;
+
+const RESET_TIMEOUT_MS: u32 = 1000;
+const PORT_RESET_TIMEOUT_MS: u32 = 500;
+const COMMAND_TIMEOUT_MS: u32 = 1000;
+/// Recovery time after a port reset before the first request (USB 2.0,
+/// section 7.1.7.3).
+const RESET_RECOVERY_MS: u32 = 10;
+
+/// Transfer Request Block: the unit of every ring.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct Trb {
+    param: u64,
+    status: u32,
+    control: u32,
+}
+
+const _: () = assert!(size_of::<Trb>() == 16);
+
+impl Trb {
+    #[inline]
+    fn trb_type(&self) -> u32 {
+        (self.control >> TRB_TYPE_SHIFT) & 0x3F
+    }
+
+    #[inline]
+    fn completion_code(&self) -> u8 {
+        (self.status >> 24) as u8
+    }
+
+    #[inline]
+    fn slot_id(&self) -> u8 {
+        (self.control >> TRB_SLOT_SHIFT) as u8
+    }
+
+    /// Endpoint ID (DCI) of a transfer event.
+    #[inline]
+    fn endpoint_id(&self) -> u8 {
+        ((self.control >> 16) & 0x1F) as u8
+    }
+}
+
+#[inline]
+fn trb_type(kind: u32) -> u32 {
+    kind << TRB_TYPE_SHIFT
+}
+
+/// A producer ring in one page, closed by a link TRB that toggles the
+/// cycle state.  Used for the command ring and every transfer ring.
+struct Ring {
+    page: OwnedPageFrame,
+    enqueue: usize,
+    cycle: bool,
+}
+
+impl Ring {
+    fn allocate() -> Option<Self> {
+        let page = OwnedPageFrame::alloc_zeroed()?;
+        let link = Trb {
+            param: page.phys_u64(),
+            status: 0,
+            control: trb_type(TRB_LINK) | TRB_TC,
+        };
+        unsafe { ptr::write_volatile(page.as_mut_ptr::<Trb>().add(RING_TRBS - 1), link) };
+        Some(Self {
+            page,
+            enqueue: 0,
+            cycle: true,
+        })
+    }
+
+    fn phys(&self) -> u64 {
+        self.page.phys_u64()
+    }
+
+    fn trb(&self, index: usize) -> *mut Trb {
+        unsafe { self.page.as_mut_ptr::<Trb>().add(index) }
+    }
+
+    /// The TRB at physical address `phys`, if it lies in this ring.
+    fn trb_at(&self, phys: u64) -> Option<Trb> {
+        let offset = phys.checked_sub(self.phys())? as usize;
+        (offset < PAGE_SIZE).then(|| unsafe { ptr::read_volatile(self.trb(offset / 16)) })
+    }
+
+    /// Write one TRB, handing it to the controller by setting its cycle
+    /// bit last, and return its physical address.
+    fn push(&mut self, param: u64, status: u32, control: u32) -> u64 {
+        let slot = self.trb(self.enqueue);
+        let phys = self.phys() + (self.enqueue * size_of::<Trb>()) as u64;
+        unsafe {
+            ptr::write_volatile(&raw mut (*slot).param, param);
+            ptr::write_volatile(&raw mut (*slot).status, status);
+            fence(Ordering::SeqCst);
+            ptr::write_volatile(&raw mut (*slot).control, control | self.cycle as u32);
+        }
+
+        self.enqueue += 1;
+        if self.enqueue == RING_TRBS - 1 {
+            let link = self.trb(RING_TRBS - 1);
+            unsafe {
+                ptr::write_volatile(
+                    &raw mut (*link).control,
+                    trb_type(TRB_LINK) | TRB_TC | self.cycle as u32,
+                );
+            }
+            self.enqueue = 0;
+            self.cycle = !self.cycle;
+        }
+        phys
+    }
+}
+
+/// Event Ring Segment Table entry.
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct ErstEntry {
+    base: u64,
+    size: u32,
+    _reserved: u32,
+}
+
+/// The consumer side of interrupter 0's single-segment event ring.
+struct EventRing {
+    page: OwnedPageFrame,
+    erst: OwnedPageFrame,
+    dequeue: usize,
+    cycle: bool,
+}
+
+impl EventRing {
+    fn allocate() -> Option<Self> {
+        let page = OwnedPageFrame::alloc_zeroed()?;
+        let erst = OwnedPageFrame::alloc_zeroed()?;
+        let entry = ErstEntry {
+            base: page.phys_u64(),
+            size: EVENT_TRBS as u32,
+            _reserved: 0,
+        };
+        unsafe { ptr::write_volatile(erst.as_mut_ptr::<ErstEntry>(), entry) };
+        Some(Self {
+            page,
+            erst,
+            dequeue: 0,
+            cycle: true,
+        })
+    }
+
+    fn dequeue_phys(&self) -> u64 {
+        self.page.phys_u64() + (self.dequeue * size_of::<Trb>()) as u64
+    }
+
+    /// Pop the next event the controller has posted, if any.
+    fn pop(&mut self) -> Option<Trb> {
+        let event = unsafe { ptr::read_volatile(self.page.as_mut_ptr::<Trb>().add(self.dequeue)) };
+        if (event.control & TRB_CYCLE != 0) != self.cycle {
+            return None;
+        }
+        self.dequeue += 1;
+        if self.dequeue == EVENT_TRBS {
+            self.dequeue = 0;
+            self.cycle = !self.cycle;
+        }
+        Some(event)
+    }
+}
+
+/// An enumerated HID boot device and its interrupt IN endpoint.
+struct HidDevice {
+    slot: u8,
+    port: u8,
+    /// Device context index of the interrupt endpoint.
+    dci: u8,
+    protocol: u8,
+    ring: Ring,
+    reports: OwnedPageFrame,
+    report_len: usize,
+    keyboard: BootKeyboard,
+    // Kept alive for the controller, which owns their contents.
+    _context: OwnedPageFrame,
+    _ep0: Ring,
+}
+
+impl HidDevice {
+    /// Queue a Normal TRB that fills report buffer `index`.
+    fn queue_report(&mut self, index: usize) {
+        let buffer = self.reports.phys_u64() + (index * REPORT_STRIDE) as u64;
+        self.ring.push(
+            buffer,
+            self.report_len as u32,
+            trb_type(TRB_NORMAL) | TRB_IOC | TRB_ISP,
+        );
+    }
+}
+
+/// Input from one completed report, applied once the controller lock is
+/// dropped so the keyboard and mouse paths never nest inside it.
+enum Report {
+    Keys { bytes: [u8; 32], len: usize },
+    Mouse(hid::MouseReport),
+}
+
+struct Controller {
+    regs: MmioRegion,
+    op: usize,
+    rt: usize,
+    db: usize,
+    ctx_size: usize,
+    max_ports: u8,
+    dcbaa: OwnedPageFrame,
+    /// Scratchpad buffers and their array, owned by the controller.
+    _scratchpads: Vec<OwnedPageFrame>,
+    commands: Ring,
+    events: EventRing,
+}
+
+struct XhciState {
+    controller: Option<Controller>,
+    devices: Vec<HidDevice>,
+    /// IDT vector for interrupter 0; `None` until enumeration finished.
+    vector: Option<u8>,
+    ready: bool,
+}
+
+impl XhciState {
+    const fn new() -> Self {
+        Self {
+            controller: None,
+            devices: Vec::new(),
+            vector: None,
+            ready: false,
+        }
+    }
+}
+
+static DEVICE_CLAIMED: InitFlag = InitFlag::new();
+static XHCI_STATE: IrqMutex<XhciState> = IrqMutex::new(XhciState::new());
+
+fn xhci_match(info: *const PciDeviceInfo, _context: *mut c_void) -> bool {
+    if info.is_null() {
+        return false;
+    }
+    let info = unsafe { &*info };
+    info.class_code == PCI_CLASS_SERIAL_BUS
+        && info.subclass == PCI_SUBCLASS_USB
+        && info.prog_if == PCI_PROG_IF_XHCI
+}
+
+/// Poll `reg` until `mask` reads as `want`.
+fn wait_bits(regs: &MmioRegion, reg: usize, mask: u32, want: u32, timeout_ms: u32) -> bool {
+    for _ in 0..timeout_ms {
+        if regs.read::<u32>(reg) & mask == want {
+            return true;
+        }
+        hpet::delay_ms(1);
+    }
+    false
+}
+
+/// Claim the controller from firmware through the USB Legacy Support
+/// extended capability, if it has one.
+fn take_ownership(regs: &MmioRegion, hccparams1: u32) {
+    let mut offset = ((hccparams1 >> 16) as usize) << 2;
+    while offset != 0 && offset < regs.size() {
+        let cap = regs.read::<u32>(offset);
+        if cap & 0xFF == XECP_ID_LEGACY {
+            if cap & USBLEGSUP_BIOS_OWNED != 0 {
+                regs.write::<u32>(offset, cap | USBLEGSUP_OS_OWNED);
+                if !wait_bits(regs, offset, USBLEGSUP_BIOS_OWNED, 0, RESET_TIMEOUT_MS) {
+                    klog_info!("xhci: firmware did not release the controller");
+                }
+            }
+            // Disable SMIs the firmware may have left enabled.
+            regs.write::<u32>(offset + 4, 0);
+            return;
+        }
+        let next = ((cap >> 8) & 0xFF) as usize;
+        if next == 0 {
+            return;
+        }
+        offset += next << 2;
+    }
+}
+
+impl Controller {
+    #[inline]
+    fn op_read(&self, reg: usize) -> u32 {
+        self.regs.read::<u32>(self.op + reg)
+    }
+
+    #[inline]
+    fn op_write(&self, reg: usize, value: u32) {
+        self.regs.write::<u32>(self.op + reg, value);
+    }
+
+    #[inline]
+    fn ir_write<T: Copy>(&self, reg: usize, value: T) {
+        self.regs.write::<T>(self.rt + RT_IR0 + reg, value);
+    }
+
+    #[inline]
+    fn ring_doorbell(&self, slot: u8, target: u8) {
+        fence(Ordering::SeqCst);
+        self.regs
+            .write::<u32>(self.db + 4 * slot as usize, target as u32);
+    }
+
+    fn portsc(&self, port: u8) -> usize {
+        OP_PORTSC_BASE + OP_PORT_STRIDE * (port as usize - 1)
+    }
+
+    /// Advance ERDP past consumed events and clear Event Handler Busy.
+    fn update_dequeue(&self) {
+        self.ir_write::<u64>(IR_ERDP, self.events.dequeue_phys() | ERDP_EHB);
+    }
+
+    /// Poll the event ring for an event of `kind` about the TRB at `trb`.
+    /// Anything else that arrives first is dropped; during probe nothing
+    /// else is pending.
+    fn wait_event(&mut self, kind: u32, trb: u64) -> Option<Trb> {
+        for _ in 0..COMMAND_TIMEOUT_MS * 100 {
+            while let Some(event) = self.events.pop() {
+                self.update_dequeue();
+                if event.trb_type() == kind && event.param == trb {
+                    return Some(event);
+                }
+            }
+            hpet::delay_ns(10_000);
+        }
+        None
+    }
+
+    /// Run a command and return its completion event on success.
+    fn command(&mut self, param: u64, control: u32) -> Option<Trb> {
+        let trb = self.commands.push(param, 0, control);
+        self.ring_doorbell(0, 0);
+        let Some(event) = self.wait_event(TRB_COMMAND_COMPLETION, trb) else {
+            klog_info!(
+                "xhci: command type {} timed out",
+                (control >> TRB_TYPE_SHIFT) & 0x3F
+            );
+            return None;
+        };
+        if event.completion_code() != COMPLETION_SUCCESS {
+            klog_info!(
+                "xhci: command type {} failed, completion code {}",
+                (control >> TRB_TYPE_SHIFT) & 0x3F,
+                event.completion_code()
+            );
+            return None;
+        }
+        Some(event)
+    }
+
+    /// Run a control transfer on a device's default endpoint.  IN data
+    /// lands in `buffer`; requests without a data stage ignore it.
+    fn control(
+        &mut self,
+        slot: u8,
+        ep0: &mut Ring,
+        setup: SetupPacket,
+        buffer: &OwnedPageFrame,
+    ) -> bool {
+        let has_data = setup.length != 0;
+        let transfer_type = if has_data && setup.is_in() {
+            TRB_TRT_IN
+        } else {
+            0
+        };
+        ep0.push(
+            setup.to_u64(),
+            8,
+            trb_type(TRB_SETUP) | TRB_IDT | transfer_type,
+        );
+        if has_data {
+            ep0.push(
+                buffer.phys_u64(),
+                setup.length as u32,
+                trb_type(TRB_DATA) | TRB_DIR_IN,
+            );
+        }
+        // The status stage runs opposite to the data stage.
+        let status_dir = if has_data { 0 } else { TRB_DIR_IN };
+        let status = ep0.push(0, 0, trb_type(TRB_STATUS) | TRB_IOC | status_dir);
+        self.ring_doorbell(slot, 1);
+
+        match self.wait_event(TRB_TRANSFER_EVENT, status) {
+            Some(event) if event.completion_code() == COMPLETION_SUCCESS => true,
+            Some(event) => {
+                klog_debug!(
+                    "xhci: slot {} request 0x{:02x} failed, completion code {}",
+                    slot,
+                    setup.request,
+                    event.completion_code()
+                );
+                false
+            }
+            None => {
+                klog_debug!(
+                    "xhci: slot {} request 0x{:02x} timed out",
+                    slot,
+                    setup.request
+                );
+                false
+            }
+        }
+    }
+
+    /// Pointer to context `index` (0 = slot) in a device or input context.
+    fn context(&self, page: &OwnedPageFrame, base: usize, index: usize) -> *mut u32 {
+        unsafe { page.as_mut_ptr::<u8>().add(base + index * self.ctx_size) as *mut u32 }
+    }
+
+    fn write_context(&self, page: &OwnedPageFrame, base: usize, index: usize, dwords: &[u32]) {
+        let ctx = self.context(page, base, index);
+        for (i, &dword) in dwords.iter().enumerate() {
+            unsafe { ptr::write_volatile(ctx.add(i), dword) };
+        }
+    }
+
+    /// Fill the input control context: add the contexts in `add_flags`.
+    fn input_control(&self, input: &OwnedPageFrame, add_flags: u32) {
+        unsafe { ptr::write_bytes(input.as_mut_ptr::<u8>(), 0, PAGE_SIZE) };
+        self.write_context(input, 0, 0, &[0, add_flags]);
+    }
+
+    fn slot_context(&self, input: &OwnedPageFrame, speed: u8, port: u8, entries: u8) {
+        self.write_context(
+            input,
+            self.ctx_size,
+            0,
+            &[
+                ((entries as u32) << 27) | ((speed as u32) << 20),
+                (port as u32) << 16,
+            ],
+        );
+    }
+
+    fn ep0_context(&self, input: &OwnedPageFrame, ep0: &Ring, max_packet: u16) {
+        let dequeue = ep0.phys() | ep0.cycle as u64;
+        self.write_context(
+            input,
+            self.ctx_size,
+            1,
+            &[
+                0,
+                (3 << 1) | (EP_TYPE_CONTROL << 3) | ((max_packet as u32) << 16),
+                dequeue as u32,
+                (dequeue >> 32) as u32,
+                8,
+            ],
+        );
+    }
+
+    /// Reset a root port and return its speed once it is enabled.
+    fn reset_port(&self, port: u8) -> Option<u8> {
+        let reg = self.portsc(port);
+        let portsc = self.op_read(reg);
+        if portsc & PORTSC_CCS == 0 {
+            return None;
+        }
+        // Writing PED back as 1 would disable the port; change bits are
+        // write-1-to-clear.
+        let preserve = portsc & !(PORTSC_PED | PORTSC_CHANGE_MASK);
+        self.op_write(reg, preserve | PORTSC_PR);
+        if !wait_bits(
+            &self.regs,
+            self.op + reg,
+            PORTSC_PRC,
+            PORTSC_PRC,
+            PORT_RESET_TIMEOUT_MS,
+        ) {
+            klog_debug!("xhci: port {} reset timed out", port);
+            return None;
+        }
+        let portsc = self.op_read(reg);
+        self.op_write(
+            reg,
+            (portsc & !(PORTSC_PED | PORTSC_CHANGE_MASK)) | (portsc & PORTSC_CHANGE_MASK),
+        );
+        if portsc & PORTSC_PED == 0 {
+            return None;
+        }
+        hpet::delay_ms(RESET_RECOVERY_MS);
+        Some(((portsc & PORTSC_SPEED_MASK) >> PORTSC_SPEED_SHIFT) as u8)
+    }
+
+    /// Enumerate the device on `port` and, if it has a boot keyboard or
+    /// mouse interface, configure that interface's interrupt endpoint.
+    fn enumerate(&mut self, port: u8, speed: u8) -> Result<Option<HidDevice>, &'static str> {
+        let event = self
+            .command(0, trb_type(TRB_ENABLE_SLOT))
+            .ok_or("enable slot failed")?;
+        let slot = event.slot_id();
+        if slot == 0 || slot > MAX_SLOTS {
+            return Err("controller returned an unusable slot");
+        }
+
+        let context = OwnedPageFrame::alloc_zeroed().ok_or("device context allocation failed")?;
+        let input = OwnedPageFrame::alloc_zeroed().ok_or("input context allocation failed")?;
+        let buffer = OwnedPageFrame::alloc_zeroed().ok_or("descriptor buffer allocation failed")?;
+        let mut ep0 = Ring::allocate().ok_or("control ring allocation failed")?;
+        unsafe {
+            ptr::write_volatile(
+                self.dcbaa.as_mut_ptr::<u64>().add(slot as usize),
+                context.phys_u64(),
+            )
+        };
+
+        let mut max_packet = match speed {
+            SPEED_SUPER => 512,
+            SPEED_HIGH => 64,
+            _ => 8,
+        };
+        self.input_control(&input, 0b11);
+        self.slot_context(&input, speed, port, 1);
+        self.ep0_context(&input, &ep0, max_packet);
+        self.command(
+            input.phys_u64(),
+            trb_type(TRB_ADDRESS_DEVICE) | ((slot as u32) << TRB_SLOT_SHIFT),
+        )
+        .ok_or("address device failed")?;
+
+        // The first 8 bytes carry bMaxPacketSize0, which full-speed
+        // devices may set above the default of 8.
+        if !self.control(
+            slot,
+            &mut ep0,
+            SetupPacket::get_descriptor(DESC_DEVICE, 8),
+            &buffer,
+        ) {
+            return Err("device descriptor read failed");
+        }
+        let desc = unsafe { core::slice::from_raw_parts(buffer.as_mut_ptr::<u8>(), 8) };
+        let reported = descriptor::device_max_packet0(desc).ok_or("bad device descriptor")?;
+        if speed != SPEED_SUPER && reported as u16 != max_packet && reported != 0 {
+            max_packet = reported as u16;
+            self.input_control(&input, 0b10);
+            self.ep0_context(&input, &ep0, max_packet);
+            self.command(
+                input.phys_u64(),
+                trb_type(TRB_EVALUATE_CONTEXT) | ((slot as u32) << TRB_SLOT_SHIFT),
+            )
+            .ok_or("evaluate context failed")?;
+        }
+
+        if !self.control(
+            slot,
+            &mut ep0,
+            SetupPacket::get_descriptor(DESC_CONFIGURATION, descriptor::CONFIG_DESC_LEN as u16),
+            &buffer,
+        ) {
+            return Err("configuration descriptor read failed");
+        }
+        let header = unsafe {
+            core::slice::from_raw_parts(buffer.as_mut_ptr::<u8>(), descriptor::CONFIG_DESC_LEN)
+        };
+        let total = descriptor::config_total_length(header)
+            .ok_or("bad configuration descriptor")?
+            .min(PAGE_SIZE as u16);
+        if !self.control(
+            slot,
+            &mut ep0,
+            SetupPacket::get_descriptor(DESC_CONFIGURATION, total),
+            &buffer,
+        ) {
+            return Err("configuration descriptor read failed");
+        }
+        let config =
+            unsafe { core::slice::from_raw_parts(buffer.as_mut_ptr::<u8>(), total as usize) };
+        let Some(iface) = descriptor::find_boot_interface(config) else {
+            klog_info!("xhci: port {} slot {}: no HID boot interface", port, slot);
+            return Ok(None);
+        };
+
+        for setup in [
+            SetupPacket::set_configuration(iface.configuration),
+            SetupPacket::set_boot_protocol(iface.interface),
+        ] {
+            if !self.control(slot, &mut ep0, setup, &buffer) {
+                return Err("HID setup request failed");
+            }
+        }
+        // SET_IDLE is optional; a keyboard that stalls it still works.
+        if iface.protocol == HID_PROTOCOL_KEYBOARD {
+            self.control(
+                slot,
+                &mut ep0,
+                SetupPacket::set_idle(iface.interface),
+                &buffer,
+            );
+        }
+
+        let ring = Ring::allocate().ok_or("interrupt ring allocation failed")?;
+        let dci = self.configure_endpoint(slot, port, speed, &input, &ring, &iface)?;
+        let report_len = (iface.max_packet as usize).clamp(1, REPORT_STRIDE);
+
+        klog_info!(
+            "xhci: port {} slot {}: HID boot {} on endpoint {}, {}-byte reports",
+            port,
+            slot,
+            if iface.protocol == HID_PROTOCOL_KEYBOARD {
+                "keyboard"
+            } else {
+                "mouse"
+            },
+            iface.endpoint,
+            report_len
+        );
+
+        Ok(Some(HidDevice {
+            slot,
+            port,
+            dci,
+            protocol: iface.protocol,
+            ring,
+            reports: buffer,
+            report_len,
+            keyboard: BootKeyboard::new(),
+            _context: context,
+            _ep0: ep0,
+        }))
+    }
+
+    /// Add the interrupt IN endpoint of `iface` to the slot and return its
+    /// device context index.
+    fn configure_endpoint(
+        &mut self,
+        slot: u8,
+        port: u8,
+        speed: u8,
+        input: &OwnedPageFrame,
+        ring: &Ring,
+        iface: &BootInterface,
+    ) -> Result<u8, &'static str> {
+        let dci = iface.endpoint * 2 + 1;
+        let interval = endpoint_interval(speed, iface.interval);
+        let dequeue = ring.phys() | ring.cycle as u64;
+        let max_packet = iface.max_packet as u32;
+
+        self.input_control(input, 1 | (1 << dci));
+        self.slot_context(input, speed, port, dci);
+        self.write_context(
+            input,
+            self.ctx_size,
+            dci as usize,
+            &[
+                (interval as u32) << 16,
+                (3 << 1) | (EP_TYPE_INTERRUPT_IN << 3) | (max_packet << 16),
+                dequeue as u32,
+                (dequeue >> 32) as u32,
+                max_packet | (max_packet << 16),
+            ],
+        );
+        self.command(
+            input.phys_u64(),
+            trb_type(TRB_CONFIGURE_ENDPOINT) | ((slot as u32) << TRB_SLOT_SHIFT),
+        )
+        .ok_or("configure endpoint failed")?;
+        Ok(dci)
+    }
+}
+
+/// Endpoint context interval, in 125 us units as a power of two, for an
+/// interrupt endpoint's `bInterval`.
+pub fn endpoint_interval(speed: u8, b_interval: u8) -> u8 {
+    match speed {
+        // bInterval is already an exponent: 2^(bInterval - 1) microframes.
+        SPEED_HIGH | SPEED_SUPER => b_interval.clamp(1, 16) - 1,
+        // bInterval is in 1 ms frames, eight microframes each.
+        _ => {
+            let microframes = (b_interval.max(1) as u32) * 8;
+            ((31 - microframes.leading_zeros()) as u8).clamp(3, 10)
+        }
+    }
+}
+
+/// Map BAR0, which holds all four register sets.
+fn map_registers(info: &PciDeviceInfo) -> Option<MmioRegion> {
+    let bar = &info.bars[0];
+    if bar.base == 0 || bar.is_io != 0 {
+        return None;
+    }
+    let len = (bar.size as usize).max(4 * PAGE_SIZE);
+    MmioRegion::map(PhysAddr::new(bar.base), len)
+}
+
+/// Route interrupter 0 to a freshly allocated vector on the BSP, over
+/// MSI-X entry 0 if the controller has it and MSI otherwise.
+fn setup_interrupts(info: &PciDeviceInfo) -> Option<u8> {
+    setup_msix(info).or_else(|| setup_msi(info))
+}
+
+/// Bind MSI-X entry 0 through the device's [`MsixDomain`].
+fn setup_msix(info: &PciDeviceInfo) -> Option<u8> {
+    let mut domain = match MsixDomain::open(info) {
+        Ok(d) => d,
+        Err(MsixError::NoCapability) => return None,
+        Err(e) => {
+            klog_debug!("xhci: MSI-X table map failed: {:?}", e);
+            return None;
+        }
+    };
+    let vector = match domain.alloc_entry(0, 0) {
+        Ok(v) => v,
+        Err(e) => {
+            klog_debug!("xhci: MSI-X configure failed: {:?}", e);
+            return None;
+        }
+    };
+    if domain
+        .register_handler(0, xhci_irq_handler, ptr::null_mut())
+        .is_err()
+    {
+        domain.free_entry(0);
+        return None;
+    }
+    domain.enable();
+    Some(vector)
+}
+
+/// Fall back to plain MSI for controllers without an MSI-X table.
+fn setup_msi(info: &PciDeviceInfo) -> Option<u8> {
+    let cap_offset = info.msi_cap_offset?;
+    let device_bdf =
+        ((info.bus as u32) << 16) | ((info.device as u32) << 8) | (info.function as u32);
+    let vector = slopos_core::irq::msi_alloc_vector()?;
+    let cap = msi::msi_read_capability(info.bus, info.device, info.function, cap_offset);
+    if msi::msi_configure(info.bus, info.device, info.function, &cap, vector, 0).is_err() {
+        slopos_core::irq::msi_free_vector(vector);
+        return None;
+    }
+    slopos_core::irq::msi_register_handler(vector, xhci_irq_handler, ptr::null_mut(), device_bdf);
+    Some(vector)
+}
+
+/// Pop one event and turn a completed report into input.  Returns `None`
+/// once the event ring is empty.
+fn next_report(state: &mut XhciState) -> Option<Option<Report>> {
+    let XhciState {
+        controller,
+        devices,
+        ..
+    } = state;
+    let controller = controller.as_mut()?;
+    let event = controller.events.pop()?;
+    controller.update_dequeue();
+
+    if event.trb_type() != TRB_TRANSFER_EVENT {
+        if event.trb_type() == TRB_PORT_STATUS_CHANGE {
+            klog_debug!("xhci: port {} status changed", (event.param >> 24) & 0xFF);
+        }
+        return Some(None);
+    }
+    let Some(device) = devices
+        .iter_mut()
+        .find(|d| d.slot == event.slot_id() && d.dci == event.endpoint_id())
+    else {
+        return Some(None);
+    };
+    let Some(trb) = device.ring.trb_at(event.param) else {
+        return Some(None);
+    };
+    let Some(offset) = trb.param.checked_sub(device.reports.phys_u64()) else {
+        return Some(None);
+    };
+    let index = offset as usize / REPORT_STRIDE;
+    if index >= REPORTS_QUEUED {
+        return Some(None);
+    }
+
+    let code = event.completion_code();
+    let report = if code == COMPLETION_SUCCESS || code == COMPLETION_SHORT_PACKET {
+        let received = device
+            .report_len
+            .saturating_sub((event.status & 0x00FF_FFFF) as usize);
+        let data = unsafe {
+            core::slice::from_raw_parts(
+                device.reports.as_mut_ptr::<u8>().add(index * REPORT_STRIDE),
+                received,
+            )
+        };
+        if device.protocol == HID_PROTOCOL_KEYBOARD {
+            let mut bytes = [0u8; 32];
+            let mut len = 0;
+            device.keyboard.update(data, |b| {
+                if len < bytes.len() {
+                    bytes[len] = b;
+                    len += 1;
+                }
+            });
+            Some(Report::Keys { bytes, len })
+        } else {
+            hid::parse_mouse_report(data).map(Report::Mouse)
+        }
+    } else {
+        klog_debug!(
+            "xhci: slot {} report failed, completion code {}",
+            device.slot,
+            code
+        );
+        None
+    };
+
+    device.queue_report(index);
+    controller.ring_doorbell(device.slot, device.dci);
+    Some(report)
+}
+
+fn apply_report(report: Report) {
+    match report {
+        Report::Keys { bytes, len } => {
+            for &b in &bytes[..len] {
+                irq_increment_keyboard_events();
+                ps2::keyboard::handle_scancode(b);
+            }
+        }
+        Report::Mouse(m) => ps2::mouse::report_motion(ps2::mouse::MousePacket {
+            dx: m.dx,
+            dy: m.dy,
+            dz: 0,
+            buttons: m.buttons,
+        }),
+    }
+}
+
+/// Handler for interrupter 0: drain the event ring.
+extern "C" fn xhci_irq_handler(
+    _vector: u8,
+    _frame: *mut slopos_lib::InterruptFrame,
+    _ctx: *mut c_void,
+) {
+    {
+        let state = XHCI_STATE.lock();
+        let Some(controller) = state.controller.as_ref() else {
+            return;
+        };
+        controller.ir_write::<u32>(IR_IMAN, IMAN_IP | IMAN_IE);
+        controller.op_write(OP_USBSTS, USBSTS_EINT);
+    }
+    loop {
+        let next = next_report(&mut XHCI_STATE.lock());
+        match next {
+            None => break,
+            Some(Some(report)) => apply_report(report),
+            Some(None) => {}
+        }
+    }
+}
+
+fn xhci_probe(info: *const PciDeviceInfo, _context: *mut c_void) -> c_int {
+    if !DEVICE_CLAIMED.claim() {
+        klog_debug!("xhci: already claimed");
+        return -1;
+    }
+    let info = unsafe { &*info };
+    match xhci_bring_up(info) {
+        Ok(()) => 0,
+        Err(msg) => {
+            klog_info!("xhci: {}", msg);
+            DEVICE_CLAIMED.reset();
+            -1
+        }
+    }
+}
+
+fn xhci_bring_up(info: &PciDeviceInfo) -> Result<(), &'static str> {
+    klog_info!(
+        "xhci: probing {:04x}:{:04x} at {:02x}:{:02x}.{}",
+        info.vendor_id,
+        info.device_id,
+        info.bus,
+        info.device,
+        info.function
+    );
+
+    enable_bus_master(info);
+    let regs = map_registers(info).ok_or("BAR0 unavailable")?;
+
+    let op = regs.read::<u8>(CAP_CAPLENGTH) as usize;
+    let version = regs.read::<u16>(CAP_HCIVERSION);
+    let hcsparams1 = regs.read::<u32>(CAP_HCSPARAMS1);
+    let hcsparams2 = regs.read::<u32>(CAP_HCSPARAMS2);
+    let hccparams1 = regs.read::<u32>(CAP_HCCPARAMS1);
+    let db = (regs.read::<u32>(CAP_DBOFF) & !0x3) as usize;
+    let rt = (regs.read::<u32>(CAP_RTSOFF) & !0x1F) as usize;
+    let max_ports = (hcsparams1 >> 24) as u8;
+    let max_slots = (hcsparams1 & 0xFF) as u8;
+    let scratchpad_count = ((((hcsparams2 >> 21) & 0x1F) << 5) | (hcsparams2 >> 27)) as usize;
+    let ctx_size = if hccparams1 & HCCPARAMS1_CSZ != 0 {
+        64
+    } else {
+        32
+    };
+    klog_debug!(
+        "xhci: version {:x}.{:02x}, {} ports, {} slots, {} scratchpads, {}-byte contexts",
+        version >> 8,
+        version & 0xFF,
+        max_ports,
+        max_slots,
+        scratchpad_count,
+        ctx_size
+    );
+    if regs.read::<u32>(op + OP_PAGESIZE) & 1 == 0 {
+        return Err("controller does not support 4 KiB pages");
+    }
+
+    take_ownership(&regs, hccparams1);
+
+    // Halt, then reset.
+    let cmd = regs.read::<u32>(op + OP_USBCMD);
+    regs.write::<u32>(op + OP_USBCMD, cmd & !(USBCMD_RS | USBCMD_INTE));
+    if !wait_bits(
+        &regs,
+        op + OP_USBSTS,
+        USBSTS_HCH,
+        USBSTS_HCH,
+        RESET_TIMEOUT_MS,
+    ) {
+        return Err("controller did not halt");
+    }
+    regs.write::<u32>(op + OP_USBCMD, USBCMD_HCRST);
+    if !wait_bits(&regs, op + OP_USBCMD, USBCMD_HCRST, 0, RESET_TIMEOUT_MS)
+        || !wait_bits(&regs, op + OP_USBSTS, USBSTS_CNR, 0, RESET_TIMEOUT_MS)
+    {
+        return Err("controller reset timed out");
+    }
+
+    let dcbaa = OwnedPageFrame::alloc_zeroed().ok_or("DCBAA allocation failed")?;
+    let mut scratchpads = Vec::new();
+    if scratchpad_count > 0 {
+        if scratchpad_count > PAGE_SIZE / 8 - 1 {
+            return Err("too many scratchpad buffers");
+        }
+        let array = OwnedPageFrame::alloc_zeroed().ok_or("scratchpad allocation failed")?;
+        for i in 0..scratchpad_count {
+            let page = OwnedPageFrame::alloc_zeroed().ok_or("scratchpad allocation failed")?;
+            unsafe { ptr::write_volatile(array.as_mut_ptr::<u64>().add(i), page.phys_u64()) };
+            scratchpads.push(page);
+        }
+        unsafe { ptr::write_volatile(dcbaa.as_mut_ptr::<u64>(), array.phys_u64()) };
+        scratchpads.push(array);
+    }
+
+    let commands = Ring::allocate().ok_or("command ring allocation failed")?;
+    let events = EventRing::allocate().ok_or("event ring allocation failed")?;
+
+    let mut controller = Controller {
+        regs,
+        op,
+        rt,
+        db,
+        ctx_size,
+        max_ports,
+        dcbaa,
+        _scratchpads: scratchpads,
+        commands,
+        events,
+    };
+
+    controller.op_write(OP_CONFIG, MAX_SLOTS.min(max_slots) as u32);
+    controller
+        .regs
+        .write::<u64>(op + OP_DCBAAP, controller.dcbaa.phys_u64());
+    controller
+        .regs
+        .write::<u64>(op + OP_CRCR, controller.commands.phys() | CRCR_RCS);
+    controller.ir_write::<u32>(IR_ERSTSZ, 1);
+    controller.ir_write::<u64>(IR_ERDP, controller.events.dequeue_phys());
+    controller.ir_write::<u64>(IR_ERSTBA, controller.events.erst.phys_u64());
+    controller.ir_write::<u32>(IR_IMOD, 0);
+    controller.op_write(OP_USBCMD, USBCMD_RS);
+    if !wait_bits(
+        &controller.regs,
+        op + OP_USBSTS,
+        USBSTS_HCH,
+        0,
+        RESET_TIMEOUT_MS,
+    ) {
+        return Err("controller did not start");
+    }
+
+    let mut devices = Vec::new();
+    for port in 1..=controller.max_ports {
+        if devices.len() == MAX_HID_DEVICES {
+            break;
+        }
+        let Some(speed) = controller.reset_port(port) else {
+            continue;
+        };
+        match controller.enumerate(port, speed) {
+            Ok(Some(device)) => devices.push(device),
+            Ok(None) => {}
+            Err(msg) => klog_info!("xhci: port {}: {}", port, msg),
+        }
+    }
+    if controller.op_read(OP_USBSTS) & USBSTS_HSE != 0 {
+        return Err("host system error during enumeration");
+    }
+
+    let vector = setup_interrupts(info);
+    if vector.is_some() {
+        controller.ir_write::<u32>(IR_IMAN, IMAN_IP | IMAN_IE);
+        controller.op_write(OP_USBCMD, USBCMD_RS | USBCMD_INTE);
+    } else {
+        klog_info!("xhci: no MSI-X or MSI vector; USB input disabled");
+    }
+
+    let device_count = devices.len();
+    {
+        let mut state = XHCI_STATE.lock();
+        state.controller = Some(controller);
+        state.devices = devices;
+        state.vector = vector;
+        state.ready = true;
+
+        let state = &mut *state;
+        if let Some(controller) = state.controller.as_ref()
+            && vector.is_some()
+        {
+            for device in state.devices.iter_mut() {
+                for index in 0..REPORTS_QUEUED {
+                    device.queue_report(index);
+                }
+                controller.ring_doorbell(device.slot, device.dci);
+            }
+        }
+    }
+
+    klog_info!(
+        "xhci: ready, {} HID device(s), vector {:?}",
+        device_count,
+        vector
+    );
+    Ok(())
+}
+
+static XHCI_DRIVER: PciDriver = PciDriver {
+    name: c"xhci".as_ptr().cast(),
+    match_fn: Some(xhci_match),
+    probe: Some(xhci_probe),
+    context: ptr::null_mut(),
+};
+
+pub fn xhci_register_driver() {
+    if pci_register_driver(&XHCI_DRIVER) != 0 {
+        klog_info!("xhci: driver registration failed");
+    }
+}
+
+pub fn xhci_is_ready() -> bool {
+    XHCI_STATE.lock().ready
+}
+
+/// Whether interrupter 0 has a vector, i.e. reports are being delivered.
+pub fn xhci_interrupts_enabled() -> bool {
+    XHCI_STATE.lock().vector.is_some()
+}
+
+/// `(root port, protocol)` of each enumerated HID boot device, protocol
+/// being `HID_PROTOCOL_KEYBOARD` or `HID_PROTOCOL_MOUSE`.
+pub fn xhci_hid_devices() -> Vec<(u8, u8)> {
+    XHCI_STATE
+        .lock()
+        .devices
+        .iter()
+        .map(|d| (d.port, d.protocol))
+        .collect()
+}
@@ -0,0 +1,245 @@
+//! USB integration tests.
+//!
+//! The translation tests are pure.  The test harness boots QEMU with a
+//! `qemu-xhci` controller carrying a USB keyboard and mouse; the
+//! enumeration tests skip when no controller was probed.
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, pass};
+
+use crate::usb::descriptor::{self, HID_PROTOCOL_KEYBOARD, HID_PROTOCOL_MOUSE, SetupPacket};
+use crate::usb::hid::{self, BootKeyboard, MouseReport};
+use crate::usb::regs::{SPEED_FULL, SPEED_HIGH, SPEED_LOW};
+use crate::usb::xhci;
+
+const USAGE_A: u8 = 0x04;
+const USAGE_B: u8 = 0x05;
+const MOD_LEFT_SHIFT: u8 = 0x02;
+const MOD_RIGHT_CTRL: u8 = 0x10;
+
+fn skip_without_xhci() -> Option<TestResult> {
+    if xhci::xhci_is_ready() {
+        None
+    } else {
+        Some(TestResult::Skipped)
+    }
+}
+
+/// Feed `report` to `keyboard` and collect the scancodes it emits.
+fn scancodes(keyboard: &mut BootKeyboard, report: [u8; 8]) -> alloc::vec::Vec<u8> {
+    let mut out = alloc::vec::Vec::new();
+    keyboard.update(&report, |b| out.push(b));
+    out
+}
+
+// =============================================================================
+// HID boot keyboard
+// =============================================================================
+
+pub fn test_hid_usage_table() -> TestResult {
+    assert_eq_test!(hid::usage_to_scancode(USAGE_A), Some(0x1E), "a");
+    assert_eq_test!(hid::usage_to_scancode(0x27), Some(0x0B), "0");
+    assert_eq_test!(hid::usage_to_scancode(0x28), Some(0x1C), "enter");
+    assert_eq_test!(hid::usage_to_scancode(0x45), Some(0x58), "F12");
+    assert_eq_test!(
+        hid::usage_to_scancode(0x52),
+        Some(0x80 | 0x48),
+        "up is extended"
+    );
+    assert_eq_test!(hid::usage_to_scancode(0x00), None, "no event");
+    assert_eq_test!(hid::usage_to_scancode(0x48), None, "pause is unmapped");
+    assert_eq_test!(hid::usage_to_scancode(0xE0), None, "past the table");
+    pass!()
+}
+
+pub fn test_hid_keyboard_press_release() -> TestResult {
+    let mut kbd = BootKeyboard::new();
+    assert_eq_test!(
+        scancodes(&mut kbd, [0, 0, USAGE_A, 0, 0, 0, 0, 0]),
+        alloc::vec![0x1E],
+        "press a"
+    );
+    assert_eq_test!(
+        scancodes(&mut kbd, [0, 0, USAGE_A, 0, 0, 0, 0, 0]),
+        alloc::vec![],
+        "repeated report is silent"
+    );
+    assert_eq_test!(
+        scancodes(&mut kbd, [0, 0, USAGE_B, USAGE_A, 0, 0, 0, 0]),
+        alloc::vec![0x30],
+        "press b, a held in another slot"
+    );
+    assert_eq_test!(
+        scancodes(&mut kbd, [0; 8]),
+        alloc::vec![0xB0, 0x9E],
+        "release both, in report order"
+    );
+    pass!()
+}
+
+pub fn test_hid_keyboard_modifier_order() -> TestResult {
+    let mut kbd = BootKeyboard::new();
+    assert_eq_test!(
+        scancodes(&mut kbd, [MOD_LEFT_SHIFT, 0, USAGE_A, 0, 0, 0, 0, 0]),
+        alloc::vec![0x2A, 0x1E],
+        "shift goes down before the key"
+    );
+    assert_eq_test!(
+        scancodes(&mut kbd, [0; 8]),
+        alloc::vec![0x9E, 0xAA],
+        "shift comes up after the key"
+    );
+    assert_eq_test!(
+        scancodes(&mut kbd, [MOD_RIGHT_CTRL, 0, 0x4F, 0, 0, 0, 0, 0]),
+        alloc::vec![0xE0, 0x1D, 0xE0, 0x4D],
+        "extended modifier and key"
+    );
+    pass!()
+}
+
+pub fn test_hid_keyboard_ignores_rollover() -> TestResult {
+    let mut kbd = BootKeyboard::new();
+    scancodes(&mut kbd, [0, 0, USAGE_A, 0, 0, 0, 0, 0]);
+    assert_eq_test!(
+        scancodes(&mut kbd, [0, 0, 1, 1, 1, 1, 1, 1]),
+        alloc::vec![],
+        "rollover report"
+    );
+    assert_eq_test!(
+        scancodes(&mut kbd, [0; 8]),
+        alloc::vec![0x9E],
+        "state kept across rollover"
+    );
+    pass!()
+}
+
+pub fn test_hid_mouse_report() -> TestResult {
+    assert_eq_test!(
+        hid::parse_mouse_report(&[0x05, 0xFF, 0x02, 0x7F]),
+        Some(MouseReport {
+            buttons: 0x05,
+            dx: -1,
+            dy: 2,
+        }),
+        "buttons, signed deltas, extra bytes ignored"
+    );
+    assert_eq_test!(hid::parse_mouse_report(&[0, 0]), None, "short report");
+    pass!()
+}
+
+// =============================================================================
+// Descriptors
+// =============================================================================
+
+/// Configuration 1: a vendor interface, then a boot keyboard (interface 1)
+/// with an OUT endpoint before its interrupt IN endpoint 2.
+const KEYBOARD_CONFIG: [u8; 41] = [
+    9, 2, 41, 0, 2, 1, 0, 0xA0, 50, // configuration
+    9, 4, 0, 0, 1, 0xFF, 0, 0, 0, // vendor interface
+    7, 5, 0x81, 3, 8, 0, 10, // its endpoint
+    9, 4, 1, 0, 2, 3, 1, 1, 0, // boot keyboard
+    7, 5, 0x01, 3, 8, 0, 10, // interrupt OUT
+];
+
+pub fn test_descriptor_setup_packet() -> TestResult {
+    assert_eq_test!(
+        SetupPacket::get_descriptor(descriptor::DESC_DEVICE, 18).to_u64(),
+        0x0012_0000_0100_0680,
+        "GET_DESCRIPTOR(device)"
+    );
+    assert_test!(
+        SetupPacket::get_descriptor(descriptor::DESC_DEVICE, 8).is_in(),
+        "descriptor reads are IN"
+    );
+    assert_eq_test!(
+        SetupPacket::set_boot_protocol(1).to_u64(),
+        0x0000_0001_0000_0B21,
+        "SET_PROTOCOL(boot) on interface 1"
+    );
+    pass!()
+}
+
+pub fn test_descriptor_finds_boot_interface() -> TestResult {
+    let mut config = alloc::vec::Vec::from(KEYBOARD_CONFIG);
+    assert_eq_test!(
+        descriptor::find_boot_interface(&config),
+        None,
+        "keyboard without an IN endpoint"
+    );
+
+    config.extend_from_slice(&[7, 5, 0x82, 3, 8, 0, 10]);
+    config[2] = config.len() as u8;
+    let iface = descriptor::find_boot_interface(&config);
+    assert_test!(iface.is_some(), "boot keyboard not found");
+    let iface = iface.unwrap();
+    assert_eq_test!(iface.configuration, 1, "configuration value");
+    assert_eq_test!(iface.interface, 1, "interface number");
+    assert_eq_test!(iface.protocol, HID_PROTOCOL_KEYBOARD, "protocol");
+    assert_eq_test!(iface.endpoint, 2, "endpoint number");
+    assert_eq_test!(iface.max_packet, 8, "max packet");
+    assert_eq_test!(iface.interval, 10, "interval");
+
+    // A truncated descriptor must not be read past its end.
+    config[2] = 0xFF;
+    config[3] = 0xFF;
+    assert_test!(
+        descriptor::find_boot_interface(&config).is_some(),
+        "wTotalLength past the buffer"
+    );
+    assert_eq_test!(
+        descriptor::find_boot_interface(&config[..5]),
+        None,
+        "short header"
+    );
+    pass!()
+}
+
+pub fn test_endpoint_interval() -> TestResult {
+    assert_eq_test!(xhci::endpoint_interval(SPEED_FULL, 10), 6, "10 ms");
+    assert_eq_test!(xhci::endpoint_interval(SPEED_LOW, 1), 3, "1 ms");
+    assert_eq_test!(xhci::endpoint_interval(SPEED_LOW, 255), 10, "clamped");
+    assert_eq_test!(xhci::endpoint_interval(SPEED_HIGH, 4), 3, "HS exponent");
+    assert_eq_test!(xhci::endpoint_interval(SPEED_HIGH, 0), 0, "HS zero");
+    pass!()
+}
+
+// =============================================================================
+// Enumeration
+// =============================================================================
+
+pub fn test_xhci_enumerates_boot_devices() -> TestResult {
+    if let Some(skip) = skip_without_xhci() {
+        return skip;
+    }
+    let devices = xhci::xhci_hid_devices();
+    assert_test!(
+        devices.iter().any(|&(_, p)| p == HID_PROTOCOL_KEYBOARD),
+        "no boot keyboard enumerated: {:?}",
+        devices
+    );
+    assert_test!(
+        devices.iter().any(|&(_, p)| p == HID_PROTOCOL_MOUSE),
+        "no boot mouse enumerated: {:?}",
+        devices
+    );
+    assert_test!(
+        xhci::xhci_interrupts_enabled(),
+        "interrupter 0 has no vector"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    usb,
+    [
+        test_hid_usage_table,
+        test_hid_keyboard_press_release,
+        test_hid_keyboard_modifier_order,
+        test_hid_keyboard_ignores_rollover,
+        test_hid_mouse_report,
+        test_descriptor_setup_packet,
+        test_descriptor_finds_boot_interface,
+        test_endpoint_interval,
+        test_xhci_enumerates_boot_devices,
+    ]
+);
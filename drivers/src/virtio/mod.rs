@@ -275,18 +275,14 @@ pub enum InterruptMode {
 
 /// Per-device MSI-X state produced by [`pci::try_setup_msix`].
 ///
-/// Stores the mapped MSI-X table, the allocated IDT vectors for each
-/// virtqueue, and the overall enable state.  Callers must keep this alive
-/// for the lifetime of the device because it owns the MMIO mappings and
-/// the vector allocations.
+/// Wraps the device's [`MsixDomain`](crate::msix::MsixDomain), in which
+/// table entry `i` carries queue `i`.  Callers must keep this alive for the
+/// lifetime of the device because the domain owns the MMIO mappings and the
+/// vector allocations.
 #[derive(Clone, Copy)]
 pub struct VirtioMsixState {
-    /// Parsed MSI-X capability from PCI config space.
-    pub cap: crate::msix::MsixCapability,
-    /// Mapped MSI-X table (MMIO).
-    pub table: crate::msix::MsixTable,
-    /// Allocated IDT vector for each queue (0 = not assigned).
-    pub queue_vectors: [u8; MAX_MSIX_QUEUES],
+    /// MSI-X table and the vectors bound to the queue entries.
+    pub domain: crate::msix::MsixDomain,
     /// Number of queues that were assigned MSI-X vectors.
     pub num_queues: u8,
 }
@@ -298,8 +294,7 @@ impl VirtioMsixState {
     /// Returns [`VIRTIO_MSI_NO_VECTOR`] if the queue has no vector assigned.
     #[inline]
     pub fn queue_msix_entry(&self, queue_idx: u16) -> u16 {
-        let i = queue_idx as usize;
-        if i < self.num_queues as usize && self.queue_vectors[i] != 0 {
+        if self.queue_idt_vector(queue_idx).is_some() {
             queue_idx
         } else {
             VIRTIO_MSI_NO_VECTOR
@@ -309,13 +304,19 @@ impl VirtioMsixState {
     /// IDT vector allocated to the given queue, or `None`.
     #[inline]
     pub fn queue_idt_vector(&self, queue_idx: u16) -> Option<u8> {
-        let i = queue_idx as usize;
-        if i < self.num_queues as usize && self.queue_vectors[i] != 0 {
-            Some(self.queue_vectors[i])
+        if queue_idx < self.num_queues as u16 {
+            self.domain.vector(queue_idx)
         } else {
             None
         }
     }
+
+    /// Re-point the MSI-X entry for `queue_idx` at `apic_id`, keeping its
+    /// vector.  Returns `false` if the queue has no vector or the table
+    /// write fails.
+    pub fn retarget_queue(&self, queue_idx: u16, apic_id: u8) -> bool {
+        queue_idx < self.num_queues as u16 && self.domain.retarget(queue_idx, apic_id).is_ok()
+    }
 }
 
 // =============================================================================
@@ -1,7 +1,7 @@
 //! VirtIO PCI capability parsing, device initialization, and MSI-X/MSI setup
 
 use crate::msi::{self, MsiCapability};
-use crate::msix;
+use crate::msix::{MsixDomain, MsixError};
 use crate::pci_defs::{PCI_COMMAND_BUS_MASTER, PCI_COMMAND_MEMORY_SPACE, PCI_COMMAND_OFFSET};
 use slopos_abi::addr::PhysAddr;
 use slopos_lib::{klog_debug, klog_info};
@@ -188,75 +188,43 @@ pub fn try_setup_msix(
     caps: &VirtioMmioCaps,
     num_queues: u8,
 ) -> Option<VirtioMsixState> {
-    let cap_offset = info.msix_cap_offset?;
     let nq = (num_queues as usize).min(MAX_MSIX_QUEUES);
     if nq == 0 {
         return None;
     }
 
-    // 1. Parse MSI-X capability.
-    let cap = msix::msix_read_capability(info.bus, info.device, info.function, cap_offset);
-    if (cap.table_size as usize) < nq {
+    // 1. Parse the capability and map the MSI-X table + PBA.
+    let mut domain = match MsixDomain::open(info) {
+        Ok(d) => d,
+        Err(MsixError::NoCapability) => return None,
+        Err(e) => {
+            klog_debug!("virtio-msix: table map failed: {:?}", e);
+            return None;
+        }
+    };
+
+    // 2. Bind a vector to each queue's entry, aimed at the BSP.
+    if let Err(e) = domain.alloc_entries(nq as u16) {
         klog_debug!(
-            "virtio-msix: device {}:{}.{} has only {} MSI-X entries, need {}",
+            "virtio-msix: device {}:{}.{} ({} entries) cannot take {} queue vectors: {:?}",
             info.bus,
             info.device,
             info.function,
-            cap.table_size,
+            domain.capability().table_size,
             nq,
+            e,
         );
         return None;
     }
 
-    // 2. Map the MSI-X table + PBA.
-    let table = match msix::msix_map_table(info, &cap) {
-        Ok(t) => t,
-        Err(e) => {
-            klog_debug!("virtio-msix: table map failed: {:?}", e);
-            return None;
-        }
-    };
-
-    // 3. Allocate IDT vectors and program table entries.
-    let apic_id: u8 = 0; // target BSP
-    let mut queue_vectors = [0u8; MAX_MSIX_QUEUES];
-    for i in 0..nq {
-        let vector = match slopos_core::irq::msi_alloc_vector() {
-            Some(v) => v,
-            None => {
-                // Roll back previously allocated vectors.
-                for v in &queue_vectors[..i] {
-                    if *v != 0 {
-                        slopos_core::irq::msi_free_vector(*v);
-                    }
-                }
-                klog_debug!("virtio-msix: vector allocation exhausted at queue {}", i);
-                return None;
-            }
-        };
-
-        if let Err(e) = msix::msix_configure(&table, i as u16, vector, apic_id) {
-            slopos_core::irq::msi_free_vector(vector);
-            for v in &queue_vectors[..i] {
-                if *v != 0 {
-                    slopos_core::irq::msi_free_vector(*v);
-                }
-            }
-            klog_debug!("virtio-msix: configure entry {} failed: {:?}", i, e);
-            return None;
-        }
-
-        queue_vectors[i] = vector;
-    }
-
-    // 4. Tell the device we are NOT using a config-change MSI-X vector.
+    // 3. Tell the device we are NOT using a config-change MSI-X vector.
     if caps.has_common_cfg() {
         caps.common_cfg
             .write::<u16>(COMMON_CFG_MSIX_CONFIG, VIRTIO_MSI_NO_VECTOR);
     }
 
-    // 5. Enable MSI-X on the PCI function.
-    msix::msix_enable(info.bus, info.device, info.function, &cap);
+    // 4. Enable MSI-X on the PCI function.
+    domain.enable();
 
     klog_info!(
         "virtio-msix: {}:{}.{} enabled, {} queue vectors",
@@ -267,9 +235,7 @@ pub fn try_setup_msix(
     );
 
     Some(VirtioMsixState {
-        cap,
-        table,
-        queue_vectors,
+        domain,
         num_queues: nq as u8,
     })
 }
@@ -344,7 +310,8 @@ pub fn setup_interrupts(
 /// interrupt.  For MSI-X, a handler is registered for each per-queue vector.
 /// For MSI, a single handler is registered for the shared vector.
 ///
-/// `device_bdf` is `(bus << 16) | (dev << 8) | func`.
+/// `device_bdf` is `(bus << 16) | (dev << 8) | func`; MSI-X vectors take it
+/// from the device's domain instead.
 pub fn register_irq_handlers(
     mode: &InterruptMode,
     msix_state: Option<&VirtioMsixState>,
@@ -354,16 +321,12 @@ pub fn register_irq_handlers(
     match mode {
         InterruptMode::Msix { num_queues } => {
             if let Some(state) = msix_state {
-                for i in 0..*num_queues as usize {
-                    let vec = state.queue_vectors[i];
-                    if vec != 0 {
-                        slopos_core::irq::msi_register_handler(
-                            vec,
-                            handler,
-                            i as *mut core::ffi::c_void,
-                            device_bdf,
-                        );
-                    }
+                for queue in 0..*num_queues as u16 {
+                    let _ = state.domain.register_handler(
+                        queue,
+                        handler,
+                        queue as usize as *mut core::ffi::c_void,
+                    );
                 }
             }
         }
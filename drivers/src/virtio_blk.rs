@@ -3,7 +3,7 @@ use core::mem::size_of;
 use core::ptr;
 use core::sync::atomic::{AtomicBool, Ordering};
 
-use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_info};
+use slopos_lib::{InitFlag, IrqMutex, clock, klog_debug, klog_info};
 
 use crate::pci::{PciDeviceInfo, PciDriver, pci_register_driver};
 use crate::virtio::{
@@ -27,6 +27,8 @@ const VIRTIO_BLK_S_OK: u8 = 0;
 
 const SECTOR_SIZE: u64 = 512;
 const REQUEST_TIMEOUT_MS: u32 = 5000;
+/// Largest write [`virtio_blk_panic_write`] takes: one bounce page.
+pub const VIRTIO_BLK_PANIC_WRITE_MAX: usize = 4096;
 
 #[repr(C)]
 struct VirtioBlkReqHeader {
@@ -60,6 +62,9 @@ struct VirtioBlkState {
     device: VirtioBlkDevice,
     caps: VirtioMmioCaps,
     msix_state: Option<VirtioMsixState>,
+    /// Buffers set aside by [`virtio_blk_panic_prepare`] so that
+    /// [`virtio_blk_panic_write`] never has to allocate.
+    panic_buffers: Option<RequestBuffers>,
 }
 
 impl VirtioBlkState {
@@ -68,6 +73,7 @@ impl VirtioBlkState {
             device: VirtioBlkDevice::new(),
             caps: VirtioMmioCaps::empty(),
             msix_state: None,
+            panic_buffers: None,
         }
     }
 }
@@ -373,6 +379,10 @@ pub fn virtio_blk_is_ready() -> bool {
     VIRTIO_BLK_STATE.lock().device.ready
 }
 
+pub fn virtio_blk_block_size() -> u32 {
+    SECTOR_SIZE as u32
+}
+
 pub fn virtio_blk_capacity() -> u64 {
     let state = VIRTIO_BLK_STATE.lock();
     state.device.capacity_sectors * SECTOR_SIZE
@@ -462,6 +472,122 @@ pub fn virtio_blk_write(offset: u64, buffer: &[u8]) -> bool {
     true
 }
 
+// =============================================================================
+// Panic-time writes
+// =============================================================================
+
+/// Set aside the buffers [`virtio_blk_panic_write`] needs.  Call once the
+/// device is up; returns `false` if it is not or allocation fails.
+pub fn virtio_blk_panic_prepare() -> bool {
+    let mut state = VIRTIO_BLK_STATE.lock();
+    if !state.device.ready {
+        return false;
+    }
+    if state.panic_buffers.is_none() {
+        state.panic_buffers = RequestBuffers::allocate();
+    }
+    state.panic_buffers.is_some()
+}
+
+/// Write `data` at byte `offset` from a panicking CPU.
+///
+/// Interrupts may be off and other CPUs may be stopped holding locks, so
+/// this never blocks: it gives up if the device lock is held or a request
+/// is already in flight, uses the buffers from [`virtio_blk_panic_prepare`]
+/// and polls the used ring instead of waiting for the completion IRQ.
+/// `offset` and `data.len()` must be whole sectors, at most
+/// [`VIRTIO_BLK_PANIC_WRITE_MAX`] bytes.
+pub fn virtio_blk_panic_write(offset: u64, data: &[u8]) -> bool {
+    if data.is_empty()
+        || data.len() > VIRTIO_BLK_PANIC_WRITE_MAX
+        || !offset.is_multiple_of(SECTOR_SIZE)
+        || !(data.len() as u64).is_multiple_of(SECTOR_SIZE)
+    {
+        return false;
+    }
+    if BLK_REQUEST_IN_FLIGHT
+        .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        return false;
+    }
+    let _request_guard = RequestGuard;
+
+    let Some(mut state) = VIRTIO_BLK_STATE.try_lock() else {
+        return false;
+    };
+    if !state.device.ready || !state.device.queue.is_ready() {
+        return false;
+    }
+    let (req_virt, req_phys, bounce_virt, bounce_phys) = match state.panic_buffers.as_ref() {
+        Some(b) => (
+            b.req_page.as_mut_ptr::<u8>(),
+            b.req_page.phys_u64(),
+            b.bounce_page.as_mut_ptr::<u8>(),
+            b.bounce_page.phys_u64(),
+        ),
+        None => return false,
+    };
+    let header = req_virt as *mut VirtioBlkReqHeader;
+    let status_offset = size_of::<VirtioBlkReqHeader>();
+    let status_ptr = unsafe { req_virt.add(status_offset) };
+    let status_phys = req_phys + status_offset as u64;
+
+    unsafe {
+        ptr::copy_nonoverlapping(data.as_ptr(), bounce_virt, data.len());
+        (*header).type_ = VIRTIO_BLK_T_OUT;
+        (*header).reserved = 0;
+        (*header).sector = offset / SECTOR_SIZE;
+        ptr::write_volatile(status_ptr, 0xFF);
+    }
+
+    state.device.queue.write_desc(
+        0,
+        VirtqDesc {
+            addr: req_phys,
+            len: size_of::<VirtioBlkReqHeader>() as u32,
+            flags: VIRTQ_DESC_F_NEXT,
+            next: 1,
+        },
+    );
+    state.device.queue.write_desc(
+        1,
+        VirtqDesc {
+            addr: bounce_phys,
+            len: data.len() as u32,
+            flags: VIRTQ_DESC_F_NEXT,
+            next: 2,
+        },
+    );
+    state.device.queue.write_desc(
+        2,
+        VirtqDesc {
+            addr: status_phys,
+            len: 1,
+            flags: VIRTQ_DESC_F_WRITE,
+            next: 0,
+        },
+    );
+
+    state.device.queue.submit(0);
+    queue::notify_queue(
+        &state.caps.notify_cfg,
+        state.caps.notify_off_multiplier,
+        &state.device.queue,
+        0,
+    );
+
+    let deadline = clock::monotonic_ns() + REQUEST_TIMEOUT_MS as u64 * 1_000_000;
+    while !state.device.queue.advance_used() {
+        if clock::monotonic_ns() > deadline {
+            return false;
+        }
+        core::hint::spin_loop();
+    }
+
+    unsafe { ptr::read_volatile(status_ptr) == VIRTIO_BLK_S_OK }
+}
+
 // =============================================================================
 // Test-only accessors
 // =============================================================================
@@ -0,0 +1,147 @@
+//! virtio-gpu control-queue wire format (VirtIO 1.2 §5.7.6).
+
+pub const VIRTIO_GPU_CMD_GET_DISPLAY_INFO: u32 = 0x0100;
+pub const VIRTIO_GPU_CMD_RESOURCE_CREATE_2D: u32 = 0x0101;
+pub const VIRTIO_GPU_CMD_RESOURCE_UNREF: u32 = 0x0102;
+pub const VIRTIO_GPU_CMD_SET_SCANOUT: u32 = 0x0103;
+pub const VIRTIO_GPU_CMD_RESOURCE_FLUSH: u32 = 0x0104;
+pub const VIRTIO_GPU_CMD_TRANSFER_TO_HOST_2D: u32 = 0x0105;
+pub const VIRTIO_GPU_CMD_RESOURCE_ATTACH_BACKING: u32 = 0x0106;
+pub const VIRTIO_GPU_CMD_RESOURCE_DETACH_BACKING: u32 = 0x0107;
+
+pub const VIRTIO_GPU_RESP_OK_NODATA: u32 = 0x1100;
+pub const VIRTIO_GPU_RESP_OK_DISPLAY_INFO: u32 = 0x1101;
+
+/// Little-endian B, G, R, X bytes — the kernel's `PixelFormat::Xrgb8888`.
+pub const VIRTIO_GPU_FORMAT_B8G8R8X8_UNORM: u32 = 2;
+
+pub const VIRTIO_GPU_MAX_SCANOUTS: usize = 16;
+
+/// Device config: `events_read`, `events_clear`, `num_scanouts`.
+pub const VIRTIO_GPU_CFG_NUM_SCANOUTS: usize = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct CtrlHdr {
+    pub type_: u32,
+    pub flags: u32,
+    pub fence_id: u64,
+    pub ctx_id: u32,
+    pub ring_idx: u8,
+    pub padding: [u8; 3],
+}
+
+impl CtrlHdr {
+    pub const fn new(type_: u32) -> Self {
+        Self {
+            type_,
+            flags: 0,
+            fence_id: 0,
+            ctx_id: 0,
+            ring_idx: 0,
+            padding: [0; 3],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub const fn full(width: u32, height: u32) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct DisplayOne {
+    pub r: Rect,
+    pub enabled: u32,
+    pub flags: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct RespDisplayInfo {
+    pub hdr: CtrlHdr,
+    pub pmodes: [DisplayOne; VIRTIO_GPU_MAX_SCANOUTS],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ResourceCreate2d {
+    pub hdr: CtrlHdr,
+    pub resource_id: u32,
+    pub format: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ResourceUnref {
+    pub hdr: CtrlHdr,
+    pub resource_id: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct SetScanout {
+    pub hdr: CtrlHdr,
+    pub r: Rect,
+    pub scanout_id: u32,
+    pub resource_id: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ResourceFlush {
+    pub hdr: CtrlHdr,
+    pub r: Rect,
+    pub resource_id: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TransferToHost2d {
+    pub hdr: CtrlHdr,
+    pub r: Rect,
+    pub offset: u64,
+    pub resource_id: u32,
+    pub padding: u32,
+}
+
+/// `RESOURCE_ATTACH_BACKING` with a single memory entry; the backing is
+/// always one physically contiguous allocation.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ResourceAttachBacking {
+    pub hdr: CtrlHdr,
+    pub resource_id: u32,
+    pub nr_entries: u32,
+    pub addr: u64,
+    pub length: u32,
+    pub padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct ResourceDetachBacking {
+    pub hdr: CtrlHdr,
+    pub resource_id: u32,
+    pub padding: u32,
+}
@@ -0,0 +1,584 @@
+//! virtio-gpu 2D driver.
+//!
+//! The scanout is a host resource backed by one physically contiguous run
+//! of guest pages.  The kernel draws into those pages like any linear
+//! framebuffer; [`virtio_gpu_flush`] then asks the host to pull them in
+//! (`TRANSFER_TO_HOST_2D`) and repaint (`RESOURCE_FLUSH`).  Changing
+//! resolution means creating a new resource and pointing scanout 0 at it,
+//! which the device allows at any time.
+//!
+//! Only the control queue is used; there is no cursor plane or 3D.
+
+use core::mem::size_of;
+use core::ptr;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_abi::{DisplayInfo, FramebufferData, PhysAddr, PixelFormat};
+use slopos_lib::cpu;
+use slopos_lib::{InitFlag, IrqMutex, align_up_u64, klog_debug, klog_info, klog_warn};
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::page_alloc::{ALLOC_FLAG_ZERO, OwnedPageFrame, alloc_page_frames, free_page_frame};
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+
+use crate::pci::{PciDeviceInfo, PciDriver, pci_register_driver};
+use crate::virtio::{
+    self, QueueEvent, VIRTIO_MSI_NO_VECTOR, VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE, VirtioMmioCaps,
+    VirtioMsixState,
+    pci::{
+        PCI_VENDOR_ID_VIRTIO, enable_bus_master, negotiate_features, parse_capabilities,
+        register_irq_handlers, set_driver_ok, setup_interrupts,
+    },
+    queue::{self, DEFAULT_QUEUE_SIZE, VirtqDesc, Virtqueue},
+};
+
+mod cmd;
+
+use cmd::{
+    CtrlHdr, Rect, ResourceAttachBacking, ResourceCreate2d, ResourceDetachBacking, ResourceFlush,
+    ResourceUnref, RespDisplayInfo, SetScanout, TransferToHost2d,
+};
+
+pub const VIRTIO_GPU_DEVICE_ID: u16 = 0x1050;
+
+const CONTROL_QUEUE: u16 = 0;
+const SCANOUT_ID: u32 = 0;
+const REQUEST_TIMEOUT_MS: u32 = 1000;
+/// Responses land in the second half of the command page.
+const RESP_OFFSET: usize = 2048;
+const BYTES_PER_PIXEL: u32 = 4;
+
+/// Used when the host reports no enabled scanout and there is no boot
+/// framebuffer to copy the size from.
+const FALLBACK_WIDTH: u32 = 1024;
+const FALLBACK_HEIGHT: u32 = 768;
+
+/// A resource bound (or formerly bound) to scanout 0, with its backing.
+#[derive(Clone, Copy)]
+struct GpuScanout {
+    resource_id: u32,
+    phys: PhysAddr,
+    virt: *mut u8,
+    width: u32,
+    height: u32,
+    pitch: u32,
+}
+
+// Safety: the backing pages are owned by the driver for the life of the
+// resource; access is synchronized through `VIRTIO_GPU_STATE`.
+unsafe impl Send for GpuScanout {}
+
+impl GpuScanout {
+    fn framebuffer(&self) -> FramebufferData {
+        FramebufferData {
+            address: self.virt,
+            info: DisplayInfo::new(self.width, self.height, self.pitch, PixelFormat::Xrgb8888),
+        }
+    }
+
+    fn size(&self) -> u64 {
+        self.pitch as u64 * self.height as u64
+    }
+}
+
+struct VirtioGpuState {
+    queue: Virtqueue,
+    caps: VirtioMmioCaps,
+    msix_state: Option<VirtioMsixState>,
+    cmd_page: Option<OwnedPageFrame>,
+    scanout: Option<GpuScanout>,
+    /// Previous scanout after a mode change, kept alive until the video
+    /// layer has stopped drawing into it.
+    retired: Option<GpuScanout>,
+    next_resource_id: u32,
+    ready: bool,
+}
+
+impl VirtioGpuState {
+    const fn new() -> Self {
+        Self {
+            queue: Virtqueue::new(),
+            caps: VirtioMmioCaps::empty(),
+            msix_state: None,
+            cmd_page: None,
+            scanout: None,
+            retired: None,
+            next_resource_id: 1,
+            ready: false,
+        }
+    }
+}
+
+static DEVICE_CLAIMED: InitFlag = InitFlag::new();
+static VIRTIO_GPU_STATE: IrqMutex<VirtioGpuState> = IrqMutex::new(VirtioGpuState::new());
+static GPU_QUEUE_EVENT: QueueEvent = QueueEvent::new();
+static GPU_REQUEST_IN_FLIGHT: AtomicBool = AtomicBool::new(false);
+
+/// Serializes whole command sequences (a flush is two commands, a mode
+/// change four) so they don't interleave on the single command page.
+struct RequestGuard;
+
+impl RequestGuard {
+    fn acquire(flag: &AtomicBool) -> Self {
+        while flag
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        Self
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        GPU_REQUEST_IN_FLIGHT.store(false, Ordering::Release);
+    }
+}
+
+fn virtio_gpu_match(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> bool {
+    if info.is_null() {
+        return false;
+    }
+    let info = unsafe { &*info };
+    info.vendor_id == PCI_VENDOR_ID_VIRTIO && info.device_id == VIRTIO_GPU_DEVICE_ID
+}
+
+/// Send one command and wait for its response.  Caller holds the
+/// [`RequestGuard`].  Polls the used ring when interrupts are off (panic
+/// screen), otherwise sleeps on the queue event.
+fn exec<Req: Copy, Resp: Copy>(req: &Req) -> Option<Resp> {
+    debug_assert!(size_of::<Req>() <= RESP_OFFSET);
+    debug_assert!(size_of::<Resp>() <= PAGE_SIZE_4KB as usize - RESP_OFFSET);
+
+    let resp_ptr = {
+        let mut state = VIRTIO_GPU_STATE.lock();
+        let state = &mut *state;
+        let page = state.cmd_page.as_ref()?;
+        if !state.queue.is_ready() {
+            return None;
+        }
+        let base = page.as_mut_ptr::<u8>();
+        let resp_ptr = unsafe { base.add(RESP_OFFSET) };
+        unsafe {
+            ptr::write(base as *mut Req, *req);
+            ptr::write_bytes(resp_ptr, 0, size_of::<Resp>());
+        }
+
+        GPU_QUEUE_EVENT.reset();
+        state.queue.write_desc(
+            0,
+            VirtqDesc {
+                addr: page.phys_u64(),
+                len: size_of::<Req>() as u32,
+                flags: VIRTQ_DESC_F_NEXT,
+                next: 1,
+            },
+        );
+        state.queue.write_desc(
+            1,
+            VirtqDesc {
+                addr: page.phys_u64() + RESP_OFFSET as u64,
+                len: size_of::<Resp>() as u32,
+                flags: VIRTQ_DESC_F_WRITE,
+                next: 0,
+            },
+        );
+        state.queue.submit(0);
+        queue::notify_queue(
+            &state.caps.notify_cfg,
+            state.caps.notify_off_multiplier,
+            &state.queue,
+            CONTROL_QUEUE,
+        );
+        resp_ptr
+    };
+
+    let deadline = slopos_lib::clock::uptime_ms() + REQUEST_TIMEOUT_MS as u64;
+    loop {
+        if VIRTIO_GPU_STATE.lock().queue.try_pop_used().is_some() {
+            break;
+        }
+        let now = slopos_lib::clock::uptime_ms();
+        if now >= deadline {
+            klog_info!("virtio-gpu: command timeout");
+            return None;
+        }
+        if cpu::are_interrupts_enabled() {
+            GPU_QUEUE_EVENT.wait_timeout_ms((deadline - now) as u32);
+        } else {
+            core::hint::spin_loop();
+        }
+    }
+
+    Some(unsafe { ptr::read(resp_ptr as *const Resp) })
+}
+
+/// Send a command whose only valid answer is `RESP_OK_NODATA`.
+fn exec_nodata<Req: Copy>(req: &Req, what: &str) -> bool {
+    match exec::<Req, CtrlHdr>(req) {
+        Some(hdr) if hdr.type_ == cmd::VIRTIO_GPU_RESP_OK_NODATA => true,
+        Some(hdr) => {
+            klog_warn!("virtio-gpu: {} failed (resp 0x{:x})", what, hdr.type_);
+            false
+        }
+        None => false,
+    }
+}
+
+fn query_preferred_mode() -> Option<(u32, u32)> {
+    let info: RespDisplayInfo = exec(&CtrlHdr::new(cmd::VIRTIO_GPU_CMD_GET_DISPLAY_INFO))?;
+    if info.hdr.type_ != cmd::VIRTIO_GPU_RESP_OK_DISPLAY_INFO {
+        return None;
+    }
+    let mode = info.pmodes[SCANOUT_ID as usize];
+    (mode.enabled != 0 && mode.r.width != 0 && mode.r.height != 0)
+        .then_some((mode.r.width, mode.r.height))
+}
+
+fn destroy_resource(scanout: &GpuScanout) {
+    exec_nodata(
+        &ResourceDetachBacking {
+            hdr: CtrlHdr::new(cmd::VIRTIO_GPU_CMD_RESOURCE_DETACH_BACKING),
+            resource_id: scanout.resource_id,
+            padding: 0,
+        },
+        "detach backing",
+    );
+    exec_nodata(
+        &ResourceUnref {
+            hdr: CtrlHdr::new(cmd::VIRTIO_GPU_CMD_RESOURCE_UNREF),
+            resource_id: scanout.resource_id,
+            padding: 0,
+        },
+        "resource unref",
+    );
+    let _ = free_page_frame(scanout.phys);
+}
+
+/// Create a `width`×`height` resource with fresh backing and point
+/// scanout 0 at it.  Caller holds the [`RequestGuard`].
+fn create_scanout(width: u32, height: u32) -> Option<GpuScanout> {
+    let pitch = width * BYTES_PER_PIXEL;
+    let size = align_up_u64(pitch as u64 * height as u64, PAGE_SIZE_4KB);
+    let pages = (size / PAGE_SIZE_4KB) as u32;
+
+    let resource_id = {
+        let mut state = VIRTIO_GPU_STATE.lock();
+        let id = state.next_resource_id;
+        state.next_resource_id = id.wrapping_add(1).max(1);
+        id
+    };
+
+    let phys = alloc_page_frames(pages, ALLOC_FLAG_ZERO);
+    if phys.is_null() {
+        klog_warn!("virtio-gpu: no memory for {}x{} backing", width, height);
+        return None;
+    }
+    let Some(virt) = phys.to_virt_checked() else {
+        let _ = free_page_frame(phys);
+        return None;
+    };
+    let scanout = GpuScanout {
+        resource_id,
+        phys,
+        virt: virt.as_mut_ptr::<u8>(),
+        width,
+        height,
+        pitch,
+    };
+
+    if !exec_nodata(
+        &ResourceCreate2d {
+            hdr: CtrlHdr::new(cmd::VIRTIO_GPU_CMD_RESOURCE_CREATE_2D),
+            resource_id,
+            format: cmd::VIRTIO_GPU_FORMAT_B8G8R8X8_UNORM,
+            width,
+            height,
+        },
+        "resource create",
+    ) {
+        let _ = free_page_frame(phys);
+        return None;
+    }
+
+    let bound = exec_nodata(
+        &ResourceAttachBacking {
+            hdr: CtrlHdr::new(cmd::VIRTIO_GPU_CMD_RESOURCE_ATTACH_BACKING),
+            resource_id,
+            nr_entries: 1,
+            addr: phys.as_u64(),
+            length: scanout.size() as u32,
+            padding: 0,
+        },
+        "attach backing",
+    ) && exec_nodata(
+        &SetScanout {
+            hdr: CtrlHdr::new(cmd::VIRTIO_GPU_CMD_SET_SCANOUT),
+            r: Rect::full(width, height),
+            scanout_id: SCANOUT_ID,
+            resource_id,
+        },
+        "set scanout",
+    );
+    if !bound {
+        destroy_resource(&scanout);
+        return None;
+    }
+    Some(scanout)
+}
+
+/// MSI-X / MSI interrupt handler: the control queue has a response.
+extern "C" fn virtio_gpu_irq_handler(
+    _vector: u8,
+    _frame: *mut slopos_lib::InterruptFrame,
+    _ctx: *mut core::ffi::c_void,
+) {
+    GPU_QUEUE_EVENT.signal();
+}
+
+fn virtio_gpu_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> i32 {
+    if !DEVICE_CLAIMED.claim() {
+        klog_debug!("virtio-gpu: already claimed");
+        return -1;
+    }
+
+    let info = unsafe { &*info };
+    klog_info!(
+        "virtio-gpu: probing {:04x}:{:04x} at {:02x}:{:02x}.{}",
+        info.vendor_id,
+        info.device_id,
+        info.bus,
+        info.device,
+        info.function
+    );
+
+    enable_bus_master(info);
+    let caps = parse_capabilities(info);
+    if !caps.has_common_cfg() || !caps.has_notify_cfg() {
+        klog_info!("virtio-gpu: missing common/notify cfg");
+        DEVICE_CLAIMED.reset();
+        return -1;
+    }
+
+    let feat_result = negotiate_features(&caps, virtio::VIRTIO_F_VERSION_1, 0);
+    if !feat_result.success {
+        klog_info!("virtio-gpu: features negotiation failed");
+        DEVICE_CLAIMED.reset();
+        return -1;
+    }
+
+    // Display is optional: without interrupts, leave the device to the
+    // boot framebuffer instead of panicking like the storage drivers.
+    let (irq_mode, msix_state) = match setup_interrupts(info, &caps, 1) {
+        Ok(setup) => setup,
+        Err(msg) => {
+            klog_warn!("virtio-gpu: {}", msg);
+            DEVICE_CLAIMED.reset();
+            return -1;
+        }
+    };
+    let q0_msix_entry = msix_state
+        .as_ref()
+        .map_or(VIRTIO_MSI_NO_VECTOR, |s| s.queue_msix_entry(CONTROL_QUEUE));
+
+    let Some(queue) = queue::setup_queue(
+        &caps.common_cfg,
+        CONTROL_QUEUE,
+        DEFAULT_QUEUE_SIZE,
+        q0_msix_entry,
+    ) else {
+        klog_info!("virtio-gpu: control queue setup failed");
+        DEVICE_CLAIMED.reset();
+        return -1;
+    };
+    let Some(cmd_page) = OwnedPageFrame::alloc_zeroed() else {
+        klog_info!("virtio-gpu: no memory for command page");
+        DEVICE_CLAIMED.reset();
+        return -1;
+    };
+
+    let device_bdf =
+        ((info.bus as u32) << 16) | ((info.device as u32) << 8) | (info.function as u32);
+    register_irq_handlers(
+        &irq_mode,
+        msix_state.as_ref(),
+        virtio_gpu_irq_handler,
+        device_bdf,
+    );
+
+    set_driver_ok(&caps);
+
+    let num_scanouts = if caps.has_device_cfg() {
+        caps.device_cfg
+            .read::<u32>(cmd::VIRTIO_GPU_CFG_NUM_SCANOUTS)
+    } else {
+        0
+    };
+
+    {
+        let mut state = VIRTIO_GPU_STATE.lock();
+        state.queue = queue;
+        state.caps = caps;
+        state.msix_state = msix_state;
+        state.cmd_page = Some(cmd_page);
+        state.ready = true;
+    }
+
+    klog_info!(
+        "virtio-gpu: ready, {} scanout(s), irq {:?}",
+        num_scanouts,
+        irq_mode
+    );
+    0
+}
+
+static VIRTIO_GPU_DRIVER: PciDriver = PciDriver {
+    name: c"virtio-gpu".as_ptr().cast(),
+    match_fn: Some(virtio_gpu_match),
+    probe: Some(virtio_gpu_probe),
+    context: ptr::null_mut(),
+};
+
+pub fn virtio_gpu_register_driver() {
+    if pci_register_driver(&VIRTIO_GPU_DRIVER) != 0 {
+        klog_info!("virtio-gpu: driver registration failed");
+    }
+}
+
+pub fn virtio_gpu_is_ready() -> bool {
+    VIRTIO_GPU_STATE.lock().ready
+}
+
+/// The host's preferred size for scanout 0 (e.g. the QEMU window).
+pub fn virtio_gpu_preferred_mode() -> Option<(u32, u32)> {
+    if !virtio_gpu_is_ready() {
+        return None;
+    }
+    let _guard = RequestGuard::acquire(&GPU_REQUEST_IN_FLIGHT);
+    query_preferred_mode()
+}
+
+/// Bring up scanout 0 and return the framebuffer to draw into.
+///
+/// Sizes the scanout from the host's preferred mode, then the boot
+/// framebuffer.  Returns `None` if the device is missing or refuses the
+/// resource, in which case the caller keeps the boot framebuffer.
+pub fn virtio_gpu_framebuffer_init(boot_fb: Option<FramebufferData>) -> Option<FramebufferData> {
+    if !virtio_gpu_is_ready() {
+        klog_warn!("virtio-gpu: device not probed");
+        return None;
+    }
+
+    let _guard = RequestGuard::acquire(&GPU_REQUEST_IN_FLIGHT);
+    if let Some(active) = VIRTIO_GPU_STATE.lock().scanout {
+        return Some(active.framebuffer());
+    }
+
+    let (width, height) = query_preferred_mode()
+        .or_else(|| boot_fb.map(|fb| (fb.info.width, fb.info.height)))
+        .unwrap_or((FALLBACK_WIDTH, FALLBACK_HEIGHT));
+
+    let Some(scanout) = create_scanout(width, height) else {
+        klog_warn!("virtio-gpu: scanout setup failed");
+        return None;
+    };
+    VIRTIO_GPU_STATE.lock().scanout = Some(scanout);
+
+    klog_info!(
+        "virtio-gpu: scanout {} -> resource {} ({}x{})",
+        SCANOUT_ID,
+        scanout.resource_id,
+        width,
+        height
+    );
+    Some(scanout.framebuffer())
+}
+
+/// Switch scanout 0 to a new `width`×`height` resource.
+///
+/// The old backing stays mapped until [`virtio_gpu_release_retired`], so
+/// the caller can repoint its framebuffer state before the pages go away.
+pub fn virtio_gpu_set_mode(width: u32, height: u32) -> Option<FramebufferData> {
+    if width == 0
+        || height == 0
+        || width > DisplayInfo::MAX_DIMENSION
+        || height > DisplayInfo::MAX_DIMENSION
+        || !virtio_gpu_is_ready()
+    {
+        return None;
+    }
+
+    let _guard = RequestGuard::acquire(&GPU_REQUEST_IN_FLIGHT);
+    // Only one generation may be retired at a time.
+    if let Some(stale) = VIRTIO_GPU_STATE.lock().retired.take() {
+        destroy_resource(&stale);
+    }
+
+    let scanout = create_scanout(width, height)?;
+    {
+        let mut state = VIRTIO_GPU_STATE.lock();
+        state.retired = state.scanout.replace(scanout);
+    }
+    klog_info!(
+        "virtio-gpu: mode set to {}x{} (resource {})",
+        width,
+        height,
+        scanout.resource_id
+    );
+    Some(scanout.framebuffer())
+}
+
+/// Free the scanout replaced by the last [`virtio_gpu_set_mode`].
+pub fn virtio_gpu_release_retired() {
+    let _guard = RequestGuard::acquire(&GPU_REQUEST_IN_FLIGHT);
+    if let Some(stale) = VIRTIO_GPU_STATE.lock().retired.take() {
+        destroy_resource(&stale);
+    }
+}
+
+/// Framebuffer flush callback: push the whole scanout to the host.
+pub fn virtio_gpu_flush() -> i32 {
+    let _guard = RequestGuard::acquire(&GPU_REQUEST_IN_FLIGHT);
+    let Some(scanout) = VIRTIO_GPU_STATE.lock().scanout else {
+        return -1;
+    };
+    let r = Rect::full(scanout.width, scanout.height);
+
+    let ok = exec_nodata(
+        &TransferToHost2d {
+            hdr: CtrlHdr::new(cmd::VIRTIO_GPU_CMD_TRANSFER_TO_HOST_2D),
+            r,
+            offset: 0,
+            resource_id: scanout.resource_id,
+            padding: 0,
+        },
+        "transfer to host",
+    ) && exec_nodata(
+        &ResourceFlush {
+            hdr: CtrlHdr::new(cmd::VIRTIO_GPU_CMD_RESOURCE_FLUSH),
+            r,
+            resource_id: scanout.resource_id,
+            padding: 0,
+        },
+        "resource flush",
+    );
+    if ok { 0 } else { -1 }
+}
+
+// =============================================================================
+// Test-only accessors
+// =============================================================================
+
+/// `(resource_id, width, height)` of the active scanout.
+#[cfg(feature = "itests")]
+pub fn virtio_gpu_scanout() -> Option<(u32, u32, u32)> {
+    VIRTIO_GPU_STATE
+        .lock()
+        .scanout
+        .map(|s| (s.resource_id, s.width, s.height))
+}
+
+#[cfg(feature = "itests")]
+pub fn virtio_gpu_has_retired() -> bool {
+    VIRTIO_GPU_STATE.lock().retired.is_some()
+}
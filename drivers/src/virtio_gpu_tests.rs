@@ -0,0 +1,123 @@
+//! virtio-gpu driver integration tests.
+//!
+//! Skipped unless QEMU exposes a `virtio-gpu-pci` device and the kernel
+//! was booted with `video=virtio-gpu` (otherwise the driver isn't
+//! registered).  When the video layer already drives the scanout, the
+//! mode-change test is skipped so it doesn't pull pages out from under it.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::virtio_gpu;
+
+/// Set when these tests brought up scanout 0 themselves.
+static TEST_OWNS_SCANOUT: AtomicBool = AtomicBool::new(false);
+
+fn skip_without_gpu() -> Option<TestResult> {
+    if virtio_gpu::virtio_gpu_is_ready() {
+        None
+    } else {
+        Some(TestResult::Skipped)
+    }
+}
+
+pub fn test_virtio_gpu_preferred_mode() -> TestResult {
+    if let Some(skip) = skip_without_gpu() {
+        return skip;
+    }
+    let Some((width, height)) = virtio_gpu::virtio_gpu_preferred_mode() else {
+        return fail!("GET_DISPLAY_INFO reported no enabled scanout");
+    };
+    assert_test!(width > 0 && height > 0, "bad mode {}x{}", width, height);
+    pass!()
+}
+
+pub fn test_virtio_gpu_flush() -> TestResult {
+    if let Some(skip) = skip_without_gpu() {
+        return skip;
+    }
+    if virtio_gpu::virtio_gpu_scanout().is_none() {
+        assert_test!(
+            virtio_gpu::virtio_gpu_framebuffer_init(None).is_some(),
+            "scanout setup failed"
+        );
+        TEST_OWNS_SCANOUT.store(true, Ordering::Relaxed);
+    }
+    assert_eq_test!(virtio_gpu::virtio_gpu_flush(), 0, "flush failed");
+    pass!()
+}
+
+/// Switch to 640x480 and back; both scanouts must accept a flush and the
+/// first must stay retired until released.
+pub fn test_virtio_gpu_mode_change() -> TestResult {
+    if let Some(skip) = skip_without_gpu() {
+        return skip;
+    }
+    if !TEST_OWNS_SCANOUT.load(Ordering::Relaxed) {
+        return TestResult::Skipped;
+    }
+    let Some((first_id, width, height)) = virtio_gpu::virtio_gpu_scanout() else {
+        return fail!("no active scanout");
+    };
+
+    let Some(fb) = virtio_gpu::virtio_gpu_set_mode(640, 480) else {
+        return fail!("set_mode 640x480 failed");
+    };
+    assert_eq_test!(fb.info.width, 640, "width after mode set");
+    assert_eq_test!(fb.info.height, 480, "height after mode set");
+    assert_eq_test!(fb.info.pitch, 640 * 4, "pitch after mode set");
+    assert_test!(
+        virtio_gpu::virtio_gpu_has_retired(),
+        "old scanout freed before release"
+    );
+    let Some((second_id, _, _)) = virtio_gpu::virtio_gpu_scanout() else {
+        return fail!("scanout lost after mode set");
+    };
+    assert_test!(second_id != first_id, "mode set reused resource id");
+
+    // SAFETY: fb describes the new scanout's backing, mapped via HHDM.
+    unsafe {
+        core::ptr::write_bytes(fb.address, 0x80, fb.info.buffer_size());
+    }
+    assert_eq_test!(virtio_gpu::virtio_gpu_flush(), 0, "flush after mode set");
+
+    virtio_gpu::virtio_gpu_release_retired();
+    assert_test!(!virtio_gpu::virtio_gpu_has_retired(), "retired not freed");
+
+    assert_test!(
+        virtio_gpu::virtio_gpu_set_mode(width, height).is_some(),
+        "restoring {}x{} failed",
+        width,
+        height
+    );
+    virtio_gpu::virtio_gpu_release_retired();
+    assert_eq_test!(virtio_gpu::virtio_gpu_flush(), 0, "flush after restore");
+    pass!()
+}
+
+pub fn test_virtio_gpu_rejects_bad_mode() -> TestResult {
+    if let Some(skip) = skip_without_gpu() {
+        return skip;
+    }
+    assert_test!(
+        virtio_gpu::virtio_gpu_set_mode(0, 480).is_none(),
+        "zero width accepted"
+    );
+    assert_test!(
+        virtio_gpu::virtio_gpu_set_mode(640, 100_000).is_none(),
+        "oversized height accepted"
+    );
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    virtio_gpu,
+    [
+        test_virtio_gpu_preferred_mode,
+        test_virtio_gpu_flush,
+        test_virtio_gpu_mode_change,
+        test_virtio_gpu_rejects_bad_mode,
+    ]
+);
@@ -74,7 +74,7 @@ pub fn test_virtio_blk_vector_in_range() -> TestResult {
         Some(s) => s,
         None => return fail!("virtio-blk MSI-X state is None"),
     };
-    let vec = state.queue_vectors[0];
+    let vec = state.queue_idt_vector(0).unwrap_or(0);
     assert_test!(vec != 0, "virtio-blk queue 0 vector should be allocated");
     assert_test!(
         vec >= MSI_VECTOR_BASE && vec <= MSI_VECTOR_MAX,
@@ -93,8 +93,8 @@ pub fn test_virtio_blk_table_entry_matches_vector() -> TestResult {
         Some(s) => s,
         None => return fail!("virtio-blk MSI-X state is None"),
     };
-    let expected_vec = state.queue_vectors[0];
-    let msg_data = match state.table.read_msg_data(0) {
+    let expected_vec = state.queue_idt_vector(0).unwrap_or(0);
+    let msg_data = match state.domain.table().read_msg_data(0) {
         Some(d) => d,
         None => return fail!("failed to read MSI-X table entry 0 data"),
     };
@@ -113,7 +113,7 @@ pub fn test_virtio_blk_table_entry_targets_bsp() -> TestResult {
         Some(s) => s,
         None => return fail!("virtio-blk MSI-X state is None"),
     };
-    let addr_lo = match state.table.read_msg_addr_lo(0) {
+    let addr_lo = match state.domain.table().read_msg_addr_lo(0) {
         Some(a) => a,
         None => return fail!("failed to read MSI-X table entry 0 addr"),
     };
@@ -133,7 +133,7 @@ pub fn test_virtio_blk_entry_unmasked() -> TestResult {
         Some(s) => s,
         None => return fail!("virtio-blk MSI-X state is None"),
     };
-    let ctrl = match state.table.read_vector_control(0) {
+    let ctrl = match state.domain.table().read_vector_control(0) {
         Some(c) => c,
         None => return fail!("failed to read MSI-X table entry 0 vector control"),
     };
@@ -173,28 +173,35 @@ pub fn test_virtio_net_ready() -> TestResult {
     pass!()
 }
 
-/// VirtIO-net must have MSI-X state with 2 queue vectors (RX + TX).
+/// VirtIO-net must have MSI-X state with an RX + TX vector per queue pair.
 pub fn test_virtio_net_has_msix_state() -> TestResult {
     let state = match virtio_net::virtio_net_msix_state() {
         Some(s) => s,
         None => return fail!("virtio-net MSI-X state is None — unexpected on q35"),
     };
-    assert_eq_test!(
-        state.num_queues,
-        2,
-        "virtio-net should have 2 queue vectors (RX + TX)"
+    assert_test!(
+        state.num_queues >= 2 && state.num_queues % 2 == 0,
+        "virtio-net should have RX + TX vectors per pair, got {}",
+        state.num_queues
+    );
+    let pairs = virtio_net::virtio_net_queue_pairs();
+    assert_test!(
+        state.num_queues as usize >= pairs * 2,
+        "virtio-net uses {} queue pairs but has only {} vectors",
+        pairs,
+        state.num_queues
     );
     pass!()
 }
 
-/// Both VirtIO-net queue vectors must be in the valid IDT MSI range.
+/// Every VirtIO-net queue vector must be in the valid IDT MSI range.
 pub fn test_virtio_net_vectors_in_range() -> TestResult {
     let state = match virtio_net::virtio_net_msix_state() {
         Some(s) => s,
         None => return fail!("virtio-net MSI-X state is None"),
     };
-    for q in 0..2u8 {
-        let vec = state.queue_vectors[q as usize];
+    for q in 0..state.num_queues {
+        let vec = state.queue_idt_vector(q as u16).unwrap_or(0);
         assert_test!(
             vec != 0,
             "virtio-net queue {} vector should be allocated",
@@ -212,32 +219,37 @@ pub fn test_virtio_net_vectors_in_range() -> TestResult {
     pass!()
 }
 
-/// VirtIO-net RX and TX vectors must be distinct (per-queue isolation).
+/// VirtIO-net queue vectors must be distinct (per-queue isolation).
 pub fn test_virtio_net_vectors_distinct() -> TestResult {
     let state = match virtio_net::virtio_net_msix_state() {
         Some(s) => s,
         None => return fail!("virtio-net MSI-X state is None"),
     };
-    let rx_vec = state.queue_vectors[0];
-    let tx_vec = state.queue_vectors[1];
-    assert_test!(
-        rx_vec != tx_vec,
-        "RX vector ({}) and TX vector ({}) should be distinct",
-        rx_vec,
-        tx_vec
-    );
+    let vectors = (0..state.num_queues as u16).map(|q| state.queue_idt_vector(q).unwrap_or(0));
+    for (i, a) in vectors.clone().enumerate() {
+        for (j, b) in vectors.clone().enumerate().skip(i + 1) {
+            assert_test!(
+                a != b,
+                "queue {} vector ({}) and queue {} vector ({}) should be distinct",
+                i,
+                a,
+                j,
+                b
+            );
+        }
+    }
     pass!()
 }
 
-/// MSI-X table entries for both net queues must contain the correct vectors.
+/// MSI-X table entries for every net queue must contain the correct vectors.
 pub fn test_virtio_net_table_entries_match_vectors() -> TestResult {
     let state = match virtio_net::virtio_net_msix_state() {
         Some(s) => s,
         None => return fail!("virtio-net MSI-X state is None"),
     };
-    for q in 0..2u16 {
-        let expected = state.queue_vectors[q as usize];
-        let msg_data = match state.table.read_msg_data(q) {
+    for q in 0..state.num_queues as u16 {
+        let expected = state.queue_idt_vector(q).unwrap_or(0);
+        let msg_data = match state.domain.table().read_msg_data(q) {
             Some(d) => d,
             None => return fail!("failed to read MSI-X table entry {} data", q),
         };
@@ -251,14 +263,14 @@ pub fn test_virtio_net_table_entries_match_vectors() -> TestResult {
     pass!()
 }
 
-/// MSI-X table entries for both net queues must target APIC ID 0 (BSP).
+/// MSI-X table entries for net queue pair 0 must target APIC ID 0 (BSP).
 pub fn test_virtio_net_table_entries_target_bsp() -> TestResult {
     let state = match virtio_net::virtio_net_msix_state() {
         Some(s) => s,
         None => return fail!("virtio-net MSI-X state is None"),
     };
     for q in 0..2u16 {
-        let addr_lo = match state.table.read_msg_addr_lo(q) {
+        let addr_lo = match state.domain.table().read_msg_addr_lo(q) {
             Some(a) => a,
             None => return fail!("failed to read MSI-X table entry {} addr", q),
         };
@@ -272,14 +284,14 @@ pub fn test_virtio_net_table_entries_target_bsp() -> TestResult {
     pass!()
 }
 
-/// Both MSI-X table entries for net queues must be unmasked.
+/// MSI-X table entries for every net queue must be unmasked.
 pub fn test_virtio_net_entries_unmasked() -> TestResult {
     let state = match virtio_net::virtio_net_msix_state() {
         Some(s) => s,
         None => return fail!("virtio-net MSI-X state is None"),
     };
-    for q in 0..2u16 {
-        let ctrl = match state.table.read_vector_control(q) {
+    for q in 0..state.num_queues as u16 {
+        let ctrl = match state.domain.table().read_vector_control(q) {
             Some(c) => c,
             None => return fail!("failed to read MSI-X entry {} vector control", q),
         };
@@ -292,6 +304,33 @@ pub fn test_virtio_net_entries_unmasked() -> TestResult {
     pass!()
 }
 
+/// Each extra net queue pair must interrupt the CPU that transmits on it.
+pub fn test_virtio_net_pairs_target_own_cpu() -> TestResult {
+    let state = match virtio_net::virtio_net_msix_state() {
+        Some(s) => s,
+        None => return fail!("virtio-net MSI-X state is None"),
+    };
+    for pair in 1..virtio_net::virtio_net_queue_pairs() {
+        let Some(cpu) = slopos_lib::pcr::get_pcr(pair) else {
+            return fail!("no PCR for CPU {} backing queue pair {}", pair, pair);
+        };
+        for q in [pair as u16 * 2, pair as u16 * 2 + 1] {
+            let addr_lo = match state.domain.table().read_msg_addr_lo(q) {
+                Some(a) => a,
+                None => return fail!("failed to read MSI-X table entry {} addr", q),
+            };
+            let dest_id = (addr_lo >> 12) & 0xFF;
+            assert_test!(
+                dest_id == cpu.apic_id & 0xFF,
+                "MSI-X entry {} should target CPU {}",
+                q,
+                pair
+            );
+        }
+    }
+    pass!()
+}
+
 /// MSI-X must be enabled in PCI config space for the VirtIO-net device.
 pub fn test_virtio_net_msix_enabled_in_config() -> TestResult {
     let dev = match find_device(0x1af4, 0x1041) {
@@ -313,6 +352,40 @@ pub fn test_virtio_net_msix_enabled_in_config() -> TestResult {
 // =============================================================================
 
 /// VirtIO-blk and VirtIO-net must not share any MSI-X vectors.
+/// The core vector table and the domain registry must agree with the table
+/// entries: each net vector is owned by the device and aimed where the entry
+/// points.
+pub fn test_virtio_net_domain_tracks_vectors() -> TestResult {
+    let state = match virtio_net::virtio_net_msix_state() {
+        Some(s) => s,
+        None => return fail!("virtio-net MSI-X state is None"),
+    };
+    let bdf = state.domain.bdf();
+    assert_test!(
+        crate::msix::msix_domain_lookup(bdf).is_some(),
+        "virtio-net domain not registered"
+    );
+    for q in 0..state.num_queues as u16 {
+        let Some(vector) = state.queue_idt_vector(q) else {
+            return fail!("queue {} has no vector", q);
+        };
+        let Some(info) = slopos_core::irq::msi_vector_info(vector) else {
+            return fail!("vector {} not allocated in the core table", vector);
+        };
+        let Some(addr_lo) = state.domain.table().read_msg_addr_lo(q) else {
+            return fail!("failed to read MSI-X table entry {} addr", q);
+        };
+        assert_eq_test!(info.device_bdf, bdf, "vector owner");
+        assert_test!(info.registered, "queue {} vector has no handler", q);
+        assert_eq_test!(
+            info.target_apic as u32,
+            (addr_lo >> 12) & 0xFF,
+            "recorded affinity differs from the table entry"
+        );
+    }
+    pass!()
+}
+
 pub fn test_blk_and_net_vectors_disjoint() -> TestResult {
     let blk = match virtio_blk::virtio_blk_msix_state() {
         Some(s) => s,
@@ -324,12 +397,12 @@ pub fn test_blk_and_net_vectors_disjoint() -> TestResult {
     };
 
     for bq in 0..blk.num_queues as usize {
-        let bv = blk.queue_vectors[bq];
+        let bv = blk.queue_idt_vector(bq as u16).unwrap_or(0);
         if bv == 0 {
             continue;
         }
         for nq in 0..net.num_queues as usize {
-            let nv = net.queue_vectors[nq];
+            let nv = net.queue_idt_vector(nq as u16).unwrap_or(0);
             assert_test!(
                 bv != nv,
                 "blk queue {} vector {} collides with net queue {} vector {}",
@@ -423,8 +496,10 @@ slopos_lib::define_test_suite!(
         test_virtio_net_table_entries_match_vectors,
         test_virtio_net_table_entries_target_bsp,
         test_virtio_net_entries_unmasked,
+        test_virtio_net_pairs_target_own_cpu,
         test_virtio_net_msix_enabled_in_config,
         // Cross-device
+        test_virtio_net_domain_tracks_vectors,
         test_blk_and_net_vectors_disjoint,
         test_msix_preferred_over_msi_on_q35,
         test_queue_msix_entry_helper,
@@ -4,25 +4,30 @@ use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::ffi::c_int;
 use core::mem::size_of;
-use core::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, Ordering};
 
 use slopos_abi::net::{
-    USER_NET_MEMBER_FLAG_ARP, USER_NET_MEMBER_FLAG_IPV4, UserNetInfo, UserNetMember,
+    NET_CONFIG_DHCP, NET_CONFIG_NONE, NET_CONFIG_STATIC, USER_NET_MEMBER_FLAG_ARP,
+    USER_NET_MEMBER_FLAG_IPV4, UserNetInfo, UserNetMember,
 };
-use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_info};
+use slopos_lib::lockstat::LockClass;
+use slopos_lib::{InitFlag, IrqMutex, klog_debug, klog_info, pcr};
 
 use crate::net::{
-    self, PACKET_POOL, dhcp, ingress,
+    self, PACKET_POOL, dhcp, ingress, loopback,
     napi::NapiContext,
     netdev::{DEVICE_REGISTRY, DeviceHandle, NetDevice, NetDeviceFeatures, NetDeviceStats},
+    netstack::NET_STACK,
     packetbuf::PacketBuf,
     pool::PacketPool,
     socket, tcp,
-    types::{MacAddr, NetError},
+    timer::{NET_TIMER_WHEEL, TimerKind},
+    types::{DevIndex, Ipv4Addr, MacAddr, NetError},
 };
 use crate::pci::{PciDeviceInfo, PciDriver, pci_register_driver};
 use crate::virtio::{
-    self, QueueEvent, VIRTIO_MSI_NO_VECTOR, VIRTQ_DESC_F_WRITE, VirtioMmioCaps, VirtioMsixState,
+    self, MAX_MSIX_QUEUES, QueueEvent, VIRTIO_MSI_NO_VECTOR, VIRTQ_DESC_F_NEXT, VIRTQ_DESC_F_WRITE,
+    VirtioMmioCaps, VirtioMsixState,
     pci::{
         PCI_VENDOR_ID_VIRTIO, enable_bus_master, negotiate_features, parse_capabilities,
         register_irq_handlers, set_driver_ok, setup_interrupts,
@@ -36,21 +41,32 @@ use slopos_mm::page_alloc::OwnedPageFrame;
 pub const VIRTIO_NET_DEVICE_ID_LEGACY: u16 = 0x1000;
 pub const VIRTIO_NET_DEVICE_ID_MODERN: u16 = 0x1041;
 
-const VIRTIO_NET_QUEUE_RX: u16 = 0;
-const VIRTIO_NET_QUEUE_TX: u16 = 1;
-
 const VIRTIO_NET_F_CSUM: u64 = 1 << 0;
 const VIRTIO_NET_F_MAC: u64 = 1 << 5;
 const VIRTIO_NET_F_STATUS: u64 = 1 << 16;
 const VIRTIO_NET_F_MTU: u64 = 1 << 3;
 const VIRTIO_NET_F_GUEST_CSUM: u64 = 1 << 1;
+const VIRTIO_NET_F_CTRL_VQ: u64 = 1 << 17;
+const VIRTIO_NET_F_MQ: u64 = 1 << 22;
 
 const VIRTIO_NET_S_LINK_UP: u16 = 1;
 
+const VIRTIO_NET_HDR_F_NEEDS_CSUM: u8 = 1;
+
+const VIRTIO_NET_CTRL_MQ: u8 = 4;
+const VIRTIO_NET_CTRL_MQ_VQ_PAIRS_SET: u8 = 0;
+const VIRTIO_NET_OK: u8 = 0;
+
 const DEV_CFG_MAC_OFFSET: usize = 0x00;
 const DEV_CFG_STATUS_OFFSET: usize = 0x06;
+const DEV_CFG_MAX_PAIRS_OFFSET: usize = 0x08;
 const DEV_CFG_MTU_OFFSET: usize = 0x0A;
 
+/// Queue pairs we drive: every RX and TX queue gets its own MSI-X vector.
+const MAX_QUEUE_PAIRS: usize = MAX_MSIX_QUEUES / 2;
+/// How long to spin for the device to acknowledge a control command.
+const CTRL_TIMEOUT_MS: u64 = 100;
+
 const DHCP_REQUEST_TIMEOUT_MS: u32 = 5000;
 /// Short timeout for ARP probe / scan operations (ms).  ARP replies on a
 /// local LAN arrive in < 10 ms; 150 ms is generous while keeping the scan
@@ -66,8 +82,12 @@ const DHCP_RX_MAX_POLLS: usize = 64;
 const RX_RING_SIZE: usize = 64;
 const TX_RING_SIZE: usize = 64;
 const NAPI_BUDGET: u32 = 64;
+/// How often the link status is polled for the DHCP client (~500 ms).
+const LINK_WATCH_TICKS: u64 = 50;
 
 static DHCP_XID_COUNTER: AtomicU32 = AtomicU32::new(0x534c_4f50);
+/// Link status at the last poll.
+static LINK_WAS_UP: AtomicBool = AtomicBool::new(false);
 
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
@@ -84,8 +104,10 @@ struct VirtioNetHdrV1 {
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
 struct VirtioNetDevice {
-    rx_queue: Virtqueue,
-    tx_queue: Virtqueue,
+    ctrl_queue: Virtqueue,
+    ctrl_queue_index: u16,
+    /// Queue pairs the device is steering traffic across.
+    num_pairs: usize,
     negotiated_features: u64,
     mac: [u8; 6],
     mtu: u16,
@@ -95,8 +117,9 @@ struct VirtioNetDevice {
 impl VirtioNetDevice {
     const fn new() -> Self {
         Self {
-            rx_queue: Virtqueue::new(),
-            tx_queue: Virtqueue::new(),
+            ctrl_queue: Virtqueue::new(),
+            ctrl_queue_index: 0,
+            num_pairs: 0,
             negotiated_features: 0,
             mac: [0; 6],
             mtu: DEFAULT_MTU,
@@ -105,6 +128,35 @@ impl VirtioNetDevice {
     }
 }
 
+/// RX queue `2n` and TX queue `2n + 1` plus the pages posted to them.
+struct VirtioNetQueuePair {
+    rx_queue: Virtqueue,
+    tx_queue: Virtqueue,
+    rx_buffers: [Option<OwnedPageFrame>; RX_RING_SIZE],
+    tx_buffers: [Option<OwnedPageFrame>; TX_RING_SIZE],
+}
+
+impl VirtioNetQueuePair {
+    const fn new() -> Self {
+        Self {
+            rx_queue: Virtqueue::new(),
+            tx_queue: Virtqueue::new(),
+            rx_buffers: [const { None }; RX_RING_SIZE],
+            tx_buffers: [const { None }; TX_RING_SIZE],
+        }
+    }
+}
+
+#[inline]
+fn rx_queue_index(pair: usize) -> u16 {
+    (pair * 2) as u16
+}
+
+#[inline]
+fn tx_queue_index(pair: usize) -> u16 {
+    (pair * 2 + 1) as u16
+}
+
 struct VirtioNetState {
     device: VirtioNetDevice,
     caps: VirtioMmioCaps,
@@ -113,10 +165,11 @@ struct VirtioNetState {
     subnet_mask: [u8; 4],
     router: [u8; 4],
     dns: [u8; 4],
+    /// Where the address came from (`NET_CONFIG_*`).
+    config_source: u8,
     members: [UserNetMember; MAX_NET_MEMBERS],
     member_count: usize,
-    rx_buffers: [Option<OwnedPageFrame>; RX_RING_SIZE],
-    tx_buffers: [Option<OwnedPageFrame>; TX_RING_SIZE],
+    pairs: [VirtioNetQueuePair; MAX_QUEUE_PAIRS],
     tx_inflight: AtomicU32,
 }
 
@@ -130,27 +183,26 @@ impl VirtioNetState {
             subnet_mask: [0; 4],
             router: [0; 4],
             dns: [0; 4],
+            config_source: NET_CONFIG_NONE,
             members: [UserNetMember {
                 ipv4: [0; 4],
                 mac: [0; 6],
                 flags: 0,
             }; MAX_NET_MEMBERS],
             member_count: 0,
-            rx_buffers: [const { None }; RX_RING_SIZE],
-            tx_buffers: [const { None }; TX_RING_SIZE],
+            pairs: [const { VirtioNetQueuePair::new() }; MAX_QUEUE_PAIRS],
             tx_inflight: AtomicU32::new(0),
         }
     }
 }
 
 static DEVICE_CLAIMED: InitFlag = InitFlag::new();
-static VIRTIO_NET_STATE: IrqMutex<VirtioNetState> = IrqMutex::new(VirtioNetState::new());
+static VIRTIO_NET_LOCK_CLASS: LockClass = LockClass::new("virtio_net");
+static VIRTIO_NET_STATE: IrqMutex<VirtioNetState> =
+    IrqMutex::with_class(&VIRTIO_NET_LOCK_CLASS, VirtioNetState::new());
 static DHCP_RX_EVENT: QueueEvent = QueueEvent::new();
 static NAPI_EVENT: QueueEvent = QueueEvent::new();
 static NAPI_CONTEXT: NapiContext = NapiContext::new(NAPI_BUDGET);
-static DNS_RX_EVENT: QueueEvent = QueueEvent::new();
-/// Buffer for the most recent DNS response payload (UDP body only).
-static DNS_RX_BUF: IrqMutex<DnsRxBuf> = IrqMutex::new(DnsRxBuf::new());
 
 static DEVICE_HANDLE_PTR: AtomicPtr<DeviceHandle> = AtomicPtr::new(core::ptr::null_mut());
 
@@ -169,20 +221,6 @@ fn set_device_handle(handle: DeviceHandle) {
     DEVICE_HANDLE_PTR.store(ptr, Ordering::Release);
 }
 
-struct DnsRxBuf {
-    data: [u8; 512],
-    len: usize,
-}
-
-impl DnsRxBuf {
-    const fn new() -> Self {
-        Self {
-            data: [0; 512],
-            len: 0,
-        }
-    }
-}
-
 pub struct VirtioNetDev;
 
 impl NetDevice for VirtioNetDev {
@@ -209,6 +247,11 @@ impl NetDevice for VirtioNetDev {
                 payload.len(),
             );
         }
+        // DeviceHandle only passes partial checksums through when we
+        // advertise CHECKSUM_TX, i.e. VIRTIO_NET_F_CSUM was negotiated.
+        if let Some((csum_start, csum_offset)) = pkt.checksum_partial() {
+            mark_needs_csum(&tx_page, csum_start, csum_offset);
+        }
 
         if submit_tx(&mut state, tx_page, (hdr_len + payload.len()) as u32) {
             Ok(())
@@ -222,53 +265,11 @@ impl NetDevice for VirtioNetDev {
         let _ = virtnet_clean_tx(&mut state);
 
         let mut packets = Vec::with_capacity(budget.min(64));
-        let mut posted = 0usize;
-
-        for _ in 0..budget {
-            let Some(used) = state.device.rx_queue.try_pop_used() else {
-                break;
-            };
-
-            let idx = (used.id as usize) % RX_RING_SIZE;
-            let Some(page) = state.rx_buffers[idx].take() else {
-                continue;
-            };
-
-            let hdr_len = size_of::<VirtioNetHdrV1>();
-            if (used.len as usize) > hdr_len {
-                let payload_len = (used.len as usize) - hdr_len;
-                let frame = unsafe {
-                    core::slice::from_raw_parts(page.as_mut_ptr::<u8>().add(hdr_len), payload_len)
-                };
-                if let Some(pkt) = PacketBuf::from_raw_copy(frame) {
-                    packets.push(pkt);
-                }
+        virtnet_drain_rx(&mut state, budget, |_, frame| {
+            if let Some(pkt) = PacketBuf::from_raw_copy(frame) {
+                packets.push(pkt);
             }
-
-            if let Some(new_page) = OwnedPageFrame::alloc_zeroed() {
-                state.device.rx_queue.write_desc(
-                    idx as u16,
-                    VirtqDesc {
-                        addr: new_page.phys_u64(),
-                        len: PACKET_BUFFER_SIZE as u32,
-                        flags: VIRTQ_DESC_F_WRITE,
-                        next: 0,
-                    },
-                );
-                state.rx_buffers[idx] = Some(new_page);
-                state.device.rx_queue.submit(idx as u16);
-                posted += 1;
-            }
-        }
-
-        if posted > 0 {
-            queue::notify_queue(
-                &state.caps.notify_cfg,
-                state.caps.notify_off_multiplier,
-                &state.device.rx_queue,
-                VIRTIO_NET_QUEUE_RX,
-            );
-        }
+        });
 
         packets
     }
@@ -305,15 +306,6 @@ impl NetDevice for VirtioNetDev {
     }
 }
 
-pub fn dns_intercept_response(payload: &[u8]) {
-    let copy_len = payload.len().min(512);
-    let mut dns_buf = DNS_RX_BUF.lock();
-    dns_buf.data[..copy_len].copy_from_slice(&payload[..copy_len]);
-    dns_buf.len = copy_len;
-    drop(dns_buf);
-    DNS_RX_EVENT.signal();
-}
-
 pub fn sniff_packet_for_members(frame: &[u8]) {
     let mut state = VIRTIO_NET_STATE.lock();
     sniff_frame_for_members(&mut state, frame);
@@ -361,6 +353,17 @@ fn read_mtu(caps: &VirtioMmioCaps, negotiated_features: u64) -> u16 {
     caps.device_cfg.read::<u16>(DEV_CFG_MTU_OFFSET)
 }
 
+/// `max_virtqueue_pairs` from device config, or 1 without `VIRTIO_NET_F_MQ`.
+fn read_max_pairs(caps: &VirtioMmioCaps, negotiated_features: u64) -> u16 {
+    if (negotiated_features & VIRTIO_NET_F_MQ) == 0
+        || !caps.has_device_cfg()
+        || caps.device_cfg_len < (DEV_CFG_MAX_PAIRS_OFFSET as u32 + 2)
+    {
+        return 1;
+    }
+    caps.device_cfg.read::<u16>(DEV_CFG_MAX_PAIRS_OFFSET).max(1)
+}
+
 fn link_is_up(state: &VirtioNetState) -> bool {
     if !state.device.ready {
         return false;
@@ -450,21 +453,31 @@ fn sniff_frame_for_members(state: &mut VirtioNetState, frame: &[u8]) {
 
 fn virtnet_clean_tx(state: &mut VirtioNetState) -> usize {
     let mut cleaned = 0usize;
-    while let Some(used) = state.device.tx_queue.try_pop_used() {
-        let idx = (used.id as usize) % TX_RING_SIZE;
-        let _ = state.tx_buffers[idx].take();
-        state.tx_inflight.fetch_sub(1, Ordering::Relaxed);
-        cleaned += 1;
+    let num_pairs = state.device.num_pairs;
+    for pair in &mut state.pairs[..num_pairs] {
+        while let Some(used) = pair.tx_queue.try_pop_used() {
+            let idx = (used.id as usize) % TX_RING_SIZE;
+            let _ = pair.tx_buffers[idx].take();
+            state.tx_inflight.fetch_sub(1, Ordering::Relaxed);
+            cleaned += 1;
+        }
     }
     cleaned
 }
 
+/// Each CPU transmits on its own queue pair so TX completions stay local.
+fn tx_pair_for_current_cpu(state: &VirtioNetState) -> usize {
+    pcr::current_cpu_id() % state.device.num_pairs.max(1)
+}
+
 fn submit_tx(state: &mut VirtioNetState, page: OwnedPageFrame, total_len: u32) -> bool {
     let _ = virtnet_clean_tx(state);
 
+    let pair_idx = tx_pair_for_current_cpu(state);
+    let pair = &mut state.pairs[pair_idx];
     let mut slot = None;
     for idx in 0..TX_RING_SIZE {
-        if state.tx_buffers[idx].is_none() {
+        if pair.tx_buffers[idx].is_none() {
             slot = Some(idx);
             break;
         }
@@ -473,7 +486,7 @@ fn submit_tx(state: &mut VirtioNetState, page: OwnedPageFrame, total_len: u32) -
         return false;
     };
 
-    state.device.tx_queue.write_desc(
+    pair.tx_queue.write_desc(
         slot_idx as u16,
         VirtqDesc {
             addr: page.phys_u64(),
@@ -482,15 +495,15 @@ fn submit_tx(state: &mut VirtioNetState, page: OwnedPageFrame, total_len: u32) -
             next: 0,
         },
     );
-    state.tx_buffers[slot_idx] = Some(page);
+    pair.tx_buffers[slot_idx] = Some(page);
     state.tx_inflight.fetch_add(1, Ordering::Relaxed);
 
-    state.device.tx_queue.submit(slot_idx as u16);
+    pair.tx_queue.submit(slot_idx as u16);
     queue::notify_queue(
         &state.caps.notify_cfg,
         state.caps.notify_off_multiplier,
-        &state.device.tx_queue,
-        VIRTIO_NET_QUEUE_TX,
+        &pair.tx_queue,
+        tx_queue_index(pair_idx),
     );
     true
 }
@@ -505,12 +518,132 @@ fn alloc_tx_page() -> Option<OwnedPageFrame> {
     Some(page)
 }
 
+/// Ask the device to fill in the L4 checksum: it sums the frame from
+/// `csum_start` onward and stores the result at `csum_start + csum_offset`.
+fn mark_needs_csum(page: &OwnedPageFrame, csum_start: u16, csum_offset: u16) {
+    unsafe {
+        let hdr = &mut *page.as_mut_ptr::<VirtioNetHdrV1>();
+        hdr.flags = VIRTIO_NET_HDR_F_NEEDS_CSUM;
+        hdr.csum_start = csum_start;
+        hdr.csum_offset = csum_offset;
+    }
+}
+
+// =============================================================================
+// Control virtqueue
+// =============================================================================
+
+/// Send a control command and spin until the device acknowledges it.
+///
+/// The command is a three-descriptor chain: class/command header, `data`,
+/// and a device-writable ack byte.  The control queue has no MSI-X vector
+/// and is only used during probe, so polling is fine.
+fn virtnet_ctrl_command(state: &mut VirtioNetState, class: u8, cmd: u8, data: &[u8]) -> bool {
+    const DATA_OFFSET: usize = 64;
+    const ACK_OFFSET: usize = 128;
+
+    if !state.device.ctrl_queue.is_ready() || data.len() > ACK_OFFSET - DATA_OFFSET {
+        return false;
+    }
+    let Some(page) = OwnedPageFrame::alloc_zeroed() else {
+        return false;
+    };
+    let base = page.as_mut_ptr::<u8>();
+    unsafe {
+        *base = class;
+        *base.add(1) = cmd;
+        core::ptr::copy_nonoverlapping(data.as_ptr(), base.add(DATA_OFFSET), data.len());
+        *base.add(ACK_OFFSET) = !VIRTIO_NET_OK;
+    }
+
+    let phys = page.phys_u64();
+    let ctrl = &mut state.device.ctrl_queue;
+    ctrl.write_desc(
+        0,
+        VirtqDesc {
+            addr: phys,
+            len: 2,
+            flags: VIRTQ_DESC_F_NEXT,
+            next: 1,
+        },
+    );
+    ctrl.write_desc(
+        1,
+        VirtqDesc {
+            addr: phys + DATA_OFFSET as u64,
+            len: data.len() as u32,
+            flags: VIRTQ_DESC_F_NEXT,
+            next: 2,
+        },
+    );
+    ctrl.write_desc(
+        2,
+        VirtqDesc {
+            addr: phys + ACK_OFFSET as u64,
+            len: 1,
+            flags: VIRTQ_DESC_F_WRITE,
+            next: 0,
+        },
+    );
+    ctrl.submit(0);
+    queue::notify_queue(
+        &state.caps.notify_cfg,
+        state.caps.notify_off_multiplier,
+        &state.device.ctrl_queue,
+        state.device.ctrl_queue_index,
+    );
+
+    let deadline = slopos_lib::clock::uptime_ms() + CTRL_TIMEOUT_MS;
+    loop {
+        if state.device.ctrl_queue.try_pop_used().is_some() {
+            return unsafe { core::ptr::read_volatile(base.add(ACK_OFFSET)) } == VIRTIO_NET_OK;
+        }
+        if slopos_lib::clock::uptime_ms() >= deadline {
+            // Intentional leak: device may still be DMA-ing.
+            let _ = page.into_phys();
+            return false;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+/// Switch the device to `pairs` RX/TX queue pairs and point each extra
+/// pair's MSI-X vectors at the CPU that transmits on it.
+fn virtnet_enable_queue_pairs(state: &mut VirtioNetState, pairs: usize) {
+    if pairs <= 1 {
+        return;
+    }
+    let data = (pairs as u16).to_le_bytes();
+    if !virtnet_ctrl_command(
+        state,
+        VIRTIO_NET_CTRL_MQ,
+        VIRTIO_NET_CTRL_MQ_VQ_PAIRS_SET,
+        &data,
+    ) {
+        klog_info!("virtio-net: device rejected {} queue pairs, using 1", pairs);
+        return;
+    }
+    state.device.num_pairs = pairs;
+
+    if let Some(msix) = state.msix_state.as_ref() {
+        for pair in 1..pairs {
+            let Some(cpu) = pcr::get_pcr(pair) else {
+                continue;
+            };
+            let apic_id = cpu.apic_id as u8;
+            msix.retarget_queue(rx_queue_index(pair), apic_id);
+            msix.retarget_queue(tx_queue_index(pair), apic_id);
+        }
+    }
+    klog_info!("virtio-net: {} queue pairs enabled", pairs);
+}
+
 // =============================================================================
 // ARP
 // =============================================================================
 
 fn transmit_arp_request(state: &mut VirtioNetState, target_ip: [u8; 4]) -> bool {
-    if !state.device.ready || !state.device.tx_queue.is_ready() {
+    if !state.device.ready || !state.pairs[0].tx_queue.is_ready() {
         return false;
     }
 
@@ -556,38 +689,91 @@ fn transmit_arp_request(state: &mut VirtioNetState, target_ip: [u8; 4]) -> bool
 // Receive path
 // =============================================================================
 
+/// Post a fresh receive page at descriptor `idx`.
+fn post_rx_buffer(pair: &mut VirtioNetQueuePair, idx: usize) -> bool {
+    let Some(page) = OwnedPageFrame::alloc_zeroed() else {
+        return false;
+    };
+    pair.rx_queue.write_desc(
+        idx as u16,
+        VirtqDesc {
+            addr: page.phys_u64(),
+            len: PACKET_BUFFER_SIZE as u32,
+            flags: VIRTQ_DESC_F_WRITE,
+            next: 0,
+        },
+    );
+    pair.rx_buffers[idx] = Some(page);
+    pair.rx_queue.submit(idx as u16);
+    true
+}
+
+fn notify_rx(state: &VirtioNetState, pair: usize) {
+    queue::notify_queue(
+        &state.caps.notify_cfg,
+        state.caps.notify_off_multiplier,
+        &state.pairs[pair].rx_queue,
+        rx_queue_index(pair),
+    );
+}
+
 fn virtnet_prepost_rx_buffers(state: &mut VirtioNetState) {
-    let mut posted = 0usize;
-    let queue_size = (state.device.rx_queue.size as usize).min(RX_RING_SIZE);
-    for idx in 0..queue_size {
-        if state.rx_buffers[idx].is_some() {
-            continue;
+    for pair_idx in 0..state.device.num_pairs {
+        let pair = &mut state.pairs[pair_idx];
+        let mut posted = 0usize;
+        let queue_size = (pair.rx_queue.size as usize).min(RX_RING_SIZE);
+        for idx in 0..queue_size {
+            if pair.rx_buffers[idx].is_none() && post_rx_buffer(pair, idx) {
+                posted += 1;
+            }
+        }
+        if posted > 0 {
+            notify_rx(state, pair_idx);
         }
-        let Some(page) = OwnedPageFrame::alloc_zeroed() else {
-            continue;
-        };
-        state.device.rx_queue.write_desc(
-            idx as u16,
-            VirtqDesc {
-                addr: page.phys_u64(),
-                len: PACKET_BUFFER_SIZE as u32,
-                flags: VIRTQ_DESC_F_WRITE,
-                next: 0,
-            },
-        );
-        state.rx_buffers[idx] = Some(page);
-        state.device.rx_queue.submit(idx as u16);
-        posted += 1;
     }
+}
 
-    if posted > 0 {
-        queue::notify_queue(
-            &state.caps.notify_cfg,
-            state.caps.notify_off_multiplier,
-            &state.device.rx_queue,
-            VIRTIO_NET_QUEUE_RX,
-        );
+/// Pop up to `budget` received frames across all RX queues, hand each to
+/// `on_frame` and repost its descriptor.  Returns the number of frames seen.
+fn virtnet_drain_rx(
+    state: &mut VirtioNetState,
+    budget: usize,
+    mut on_frame: impl FnMut(&mut VirtioNetState, &[u8]),
+) -> usize {
+    let hdr_len = size_of::<VirtioNetHdrV1>();
+    let mut processed = 0usize;
+
+    for pair_idx in 0..state.device.num_pairs {
+        let mut posted = 0usize;
+        while processed < budget {
+            let Some(used) = state.pairs[pair_idx].rx_queue.try_pop_used() else {
+                break;
+            };
+
+            let idx = (used.id as usize) % RX_RING_SIZE;
+            let Some(page) = state.pairs[pair_idx].rx_buffers[idx].take() else {
+                continue;
+            };
+
+            if (used.len as usize) > hdr_len {
+                let payload_len = (used.len as usize) - hdr_len;
+                let frame = unsafe {
+                    core::slice::from_raw_parts(page.as_mut_ptr::<u8>().add(hdr_len), payload_len)
+                };
+                on_frame(state, frame);
+            }
+            processed += 1;
+
+            if post_rx_buffer(&mut state.pairs[pair_idx], idx) {
+                posted += 1;
+            }
+        }
+        if posted > 0 {
+            notify_rx(state, pair_idx);
+        }
     }
+
+    processed
 }
 
 fn dispatch_rx_frame(state: &mut VirtioNetState, frame: &[u8]) {
@@ -637,12 +823,7 @@ fn dispatch_rx_frame(state: &mut VirtioNetState, frame: &[u8]) {
             if let Some((src_port, dst_port, udp_payload)) = net::parse_udp_header(ip_payload) {
                 // Intercept DNS responses (src port 53) for the in-kernel resolver
                 if src_port == net::dns::DNS_PORT {
-                    let copy_len = udp_payload.len().min(512);
-                    let mut dns_buf = DNS_RX_BUF.lock();
-                    dns_buf.data[..copy_len].copy_from_slice(&udp_payload[..copy_len]);
-                    dns_buf.len = copy_len;
-                    drop(dns_buf);
-                    DNS_RX_EVENT.signal();
+                    net::dns::dns_handle_response(src_ip, udp_payload);
                 }
                 // Always deliver to socket table too (userland might have a UDP
                 // socket bound to port 53 for its own purposes).
@@ -663,57 +844,8 @@ fn dispatch_rx_frame(state: &mut VirtioNetState, frame: &[u8]) {
 }
 
 fn virtnet_poll(state: &mut VirtioNetState, budget: u32) -> usize {
-    let mut processed = 0usize;
-    let mut posted = 0usize;
     let _ = virtnet_clean_tx(state);
-
-    while (processed as u32) < budget {
-        let Some(used) = state.device.rx_queue.try_pop_used() else {
-            break;
-        };
-
-        let idx = (used.id as usize) % RX_RING_SIZE;
-        let Some(page) = state.rx_buffers[idx].take() else {
-            continue;
-        };
-
-        let hdr_len = size_of::<VirtioNetHdrV1>();
-        if (used.len as usize) > hdr_len {
-            let payload_len = (used.len as usize) - hdr_len;
-            let frame = unsafe {
-                core::slice::from_raw_parts(page.as_mut_ptr::<u8>().add(hdr_len), payload_len)
-            };
-            dispatch_rx_frame(state, frame);
-        }
-
-        processed += 1;
-
-        if let Some(new_page) = OwnedPageFrame::alloc_zeroed() {
-            state.device.rx_queue.write_desc(
-                idx as u16,
-                VirtqDesc {
-                    addr: new_page.phys_u64(),
-                    len: PACKET_BUFFER_SIZE as u32,
-                    flags: VIRTQ_DESC_F_WRITE,
-                    next: 0,
-                },
-            );
-            state.rx_buffers[idx] = Some(new_page);
-            state.device.rx_queue.submit(idx as u16);
-            posted += 1;
-        }
-    }
-
-    if posted > 0 {
-        queue::notify_queue(
-            &state.caps.notify_cfg,
-            state.caps.notify_off_multiplier,
-            &state.device.rx_queue,
-            VIRTIO_NET_QUEUE_RX,
-        );
-    }
-
-    processed
+    virtnet_drain_rx(state, budget as usize, dispatch_rx_frame)
 }
 
 fn poll_one_rx_frame(state: &mut VirtioNetState, out_payload: Option<&mut [u8]>) -> Option<usize> {
@@ -729,7 +861,8 @@ fn poll_one_rx_frame_timeout(
     let rx_virt = rx_page.as_mut_ptr::<u8>();
     let rx_phys = rx_page.phys_u64();
 
-    state.device.rx_queue.write_desc(
+    let rx_queue = &mut state.pairs[0].rx_queue;
+    rx_queue.write_desc(
         0,
         VirtqDesc {
             addr: rx_phys,
@@ -739,20 +872,15 @@ fn poll_one_rx_frame_timeout(
         },
     );
 
-    state.device.rx_queue.submit(0);
-    queue::notify_queue(
-        &state.caps.notify_cfg,
-        state.caps.notify_off_multiplier,
-        &state.device.rx_queue,
-        VIRTIO_NET_QUEUE_RX,
-    );
+    rx_queue.submit(0);
+    notify_rx(state, 0);
 
     if !DHCP_RX_EVENT.wait_timeout_ms(timeout_ms) {
         // Intentional leak: device may still be DMA-ing.
         let _ = rx_page.into_phys();
         return None;
     }
-    let used = state.device.rx_queue.try_pop_used()?;
+    let used = state.pairs[0].rx_queue.try_pop_used()?;
 
     let hdr_len = size_of::<VirtioNetHdrV1>();
     if (used.len as usize) <= hdr_len {
@@ -780,7 +908,7 @@ fn transmit_udp_packet_locked(
     dst_port: u16,
     payload: &[u8],
 ) -> bool {
-    if !state.device.ready || !state.device.tx_queue.is_ready() || !link_is_up(state) {
+    if !state.device.ready || !state.pairs[0].tx_queue.is_ready() || !link_is_up(state) {
         return false;
     }
 
@@ -914,15 +1042,6 @@ fn wait_for_dhcp_reply(
     None
 }
 
-/// Use the preferred value unless it's zeroed, in which case fall back.
-fn or_fallback(preferred: [u8; 4], fallback: [u8; 4]) -> [u8; 4] {
-    if preferred != [0; 4] {
-        preferred
-    } else {
-        fallback
-    }
-}
-
 fn dhcp_acquire_lease(state: &mut VirtioNetState) -> Option<dhcp::DhcpLease> {
     let xid = DHCP_XID_COUNTER
         .fetch_add(1, Ordering::Relaxed)
@@ -942,12 +1061,8 @@ fn dhcp_acquire_lease(state: &mut VirtioNetState) -> Option<dhcp::DhcpLease> {
 
     let ack = wait_for_dhcp_reply(state, xid, dhcp::MSG_ACK)?;
 
-    let lease = dhcp::DhcpLease {
-        ipv4: ack.yiaddr,
-        subnet_mask: or_fallback(ack.subnet_mask, offer.subnet_mask),
-        router: or_fallback(ack.router, offer.router),
-        dns: or_fallback(ack.dns, offer.dns),
-    };
+    let offered = dhcp::DhcpLease::from_ack(&offer, None);
+    let lease = dhcp::DhcpLease::from_ack(&ack, Some(&offered));
     if lease.is_valid() { Some(lease) } else { None }
 }
 
@@ -990,10 +1105,9 @@ fn virtnet_napi_poll_loop() {
     }
     NAPI_CONTEXT.add_processed(processed as u32);
 
-    // Phase 3C: also poll the loopback device.  Packets sent to 127.0.0.0/8
-    // are queued internally by LoopbackDev::tx() and need to be drained back
-    // through the ingress pipeline.
-    poll_loopback();
+    // Phase 3C: also drain the loopback device.  Local traffic is queued by
+    // LoopbackDev::tx() and needs to be fed back through the ingress pipeline.
+    loopback::loopback_flush();
 
     napi_complete();
 
@@ -1018,75 +1132,37 @@ fn virtnet_napi_poll_loop() {
     }
 }
 
-/// Phase 3C: Poll the loopback device and feed packets through ingress.
-///
-/// Called from the NAPI loop and idle wakeup.  The loopback device (DevIndex 0)
-/// stores TX'd packets internally; this function drains them back through
-/// `net_rx()` so they appear as received local traffic.
-fn poll_loopback() {
-    use crate::net::netdev::DEVICE_REGISTRY;
-    use crate::net::types::DevIndex;
-
-    // The loopback device is at DevIndex(0).  Use the registry to poll it.
-    let lo_packets = DEVICE_REGISTRY.poll_rx_by_index(DevIndex(0), 32, &PACKET_POOL);
-
-    for pkt in lo_packets {
-        // Loopback packets bypass MAC filtering — they go straight
-        // to IPv4/ARP dispatch.  We call ipv4::handle_rx directly.
-        let checksum_rx = true; // Loopback doesn't need checksum verification.
-        let data = pkt.payload();
-        if data.len() >= crate::net::ETH_HEADER_LEN {
-            let ethertype_raw = u16::from_be_bytes([data[12], data[13]]);
-            let mut pkt = pkt;
-            // Set layer offsets.
-            pkt.set_l2(pkt.head());
-            pkt.set_l3(pkt.head() + crate::net::ETH_HEADER_LEN as u16);
-            // Pull Ethernet header.
-            if pkt.pull_header(crate::net::ETH_HEADER_LEN).is_ok() {
-                match crate::net::EtherType::from_u16(ethertype_raw) {
-                    Some(crate::net::EtherType::Ipv4) => {
-                        crate::net::ipv4::handle_rx(DevIndex(0), pkt, checksum_rx);
-                    }
-                    _ => {
-                        // Loopback only handles IPv4 for now.
-                    }
-                }
-            }
-        }
-    }
-}
-
 fn virtnet_idle_wakeup_cb() -> c_int {
     // Process network timers even when idle (ARP expiry, keepalives, etc.).
     crate::net::timer::net_timer_process();
 
+    // Timers may have queued local retransmits.
+    let looped = loopback::loopback_flush();
     if NAPI_EVENT.try_consume() || NAPI_CONTEXT.is_scheduled() {
         virtnet_napi_poll_loop();
         return 1;
     }
-    0
+    (looped != 0) as c_int
 }
 
 /// MSI-X / MSI interrupt handler for virtio-net.
 ///
-/// The `ctx` pointer encodes the queue index (0 = RX, 1 = TX).
-/// The handler signals the matching queue completion event.
+/// The `ctx` pointer encodes the queue index: even queues are RX, odd are
+/// TX.  The handler signals the matching queue completion event.
 extern "C" fn virtio_net_irq_handler(
     _vector: u8,
     _frame: *mut slopos_lib::InterruptFrame,
     ctx: *mut core::ffi::c_void,
 ) {
-    match ctx as usize {
-        0 => {
+    let queue_idx = ctx as usize;
+    if queue_idx.is_multiple_of(2) {
+        // The DHCP client waits on pair 0 only.
+        if queue_idx == 0 {
             DHCP_RX_EVENT.signal();
-            napi_schedule();
-            NAPI_EVENT.signal();
-        }
-        1 => {
-            NAPI_EVENT.signal();
         }
-        _ => {}
+        napi_schedule();
     }
+    NAPI_EVENT.signal();
 }
 
 fn virtio_net_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void) -> c_int {
@@ -1132,7 +1208,9 @@ fn virtio_net_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void
         | VIRTIO_NET_F_GUEST_CSUM
         | VIRTIO_NET_F_MAC
         | VIRTIO_NET_F_STATUS
-        | VIRTIO_NET_F_MTU;
+        | VIRTIO_NET_F_MTU
+        | VIRTIO_NET_F_CTRL_VQ
+        | VIRTIO_NET_F_MQ;
     let feat_result = negotiate_features(&caps, required_features, optional_features);
     if !feat_result.success {
         klog_info!("virtio-net: features negotiation failed");
@@ -1140,43 +1218,85 @@ fn virtio_net_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void
         return -1;
     }
 
-    // --- MSI-X / MSI interrupt setup ---
-    // Request 2 vectors: one for RX (queue 0), one for TX (queue 1).
-    let (irq_mode, msix_state) = setup_interrupts(info, &caps, 2).unwrap_or_else(|msg| {
-        panic!(
-            "virtio-net: {}:{}.{} {}",
-            info.bus, info.device, info.function, msg
-        )
-    });
-    let rx_msix_entry = msix_state.as_ref().map_or(VIRTIO_MSI_NO_VECTOR, |s| {
-        s.queue_msix_entry(VIRTIO_NET_QUEUE_RX)
-    });
-    let tx_msix_entry = msix_state.as_ref().map_or(VIRTIO_MSI_NO_VECTOR, |s| {
-        s.queue_msix_entry(VIRTIO_NET_QUEUE_TX)
-    });
-
-    let Some(rx_queue) = queue::setup_queue(
-        &caps.common_cfg,
-        VIRTIO_NET_QUEUE_RX,
-        DEFAULT_QUEUE_SIZE,
-        rx_msix_entry,
-    ) else {
-        klog_info!("virtio-net: rx queue setup failed");
-        DEVICE_CLAIMED.reset();
-        return -1;
+    let negotiated_features = feat_result.driver_features;
+
+    // One queue pair per CPU, capped by what the device offers and by the
+    // MSI-X vectors we can hand out.  Extra pairs need the control queue to
+    // switch on, so without it we stay on pair 0.
+    let max_pairs = read_max_pairs(&caps, negotiated_features);
+    let mut num_pairs = if (negotiated_features & VIRTIO_NET_F_CTRL_VQ) != 0 {
+        (max_pairs as usize)
+            .min(pcr::get_cpu_count().max(1))
+            .min(MAX_QUEUE_PAIRS)
+    } else {
+        1
     };
 
-    let Some(tx_queue) = queue::setup_queue(
-        &caps.common_cfg,
-        VIRTIO_NET_QUEUE_TX,
-        DEFAULT_QUEUE_SIZE,
-        tx_msix_entry,
-    ) else {
-        klog_info!("virtio-net: tx queue setup failed");
-        DEVICE_CLAIMED.reset();
-        return -1;
+    // --- MSI-X / MSI interrupt setup ---
+    // Request one vector per queue: RX on even indices, TX on odd.
+    let (irq_mode, msix_state) = setup_interrupts(info, &caps, (num_pairs * 2) as u8)
+        .unwrap_or_else(|msg| {
+            panic!(
+                "virtio-net: {}:{}.{} {}",
+                info.bus, info.device, info.function, msg
+            )
+        });
+    let msix_entry = |queue_idx: u16| {
+        msix_state
+            .as_ref()
+            .map_or(VIRTIO_MSI_NO_VECTOR, |s| s.queue_msix_entry(queue_idx))
     };
 
+    let mut rx_queues = [Virtqueue::new(); MAX_QUEUE_PAIRS];
+    let mut tx_queues = [Virtqueue::new(); MAX_QUEUE_PAIRS];
+    for pair in 0..num_pairs {
+        let rx = queue::setup_queue(
+            &caps.common_cfg,
+            rx_queue_index(pair),
+            DEFAULT_QUEUE_SIZE,
+            msix_entry(rx_queue_index(pair)),
+        );
+        let tx = queue::setup_queue(
+            &caps.common_cfg,
+            tx_queue_index(pair),
+            DEFAULT_QUEUE_SIZE,
+            msix_entry(tx_queue_index(pair)),
+        );
+        match (rx, tx) {
+            (Some(rx), Some(tx)) => {
+                rx_queues[pair] = rx;
+                tx_queues[pair] = tx;
+            }
+            _ if pair == 0 => {
+                klog_info!("virtio-net: queue pair 0 setup failed");
+                DEVICE_CLAIMED.reset();
+                return -1;
+            }
+            _ => {
+                klog_info!("virtio-net: queue pair {} setup failed", pair);
+                num_pairs = pair;
+                break;
+            }
+        }
+    }
+
+    let ctrl_queue_index = max_pairs * 2;
+    let mut ctrl_queue = Virtqueue::new();
+    if num_pairs > 1 {
+        match queue::setup_queue(
+            &caps.common_cfg,
+            ctrl_queue_index,
+            DEFAULT_QUEUE_SIZE,
+            VIRTIO_MSI_NO_VECTOR,
+        ) {
+            Some(q) => ctrl_queue = q,
+            None => {
+                klog_info!("virtio-net: control queue setup failed");
+                num_pairs = 1;
+            }
+        }
+    }
+
     // Register MSI-X/MSI handlers that signal queue completion events.
     let device_bdf =
         ((info.bus as u32) << 16) | ((info.device as u32) << 8) | (info.function as u32);
@@ -1187,22 +1307,28 @@ fn virtio_net_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void
         device_bdf,
     );
 
-    let negotiated_features = feat_result.driver_features;
     let mac = read_mac(&caps, negotiated_features);
     let mtu = read_mtu(&caps, negotiated_features);
 
     set_driver_ok(&caps);
 
+    // The lease the boot exchange got, for the DHCP client to maintain.
+    let mut boot_lease = None;
     {
         let mut state = VIRTIO_NET_STATE.lock();
         state.device = VirtioNetDevice {
-            rx_queue,
-            tx_queue,
+            ctrl_queue,
+            ctrl_queue_index,
+            num_pairs: 1,
             negotiated_features,
             mac,
             mtu,
             ready: true,
         };
+        for pair in 0..num_pairs {
+            state.pairs[pair].rx_queue = rx_queues[pair];
+            state.pairs[pair].tx_queue = tx_queues[pair];
+        }
         state.caps = caps;
         state.msix_state = msix_state;
         state.ipv4_addr = [0; 4];
@@ -1210,11 +1336,20 @@ fn virtio_net_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void
         state.router = [0; 4];
         state.dns = [0; 4];
 
-        if let Some(lease) = dhcp_acquire_lease(&mut state) {
-            state.ipv4_addr = lease.ipv4;
-            state.subnet_mask = lease.subnet_mask;
-            state.router = lease.router;
-            state.dns = lease.dns;
+        state.config_source = NET_CONFIG_NONE;
+
+        // A static config from the cmdline replaces DHCP entirely.
+        let config = if let Some(cfg) = NET_STACK.static_config() {
+            klog_info!(
+                "virtio-net: static config ip={} netmask={} gw={} dns={}",
+                cfg.addr,
+                cfg.netmask,
+                cfg.gateway,
+                cfg.dns[0]
+            );
+            state.config_source = NET_CONFIG_STATIC;
+            Some((cfg.addr, cfg.netmask, cfg.gateway, cfg.dns))
+        } else if let Some(lease) = dhcp_acquire_lease(&mut state) {
             klog_info!(
                 "virtio-net: DHCP lease ip={}.{}.{}.{} gw={}.{}.{}.{} dns={}.{}.{}.{}",
                 lease.ipv4[0],
@@ -1230,28 +1365,39 @@ fn virtio_net_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void
                 lease.dns[2],
                 lease.dns[3]
             );
+            state.config_source = NET_CONFIG_DHCP;
+            boot_lease = Some(lease);
+            Some((
+                Ipv4Addr::from_bytes(lease.ipv4),
+                Ipv4Addr::from_bytes(lease.subnet_mask),
+                Ipv4Addr::from_bytes(lease.router),
+                [
+                    Ipv4Addr::from_bytes(lease.dns),
+                    Ipv4Addr::from_bytes(lease.dns_secondary),
+                ],
+            ))
+        } else {
+            klog_info!("virtio-net: DHCP lease unavailable");
+            None
+        };
+
+        if let Some((addr, netmask, gateway, dns)) = config {
+            state.ipv4_addr = addr.0;
+            state.subnet_mask = netmask.0;
+            state.router = gateway.0;
+            state.dns = dns[0].0;
 
-            // Phase 3A: propagate DHCP lease to the centralised NetStack.
+            // Phase 3A: propagate the address to the centralised NetStack.
             // The DeviceHandle hasn't been created yet, but we know VirtIO-net
             // will get the next available slot.  We read the current count
             // from the registry to predict the DevIndex.  (After Phase 3C adds
             // loopback at index 0, VirtIO-net will be index 1.)
-            {
-                use crate::net::netstack::NET_STACK;
-                use crate::net::types::{DevIndex, Ipv4Addr};
-                let dev_idx = DevIndex(DEVICE_REGISTRY.device_count());
-                NET_STACK.configure(
-                    dev_idx,
-                    Ipv4Addr::from_bytes(lease.ipv4),
-                    Ipv4Addr::from_bytes(lease.subnet_mask),
-                    Ipv4Addr::from_bytes(lease.router),
-                    [Ipv4Addr::from_bytes(lease.dns), Ipv4Addr::UNSPECIFIED],
-                );
-            }
-        } else {
-            klog_info!("virtio-net: DHCP lease unavailable");
+            let dev_idx = DevIndex(DEVICE_REGISTRY.device_count());
+            NET_STACK.configure(dev_idx, addr, netmask, gateway, dns);
         }
 
+        // DHCP ran on pair 0 alone; only now spread traffic across the rest.
+        virtnet_enable_queue_pairs(&mut state, num_pairs);
         virtnet_prepost_rx_buffers(&mut state);
 
         PACKET_POOL.init();
@@ -1269,6 +1415,22 @@ fn virtio_net_probe(info: *const PciDeviceInfo, _context: *mut core::ffi::c_void
     }
 
     register_idle_wakeup_callback(Some(virtnet_idle_wakeup_cb));
+    crate::net::timer::net_timer_init();
+
+    let link_up = virtio_net_link_up();
+    LINK_WAS_UP.store(link_up, Ordering::Release);
+    NET_TIMER_WHEEL.schedule(LINK_WATCH_TICKS, TimerKind::LinkWatch, 0);
+    if NET_STACK.static_config().is_none()
+        && let Some(handle) = get_device_handle()
+    {
+        dhcp::dhcp_client_start(
+            handle.index(),
+            mac,
+            DHCP_XID_COUNTER.load(Ordering::Relaxed),
+            link_up,
+            boot_lease,
+        );
+    }
 
     klog_info!(
         "virtio-net: ready mtu={} mac={:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x} irq {:?}",
@@ -1310,6 +1472,39 @@ pub fn virtio_net_link_up() -> bool {
     link_is_up(&state)
 }
 
+/// Dispatch for [`TimerKind::LinkWatch`]: report a link transition to the
+/// DHCP client, then poll again.
+pub fn virtio_net_link_watch() {
+    let up = virtio_net_link_up();
+    if LINK_WAS_UP.swap(up, Ordering::AcqRel) != up {
+        klog_info!("virtio-net: link {}", if up { "up" } else { "down" });
+        dhcp::dhcp_link_changed(up);
+    }
+    NET_TIMER_WHEEL.schedule(LINK_WATCH_TICKS, TimerKind::LinkWatch, 0);
+}
+
+/// Mirror a DHCP lease change into the driver's address fields; `None`
+/// once the lease is gone.
+pub fn virtio_net_set_dhcp_config(lease: Option<&dhcp::DhcpLease>) {
+    let mut state = VIRTIO_NET_STATE.lock();
+    match lease {
+        Some(lease) => {
+            state.ipv4_addr = lease.ipv4;
+            state.subnet_mask = lease.subnet_mask;
+            state.router = lease.router;
+            state.dns = lease.dns;
+            state.config_source = NET_CONFIG_DHCP;
+        }
+        None => {
+            state.ipv4_addr = [0; 4];
+            state.subnet_mask = [0; 4];
+            state.router = [0; 4];
+            state.dns = [0; 4];
+            state.config_source = NET_CONFIG_NONE;
+        }
+    }
+}
+
 pub fn virtio_net_scan_members(out: *mut UserNetMember, max: usize, active_probe: bool) -> usize {
     if out.is_null() || max == 0 {
         return 0;
@@ -1372,7 +1567,7 @@ pub fn virtio_net_queue_sizes() -> Option<(u16, u16)> {
     if !state.device.ready {
         return None;
     }
-    Some((state.device.rx_queue.size, state.device.tx_queue.size))
+    Some((state.pairs[0].rx_queue.size, state.pairs[0].tx_queue.size))
 }
 
 pub fn virtio_net_mac() -> Option<[u8; 6]> {
@@ -1405,6 +1600,7 @@ pub fn virtio_net_get_info(out: &mut UserNetInfo) {
     out.link_up = u8::from(state.device.ready && link_is_up(&state));
     out.mac = state.device.mac;
     out.mtu = state.device.mtu;
+    out.config_source = state.config_source;
 
     // Phase 3A: prefer NetStack as the source of truth for IP config.
     if let Some(iface) = crate::net::netstack::NET_STACK.first_iface() {
@@ -1422,6 +1618,17 @@ pub fn virtio_net_get_info(out: &mut UserNetInfo) {
 }
 
 pub fn virtio_net_transmit(packet: &[u8]) -> bool {
+    transmit_frame(packet, None)
+}
+
+/// Transmit a frame whose L4 checksum field holds only the pseudo-header
+/// sum; the device completes it.  Fails unless
+/// [`virtio_net_checksum_offload`] is true.
+pub fn virtio_net_transmit_partial(packet: &[u8], csum_start: u16, csum_offset: u16) -> bool {
+    transmit_frame(packet, Some((csum_start, csum_offset)))
+}
+
+fn transmit_frame(packet: &[u8], partial_csum: Option<(u16, u16)>) -> bool {
     if packet.is_empty() {
         return true;
     }
@@ -1430,6 +1637,9 @@ pub fn virtio_net_transmit(packet: &[u8]) -> bool {
     if !state.device.ready || !link_is_up(&state) {
         return false;
     }
+    if partial_csum.is_some() && (state.device.negotiated_features & VIRTIO_NET_F_CSUM) == 0 {
+        return false;
+    }
 
     let hdr_len = size_of::<VirtioNetHdrV1>();
     if packet.len() + hdr_len > PACKET_BUFFER_SIZE {
@@ -1447,10 +1657,24 @@ pub fn virtio_net_transmit(packet: &[u8]) -> bool {
             packet.len(),
         );
     }
+    if let Some((csum_start, csum_offset)) = partial_csum {
+        mark_needs_csum(&tx_page, csum_start, csum_offset);
+    }
 
     submit_tx(&mut state, tx_page, (hdr_len + packet.len()) as u32)
 }
 
+/// Whether the device finishes TX checksums (`VIRTIO_NET_F_CSUM`).
+pub fn virtio_net_checksum_offload() -> bool {
+    let state = VIRTIO_NET_STATE.lock();
+    state.device.ready && (state.device.negotiated_features & VIRTIO_NET_F_CSUM) != 0
+}
+
+/// Number of RX/TX queue pairs currently in use.
+pub fn virtio_net_queue_pairs() -> usize {
+    VIRTIO_NET_STATE.lock().device.num_pairs
+}
+
 pub fn virtio_net_receive(buffer: &mut [u8]) -> Option<usize> {
     if buffer.is_empty() {
         return Some(0);
@@ -1477,43 +1701,14 @@ pub fn virtio_net_dns() -> Option<[u8; 4]> {
     Some(state.dns)
 }
 
-/// Clear any stale DNS response buffer.
-pub fn dns_rx_clear() {
-    DNS_RX_EVENT.try_consume();
-    let mut buf = DNS_RX_BUF.lock();
-    buf.len = 0;
-}
-
-/// Wait for a DNS response with timeout. Returns `true` if signaled.
+/// Wait up to `timeout_ms` for a receive interrupt, then run NAPI inline.
 ///
-/// The IRQ handler signals `NAPI_EVENT` (not `DNS_RX_EVENT`), so we must
-/// poll NAPI inline after each wakeup to process RX frames; NAPI's
-/// `dispatch_rx_frame` intercepts DNS replies and signals `DNS_RX_EVENT`.
-pub fn dns_rx_wait(timeout_ms: u32) -> bool {
-    let start = slopos_lib::clock::uptime_ms();
-    loop {
-        // Already arrived?
-        if DNS_RX_EVENT.try_consume() {
-            return true;
-        }
-        let elapsed = slopos_lib::clock::uptime_ms() - start;
-        if elapsed >= timeout_ms as u64 {
-            return false;
-        }
-        let remaining = (timeout_ms as u64 - elapsed) as u32;
-        // Wait for any RX interrupt (capped at 100 ms to avoid wedging).
-        NAPI_EVENT.wait_timeout_ms(remaining.min(100));
-        virtnet_napi_poll_loop();
-    }
-}
-
-/// Read the most recent DNS response into the provided buffer.
-/// Returns the number of bytes copied.
-pub fn dns_rx_read(out: &mut [u8]) -> usize {
-    let buf = DNS_RX_BUF.lock();
-    let copy_len = buf.len.min(out.len());
-    out[..copy_len].copy_from_slice(&buf.data[..copy_len]);
-    copy_len
+/// For kernel clients that wait synchronously for a reply outside any
+/// socket (the DNS resolver, ICMP echo): the IRQ only signals `NAPI_EVENT`,
+/// so the waiter does the RX processing itself.
+pub fn net_rx_wait(timeout_ms: u32) {
+    NAPI_EVENT.wait_timeout_ms(timeout_ms);
+    virtnet_napi_poll_loop();
 }
 
 // =============================================================================
@@ -0,0 +1,384 @@
+//! Blitter copy engine: one ring and execlist context on BCS0, fenced by a
+//! post-sync write from `MI_FLUSH_DW` that the CPU polls.
+//!
+//! Every submission is waited on, so the ring is always idle between
+//! calls and never needs more than one submission's worth of space.
+
+use core::ptr;
+
+use slopos_abi::PhysAddr;
+use slopos_abi::damage::DamageRect;
+use slopos_lib::klog_warn;
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::mmio::MmioRegion;
+use slopos_mm::page_alloc::{ALLOC_FLAG_ZERO, alloc_page_frames, free_page_frame};
+use slopos_mm::paging_defs::PAGE_SIZE_4KB;
+
+use crate::hpet;
+
+use super::ggtt::{self, XeGgtt};
+use super::regs;
+
+const RING_BYTES: u32 = PAGE_SIZE_4KB as u32;
+const RING_DWORDS: u32 = RING_BYTES / 4;
+/// `MI_FLUSH_DW` with its post-sync write.
+const FENCE_DWORDS: u32 = 4;
+const FENCE_TIMEOUT_NS: u64 = 50_000_000;
+const CONTEXT_ID: u32 = 1;
+
+/// Register state page dword holding the ring tail the context restores.
+const CTX_RING_TAIL: usize = 7;
+const CTX_CONTEXT_CONTROL: usize = 3;
+
+/// Scratch in the fence page's upper half, for the bring-up self-test.
+const SELFTEST_SRC: u64 = 2048;
+const SELFTEST_DST: u64 = 3072;
+const SELFTEST_SIZE: u32 = 16;
+
+/// Pages mapped for both the CPU (through the HHDM) and the GPU.
+#[derive(Copy, Clone)]
+struct GpuPages {
+    virt: *mut u8,
+    ggtt_addr: u64,
+}
+
+impl GpuPages {
+    const fn empty() -> Self {
+        Self {
+            virt: ptr::null_mut(),
+            ggtt_addr: 0,
+        }
+    }
+
+    fn alloc(ggtt: &mut XeGgtt, pages: u32) -> Option<Self> {
+        let phys = alloc_page_frames(pages, ALLOC_FLAG_ZERO);
+        if phys.is_null() {
+            return None;
+        }
+        let Some(virt) = phys.to_virt_checked() else {
+            let _ = free_page_frame(phys);
+            return None;
+        };
+        let mapped = ggtt::xe_ggtt_alloc(ggtt, pages, 1)
+            .filter(|&entry| ggtt::xe_ggtt_map(ggtt, entry, phys, pages));
+        let Some(entry) = mapped else {
+            let _ = free_page_frame(phys);
+            return None;
+        };
+        Some(Self {
+            virt: virt.as_mut_ptr::<u8>(),
+            ggtt_addr: entry as u64 * PAGE_SIZE_4KB,
+        })
+    }
+
+    fn dwords(&self) -> *mut u32 {
+        self.virt.cast::<u32>()
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct XeBlitter {
+    pub ready: bool,
+    ring: GpuPages,
+    /// Per-process status page, then the register state page.
+    context: GpuPages,
+    /// Dword 0 holds the last completed sequence number.
+    fence: GpuPages,
+    /// Engine hardware status page, where the CSB and its write pointer land.
+    hws: GpuPages,
+    tail: u32,
+    seqno: u32,
+    submitted: bool,
+    /// GGTT window SHM sources are bound into, and what is bound there.
+    staging_entry: u32,
+    staging_pages: u32,
+    staging_phys: PhysAddr,
+}
+
+// Safety: Access to this state is synchronized through `XE_DEVICE` IrqMutex.
+unsafe impl Send for XeBlitter {}
+
+impl XeBlitter {
+    pub const fn empty() -> Self {
+        Self {
+            ready: false,
+            ring: GpuPages::empty(),
+            context: GpuPages::empty(),
+            fence: GpuPages::empty(),
+            hws: GpuPages::empty(),
+            tail: 0,
+            seqno: 0,
+            submitted: false,
+            staging_entry: 0,
+            staging_pages: 0,
+            staging_phys: PhysAddr::NULL,
+        }
+    }
+
+    /// Bind an SHM buffer of `pages` pages at `phys` into the staging
+    /// window, returning its GGTT address.
+    pub fn bind_source(
+        &mut self,
+        ggtt: &XeGgtt,
+        mmio: &MmioRegion,
+        phys: PhysAddr,
+        pages: u32,
+    ) -> Option<u64> {
+        if pages == 0 || pages > self.staging_pages {
+            return None;
+        }
+        if self.staging_phys != phys {
+            if !ggtt::xe_ggtt_map(ggtt, self.staging_entry, phys, pages) {
+                return None;
+            }
+            mmio.write::<u32>(regs::GFX_FLSH_CNTL, regs::GFX_FLSH_CNTL_EN);
+            self.staging_phys = phys;
+        }
+        Some(self.staging_entry as u64 * PAGE_SIZE_4KB)
+    }
+
+    /// Copy each valid rect in `rects`, clipped to `bounds`, from `src` to
+    /// `dst` (32bpp surfaces with `pitch`-byte rows) and wait for it.
+    pub fn copy(
+        &mut self,
+        mmio: &MmioRegion,
+        src: u64,
+        dst: u64,
+        pitch: u32,
+        bounds: (u32, u32),
+        rects: &[DamageRect],
+    ) -> bool {
+        if !self.ready {
+            return false;
+        }
+        let (width, height) = (bounds.0 as i32, bounds.1 as i32);
+        let clipped = rects.iter().filter_map(|rect| {
+            let clip = DamageRect {
+                x0: rect.x0.max(0),
+                y0: rect.y0.max(0),
+                x1: rect.x1.min(width - 1),
+                y1: rect.y1.min(height - 1),
+            };
+            (rect.is_valid() && clip.is_valid()).then_some(clip)
+        });
+        let count = clipped.clone().count() as u32;
+        if count * regs::XY_FAST_COPY_DWORDS + FENCE_DWORDS + 2 > RING_DWORDS {
+            return false;
+        }
+        for rect in clipped {
+            self.emit(&fast_copy(src, dst, pitch, &rect));
+        }
+        self.submit_and_wait(mmio)
+    }
+
+    fn emit(&mut self, dwords: &[u32]) {
+        let ring = self.ring.dwords();
+        if self.tail / 4 + dwords.len() as u32 > RING_DWORDS {
+            while self.tail < RING_BYTES {
+                // SAFETY: tail stays within the ring page.
+                unsafe {
+                    ring.add(self.tail as usize / 4)
+                        .write_volatile(regs::MI_NOOP)
+                };
+                self.tail += 4;
+            }
+            self.tail = 0;
+        }
+        for &dword in dwords {
+            // SAFETY: the wrap above leaves room for `dwords` in the ring page.
+            unsafe { ring.add(self.tail as usize / 4).write_volatile(dword) };
+            self.tail += 4;
+        }
+    }
+
+    /// Fence what has been emitted, hand the context to the engine and
+    /// poll for the fence.  A timeout takes the blitter out of service.
+    fn submit_and_wait(&mut self, mmio: &MmioRegion) -> bool {
+        self.seqno = self.seqno.wrapping_add(1);
+        self.emit(&[
+            regs::MI_FLUSH_DW | regs::MI_FLUSH_DW_OP_STOREDW | (FENCE_DWORDS - 2),
+            self.fence.ggtt_addr as u32 | regs::MI_FLUSH_DW_USE_GTT,
+            (self.fence.ggtt_addr >> 32) as u32,
+            self.seqno,
+        ]);
+        if !self.tail.is_multiple_of(8) {
+            self.emit(&[regs::MI_NOOP]);
+        }
+
+        let state = self.state_page();
+        // SAFETY: the register state page belongs to this context, which
+        // the engine is idle on between submissions.
+        unsafe {
+            state.add(CTX_RING_TAIL).write_volatile(self.tail);
+            if self.submitted {
+                state
+                    .add(CTX_CONTEXT_CONTROL)
+                    .write_volatile(regs::masked_disable(regs::CTX_CTRL_RESTORE_INHIBIT));
+            }
+        }
+        self.submitted = true;
+
+        let desc_lo = self.context.ggtt_addr as u32
+            | regs::CTX_DESC_VALID
+            | regs::CTX_DESC_LEGACY_32B
+            | regs::CTX_DESC_PRIVILEGE;
+        let engine = regs::BCS0_BASE;
+        mmio.write::<u32>(engine + regs::RING_EXECLIST_SQ_LO, desc_lo);
+        mmio.write::<u32>(
+            engine + regs::RING_EXECLIST_SQ_HI,
+            CONTEXT_ID << regs::CTX_DESC_ID_SHIFT,
+        );
+        mmio.write::<u32>(
+            engine + regs::RING_EXECLIST_CONTROL,
+            regs::EXECLIST_CONTROL_LOAD,
+        );
+
+        let deadline = hpet::read_counter().saturating_add(hpet::ticks_for_ns(FENCE_TIMEOUT_NS));
+        let fence = self.fence.dwords();
+        loop {
+            // SAFETY: the fence page is mapped for as long as the device is.
+            if unsafe { fence.read_volatile() } == self.seqno {
+                return true;
+            }
+            if hpet::read_counter() >= deadline {
+                klog_warn!("XE: Blitter fence {} timed out", self.seqno);
+                self.ready = false;
+                return false;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    fn state_page(&self) -> *mut u32 {
+        // SAFETY: the context is two pages; the second is register state.
+        unsafe { self.context.virt.add(PAGE_SIZE_4KB as usize).cast::<u32>() }
+    }
+
+    /// Copy a pattern between two scratch squares and check it arrived.
+    fn selftest(&mut self, mmio: &MmioRegion) -> bool {
+        let pitch = SELFTEST_SIZE * 4;
+        let words = (SELFTEST_SIZE * SELFTEST_SIZE) as usize;
+        let base = self.fence.dwords();
+        // SAFETY: both scratch squares lie within the fence page.
+        let (src, dst) = unsafe {
+            (
+                base.add(SELFTEST_SRC as usize / 4),
+                base.add(SELFTEST_DST as usize / 4),
+            )
+        };
+        for i in 0..words {
+            // SAFETY: `i` is within the scratch square.
+            unsafe {
+                src.add(i).write_volatile(0x5a00_0000 | i as u32);
+                dst.add(i).write_volatile(0);
+            }
+        }
+        let rect = DamageRect {
+            x0: 0,
+            y0: 0,
+            x1: SELFTEST_SIZE as i32 - 1,
+            y1: SELFTEST_SIZE as i32 - 1,
+        };
+        let copied = self.copy(
+            mmio,
+            self.fence.ggtt_addr + SELFTEST_SRC,
+            self.fence.ggtt_addr + SELFTEST_DST,
+            pitch,
+            (SELFTEST_SIZE, SELFTEST_SIZE),
+            &[rect],
+        );
+        // SAFETY: `i` is within the scratch square.
+        copied
+            && (0..words).all(|i| unsafe { dst.add(i).read_volatile() } == 0x5a00_0000 | i as u32)
+    }
+}
+
+/// Linear-to-linear `XY_FAST_COPY_BLT` of `rect` (inclusive corners).
+fn fast_copy(src: u64, dst: u64, pitch: u32, rect: &DamageRect) -> [u32; 10] {
+    let top_left = ((rect.y0 as u32) << 16) | rect.x0 as u32;
+    let bottom_right = (((rect.y1 + 1) as u32) << 16) | (rect.x1 + 1) as u32;
+    [
+        regs::XY_FAST_COPY_BLT | (regs::XY_FAST_COPY_DWORDS - 2),
+        regs::XY_FAST_COPY_32BPP | pitch,
+        top_left,
+        bottom_right,
+        dst as u32,
+        (dst >> 32) as u32,
+        top_left,
+        pitch,
+        src as u32,
+        (src >> 32) as u32,
+    ]
+}
+
+/// Bring up the blitter with a staging window of `staging_pages` GGTT
+/// pages for SHM sources.  Returns a blitter that is not ready if any step,
+/// including the self-test copy, fails.
+pub fn xe_blit_init(mmio: &MmioRegion, ggtt: &mut XeGgtt, staging_pages: u32) -> XeBlitter {
+    let mut blitter = XeBlitter::empty();
+    let (Some(ring), Some(context), Some(fence), Some(hws)) = (
+        GpuPages::alloc(ggtt, 1),
+        GpuPages::alloc(ggtt, 2),
+        GpuPages::alloc(ggtt, 1),
+        GpuPages::alloc(ggtt, 1),
+    ) else {
+        klog_warn!("XE: Blitter page allocation failed");
+        return blitter;
+    };
+    let Some(staging_entry) = ggtt::xe_ggtt_alloc(ggtt, staging_pages, 16) else {
+        klog_warn!("XE: Blitter staging window allocation failed");
+        return blitter;
+    };
+    blitter.ring = ring;
+    blitter.context = context;
+    blitter.fence = fence;
+    blitter.hws = hws;
+    blitter.staging_entry = staging_entry;
+    blitter.staging_pages = staging_pages;
+
+    // Just the ring registers; everything else keeps the engine's reset
+    // values, since the first submission inhibits the restore.
+    let engine = regs::BCS0_BASE as u32;
+    let state: [u32; 13] = [
+        regs::MI_NOOP,
+        regs::MI_LOAD_REGISTER_IMM | (2 * 5 - 1),
+        engine + regs::RING_CONTEXT_CONTROL as u32,
+        regs::masked_enable(regs::CTX_CTRL_RESTORE_INHIBIT),
+        engine + regs::RING_HEAD as u32,
+        0,
+        engine + regs::RING_TAIL as u32,
+        0,
+        engine + regs::RING_START as u32,
+        ring.ggtt_addr as u32,
+        engine + regs::RING_CTL as u32,
+        (RING_BYTES - PAGE_SIZE_4KB as u32) | regs::RING_CTL_VALID,
+        regs::MI_BATCH_BUFFER_END,
+    ];
+    let page = blitter.state_page();
+    for (i, &dword) in state.iter().enumerate() {
+        // SAFETY: the state is far smaller than the register state page.
+        unsafe { page.add(i).write_volatile(dword) };
+    }
+
+    let engine = regs::BCS0_BASE;
+    // The status page has to be in place before execlists are enabled, or
+    // the engine writes context-switch status to GGTT address 0.  Read it
+    // back so the write has landed before RING_MODE changes.
+    mmio.write::<u32>(engine + regs::RING_HWS_PGA, hws.ggtt_addr as u32);
+    let _ = mmio.read::<u32>(engine + regs::RING_HWS_PGA);
+    mmio.write::<u32>(
+        engine + regs::RING_MODE,
+        regs::masked_enable(regs::RING_MODE_DISABLE_LEGACY | regs::RING_MODE_RUN_LIST_ENABLE),
+    );
+    mmio.write::<u32>(
+        engine + regs::RING_MI_MODE,
+        regs::masked_disable(regs::MI_MODE_STOP_RING),
+    );
+
+    blitter.ready = true;
+    if !blitter.selftest(mmio) {
+        klog_warn!("XE: Blitter self-test failed; copying with the CPU");
+        blitter.ready = false;
+    }
+    blitter
+}
@@ -1,14 +1,38 @@
 use slopos_mm::mmio::MmioRegion;
 
-use super::regs;
+use super::regs::{self, pipe_reg};
+
+/// A pipe's primary plane, scanning out the `width` x `height` rectangle
+/// of the framebuffer at `x, y`.
+#[derive(Copy, Clone)]
+pub struct XePlane {
+    pub pipe: usize,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Size of the image pipe `pipe` is scanning out, if firmware left it
+/// running.
+pub fn xe_display_active_pipe_size(mmio: &MmioRegion, pipe: usize) -> Option<(u32, u32)> {
+    let conf = mmio.read::<u32>(pipe_reg(regs::TRANSCONF_A, pipe));
+    if conf == u32::MAX || conf & regs::TRANSCONF_ENABLE == 0 {
+        return None;
+    }
+    let src = mmio.read::<u32>(pipe_reg(regs::PIPESRC_A, pipe));
+    let width = (src >> 16) + 1;
+    let height = (src & 0xffff) + 1;
+    Some((width, height))
+}
 
 pub fn xe_display_program_primary(
     mmio: &MmioRegion,
+    plane: &XePlane,
     ggtt_addr: u64,
-    width: u32,
-    height: u32,
     pitch: u32,
 ) -> bool {
+    let (width, height) = (plane.width, plane.height);
     if width == 0 || height == 0 {
         return false;
     }
@@ -20,26 +44,79 @@ pub fn xe_display_program_primary(
         return false;
     }
 
+    let pipe = plane.pipe;
     let stride = pitch / regs::PLANE_STRIDE_ALIGN;
     let size = ((height - 1) << 16) | (width - 1);
     let addr = ggtt_addr as u32;
 
-    mmio.write::<u32>(regs::PLANE_POS_A, 0);
-    mmio.write::<u32>(regs::PLANE_SIZE_A, size);
-    mmio.write::<u32>(regs::PLANE_STRIDE_A, stride);
-    mmio.write::<u32>(regs::PLANE_OFFSET_A, 0);
-    mmio.write::<u32>(regs::PLANE_SURF_A, addr);
+    mmio.write::<u32>(pipe_reg(regs::PLANE_POS_A, pipe), 0);
+    mmio.write::<u32>(pipe_reg(regs::PLANE_SIZE_A, pipe), size);
+    mmio.write::<u32>(pipe_reg(regs::PLANE_STRIDE_A, pipe), stride);
+    mmio.write::<u32>(
+        pipe_reg(regs::PLANE_OFFSET_A, pipe),
+        (plane.y << 16) | plane.x,
+    );
+    mmio.write::<u32>(pipe_reg(regs::PLANE_SURF_A, pipe), addr);
 
     let ctl = regs::PLANE_CTL_ENABLE | regs::PLANE_CTL_FORMAT_XRGB_8888;
-    mmio.write::<u32>(regs::PLANE_CTL_A, ctl);
+    mmio.write::<u32>(pipe_reg(regs::PLANE_CTL_A, pipe), ctl);
 
-    let _ = mmio.read::<u32>(regs::PLANE_SURF_A);
+    let _ = mmio.read::<u32>(pipe_reg(regs::PLANE_SURF_A, pipe));
     true
 }
 
-pub fn xe_display_flush(mmio: &MmioRegion, ggtt_addr: u64) -> bool {
+/// Point `plane` at a new rectangle of the framebuffer.  The PLANE_SURF
+/// write arms the update.
+pub fn xe_display_set_offset(mmio: &MmioRegion, plane: &XePlane, ggtt_addr: u64) {
+    let pipe = plane.pipe;
+    mmio.write::<u32>(
+        pipe_reg(regs::PLANE_OFFSET_A, pipe),
+        (plane.y << 16) | plane.x,
+    );
+    mmio.write::<u32>(pipe_reg(regs::PLANE_SURF_A, pipe), ggtt_addr as u32);
+    let _ = mmio.read::<u32>(pipe_reg(regs::PLANE_SURF_A, pipe));
+}
+
+pub fn xe_display_flush(mmio: &MmioRegion, pipe: usize, ggtt_addr: u64) -> bool {
     let addr = ggtt_addr as u32;
-    mmio.write::<u32>(regs::PLANE_SURF_A, addr);
-    let _ = mmio.read::<u32>(regs::PLANE_SURF_A);
+    mmio.write::<u32>(pipe_reg(regs::PLANE_SURF_A, pipe), addr);
+    let _ = mmio.read::<u32>(pipe_reg(regs::PLANE_SURF_A, pipe));
     true
 }
+
+/// Whether pipe `pipe` has latched a flip to `ggtt_addr`.
+pub fn xe_display_flip_done(mmio: &MmioRegion, pipe: usize, ggtt_addr: u64) -> bool {
+    mmio.read::<u32>(pipe_reg(regs::PLANE_SURFLIVE_A, pipe)) == ggtt_addr as u32
+}
+
+fn cursor_pos(x: i32, y: i32) -> u32 {
+    let mut pos = (y.unsigned_abs() & regs::CUR_POS_MAGNITUDE_MASK) << regs::CUR_POS_Y_SHIFT;
+    pos |= x.unsigned_abs() & regs::CUR_POS_MAGNITUDE_MASK;
+    if y < 0 {
+        pos |= regs::CUR_POS_Y_SIGN;
+    }
+    if x < 0 {
+        pos |= regs::CUR_POS_X_SIGN;
+    }
+    pos
+}
+
+/// Enable pipe `pipe`'s 64x64 ARGB cursor plane with its top left corner
+/// at `x, y` on that pipe.  The CUR_BASE write arms the update.
+pub fn xe_display_program_cursor(mmio: &MmioRegion, pipe: usize, ggtt_addr: u64, x: i32, y: i32) {
+    mmio.write::<u32>(pipe_reg(regs::CUR_CTL_A, pipe), regs::CUR_CTL_MODE_64_ARGB);
+    mmio.write::<u32>(pipe_reg(regs::CUR_POS_A, pipe), cursor_pos(x, y));
+    mmio.write::<u32>(pipe_reg(regs::CUR_BASE_A, pipe), ggtt_addr as u32);
+    let _ = mmio.read::<u32>(pipe_reg(regs::CUR_BASE_A, pipe));
+}
+
+pub fn xe_display_move_cursor(mmio: &MmioRegion, pipe: usize, ggtt_addr: u64, x: i32, y: i32) {
+    mmio.write::<u32>(pipe_reg(regs::CUR_POS_A, pipe), cursor_pos(x, y));
+    mmio.write::<u32>(pipe_reg(regs::CUR_BASE_A, pipe), ggtt_addr as u32);
+}
+
+pub fn xe_display_disable_cursor(mmio: &MmioRegion, pipe: usize) {
+    mmio.write::<u32>(pipe_reg(regs::CUR_CTL_A, pipe), 0);
+    mmio.write::<u32>(pipe_reg(regs::CUR_BASE_A, pipe), 0);
+    let _ = mmio.read::<u32>(pipe_reg(regs::CUR_BASE_A, pipe));
+}
@@ -1,3 +1,5 @@
+use core::sync::atomic::{Ordering, fence};
+
 use slopos_abi::PhysAddr;
 use slopos_lib::align_up_u64;
 use slopos_mm::mmio::MmioRegion;
@@ -28,7 +30,10 @@ pub fn xe_ggtt_init(mmio: &MmioRegion) -> Option<XeGgtt> {
     }
 
     let table_size = ggtt_table_size(mmio.size())?;
-    let table = mmio.sub_region(regs::GTTMMADR_GGTT_OFFSET, table_size)?;
+    // The PTE half of GTTMMADR is only ever written in bulk; map it WC
+    // instead of reusing the UC- register mapping.
+    let uc_table = mmio.sub_region(regs::GTTMMADR_GGTT_OFFSET, table_size)?;
+    let table = MmioRegion::map_wc(uc_table.phys_base(), table_size).unwrap_or(uc_table);
     let entries = (table.size() / regs::GGTT_PTE_BYTES) as u32;
     if entries <= regs::GGTT_START_ENTRY {
         return None;
@@ -77,6 +82,9 @@ pub fn xe_ggtt_map(ggtt: &XeGgtt, start_entry: u32, phys: PhysAddr, pages: u32)
         ggtt.table.write::<u64>(offset, pte);
     }
 
+    // Drain the WC buffers, then read back so the PTEs have landed before
+    // the display engine is pointed at them.
+    fence(Ordering::SeqCst);
     let last_offset = (end - 1) as usize * regs::GGTT_PTE_BYTES;
     let _ = ggtt.table.read::<u64>(last_offset);
     true
@@ -1,6 +1,8 @@
 #![allow(unsafe_op_in_unsafe_fn)]
 
-use slopos_abi::{DisplayInfo, FramebufferData, PhysAddr, PixelFormat};
+use slopos_abi::damage::DamageRect;
+use slopos_abi::window::{CURSOR_IMAGE_BYTES, CURSOR_IMAGE_SIZE};
+use slopos_abi::{DisplayInfo, FramebufferData, OutputInfo, PhysAddr, PixelFormat};
 use slopos_lib::{InitFlag, IrqMutex, align_up_u64, klog_info, klog_warn};
 use slopos_mm::hhdm::PhysAddrHhdm;
 use slopos_mm::mmio::MmioRegion;
@@ -10,6 +12,7 @@ use slopos_mm::paging_defs::PAGE_SIZE_4KB;
 use crate::pci::{PciDeviceInfo, PciGpuInfo, pci_get_primary_gpu};
 use crate::pci_defs::PCI_CLASS_DISPLAY;
 
+mod blit;
 mod display;
 mod forcewake;
 mod ggtt;
@@ -28,6 +31,10 @@ struct XeDevice {
     ggtt: ggtt::XeGgtt,
     ggtt_ready: bool,
     fb: XeFramebuffer,
+    /// Primary planes of the pipes scanning out, by pipe.
+    planes: [Option<display::XePlane>; regs::XE_MAX_PIPES],
+    cursor: XeCursor,
+    blitter: blit::XeBlitter,
 }
 
 impl XeDevice {
@@ -41,10 +48,15 @@ impl XeDevice {
             ggtt: ggtt::XeGgtt::empty(),
             ggtt_ready: false,
             fb: XeFramebuffer::empty(),
+            planes: [None; regs::XE_MAX_PIPES],
+            cursor: XeCursor::empty(),
+            blitter: blit::XeBlitter::empty(),
         }
     }
 }
 
+/// The buffer on screen is the framebuffer's own `phys`/`virt`/`ggtt_addr`;
+/// `back` is the one a flip swaps in.
 #[derive(Copy, Clone)]
 #[allow(dead_code)]
 struct XeFramebuffer {
@@ -57,6 +69,16 @@ struct XeFramebuffer {
     height: u32,
     pitch: u32,
     format: PixelFormat,
+    back: Option<XeScanoutBuffer>,
+    /// A flip is armed and the planes may still be scanning out `back`.
+    flip_pending: bool,
+}
+
+#[derive(Copy, Clone)]
+struct XeScanoutBuffer {
+    phys: PhysAddr,
+    virt: *mut u8,
+    ggtt_addr: u64,
 }
 
 impl XeFramebuffer {
@@ -71,6 +93,8 @@ impl XeFramebuffer {
             height: 0,
             pitch: 0,
             format: PixelFormat::Argb8888,
+            back: None,
+            flip_pending: false,
         }
     }
 }
@@ -78,6 +102,39 @@ impl XeFramebuffer {
 // Safety: Access to this state is synchronized through `XE_DEVICE` IrqMutex.
 unsafe impl Send for XeFramebuffer {}
 
+/// The 64x64 ARGB cursor plane's image buffer, allocated on first use.
+#[derive(Copy, Clone)]
+struct XeCursor {
+    virt: *mut u8,
+    ggtt_addr: u64,
+    /// An image has been set and not hidden.
+    visible: bool,
+    /// Pipe whose cursor plane shows it.
+    pipe: Option<usize>,
+    hot_x: i32,
+    hot_y: i32,
+    x: i32,
+    y: i32,
+}
+
+impl XeCursor {
+    const fn empty() -> Self {
+        Self {
+            virt: core::ptr::null_mut(),
+            ggtt_addr: 0,
+            visible: false,
+            pipe: None,
+            hot_x: 0,
+            hot_y: 0,
+            x: 0,
+            y: 0,
+        }
+    }
+}
+
+// Safety: Access to this state is synchronized through `XE_DEVICE` IrqMutex.
+unsafe impl Send for XeCursor {}
+
 static XE_DEVICE: IrqMutex<XeDevice> = IrqMutex::new(XeDevice::empty());
 static XE_PROBED: InitFlag = InitFlag::new();
 
@@ -148,6 +205,9 @@ pub fn xe_probe() -> bool {
             ggtt: ggtt::XeGgtt::empty(),
             ggtt_ready: false,
             fb: XeFramebuffer::empty(),
+            planes: [None; regs::XE_MAX_PIPES],
+            cursor: XeCursor::empty(),
+            blitter: blit::XeBlitter::empty(),
         };
     }
 
@@ -185,6 +245,40 @@ pub fn xe_framebuffer_init(boot_fb: Option<FramebufferData>) -> Option<Framebuff
         return Some(boot);
     }
 
+    // The boot display is pipe A.  Monitors firmware lit on the other pipes
+    // go to its right, and the framebuffer spans them all.
+    let mut planes = [None; regs::XE_MAX_PIPES];
+    planes[0] = Some(display::XePlane {
+        pipe: 0,
+        x: 0,
+        y: 0,
+        width,
+        height,
+    });
+    let (mut desktop_width, mut desktop_height) = (width, height);
+    {
+        let mmio = XE_DEVICE.lock().mmio;
+        for (pipe, slot) in planes.iter_mut().enumerate().skip(1) {
+            let Some((w, h)) = display::xe_display_active_pipe_size(&mmio, pipe) else {
+                continue;
+            };
+            if desktop_width + w > DisplayInfo::MAX_DIMENSION || h > DisplayInfo::MAX_DIMENSION {
+                klog_warn!("XE: Pipe {} ({}x{}) does not fit the desktop", pipe, w, h);
+                continue;
+            }
+            *slot = Some(display::XePlane {
+                pipe,
+                x: desktop_width,
+                y: 0,
+                width: w,
+                height: h,
+            });
+            desktop_width += w;
+            desktop_height = desktop_height.max(h);
+        }
+    }
+    let (width, height) = (desktop_width, desktop_height);
+
     let pitch = align_up_u64(width as u64 * 4, regs::PLANE_STRIDE_ALIGN as u64) as u32;
     let size = pitch as u64 * height as u64;
     let size_aligned = align_up_u64(size, PAGE_SIZE_4KB);
@@ -234,14 +328,34 @@ pub fn xe_framebuffer_init(boot_fb: Option<FramebufferData>) -> Option<Framebuff
         (mmio, start_entry as u64 * PAGE_SIZE_4KB)
     };
 
-    if !display::xe_display_program_primary(&mmio, ggtt_addr, width, height, pitch) {
-        klog_warn!("XE: Display plane programming failed");
-        let _ = free_page_frame(phys);
-        return Some(boot);
+    for slot in planes.iter_mut() {
+        let Some(plane) = slot else {
+            continue;
+        };
+        if display::xe_display_program_primary(&mmio, plane, ggtt_addr, pitch) {
+            continue;
+        }
+        if plane.pipe == 0 {
+            klog_warn!("XE: Display plane programming failed");
+            let _ = free_page_frame(phys);
+            return Some(boot);
+        }
+        klog_warn!("XE: Plane programming failed on pipe {}", plane.pipe);
+        *slot = None;
     }
+    klog_info!(
+        "XE: {} output(s), desktop {}x{}",
+        planes.iter().flatten().count(),
+        width,
+        height
+    );
 
     {
         let mut dev = XE_DEVICE.lock();
+        let back = xe_scanout_alloc(&mut dev, pages);
+        if back.is_none() {
+            klog_warn!("XE: No second scanout buffer; flushing in place");
+        }
         dev.fb = XeFramebuffer {
             ready: true,
             phys,
@@ -252,7 +366,16 @@ pub fn xe_framebuffer_init(boot_fb: Option<FramebufferData>) -> Option<Framebuff
             height,
             pitch,
             format: PixelFormat::Xrgb8888,
+            back,
+            flip_pending: false,
         };
+        dev.planes = planes;
+        // SHM sources are bound into a window the size of the scanout.
+        let mmio = dev.mmio;
+        dev.blitter = blit::xe_blit_init(&mmio, &mut dev.ggtt, pages);
+        if dev.blitter.ready {
+            klog_info!("XE: Blitter online");
+        }
     }
 
     Some(FramebufferData {
@@ -262,16 +385,310 @@ pub fn xe_framebuffer_init(boot_fb: Option<FramebufferData>) -> Option<Framebuff
 }
 
 pub fn xe_flush() -> i32 {
-    let (present, ready, mmio, ggtt_addr) = {
+    let (present, ready, mmio, ggtt_addr, planes) = {
         let dev = XE_DEVICE.lock();
-        (dev.present, dev.fb.ready, dev.mmio, dev.fb.ggtt_addr)
+        (
+            dev.present,
+            dev.fb.ready,
+            dev.mmio,
+            dev.fb.ggtt_addr,
+            dev.planes,
+        )
     };
     if !present || !ready {
         return -1;
     }
-    if display::xe_display_flush(&mmio, ggtt_addr) {
-        0
+    let mut ok = true;
+    for plane in planes.iter().flatten() {
+        ok &= display::xe_display_flush(&mmio, plane.pipe, ggtt_addr);
+    }
+    if ok { 0 } else { -1 }
+}
+
+/// Allocate and map a scanout buffer of `pages` pages.  Called with the
+/// device lock held, once the GGTT is up.
+fn xe_scanout_alloc(dev: &mut XeDevice, pages: u32) -> Option<XeScanoutBuffer> {
+    let phys = alloc_page_frames(pages, ALLOC_FLAG_ZERO);
+    if phys.is_null() {
+        return None;
+    }
+    let Some(virt) = phys.to_virt_checked() else {
+        let _ = free_page_frame(phys);
+        return None;
+    };
+    let mapped = ggtt::xe_ggtt_alloc(&mut dev.ggtt, pages, 16)
+        .filter(|&entry| ggtt::xe_ggtt_map(&dev.ggtt, entry, phys, pages));
+    let Some(entry) = mapped else {
+        let _ = free_page_frame(phys);
+        return None;
+    };
+    Some(XeScanoutBuffer {
+        phys,
+        virt: virt.as_mut_ptr::<u8>(),
+        ggtt_addr: entry as u64 * PAGE_SIZE_4KB,
+    })
+}
+
+/// Physical address of the scanout buffer that is off screen, for the
+/// next frame to be drawn into.  `None` when single-buffered.  The caller
+/// waits for [`xe_flip_done`] before drawing.
+pub fn xe_back_buffer() -> Option<PhysAddr> {
+    let dev = XE_DEVICE.lock();
+    if !dev.fb.ready {
+        return None;
+    }
+    dev.fb.back.map(|back| back.phys)
+}
+
+/// Swap the back buffer on screen.  The planes latch it at the next
+/// vblank; [`xe_flip_done`] reports when they have.
+pub fn xe_page_flip() -> bool {
+    let mut dev = XE_DEVICE.lock();
+    let dev = &mut *dev;
+    let fb = &mut dev.fb;
+    if !fb.ready {
+        return false;
+    }
+    let Some(back) = fb.back.as_mut() else {
+        return false;
+    };
+    core::mem::swap(&mut fb.phys, &mut back.phys);
+    core::mem::swap(&mut fb.virt, &mut back.virt);
+    core::mem::swap(&mut fb.ggtt_addr, &mut back.ggtt_addr);
+    let front = fb.ggtt_addr;
+    let mut ok = true;
+    for plane in dev.planes.iter().flatten() {
+        ok &= display::xe_display_flush(&dev.mmio, plane.pipe, front);
+    }
+    dev.fb.flip_pending = true;
+    ok
+}
+
+/// Whether every plane is scanning out the buffer last flipped to.
+pub fn xe_flip_done() -> bool {
+    let mut dev = XE_DEVICE.lock();
+    if !dev.fb.flip_pending {
+        return true;
+    }
+    let front = dev.fb.ggtt_addr;
+    let done = dev
+        .planes
+        .iter()
+        .flatten()
+        .all(|plane| display::xe_display_flip_done(&dev.mmio, plane.pipe, front));
+    if done {
+        dev.fb.flip_pending = false;
+    }
+    done
+}
+
+/// Copy `rect` from `src_ggtt` to `dst_ggtt` with the blitter, both laid
+/// out like the scanout.  Returns false if the blitter is not running.
+pub fn xe_blit(src_ggtt: u64, dst_ggtt: u64, rect: &DamageRect) -> bool {
+    let mut dev = XE_DEVICE.lock();
+    let (mmio, fb) = (dev.mmio, dev.fb);
+    if !fb.ready {
+        return false;
+    }
+    dev.blitter.copy(
+        &mmio,
+        src_ggtt,
+        dst_ggtt,
+        fb.pitch,
+        (fb.width, fb.height),
+        core::slice::from_ref(rect),
+    )
+}
+
+/// Blit `damage` (the whole screen if empty) from an SHM buffer laid out
+/// like the scanout onto it, or onto the back buffer when double-buffered.
+/// Returns false to leave the copy to the CPU.
+pub fn xe_flip_from_shm(shm_phys: PhysAddr, size: usize, damage: &[DamageRect]) -> bool {
+    let mut dev = XE_DEVICE.lock();
+    let dev = &mut *dev;
+    let fb = dev.fb;
+    if !fb.ready || !dev.blitter.ready || (size as u64) < fb.size {
+        return false;
+    }
+    let pages = fb.size.div_ceil(PAGE_SIZE_4KB) as u32;
+    let Some(src) = dev
+        .blitter
+        .bind_source(&dev.ggtt, &dev.mmio, shm_phys, pages)
+    else {
+        return false;
+    };
+    let whole = [DamageRect {
+        x0: 0,
+        y0: 0,
+        x1: fb.width as i32 - 1,
+        y1: fb.height as i32 - 1,
+    }];
+    let rects = if damage.is_empty() {
+        &whole[..]
+    } else {
+        damage
+    };
+    let dst = fb.back.map_or(fb.ggtt_addr, |back| back.ggtt_addr);
+    dev.blitter
+        .copy(&dev.mmio, src, dst, fb.pitch, (fb.width, fb.height), rects)
+}
+
+/// Allocate and map the cursor image buffer.  Called with the device lock
+/// held, once the scanout is up (which set up the GGTT).
+fn xe_cursor_alloc(dev: &mut XeDevice) -> bool {
+    if !dev.cursor.virt.is_null() {
+        return true;
+    }
+    if !dev.fb.ready {
+        return false;
+    }
+
+    let pages = (CURSOR_IMAGE_BYTES as u64).div_ceil(PAGE_SIZE_4KB) as u32;
+    let phys = alloc_page_frames(pages, ALLOC_FLAG_ZERO);
+    if phys.is_null() {
+        klog_warn!("XE: Failed to allocate cursor pages");
+        return false;
+    }
+    let Some(virt) = phys.to_virt_checked() else {
+        let _ = free_page_frame(phys);
+        return false;
+    };
+    let Some(start_entry) = ggtt::xe_ggtt_alloc(&mut dev.ggtt, pages, 16) else {
+        klog_warn!("XE: GGTT allocation for cursor failed");
+        let _ = free_page_frame(phys);
+        return false;
+    };
+    if !ggtt::xe_ggtt_map(&dev.ggtt, start_entry, phys, pages) {
+        klog_warn!("XE: GGTT mapping for cursor failed");
+        let _ = free_page_frame(phys);
+        return false;
+    }
+
+    dev.cursor.virt = virt.as_mut_ptr::<u8>();
+    dev.cursor.ggtt_addr = start_entry as u64 * PAGE_SIZE_4KB;
+    true
+}
+
+/// Show the cursor on the pipe whose output holds its hotspot, and take it
+/// off the one that showed it before.  `reload` reprograms the plane even
+/// if the pipe is unchanged, for a new image.
+fn xe_cursor_place(dev: &mut XeDevice, reload: bool) {
+    let cursor = dev.cursor;
+    let target = if cursor.visible {
+        dev.planes
+            .iter()
+            .flatten()
+            .find(|plane| plane_contains(plane, cursor.x, cursor.y))
+            .copied()
     } else {
-        -1
+        None
+    };
+    let target_pipe = target.map(|plane| plane.pipe);
+    if let Some(old) = cursor.pipe
+        && target_pipe != Some(old)
+    {
+        display::xe_display_disable_cursor(&dev.mmio, old);
     }
+    dev.cursor.pipe = target_pipe;
+
+    let Some(plane) = target else {
+        return;
+    };
+    let x = cursor.x - plane.x as i32 - cursor.hot_x;
+    let y = cursor.y - plane.y as i32 - cursor.hot_y;
+    if reload || cursor.pipe != target_pipe {
+        display::xe_display_program_cursor(&dev.mmio, plane.pipe, cursor.ggtt_addr, x, y);
+    } else {
+        display::xe_display_move_cursor(&dev.mmio, plane.pipe, cursor.ggtt_addr, x, y);
+    }
+}
+
+/// Show `image` (`CURSOR_IMAGE_BYTES` of premultiplied ARGB8888) on the
+/// cursor plane with its hotspot at `hot_x, hot_y`.  Returns false when
+/// the plane is unavailable.
+pub fn xe_cursor_set_image(image: &[u8], hot_x: u32, hot_y: u32) -> bool {
+    if image.len() != CURSOR_IMAGE_BYTES || hot_x >= CURSOR_IMAGE_SIZE || hot_y >= CURSOR_IMAGE_SIZE
+    {
+        return false;
+    }
+
+    let mut dev = XE_DEVICE.lock();
+    if !dev.present || !xe_cursor_alloc(&mut dev) {
+        return false;
+    }
+
+    // SAFETY: the cursor buffer is CURSOR_IMAGE_BYTES long and only touched
+    // under the device lock.
+    unsafe {
+        core::ptr::copy_nonoverlapping(image.as_ptr(), dev.cursor.virt, CURSOR_IMAGE_BYTES);
+    }
+    dev.cursor.hot_x = hot_x as i32;
+    dev.cursor.hot_y = hot_y as i32;
+    dev.cursor.visible = true;
+    xe_cursor_place(&mut dev, true);
+    true
+}
+
+/// Move the cursor's hotspot to desktop position `x, y`.
+pub fn xe_cursor_move(x: i32, y: i32) {
+    let mut dev = XE_DEVICE.lock();
+    dev.cursor.x = x;
+    dev.cursor.y = y;
+    xe_cursor_place(&mut dev, false);
+}
+
+pub fn xe_cursor_hide() {
+    let mut dev = XE_DEVICE.lock();
+    dev.cursor.visible = false;
+    xe_cursor_place(&mut dev, false);
+}
+
+fn plane_contains(plane: &display::XePlane, x: i32, y: i32) -> bool {
+    let (px, py) = (plane.x as i32, plane.y as i32);
+    x >= px && y >= py && x < px + plane.width as i32 && y < py + plane.height as i32
+}
+
+const PIPE_NAMES: [&[u8]; regs::XE_MAX_PIPES] = [b"pipe-a", b"pipe-b", b"pipe-c"];
+
+/// The pipes scanning out, as display outputs numbered by pipe.
+pub fn xe_outputs(out: &mut [OutputInfo]) -> usize {
+    let dev = XE_DEVICE.lock();
+    let mut count = 0;
+    for plane in dev.planes.iter().flatten() {
+        let Some(slot) = out.get_mut(count) else {
+            break;
+        };
+        *slot = OutputInfo::new(
+            plane.pipe as u32,
+            PIPE_NAMES[plane.pipe],
+            plane.x as i32,
+            plane.y as i32,
+            plane.width,
+            plane.height,
+        );
+        count += 1;
+    }
+    count
+}
+
+/// Scan out output `id` from desktop position `x, y`.  The output must
+/// stay within the framebuffer.
+pub fn xe_output_set_position(id: u32, x: i32, y: i32) -> bool {
+    let mut dev = XE_DEVICE.lock();
+    let fb = dev.fb;
+    let Some(Some(plane)) = dev.planes.get_mut(id as usize) else {
+        return false;
+    };
+    let (Ok(x), Ok(y)) = (u32::try_from(x), u32::try_from(y)) else {
+        return false;
+    };
+    if !fb.ready || x + plane.width > fb.width || y + plane.height > fb.height {
+        return false;
+    }
+    plane.x = x;
+    plane.y = y;
+    let plane = *plane;
+    display::xe_display_set_offset(&dev.mmio, &plane, fb.ggtt_addr);
+    xe_cursor_place(&mut dev, true);
+    true
 }
@@ -15,17 +15,43 @@ pub const GGTT_PTE_PRESENT: u64 = 1;
 pub const GGTT_PTE_ADDR_MASK: u64 = 0x000f_ffff_ffff_f000;
 pub const GGTT_START_ENTRY: u32 = 0x1000;
 
+/// Pipes A to C; registers for pipe N sit `N * PIPE_STRIDE` past pipe A's.
+pub const XE_MAX_PIPES: usize = 3;
+pub const PIPE_STRIDE: usize = 0x1000;
+
+pub const PIPESRC_A: usize = 0x6001c;
+pub const TRANSCONF_A: usize = 0x70008;
+pub const TRANSCONF_ENABLE: u32 = 1 << 31;
+
 pub const PLANE_CTL_A: usize = 0x70180;
 pub const PLANE_STRIDE_A: usize = 0x70188;
 pub const PLANE_POS_A: usize = 0x7018c;
 pub const PLANE_SIZE_A: usize = 0x70190;
 pub const PLANE_SURF_A: usize = 0x7019c;
 pub const PLANE_OFFSET_A: usize = 0x701a4;
+/// The surface address the plane is scanning out now; PLANE_SURF writes
+/// reach it at the next vblank.
+pub const PLANE_SURFLIVE_A: usize = 0x701ac;
 
 pub const PLANE_CTL_ENABLE: u32 = 1 << 31;
 pub const PLANE_CTL_FORMAT_XRGB_8888: u32 = 4 << 24;
 pub const PLANE_STRIDE_ALIGN: u32 = 64;
 
+pub const CUR_CTL_A: usize = 0x70080;
+pub const CUR_BASE_A: usize = 0x70084;
+pub const CUR_POS_A: usize = 0x70088;
+
+pub const CUR_CTL_MODE_64_ARGB: u32 = 0x27;
+pub const CUR_POS_Y_SIGN: u32 = 1 << 31;
+pub const CUR_POS_Y_SHIFT: u32 = 16;
+pub const CUR_POS_X_SIGN: u32 = 1 << 15;
+pub const CUR_POS_MAGNITUDE_MASK: u32 = 0xfff;
+
+/// Pipe `pipe`'s copy of pipe A register `reg_a`.
+pub const fn pipe_reg(reg_a: usize, pipe: usize) -> usize {
+    reg_a + pipe * PIPE_STRIDE
+}
+
 pub const fn bit(shift: u32) -> u32 {
     1u32 << shift
 }
@@ -33,3 +59,57 @@ pub const fn bit(shift: u32) -> u32 {
 pub const fn reg_field_get(mask: u32, value: u32) -> u32 {
     (value & mask) >> mask.trailing_zeros()
 }
+
+/// Blitter copy engine (BCS0); ring registers sit at fixed offsets from it.
+pub const BCS0_BASE: usize = 0x22000;
+pub const RING_TAIL: usize = 0x30;
+pub const RING_HEAD: usize = 0x34;
+pub const RING_START: usize = 0x38;
+pub const RING_CTL: usize = 0x3c;
+pub const RING_HWS_PGA: usize = 0x80;
+pub const RING_MI_MODE: usize = 0x9c;
+pub const RING_CONTEXT_CONTROL: usize = 0x244;
+pub const RING_MODE: usize = 0x29c;
+pub const RING_EXECLIST_SQ_LO: usize = 0x510;
+pub const RING_EXECLIST_SQ_HI: usize = 0x514;
+pub const RING_EXECLIST_CONTROL: usize = 0x550;
+
+pub const RING_CTL_VALID: u32 = 1;
+pub const MI_MODE_STOP_RING: u32 = 1 << 8;
+pub const RING_MODE_DISABLE_LEGACY: u32 = 1 << 3;
+pub const RING_MODE_RUN_LIST_ENABLE: u32 = 1 << 15;
+pub const EXECLIST_CONTROL_LOAD: u32 = 1;
+pub const CTX_CTRL_RESTORE_INHIBIT: u32 = 1 << 0;
+
+/// Context descriptor bits: valid, GGTT-privileged, legacy 32-bit
+/// addressing.
+pub const CTX_DESC_VALID: u32 = 1 << 0;
+pub const CTX_DESC_LEGACY_32B: u32 = 1 << 3;
+pub const CTX_DESC_PRIVILEGE: u32 = 1 << 8;
+pub const CTX_DESC_ID_SHIFT: u32 = 37 - 32;
+
+/// Invalidates the GPU's GGTT TLBs after PTEs change.
+pub const GFX_FLSH_CNTL: usize = 0x101008;
+pub const GFX_FLSH_CNTL_EN: u32 = 1;
+
+pub const MI_NOOP: u32 = 0;
+pub const MI_BATCH_BUFFER_END: u32 = 0x0a << 23;
+pub const MI_LOAD_REGISTER_IMM: u32 = 0x22 << 23;
+pub const MI_FLUSH_DW: u32 = 0x26 << 23;
+pub const MI_FLUSH_DW_OP_STOREDW: u32 = 1 << 14;
+pub const MI_FLUSH_DW_USE_GTT: u32 = 1 << 2;
+
+/// XY_FAST_COPY_BLT, linear 32bpp to linear 32bpp.
+pub const XY_FAST_COPY_BLT: u32 = (2 << 29) | (0x42 << 22);
+pub const XY_FAST_COPY_DWORDS: u32 = 10;
+pub const XY_FAST_COPY_32BPP: u32 = 3 << 24;
+
+/// A masked register write setting `bits`.
+pub const fn masked_enable(bits: u32) -> u32 {
+    (bits << 16) | bits
+}
+
+/// A masked register write clearing `bits`.
+pub const fn masked_disable(bits: u32) -> u32 {
+    bits << 16
+}
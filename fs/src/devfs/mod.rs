@@ -1,30 +1,73 @@
 use crate::vfs::{FileStat, FileSystem, FileType, InodeId, VfsError, VfsResult};
-use slopos_lib::IrqMutex;
+use slopos_abi::syscall::{FBIOGET_VSCREENINFO, TtyIndex};
+use slopos_abi::{DisplayInfo, InputEvent};
+use slopos_lib::kernel_services::platform;
+use slopos_lib::kernel_services::syscall_services::{input, tty, video};
+use slopos_mm::user_copy::copy_to_user;
+use slopos_mm::user_ptr::UserPtr;
 
 const ROOT_INODE: InodeId = 1;
 const NULL_INODE: InodeId = 2;
 const ZERO_INODE: InodeId = 3;
 const RANDOM_INODE: InodeId = 4;
 const CONSOLE_INODE: InodeId = 5;
+const FB0_INODE: InodeId = 6;
+const INPUT_DIR_INODE: InodeId = 7;
+const EVENT0_INODE: InodeId = 8;
+const TTYS0_INODE: InodeId = 9;
+const TTY0_INODE: InodeId = 10;
+const URANDOM_INODE: InodeId = 11;
+
+/// TTY slot backing `/dev/ttyS0` (serial console, COM1).
+pub const TTYS0_INDEX: TtyIndex = TtyIndex(0);
+/// TTY slot backing `/dev/tty0` (virtual console).
+pub const TTY0_INDEX: TtyIndex = TtyIndex(1);
 
 use crate::MAX_NAME_LEN;
 
 struct DeviceEntry {
     name: [u8; MAX_NAME_LEN],
     name_len: usize,
+    parent: InodeId,
     inode: InodeId,
+    file_type: FileType,
     major: u32,
     minor: u32,
+    mode: u16,
 }
 
+/// Mode of nodes any user may read and write.
+const MODE_PUBLIC: u16 = 0o666;
+/// Mode of nodes that expose the screen or raw input: root only, so one
+/// user cannot scrape another's display or log their keystrokes.
+const MODE_PRIVATE: u16 = 0o600;
+
 impl DeviceEntry {
     const fn new(name: &[u8], inode: InodeId, major: u32, minor: u32) -> Self {
+        Self::with_parent(name, ROOT_INODE, inode, FileType::CharDevice, major, minor)
+    }
+
+    const fn dir(name: &[u8], inode: InodeId) -> Self {
+        Self::with_parent(name, ROOT_INODE, inode, FileType::Directory, 0, 0)
+    }
+
+    const fn with_parent(
+        name: &[u8],
+        parent: InodeId,
+        inode: InodeId,
+        file_type: FileType,
+        major: u32,
+        minor: u32,
+    ) -> Self {
         let mut entry = Self {
             name: [0; MAX_NAME_LEN],
             name_len: 0,
+            parent,
             inode,
+            file_type,
             major,
             minor,
+            mode: MODE_PUBLIC,
         };
         let len = if name.len() < MAX_NAME_LEN {
             name.len()
@@ -39,45 +82,86 @@ impl DeviceEntry {
         entry.name_len = len;
         entry
     }
+
+    const fn private(mut self) -> Self {
+        self.mode = MODE_PRIVATE;
+        self
+    }
+
+    fn is_dir(&self) -> bool {
+        self.file_type == FileType::Directory
+    }
 }
 
-static DEVICES: [DeviceEntry; 4] = [
+// Major/minor numbers follow Linux so ported tools recognise the nodes.
+static DEVICES: [DeviceEntry; 10] = [
     DeviceEntry::new(b"null", NULL_INODE, 1, 3),
     DeviceEntry::new(b"zero", ZERO_INODE, 1, 5),
     DeviceEntry::new(b"random", RANDOM_INODE, 1, 8),
+    DeviceEntry::new(b"urandom", URANDOM_INODE, 1, 9),
     DeviceEntry::new(b"console", CONSOLE_INODE, 5, 1),
+    DeviceEntry::new(b"fb0", FB0_INODE, 29, 0).private(),
+    DeviceEntry::dir(b"input", INPUT_DIR_INODE),
+    DeviceEntry::with_parent(
+        b"event0",
+        INPUT_DIR_INODE,
+        EVENT0_INODE,
+        FileType::CharDevice,
+        13,
+        64,
+    )
+    .private(),
+    DeviceEntry::new(b"ttyS0", TTYS0_INODE, 4, 64),
+    DeviceEntry::new(b"tty0", TTY0_INODE, 4, 0),
 ];
 
-struct DevFsInner {
-    rng_state: u64,
+fn find_device(inode: InodeId) -> Option<&'static DeviceEntry> {
+    DEVICES.iter().find(|dev| dev.inode == inode)
 }
 
-impl DevFsInner {
-    const fn new() -> Self {
-        Self {
-            rng_state: 0x853c49e6748fea9b,
-        }
-    }
+fn is_directory(inode: InodeId) -> bool {
+    inode == ROOT_INODE || find_device(inode).is_some_and(DeviceEntry::is_dir)
+}
+
+fn parent_of(inode: InodeId) -> InodeId {
+    find_device(inode).map_or(ROOT_INODE, |dev| dev.parent)
+}
 
-    fn next_random(&mut self) -> u64 {
-        let mut x = self.rng_state;
-        x ^= x >> 12;
-        x ^= x << 25;
-        x ^= x >> 27;
-        self.rng_state = x;
-        x.wrapping_mul(0x2545F4914F6CDD1D)
+const EVENT_SIZE: usize = core::mem::size_of::<InputEvent>();
+
+/// Drain whole `InputEvent` records into `buf`. Short buffers are rejected
+/// rather than handing out torn records.
+fn read_input_events(buf: &mut [u8]) -> VfsResult<usize> {
+    let want = buf.len() / EVENT_SIZE;
+    if want == 0 {
+        return Err(VfsError::InvalidArgument);
     }
+
+    let mut events = [InputEvent::default(); 16];
+    let max = want.min(events.len());
+    let count = input::device_read(&mut events[..max]);
+
+    let bytes = count * EVENT_SIZE;
+    // SAFETY: `InputEvent` is `repr(C)` plain data; the first `count`
+    // entries were initialised above and span exactly `bytes` bytes.
+    let raw = unsafe { core::slice::from_raw_parts(events.as_ptr() as *const u8, bytes) };
+    buf[..bytes].copy_from_slice(raw);
+    Ok(bytes)
 }
 
-pub struct DevFs {
-    inner: IrqMutex<DevFsInner>,
+fn tty_read(index: TtyIndex, buf: &mut [u8]) -> VfsResult<usize> {
+    match tty::read_cooked(index, buf.as_mut_ptr(), buf.len(), true) {
+        n if n >= 0 => Ok(n as usize),
+        -11 => Ok(0),
+        _ => Err(VfsError::IoError),
+    }
 }
 
+pub struct DevFs;
+
 impl DevFs {
     pub const fn new() -> Self {
-        Self {
-            inner: IrqMutex::new(DevFsInner::new()),
-        }
+        Self
     }
 }
 
@@ -97,16 +181,19 @@ impl FileSystem for DevFs {
     }
 
     fn lookup(&self, parent: InodeId, name: &[u8]) -> VfsResult<InodeId> {
-        if parent != ROOT_INODE {
+        if !is_directory(parent) {
             return Err(VfsError::NotDirectory);
         }
 
-        if name == b"." || name == b".." {
-            return Ok(ROOT_INODE);
+        if name == b"." {
+            return Ok(parent);
+        }
+        if name == b".." {
+            return Ok(parent_of(parent));
         }
 
         for dev in &DEVICES {
-            if dev.name_len == name.len() && &dev.name[..dev.name_len] == name {
+            if dev.parent == parent && &dev.name[..dev.name_len] == name {
                 return Ok(dev.inode);
             }
         }
@@ -115,20 +202,20 @@ impl FileSystem for DevFs {
     }
 
     fn stat(&self, inode: InodeId) -> VfsResult<FileStat> {
-        if inode == ROOT_INODE {
-            return Ok(FileStat::new_directory(ROOT_INODE));
+        if is_directory(inode) {
+            return Ok(FileStat::new_directory(inode));
         }
 
-        for dev in &DEVICES {
-            if dev.inode == inode {
-                return Ok(FileStat::new_char_device(inode, dev.major, dev.minor));
-            }
+        let dev = find_device(inode).ok_or(VfsError::NotFound)?;
+        let mut stat = FileStat::new_char_device(inode, dev.major, dev.minor);
+        stat.mode = dev.mode;
+        if inode == FB0_INODE {
+            stat.size = video::get_display_info().map_or(0, |info| info.buffer_size() as u64);
         }
-
-        Err(VfsError::NotFound)
+        Ok(stat)
     }
 
-    fn read(&self, inode: InodeId, _offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+    fn read(&self, inode: InodeId, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
         match inode {
             NULL_INODE => Ok(0),
 
@@ -137,48 +224,66 @@ impl FileSystem for DevFs {
                 Ok(buf.len())
             }
 
+            // /dev/random refuses to hand out output until the CSPRNG has
+            // been seeded; it cannot sleep here because the caller holds the
+            // descriptor table lock. Use getrandom(2) to wait for seeding.
             RANDOM_INODE => {
-                let mut inner = self.inner.lock();
-                let mut pos = 0;
-                while pos + 8 <= buf.len() {
-                    let val = inner.next_random();
-                    buf[pos..pos + 8].copy_from_slice(&val.to_le_bytes());
-                    pos += 8;
+                if !platform::rng_is_seeded() {
+                    return Err(VfsError::Busy);
                 }
-                if pos < buf.len() {
-                    let val = inner.next_random();
-                    let remaining = buf.len() - pos;
-                    buf[pos..].copy_from_slice(&val.to_le_bytes()[..remaining]);
-                    pos = buf.len();
-                }
-                Ok(pos)
+                Ok(platform::rng_fill(buf))
             }
 
+            URANDOM_INODE => Ok(platform::rng_fill(buf)),
+
             CONSOLE_INODE => Ok(0),
 
-            ROOT_INODE => Err(VfsError::IsDirectory),
+            FB0_INODE => Ok(video::fb_read(offset as usize, buf)),
+
+            EVENT0_INODE => read_input_events(buf),
+
+            TTYS0_INODE => tty_read(TTYS0_INDEX, buf),
+
+            TTY0_INODE => tty_read(TTY0_INDEX, buf),
+
+            ROOT_INODE | INPUT_DIR_INODE => Err(VfsError::IsDirectory),
 
             _ => Err(VfsError::NotFound),
         }
     }
 
-    fn write(&self, inode: InodeId, _offset: u64, buf: &[u8]) -> VfsResult<usize> {
+    fn write(&self, inode: InodeId, offset: u64, buf: &[u8]) -> VfsResult<usize> {
         match inode {
             NULL_INODE | ZERO_INODE => Ok(buf.len()),
 
-            RANDOM_INODE => {
-                let mut inner = self.inner.lock();
+            // Written data is mixed into the pool but, as on Linux, never
+            // credited: userspace cannot vouch for its own randomness.
+            RANDOM_INODE | URANDOM_INODE => {
                 for chunk in buf.chunks(8) {
                     let mut bytes = [0u8; 8];
                     bytes[..chunk.len()].copy_from_slice(chunk);
-                    inner.rng_state ^= u64::from_le_bytes(bytes);
+                    platform::rng_add_entropy(u64::from_le_bytes(bytes), 0);
                 }
                 Ok(buf.len())
             }
 
             CONSOLE_INODE => Ok(buf.len()),
 
-            ROOT_INODE => Err(VfsError::IsDirectory),
+            FB0_INODE => {
+                let written = video::fb_write(offset as usize, buf);
+                if written == 0 && !buf.is_empty() {
+                    return Err(VfsError::NoSpace);
+                }
+                Ok(written)
+            }
+
+            EVENT0_INODE => Err(VfsError::NotSupported),
+
+            TTYS0_INODE => Ok(tty::write_bytes(TTYS0_INDEX, buf.as_ptr(), buf.len())),
+
+            TTY0_INODE => Ok(tty::write_bytes(TTY0_INDEX, buf.as_ptr(), buf.len())),
+
+            ROOT_INODE | INPUT_DIR_INODE => Err(VfsError::IsDirectory),
 
             _ => Err(VfsError::NotFound),
         }
@@ -198,7 +303,7 @@ impl FileSystem for DevFs {
         offset: usize,
         callback: &mut dyn FnMut(&[u8], InodeId, FileType) -> bool,
     ) -> VfsResult<usize> {
-        if inode != ROOT_INODE {
+        if !is_directory(inode) {
             return Err(VfsError::NotDirectory);
         }
 
@@ -206,7 +311,7 @@ impl FileSystem for DevFs {
         let mut current = 0;
 
         if current >= offset {
-            if !callback(b".", ROOT_INODE, FileType::Directory) {
+            if !callback(b".", inode, FileType::Directory) {
                 return Ok(count);
             }
             count += 1;
@@ -214,16 +319,16 @@ impl FileSystem for DevFs {
         current += 1;
 
         if current >= offset {
-            if !callback(b"..", ROOT_INODE, FileType::Directory) {
+            if !callback(b"..", parent_of(inode), FileType::Directory) {
                 return Ok(count);
             }
             count += 1;
         }
         current += 1;
 
-        for dev in &DEVICES {
+        for dev in DEVICES.iter().filter(|dev| dev.parent == inode) {
             if current >= offset {
-                if !callback(&dev.name[..dev.name_len], dev.inode, FileType::CharDevice) {
+                if !callback(&dev.name[..dev.name_len], dev.inode, dev.file_type) {
                     return Ok(count);
                 }
                 count += 1;
@@ -238,10 +343,20 @@ impl FileSystem for DevFs {
         Err(VfsError::NotSupported)
     }
 
+    fn ioctl(&self, inode: InodeId, cmd: u64, arg: u64) -> VfsResult<u64> {
+        match (inode, cmd) {
+            (FB0_INODE, FBIOGET_VSCREENINFO) => {
+                let info: DisplayInfo = video::get_display_info().ok_or(VfsError::IoError)?;
+                let ptr =
+                    UserPtr::<DisplayInfo>::try_new(arg).map_err(|_| VfsError::InvalidArgument)?;
+                copy_to_user(ptr, &info).map_err(|_| VfsError::InvalidArgument)?;
+                Ok(0)
+            }
+            _ => Err(VfsError::NotSupported),
+        }
+    }
+
     fn sync(&self) -> VfsResult<()> {
         Ok(())
     }
 }
-
-unsafe impl Send for DevFs {}
-unsafe impl Sync for DevFs {}
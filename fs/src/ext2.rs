@@ -7,6 +7,11 @@ const EXT2_MIN_BLOCK_SIZE: u32 = 1024;
 const EXT2_MAX_BLOCK_SIZE: u32 = 4096;
 const EXT2_MAX_BLOCK_SIZE_USIZE: usize = EXT2_MAX_BLOCK_SIZE as usize;
 
+/// Inode timestamps are 32-bit Unix seconds.
+fn now_secs() -> u32 {
+    slopos_lib::walltime::realtime_secs() as u32
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Ext2Error {
     InvalidSuperblock,
@@ -151,6 +156,38 @@ impl<'a> Ext2Fs<'a> {
         self.unlink_entry_internal(parent_inode, name)
     }
 
+    /// Replace the permission bits of `inode`, keeping its file type.
+    pub fn set_mode(&mut self, inode: u32, mode: u16) -> Result<(), Ext2Error> {
+        let mut disk = self.read_inode_internal(inode)?;
+        disk.mode = (disk.mode & MODE_TYPE_MASK) | (mode & !MODE_TYPE_MASK);
+        disk.ctime = now_secs();
+        self.write_inode(inode, disk)
+    }
+
+    pub fn set_owner(&mut self, inode: u32, uid: u16, gid: u16) -> Result<(), Ext2Error> {
+        let mut disk = self.read_inode_internal(inode)?;
+        disk.uid = uid;
+        disk.gid = gid;
+        disk.ctime = now_secs();
+        self.write_inode(inode, disk)
+    }
+
+    /// Disk byte offset of each block of regular file `inode`, in file
+    /// order, into `out`.  Fails if the file has holes.  Returns how many
+    /// blocks the file has, which may be more than `out` holds.
+    pub fn file_block_offsets(&mut self, inode: u32, out: &mut [u64]) -> Result<usize, Ext2Error> {
+        let inode = self.read_inode_internal(inode)?;
+        if !inode.is_regular_file() {
+            return Err(Ext2Error::NotFile);
+        }
+        let blocks = inode.size.div_ceil(self.block_size) as usize;
+        for (file_block, slot) in out.iter_mut().take(blocks).enumerate() {
+            let block = self.map_block(&inode, file_block as u32)?;
+            *slot = block as u64 * self.block_size as u64;
+        }
+        Ok(blocks)
+    }
+
     pub(crate) fn init_internal(device: &'a mut dyn BlockDevice) -> Result<Self, Ext2Error> {
         let mut sb_buf = [0u8; 1024];
         device
@@ -424,6 +461,9 @@ impl<'a> Ext2Fs<'a> {
                 .blocks
                 .saturating_add(allocated_blocks * sectors_per_block);
         }
+        let now = now_secs();
+        inode.mtime = now;
+        inode.ctime = now;
         self.write_inode(inode_num, inode)?;
         Ok(written)
     }
@@ -524,13 +564,18 @@ impl<'a> Ext2Fs<'a> {
             return Err(Ext2Error::NotDirectory);
         }
         let inode_num = self.allocate_inode()?;
+        let now = now_secs();
         let mut inode = Ext2Inode {
-            mode: if is_dir { MODE_DIRECTORY } else { MODE_FILE },
+            mode: if is_dir {
+                MODE_DIRECTORY | MODE_DEFAULT_DIR_PERMS
+            } else {
+                MODE_FILE | MODE_DEFAULT_FILE_PERMS
+            },
             uid: 0,
             size: 0,
-            atime: 0,
-            ctime: 0,
-            mtime: 0,
+            atime: now,
+            ctime: now,
+            mtime: now,
             dtime: 0,
             gid: 0,
             links_count: if is_dir { 2 } else { 1 },
@@ -1235,3 +1280,6 @@ fn clear_bit(bitmap: &mut [u8], bit: usize) {
 
 const MODE_FILE: u16 = 0x8000;
 const MODE_DIRECTORY: u16 = 0x4000;
+const MODE_TYPE_MASK: u16 = 0xF000;
+const MODE_DEFAULT_FILE_PERMS: u16 = 0o644;
+const MODE_DEFAULT_DIR_PERMS: u16 = 0o755;
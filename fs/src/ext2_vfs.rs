@@ -122,6 +122,18 @@ impl<T: Ext2VfsBackend + Send + Sync> FileSystem for T {
         self.with_ext2(|fs| fs.unlink_entry(parent as u32, name))
     }
 
+    fn set_mode(&self, inode: InodeId, mode: u16) -> VfsResult<()> {
+        self.with_ext2(|fs| fs.set_mode(inode as u32, mode))
+    }
+
+    fn set_owner(&self, inode: InodeId, uid: u32, gid: u32) -> VfsResult<()> {
+        // The 16-bit on-disk fields are all this driver reads back.
+        let (Ok(uid), Ok(gid)) = (u16::try_from(uid), u16::try_from(gid)) else {
+            return Err(VfsError::InvalidArgument);
+        };
+        self.with_ext2(|fs| fs.set_owner(inode as u32, uid, gid))
+    }
+
     fn readdir(
         &self,
         inode: InodeId,
@@ -197,6 +209,18 @@ pub fn ext2_vfs_is_initialized() -> bool {
     EXT2_VFS_INIT.is_set()
 }
 
+/// Where the blocks of the file at `path` on the ext2 root sit on disk, for
+/// writers that cannot go through the filesystem (the kernel crash dump).
+/// Fills `out` with the byte offset of each block and returns the block
+/// count and size.
+pub fn ext2_vfs_map_file(path: &[u8], out: &mut [u64]) -> VfsResult<(usize, u32)> {
+    EXT2_VFS_STATIC.with_fs(|fs| {
+        let inode = fs.resolve_path(path)?;
+        let blocks = fs.file_block_offsets(inode, out)?;
+        Ok((blocks, fs.block_size()))
+    })
+}
+
 // ============================================================================
 // Helper functions
 // ============================================================================
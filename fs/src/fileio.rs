@@ -1,13 +1,16 @@
 use core::ffi::{c_char, c_int};
 use core::slice;
 
+use slopos_lib::handle_table::HandleTable;
 use slopos_lib::{InitFlag, IrqMutex};
 
 use slopos_abi::fs::{FS_TYPE_FILE, USER_FS_OPEN_CREAT, UserFsEntry, UserFsStat};
-use slopos_abi::net::INVALID_SOCKET_IDX;
+use slopos_abi::handle::{Handle, HandleKind};
+use slopos_abi::net::MAX_SOCKETS;
 use slopos_abi::syscall::{
-    F_DUPFD, F_GETFD, F_GETFL, F_SETFD, F_SETFL, FD_CLOEXEC, O_CLOEXEC, O_NOCTTY, O_NONBLOCK,
-    POLLERR, POLLHUP, POLLIN, POLLNVAL, POLLOUT, POLLPRI, SEEK_CUR, SEEK_END, SEEK_SET, TtyIndex,
+    F_DUPFD, F_DUPFD_CLOEXEC, F_GETFD, F_GETFL, F_SETFD, F_SETFL, FD_CLOEXEC, O_CLOEXEC, O_NOCTTY,
+    O_NONBLOCK, POLLERR, POLLHUP, POLLIN, POLLNVAL, POLLOUT, POLLPRI, SEEK_CUR, SEEK_END, SEEK_SET,
+    TtyIndex,
 };
 
 use slopos_lib::kernel_services::driver_runtime::{
@@ -18,7 +21,10 @@ use slopos_lib::kernel_services::driver_runtime::{
 use slopos_lib::kernel_services::syscall_services::socket;
 use slopos_lib::kernel_services::syscall_services::tty;
 
-use crate::vfs::{FileSystem, InodeId, vfs_list, vfs_mkdir, vfs_open, vfs_stat, vfs_unlink};
+use crate::vfs::{
+    FileSystem, InodeId, MAY_READ, MAY_WRITE, current_credentials, vfs_list, vfs_mkdir_as,
+    vfs_open_as, vfs_stat, vfs_unlink_as,
+};
 
 #[allow(non_camel_case_types)]
 type ssize_t = isize;
@@ -31,6 +37,7 @@ use slopos_abi::task::INVALID_PROCESS_ID;
 use slopos_mm::memory_layout_defs::MAX_PROCESSES;
 
 use crate::MAX_PATH_LEN;
+use crate::devfs::{TTY0_INDEX, TTYS0_INDEX};
 
 const FILEIO_MAX_OPEN_FILES: usize = 32;
 const MAX_PIPES: usize = 64;
@@ -89,6 +96,19 @@ impl PipeState {
 
 static PIPE_STATE: IrqMutex<PipeState> = IrqMutex::new(PipeState::new());
 
+/// Socket references held by descriptors, keyed by net-stack socket index.
+/// dup and fork take another reference on the same handle; the socket is
+/// closed when the last descriptor holding it goes away.
+static SOCKET_HANDLES: IrqMutex<HandleTable<u32, MAX_SOCKETS>> =
+    IrqMutex::new(HandleTable::new(HandleKind::Socket));
+
+fn socket_idx_of(desc: &FileDescriptor) -> Option<u32> {
+    if desc.socket.is_null() {
+        return None;
+    }
+    SOCKET_HANDLES.lock().get(desc.socket).copied()
+}
+
 #[derive(Clone, Copy)]
 struct FileDescriptor {
     inode: InodeId,
@@ -101,7 +121,7 @@ struct FileDescriptor {
     /// When `None`, this is not a TTY descriptor.
     tty_index: Option<TtyIndex>,
     pipe_id: u32,
-    socket_idx: u32,
+    socket: Handle,
     pipe_read_end: bool,
     pipe_write_end: bool,
 }
@@ -117,7 +137,7 @@ impl FileDescriptor {
             cloexec: false,
             tty_index: None,
             pipe_id: INVALID_PIPE_ID,
-            socket_idx: INVALID_SOCKET_IDX,
+            socket: Handle::NULL,
             pipe_read_end: false,
             pipe_write_end: false,
         }
@@ -184,8 +204,11 @@ fn with_tables<R>(
 }
 
 fn reset_descriptor(desc: &mut FileDescriptor) {
-    if desc.valid && desc.socket_idx != INVALID_SOCKET_IDX {
-        let _ = socket::close(desc.socket_idx);
+    if desc.valid && !desc.socket.is_null() {
+        let last = SOCKET_HANDLES.lock().close(desc.socket);
+        if let Some(socket_idx) = last {
+            let _ = socket::close(socket_idx);
+        }
     }
 
     if desc.valid && desc.pipe_id != INVALID_PIPE_ID {
@@ -229,7 +252,7 @@ fn reset_descriptor(desc: &mut FileDescriptor) {
     desc.cloexec = false;
     desc.tty_index = None;
     desc.pipe_id = INVALID_PIPE_ID;
-    desc.socket_idx = INVALID_SOCKET_IDX;
+    desc.socket = Handle::NULL;
     desc.pipe_read_end = false;
     desc.pipe_write_end = false;
 }
@@ -385,6 +408,9 @@ fn pipe_wake_all_writers(slot: &mut PipeSlot) {
 
 fn clone_descriptor_for_dup(src: &FileDescriptor) -> Option<FileDescriptor> {
     let copy = *src;
+    if !copy.socket.is_null() {
+        SOCKET_HANDLES.lock().dup(copy.socket)?;
+    }
     if let Some(idx) = copy.tty_index {
         let _ = tty::open_ref(idx);
     }
@@ -526,6 +552,16 @@ fn parse_pts_path(path: &[u8]) -> Option<TtyIndex> {
     Some(TtyIndex(idx))
 }
 
+/// Fixed console nodes that bypass devfs so reads get the full TTY path
+/// (line discipline, job control, blocking).
+fn parse_console_tty_path(path: &[u8]) -> Option<TtyIndex> {
+    match path {
+        b"/dev/ttyS0" => Some(TTYS0_INDEX),
+        b"/dev/tty0" => Some(TTY0_INDEX),
+        _ => None,
+    }
+}
+
 /// Bootstrap FD 0 (stdin), 1 (stdout), 2 (stderr) as console descriptors.
 ///
 /// Console descriptors are valid file descriptors that route reads/writes
@@ -542,7 +578,7 @@ fn bootstrap_console_fds(table: &mut FileTableSlot) {
         cloexec: false,
         tty_index: Some(TtyIndex(0)),
         pipe_id: INVALID_PIPE_ID,
-        socket_idx: INVALID_SOCKET_IDX,
+        socket: Handle::NULL,
         pipe_read_end: false,
         pipe_write_end: false,
     };
@@ -556,7 +592,7 @@ fn bootstrap_console_fds(table: &mut FileTableSlot) {
         cloexec: false,
         tty_index: Some(TtyIndex(0)),
         pipe_id: INVALID_PIPE_ID,
-        socket_idx: INVALID_SOCKET_IDX,
+        socket: Handle::NULL,
         pipe_read_end: false,
         pipe_write_end: false,
     };
@@ -570,7 +606,7 @@ fn bootstrap_console_fds(table: &mut FileTableSlot) {
         cloexec: false,
         tty_index: Some(TtyIndex(0)),
         pipe_id: INVALID_PIPE_ID,
-        socket_idx: INVALID_SOCKET_IDX,
+        socket: Handle::NULL,
         pipe_read_end: false,
         pipe_write_end: false,
     };
@@ -680,6 +716,69 @@ pub fn fileio_clone_table_for_process(src_process_id: u32, dst_process_id: u32)
     })
 }
 
+/// Give `dst_process_id` the descriptors a freshly exec'd child of
+/// `src_process_id` should start with: a duplicate of the parent's table
+/// minus everything marked `FD_CLOEXEC`.  Replaces any table `dst` had.
+pub fn fileio_inherit_table_for_exec(src_process_id: u32, dst_process_id: u32) -> c_int {
+    fileio_destroy_table_for_process(dst_process_id);
+    if fileio_clone_table_for_process(src_process_id, dst_process_id) != 0 {
+        return -1;
+    }
+    fileio_close_on_exec(dst_process_id);
+    0
+}
+
+/// Install a descriptor routed to TTY `tty_idx` and take an open reference.
+fn open_tty_fd(process_id: u32, flags: u32, tty_idx: TtyIndex) -> c_int {
+    with_tables(|kernel, processes| {
+        let kernel_ptr = kernel as *mut FileTableSlot;
+        let table_ptr = if let Some(t) = table_for_pid(kernel, processes, process_id) {
+            t as *mut FileTableSlot
+        } else if let Some(t) = find_free_table(processes) {
+            t as *mut FileTableSlot
+        } else {
+            kernel_ptr
+        };
+        let table: &mut FileTableSlot = unsafe { &mut *table_ptr };
+
+        if !table.in_use {
+            table.in_use = true;
+            table.process_id = process_id;
+            reset_table(table);
+        }
+
+        let table_ptr: *mut FileTableSlot = table;
+        let guard = unsafe { (&(*table_ptr).lock).lock() };
+
+        let Some(slot_idx) = find_free_slot(table) else {
+            drop(guard);
+            return -1;
+        };
+
+        let desc = unsafe { &mut (*table_ptr).descriptors[slot_idx] };
+        desc.inode = 0;
+        desc.fs = None;
+        desc.flags = flags;
+        desc.position = 0;
+        desc.valid = true;
+        desc.cloexec = (flags & O_CLOEXEC as u32) != 0;
+        desc.tty_index = Some(tty_idx);
+        desc.pipe_id = INVALID_PIPE_ID;
+        desc.socket = Handle::NULL;
+        desc.pipe_read_end = false;
+        desc.pipe_write_end = false;
+
+        if tty::open_ref(tty_idx) < 0 {
+            reset_descriptor(desc);
+            drop(guard);
+            return -1;
+        }
+
+        drop(guard);
+        slot_idx as c_int
+    })
+}
+
 pub fn file_open_for_process(process_id: u32, path: *const c_char, flags: u32) -> c_int {
     if path.is_null() || (flags & (FILE_OPEN_READ | FILE_OPEN_WRITE)) == 0 {
         return -1;
@@ -694,58 +793,18 @@ pub fn file_open_for_process(process_id: u32, path: *const c_char, flags: u32) -
     };
 
     if path_bytes == b"/dev/tty" {
-        return with_tables(|kernel, processes| {
-            let tty_idx = match current_task_controlling_tty() {
-                Some(idx) => idx,
-                None => return -6,
-            };
-
-            let kernel_ptr = kernel as *mut FileTableSlot;
-            let table_ptr = if let Some(t) = table_for_pid(kernel, processes, process_id) {
-                t as *mut FileTableSlot
-            } else if let Some(t) = find_free_table(processes) {
-                t as *mut FileTableSlot
-            } else {
-                kernel_ptr
-            };
-            let table: &mut FileTableSlot = unsafe { &mut *table_ptr };
-
-            if !table.in_use {
-                table.in_use = true;
-                table.process_id = process_id;
-                reset_table(table);
-            }
-
-            let table_ptr: *mut FileTableSlot = table;
-            let guard = unsafe { (&(*table_ptr).lock).lock() };
-
-            let Some(slot_idx) = find_free_slot(table) else {
-                drop(guard);
-                return -1;
-            };
-
-            let desc = unsafe { &mut (*table_ptr).descriptors[slot_idx] };
-            desc.inode = 0;
-            desc.fs = None;
-            desc.flags = flags;
-            desc.position = 0;
-            desc.valid = true;
-            desc.cloexec = (flags & O_CLOEXEC as u32) != 0;
-            desc.tty_index = Some(tty_idx);
-            desc.pipe_id = INVALID_PIPE_ID;
-            desc.socket_idx = INVALID_SOCKET_IDX;
-            desc.pipe_read_end = false;
-            desc.pipe_write_end = false;
-
-            if tty::open_ref(tty_idx) < 0 {
-                reset_descriptor(desc);
-                drop(guard);
-                return -1;
-            }
+        let Some(tty_idx) = current_task_controlling_tty() else {
+            return -6;
+        };
+        return open_tty_fd(process_id, flags, tty_idx);
+    }
 
-            drop(guard);
-            slot_idx as c_int
-        });
+    if let Some(tty_idx) = parse_console_tty_path(path_bytes) {
+        let fd = open_tty_fd(process_id, flags, tty_idx);
+        if fd >= 0 {
+            maybe_acquire_controlling_tty_on_open(tty_idx, flags);
+        }
+        return fd;
     }
 
     if path_bytes == b"/dev/ptmx" {
@@ -789,7 +848,7 @@ pub fn file_open_for_process(process_id: u32, path: *const c_char, flags: u32) -
             desc.cloexec = (flags & O_CLOEXEC as u32) != 0;
             desc.tty_index = Some(master_idx);
             desc.pipe_id = INVALID_PIPE_ID;
-            desc.socket_idx = INVALID_SOCKET_IDX;
+            desc.socket = Handle::NULL;
             desc.pipe_read_end = false;
             desc.pipe_write_end = false;
 
@@ -809,59 +868,23 @@ pub fn file_open_for_process(process_id: u32, path: *const c_char, flags: u32) -
             return -1;
         }
 
-        return with_tables(|kernel, processes| {
-            let kernel_ptr = kernel as *mut FileTableSlot;
-            let table_ptr = if let Some(t) = table_for_pid(kernel, processes, process_id) {
-                t as *mut FileTableSlot
-            } else if let Some(t) = find_free_table(processes) {
-                t as *mut FileTableSlot
-            } else {
-                kernel_ptr
-            };
-            let table: &mut FileTableSlot = unsafe { &mut *table_ptr };
-
-            if !table.in_use {
-                table.in_use = true;
-                table.process_id = process_id;
-                reset_table(table);
-            }
-
-            let table_ptr: *mut FileTableSlot = table;
-            let guard = unsafe { (&(*table_ptr).lock).lock() };
-
-            let Some(slot_idx) = find_free_slot(table) else {
-                drop(guard);
-                return -1;
-            };
-
-            let desc = unsafe { &mut (*table_ptr).descriptors[slot_idx] };
-            desc.inode = 0;
-            desc.fs = None;
-            desc.flags = flags;
-            desc.position = 0;
-            desc.valid = true;
-            desc.cloexec = (flags & O_CLOEXEC as u32) != 0;
-            desc.tty_index = Some(slave_idx);
-            desc.pipe_id = INVALID_PIPE_ID;
-            desc.socket_idx = INVALID_SOCKET_IDX;
-            desc.pipe_read_end = false;
-            desc.pipe_write_end = false;
-
-            if tty::open_ref(slave_idx) < 0 {
-                reset_descriptor(desc);
-                drop(guard);
-                return -1;
-            }
-
-            drop(guard);
+        let fd = open_tty_fd(process_id, flags, slave_idx);
+        if fd >= 0 {
             maybe_acquire_controlling_tty_on_open(slave_idx, flags);
-            slot_idx as c_int
-        });
+        }
+        return fd;
     }
 
     let create = (flags & USER_FS_OPEN_CREAT) != 0;
+    let mut want = 0;
+    if (flags & FILE_OPEN_READ) != 0 {
+        want |= MAY_READ;
+    }
+    if (flags & FILE_OPEN_WRITE) != 0 {
+        want |= MAY_WRITE;
+    }
 
-    let handle = match vfs_open(path_bytes, create) {
+    let handle = match vfs_open_as(path_bytes, create, want, &current_credentials()) {
         Ok(h) => h,
         Err(_) => return -1,
     };
@@ -913,7 +936,7 @@ pub fn file_open_for_process(process_id: u32, path: *const c_char, flags: u32) -
         desc.cloexec = (flags & O_CLOEXEC as u32) != 0;
         desc.tty_index = None;
         desc.pipe_id = INVALID_PIPE_ID;
-        desc.socket_idx = INVALID_SOCKET_IDX;
+        desc.socket = Handle::NULL;
         desc.pipe_read_end = false;
         desc.pipe_write_end = false;
 
@@ -955,7 +978,7 @@ pub fn file_read_fd(process_id: u32, fd: c_int, buffer: *mut c_char, count: usiz
                 return None; // writing end, can't read
             }
             Some((pipe_id, is_nonblock))
-        } else if desc.socket_idx != INVALID_SOCKET_IDX {
+        } else if !desc.socket.is_null() {
             Some((INVALID_PIPE_ID, is_nonblock))
         } else {
             // Signal "not a pipe" -- fall through to normal path below
@@ -989,8 +1012,7 @@ pub fn file_read_fd(process_id: u32, fd: c_int, buffer: *mut c_char, count: usiz
                 return tty::read_cooked(tty_idx, buffer as *mut u8, count, is_nonblock);
             }
 
-            if desc.socket_idx != INVALID_SOCKET_IDX {
-                let socket_idx = desc.socket_idx;
+            if let Some(socket_idx) = socket_idx_of(desc) {
                 drop(guard);
                 return socket::recv(socket_idx, buffer as *mut u8, count) as ssize_t;
             }
@@ -1126,7 +1148,7 @@ pub fn file_write_fd(process_id: u32, fd: c_int, buffer: *const c_char, count: u
                 return None;
             }
             Some((pipe_id, is_nonblock))
-        } else if desc.socket_idx != INVALID_SOCKET_IDX {
+        } else if !desc.socket.is_null() {
             Some((INVALID_PIPE_ID, is_nonblock))
         } else {
             Some((INVALID_PIPE_ID, false))
@@ -1160,8 +1182,7 @@ pub fn file_write_fd(process_id: u32, fd: c_int, buffer: *const c_char, count: u
                 return written as ssize_t;
             }
 
-            if desc.socket_idx != INVALID_SOCKET_IDX {
-                let socket_idx = desc.socket_idx;
+            if let Some(socket_idx) = socket_idx_of(desc) {
                 drop(guard);
                 return socket::send(socket_idx, buffer as *const u8, count) as ssize_t;
             }
@@ -1268,10 +1289,6 @@ pub fn file_close_fd(process_id: u32, fd: c_int) -> c_int {
             drop(guard);
             return -1;
         };
-        if desc.socket_idx != INVALID_SOCKET_IDX {
-            let _ = socket::close(desc.socket_idx);
-            desc.socket_idx = INVALID_SOCKET_IDX;
-        }
         reset_descriptor(desc);
         drop(guard);
         0
@@ -1391,7 +1408,7 @@ pub fn file_unlink_path(path: *const c_char) -> c_int {
         Some(p) => p,
         None => return -1,
     };
-    if vfs_unlink(path_bytes).is_ok() {
+    if vfs_unlink_as(path_bytes, &current_credentials()).is_ok() {
         0
     } else {
         -1
@@ -1406,7 +1423,11 @@ pub fn file_mkdir_path(path: *const c_char) -> c_int {
         Some(p) => p,
         None => return -1,
     };
-    if vfs_mkdir(path_bytes).is_ok() { 0 } else { -1 }
+    if vfs_mkdir_as(path_bytes, &current_credentials()).is_ok() {
+        0
+    } else {
+        -1
+    }
 }
 
 pub fn file_stat_path(path: *const c_char, out_type: &mut u8, out_size: &mut u32) -> c_int {
@@ -1486,6 +1507,73 @@ pub fn file_get_tty_index(process_id: u32, fd: c_int) -> Option<TtyIndex> {
     })
 }
 
+/// Forward a non-TTY `ioctl` to the filesystem backing `fd`.
+///
+/// Returns the filesystem's result value, or -1 if the descriptor is invalid
+/// or the filesystem rejects the request.
+pub fn file_ioctl_fd(process_id: u32, fd: c_int, cmd: u64, arg: u64) -> i64 {
+    let target = with_tables(|kernel, processes| {
+        let table = table_for_pid(kernel, processes, process_id)?;
+        if !table.in_use {
+            return None;
+        }
+        let table_ptr: *mut FileTableSlot = table;
+        let guard = unsafe { (&(*table_ptr).lock).lock() };
+        let target = unsafe { get_descriptor(&mut *table_ptr, fd) }
+            .and_then(|d| d.fs.map(|fs| (fs, d.inode)));
+        drop(guard);
+        target
+    });
+
+    let Some((fs, inode)) = target else {
+        return -1;
+    };
+    match fs.ioctl(inode, cmd, arg) {
+        Ok(value) => value as i64,
+        Err(_) => -1,
+    }
+}
+
+/// Allocate a pty pair and open both ends for `process_id`.  Neither end
+/// becomes the controlling terminal; a session leader that wants the slave
+/// as one asks with `TIOCSCTTY`.
+pub fn file_openpty(
+    process_id: u32,
+    flags: u32,
+    out_master_fd: &mut c_int,
+    out_slave_fd: &mut c_int,
+) -> c_int {
+    if flags & !(O_NONBLOCK as u32 | O_CLOEXEC as u32) != 0 {
+        return -1;
+    }
+
+    let master_idx_raw = tty::alloc_pty();
+    if master_idx_raw < 0 {
+        return -1;
+    }
+    let master_idx = TtyIndex(master_idx_raw as u8);
+    let slave_idx_raw = tty::get_pty_number(master_idx);
+    if slave_idx_raw < 0 {
+        return -1;
+    }
+
+    let open_flags = FILE_OPEN_READ | FILE_OPEN_WRITE | O_NOCTTY as u32 | flags;
+    let master_fd = open_tty_fd(process_id, open_flags, master_idx);
+    if master_fd < 0 {
+        return -1;
+    }
+    let slave_fd = open_tty_fd(process_id, open_flags, TtyIndex(slave_idx_raw as u8));
+    if slave_fd < 0 {
+        // Closing the only open end frees the pair.
+        file_close_fd(process_id, master_fd);
+        return -1;
+    }
+
+    *out_master_fd = master_fd;
+    *out_slave_fd = slave_fd;
+    0
+}
+
 pub fn file_pipe_create(
     process_id: u32,
     flags: u32,
@@ -1543,7 +1631,7 @@ pub fn file_pipe_create(
             cloexec,
             tty_index: None,
             pipe_id,
-            socket_idx: INVALID_SOCKET_IDX,
+            socket: Handle::NULL,
             pipe_read_end: true,
             pipe_write_end: false,
         };
@@ -1557,7 +1645,7 @@ pub fn file_pipe_create(
             cloexec,
             tty_index: None,
             pipe_id,
-            socket_idx: INVALID_SOCKET_IDX,
+            socket: Handle::NULL,
             pipe_read_end: false,
             pipe_write_end: true,
         };
@@ -1616,8 +1704,7 @@ pub fn file_poll_fd(process_id: u32, fd: c_int, events: u16) -> u16 {
             return revents;
         }
 
-        if desc.socket_idx != INVALID_SOCKET_IDX {
-            let socket_idx = desc.socket_idx;
+        if let Some(socket_idx) = socket_idx_of(desc) {
             let readable = socket::poll_readable(socket_idx) as u16;
             let writable = socket::poll_writable(socket_idx) as u16;
             let mut revents = 0u16;
@@ -1671,12 +1758,12 @@ pub fn file_poll_fd(process_id: u32, fd: c_int, events: u16) -> u16 {
 /// Duplicate a file descriptor to the lowest available fd.
 /// Returns the new fd on success, -1 on error.
 pub fn file_dup_fd(process_id: u32, old_fd: c_int) -> c_int {
-    file_dup_fd_min(process_id, old_fd, 0)
+    file_dup_fd_min(process_id, old_fd, 0, false)
 }
 
 /// Duplicate a file descriptor to the lowest available fd >= min_fd.
-/// Used by both dup() (min_fd=0) and fcntl F_DUPFD.
-fn file_dup_fd_min(process_id: u32, old_fd: c_int, min_fd: usize) -> c_int {
+/// Used by both dup() (min_fd=0) and fcntl F_DUPFD / F_DUPFD_CLOEXEC.
+fn file_dup_fd_min(process_id: u32, old_fd: c_int, min_fd: usize, cloexec: bool) -> c_int {
     with_tables(|kernel, processes| {
         let Some(table) = table_for_pid(kernel, processes, process_id) else {
             return -1;
@@ -1687,6 +1774,12 @@ fn file_dup_fd_min(process_id: u32, old_fd: c_int, min_fd: usize) -> c_int {
         let table_ptr: *mut FileTableSlot = table;
         let guard = unsafe { (&(*table_ptr).lock).lock() };
 
+        // Pick the slot first: a copy taken before failing here would leak
+        // the pipe/socket/TTY references it holds.
+        let Some(new_idx) = find_free_slot_from(unsafe { &*table_ptr }, min_fd) else {
+            drop(guard);
+            return -1;
+        };
         let src = unsafe { get_descriptor(&mut *table_ptr, old_fd) };
         let Some(src) = src else {
             drop(guard);
@@ -1698,14 +1791,9 @@ fn file_dup_fd_min(process_id: u32, old_fd: c_int, min_fd: usize) -> c_int {
         };
 
         let table = unsafe { &mut *table_ptr };
-        let Some(new_idx) = find_free_slot_from(table, min_fd) else {
-            drop(guard);
-            return -1;
-        };
-
         table.descriptors[new_idx] = copy;
-        // dup() clears FD_CLOEXEC on the new descriptor
-        table.descriptors[new_idx].cloexec = false;
+        // dup() clears FD_CLOEXEC on the new descriptor; F_DUPFD_CLOEXEC sets it
+        table.descriptors[new_idx].cloexec = cloexec;
         drop(guard);
         new_idx as c_int
     })
@@ -1816,6 +1904,7 @@ pub fn file_dup3_fd(process_id: u32, old_fd: c_int, new_fd: c_int, flags: u32) -
 ///
 /// Supported commands:
 /// - F_DUPFD: duplicate fd to lowest available >= arg
+/// - F_DUPFD_CLOEXEC: same, with FD_CLOEXEC set on the new fd
 /// - F_GETFD: get FD_CLOEXEC flag
 /// - F_SETFD: set FD_CLOEXEC flag
 /// - F_GETFL: get file status flags (open mode)
@@ -1824,7 +1913,8 @@ pub fn file_dup3_fd(process_id: u32, old_fd: c_int, new_fd: c_int, flags: u32) -
 /// Returns command-specific value on success, -1 on error.
 pub fn file_fcntl_fd(process_id: u32, fd: c_int, cmd: u64, arg: u64) -> i64 {
     match cmd {
-        F_DUPFD => file_dup_fd_min(process_id, fd, arg as usize) as i64,
+        F_DUPFD => file_dup_fd_min(process_id, fd, arg as usize, false) as i64,
+        F_DUPFD_CLOEXEC => file_dup_fd_min(process_id, fd, arg as usize, true) as i64,
         F_GETFD => with_tables(|kernel, processes| {
             let Some(table) = table_for_pid(kernel, processes, process_id) else {
                 return -1i64;
@@ -1896,8 +1986,8 @@ pub fn file_fcntl_fd(process_id: u32, fd: c_int, cmd: u64, arg: u64) -> i64 {
                 next_flags |= O_NONBLOCK as u32;
             }
             desc.flags = next_flags;
-            if desc.socket_idx != INVALID_SOCKET_IDX {
-                let _ = socket::set_nonblocking(desc.socket_idx, (arg & O_NONBLOCK) != 0);
+            if let Some(socket_idx) = socket_idx_of(desc) {
+                let _ = socket::set_nonblocking(socket_idx, (arg & O_NONBLOCK) != 0);
             }
             drop(guard);
             0
@@ -2005,6 +2095,10 @@ pub fn fileio_open_socket_fd(process_id: u32, socket_idx: u32) -> i32 {
             drop(guard);
             return -1;
         };
+        let Ok(handle) = SOCKET_HANDLES.lock().insert(socket_idx) else {
+            drop(guard);
+            return -1;
+        };
 
         table.descriptors[slot_idx] = FileDescriptor {
             inode: 0,
@@ -2015,7 +2109,7 @@ pub fn fileio_open_socket_fd(process_id: u32, socket_idx: u32) -> i32 {
             cloexec: false,
             tty_index: None,
             pipe_id: INVALID_PIPE_ID,
-            socket_idx,
+            socket: handle,
             pipe_read_end: false,
             pipe_write_end: false,
         };
@@ -2033,9 +2127,7 @@ pub fn fileio_get_socket_idx(process_id: u32, fd: i32) -> Option<u32> {
         }
         let table_ptr: *mut FileTableSlot = table;
         let guard = unsafe { (&(*table_ptr).lock).lock() };
-        let out = unsafe { get_descriptor(&mut *table_ptr, fd) }
-            .map(|d| d.socket_idx)
-            .filter(|idx| *idx != INVALID_SOCKET_IDX);
+        let out = unsafe { get_descriptor(&mut *table_ptr, fd) }.and_then(|d| socket_idx_of(d));
         drop(guard);
         out
     })
@@ -8,6 +8,7 @@ pub mod devfs;
 pub mod ext2;
 pub mod ext2_vfs;
 pub mod fileio;
+pub mod procfs;
 pub mod ramfs;
 pub mod vfs;
 
@@ -19,8 +20,9 @@ extern crate std;
 pub use blockdev::*;
 pub use devfs::DevFs;
 pub use ext2::*;
-pub use ext2_vfs::{ext2_vfs_init_with_callbacks, ext2_vfs_is_initialized};
+pub use ext2_vfs::{ext2_vfs_init_with_callbacks, ext2_vfs_is_initialized, ext2_vfs_map_file};
 pub use fileio::*;
+pub use procfs::ProcFs;
 pub use ramfs::RamFs;
 pub use vfs::{
     FileStat, FileSystem, FileType, InodeId, VfsError, VfsResult, mount,
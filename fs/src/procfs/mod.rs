@@ -0,0 +1,433 @@
+//! Kernel information filesystem mounted at `/proc`.
+//!
+//! Every file is backed by a generator that renders its contents into a
+//! small text buffer on each read, so values are always current and nothing
+//! is cached between calls. A file longer than the buffer is rendered again
+//! for every read, keeping only the window at the read offset. The tree is a
+//! static table; add a generator and an entry to expose a new counter.
+
+use core::fmt::{self, Write};
+
+use crate::vfs::{FileStat, FileSystem, FileType, InodeId, VfsError, VfsResult};
+use slopos_abi::net::{NET_SOCK_PROTO_TCP, NET_SOCK_PROTO_UDP, net_sock_state_name};
+use slopos_lib::kernel_services::platform;
+use slopos_lib::kernel_services::syscall_services::net;
+use slopos_mm::memmap::{memmap_for_each, memmap_hhdm_extent, memmap_kernel_image};
+
+const ROOT_INODE: InodeId = 1;
+const SYS_DIR_INODE: InodeId = 2;
+const SYS_KERNEL_DIR_INODE: InodeId = 3;
+const RANDOM_DIR_INODE: InodeId = 4;
+const ENTROPY_AVAIL_INODE: InodeId = 5;
+const POOLSIZE_INODE: InodeId = 6;
+const RANDOM_STATS_INODE: InodeId = 7;
+const MEMMAP_INODE: InodeId = 8;
+const NET_DIR_INODE: InodeId = 9;
+const NET_SOCKETS_INODE: InodeId = 10;
+const STAT_INODE: InodeId = 11;
+
+/// Bytes kept per render; the most a single read returns.
+const PROC_BUF_SIZE: usize = 512;
+
+/// Fixed-size text sink handed to generators.
+struct ProcWriter {
+    buf: [u8; PROC_BUF_SIZE],
+    len: usize,
+    /// Rendered bytes to drop before filling `buf`.
+    skip: usize,
+    /// Everything the generator produced, kept or not.
+    total: usize,
+}
+
+impl ProcWriter {
+    const fn new(skip: usize) -> Self {
+        Self {
+            buf: [0; PROC_BUF_SIZE],
+            len: 0,
+            skip,
+            total: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl Write for ProcWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let bytes = s.as_bytes();
+        let start = self.skip.saturating_sub(self.total).min(bytes.len());
+        self.total += bytes.len();
+        let take = (bytes.len() - start).min(PROC_BUF_SIZE - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&bytes[start..start + take]);
+        self.len += take;
+        Ok(())
+    }
+}
+
+type Generator = fn(&mut ProcWriter) -> fmt::Result;
+
+struct ProcEntry {
+    name: &'static [u8],
+    parent: InodeId,
+    inode: InodeId,
+    /// `None` for directories.
+    generate: Option<Generator>,
+}
+
+impl ProcEntry {
+    const fn dir(name: &'static [u8], parent: InodeId, inode: InodeId) -> Self {
+        Self {
+            name,
+            parent,
+            inode,
+            generate: None,
+        }
+    }
+
+    const fn file(
+        name: &'static [u8],
+        parent: InodeId,
+        inode: InodeId,
+        generate: Generator,
+    ) -> Self {
+        Self {
+            name,
+            parent,
+            inode,
+            generate: Some(generate),
+        }
+    }
+
+    fn file_type(&self) -> FileType {
+        if self.generate.is_some() {
+            FileType::Regular
+        } else {
+            FileType::Directory
+        }
+    }
+}
+
+fn gen_entropy_avail(w: &mut ProcWriter) -> fmt::Result {
+    writeln!(w, "{}", platform::rng_stats().entropy_avail)
+}
+
+fn gen_poolsize(w: &mut ProcWriter) -> fmt::Result {
+    writeln!(w, "{}", platform::rng_stats().poolsize)
+}
+
+fn gen_random_stats(w: &mut ProcWriter) -> fmt::Result {
+    let stats = platform::rng_stats();
+    writeln!(w, "seeded {}", stats.seeded as u8)?;
+    writeln!(w, "entropy_avail {}", stats.entropy_avail)?;
+    writeln!(w, "entropy_added {}", stats.entropy_added)?;
+    writeln!(w, "reseeds {}", stats.reseeds)?;
+    writeln!(w, "bytes_generated {}", stats.bytes_generated)?;
+    writeln!(w, "unseeded_reads {}", stats.unseeded_reads)?;
+    writeln!(w, "arch_samples {}", stats.arch_samples)
+}
+
+fn gen_memmap(w: &mut ProcWriter) -> fmt::Result {
+    writeln!(
+        w,
+        "{:<18} {:<18} {:>10} {:<18} label",
+        "start", "end", "size_kb", "type"
+    )?;
+    let mut result = Ok(());
+    memmap_for_each(|entry| {
+        if result.is_ok() {
+            result = writeln!(
+                w,
+                "0x{:016x} 0x{:016x} {:>10} {:<18} {}",
+                entry.phys_base,
+                entry.end() - 1,
+                entry.length / 1024,
+                entry.type_name,
+                entry.label()
+            );
+        }
+    });
+    result?;
+    if let Some((phys_start, phys_end, virt_start)) = memmap_kernel_image() {
+        writeln!(
+            w,
+            "kernel image: phys 0x{:x} - 0x{:x}, virt 0x{:x} - 0x{:x}",
+            phys_start,
+            phys_end - 1,
+            virt_start,
+            virt_start + (phys_end - phys_start) - 1
+        )?;
+    }
+    if let Some((start, end)) = memmap_hhdm_extent() {
+        writeln!(w, "hhdm: 0x{:x} - 0x{:x}", start, end - 1)?;
+    }
+    Ok(())
+}
+
+fn dec_len(mut value: u32) -> usize {
+    let mut len = 1;
+    while value >= 10 {
+        value /= 10;
+        len += 1;
+    }
+    len
+}
+
+/// `a.b.c.d:port` left-aligned in a column of `width`, then a space.
+fn write_endpoint(w: &mut ProcWriter, addr: [u8; 4], port: u16, width: usize) -> fmt::Result {
+    let len = addr.iter().map(|&b| dec_len(b as u32)).sum::<usize>() + dec_len(port as u32) + 4;
+    let [a, b, c, d] = addr;
+    write!(
+        w,
+        "{}.{}.{}.{}:{}{:pad$}",
+        a,
+        b,
+        c,
+        d,
+        port,
+        "",
+        pad = width.saturating_sub(len) + 1
+    )
+}
+
+fn gen_net_sockets(w: &mut ProcWriter) -> fmt::Result {
+    writeln!(
+        w,
+        "{:>3} {:<5} {:<21} {:<21} {:<12} {:>6} {:>6} {:>10} {:>10} {:>6}",
+        "sl", "proto", "local", "remote", "state", "rx_q", "tx_q", "rx_bytes", "tx_bytes", "drops"
+    )?;
+    if !net::is_net_initialized() {
+        return Ok(());
+    }
+    let mut next = 0;
+    while let Some(stat) = net::socket_stat(next) {
+        next = stat.index as usize + 1;
+        let proto = match stat.proto {
+            NET_SOCK_PROTO_TCP => "tcp",
+            NET_SOCK_PROTO_UDP => "udp",
+            _ => "raw",
+        };
+        write!(w, "{:>3} {:<5} ", stat.index, proto)?;
+        write_endpoint(w, stat.local_addr, stat.local_port, 21)?;
+        write_endpoint(w, stat.remote_addr, stat.remote_port, 21)?;
+        writeln!(
+            w,
+            "{:<12} {:>6} {:>6} {:>10} {:>10} {:>6}",
+            net_sock_state_name(stat.state),
+            stat.recv_queue,
+            stat.send_queue,
+            stat.rx_bytes,
+            stat.tx_bytes,
+            stat.rx_dropped
+        )?;
+    }
+    Ok(())
+}
+
+/// One row per CPU: scheduler cycles split into busy and idle, and the
+/// tick, switch and preemption counters behind them.
+fn gen_stat(w: &mut ProcWriter) -> fmt::Result {
+    writeln!(
+        w,
+        "{:<6} {:>16} {:>16} {:>10} {:>10} {:>10} {:>5}",
+        "cpu", "busy_cycles", "idle_cycles", "ticks", "switches", "preempts", "ready"
+    )?;
+    for cpu in 0..slopos_lib::get_cpu_count() {
+        let Some(usage) = platform::cpu_usage(cpu) else {
+            continue;
+        };
+        writeln!(
+            w,
+            "cpu{:<3} {:>16} {:>16} {:>10} {:>10} {:>10} {:>5}",
+            usage.cpu,
+            usage.busy_cycles,
+            usage.idle_cycles,
+            usage.ticks,
+            usage.switches,
+            usage.preemptions,
+            usage.ready
+        )?;
+    }
+    Ok(())
+}
+
+static ENTRIES: [ProcEntry; 10] = [
+    ProcEntry::dir(b"sys", ROOT_INODE, SYS_DIR_INODE),
+    ProcEntry::dir(b"kernel", SYS_DIR_INODE, SYS_KERNEL_DIR_INODE),
+    ProcEntry::dir(b"random", SYS_KERNEL_DIR_INODE, RANDOM_DIR_INODE),
+    ProcEntry::file(
+        b"entropy_avail",
+        RANDOM_DIR_INODE,
+        ENTROPY_AVAIL_INODE,
+        gen_entropy_avail,
+    ),
+    ProcEntry::file(b"poolsize", RANDOM_DIR_INODE, POOLSIZE_INODE, gen_poolsize),
+    ProcEntry::file(
+        b"stats",
+        RANDOM_DIR_INODE,
+        RANDOM_STATS_INODE,
+        gen_random_stats,
+    ),
+    ProcEntry::file(b"memmap", ROOT_INODE, MEMMAP_INODE, gen_memmap),
+    ProcEntry::file(b"stat", ROOT_INODE, STAT_INODE, gen_stat),
+    ProcEntry::dir(b"net", ROOT_INODE, NET_DIR_INODE),
+    ProcEntry::file(
+        b"sockets",
+        NET_DIR_INODE,
+        NET_SOCKETS_INODE,
+        gen_net_sockets,
+    ),
+];
+
+fn find_entry(inode: InodeId) -> Option<&'static ProcEntry> {
+    ENTRIES.iter().find(|entry| entry.inode == inode)
+}
+
+fn is_directory(inode: InodeId) -> bool {
+    inode == ROOT_INODE || find_entry(inode).is_some_and(|entry| entry.generate.is_none())
+}
+
+fn parent_of(inode: InodeId) -> InodeId {
+    find_entry(inode).map_or(ROOT_INODE, |entry| entry.parent)
+}
+
+/// Render `inode`, keeping the bytes from `offset` on.
+fn render(inode: InodeId, offset: usize) -> VfsResult<ProcWriter> {
+    if is_directory(inode) {
+        return Err(VfsError::IsDirectory);
+    }
+    let generate = find_entry(inode)
+        .and_then(|entry| entry.generate)
+        .ok_or(VfsError::NotFound)?;
+    let mut out = ProcWriter::new(offset);
+    // Overflow only truncates; a partial file beats an I/O error.
+    let _ = generate(&mut out);
+    Ok(out)
+}
+
+pub struct ProcFs;
+
+impl ProcFs {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for ProcFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for ProcFs {
+    fn name(&self) -> &'static str {
+        "procfs"
+    }
+
+    fn root_inode(&self) -> InodeId {
+        ROOT_INODE
+    }
+
+    fn lookup(&self, parent: InodeId, name: &[u8]) -> VfsResult<InodeId> {
+        if !is_directory(parent) {
+            return Err(VfsError::NotDirectory);
+        }
+
+        if name == b"." {
+            return Ok(parent);
+        }
+        if name == b".." {
+            return Ok(parent_of(parent));
+        }
+
+        ENTRIES
+            .iter()
+            .find(|entry| entry.parent == parent && entry.name == name)
+            .map(|entry| entry.inode)
+            .ok_or(VfsError::NotFound)
+    }
+
+    fn stat(&self, inode: InodeId) -> VfsResult<FileStat> {
+        if is_directory(inode) {
+            return Ok(FileStat::new_directory(inode));
+        }
+        let size = render(inode, 0)?.total as u64;
+        let mut stat = FileStat::new_file(inode, size);
+        stat.mode = 0o444;
+        Ok(stat)
+    }
+
+    fn read(&self, inode: InodeId, offset: u64, buf: &mut [u8]) -> VfsResult<usize> {
+        let out = render(inode, offset as usize)?;
+        let data = out.as_bytes();
+        let count = data.len().min(buf.len());
+        buf[..count].copy_from_slice(&data[..count]);
+        Ok(count)
+    }
+
+    fn write(&self, inode: InodeId, _offset: u64, _buf: &[u8]) -> VfsResult<usize> {
+        if is_directory(inode) {
+            return Err(VfsError::IsDirectory);
+        }
+        Err(VfsError::ReadOnly)
+    }
+
+    fn create(&self, _parent: InodeId, _name: &[u8], _file_type: FileType) -> VfsResult<InodeId> {
+        Err(VfsError::ReadOnly)
+    }
+
+    fn unlink(&self, _parent: InodeId, _name: &[u8]) -> VfsResult<()> {
+        Err(VfsError::ReadOnly)
+    }
+
+    fn readdir(
+        &self,
+        inode: InodeId,
+        offset: usize,
+        callback: &mut dyn FnMut(&[u8], InodeId, FileType) -> bool,
+    ) -> VfsResult<usize> {
+        if !is_directory(inode) {
+            return Err(VfsError::NotDirectory);
+        }
+
+        let mut count = 0;
+        let mut current = 0;
+
+        if current >= offset {
+            if !callback(b".", inode, FileType::Directory) {
+                return Ok(count);
+            }
+            count += 1;
+        }
+        current += 1;
+
+        if current >= offset {
+            if !callback(b"..", parent_of(inode), FileType::Directory) {
+                return Ok(count);
+            }
+            count += 1;
+        }
+        current += 1;
+
+        for entry in ENTRIES.iter().filter(|entry| entry.parent == inode) {
+            if current >= offset {
+                if !callback(entry.name, entry.inode, entry.file_type()) {
+                    return Ok(count);
+                }
+                count += 1;
+            }
+            current += 1;
+        }
+
+        Ok(count)
+    }
+
+    fn truncate(&self, _inode: InodeId, _size: u64) -> VfsResult<()> {
+        Err(VfsError::ReadOnly)
+    }
+
+    fn sync(&self) -> VfsResult<()> {
+        Ok(())
+    }
+}
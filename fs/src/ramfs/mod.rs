@@ -35,6 +35,8 @@ struct RamInode {
     parent: InodeId,
     mode: u16,
     nlink: u32,
+    uid: u32,
+    gid: u32,
 }
 
 impl RamInode {
@@ -49,6 +51,8 @@ impl RamInode {
             parent: 0,
             mode: 0o644,
             nlink: 1,
+            uid: 0,
+            gid: 0,
         }
     }
 
@@ -245,8 +249,8 @@ impl FileSystem for RamFs {
                 size: ram_inode.data_len as u64,
                 mode: ram_inode.mode,
                 nlink: ram_inode.nlink,
-                uid: 0,
-                gid: 0,
+                uid: ram_inode.uid,
+                gid: ram_inode.gid,
                 atime: 0,
                 mtime: 0,
                 ctime: 0,
@@ -426,6 +430,22 @@ impl FileSystem for RamFs {
         })
     }
 
+    fn set_mode(&self, inode: InodeId, mode: u16) -> VfsResult<()> {
+        self.with_inner_mut(|inner| {
+            inner.get_inode_mut(inode)?.mode = mode & 0o7777;
+            Ok(())
+        })
+    }
+
+    fn set_owner(&self, inode: InodeId, uid: u32, gid: u32) -> VfsResult<()> {
+        self.with_inner_mut(|inner| {
+            let ram_inode = inner.get_inode_mut(inode)?;
+            ram_inode.uid = uid;
+            ram_inode.gid = gid;
+            Ok(())
+        })
+    }
+
     fn rename(
         &self,
         old_parent: InodeId,
@@ -1,14 +1,18 @@
 use core::ptr;
 
 use slopos_abi::fs::UserFsEntry;
+use slopos_abi::task::Credentials;
 use slopos_lib::klog_info;
 use slopos_lib::testing::TestResult;
 
 use crate::blockdev::{BlockDevice, BlockDeviceError, MemoryBlockDevice};
+use crate::devfs::DevFs;
 use crate::ext2::{Ext2Error, Ext2Fs};
+use crate::procfs::ProcFs;
 use crate::vfs::{
-    vfs_init_builtin_filesystems, vfs_is_initialized, vfs_list, vfs_mkdir, vfs_open, vfs_stat,
-    vfs_unlink,
+    FileSystem, FileType, MAY_READ, MAY_WRITE, VfsError, vfs_chmod_as, vfs_chown_as,
+    vfs_init_builtin_filesystems, vfs_is_initialized, vfs_list, vfs_mkdir, vfs_open, vfs_open_as,
+    vfs_stat, vfs_unlink, vfs_unlink_as,
 };
 
 pub fn test_vfs_initialized() -> TestResult {
@@ -101,6 +105,197 @@ pub fn test_vfs_unlink() -> TestResult {
     TestResult::Pass
 }
 
+pub fn test_vfs_permission_checks() -> TestResult {
+    klog_info!("VFS_TEST: permission checks");
+    let alice = Credentials::new(1000, 1000);
+    let bob = Credentials::new(1001, 1001);
+    let path = b"/tmp/perm_test.txt";
+
+    if vfs_open_as(path, true, MAY_WRITE, &alice).is_err() {
+        return TestResult::Fail;
+    }
+    if vfs_chmod_as(path, 0o600, &bob) != Err(VfsError::NotPermitted)
+        || vfs_chmod_as(path, 0o600, &alice).is_err()
+    {
+        return TestResult::Fail;
+    }
+    if vfs_open_as(path, false, MAY_READ, &bob).err() != Some(VfsError::PermissionDenied)
+        || vfs_open_as(path, false, MAY_READ | MAY_WRITE, &alice).is_err()
+        || vfs_open_as(path, false, MAY_READ, &Credentials::ROOT).is_err()
+    {
+        return TestResult::Fail;
+    }
+
+    // Only root gives files away; /tmp is sticky, so only the owner removes.
+    if vfs_chown_as(path, Some(bob.uid), None, &alice) != Err(VfsError::NotPermitted)
+        || vfs_unlink_as(path, &bob).is_ok()
+        || vfs_unlink_as(path, &alice).is_err()
+    {
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+pub fn test_devfs_nested_input_directory() -> TestResult {
+    klog_info!("VFS_TEST: devfs /dev/input/event0");
+    let devfs = DevFs::new();
+    let root = devfs.root_inode();
+
+    let Ok(input) = devfs.lookup(root, b"input") else {
+        return TestResult::Fail;
+    };
+    match devfs.stat(input) {
+        Ok(stat) if stat.file_type == FileType::Directory => {}
+        _ => return TestResult::Fail,
+    }
+    if devfs.lookup(input, b"..") != Ok(root) {
+        return TestResult::Fail;
+    }
+
+    let Ok(event0) = devfs.lookup(input, b"event0") else {
+        return TestResult::Fail;
+    };
+    match devfs.stat(event0) {
+        Ok(stat) if stat.file_type == FileType::CharDevice && stat.dev_major == 13 => {}
+        _ => return TestResult::Fail,
+    }
+
+    // event0 lives under input/, not the devfs root.
+    if devfs.lookup(root, b"event0").is_ok() {
+        return TestResult::Fail;
+    }
+
+    let mut found = false;
+    let _ = devfs.readdir(input, 0, &mut |name, inode, _| {
+        found |= name == b"event0" && inode == event0;
+        true
+    });
+    if !found {
+        return TestResult::Fail;
+    }
+
+    // A short read must not hand out a torn InputEvent record.
+    let mut tiny = [0u8; 4];
+    if devfs.read(event0, 0, &mut tiny).is_ok() {
+        return TestResult::Fail;
+    }
+
+    for name in [b"fb0".as_slice(), b"ttyS0", b"tty0"] {
+        if devfs.lookup(root, name).is_err() {
+            return TestResult::Fail;
+        }
+    }
+    TestResult::Pass
+}
+
+pub fn test_devfs_private_device_modes() -> TestResult {
+    klog_info!("VFS_TEST: devfs fb0/event0 are root-only");
+    let devfs = DevFs::new();
+    let root = devfs.root_inode();
+    let mode_of = |parent, name: &[u8]| {
+        devfs
+            .lookup(parent, name)
+            .and_then(|inode| devfs.stat(inode))
+            .map(|stat| (stat.mode, stat.uid))
+    };
+
+    let Ok(input) = devfs.lookup(root, b"input") else {
+        return TestResult::Fail;
+    };
+    if mode_of(root, b"fb0") != Ok((0o600, 0))
+        || mode_of(input, b"event0") != Ok((0o600, 0))
+        || mode_of(root, b"null") != Ok((0o666, 0))
+    {
+        return TestResult::Fail;
+    }
+
+    let user = Credentials::new(1000, 1000);
+    if vfs_open_as(b"/dev/fb0", false, MAY_READ, &user).err() != Some(VfsError::PermissionDenied)
+        || vfs_open_as(b"/dev/input/event0", false, MAY_READ, &user).err()
+            != Some(VfsError::PermissionDenied)
+        || vfs_open_as(b"/dev/null", false, MAY_READ | MAY_WRITE, &user).is_err()
+    {
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
+pub fn test_procfs_random_counters() -> TestResult {
+    klog_info!("VFS_TEST: procfs /proc/sys/kernel/random");
+    let procfs = ProcFs::new();
+    let mut inode = procfs.root_inode();
+    for name in [b"sys".as_slice(), b"kernel", b"random"] {
+        match procfs.lookup(inode, name) {
+            Ok(next) => inode = next,
+            Err(_) => return TestResult::Fail,
+        }
+    }
+
+    let Ok(poolsize) = procfs.lookup(inode, b"poolsize") else {
+        return TestResult::Fail;
+    };
+    let mut buf = [0u8; 16];
+    match procfs.read(poolsize, 0, &mut buf) {
+        Ok(n) if &buf[..n] == b"256\n" => {}
+        _ => return TestResult::Fail,
+    }
+    if procfs.write(poolsize, 0, b"1").is_ok() {
+        return TestResult::Fail;
+    }
+
+    // Reads past the rendered text are EOF, not an error.
+    let Ok(stats) = procfs.lookup(inode, b"stats") else {
+        return TestResult::Fail;
+    };
+    if procfs.read(stats, 4096, &mut buf) != Ok(0) {
+        return TestResult::Fail;
+    }
+
+    // Boot seeding runs long before the harness, so /dev/random must serve.
+    let devfs = DevFs::new();
+    let Ok(random) = devfs.lookup(devfs.root_inode(), b"random") else {
+        return TestResult::Fail;
+    };
+    let mut out = [0u8; 32];
+    match devfs.read(random, 0, &mut out) {
+        Ok(32) if out.iter().any(|&b| b != 0) => TestResult::Pass,
+        _ => TestResult::Fail,
+    }
+}
+
+pub fn test_procfs_memmap_reads_in_windows() -> TestResult {
+    klog_info!("VFS_TEST: procfs /proc/memmap");
+    let procfs = ProcFs::new();
+    let Ok(memmap) = procfs.lookup(procfs.root_inode(), b"memmap") else {
+        return TestResult::Fail;
+    };
+    let Ok(stat) = procfs.stat(memmap) else {
+        return TestResult::Fail;
+    };
+
+    // Small reads walk the whole file, past the 512-byte render buffer.
+    let mut buf = [0u8; 100];
+    let mut offset = 0u64;
+    let mut last = 0u8;
+    loop {
+        match procfs.read(memmap, offset, &mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                if offset == 0 && !buf.starts_with(b"start") {
+                    return TestResult::Fail;
+                }
+                last = buf[n - 1];
+                offset += n as u64;
+            }
+            Err(_) => return TestResult::Fail,
+        }
+    }
+    if offset != stat.size || last != b'\n' {
+        return TestResult::Fail;
+    }
+    TestResult::Pass
+}
+
 pub fn test_vfs_storage_contention_stress_baseline() -> TestResult {
     if vfs_mkdir(b"/vfs_stress").is_err() {
         return TestResult::Fail;
@@ -598,6 +793,11 @@ fn run_ext2_suite(_config: *const (), out: *mut slopos_lib::testing::TestSuiteRe
     slopos_lib::run_test!(passed, total, test_vfs_file_roundtrip);
     slopos_lib::run_test!(passed, total, test_vfs_list);
     slopos_lib::run_test!(passed, total, test_vfs_unlink);
+    slopos_lib::run_test!(passed, total, test_vfs_permission_checks);
+    slopos_lib::run_test!(passed, total, test_devfs_nested_input_directory);
+    slopos_lib::run_test!(passed, total, test_devfs_private_device_modes);
+    slopos_lib::run_test!(passed, total, test_procfs_random_counters);
+    slopos_lib::run_test!(passed, total, test_procfs_memmap_reads_in_windows);
     slopos_lib::run_test!(passed, total, test_vfs_storage_contention_stress_baseline);
     slopos_lib::run_test!(passed, total, test_ext2_invalid_superblock_magic);
     slopos_lib::run_test!(passed, total, test_ext2_unsupported_block_size);
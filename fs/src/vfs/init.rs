@@ -2,15 +2,17 @@ use slopos_lib::InitFlag;
 
 use crate::devfs::DevFs;
 use crate::ext2_vfs::{EXT2_VFS_STATIC, ext2_vfs_is_initialized};
+use crate::procfs::ProcFs;
 use crate::ramfs::RamFs;
-use crate::vfs::VfsResult;
 use crate::vfs::mount::mount;
+use crate::vfs::{FileSystem, VfsResult};
 
 static VFS_INIT: InitFlag = InitFlag::new();
 
 static RAMFS_ROOT_STATIC: RamFs = RamFs::new_const();
 static RAMFS_TMP_STATIC: RamFs = RamFs::new_const();
 static DEVFS_STATIC: DevFs = DevFs::new();
+static PROCFS_STATIC: ProcFs = ProcFs::new();
 
 pub fn vfs_init_builtin_filesystems() -> VfsResult<()> {
     if !VFS_INIT.init_once() {
@@ -23,8 +25,11 @@ pub fn vfs_init_builtin_filesystems() -> VfsResult<()> {
         mount(b"/", &RAMFS_ROOT_STATIC, 0)?;
     }
 
+    // World-writable and sticky, so any user can keep scratch files there.
+    RAMFS_TMP_STATIC.set_mode(RAMFS_TMP_STATIC.root_inode(), 0o1777)?;
     mount(b"/tmp", &RAMFS_TMP_STATIC, 0)?;
     mount(b"/dev", &DEVFS_STATIC, 0)?;
+    mount(b"/proc", &PROCFS_STATIC, 0)?;
 
     Ok(())
 }
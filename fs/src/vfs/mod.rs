@@ -2,10 +2,15 @@ pub mod init;
 pub mod mount;
 pub mod ops;
 pub mod path;
+pub mod perm;
 pub mod traits;
 
 pub use init::{vfs_init_builtin_filesystems, vfs_is_initialized};
 pub use mount::{mount, unmount, with_mount_table};
-pub use ops::{VfsHandle, vfs_list, vfs_mkdir, vfs_open, vfs_rename, vfs_stat, vfs_unlink};
+pub use ops::{
+    VfsHandle, vfs_chmod_as, vfs_chown_as, vfs_list, vfs_mkdir, vfs_mkdir_as, vfs_open,
+    vfs_open_as, vfs_rename, vfs_rename_as, vfs_stat, vfs_unlink, vfs_unlink_as,
+};
 pub use path::{ResolvedPath, resolve_parent, resolve_path};
+pub use perm::{MAY_EXEC, MAY_READ, MAY_WRITE, check_access, current_credentials};
 pub use traits::{FileStat, FileSystem, FileType, InodeId, VfsError, VfsResult};
@@ -1,5 +1,6 @@
 use crate::vfs::traits::{FileSystem, VfsError, VfsResult};
 use slopos_lib::IrqRwLock;
+use slopos_lib::lockstat::LockClass;
 
 use crate::MAX_PATH_LEN;
 
@@ -202,7 +203,9 @@ impl MountTable {
     }
 }
 
-static MOUNT_TABLE: IrqRwLock<MountTable> = IrqRwLock::new(MountTable::new());
+static MOUNT_TABLE_LOCK_CLASS: LockClass = LockClass::new("mount_table");
+static MOUNT_TABLE: IrqRwLock<MountTable> =
+    IrqRwLock::with_class(&MOUNT_TABLE_LOCK_CLASS, MountTable::new());
 
 pub fn mount(path: &[u8], fs: &'static dyn FileSystem, flags: u32) -> VfsResult<()> {
     MOUNT_TABLE.write().mount(path, fs, flags)
@@ -1,7 +1,12 @@
 use crate::vfs::mount::with_mount_table;
-use crate::vfs::path::{resolve_parent, resolve_path};
+use crate::vfs::path::{ResolvedPath, resolve_parent, resolve_path};
+use crate::vfs::perm::{MAY_EXEC, MAY_WRITE, MODE_PERM_MASK, check_access, check_owner};
 use crate::vfs::traits::{FileType, InodeId, VfsError, VfsResult};
 use slopos_abi::fs::{FS_TYPE_DIRECTORY, FS_TYPE_FILE, FS_TYPE_UNKNOWN, UserFsEntry};
+use slopos_abi::task::{Credentials, ROOT_GID, ROOT_UID};
+
+/// Directory sticky bit: only an entry's owner may remove it.
+const MODE_STICKY: u16 = 0o1000;
 
 pub struct VfsHandle {
     pub inode: InodeId,
@@ -29,12 +34,24 @@ impl VfsHandle {
 }
 
 pub fn vfs_open(path: &[u8], create: bool) -> VfsResult<VfsHandle> {
+    vfs_open_as(path, create, 0, &Credentials::ROOT)
+}
+
+/// Open `path` for the `MAY_*` access in `want` on behalf of `creds`.  A
+/// file created here belongs to the caller.
+pub fn vfs_open_as(
+    path: &[u8],
+    create: bool,
+    want: u16,
+    creds: &Credentials,
+) -> VfsResult<VfsHandle> {
     match resolve_path(path) {
         Ok(resolved) => {
             let stat = resolved.fs.stat(resolved.inode)?;
             if stat.file_type == FileType::Directory {
                 return Err(VfsError::IsDirectory);
             }
+            check_access(&stat, creds, want)?;
             Ok(VfsHandle {
                 inode: resolved.inode,
                 fs: resolved.fs,
@@ -42,7 +59,9 @@ pub fn vfs_open(path: &[u8], create: bool) -> VfsResult<VfsHandle> {
         }
         Err(VfsError::NotFound) if create => {
             let (parent, name) = resolve_parent(path)?;
+            check_parent_writable(&parent, creds)?;
             let new_inode = parent.fs.create(parent.inode, name, FileType::Regular)?;
+            give_to_creator(&parent, new_inode, creds);
             Ok(VfsHandle {
                 inode: new_inode,
                 fs: parent.fs,
@@ -52,6 +71,36 @@ pub fn vfs_open(path: &[u8], create: bool) -> VfsResult<VfsHandle> {
     }
 }
 
+fn check_parent_writable(parent: &ResolvedPath, creds: &Credentials) -> VfsResult<()> {
+    let stat = parent.fs.stat(parent.inode)?;
+    check_access(&stat, creds, MAY_WRITE | MAY_EXEC)
+}
+
+/// Removing or renaming `name` out of `parent` also needs ownership when
+/// the directory is sticky.
+fn check_may_remove(parent: &ResolvedPath, name: &[u8], creds: &Credentials) -> VfsResult<()> {
+    let dir = parent.fs.stat(parent.inode)?;
+    check_access(&dir, creds, MAY_WRITE | MAY_EXEC)?;
+    if dir.mode & MODE_STICKY == 0 || creds.is_root() || creds.euid == dir.uid {
+        return Ok(());
+    }
+    let target = parent.fs.lookup(parent.inode, name)?;
+    let stat = parent.fs.stat(target)?;
+    if creds.euid == stat.uid {
+        Ok(())
+    } else {
+        Err(VfsError::NotPermitted)
+    }
+}
+
+/// New inodes start out owned by root; hand them to anyone else.
+/// Filesystems without ownership keep their default.
+fn give_to_creator(parent: &ResolvedPath, inode: InodeId, creds: &Credentials) {
+    if creds.euid != ROOT_UID || creds.egid != ROOT_GID {
+        let _ = parent.fs.set_owner(inode, creds.euid, creds.egid);
+    }
+}
+
 pub fn vfs_stat(path: &[u8]) -> VfsResult<(u8, u32)> {
     let resolved = resolve_path(path)?;
     let stat = resolved.fs.stat(resolved.inode)?;
@@ -66,29 +115,74 @@ pub fn vfs_stat(path: &[u8]) -> VfsResult<(u8, u32)> {
 }
 
 pub fn vfs_mkdir(path: &[u8]) -> VfsResult<()> {
+    vfs_mkdir_as(path, &Credentials::ROOT)
+}
+
+pub fn vfs_mkdir_as(path: &[u8], creds: &Credentials) -> VfsResult<()> {
     let (parent, name) = resolve_parent(path)?;
-    parent.fs.create(parent.inode, name, FileType::Directory)?;
+    check_parent_writable(&parent, creds)?;
+    let inode = parent.fs.create(parent.inode, name, FileType::Directory)?;
+    give_to_creator(&parent, inode, creds);
     Ok(())
 }
 
 pub fn vfs_unlink(path: &[u8]) -> VfsResult<()> {
+    vfs_unlink_as(path, &Credentials::ROOT)
+}
+
+pub fn vfs_unlink_as(path: &[u8], creds: &Credentials) -> VfsResult<()> {
     let (parent, name) = resolve_parent(path)?;
+    check_may_remove(&parent, name, creds)?;
     parent.fs.unlink(parent.inode, name)
 }
 
 pub fn vfs_rename(old_path: &[u8], new_path: &[u8]) -> VfsResult<()> {
+    vfs_rename_as(old_path, new_path, &Credentials::ROOT)
+}
+
+pub fn vfs_rename_as(old_path: &[u8], new_path: &[u8], creds: &Credentials) -> VfsResult<()> {
     let (old_parent, old_name) = resolve_parent(old_path)?;
     let (new_parent, new_name) = resolve_parent(new_path)?;
 
     if !core::ptr::eq(old_parent.fs, new_parent.fs) {
         return Err(VfsError::CrossDevice);
     }
+    check_may_remove(&old_parent, old_name, creds)?;
+    check_parent_writable(&new_parent, creds)?;
 
     old_parent
         .fs
         .rename(old_parent.inode, old_name, new_parent.inode, new_name)
 }
 
+/// Set the permission bits of `path`; the owner or root only.
+pub fn vfs_chmod_as(path: &[u8], mode: u16, creds: &Credentials) -> VfsResult<()> {
+    let resolved = resolve_path(path)?;
+    let stat = resolved.fs.stat(resolved.inode)?;
+    check_owner(&stat, creds)?;
+    resolved.fs.set_mode(resolved.inode, mode & MODE_PERM_MASK)
+}
+
+/// Change the owner and group of `path`; root only.  `None` keeps the
+/// current value.
+pub fn vfs_chown_as(
+    path: &[u8],
+    uid: Option<u32>,
+    gid: Option<u32>,
+    creds: &Credentials,
+) -> VfsResult<()> {
+    if !creds.is_root() {
+        return Err(VfsError::NotPermitted);
+    }
+    let resolved = resolve_path(path)?;
+    let stat = resolved.fs.stat(resolved.inode)?;
+    resolved.fs.set_owner(
+        resolved.inode,
+        uid.unwrap_or(stat.uid),
+        gid.unwrap_or(stat.gid),
+    )
+}
+
 pub fn vfs_list(path: &[u8], entries: &mut [UserFsEntry]) -> VfsResult<usize> {
     let resolved = resolve_path(path)?;
     let stat = resolved.fs.stat(resolved.inode)?;
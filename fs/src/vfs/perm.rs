@@ -0,0 +1,72 @@
+//! Unix permission checks for path operations.
+//!
+//! The plain `vfs_*` entry points act with full rights for kernel callers;
+//! the `*_as` variants take the caller's credentials and refuse what the
+//! mode bits do not grant.  The effective uid 0 bypasses read and write
+//! checks, and may execute a file if any execute bit is set.
+
+use slopos_abi::task::Credentials;
+use slopos_lib::kernel_services::driver_runtime::{
+    current_task_creds, is_driver_runtime_initialized,
+};
+
+use crate::vfs::traits::{FileStat, FileType, VfsError, VfsResult};
+
+pub const MAY_EXEC: u16 = 0o1;
+pub const MAY_WRITE: u16 = 0o2;
+pub const MAY_READ: u16 = 0o4;
+
+/// Permission bits a mode may carry: setuid, setgid, sticky and rwxrwxrwx.
+pub const MODE_PERM_MASK: u16 = 0o7777;
+
+const MODE_ANY_EXEC: u16 = 0o111;
+
+/// Credentials of the task on whose behalf the kernel is running; root
+/// before the scheduler exists.
+pub fn current_credentials() -> Credentials {
+    if is_driver_runtime_initialized() {
+        current_task_creds()
+    } else {
+        Credentials::ROOT
+    }
+}
+
+/// Check that `creds` may access a file described by `stat` for every
+/// `MAY_*` bit in `want`.
+pub fn check_access(stat: &FileStat, creds: &Credentials, want: u16) -> VfsResult<()> {
+    if want == 0 {
+        return Ok(());
+    }
+    if creds.is_root() {
+        let exec_ok = want & MAY_EXEC == 0
+            || stat.file_type == FileType::Directory
+            || stat.mode & MODE_ANY_EXEC != 0;
+        return if exec_ok {
+            Ok(())
+        } else {
+            Err(VfsError::PermissionDenied)
+        };
+    }
+
+    let granted = if creds.euid == stat.uid {
+        (stat.mode >> 6) & 0o7
+    } else if creds.egid == stat.gid {
+        (stat.mode >> 3) & 0o7
+    } else {
+        stat.mode & 0o7
+    };
+    if granted & want == want {
+        Ok(())
+    } else {
+        Err(VfsError::PermissionDenied)
+    }
+}
+
+/// Whether `creds` may change the mode of a file owned by `stat.uid`.
+pub fn check_owner(stat: &FileStat, creds: &Credentials) -> VfsResult<()> {
+    if creds.is_root() || creds.euid == stat.uid {
+        Ok(())
+    } else {
+        Err(VfsError::NotPermitted)
+    }
+}
@@ -129,6 +129,8 @@ pub enum VfsError {
     IsDirectory,
     /// Permission denied (EACCES)
     PermissionDenied,
+    /// Operation not permitted for this caller (EPERM)
+    NotPermitted,
     /// Filesystem is read-only (EROFS)
     ReadOnly,
     /// No space left on device (ENOSPC)
@@ -271,6 +273,30 @@ pub trait FileSystem: Send + Sync {
         Err(VfsError::NotSupported)
     }
 
+    /// Replace the permission bits (`0o7777`) of an inode.
+    fn set_mode(&self, inode: InodeId, mode: u16) -> VfsResult<()> {
+        let _ = (inode, mode);
+        Err(VfsError::NotSupported)
+    }
+
+    /// Change the owner and group of an inode.
+    fn set_owner(&self, inode: InodeId, uid: u32, gid: u32) -> VfsResult<()> {
+        let _ = (inode, uid, gid);
+        Err(VfsError::NotSupported)
+    }
+
+    /// Device-specific control operation (`ioctl(2)` on a non-TTY descriptor).
+    ///
+    /// `arg` is passed through untouched; implementations that treat it as a
+    /// user pointer must copy through `slopos_mm::user_copy`.
+    ///
+    /// # Returns
+    /// The non-negative value to hand back to userspace.
+    fn ioctl(&self, inode: InodeId, cmd: u64, arg: u64) -> VfsResult<u64> {
+        let _ = (inode, cmd, arg);
+        Err(VfsError::NotSupported)
+    }
+
     /// Sync filesystem metadata and data to backing store.
     fn sync(&self) -> VfsResult<()> {
         // Default: no-op for in-memory filesystems
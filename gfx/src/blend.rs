@@ -0,0 +1,130 @@
+//! Alpha blending onto pixel bytes, with premultiplied alpha:
+//! `out = src * opacity + dst * (1 - src_alpha * opacity)`.
+
+use slopos_abi::damage::DamageRect;
+use slopos_abi::draw::Color32;
+use slopos_abi::pixel::PixelFormat;
+
+use crate::DrawBuffer;
+
+/// How source pixels combine with the pixels under them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Blend {
+    /// Opacity of the whole source, 0 (invisible) to 255.
+    pub opacity: u8,
+    /// Byte offset of the alpha channel within a source pixel, when the
+    /// source carries premultiplied per-pixel alpha.
+    pub alpha_byte: Option<usize>,
+}
+
+impl Blend {
+    /// Source pixels replace what is under them.
+    pub const OPAQUE: Self = Self {
+        opacity: 255,
+        alpha_byte: None,
+    };
+
+    /// Whether blending is a plain copy.
+    #[inline]
+    pub fn is_opaque(&self) -> bool {
+        self.opacity == 255 && self.alpha_byte.is_none()
+    }
+}
+
+/// Byte offset of the alpha channel in a pixel of `format`, if it has one.
+pub fn alpha_byte(format: PixelFormat) -> Option<usize> {
+    match format {
+        // 0xAARRGGBB little-endian: [B, G, R, A].
+        PixelFormat::Argb8888 => Some(3),
+        // Alpha in the low byte: [A, ..].
+        PixelFormat::Rgba8888 | PixelFormat::Bgra8888 => Some(0),
+        PixelFormat::Xrgb8888 | PixelFormat::Rgb888 | PixelFormat::Bgr888 => None,
+    }
+}
+
+/// `c * a / 255`, rounded.
+#[inline]
+fn mul(c: u8, a: u8) -> u32 {
+    let t = c as u32 * a as u32 + 128;
+    ((t >> 8) + t) >> 8
+}
+
+/// Blend the pixels of `src` onto `dst`, both `bytes_pp` bytes per pixel in
+/// the same format.  Stops at the end of the shorter slice.
+pub fn blend_span(dst: &mut [u8], src: &[u8], bytes_pp: usize, blend: Blend) {
+    if blend.is_opaque() {
+        let len = dst.len().min(src.len());
+        dst[..len].copy_from_slice(&src[..len]);
+        return;
+    }
+    let opacity = blend.opacity;
+    for (d, s) in dst
+        .chunks_exact_mut(bytes_pp)
+        .zip(src.chunks_exact(bytes_pp))
+    {
+        let src_alpha = blend
+            .alpha_byte
+            .and_then(|i| s.get(i).copied())
+            .unwrap_or(255);
+        let keep = 255 - mul(src_alpha, opacity) as u8;
+        for (d, &s) in d.iter_mut().zip(s) {
+            *d = (mul(s, opacity) + mul(*d, keep)).min(255) as u8;
+        }
+    }
+}
+
+/// Blend `color` over `under` at `alpha`.
+pub fn mix(under: Color32, color: Color32, alpha: u8) -> Color32 {
+    let keep = 255 - alpha;
+    let channel = |u: u8, c: u8| (mul(c, alpha) + mul(u, keep)).min(255) as u8;
+    Color32::rgb(
+        channel(under.red(), color.red()),
+        channel(under.green(), color.green()),
+        channel(under.blue(), color.blue()),
+    )
+}
+
+/// Fill a rectangle with `color`, blended onto what is there by the
+/// color's alpha, within `clip` and the buffer.
+pub fn fill_rect_blended_clipped(
+    buf: &mut DrawBuffer,
+    x: i32,
+    y: i32,
+    w: i32,
+    h: i32,
+    color: Color32,
+    clip: &DamageRect,
+) {
+    let x0 = x.max(clip.x0).max(0);
+    let y0 = y.max(clip.y0).max(0);
+    let x1 = (x + w - 1).min(clip.x1).min(buf.width() as i32 - 1);
+    let y1 = (y + h - 1).min(clip.y1).min(buf.height() as i32 - 1);
+    if x0 > x1 || y0 > y1 {
+        return;
+    }
+
+    let bytes_pp = buf.bytes_pp() as usize;
+    let pitch = buf.pitch();
+    let pixel = buf
+        .pixel_format()
+        .encode(color.with_alpha(0xFF))
+        .to_u32()
+        .to_le_bytes();
+    let pixel = &pixel[..bytes_pp];
+    let blend = Blend {
+        opacity: color.alpha(),
+        alpha_byte: None,
+    };
+    let data = buf.data_mut();
+    for row in y0..=y1 {
+        let start = row as usize * pitch + x0 as usize * bytes_pp;
+        let end = start + (x1 - x0 + 1) as usize * bytes_pp;
+        let Some(span) = data.get_mut(start..end) else {
+            break;
+        };
+        for dst in span.chunks_exact_mut(bytes_pp) {
+            blend_span(dst, pixel, bytes_pp, blend);
+        }
+    }
+    buf.add_damage(x0, y0, x1, y1);
+}
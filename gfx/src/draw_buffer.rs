@@ -260,6 +260,15 @@ impl Canvas for DrawBuffer<'_> {
         }
     }
 
+    #[inline]
+    fn read_encoded_at(&self, byte_offset: usize) -> Option<EncodedPixel> {
+        let bytes_pp = self.bytes_pp as usize;
+        let px = self.data.get(byte_offset..byte_offset + bytes_pp)?;
+        let mut bytes = [0u8; 4];
+        bytes[..bytes_pp].copy_from_slice(px);
+        Some(EncodedPixel(u32::from_le_bytes(bytes)))
+    }
+
     #[inline]
     fn fill_row_span(&mut self, row: i32, x0: i32, x1: i32, pixel: EncodedPixel) {
         let Some((row, x0, x1)) = self.clip_row_span(row, x0, x1) else {
@@ -0,0 +1,366 @@
+//! Bitmap fonts: PSF2 parsing, a registry queried by pixel size, and
+//! anti-aliased glyph drawing at any size.
+//!
+//! Fonts borrow their bytes, so whoever loads a font keeps the file
+//! contents alive; the built-in 8x16 font is always available.  Glyphs are
+//! drawn scaled into a box: each target pixel samples its share of the
+//! bitmap on a [`SAMPLES`]x[`SAMPLES`] grid and the coverage becomes the
+//! glyph's alpha there.  At the font's own size every sample agrees, so
+//! text stays as crisp as the bitmap.
+
+use slopos_abi::damage::DamageRect;
+use slopos_abi::draw::{Canvas, Color32};
+use slopos_abi::font::{FONT_CHAR_HEIGHT, FONT_CHAR_WIDTH, get_glyph_or_space};
+
+use crate::blend::mix;
+
+/// First four bytes of a PSF2 file.
+pub const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+const PSF2_HEADER_SIZE: usize = 32;
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+/// Ends each glyph's entry in the Unicode table.
+const PSF2_SEPARATOR: u8 = 0xFF;
+/// Starts the multi-codepoint sequences of an entry, which are skipped.
+const PSF2_START_SEQ: u8 = 0xFE;
+
+/// Samples per axis when scaling a glyph.
+const SAMPLES: u32 = 4;
+
+/// Fonts a [`FontRegistry`] holds, the built-in one included.
+pub const MAX_FONTS: usize = 8;
+
+const NO_GLYPH: u16 = u16::MAX;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FontError {
+    /// Shorter than its header says.
+    Truncated,
+    BadMagic,
+    /// Zero-sized glyphs, or glyphs larger than their stride.
+    BadHeader,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// A PSF2 bitmap font borrowed from its file contents.
+#[derive(Copy, Clone)]
+pub struct Psf2Font<'a> {
+    glyphs: &'a [u8],
+    unicode: Option<&'a [u8]>,
+    glyph_count: u32,
+    bytes_per_glyph: usize,
+    width: u32,
+    height: u32,
+    /// Glyph of each ASCII character, so common text skips the table.
+    ascii: [u16; 128],
+}
+
+impl<'a> Psf2Font<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, FontError> {
+        if data.len() < PSF2_HEADER_SIZE {
+            return Err(FontError::Truncated);
+        }
+        if data[..4] != PSF2_MAGIC {
+            return Err(FontError::BadMagic);
+        }
+        let header_size = read_u32(data, 8) as usize;
+        let flags = read_u32(data, 12);
+        let glyph_count = read_u32(data, 16);
+        let bytes_per_glyph = read_u32(data, 20) as usize;
+        let height = read_u32(data, 24);
+        let width = read_u32(data, 28);
+
+        let row_bytes = width.div_ceil(8) as usize;
+        if header_size < PSF2_HEADER_SIZE
+            || glyph_count == 0
+            || width == 0
+            || height == 0
+            || bytes_per_glyph < row_bytes * height as usize
+        {
+            return Err(FontError::BadHeader);
+        }
+        let glyphs_end = (glyph_count as usize)
+            .checked_mul(bytes_per_glyph)
+            .and_then(|len| len.checked_add(header_size))
+            .ok_or(FontError::BadHeader)?;
+        if data.len() < glyphs_end {
+            return Err(FontError::Truncated);
+        }
+
+        let mut font = Self {
+            glyphs: &data[header_size..glyphs_end],
+            unicode: (flags & PSF2_HAS_UNICODE_TABLE != 0).then(|| &data[glyphs_end..]),
+            glyph_count,
+            bytes_per_glyph,
+            width,
+            height,
+            ascii: [NO_GLYPH; 128],
+        };
+        for c in 0..128u8 {
+            if let Some(index) = font.lookup(c as char) {
+                font.ascii[c as usize] = index as u16;
+            }
+        }
+        Ok(font)
+    }
+
+    /// Glyph index for `ch`, through the Unicode table if there is one.
+    fn lookup(&self, ch: char) -> Option<u32> {
+        let Some(table) = self.unicode else {
+            return ((ch as u32) < self.glyph_count).then_some(ch as u32);
+        };
+        table
+            .split(|&b| b == PSF2_SEPARATOR)
+            .take(self.glyph_count as usize)
+            .position(|entry| {
+                let singles = entry.split(|&b| b == PSF2_START_SEQ).next().unwrap_or(&[]);
+                core::str::from_utf8(singles).is_ok_and(|s| s.contains(ch))
+            })
+            .map(|index| index as u32)
+    }
+
+    fn glyph_index(&self, ch: char) -> Option<u32> {
+        match self.ascii.get(ch as usize) {
+            Some(&NO_GLYPH) => None,
+            Some(&index) => Some(index as u32),
+            None => self.lookup(ch),
+        }
+    }
+
+    fn glyph(&self, ch: char) -> Glyph<'a> {
+        let index = self
+            .glyph_index(ch)
+            .or_else(|| self.glyph_index('?'))
+            .unwrap_or(0) as usize;
+        let start = index * self.bytes_per_glyph;
+        Glyph {
+            bits: &self.glyphs[start..start + self.bytes_per_glyph],
+            row_bytes: self.width.div_ceil(8) as usize,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// One glyph's bitmap, rows padded to whole bytes, MSB leftmost.
+#[derive(Copy, Clone)]
+struct Glyph<'a> {
+    bits: &'a [u8],
+    row_bytes: usize,
+    width: u32,
+    height: u32,
+}
+
+impl Glyph<'_> {
+    #[inline]
+    fn lit(&self, x: u32, y: u32) -> bool {
+        let byte = self.bits[y as usize * self.row_bytes + (x / 8) as usize];
+        byte & (0x80 >> (x % 8)) != 0
+    }
+
+    /// Share of the glyph's bitmap lit within target pixel (`tx`, `ty`)
+    /// of a `w`x`h` box, 0 to 255.
+    fn coverage(&self, tx: u32, ty: u32, w: u32, h: u32) -> u8 {
+        let mut lit = 0;
+        for sy in 0..SAMPLES {
+            let gy = ((ty * SAMPLES + sy) * 2 + 1) * self.height / (h * SAMPLES * 2);
+            for sx in 0..SAMPLES {
+                let gx = ((tx * SAMPLES + sx) * 2 + 1) * self.width / (w * SAMPLES * 2);
+                lit += self.lit(gx, gy) as u32;
+            }
+        }
+        (lit * 255 / (SAMPLES * SAMPLES)) as u8
+    }
+}
+
+#[derive(Copy, Clone)]
+pub enum Font<'a> {
+    /// The 8x16 font in [`slopos_abi::font`].
+    Builtin,
+    Psf2(Psf2Font<'a>),
+}
+
+impl<'a> Font<'a> {
+    /// Width of the font's bitmaps in pixels.
+    pub fn width(&self) -> u32 {
+        match self {
+            Self::Builtin => FONT_CHAR_WIDTH as u32,
+            Self::Psf2(font) => font.width,
+        }
+    }
+
+    /// Height of the font's bitmaps in pixels.
+    pub fn height(&self) -> u32 {
+        match self {
+            Self::Builtin => FONT_CHAR_HEIGHT as u32,
+            Self::Psf2(font) => font.height,
+        }
+    }
+
+    /// Horizontal advance of a glyph drawn `size` pixels tall.
+    pub fn advance(&self, size: u32) -> u32 {
+        ((self.width() * size + self.height() / 2) / self.height()).max(1)
+    }
+
+    fn glyph(&self, ch: char) -> Glyph<'a> {
+        match self {
+            Self::Builtin => Glyph {
+                bits: get_glyph_or_space(if ch.is_ascii() { ch as u8 } else { 0 }),
+                row_bytes: 1,
+                width: FONT_CHAR_WIDTH as u32,
+                height: FONT_CHAR_HEIGHT as u32,
+            },
+            Self::Psf2(font) => font.glyph(ch),
+        }
+    }
+}
+
+/// Loaded fonts, looked up by the pixel size text is wanted at.
+pub struct FontRegistry<'a> {
+    fonts: [Option<Font<'a>>; MAX_FONTS],
+}
+
+impl<'a> FontRegistry<'a> {
+    /// A registry holding just the built-in font.
+    pub const fn new() -> Self {
+        let mut fonts = [None; MAX_FONTS];
+        fonts[0] = Some(Font::Builtin);
+        Self { fonts }
+    }
+
+    /// Add `font`; false when the registry is full.
+    pub fn register(&mut self, font: Font<'a>) -> bool {
+        let Some(slot) = self.fonts.iter_mut().find(|slot| slot.is_none()) else {
+            return false;
+        };
+        *slot = Some(font);
+        true
+    }
+
+    /// The font to draw text `size` pixels tall with: the shortest font at
+    /// least that tall, since scaling down keeps more detail than scaling
+    /// up, else the tallest.  Fonts registered later win ties.
+    pub fn font_for_size(&self, size: u32) -> Font<'a> {
+        self.fonts
+            .iter()
+            .rev()
+            .flatten()
+            .min_by_key(|font| {
+                let height = font.height();
+                if height >= size {
+                    (0, height)
+                } else {
+                    (1, u32::MAX - height)
+                }
+            })
+            .copied()
+            .unwrap_or(Font::Builtin)
+    }
+
+    pub fn count(&self) -> usize {
+        self.fonts.iter().flatten().count()
+    }
+}
+
+impl Default for FontRegistry<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Draw `ch` scaled into the `w`x`h` box at (`x`, `y`), within `clip`.
+/// Partly covered pixels blend `fg` over `bg`, or over what is already
+/// there when `bg` is 0 and the target can be read back; otherwise they
+/// are drawn when at least half covered.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_glyph<T: Canvas>(
+    target: &mut T,
+    font: &Font,
+    ch: char,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    fg: Color32,
+    bg: Color32,
+    clip: &DamageRect,
+) {
+    if w == 0
+        || h == 0
+        || x > clip.x1
+        || y > clip.y1
+        || x + w as i32 - 1 < clip.x0
+        || y + h as i32 - 1 < clip.y0
+    {
+        return;
+    }
+    let fmt = target.pixel_format();
+    let fg_px = fmt.encode(fg);
+    let bg_px = fmt.encode(bg);
+    let has_bg = bg.0 != 0;
+    let glyph = font.glyph(ch);
+
+    for ty in 0..h {
+        let py = y + ty as i32;
+        if py < clip.y0 || py > clip.y1 {
+            continue;
+        }
+        for tx in 0..w {
+            let px = x + tx as i32;
+            if px < clip.x0 || px > clip.x1 {
+                continue;
+            }
+            let pixel = match glyph.coverage(tx, ty, w, h) {
+                0 if has_bg => bg_px,
+                0 => continue,
+                255 => fg_px,
+                alpha if has_bg => fmt.encode(mix(bg, fg, alpha)),
+                alpha => match target.get_pixel(px, py) {
+                    Some(under) => fmt.encode(mix(fmt.decode(under), fg, alpha)),
+                    None if alpha >= 128 => fg_px,
+                    None => continue,
+                },
+            };
+            target.put_pixel(px, py, pixel);
+        }
+    }
+}
+
+/// Draw a line of `text` `size` pixels tall at (`x`, `y`), within `clip`.
+/// Returns the x just past the text.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text<T: Canvas>(
+    target: &mut T,
+    font: &Font,
+    size: u32,
+    x: i32,
+    y: i32,
+    text: &str,
+    fg: Color32,
+    bg: Color32,
+    clip: &DamageRect,
+) -> i32 {
+    let advance = font.advance(size);
+    let mut cx = x;
+    for ch in text.chars() {
+        if ch == '\0' || ch == '\n' || cx > clip.x1 {
+            break;
+        }
+        draw_glyph(target, font, ch, cx, y, advance, size, fg, bg, clip);
+        cx += advance as i32;
+    }
+    cx
+}
+
+/// Width of a line of `text` drawn `size` pixels tall.
+pub fn text_width(font: &Font, size: u32, text: &str) -> i32 {
+    let glyphs = text.chars().take_while(|&ch| ch != '\0' && ch != '\n');
+    glyphs.count() as i32 * font.advance(size) as i32
+}
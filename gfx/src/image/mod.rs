@@ -0,0 +1,282 @@
+//! Image decoding (BMP, PPM and QOI) and scaling an image onto a canvas.
+//!
+//! Like fonts, a [`Bmp`] borrows the file contents and reads pixels out of
+//! them on demand, so nothing is allocated.  24- and 32-bit images are
+//! supported, stored bottom-up or top-down; 32-bit images may carry the
+//! usual BGRA bitfield masks.  [`Ppm`] works the same way; [`Qoi`] is
+//! compressed and decodes into a caller-provided buffer instead.
+
+mod ppm;
+mod qoi;
+
+pub use ppm::{PGM_MAGIC, PPM_MAGIC, Ppm};
+pub use qoi::{QOI_MAGIC, Qoi, RgbaImage};
+
+use slopos_abi::draw::{Canvas, Color32};
+
+/// First two bytes of a BMP file.
+pub const BMP_MAGIC: [u8; 2] = *b"BM";
+const BMP_FILE_HEADER_SIZE: usize = 14;
+/// The smallest DIB header, `BITMAPINFOHEADER`.
+const BMP_INFO_HEADER_SIZE: usize = 40;
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+/// Red, green and blue masks of a 32-bit BGRA image.
+const BGRA_MASKS: [u32; 3] = [0x00FF_0000, 0x0000_FF00, 0x0000_00FF];
+
+/// Images wider or taller than this are rejected.
+pub const MAX_IMAGE_DIM: u32 = 16384;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageError {
+    /// Shorter than its headers say.
+    Truncated,
+    BadMagic,
+    /// Compressed, palettized, or a size of zero or over [`MAX_IMAGE_DIM`].
+    Unsupported,
+    /// The output buffer given to [`Qoi::decode_into`] is too small.
+    BufferTooSmall,
+}
+
+/// File formats [`ImageFormat::detect`] recognises.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Bmp,
+    Ppm,
+    Qoi,
+}
+
+impl ImageFormat {
+    /// Identify an image file by its magic bytes.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&BMP_MAGIC) {
+            Some(Self::Bmp)
+        } else if data.starts_with(&PPM_MAGIC) || data.starts_with(&PGM_MAGIC) {
+            Some(Self::Ppm)
+        } else if data.starts_with(&QOI_MAGIC) {
+            Some(Self::Qoi)
+        } else {
+            None
+        }
+    }
+}
+
+/// An image whose pixels can be read by position, whatever its format.
+pub trait PixelSource {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    /// Colour of the pixel at (`x`, `y`) from the top-left corner.
+    fn pixel(&self, x: u32, y: u32) -> Color32;
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// A BMP image borrowed from its file contents.
+#[derive(Copy, Clone)]
+pub struct Bmp<'a> {
+    pixels: &'a [u8],
+    width: u32,
+    height: u32,
+    bytes_pp: usize,
+    stride: usize,
+    top_down: bool,
+}
+
+impl<'a> Bmp<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ImageError> {
+        if data.len() < BMP_FILE_HEADER_SIZE + BMP_INFO_HEADER_SIZE {
+            return Err(ImageError::Truncated);
+        }
+        if data[..2] != BMP_MAGIC {
+            return Err(ImageError::BadMagic);
+        }
+        let pixel_offset = read_u32(data, 10) as usize;
+        let header_size = read_u32(data, 14) as usize;
+        let width = read_u32(data, 18) as i32;
+        let height = read_u32(data, 22) as i32;
+        let planes = read_u16(data, 26);
+        let bits = read_u16(data, 28);
+        let compression = read_u32(data, 30);
+
+        let formats_ok = match (bits, compression) {
+            (24, BI_RGB) | (32, BI_RGB) => true,
+            (32, BI_BITFIELDS) => {
+                // The masks follow a BITMAPINFOHEADER and sit at the same
+                // place inside the larger V4/V5 headers.
+                let masks = BMP_FILE_HEADER_SIZE + BMP_INFO_HEADER_SIZE;
+                data.len() >= masks + 12
+                    && (0..3).all(|i| read_u32(data, masks + i * 4) == BGRA_MASKS[i])
+            }
+            _ => false,
+        };
+        let (w, h) = (width.unsigned_abs(), height.unsigned_abs());
+        if header_size < BMP_INFO_HEADER_SIZE
+            || planes != 1
+            || !formats_ok
+            || width <= 0
+            || h == 0
+            || w > MAX_IMAGE_DIM
+            || h > MAX_IMAGE_DIM
+        {
+            return Err(ImageError::Unsupported);
+        }
+
+        let bytes_pp = bits as usize / 8;
+        let stride = (w as usize * bytes_pp).next_multiple_of(4);
+        let pixels_end = pixel_offset + stride * h as usize;
+        if pixel_offset < BMP_FILE_HEADER_SIZE + header_size || data.len() < pixels_end {
+            return Err(ImageError::Truncated);
+        }
+
+        Ok(Self {
+            pixels: &data[pixel_offset..pixels_end],
+            width: w,
+            height: h,
+            bytes_pp,
+            stride,
+            top_down: height < 0,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Colour of the pixel at (`x`, `y`) from the top-left corner.  Images
+    /// are drawn opaque, so any alpha channel is ignored.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Color32 {
+        let row = if self.top_down {
+            y
+        } else {
+            self.height - 1 - y
+        };
+        let off = row as usize * self.stride + x as usize * self.bytes_pp;
+        let px = &self.pixels[off..off + 3];
+        Color32::rgb(px[2], px[1], px[0])
+    }
+}
+
+impl PixelSource for Bmp<'_> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> Color32 {
+        Bmp::pixel(self, x, y)
+    }
+}
+
+/// How an image is fitted to a canvas of another size.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum ScaleMode {
+    /// At its own size in the middle, cropped or bordered by the
+    /// background colour.
+    Center,
+    /// Stretched to cover the canvas exactly.
+    #[default]
+    Stretch,
+    /// Repeated from the top-left corner.
+    Tile,
+    /// Scaled to fill as much of the canvas as it can without changing
+    /// its aspect ratio, centred, and bordered by the background colour.
+    Fit,
+}
+
+impl ScaleMode {
+    pub fn from_name(name: &[u8]) -> Option<Self> {
+        match name {
+            b"center" => Some(Self::Center),
+            b"stretch" => Some(Self::Stretch),
+            b"tile" => Some(Self::Tile),
+            b"fit" => Some(Self::Fit),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static [u8] {
+        match self {
+            Self::Center => b"center",
+            Self::Stretch => b"stretch",
+            Self::Tile => b"tile",
+            Self::Fit => b"fit",
+        }
+    }
+}
+
+/// Cover all of `target` with `image` fitted by `mode`.  Whatever the
+/// image leaves uncovered is filled with `background`.
+pub fn draw_image_scaled<T: Canvas, I: PixelSource + ?Sized>(
+    target: &mut T,
+    image: &I,
+    mode: ScaleMode,
+    background: Color32,
+) {
+    let fmt = target.pixel_format();
+    let (tw, th) = (target.width(), target.height());
+    let (iw, ih) = (image.width(), image.height());
+    // Centring offsets; negative when the image is the larger one.
+    let off_x = (tw as i64 - iw as i64).div_euclid(2);
+    let off_y = (th as i64 - ih as i64).div_euclid(2);
+    let bg_px = fmt.encode(background);
+
+    // Size and offsets of the image once fitted; whichever side runs out
+    // of room first sets the scale.
+    let (fit_w, fit_h) = if iw as u64 * th as u64 <= tw as u64 * ih as u64 {
+        ((iw as u64 * th as u64 / ih as u64).max(1) as u32, th)
+    } else {
+        (tw, (ih as u64 * tw as u64 / iw as u64).max(1) as u32)
+    };
+    let fit_x = (tw - fit_w.min(tw)) / 2;
+    let fit_y = (th - fit_h.min(th)) / 2;
+
+    for y in 0..th {
+        for x in 0..tw {
+            let source = match mode {
+                ScaleMode::Stretch => Some((
+                    (x as u64 * iw as u64 / tw as u64) as u32,
+                    (y as u64 * ih as u64 / th as u64) as u32,
+                )),
+                ScaleMode::Tile => Some((x % iw, y % ih)),
+                ScaleMode::Center => {
+                    let (ix, iy) = (x as i64 - off_x, y as i64 - off_y);
+                    (ix >= 0 && iy >= 0 && ix < iw as i64 && iy < ih as i64)
+                        .then_some((ix as u32, iy as u32))
+                }
+                ScaleMode::Fit => {
+                    let (fx, fy) = (x.wrapping_sub(fit_x), y.wrapping_sub(fit_y));
+                    (fx < fit_w && fy < fit_h).then(|| {
+                        (
+                            (fx as u64 * iw as u64 / fit_w as u64) as u32,
+                            (fy as u64 * ih as u64 / fit_h as u64) as u32,
+                        )
+                    })
+                }
+            };
+            let pixel = match source {
+                Some((ix, iy)) => fmt.encode(image.pixel(ix, iy)),
+                None => bg_px,
+            };
+            target.put_pixel(x as i32, y as i32, pixel);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Binary PPM (`P6`) and PGM (`P5`) decoding.
+//!
+//! Like [`Bmp`](super::Bmp), a [`Ppm`] borrows the file and reads pixels
+//! on demand.  Samples of up to 8 bits are supported; the ASCII forms
+//! (`P3`, `P2`) and 16-bit samples are not.
+
+use slopos_abi::draw::Color32;
+
+use super::{ImageError, MAX_IMAGE_DIM, PixelSource};
+
+/// First two bytes of a binary PPM file.
+pub const PPM_MAGIC: [u8; 2] = *b"P6";
+/// First two bytes of a binary PGM file.
+pub const PGM_MAGIC: [u8; 2] = *b"P5";
+
+/// A binary PPM or PGM image borrowed from its file contents.
+#[derive(Copy, Clone)]
+pub struct Ppm<'a> {
+    pixels: &'a [u8],
+    width: u32,
+    height: u32,
+    /// 3 for PPM, 1 for PGM.
+    channels: usize,
+    max_value: u32,
+}
+
+/// Reads the whitespace-separated header fields, skipping `#` comments.
+struct HeaderReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl HeaderReader<'_> {
+    fn skip_space(&mut self) {
+        while let Some(&byte) = self.data.get(self.pos) {
+            match byte {
+                b'#' => {
+                    while self.data.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                b' ' | b'\t' | b'\n' | b'\r' | 0x0B | 0x0C => self.pos += 1,
+                _ => return,
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, ImageError> {
+        self.skip_space();
+        let start = self.pos;
+        let mut value: u32 = 0;
+        while let Some(&byte) = self.data.get(self.pos).filter(|b| b.is_ascii_digit()) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add((byte - b'0') as u32))
+                .ok_or(ImageError::Unsupported)?;
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return Err(if self.pos == self.data.len() {
+                ImageError::Truncated
+            } else {
+                ImageError::Unsupported
+            });
+        }
+        Ok(value)
+    }
+}
+
+impl<'a> Ppm<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ImageError> {
+        if data.len() < 2 {
+            return Err(ImageError::Truncated);
+        }
+        let channels = match [data[0], data[1]] {
+            PPM_MAGIC => 3,
+            PGM_MAGIC => 1,
+            _ => return Err(ImageError::BadMagic),
+        };
+
+        let mut header = HeaderReader { data, pos: 2 };
+        let width = header.number()?;
+        let height = header.number()?;
+        let max_value = header.number()?;
+        if width == 0
+            || height == 0
+            || width > MAX_IMAGE_DIM
+            || height > MAX_IMAGE_DIM
+            || max_value == 0
+            || max_value > 255
+        {
+            return Err(ImageError::Unsupported);
+        }
+
+        // One whitespace byte separates the header from the samples.
+        let start = header.pos + 1;
+        let end = start + width as usize * height as usize * channels;
+        if data.len() < end {
+            return Err(ImageError::Truncated);
+        }
+
+        Ok(Self {
+            pixels: &data[start..end],
+            width,
+            height,
+            channels,
+            max_value,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline]
+    fn sample(&self, value: u8) -> u8 {
+        if self.max_value == 255 {
+            value
+        } else {
+            (value as u32 * 255 / self.max_value).min(255) as u8
+        }
+    }
+
+    /// Colour of the pixel at (`x`, `y`) from the top-left corner.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Color32 {
+        let off = (y as usize * self.width as usize + x as usize) * self.channels;
+        if self.channels == 1 {
+            let v = self.sample(self.pixels[off]);
+            return Color32::rgb(v, v, v);
+        }
+        let px = &self.pixels[off..off + 3];
+        Color32::rgb(self.sample(px[0]), self.sample(px[1]), self.sample(px[2]))
+    }
+}
+
+impl PixelSource for Ppm<'_> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> Color32 {
+        Ppm::pixel(self, x, y)
+    }
+}
@@ -0,0 +1,195 @@
+//! QOI ("Quite OK Image") decoding.
+//!
+//! QOI is compressed, so unlike BMP and PPM its pixels can't be read in
+//! place: [`Qoi::decode_into`] expands the whole image into a buffer the
+//! caller provides, which [`RgbaImage`] then reads.
+
+use slopos_abi::draw::Color32;
+
+use super::{ImageError, MAX_IMAGE_DIM, PixelSource};
+
+/// First four bytes of a QOI file.
+pub const QOI_MAGIC: [u8; 4] = *b"qoif";
+const QOI_HEADER_SIZE: usize = 14;
+/// Seven zero bytes and a one end the stream.
+const QOI_END_MARKER_SIZE: usize = 8;
+
+const QOI_OP_INDEX: u8 = 0x00;
+const QOI_OP_DIFF: u8 = 0x40;
+const QOI_OP_LUMA: u8 = 0x80;
+const QOI_OP_RUN: u8 = 0xC0;
+const QOI_OP_RGB: u8 = 0xFE;
+const QOI_OP_RGBA: u8 = 0xFF;
+const QOI_MASK_2: u8 = 0xC0;
+
+/// A QOI image header and its compressed pixel stream.
+#[derive(Copy, Clone)]
+pub struct Qoi<'a> {
+    chunks: &'a [u8],
+    width: u32,
+    height: u32,
+}
+
+fn color_hash(px: [u8; 4]) -> usize {
+    (px[0] as usize * 3 + px[1] as usize * 5 + px[2] as usize * 7 + px[3] as usize * 11) % 64
+}
+
+impl<'a> Qoi<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ImageError> {
+        if data.len() < QOI_HEADER_SIZE {
+            return Err(ImageError::Truncated);
+        }
+        if data[..4] != QOI_MAGIC {
+            return Err(ImageError::BadMagic);
+        }
+        let width = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let height = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+        let channels = data[12];
+        let colorspace = data[13];
+        if width == 0
+            || height == 0
+            || width > MAX_IMAGE_DIM
+            || height > MAX_IMAGE_DIM
+            || !matches!(channels, 3 | 4)
+            || colorspace > 1
+        {
+            return Err(ImageError::Unsupported);
+        }
+        Ok(Self {
+            chunks: &data[QOI_HEADER_SIZE..],
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Bytes [`Qoi::decode_into`] writes: four per pixel.
+    pub fn decoded_len(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+
+    /// Expand the image into `out` as RGBA rows, top row first.  `out`
+    /// must hold at least [`Qoi::decoded_len`] bytes.
+    pub fn decode_into<'o>(&self, out: &'o mut [u8]) -> Result<RgbaImage<'o>, ImageError> {
+        let len = self.decoded_len();
+        if out.len() < len {
+            return Err(ImageError::BufferTooSmall);
+        }
+        // The end marker can't start a chunk, so stop reading before it.
+        let chunks_len = self.chunks.len().saturating_sub(QOI_END_MARKER_SIZE);
+        let chunks = &self.chunks[..chunks_len];
+
+        let mut index = [[0u8; 4]; 64];
+        let mut px = [0u8, 0, 0, 255];
+        let mut pos = 0;
+        let mut run = 0u32;
+        for out_px in out[..len].chunks_exact_mut(4) {
+            if run > 0 {
+                run -= 1;
+            } else {
+                let Some(&b1) = chunks.get(pos) else {
+                    return Err(ImageError::Truncated);
+                };
+                pos += 1;
+                let need = match b1 {
+                    QOI_OP_RGB => 3,
+                    QOI_OP_RGBA => 4,
+                    _ if b1 & QOI_MASK_2 == QOI_OP_LUMA => 1,
+                    _ => 0,
+                };
+                let Some(args) = chunks.get(pos..pos + need) else {
+                    return Err(ImageError::Truncated);
+                };
+                pos += need;
+                match b1 {
+                    QOI_OP_RGB => px[..3].copy_from_slice(args),
+                    QOI_OP_RGBA => px.copy_from_slice(args),
+                    _ => match b1 & QOI_MASK_2 {
+                        QOI_OP_INDEX => px = index[b1 as usize],
+                        QOI_OP_DIFF => {
+                            px[0] = px[0].wrapping_add(((b1 >> 4) & 0x03).wrapping_sub(2));
+                            px[1] = px[1].wrapping_add(((b1 >> 2) & 0x03).wrapping_sub(2));
+                            px[2] = px[2].wrapping_add((b1 & 0x03).wrapping_sub(2));
+                        }
+                        QOI_OP_LUMA => {
+                            let dg = (b1 & 0x3F).wrapping_sub(32);
+                            let b2 = args[0];
+                            px[0] = px[0].wrapping_add(dg.wrapping_add(b2 >> 4).wrapping_sub(8));
+                            px[1] = px[1].wrapping_add(dg);
+                            px[2] = px[2].wrapping_add(dg.wrapping_add(b2 & 0x0F).wrapping_sub(8));
+                        }
+                        QOI_OP_RUN => run = (b1 & 0x3F) as u32,
+                        _ => unreachable!("2-bit QOI tag"),
+                    },
+                }
+                index[color_hash(px)] = px;
+            }
+            out_px.copy_from_slice(&px);
+        }
+
+        Ok(RgbaImage {
+            pixels: &out[..len],
+            width: self.width,
+            height: self.height,
+        })
+    }
+}
+
+/// Decoded RGBA pixels, four bytes each, top row first.
+#[derive(Copy, Clone)]
+pub struct RgbaImage<'a> {
+    pixels: &'a [u8],
+    width: u32,
+    height: u32,
+}
+
+impl<'a> RgbaImage<'a> {
+    /// View `pixels` as a `width`x`height` image; `None` if it is too
+    /// short.
+    pub fn new(pixels: &'a [u8], width: u32, height: u32) -> Option<Self> {
+        let len = width as usize * height as usize * 4;
+        Some(Self {
+            pixels: pixels.get(..len)?,
+            width,
+            height,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Colour of the pixel at (`x`, `y`); alpha is ignored, as for the
+    /// other formats.
+    #[inline]
+    pub fn pixel(&self, x: u32, y: u32) -> Color32 {
+        let off = (y as usize * self.width as usize + x as usize) * 4;
+        let px = &self.pixels[off..off + 3];
+        Color32::rgb(px[0], px[1], px[2])
+    }
+}
+
+impl PixelSource for RgbaImage<'_> {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> Color32 {
+        RgbaImage::pixel(self, x, y)
+    }
+}
@@ -1,10 +1,14 @@
 #![no_std]
 #![forbid(unsafe_code)]
 
+pub mod blend;
 pub mod canvas_font;
 pub mod canvas_ops;
 pub mod damage;
 pub mod draw_buffer;
+pub mod font_render;
+pub mod image;
+pub mod scale;
 
 pub use damage::{DamageTracker, InternalDamageTracker};
 pub use draw_buffer::DrawBuffer;
@@ -0,0 +1,90 @@
+//! Integer magnification of pixel buffers, for drawing a low-DPI client
+//! buffer on a high-DPI desktop.
+//!
+//! Pixels are sampled one at a time in the source's own byte layout, so
+//! the result can go straight through [`crate::blend::blend_span`].
+
+/// How magnified pixels are sampled.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ScaleFilter {
+    /// Each source pixel becomes a `scale` x `scale` block: crisp text,
+    /// blocky edges.
+    #[default]
+    Nearest,
+    /// Bilinear between the four nearest source pixels: smooth, slightly
+    /// soft.
+    Linear,
+}
+
+/// Fractional bits of sample positions.
+const FRAC_BITS: u32 = 8;
+const FRAC_ONE: u32 = 1 << FRAC_BITS;
+
+/// A source buffer drawn `scale` times its size.
+#[derive(Copy, Clone)]
+pub struct ScaledSource<'a> {
+    pub data: &'a [u8],
+    pub pitch: usize,
+    pub width: u32,
+    pub height: u32,
+    pub bytes_pp: usize,
+    pub scale: u32,
+}
+
+impl ScaledSource<'_> {
+    /// Size once magnified.
+    pub fn scaled_size(&self) -> (u32, u32) {
+        (self.width * self.scale, self.height * self.scale)
+    }
+
+    /// The pixel at (`x`, `y`) of the magnified image, in the source's
+    /// byte layout; the first `bytes_pp` bytes are used.  Points past the
+    /// edge take the nearest edge pixel.
+    pub fn sample(&self, x: u32, y: u32, filter: ScaleFilter) -> [u8; 4] {
+        let scale = self.scale.max(1);
+        if self.width == 0 || self.height == 0 {
+            return [0; 4];
+        }
+        match filter {
+            ScaleFilter::Nearest => self.pixel(x / scale, y / scale),
+            ScaleFilter::Linear if scale == 1 => self.pixel(x, y),
+            ScaleFilter::Linear => {
+                let (x0, x1, fx) = Self::taps(x, scale, self.width);
+                let (y0, y1, fy) = Self::taps(y, scale, self.height);
+                let top = lerp(self.pixel(x0, y0), self.pixel(x1, y0), fx);
+                let bottom = lerp(self.pixel(x0, y1), self.pixel(x1, y1), fx);
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+
+    /// The two source pixels around magnified coordinate `pos` and the
+    /// weight of the second.  Sample points sit at pixel centres.
+    fn taps(pos: u32, scale: u32, len: u32) -> (u32, u32, u32) {
+        let centre = ((2 * pos + 1) * FRAC_ONE / (2 * scale)).saturating_sub(FRAC_ONE / 2);
+        let first = (centre >> FRAC_BITS).min(len - 1);
+        let second = (first + 1).min(len - 1);
+        (first, second, centre & (FRAC_ONE - 1))
+    }
+
+    fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let x = x.min(self.width - 1) as usize;
+        let y = y.min(self.height - 1) as usize;
+        let off = y * self.pitch + x * self.bytes_pp;
+        let mut px = [0u8; 4];
+        let len = self.bytes_pp.min(4);
+        if let Some(src) = self.data.get(off..off + len) {
+            px[..len].copy_from_slice(src);
+        }
+        px
+    }
+}
+
+/// `a` moved `weight / FRAC_ONE` of the way to `b`, per byte.
+fn lerp(a: [u8; 4], b: [u8; 4], weight: u32) -> [u8; 4] {
+    let mut out = [0u8; 4];
+    for (o, (&a, &b)) in out.iter_mut().zip(a.iter().zip(&b)) {
+        *o = ((a as u32 * (FRAC_ONE - weight) + b as u32 * weight) >> FRAC_BITS) as u8;
+    }
+    out
+}
@@ -0,0 +1,165 @@
+//! Boot-step watchdog.
+//!
+//! The boot init runner brackets every step with [`boot_watchdog_begin`] /
+//! [`boot_watchdog_end`].  While a step is running, two things look at the
+//! clock:
+//!
+//! * the BSP's LAPIC timer interrupt calls [`boot_watchdog_tick`], which
+//!   catches steps that spin with interrupts enabled and dumps the
+//!   interrupted frame, i.e. exactly where the step is stuck;
+//! * polling loops that run with interrupts off (PS/2 controller setup,
+//!   firmware table walks) call [`boot_watchdog_poll`], which dumps the
+//!   current CPU state and tells the loop to give up.
+//!
+//! Either path reports a step once.  [`boot_watchdog_end`] also notices
+//! overruns nobody observed live, so a step that blocked with interrupts
+//! off and never polled is still named in the log once it returns.
+//!
+//! The clock reads 0 until HPET is up, so steps before that are never
+//! flagged.
+
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
+
+use crate::InterruptFrame;
+use crate::{clock, kdiag, pcr};
+
+/// Default per-step budget; override with `boot.step_budget_ms=` on the
+/// kernel command line.
+pub const BOOT_STEP_BUDGET_MS_DEFAULT: u64 = 5_000;
+
+static BUDGET_MS: AtomicU64 = AtomicU64::new(BOOT_STEP_BUDGET_MS_DEFAULT);
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static EXPIRED: AtomicBool = AtomicBool::new(false);
+static STEP_NAME_PTR: AtomicPtr<u8> = AtomicPtr::new(ptr::null_mut());
+static STEP_NAME_LEN: AtomicUsize = AtomicUsize::new(0);
+static STEP_CPU: AtomicUsize = AtomicUsize::new(0);
+static STEP_START_MS: AtomicU64 = AtomicU64::new(0);
+/// Budget of the running step; 0 means unbounded.
+static STEP_BUDGET_MS: AtomicU64 = AtomicU64::new(0);
+
+/// Outcome of a watched step, returned by [`boot_watchdog_end`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BootStepReport {
+    pub elapsed_ms: u64,
+    /// The step ran past its budget.
+    pub overran: bool,
+    /// The overrun was caught while the step was still running.
+    pub expired: bool,
+}
+
+pub fn boot_watchdog_budget_ms() -> u64 {
+    BUDGET_MS.load(Ordering::Relaxed)
+}
+
+pub fn boot_watchdog_set_budget_ms(ms: u64) {
+    BUDGET_MS.store(ms, Ordering::Relaxed);
+}
+
+fn step_name() -> &'static str {
+    let ptr = STEP_NAME_PTR.load(Ordering::Acquire);
+    if ptr.is_null() {
+        return "(unnamed)";
+    }
+    let len = STEP_NAME_LEN.load(Ordering::Relaxed);
+    // Both halves come from the same `&'static str` in `boot_watchdog_begin`.
+    unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr, len)) }
+}
+
+/// Start watching `name`.  A `budget_ms` of 0 records the step without
+/// ever flagging it (e.g. the test harness).
+pub fn boot_watchdog_begin(name: &'static str, budget_ms: u64) {
+    ACTIVE.store(false, Ordering::Release);
+    STEP_NAME_LEN.store(name.len(), Ordering::Relaxed);
+    STEP_NAME_PTR.store(name.as_ptr() as *mut u8, Ordering::Release);
+    STEP_CPU.store(pcr::current_cpu_id(), Ordering::Relaxed);
+    STEP_BUDGET_MS.store(budget_ms, Ordering::Relaxed);
+    STEP_START_MS.store(clock::uptime_ms(), Ordering::Relaxed);
+    EXPIRED.store(false, Ordering::Relaxed);
+    ACTIVE.store(true, Ordering::Release);
+}
+
+/// Stop watching the current step.  Returns a zeroed report if no step
+/// was being watched.
+pub fn boot_watchdog_end() -> BootStepReport {
+    if !ACTIVE.swap(false, Ordering::AcqRel) {
+        return BootStepReport::default();
+    }
+    let elapsed_ms = clock::uptime_ms().saturating_sub(STEP_START_MS.load(Ordering::Relaxed));
+    let budget_ms = STEP_BUDGET_MS.load(Ordering::Relaxed);
+    let expired = EXPIRED.load(Ordering::Relaxed);
+    let overran = expired || (budget_ms != 0 && elapsed_ms > budget_ms);
+    if overran && !expired {
+        crate::klog_warn!(
+            "BOOT WATCHDOG: step '{}' took {} ms (budget {} ms); it never yielded to the watchdog",
+            step_name(),
+            elapsed_ms,
+            budget_ms
+        );
+    }
+    BootStepReport {
+        elapsed_ms,
+        overran,
+        expired,
+    }
+}
+
+/// Elapsed time of the running step if it has blown its budget.
+fn overdue_ms() -> Option<u64> {
+    if !ACTIVE.load(Ordering::Acquire) {
+        return None;
+    }
+    let budget_ms = STEP_BUDGET_MS.load(Ordering::Relaxed);
+    if budget_ms == 0 {
+        return None;
+    }
+    let elapsed_ms = clock::uptime_ms().saturating_sub(STEP_START_MS.load(Ordering::Relaxed));
+    (elapsed_ms > budget_ms).then_some(elapsed_ms)
+}
+
+/// Claim the one-shot report for the running step.
+fn claim_report(elapsed_ms: u64) -> bool {
+    if EXPIRED.swap(true, Ordering::AcqRel) {
+        return false;
+    }
+    crate::klog_warn!(
+        "BOOT WATCHDOG: step '{}' exceeded its {} ms budget ({} ms elapsed)",
+        step_name(),
+        STEP_BUDGET_MS.load(Ordering::Relaxed),
+        elapsed_ms
+    );
+    true
+}
+
+/// LAPIC timer hook.  Only the CPU running the step looks, so the dumped
+/// frame is the one the step was interrupted in.
+pub fn boot_watchdog_tick(frame: *const InterruptFrame) {
+    if STEP_CPU.load(Ordering::Relaxed) != pcr::current_cpu_id() {
+        return;
+    }
+    let Some(elapsed_ms) = overdue_ms() else {
+        return;
+    };
+    if claim_report(elapsed_ms) {
+        kdiag::kdiag_dump_interrupt_frame(frame);
+        kdiag::kdiag_dump_stack_trace_from_frame(frame);
+    }
+}
+
+/// For polling loops inside boot steps.  Returns `true` once the running
+/// step is past its budget, in which case the loop should bail out and
+/// let the step fail.
+pub fn boot_watchdog_poll() -> bool {
+    if EXPIRED.load(Ordering::Relaxed) {
+        return ACTIVE.load(Ordering::Acquire);
+    }
+    let Some(elapsed_ms) = overdue_ms() else {
+        return false;
+    };
+    if claim_report(elapsed_ms) {
+        kdiag::kdiag_dump_cpu_state();
+        kdiag::kdiag_dump_stack_trace();
+    }
+    true
+}
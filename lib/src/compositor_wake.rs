@@ -0,0 +1,38 @@
+//! Wakeups for the compositor's main loop.
+//!
+//! Anything that gives the compositor work — a queued client operation, an
+//! input event, a timed present — calls [`compositor_wake`].  Between frames
+//! the compositor blocks in [`compositor_wait`] instead of polling, so an
+//! idle desktop costs no CPU.  A wakeup that arrives while the compositor is
+//! busy is remembered and makes the next wait return at once.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::WaitQueue;
+
+static PENDING: AtomicBool = AtomicBool::new(false);
+static WAITERS: WaitQueue = WaitQueue::new();
+
+/// Tell the compositor it has work.  Safe from interrupt context.
+pub fn compositor_wake() {
+    PENDING.store(true, Ordering::Release);
+    WAITERS.wake_all();
+}
+
+/// Rouse a waiting compositor without signalling work, so it re-checks the
+/// `expired` condition it is waiting on.  Used by wait timeouts.
+pub fn compositor_kick() {
+    WAITERS.wake_all();
+}
+
+/// Consume a pending wakeup without blocking.
+pub fn compositor_take_wake() -> bool {
+    PENDING.swap(false, Ordering::AcqRel)
+}
+
+/// Block until [`compositor_wake`] is called or `expired()` returns true,
+/// then consume the wakeup.  Returns whether work was signalled.
+pub fn compositor_wait<F: Fn() -> bool>(expired: F) -> bool {
+    WAITERS.wait_event(|| PENDING.load(Ordering::Acquire) || expired());
+    compositor_take_wake()
+}
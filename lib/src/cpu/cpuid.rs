@@ -43,6 +43,9 @@ pub const CPUID_LEAF_FEATURES: u32 = 0x01;
 /// Structured extended feature flags (subleaf 0).
 pub const CPUID_LEAF_STRUCTURED_EXT: u32 = 0x07;
 
+/// Architectural performance monitoring (counter count and widths).
+pub const CPUID_LEAF_PERFMON: u32 = 0x0A;
+
 /// XSAVE state enumeration (subleaf 0 = main, subleaf 1 = extended features).
 pub const CPUID_LEAF_XSAVE: u32 = 0x0D;
 
@@ -65,6 +68,9 @@ pub const CPUID_FEAT_EDX_PGE: u32 = 1 << 13;
 /// Page Attribute Table.
 pub const CPUID_FEAT_EDX_PAT: u32 = 1 << 16;
 
+/// SSE2 instructions.
+pub const CPUID_FEAT_EDX_SSE2: u32 = 1 << 26;
+
 // =============================================================================
 // CPUID Leaf 1 - ECX Feature Flags
 // =============================================================================
@@ -75,19 +81,37 @@ pub const CPUID_FEAT_ECX_PCID: u32 = 1 << 17;
 /// x2APIC support.
 pub const CPUID_FEAT_ECX_X2APIC: u32 = 1 << 21;
 
+/// LAPIC timer supports TSC-deadline mode.
+pub const CPUID_FEAT_ECX_TSC_DEADLINE: u32 = 1 << 24;
+
 /// XSAVE/XRSTOR/XGETBV/XSETBV instruction support.
 pub const CPUID_FEAT_ECX_XSAVE: u32 = 1 << 26;
 
 /// OS has enabled XSAVE via CR4.OSXSAVE.
 /// When set, userland can execute XGETBV and the kernel has set CR4.OSXSAVE.
 pub const CPUID_FEAT_ECX_OSXSAVE: u32 = 1 << 27;
+
+/// AVX instructions (usable once XCR0 enables the AVX state).
+pub const CPUID_FEAT_ECX_AVX: u32 = 1 << 28;
+
+/// RDRAND instruction support.
+pub const CPUID_FEAT_ECX_RDRAND: u32 = 1 << 30;
 // =============================================================================
 // CPUID Leaf 7 (Subleaf 0) - EBX Structured Extended Feature Flags
 // =============================================================================
 
+/// Supervisor-mode execution prevention (CR4.SMEP).
+pub const CPUID_SEXT_EBX_SMEP: u32 = 1 << 7;
+
 /// INVPCID instruction support.
 pub const CPUID_SEXT_EBX_INVPCID: u32 = 1 << 10;
 
+/// RDSEED instruction support.
+pub const CPUID_SEXT_EBX_RDSEED: u32 = 1 << 18;
+
+/// Supervisor-mode access prevention (CR4.SMAP, STAC/CLAC).
+pub const CPUID_SEXT_EBX_SMAP: u32 = 1 << 20;
+
 // =============================================================================
 // CPUID Extended Leaf 0x80000001 - EDX Flags
 // =============================================================================
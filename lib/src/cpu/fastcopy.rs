@@ -0,0 +1,157 @@
+//! Bulk copies with SSE2 or AVX, for moving pixels to the framebuffer.
+//!
+//! The kernel is built soft-float, so nothing else touches vector
+//! registers in kernel mode and they still hold the current task's user
+//! state.  Each copy saves the registers it uses and restores them before
+//! returning; an interrupt in between is harmless, since IRQ handlers do
+//! not use them either and a context switch saves the full XSAVE state.
+
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use super::control_regs::Xcr0Flags;
+use super::cpuid::{CPUID_FEAT_ECX_AVX, CPUID_FEAT_EDX_SSE2, CPUID_LEAF_FEATURES, cpuid};
+use super::xsave;
+
+/// How [`copy_span`] moves bytes.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum CopyPath {
+    Bytes = 1,
+    /// 64 bytes per step through XMM0–XMM3.
+    Sse2 = 2,
+    /// 128 bytes per step through YMM0–YMM3.
+    Avx = 3,
+}
+
+const SSE2_BLOCK: usize = 64;
+const AVX_BLOCK: usize = 128;
+
+/// Chosen path; 0 until detected.
+static COPY_PATH: AtomicU8 = AtomicU8::new(0);
+
+/// The widest copy this CPU and the enabled XSAVE state allow.  Detected
+/// on first use once XCR0 is written; AVX needs its state in XCR0, or the
+/// context switch would not preserve the upper halves.
+pub fn copy_path() -> CopyPath {
+    match COPY_PATH.load(Ordering::Relaxed) {
+        1 => return CopyPath::Bytes,
+        2 => return CopyPath::Sse2,
+        3 => return CopyPath::Avx,
+        _ => {}
+    }
+    let xcr0 = xsave::active_xcr0();
+    let (_, _, ecx, edx) = cpuid(CPUID_LEAF_FEATURES);
+    let path = if ecx & CPUID_FEAT_ECX_AVX != 0 && xcr0 & Xcr0Flags::AVX.bits() != 0 {
+        CopyPath::Avx
+    } else if edx & CPUID_FEAT_EDX_SSE2 != 0 && xcr0 & Xcr0Flags::SSE.bits() != 0 {
+        CopyPath::Sse2
+    } else {
+        CopyPath::Bytes
+    };
+    if xcr0 != 0 {
+        COPY_PATH.store(path as u8, Ordering::Relaxed);
+    }
+    path
+}
+
+/// Copy `len` bytes from `src` to `dst` on the widest available path.
+///
+/// # Safety
+/// `src` must be valid for `len` bytes of reads and `dst` for `len` bytes
+/// of writes, and the ranges must not overlap.
+pub unsafe fn copy_span(dst: *mut u8, src: *const u8, len: usize) {
+    let copied = match copy_path() {
+        CopyPath::Bytes => 0,
+        // SAFETY: forwarded from the caller.
+        CopyPath::Sse2 => unsafe { copy_blocks_sse2(dst, src, len / SSE2_BLOCK) },
+        // SAFETY: forwarded from the caller.
+        CopyPath::Avx => unsafe { copy_blocks_avx(dst, src, len / AVX_BLOCK) },
+    };
+    // SAFETY: the tail lies within the caller's ranges.
+    unsafe {
+        core::ptr::copy_nonoverlapping(src.add(copied), dst.add(copied), len - copied);
+    }
+}
+
+/// Copy `blocks` 64-byte blocks; returns the bytes copied.
+unsafe fn copy_blocks_sse2(dst: *mut u8, src: *const u8, blocks: usize) -> usize {
+    if blocks == 0 {
+        return 0;
+    }
+    let mut save = [0u8; 64];
+    // SAFETY: the caller guarantees the ranges; `save` holds the four XMM
+    // registers used, which are restored before the block ends.
+    unsafe {
+        core::arch::asm!(
+            "movdqu [{save}], xmm0",
+            "movdqu [{save} + 16], xmm1",
+            "movdqu [{save} + 32], xmm2",
+            "movdqu [{save} + 48], xmm3",
+            "2:",
+            "movdqu xmm0, [{src}]",
+            "movdqu xmm1, [{src} + 16]",
+            "movdqu xmm2, [{src} + 32]",
+            "movdqu xmm3, [{src} + 48]",
+            "movdqu [{dst}], xmm0",
+            "movdqu [{dst} + 16], xmm1",
+            "movdqu [{dst} + 32], xmm2",
+            "movdqu [{dst} + 48], xmm3",
+            "add {src}, 64",
+            "add {dst}, 64",
+            "dec {n}",
+            "jnz 2b",
+            "movdqu xmm0, [{save}]",
+            "movdqu xmm1, [{save} + 16]",
+            "movdqu xmm2, [{save} + 32]",
+            "movdqu xmm3, [{save} + 48]",
+            save = in(reg) save.as_mut_ptr(),
+            src = inout(reg) src => _,
+            dst = inout(reg) dst => _,
+            n = inout(reg) blocks => _,
+            options(nostack),
+        );
+    }
+    blocks * SSE2_BLOCK
+}
+
+/// Copy `blocks` 128-byte blocks; returns the bytes copied.
+unsafe fn copy_blocks_avx(dst: *mut u8, src: *const u8, blocks: usize) -> usize {
+    if blocks == 0 {
+        return 0;
+    }
+    let mut save = [0u8; 128];
+    // SAFETY: the caller guarantees the ranges; `save` holds all 256 bits
+    // of the four YMM registers used, which are restored before the block
+    // ends.
+    unsafe {
+        core::arch::asm!(
+            "vmovdqu [{save}], ymm0",
+            "vmovdqu [{save} + 32], ymm1",
+            "vmovdqu [{save} + 64], ymm2",
+            "vmovdqu [{save} + 96], ymm3",
+            "2:",
+            "vmovdqu ymm0, [{src}]",
+            "vmovdqu ymm1, [{src} + 32]",
+            "vmovdqu ymm2, [{src} + 64]",
+            "vmovdqu ymm3, [{src} + 96]",
+            "vmovdqu [{dst}], ymm0",
+            "vmovdqu [{dst} + 32], ymm1",
+            "vmovdqu [{dst} + 64], ymm2",
+            "vmovdqu [{dst} + 96], ymm3",
+            "add {src}, 128",
+            "add {dst}, 128",
+            "dec {n}",
+            "jnz 2b",
+            "vmovdqu ymm0, [{save}]",
+            "vmovdqu ymm1, [{save} + 32]",
+            "vmovdqu ymm2, [{save} + 64]",
+            "vmovdqu ymm3, [{save} + 96]",
+            save = in(reg) save.as_mut_ptr(),
+            src = inout(reg) src => _,
+            dst = inout(reg) dst => _,
+            n = inout(reg) blocks => _,
+            options(nostack),
+        );
+    }
+    blocks * AVX_BLOCK
+}
@@ -2,8 +2,12 @@ pub mod apic_msr;
 pub mod control_regs;
 pub mod core;
 pub mod cpuid;
+pub mod fastcopy;
 pub mod interrupts;
 pub mod msr;
+pub mod pmu;
+pub mod rand;
+pub mod smap;
 pub mod sse;
 pub mod stack;
 pub mod tlb;
@@ -20,3 +24,6 @@ pub use stack::*;
 pub use tlb::*;
 // Note: xsave is NOT glob-exported — use `cpu::xsave::*` to avoid name
 // collisions with the cpuid free functions (`xsave_area_size`, etc.).
+// Neither is pmu: reach the performance counters as `cpu::pmu::*`.
+// smap stays namespaced too, so `stac`/`clac` read as `smap::stac()`,
+// and so does rand (`rand::rdseed()`).
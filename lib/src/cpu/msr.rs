@@ -26,6 +26,10 @@ impl Msr {
     /// APIC Base MSR - contains physical base address and enable flags.
     pub const APIC_BASE: Self = Self(0x1B);
 
+    /// First general-purpose performance counter (IA32_PMC0); counter `n`
+    /// is at `PMC0 + n`.
+    pub const PMC0: Self = Self(0xC1);
+
     /// Memory Type Range Register capabilities.
     pub const MTRR_CAP: Self = Self(0xFE);
 
@@ -38,9 +42,25 @@ impl Msr {
     /// SYSENTER EIP (instruction pointer).
     pub const SYSENTER_EIP: Self = Self(0x176);
 
+    /// Event select of general-purpose counter 0 (IA32_PERFEVTSEL0);
+    /// counter `n`'s is at `PERFEVTSEL0 + n`.
+    pub const PERFEVTSEL0: Self = Self(0x186);
+
     /// Page Attribute Table.
     pub const PAT: Self = Self(0x277);
 
+    /// First fixed-function performance counter (IA32_FIXED_CTR0).
+    pub const FIXED_CTR0: Self = Self(0x309);
+
+    /// Enable bits of the fixed-function counters, four per counter.
+    pub const FIXED_CTR_CTRL: Self = Self(0x38D);
+
+    /// Global enable of every performance counter (architectural PMU v2+).
+    pub const PERF_GLOBAL_CTRL: Self = Self(0x38F);
+
+    /// LAPIC timer TSC deadline (TSC-deadline mode only; 0 disarms).
+    pub const TSC_DEADLINE: Self = Self(0x6E0);
+
     // =========================================================================
     // AMD64/Intel 64 MSRs (0xC000_0000+)
     // =========================================================================
@@ -0,0 +1,223 @@
+//! Intel architectural performance monitoring unit (PMU).
+//!
+//! CPUID leaf `0x0A` reports how many general-purpose and fixed-function
+//! counters the CPU has and which architectural events it can count.  The
+//! kernel only drives version 2 and later, where `IA32_PERF_GLOBAL_CTRL`
+//! starts and stops every counter with one write; CPUs without it (older
+//! Intel parts, AMD, most emulators) report [`PmuInfo::usable`] false.
+//!
+//! These functions touch the counters of the calling CPU only.
+
+use super::cpuid::{CPUID_LEAF_PERFMON, cpuid};
+use super::msr::{Msr, read_msr, write_msr};
+use crate::OnceLock;
+
+/// Most general-purpose counters the kernel programs.
+pub const PMU_MAX_GENERAL: u8 = 8;
+
+/// Most fixed-function counters the kernel programs.
+pub const PMU_MAX_FIXED: u8 = 3;
+
+/// `IA32_PERFEVTSELx`: count in user mode.
+const EVTSEL_USR: u64 = 1 << 16;
+/// `IA32_PERFEVTSELx`: count in kernel mode.
+const EVTSEL_OS: u64 = 1 << 17;
+/// `IA32_PERFEVTSELx`: counter enabled.
+const EVTSEL_EN: u64 = 1 << 22;
+
+/// `IA32_FIXED_CTR_CTRL` field bits, per counter.
+const FIXED_OS: u64 = 1 << 0;
+const FIXED_USR: u64 = 1 << 1;
+const FIXED_FIELD_BITS: u32 = 4;
+
+/// `IA32_PERF_GLOBAL_CTRL` bit of fixed counter 0.
+const GLOBAL_FIXED_SHIFT: u32 = 32;
+
+/// Architectural events, numbered as in CPUID leaf `0x0A` EBX.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ArchEvent {
+    CoreCycles = 0,
+    Instructions = 1,
+    RefCycles = 2,
+    LlcReferences = 3,
+    LlcMisses = 4,
+    Branches = 5,
+    BranchMisses = 6,
+}
+
+impl ArchEvent {
+    /// Event select and unit mask for a general-purpose counter.
+    pub const fn select(self) -> (u8, u8) {
+        match self {
+            Self::CoreCycles => (0x3C, 0x00),
+            Self::Instructions => (0xC0, 0x00),
+            Self::RefCycles => (0x3C, 0x01),
+            Self::LlcReferences => (0x2E, 0x4F),
+            Self::LlcMisses => (0x2E, 0x41),
+            Self::Branches => (0xC4, 0x00),
+            Self::BranchMisses => (0xC5, 0x00),
+        }
+    }
+
+    /// The fixed-function counter that counts this event, if any.
+    pub const fn fixed_counter(self) -> Option<u8> {
+        match self {
+            Self::Instructions => Some(0),
+            Self::CoreCycles => Some(1),
+            Self::RefCycles => Some(2),
+            _ => None,
+        }
+    }
+}
+
+/// A hardware counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PmuCounter {
+    Fixed(u8),
+    General(u8),
+}
+
+impl PmuCounter {
+    /// The counter's bit in `IA32_PERF_GLOBAL_CTRL`.
+    pub const fn enable_bit(self) -> u64 {
+        match self {
+            Self::Fixed(n) => 1 << (GLOBAL_FIXED_SHIFT + n as u32),
+            Self::General(n) => 1 << n,
+        }
+    }
+}
+
+/// What CPUID leaf `0x0A` reports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PmuInfo {
+    pub version: u8,
+    pub general: u8,
+    pub general_width: u8,
+    pub fixed: u8,
+    pub fixed_width: u8,
+    /// Bit `n` set when [`ArchEvent`] `n` can be counted.
+    pub events: u8,
+}
+
+impl PmuInfo {
+    /// Decode CPUID leaf `0x0A`.  Counter counts are capped at what the
+    /// kernel programs.
+    pub const fn from_cpuid(eax: u32, ebx: u32, edx: u32) -> Self {
+        let version = eax as u8;
+        let general = (eax >> 8) as u8;
+        let general_width = (eax >> 16) as u8;
+        // EBX has a bit per event, set when the event is NOT available;
+        // only the first `ebx_len` bits mean anything.
+        let ebx_len = (eax >> 24) as u8;
+        let known = if ebx_len >= 8 {
+            0xFF
+        } else {
+            (1u8 << ebx_len) - 1
+        };
+        let (fixed, fixed_width) = if version >= 2 {
+            ((edx & 0x1F) as u8, (edx >> 5) as u8)
+        } else {
+            (0, 0)
+        };
+        Self {
+            version,
+            general: if general > PMU_MAX_GENERAL {
+                PMU_MAX_GENERAL
+            } else {
+                general
+            },
+            general_width,
+            fixed: if fixed > PMU_MAX_FIXED {
+                PMU_MAX_FIXED
+            } else {
+                fixed
+            },
+            fixed_width,
+            events: known & !(ebx as u8),
+        }
+    }
+
+    /// Whether the kernel can drive this PMU.
+    pub const fn usable(&self) -> bool {
+        self.version >= 2 && self.general > 0 && self.general_width > 0
+    }
+
+    pub const fn has_event(&self, event: ArchEvent) -> bool {
+        self.events & (1 << event as u8) != 0
+    }
+
+    fn width(&self, counter: PmuCounter) -> u8 {
+        match counter {
+            PmuCounter::Fixed(_) => self.fixed_width,
+            PmuCounter::General(_) => self.general_width,
+        }
+    }
+}
+
+static PMU_INFO: OnceLock<PmuInfo> = OnceLock::new();
+
+/// This CPU's PMU, read from CPUID once.  All CPUs are assumed alike.
+pub fn pmu_info() -> PmuInfo {
+    PMU_INFO.call_once(|| {
+        let (max_leaf, _, _, _) = cpuid(0);
+        if max_leaf < CPUID_LEAF_PERFMON {
+            return PmuInfo::default();
+        }
+        let (eax, ebx, _, edx) = cpuid(CPUID_LEAF_PERFMON);
+        PmuInfo::from_cpuid(eax, ebx, edx)
+    });
+    PMU_INFO.get().copied().unwrap_or_default()
+}
+
+/// Stop every counter.  The counts stay readable.
+pub fn pmu_stop() {
+    write_msr(Msr::PERF_GLOBAL_CTRL, 0);
+}
+
+/// Zero `counter` and set it to count `event`, in user mode only or in
+/// both modes.  It starts counting at the next [`pmu_start`] that
+/// includes its [`PmuCounter::enable_bit`].
+pub fn pmu_configure(counter: PmuCounter, event: ArchEvent, user_only: bool) {
+    match counter {
+        PmuCounter::Fixed(n) => {
+            let shift = n as u32 * FIXED_FIELD_BITS;
+            let field = if user_only {
+                FIXED_USR
+            } else {
+                FIXED_USR | FIXED_OS
+            };
+            let ctrl = read_msr(Msr::FIXED_CTR_CTRL) & !(0xF << shift);
+            write_msr(Msr::FIXED_CTR_CTRL, ctrl | field << shift);
+            write_msr(Msr::new(Msr::FIXED_CTR0.address() + n as u32), 0);
+        }
+        PmuCounter::General(n) => {
+            let (select, umask) = event.select();
+            let mut evtsel = select as u64 | (umask as u64) << 8 | EVTSEL_USR | EVTSEL_EN;
+            if !user_only {
+                evtsel |= EVTSEL_OS;
+            }
+            write_msr(Msr::new(Msr::PERFEVTSEL0.address() + n as u32), evtsel);
+            write_msr(Msr::new(Msr::PMC0.address() + n as u32), 0);
+        }
+    }
+}
+
+/// Start the counters whose enable bits are in `mask` and stop the rest.
+pub fn pmu_start(mask: u64) {
+    write_msr(Msr::PERF_GLOBAL_CTRL, mask);
+}
+
+/// Events `counter` has counted since it was configured.
+pub fn pmu_read(info: &PmuInfo, counter: PmuCounter) -> u64 {
+    let raw = match counter {
+        PmuCounter::Fixed(n) => read_msr(Msr::new(Msr::FIXED_CTR0.address() + n as u32)),
+        PmuCounter::General(n) => read_msr(Msr::new(Msr::PMC0.address() + n as u32)),
+    };
+    let width = info.width(counter);
+    if width >= 64 {
+        raw
+    } else {
+        raw & ((1u64 << width) - 1)
+    }
+}
@@ -0,0 +1,112 @@
+//! Hardware random number instructions (RDRAND / RDSEED).
+//!
+//! RDRAND returns output of the CPU's own DRBG; RDSEED returns conditioned
+//! samples straight from the entropy source and may run dry under load.
+//! Both report failure through CF, so each read is retried a few times
+//! before giving up.  Neither is trusted alone: callers mix the values
+//! into a software pool.
+
+use core::arch::asm;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+use super::cpuid::{
+    CPUID_FEAT_ECX_RDRAND, CPUID_LEAF_FEATURES, CPUID_LEAF_STRUCTURED_EXT, CPUID_SEXT_EBX_RDSEED,
+    cpuid, cpuid_count,
+};
+
+/// Attempts per RDRAND read, as Intel recommends.
+const RDRAND_RETRIES: u32 = 10;
+
+/// Attempts per RDSEED read; the entropy source underflows far more often.
+const RDSEED_RETRIES: u32 = 64;
+
+const FEAT_PROBED: u8 = 1 << 0;
+const FEAT_RDRAND: u8 = 1 << 1;
+const FEAT_RDSEED: u8 = 1 << 2;
+
+/// CPUID results, probed once; CPUID is a VM exit under virtualization.
+static FEATURES: AtomicU8 = AtomicU8::new(0);
+
+#[inline(always)]
+fn features() -> u8 {
+    let cached = FEATURES.load(Ordering::Relaxed);
+    if cached & FEAT_PROBED != 0 {
+        return cached;
+    }
+    let mut features = FEAT_PROBED;
+    let (max_leaf, _, _, _) = cpuid(0);
+    let (_, _, ecx, _) = cpuid(CPUID_LEAF_FEATURES);
+    if ecx & CPUID_FEAT_ECX_RDRAND != 0 {
+        features |= FEAT_RDRAND;
+    }
+    if max_leaf >= CPUID_LEAF_STRUCTURED_EXT {
+        let (_, ebx, _, _) = cpuid_count(CPUID_LEAF_STRUCTURED_EXT, 0);
+        if ebx & CPUID_SEXT_EBX_RDSEED != 0 {
+            features |= FEAT_RDSEED;
+        }
+    }
+    FEATURES.store(features, Ordering::Relaxed);
+    features
+}
+
+#[inline(always)]
+pub fn rdrand_supported() -> bool {
+    features() & FEAT_RDRAND != 0
+}
+
+pub fn rdseed_supported() -> bool {
+    features() & FEAT_RDSEED != 0
+}
+
+/// One RDRAND value, or `None` if the instruction is missing or kept
+/// failing.  Always inlined so the stack-protector setup can use it
+/// without entering a protected frame.
+#[inline(always)]
+pub fn rdrand() -> Option<u64> {
+    if !rdrand_supported() {
+        return None;
+    }
+    for _ in 0..RDRAND_RETRIES {
+        let value: u64;
+        let ok: u8;
+        unsafe {
+            asm!(
+                "rdrand {value}",
+                "setc {ok}",
+                value = out(reg) value,
+                ok = out(reg_byte) ok,
+                options(nomem, nostack),
+            );
+        }
+        if ok != 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// One RDSEED value, or `None` if the instruction is missing or the
+/// entropy source stayed empty.
+pub fn rdseed() -> Option<u64> {
+    if !rdseed_supported() {
+        return None;
+    }
+    for _ in 0..RDSEED_RETRIES {
+        let value: u64;
+        let ok: u8;
+        unsafe {
+            asm!(
+                "rdseed {value}",
+                "setc {ok}",
+                value = out(reg) value,
+                ok = out(reg_byte) ok,
+                options(nomem, nostack),
+            );
+        }
+        if ok != 0 {
+            return Some(value);
+        }
+        core::hint::spin_loop();
+    }
+    None
+}
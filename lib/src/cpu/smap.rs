@@ -0,0 +1,108 @@
+//! Supervisor-mode execution and access prevention (SMEP / SMAP).
+//!
+//! With `CR4.SMEP` set the CPU refuses to fetch kernel instructions from
+//! user pages, and with `CR4.SMAP` set any kernel load or store to a user
+//! page faults unless `RFLAGS.AC` is set.  The kernel sets AC only for the
+//! duration of a checked user copy (see [`UserAccess`]), so a stray
+//! dereference of a user pointer faults at the offending instruction.
+//!
+//! The BSP calls [`init`] during CPU verification; every AP calls
+//! [`enable_on_current_cpu`] to replicate the same CR4 bits.  `stac` and
+//! `clac` raise #UD on CPUs without SMAP, so both helpers are no-ops until
+//! [`init`] has seen the feature.
+
+use core::arch::asm;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use super::control_regs::{Cr4Flags, read_cr4, write_cr4};
+use super::cpuid::{
+    CPUID_LEAF_STRUCTURED_EXT, CPUID_SEXT_EBX_SMAP, CPUID_SEXT_EBX_SMEP, cpuid, cpuid_count,
+};
+
+static SMEP_ACTIVE: AtomicBool = AtomicBool::new(false);
+static SMAP_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Returns `(smep, smap)` support as reported by CPUID leaf 7.
+pub fn supported() -> (bool, bool) {
+    let (max_leaf, _, _, _) = cpuid(0);
+    if max_leaf < CPUID_LEAF_STRUCTURED_EXT {
+        return (false, false);
+    }
+    let (_, ebx, _, _) = cpuid_count(CPUID_LEAF_STRUCTURED_EXT, 0);
+    (
+        ebx & CPUID_SEXT_EBX_SMEP != 0,
+        ebx & CPUID_SEXT_EBX_SMAP != 0,
+    )
+}
+
+/// Detect SMEP/SMAP on the BSP and turn on whatever the CPU has.
+///
+/// Returns `(smep, smap)` as enabled.
+pub fn init() -> (bool, bool) {
+    let (smep, smap) = supported();
+    SMEP_ACTIVE.store(smep, Ordering::Release);
+    SMAP_ACTIVE.store(smap, Ordering::Release);
+    enable_on_current_cpu();
+    (smep, smap)
+}
+
+/// Set the CR4 bits chosen by [`init`] on the calling CPU.
+pub fn enable_on_current_cpu() {
+    let mut cr4 = read_cr4();
+    if SMEP_ACTIVE.load(Ordering::Acquire) {
+        cr4 |= Cr4Flags::SMEP.bits();
+    }
+    if SMAP_ACTIVE.load(Ordering::Acquire) {
+        // Never turn SMAP on with AC left set by whoever ran before us.
+        clac();
+        cr4 |= Cr4Flags::SMAP.bits();
+    }
+    write_cr4(cr4);
+}
+
+#[inline]
+pub fn smep_enabled() -> bool {
+    SMEP_ACTIVE.load(Ordering::Relaxed)
+}
+
+#[inline]
+pub fn smap_enabled() -> bool {
+    SMAP_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Set `RFLAGS.AC`: allow supervisor access to user pages.
+#[inline(always)]
+pub fn stac() {
+    if smap_enabled() {
+        unsafe { asm!("stac", options(nomem, nostack)) };
+    }
+}
+
+/// Clear `RFLAGS.AC`: user pages are off limits again.
+#[inline(always)]
+pub fn clac() {
+    if smap_enabled() {
+        unsafe { asm!("clac", options(nomem, nostack)) };
+    }
+}
+
+/// Open window for kernel access to user pages; closed on drop.
+///
+/// Keep the window to the copy itself: nothing that can sleep, take locks
+/// or fault on kernel data should run while it is open.
+pub struct UserAccess(());
+
+impl UserAccess {
+    #[inline(always)]
+    pub fn begin() -> Self {
+        stac();
+        Self(())
+    }
+}
+
+impl Drop for UserAccess {
+    #[inline(always)]
+    fn drop(&mut self) {
+        clac();
+    }
+}
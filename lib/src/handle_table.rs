@@ -0,0 +1,214 @@
+//! Fixed-capacity, generation-checked object table.
+//!
+//! Backs every kernel namespace that hands out [`Handle`]s.  Each slot keeps
+//! a generation and a reference count: [`HandleTable::dup`] takes another
+//! reference, [`HandleTable::close`] drops one and hands the object back
+//! only when the last reference goes, so the owner tears it down exactly
+//! once.  Freeing a slot bumps its generation, which invalidates every
+//! outstanding copy of the old handle.
+
+use slopos_abi::handle::{HANDLE_MAX_INDEX, Handle, HandleKind};
+
+struct HandleSlot<T> {
+    value: Option<T>,
+    generation: u16,
+    refs: u32,
+}
+
+pub struct HandleTable<T, const N: usize> {
+    kind: HandleKind,
+    slots: [HandleSlot<T>; N],
+    live: usize,
+}
+
+impl<T, const N: usize> HandleTable<T, N> {
+    const CAPACITY_FITS: () = assert!(N <= HANDLE_MAX_INDEX, "handle table too large");
+
+    pub const fn new(kind: HandleKind) -> Self {
+        let () = Self::CAPACITY_FITS;
+        Self {
+            kind,
+            slots: [const {
+                HandleSlot {
+                    value: None,
+                    generation: 1,
+                    refs: 0,
+                }
+            }; N],
+            live: 0,
+        }
+    }
+
+    #[inline]
+    pub const fn kind(&self) -> HandleKind {
+        self.kind
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.live
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    fn slot_index(&self, handle: Handle) -> Option<usize> {
+        if handle.kind() != Some(self.kind) {
+            return None;
+        }
+        let idx = handle.index();
+        let slot = self.slots.get(idx)?;
+        (slot.value.is_some() && slot.generation == handle.generation()).then_some(idx)
+    }
+
+    fn handle_at(&self, idx: usize) -> Handle {
+        Handle::new(self.kind, idx, self.slots[idx].generation)
+    }
+
+    /// Store `value` with one reference.  Gives the value back when full.
+    pub fn insert(&mut self, value: T) -> Result<Handle, T> {
+        let Some(idx) = self.slots.iter().position(|s| s.value.is_none()) else {
+            return Err(value);
+        };
+        let slot = &mut self.slots[idx];
+        slot.value = Some(value);
+        slot.refs = 1;
+        self.live += 1;
+        Ok(self.handle_at(idx))
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.slot_index(handle).is_some()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        let idx = self.slot_index(handle)?;
+        self.slots[idx].value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        let idx = self.slot_index(handle)?;
+        self.slots[idx].value.as_mut()
+    }
+
+    /// Reference count of a live handle, 0 for stale ones.
+    pub fn refs(&self, handle: Handle) -> u32 {
+        self.slot_index(handle)
+            .map_or(0, |idx| self.slots[idx].refs)
+    }
+
+    /// Take another reference; the returned handle is the same value.
+    pub fn dup(&mut self, handle: Handle) -> Option<Handle> {
+        let idx = self.slot_index(handle)?;
+        let slot = &mut self.slots[idx];
+        slot.refs = slot.refs.checked_add(1)?;
+        Some(handle)
+    }
+
+    /// Drop one reference.  Returns the object when that was the last one.
+    pub fn close(&mut self, handle: Handle) -> Option<T> {
+        let idx = self.slot_index(handle)?;
+        let slot = &mut self.slots[idx];
+        slot.refs -= 1;
+        if slot.refs > 0 {
+            return None;
+        }
+        self.free_slot(idx)
+    }
+
+    /// Drop the object regardless of outstanding references.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let idx = self.slot_index(handle)?;
+        self.free_slot(idx)
+    }
+
+    fn free_slot(&mut self, idx: usize) -> Option<T> {
+        let slot = &mut self.slots[idx];
+        let value = slot.value.take();
+        slot.refs = 0;
+        slot.generation = match slot.generation.wrapping_add(1) {
+            0 => 1,
+            g => g,
+        };
+        self.live -= 1;
+        value
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(idx, slot)| {
+            let value = slot.value.as_ref()?;
+            Some((Handle::new(self.kind, idx, slot.generation), value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle, &mut T)> {
+        let kind = self.kind;
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(move |(idx, slot)| {
+                let generation = slot.generation;
+                let value = slot.value.as_mut()?;
+                Some((Handle::new(kind, idx, generation), value))
+            })
+    }
+
+    /// First live handle whose object satisfies `pred`.
+    pub fn find(&self, mut pred: impl FnMut(&T) -> bool) -> Option<Handle> {
+        self.iter().find(|(_, v)| pred(v)).map(|(h, _)| h)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stale_handle_misses_reused_slot() {
+        let mut table: HandleTable<u32, 2> = HandleTable::new(HandleKind::Shm);
+        let first = table.insert(10).unwrap();
+        assert_eq!(table.close(first), Some(10));
+        let second = table.insert(20).unwrap();
+        assert_eq!(first.index(), second.index());
+        assert_ne!(first, second);
+        assert_eq!(table.get(first), None);
+        assert_eq!(table.get(second), Some(&20));
+    }
+
+    #[test]
+    fn dup_defers_teardown_to_last_close() {
+        let mut table: HandleTable<u32, 4> = HandleTable::new(HandleKind::Socket);
+        let h = table.insert(7).unwrap();
+        assert_eq!(table.dup(h), Some(h));
+        assert_eq!(table.refs(h), 2);
+        assert_eq!(table.close(h), None);
+        assert_eq!(table.get(h), Some(&7));
+        assert_eq!(table.close(h), Some(7));
+        assert!(table.is_empty());
+        assert_eq!(table.close(h), None);
+    }
+
+    #[test]
+    fn wrong_kind_is_rejected() {
+        let mut table: HandleTable<u32, 4> = HandleTable::new(HandleKind::Shm);
+        let h = table.insert(1).unwrap();
+        let forged = Handle::new(HandleKind::Socket, h.index(), h.generation());
+        assert_eq!(table.get(forged), None);
+        assert_eq!(table.remove(forged), None);
+        assert_eq!(table.len(), 1);
+    }
+
+    #[test]
+    fn full_table_returns_value() {
+        let mut table: HandleTable<u32, 1> = HandleTable::new(HandleKind::Shm);
+        table.insert(1).unwrap();
+        assert_eq!(table.insert(2), Err(2));
+    }
+}
@@ -7,12 +7,14 @@ use crate::stacktrace::{self, StacktraceEntry};
 use crate::tsc;
 
 // ---------------------------------------------------------------------------
-// Register snapshot — only used by kdiag_dump_cpu_state() below.
+// Register snapshot — dumped by kdiag_dump_cpu_state() below and shown on the
+// panic screen.
 // ---------------------------------------------------------------------------
 
+/// General purpose registers as they were inside [`kdiag_snapshot_regs`].
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
-struct RegSnapshot {
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegSnapshot {
     pub rax: u64,
     pub rbx: u64,
     pub rcx: u64,
@@ -32,7 +34,7 @@ struct RegSnapshot {
 }
 
 #[inline(never)]
-fn snapshot_regs() -> RegSnapshot {
+pub fn kdiag_snapshot_regs() -> RegSnapshot {
     let (rax, rbx, rcx, rdx): (u64, u64, u64, u64);
     let (rsi, rdi, rbp, rsp): (u64, u64, u64, u64);
     let (r8, r9, r10, r11): (u64, u64, u64, u64);
@@ -106,6 +108,7 @@ fn snapshot_regs() -> RegSnapshot {
 pub const KDIAG_STACK_TRACE_DEPTH: usize = 16;
 
 #[repr(C)]
+#[derive(Debug, Clone, Copy)]
 pub struct InterruptFrame {
     pub r15: u64,
     pub r14: u64,
@@ -131,7 +134,7 @@ pub struct InterruptFrame {
     pub ss: u64,
 }
 
-fn exception_name(vector: u8) -> &'static str {
+pub fn exception_name(vector: u8) -> &'static str {
     match vector {
         0 => "Divide Error",
         1 => "Debug",
@@ -168,7 +171,7 @@ pub fn kdiag_timestamp() -> u64 {
     MONOTONIC_TIME.load(Ordering::Relaxed)
 }
 pub fn kdiag_dump_cpu_state() {
-    let regs = snapshot_regs();
+    let regs = kdiag_snapshot_regs();
     let rflags = cpu::read_rflags();
     let cr0 = cpu::read_cr0();
     let cr2 = cpu::read_cr2();
@@ -22,6 +22,7 @@ crate::define_service! {
         current_task_id() -> u32;
         current_task_pgid() -> u32;
         current_task_sid() -> u32;
+        current_task_creds() -> slopos_abi::task::Credentials;
         current_task_controlling_tty() -> Option<slopos_abi::syscall::TtyIndex>;
         set_current_task_controlling_tty(tty: Option<slopos_abi::syscall::TtyIndex>) -> bool;
         clear_session_controlling_tty(session_id: u32, tty: slopos_abi::syscall::TtyIndex) -> usize;
@@ -1,5 +1,24 @@
 use core::ffi::{c_char, c_int, c_void};
 
+/// Kernel RNG state, as exported under `/proc/sys/kernel/random`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RngStats {
+    /// Enough entropy has been credited to key the CSPRNG.
+    pub seeded: bool,
+    /// Current entropy estimate, in bits (capped at `poolsize`).
+    pub entropy_avail: u32,
+    /// Pool size, in bits.
+    pub poolsize: u32,
+    pub bytes_generated: u64,
+    pub reseeds: u64,
+    /// Total entropy ever credited, in bits.
+    pub entropy_added: u64,
+    /// Nonblocking reads served before the pool was seeded.
+    pub unseeded_reads: u64,
+    /// RDSEED/RDRAND values mixed into the pool.
+    pub arch_samples: u64,
+}
+
 crate::define_service! {
     platform => PlatformServices {
         timer_ticks() -> u64;
@@ -8,11 +27,18 @@ crate::define_service! {
         timer_sleep_ms(ms: u32);
         timer_enable_irq();
         timer_disable_irq();
+        timer_arm_ns(delay_ns: u64);
+        timer_disarm();
 
         console_putc(c: u8);
         @no_wrapper console_puts(s: &[u8]);
 
         rng_next() -> u64;
+        rng_fill(dst: &mut [u8]) -> usize;
+        rng_add_entropy(sample: u64, bits: u32);
+        rng_is_seeded() -> bool;
+        rng_wait_seeded(nonblock: bool) -> bool;
+        rng_stats() -> RngStats;
 
         gdt_set_kernel_rsp0(rsp0: u64);
 
@@ -30,6 +56,8 @@ crate::define_service! {
         irq_unmask_gsi(gsi: u32) -> i32;
 
         @no_wrapper clock_monotonic_ns() -> u64;
+
+        cpu_usage(cpu: usize) -> Option<slopos_abi::task::CpuUsage>;
     }
 }
 
@@ -0,0 +1,16 @@
+use slopos_abi::audio::AudioInfo;
+
+crate::define_service! {
+    audio => AudioServices {
+        /// Queue interleaved frames, blocking while the ring is full.
+        ///
+        /// Returns bytes queued; short only when playback stalls or no
+        /// device is present.
+        write(data: &[u8]) -> usize;
+        info() -> AudioInfo;
+        /// Software volume, 0..=`AUDIO_VOLUME_MAX`.
+        set_volume(volume: u32);
+        /// Drop queued audio and halt playback.
+        stop();
+    }
+}
@@ -4,9 +4,10 @@ crate::define_service! {
         ///
         /// * `hostname` — pointer to hostname bytes (not NUL-terminated)
         /// * `hostname_len` — length of hostname in bytes
+        /// * `nonblocking` — return -EAGAIN instead of waiting for the network
         /// * `result` — pointer to `[u8; 4]` output for the resolved address
         ///
         /// Returns 0 on success, negative errno on failure.
-        resolve(hostname: *const u8, hostname_len: usize, result: *mut [u8; 4]) -> i32;
+        resolve(hostname: *const u8, hostname_len: usize, nonblocking: bool, result: *mut [u8; 4]) -> i32;
     }
 }
@@ -0,0 +1,7 @@
+crate::define_service! {
+    hw => HwServices {
+        /// Copy inventory entry `index` to `out`.  Returns false past the
+        /// end of the inventory.
+        device(index: usize, out: *mut slopos_abi::hw::HwDevice) -> bool;
+    }
+}
@@ -1,18 +1,29 @@
-use slopos_abi::InputEvent;
+use slopos_abi::{ClipboardError, InputEvent};
 
 crate::define_service! {
     input => InputServices {
         poll(task_id: u32) -> Option<InputEvent>;
-        drain_batch(task_id: u32, buffer: *mut InputEvent, max_count: usize) -> usize;
+        drain_batch(task_id: u32, dst: &mut [InputEvent]) -> usize;
         event_count(task_id: u32) -> usize;
         set_keyboard_focus(task_id: u32);
+        set_shortcut_focus(task_id: u32);
+        take_keys(task_id: u32);
         set_pointer_focus(task_id: u32, timestamp_ms: u64);
-        set_pointer_focus_with_offset(task_id: u32, x: i32, y: i32, timestamp_ms: u64);
+        set_pointer_focus_with_offset(task_id: u32, x: i32, y: i32, scale: u32, timestamp_ms: u64);
         request_close(task_id: u32, timestamp_ms: u64) -> i32;
+        request_configure(task_id: u32, width: u32, height: u32, timestamp_ms: u64) -> i32;
         get_pointer_focus() -> u32;
         get_pointer_position() -> (i32, i32);
         get_button_state() -> u32;
         clipboard_copy(src: &[u8]) -> usize;
         clipboard_paste(dst: &mut [u8]) -> usize;
+        /// Replace the clipboard with `data` of type `mime`; returns the bytes stored.
+        clipboard_set(mime: &[u8], data: &[u8]) -> Result<usize, ClipboardError>;
+        /// Copy the clipboard into `dst` if it holds `mime`; returns its full length.
+        clipboard_get(mime: &[u8], dst: &mut [u8]) -> Result<usize, ClipboardError>;
+        device_read(dst: &mut [InputEvent]) -> usize;
+        /// Switch the keyboard layout; returns the previous ID, or `None` for an unknown one.
+        set_keymap(id: u32) -> Option<u32>;
+        keymap() -> u32;
     }
 }
@@ -1,4 +1,6 @@
+pub mod audio;
 pub mod dns;
+pub mod hw;
 pub mod input;
 pub mod net;
 pub mod socket;
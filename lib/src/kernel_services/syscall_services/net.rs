@@ -1,7 +1,34 @@
+use slopos_abi::net::{
+    NetFilterRule, NetFilterStatus, NetLease, NetPingReply, NetPingRequest, NetRoute, NetSocketStat,
+};
+
 crate::define_service! {
     net => NetServices {
         scan_members(out: *mut slopos_abi::net::UserNetMember, max: usize, active: u32) -> usize;
         is_ready() -> u32;
         get_info(out: *mut slopos_abi::net::UserNetInfo) -> u32;
+        /// Append a packet filter rule.  Returns its index or a negative errno.
+        filter_append(rule: NetFilterRule) -> i32;
+        /// Delete the filter rule at an index.  Returns 0 or a negative errno.
+        filter_delete(idx: usize) -> i32;
+        filter_flush();
+        /// Copy filter rules into `out`.  Returns how many were copied.
+        filter_list(out: &mut [NetFilterRule]) -> usize;
+        /// Set a direction's default verdict.  Returns 0 or a negative errno.
+        filter_set_policy(direction: u8, action: u8) -> i32;
+        filter_status() -> NetFilterStatus;
+        /// Send one echo request and wait for the reply.  Returns 0 or a
+        /// negative errno.
+        ping(request: NetPingRequest, reply: &mut NetPingReply) -> i32;
+        /// Add a route.  Returns 0 or a negative errno.
+        route_add(route: NetRoute) -> i32;
+        /// Delete a route.  Returns 0 or a negative errno.
+        route_del(route: NetRoute) -> i32;
+        /// Copy routes into `out`.  Returns how many were copied.
+        route_list(out: &mut [NetRoute]) -> usize;
+        /// Current DHCP lease state.
+        lease_info() -> NetLease;
+        /// The first open socket at index `start` or above.
+        socket_stat(start: usize) -> Option<NetSocketStat>;
     }
 }
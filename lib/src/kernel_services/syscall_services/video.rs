@@ -2,9 +2,11 @@ use core::ffi::c_int;
 
 use slopos_abi::CompositorError;
 use slopos_abi::DisplayInfo;
+use slopos_abi::OutputInfo;
 use slopos_abi::WindowInfo;
 use slopos_abi::addr::PhysAddr;
 use slopos_abi::damage::DamageRect;
+use slopos_abi::present::{PresentError, PresentFeedback};
 use slopos_abi::video_traits::VideoResult;
 
 pub type CompositorResult = Result<(), CompositorError>;
@@ -12,7 +14,11 @@ pub type CompositorResult = Result<(), CompositorError>;
 crate::define_service! {
     video => VideoServices {
         get_display_info() -> Option<DisplayInfo>;
-        surface_enumerate_windows(out_buffer: *mut WindowInfo, max_count: u32) -> u32;
+        fb_read(offset: usize, dst: &mut [u8]) -> usize;
+        fb_write(offset: usize, src: &[u8]) -> usize;
+        fb_flush() -> c_int;
+        scanout_region() -> Option<(PhysAddr, usize)>;
+        surface_enumerate_windows(out: &mut [WindowInfo]) -> u32;
         surface_set_window_position(task_id: u32, x: i32, y: i32) -> CompositorResult;
         surface_set_window_state(task_id: u32, state: u8) -> CompositorResult;
         surface_set_cursor_shape(task_id: u32, shape: u8) -> CompositorResult;
@@ -20,14 +26,29 @@ crate::define_service! {
         surface_commit(task_id: u32) -> CompositorResult;
         register_surface(task_id: u32, width: u32, height: u32, shm_token: u32) -> CompositorResult;
         drain_queue();
+        compositor_wait(timeout_ms: u64) -> bool;
+        wallpaper_request_reload();
+        wallpaper_take_reload() -> bool;
         surface_request_frame_callback(task_id: u32) -> CompositorResult;
         surface_mark_frames_done(present_time_ms: u64);
         surface_poll_frame_done(task_id: u32) -> u64;
+        wait_vblank(after_seq: u64) -> u64;
+        surface_set_cursor_image(image: &[u8], hot_x: u32, hot_y: u32) -> bool;
+        surface_move_cursor(x: i32, y: i32);
+        output_enumerate(out: &mut [OutputInfo]) -> usize;
+        output_configure(id: u32, x: i32, y: i32, width: u32, height: u32, flags: u32) -> CompositorResult;
         surface_add_damage(task_id: u32, x: i32, y: i32, width: i32, height: i32) -> CompositorResult;
         surface_get_buffer_age(task_id: u32) -> u8;
+        surface_title(task_id: u32) -> Option<[u8; 32]>;
         surface_set_role(task_id: u32, role: u8) -> CompositorResult;
         surface_set_parent(task_id: u32, parent_task_id: u32) -> CompositorResult;
         surface_set_relative_position(task_id: u32, rel_x: i32, rel_y: i32) -> CompositorResult;
+        surface_set_opacity(task_id: u32, opacity: u8, flags: u8) -> CompositorResult;
+        surface_set_window_flags(task_id: u32, flags: u8) -> CompositorResult;
+        surface_set_buffer_scale(task_id: u32, scale: u32) -> CompositorResult;
+        surface_render_thumbnail(target_task: u32, process_id: u32, dst_token: u32, max_width: u32, max_height: u32) -> Result<(u32, u32), CompositorError>;
+        surface_present(task_id: u32, process_id: u32, token: u32, target_ns: u64, flags: u32, display_exclusive: bool) -> Result<u64, PresentError>;
+        present_feedback(task_id: u32) -> PresentFeedback;
         @no_wrapper fb_flip(phys_addr: PhysAddr, size: usize, damage: *const DamageRect, damage_count: u32) -> c_int;
         @no_wrapper roulette_draw(fate: u32) -> VideoResult;
         @no_wrapper surface_set_title(task_id: u32, ptr: *const u8, len: usize) -> CompositorResult;
@@ -25,11 +25,34 @@
 //! // In your serial driver init:
 //! slopos_lib::klog::klog_register_backend(my_backend_fn);
 //! ```
+//!
+//! # Tail
+//!
+//! Whatever the backend, the last [`KLOG_TAIL_LEN`] bytes of log text are
+//! also kept in memory, so crash reports can include them
+//! ([`klog_tail`]).
+//!
+//! # Subsystems
+//!
+//! Each line is logged under a [`KlogModule`]: the one its module path
+//! maps to, or the one named with `target:`:
+//!
+//! ```ignore
+//! klog_debug!("probing BARs");                  // drivers, from the path
+//! klog_debug!(target: Net, "rx ring {}", idx);  // net
+//! ```
+//!
+//! A subsystem follows the default level ([`klog_set_level`]) until it is
+//! given its own ([`klog_set_module_level`]).
 
+use core::cell::UnsafeCell;
 use core::ffi::c_int;
 use core::fmt;
-use core::sync::atomic::{AtomicPtr, AtomicU8, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, Ordering};
+
+use slopos_abi::klog::{KLOG_MODULE_COUNT, KLOG_MODULE_NAMES};
 
+use crate::cpu;
 use crate::ports::COM1;
 
 // ---------------------------------------------------------------------------
@@ -56,6 +79,11 @@ impl KlogLevel {
             _ => KlogLevel::Trace,
         }
     }
+
+    /// The level numbered `raw` in `slopos_abi::klog`.
+    pub fn from_abi(raw: u64) -> Option<Self> {
+        (raw <= KlogLevel::Trace as u64).then(|| Self::from_raw(raw as u8))
+    }
 }
 
 static CURRENT_LEVEL: AtomicU8 = AtomicU8::new(KlogLevel::Info as u8);
@@ -65,6 +93,132 @@ fn is_enabled(level: KlogLevel) -> bool {
     level as u8 <= CURRENT_LEVEL.load(Ordering::Relaxed)
 }
 
+// ---------------------------------------------------------------------------
+// Subsystems
+// ---------------------------------------------------------------------------
+
+/// A subsystem with its own log level, numbered as in `slopos_abi::klog`.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KlogModule {
+    Core = 0,
+    Boot = 1,
+    Mm = 2,
+    Sched = 3,
+    Net = 4,
+    Video = 5,
+    Fs = 6,
+    Input = 7,
+    Audio = 8,
+    Drivers = 9,
+}
+
+/// Module path prefixes and the subsystem they log under, most specific
+/// first.  Paths matching none are [`KlogModule::Core`].
+const MODULE_PATHS: &[(&str, KlogModule)] = &[
+    ("slopos_core::scheduler", KlogModule::Sched),
+    ("slopos_core::syscall::net", KlogModule::Net),
+    ("slopos_drivers::net", KlogModule::Net),
+    ("slopos_drivers::virtio_net", KlogModule::Net),
+    ("slopos_drivers::ps2", KlogModule::Input),
+    ("slopos_drivers::input_event", KlogModule::Input),
+    ("slopos_drivers::audio", KlogModule::Audio),
+    ("slopos_drivers::virtio_gpu", KlogModule::Video),
+    ("slopos_drivers::xe", KlogModule::Video),
+    ("slopos_drivers::virtio_blk", KlogModule::Fs),
+    ("slopos_drivers::nvme", KlogModule::Fs),
+    ("slopos_drivers", KlogModule::Drivers),
+    ("slopos_acpi", KlogModule::Drivers),
+    ("slopos_video", KlogModule::Video),
+    ("slopos_gfx", KlogModule::Video),
+    ("slopos_fs", KlogModule::Fs),
+    ("slopos_mm", KlogModule::Mm),
+    ("slopos_boot", KlogModule::Boot),
+];
+
+const fn starts_with(path: &str, prefix: &str) -> bool {
+    let (path, prefix) = (path.as_bytes(), prefix.as_bytes());
+    if path.len() < prefix.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if path[i] != prefix[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+impl KlogModule {
+    pub const ALL: [KlogModule; KLOG_MODULE_COUNT] = [
+        KlogModule::Core,
+        KlogModule::Boot,
+        KlogModule::Mm,
+        KlogModule::Sched,
+        KlogModule::Net,
+        KlogModule::Video,
+        KlogModule::Fs,
+        KlogModule::Input,
+        KlogModule::Audio,
+        KlogModule::Drivers,
+    ];
+
+    /// The subsystem numbered `raw` in `slopos_abi::klog`.
+    pub fn from_abi(raw: u64) -> Option<Self> {
+        Self::ALL.get(raw as usize).copied()
+    }
+
+    pub fn name(self) -> &'static str {
+        KLOG_MODULE_NAMES[self as usize]
+    }
+
+    /// The subsystem code in module `path` logs under.  Const, so the
+    /// macros resolve it at compile time.
+    pub const fn from_module_path(path: &str) -> Self {
+        let mut i = 0;
+        while i < MODULE_PATHS.len() {
+            let (prefix, module) = MODULE_PATHS[i];
+            if starts_with(path, prefix) {
+                return module;
+            }
+            i += 1;
+        }
+        KlogModule::Core
+    }
+}
+
+/// Stored for a subsystem that follows the default level.
+const LEVEL_INHERIT: u8 = u8::MAX;
+
+static MODULE_LEVELS: [AtomicU8; KLOG_MODULE_COUNT] =
+    [const { AtomicU8::new(LEVEL_INHERIT) }; KLOG_MODULE_COUNT];
+
+/// The most verbose level any subsystem logs at, so lines nobody wants are
+/// dropped with one load.
+static MAX_LEVEL: AtomicU8 = AtomicU8::new(KlogLevel::Info as u8);
+
+fn update_max_level() {
+    let max = MODULE_LEVELS
+        .iter()
+        .map(|level| level.load(Ordering::Relaxed))
+        .filter(|&level| level != LEVEL_INHERIT)
+        .fold(CURRENT_LEVEL.load(Ordering::Relaxed), u8::max);
+    MAX_LEVEL.store(max, Ordering::Relaxed);
+}
+
+#[inline(always)]
+fn is_module_enabled(module: KlogModule, level: KlogLevel) -> bool {
+    if level as u8 > MAX_LEVEL.load(Ordering::Relaxed) {
+        return false;
+    }
+    match MODULE_LEVELS[module as usize].load(Ordering::Relaxed) {
+        LEVEL_INHERIT => is_enabled(level),
+        own => level as u8 <= own,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Backend dispatch
 // ---------------------------------------------------------------------------
@@ -112,6 +266,97 @@ fn dispatch(args: fmt::Arguments<'_>) {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Tail
+// ---------------------------------------------------------------------------
+
+/// Bytes of recent log text kept for [`klog_tail`].
+pub const KLOG_TAIL_LEN: usize = 4096;
+
+struct TailBuf {
+    bytes: [u8; KLOG_TAIL_LEN],
+    /// Bytes written since boot; the next one goes at `written % len`.
+    written: u64,
+}
+
+impl fmt::Write for TailBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for &b in s.as_bytes() {
+            self.bytes[(self.written % KLOG_TAIL_LEN as u64) as usize] = b;
+            self.written += 1;
+        }
+        Ok(())
+    }
+}
+
+/// The tail buffer behind a spinlock taken with interrupts off.  Unlike
+/// `IrqMutex` it does not depend on the PCR, so any CPU can log at any
+/// point of boot.  The tail is best effort: a caller that cannot get the
+/// lock in [`TAIL_SPIN_LIMIT`] tries gives up rather than risk deadlocking
+/// a CPU that faulted while holding it.
+struct Tail {
+    buf: UnsafeCell<TailBuf>,
+    locked: AtomicBool,
+}
+
+// SAFETY: `buf` is only reached through `with`, which holds `locked`.
+unsafe impl Sync for Tail {}
+
+const TAIL_SPIN_LIMIT: u32 = 1 << 20;
+
+impl Tail {
+    fn with<R>(&self, f: impl FnOnce(&mut TailBuf) -> R) -> Option<R> {
+        let saved_flags = cpu::save_flags_cli();
+        let mut spins = 0;
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spins += 1;
+            if spins == TAIL_SPIN_LIMIT {
+                cpu::restore_flags(saved_flags);
+                return None;
+            }
+            core::hint::spin_loop();
+        }
+        // SAFETY: `locked` gives exclusive access.
+        let result = f(unsafe { &mut *self.buf.get() });
+        self.locked.store(false, Ordering::Release);
+        cpu::restore_flags(saved_flags);
+        Some(result)
+    }
+}
+
+static TAIL: Tail = Tail {
+    buf: UnsafeCell::new(TailBuf {
+        bytes: [0; KLOG_TAIL_LEN],
+        written: 0,
+    }),
+    locked: AtomicBool::new(false),
+};
+
+fn record_tail(args: fmt::Arguments<'_>) {
+    TAIL.with(|tail| {
+        let _ = fmt::write(tail, args);
+        let _ = fmt::Write::write_str(tail, "\n");
+    });
+}
+
+/// Copy the most recent log text, oldest first, into `out`.  Returns the
+/// number of bytes copied; the first line may be cut off.
+pub fn klog_tail(out: &mut [u8]) -> usize {
+    TAIL.with(|tail| {
+        let len = (tail.written.min(KLOG_TAIL_LEN as u64) as usize).min(out.len());
+        let start = tail.written - len as u64;
+        for (i, b) in out[..len].iter_mut().enumerate() {
+            *b = tail.bytes[((start + i as u64) % KLOG_TAIL_LEN as u64) as usize];
+        }
+        len
+    })
+    .unwrap_or(0)
+}
+
 // ---------------------------------------------------------------------------
 // Public API
 // ---------------------------------------------------------------------------
@@ -126,10 +371,13 @@ pub fn klog_register_backend(backend: KlogBackend) {
 /// Initialise klog (sets default level).  Called very early in boot.
 pub fn klog_init() {
     CURRENT_LEVEL.store(KlogLevel::Info as u8, Ordering::Relaxed);
+    update_max_level();
 }
 
+/// Set the default level, which subsystems without their own follow.
 pub fn klog_set_level(level: KlogLevel) {
     CURRENT_LEVEL.store(level as u8, Ordering::Relaxed);
+    update_max_level();
 }
 
 pub fn klog_get_level() -> KlogLevel {
@@ -144,15 +392,42 @@ pub fn is_enabled_level(level: KlogLevel) -> bool {
     is_enabled(level)
 }
 
-/// Emit a formatted log line at the given level.
+/// Give `module` its own level, or with `None` have it follow the default
+/// again.
+pub fn klog_set_module_level(module: KlogModule, level: Option<KlogLevel>) {
+    let raw = level.map_or(LEVEL_INHERIT, |level| level as u8);
+    MODULE_LEVELS[module as usize].store(raw, Ordering::Relaxed);
+    update_max_level();
+}
+
+/// `module`'s own level; `None` when it follows the default.
+pub fn klog_module_level(module: KlogModule) -> Option<KlogLevel> {
+    match MODULE_LEVELS[module as usize].load(Ordering::Relaxed) {
+        LEVEL_INHERIT => None,
+        raw => Some(KlogLevel::from_raw(raw)),
+    }
+}
+
+pub fn klog_module_enabled(module: KlogModule, level: KlogLevel) -> bool {
+    is_module_enabled(module, level)
+}
+
+/// Emit a formatted log line at the given level, under
+/// [`KlogModule::Core`].
 ///
 /// The backend appends a trailing newline — callers should **not** include
 /// one in their format string.
 pub fn log_args(level: KlogLevel, args: fmt::Arguments<'_>) {
-    if !is_enabled(level) {
+    log_module_args(KlogModule::Core, level, args);
+}
+
+/// Emit a formatted log line at the given level under `module`.
+pub fn log_module_args(module: KlogModule, level: KlogLevel, args: fmt::Arguments<'_>) {
+    if !is_module_enabled(module, level) {
         return;
     }
     dispatch(args);
+    record_tail(args);
 }
 
 // ---------------------------------------------------------------------------
@@ -161,42 +436,68 @@ pub fn log_args(level: KlogLevel, args: fmt::Arguments<'_>) {
 
 #[macro_export]
 macro_rules! klog {
+    (target: $module:ident, $level:expr, $($arg:tt)*) => {{
+        $crate::klog::log_module_args(
+            $crate::klog::KlogModule::$module,
+            $level,
+            ::core::format_args!($($arg)*),
+        );
+    }};
     ($level:expr, $($arg:tt)*) => {{
-        $crate::klog::log_args($level, ::core::format_args!($($arg)*));
+        $crate::klog::log_module_args(
+            const { $crate::klog::KlogModule::from_module_path(::core::module_path!()) },
+            $level,
+            ::core::format_args!($($arg)*),
+        );
     }};
 }
 
 #[macro_export]
 macro_rules! klog_error {
+    (target: $module:ident, $($arg:tt)*) => {
+        $crate::klog!(target: $module, $crate::klog::KlogLevel::Error, $($arg)*)
+    };
     ($($arg:tt)*) => {
-        $crate::klog::log_args($crate::klog::KlogLevel::Error, ::core::format_args!($($arg)*))
+        $crate::klog!($crate::klog::KlogLevel::Error, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! klog_warn {
+    (target: $module:ident, $($arg:tt)*) => {
+        $crate::klog!(target: $module, $crate::klog::KlogLevel::Warn, $($arg)*)
+    };
     ($($arg:tt)*) => {
-        $crate::klog::log_args($crate::klog::KlogLevel::Warn, ::core::format_args!($($arg)*))
+        $crate::klog!($crate::klog::KlogLevel::Warn, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! klog_info {
+    (target: $module:ident, $($arg:tt)*) => {
+        $crate::klog!(target: $module, $crate::klog::KlogLevel::Info, $($arg)*)
+    };
     ($($arg:tt)*) => {
-        $crate::klog::log_args($crate::klog::KlogLevel::Info, ::core::format_args!($($arg)*))
+        $crate::klog!($crate::klog::KlogLevel::Info, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! klog_debug {
+    (target: $module:ident, $($arg:tt)*) => {
+        $crate::klog!(target: $module, $crate::klog::KlogLevel::Debug, $($arg)*)
+    };
     ($($arg:tt)*) => {
-        $crate::klog::log_args($crate::klog::KlogLevel::Debug, ::core::format_args!($($arg)*))
+        $crate::klog!($crate::klog::KlogLevel::Debug, $($arg)*)
     };
 }
 
 #[macro_export]
 macro_rules! klog_trace {
+    (target: $module:ident, $($arg:tt)*) => {
+        $crate::klog!(target: $module, $crate::klog::KlogLevel::Trace, $($arg)*)
+    };
     ($($arg:tt)*) => {
-        $crate::klog::log_args($crate::klog::KlogLevel::Trace, ::core::format_args!($($arg)*))
+        $crate::klog!($crate::klog::KlogLevel::Trace, $($arg)*)
     };
 }
@@ -0,0 +1,340 @@
+//! Kernel event tracer (ktrace).
+//!
+//! Records timestamped scheduler switches and wakeups, syscall entry/exit,
+//! IRQ entry/exit and page faults into fixed in-memory rings so short
+//! timelines can be inspected after the fact (see `slopos_core::ktrace` for
+//! the Chrome trace exporter and `SYSCALL_KTRACE` for the controls).
+//!
+//! Each CPU records into its own ring, so CPUs do not contend on one slot
+//! counter; readers merge the rings back into one timeline by timestamp.
+//! CPUs past [`KTRACE_CPU_RINGS`] share rings, which stay correct with
+//! several writers.
+//!
+//! Tracing is off by default.  While it is off, [`ktrace_record`] costs one
+//! relaxed load.  While it is on, each event takes a slot with a single
+//! `fetch_add` and overwrites the oldest event once the ring is full.
+//!
+//! Events can only be read back after [`ktrace_stop`].  `ktrace_stop` waits
+//! for any writer still filling a slot, so a stopped ring is stable.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use slopos_abi::syscall::{
+    KTRACE_KIND_IRQ_ENTER, KTRACE_KIND_IRQ_EXIT, KTRACE_KIND_PAGE_FAULT, KTRACE_KIND_SCHED_SWITCH,
+    KTRACE_KIND_SCHED_WAKEUP, KTRACE_KIND_SYSCALL_ENTER, KTRACE_KIND_SYSCALL_EXIT, KtraceRecord,
+};
+
+use crate::{clock, pcr};
+
+pub use slopos_abi::syscall::KTRACE_NO_TASK;
+
+/// Rings in the global tracer, one per CPU up to this many.
+pub const KTRACE_CPU_RINGS: usize = 8;
+
+/// Events kept by each ring; older events are overwritten.
+pub const KTRACE_CPU_CAPACITY: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KtraceKind {
+    /// `arg0` = outgoing task id, `arg1` = incoming task id.
+    SchedSwitch = KTRACE_KIND_SCHED_SWITCH,
+    /// `arg0` = syscall number, `arg1` = task id.
+    SyscallEnter = KTRACE_KIND_SYSCALL_ENTER,
+    /// `arg0` = syscall number, `arg1` = task id.
+    SyscallExit = KTRACE_KIND_SYSCALL_EXIT,
+    /// `arg0` = interrupt vector.
+    IrqEnter = KTRACE_KIND_IRQ_ENTER,
+    /// `arg0` = interrupt vector.
+    IrqExit = KTRACE_KIND_IRQ_EXIT,
+    /// `arg0` = woken task id, `arg1` = waking task id.
+    SchedWakeup = KTRACE_KIND_SCHED_WAKEUP,
+    /// `arg0` = error code, `arg1` = task id, `addr` = faulting address.
+    PageFault = KTRACE_KIND_PAGE_FAULT,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KtraceEvent {
+    pub timestamp_ns: u64,
+    pub kind: KtraceKind,
+    pub cpu: u8,
+    pub arg0: u32,
+    pub arg1: u32,
+    /// Address the event is about; 0 for kinds that have none.
+    pub addr: u64,
+}
+
+impl KtraceEvent {
+    const EMPTY: Self = Self {
+        timestamp_ns: 0,
+        kind: KtraceKind::SchedSwitch,
+        cpu: 0,
+        arg0: 0,
+        arg1: 0,
+        addr: 0,
+    };
+
+    /// The event as `KTRACE_OP_READ` hands it to userland.
+    pub fn to_record(&self) -> KtraceRecord {
+        KtraceRecord {
+            timestamp_ns: self.timestamp_ns,
+            addr: self.addr,
+            arg0: self.arg0,
+            arg1: self.arg1,
+            kind: self.kind as u8,
+            cpu: self.cpu,
+            _reserved: [0; 6],
+        }
+    }
+}
+
+/// Lock-free ring of `N` events, safe to write from several CPUs at once.
+/// The global tracer keeps one per CPU in a [`KtraceCpuRings`].
+pub struct KtraceRing<const N: usize> {
+    events: UnsafeCell<[KtraceEvent; N]>,
+    enabled: AtomicBool,
+    /// Events recorded since the last start, including overwritten ones.
+    next: AtomicUsize,
+    /// Writers between claiming a slot and finishing the store.
+    in_flight: AtomicUsize,
+}
+
+// SAFETY: writers only touch the slot they claimed with `fetch_add`, and
+// readers only run once `stop` has drained `in_flight`.
+unsafe impl<const N: usize> Sync for KtraceRing<N> {}
+
+impl<const N: usize> Default for KtraceRing<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> KtraceRing<N> {
+    pub const fn new() -> Self {
+        Self {
+            events: UnsafeCell::new([KtraceEvent::EMPTY; N]),
+            enabled: AtomicBool::new(false),
+            next: AtomicUsize::new(0),
+            in_flight: AtomicUsize::new(0),
+        }
+    }
+
+    /// Discard recorded events and start recording.
+    pub fn start(&self) {
+        self.stop();
+        self.next.store(0, Ordering::SeqCst);
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Stop recording and wait for in-progress writers to finish.
+    pub fn stop(&self) {
+        self.enabled.store(false, Ordering::SeqCst);
+        while self.in_flight.load(Ordering::SeqCst) != 0 {
+            core::hint::spin_loop();
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn record(&self, event: KtraceEvent) {
+        if !self.is_enabled() {
+            return;
+        }
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        // Re-check under `in_flight` so `stop` cannot miss this writer.
+        if self.enabled.load(Ordering::SeqCst) {
+            let slot = self.next.fetch_add(1, Ordering::Relaxed) % N;
+            // SAFETY: `slot` was claimed by this writer alone; the ring only
+            // wraps onto it again after N more claims.
+            unsafe { (*self.events.get())[slot] = event };
+        }
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Events recorded since the last start that were overwritten.
+    pub fn dropped(&self) -> usize {
+        self.next.load(Ordering::Relaxed).saturating_sub(N)
+    }
+
+    /// Sequence numbers of the retained events, oldest first.
+    fn window(&self) -> core::ops::Range<usize> {
+        let total = self.next.load(Ordering::SeqCst);
+        total.saturating_sub(N)..total
+    }
+
+    /// The event with sequence number `seq`.
+    ///
+    /// # Safety
+    /// Recording must be stopped.
+    unsafe fn event(&self, seq: usize) -> &KtraceEvent {
+        // SAFETY: the caller guarantees `stop` drained every writer.
+        unsafe { &(*self.events.get())[seq % N] }
+    }
+
+    /// Visit the retained events oldest first.  Returns how many were
+    /// visited, or `None` while recording is still on.
+    pub fn for_each(&self, mut f: impl FnMut(&KtraceEvent)) -> Option<usize> {
+        if self.enabled.load(Ordering::SeqCst) {
+            return None;
+        }
+        let window = self.window();
+        let count = window.len();
+        for seq in window {
+            // SAFETY: recording is off.
+            f(unsafe { self.event(seq) });
+        }
+        Some(count)
+    }
+}
+
+/// `CPUS` rings of `N` events, each written by the CPUs whose id maps to
+/// it.  The kernel uses the global instance behind the `ktrace_*`
+/// functions; tests drive private ones.
+pub struct KtraceCpuRings<const CPUS: usize, const N: usize> {
+    rings: [KtraceRing<N>; CPUS],
+}
+
+impl<const CPUS: usize, const N: usize> Default for KtraceCpuRings<CPUS, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const CPUS: usize, const N: usize> KtraceCpuRings<CPUS, N> {
+    pub const fn new() -> Self {
+        Self {
+            rings: [const { KtraceRing::new() }; CPUS],
+        }
+    }
+
+    /// Discard recorded events and start recording on every ring.
+    pub fn start(&self) {
+        self.stop();
+        for ring in &self.rings {
+            ring.start();
+        }
+    }
+
+    /// Stop recording and wait for in-progress writers to finish.
+    pub fn stop(&self) {
+        for ring in &self.rings {
+            ring.stop();
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.rings[0].is_enabled()
+    }
+
+    /// Record `event` in the ring of `event.cpu`.
+    #[inline]
+    pub fn record(&self, event: KtraceEvent) {
+        self.rings[event.cpu as usize % CPUS].record(event);
+    }
+
+    /// Events recorded since the last start that were overwritten.
+    pub fn dropped(&self) -> usize {
+        self.rings.iter().map(KtraceRing::dropped).sum()
+    }
+
+    /// Visit the retained events of all rings as one timeline, oldest
+    /// first.  Returns how many were visited, or `None` while recording is
+    /// still on.
+    pub fn for_each(&self, mut f: impl FnMut(&KtraceEvent)) -> Option<usize> {
+        if self
+            .rings
+            .iter()
+            .any(|ring| ring.enabled.load(Ordering::SeqCst))
+        {
+            return None;
+        }
+        let mut cursors: [core::ops::Range<usize>; CPUS] =
+            core::array::from_fn(|i| self.rings[i].window());
+        let mut count = 0;
+        loop {
+            // Each ring is in order, so the oldest event left is at the
+            // head of one of them.  Ties go to the lower ring.
+            let mut oldest: Option<(usize, &KtraceEvent)> = None;
+            for (i, cursor) in cursors.iter().enumerate() {
+                if cursor.is_empty() {
+                    continue;
+                }
+                // SAFETY: every ring is stopped.
+                let head = unsafe { self.rings[i].event(cursor.start) };
+                if oldest.is_none_or(|(_, e)| head.timestamp_ns < e.timestamp_ns) {
+                    oldest = Some((i, head));
+                }
+            }
+            let Some((i, event)) = oldest else {
+                return Some(count);
+            };
+            f(event);
+            cursors[i].start += 1;
+            count += 1;
+        }
+    }
+}
+
+static KTRACE: KtraceCpuRings<KTRACE_CPU_RINGS, KTRACE_CPU_CAPACITY> = KtraceCpuRings::new();
+
+#[inline]
+fn record(kind: KtraceKind, arg0: u32, arg1: u32, addr: u64) {
+    KTRACE.record(KtraceEvent {
+        timestamp_ns: clock::monotonic_ns(),
+        kind,
+        cpu: pcr::current_cpu_id() as u8,
+        arg0,
+        arg1,
+        addr,
+    });
+}
+
+/// Record an event on the current CPU if tracing is on.
+#[inline]
+pub fn ktrace_record(kind: KtraceKind, arg0: u32, arg1: u32) {
+    if !KTRACE.is_enabled() {
+        return;
+    }
+    record(kind, arg0, arg1, 0);
+}
+
+/// Record a page fault at `addr` by `task` on the current CPU if tracing
+/// is on.
+#[inline]
+pub fn ktrace_record_page_fault(addr: u64, error_code: u32, task: u32) {
+    if !KTRACE.is_enabled() {
+        return;
+    }
+    record(KtraceKind::PageFault, error_code, task, addr);
+}
+
+/// Clear the rings and start tracing.
+pub fn ktrace_start() {
+    KTRACE.start();
+}
+
+/// Stop tracing; the recorded events stay readable until the next start.
+pub fn ktrace_stop() {
+    KTRACE.stop();
+}
+
+pub fn ktrace_is_enabled() -> bool {
+    KTRACE.is_enabled()
+}
+
+/// Events lost to wrap-around since the last start.
+pub fn ktrace_dropped() -> usize {
+    KTRACE.dropped()
+}
+
+/// Visit the recorded events of every CPU oldest first; `None` while
+/// tracing is on.
+pub fn ktrace_for_each(f: impl FnMut(&KtraceEvent)) -> Option<usize> {
+    KTRACE.for_each(f)
+}
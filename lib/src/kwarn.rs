@@ -0,0 +1,210 @@
+//! Kernel warnings and assertions: `kwarn!`, `kwarn_once!` and `kbug!`.
+//!
+//! A plain `klog_warn!` is one line in a busy serial log and easy to miss.
+//! These macros are for conditions that indicate a kernel bug rather than
+//! bad input or flaky hardware.  Each hit is counted, reported with its
+//! call site and a frame-pointer backtrace, and can escalate to a panic
+//! when `panic_on_warn` is set (cmdline `panic_on_warn=1` or
+//! `SYSCALL_KWARN_STATS`).
+//!
+//! ```ignore
+//! if kwarn!(block.is_null(), "kfree: null block from {:?}", caller) {
+//!     return;
+//! }
+//! kwarn_once!(fb.is_none(), "framebuffer state missing after init");
+//! kbug!(refcount == 0, "dropping dead page 0x{:x}", phys);
+//! ```
+//!
+//! `kwarn!` and `kwarn_once!` evaluate to the condition so they can guard
+//! the recovery path.  `kwarn!` reports a given call site at most once per
+//! [`KWARN_RATELIMIT_MS`] and mentions how many hits it swallowed;
+//! `kwarn_once!` reports only the first hit.  `kbug!` always panics.
+//!
+//! Backtraces print return addresses only: the kernel carries no symbol
+//! table, so resolve them with `addr2line -e kernel <rip>`.
+
+use core::ffi::c_int;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+use slopos_abi::syscall::KwarnStats;
+
+use crate::clock;
+use crate::cpu;
+use crate::stacktrace::{self, StacktraceEntry};
+
+/// Minimum gap between two reports from the same `kwarn!` call site.
+pub const KWARN_RATELIMIT_MS: u64 = 5_000;
+
+const KWARN_BACKTRACE_DEPTH: usize = 8;
+
+/// `last_report_ms` value for a site that has never been reported.
+const NEVER_REPORTED: u64 = u64::MAX;
+
+static WARNINGS: AtomicU64 = AtomicU64::new(0);
+static SUPPRESSED: AtomicU64 = AtomicU64::new(0);
+static BUGS: AtomicU64 = AtomicU64::new(0);
+static PANIC_ON_WARN: AtomicBool = AtomicBool::new(false);
+
+/// Per-call-site state.  Each macro expansion owns one `static` of these.
+pub struct WarnSite {
+    file: &'static str,
+    line: u32,
+    once: bool,
+    last_report_ms: AtomicU64,
+    /// Hits swallowed since the last report.
+    suppressed: AtomicU32,
+}
+
+impl WarnSite {
+    pub const fn new(file: &'static str, line: u32, once: bool) -> Self {
+        Self {
+            file,
+            line,
+            once,
+            last_report_ms: AtomicU64::new(NEVER_REPORTED),
+            suppressed: AtomicU32::new(0),
+        }
+    }
+
+    /// Decide whether this hit gets logged.  Returns the number of hits
+    /// swallowed since the previous report, or `None` to stay quiet.
+    fn admit(&self) -> Option<u32> {
+        let last = self.last_report_ms.load(Ordering::Relaxed);
+        let now = clock::uptime_ms();
+        let due = if self.once {
+            last == NEVER_REPORTED
+        } else {
+            last == NEVER_REPORTED || now.saturating_sub(last) >= KWARN_RATELIMIT_MS
+        };
+        // A racing CPU that wins the exchange reports instead of us.
+        if due
+            && self
+                .last_report_ms
+                .compare_exchange(last, now, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+        {
+            return Some(self.suppressed.swap(0, Ordering::Relaxed));
+        }
+        self.suppressed.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+}
+
+fn log_backtrace() {
+    let mut entries = [StacktraceEntry {
+        frame_pointer: 0,
+        return_address: 0,
+    }; KWARN_BACKTRACE_DEPTH];
+    let captured = stacktrace::stacktrace_capture_from(
+        cpu::read_rbp(),
+        entries.as_mut_ptr(),
+        KWARN_BACKTRACE_DEPTH as c_int,
+    );
+    for (i, entry) in entries[..captured.max(0) as usize].iter().enumerate() {
+        crate::klog_warn!("  #{} rip=0x{:016x}", i, entry.return_address);
+    }
+}
+
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+pub fn warn_hit(site: &'static WarnSite, args: fmt::Arguments<'_>) {
+    WARNINGS.fetch_add(1, Ordering::Relaxed);
+    match site.admit() {
+        Some(skipped) => {
+            crate::klog_warn!("WARNING: {}:{}: {}", site.file, site.line, args);
+            if skipped > 0 {
+                crate::klog_warn!("  ({} earlier hits suppressed)", skipped);
+            }
+            log_backtrace();
+        }
+        None => {
+            SUPPRESSED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    if PANIC_ON_WARN.load(Ordering::Relaxed) {
+        panic!("panic_on_warn: warning at {}:{}", site.file, site.line);
+    }
+}
+
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+pub fn bug_hit(file: &'static str, line: u32, args: fmt::Arguments<'_>) -> ! {
+    BUGS.fetch_add(1, Ordering::Relaxed);
+    crate::klog_error!("BUG: {}:{}: {}", file, line, args);
+    log_backtrace();
+    panic!("BUG at {}:{}", file, line);
+}
+
+pub fn kwarn_stats() -> KwarnStats {
+    KwarnStats {
+        warnings: WARNINGS.load(Ordering::Relaxed),
+        suppressed: SUPPRESSED.load(Ordering::Relaxed),
+        bugs: BUGS.load(Ordering::Relaxed),
+        panic_on_warn: PANIC_ON_WARN.load(Ordering::Relaxed) as u32,
+        _reserved: 0,
+    }
+}
+
+pub fn kwarn_panic_on_warn() -> bool {
+    PANIC_ON_WARN.load(Ordering::Relaxed)
+}
+
+pub fn kwarn_set_panic_on_warn(enabled: bool) {
+    PANIC_ON_WARN.store(enabled, Ordering::Relaxed);
+}
+
+/// Warn if `cond` holds, rate-limited per call site.  Evaluates to `cond`.
+#[macro_export]
+macro_rules! kwarn {
+    ($cond:expr $(,)?) => {
+        $crate::kwarn!($cond, "{}", ::core::stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {{
+        let cond: bool = $cond;
+        if cond {
+            static SITE: $crate::kwarn::WarnSite =
+                $crate::kwarn::WarnSite::new(::core::file!(), ::core::line!(), false);
+            $crate::kwarn::warn_hit(&SITE, ::core::format_args!($($arg)+));
+        }
+        cond
+    }};
+}
+
+/// Warn the first time `cond` holds; later hits are only counted.
+/// Evaluates to `cond`.
+#[macro_export]
+macro_rules! kwarn_once {
+    ($cond:expr $(,)?) => {
+        $crate::kwarn_once!($cond, "{}", ::core::stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {{
+        let cond: bool = $cond;
+        if cond {
+            static SITE: $crate::kwarn::WarnSite =
+                $crate::kwarn::WarnSite::new(::core::file!(), ::core::line!(), true);
+            $crate::kwarn::warn_hit(&SITE, ::core::format_args!($($arg)+));
+        }
+        cond
+    }};
+}
+
+/// Report and panic if `cond` holds.  For states the kernel cannot
+/// continue from, regardless of `panic_on_warn`.
+#[macro_export]
+macro_rules! kbug {
+    ($cond:expr $(,)?) => {
+        $crate::kbug!($cond, "{}", ::core::stringify!($cond))
+    };
+    ($cond:expr, $($arg:tt)+) => {
+        if $cond {
+            $crate::kwarn::bug_hit(
+                ::core::file!(),
+                ::core::line!(),
+                ::core::format_args!($($arg)+),
+            );
+        }
+    };
+}
@@ -5,7 +5,9 @@
 
 pub mod arch;
 pub mod boot_info;
+pub mod boot_watchdog;
 pub mod clock;
+pub mod compositor_wake;
 pub mod cpu;
 
 pub mod io;
@@ -13,6 +15,21 @@ pub mod ports;
 
 pub mod tsc {
     use core::arch::asm;
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    /// TSC frequency measured against a reference clock, 0 until calibrated.
+    static CALIBRATED_CYCLES_PER_MS: AtomicU64 = AtomicU64::new(0);
+
+    /// Record a TSC frequency measured by a timer driver (HPET at boot).
+    pub fn set_cycles_per_ms(cycles_per_ms: u64) {
+        CALIBRATED_CYCLES_PER_MS.store(cycles_per_ms, Ordering::Relaxed);
+    }
+
+    /// Calibrated TSC cycles per millisecond, or `0` if never measured.
+    #[inline]
+    pub fn calibrated_cycles_per_ms() -> u64 {
+        CALIBRATED_CYCLES_PER_MS.load(Ordering::Relaxed)
+    }
 
     #[inline(always)]
     pub fn rdtsc() -> u64 {
@@ -32,10 +49,15 @@ pub mod tsc {
 
 pub mod alignment;
 pub mod cpu_local;
+pub mod handle_table;
 pub mod init_flag;
 pub mod kdiag;
 pub mod kernel_services;
 pub mod klog;
+pub mod ktrace;
+pub mod kwarn;
+pub mod lockdep;
+pub mod lockstat;
 pub mod memory;
 pub mod numfmt;
 pub mod once_lock;
@@ -46,10 +68,13 @@ pub mod ring_buffer;
 pub mod service_cell;
 pub mod service_macro;
 pub mod spinlock;
+pub mod stack_protector;
+pub mod stack_watermark;
 pub mod stacktrace;
 pub mod string;
 pub mod testing;
 pub mod waitqueue;
+pub mod walltime;
 pub mod wl_currency;
 
 #[doc(hidden)]
@@ -58,9 +83,10 @@ pub use paste;
 pub use alignment::{align_down_u64, align_down_usize, align_up_u64, align_up_usize};
 pub use alignment::{align_down_usize as align_down, align_up_usize as align_up};
 pub use kdiag::kdiag_dump_interrupt_frame;
-pub use kdiag::{InterruptFrame, KDIAG_STACK_TRACE_DEPTH, kdiag_timestamp};
+pub use kdiag::{InterruptFrame, KDIAG_STACK_TRACE_DEPTH, RegSnapshot, kdiag_timestamp};
 pub use klog::{
-    KlogLevel, klog_get_level, klog_init, klog_is_enabled, klog_register_backend, klog_set_level,
+    KLOG_TAIL_LEN, KlogLevel, KlogModule, klog_get_level, klog_init, klog_is_enabled,
+    klog_module_level, klog_register_backend, klog_set_level, klog_set_module_level, klog_tail,
 };
 pub use ports::COM1;
 pub use preempt::{IrqPreemptGuard, PreemptGuard, is_preemption_disabled, preempt_count};
@@ -0,0 +1,406 @@
+//! Lock dependency validator (the `lockdep` feature).
+//!
+//! Every [`IrqMutex`](crate::IrqMutex) and [`IrqRwLock`](crate::IrqRwLock)
+//! built with a [`LockClass`] reports its acquisitions here.  Each CPU keeps
+//! the stack of classes it holds, and taking class B while holding class A
+//! records the edge A → B in a global order graph, together with the stack
+//! trace of the first time it happened.  Two mistakes panic:
+//!
+//! - an **inversion**: taking B while holding A when B → … → A is already
+//!   in the graph, i.e. some other path takes the same locks the other way
+//!   round.  Both traces are printed: the earlier one here, the current
+//!   one by the panic handler.
+//! - an **IRQ-unsafe acquisition**: taking a lock with interrupts enabled
+//!   while another is held.  Guards restore the interrupt flag saved when
+//!   they were taken, so this means one was dropped out of order (or
+//!   something re-enabled interrupts) and an interrupt handler taking the
+//!   held lock would spin forever.
+//!
+//! The check runs before spinning, so an inversion is caught on the first
+//! run that takes the locks in the wrong order, not only on the run that
+//! deadlocks.  `try_lock` cannot wait, so it adds no edges but its lock
+//! still counts as held.  Locks of one class may nest (the per-CPU run
+//! queues are taken in CPU order); only edges between classes are checked.
+//! Locks without a class are not tracked.
+//!
+//! Without the feature the functions below exist but record nothing.  The
+//! first report turns the validator off, so the panic path can take locks
+//! freely.
+
+use crate::lockstat::LockClass;
+
+#[cfg(feature = "lockdep")]
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicU64, AtomicUsize, Ordering};
+
+#[cfg(feature = "lockdep")]
+use crate::stacktrace::{self, StacktraceEntry};
+#[cfg(feature = "lockdep")]
+use crate::{MAX_CPUS, cpu, klog_info, pcr};
+
+/// Most classes the order graph tracks; later ones are ignored.
+pub const LOCKDEP_MAX_CLASSES: usize = 64;
+/// Deepest nesting tracked per CPU.
+#[cfg(feature = "lockdep")]
+const MAX_HELD: usize = 16;
+/// Edges whose first stack trace is kept.
+#[cfg(feature = "lockdep")]
+const MAX_EDGE_TRACES: usize = 256;
+#[cfg(feature = "lockdep")]
+const TRACE_DEPTH: usize = 8;
+/// Class id of a class that came after the table filled up.
+#[cfg(feature = "lockdep")]
+const UNTRACKED: u8 = u8::MAX;
+
+/// The validator's per-class state, embedded in [`LockClass`].
+#[cfg(feature = "lockdep")]
+pub(crate) struct ClassDep {
+    /// 0 before the class is first taken, then its graph index plus one.
+    id: AtomicU8,
+}
+
+#[cfg(feature = "lockdep")]
+impl ClassDep {
+    pub(crate) const fn new() -> Self {
+        Self {
+            id: AtomicU8::new(0),
+        }
+    }
+}
+
+#[cfg(feature = "lockdep")]
+static CLASSES: [AtomicPtr<LockClass>; LOCKDEP_MAX_CLASSES] =
+    [const { AtomicPtr::new(core::ptr::null_mut()) }; LOCKDEP_MAX_CLASSES];
+#[cfg(feature = "lockdep")]
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+/// Bit `b` of `AFTER[a]`: class `b` has been taken while `a` was held.
+#[cfg(feature = "lockdep")]
+static AFTER: [AtomicU64; LOCKDEP_MAX_CLASSES] = [const { AtomicU64::new(0) }; LOCKDEP_MAX_CLASSES];
+#[cfg(feature = "lockdep")]
+static TRACES: [EdgeTrace; MAX_EDGE_TRACES] = [const { EdgeTrace::new() }; MAX_EDGE_TRACES];
+#[cfg(feature = "lockdep")]
+static HELD: [HeldLocks; MAX_CPUS] = [const { HeldLocks::new() }; MAX_CPUS];
+/// Set by the first report.
+#[cfg(feature = "lockdep")]
+static TRIPPED: AtomicBool = AtomicBool::new(false);
+
+/// Where an edge of the order graph was first seen.
+#[cfg(feature = "lockdep")]
+struct EdgeTrace {
+    /// `from << 8 | to`, plus one; 0 while the slot is free.
+    key: AtomicUsize,
+    frames: [AtomicU64; TRACE_DEPTH],
+}
+
+#[cfg(feature = "lockdep")]
+impl EdgeTrace {
+    const fn new() -> Self {
+        Self {
+            key: AtomicUsize::new(0),
+            frames: [const { AtomicU64::new(0) }; TRACE_DEPTH],
+        }
+    }
+}
+
+/// Classes held by one CPU, innermost last.  Only that CPU touches it, with
+/// interrupts off.
+#[cfg(feature = "lockdep")]
+struct HeldLocks {
+    /// May exceed `MAX_HELD`; the extra entries are not recorded.
+    depth: AtomicUsize,
+    classes: [AtomicU8; MAX_HELD],
+}
+
+#[cfg(feature = "lockdep")]
+impl HeldLocks {
+    const fn new() -> Self {
+        Self {
+            depth: AtomicUsize::new(0),
+            classes: [const { AtomicU8::new(0) }; MAX_HELD],
+        }
+    }
+
+    fn ids(&self, out: &mut [u8; MAX_HELD]) -> usize {
+        let n = self.depth.load(Ordering::Relaxed).min(MAX_HELD);
+        for (slot, class) in out.iter_mut().zip(&self.classes[..n]) {
+            *slot = class.load(Ordering::Relaxed);
+        }
+        n
+    }
+
+    fn push(&self, id: u8) {
+        let depth = self.depth.load(Ordering::Relaxed);
+        if let Some(slot) = self.classes.get(depth) {
+            slot.store(id, Ordering::Relaxed);
+        }
+        self.depth.store(depth + 1, Ordering::Relaxed);
+    }
+
+    /// Drop the innermost entry for `id`; guards may be dropped in any
+    /// order.
+    fn remove(&self, id: u8) {
+        let depth = self.depth.load(Ordering::Relaxed);
+        if depth == 0 {
+            return;
+        }
+        if depth > MAX_HELD {
+            self.depth.store(depth - 1, Ordering::Relaxed);
+            return;
+        }
+        let Some(pos) = (0..depth)
+            .rev()
+            .find(|&i| self.classes[i].load(Ordering::Relaxed) == id)
+        else {
+            return;
+        };
+        for i in pos..depth - 1 {
+            let next = self.classes[i + 1].load(Ordering::Relaxed);
+            self.classes[i].store(next, Ordering::Relaxed);
+        }
+        self.depth.store(depth - 1, Ordering::Relaxed);
+    }
+}
+
+/// Graph index of `class`, numbering it on first use.
+#[cfg(feature = "lockdep")]
+fn class_id(class: &'static LockClass) -> Option<u8> {
+    let dep = &class.dep;
+    match dep.id.load(Ordering::Acquire) {
+        0 => {}
+        UNTRACKED => return None,
+        id => return Some(id - 1),
+    }
+    let next = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let id = if next < LOCKDEP_MAX_CLASSES {
+        next as u8 + 1
+    } else {
+        UNTRACKED
+    };
+    match dep
+        .id
+        .compare_exchange(0, id, Ordering::AcqRel, Ordering::Acquire)
+    {
+        Ok(_) if id != UNTRACKED => {
+            CLASSES[next].store(
+                class as *const LockClass as *mut LockClass,
+                Ordering::Release,
+            );
+            Some(id - 1)
+        }
+        Ok(_) | Err(UNTRACKED) => None,
+        // Another CPU numbered it first; our index stays unused.
+        Err(id) => Some(id - 1),
+    }
+}
+
+#[cfg(feature = "lockdep")]
+fn class_name(id: u8) -> &'static str {
+    let class = CLASSES[id as usize].load(Ordering::Acquire);
+    if class.is_null() {
+        return "?";
+    }
+    // SAFETY: only `&'static LockClass` values are stored.
+    unsafe { &*class }.name()
+}
+
+#[cfg(feature = "lockdep")]
+fn edge_key(from: u8, to: u8) -> usize {
+    ((from as usize) << 8 | to as usize) + 1
+}
+
+#[cfg(feature = "lockdep")]
+fn record_trace(from: u8, to: u8) {
+    let key = edge_key(from, to);
+    let Some(slot) = TRACES.iter().find(|slot| {
+        slot.key
+            .compare_exchange(0, key, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+    }) else {
+        return;
+    };
+    let mut entries = [StacktraceEntry {
+        frame_pointer: 0,
+        return_address: 0,
+    }; TRACE_DEPTH];
+    let n = stacktrace::stacktrace_capture_from(
+        cpu::read_rbp(),
+        entries.as_mut_ptr(),
+        TRACE_DEPTH as core::ffi::c_int,
+    );
+    for (frame, entry) in slot.frames.iter().zip(&entries[..n.max(0) as usize]) {
+        frame.store(entry.return_address, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "lockdep")]
+fn dump_trace(from: u8, to: u8) {
+    klog_info!(
+        "LOCKDEP: '{}' then '{}' first taken at:",
+        class_name(from),
+        class_name(to)
+    );
+    let key = edge_key(from, to);
+    let Some(slot) = TRACES
+        .iter()
+        .find(|slot| slot.key.load(Ordering::Acquire) == key)
+    else {
+        klog_info!("LOCKDEP:   (trace not kept)");
+        return;
+    };
+    for (i, frame) in slot.frames.iter().enumerate() {
+        match frame.load(Ordering::Relaxed) {
+            0 => break,
+            rip => klog_info!("LOCKDEP:   Frame {}: RIP=0x{:x}", i, rip),
+        }
+    }
+}
+
+/// A path `src` → … → `dst` in the order graph, as each class's parent on
+/// it.
+#[cfg(feature = "lockdep")]
+fn find_path(src: u8, dst: u8) -> Option<[u8; LOCKDEP_MAX_CLASSES]> {
+    let mut parent = [UNTRACKED; LOCKDEP_MAX_CLASSES];
+    let mut queue = [0u8; LOCKDEP_MAX_CLASSES];
+    let (mut head, mut tail) = (0, 1);
+    let mut seen = 1u64 << src;
+    queue[0] = src;
+    while head < tail {
+        let node = queue[head];
+        head += 1;
+        let mut next = AFTER[node as usize].load(Ordering::Acquire) & !seen;
+        while next != 0 {
+            let k = next.trailing_zeros() as u8;
+            next &= next - 1;
+            seen |= 1u64 << k;
+            parent[k as usize] = node;
+            if k == dst {
+                return Some(parent);
+            }
+            queue[tail] = k;
+            tail += 1;
+        }
+    }
+    None
+}
+
+#[cfg(feature = "lockdep")]
+#[cold]
+fn report_inversion(held: u8, taking: u8, parent: &[u8; LOCKDEP_MAX_CLASSES]) -> ! {
+    TRIPPED.store(true, Ordering::Relaxed);
+    klog_info!(
+        "LOCKDEP: lock order inversion on CPU {}: taking '{}' while holding '{}'",
+        pcr::current_cpu_id(),
+        class_name(taking),
+        class_name(held)
+    );
+    klog_info!("LOCKDEP: but the opposite order was seen before:");
+    // Walk back from `held` to `taking`, printing each edge.
+    let mut node = held;
+    while node != taking {
+        let prev = parent[node as usize];
+        dump_trace(prev, node);
+        node = prev;
+    }
+    klog_info!("LOCKDEP: current acquisition follows");
+    panic!(
+        "lockdep: '{}' taken while holding '{}', inverting an earlier order",
+        class_name(taking),
+        class_name(held)
+    );
+}
+
+#[cfg(feature = "lockdep")]
+#[cold]
+fn report_irq_unsafe(held: u8, taking: u8) -> ! {
+    TRIPPED.store(true, Ordering::Relaxed);
+    klog_info!(
+        "LOCKDEP: CPU {} takes '{}' with interrupts enabled while holding '{}'",
+        pcr::current_cpu_id(),
+        class_name(taking),
+        class_name(held)
+    );
+    panic!(
+        "lockdep: interrupts enabled while holding '{}'",
+        class_name(held)
+    );
+}
+
+/// Note that this CPU is about to take a lock of `class`.  `irqs_enabled`
+/// is the interrupt flag from before the lock disabled interrupts.
+#[cfg(feature = "lockdep")]
+pub(crate) fn lockdep_acquire(class: &'static LockClass, irqs_enabled: bool, trylock: bool) {
+    if TRIPPED.load(Ordering::Relaxed) {
+        return;
+    }
+    let Some(id) = class_id(class) else {
+        return;
+    };
+    let Some(held) = HELD.get(pcr::current_cpu_id()) else {
+        return;
+    };
+    if !trylock {
+        let mut ids = [0u8; MAX_HELD];
+        let n = held.ids(&mut ids);
+        if irqs_enabled && n > 0 {
+            report_irq_unsafe(ids[n - 1], id);
+        }
+        for &from in &ids[..n] {
+            let after = &AFTER[from as usize];
+            if from == id || after.load(Ordering::Relaxed) & 1u64 << id != 0 {
+                continue;
+            }
+            if let Some(parent) = find_path(id, from) {
+                report_inversion(from, id, &parent);
+            }
+            after.fetch_or(1u64 << id, Ordering::AcqRel);
+            record_trace(from, id);
+        }
+    }
+    held.push(id);
+}
+
+/// Note that this CPU dropped a lock of `class`.
+#[cfg(feature = "lockdep")]
+pub(crate) fn lockdep_release(class: &'static LockClass) {
+    let id = match class.dep.id.load(Ordering::Relaxed) {
+        0 | UNTRACKED => return,
+        id => id - 1,
+    };
+    if let Some(held) = HELD.get(pcr::current_cpu_id()) {
+        held.remove(id);
+    }
+}
+
+/// Whether this kernel was built with the `lockdep` feature.
+pub const fn lockdep_enabled() -> bool {
+    cfg!(feature = "lockdep")
+}
+
+/// Whether `then` has been taken while `first` was held, directly or
+/// through other classes.  Always `false` without the `lockdep` feature.
+pub fn lockdep_depends_on(first: &'static LockClass, then: &'static LockClass) -> bool {
+    #[cfg(feature = "lockdep")]
+    if let (Some(first), Some(then)) = (class_id(first), class_id(then)) {
+        return first != then && find_path(first, then).is_some();
+    }
+    #[cfg(not(feature = "lockdep"))]
+    let _ = (first, then);
+    false
+}
+
+/// Classed locks the current CPU holds.
+pub fn lockdep_held_count() -> usize {
+    #[cfg(feature = "lockdep")]
+    if let Some(held) = HELD.get(pcr::current_cpu_id()) {
+        return held.depth.load(Ordering::Relaxed);
+    }
+    0
+}
+
+/// Forget the locks the current CPU holds, for panic recovery: the guards
+/// were skipped over and the locks force-unlocked.
+pub fn lockdep_forget_held() {
+    #[cfg(feature = "lockdep")]
+    if let Some(held) = HELD.get(pcr::current_cpu_id()) {
+        held.depth.store(0, Ordering::Relaxed);
+    }
+}
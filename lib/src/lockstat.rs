@@ -0,0 +1,185 @@
+//! Lock contention statistics (the `lockstat` feature).
+//!
+//! An [`IrqMutex`](crate::IrqMutex) built with
+//! [`IrqMutex::with_class`](crate::IrqMutex::with_class) charges every
+//! acquisition to a named [`LockClass`]: how often it was taken, how often
+//! the taker found it held, the total and longest wait, and the total time
+//! held.  Locks of one kind share a class (every per-CPU run queue charges
+//! `runqueue`), so the numbers rank kinds of lock rather than instances,
+//! which is the question when picking what to convert to per-CPU or RCU.
+//!
+//! Without the feature the classes still exist, so call sites need no
+//! `cfg`, but nothing is counted and `IrqMutex` keeps its usual size.
+//!
+//! Times are kept in TSC cycles, so recording never touches the clock
+//! services (which take locks themselves), and are converted to
+//! nanoseconds when read.  Classes register themselves on their first
+//! acquisition; a class nobody has taken yet is not listed.
+//!
+//! The same classes name locks for the `lockdep` ordering checks; see
+//! [`lockdep`](crate::lockdep).
+
+use slopos_abi::syscall::{LOCK_CLASS_NAME_LEN, LockStat};
+
+#[cfg(feature = "lockdep")]
+use crate::lockdep::ClassDep;
+
+#[cfg(feature = "lockstat")]
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+
+/// A named group of locks whose contention is counted together.
+pub struct LockClass {
+    name: &'static str,
+    #[cfg(feature = "lockstat")]
+    counters: ClassCounters,
+    #[cfg(feature = "lockdep")]
+    pub(crate) dep: ClassDep,
+}
+
+#[cfg(feature = "lockstat")]
+struct ClassCounters {
+    acquisitions: AtomicU64,
+    contended: AtomicU64,
+    wait_cycles: AtomicU64,
+    max_wait_cycles: AtomicU64,
+    held_cycles: AtomicU64,
+    registered: AtomicBool,
+    /// Next class in the registry list.
+    next: AtomicPtr<LockClass>,
+}
+
+/// Head of the list of classes that have been acquired at least once.
+#[cfg(feature = "lockstat")]
+static CLASSES: AtomicPtr<LockClass> = AtomicPtr::new(core::ptr::null_mut());
+
+impl LockClass {
+    /// `name` is truncated to `LOCK_CLASS_NAME_LEN - 1` bytes when reported.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            #[cfg(feature = "lockstat")]
+            counters: ClassCounters {
+                acquisitions: AtomicU64::new(0),
+                contended: AtomicU64::new(0),
+                wait_cycles: AtomicU64::new(0),
+                max_wait_cycles: AtomicU64::new(0),
+                held_cycles: AtomicU64::new(0),
+                registered: AtomicBool::new(false),
+                next: AtomicPtr::new(core::ptr::null_mut()),
+            },
+            #[cfg(feature = "lockdep")]
+            dep: ClassDep::new(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Charge one acquisition that waited `wait_cycles` for the lock.
+    #[cfg(feature = "lockstat")]
+    #[inline]
+    pub(crate) fn record_acquire(&'static self, wait_cycles: u64, contended: bool) {
+        let c = &self.counters;
+        if !c.registered.load(Ordering::Relaxed) {
+            self.register();
+        }
+        c.acquisitions.fetch_add(1, Ordering::Relaxed);
+        if contended {
+            c.contended.fetch_add(1, Ordering::Relaxed);
+            c.wait_cycles.fetch_add(wait_cycles, Ordering::Relaxed);
+            c.max_wait_cycles.fetch_max(wait_cycles, Ordering::Relaxed);
+        }
+    }
+
+    #[cfg(feature = "lockstat")]
+    #[inline]
+    pub(crate) fn record_release(&self, held_cycles: u64) {
+        self.counters
+            .held_cycles
+            .fetch_add(held_cycles, Ordering::Relaxed);
+    }
+
+    #[cfg(feature = "lockstat")]
+    #[cold]
+    fn register(&'static self) {
+        if self.counters.registered.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let this = self as *const Self as *mut Self;
+        let mut head = CLASSES.load(Ordering::Acquire);
+        loop {
+            self.counters.next.store(head, Ordering::Relaxed);
+            match CLASSES.compare_exchange_weak(head, this, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Current counters, with times in nanoseconds.
+    pub fn snapshot(&self) -> LockStat {
+        let mut stat = LockStat::default();
+        let len = self.name.len().min(LOCK_CLASS_NAME_LEN - 1);
+        stat.name[..len].copy_from_slice(&self.name.as_bytes()[..len]);
+        #[cfg(feature = "lockstat")]
+        {
+            let c = &self.counters;
+            stat.acquisitions = c.acquisitions.load(Ordering::Relaxed);
+            stat.contended = c.contended.load(Ordering::Relaxed);
+            stat.total_wait_ns = cycles_to_ns(c.wait_cycles.load(Ordering::Relaxed));
+            stat.max_wait_ns = cycles_to_ns(c.max_wait_cycles.load(Ordering::Relaxed));
+            stat.total_held_ns = cycles_to_ns(c.held_cycles.load(Ordering::Relaxed));
+        }
+        stat
+    }
+
+    /// Zero the counters.  The class stays registered.
+    pub fn reset(&self) {
+        #[cfg(feature = "lockstat")]
+        {
+            let c = &self.counters;
+            c.acquisitions.store(0, Ordering::Relaxed);
+            c.contended.store(0, Ordering::Relaxed);
+            c.wait_cycles.store(0, Ordering::Relaxed);
+            c.max_wait_cycles.store(0, Ordering::Relaxed);
+            c.held_cycles.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(feature = "lockstat")]
+fn cycles_to_ns(cycles: u64) -> u64 {
+    match crate::tsc::calibrated_cycles_per_ms() {
+        0 => 0,
+        per_ms => (cycles as u128 * 1_000_000 / per_ms as u128) as u64,
+    }
+}
+
+/// Whether this kernel was built with the `lockstat` feature.
+pub const fn lockstat_enabled() -> bool {
+    cfg!(feature = "lockstat")
+}
+
+/// Visit every class acquired since boot, most recently registered first.
+/// Visits nothing without the `lockstat` feature.
+pub fn lockstat_for_each(mut f: impl FnMut(&'static LockClass)) {
+    #[cfg(feature = "lockstat")]
+    {
+        let mut cursor = CLASSES.load(Ordering::Acquire);
+        while !cursor.is_null() {
+            // SAFETY: only `&'static LockClass` values are ever linked in,
+            // and they are never unlinked.
+            let class: &'static LockClass = unsafe { &*cursor };
+            f(class);
+            cursor = class.counters.next.load(Ordering::Acquire);
+        }
+    }
+    #[cfg(not(feature = "lockstat"))]
+    let _ = &mut f;
+}
+
+/// Zero the counters of every registered class.
+pub fn lockstat_reset() {
+    lockstat_for_each(|class| class.reset());
+}
@@ -14,6 +14,25 @@
 //! let text = buf.format_u64(12345);      // b"12345\0"
 //! let hex  = buf.format_hex_u64(0xBEEF); // b"0x000000000000BEEF\0"
 //! ```
+//!
+//! # Human-readable output
+//!
+//! Tools that print sizes and times (`free`, `uptime`, `time`, `date`) share
+//! the helpers below instead of rolling their own digit loops:
+//!
+//! | Function              | Input               | Output                |
+//! |-----------------------|---------------------|-----------------------|
+//! | [`fmt_u64_grouped`]   | `1234567`           | `"1,234,567"`         |
+//! | [`fmt_u64_padded`]    | `7`, width 3, `'0'` | `"007"`               |
+//! | [`fmt_fixed`]         | `12345`, 3 places   | `"12.345"`            |
+//! | [`fmt_ms`]            | `1500` ms           | `"1.500"`             |
+//! | [`fmt_bytes`]         | `1572864`, binary   | `"1.5 MiB"`           |
+//! | [`fmt_hms`]           | `3725` s            | `"01:02:05"`          |
+//! | [`fmt_duration_ms`]   | `3725042` ms        | `"1h 02:05.042"`      |
+//!
+//! Like the integer formatters they NUL-terminate their output; use
+//! [`trim_nul`] before handing the text to a byte-stream writer.  Output
+//! that does not fit the buffer collapses to an empty string.
 
 const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
 
@@ -160,9 +179,12 @@ pub fn fmt_hex_u8(value: u8, buf: &mut [u8]) -> &[u8] {
 /// - Decimal `i64::MIN` needs 21 bytes ('-' + 19 digits + NUL)
 /// - Hex `u64` needs 19 bytes ("0x" + 16 nibbles + NUL)
 /// - Decimal `u32::MAX` needs 11 bytes (10 digits + NUL)
+/// - Grouped `u64::MAX` needs 27 bytes (20 digits + 6 commas + NUL)
+/// - Scaled byte counts need at most 16 bytes
+/// - Durations need at most 32 bytes
 ///
 /// Common sizes: `NumBuf::<21>` (any integer), `NumBuf::<12>` (u32),
-/// `NumBuf::<19>` (hex u64).
+/// `NumBuf::<19>` (hex u64), `NumBuf::<32>` (any human-readable form).
 pub struct NumBuf<const N: usize> {
     buf: [u8; N],
 }
@@ -202,4 +224,333 @@ impl<const N: usize> NumBuf<N> {
     pub fn format_hex_u8(&mut self, value: u8) -> &[u8] {
         fmt_hex_u8(value, &mut self.buf)
     }
+
+    /// Format a `u64` with thousands separators.
+    #[inline]
+    pub fn format_grouped(&mut self, value: u64) -> &[u8] {
+        fmt_u64_grouped(value, &mut self.buf)
+    }
+
+    /// Format a `u64` right-aligned to `width` columns, filled with `pad`.
+    #[inline]
+    pub fn format_padded(&mut self, value: u64, width: usize, pad: u8) -> &[u8] {
+        fmt_u64_padded(value, width, pad, &mut self.buf)
+    }
+
+    /// Format `value` as a fixed-point number with `places` decimals.
+    #[inline]
+    pub fn format_fixed(&mut self, value: u64, places: u32) -> &[u8] {
+        fmt_fixed(value, places, &mut self.buf)
+    }
+
+    /// Format milliseconds as seconds with three decimals.
+    #[inline]
+    pub fn format_ms(&mut self, ms: u64) -> &[u8] {
+        fmt_ms(ms, &mut self.buf)
+    }
+
+    /// Format a byte count scaled to the largest fitting unit.
+    #[inline]
+    pub fn format_bytes(&mut self, bytes: u64, base: UnitBase) -> &[u8] {
+        fmt_bytes(bytes, base, &mut self.buf)
+    }
+
+    /// Format seconds as `HH:MM:SS`.
+    #[inline]
+    pub fn format_hms(&mut self, secs: u64) -> &[u8] {
+        fmt_hms(secs, &mut self.buf)
+    }
+
+    /// Format a millisecond duration as `[Nd ][Nh ]MM:SS.mmm`.
+    #[inline]
+    pub fn format_duration_ms(&mut self, ms: u64) -> &[u8] {
+        fmt_duration_ms(ms, &mut self.buf)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Human-readable formatting
+// ---------------------------------------------------------------------------
+
+/// Strip the trailing NUL from a formatter result.
+#[inline]
+pub fn trim_nul(text: &[u8]) -> &[u8] {
+    text.strip_suffix(&[0]).unwrap_or(text)
+}
+
+/// Base used by [`fmt_bytes`] when scaling.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnitBase {
+    /// Powers of 1024: KiB, MiB, GiB, ...
+    Binary,
+    /// Powers of 1000: kB, MB, GB, ...
+    Si,
+}
+
+impl UnitBase {
+    #[inline]
+    const fn step(self) -> u64 {
+        match self {
+            UnitBase::Binary => 1024,
+            UnitBase::Si => 1000,
+        }
+    }
+
+    #[inline]
+    const fn units(self) -> &'static [&'static [u8]; 7] {
+        match self {
+            UnitBase::Binary => &[b"B", b"KiB", b"MiB", b"GiB", b"TiB", b"PiB", b"EiB"],
+            UnitBase::Si => &[b"B", b"kB", b"MB", b"GB", b"TB", b"PB", b"EB"],
+        }
+    }
+}
+
+/// Append-only writer over a caller buffer that always keeps one byte for
+/// the NUL terminator.
+struct Cursor<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    overflow: bool,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            len: 0,
+            overflow: false,
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.len + 1 < self.buf.len() {
+            self.buf[self.len] = byte;
+            self.len += 1;
+        } else {
+            self.overflow = true;
+        }
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.push(b);
+        }
+    }
+
+    fn push_u64(&mut self, value: u64) {
+        let mut scratch = [0u8; 20];
+        self.push_bytes(decimal_digits(value, &mut scratch));
+    }
+
+    fn push_u64_padded(&mut self, value: u64, width: usize, pad: u8) {
+        let mut scratch = [0u8; 20];
+        let digits = decimal_digits(value, &mut scratch);
+        for _ in digits.len()..width {
+            self.push(pad);
+        }
+        self.push_bytes(digits);
+    }
+
+    /// NUL-terminate and return the written text, or an empty string if
+    /// anything was dropped.
+    fn finish(self) -> &'a [u8] {
+        if self.buf.is_empty() {
+            return self.buf;
+        }
+        let len = if self.overflow { 0 } else { self.len };
+        self.buf[len] = 0;
+        &self.buf[..len + 1]
+    }
+}
+
+/// Decimal digits of `value` (no terminator) written into `scratch`.
+fn decimal_digits(value: u64, scratch: &mut [u8; 20]) -> &[u8] {
+    let mut pos = scratch.len();
+    let mut n = value;
+    loop {
+        pos -= 1;
+        scratch[pos] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    &scratch[pos..]
+}
+
+/// Format a `u64` with `,` between groups of three digits.
+///
+/// `1234567` becomes `"1,234,567\0"`. `u64::MAX` needs 27 bytes.
+pub fn fmt_u64_grouped(value: u64, buf: &mut [u8]) -> &[u8] {
+    let mut scratch = [0u8; 20];
+    let digits = decimal_digits(value, &mut scratch);
+    let mut out = Cursor::new(buf);
+    let lead = match digits.len() % 3 {
+        0 => 3,
+        n => n,
+    };
+    for (i, &d) in digits.iter().enumerate() {
+        if i != 0 && (i + 3 - lead) % 3 == 0 {
+            out.push(b',');
+        }
+        out.push(d);
+    }
+    out.finish()
+}
+
+/// Format a `u64` right-aligned in `width` columns, filled with `pad`
+/// (typically `b' '` or `b'0'`).  Wider values are not truncated.
+pub fn fmt_u64_padded(value: u64, width: usize, pad: u8, buf: &mut [u8]) -> &[u8] {
+    let mut out = Cursor::new(buf);
+    out.push_u64_padded(value, width, pad);
+    out.finish()
+}
+
+/// Format a fixed-point value: `value / 10^places` with exactly `places`
+/// decimals, e.g. `fmt_fixed(12345, 3)` gives `"12.345\0"`.
+///
+/// `places` is capped at 19, the most a `u64` scale factor can hold.
+pub fn fmt_fixed(value: u64, places: u32, buf: &mut [u8]) -> &[u8] {
+    let places = places.min(19);
+    let scale = 10u64.pow(places);
+    let mut out = Cursor::new(buf);
+    out.push_u64(value / scale);
+    if places > 0 {
+        out.push(b'.');
+        out.push_u64_padded(value % scale, places as usize, b'0');
+    }
+    out.finish()
+}
+
+/// Format a millisecond count as seconds with millisecond precision,
+/// e.g. `1500` gives `"1.500\0"`.
+#[inline]
+pub fn fmt_ms(ms: u64, buf: &mut [u8]) -> &[u8] {
+    fmt_fixed(ms, 3, buf)
+}
+
+/// Format a byte count in the largest unit that keeps the integer part
+/// below the base, with one decimal: `"512 B"`, `"1.5 MiB"`, `"4.3 GB"`.
+///
+/// A 16-byte buffer holds any `u64` in either base.
+pub fn fmt_bytes(bytes: u64, base: UnitBase, buf: &mut [u8]) -> &[u8] {
+    let step = base.step();
+    let units = base.units();
+    let mut out = Cursor::new(buf);
+
+    if bytes < step {
+        out.push_u64(bytes);
+        out.push(b' ');
+        out.push_bytes(units[0]);
+        return out.finish();
+    }
+
+    let mut unit = 0usize;
+    let mut divisor = 1u128;
+    while unit + 1 < units.len() && bytes as u128 >= divisor * step as u128 {
+        divisor *= step as u128;
+        unit += 1;
+    }
+    // Round to the nearest tenth; if that carries into the next unit
+    // (1023.96 KiB), report it there instead.
+    let mut tenths = (bytes as u128 * 10 + divisor / 2) / divisor;
+    if tenths >= step as u128 * 10 && unit + 1 < units.len() {
+        divisor *= step as u128;
+        unit += 1;
+        tenths = (bytes as u128 * 10 + divisor / 2) / divisor;
+    }
+
+    out.push_u64((tenths / 10) as u64);
+    out.push(b'.');
+    out.push(b'0' + (tenths % 10) as u8);
+    out.push(b' ');
+    out.push_bytes(units[unit]);
+    out.finish()
+}
+
+/// Format a second count as `HH:MM:SS`; hours are not wrapped at 24.
+pub fn fmt_hms(secs: u64, buf: &mut [u8]) -> &[u8] {
+    let mut out = Cursor::new(buf);
+    out.push_u64_padded(secs / 3600, 2, b'0');
+    out.push(b':');
+    out.push_u64_padded((secs % 3600) / 60, 2, b'0');
+    out.push(b':');
+    out.push_u64_padded(secs % 60, 2, b'0');
+    out.finish()
+}
+
+/// Format a millisecond duration as `[Nd ][Nh ]MM:SS.mmm`.
+///
+/// Days and hours are only shown once non-zero, so short durations read
+/// like a stopwatch: `"00:42.125"`, `"3h 07:00.000"`, `"2d 0h 00:05.000"`.
+pub fn fmt_duration_ms(ms: u64, buf: &mut [u8]) -> &[u8] {
+    let total_secs = ms / 1000;
+    let days = total_secs / 86_400;
+    let hours = (total_secs % 86_400) / 3600;
+
+    let mut out = Cursor::new(buf);
+    if days > 0 {
+        out.push_u64(days);
+        out.push_bytes(b"d ");
+    }
+    if days > 0 || hours > 0 {
+        out.push_u64(hours);
+        out.push_bytes(b"h ");
+    }
+    out.push_u64_padded((total_secs % 3600) / 60, 2, b'0');
+    out.push(b':');
+    out.push_u64_padded(total_secs % 60, 2, b'0');
+    out.push(b'.');
+    out.push_u64_padded(ms % 1000, 3, b'0');
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_formats_in_every_style() {
+        let mut buf = NumBuf::<32>::new();
+        assert_eq!(buf.format_u64(0), b"0\0");
+        assert_eq!(buf.format_grouped(0), b"0\0");
+        assert_eq!(buf.format_fixed(0, 3), b"0.000\0");
+        assert_eq!(buf.format_bytes(0, UnitBase::Binary), b"0 B\0");
+        assert_eq!(buf.format_hms(0), b"00:00:00\0");
+        assert_eq!(buf.format_duration_ms(0), b"00:00.000\0");
+    }
+
+    #[test]
+    fn byte_rounding_carries_into_next_unit() {
+        let mut buf = NumBuf::<16>::new();
+        assert_eq!(buf.format_bytes(1023, UnitBase::Binary), b"1023 B\0");
+        assert_eq!(buf.format_bytes(1_048_575, UnitBase::Binary), b"1.0 MiB\0");
+        assert_eq!(buf.format_bytes(999_999, UnitBase::Si), b"1.0 MB\0");
+        assert_eq!(buf.format_bytes(1_572_864, UnitBase::Binary), b"1.5 MiB\0");
+    }
+
+    #[test]
+    fn u64_max_fits_documented_sizes() {
+        let mut buf = NumBuf::<21>::new();
+        assert_eq!(buf.format_u64(u64::MAX), b"18446744073709551615\0");
+        assert_eq!(buf.format_i64(i64::MIN), b"-9223372036854775808\0");
+        let mut grouped = NumBuf::<27>::new();
+        assert_eq!(
+            grouped.format_grouped(u64::MAX),
+            b"18,446,744,073,709,551,615\0"
+        );
+        let mut bytes = NumBuf::<16>::new();
+        assert_eq!(
+            bytes.format_bytes(u64::MAX, UnitBase::Binary),
+            b"16.0 EiB\0"
+        );
+        assert_eq!(bytes.format_bytes(u64::MAX, UnitBase::Si), b"18.4 EB\0");
+    }
+
+    #[test]
+    fn overflow_collapses_to_empty() {
+        let mut buf = NumBuf::<26>::new();
+        assert_eq!(buf.format_grouped(u64::MAX), b"\0");
+        assert_eq!(trim_nul(buf.format_grouped(1_234_567)), b"1,234,567");
+    }
 }
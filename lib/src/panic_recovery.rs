@@ -58,6 +58,7 @@ pub fn register_panic_cleanup(handler: PanicCleanupFn) {
 }
 
 pub fn call_panic_cleanup() {
+    crate::lockdep::lockdep_forget_held();
     let count = PANIC_CLEANUP_COUNT
         .load(Ordering::SeqCst)
         .min(MAX_PANIC_CLEANUP_HANDLERS);
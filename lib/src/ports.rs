@@ -1,6 +1,10 @@
 use crate::io::Port;
 
 pub const COM1: Port<u8> = Port::new(0x3F8);
+pub const COM2: Port<u8> = Port::new(0x2F8);
+
+pub const CMOS_INDEX: Port<u8> = Port::new(0x70);
+pub const CMOS_DATA: Port<u8> = Port::new(0x71);
 
 pub const PIT_CHANNEL0: Port<u8> = Port::new(0x40);
 pub const PIT_COMMAND: Port<u8> = Port::new(0x43);
@@ -35,6 +39,7 @@ pub const UART_FCR_ENABLE_FIFO: u8 = 0x01;
 pub const UART_FCR_CLEAR_RX: u8 = 0x02;
 pub const UART_FCR_CLEAR_TX: u8 = 0x04;
 pub const UART_FCR_14_BYTE_THRESHOLD: u8 = 0xC0;
+pub const UART_IER_RX_AVAILABLE: u8 = 0x01;
 pub const UART_LSR_DATA_READY: u8 = 0x01;
 pub const UART_LSR_TX_EMPTY: u8 = 0x20;
 pub const UART_MCR_DTR: u8 = 0x01;
@@ -4,8 +4,15 @@ use core::ops::{Deref, DerefMut};
 use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
 
 use crate::cpu;
+#[cfg(feature = "lockdep")]
+use crate::lockdep::{lockdep_acquire, lockdep_release};
+use crate::lockstat::LockClass;
 use crate::preempt::PreemptGuard;
 
+/// `rflags.IF`, as saved by `save_flags_cli`.
+#[cfg(feature = "lockdep")]
+const RFLAGS_IF: u64 = 1 << 9;
+
 /// Mutex that disables interrupts AND preemption while held.
 /// Essential for kernel code accessed from both normal and interrupt contexts.
 ///
@@ -18,6 +25,10 @@ use crate::preempt::PreemptGuard;
 /// force-unlock via `poison_unlock()`, the mutex is marked poisoned.
 /// Callers can check `is_poisoned()` to determine if the protected data
 /// may be in an inconsistent state and needs reinitialization.
+///
+/// Locks created with [`with_class`](Self::with_class) report contention to
+/// their [`LockClass`] when the kernel is built with the `lockstat` feature,
+/// and have their ordering checked with the `lockdep` feature.
 pub struct IrqMutex<T> {
     /// Monotonically-increasing ticket counter. Each `lock()` call takes the
     /// next ticket via `fetch_add(1)`. Wraps at `u16::MAX` — equality checks
@@ -27,6 +38,8 @@ pub struct IrqMutex<T> {
     /// unlock. A waiter spins until `now_serving == my_ticket`.
     now_serving: AtomicU16,
     poisoned: AtomicBool,
+    #[cfg(any(feature = "lockstat", feature = "lockdep"))]
+    class: Option<&'static LockClass>,
     data: UnsafeCell<T>,
 }
 
@@ -39,6 +52,9 @@ pub struct IrqMutexGuard<'a, T> {
     mutex: &'a IrqMutex<T>,
     saved_flags: u64,
     _preempt: PreemptGuard,
+    /// TSC at acquisition, for the class's held time.
+    #[cfg(feature = "lockstat")]
+    acquired_at: u64,
 }
 
 impl<T> IrqMutex<T> {
@@ -48,10 +64,38 @@ impl<T> IrqMutex<T> {
             next_ticket: AtomicU16::new(0),
             now_serving: AtomicU16::new(0),
             poisoned: AtomicBool::new(false),
+            #[cfg(any(feature = "lockstat", feature = "lockdep"))]
+            class: None,
             data: UnsafeCell::new(data),
         }
     }
 
+    /// Like [`new`](Self::new), charging acquisitions to `class`.
+    #[inline]
+    pub const fn with_class(class: &'static LockClass, data: T) -> Self {
+        #[cfg(not(any(feature = "lockstat", feature = "lockdep")))]
+        let _ = class;
+        Self {
+            next_ticket: AtomicU16::new(0),
+            now_serving: AtomicU16::new(0),
+            poisoned: AtomicBool::new(false),
+            #[cfg(any(feature = "lockstat", feature = "lockdep"))]
+            class: Some(class),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    #[inline]
+    fn guard(&self, saved_flags: u64, preempt: PreemptGuard) -> IrqMutexGuard<'_, T> {
+        IrqMutexGuard {
+            mutex: self,
+            saved_flags,
+            _preempt: preempt,
+            #[cfg(feature = "lockstat")]
+            acquired_at: crate::tsc::rdtsc(),
+        }
+    }
+
     /// Force unlock the mutex without proper guard handling.
     ///
     /// Advances `now_serving` to match `next_ticket`, releasing the lock and
@@ -117,6 +161,12 @@ impl<T> IrqMutex<T> {
     pub fn lock(&self) -> IrqMutexGuard<'_, T> {
         let preempt = PreemptGuard::new();
         let saved_flags = cpu::save_flags_cli();
+        #[cfg(feature = "lockdep")]
+        if let Some(class) = self.class {
+            lockdep_acquire(class, saved_flags & RFLAGS_IF != 0, false);
+        }
+        #[cfg(feature = "lockstat")]
+        let wait_start = crate::tsc::rdtsc();
 
         // Take a ticket. fetch_add wraps at u16::MAX → 0; equality checks are
         // wrap-safe so this is correct for any number of acquisitions.
@@ -129,11 +179,17 @@ impl<T> IrqMutex<T> {
         // Proportional backoff: the further away our ticket is from now_serving,
         // the more PAUSE iterations we issue per check. This reduces cache-line
         // traffic when multiple CPUs are queued.
+        #[cfg(feature = "lockstat")]
+        let mut contended = false;
         loop {
             let serving = self.now_serving.load(Ordering::Acquire);
             if serving == my_ticket {
                 break;
             }
+            #[cfg(feature = "lockstat")]
+            {
+                contended = true;
+            }
             // Proportional backoff: pause 1× per ticket of distance, capped at 64.
             let distance = my_ticket.wrapping_sub(serving) as u32;
             for _ in 0..distance.min(64) {
@@ -141,11 +197,12 @@ impl<T> IrqMutex<T> {
             }
         }
 
-        IrqMutexGuard {
-            mutex: self,
-            saved_flags,
-            _preempt: preempt,
+        let guard = self.guard(saved_flags, preempt);
+        #[cfg(feature = "lockstat")]
+        if let Some(class) = self.class {
+            class.record_acquire(guard.acquired_at.wrapping_sub(wait_start), contended);
         }
+        guard
     }
 
     #[inline]
@@ -167,11 +224,16 @@ impl<T> IrqMutex<T> {
             )
             .is_ok()
         {
-            Some(IrqMutexGuard {
-                mutex: self,
-                saved_flags,
-                _preempt: preempt,
-            })
+            let guard = self.guard(saved_flags, preempt);
+            #[cfg(feature = "lockstat")]
+            if let Some(class) = self.class {
+                class.record_acquire(0, false);
+            }
+            #[cfg(feature = "lockdep")]
+            if let Some(class) = self.class {
+                lockdep_acquire(class, saved_flags & RFLAGS_IF != 0, true);
+            }
+            Some(guard)
         } else {
             cpu::restore_flags(saved_flags);
             drop(preempt);
@@ -201,6 +263,14 @@ impl<'a, T> Drop for IrqMutexGuard<'a, T> {
     fn drop(&mut self) {
         // Advance now_serving to hand the lock to the next waiter in FIFO order.
         // Release ordering ensures our writes are visible to the next acquirer.
+        #[cfg(feature = "lockstat")]
+        if let Some(class) = self.mutex.class {
+            class.record_release(crate::tsc::rdtsc().wrapping_sub(self.acquired_at));
+        }
+        #[cfg(feature = "lockdep")]
+        if let Some(class) = self.mutex.class {
+            lockdep_release(class);
+        }
         self.mutex.now_serving.fetch_add(1, Ordering::Release);
         cpu::restore_flags(self.saved_flags);
         // _preempt drops after this, potentially triggering deferred reschedule
@@ -215,12 +285,17 @@ impl<'a, T> Drop for IrqMutexGuard<'a, T> {
 /// Multiple readers can hold the lock simultaneously, but writers get exclusive access.
 /// When a writer is waiting, new readers yield to prevent writer starvation.
 /// Essential for kernel data structures that need concurrent read access but exclusive writes.
+///
+/// Locks created with [`with_class`](Self::with_class) have their ordering
+/// checked with the `lockdep` feature; `lockstat` does not count them.
 pub struct IrqRwLock<T> {
     /// State: 0 = unlocked, -1 = write-locked, >0 = number of readers
     state: core::sync::atomic::AtomicI32,
     /// Number of writers waiting for access.  When > 0, new readers yield
     /// to prevent writer starvation under continuous read traffic.
     writer_waiting: AtomicU32,
+    #[cfg(feature = "lockdep")]
+    class: Option<&'static LockClass>,
     data: UnsafeCell<T>,
 }
 
@@ -250,16 +325,51 @@ impl<T> IrqRwLock<T> {
         Self {
             state: core::sync::atomic::AtomicI32::new(0),
             writer_waiting: AtomicU32::new(0),
+            #[cfg(feature = "lockdep")]
+            class: None,
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Like [`new`](Self::new), with readers and writers checked as
+    /// `class`.
+    #[inline]
+    pub const fn with_class(class: &'static LockClass, data: T) -> Self {
+        #[cfg(not(feature = "lockdep"))]
+        let _ = class;
+        Self {
+            state: core::sync::atomic::AtomicI32::new(0),
+            writer_waiting: AtomicU32::new(0),
+            #[cfg(feature = "lockdep")]
+            class: Some(class),
             data: UnsafeCell::new(data),
         }
     }
 
+    #[cfg(feature = "lockdep")]
+    #[inline]
+    fn lockdep_acquire(&self, saved_flags: u64, trylock: bool) {
+        if let Some(class) = self.class {
+            lockdep_acquire(class, saved_flags & RFLAGS_IF != 0, trylock);
+        }
+    }
+
+    #[cfg(feature = "lockdep")]
+    #[inline]
+    fn lockdep_release(&self) {
+        if let Some(class) = self.class {
+            lockdep_release(class);
+        }
+    }
+
     /// Acquire read access. Multiple readers can hold the lock simultaneously.
     /// Blocks if a writer holds the lock or if writers are waiting (writer preference).
     #[inline]
     pub fn read(&self) -> IrqRwLockReadGuard<'_, T> {
         let preempt = PreemptGuard::new();
         let saved_flags = cpu::save_flags_cli();
+        #[cfg(feature = "lockdep")]
+        self.lockdep_acquire(saved_flags, false);
 
         loop {
             let state = self.state.load(Ordering::Relaxed);
@@ -297,6 +407,8 @@ impl<T> IrqRwLock<T> {
                 .compare_exchange(state, state + 1, Ordering::Acquire, Ordering::Relaxed)
                 .is_ok()
             {
+                #[cfg(feature = "lockdep")]
+                self.lockdep_acquire(saved_flags, true);
                 return Some(IrqRwLockReadGuard {
                     lock: self,
                     saved_flags,
@@ -316,6 +428,8 @@ impl<T> IrqRwLock<T> {
     pub fn write(&self) -> IrqRwLockWriteGuard<'_, T> {
         let preempt = PreemptGuard::new();
         let saved_flags = cpu::save_flags_cli();
+        #[cfg(feature = "lockdep")]
+        self.lockdep_acquire(saved_flags, false);
 
         // Signal that a writer is waiting — new readers will yield.
         self.writer_waiting.fetch_add(1, Ordering::Relaxed);
@@ -350,6 +464,8 @@ impl<T> IrqRwLock<T> {
             .compare_exchange(0, -1, Ordering::Acquire, Ordering::Relaxed)
             .is_ok()
         {
+            #[cfg(feature = "lockdep")]
+            self.lockdep_acquire(saved_flags, true);
             return Some(IrqRwLockWriteGuard {
                 lock: self,
                 saved_flags,
@@ -375,6 +491,8 @@ impl<'a, T> Deref for IrqRwLockReadGuard<'a, T> {
 impl<'a, T> Drop for IrqRwLockReadGuard<'a, T> {
     #[inline]
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        self.lock.lockdep_release();
         self.lock.state.fetch_sub(1, Ordering::Release);
         cpu::restore_flags(self.saved_flags);
         // _preempt drops after this
@@ -402,6 +520,8 @@ impl<'a, T> DerefMut for IrqRwLockWriteGuard<'a, T> {
 impl<'a, T> Drop for IrqRwLockWriteGuard<'a, T> {
     #[inline]
     fn drop(&mut self) {
+        #[cfg(feature = "lockdep")]
+        self.lock.lockdep_release();
         self.lock.state.store(0, Ordering::Release);
         cpu::restore_flags(self.saved_flags);
         // _preempt drops after this
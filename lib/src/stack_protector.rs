@@ -0,0 +1,75 @@
+//! Stack smashing protection (`-Z stack-protector`).
+//!
+//! On a bare-metal target LLVM loads the canary from the global
+//! `__stack_chk_guard` in every protected prologue and compares it again
+//! before returning, calling `__stack_chk_fail` on a mismatch.
+//!
+//! The guard starts out as a fixed constant so frames entered before boot
+//! randomizes it still check against a consistent value.  [`init_guard`]
+//! swaps in a random one; it must run in a frame that never returns (the
+//! kernel entry), because every protected frame live at that moment saved
+//! the old value.
+
+use core::arch::naked_asm;
+use core::cell::SyncUnsafeCell;
+
+use crate::cpu::rand::rdrand;
+use crate::tsc;
+
+/// Link-time guard value, used until [`init_guard`] runs.
+pub const STACK_GUARD_DEFAULT: u64 = 0x595e_9fbd_94fd_a700;
+
+#[allow(non_upper_case_globals)]
+#[unsafe(no_mangle)]
+pub static __stack_chk_guard: SyncUnsafeCell<u64> = SyncUnsafeCell::new(STACK_GUARD_DEFAULT);
+
+/// Replace the link-time guard with a random one.
+///
+/// Always inlined into the caller, which must never return.  The low byte
+/// is kept zero so an overflow by a NUL-terminated string cannot
+/// reproduce the canary.
+#[inline(always)]
+pub fn init_guard() {
+    let mut seed = rdrand().unwrap_or(0) ^ tsc::rdtsc().rotate_left(29);
+    if seed >> 8 == 0 {
+        seed = STACK_GUARD_DEFAULT ^ tsc::rdtsc();
+    }
+    unsafe { core::ptr::write_volatile(__stack_chk_guard.get(), seed & !0xFF) };
+}
+
+/// Current guard value.
+pub fn guard() -> u64 {
+    unsafe { core::ptr::read_volatile(__stack_chk_guard.get()) }
+}
+
+/// Whether [`init_guard`] has replaced the link-time guard.
+pub fn guard_randomized() -> bool {
+    guard() != STACK_GUARD_DEFAULT
+}
+
+/// Called by a protected function whose canary no longer matches.
+///
+/// The return address on the stack points into the function that owned
+/// the smashed frame; hand it to the panic.
+///
+/// # Safety
+///
+/// Only compiler-emitted canary checks may call this, with a plain `call`
+/// so `[rsp]` holds the return address into the failing function.  It
+/// never returns.
+#[unsafe(naked)]
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn __stack_chk_fail() -> ! {
+    naked_asm!(
+        "mov rdi, [rsp]",
+        "jmp {fail}",
+        fail = sym stack_chk_fail_at,
+    )
+}
+
+extern "C" fn stack_chk_fail_at(return_address: u64) -> ! {
+    panic!(
+        "stack smashing detected in function at/near 0x{:x}",
+        return_address
+    );
+}
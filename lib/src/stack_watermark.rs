@@ -0,0 +1,95 @@
+//! Stack high-water marks.
+//!
+//! Debug builds fill fresh kernel stacks with [`STACK_POISON`].  Stacks grow
+//! down, so the deepest point a stack has ever reached is the lowest word
+//! that no longer holds the pattern; [`stack_high_water`] finds it by
+//! scanning up from the base.  The scheduler does this for the outgoing
+//! task on every switch, and the IST code for its interrupt stacks, so a
+//! stack that is getting close to its guard is reported before it faults.
+//!
+//! Release builds skip the fill and the scans; only the cheap RSP sampling
+//! done through [`StackPeak`] remains.
+
+use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Whether stacks are poisoned and scanned in this build.
+pub const STACK_WATERMARK_ENABLED: bool = cfg!(debug_assertions);
+
+/// Fill pattern of never-used stack words.
+pub const STACK_POISON: u64 = 0x57AC_57AC_57AC_57AC;
+
+/// Percentage of a stack in use above which it is reported as close to
+/// overflowing.
+pub const STACK_WARN_PERCENT: u64 = 75;
+
+/// Fill `size` bytes at `base` with [`STACK_POISON`].  Does nothing when
+/// [`STACK_WATERMARK_ENABLED`] is false.
+///
+/// # Safety
+/// `base..base + size` must be writable, 8-byte aligned memory that nobody
+/// is using as a stack yet.
+pub unsafe fn stack_poison(base: u64, size: u64) {
+    if !STACK_WATERMARK_ENABLED || base == 0 {
+        return;
+    }
+    let words = base as *mut u64;
+    for i in 0..(size / 8) as usize {
+        unsafe { ptr::write_volatile(words.add(i), STACK_POISON) };
+    }
+}
+
+/// Deepest use, in bytes from the top, of the stack at `base..base + size`.
+/// Scanning stops `known` bytes below the top, since a high-water mark
+/// never goes down.
+///
+/// # Safety
+/// `base..base + size` must be readable, 8-byte aligned memory that was
+/// filled by [`stack_poison`].
+pub unsafe fn stack_high_water(base: u64, size: u64, known: u64) -> u64 {
+    if !STACK_WATERMARK_ENABLED || base == 0 {
+        return known;
+    }
+    let words = base as *const u64;
+    let limit = (size.saturating_sub(known) / 8) as usize;
+    for i in 0..limit {
+        if unsafe { ptr::read_volatile(words.add(i)) } != STACK_POISON {
+            return size - i as u64 * 8;
+        }
+    }
+    known
+}
+
+/// Whether `used` bytes of a `size`-byte stack is past [`STACK_WARN_PERCENT`].
+#[inline]
+pub const fn stack_usage_is_high(used: u64, size: u64) -> bool {
+    size != 0 && used * 100 > size * STACK_WARN_PERCENT
+}
+
+/// Largest stack usage seen, updated from any CPU.
+pub struct StackPeak(AtomicU64);
+
+impl StackPeak {
+    pub const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.0.store(0, Ordering::Relaxed);
+    }
+
+    /// Record `usage` bytes.  Returns true if it is a new peak.
+    pub fn record(&self, usage: u64) -> bool {
+        self.0.fetch_max(usage, Ordering::Relaxed) < usage
+    }
+}
+
+impl Default for StackPeak {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -147,8 +147,16 @@ fn cached_cycles_per_ms_mut() -> *mut u64 {
     CACHED_CYCLES_PER_MS.get()
 }
 
-/// Estimate CPU cycles per millisecond using CPUID if available.
+/// Estimate CPU cycles per millisecond.
+///
+/// Prefers the boot-time HPET calibration; before that (or without it)
+/// falls back to CPUID leaf 0x16, then to a 3 GHz guess.
 pub fn estimate_cycles_per_ms() -> u64 {
+    let calibrated = crate::tsc::calibrated_cycles_per_ms();
+    if calibrated != 0 {
+        return calibrated;
+    }
+
     unsafe {
         if *cached_cycles_per_ms_mut() != 0 {
             return *cached_cycles_per_ms_mut();
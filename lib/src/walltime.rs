@@ -0,0 +1,49 @@
+//! Wall-clock (UTC) time.
+//!
+//! The RTC is read once at boot and only has one-second resolution, so
+//! wall time is kept as that epoch plus the monotonic clock's progress
+//! since the read.  Until [`walltime_set`] runs, realtime reads fall back
+//! to the monotonic clock (i.e. "1970-01-01 plus uptime").
+
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use slopos_abi::time::NSEC_PER_SEC;
+
+use crate::clock;
+
+static SYNCED: AtomicBool = AtomicBool::new(false);
+/// Unix time in nanoseconds at the moment of the last sync.
+static EPOCH_NS: AtomicU64 = AtomicU64::new(0);
+/// Monotonic clock reading taken at the same moment.
+static SYNC_MONO_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Anchor wall time: "it is `unix_secs` right now".
+pub fn walltime_set(unix_secs: u64) {
+    SYNCED.store(false, Ordering::Release);
+    EPOCH_NS.store(unix_secs.saturating_mul(NSEC_PER_SEC), Ordering::Relaxed);
+    SYNC_MONO_NS.store(clock::monotonic_ns(), Ordering::Relaxed);
+    SYNCED.store(true, Ordering::Release);
+}
+
+/// Whether an RTC reading has anchored wall time.
+#[inline]
+pub fn walltime_is_set() -> bool {
+    SYNCED.load(Ordering::Acquire)
+}
+
+/// Nanoseconds since the Unix epoch.
+pub fn realtime_ns() -> u64 {
+    let now = clock::monotonic_ns();
+    if !walltime_is_set() {
+        return now;
+    }
+    let epoch = EPOCH_NS.load(Ordering::Relaxed);
+    let sync = SYNC_MONO_NS.load(Ordering::Relaxed);
+    epoch.saturating_add(now.saturating_sub(sync))
+}
+
+/// Seconds since the Unix epoch.
+#[inline]
+pub fn realtime_secs() -> u64 {
+    realtime_ns() / NSEC_PER_SEC
+}
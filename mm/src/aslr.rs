@@ -1,18 +1,31 @@
 //! Address Space Layout Randomization (ASLR) for SlopOS.
 //!
-//! Randomizes stack (1MB range) and heap (16MB range) to mitigate exploitation.
-//! Uses TSC-seeded LFSR64 RNG.
+//! Per process: stack top (1MB range), heap start (16MB range), PIE load
+//! base (4MB range) and mmap base (4GB range).  Offsets come from the kernel
+//! RNG once the platform services are up, falling back to a TSC xorshift
+//! for anything created earlier.
+//!
+//! Sliding the kernel image itself is out of scope here.  It needs a
+//! relocatable (PIE) kernel link, which is separate work; until then the
+//! kernel is linked with `relocation-model=static` and Limine maps it at
+//! its link address.  The slide from the executable address response is
+//! still recorded, and code that needs the kernel's load address goes
+//! through `kernel_image_base()`, so nothing here assumes a fixed base.
 
 use core::cell::SyncUnsafeCell;
+use core::sync::atomic::{AtomicU64, Ordering};
 
-use crate::memory_layout_defs::ProcessMemoryLayout;
+use crate::memory_layout_defs::{KERNEL_VIRTUAL_BASE, ProcessMemoryLayout};
 use crate::paging_defs::PAGE_SIZE_4KB;
+use slopos_lib::kernel_services::platform;
 use slopos_lib::tsc;
 
 #[derive(Clone, Copy)]
 pub struct AslrConfig {
     pub stack_entropy_bits: u8,
     pub heap_entropy_bits: u8,
+    pub load_entropy_bits: u8,
+    pub mmap_entropy_bits: u8,
     pub enabled: bool,
 }
 
@@ -21,6 +34,8 @@ impl AslrConfig {
         Self {
             stack_entropy_bits: 8,
             heap_entropy_bits: 12,
+            load_entropy_bits: 10,
+            mmap_entropy_bits: 20,
             enabled: true,
         }
     }
@@ -29,6 +44,8 @@ impl AslrConfig {
         Self {
             stack_entropy_bits: 0,
             heap_entropy_bits: 0,
+            load_entropy_bits: 0,
+            mmap_entropy_bits: 0,
             enabled: false,
         }
     }
@@ -57,6 +74,9 @@ pub fn is_enabled() -> bool {
 }
 
 fn get_random() -> u64 {
+    if platform::is_platform_initialized() {
+        return platform::rng_next();
+    }
     let mut x = tsc::rdtsc() | 1;
     x ^= x << 13;
     x ^= x >> 7;
@@ -64,6 +84,15 @@ fn get_random() -> u64 {
     x
 }
 
+/// A page-aligned offset of up to `bits` bits worth of pages.
+fn random_pages(bits: u8) -> u64 {
+    if bits == 0 {
+        return 0;
+    }
+    let mask = (1u64 << bits) - 1;
+    (get_random() & mask) * PAGE_SIZE_4KB
+}
+
 pub fn randomize_layout(base: &ProcessMemoryLayout) -> ProcessMemoryLayout {
     let config = get_config();
 
@@ -73,31 +102,34 @@ pub fn randomize_layout(base: &ProcessMemoryLayout) -> ProcessMemoryLayout {
 
     let mut layout = *base;
 
-    let stack_random = get_random();
-    let heap_random = get_random();
-
-    if config.stack_entropy_bits > 0 {
-        let stack_mask = (1u64 << config.stack_entropy_bits) - 1;
-        let stack_offset = (stack_random & stack_mask) * PAGE_SIZE_4KB;
-
-        let min_stack_top = base.heap_max + base.stack_size + PAGE_SIZE_4KB;
-        let new_stack_top = base.stack_top.saturating_sub(stack_offset);
-
-        if new_stack_top > min_stack_top {
-            layout.stack_top = new_stack_top;
-        }
+    let stack_offset = random_pages(config.stack_entropy_bits);
+    let min_stack_top = base.heap_max + base.stack_size + PAGE_SIZE_4KB;
+    let new_stack_top = base.stack_top.saturating_sub(stack_offset);
+    if new_stack_top > min_stack_top {
+        layout.stack_top = new_stack_top;
     }
 
-    if config.heap_entropy_bits > 0 {
-        let heap_mask = (1u64 << config.heap_entropy_bits) - 1;
-        let heap_offset = (heap_random & heap_mask) * PAGE_SIZE_4KB;
+    let heap_offset = random_pages(config.heap_entropy_bits);
+    let max_heap_start = base.heap_max.saturating_sub(0x1000_0000);
+    let new_heap_start = base.heap_start.saturating_add(heap_offset);
+    if new_heap_start < max_heap_start {
+        layout.heap_start = new_heap_start;
+    }
 
-        let max_heap_start = base.heap_max.saturating_sub(0x1000_0000);
-        let new_heap_start = base.heap_start.saturating_add(heap_offset);
+    // PIE images keep their entry inside the code region; the loader falls
+    // back to the fixed base if the image would run into the heap.
+    let new_load_base = base
+        .load_base
+        .saturating_add(random_pages(config.load_entropy_bits));
+    if new_load_base < base.data_start {
+        layout.load_base = new_load_base;
+    }
 
-        if new_heap_start < max_heap_start {
-            layout.heap_start = new_heap_start;
-        }
+    let new_mmap_base = base
+        .mmap_base
+        .saturating_add(random_pages(config.mmap_entropy_bits));
+    if new_mmap_base < layout.stack_top - base.stack_size {
+        layout.mmap_base = new_mmap_base;
     }
 
     layout
@@ -106,3 +138,25 @@ pub fn randomize_layout(base: &ProcessMemoryLayout) -> ProcessMemoryLayout {
 pub fn randomize_process_layout(base: &ProcessMemoryLayout) -> ProcessMemoryLayout {
     randomize_layout(base)
 }
+
+static KERNEL_SLIDE: AtomicU64 = AtomicU64::new(0);
+
+/// Record where the bootloader placed the kernel image.
+///
+/// `virt_base` is the virtual address the image's first byte was mapped
+/// at; anything above the link address is the slide (0 while the kernel is
+/// linked static).
+pub fn set_kernel_slide(virt_base: u64) {
+    let slide = virt_base.saturating_sub(KERNEL_VIRTUAL_BASE);
+    KERNEL_SLIDE.store(slide, Ordering::Relaxed);
+}
+
+/// Bytes between the kernel's link address and where it actually runs.
+pub fn kernel_slide() -> u64 {
+    KERNEL_SLIDE.load(Ordering::Relaxed)
+}
+
+/// Virtual address the kernel image is mapped at.
+pub fn kernel_image_base() -> u64 {
+    KERNEL_VIRTUAL_BASE + kernel_slide()
+}
@@ -0,0 +1,94 @@
+//! ASLR tests: randomized layouts stay inside their regions, mmap honours
+//! the per-process base, and the kernel slide is consistent.
+
+use slopos_abi::syscall::{MAP_ANONYMOUS, MAP_PRIVATE, PROT_READ, PROT_WRITE};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::aslr::{is_enabled, kernel_image_base, kernel_slide, randomize_layout};
+use crate::memory_layout_defs::{
+    DEFAULT_PROCESS_LAYOUT, KERNEL_VIRTUAL_BASE, PROCESS_MMAP_END_VA, PROCESS_MMAP_START_VA,
+};
+use crate::paging_defs::PAGE_SIZE_4KB;
+use crate::process_vm::{process_vm_get_mmap_base, process_vm_mmap, process_vm_munmap};
+use crate::test_fixtures::ProcessVmGuard;
+
+const PAGE_MASK: u64 = PAGE_SIZE_4KB - 1;
+
+pub fn test_aslr_layout_within_bounds() -> TestResult {
+    let base = DEFAULT_PROCESS_LAYOUT;
+    for _ in 0..64 {
+        let l = randomize_layout(&base);
+        assert_test!(l.stack_top <= base.stack_top, "stack above its top");
+        assert_test!(
+            l.stack_top - l.stack_size > base.heap_max,
+            "stack reaches the heap"
+        );
+        assert_test!(l.heap_start >= base.heap_start, "heap below its start");
+        assert_test!(l.heap_start < base.heap_max, "heap past its max");
+        assert_test!(l.load_base >= base.code_start, "load base below code");
+        assert_test!(l.load_base < base.data_start, "load base past code");
+        assert_test!(l.mmap_base >= PROCESS_MMAP_START_VA, "mmap base too low");
+        assert_test!(l.mmap_base < PROCESS_MMAP_END_VA, "mmap base too high");
+        assert_eq_test!(
+            (l.stack_top | l.heap_start | l.load_base | l.mmap_base) & PAGE_MASK,
+            0,
+            "unaligned layout"
+        );
+    }
+    pass!()
+}
+
+pub fn test_aslr_layouts_differ() -> TestResult {
+    if !is_enabled() {
+        return pass!();
+    }
+    let first = randomize_layout(&DEFAULT_PROCESS_LAYOUT);
+    for _ in 0..16 {
+        let next = randomize_layout(&DEFAULT_PROCESS_LAYOUT);
+        if next.mmap_base != first.mmap_base || next.stack_top != first.stack_top {
+            return pass!();
+        }
+    }
+    fail!("17 layouts were identical")
+}
+
+pub fn test_aslr_mmap_starts_at_base() -> TestResult {
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    let base = process_vm_get_mmap_base(vm.pid);
+    assert_test!(base >= PROCESS_MMAP_START_VA, "mmap base {:#x}", base);
+
+    let addr = process_vm_mmap(
+        vm.pid,
+        0,
+        PAGE_SIZE_4KB,
+        PROT_READ | PROT_WRITE,
+        MAP_ANONYMOUS | MAP_PRIVATE,
+        -1,
+        0,
+    );
+    assert_test!(addr != 0, "mmap failed");
+    assert_eq_test!(addr, base, "first mapping not at the base");
+    assert_eq_test!(process_vm_munmap(vm.pid, addr, PAGE_SIZE_4KB), 0);
+    pass!()
+}
+
+pub fn test_aslr_kernel_slide() -> TestResult {
+    assert_eq_test!(kernel_image_base(), KERNEL_VIRTUAL_BASE + kernel_slide());
+    assert_eq_test!(kernel_slide() & PAGE_MASK, 0, "unaligned slide");
+    let here = test_aslr_kernel_slide as *const () as u64;
+    assert_test!(here >= kernel_image_base(), "code below the image base");
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    aslr,
+    [
+        test_aslr_layout_within_bounds,
+        test_aslr_layouts_differ,
+        test_aslr_mmap_starts_at_base,
+        test_aslr_kernel_slide,
+    ]
+);
@@ -76,7 +76,8 @@ fn resolve_multi_ref(
         );
     }
 
-    let new_flags = PageFlags::USER_RW;
+    // The private copy is writable, so W^X keeps it non-executable.
+    let new_flags = PageFlags::USER_RW | PageFlags::NO_EXECUTE;
 
     // map_page_4kb_in_dir replaces the old PTE and frees old_phys (decrementing
     // its refcount). Do NOT call free_page_frame(old_phys) again — that would
@@ -45,6 +45,9 @@ pub const ET_EXEC: u16 = 2;
 /// ELF type: Shared object (position-independent executable)
 pub const ET_DYN: u16 = 3;
 
+/// ELF type: Core file
+pub const ET_CORE: u16 = 4;
+
 /// ELF machine: x86-64
 pub const EM_X86_64: u16 = 0x3E;
 
@@ -72,6 +75,12 @@ pub const PT_GNU_STACK: u32 = 0x6474_e551;
 /// Program header type: GNU relro
 pub const PT_GNU_RELRO: u32 = 0x6474_e552;
 
+/// Core note: `elf_prstatus`, the registers of one thread
+pub const NT_PRSTATUS: u32 = 1;
+
+/// Core note: `elf_prpsinfo`, the process name and IDs
+pub const NT_PRPSINFO: u32 = 3;
+
 /// Segment flag: Executable
 pub const PF_X: u32 = 0x1;
 
@@ -93,6 +102,85 @@ pub const MAX_TOTAL_MAPPED_SIZE: u64 = 256 * 1024 * 1024;
 /// Minimum ELF header size
 pub const MIN_ELF_SIZE: usize = 64;
 
+/// Longest `PT_INTERP` path accepted, including the terminating NUL
+pub const MAX_INTERP_PATH: usize = 256;
+
+/// Maximum number of `PT_DYNAMIC` entries we'll walk
+pub const MAX_DYNAMIC_ENTRIES: usize = 512;
+
+// =============================================================================
+// Dynamic Linking Constants
+// =============================================================================
+
+/// Dynamic tag: end of the dynamic array
+pub const DT_NULL: u64 = 0;
+
+/// Dynamic tag: string table offset of a needed library's name
+pub const DT_NEEDED: u64 = 1;
+
+/// Dynamic tag: size of the PLT relocation table
+pub const DT_PLTRELSZ: u64 = 2;
+
+/// Dynamic tag: address of the SysV symbol hash table
+pub const DT_HASH: u64 = 4;
+
+/// Dynamic tag: address of the dynamic string table
+pub const DT_STRTAB: u64 = 5;
+
+/// Dynamic tag: address of the dynamic symbol table
+pub const DT_SYMTAB: u64 = 6;
+
+/// Dynamic tag: address of the `Elf64_Rela` table
+pub const DT_RELA: u64 = 7;
+
+/// Dynamic tag: size of the `Elf64_Rela` table
+pub const DT_RELASZ: u64 = 8;
+
+/// Dynamic tag: size of one `Elf64_Rela` entry
+pub const DT_RELAENT: u64 = 9;
+
+/// Dynamic tag: size of the dynamic string table
+pub const DT_STRSZ: u64 = 10;
+
+/// Dynamic tag: relocation type used by the PLT
+pub const DT_PLTREL: u64 = 20;
+
+/// Dynamic tag: address of the PLT relocation table
+pub const DT_JMPREL: u64 = 23;
+
+/// Size of an `Elf64_Rela` entry
+pub const ELF64_RELA_SIZE: u64 = 24;
+
+/// Size of an `Elf64_Sym` entry
+pub const ELF64_SYM_SIZE: u64 = 24;
+
+/// Relocation: none
+pub const R_X86_64_NONE: u32 = 0;
+
+/// Relocation: absolute 64-bit, S + A
+pub const R_X86_64_64: u32 = 1;
+
+/// Relocation: RIP-relative 32-bit, S + A - P
+pub const R_X86_64_PC32: u32 = 2;
+
+/// Relocation: PLT-relative 32-bit, treated as PC32 for static images
+pub const R_X86_64_PLT32: u32 = 4;
+
+/// Relocation: GOT entry for a symbol, S
+pub const R_X86_64_GLOB_DAT: u32 = 6;
+
+/// Relocation: PLT slot for a symbol, S
+pub const R_X86_64_JUMP_SLOT: u32 = 7;
+
+/// Relocation: load base plus addend, B + A
+pub const R_X86_64_RELATIVE: u32 = 8;
+
+/// Relocation: absolute 32-bit, S + A
+pub const R_X86_64_32: u32 = 10;
+
+/// Relocation: absolute 32-bit sign-extended, S + A
+pub const R_X86_64_32S: u32 = 11;
+
 // =============================================================================
 // Error Types
 // =============================================================================
@@ -146,8 +234,12 @@ pub enum ElfError {
     NoLoadSegments,
     /// Null pointer passed
     NullPointer,
-    /// Dynamic linking (PT_INTERP) not supported
-    DynamicNotSupported,
+    /// PT_INTERP is malformed or names an unusable interpreter
+    InvalidInterpreter,
+    /// PT_DYNAMIC is malformed
+    InvalidDynamic,
+    /// Segment is both writable and executable (W^X)
+    WritableExecutable,
 }
 
 impl fmt::Display for ElfError {
@@ -176,7 +268,9 @@ impl fmt::Display for ElfError {
             Self::TooManyLoadSegments => write!(f, "too many PT_LOAD segments"),
             Self::NoLoadSegments => write!(f, "no PT_LOAD segments found"),
             Self::NullPointer => write!(f, "null pointer"),
-            Self::DynamicNotSupported => write!(f, "dynamic linking (PT_INTERP) not supported"),
+            Self::InvalidInterpreter => write!(f, "invalid program interpreter"),
+            Self::InvalidDynamic => write!(f, "invalid dynamic section"),
+            Self::WritableExecutable => write!(f, "segment is both writable and executable"),
         }
     }
 }
@@ -679,7 +773,12 @@ impl<'a> ElfValidator<'a> {
             return Err(ElfError::InvalidAlignment);
         }
 
-        // 5. Calculate actual addresses (apply load base for PIE)
+        // 5. W^X: no segment may be mapped both writable and executable
+        if phdr.p_flags & (PF_W | PF_X) == (PF_W | PF_X) {
+            return Err(ElfError::WritableExecutable);
+        }
+
+        // 6. Calculate actual addresses (apply load base for PIE)
         let vaddr = if self.header.is_pie() {
             self.load_base.wrapping_add(phdr.p_vaddr)
         } else {
@@ -692,7 +791,7 @@ impl<'a> ElfValidator<'a> {
             vaddr_end
         };
 
-        // 6. Validate address space: must be in user space
+        // 7. Validate address space: must be in user space
         // Check for kernel address space (high canonical addresses)
         if vaddr >= KERNEL_SPACE_START_VA || mem_end > KERNEL_SPACE_START_VA {
             return Err(ElfError::KernelAddressViolation);
@@ -703,7 +802,7 @@ impl<'a> ElfValidator<'a> {
             return Err(ElfError::AddressOutOfBounds);
         }
 
-        // 7. Calculate page-aligned boundaries
+        // 8. Calculate page-aligned boundaries
         let aligned_start = vaddr & !(PAGE_SIZE_4KB - 1);
         let aligned_end = (mem_end + PAGE_SIZE_4KB - 1) & !(PAGE_SIZE_4KB - 1);
 
@@ -735,13 +834,136 @@ impl<'a> ElfValidator<'a> {
 
     /// Check if the ELF requires a dynamic interpreter (PT_INTERP).
     pub fn has_interpreter(&self) -> ElfResult<bool> {
+        Ok(self.find_program_header(PT_INTERP)?.is_some())
+    }
+
+    /// The absolute path named by PT_INTERP, without its NUL, if any.
+    pub fn interpreter(&self) -> ElfResult<Option<&'a [u8]>> {
+        let Some(phdr) = self.find_program_header(PT_INTERP)? else {
+            return Ok(None);
+        };
+        let file_end = phdr.file_end()?;
+        if file_end > self.data.len() as u64
+            || phdr.p_filesz < 2
+            || phdr.p_filesz > MAX_INTERP_PATH as u64
+        {
+            return Err(ElfError::InvalidInterpreter);
+        }
+
+        let raw = &self.data[phdr.p_offset as usize..file_end as usize];
+        let len = raw
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(ElfError::InvalidInterpreter)?;
+        let path = &raw[..len];
+        if path.first() != Some(&b'/') {
+            return Err(ElfError::InvalidInterpreter);
+        }
+        Ok(Some(path))
+    }
+
+    /// Parse the PT_DYNAMIC array, if any.
+    ///
+    /// Addresses in the result are link-time virtual addresses; the loader
+    /// adds the image's load bias before using them.
+    pub fn dynamic_info(&self) -> ElfResult<Option<DynamicInfo>> {
+        let Some(phdr) = self.find_program_header(PT_DYNAMIC)? else {
+            return Ok(None);
+        };
+        let file_end = phdr.file_end()?;
+        if file_end > self.data.len() as u64 {
+            return Err(ElfError::InvalidDynamic);
+        }
+
+        let raw = &self.data[phdr.p_offset as usize..file_end as usize];
+        let mut info = DynamicInfo::default();
+        let mut pltrel = DT_RELA;
+        let mut terminated = false;
+
+        for entry in raw.chunks_exact(16).take(MAX_DYNAMIC_ENTRIES) {
+            let tag = u64::from_le_bytes(entry[0..8].try_into().unwrap_or([0; 8]));
+            let val = u64::from_le_bytes(entry[8..16].try_into().unwrap_or([0; 8]));
+            match tag {
+                DT_NULL => {
+                    terminated = true;
+                    break;
+                }
+                DT_NEEDED => info.needed_count += 1,
+                DT_PLTRELSZ => info.pltrelsz = val,
+                DT_HASH => info.hash = val,
+                DT_STRTAB => info.strtab = val,
+                DT_SYMTAB => info.symtab = val,
+                DT_RELA => info.rela = val,
+                DT_RELASZ => info.relasz = val,
+                DT_RELAENT if val != ELF64_RELA_SIZE => return Err(ElfError::InvalidDynamic),
+                DT_STRSZ => info.strsz = val,
+                DT_PLTREL => pltrel = val,
+                DT_JMPREL => info.jmprel = val,
+                _ => {}
+            }
+        }
+
+        if !terminated
+            || pltrel != DT_RELA
+            || info.relasz % ELF64_RELA_SIZE != 0
+            || info.pltrelsz % ELF64_RELA_SIZE != 0
+            || (info.needed_count != 0 && info.strtab == 0)
+        {
+            return Err(ElfError::InvalidDynamic);
+        }
+        Ok(Some(info))
+    }
+
+    /// First program header of type `p_type`.
+    fn find_program_header(&self, p_type: u32) -> ElfResult<Option<Elf64Phdr>> {
         for i in 0..self.header.e_phnum as usize {
             let phdr = self.get_program_header(i)?;
-            if phdr.p_type == PT_INTERP {
-                return Ok(true);
+            if phdr.p_type == p_type {
+                return Ok(Some(phdr));
             }
         }
-        Ok(false)
+        Ok(None)
+    }
+}
+
+// =============================================================================
+// Dynamic Linking
+// =============================================================================
+
+/// What the runtime loader needs from an image's PT_DYNAMIC array.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DynamicInfo {
+    /// Number of DT_NEEDED entries.
+    pub needed_count: usize,
+    /// DT_STRTAB address.
+    pub strtab: u64,
+    /// DT_STRSZ.
+    pub strsz: u64,
+    /// DT_SYMTAB address.
+    pub symtab: u64,
+    /// DT_HASH address (0 when absent).
+    pub hash: u64,
+    /// DT_RELA address.
+    pub rela: u64,
+    /// DT_RELASZ in bytes.
+    pub relasz: u64,
+    /// DT_JMPREL address.
+    pub jmprel: u64,
+    /// DT_PLTRELSZ in bytes.
+    pub pltrelsz: u64,
+}
+
+/// The value a dynamic relocation stores at its target.
+///
+/// `base` is the load bias of the image being relocated and `symbol` the
+/// resolved address of the referenced symbol.  Returns `None` for types a
+/// runtime loader does not handle.
+pub fn relocation_value(kind: u32, base: u64, symbol: u64, addend: i64) -> Option<u64> {
+    match kind {
+        R_X86_64_RELATIVE => Some(base.wrapping_add_signed(addend)),
+        R_X86_64_64 => Some(symbol.wrapping_add_signed(addend)),
+        R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => Some(symbol),
+        _ => None,
     }
 }
 
@@ -761,4 +983,6 @@ pub struct ElfExecInfo {
     pub phent_size: u16,
     /// Number of program headers.
     pub phnum: u16,
+    /// Load bias of the program interpreter (0 for static images).
+    pub interp_base: u64,
 }
@@ -3,7 +3,8 @@ use core::mem;
 use core::ptr;
 
 use slopos_abi::addr::VirtAddr;
-use slopos_lib::{IrqMutex, align_down_u64, align_up_usize, klog_debug, klog_info};
+use slopos_lib::lockstat::LockClass;
+use slopos_lib::{IrqMutex, align_down_u64, align_up_usize, klog_debug, klog_info, kwarn};
 
 use crate::memory_layout_defs::{KERNEL_HEAP_VBASE, KERNEL_HEAP_VEND};
 use crate::page_alloc::{alloc_page_frame, free_page_frame};
@@ -17,6 +18,9 @@ const LARGE_MAGIC: u32 = 0x4C_4152_47;
 const LARGE_FREE_MAGIC: u32 = 0x4C_4652_45;
 const SIZE_CLASSES: [usize; NUM_SIZE_CLASSES] = [16, 32, 64, 128, 256, 512, 1024, 2048];
 
+/// Buckets of [`HeapFragmentation::free_block_histogram`].
+pub const HEAP_FREE_HISTOGRAM_BUCKETS: usize = 8;
+
 #[repr(C)]
 #[derive(Clone, Copy, Default)]
 pub struct HeapStats {
@@ -62,6 +66,50 @@ impl HeapStats {
     }
 }
 
+/// What a coalesce or trim pass did.  Also kept as running totals since
+/// boot in [`HeapFragmentation::reclaimed`].
+#[derive(Clone, Copy, Default, Debug)]
+pub struct HeapReclaimStats {
+    /// Slabs with every object free, handed back to the free-block list.
+    pub slabs_released: u32,
+    /// Free blocks merged into the free block right before them.
+    pub blocks_merged: u32,
+    /// Pages unmapped from the top of the heap and returned to the page
+    /// allocator.
+    pub pages_trimmed: u32,
+}
+
+impl HeapReclaimStats {
+    fn add(&mut self, other: &Self) {
+        self.slabs_released = self.slabs_released.saturating_add(other.slabs_released);
+        self.blocks_merged = self.blocks_merged.saturating_add(other.blocks_merged);
+        self.pages_trimmed = self.pages_trimmed.saturating_add(other.pages_trimmed);
+    }
+}
+
+/// Snapshot of how the heap's free space is split up.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct HeapFragmentation {
+    /// Free blocks by size: bucket `i` counts blocks of `2^i` up to
+    /// `2^(i+1) - 1` pages, the last bucket everything larger.
+    pub free_block_histogram: [u32; HEAP_FREE_HISTOGRAM_BUCKETS],
+    /// Free objects in each slab size class, smallest class first.
+    pub free_slab_objects: [u32; NUM_SIZE_CLASSES],
+    /// Slabs with every object free.
+    pub empty_slabs: u32,
+    pub free_blocks: u32,
+    pub free_block_bytes: u64,
+    pub largest_free_block: u64,
+    /// Free blocks that start where another free block ends, i.e. merges
+    /// the next coalesce pass would make.
+    pub adjacent_free_blocks: u32,
+    /// Share of the free-block bytes outside the largest free block, per
+    /// mille: 0 when all of it is one block, towards 1000 as it splinters.
+    pub fragmentation_permille: u32,
+    pub reclaim_passes: u32,
+    pub reclaimed: HeapReclaimStats,
+}
+
 #[repr(C)]
 struct SlabHeader {
     magic: u32,
@@ -103,6 +151,8 @@ struct KernelHeap {
     caches: [SlabCache; NUM_SIZE_CLASSES],
     large_free_list: *mut LargeAllocHeader,
     stats: HeapStats,
+    reclaim_passes: u32,
+    reclaimed: HeapReclaimStats,
     initialized: bool,
     diagnostics_enabled: bool,
 }
@@ -127,13 +177,21 @@ impl KernelHeap {
                 allocation_count: 0,
                 free_count: 0,
             },
+            reclaim_passes: 0,
+            reclaimed: HeapReclaimStats {
+                slabs_released: 0,
+                blocks_merged: 0,
+                pages_trimmed: 0,
+            },
             initialized: false,
             diagnostics_enabled: true,
         }
     }
 }
 
-static KERNEL_HEAP: IrqMutex<KernelHeap> = IrqMutex::new(KernelHeap::new());
+static KERNEL_HEAP_LOCK_CLASS: LockClass = LockClass::new("kernel_heap");
+static KERNEL_HEAP: IrqMutex<KernelHeap> =
+    IrqMutex::with_class(&KERNEL_HEAP_LOCK_CLASS, KernelHeap::new());
 
 fn slab_object_start() -> usize {
     align_up_usize(mem::size_of::<SlabHeader>(), 16)
@@ -237,7 +295,7 @@ fn slab_create(heap: &mut KernelHeap, object_size: usize) -> *mut SlabHeader {
         return ptr::null_mut();
     }
 
-    let slab_addr = match map_heap_pages(heap, 1) {
+    let slab_addr = match take_free_pages(heap, 1).or_else(|| map_heap_pages(heap, 1)) {
         Some(addr) => addr,
         None => return ptr::null_mut(),
     };
@@ -320,46 +378,70 @@ fn alloc_large(heap: &mut KernelHeap, size: usize) -> *mut c_void {
         return ptr::null_mut();
     }
 
+    let base =
+        match take_free_pages(heap, pages as u32).or_else(|| map_heap_pages(heap, pages as u32)) {
+            Some(addr) => addr,
+            None => return ptr::null_mut(),
+        };
+
+    let header = base as *mut LargeAllocHeader;
+    unsafe {
+        (*header).magic = LARGE_MAGIC;
+        (*header).pages = pages as u32;
+        (*header).size = size as u32;
+        (*header).reserved = 0;
+        (*header).next = ptr::null_mut();
+    }
+
+    heap.stats.total_blocks = heap.stats.total_blocks.saturating_add(1);
+    heap.stats.record_large_alloc(size as u64);
+
+    unsafe { (base as *mut u8).add(header_size) as *mut c_void }
+}
+
+/// Unlink the first free block of at least `pages` pages and return its
+/// base.  Whatever it has beyond `pages` stays on the list as a new block.
+fn take_free_pages(heap: &mut KernelHeap, pages: u32) -> Option<u64> {
     let mut prev: *mut LargeAllocHeader = ptr::null_mut();
     let mut current = heap.large_free_list;
     while !current.is_null() {
         unsafe {
-            if (*current).pages as usize >= pages {
+            if (*current).pages >= pages {
                 if prev.is_null() {
                     heap.large_free_list = (*current).next;
                 } else {
                     (*prev).next = (*current).next;
                 }
+                heap.stats.total_blocks = heap.stats.total_blocks.saturating_sub(1);
                 let base = current as u64;
-                (*current).magic = LARGE_MAGIC;
-                (*current).size = size as u32;
-                (*current).next = ptr::null_mut();
-                heap.stats.record_large_alloc(size as u64);
-                return (base as *mut u8).add(header_size) as *mut c_void;
+                let rest = (*current).pages - pages;
+                if rest > 0 {
+                    push_free_block(heap, base + pages as u64 * PAGE_SIZE_4KB, rest);
+                }
+                return Some(base);
             }
             prev = current;
             current = (*current).next;
         }
     }
+    None
+}
 
-    let base = match map_heap_pages(heap, pages as u32) {
-        Some(addr) => addr,
-        None => return ptr::null_mut(),
-    };
-
+fn push_free_block(heap: &mut KernelHeap, base: u64, pages: u32) {
     let header = base as *mut LargeAllocHeader;
     unsafe {
-        (*header).magic = LARGE_MAGIC;
-        (*header).pages = pages as u32;
-        (*header).size = size as u32;
+        (*header).magic = LARGE_FREE_MAGIC;
+        (*header).pages = pages;
+        (*header).size = 0;
         (*header).reserved = 0;
-        (*header).next = ptr::null_mut();
+        (*header).next = heap.large_free_list;
     }
-
+    heap.large_free_list = header;
     heap.stats.total_blocks = heap.stats.total_blocks.saturating_add(1);
-    heap.stats.record_large_alloc(size as u64);
+}
 
-    unsafe { (base as *mut u8).add(header_size) as *mut c_void }
+fn free_block_end(block: *const LargeAllocHeader) -> u64 {
+    block as u64 + unsafe { (*block).pages } as u64 * PAGE_SIZE_4KB
 }
 
 fn free_large(heap: &mut KernelHeap, base: u64) -> c_int {
@@ -436,6 +518,229 @@ fn slab_free(heap: &mut KernelHeap, ptr_in: *mut c_void) -> c_int {
     0
 }
 
+/// Turn every slab whose objects are all free back into a one-page free
+/// block, so large allocations and other size classes can use the page.
+fn release_empty_slabs(heap: &mut KernelHeap) -> u32 {
+    let mut released = 0u32;
+    for idx in 0..NUM_SIZE_CLASSES {
+        let mut prev: *mut SlabHeader = ptr::null_mut();
+        let mut slab = heap.caches[idx].slabs;
+        while !slab.is_null() {
+            unsafe {
+                let next = (*slab).next;
+                if (*slab).free_count == (*slab).total_count {
+                    if prev.is_null() {
+                        heap.caches[idx].slabs = next;
+                    } else {
+                        (*prev).next = next;
+                    }
+                    let count = (*slab).total_count as u32;
+                    heap.stats.total_blocks = heap.stats.total_blocks.saturating_sub(count);
+                    heap.stats.free_blocks = heap.stats.free_blocks.saturating_sub(count);
+                    push_free_block(heap, slab as u64, 1);
+                    released += 1;
+                } else {
+                    prev = slab;
+                }
+                slab = next;
+            }
+        }
+    }
+    released
+}
+
+/// Sort the free-block list by address and merge blocks that touch.
+fn coalesce_free_blocks(heap: &mut KernelHeap) -> u32 {
+    // Insertion sort: the list is short, and this runs rarely.
+    let mut sorted: *mut LargeAllocHeader = ptr::null_mut();
+    let mut current = heap.large_free_list;
+    while !current.is_null() {
+        unsafe {
+            let next = (*current).next;
+            let mut link: *mut *mut LargeAllocHeader = &mut sorted;
+            while !(*link).is_null() && (*link as u64) < current as u64 {
+                link = &mut (**link).next;
+            }
+            (*current).next = *link;
+            *link = current;
+            current = next;
+        }
+    }
+    heap.large_free_list = sorted;
+
+    let mut merged = 0u32;
+    let mut block = sorted;
+    while !block.is_null() {
+        unsafe {
+            let next = (*block).next;
+            if !next.is_null() && free_block_end(block) == next as u64 {
+                (*block).pages += (*next).pages;
+                (*block).next = (*next).next;
+                (*next).magic = 0;
+                merged += 1;
+            } else {
+                block = next;
+            }
+        }
+    }
+    heap.stats.total_blocks = heap.stats.total_blocks.saturating_sub(merged);
+    merged
+}
+
+/// Unmap free blocks that end at the heap break, lowering the break, and
+/// return how many pages went back to the page allocator.
+fn trim_free_blocks(heap: &mut KernelHeap) -> u32 {
+    let mut trimmed = 0u32;
+    loop {
+        let mut prev: *mut LargeAllocHeader = ptr::null_mut();
+        let mut current = heap.large_free_list;
+        while !current.is_null() && free_block_end(current) != heap.current_break {
+            prev = current;
+            current = unsafe { (*current).next };
+        }
+        if current.is_null() {
+            break;
+        }
+
+        let (base, pages) = unsafe {
+            if prev.is_null() {
+                heap.large_free_list = (*current).next;
+            } else {
+                (*prev).next = (*current).next;
+            }
+            (current as u64, (*current).pages)
+        };
+        // unmap_page hands each frame back to the page allocator.
+        for i in 0..pages {
+            unmap_page(VirtAddr::new(base + i as u64 * PAGE_SIZE_4KB));
+        }
+
+        let bytes = pages as u64 * PAGE_SIZE_4KB;
+        heap.current_break = base;
+        heap.stats.total_blocks = heap.stats.total_blocks.saturating_sub(1);
+        heap.stats.total_size = heap.stats.total_size.saturating_sub(bytes);
+        heap.stats.free_size = heap
+            .stats
+            .total_size
+            .saturating_sub(heap.stats.allocated_size);
+        trimmed += pages;
+    }
+    if trimmed > 0 {
+        paging_bump_kernel_mapping_gen();
+    }
+    trimmed
+}
+
+fn heap_reclaim(heap: &mut KernelHeap, trim: bool) -> HeapReclaimStats {
+    let mut pass = HeapReclaimStats {
+        slabs_released: release_empty_slabs(heap),
+        blocks_merged: coalesce_free_blocks(heap),
+        pages_trimmed: 0,
+    };
+    if trim {
+        pass.pages_trimmed = trim_free_blocks(heap);
+    }
+    heap.reclaim_passes = heap.reclaim_passes.saturating_add(1);
+    heap.reclaimed.add(&pass);
+    if pass.slabs_released != 0 || pass.pages_trimmed != 0 {
+        klog_debug!(
+            "kernel_heap: released {} slabs, merged {} blocks, trimmed {} pages",
+            pass.slabs_released,
+            pass.blocks_merged,
+            pass.pages_trimmed
+        );
+    }
+    pass
+}
+
+fn heap_fragmentation(heap: &KernelHeap) -> HeapFragmentation {
+    let mut report = HeapFragmentation {
+        reclaim_passes: heap.reclaim_passes,
+        reclaimed: heap.reclaimed,
+        ..HeapFragmentation::default()
+    };
+
+    let mut block = heap.large_free_list as *const LargeAllocHeader;
+    while !block.is_null() {
+        unsafe {
+            let pages = (*block).pages;
+            let bucket = (pages.max(1).ilog2() as usize).min(HEAP_FREE_HISTOGRAM_BUCKETS - 1);
+            report.free_block_histogram[bucket] += 1;
+            let bytes = pages as u64 * PAGE_SIZE_4KB;
+            report.free_blocks += 1;
+            report.free_block_bytes += bytes;
+            report.largest_free_block = report.largest_free_block.max(bytes);
+
+            let end = free_block_end(block);
+            let mut other = heap.large_free_list as *const LargeAllocHeader;
+            while !other.is_null() {
+                if other as u64 == end {
+                    report.adjacent_free_blocks += 1;
+                    break;
+                }
+                other = (*other).next;
+            }
+            block = (*block).next;
+        }
+    }
+    if let Some(largest) = (report.largest_free_block * 1000).checked_div(report.free_block_bytes) {
+        report.fragmentation_permille = (1000 - largest) as u32;
+    }
+
+    for (idx, cache) in heap.caches.iter().enumerate() {
+        let mut slab = cache.slabs as *const SlabHeader;
+        while !slab.is_null() {
+            unsafe {
+                report.free_slab_objects[idx] += (*slab).free_count as u32;
+                if (*slab).free_count == (*slab).total_count {
+                    report.empty_slabs += 1;
+                }
+                slab = (*slab).next;
+            }
+        }
+    }
+    report
+}
+
+/// Report how the heap's free space is split between free blocks and slab
+/// objects, and how much of it earlier reclaim passes recovered.
+pub fn kernel_heap_fragmentation() -> HeapFragmentation {
+    heap_fragmentation(&KERNEL_HEAP.lock())
+}
+
+/// Return empty slabs to the free-block list and merge neighbouring free
+/// blocks.  No memory leaves the heap; see [`kernel_heap_trim`].
+pub fn kernel_heap_coalesce() -> HeapReclaimStats {
+    let mut heap = KERNEL_HEAP.lock();
+    if !heap.initialized {
+        return HeapReclaimStats::default();
+    }
+    heap_reclaim(&mut heap, false)
+}
+
+/// Coalesce, then unmap the free blocks at the top of the heap and give
+/// their frames back to the page allocator.
+pub fn kernel_heap_trim() -> HeapReclaimStats {
+    let mut heap = KERNEL_HEAP.lock();
+    if !heap.initialized {
+        return HeapReclaimStats::default();
+    }
+    heap_reclaim(&mut heap, true)
+}
+
+/// [`kernel_heap_trim`] for the page allocator's reclaim path.  Gives up
+/// rather than waits when the heap is locked, since the holder may be this
+/// CPU growing the heap.  Returns the number of pages freed.
+pub fn kernel_heap_try_reclaim() -> u32 {
+    let Some(mut heap) = KERNEL_HEAP.try_lock() else {
+        return 0;
+    };
+    if !heap.initialized {
+        return 0;
+    }
+    heap_reclaim(&mut heap, true).pages_trimmed
+}
+
 pub fn kmalloc(size: usize) -> *mut c_void {
     let mut heap = KERNEL_HEAP.lock();
 
@@ -480,8 +785,11 @@ pub fn kfree(ptr_in: *mut c_void) {
     }
 
     let base = align_down_u64(ptr_in as u64, PAGE_SIZE_4KB);
-    if base < heap.start_addr || base >= heap.current_break {
-        klog_info!("kfree: Invalid block or double free detected");
+    if kwarn!(
+        base < heap.start_addr || base >= heap.current_break,
+        "kfree: {:p} is outside the kernel heap",
+        ptr_in
+    ) {
         return;
     }
     let slab_result = slab_free(&mut heap, ptr_in);
@@ -494,7 +802,7 @@ pub fn kfree(ptr_in: *mut c_void) {
         return;
     }
 
-    klog_info!("kfree: Invalid block or double free detected");
+    kwarn!(true, "kfree: invalid block or double free of {:p}", ptr_in);
 }
 
 /// Minimum pages required for soft reboot coherency fix.
@@ -516,6 +824,8 @@ pub fn init_kernel_heap() -> c_int {
 
     heap.stats = HeapStats::default();
     heap.large_free_list = ptr::null_mut();
+    heap.reclaim_passes = 0;
+    heap.reclaimed = HeapReclaimStats::default();
 
     // ============================================================================
     // SOFT REBOOT COHERENCY FIX - DO NOT REMOVE
@@ -565,6 +875,11 @@ pub fn get_heap_stats(stats: *mut HeapStats) {
     }
 }
 
+/// The heap counters, or `None` when the heap is locked.
+pub fn try_heap_stats() -> Option<HeapStats> {
+    KERNEL_HEAP.try_lock().map(|heap| heap.stats)
+}
+
 pub fn kernel_heap_enable_diagnostics(enable: c_int) {
     let mut heap = KERNEL_HEAP.lock();
     heap.diagnostics_enabled = enable != 0;
@@ -584,6 +899,15 @@ pub fn print_heap_stats() {
         return;
     }
 
+    let frag = heap_fragmentation(&heap);
+    klog_info!(
+        "Free blocks: {} ({} bytes, largest {}), fragmentation {}/1000",
+        frag.free_blocks,
+        frag.free_block_bytes,
+        frag.largest_free_block,
+        frag.fragmentation_permille
+    );
+
     for cache in heap.caches.iter() {
         if cache.object_size == 0 {
             continue;
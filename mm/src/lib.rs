@@ -3,12 +3,17 @@
 #![feature(sync_unsafe_cell)]
 
 pub mod aslr;
+#[cfg(feature = "itests")]
+pub mod aslr_tests;
 pub mod cow;
 pub mod demand;
 pub mod elf;
 pub mod error;
 pub mod hhdm;
 pub mod kernel_heap;
+pub mod memmap;
+#[cfg(feature = "itests")]
+pub mod memmap_tests;
 pub mod memory_init;
 pub mod memory_layout;
 pub mod memory_layout_defs;
@@ -40,6 +45,8 @@ pub mod user_copy;
 pub mod user_ptr;
 pub mod vma_flags;
 pub mod vma_tree;
+#[cfg(feature = "itests")]
+pub mod wx_tests;
 
 use core::alloc::{GlobalAlloc, Layout};
 use core::cell::SyncUnsafeCell;
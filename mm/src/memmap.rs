@@ -0,0 +1,181 @@
+//! The final physical memory map, as the kernel sees it once the page
+//! allocator is up.
+//!
+//! [`memmap_for_each`] walks the region store in address order and fills the
+//! gaps between regions with [`MemMapKind::Hole`] entries: address space the
+//! kernel does not track, which covers MMIO windows as well as firmware and
+//! bootloader ranges.  `/proc/memmap` renders the walk together with the
+//! kernel image and the HHDM extent.
+//!
+//! [`memmap_audit`] checks the page allocator against the map.  Every frame
+//! of a reservation that excludes allocators must have been left out of the
+//! allocator zones; a Limine layout the seeding code does not expect shows
+//! up here at boot rather than as corrupted firmware memory later.
+
+use slopos_lib::klog_info;
+use slopos_lib::string::bytes_as_str;
+
+use crate::memory_init::virt_to_phys_kernel;
+use crate::memory_layout::kernel_image_bounds;
+use crate::memory_reservations::{
+    MM_RESERVATION_FLAG_EXCLUDE_ALLOCATORS, MmRegion, MmRegionKind, mm_region_count, mm_region_get,
+    mm_reservation_type_name,
+};
+use crate::page_alloc::page_allocator_zone_frames_in;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemMapKind {
+    Usable,
+    Reserved,
+    /// Not described by the region store.
+    Hole,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MemMapEntry {
+    pub phys_base: u64,
+    pub length: u64,
+    pub kind: MemMapKind,
+    /// Reservation type for [`MemMapKind::Reserved`], else the kind.
+    pub type_name: &'static str,
+    pub flags: u32,
+    pub label: [u8; 32],
+}
+
+impl MemMapEntry {
+    fn from_region(region: &MmRegion) -> Self {
+        let (kind, type_name) = match region.kind {
+            MmRegionKind::Usable => (MemMapKind::Usable, "usable"),
+            MmRegionKind::Reserved => {
+                (MemMapKind::Reserved, mm_reservation_type_name(region.type_))
+            }
+        };
+        Self {
+            phys_base: region.phys_base,
+            length: region.length,
+            kind,
+            type_name,
+            flags: region.flags,
+            label: region.label,
+        }
+    }
+
+    fn hole(phys_base: u64, end: u64) -> Self {
+        Self {
+            phys_base,
+            length: end - phys_base,
+            kind: MemMapKind::Hole,
+            type_name: "hole",
+            flags: 0,
+            label: [0; 32],
+        }
+    }
+
+    pub fn end(&self) -> u64 {
+        self.phys_base + self.length
+    }
+
+    pub fn label(&self) -> &str {
+        bytes_as_str(&self.label)
+    }
+}
+
+fn for_each_region(mut f: impl FnMut(&MmRegion)) {
+    for i in 0..mm_region_count() {
+        let region = mm_region_get(i);
+        if region.is_null() {
+            continue;
+        }
+        let region = unsafe { &*region };
+        if region.length != 0 {
+            f(region);
+        }
+    }
+}
+
+/// Visit the memory map from physical address 0 up, holes included.
+pub fn memmap_for_each(mut f: impl FnMut(&MemMapEntry)) {
+    let mut cursor = 0u64;
+    for_each_region(|region| {
+        if region.phys_base > cursor {
+            f(&MemMapEntry::hole(cursor, region.phys_base));
+        }
+        f(&MemMapEntry::from_region(region));
+        cursor = cursor.max(region.phys_base + region.length);
+    });
+}
+
+/// Physical range of the kernel image, and the virtual address it runs at.
+/// `None` before the kernel bounds are known.
+pub fn memmap_kernel_image() -> Option<(u64, u64, u64)> {
+    let (start, end) = kernel_image_bounds();
+    if start == 0 || end <= start {
+        return None;
+    }
+    let phys = virt_to_phys_kernel(start);
+    Some((phys, phys + (end - start), start))
+}
+
+/// Virtual range of the higher-half direct map that covers the memory map.
+/// `None` before the HHDM offset is known.
+pub fn memmap_hhdm_extent() -> Option<(u64, u64)> {
+    let offset = crate::hhdm::try_offset()?;
+    let mut top = 0u64;
+    for_each_region(|region| top = top.max(region.phys_base + region.length));
+    Some((offset, offset + top))
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MemMapAudit {
+    /// Reservations that exclude allocators.
+    pub reservations_checked: u32,
+    /// Of those, reservations with frames inside an allocator zone.
+    pub overlapping_reservations: u32,
+    pub overlapping_frames: u32,
+    /// Regions out of order or overlapping their predecessor.
+    pub misordered_regions: u32,
+}
+
+impl MemMapAudit {
+    pub fn is_clean(&self) -> bool {
+        self.overlapping_reservations == 0 && self.misordered_regions == 0
+    }
+}
+
+/// Check the region store is ordered and that no allocator zone holds a
+/// frame of a reservation that excludes allocators.  Offenders are logged.
+pub fn memmap_audit() -> MemMapAudit {
+    let mut audit = MemMapAudit::default();
+    let mut prev_end = 0u64;
+    for_each_region(|region| {
+        if region.phys_base < prev_end {
+            klog_info!(
+                "MM: memmap region 0x{:x} starts inside its predecessor (ends 0x{:x})",
+                region.phys_base,
+                prev_end
+            );
+            audit.misordered_regions += 1;
+        }
+        prev_end = prev_end.max(region.phys_base + region.length);
+
+        if region.kind != MmRegionKind::Reserved
+            || region.flags & MM_RESERVATION_FLAG_EXCLUDE_ALLOCATORS == 0
+        {
+            return;
+        }
+        audit.reservations_checked += 1;
+        let frames = page_allocator_zone_frames_in(region.phys_base, region.length);
+        if frames != 0 {
+            klog_info!(
+                "MM: {} frames of reserved region {} (0x{:x} - 0x{:x}) are in an allocator zone",
+                frames,
+                bytes_as_str(&region.label),
+                region.phys_base,
+                region.phys_base + region.length - 1
+            );
+            audit.overlapping_reservations += 1;
+            audit.overlapping_frames += frames;
+        }
+    });
+    audit
+}
@@ -0,0 +1,99 @@
+//! Memory map tests: the walk `/proc/memmap` renders and the boot audit.
+
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::memmap::{
+    MemMapKind, memmap_audit, memmap_for_each, memmap_hhdm_extent, memmap_kernel_image,
+};
+use crate::memory_reservations::{MmRegionKind, mm_region_total_bytes};
+use crate::page_alloc::{alloc_page_frame, free_page_frame, page_allocator_zone_frames_in};
+use crate::paging_defs::PAGE_SIZE_4KB;
+
+pub fn test_memmap_audit_is_clean() -> TestResult {
+    let audit = memmap_audit();
+    assert_test!(audit.reservations_checked > 0, "no reservations audited");
+    assert_eq_test!(audit.overlapping_frames, 0, "reserved frames in a zone");
+    assert_test!(audit.is_clean());
+    pass!()
+}
+
+pub fn test_memmap_entries_tile_address_space() -> TestResult {
+    let mut cursor = 0u64;
+    let mut gaps = 0u32;
+    let mut usable = 0u64;
+    memmap_for_each(|entry| {
+        if entry.phys_base != cursor {
+            gaps += 1;
+        }
+        if entry.kind == MemMapKind::Usable {
+            usable += entry.length;
+        }
+        cursor = entry.end();
+    });
+    assert_eq_test!(gaps, 0, "entries leave gaps or overlap");
+    assert_eq_test!(usable, mm_region_total_bytes(MmRegionKind::Usable));
+    pass!()
+}
+
+pub fn test_memmap_lists_kernel_image() -> TestResult {
+    let Some((phys_start, phys_end, virt_start)) = memmap_kernel_image() else {
+        return fail!("kernel image bounds unknown");
+    };
+    assert_test!(phys_end > phys_start);
+    assert_test!(virt_start > phys_start, "image not in the higher half");
+
+    // Other boot reservations may split it, but every byte is reserved.
+    let mut reserved = 0u64;
+    memmap_for_each(|entry| {
+        if entry.kind == MemMapKind::Reserved {
+            let start = entry.phys_base.max(phys_start);
+            reserved += entry.end().min(phys_end).saturating_sub(start);
+        }
+    });
+    assert_eq_test!(reserved, phys_end - phys_start, "kernel image not reserved");
+    assert_eq_test!(
+        page_allocator_zone_frames_in(phys_start, phys_end - phys_start),
+        0,
+        "kernel image in an allocator zone"
+    );
+    pass!()
+}
+
+pub fn test_memmap_hhdm_covers_usable_memory() -> TestResult {
+    let Some((start, end)) = memmap_hhdm_extent() else {
+        return fail!("HHDM offset unknown");
+    };
+    let mut top = 0u64;
+    memmap_for_each(|entry| {
+        if entry.kind == MemMapKind::Usable {
+            top = top.max(entry.end());
+        }
+    });
+    assert_eq_test!(start, crate::hhdm::offset());
+    assert_test!(end - start >= top, "HHDM ends below usable memory");
+    pass!()
+}
+
+pub fn test_memmap_zone_frames_counts_allocated_frame() -> TestResult {
+    let phys = alloc_page_frame(0);
+    if phys.is_null() {
+        return fail!("alloc_page_frame failed");
+    }
+    let frames = page_allocator_zone_frames_in(phys.as_u64(), PAGE_SIZE_4KB);
+    free_page_frame(phys);
+    assert_eq_test!(frames, 1, "allocated frame outside every zone");
+    assert_eq_test!(page_allocator_zone_frames_in(phys.as_u64(), 0), 0);
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    memmap,
+    [
+        test_memmap_audit_is_clean,
+        test_memmap_entries_tile_address_space,
+        test_memmap_lists_kernel_image,
+        test_memmap_hhdm_covers_usable_memory,
+        test_memmap_zone_frames_counts_allocated_frame,
+    ]
+);
@@ -1,8 +1,10 @@
+use crate::aslr::kernel_image_base;
 use crate::kernel_heap::init_kernel_heap;
+use crate::memmap::memmap_audit;
 use crate::memory_layout::{init_kernel_bounds, kernel_image_bounds};
 use crate::memory_layout_defs::{
     BOOT_STACK_PHYS_ADDR, BOOT_STACK_SIZE, EARLY_PD_PHYS_ADDR, EARLY_PDPT_PHYS_ADDR,
-    EARLY_PML4_PHYS_ADDR, HHDM_VIRT_BASE, KERNEL_VIRTUAL_BASE,
+    EARLY_PML4_PHYS_ADDR, HHDM_VIRT_BASE,
 };
 use crate::memory_reservations::{
     MM_RESERVATION_FLAG_ALLOW_MM_PHYS_TO_VIRT, MM_RESERVATION_FLAG_EXCLUDE_ALLOCATORS,
@@ -139,9 +141,10 @@ fn add_usable_or_panic(base: u64, length: u64, label: *const c_char) {
     }
 }
 
-fn virt_to_phys_kernel(virt: u64) -> u64 {
-    if virt >= KERNEL_VIRTUAL_BASE {
-        return virt - KERNEL_VIRTUAL_BASE;
+pub(crate) fn virt_to_phys_kernel(virt: u64) -> u64 {
+    let image_base = kernel_image_base();
+    if virt >= image_base {
+        return virt - image_base;
     }
     if crate::hhdm::is_available() {
         let hhdm_base = crate::hhdm::offset();
@@ -623,6 +626,13 @@ pub fn init_memory_system(
         if finalize_page_allocator() != 0 {
             klog_info!("MM: WARNING - page allocator finalization reported issues");
         }
+        let audit = memmap_audit();
+        if !audit.is_clean() {
+            panic!(
+                "MM: memory map audit failed ({} reserved regions in allocator zones, {} misordered)",
+                audit.overlapping_reservations, audit.misordered_regions
+            );
+        }
 
         slopos_lib::panic_recovery::register_panic_cleanup(mm_panic_cleanup);
 
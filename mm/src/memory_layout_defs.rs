@@ -98,6 +98,13 @@ pub const PROCESS_MMAP_START_VA: u64 = 0x0000_0000_4000_0000;
 /// mmap region end virtual address (below stack).
 pub const PROCESS_MMAP_END_VA: u64 = 0x0000_7FFF_FE00_0000;
 
+/// Where the program interpreter of a dynamically linked image is loaded,
+/// between the mmap region and the lowest randomized stack.
+pub const PROCESS_INTERP_BASE_VA: u64 = PROCESS_MMAP_END_VA;
+
+/// Largest span the program interpreter may occupy (8 MB).
+pub const PROCESS_INTERP_MAX_SIZE: u64 = 0x0000_0000_0080_0000;
+
 // =============================================================================
 // Exception Stack Region
 // =============================================================================
@@ -135,6 +142,10 @@ pub struct ProcessMemoryLayout {
     pub heap_max: u64,
     pub stack_top: u64,
     pub stack_size: u64,
+    /// Load address for position-independent executables.
+    pub load_base: u64,
+    /// Where the kernel starts looking for free mmap space.
+    pub mmap_base: u64,
     pub user_space_start: u64,
     pub user_space_end: u64,
 }
@@ -146,6 +157,8 @@ pub const DEFAULT_PROCESS_LAYOUT: ProcessMemoryLayout = ProcessMemoryLayout {
     heap_max: PROCESS_HEAP_MAX_VA,
     stack_top: PROCESS_STACK_TOP_VA,
     stack_size: PROCESS_STACK_SIZE_BYTES,
+    load_base: PROCESS_CODE_START_VA,
+    mmap_base: PROCESS_MMAP_START_VA,
     user_space_start: USER_SPACE_START_VA,
     user_space_end: USER_SPACE_END_VA,
 };
@@ -51,7 +51,19 @@ impl MmioRegion {
         }
     }
 
+    /// Map device registers uncached (UC-).
     pub fn map(phys: PhysAddr, size: usize) -> Option<Self> {
+        Self::map_with_flags(phys, size, PageFlags::MMIO)
+    }
+
+    /// Map write-combining, for framebuffers and GTT tables the CPU only
+    /// streams writes into.  Reads are uncached and slow; callers that need
+    /// their writes visible to the device must fence first.
+    pub fn map_wc(phys: PhysAddr, size: usize) -> Option<Self> {
+        Self::map_with_flags(phys, size, PageFlags::MMIO_WC)
+    }
+
+    fn map_with_flags(phys: PhysAddr, size: usize, flags: PageFlags) -> Option<Self> {
         if phys.is_null() || size == 0 {
             return None;
         }
@@ -68,13 +80,11 @@ impl MmioRegion {
 
         let virt_base = mmio_alloc_virt(total_size)?;
 
-        let mmio_flags = PageFlags::MMIO.bits();
-
         for i in 0..num_pages {
             let page_phys = PhysAddr::new(aligned_phys + i * PAGE_SIZE_4KB);
             let page_virt = VirtAddr::new(virt_base + i * PAGE_SIZE_4KB);
 
-            if map_page_4kb(page_virt, page_phys, mmio_flags) != 0 {
+            if map_page_4kb(page_virt, page_phys, flags.bits()) != 0 {
                 return None;
             }
         }
@@ -1,8 +1,11 @@
-use slopos_abi::addr::PhysAddr;
+use slopos_abi::addr::{PhysAddr, VirtAddr};
 use slopos_lib::klog_info;
 use slopos_lib::testing::TestResult;
 
 use crate::mmio::MmioRegion;
+use crate::paging::{paging_get_kernel_directory, paging_get_pte_flags};
+use crate::paging_defs::{PAGE_SIZE_4KB, PageFlags};
+use crate::pat::{PAGE_FLAGS_UC_MINUS, PAGE_FLAGS_WC};
 
 pub fn test_mmio_empty_region_state() -> TestResult {
     let region = MmioRegion::empty();
@@ -132,6 +135,44 @@ pub fn test_mmio_map_near_phys_limit() -> TestResult {
     TestResult::Pass
 }
 
+/// `map_wc` must select PAT entry 1 (PWT only); `map` stays UC- (PCD only).
+///
+/// Maps the LAPIC page, which exists on every x86 machine and is never
+/// freed; the aliases are only inspected, not accessed.
+pub fn test_mmio_map_wc_cache_bits() -> TestResult {
+    const LAPIC_PHYS: u64 = 0xFEE0_0000;
+    let phys = PhysAddr::new(LAPIC_PHYS);
+
+    let flags_of = |region: Option<MmioRegion>| {
+        let virt = VirtAddr::new(region?.virt_base());
+        paging_get_pte_flags(paging_get_kernel_directory(), virt)
+    };
+    let wc_flags = flags_of(MmioRegion::map_wc(phys, PAGE_SIZE_4KB as usize));
+    let uc_flags = flags_of(MmioRegion::map(phys, PAGE_SIZE_4KB as usize));
+
+    let (Some(wc_flags), Some(uc_flags)) = (wc_flags, uc_flags) else {
+        klog_info!("MMIO_TEST: WC/UC mapping of the LAPIC page failed");
+        return TestResult::Fail;
+    };
+    let cache_bits = PageFlags::WRITE_THROUGH | PageFlags::CACHE_DISABLE;
+    if wc_flags & cache_bits != PAGE_FLAGS_WC {
+        klog_info!(
+            "MMIO_TEST: map_wc PTE flags {:#x} are not WC",
+            wc_flags.bits()
+        );
+        return TestResult::Fail;
+    }
+    if uc_flags & cache_bits != PAGE_FLAGS_UC_MINUS {
+        klog_info!(
+            "MMIO_TEST: map PTE flags {:#x} are not UC-",
+            uc_flags.bits()
+        );
+        return TestResult::Fail;
+    }
+
+    TestResult::Pass
+}
+
 slopos_lib::define_test_suite!(
     mmio,
     [
@@ -143,5 +184,6 @@ slopos_lib::define_test_suite!(
         test_mmio_map_null_addr,
         test_mmio_map_large_size,
         test_mmio_map_near_phys_limit,
+        test_mmio_map_wc_cache_bits,
     ]
 );
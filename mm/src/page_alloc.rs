@@ -41,6 +41,7 @@ use core::ptr;
 use core::sync::atomic::{AtomicU32, Ordering};
 
 use slopos_abi::addr::PhysAddr;
+use slopos_lib::lockstat::LockClass;
 use slopos_lib::{
     InitFlag, IrqMutex, MAX_CPUS, align_down_u64, align_up_u64, klog_debug, klog_info,
 };
@@ -492,7 +493,9 @@ impl PageAllocator {
     }
 }
 
-static PAGE_ALLOCATOR: IrqMutex<PageAllocator> = IrqMutex::new(PageAllocator::new());
+static PAGE_ALLOCATOR_LOCK_CLASS: LockClass = LockClass::new("page_alloc");
+static PAGE_ALLOCATOR: IrqMutex<PageAllocator> =
+    IrqMutex::with_class(&PAGE_ALLOCATOR_LOCK_CLASS, PageAllocator::new());
 
 const DMA_MEMORY_LIMIT: u64 = 0x0100_0000;
 
@@ -809,6 +812,7 @@ pub fn alloc_page_frames(count: u32, flags: u32) -> PhysAddr {
         && PCP_INIT.is_set();
 
     let mut attempts = 0u32;
+    let mut reclaimed = false;
     loop {
         let frame_num = if use_pcp {
             let cpu = get_current_cpu();
@@ -838,6 +842,14 @@ pub fn alloc_page_frames(count: u32, flags: u32) -> PhysAddr {
         };
 
         if frame_num == INVALID_PAGE_FRAME {
+            // Free pages at the top of the kernel heap are the one thing we
+            // can get back cheaply; retry once if it had any.
+            if !reclaimed {
+                reclaimed = true;
+                if crate::kernel_heap::kernel_heap_try_reclaim() != 0 {
+                    continue;
+                }
+            }
             klog_info!("alloc_page_frames: No suitable block available");
             return PhysAddr::NULL;
         }
@@ -975,6 +987,17 @@ pub fn get_page_allocator_stats(total: *mut u32, free: *mut u32, allocated: *mut
     }
 }
 
+/// Total, free and allocated frames, or `None` when the allocator is
+/// locked.  For the panic path, which must not wait on a lock.
+pub fn try_page_allocator_stats() -> Option<(u32, u32, u32)> {
+    let alloc = PAGE_ALLOCATOR.try_lock()?;
+    Some((
+        alloc.total_frames,
+        alloc.free_frames,
+        alloc.allocated_frames,
+    ))
+}
+
 pub fn get_pcp_stats(cpu: usize, count: *mut u32, allocs: *mut u32, frees: *mut u32) {
     if cpu >= MAX_CPUS {
         return;
@@ -1000,6 +1023,22 @@ pub fn page_frame_is_tracked(phys_addr: PhysAddr) -> c_int {
     (frame_num < alloc.total_frames) as c_int
 }
 
+/// Number of frames in `phys_base..phys_base + length` that were seeded into
+/// an allocator zone, whatever their state now.
+pub fn page_allocator_zone_frames_in(phys_base: u64, length: u64) -> u32 {
+    let alloc = PAGE_ALLOCATOR.lock();
+    let start = alloc.phys_to_frame(PhysAddr::new(align_down_u64(phys_base, PAGE_SIZE_4KB)));
+    let end = alloc
+        .phys_to_frame(PhysAddr::new(align_up_u64(
+            phys_base.saturating_add(length),
+            PAGE_SIZE_4KB,
+        )))
+        .min(alloc.total_frames);
+    (start..end)
+        .filter(|&frame| alloc.frame_region_id(frame) != INVALID_REGION_ID)
+        .count() as u32
+}
+
 pub fn page_frame_can_free(phys_addr: PhysAddr) -> c_int {
     let alloc = PAGE_ALLOCATOR.lock();
     let frame_num = alloc.phys_to_frame(phys_addr);
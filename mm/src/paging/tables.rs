@@ -9,6 +9,7 @@ use slopos_abi::addr::{PhysAddr, VirtAddr};
 use slopos_lib::{cpu, klog_debug, klog_info};
 
 use super::walker::{PageTableWalker, WalkAction};
+use crate::aslr::kernel_image_base;
 use crate::hhdm::{self, PhysAddrHhdm};
 use crate::memory_layout_defs::KERNEL_VIRTUAL_BASE;
 use crate::page_alloc::{
@@ -601,7 +602,7 @@ pub fn init_paging() {
         }
         (*KERNEL_PAGE_DIR.get()).pml4 = pml4_ptr;
 
-        let kernel_phys = virt_to_phys(VirtAddr::new(KERNEL_VIRTUAL_BASE));
+        let kernel_phys = virt_to_phys(VirtAddr::new(kernel_image_base()));
         if kernel_phys.is_null() {
             panic!("Higher-half kernel mapping not found");
         }
@@ -625,10 +626,10 @@ pub fn init_paging() {
 pub fn get_memory_layout_info(kernel_virt_base: *mut u64, kernel_phys_base: *mut u64) {
     unsafe {
         if !kernel_virt_base.is_null() {
-            *kernel_virt_base = KERNEL_VIRTUAL_BASE;
+            *kernel_virt_base = kernel_image_base();
         }
         if !kernel_phys_base.is_null() {
-            *kernel_phys_base = virt_to_phys(VirtAddr::new(KERNEL_VIRTUAL_BASE)).as_u64();
+            *kernel_phys_base = virt_to_phys(VirtAddr::new(kernel_image_base())).as_u64();
         }
     }
 }
@@ -1041,3 +1042,65 @@ pub fn paging_get_pte_flags(page_dir: *mut ProcessPageDir, vaddr: VirtAddr) -> O
         Some(pt_entry.flags())
     }
 }
+
+/// Call `f(vaddr, size, flags)` for every present leaf mapping in the user
+/// half of `page_dir`.
+///
+/// `flags` are the effective permissions: USER and WRITABLE only if every
+/// level grants them, NO_EXECUTE if any level sets it.
+pub fn paging_for_each_user_mapping(
+    page_dir: *mut ProcessPageDir,
+    mut f: impl FnMut(VirtAddr, u64, PageFlags),
+) {
+    if page_dir.is_null() || unsafe { (*page_dir).pml4.is_null() } {
+        return;
+    }
+    unsafe {
+        let pml4 = (*page_dir).pml4;
+        // Entries 256-511 are the shared kernel half.
+        for pml4_idx in 0..256 {
+            let entry = (&*pml4).entry(pml4_idx);
+            if !entry.is_present() {
+                continue;
+            }
+            let base = pml4_idx as u64 * PageTableLevel::Four.entry_size();
+            walk_user_level(
+                entry.table_ptr(),
+                PageTableLevel::Three,
+                base,
+                entry.flags(),
+                &mut f,
+            );
+        }
+    }
+}
+
+unsafe fn walk_user_level(
+    table: *mut PageTable,
+    level: PageTableLevel,
+    base: u64,
+    parent: PageFlags,
+    f: &mut impl FnMut(VirtAddr, u64, PageFlags),
+) {
+    if table.is_null() {
+        return;
+    }
+    let inherited = PageFlags::USER | PageFlags::WRITABLE;
+    for (idx, entry) in (*table).iter().enumerate() {
+        if !entry.is_present() {
+            continue;
+        }
+        let flags = entry.flags();
+        let effective =
+            (flags - inherited) | (flags & parent & inherited) | (parent & PageFlags::NO_EXECUTE);
+        let vaddr = base + idx as u64 * level.entry_size();
+
+        if level == PageTableLevel::One || entry.is_huge() {
+            f(VirtAddr::new(vaddr), level.entry_size(), effective);
+            continue;
+        }
+        if let Some(next) = level.next_lower() {
+            walk_user_level(entry.table_ptr(), next, vaddr, effective, f);
+        }
+    }
+}
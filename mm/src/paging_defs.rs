@@ -69,7 +69,10 @@ bitflags! {
         const USER_RO = Self::PRESENT.bits() | Self::USER.bits();
         /// Large kernel page (PRESENT | WRITABLE | HUGE).
         const LARGE_KERNEL_RW = Self::PRESENT.bits() | Self::WRITABLE.bits() | Self::HUGE.bits();
+        /// Device registers: UC- under the SlopOS PAT layout.
         const MMIO = Self::PRESENT.bits() | Self::WRITABLE.bits() | Self::CACHE_DISABLE.bits() | Self::NO_EXECUTE.bits();
+        /// Framebuffers and other streamed-to memory: WC (PAT entry 1).
+        const MMIO_WC = Self::PRESENT.bits() | Self::WRITABLE.bits() | Self::WRITE_THROUGH.bits() | Self::NO_EXECUTE.bits();
     }
 }
 
@@ -27,6 +27,8 @@ use slopos_lib::cpu::cpuid::{CPUID_FEAT_EDX_PAT, CPUID_LEAF_FEATURES};
 use slopos_lib::cpu::msr::Msr;
 use slopos_lib::{InitFlag, cpu, klog_debug, klog_info};
 
+use crate::paging_defs::PageFlags;
+
 // =============================================================================
 // Memory Type Constants
 // =============================================================================
@@ -74,6 +76,32 @@ const PAT_VALUE: u64 = (MEM_TYPE_WB as u64)
     | ((MEM_TYPE_UC_MINUS as u64) << 48)
     | ((MEM_TYPE_UC as u64) << 56);
 
+// =============================================================================
+// PTE Cache Bits
+// =============================================================================
+
+/// PTE bits selecting WC for a 4 KiB page (PA1: PWT=1, PCD=0, PAT=0).
+pub const PAGE_FLAGS_WC: PageFlags = PageFlags::WRITE_THROUGH;
+
+/// PTE bits selecting UC- for a 4 KiB page (PA2: PWT=0, PCD=1, PAT=0).
+pub const PAGE_FLAGS_UC_MINUS: PageFlags = PageFlags::CACHE_DISABLE;
+
+/// PTE bits selecting UC for a 4 KiB page (PA3: PWT=1, PCD=1, PAT=0).
+pub const PAGE_FLAGS_UC: PageFlags = PageFlags::WRITE_THROUGH.union(PageFlags::CACHE_DISABLE);
+
+/// PTE bits selecting `mem_type` for a 4 KiB page under [`PAT_VALUE`].
+///
+/// Returns `None` for types the layout has no entry for (WT, WP).
+pub const fn page_flags_for(mem_type: u8) -> Option<PageFlags> {
+    match mem_type {
+        MEM_TYPE_WB => Some(PageFlags::empty()),
+        MEM_TYPE_WC => Some(PAGE_FLAGS_WC),
+        MEM_TYPE_UC_MINUS => Some(PAGE_FLAGS_UC_MINUS),
+        MEM_TYPE_UC => Some(PAGE_FLAGS_UC),
+        _ => None,
+    }
+}
+
 static PAT_INIT: InitFlag = InitFlag::new();
 static PAT_SUPPORTED: InitFlag = InitFlag::new();
 
@@ -144,6 +172,24 @@ pub fn pat_init() {
     let old_pat = cpu::read_msr(Msr::PAT);
     klog_debug!("PAT: Current value: 0x{:016x}", old_pat);
 
+    program_pat();
+    klog_info!("PAT: Initialized with WC support (PA1=WC, PA5=WC)");
+}
+
+/// Load the SlopOS PAT layout on an application processor.
+///
+/// The PAT MSR is per-CPU; every AP must match the BSP before it touches
+/// a WC mapping, or the same PTE bits select a different memory type on
+/// different CPUs.
+pub fn pat_init_ap() {
+    if !is_initialized() {
+        panic!("PAT: AP init before BSP init");
+    }
+    program_pat();
+}
+
+/// Write [`PAT_VALUE`] on the calling CPU (steps 1–10 above).
+fn program_pat() {
     let flags = cpu::save_flags_cli();
 
     cpu::wbinvd();
@@ -168,5 +214,9 @@ pub fn pat_init() {
             PAT_VALUE, new_pat
         );
     }
-    klog_info!("PAT: Initialized with WC support (PA1=WC, PA5=WC)");
+}
+
+/// Whether the calling CPU's PAT MSR holds the SlopOS layout.
+pub fn pat_matches_layout() -> bool {
+    cpu::read_msr(Msr::PAT) == PAT_VALUE
 }
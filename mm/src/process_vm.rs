@@ -1,11 +1,15 @@
 use core::ffi::c_int;
 use core::ptr;
 
-use slopos_abi::addr::VirtAddr;
+use slopos_abi::addr::{PhysAddr, VirtAddr};
+use slopos_lib::lockstat::LockClass;
 use slopos_lib::{IrqMutex, align_down, align_up, klog_debug, klog_info};
 
 use crate::aslr;
-use crate::elf::{ElfError, ElfValidator, MAX_LOAD_SEGMENTS, PF_W, ValidatedSegment};
+use crate::elf::{
+    ElfError, ElfValidator, MAX_LOAD_SEGMENTS, PF_W, PF_X, R_X86_64_32, R_X86_64_32S, R_X86_64_64,
+    R_X86_64_PC32, R_X86_64_PLT32, R_X86_64_RELATIVE, ValidatedSegment,
+};
 use crate::hhdm::PhysAddrHhdm;
 use crate::kernel_heap::{kfree, kmalloc};
 use crate::memory_layout_defs::DEFAULT_PROCESS_LAYOUT;
@@ -15,8 +19,9 @@ use crate::page_alloc::{
 };
 use crate::paging::{
     PageTable, ProcessPageDir, map_page_4kb_in_dir, paging_copy_kernel_mappings,
-    paging_free_user_space, paging_get_pte_flags, paging_mark_cow, paging_mark_range_user,
-    paging_sync_kernel_mappings, unmap_page_in_dir, virt_to_phys_in_dir,
+    paging_for_each_user_mapping, paging_free_user_space, paging_get_pte_flags, paging_mark_cow,
+    paging_mark_range_user, paging_sync_kernel_mappings, paging_update_range_protection,
+    unmap_page_in_dir, virt_to_phys_in_dir,
 };
 use crate::paging_defs::{PAGE_SIZE_4KB, PageFlags};
 use crate::vma_flags::VmaFlags;
@@ -34,6 +39,8 @@ struct ProcessVm {
     heap_end: u64,
     stack_start: u64,
     stack_end: u64,
+    load_base: u64,
+    mmap_base: u64,
     total_pages: u32,
     flags: u32,
     next: *mut ProcessVm,
@@ -53,6 +60,8 @@ impl ProcessVm {
             heap_end: 0,
             stack_start: 0,
             stack_end: 0,
+            load_base: 0,
+            mmap_base: 0,
             total_pages: 0,
             flags: 0,
             next: ptr::null_mut(),
@@ -68,6 +77,8 @@ impl ProcessVm {
         self.heap_end = 0;
         self.stack_start = 0;
         self.stack_end = 0;
+        self.load_base = 0;
+        self.mmap_base = 0;
         self.total_pages = 0;
         self.flags = 0;
         self.next = ptr::null_mut();
@@ -96,7 +107,9 @@ impl VmManager {
     }
 }
 
-static VM_MANAGER: IrqMutex<VmManager> = IrqMutex::new(VmManager::new());
+static VM_MANAGER_LOCK_CLASS: LockClass = LockClass::new("vm_manager");
+static VM_MANAGER: IrqMutex<VmManager> =
+    IrqMutex::with_class(&VM_MANAGER_LOCK_CLASS, VmManager::new());
 
 fn vma_range_valid(start: u64, end: u64) -> bool {
     start < end && (start & (PAGE_SIZE_4KB - 1)) == 0 && (end & (PAGE_SIZE_4KB - 1)) == 0
@@ -351,12 +364,6 @@ struct Elf64Rela {
 // ELF section types
 const SHT_RELA: u32 = 4;
 
-// x86-64 relocation types
-const R_X86_64_64: u32 = 1; // Absolute 64-bit
-const R_X86_64_PC32: u32 = 2; // RIP-relative 32-bit
-const R_X86_64_32: u32 = 10; // Absolute 32-bit
-const R_X86_64_32S: u32 = 11; // Absolute 32-bit sign-extended
-
 fn apply_elf_relocations(
     payload: *const u8,
     payload_len: usize,
@@ -498,8 +505,7 @@ fn apply_elf_relocations(
             // For R_X86_64_PLT32/PC32: read current offset, calculate symbol = rip_after + offset + addend
             // For others: use addend or read from target
             let symbol_va = match reloc_type {
-                R_X86_64_PC32 | 4 => {
-                    // 4 = R_X86_64_PLT32
+                R_X86_64_PC32 | R_X86_64_PLT32 => {
                     // For PC32/PLT32, read current offset from instruction and calculate symbol
                     let read_page_va = reloc_user_addr & !(PAGE_SIZE_4KB - 1);
                     let read_page_off = (reloc_user_addr & (PAGE_SIZE_4KB - 1)) as usize;
@@ -594,8 +600,8 @@ fn apply_elf_relocations(
                         core::ptr::write_unaligned(reloc_ptr as *mut u64, user_symbol_va);
                     }
                 }
-                R_X86_64_PC32 | 4 => {
-                    // 4 = R_X86_64_PLT32, same as PC32 for static binaries
+                R_X86_64_PC32 | R_X86_64_PLT32 => {
+                    // PLT32 is the same as PC32 for static binaries
                     // RIP-relative 32-bit: offset = symbol - (RIP after instruction)
                     let rip_after = reloc_user_addr + 4; // 32-bit = 4 bytes
                     let offset = (user_symbol_va as i64 - rip_after as i64) as i32;
@@ -625,15 +631,20 @@ pub fn process_vm_load_elf_data(
     data: &[u8],
     entry_out: &mut u64,
 ) -> Result<crate::elf::ElfExecInfo, ElfError> {
-    let code_base = crate::memory_layout_defs::PROCESS_CODE_START_VA;
+    let validator = ElfValidator::new(data)?;
+    let pie = validator.header().is_pie();
+    let code_base = if pie {
+        pie_load_base(process_id, &validator)?
+    } else {
+        crate::memory_layout_defs::PROCESS_CODE_START_VA
+    };
 
-    let validator = ElfValidator::new(data)?.with_load_base(code_base);
+    let validator = validator.with_load_base(code_base);
     let header = validator.header();
 
-    // Reject dynamically-linked binaries (PT_INTERP present).
-    if validator.has_interpreter()? {
-        return Err(ElfError::DynamicNotSupported);
-    }
+    // A dynamically linked image is relocated by its interpreter, which the
+    // caller loads with `process_vm_load_interpreter` once this returns.
+    let dynamic = validator.has_interpreter()?;
 
     let (segments, segment_count) = validator.validate_load_segments()?;
     let segments = &segments[..segment_count];
@@ -648,8 +659,10 @@ pub fn process_vm_load_elf_data(
     }
 
     let (min_vaddr, needs_reloc) = calculate_load_offset(segments, code_base);
+    let needs_reloc = needs_reloc && !pie;
 
     unmap_existing_code_region(page_dir, code_base);
+    clear_interp_region(process);
 
     let mut section_mappings: [(u64, u64, u64); MAX_LOAD_SEGMENTS] = [(0, 0, 0); MAX_LOAD_SEGMENTS];
     let mut mapping_count = 0usize;
@@ -677,7 +690,7 @@ pub fn process_vm_load_elf_data(
         mapped_pages = mapped_pages.saturating_add(pages);
     }
 
-    if needs_reloc {
+    if needs_reloc && !dynamic {
         let _ = apply_elf_relocations(
             data.as_ptr(),
             data.len(),
@@ -685,8 +698,15 @@ pub fn process_vm_load_elf_data(
             &section_mappings[..mapping_count],
         );
     }
+    if pie && !dynamic {
+        apply_pie_relocations(data, page_dir, code_base);
+    }
 
-    let user_entry = process_vm_translate_elf_address(header.e_entry, min_vaddr, code_base);
+    let user_entry = if pie {
+        validator.adjusted_entry_point()
+    } else {
+        process_vm_translate_elf_address(header.e_entry, min_vaddr, code_base)
+    };
 
     // Compute the user-space address of the program headers. The ELF spec says
     // the phdr table lives at file offset e_phoff, which usually falls inside
@@ -719,9 +739,221 @@ pub fn process_vm_load_elf_data(
         phdr_addr: phdr_user_addr,
         phent_size: header.e_phentsize,
         phnum: header.e_phnum,
+        interp_base: 0,
     })
 }
 
+/// Load the program interpreter of a dynamically linked image.
+///
+/// The interpreter must be a self-contained ET_DYN image; it is placed at
+/// `PROCESS_INTERP_BASE_VA` and its `R_X86_64_RELATIVE` relocations are
+/// applied here so it can run before relocating anything else.  Returns
+/// the load bias (`AT_BASE`) and the interpreter's entry point.
+pub fn process_vm_load_interpreter(process_id: u32, data: &[u8]) -> Result<(u64, u64), ElfError> {
+    use crate::memory_layout_defs::{PROCESS_INTERP_BASE_VA, PROCESS_INTERP_MAX_SIZE};
+
+    let validator = ElfValidator::new(data)?;
+    if !validator.header().is_pie() || validator.has_interpreter()? {
+        return Err(ElfError::InvalidInterpreter);
+    }
+
+    // Validate once unbiased to find the span, then again at the real base.
+    let (segments, segment_count) = validator.validate_load_segments()?;
+    let segments = &segments[..segment_count];
+    let low = segments.iter().map(|s| s.vaddr_start).min().unwrap_or(0);
+    let high = segments.iter().map(|s| s.vaddr_end).max().unwrap_or(0);
+    if high - low > PROCESS_INTERP_MAX_SIZE {
+        return Err(ElfError::TotalSizeExceeded);
+    }
+    let bias = PROCESS_INTERP_BASE_VA.wrapping_sub(low);
+
+    let validator = validator.with_load_base(bias);
+    let (segments, segment_count) = validator.validate_load_segments()?;
+    let segments = &segments[..segment_count];
+    let entry = validator.validate_entry_point(segments)?;
+
+    let process = find_process_vm(process_id);
+    if process.is_null() {
+        return Err(ElfError::NullPointer);
+    }
+    let page_dir = unsafe { (*process).page_dir };
+    if page_dir.is_null() {
+        return Err(ElfError::NullPointer);
+    }
+
+    clear_interp_region(process);
+
+    let mut mapped_pages: u32 = 0;
+    for segment in segments.iter() {
+        let user_start = segment.original_vaddr;
+        let user_end = user_start + segment.mem_size;
+        match load_segment_pages(page_dir, data, segment, user_start, user_end) {
+            Ok(pages) => mapped_pages = mapped_pages.saturating_add(pages),
+            Err(err) => {
+                clear_interp_region(process);
+                return Err(err);
+            }
+        }
+    }
+    apply_pie_relocations(data, page_dir, bias);
+
+    let span_end = PROCESS_INTERP_BASE_VA + (high - low);
+    if add_vma_to_process(
+        process,
+        PROCESS_INTERP_BASE_VA,
+        span_end,
+        VmaFlags::USER_CODE,
+    ) != 0
+    {
+        clear_interp_region(process);
+        return Err(ElfError::NullPointer);
+    }
+
+    unsafe {
+        (*process).total_pages = (*process).total_pages.saturating_add(mapped_pages);
+    }
+    klog_debug!(
+        "process_vm_load_interpreter: pid {} interpreter at {:#x}, entry {:#x}",
+        process_id,
+        bias,
+        entry
+    );
+    Ok((bias, entry))
+}
+
+/// Unmap whatever a previous image left in the interpreter window.
+fn clear_interp_region(process: *mut ProcessVm) {
+    use crate::memory_layout_defs::{PROCESS_INTERP_BASE_VA, PROCESS_INTERP_MAX_SIZE};
+
+    let start = PROCESS_INTERP_BASE_VA;
+    let end = start + PROCESS_INTERP_MAX_SIZE;
+    let freed = unmap_and_free_range(process, start, end);
+    unsafe {
+        (*process).total_pages = (*process).total_pages.saturating_sub(freed);
+        let tree = &mut (*process).vma_tree;
+        loop {
+            let node = tree.find_overlapping(start, end);
+            if node.is_null() || !tree.remove((*node).start, (*node).end) {
+                break;
+            }
+        }
+    }
+}
+
+/// Pick the load address for a PIE image: the process's randomized base,
+/// or the fixed code base when the image would not fit below the heap.
+fn pie_load_base(process_id: u32, validator: &ElfValidator) -> Result<u64, ElfError> {
+    let fixed = crate::memory_layout_defs::PROCESS_CODE_START_VA;
+    let (segments, segment_count) = validator.validate_load_segments()?;
+    let span = segments[..segment_count]
+        .iter()
+        .map(|s| s.vaddr_end)
+        .max()
+        .unwrap_or(0);
+
+    let process = find_process_vm(process_id);
+    if process.is_null() {
+        return Ok(fixed);
+    }
+    let (base, heap_start) = unsafe { ((*process).load_base, (*process).heap_start) };
+    if base == 0 || base.saturating_add(span) > heap_start {
+        return Ok(fixed);
+    }
+    Ok(base)
+}
+
+/// Apply `R_X86_64_RELATIVE` relocations from the `.rela.dyn`-style
+/// sections of a PIE image loaded at `load_base`.
+fn apply_pie_relocations(data: &[u8], page_dir: *mut ProcessPageDir, load_base: u64) {
+    use crate::memory_layout_defs::USER_SPACE_END_VA;
+
+    let read_u64 = |off: usize| -> Option<u64> {
+        Some(u64::from_le_bytes(
+            data.get(off..off.checked_add(8)?)?.try_into().ok()?,
+        ))
+    };
+    let read_u32 = |off: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            data.get(off..off.checked_add(4)?)?.try_into().ok()?,
+        ))
+    };
+    let read_u16 = |off: usize| -> Option<u16> {
+        Some(u16::from_le_bytes(
+            data.get(off..off.checked_add(2)?)?.try_into().ok()?,
+        ))
+    };
+
+    let (Some(sh_off), Some(sh_size), Some(sh_num)) =
+        (read_u64(0x28), read_u16(0x3A), read_u16(0x3C))
+    else {
+        return;
+    };
+    if sh_off == 0 || (sh_size as usize) < core::mem::size_of::<Elf64Shdr>() {
+        return;
+    }
+
+    for i in 0..sh_num as usize {
+        let shdr = (sh_off as usize).wrapping_add(i * sh_size as usize);
+        if read_u32(shdr.wrapping_add(4)) != Some(SHT_RELA) {
+            continue;
+        }
+        let (Some(rela_off), Some(rela_size), Some(entsize)) = (
+            read_u64(shdr.wrapping_add(24)),
+            read_u64(shdr.wrapping_add(32)),
+            read_u64(shdr.wrapping_add(56)),
+        ) else {
+            continue;
+        };
+        let entsize = if entsize == 0 {
+            core::mem::size_of::<Elf64Rela>() as u64
+        } else {
+            entsize
+        };
+
+        for j in 0..rela_size / entsize {
+            let rela = rela_off.wrapping_add(j * entsize) as usize;
+            let (Some(r_offset), Some(r_info), Some(r_addend)) = (
+                read_u64(rela),
+                read_u64(rela.wrapping_add(8)),
+                read_u64(rela.wrapping_add(16)),
+            ) else {
+                break;
+            };
+            if (r_info & 0xffffffff) as u32 != R_X86_64_RELATIVE {
+                continue;
+            }
+
+            // Never patch kernel addresses.  The slot may straddle a page
+            // boundary, so write it bytewise.
+            let target = load_base.wrapping_add(r_offset);
+            if target
+                .checked_add(8)
+                .map_or(true, |end| end > USER_SPACE_END_VA)
+            {
+                continue;
+            }
+            let value = load_base.wrapping_add(r_addend).to_le_bytes();
+            for (k, &byte) in value.iter().enumerate() {
+                let va = target.wrapping_add(k as u64);
+                let page_va = va & !(PAGE_SIZE_4KB - 1);
+                let page_off = (va & (PAGE_SIZE_4KB - 1)) as usize;
+
+                let phys = virt_to_phys_in_dir(page_dir, VirtAddr::new(page_va));
+                if phys.is_null() {
+                    break;
+                }
+                let virt = phys.to_virt();
+                if virt.is_null() {
+                    break;
+                }
+                unsafe {
+                    *virt.as_mut_ptr::<u8>().add(page_off) = byte;
+                }
+            }
+        }
+    }
+}
+
 fn calculate_load_offset(segments: &[ValidatedSegment], code_base: u64) -> (u64, bool) {
     let min_vaddr = segments.iter().map(|s| s.original_vaddr).min().unwrap_or(0);
 
@@ -759,11 +991,17 @@ fn load_segment_pages(
     user_start: u64,
     user_end: u64,
 ) -> Result<u32, ElfError> {
-    let map_flags = if (segment.flags & PF_W) != 0 {
-        PageFlags::USER_RW.bits()
+    let writable = (segment.flags & PF_W) != 0;
+    let executable = (segment.flags & PF_X) != 0;
+    let mut map_flags = if writable {
+        PageFlags::USER_RW
     } else {
-        PageFlags::USER_RO.bits()
+        PageFlags::USER_RO
     };
+    if !executable {
+        map_flags |= PageFlags::NO_EXECUTE;
+    }
+    let map_flags = map_flags.bits();
 
     let page_start = align_down(user_start as usize, PAGE_SIZE_4KB as usize) as u64;
     let page_end = align_up(user_end as usize, PAGE_SIZE_4KB as usize) as u64;
@@ -774,13 +1012,29 @@ fn load_segment_pages(
     while dst < page_end {
         let existing_phys = virt_to_phys_in_dir(page_dir, VirtAddr::new(dst));
         let phys = if !existing_phys.is_null() {
-            if (map_flags & PageFlags::WRITABLE.bits()) != 0 {
+            // A page shared with an earlier segment takes the union of both
+            // permissions, which must still respect W^X.
+            let existing =
+                paging_get_pte_flags(page_dir, VirtAddr::new(dst)).unwrap_or(PageFlags::empty());
+            let was_writable = existing.contains(PageFlags::WRITABLE);
+            let was_executable = !existing.contains(PageFlags::NO_EXECUTE);
+            if (writable || was_writable) && (executable || was_executable) {
+                return Err(ElfError::WritableExecutable);
+            }
+            if writable {
                 let _ = paging_mark_range_user(
                     page_dir,
                     VirtAddr::new(dst),
                     VirtAddr::new(dst + PAGE_SIZE_4KB),
                     1,
                 );
+            } else if executable && !was_executable {
+                let _ = paging_update_range_protection(
+                    page_dir,
+                    VirtAddr::new(dst),
+                    VirtAddr::new(dst + PAGE_SIZE_4KB),
+                    existing - PageFlags::NO_EXECUTE,
+                );
             }
             existing_phys
         } else {
@@ -922,6 +1176,8 @@ pub fn create_process_vm() -> u32 {
         proc.heap_end = layout.heap_start;
         proc.stack_start = layout.stack_top - layout.stack_size;
         proc.stack_end = layout.stack_top;
+        proc.load_base = layout.load_base;
+        proc.mmap_base = layout.mmap_base;
         proc.total_pages = 1;
         proc.flags = 0;
         proc.next = manager.process_list;
@@ -982,7 +1238,7 @@ pub fn create_process_vm() -> u32 {
             proc.page_dir,
             0,
             PAGE_SIZE_4KB,
-            PageFlags::USER_RW.bits(),
+            (PageFlags::USER_RW | PageFlags::NO_EXECUTE).bits(),
             &mut null_pages,
         ) == 0
         {
@@ -1208,6 +1464,78 @@ pub fn process_vm_get_vma_flags(process_id: u32, addr: u64) -> Option<VmaFlags>
     Some(unsafe { (*vma).flags })
 }
 
+/// Call `f` with the start, end and flags of each region of a process, in
+/// address order.
+pub fn process_vm_for_each_vma(process_id: u32, mut f: impl FnMut(u64, u64, VmaFlags)) {
+    let process_ptr = find_process_vm(process_id);
+    if process_ptr.is_null() {
+        return;
+    }
+
+    let tree = unsafe { &(*process_ptr).vma_tree };
+    let mut vma = tree.first();
+    while !vma.is_null() {
+        unsafe { f((*vma).start, (*vma).end, (*vma).flags) };
+        vma = tree.next(vma);
+    }
+}
+
+/// Outcome of a W^X audit over one process's address space.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WxAudit {
+    /// Present user-accessible leaf mappings inspected.
+    pub mappings: u32,
+    /// User mappings that are both writable and executable.
+    pub wx_mappings: u32,
+    /// Regions whose flags allow both write and execute.
+    pub wx_vmas: u32,
+    /// Address of the first offending mapping or region, or 0.
+    pub first_violation: u64,
+}
+
+impl WxAudit {
+    pub fn is_clean(&self) -> bool {
+        self.wx_mappings == 0 && self.wx_vmas == 0
+    }
+}
+
+/// Check that no user-accessible page of a process is both writable and
+/// executable, in its page tables or in its region flags.  `None` if the
+/// process does not exist.
+pub fn process_vm_audit_wx(process_id: u32) -> Option<WxAudit> {
+    let process_ptr = find_process_vm(process_id);
+    if process_ptr.is_null() {
+        return None;
+    }
+    let page_dir = unsafe { (*process_ptr).page_dir };
+    if page_dir.is_null() {
+        return None;
+    }
+
+    let mut audit = WxAudit::default();
+    paging_for_each_user_mapping(page_dir, |vaddr, _size, flags| {
+        if !flags.contains(PageFlags::USER) {
+            return;
+        }
+        audit.mappings += 1;
+        if flags.contains(PageFlags::WRITABLE) && !flags.contains(PageFlags::NO_EXECUTE) {
+            audit.wx_mappings += 1;
+            if audit.first_violation == 0 {
+                audit.first_violation = vaddr.as_u64();
+            }
+        }
+    });
+    process_vm_for_each_vma(process_id, |start, _end, flags| {
+        if flags.contains(VmaFlags::WRITE | VmaFlags::EXEC) {
+            audit.wx_vmas += 1;
+            if audit.first_violation == 0 {
+                audit.first_violation = start;
+            }
+        }
+    });
+    Some(audit)
+}
+
 pub fn process_vm_increment_pages(process_id: u32, count: u32) {
     let process_ptr = find_process_vm(process_id);
     if process_ptr.is_null() {
@@ -1219,6 +1547,15 @@ pub fn process_vm_increment_pages(process_id: u32, count: u32) {
     }
 }
 
+/// Pages mapped into a process, or 0 if it does not exist.
+pub fn process_vm_resident_pages(process_id: u32) -> u32 {
+    let process_ptr = find_process_vm(process_id);
+    if process_ptr.is_null() {
+        return 0;
+    }
+    unsafe { (*process_ptr).total_pages }
+}
+
 pub fn process_vm_get_stack_top(process_id: u32) -> u64 {
     let process_ptr = find_process_vm(process_id);
     if process_ptr.is_null() {
@@ -1227,6 +1564,14 @@ pub fn process_vm_get_stack_top(process_id: u32) -> u64 {
     unsafe { (*process_ptr).stack_end }
 }
 
+pub fn process_vm_get_mmap_base(process_id: u32) -> u64 {
+    let process_ptr = find_process_vm(process_id);
+    if process_ptr.is_null() {
+        return 0;
+    }
+    unsafe { (*process_ptr).mmap_base }
+}
+
 pub fn process_vm_brk(process_id: u32, new_brk: u64) -> u64 {
     let process_ptr = find_process_vm(process_id);
     if process_ptr.is_null() {
@@ -1304,13 +1649,21 @@ fn prot_to_vma_flags(prot: u64) -> VmaFlags {
     flags
 }
 
+/// W^X: a user mapping may be writable or executable, never both.
+fn prot_is_wx(prot: u64) -> bool {
+    use slopos_abi::syscall::{PROT_EXEC, PROT_WRITE};
+
+    prot & (PROT_WRITE | PROT_EXEC) == (PROT_WRITE | PROT_EXEC)
+}
+
 /// Find a free gap in the process address space within the mmap region.
 ///
-/// Walks the VMA tree starting at `PROCESS_MMAP_START_VA` looking for a gap
-/// of at least `size` bytes. Returns the start address of the gap, or 0 on
-/// failure.
+/// Walks the VMA tree from the process's (randomized) mmap base looking for
+/// a gap of at least `size` bytes, then retries from `PROCESS_MMAP_START_VA`
+/// if the space above the base is full. Returns the start address of the
+/// gap, or 0 on failure.
 fn find_mmap_gap(process: *const ProcessVm, size: u64) -> u64 {
-    use crate::memory_layout_defs::{PROCESS_MMAP_END_VA, PROCESS_MMAP_START_VA};
+    use crate::memory_layout_defs::PROCESS_MMAP_START_VA;
 
     if process.is_null() || size == 0 {
         return 0;
@@ -1318,9 +1671,27 @@ fn find_mmap_gap(process: *const ProcessVm, size: u64) -> u64 {
 
     unsafe {
         let tree = &(*process).vma_tree;
-        let mut candidate = PROCESS_MMAP_START_VA;
+        let base = (*process).mmap_base.max(PROCESS_MMAP_START_VA);
+        match find_gap_from(tree, base, size) {
+            0 if base > PROCESS_MMAP_START_VA => find_gap_from(tree, PROCESS_MMAP_START_VA, size),
+            addr => addr,
+        }
+    }
+}
 
-        let mut cursor = tree.find_first_at_or_after(PROCESS_MMAP_START_VA);
+fn find_gap_from(tree: &VmaTree, from: u64, size: u64) -> u64 {
+    use crate::memory_layout_defs::PROCESS_MMAP_END_VA;
+
+    let mut candidate = from;
+
+    unsafe {
+        // A mapping straddling `from` is not found by the walk below.
+        let straddling = tree.find_containing(from);
+        if !straddling.is_null() {
+            candidate = (*straddling).end;
+        }
+
+        let mut cursor = tree.find_first_at_or_after(from);
 
         while !cursor.is_null() {
             let vma_start = (*cursor).start;
@@ -1338,11 +1709,11 @@ fn find_mmap_gap(process: *const ProcessVm, size: u64) -> u64 {
 
             cursor = tree.next(cursor);
         }
+    }
 
-        // Check space after the last VMA
-        if candidate + size <= PROCESS_MMAP_END_VA {
-            return candidate;
-        }
+    // Check space after the last VMA
+    if candidate + size <= PROCESS_MMAP_END_VA {
+        return candidate;
     }
 
     0
@@ -1386,6 +1757,10 @@ pub fn process_vm_mmap(
     if length == 0 {
         return 0;
     }
+    if prot_is_wx(prot) {
+        klog_info!("process_vm_mmap: PROT_WRITE|PROT_EXEC rejected (W^X)");
+        return 0;
+    }
 
     let size = match length.checked_add(PAGE_SIZE_4KB - 1) {
         Some(v) => v & !(PAGE_SIZE_4KB - 1),
@@ -1474,6 +1849,97 @@ pub fn process_vm_mmap(
     start_addr
 }
 
+/// Map physical memory (a framebuffer or other device region) into the
+/// process address space at a kernel-chosen address.
+///
+/// `phys` need not be page-aligned; the returned address carries the same
+/// in-page offset.  `mem_type` is a PAT memory type (`pat::MEM_TYPE_*`).
+/// Pages owned by the page allocator get an extra reference so munmap and
+/// process teardown drop only the mapping's share.  The VMA is marked
+/// [`VmaFlags::DEVICE`], so it is never demand-paged or COW-shared.
+///
+/// Returns the virtual address on success, or 0 on failure.
+pub fn process_vm_map_phys(
+    process_id: u32,
+    phys: PhysAddr,
+    length: u64,
+    prot: u64,
+    mem_type: u8,
+) -> u64 {
+    if phys.is_null() || length == 0 || prot_is_wx(prot) {
+        return 0;
+    }
+    let Some(cache_flags) = crate::pat::page_flags_for(mem_type) else {
+        klog_info!(
+            "process_vm_map_phys: unsupported memory type {:#x}",
+            mem_type
+        );
+        return 0;
+    };
+
+    let page_offset = phys.as_u64() & (PAGE_SIZE_4KB - 1);
+    let phys_base = phys.as_u64() - page_offset;
+    let size = match length
+        .checked_add(page_offset)
+        .and_then(|len| len.checked_add(PAGE_SIZE_4KB - 1))
+    {
+        Some(v) => v & !(PAGE_SIZE_4KB - 1),
+        None => return 0,
+    };
+
+    let process_ptr = find_process_vm(process_id);
+    if process_ptr.is_null() {
+        return 0;
+    }
+    let page_dir = unsafe { (*process_ptr).page_dir };
+    if page_dir.is_null() {
+        return 0;
+    }
+
+    let start_addr = find_mmap_gap(process_ptr, size);
+    if start_addr == 0 {
+        klog_info!(
+            "process_vm_map_phys: No free region found for {} bytes",
+            size
+        );
+        return 0;
+    }
+    let end_addr = start_addr + size;
+
+    let vma_flags = (prot_to_vma_flags(prot).protection_only() | VmaFlags::USER) | VmaFlags::DEVICE;
+    if add_vma_to_process(process_ptr, start_addr, end_addr, vma_flags) != 0 {
+        klog_info!("process_vm_map_phys: Failed to insert VMA");
+        return 0;
+    }
+
+    let pte_flags = (vma_flags.to_page_flags() | cache_flags).bits();
+    let mut owned_pages = 0u32;
+    let mut offset = 0u64;
+    while offset < size {
+        let vaddr = VirtAddr::new(start_addr + offset);
+        let page_phys = PhysAddr::new(phys_base + offset);
+        if map_page_4kb_in_dir(page_dir, vaddr, page_phys, pte_flags) != 0 {
+            klog_info!(
+                "process_vm_map_phys: Failed to map page {:#x}",
+                vaddr.as_u64()
+            );
+            unmap_and_free_range(process_ptr, start_addr, start_addr + offset);
+            remove_vma_from_process(process_ptr, start_addr, end_addr);
+            return 0;
+        }
+        if page_frame_can_free(page_phys) != 0 {
+            page_frame_inc_ref(page_phys);
+            owned_pages += 1;
+        }
+        offset += PAGE_SIZE_4KB;
+    }
+    unsafe {
+        (*process_ptr).total_pages += owned_pages;
+    }
+
+    start_addr + page_offset
+}
+
 /// Unmap a previously mmap'd memory region.
 ///
 /// `addr`: start address (must be page-aligned).
@@ -1568,11 +2034,13 @@ pub fn process_vm_munmap(process_id: u32, addr: u64, length: u64) -> i32 {
 ///
 /// Returns 0 on success, -1 on failure.
 pub fn process_vm_mprotect(process_id: u32, addr: u64, length: u64, prot: u64) -> i32 {
-    use crate::paging::paging_update_range_protection;
-
     if length == 0 || (addr & (PAGE_SIZE_4KB - 1)) != 0 {
         return -1;
     }
+    if prot_is_wx(prot) {
+        klog_info!("process_vm_mprotect: PROT_WRITE|PROT_EXEC rejected (W^X)");
+        return -1;
+    }
 
     let size = match length.checked_add(PAGE_SIZE_4KB - 1) {
         Some(v) => v & !(PAGE_SIZE_4KB - 1),
@@ -1713,6 +2181,8 @@ pub fn process_vm_clone_cow(parent_id: u32) -> u32 {
     child.heap_end = parent.heap_end;
     child.stack_start = parent.stack_start;
     child.stack_end = parent.stack_end;
+    child.load_base = parent.load_base;
+    child.mmap_base = parent.mmap_base;
     child.total_pages = 0;
     child.flags = parent.flags;
     child.next = manager.process_list;
@@ -1729,6 +2199,10 @@ pub fn process_vm_clone_cow(parent_id: u32) -> u32 {
         let mut cursor = parent_tree.first();
         while !cursor.is_null() {
             let vma = &*cursor;
+            if vma.flags.contains(VmaFlags::DEVICE) {
+                cursor = parent_tree.next(cursor);
+                continue;
+            }
             let vma_start = vma.start;
             let vma_end = vma.end;
             let child_vma_flags = vma.flags | VmaFlags::COW;
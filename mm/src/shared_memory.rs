@@ -4,11 +4,13 @@
 //! Used for client-compositor buffer sharing in the graphics stack.
 
 use core::ffi::c_int;
-use core::sync::atomic::{AtomicU32, Ordering};
 
 use slopos_lib::IrqRwLock;
+use slopos_lib::handle_table::HandleTable;
+use slopos_lib::lockstat::LockClass;
 
 use slopos_abi::addr::{PhysAddr, VirtAddr};
+use slopos_abi::handle::{Handle, HandleKind};
 pub use slopos_abi::pixel::PixelFormat;
 
 use crate::page_alloc::{ALLOC_FLAG_ZERO, alloc_page_frames, free_page_frame};
@@ -109,10 +111,6 @@ struct SharedBuffer {
     pages: u32,
     /// Task ID of the owner (who created it)
     owner_task: u32,
-    /// Unique token for cross-process reference
-    token: u32,
-    /// Whether this slot is in use
-    active: bool,
     /// Mappings into various processes
     mappings: [ShmMapping; MAX_MAPPINGS_PER_BUFFER],
     /// Number of active mappings
@@ -131,29 +129,42 @@ struct SharedBuffer {
 }
 
 impl SharedBuffer {
-    const fn empty() -> Self {
+    const fn new(
+        phys_addr: PhysAddr,
+        size: usize,
+        pages: u32,
+        owner_task: u32,
+        format: PixelFormat,
+    ) -> Self {
         Self {
-            phys_addr: PhysAddr::NULL,
-            size: 0,
-            pages: 0,
-            owner_task: 0,
-            token: 0,
-            active: false,
+            phys_addr,
+            size,
+            pages,
+            owner_task,
             mappings: [ShmMapping::empty(); MAX_MAPPINGS_PER_BUFFER],
             mapping_count: 0,
             surface_width: 0,
             surface_height: 0,
-            ref_count: 0,
+            ref_count: 1, // Owner holds initial reference
             released: false,
-            format: PixelFormat::Argb8888, // Default format
+            format,
+        }
+    }
+
+    fn free_pages(&self) {
+        for i in 0..self.pages {
+            free_page_frame(self.phys_addr.offset((i as u64) * PAGE_SIZE_4KB));
         }
     }
 }
 
-/// Global registry of shared buffers
+/// Global registry of shared buffers.
+///
+/// Buffer tokens are [`HandleKind::Shm`] handles into `buffers`: they stay
+/// global so the compositor can name a client's buffer, and the handle
+/// generation makes a token dead for good once its buffer is destroyed.
 struct SharedBufferRegistry {
-    buffers: [SharedBuffer; MAX_SHARED_BUFFERS],
-    next_token: AtomicU32,
+    buffers: HandleTable<SharedBuffer, MAX_SHARED_BUFFERS>,
     /// Next virtual address offset for mappings (bump allocator fallback)
     next_vaddr_offset: VirtAddr,
     /// Free list for virtual address reclamation
@@ -163,34 +174,38 @@ struct SharedBufferRegistry {
 impl SharedBufferRegistry {
     const fn new() -> Self {
         Self {
-            buffers: [const { SharedBuffer::empty() }; MAX_SHARED_BUFFERS],
-            next_token: AtomicU32::new(1), // Token 0 is invalid
+            buffers: HandleTable::new(HandleKind::Shm),
             next_vaddr_offset: VirtAddr::NULL,
             free_list: [const { FreeListEntry::empty() }; MAX_VADDR_FREE_LIST],
         }
     }
 
-    /// Find a free slot in the buffer registry
-    fn find_free_slot(&mut self) -> Option<usize> {
-        for (i, buf) in self.buffers.iter().enumerate() {
-            if !buf.active {
-                return Some(i);
-            }
-        }
-        None
+    /// Look up a live buffer by token
+    fn get(&self, token: u32) -> Option<&SharedBuffer> {
+        self.buffers.get(Handle::from_raw(token))
+    }
+
+    fn get_mut(&mut self, token: u32) -> Option<&mut SharedBuffer> {
+        self.buffers.get_mut(Handle::from_raw(token))
     }
 
-    /// Find a buffer by token
-    fn find_by_token(&self, token: u32) -> Option<usize> {
-        if token == 0 {
-            return None;
+    /// Drop a buffer from the registry and free its pages
+    fn destroy(&mut self, handle: Handle) {
+        if let Some(buffer) = self.buffers.remove(handle) {
+            buffer.free_pages();
         }
-        for (i, buf) in self.buffers.iter().enumerate() {
-            if buf.active && buf.token == token {
-                return Some(i);
+    }
+
+    /// Register a freshly allocated buffer, freeing its pages if the
+    /// registry is full.
+    fn insert(&mut self, buffer: SharedBuffer) -> Option<u32> {
+        match self.buffers.insert(buffer) {
+            Ok(handle) => Some(handle.raw()),
+            Err(buffer) => {
+                buffer.free_pages();
+                None
             }
         }
-        None
     }
 
     /// Allocate a virtual address range for a mapping.
@@ -250,7 +265,9 @@ impl SharedBufferRegistry {
     }
 }
 
-static REGISTRY: IrqRwLock<SharedBufferRegistry> = IrqRwLock::new(SharedBufferRegistry::new());
+static REGISTRY_LOCK_CLASS: LockClass = LockClass::new("shm_registry");
+static REGISTRY: IrqRwLock<SharedBufferRegistry> =
+    IrqRwLock::with_class(&REGISTRY_LOCK_CLASS, SharedBufferRegistry::new());
 
 /// Create a new shared memory buffer.
 ///
@@ -276,37 +293,16 @@ pub fn shm_create(owner_process: u32, size: u64, flags: u32) -> u32 {
         return 0;
     }
 
-    let mut registry = REGISTRY.write();
-    let slot = match registry.find_free_slot() {
-        Some(s) => s,
-        None => {
-            // Free the allocated pages
-            for i in 0..pages {
-                free_page_frame(PhysAddr::new(
-                    phys_addr.as_u64() + (i as u64) * PAGE_SIZE_4KB,
-                ));
-            }
-            klog_info!("shm_create: no free slots");
-            return 0;
-        }
-    };
-
-    let token = registry.next_token.fetch_add(1, Ordering::Relaxed);
-
-    registry.buffers[slot] = SharedBuffer {
+    let buffer = SharedBuffer::new(
         phys_addr,
-        size: aligned_size,
+        aligned_size,
         pages,
-        owner_task: owner_process,
-        token,
-        active: true,
-        mappings: [ShmMapping::empty(); MAX_MAPPINGS_PER_BUFFER],
-        mapping_count: 0,
-        surface_width: 0,
-        surface_height: 0,
-        ref_count: 1, // Owner holds initial reference
-        released: false,
-        format: DEFAULT_PIXEL_FORMAT,
+        owner_process,
+        DEFAULT_PIXEL_FORMAT,
+    );
+    let Some(token) = REGISTRY.write().insert(buffer) else {
+        klog_info!("shm_create: no free slots");
+        return 0;
     };
 
     token
@@ -329,18 +325,13 @@ pub fn shm_map(process_id: u32, token: u32, access: ShmAccess) -> u64 {
     }
 
     let mut registry = REGISTRY.write();
-    let slot = match registry.find_by_token(token) {
-        Some(s) => s,
-        None => {
-            klog_info!("shm_map: invalid token {}", token);
-            return 0;
-        }
+    let Some(buffer) = registry.get(token) else {
+        klog_info!("shm_map: invalid token {}", token);
+        return 0;
     };
 
     // First pass: check if already mapped and gather info
     {
-        let buffer = &registry.buffers[slot];
-
         // Check if already mapped for this process
         for mapping in buffer.mappings.iter() {
             if mapping.active && mapping.task_id == process_id {
@@ -357,10 +348,10 @@ pub fn shm_map(process_id: u32, token: u32, access: ShmAccess) -> u64 {
     }
 
     // Extract needed info before second mutable borrow
-    let buffer_size = registry.buffers[slot].size;
-    let owner_task = registry.buffers[slot].owner_task;
-    let phys_base = registry.buffers[slot].phys_addr;
-    let pages = registry.buffers[slot].pages;
+    let buffer_size = buffer.size;
+    let owner_task = buffer.owner_task;
+    let phys_base = buffer.phys_addr;
+    let pages = buffer.pages;
 
     // Only owner can have RW access
     let actual_access = if process_id == owner_task {
@@ -373,9 +364,9 @@ pub fn shm_map(process_id: u32, token: u32, access: ShmAccess) -> u64 {
     let vaddr = registry.alloc_vaddr(buffer_size);
 
     let map_flags = if actual_access == ShmAccess::ReadWrite {
-        PageFlags::USER_RW.bits()
+        (PageFlags::USER_RW | PageFlags::NO_EXECUTE).bits()
     } else {
-        PageFlags::USER_RO.bits()
+        (PageFlags::USER_RO | PageFlags::NO_EXECUTE).bits()
     };
 
     for i in 0..pages {
@@ -394,7 +385,9 @@ pub fn shm_map(process_id: u32, token: u32, access: ShmAccess) -> u64 {
     }
 
     // Second pass: record the mapping
-    let buffer = &mut registry.buffers[slot];
+    let Some(buffer) = registry.get_mut(token) else {
+        return 0;
+    };
     let mapping_slot = match buffer.mappings.iter().position(|m| !m.active) {
         Some(s) => s,
         None => {
@@ -438,19 +431,15 @@ pub fn shm_unmap(process_id: u32, virt_addr: u64) -> c_int {
     let mut registry = REGISTRY.write();
 
     // First pass: find the buffer and mapping, capture size for free list
-    let mut found_info: Option<(usize, usize, usize, VirtAddr)> = None; // (buffer_idx, mapping_idx, size, vaddr)
-
-    for (buf_idx, buffer) in registry.buffers.iter().enumerate() {
-        if !buffer.active {
-            continue;
-        }
+    let mut found_info: Option<(Handle, usize, usize, VirtAddr)> = None; // (handle, mapping_idx, size, vaddr)
 
+    for (handle, buffer) in registry.buffers.iter() {
         for (map_idx, mapping) in buffer.mappings.iter().enumerate() {
             if mapping.active
                 && mapping.task_id == process_id
                 && mapping.virt_addr == virt_addr_typed
             {
-                found_info = Some((buf_idx, map_idx, buffer.size, mapping.virt_addr));
+                found_info = Some((handle, map_idx, buffer.size, mapping.virt_addr));
                 break;
             }
         }
@@ -459,38 +448,34 @@ pub fn shm_unmap(process_id: u32, virt_addr: u64) -> c_int {
         }
     }
 
-    let (buf_idx, map_idx, buffer_size, mapping_vaddr) = match found_info {
+    let (handle, map_idx, buffer_size, mapping_vaddr) = match found_info {
         Some(info) => info,
         None => return -1,
     };
+    let Some(buffer) = registry.buffers.get_mut(handle) else {
+        return -1;
+    };
 
     // Unmap all pages
-    let pages = registry.buffers[buf_idx].pages;
-    for i in 0..pages {
+    for i in 0..buffer.pages {
         let page_vaddr = mapping_vaddr.offset((i as u64) * PAGE_SIZE_4KB);
         unmap_page_in_dir(page_dir, page_vaddr);
     }
 
     // Clear the mapping
-    registry.buffers[buf_idx].mappings[map_idx] = ShmMapping::empty();
-    registry.buffers[buf_idx].mapping_count =
-        registry.buffers[buf_idx].mapping_count.saturating_sub(1);
+    buffer.mappings[map_idx] = ShmMapping::empty();
+    buffer.mapping_count = buffer.mapping_count.saturating_sub(1);
+    let orphaned = buffer.owner_task == 0 && buffer.mapping_count == 0;
 
     // Return virtual address to free list for reuse
     registry.free_vaddr(mapping_vaddr, buffer_size);
 
-    if registry.buffers[buf_idx].active
-        && registry.buffers[buf_idx].owner_task == 0
-        && registry.buffers[buf_idx].mapping_count == 0
-    {
-        let pages = registry.buffers[buf_idx].pages;
-        let phys_addr = registry.buffers[buf_idx].phys_addr;
-        let token = registry.buffers[buf_idx].token;
-        for i in 0..pages {
-            free_page_frame(phys_addr.offset((i as u64) * PAGE_SIZE_4KB));
-        }
-        registry.buffers[buf_idx] = SharedBuffer::empty();
-        klog_debug!("shm_unmap: finalized deferred destroy token={}", token);
+    if orphaned {
+        registry.destroy(handle);
+        klog_debug!(
+            "shm_unmap: finalized deferred destroy token={}",
+            handle.raw()
+        );
     }
 
     klog_debug!(
@@ -516,13 +501,12 @@ pub fn shm_unmap(process_id: u32, virt_addr: u64) -> c_int {
 pub fn shm_destroy(process_id: u32, token: u32) -> c_int {
     let mut registry = REGISTRY.write();
 
-    let slot = match registry.find_by_token(token) {
-        Some(s) => s,
-        None => return -1,
+    let Some(buffer) = registry.get(token) else {
+        return -1;
     };
 
     // Only owner can destroy
-    if registry.buffers[slot].owner_task != process_id {
+    if buffer.owner_task != process_id {
         klog_info!(
             "shm_destroy: process {} is not owner of token {}",
             process_id,
@@ -531,15 +515,14 @@ pub fn shm_destroy(process_id: u32, token: u32) -> c_int {
         return -1;
     }
 
-    let buffer_size = registry.buffers[slot].size;
-    let pages = registry.buffers[slot].pages;
-    let phys_addr = registry.buffers[slot].phys_addr;
+    let buffer_size = buffer.size;
+    let pages = buffer.pages;
 
     let mut vaddrs_to_free: [(VirtAddr, u32); MAX_MAPPINGS_PER_BUFFER] =
         [(VirtAddr::NULL, 0); MAX_MAPPINGS_PER_BUFFER];
     let mut vaddr_count = 0;
 
-    for mapping in registry.buffers[slot].mappings.iter() {
+    for mapping in buffer.mappings.iter() {
         if mapping.active {
             vaddrs_to_free[vaddr_count] = (mapping.virt_addr, mapping.task_id);
             vaddr_count += 1;
@@ -557,15 +540,7 @@ pub fn shm_destroy(process_id: u32, token: u32) -> c_int {
         }
     }
 
-    for mapping in registry.buffers[slot].mappings.iter_mut() {
-        *mapping = ShmMapping::empty();
-    }
-
-    for i in 0..pages {
-        free_page_frame(phys_addr.offset((i as u64) * PAGE_SIZE_4KB));
-    }
-
-    registry.buffers[slot] = SharedBuffer::empty();
+    registry.destroy(Handle::from_raw(token));
 
     for i in 0..vaddr_count {
         let (vaddr, _) = vaddrs_to_free[i];
@@ -587,11 +562,8 @@ pub fn shm_destroy(process_id: u32, token: u32) -> c_int {
 /// (phys_addr, size, owner_task) or (NULL, 0, 0) if not found
 pub fn shm_get_buffer_info(token: u32) -> (PhysAddr, usize, u32) {
     let registry = REGISTRY.read();
-    match registry.find_by_token(token) {
-        Some(slot) => {
-            let buf = &registry.buffers[slot];
-            (buf.phys_addr, buf.size, buf.owner_task)
-        }
+    match registry.get(token) {
+        Some(buf) => (buf.phys_addr, buf.size, buf.owner_task),
         None => (PhysAddr::NULL, 0, 0),
     }
 }
@@ -609,13 +581,10 @@ pub fn shm_get_buffer_info(token: u32) -> (PhysAddr, usize, u32) {
 pub fn surface_attach(process_id: u32, token: u32, width: u32, height: u32) -> c_int {
     let mut registry = REGISTRY.write();
 
-    let slot = match registry.find_by_token(token) {
-        Some(s) => s,
-        None => return -1,
+    let Some(buffer) = registry.get_mut(token) else {
+        return -1;
     };
 
-    let buffer = &mut registry.buffers[slot];
-
     // Only owner can attach (owner_task stores process_id)
     if buffer.owner_task != process_id {
         return -1;
@@ -645,14 +614,10 @@ pub fn surface_attach(process_id: u32, token: u32, width: u32, height: u32) -> c
 pub fn get_surface_for_task(task_id: u32) -> (u32, u32, u32, PhysAddr) {
     let registry = REGISTRY.read();
 
-    for buffer in registry.buffers.iter() {
-        if buffer.active
-            && buffer.owner_task == task_id
-            && buffer.surface_width > 0
-            && buffer.surface_height > 0
-        {
+    for (handle, buffer) in registry.buffers.iter() {
+        if buffer.owner_task == task_id && buffer.surface_width > 0 && buffer.surface_height > 0 {
             return (
-                buffer.token,
+                handle.raw(),
                 buffer.surface_width,
                 buffer.surface_height,
                 buffer.phys_addr,
@@ -667,19 +632,15 @@ pub fn get_surface_for_task(task_id: u32) -> (u32, u32, u32, PhysAddr) {
 /// Used by FB_FLIP syscall.
 pub fn shm_get_phys_addr(token: u32) -> PhysAddr {
     let registry = REGISTRY.read();
-    match registry.find_by_token(token) {
-        Some(slot) => registry.buffers[slot].phys_addr,
-        None => PhysAddr::NULL,
-    }
+    registry
+        .get(token)
+        .map_or(PhysAddr::NULL, |buf| buf.phys_addr)
 }
 
 /// Get the size of a shared buffer by token.
 pub fn shm_get_size(token: u32) -> usize {
     let registry = REGISTRY.read();
-    match registry.find_by_token(token) {
-        Some(slot) => registry.buffers[slot].size,
-        None => 0,
-    }
+    registry.get(token).map_or(0, |buf| buf.size)
 }
 
 /// Clean up all shared buffers owned by a task.
@@ -691,11 +652,7 @@ pub fn shm_cleanup_task(task_id: u32) {
         [(VirtAddr::NULL, 0); MAX_SHARED_BUFFERS];
     let mut mapping_vaddr_count = 0;
 
-    for buffer in registry.buffers.iter_mut() {
-        if !buffer.active {
-            continue;
-        }
-
+    for (_, buffer) in registry.buffers.iter_mut() {
         for mapping in buffer.mappings.iter_mut() {
             if mapping.active && mapping.task_id == task_id {
                 if mapping_vaddr_count < MAX_SHARED_BUFFERS {
@@ -708,35 +665,31 @@ pub fn shm_cleanup_task(task_id: u32) {
         }
     }
 
-    let mut owned_buffer_slots: [usize; MAX_SHARED_BUFFERS] = [0; MAX_SHARED_BUFFERS];
+    let mut owned_buffers: [Handle; MAX_SHARED_BUFFERS] = [Handle::NULL; MAX_SHARED_BUFFERS];
     let mut owned_count = 0;
 
-    for (idx, buffer) in registry.buffers.iter().enumerate() {
-        if buffer.active && buffer.owner_task == task_id {
-            owned_buffer_slots[owned_count] = idx;
+    for (handle, buffer) in registry.buffers.iter() {
+        if buffer.owner_task == task_id {
+            owned_buffers[owned_count] = handle;
             owned_count += 1;
         }
     }
 
-    for i in 0..owned_count {
-        let slot = owned_buffer_slots[i];
-        let buffer = &mut registry.buffers[slot];
-        let phys_addr = buffer.phys_addr;
-        let pages = buffer.pages;
+    for &handle in &owned_buffers[..owned_count] {
+        let Some(buffer) = registry.buffers.get_mut(handle) else {
+            continue;
+        };
 
         if buffer.mapping_count == 0 {
-            for j in 0..pages {
-                free_page_frame(phys_addr.offset((j as u64) * PAGE_SIZE_4KB));
-            }
-            klog_debug!("shm_cleanup_task: destroyed buffer token={}", buffer.token);
-            *buffer = SharedBuffer::empty();
+            registry.destroy(handle);
+            klog_debug!("shm_cleanup_task: destroyed buffer token={}", handle.raw());
         } else {
             buffer.owner_task = 0;
             buffer.surface_width = 0;
             buffer.surface_height = 0;
             klog_debug!(
                 "shm_cleanup_task: deferred destroy token={} (mappings={})",
-                buffer.token,
+                handle.raw(),
                 buffer.mapping_count
             );
         }
@@ -765,12 +718,9 @@ pub fn shm_cleanup_task(task_id: u32) {
 pub fn shm_acquire(token: u32) -> c_int {
     let mut registry = REGISTRY.write();
 
-    let slot = match registry.find_by_token(token) {
-        Some(s) => s,
-        None => return -1,
+    let Some(buffer) = registry.get_mut(token) else {
+        return -1;
     };
-
-    let buffer = &mut registry.buffers[slot];
     buffer.ref_count = buffer.ref_count.saturating_add(1);
     buffer.released = false;
 
@@ -797,12 +747,9 @@ pub fn shm_acquire(token: u32) -> c_int {
 pub fn shm_release(token: u32) -> c_int {
     let mut registry = REGISTRY.write();
 
-    let slot = match registry.find_by_token(token) {
-        Some(s) => s,
-        None => return -1,
+    let Some(buffer) = registry.get_mut(token) else {
+        return -1;
     };
-
-    let buffer = &mut registry.buffers[slot];
     buffer.ref_count = buffer.ref_count.saturating_sub(1);
     buffer.released = true;
 
@@ -828,12 +775,9 @@ pub fn shm_release(token: u32) -> c_int {
 pub fn shm_poll_released(token: u32) -> c_int {
     let mut registry = REGISTRY.write();
 
-    let slot = match registry.find_by_token(token) {
-        Some(s) => s,
-        None => return -1,
+    let Some(buffer) = registry.get_mut(token) else {
+        return -1;
     };
-
-    let buffer = &mut registry.buffers[slot];
     if buffer.released {
         buffer.released = false; // Clear after polling
         1
@@ -852,10 +796,7 @@ pub fn shm_poll_released(token: u32) -> c_int {
 pub fn shm_get_ref_count(token: u32) -> u32 {
     let registry = REGISTRY.read();
 
-    match registry.find_by_token(token) {
-        Some(slot) => registry.buffers[slot].ref_count,
-        None => 0,
-    }
+    registry.get(token).map_or(0, |buf| buf.ref_count)
 }
 
 // =============================================================================
@@ -904,34 +845,10 @@ pub fn shm_create_with_format(owner_task: u32, size: u64, format: PixelFormat) -
         return 0;
     }
 
-    let mut registry = REGISTRY.write();
-    let slot = match registry.find_free_slot() {
-        Some(s) => s,
-        None => {
-            for i in 0..pages {
-                free_page_frame(phys_addr.offset((i as u64) * PAGE_SIZE_4KB));
-            }
-            klog_info!("shm_create_with_format: no free slots");
-            return 0;
-        }
-    };
-
-    let token = registry.next_token.fetch_add(1, Ordering::Relaxed);
-
-    registry.buffers[slot] = SharedBuffer {
-        phys_addr,
-        size: aligned_size,
-        pages,
-        owner_task,
-        token,
-        active: true,
-        mappings: [ShmMapping::empty(); MAX_MAPPINGS_PER_BUFFER],
-        mapping_count: 0,
-        surface_width: 0,
-        surface_height: 0,
-        ref_count: 1,
-        released: false,
-        format,
+    let buffer = SharedBuffer::new(phys_addr, aligned_size, pages, owner_task, format);
+    let Some(token) = REGISTRY.write().insert(buffer) else {
+        klog_info!("shm_create_with_format: no free slots");
+        return 0;
     };
 
     klog_debug!(
@@ -955,8 +872,7 @@ pub fn shm_create_with_format(owner_task: u32, size: u64, format: PixelFormat) -
 pub fn shm_get_format(token: u32) -> u32 {
     let registry = REGISTRY.read();
 
-    match registry.find_by_token(token) {
-        Some(slot) => registry.buffers[slot].format as u32,
-        None => u32::MAX,
-    }
+    registry
+        .get(token)
+        .map_or(u32::MAX, |buf| buf.format as u32)
 }
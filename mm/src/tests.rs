@@ -9,13 +9,16 @@ use alloc::vec::Vec;
 use slopos_abi::addr::{PhysAddr, VirtAddr};
 use slopos_lib::cpu::msr::Msr;
 use slopos_lib::testing::TestResult;
-use slopos_lib::{assert_not_null, assert_test, cpu, fail, klog_info, pass};
+use slopos_lib::{assert_eq_test, assert_not_null, assert_test, cpu, fail, klog_info, pass};
 
 use crate::hhdm::PhysAddrHhdm;
-use crate::kernel_heap::{get_heap_stats, kfree, kmalloc, kzalloc};
+use crate::kernel_heap::{
+    HeapStats, get_heap_stats, kernel_heap_coalesce, kernel_heap_fragmentation, kernel_heap_trim,
+    kfree, kmalloc, kzalloc,
+};
 use crate::page_alloc::{
     ALLOC_FLAG_ZERO, alloc_page_frame, alloc_page_frames, free_page_frame,
-    get_page_allocator_stats, page_frame_get_ref, page_frame_inc_ref,
+    get_page_allocator_stats, page_frame_get_ref, page_frame_inc_ref, pcp_drain_all,
 };
 use crate::paging::{
     paging_get_kernel_directory, paging_is_cow, paging_is_user_accessible, virt_to_phys,
@@ -275,6 +278,45 @@ pub fn test_heap_large_alloc() -> TestResult {
     pass!()
 }
 
+pub fn test_heap_trim_returns_pages() -> TestResult {
+    kernel_heap_trim();
+    let Some(pages) = fresh_block_pages() else {
+        klog_info!("HEAP_TEST: free blocks too large to force a fresh one, skipping");
+        return pass!();
+    };
+    let p = kmalloc(block_size(pages));
+    assert_not_null!(p, "alloc fresh block");
+    let size = heap_total_size();
+    // Freed frames land in the per-CPU caches first; drain them so the
+    // buddy counts show them.
+    let (mut free_before, mut free_after) = (0u32, 0u32);
+    pcp_drain_all();
+    get_page_allocator_stats(ptr::null_mut(), &mut free_before, ptr::null_mut());
+
+    kfree(p);
+    let trimmed = kernel_heap_trim();
+    pcp_drain_all();
+    get_page_allocator_stats(ptr::null_mut(), &mut free_after, ptr::null_mut());
+    assert_test!(
+        trimmed.pages_trimmed as u64 >= pages,
+        "trimmed {} of {} pages",
+        trimmed.pages_trimmed,
+        pages
+    );
+    assert_test!(heap_total_size() <= size - pages * PAGE_SIZE_4KB);
+    assert_test!(
+        free_after as u64 >= free_before as u64 + pages,
+        "frames not returned to the page allocator"
+    );
+    assert_test!(kernel_heap_fragmentation().reclaimed.pages_trimmed as u64 >= pages);
+
+    // The heap grows back on demand.
+    let q = kmalloc(block_size(pages));
+    assert_not_null!(q, "alloc after trim");
+    kfree(q);
+    pass!()
+}
+
 /// Test 4: kzalloc returns zeroed memory
 pub fn test_heap_kzalloc_zeroed() -> TestResult {
     let ptr = kzalloc(128);
@@ -453,9 +495,18 @@ pub fn test_heap_fragmentation_behind_head() -> TestResult {
         }
     }
 
+    let before = kernel_heap_fragmentation();
     kfree(ptrs[0]);
     kfree(ptrs[2]);
     kfree(ptrs[3]);
+    let after = kernel_heap_fragmentation();
+    let freed_objects =
+        |f: &crate::kernel_heap::HeapFragmentation| -> u32 { f.free_slab_objects.iter().sum() };
+    if freed_objects(&after) < freed_objects(&before) + 3 {
+        kfree(ptrs[1]);
+        kfree(ptrs[4]);
+        return fail!("freed slab objects not reported");
+    }
 
     let needed = kmalloc(400);
     if needed.is_null() {
@@ -470,6 +521,96 @@ pub fn test_heap_fragmentation_behind_head() -> TestResult {
     pass!()
 }
 
+fn heap_total_size() -> u64 {
+    let mut stats = HeapStats::default();
+    get_heap_stats(&mut stats);
+    stats.total_size
+}
+
+/// Pages per block for tests that need blocks carved fresh from the heap
+/// break: one more than the largest free block, so none can be reused.
+/// `None` if that is beyond what kmalloc will hand out.
+fn fresh_block_pages() -> Option<u64> {
+    let pages = kernel_heap_fragmentation().largest_free_block / PAGE_SIZE_4KB + 1;
+    (pages < 256).then_some(pages)
+}
+
+/// kmalloc size that takes exactly `pages` pages with the block header.
+fn block_size(pages: u64) -> usize {
+    (pages * PAGE_SIZE_4KB) as usize - 64
+}
+
+pub fn test_heap_coalesce_merges_neighbours() -> TestResult {
+    kernel_heap_trim();
+    let Some(pages) = fresh_block_pages() else {
+        klog_info!("HEAP_TEST: free blocks too large to force fresh ones, skipping");
+        return pass!();
+    };
+    let mut blocks = [ptr::null_mut(); 3];
+    for i in 0..blocks.len() {
+        blocks[i] = kmalloc(block_size(pages));
+        if blocks[i].is_null() {
+            blocks[..i].iter().for_each(|&b| kfree(b));
+            return fail!("alloc block {} of {} pages", i, pages);
+        }
+    }
+
+    kfree(blocks[0]);
+    kfree(blocks[2]);
+    let split = kernel_heap_fragmentation();
+    kfree(blocks[1]);
+    let freed = kernel_heap_fragmentation();
+    let pass_stats = kernel_heap_coalesce();
+    let merged = kernel_heap_fragmentation();
+    kernel_heap_trim();
+
+    // The two outer blocks are the largest free blocks, so neither holds
+    // more than half the free space.
+    assert_test!(
+        split.fragmentation_permille >= 500,
+        "fragmentation {} with two split blocks",
+        split.fragmentation_permille
+    );
+    assert_test!(freed.adjacent_free_blocks >= 2, "neighbours not reported");
+    assert_test!(pass_stats.blocks_merged >= 2, "neighbours not merged");
+    assert_test!(merged.largest_free_block >= 3 * pages * PAGE_SIZE_4KB);
+    assert_test!(
+        merged.fragmentation_permille < freed.fragmentation_permille,
+        "fragmentation {} -> {} after coalescing",
+        freed.fragmentation_permille,
+        merged.fragmentation_permille
+    );
+    assert_test!(merged.reclaim_passes > freed.reclaim_passes);
+    pass!()
+}
+
+pub fn test_heap_release_empty_slabs() -> TestResult {
+    // A 2 KiB slab holds a single object, so freeing it empties the slab.
+    let p = kmalloc(2048);
+    assert_not_null!(p, "alloc 2048 bytes");
+    kfree(p);
+
+    let before = kernel_heap_fragmentation();
+    assert_test!(before.empty_slabs >= 1, "freed slab not reported empty");
+    let pass_stats = kernel_heap_coalesce();
+    let after = kernel_heap_fragmentation();
+    assert_eq_test!(pass_stats.slabs_released, before.empty_slabs);
+    assert_eq_test!(after.empty_slabs, 0, "empty slab left behind");
+    assert_test!(
+        after.free_block_bytes >= before.free_block_bytes + PAGE_SIZE_4KB,
+        "released slab page not on the free-block list"
+    );
+
+    // The next slab reuses a released page instead of growing the heap.
+    let size = heap_total_size();
+    let q = kmalloc(2048);
+    assert_not_null!(q, "alloc 2048 bytes again");
+    let grown = heap_total_size() != size;
+    kfree(q);
+    assert_test!(!grown, "heap grew with free pages available");
+    pass!()
+}
+
 // ============================================================================
 // PROCESS VM TESTS (existing)
 // ============================================================================
@@ -1589,6 +1730,110 @@ pub fn test_vma_flags_retrieval() -> TestResult {
     pass!()
 }
 
+pub fn test_map_phys_write_combining() -> TestResult {
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+
+    use crate::paging::paging_get_pte_flags;
+    use crate::pat::MEM_TYPE_WC;
+    use crate::process_vm::{process_vm_map_phys, process_vm_munmap};
+    use slopos_abi::syscall::{PROT_READ, PROT_WRITE};
+
+    let phys = alloc_page_frame(ALLOC_FLAG_ZERO);
+    assert_test!(!phys.is_null(), "alloc backing page");
+    let refs_before = page_frame_get_ref(phys);
+
+    let vaddr = process_vm_map_phys(
+        vm.pid,
+        phys,
+        PAGE_SIZE_4KB,
+        PROT_READ | PROT_WRITE,
+        MEM_TYPE_WC,
+    );
+    if vaddr == 0 {
+        free_page_frame(phys);
+        return fail!("process_vm_map_phys returned 0");
+    }
+
+    let flags =
+        paging_get_pte_flags(vm.page_dir, VirtAddr::new(vaddr)).unwrap_or(PageFlags::empty());
+    let mapped_to = virt_to_phys_in_dir(vm.page_dir, VirtAddr::new(vaddr));
+    let refs_mapped = page_frame_get_ref(phys);
+    let unmap_rc = process_vm_munmap(vm.pid, vaddr, PAGE_SIZE_4KB);
+    let refs_after = page_frame_get_ref(phys);
+    free_page_frame(phys);
+
+    assert_test!(
+        mapped_to == phys,
+        "mapping points at {:#x}",
+        mapped_to.as_u64()
+    );
+    assert_test!(
+        flags.contains(PageFlags::USER | PageFlags::WRITABLE | PageFlags::WRITE_THROUGH),
+        "PTE flags {:#x} missing USER|WRITABLE|PWT",
+        flags.bits()
+    );
+    assert_test!(
+        !flags.contains(PageFlags::CACHE_DISABLE),
+        "PCD set: page would be UC, not WC"
+    );
+    assert_test!(
+        refs_mapped == refs_before + 1,
+        "mapping took no page reference"
+    );
+    assert_test!(unmap_rc == 0, "munmap failed");
+    assert_test!(refs_after == refs_before, "munmap freed the backing page");
+    pass!()
+}
+
+pub fn test_map_phys_not_inherited_by_fork() -> TestResult {
+    let Some(parent) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+
+    use crate::paging::paging_get_pte_flags;
+    use crate::pat::MEM_TYPE_WC;
+    use crate::process_vm::{process_vm_get_vma_flags, process_vm_map_phys};
+    use crate::vma_flags::VmaFlags;
+    use slopos_abi::syscall::{PROT_READ, PROT_WRITE};
+
+    let phys = alloc_page_frame(ALLOC_FLAG_ZERO);
+    assert_test!(!phys.is_null(), "alloc backing page");
+
+    let vaddr = process_vm_map_phys(
+        parent.pid,
+        phys,
+        PAGE_SIZE_4KB,
+        PROT_READ | PROT_WRITE,
+        MEM_TYPE_WC,
+    );
+    let device =
+        process_vm_get_vma_flags(parent.pid, vaddr).is_some_and(|f| f.contains(VmaFlags::DEVICE));
+    let child_phys = parent
+        .clone_cow()
+        .map(|child| virt_to_phys_in_dir(child.page_dir, VirtAddr::new(vaddr)));
+    let parent_flags =
+        paging_get_pte_flags(parent.page_dir, VirtAddr::new(vaddr)).unwrap_or(PageFlags::empty());
+    drop(parent);
+    free_page_frame(phys);
+
+    assert_test!(vaddr != 0, "process_vm_map_phys returned 0");
+    assert_test!(device, "mapping VMA not marked DEVICE");
+    let Some(child_phys) = child_phys else {
+        return fail!("fork failed");
+    };
+    assert_test!(
+        child_phys.is_null(),
+        "device mapping leaked into fork child"
+    );
+    assert_test!(
+        parent_flags.contains(PageFlags::WRITABLE) && !parent_flags.contains(PageFlags::COW),
+        "fork made the parent's device mapping COW"
+    );
+    pass!()
+}
+
 // ============================================================================
 // PAT (PAGE ATTRIBUTE TABLE) TESTS
 // ============================================================================
@@ -1612,6 +1857,16 @@ pub fn test_pat_wc_enabled() -> TestResult {
     pass!()
 }
 
+/// The full PAT layout, not just the WC entry, must be loaded on this CPU.
+pub fn test_pat_layout_programmed() -> TestResult {
+    assert_test!(
+        crate::pat::pat_matches_layout(),
+        "PAT MSR {:#018x} differs from the SlopOS layout",
+        cpu::read_msr(Msr::PAT)
+    );
+    pass!()
+}
+
 // ============================================================================
 // SUITE REGISTRATION — tests are auto-collected via linker section
 // ============================================================================
@@ -1628,6 +1883,8 @@ define_test_suite!(
     [
         test_heap_free_list_search,
         test_heap_fragmentation_behind_head,
+        test_heap_coalesce_merges_neighbours,
+        test_heap_release_empty_slabs,
     ]
 );
 
@@ -1652,6 +1909,7 @@ define_test_suite!(
         test_heap_small_alloc,
         test_heap_medium_alloc,
         test_heap_large_alloc,
+        test_heap_trim_returns_pages,
         test_heap_kzalloc_zeroed,
         test_heap_kfree_null,
         test_heap_alloc_zero,
@@ -1668,6 +1926,7 @@ define_test_suite!(
         test_paging_user_accessible_kernel,
         test_paging_cow_kernel,
         test_pat_wc_enabled,
+        test_pat_layout_programmed,
     ]
 );
 
@@ -1735,5 +1994,7 @@ define_test_suite!(
         test_cow_fault_handling,
         test_multiple_process_vms,
         test_vma_flags_retrieval,
+        test_map_phys_write_combining,
+        test_map_phys_not_inherited_by_fork,
     ]
 );
@@ -2,6 +2,7 @@ use core::ptr;
 use core::sync::atomic::Ordering;
 
 use slopos_abi::addr::VirtAddr;
+use slopos_lib::cpu::smap::UserAccess;
 use slopos_lib::pcr;
 use slopos_lib::{InitFlag, IrqMutex};
 
@@ -128,10 +129,11 @@ fn validate_user_pages(
 pub fn copy_from_user<T: Copy>(src: UserPtr<T>) -> Result<T, UserPtrError> {
     let dir = current_process_dir();
     validate_user_pages(src.addr(), core::mem::size_of::<T>(), dir)?;
+    let mut val = core::mem::MaybeUninit::<T>::uninit();
+    let _access = UserAccess::begin();
     // SAFETY: Byte-wise copy avoids alignment requirements on the user pointer.
     // This mirrors Linux's copy_from_user which treats userspace as unaligned.
     unsafe {
-        let mut val = core::mem::MaybeUninit::<T>::uninit();
         ptr::copy_nonoverlapping(
             src.as_ptr() as *const u8,
             val.as_mut_ptr() as *mut u8,
@@ -144,6 +146,7 @@ pub fn copy_from_user<T: Copy>(src: UserPtr<T>) -> Result<T, UserPtrError> {
 pub fn copy_to_user<T: Copy>(dst: UserPtr<T>, value: &T) -> Result<(), UserPtrError> {
     let dir = current_process_dir();
     validate_user_pages(dst.addr(), core::mem::size_of::<T>(), dir)?;
+    let _access = UserAccess::begin();
     // SAFETY: Byte-wise copy avoids alignment requirements on the user pointer.
     // This mirrors Linux's copy_to_user which treats userspace as unaligned.
     unsafe {
@@ -165,6 +168,7 @@ pub fn copy_bytes_from_user(src: UserBytes, dst: &mut [u8]) -> Result<usize, Use
     let dir = current_process_dir();
     validate_user_pages(src.base(), copy_len, dir)?;
 
+    let _access = UserAccess::begin();
     unsafe {
         ptr::copy_nonoverlapping(src.base().as_ptr(), dst.as_mut_ptr(), copy_len);
     }
@@ -180,6 +184,7 @@ pub fn copy_bytes_to_user(dst: UserBytes, src: &[u8]) -> Result<usize, UserPtrEr
     let dir = current_process_dir();
     validate_user_pages(dst.base(), copy_len, dir)?;
 
+    let _access = UserAccess::begin();
     unsafe {
         ptr::copy_nonoverlapping(src.as_ptr(), dst.base().as_mut_ptr(), copy_len);
     }
@@ -8,7 +8,7 @@
 //!
 //! The flags are split into two categories:
 //! - **Protection flags** (bits 0-3): Mirror x86_64 PTE flags for easy conversion
-//! - **VMA state flags** (bits 16-23): Track memory region state (CoW, lazy, etc.)
+//! - **VMA state flags** (bits 16-24): Track memory region state (CoW, lazy, etc.)
 //!
 //! # Example
 //!
@@ -69,7 +69,7 @@ impl VmaFlags {
     pub const USER: Self = Self(1 << 3);
 
     // =========================================================================
-    // VMA state flags (bits 16-23)
+    // VMA state flags (bits 16-24)
     // =========================================================================
 
     /// Copy-on-Write: page is shared read-only, copy on write fault
@@ -88,6 +88,9 @@ impl VmaFlags {
     pub const STACK: Self = Self(1 << 22);
     /// Heap region: grows upward via brk()
     pub const HEAP: Self = Self(1 << 23);
+    /// Device/physical mapping: pages belong to a driver, never COW'd,
+    /// not inherited across fork()
+    pub const DEVICE: Self = Self(1 << 24);
 
     // =========================================================================
     // Convenience combinations
@@ -0,0 +1,89 @@
+//! W^X tests: user mappings are never writable and executable at once, and
+//! the audit notices when one is.
+
+use slopos_abi::syscall::{MAP_ANONYMOUS, MAP_PRIVATE, PROT_EXEC, PROT_READ, PROT_WRITE};
+use slopos_lib::testing::TestResult;
+use slopos_lib::{assert_eq_test, assert_test, fail, pass};
+
+use crate::paging_defs::{PAGE_SIZE_4KB, PageFlags};
+use crate::process_vm::{process_vm_audit_wx, process_vm_mmap, process_vm_mprotect};
+use crate::test_fixtures::{ProcessVmGuard, map_test_page};
+
+fn mmap_anon(pid: u32, prot: u64) -> u64 {
+    process_vm_mmap(
+        pid,
+        0,
+        PAGE_SIZE_4KB,
+        prot,
+        MAP_ANONYMOUS | MAP_PRIVATE,
+        -1,
+        0,
+    )
+}
+
+pub fn test_wx_fresh_vm_is_clean() -> TestResult {
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    let Some(audit) = process_vm_audit_wx(vm.pid) else {
+        return fail!("audit found no process");
+    };
+    assert_test!(audit.mappings > 0, "no user mappings walked");
+    assert_test!(
+        audit.is_clean(),
+        "W+X at {:#x} in a fresh VM",
+        audit.first_violation
+    );
+    pass!()
+}
+
+pub fn test_wx_mmap_and_mprotect_refuse_wx() -> TestResult {
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    assert_eq_test!(
+        mmap_anon(vm.pid, PROT_READ | PROT_WRITE | PROT_EXEC),
+        0,
+        "W+X mmap allowed"
+    );
+
+    let addr = mmap_anon(vm.pid, PROT_READ | PROT_WRITE);
+    assert_test!(addr != 0, "RW mmap failed");
+    assert_eq_test!(
+        process_vm_mprotect(vm.pid, addr, PAGE_SIZE_4KB, PROT_WRITE | PROT_EXEC),
+        -1,
+        "W+X mprotect allowed"
+    );
+    assert_eq_test!(
+        process_vm_mprotect(vm.pid, addr, PAGE_SIZE_4KB, PROT_READ | PROT_EXEC),
+        0,
+        "RX mprotect refused"
+    );
+    let audit = process_vm_audit_wx(vm.pid).unwrap_or_default();
+    assert_test!(audit.is_clean(), "W+X at {:#x}", audit.first_violation);
+    pass!()
+}
+
+pub fn test_wx_audit_catches_wx_page() -> TestResult {
+    let Some(vm) = ProcessVmGuard::new() else {
+        return fail!("create VM");
+    };
+    let vaddr = 0x2000_0000;
+    if map_test_page(vm.page_dir, vaddr, PageFlags::USER_RW.bits()).is_none() {
+        return fail!("map test page");
+    }
+    let audit = process_vm_audit_wx(vm.pid).unwrap_or_default();
+    assert_eq_test!(audit.wx_mappings, 1);
+    assert_eq_test!(audit.first_violation, vaddr);
+    assert_test!(!audit.is_clean());
+    pass!()
+}
+
+slopos_lib::define_test_suite!(
+    wx,
+    [
+        test_wx_fresh_vm_is_clean,
+        test_wx_mmap_and_mprotect_refuse_wx,
+        test_wx_audit_catches_wx_page,
+    ]
+);
@@ -6,9 +6,17 @@ set -euo pipefail
 # Usage: build_fs_image.sh <image_path> <build_dir> <bin1> [bin2] ...
 #
 # Each binary is placed in /bin/<name> except 'init' which goes to /sbin/init.
+# If the dynamic userland was built, ld.slop and libslop.so go to /lib.
+#
+# PSF2 fonts in FONTS_DIR are placed in /share/fonts, images in IMAGES_DIR
+# in /share/images, and WALLPAPER, if it exists, becomes the desktop
+# background at /etc/wallpaper.bmp.
 #
 # Environment:
 #   FS_IMAGE_SIZE - image size (default: 8M)
+#   FONTS_DIR     - fonts to install (default: assets/fonts)
+#   IMAGES_DIR    - BMP, PPM and QOI images to install (default: assets/images)
+#   WALLPAPER     - BMP wallpaper to install (default: assets/wallpaper.bmp)
 
 IMAGE_PATH="${1:?Usage: build_fs_image.sh <image_path> <build_dir> <bin1> [bin2] ...}"
 BUILD_DIR="${2:?Usage: build_fs_image.sh <image_path> <build_dir> <bin1> [bin2] ...}"
@@ -16,6 +24,9 @@ shift 2
 BINS=("$@")
 
 FS_IMAGE_SIZE="${FS_IMAGE_SIZE:-8M}"
+FONTS_DIR="${FONTS_DIR:-$(dirname "$0")/../assets/fonts}"
+IMAGES_DIR="${IMAGES_DIR:-$(dirname "$0")/../assets/images}"
+WALLPAPER="${WALLPAPER:-$(dirname "$0")/../assets/wallpaper.bmp}"
 
 # macOS: extend PATH to find e2fsprogs tools installed via Homebrew
 if [ "$(uname -s)" = "Darwin" ]; then
@@ -42,6 +53,7 @@ truncate -s "$FS_IMAGE_SIZE" "$IMAGE_PATH"
 mkfs.ext2 -F -b 4096 "$IMAGE_PATH" >/dev/null
 debugfs -w -R "mkdir /bin" "$IMAGE_PATH" >/dev/null
 debugfs -w -R "mkdir /sbin" "$IMAGE_PATH" >/dev/null
+debugfs -w -R "mkdir /etc" "$IMAGE_PATH" >/dev/null
 
 for bin in "${BINS[@]}"; do
     src="${BUILD_DIR}/${bin}.elf"
@@ -58,3 +70,28 @@ for bin in "${BINS[@]}"; do
     debugfs -w -R "write $src $dst" "$IMAGE_PATH" >/dev/null
     debugfs -w -R "set_inode_field $dst mode 0100755" "$IMAGE_PATH" >/dev/null
 done
+
+if [ -f "${BUILD_DIR}/ld.slop.elf" ] && [ -f "${BUILD_DIR}/libslop.so" ]; then
+    debugfs -w -R "mkdir /lib" "$IMAGE_PATH" >/dev/null
+    debugfs -w -R "write ${BUILD_DIR}/ld.slop.elf /lib/ld.slop" "$IMAGE_PATH" >/dev/null
+    debugfs -w -R "set_inode_field /lib/ld.slop mode 0100755" "$IMAGE_PATH" >/dev/null
+    debugfs -w -R "write ${BUILD_DIR}/libslop.so /lib/libslop.so" "$IMAGE_PATH" >/dev/null
+    debugfs -w -R "set_inode_field /lib/libslop.so mode 0100644" "$IMAGE_PATH" >/dev/null
+fi
+
+debugfs -w -R "mkdir /share" "$IMAGE_PATH" >/dev/null
+debugfs -w -R "mkdir /share/fonts" "$IMAGE_PATH" >/dev/null
+for font in "$FONTS_DIR"/*.psf; do
+    [ -f "$font" ] || continue
+    debugfs -w -R "write $font /share/fonts/$(basename "$font")" "$IMAGE_PATH" >/dev/null
+done
+
+debugfs -w -R "mkdir /share/images" "$IMAGE_PATH" >/dev/null
+for image in "$IMAGES_DIR"/*.bmp "$IMAGES_DIR"/*.ppm "$IMAGES_DIR"/*.pgm "$IMAGES_DIR"/*.qoi; do
+    [ -f "$image" ] || continue
+    debugfs -w -R "write $image /share/images/$(basename "$image")" "$IMAGE_PATH" >/dev/null
+done
+
+if [ -f "$WALLPAPER" ]; then
+    debugfs -w -R "write $WALLPAPER /etc/wallpaper.bmp" "$IMAGE_PATH" >/dev/null
+fi
@@ -5,12 +5,15 @@ set -euo pipefail
 #
 # Usage: build_userland.sh <build_dir> <cargo_target_dir> [--test]
 #
-# Without --test: builds init, shell, compositor, roulette, file_manager, sysinfo, nmap, ifconfig
+# Without --test: builds init, shell, compositor, roulette, file_manager, sysinfo, nmap, ifconfig, nc, life, beep, wavplay, mdns-browse, ping, wget, fetch, traceroute, sniff, slop-netstat, slop-xrandr, slop-top, htop, slopedit, terminal, imgview
 # With --test:    also builds fork_test (requires testbins feature)
 #
 # Environment:
-#   CARGO        - cargo binary (default: cargo)
-#   RUST_CHANNEL - toolchain channel (parsed from rust-toolchain.toml if unset)
+#   CARGO         - cargo binary (default: cargo)
+#   RUST_CHANNEL  - toolchain channel (parsed from rust-toolchain.toml if unset)
+#   USERLAND_LINK - dynamic (default) or static; dynamic also builds the
+#                   ld.slop loader and libslop.so and relinks every binary
+#                   except init against them
 
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 REPO_ROOT="$(cd "$SCRIPT_DIR/.." && pwd)"
@@ -22,8 +25,10 @@ TEST_MODE="${3:-}"
 CARGO="${CARGO:-cargo}"
 RUST_CHANNEL="${RUST_CHANNEL:-$(sed -n 's/^channel[[:space:]]*=[[:space:]]*"\(.*\)"/\1/p' "${REPO_ROOT}/rust-toolchain.toml")}"
 USERLAND_TARGET="${USERLAND_TARGET:-${REPO_ROOT}/targets/x86_64-slos-userland.json}"
+USERLAND_DYN_TARGET="${USERLAND_DYN_TARGET:-${REPO_ROOT}/targets/x86_64-slos-userland-dyn.json}"
+USERLAND_LINK="${USERLAND_LINK:-dynamic}"
 
-BINS="init shell compositor roulette file_manager sysinfo nmap ifconfig nc"
+BINS="init shell compositor roulette file_manager sysinfo nmap ifconfig nc life beep wavplay mdns-browse ping wget fetch traceroute sniff slop-netstat slop-xrandr slop-top htop slopedit terminal imgview"
 
 # Ensure toolchain is available
 "$SCRIPT_DIR/ensure_toolchain.sh"
@@ -56,6 +61,61 @@ done
 
 echo "Userland binaries built: $(for b in $BINS; do printf '%s/%s.elf ' "$BUILD_DIR" "$b"; done)"
 
+# Dynamic linking: ld.slop, libslop.so, and the binaries relinked against it.
+# init stays static so the system can boot even if /lib is broken.
+if [ "$USERLAND_LINK" = "dynamic" ]; then
+    # The loader runs before anything is relocated for it, so it is a
+    # self-contained static PIE; the kernel applies its relative relocations.
+    CARGO_TARGET_DIR="${CARGO_TARGET_DIR}/ldso" \
+    RUSTFLAGS="-C relocation-model=pie -C link-arg=-pie -C link-arg=--no-dynamic-linker" \
+    $CARGO +"$RUST_CHANNEL" build \
+        -Zbuild-std=core,alloc \
+        -Zunstable-options \
+        --target "$USERLAND_TARGET" \
+        --package slopos-userland \
+        --bin ld-slop \
+        --no-default-features \
+        --release
+    cp "${CARGO_TARGET_DIR}/ldso/x86_64-slos-userland/release/ld-slop" "$BUILD_DIR/ld.slop.elf"
+
+    DYN_TARGET_DIR="${CARGO_TARGET_DIR}/dyn"
+    DYN_RELEASE_DIR="${DYN_TARGET_DIR}/x86_64-slos-userland-dyn/release"
+
+    CARGO_TARGET_DIR="$DYN_TARGET_DIR" \
+    $CARGO +"$RUST_CHANNEL" rustc \
+        -Zbuild-std=core,alloc \
+        -Zunstable-options \
+        --target "$USERLAND_DYN_TARGET" \
+        --package slopos-userland \
+        --lib \
+        --crate-type dylib \
+        --no-default-features \
+        --features shared \
+        --release
+    cp "$DYN_RELEASE_DIR/libslopos_userland.so" "$BUILD_DIR/libslop.so"
+
+    for bin in $BINS; do
+        [ "$bin" = "init" ] && continue
+        CARGO_TARGET_DIR="$DYN_TARGET_DIR" \
+        $CARGO +"$RUST_CHANNEL" rustc \
+            -Zbuild-std=core,alloc \
+            -Zunstable-options \
+            --target "$USERLAND_DYN_TARGET" \
+            --package slopos-userland \
+            --bin "$bin" \
+            --no-default-features \
+            --features shared \
+            --release \
+            -- -C prefer-dynamic \
+            --extern "slopos_userland=$DYN_RELEASE_DIR/libslopos_userland.so"
+        cp "$DYN_RELEASE_DIR/$bin" "$BUILD_DIR/${bin}.elf"
+    done
+
+    echo "Dynamic userland built: $BUILD_DIR/ld.slop.elf $BUILD_DIR/libslop.so"
+else
+    rm -f "$BUILD_DIR/ld.slop.elf" "$BUILD_DIR/libslop.so"
+fi
+
 # Build test binaries if requested
 if [ "$TEST_MODE" = "--test" ]; then
     CARGO_TARGET_DIR="$CARGO_TARGET_DIR" \
@@ -0,0 +1,54 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+# Link a static userland binary and embed its symbol table.
+#
+# rustc runs this in place of rust-lld (see targets/x86_64-slos-userland.json)
+# with rust-lld, llvm-nm and llvm-objcopy on PATH.  The first pass links the
+# program as usual.  Its function symbols are then written to a
+# .slopos.syms section, one "<16 hex digit address> <name>" line each in
+# address order, and the program is linked again with that section added.
+# userland/userland.ld places .slopos.syms after everything else, so the
+# second pass leaves every other address where the first put it.  The
+# panic handler reads the table back (userland/src/runtime/backtrace.rs).
+#
+# PIE and shared links (ld.slop) are passed through untouched: their
+# link-time addresses are not the ones they run at.
+
+args=("$@")
+if [ "${args[0]:-}" = "-flavor" ]; then
+    args=("${args[@]:2}")
+fi
+
+out=""
+embed=1
+for ((i = 0; i < ${#args[@]}; i++)); do
+    case "${args[i]}" in
+        -o) out="${args[i + 1]}" ;;
+        -pie | -shared | --pie | --shared) embed=0 ;;
+    esac
+done
+
+rust-lld -flavor gnu "${args[@]}"
+
+if [ "$embed" = 0 ] || [ -z "$out" ]; then
+    exit 0
+fi
+
+tmp="$(mktemp -d)"
+trap 'rm -rf "$tmp"' EXIT
+
+# Text symbols only; drop the hash rustc appends to legacy mangled names.
+llvm-nm --defined-only --numeric-sort --demangle "$out" |
+    sed -n 's/^\([0-9a-f]\{16\}\) [tTwW] \(.*\)$/\1 \2/p' |
+    sed 's/::h[0-9a-f]\{16\}$//' >"$tmp/syms"
+
+if [ ! -s "$tmp/syms" ]; then
+    exit 0
+fi
+
+(cd "$tmp" && llvm-objcopy -I binary -O elf64-x86-64 \
+    --rename-section=.data=.slopos.syms,alloc,load,readonly,data,contents \
+    syms syms.o)
+
+rust-lld -flavor gnu "${args[@]}" "$tmp/syms.o"
@@ -8,22 +8,27 @@ set -euo pipefail
 #   mode: interactive - Full interactive boot (Ctrl+C to exit)
 #         logged      - Headless boot with timeout, logs to file
 #         test        - Test harness with exit-code interpretation
+#         autopilot   - Unattended boot→GUI→shutdown smoke test (autopilot=1
+#                       cmdline); boots a scratch copy of the fs image and
+#                       extracts the kernel's screenshot to AUTOPILOT_SCREENSHOT
 #
 # Environment (all optional, sensible defaults provided):
 #   QEMU_BIN, QEMU_SMP, QEMU_MEM, QEMU_ACCEL,
-#   VIDEO, QEMU_DISPLAY,
+#   VIDEO, VIDEO_DEVICE, QEMU_DISPLAY,
 #   QEMU_FB_WIDTH, QEMU_FB_HEIGHT, QEMU_FB_AUTO,
 #   QEMU_FB_AUTO_POLICY, QEMU_FB_AUTO_OUTPUT,
 #   QEMU_GTK_ZOOM_TO_FIT,
 #   QEMU_ENABLE_ISA_EXIT, QEMU_PCI_DEVICES,
 #   OVMF_DIR,
 #   NET, NET_PORTS,
-#   BOOT_LOG_TIMEOUT, LOG_FILE
+#   AUDIO, QEMU_AUDIODEV,
+#   BOOT_LOG_TIMEOUT, LOG_FILE, GDB_PORT,
+#   AUTOPILOT_TIMEOUT, AUTOPILOT_SCREENSHOT
 
 SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
 REPO_ROOT="$(cd "$SCRIPT_DIR/.." && pwd)"
 
-MODE="${1:?Usage: qemu_run.sh <interactive|logged|test> <iso> <fs_image>}"
+MODE="${1:?Usage: qemu_run.sh <interactive|logged|test|autopilot> <iso> <fs_image>}"
 ISO="${2:?Usage: qemu_run.sh <mode> <iso> <fs_image>}"
 FS_IMAGE="${3:?Usage: qemu_run.sh <mode> <iso> <fs_image>}"
 
@@ -44,6 +49,9 @@ else
 fi
 
 VIDEO="${VIDEO:-0}"
+# vga (default) or virtio-gpu; the latter also needs video=virtio-gpu on the
+# kernel cmdline for the kernel to drive it instead of the GOP framebuffer.
+VIDEO_DEVICE="${VIDEO_DEVICE:-vga}"
 QEMU_FB_WIDTH="${QEMU_FB_WIDTH:-1920}"
 QEMU_FB_HEIGHT="${QEMU_FB_HEIGHT:-1080}"
 QEMU_FB_AUTO="${QEMU_FB_AUTO:-1}"
@@ -53,16 +61,30 @@ QEMU_FB_DETECT_SCRIPT="${QEMU_FB_DETECT_SCRIPT:-${SCRIPT_DIR}/detect_qemu_resolu
 QEMU_GTK_ZOOM_TO_FIT="${QEMU_GTK_ZOOM_TO_FIT:-off}"
 QEMU_ENABLE_ISA_EXIT="${QEMU_ENABLE_ISA_EXIT:-0}"
 QEMU_PCI_DEVICES="${QEMU_PCI_DEVICES:-}"
+# Attach an xHCI controller with a USB keyboard and mouse (always on in test mode).
+QEMU_USB="${QEMU_USB:-0}"
 
 NET="${NET:-0}"
 NET_PORTS="${NET_PORTS:-7777,8080,8081}"
 
+# Sound card: hda (default), ac97 or none.  QEMU_AUDIODEV picks the host
+# backend (pa, pipewire, sdl, coreaudio, ...); "none" keeps the device
+# but discards its output.
+AUDIO="${AUDIO:-hda}"
+QEMU_AUDIODEV="${QEMU_AUDIODEV:-none}"
+
 OVMF_DIR="${OVMF_DIR:-${REPO_ROOT}/third_party/ovmf}"
 OVMF_CODE="${OVMF_DIR}/OVMF_CODE.fd"
 OVMF_VARS="${OVMF_DIR}/OVMF_VARS.fd"
 
 BOOT_LOG_TIMEOUT="${BOOT_LOG_TIMEOUT:-15}"
 LOG_FILE="${LOG_FILE:-test_output.log}"
+AUTOPILOT_TIMEOUT="${AUTOPILOT_TIMEOUT:-120}"
+AUTOPILOT_SCREENSHOT="${AUTOPILOT_SCREENSHOT:-autopilot.ppm}"
+
+# COM2 on a TCP port for the kernel GDB stub (gdb=1 on the cmdline):
+# gdb -ex 'target remote :$GDB_PORT'.
+GDB_PORT="${GDB_PORT:-}"
 
 # ── Validate SMP ─────────────────────────────────────────────────────────────
 if [ "$QEMU_SMP" -lt 1 ]; then
@@ -85,10 +107,29 @@ fi
 
 # ── Create runtime OVMF_VARS copy ────────────────────────────────────────────
 OVMF_VARS_RUNTIME="$(mktemp "${OVMF_DIR}/OVMF_VARS.runtime.XXXXXX.fd")"
-cleanup() { rm -f "$OVMF_VARS_RUNTIME"; }
+FS_IMAGE_SCRATCH=""
+NVME_IMAGE_SCRATCH=""
+cleanup() {
+    rm -f "$OVMF_VARS_RUNTIME" ${FS_IMAGE_SCRATCH:+"$FS_IMAGE_SCRATCH"} \
+        ${NVME_IMAGE_SCRATCH:+"$NVME_IMAGE_SCRATCH"}
+}
 trap cleanup EXIT INT TERM
 cp "$OVMF_VARS" "$OVMF_VARS_RUNTIME"
 
+# Autopilot writes its screenshot into the root filesystem; keep the
+# checked-in image pristine by booting a throwaway copy.
+if [ "$MODE" = "autopilot" ]; then
+    FS_IMAGE_SCRATCH="$(mktemp "${TMPDIR:-/tmp}/slopos-autopilot.XXXXXX.img")"
+    cp "$FS_IMAGE" "$FS_IMAGE_SCRATCH"
+    FS_IMAGE="$FS_IMAGE_SCRATCH"
+fi
+
+# The test harness exercises the NVMe driver against a blank scratch disk.
+if [ "$MODE" = "test" ]; then
+    NVME_IMAGE_SCRATCH="$(mktemp "${TMPDIR:-/tmp}/slopos-nvme.XXXXXX.img")"
+    truncate -s 16M "$NVME_IMAGE_SCRATCH"
+fi
+
 # ── Resolve framebuffer dimensions ───────────────────────────────────────────
 fb_width="$QEMU_FB_WIDTH"
 fb_height="$QEMU_FB_HEIGHT"
@@ -119,11 +160,23 @@ fi
 # ── Resolve display and extra args per mode ──────────────────────────────────
 DISPLAY_ARGS=(-display none)
 USB_ARGS=()
+XHCI_ARGS=(-device "qemu-xhci,id=xhci"
+    -device "usb-kbd,bus=xhci.0"
+    -device "usb-mouse,bus=xhci.0")
+if [ "$QEMU_USB" != "0" ]; then
+    USB_ARGS=("${XHCI_ARGS[@]}")
+fi
 EXTRA_ARGS=()
 
 case "$MODE" in
     test)
         DISPLAY_ARGS=(-nographic)
+        EXTRA_ARGS=(-device "isa-debug-exit,iobase=0xf4,iosize=0x01" -no-reboot
+            -drive "file=$NVME_IMAGE_SCRATCH,if=none,id=nvme-disk0,format=raw"
+            -device "nvme,drive=nvme-disk0,serial=slopnvme0")
+        USB_ARGS=("${XHCI_ARGS[@]}")
+        ;;
+    autopilot)
         EXTRA_ARGS=(-device "isa-debug-exit,iobase=0xf4,iosize=0x01" -no-reboot)
         ;;
     interactive|logged)
@@ -147,12 +200,42 @@ case "$MODE" in
         fi
         ;;
     *)
-        echo "Unknown mode: $MODE (expected: interactive, logged, test)" >&2
+        echo "Unknown mode: $MODE (expected: interactive, logged, test, autopilot)" >&2
         exit 1
         ;;
 esac
 
-VIDEO_ARGS=(-vga none -device "VGA,edid=on,xres=${fb_width},yres=${fb_height}")
+case "$VIDEO_DEVICE" in
+    virtio-gpu)
+        VIDEO_ARGS=(-vga none -device "virtio-gpu-pci,edid=on,xres=${fb_width},yres=${fb_height}")
+        ;;
+    *)
+        VIDEO_ARGS=(-vga none -device "VGA,edid=on,xres=${fb_width},yres=${fb_height}")
+        ;;
+esac
+
+# Headless modes never play sound, but the test harness still drives the
+# controller.
+case "$MODE" in
+    test|autopilot) QEMU_AUDIODEV=none ;;
+esac
+case "$AUDIO" in
+    hda)
+        AUDIO_ARGS=(-audiodev "$QEMU_AUDIODEV,id=slopsnd0"
+            -device intel-hda -device "hda-output,audiodev=slopsnd0")
+        ;;
+    ac97)
+        AUDIO_ARGS=(-audiodev "$QEMU_AUDIODEV,id=slopsnd0"
+            -device "AC97,audiodev=slopsnd0")
+        ;;
+    none)
+        AUDIO_ARGS=()
+        ;;
+    *)
+        echo "Unknown AUDIO: $AUDIO (expected: hda, ac97, none)" >&2
+        exit 1
+        ;;
+esac
 
 # Handle optional PCI devices
 PCI_ARGS=()
@@ -180,6 +263,12 @@ if [[ "$NET" =~ ^(1|true|on|yes)$ ]]; then
     echo "Network port forwarding enabled: ${NET_PORTS}"
 fi
 
+GDB_ARGS=()
+if [ -n "$GDB_PORT" ]; then
+    GDB_ARGS=(-serial "tcp::${GDB_PORT},server=on,wait=off")
+    echo "GDB stub on COM2: target remote :${GDB_PORT}"
+fi
+
 # ── Assemble common QEMU arguments ──────────────────────────────────────────
 QEMU_ARGS=(
     -machine "q35,accel=$QEMU_ACCEL"
@@ -198,10 +287,12 @@ QEMU_ARGS=(
     -device "virtio-net-pci,netdev=slopnet0,disable-legacy=on"
     -boot "order=d,menu=off"
     -serial stdio
+    "${GDB_ARGS[@]}"
     -monitor none
     "${DISPLAY_ARGS[@]}"
     "${VIDEO_ARGS[@]}"
     "${USB_ARGS[@]}"
+    "${AUDIO_ARGS[@]}"
     "${EXTRA_ARGS[@]}"
     "${PCI_ARGS[@]}"
 )
@@ -234,7 +325,7 @@ case "$MODE" in
         status=$?
         set -e
         trap - EXIT INT TERM
-        rm -f "$OVMF_VARS_RUNTIME"
+        cleanup
         if [ $status -eq 1 ]; then
             echo "Interrupt tests passed."
         elif [ $status -eq 3 ]; then
@@ -245,4 +336,31 @@ case "$MODE" in
             exit $status
         fi
         ;;
+
+    autopilot)
+        echo "Starting QEMU in autopilot mode (${AUTOPILOT_TIMEOUT}s timeout)..."
+        set +e
+        timeout "${AUTOPILOT_TIMEOUT}s" "$QEMU_BIN" "${QEMU_ARGS[@]}"
+        status=$?
+        set -e
+        if command -v debugfs >/dev/null 2>&1; then
+            rm -f "$AUTOPILOT_SCREENSHOT"
+            debugfs -R "dump /autopilot.ppm $AUTOPILOT_SCREENSHOT" "$FS_IMAGE" >/dev/null 2>&1 || true
+            if [ -s "$AUTOPILOT_SCREENSHOT" ]; then
+                echo "Autopilot screenshot saved to $AUTOPILOT_SCREENSHOT"
+            fi
+        fi
+        if [ $status -eq 1 ]; then
+            echo "Autopilot passed."
+        elif [ $status -eq 3 ]; then
+            echo "Autopilot reported failures." >&2
+            exit 1
+        elif [ $status -eq 124 ]; then
+            echo "Autopilot did not finish within ${AUTOPILOT_TIMEOUT}s" >&2
+            exit 1
+        else
+            echo "Unexpected QEMU exit status $status" >&2
+            exit $status
+        fi
+        ;;
 esac
@@ -0,0 +1,32 @@
+//! Clipboard access for windowed applications.
+//!
+//! The kernel keeps one MIME-typed buffer shared by every task.  Apps
+//! receive `Event::Copy`, `Event::Cut` and `Event::Paste` for Ctrl+C,
+//! Ctrl+X and Ctrl+V and move their data through these helpers.
+
+use slopos_abi::input::CLIPBOARD_MIME_TEXT;
+
+use crate::syscall::{SyscallResult, input};
+
+/// Put `data` of MIME type `mime` on the clipboard.
+pub fn set(mime: &str, data: &[u8]) -> SyscallResult<usize> {
+    input::clipboard_set(mime.as_bytes(), data)
+}
+
+/// Copy the clipboard into `buf` if it holds `mime`.  Returns the bytes
+/// written, or `None` when the clipboard holds something else.
+pub fn get(mime: &str, buf: &mut [u8]) -> Option<usize> {
+    input::clipboard_get(mime.as_bytes(), buf)
+        .ok()
+        .map(|len| len.min(buf.len()))
+}
+
+/// Put plain text on the clipboard.
+pub fn set_text(text: &[u8]) -> SyscallResult<usize> {
+    input::clipboard_set(CLIPBOARD_MIME_TEXT, text)
+}
+
+/// Copy plain text from the clipboard into `buf`; 0 if there is none.
+pub fn get_text(buf: &mut [u8]) -> usize {
+    input::clipboard_get(CLIPBOARD_MIME_TEXT, buf).map_or(0, |len| len.min(buf.len()))
+}
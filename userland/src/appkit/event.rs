@@ -5,14 +5,52 @@
 
 use crate::syscall::{InputEvent, InputEventType};
 
+const CTRL_C: u8 = 0x03;
+const CTRL_V: u8 = 0x16;
+const CTRL_X: u8 = 0x18;
+
 #[derive(Clone, Copy, Debug)]
 pub enum Event {
-    PointerMotion { x: i32, y: i32 },
-    PointerPress { button: u8 },
-    PointerRelease { button: u8 },
-    KeyPress { scancode: u8, ascii: u8 },
-    KeyRelease { scancode: u8, ascii: u8 },
+    PointerMotion {
+        x: i32,
+        y: i32,
+    },
+    PointerPress {
+        button: u8,
+    },
+    PointerRelease {
+        button: u8,
+    },
+    /// Wheel movement in detents; positive `dy` scrolls down.
+    Scroll {
+        dx: i32,
+        dy: i32,
+    },
+    /// `modifiers` holds `KEY_MOD_*` bits.
+    KeyPress {
+        scancode: u8,
+        ascii: u8,
+        modifiers: u8,
+    },
+    KeyRelease {
+        scancode: u8,
+        ascii: u8,
+    },
+    /// Ctrl+C: put the selection on the clipboard with
+    /// [`clipboard::set_text`](super::clipboard::set_text).
+    Copy,
+    /// Ctrl+X: like `Copy`, then delete the selection.
+    Cut,
+    /// Ctrl+V: insert [`clipboard::get_text`](super::clipboard::get_text).
+    Paste,
     CloseRequest,
+    /// The window manager resized the window.  `appkit::run()` has already
+    /// resized the surface by the time apps see this; apps with their own
+    /// loop resize it themselves, or ignore the event to keep their size.
+    Configure {
+        width: u32,
+        height: u32,
+    },
     Other,
 }
 
@@ -29,15 +67,29 @@ impl Event {
             InputEventType::PointerButtonRelease => Event::PointerRelease {
                 button: raw.pointer_button_code(),
             },
-            InputEventType::KeyPress => Event::KeyPress {
-                scancode: raw.key_scancode(),
-                ascii: raw.key_ascii(),
+            InputEventType::PointerScroll => Event::Scroll {
+                dx: raw.scroll_dx(),
+                dy: raw.scroll_dy(),
+            },
+            InputEventType::KeyPress => match raw.key_ascii() {
+                CTRL_C => Event::Copy,
+                CTRL_X => Event::Cut,
+                CTRL_V => Event::Paste,
+                ascii => Event::KeyPress {
+                    scancode: raw.key_scancode(),
+                    ascii,
+                    modifiers: raw.key_modifiers(),
+                },
             },
             InputEventType::KeyRelease => Event::KeyRelease {
                 scancode: raw.key_scancode(),
                 ascii: raw.key_ascii(),
             },
             InputEventType::CloseRequest => Event::CloseRequest,
+            InputEventType::Configure => {
+                let (width, height) = raw.configure_size();
+                Event::Configure { width, height }
+            }
             _ => Event::Other,
         }
     }
@@ -28,6 +28,7 @@
 //! }
 //! ```
 
+pub mod clipboard;
 pub mod event;
 pub mod run;
 pub mod surface;
@@ -65,10 +66,10 @@ pub use window::Window;
 #[macro_export]
 macro_rules! entry {
     ($main_fn:path) => {
+        #[cfg(not(feature = "shared"))]
         #[panic_handler]
-        fn panic(_info: &core::panic::PanicInfo) -> ! {
-            let _ = $crate::syscall::tty::write(b"panic!\n");
-            $crate::syscall::core::exit_with_code(101);
+        fn panic(info: &core::panic::PanicInfo) -> ! {
+            $crate::runtime::backtrace::report_panic(info)
         }
 
         /// Rust trampoline called from naked `_start`.
@@ -96,3 +97,11 @@ macro_rules! entry {
         }
     };
 }
+
+/// The panic handler `libslop.so` carries for dynamically linked programs,
+/// which leave theirs out when built with `shared`.
+#[cfg(feature = "shared")]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    crate::runtime::backtrace::report_panic(info)
+}
@@ -1,8 +1,8 @@
 //! Generic event loop for windowed applications.
 //!
 //! Provides the `WindowedApp` trait and a `run()` function that owns the
-//! poll -> dispatch -> redraw -> present -> yield loop. All hot-path calls
-//! are monomorphized (no trait objects).
+//! poll -> dispatch -> update -> redraw -> present -> yield loop. All
+//! hot-path calls are monomorphized (no trait objects).
 
 use crate::gfx::DrawBuffer;
 use crate::syscall::{InputEvent, core as sys_core, tty};
@@ -39,17 +39,26 @@ pub trait WindowedApp {
         }
     }
 
+    /// Called once per pass of the event loop, after events are dispatched.
+    /// Use it for work driven by time rather than input, and request a
+    /// redraw when it changes what is shown.
+    fn update(&mut self, _win: &mut Window) {}
+
     /// Called when a redraw was requested via `Window::request_redraw()`.
     ///
-    /// The `DrawBuffer` already has the correct pixel format set.
-    /// Width and height are available via `fb.width()` / `fb.height()`.
+    /// The `DrawBuffer` already has the correct pixel format set and still
+    /// holds the previous frame, so only what changed needs drawing; only
+    /// the damage it records is presented.  Width and height are available
+    /// via `fb.width()` / `fb.height()`.
     fn draw(&mut self, fb: &mut DrawBuffer<'_>);
 }
 
 /// Run a windowed application to completion.
 ///
 /// Creates a `Window`, calls `app.init()`, then enters the main loop:
-/// poll events -> dispatch -> redraw if requested -> present -> yield.
+/// poll events -> dispatch -> update -> redraw if requested -> present ->
+/// yield.
+/// A `Configure` event resizes the window before the app sees it.
 ///
 /// This function never returns normally; it calls `sys_core::exit()` on
 /// `ControlFlow::Exit`.
@@ -78,15 +87,21 @@ pub fn run<A: WindowedApp>(mut app: A, width: u32, height: u32) -> ! {
         for raw in &raw_buf[..count] {
             let event = Event::from_raw(raw);
             win.track_pointer(&event);
+            if !win.handle_configure(&event) {
+                continue;
+            }
             if app.on_event(&mut win, event) == ControlFlow::Exit {
                 sys_core::exit();
             }
         }
 
+        app.update(&mut win);
+
         if win.take_redraw() {
             if let Some(mut fb) = win.surface_mut().frame() {
                 app.draw(&mut fb);
-                win.surface().present_full();
+                let damage = fb.damage().clone();
+                win.surface().present_damage(&damage);
             } else {
                 win.request_redraw();
             }
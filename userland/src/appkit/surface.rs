@@ -3,10 +3,14 @@
 //! `Surface` encapsulates the full lifecycle of a window's backing store:
 //! display info query, pixel format negotiation, SHM allocation, and
 //! compositor attachment. Applications use `Surface::frame()` to obtain
-//! a `DrawBuffer` for rendering and `Surface::present_full()` /
-//! `Surface::present_region()` to push completed frames to the compositor.
+//! a `DrawBuffer` for rendering and `Surface::present_full()`,
+//! `Surface::present_region()` or `Surface::present_damage()` to push
+//! completed frames to the compositor.
+//! `Surface::resize()` moves the surface onto a buffer of a new size.
 
-use crate::gfx::{DrawBuffer, PixelFormat};
+use core::cell::Cell;
+
+use crate::gfx::{DamageTracker, DrawBuffer, PixelFormat};
 use crate::syscall::{DisplayInfo, ShmBuffer, window};
 
 #[derive(Debug, Clone, Copy)]
@@ -23,6 +27,13 @@ pub enum SurfaceError {
 /// pixel format). Created once per window via `Surface::new()`.
 pub struct Surface {
     shm: ShmBuffer,
+    /// The buffer before the last resize.  The compositor may still be
+    /// reading it until it picks up the new one, so it lives until the
+    /// next resize.
+    retired: Option<ShmBuffer>,
+    /// `shm` still has to be attached; done on the next present so the
+    /// compositor never shows it undrawn.
+    attach_pending: Cell<bool>,
     width: u32,
     height: u32,
     pitch: usize,
@@ -30,6 +41,20 @@ pub struct Surface {
     pixel_format: PixelFormat,
 }
 
+/// Row pitch and buffer size for a `width` x `height` surface.
+fn buffer_layout(width: u32, height: u32, bytes_pp: u8) -> Result<(usize, usize), SurfaceError> {
+    if width == 0 || height == 0 {
+        return Err(SurfaceError::BadSize);
+    }
+    let pitch = (width as usize)
+        .checked_mul(bytes_pp as usize)
+        .ok_or(SurfaceError::BadSize)?;
+    let size = pitch
+        .checked_mul(height as usize)
+        .ok_or(SurfaceError::BadSize)?;
+    Ok((pitch, size))
+}
+
 impl Surface {
     /// Create a new surface and attach it to the compositor.
     ///
@@ -51,12 +76,7 @@ impl Surface {
 
         let pixel_format = fb_info.format;
         let bytes_pp = pixel_format.bytes_per_pixel();
-        let pitch = (width as usize)
-            .checked_mul(bytes_pp as usize)
-            .ok_or(SurfaceError::BadSize)?;
-        let buffer_size = pitch
-            .checked_mul(height as usize)
-            .ok_or(SurfaceError::BadSize)?;
+        let (pitch, buffer_size) = buffer_layout(width, height, bytes_pp)?;
 
         let shm = ShmBuffer::create(buffer_size).map_err(|_| SurfaceError::ShmFailed)?;
         shm.attach_surface(width, height)
@@ -64,6 +84,8 @@ impl Surface {
 
         Ok(Self {
             shm,
+            retired: None,
+            attach_pending: Cell::new(false),
             width,
             height,
             pitch,
@@ -72,6 +94,36 @@ impl Surface {
         })
     }
 
+    /// Move the surface onto a new buffer of `width` x `height`.  The
+    /// contents are undefined until the next `frame()` is drawn, and the
+    /// compositor keeps showing the old buffer until the next present.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), SurfaceError> {
+        if width == self.width && height == self.height {
+            return Ok(());
+        }
+        let (pitch, buffer_size) = buffer_layout(width, height, self.bytes_pp)?;
+        let shm = ShmBuffer::create(buffer_size).map_err(|_| SurfaceError::ShmFailed)?;
+
+        let old = core::mem::replace(&mut self.shm, shm);
+        // Unless the old buffer was never attached, the one before it is
+        // no longer in use.
+        if !self.attach_pending.get() {
+            self.retired = Some(old);
+        }
+        self.attach_pending.set(true);
+        self.width = width;
+        self.height = height;
+        self.pitch = pitch;
+        Ok(())
+    }
+
+    /// Attach the buffer from the last resize, if that is still to do.
+    fn attach_if_pending(&self) {
+        if self.attach_pending.get() && self.shm.attach_surface(self.width, self.height).is_ok() {
+            self.attach_pending.set(false);
+        }
+    }
+
     /// Borrow a `DrawBuffer` for the current frame.
     ///
     /// The returned buffer has the correct pixel format already set.
@@ -91,16 +143,48 @@ impl Surface {
 
     /// Mark the full surface as damaged and commit to the compositor.
     pub fn present_full(&self) {
+        self.attach_if_pending();
         let _ = window::surface_damage(0, 0, self.width as i32, self.height as i32);
         let _ = window::surface_commit();
     }
 
     /// Mark a sub-region as damaged and commit to the compositor.
     pub fn present_region(&self, x: i32, y: i32, w: i32, h: i32) {
+        self.attach_if_pending();
         let _ = window::surface_damage(x, y, w, h);
         let _ = window::surface_commit();
     }
 
+    /// Mark the regions in `damage` as damaged and commit to the compositor.
+    /// An empty tracker commits the full surface, as nothing was recorded
+    /// about what changed.
+    pub fn present_damage(&self, damage: &DamageTracker) {
+        if damage.is_empty() || damage.is_full_damage() {
+            self.present_full();
+            return;
+        }
+        self.attach_if_pending();
+        for r in damage.regions() {
+            let _ = window::surface_damage(r.x0, r.y0, r.x1 - r.x0 + 1, r.y1 - r.y0 + 1);
+        }
+        let _ = window::surface_commit();
+    }
+
+    /// Ask the compositor to signal when the next commit has been presented
+    /// (Wayland `wl_surface.frame`). Call before `present_*()`.
+    pub fn request_frame_callback(&self) {
+        let _ = window::surface_frame();
+    }
+
+    /// Presentation timestamp (ms) of the frame requested via
+    /// [`Surface::request_frame_callback`], or `None` if still pending.
+    pub fn poll_frame_done(&self) -> Option<u64> {
+        match window::poll_frame_done() {
+            0 => None,
+            ts => Some(ts),
+        }
+    }
+
     #[inline]
     pub fn width(&self) -> u32 {
         self.width
@@ -1,5 +1,7 @@
 //! High-level window abstraction combining surface, input, and redraw state.
 
+use slopos_abi::window::{WINDOW_FLAG_SMOOTH_SCALE, WINDOW_FLAG_UNDECORATED};
+
 use crate::syscall::{InputEvent, input, window};
 
 use super::event::Event;
@@ -18,18 +20,24 @@ pub struct Window {
     redraw_needed: bool,
     pointer_x: i32,
     pointer_y: i32,
+    /// `WINDOW_FLAG_*` flags last sent to the compositor.
+    window_flags: u8,
 }
 
 impl Window {
     /// Create a new window of the given size.
     ///
-    /// Internally creates and attaches a `Surface`.
+    /// Internally creates and attaches a `Surface`, and has key presses
+    /// delivered as events while the window is focused.
     pub fn new(width: u32, height: u32) -> Result<Self, SurfaceError> {
+        let surface = Surface::new(width, height)?;
+        input::take_keys();
         Ok(Self {
-            surface: Surface::new(width, height)?,
+            surface,
             redraw_needed: true,
             pointer_x: 0,
             pointer_y: 0,
+            window_flags: 0,
         })
     }
 
@@ -38,6 +46,41 @@ impl Window {
         let _ = window::surface_set_title(title);
     }
 
+    /// Have the compositor draw a title bar and buttons for the window
+    /// (the default), or show it bare, e.g. for fullscreen apps.
+    pub fn set_decorated(&mut self, decorated: bool) {
+        self.set_window_flag(WINDOW_FLAG_UNDECORATED, !decorated);
+    }
+
+    /// Declare the scale the window's buffer is drawn at.  Buffers drawn
+    /// at 1 (the default) are magnified on high-DPI displays; apps that
+    /// draw at the display's own scale stay sharp.
+    pub fn set_buffer_scale(&self, scale: u32) {
+        let _ = window::surface_set_buffer_scale(scale);
+    }
+
+    /// Magnify the buffer with smooth filtering rather than repeated
+    /// pixels, e.g. for photos.
+    pub fn set_smooth_scaling(&mut self, smooth: bool) {
+        self.set_window_flag(WINDOW_FLAG_SMOOTH_SCALE, smooth);
+    }
+
+    fn set_window_flag(&mut self, flag: u8, on: bool) {
+        if on {
+            self.window_flags |= flag;
+        } else {
+            self.window_flags &= !flag;
+        }
+        let _ = window::surface_set_window_flags(self.window_flags);
+    }
+
+    /// Resize the window's surface and request a redraw at the new size.
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), SurfaceError> {
+        self.surface.resize(width, height)?;
+        self.redraw_needed = true;
+        Ok(())
+    }
+
     /// Request a redraw on the next frame.
     #[inline]
     pub fn request_redraw(&mut self) {
@@ -101,9 +144,21 @@ impl Window {
         }
     }
 
+    /// Resize the surface for a `Configure` event.  Returns false if the
+    /// surface could not be resized, in which case the window keeps its
+    /// size and the event should be dropped.  Other events pass.
+    pub fn handle_configure(&mut self, event: &Event) -> bool {
+        match *event {
+            Event::Configure { width, height } => self.resize(width, height).is_ok(),
+            _ => true,
+        }
+    }
+
     /// Poll input events, convert them, and call `handler` for each.
     ///
     /// Pointer state is updated per-event before the handler is called.
+    /// `Configure` events are passed on as they are; apps that can draw at
+    /// any size call [`Window::resize`] for them.
     pub fn poll_events<F: FnMut(Event)>(&mut self, mut handler: F) {
         let mut raw_events = [InputEvent::default(); EVENT_BUF_LEN];
         let count = self.poll_events_raw(&mut raw_events);
@@ -0,0 +1,108 @@
+//! `beep [freq_hz] [duration_ms]` — play a sine tone, plus the tone
+//! generator the roulette wheel uses for its win jingle.
+
+use slopos_abi::audio::{AUDIO_FRAME_BYTES, AUDIO_SAMPLE_RATE};
+
+use crate::apps::cli::{Usage, arg_at, parse_u32, write_out};
+use crate::syscall::core::exit_with_code;
+use crate::syscall::{SyscallResult, audio};
+
+const DEFAULT_FREQ_HZ: u32 = 880;
+const DEFAULT_DURATION_MS: u32 = 200;
+const MAX_DURATION_MS: u32 = 10_000;
+
+/// Peak amplitude: a quarter of full scale keeps tones comfortable.
+const AMPLITUDE: i32 = 8192;
+/// Fade in/out length, which avoids clicks at the edges of a tone.
+const RAMP_FRAMES: u32 = AUDIO_SAMPLE_RATE / 200;
+
+/// Frames generated per `audio::write` call.
+const CHUNK_FRAMES: usize = 512;
+
+/// Sine of `phase` (a full turn is 2^32) in Q15, via Bhaskara I's
+/// approximation, which is within 0.2% and needs no floating point.
+pub fn sine_q15(phase: u32) -> i32 {
+    // Half-turn position in [0, 65536), i.e. [0, pi).
+    let x = ((phase & 0x7FFF_FFFF) >> 15) as u64;
+    const PI: u64 = 1 << 16;
+    let p = x * (PI - x);
+    let value = (16 * p * 32767 / (5 * PI * PI - 4 * p)) as i32;
+    if phase & 0x8000_0000 != 0 {
+        -value
+    } else {
+        value
+    }
+}
+
+/// Play one tone, blocking until it is queued.  A frequency of 0 is a rest.
+pub fn play_tone(freq_hz: u32, duration_ms: u32) -> SyscallResult<()> {
+    let total = AUDIO_SAMPLE_RATE * duration_ms / 1000;
+    let step = ((freq_hz as u64) << 32) / AUDIO_SAMPLE_RATE as u64;
+    let mut phase: u32 = 0;
+    let mut frame = 0u32;
+    let mut buf = [0u8; CHUNK_FRAMES * AUDIO_FRAME_BYTES];
+
+    while frame < total {
+        let n = ((total - frame) as usize).min(CHUNK_FRAMES);
+        for out in buf[..n * AUDIO_FRAME_BYTES].chunks_exact_mut(AUDIO_FRAME_BYTES) {
+            let edge = frame.min(total - 1 - frame);
+            let gain = if edge < RAMP_FRAMES {
+                AMPLITUDE * edge as i32 / RAMP_FRAMES as i32
+            } else {
+                AMPLITUDE
+            };
+            let sample = if freq_hz == 0 {
+                0
+            } else {
+                ((sine_q15(phase) * gain) >> 15) as i16
+            };
+            out[..2].copy_from_slice(&sample.to_le_bytes());
+            out[2..].copy_from_slice(&sample.to_le_bytes());
+            phase = phase.wrapping_add(step as u32);
+            frame += 1;
+        }
+        audio::write(&buf[..n * AUDIO_FRAME_BYTES])?;
+    }
+    Ok(())
+}
+
+/// Rising arpeggio (C5 E5 G5 C6) for a roulette win.  Fits in the kernel's
+/// ring, so it returns before the sound finishes.
+pub fn play_win_jingle() {
+    const NOTES: [(u32, u32); 4] = [(523, 60), (659, 60), (784, 60), (1047, 140)];
+    for (freq, ms) in NOTES {
+        if play_tone(freq, ms).is_err() {
+            return;
+        }
+    }
+}
+
+const USAGE: Usage = Usage::new(b"usage: beep [freq_hz] [duration_ms]\n", 1);
+
+pub fn beep_main_args(argc: usize, argv: *const *const u8) -> ! {
+    let mut freq = DEFAULT_FREQ_HZ;
+    let mut duration = DEFAULT_DURATION_MS;
+    if argc > 3 || (argc > 1 && argv.is_null()) {
+        USAGE.exit();
+    }
+    if argc > 1 {
+        freq = parse_u32(arg_at(argv, 1)).unwrap_or_else(|| USAGE.exit());
+    }
+    if argc > 2 {
+        duration = parse_u32(arg_at(argv, 2)).unwrap_or_else(|| USAGE.exit());
+    }
+    if freq > AUDIO_SAMPLE_RATE / 2 || duration > MAX_DURATION_MS {
+        write_out(b"beep: frequency or duration out of range\n");
+        exit_with_code(1);
+    }
+
+    if !audio::info().is_ok_and(|i| i.is_present()) {
+        write_out(b"beep: no sound device\n");
+        exit_with_code(1);
+    }
+    if play_tone(freq, duration).is_err() {
+        write_out(b"beep: playback failed\n");
+        exit_with_code(1);
+    }
+    exit_with_code(0);
+}
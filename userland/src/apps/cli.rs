@@ -0,0 +1,88 @@
+//! Shared argv parsing and stdout formatting for the command-line tools.
+//!
+//! Output goes to fd 1 and falls back to the console TTY when stdout is
+//! closed, so the tools still print when started without a terminal.
+
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::runtime::u_strlen;
+use crate::syscall::core::exit_with_code;
+use crate::syscall::{fs, tty};
+
+pub fn write_out(buf: &[u8]) {
+    if fs::write_slice(1, buf).is_err() {
+        let _ = tty::write(buf);
+    }
+}
+
+pub fn write_dec(value: u64) {
+    let mut buf = NumBuf::<21>::new();
+    write_out(numfmt::trim_nul(buf.format_u64(value)));
+}
+
+/// `value / 10^places` with exactly `places` decimals.
+pub fn write_fixed(value: u64, places: u32) {
+    let mut buf = NumBuf::<42>::new();
+    write_out(numfmt::trim_nul(buf.format_fixed(value, places)));
+}
+
+pub fn write_ipv4(ip: [u8; 4]) {
+    for (i, octet) in ip.iter().enumerate() {
+        if i > 0 {
+            write_out(b".");
+        }
+        write_dec(*octet as u64);
+    }
+}
+
+/// Argument `idx` of a NUL-terminated `argv`; empty if the slot is null.
+/// The caller must keep `idx` below `argc`.
+pub fn arg_at<'a>(argv: *const *const u8, idx: usize) -> &'a [u8] {
+    // SAFETY: the loader passes `argc` valid slots in `argv` and the caller
+    // keeps `idx` below `argc`.
+    let ptr = unsafe { *argv.add(idx) };
+    if ptr.is_null() {
+        return &[];
+    }
+    // SAFETY: non-null argv entries point at NUL-terminated strings that
+    // live on the initial stack for the whole run of the program.
+    unsafe { core::slice::from_raw_parts(ptr, u_strlen(ptr)) }
+}
+
+/// Plain decimal digits only: no sign, no whitespace, no overflow.
+pub fn parse_u64(arg: &[u8]) -> Option<u64> {
+    if arg.is_empty() {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for &b in arg {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        value = value.checked_mul(10)?.checked_add((b - b'0') as u64)?;
+    }
+    Some(value)
+}
+
+pub fn parse_u32(arg: &[u8]) -> Option<u32> {
+    parse_u64(arg).and_then(|value| u32::try_from(value).ok())
+}
+
+/// A tool's usage line and the status it exits with on bad arguments.
+pub struct Usage {
+    text: &'static [u8],
+    code: i32,
+}
+
+impl Usage {
+    /// `text` is a full line, newline included.
+    pub const fn new(text: &'static [u8], code: i32) -> Self {
+        Self { text, code }
+    }
+
+    /// Print the usage line and exit.
+    pub fn exit(&self) -> ! {
+        write_out(self.text);
+        exit_with_code(self.code);
+    }
+}
@@ -0,0 +1,194 @@
+//! Compositor animations driven by the frame clock.
+//!
+//! A [`Tween`] turns the current time into eased progress through a fixed
+//! duration.  Window animations interpolate a [`Transform`] (the window's
+//! frame, standing in for scale and translation, plus its opacity) with
+//! it, and [`Fade`] does the same for a single alpha value.
+
+use crate::gfx::DamageRect;
+
+use super::MAX_WINDOWS;
+
+/// How long minimizing to and restoring from the taskbar takes.
+pub const MINIMIZE_ANIMATION_MS: u64 = 180;
+
+/// Fixed-point scale of [`Tween::progress`].
+pub const PROGRESS_ONE: u32 = 256;
+
+/// Progress through `duration_ms` starting at `start_ms`.
+#[derive(Copy, Clone)]
+pub struct Tween {
+    start_ms: u64,
+    duration_ms: u64,
+}
+
+impl Tween {
+    pub const fn new(start_ms: u64, duration_ms: u64) -> Self {
+        Self {
+            start_ms,
+            duration_ms,
+        }
+    }
+
+    /// Eased-out progress at `now_ms`, from 0 to [`PROGRESS_ONE`].
+    pub fn progress(&self, now_ms: u64) -> u32 {
+        if self.duration_ms == 0 {
+            return PROGRESS_ONE;
+        }
+        let elapsed = now_ms.saturating_sub(self.start_ms).min(self.duration_ms);
+        let linear = (elapsed * PROGRESS_ONE as u64 / self.duration_ms) as u32;
+        let rest = PROGRESS_ONE - linear;
+        PROGRESS_ONE - rest * rest / PROGRESS_ONE
+    }
+
+    pub fn done(&self, now_ms: u64) -> bool {
+        now_ms.saturating_sub(self.start_ms) >= self.duration_ms
+    }
+}
+
+fn lerp(from: i32, to: i32, t: u32) -> i32 {
+    from + (to - from) * t as i32 / PROGRESS_ONE as i32
+}
+
+/// An alpha value fading between two levels.
+#[derive(Copy, Clone)]
+pub struct Fade {
+    tween: Tween,
+    from: u8,
+    pub to: u8,
+}
+
+impl Fade {
+    pub const fn new(start_ms: u64, duration_ms: u64, from: u8, to: u8) -> Self {
+        Self {
+            tween: Tween::new(start_ms, duration_ms),
+            from,
+            to,
+        }
+    }
+
+    pub fn alpha(&self, now_ms: u64) -> u8 {
+        lerp(
+            self.from as i32,
+            self.to as i32,
+            self.tween.progress(now_ms),
+        ) as u8
+    }
+
+    pub fn done(&self, now_ms: u64) -> bool {
+        self.tween.done(now_ms)
+    }
+}
+
+/// Where and how opaquely a window is drawn: `frame` covers its title bar
+/// and content, scaled to fit.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct Transform {
+    pub frame: DamageRect,
+    pub alpha: u8,
+}
+
+impl Transform {
+    fn lerp(&self, to: &Self, t: u32) -> Self {
+        Self {
+            frame: DamageRect {
+                x0: lerp(self.frame.x0, to.frame.x0, t),
+                y0: lerp(self.frame.y0, to.frame.y0, t),
+                x1: lerp(self.frame.x1, to.frame.x1, t),
+                y1: lerp(self.frame.y1, to.frame.y1, t),
+            },
+            alpha: lerp(self.alpha as i32, to.alpha as i32, t) as u8,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct WindowAnimation {
+    task_id: u32,
+    tween: Tween,
+    from: Transform,
+    to: Transform,
+    current: Transform,
+}
+
+/// Running window animations, at most one per window.
+pub struct Animator {
+    slots: [Option<WindowAnimation>; MAX_WINDOWS],
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self {
+            slots: [None; MAX_WINDOWS],
+        }
+    }
+
+    /// Animate `task_id`'s window from `from` to `to`.  A window already
+    /// animating starts from wherever it is now, so reversing midway
+    /// doesn't jump.
+    pub fn start(&mut self, task_id: u32, now_ms: u64, from: Transform, to: Transform) {
+        let from = self.transform(task_id).unwrap_or(from);
+        let animation = WindowAnimation {
+            task_id,
+            tween: Tween::new(now_ms, MINIMIZE_ANIMATION_MS),
+            from,
+            to,
+            current: from,
+        };
+        let slot = match self.slot_of(task_id) {
+            Some(i) => Some(i),
+            None => self.slots.iter().position(Option::is_none),
+        };
+        if let Some(i) = slot {
+            self.slots[i] = Some(animation);
+        }
+    }
+
+    /// Move every animation on to `now_ms`, passing the frames it left and
+    /// entered to `damage`.  Finished animations are dropped.
+    pub fn step(&mut self, now_ms: u64, mut damage: impl FnMut(&DamageRect)) {
+        for slot in &mut self.slots {
+            let Some(animation) = slot else {
+                continue;
+            };
+            let next = animation
+                .from
+                .lerp(&animation.to, animation.tween.progress(now_ms));
+            if next != animation.current {
+                damage(&animation.current.frame);
+                damage(&next.frame);
+                animation.current = next;
+            }
+            if animation.tween.done(now_ms) {
+                damage(&animation.current.frame);
+                *slot = None;
+            }
+        }
+    }
+
+    /// Stop animating a window that went away, returning the frame it was
+    /// last drawn at.
+    pub fn cancel(&mut self, task_id: u32) -> Option<DamageRect> {
+        let i = self.slot_of(task_id)?;
+        self.slots[i]
+            .take()
+            .map(|animation| animation.current.frame)
+    }
+
+    /// How to draw `task_id`'s window this frame, if it is animating.
+    pub fn transform(&self, task_id: u32) -> Option<Transform> {
+        self.slot_of(task_id)
+            .and_then(|i| self.slots[i])
+            .map(|animation| animation.current)
+    }
+
+    pub fn active(&self) -> bool {
+        self.slots.iter().any(Option::is_some)
+    }
+
+    fn slot_of(&self, task_id: u32) -> Option<usize> {
+        self.slots
+            .iter()
+            .position(|slot| slot.is_some_and(|animation| animation.task_id == task_id))
+    }
+}
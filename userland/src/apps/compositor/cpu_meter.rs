@@ -0,0 +1,157 @@
+//! CPU meter: a bar per CPU and the overall load at the right end of the
+//! taskbar, sampled from `SYSCALL_CPU_USAGE` once a second.
+
+use slopos_abi::draw::Color32;
+use slopos_abi::font::{FONT_CHAR_HEIGHT, FONT_CHAR_WIDTH};
+use slopos_abi::task::CpuUsage;
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::gfx::{self, DamageRect, DrawBuffer};
+use crate::syscall::core as sys_core;
+use crate::theme::*;
+
+/// Time between samples.
+const SAMPLE_MS: u64 = 1_000;
+/// CPUs given a bar; the overall figure still counts the rest.
+const METER_MAX_CPUS: usize = 16;
+const METER_MARGIN: i32 = 8;
+const BAR_WIDTH: i32 = 4;
+const BAR_GAP: i32 = 2;
+/// Room for "100%".
+const LABEL_CHARS: i32 = 4;
+
+const COLOR_BAR_TRACK: Color32 = Color32::rgb(0x1A, 0x1A, 0x1C);
+const COLOR_BAR_LOW: Color32 = Color32::rgb(0x3C, 0xB3, 0x71);
+const COLOR_BAR_HIGH: Color32 = Color32::rgb(0xE8, 0x11, 0x23);
+
+pub struct CpuMeter {
+    samples: [CpuUsage; METER_MAX_CPUS],
+    cpu_count: usize,
+    percents: [u8; METER_MAX_CPUS],
+    overall: u8,
+    /// Uptime of the next sample; 0 before the first.
+    next_sample_ms: u64,
+}
+
+impl CpuMeter {
+    pub const fn new() -> Self {
+        Self {
+            samples: [CpuUsage {
+                cpu: 0,
+                ready: 0,
+                busy_cycles: 0,
+                idle_cycles: 0,
+                ticks: 0,
+                switches: 0,
+                preemptions: 0,
+            }; METER_MAX_CPUS],
+            cpu_count: 0,
+            percents: [0; METER_MAX_CPUS],
+            overall: 0,
+            next_sample_ms: 0,
+        }
+    }
+
+    /// Take a sample if one is due.  Returns true if what the meter shows
+    /// changed.
+    pub fn update(&mut self, now_ms: u64) -> bool {
+        if now_ms < self.next_sample_ms {
+            return false;
+        }
+        self.next_sample_ms = now_ms + SAMPLE_MS;
+
+        let mut current = [CpuUsage::default(); METER_MAX_CPUS];
+        let Ok(count) = sys_core::cpu_usage(&mut current) else {
+            return false;
+        };
+        let first = self.cpu_count == 0;
+        let mut percents = [0u8; METER_MAX_CPUS];
+        let (mut busy, mut total) = (0u128, 0u128);
+        for i in 0..count {
+            let (now, then) = (&current[i], &self.samples[i]);
+            percents[i] = now.busy_percent_since(then) as u8;
+            let cpu_busy = now.busy_cycles.saturating_sub(then.busy_cycles) as u128;
+            busy += cpu_busy;
+            total += cpu_busy + now.idle_cycles.saturating_sub(then.idle_cycles) as u128;
+        }
+        self.samples = current;
+        if first {
+            // The first sample only sets the baseline.
+            self.cpu_count = count;
+            return count != 0;
+        }
+        let overall = if total == 0 {
+            0
+        } else {
+            (busy * 100 / total) as u8
+        };
+        let changed =
+            count != self.cpu_count || percents != self.percents || overall != self.overall;
+        self.cpu_count = count;
+        self.percents = percents;
+        self.overall = overall;
+        changed
+    }
+
+    /// When the next sample is due.
+    pub fn next_sample_ms(&self) -> u64 {
+        self.next_sample_ms
+    }
+
+    /// Where the meter is on a `fb_width` x `fb_height` screen.
+    pub fn bounds(&self, fb_width: i32, fb_height: i32) -> DamageRect {
+        let bars = self.cpu_count as i32 * (BAR_WIDTH + BAR_GAP);
+        let width = bars + LABEL_CHARS * FONT_CHAR_WIDTH;
+        let x1 = fb_width - METER_MARGIN - 1;
+        DamageRect {
+            x0: x1 - width + 1,
+            y0: fb_height - TASKBAR_HEIGHT + TASKBAR_BUTTON_PADDING,
+            x1,
+            y1: fb_height - TASKBAR_BUTTON_PADDING - 1,
+        }
+    }
+
+    pub fn draw(&self, buf: &mut DrawBuffer, clip: &DamageRect) {
+        if self.cpu_count == 0 {
+            return;
+        }
+        let r = self.bounds(buf.width() as i32, buf.height() as i32);
+        let height = r.y1 - r.y0 + 1;
+        let mut x = r.x0;
+        for &percent in &self.percents[..self.cpu_count] {
+            let fill = height * percent as i32 / 100;
+            let color = if percent >= 80 {
+                COLOR_BAR_HIGH
+            } else {
+                COLOR_BAR_LOW
+            };
+            gfx::fill_rect_clipped(
+                buf,
+                x,
+                r.y0,
+                BAR_WIDTH,
+                height - fill,
+                COLOR_BAR_TRACK,
+                clip,
+            );
+            gfx::fill_rect_clipped(buf, x, r.y1 - fill + 1, BAR_WIDTH, fill, color, clip);
+            x += BAR_WIDTH + BAR_GAP;
+        }
+
+        let mut num = NumBuf::<4>::new();
+        let digits = numfmt::trim_nul(num.format_u64(self.overall as u64));
+        let mut label = [b' '; LABEL_CHARS as usize];
+        let start = label.len() - 1 - digits.len();
+        label[start..start + digits.len()].copy_from_slice(digits);
+        label[LABEL_CHARS as usize - 1] = b'%';
+        gfx::draw_str_clipped(
+            buf,
+            x,
+            r.y0 + (height - FONT_CHAR_HEIGHT) / 2,
+            core::str::from_utf8(&label).unwrap_or(""),
+            COLOR_TEXT,
+            COLOR_TASKBAR,
+            clip,
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Pointer cursor shapes, and the display's hardware cursor plane.
+//!
+//! With a cursor plane the cursor is uploaded once per shape and moved with
+//! `cursor_move`, so pointer motion damages nothing.  Without one (plain
+//! framebuffer, virtio-gpu) the renderer draws it into every frame.
+
+use slopos_abi::window::{CURSOR_IMAGE_BYTES, CURSOR_IMAGE_SIZE, CURSOR_SHAPE_TEXT};
+
+use crate::gfx::{self, DamageRect, DrawBuffer};
+use crate::syscall::window;
+use crate::theme::*;
+
+/// Where the hotspot sits in an uploaded cursor image; every shape fits
+/// within this margin around it.
+const CURSOR_HOTSPOT: u32 = 16;
+
+pub fn draw_cursor(buf: &mut DrawBuffer, mx: i32, my: i32, cursor_shape: u8, clip: &DamageRect) {
+    match cursor_shape {
+        CURSOR_SHAPE_TEXT => draw_cursor_text(buf, mx, my, clip),
+        _ => draw_cursor_default(buf, mx, my, clip),
+    }
+}
+
+fn draw_cursor_default(buf: &mut DrawBuffer, mx: i32, my: i32, clip: &DamageRect) {
+    const CURSOR_SIZE: i32 = 9;
+    gfx::fill_rect_clipped(buf, mx - 4, my, CURSOR_SIZE, 1, COLOR_CURSOR, clip);
+    gfx::fill_rect_clipped(buf, mx, my - 4, 1, CURSOR_SIZE, COLOR_CURSOR, clip);
+}
+
+fn draw_cursor_text(buf: &mut DrawBuffer, mx: i32, my: i32, clip: &DamageRect) {
+    const BEAM_HEIGHT: i32 = 16;
+    const SERIF_WIDTH: i32 = 5;
+    let top = my - BEAM_HEIGHT / 2;
+    gfx::fill_rect_clipped(buf, mx, top, 1, BEAM_HEIGHT, COLOR_CURSOR, clip);
+    gfx::fill_rect_clipped(
+        buf,
+        mx - SERIF_WIDTH / 2,
+        top,
+        SERIF_WIDTH,
+        1,
+        COLOR_CURSOR,
+        clip,
+    );
+    gfx::fill_rect_clipped(
+        buf,
+        mx - SERIF_WIDTH / 2,
+        top + BEAM_HEIGHT - 1,
+        SERIF_WIDTH,
+        1,
+        COLOR_CURSOR,
+        clip,
+    );
+}
+
+pub fn cursor_bounds(mx: i32, my: i32, cursor_shape: u8) -> DamageRect {
+    match cursor_shape {
+        CURSOR_SHAPE_TEXT => DamageRect {
+            x0: mx - 2,
+            y0: my - 8,
+            x1: mx + 2,
+            y1: my + 7,
+        },
+        _ => DamageRect {
+            x0: mx - 4,
+            y0: my - 4,
+            x1: mx + 4,
+            y1: my + 4,
+        },
+    }
+}
+
+/// Draw `cursor_shape` into a cursor plane image and upload it.
+fn upload_shape(cursor_shape: u8) -> bool {
+    let mut image = [0u8; CURSOR_IMAGE_BYTES];
+    let size = CURSOR_IMAGE_SIZE;
+    let Some(mut buf) = DrawBuffer::new(&mut image, size, size, size as usize * 4, 4) else {
+        return false;
+    };
+    let clip = DamageRect {
+        x0: 0,
+        y0: 0,
+        x1: size as i32 - 1,
+        y1: size as i32 - 1,
+    };
+    let hot = CURSOR_HOTSPOT as i32;
+    draw_cursor(&mut buf, hot, hot, cursor_shape, &clip);
+    window::cursor_set_image(&image, CURSOR_HOTSPOT, CURSOR_HOTSPOT).is_ok()
+}
+
+/// The cursor as shown on the hardware cursor plane.
+pub struct HardwareCursor {
+    shape: u8,
+    x: i32,
+    y: i32,
+}
+
+impl HardwareCursor {
+    /// Put the cursor on the cursor plane, if the display has one.
+    pub fn new(cursor_shape: u8, x: i32, y: i32) -> Option<Self> {
+        if !upload_shape(cursor_shape) {
+            return None;
+        }
+        window::cursor_move(x, y);
+        Some(Self {
+            shape: cursor_shape,
+            x,
+            y,
+        })
+    }
+
+    /// Follow the pointer and its shape.  Returns false if the plane
+    /// refused a new shape, after which the caller draws the cursor.
+    pub fn update(&mut self, x: i32, y: i32, cursor_shape: u8) -> bool {
+        if cursor_shape != self.shape {
+            if !upload_shape(cursor_shape) {
+                return false;
+            }
+            self.shape = cursor_shape;
+        }
+        if (x, y) != (self.x, self.y) {
+            window::cursor_move(x, y);
+            (self.x, self.y) = (x, y);
+        }
+        true
+    }
+}
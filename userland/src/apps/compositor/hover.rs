@@ -2,8 +2,9 @@
 //!
 //! Each frame the compositor registers interactive regions with their hit-test
 //! results. The registry auto-diffs against the previous frame and reports
-//! damage rects for regions whose hover state changed.
+//! damage rects for regions whose hover or pressed state changed.
 
+use crate::decorations::ButtonState;
 use crate::gfx::DamageRect;
 
 /// Maximum number of interactive regions tracked per frame.
@@ -24,6 +25,7 @@ struct HoverRegion {
     id: u32,
     rect: DamageRect,
     hovered: bool,
+    pressed: bool,
 }
 
 impl HoverRegion {
@@ -32,6 +34,7 @@ impl HoverRegion {
             id: 0,
             rect: DamageRect::invalid(),
             hovered: false,
+            pressed: false,
         }
     }
 }
@@ -64,10 +67,29 @@ impl HoverRegistry {
 
     /// Register an interactive region for the current frame.
     pub fn register(&mut self, id: u32, rect: DamageRect, hovered: bool) {
+        self.push(HoverRegion {
+            id,
+            rect,
+            hovered,
+            pressed: false,
+        });
+    }
+
+    /// Register a button that can also be held down.
+    pub fn register_button(&mut self, id: u32, rect: DamageRect, state: ButtonState) {
+        self.push(HoverRegion {
+            id,
+            rect,
+            hovered: state != ButtonState::Normal,
+            pressed: state == ButtonState::Pressed,
+        });
+    }
+
+    fn push(&mut self, region: HoverRegion) {
         if self.current_count >= MAX_HOVER_REGIONS {
             return;
         }
-        self.current[self.current_count] = HoverRegion { id, rect, hovered };
+        self.current[self.current_count] = region;
         self.current_count += 1;
     }
 
@@ -81,9 +103,18 @@ impl HoverRegistry {
         false
     }
 
+    /// How a button registered this frame should be drawn.
+    pub fn button_state(&self, id: u32) -> ButtonState {
+        match self.find_current(id) {
+            Some(region) if region.pressed => ButtonState::Pressed,
+            Some(region) if region.hovered => ButtonState::Hovered,
+            _ => ButtonState::Normal,
+        }
+    }
+
     /// Diff current vs previous frame and write damage rects for regions whose
-    /// hover state changed, appeared while hovered, or disappeared while hovered.
-    /// Returns the number of rects written.
+    /// hover or pressed state changed, appeared while hovered, or disappeared
+    /// while hovered.  Returns the number of rects written.
     pub fn changed_regions(&self, out: &mut [DamageRect]) -> usize {
         let mut count = 0usize;
 
@@ -92,7 +123,7 @@ impl HoverRegistry {
             let cur = &self.current[i];
             match self.find_previous(cur.id) {
                 Some(prev) => {
-                    if cur.hovered != prev.hovered {
+                    if cur.hovered != prev.hovered || cur.pressed != prev.pressed {
                         if count < out.len() && prev.rect.is_valid() {
                             out[count] = prev.rect;
                             count += 1;
@@ -1,8 +1,19 @@
+use slopos_abi::{
+    KEY_MOD_SUPER, SCANCODE_DOWN, SCANCODE_LEFT, SCANCODE_RIGHT, SCANCODE_UP,
+    WINDOW_STATE_MAXIMIZED, WINDOW_STATE_TILED_LEFT, WINDOW_STATE_TILED_RIGHT,
+    window_state_is_fitted,
+};
+
+use crate::decorations::{DecorationPart, decorations};
+use crate::gfx::DamageRect;
 use crate::program_registry;
-use crate::syscall::{UserWindowInfo, core as sys_core, input, process, tty, window};
+use crate::syscall::{
+    InputEvent, InputEventType, UserWindowInfo, core as sys_core, input, process, tty, window,
+};
 use crate::theme::*;
 
 use super::MAX_WINDOWS;
+use super::layout::{self, OutputLayout};
 use super::output::WINDOW_STATE_MINIMIZED;
 use super::taskbar::{self, START_MENU_ITEMS};
 
@@ -10,6 +21,33 @@ const WINDOW_STATE_NORMAL: u8 = 0;
 const CLOSE_REQUEST_GRACE_MS: u64 = 1500;
 const MAX_CURSOR_TRAIL: usize = 16;
 
+const EDGE_LEFT: u8 = 1 << 0;
+const EDGE_RIGHT: u8 = 1 << 1;
+const EDGE_TOP: u8 = 1 << 2;
+const EDGE_BOTTOM: u8 = 1 << 3;
+
+/// How close to an output's edge a dragged window snaps: the top edge
+/// maximizes it, the sides tile it.
+const SNAP_EDGE: i32 = 4;
+/// How far a maximized or tiled window is dragged before it floats again.
+const UNSNAP_DISTANCE: i32 = 8;
+
+/// A drag-resize in progress: which edges move, and where the pointer and
+/// the window's content area were when it started.
+#[derive(Clone, Copy)]
+struct ResizeDrag {
+    task_id: u32,
+    edges: u8,
+    start_mouse_x: i32,
+    start_mouse_y: i32,
+    start: DamageRect,
+    /// Height of the window's title bar, zero when undecorated.
+    top_inset: i32,
+    content_scale: u8,
+    /// The window was maximized or tiled; resizing floats it.
+    fitted: bool,
+}
+
 pub struct InputHandler {
     pub mouse_x: i32,
     pub mouse_y: i32,
@@ -18,8 +56,20 @@ pub struct InputHandler {
 
     pub dragging: bool,
     drag_task: u32,
+    drag_scale: u8,
     drag_offset_x: i32,
     drag_offset_y: i32,
+    drag_start_x: i32,
+    drag_start_y: i32,
+    /// Geometry to float a dragged maximized or tiled window at, until the
+    /// pointer pulls it loose.
+    drag_fitted: Option<DamageRect>,
+
+    resize: Option<ResizeDrag>,
+
+    /// A title bar button held down, as its window's task and the button.
+    /// It acts when released over the same button.
+    pressed_button: Option<(u32, DecorationPart)>,
 
     pub start_menu_open: bool,
     pub focused_task: u32,
@@ -42,8 +92,14 @@ impl InputHandler {
             mouse_buttons_prev: 0,
             dragging: false,
             drag_task: 0,
+            drag_scale: 1,
             drag_offset_x: 0,
             drag_offset_y: 0,
+            drag_start_x: 0,
+            drag_start_y: 0,
+            drag_fitted: None,
+            resize: None,
+            pressed_button: None,
             start_menu_open: false,
             focused_task: 0,
             needs_full_redraw: false,
@@ -83,6 +139,14 @@ impl InputHandler {
         (self.mouse_buttons & 0x01) != 0
     }
 
+    /// Whether the left button went down this frame inside `rect`.
+    pub fn clicked_in(&self, rect: &DamageRect) -> bool {
+        !self.dragging
+            && self.mouse_clicked()
+            && (rect.x0..=rect.x1).contains(&self.mouse_x)
+            && (rect.y0..=rect.y1).contains(&self.mouse_y)
+    }
+
     /// Update pointer focus to the topmost visible window under the cursor.
     ///
     /// Following the Wayland compositor pattern (wlroots `tinywl.c`), pointer
@@ -100,15 +164,100 @@ impl InputHandler {
                 continue;
             }
             if self.hit_test_content_area(&window) {
-                input::set_pointer_focus_with_offset(window.task_id, window.x, window.y);
+                input::set_pointer_focus_with_offset(
+                    window.task_id,
+                    window.x,
+                    window.y,
+                    window.content_scale as u32,
+                );
                 return;
             }
         }
         input::set_pointer_focus(0);
     }
 
+    /// Apply Super+Arrow shortcuts to the focused window: Up maximizes,
+    /// Left and Right tile, and Down restores a maximized or tiled window
+    /// or minimizes a floating one.  Tiling towards the other side restores.
+    pub fn handle_shortcuts(
+        &mut self,
+        layout: &OutputLayout,
+        fb_height: i32,
+        windows: &[UserWindowInfo; MAX_WINDOWS],
+        window_count: u32,
+    ) {
+        let mut events = [InputEvent::default(); 8];
+        loop {
+            let count = (input::poll_batch(&mut events) as usize).min(events.len());
+            for event in &events[..count] {
+                if event.event_type == InputEventType::KeyPress
+                    && event.key_modifiers() & KEY_MOD_SUPER != 0
+                {
+                    self.apply_shortcut(
+                        event.key_scancode(),
+                        layout,
+                        fb_height,
+                        windows,
+                        window_count,
+                    );
+                }
+            }
+            if count < events.len() {
+                break;
+            }
+        }
+    }
+
+    fn apply_shortcut(
+        &mut self,
+        scancode: u8,
+        layout: &OutputLayout,
+        fb_height: i32,
+        windows: &[UserWindowInfo; MAX_WINDOWS],
+        window_count: u32,
+    ) {
+        let Some(w) = windows[..window_count as usize]
+            .iter()
+            .find(|w| w.task_id == self.focused_task && w.state != WINDOW_STATE_MINIMIZED)
+        else {
+            return;
+        };
+
+        let target = match (scancode, w.state) {
+            (SCANCODE_UP, _) => Some(WINDOW_STATE_MAXIMIZED),
+            (SCANCODE_LEFT, WINDOW_STATE_TILED_RIGHT)
+            | (SCANCODE_RIGHT, WINDOW_STATE_TILED_LEFT) => None,
+            (SCANCODE_LEFT, _) => Some(WINDOW_STATE_TILED_LEFT),
+            (SCANCODE_RIGHT, _) => Some(WINDOW_STATE_TILED_RIGHT),
+            (SCANCODE_DOWN, state) if window_state_is_fitted(state) => None,
+            (SCANCODE_DOWN, _) => {
+                window::set_window_state(w.task_id, WINDOW_STATE_MINIMIZED);
+                self.needs_full_redraw = true;
+                return;
+            }
+            _ => return,
+        };
+
+        match target {
+            Some(state) => {
+                let Some(output) = layout.output_of(w) else {
+                    return;
+                };
+                fit_window(
+                    w.task_id,
+                    w.content_scale,
+                    state,
+                    &layout::fitted_rect(output, state, fb_height),
+                );
+            }
+            None => restore_window(w),
+        }
+        self.needs_full_redraw = true;
+    }
+
     pub fn handle_mouse_events(
         &mut self,
+        layout: &OutputLayout,
         fb_height: i32,
         windows: &[UserWindowInfo; MAX_WINDOWS],
         window_count: u32,
@@ -117,13 +266,38 @@ impl InputHandler {
 
         if self.dragging {
             if !self.mouse_pressed() {
-                self.stop_drag();
+                self.stop_drag(layout, fb_height);
             } else {
                 self.update_drag();
             }
             return;
         }
 
+        if self.resize.is_some() {
+            if !self.mouse_pressed() {
+                self.finish_resize();
+            } else {
+                // The outline follows the pointer.
+                self.needs_full_redraw = true;
+            }
+            return;
+        }
+
+        if let Some((task_id, part)) = self.pressed_button {
+            if self.mouse_pressed() {
+                return;
+            }
+            self.pressed_button = None;
+            let released_over = windows[..window_count as usize]
+                .iter()
+                .find(|w| w.task_id == task_id)
+                .is_some_and(|w| self.hit_test_decoration(w) == Some(part));
+            if released_over {
+                self.activate_button(task_id, part, windows, window_count);
+            }
+            return;
+        }
+
         if !clicked {
             return;
         }
@@ -154,14 +328,19 @@ impl InputHandler {
                 continue;
             }
 
-            if self.hit_test_title_bar(&window) {
-                if self.hit_test_close_button(&window) {
-                    self.request_window_close(window.task_id, windows, window_count);
-                    return;
-                }
+            let edges = self.hit_test_resize_edges(&window);
+            if edges != 0 {
+                self.start_resize(&window, edges);
+                window::raise_window(window.task_id);
+                tty::set_focus(window.task_id);
+                input::set_keyboard_focus(window.task_id);
+                self.focused_task = window.task_id;
+                return;
+            }
 
-                if self.hit_test_minimize_button(&window) {
-                    window::set_window_state(window.task_id, WINDOW_STATE_MINIMIZED);
+            if let Some(part) = self.hit_test_decoration(&window) {
+                if part != DecorationPart::TitleBar {
+                    self.pressed_button = Some((window.task_id, part));
                     return;
                 }
 
@@ -177,7 +356,12 @@ impl InputHandler {
                 window::raise_window(window.task_id);
                 tty::set_focus(window.task_id);
                 input::set_keyboard_focus(window.task_id);
-                input::set_pointer_focus_with_offset(window.task_id, window.x, window.y);
+                input::set_pointer_focus_with_offset(
+                    window.task_id,
+                    window.x,
+                    window.y,
+                    window.content_scale as u32,
+                );
                 self.focused_task = window.task_id;
                 return;
             }
@@ -221,30 +405,44 @@ impl InputHandler {
             && self.mouse_y < window.y + window.height as i32
     }
 
-    pub fn hit_test_title_bar(&self, window: &UserWindowInfo) -> bool {
-        let title_y = window.y - TITLE_BAR_HEIGHT;
-        self.mouse_x >= window.x
-            && self.mouse_x < window.x + window.width as i32
-            && self.mouse_y >= title_y
-            && self.mouse_y < window.y
+    /// The part of `window`'s decorations under the pointer, if any.
+    pub fn hit_test_decoration(&self, window: &UserWindowInfo) -> Option<DecorationPart> {
+        decorations().hit_test(window, self.mouse_x, self.mouse_y)
     }
 
-    pub fn hit_test_close_button(&self, window: &UserWindowInfo) -> bool {
-        let button_x = window.x + window.width as i32 - BUTTON_SIZE - BUTTON_PADDING;
-        let button_y = window.y - TITLE_BAR_HEIGHT + BUTTON_PADDING;
-        self.mouse_x >= button_x
-            && self.mouse_x < button_x + BUTTON_SIZE
-            && self.mouse_y >= button_y
-            && self.mouse_y < button_y + BUTTON_SIZE
+    /// The title bar button held down, as its window's task and the button.
+    pub fn pressed_button(&self) -> Option<(u32, DecorationPart)> {
+        self.pressed_button
     }
 
-    pub fn hit_test_minimize_button(&self, window: &UserWindowInfo) -> bool {
-        let button_x = window.x + window.width as i32 - (BUTTON_SIZE * 2) - (BUTTON_PADDING * 2);
-        let button_y = window.y - TITLE_BAR_HEIGHT + BUTTON_PADDING;
-        self.mouse_x >= button_x
-            && self.mouse_x < button_x + BUTTON_SIZE
-            && self.mouse_y >= button_y
-            && self.mouse_y < button_y + BUTTON_SIZE
+    /// Which edges of `window`'s frame the pointer is on, as `EDGE_*` bits.
+    /// The grab band lies just outside the frame, so it never takes clicks
+    /// from the content or the title bar.
+    fn hit_test_resize_edges(&self, window: &UserWindowInfo) -> u8 {
+        let left = window.x;
+        let right = window.x + window.width as i32;
+        let top = window.y - decorations().top_inset(window);
+        let bottom = window.y + window.height as i32;
+        if self.mouse_x < left - RESIZE_BORDER
+            || self.mouse_x >= right + RESIZE_BORDER
+            || self.mouse_y < top - RESIZE_BORDER
+            || self.mouse_y >= bottom + RESIZE_BORDER
+        {
+            return 0;
+        }
+
+        let mut edges = 0;
+        if self.mouse_x < left {
+            edges |= EDGE_LEFT;
+        } else if self.mouse_x >= right {
+            edges |= EDGE_RIGHT;
+        }
+        if self.mouse_y < top {
+            edges |= EDGE_TOP;
+        } else if self.mouse_y >= bottom {
+            edges |= EDGE_BOTTOM;
+        }
+        edges
     }
 
     pub fn hit_test_start_button(&self, fb_height: i32) -> bool {
@@ -288,22 +486,161 @@ impl InputHandler {
     fn start_drag(&mut self, window: &UserWindowInfo) {
         self.dragging = true;
         self.drag_task = window.task_id;
+        self.drag_scale = window.content_scale;
         self.drag_offset_x = self.mouse_x - window.x;
         self.drag_offset_y = self.mouse_y - window.y;
+        self.drag_start_x = self.mouse_x;
+        self.drag_start_y = self.mouse_y;
+        self.drag_fitted = window_state_is_fitted(window.state)
+            .then(|| window.restore_bounds().unwrap_or(window.bounds()));
     }
 
-    fn stop_drag(&mut self) {
+    fn stop_drag(&mut self, layout: &OutputLayout, fb_height: i32) {
+        if let Some((state, rect)) = self.snap_target(layout, fb_height) {
+            fit_window(self.drag_task, self.drag_scale, state, &rect);
+        }
         self.dragging = false;
         self.drag_task = 0;
+        self.drag_fitted = None;
+    }
+
+    /// Where the dragged window would snap if dropped now, as its fitted
+    /// state and content area.
+    fn snap_target(&self, layout: &OutputLayout, fb_height: i32) -> Option<(u8, DamageRect)> {
+        if !self.dragging || self.drag_fitted.is_some() {
+            return None;
+        }
+        let output = layout.output_at(self.mouse_x, self.mouse_y)?;
+        let state = if self.mouse_y < output.y + SNAP_EDGE {
+            WINDOW_STATE_MAXIMIZED
+        } else if self.mouse_x < output.x + SNAP_EDGE {
+            WINDOW_STATE_TILED_LEFT
+        } else if self.mouse_x >= output.x + output.width as i32 - SNAP_EDGE {
+            WINDOW_STATE_TILED_RIGHT
+        } else {
+            return None;
+        };
+        Some((state, layout::fitted_rect(output, state, fb_height)))
+    }
+
+    /// The frame, title bar included, that a drag-resize would leave its
+    /// window in, or that a dragged window would fill if dropped over a
+    /// snap edge now.
+    pub fn outline(&self, layout: &OutputLayout, fb_height: i32) -> Option<DamageRect> {
+        let (mut rect, top_inset) = match self.resize {
+            Some(drag) => (self.resize_outline()?, drag.top_inset),
+            // Only decorated windows are dragged by their title bar.
+            None => (
+                self.snap_target(layout, fb_height)?.1,
+                decorations().metrics.title_bar_height,
+            ),
+        };
+        rect.y0 -= top_inset;
+        Some(rect)
     }
 
     fn update_drag(&mut self) {
+        if let Some(restore) = self.drag_fitted {
+            let moved_x = (self.mouse_x - self.drag_start_x).abs();
+            let moved_y = (self.mouse_y - self.drag_start_y).abs();
+            if moved_x < UNSNAP_DISTANCE && moved_y < UNSNAP_DISTANCE {
+                return;
+            }
+            // Float at the old size with the grab point still on the title bar.
+            self.drag_fitted = None;
+            let width = restore.x1 - restore.x0 + 1;
+            let height = restore.y1 - restore.y0 + 1;
+            self.drag_offset_x = self.drag_offset_x.min(width / 2);
+            window::set_window_state(self.drag_task, WINDOW_STATE_NORMAL);
+            configure_window(self.drag_task, self.drag_scale, width as u32, height as u32);
+        }
         let new_x = self.mouse_x - self.drag_offset_x;
         let new_y = self.mouse_y - self.drag_offset_y;
         window::set_window_position(self.drag_task, new_x, new_y);
         self.needs_full_redraw = true;
     }
 
+    fn start_resize(&mut self, window: &UserWindowInfo, edges: u8) {
+        self.resize = Some(ResizeDrag {
+            task_id: window.task_id,
+            edges,
+            start_mouse_x: self.mouse_x,
+            start_mouse_y: self.mouse_y,
+            start: DamageRect {
+                x0: window.x,
+                y0: window.y,
+                x1: window.x + window.width as i32 - 1,
+                y1: window.y + window.height as i32 - 1,
+            },
+            top_inset: decorations().top_inset(window),
+            content_scale: window.content_scale,
+            fitted: window_state_is_fitted(window.state),
+        });
+        self.needs_full_redraw = true;
+    }
+
+    /// The content area the window would have if the resize ended now.
+    fn resize_outline(&self) -> Option<DamageRect> {
+        let drag = self.resize?;
+        let dx = self.mouse_x - drag.start_mouse_x;
+        let dy = self.mouse_y - drag.start_mouse_y;
+        let mut rect = drag.start;
+        if drag.edges & EDGE_LEFT != 0 {
+            rect.x0 = (rect.x0 + dx).min(rect.x1 + 1 - MIN_WINDOW_WIDTH);
+        }
+        if drag.edges & EDGE_RIGHT != 0 {
+            rect.x1 = (rect.x1 + dx).max(rect.x0 + MIN_WINDOW_WIDTH - 1);
+        }
+        if drag.edges & EDGE_TOP != 0 {
+            rect.y0 = (rect.y0 + dy).min(rect.y1 + 1 - MIN_WINDOW_HEIGHT);
+        }
+        if drag.edges & EDGE_BOTTOM != 0 {
+            rect.y1 = (rect.y1 + dy).max(rect.y0 + MIN_WINDOW_HEIGHT - 1);
+        }
+        Some(rect)
+    }
+
+    /// Move the window to the outline and ask its client to redraw at the
+    /// new size; the window keeps its old buffer until the client attaches
+    /// a new one.
+    fn finish_resize(&mut self) {
+        let (Some(drag), Some(rect)) = (self.resize, self.resize_outline()) else {
+            return;
+        };
+        self.resize = None;
+        self.needs_full_redraw = true;
+        if drag.fitted {
+            window::set_window_state(drag.task_id, WINDOW_STATE_NORMAL);
+        }
+
+        let width = (rect.x1 - rect.x0 + 1) as u32;
+        let height = (rect.y1 - rect.y0 + 1) as u32;
+        if (rect.x0, rect.y0) != (drag.start.x0, drag.start.y0) {
+            window::set_window_position(drag.task_id, rect.x0, rect.y0);
+        }
+        let start_width = (drag.start.x1 - drag.start.x0 + 1) as u32;
+        let start_height = (drag.start.y1 - drag.start.y0 + 1) as u32;
+        if (width, height) != (start_width, start_height) {
+            configure_window(drag.task_id, drag.content_scale, width, height);
+        }
+    }
+
+    fn activate_button(
+        &mut self,
+        task_id: u32,
+        part: DecorationPart,
+        windows: &[UserWindowInfo; MAX_WINDOWS],
+        window_count: u32,
+    ) {
+        match part {
+            DecorationPart::Close => self.request_window_close(task_id, windows, window_count),
+            DecorationPart::Minimize => {
+                window::set_window_state(task_id, WINDOW_STATE_MINIMIZED);
+            }
+            DecorationPart::TitleBar => {}
+        }
+    }
+
     fn request_window_close(
         &mut self,
         task_id: u32,
@@ -333,6 +670,14 @@ impl InputHandler {
         self.needs_full_redraw = true;
     }
 
+    /// When the earliest pending close request runs out of grace time.
+    pub fn next_close_deadline(&self) -> Option<u64> {
+        self.pending_close_deadlines[..self.pending_close_count]
+            .iter()
+            .copied()
+            .min()
+    }
+
     fn pending_close_index(&self, task_id: u32) -> Option<usize> {
         (0..self.pending_close_count).find(|&i| self.pending_close_tasks[i] == task_id)
     }
@@ -444,6 +789,35 @@ impl InputHandler {
     }
 }
 
+/// Put a window in fitted `state` and move and resize it to `rect`.  The
+/// kernel saves the floating geometry on the state change, so that goes
+/// first.
+fn fit_window(task_id: u32, content_scale: u8, state: u8, rect: &DamageRect) {
+    window::set_window_state(task_id, state);
+    window::set_window_position(task_id, rect.x0, rect.y0);
+    let width = (rect.x1 - rect.x0 + 1) as u32;
+    let height = (rect.y1 - rect.y0 + 1) as u32;
+    configure_window(task_id, content_scale, width, height);
+}
+
+/// Float a maximized or tiled window at the geometry it had before.
+fn restore_window(w: &UserWindowInfo) {
+    window::set_window_state(w.task_id, WINDOW_STATE_NORMAL);
+    if let Some(rect) = w.restore_bounds() {
+        window::set_window_position(w.task_id, rect.x0, rect.y0);
+        let width = (rect.x1 - rect.x0 + 1) as u32;
+        let height = (rect.y1 - rect.y0 + 1) as u32;
+        configure_window(w.task_id, w.content_scale, width, height);
+    }
+}
+
+/// Ask a window magnified `content_scale` times to redraw at `width` x
+/// `height` desktop pixels, which is that over the scale in its own.
+fn configure_window(task_id: u32, content_scale: u8, width: u32, height: u32) {
+    let scale = content_scale.max(1) as u32;
+    let _ = input::request_configure(task_id, (width / scale).max(1), (height / scale).max(1));
+}
+
 fn window_exists(windows: &[UserWindowInfo; MAX_WINDOWS], count: u32, task_id: u32) -> bool {
     (0..count as usize).any(|i| windows[i].task_id == task_id)
 }
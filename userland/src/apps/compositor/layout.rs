@@ -0,0 +1,121 @@
+//! Output layout: which rectangles of the desktop each monitor shows.
+//!
+//! The compositor renders one desktop-sized buffer; outputs scan out parts
+//! of it.  Windows open on the output under the pointer, and a window whose
+//! title bar ends up on no output (an output moved or went away) is moved
+//! onto the primary one.  Maximized and tiled windows fill all or half of
+//! their output's work area: the output minus the taskbar.
+
+use slopos_abi::display::MAX_OUTPUTS;
+use slopos_abi::{OutputInfo, WINDOW_STATE_MAXIMIZED, WINDOW_STATE_TILED_LEFT};
+
+use crate::decorations::decorations;
+use crate::gfx::DamageRect;
+use crate::syscall::{UserWindowInfo, window};
+use crate::theme::TASKBAR_HEIGHT;
+
+/// Where a rescued window lands, from its output's top-left corner.
+const RESCUE_OFFSET: i32 = 50;
+
+pub struct OutputLayout {
+    outputs: [OutputInfo; MAX_OUTPUTS],
+    count: usize,
+}
+
+impl OutputLayout {
+    pub fn new() -> Self {
+        Self {
+            outputs: [OutputInfo::default(); MAX_OUTPUTS],
+            count: 0,
+        }
+    }
+
+    /// Re-read the outputs.  Returns true if the layout changed.
+    pub fn refresh(&mut self) -> bool {
+        let mut outputs = [OutputInfo::default(); MAX_OUTPUTS];
+        let count = window::output_enumerate(&mut outputs).min(MAX_OUTPUTS);
+        if count == self.count && outputs[..count] == self.outputs[..count] {
+            return false;
+        }
+        self.outputs = outputs;
+        self.count = count;
+        true
+    }
+
+    pub fn outputs(&self) -> &[OutputInfo] {
+        &self.outputs[..self.count]
+    }
+
+    pub fn output_at(&self, x: i32, y: i32) -> Option<&OutputInfo> {
+        self.outputs().iter().find(|o| o.contains(x, y))
+    }
+
+    pub fn primary(&self) -> Option<&OutputInfo> {
+        self.outputs()
+            .iter()
+            .find(|o| o.is_primary())
+            .or(self.outputs().first())
+    }
+
+    /// The output showing `w`'s title bar, or the primary output.
+    pub fn output_of(&self, w: &UserWindowInfo) -> Option<&OutputInfo> {
+        self.output_at(w.x, title_bar_y(w)).or(self.primary())
+    }
+
+    /// Move a window the kernel just placed onto the output under the
+    /// pointer, keeping its offset within the output it was placed on.
+    pub fn place_new_window(&self, w: &UserWindowInfo, pointer_x: i32, pointer_y: i32) {
+        let (Some(target), Some(placed)) = (
+            self.output_at(pointer_x, pointer_y),
+            self.output_at(w.x, title_bar_y(w)),
+        ) else {
+            return;
+        };
+        if target.id != placed.id {
+            window::set_window_position(
+                w.task_id,
+                target.x + (w.x - placed.x),
+                target.y + (w.y - placed.y),
+            );
+        }
+    }
+
+    /// Move a window whose title bar is on no output onto the primary one.
+    pub fn rescue_window(&self, w: &UserWindowInfo) {
+        if self.output_at(w.x, title_bar_y(w)).is_some() {
+            return;
+        }
+        let Some(primary) = self.primary() else {
+            return;
+        };
+        window::set_window_position(
+            w.task_id,
+            primary.x + RESCUE_OFFSET,
+            primary.y + RESCUE_OFFSET + decorations().metrics.title_bar_height,
+        );
+    }
+}
+
+fn title_bar_y(w: &UserWindowInfo) -> i32 {
+    w.y - decorations().top_inset(w)
+}
+
+/// Content area a window in fitted `state` (`WINDOW_STATE_MAXIMIZED` or
+/// a tile) gets on `output`.  The taskbar runs along the bottom of the
+/// desktop, so it is cut from outputs that reach down to it.
+pub fn fitted_rect(output: &OutputInfo, state: u8, desktop_height: i32) -> DamageRect {
+    let bottom = (output.y + output.height as i32).min(desktop_height - TASKBAR_HEIGHT);
+    let mut rect = DamageRect {
+        x0: output.x,
+        y0: output.y + decorations().metrics.title_bar_height,
+        x1: output.x + output.width as i32 - 1,
+        y1: bottom - 1,
+    };
+    let mid = output.x + output.width as i32 / 2;
+    match state {
+        WINDOW_STATE_MAXIMIZED => {}
+        WINDOW_STATE_TILED_LEFT => rect.x1 = mid - 1,
+        _ => rect.x0 = mid,
+    }
+    rect
+}
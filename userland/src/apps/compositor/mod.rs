@@ -1,33 +1,52 @@
+mod animation;
+mod cpu_meter;
+mod cursor;
 mod hover;
 mod input;
+mod layout;
 mod output;
 mod renderer;
 mod surface_cache;
 mod taskbar;
+mod toast;
+mod wallpaper;
 
 use core::ffi::c_void;
 
-use crate::gfx::{DamageRect, DamageTracker};
+use slopos_abi::COMPOSITOR_WAIT_FOREVER;
+use slopos_abi::present::PRESENT_REFRESH_NS;
+use slopos_abi::window::CURSOR_SHAPE_DEFAULT;
+
+use crate::decorations::{self, ButtonState, DecorationPart, decorations};
+use crate::gfx::{self, DamageRect, DamageTracker};
 use crate::syscall::{
-    DisplayInfo, UserWindowInfo, core as sys_core, input as sys_input, tty, window,
+    DisplayInfo, UserWindowInfo, core as sys_core, input as sys_input, process, tty, window,
 };
 use crate::theme::*;
 
+use animation::{Animator, Fade, Transform};
+use cpu_meter::CpuMeter;
+use cursor::HardwareCursor;
 use hover::{
     HOVER_APP_BTN_BASE, HOVER_CLOSE_BASE, HOVER_MENU_ITEM_BASE, HOVER_MINIMIZE_BASE,
     HOVER_START_BTN, HoverRegistry,
 };
 use input::InputHandler;
+use layout::OutputLayout;
 use output::{
-    CompositorOutput, FrameMetrics, RenderMode, WINDOW_STATE_MINIMIZED, WindowBounds,
-    estimate_present_bytes,
+    CompositorOutput, FRAME_METRICS_REPORT_INTERVAL, FrameMetrics, RenderMode,
+    WINDOW_STATE_MINIMIZED, WindowBounds, estimate_present_bytes,
 };
 use renderer::Renderer;
 use surface_cache::ClientSurfaceCache;
 use taskbar::{START_MENU_ITEMS, TaskbarState};
+use toast::CrashToast;
 
 const MAX_WINDOWS: usize = 32;
 
+/// How long the start menu takes to fade in or out.
+const START_MENU_FADE_MS: u64 = 120;
+
 struct WindowManager {
     windows: [UserWindowInfo; MAX_WINDOWS],
     window_count: u32,
@@ -44,6 +63,16 @@ struct WindowManager {
     taskbar_needs_redraw: bool,
     output_damage: DamageTracker,
     prev_window_bounds: [WindowBounds; MAX_WINDOWS],
+    toast: CrashToast,
+    cpu_meter: CpuMeter,
+    /// The start menu fading in or out; `None` once it settles.
+    start_menu_fade: Option<Fade>,
+    /// Start menu opacity for this frame; 0 while closed.
+    start_menu_alpha: u8,
+    animator: Animator,
+    /// The cursor plane showing the cursor; `None` when we draw it.
+    hw_cursor: Option<HardwareCursor>,
+    layout: OutputLayout,
 }
 
 impl WindowManager {
@@ -62,13 +91,24 @@ impl WindowManager {
             taskbar_needs_redraw: true,
             output_damage: DamageTracker::new(),
             prev_window_bounds: [WindowBounds::default(); MAX_WINDOWS],
+            toast: CrashToast::new(),
+            cpu_meter: CpuMeter::new(),
+            start_menu_fade: None,
+            start_menu_alpha: 0,
+            animator: Animator::new(),
+            hw_cursor: None,
+            layout: OutputLayout::new(),
         }
     }
 
-    fn refresh_windows(&mut self) {
+    fn refresh_windows(&mut self, now_ms: u64) {
         self.prev_windows = self.windows;
         self.prev_window_count = self.window_count;
         let saved_bounds = self.prev_window_bounds;
+        let layout_changed = self.layout.refresh();
+        if layout_changed && !self.first_frame {
+            self.input.needs_full_redraw = true;
+        }
 
         let raw_count = window::enumerate_windows(&mut self.windows);
         self.window_count = if raw_count > 0 {
@@ -98,6 +138,12 @@ impl WindowManager {
             let curr_bounds = WindowBounds::from_window(&window);
 
             let prev_bounds = self.find_prev_bounds_in(&saved_bounds, window.task_id);
+            if let Some(old) = prev_bounds
+                && old.visible != curr_bounds.visible
+                && !self.first_frame
+            {
+                self.animate_minimize(i, &window, !curr_bounds.visible, now_ms);
+            }
 
             if let Some(old) = prev_bounds {
                 if old.x != curr_bounds.x
@@ -111,6 +157,13 @@ impl WindowManager {
                 }
             } else if curr_bounds.visible {
                 self.input.needs_full_redraw = true;
+                if !self.first_frame {
+                    self.layout
+                        .place_new_window(&window, self.input.mouse_x, self.input.mouse_y);
+                }
+            }
+            if layout_changed && curr_bounds.visible {
+                self.layout.rescue_window(&window);
             }
 
             self.prev_window_bounds[i] = curr_bounds;
@@ -125,9 +178,13 @@ impl WindowManager {
         }
 
         for i in 0..self.prev_window_count as usize {
-            let prev = &self.prev_windows[i];
-            if !self.window_exists(prev.task_id) {
+            let task_id = self.prev_windows[i].task_id;
+            if !self.window_exists(task_id) {
                 self.add_bounds_damage(&saved_bounds[i]);
+                if let Some(rect) = self.animator.cancel(task_id) {
+                    self.output_damage
+                        .add_rect(rect.x0, rect.y0, rect.x1, rect.y1);
+                }
             }
         }
 
@@ -135,7 +192,7 @@ impl WindowManager {
             self.add_taskbar_damage();
         }
 
-        if self.input.cursor_trail_count > 0 {
+        if self.input.cursor_trail_count > 0 && self.hw_cursor.is_none() {
             for i in 0..self.input.cursor_trail_count {
                 let (x, y) = self.input.cursor_trail[i];
                 self.add_cursor_damage_at(x, y);
@@ -146,6 +203,46 @@ impl WindowManager {
         self.register_hover_regions();
     }
 
+    /// Shrink the `index`th window into its taskbar button, or grow it
+    /// back out when restored.
+    fn animate_minimize(
+        &mut self,
+        index: usize,
+        window: &UserWindowInfo,
+        minimizing: bool,
+        now_ms: u64,
+    ) {
+        let shown = Transform {
+            frame: WindowBounds {
+                visible: true,
+                ..WindowBounds::from_window(window)
+            }
+            .to_damage_rect(),
+            alpha: u8::MAX,
+        };
+        let hidden = Transform {
+            frame: taskbar::app_button_rect(index, self.renderer.output_height as i32),
+            alpha: 0,
+        };
+        let (from, to) = if minimizing {
+            (shown, hidden)
+        } else {
+            (hidden, shown)
+        };
+        self.animator.start(window.task_id, now_ms, from, to);
+    }
+
+    /// Step window animations, damaging where they were and where they
+    /// are now.
+    fn step_animations(&mut self, now_ms: u64) {
+        let damage = &mut self.output_damage;
+        self.animator.step(now_ms, |rect| {
+            if rect.is_valid() {
+                damage.add_rect(rect.x0, rect.y0, rect.x1, rect.y1);
+            }
+        });
+    }
+
     fn register_hover_regions(&mut self) {
         self.hover_registry.begin_frame();
 
@@ -218,39 +315,32 @@ impl WindowManager {
                 continue;
             }
 
-            let close_x = w.x + w.width as i32 - BUTTON_SIZE - BUTTON_PADDING;
-            let close_y = w.y - TITLE_BAR_HEIGHT + BUTTON_PADDING;
-            let min_x = w.x + w.width as i32 - (BUTTON_SIZE * 2) - (BUTTON_PADDING * 2);
-            let min_y = w.y - TITLE_BAR_HEIGHT + BUTTON_PADDING;
-
-            let on_title_bar = !deco_hit_consumed && self.input.hit_test_title_bar(&w);
-            let close_hover = on_title_bar && self.input.hit_test_close_button(&w);
-            let min_hover = on_title_bar && self.input.hit_test_minimize_button(&w);
-
-            if on_title_bar {
+            let part = if deco_hit_consumed {
+                None
+            } else {
+                self.input.hit_test_decoration(&w)
+            };
+            if part.is_some() {
                 deco_hit_consumed = true;
             }
 
-            self.hover_registry.register(
-                HOVER_CLOSE_BASE | w.task_id,
-                DamageRect {
-                    x0: close_x,
-                    y0: close_y,
-                    x1: close_x + BUTTON_SIZE - 1,
-                    y1: close_y + BUTTON_SIZE - 1,
-                },
-                close_hover,
-            );
-            self.hover_registry.register(
-                HOVER_MINIMIZE_BASE | w.task_id,
-                DamageRect {
-                    x0: min_x,
-                    y0: min_y,
-                    x1: min_x + BUTTON_SIZE - 1,
-                    y1: min_y + BUTTON_SIZE - 1,
-                },
-                min_hover,
-            );
+            for (base, button) in [
+                (HOVER_CLOSE_BASE, DecorationPart::Close),
+                (HOVER_MINIMIZE_BASE, DecorationPart::Minimize),
+            ] {
+                let Some(rect) = decorations().button_rect(&w, button) else {
+                    continue;
+                };
+                let state = if part != Some(button) {
+                    ButtonState::Normal
+                } else if self.input.pressed_button() == Some((w.task_id, button)) {
+                    ButtonState::Pressed
+                } else {
+                    ButtonState::Hovered
+                };
+                self.hover_registry
+                    .register_button(base | w.task_id, rect, state);
+            }
         }
 
         let mut hover_damage = [DamageRect::invalid(); 32];
@@ -340,10 +430,111 @@ impl WindowManager {
         );
     }
 
+    /// Pick up new crash notices, expire the toast and let a click dismiss
+    /// it.  Returns true if the click was on the toast.
+    fn update_toast(&mut self) -> bool {
+        let (fb_w, fb_h) = (
+            self.renderer.output_width as i32,
+            self.renderer.output_height as i32,
+        );
+        let old = self.toast.visible_bounds(fb_w, fb_h);
+        let mut changed = self.toast.update(sys_core::get_time_ms());
+        let clicked = old.is_some_and(|rect| self.input.clicked_in(&rect));
+        if clicked {
+            self.toast.dismiss();
+            changed = true;
+        }
+        if changed {
+            for rect in [old, self.toast.visible_bounds(fb_w, fb_h)]
+                .into_iter()
+                .flatten()
+            {
+                self.output_damage
+                    .add_rect(rect.x0, rect.y0, rect.x1, rect.y1);
+            }
+        }
+        clicked
+    }
+
+    /// Sample the CPU meter when due and damage it if its reading moved.
+    fn update_cpu_meter(&mut self, now_ms: u64) {
+        let (fb_w, fb_h) = (
+            self.renderer.output_width as i32,
+            self.renderer.output_height as i32,
+        );
+        let old = self.cpu_meter.bounds(fb_w, fb_h);
+        if self.cpu_meter.update(now_ms) {
+            // The meter grows leftwards once it knows the CPU count.
+            for rect in [old, self.cpu_meter.bounds(fb_w, fb_h)] {
+                self.output_damage
+                    .add_rect(rect.x0, rect.y0, rect.x1, rect.y1);
+            }
+        }
+    }
+
     fn add_cursor_damage_at(&mut self, x: i32, y: i32) {
         self.output_damage.add_rect(x - 4, y - 8, x + 4, y + 8);
     }
 
+    /// Work out the start menu's opacity for this frame, fading it in as
+    /// it opens and out as it closes, and damaging it while it changes.
+    fn update_start_menu_fade(&mut self, now_ms: u64) {
+        let target = if self.input.start_menu_open {
+            u8::MAX
+        } else {
+            0
+        };
+        let heading_to = self
+            .start_menu_fade
+            .map_or(self.start_menu_alpha, |fade| fade.to);
+        if heading_to != target {
+            self.start_menu_fade = Some(Fade::new(
+                now_ms,
+                START_MENU_FADE_MS,
+                self.start_menu_alpha,
+                target,
+            ));
+        }
+
+        let Some(fade) = self.start_menu_fade else {
+            return;
+        };
+        let alpha = fade.alpha(now_ms);
+        if fade.done(now_ms) {
+            self.start_menu_fade = None;
+        }
+        if alpha != self.start_menu_alpha {
+            self.add_start_menu_damage();
+        }
+        self.start_menu_alpha = alpha;
+    }
+
+    fn animating(&self) -> bool {
+        self.start_menu_fade.is_some() || self.animator.active()
+    }
+
+    /// Shape of the cursor: the one asked for by the window whose content
+    /// is under the pointer.
+    fn cursor_shape(&self) -> u8 {
+        for window in self.windows[..self.window_count as usize].iter().rev() {
+            if window.state != WINDOW_STATE_MINIMIZED && self.input.hit_test_content_area(window) {
+                return window.cursor_shape;
+            }
+        }
+        CURSOR_SHAPE_DEFAULT
+    }
+
+    /// Move the hardware cursor to the pointer, falling back to drawing
+    /// the cursor if the plane stops taking it.
+    fn update_hw_cursor(&mut self, cursor_shape: u8) {
+        if let Some(cursor) = &mut self.hw_cursor
+            && !cursor.update(self.input.mouse_x, self.input.mouse_y, cursor_shape)
+        {
+            self.hw_cursor = None;
+            self.input.needs_full_redraw = true;
+        }
+    }
+
     fn needs_redraw(&self) -> bool {
         self.first_frame
             || self.input.needs_full_redraw
@@ -379,9 +570,28 @@ pub fn compositor_user_main(_arg: *mut c_void) {
     wm.renderer
         .set_output_info(output.width, output.height, output.bytes_pp, output.pitch);
 
+    gfx::font::load_system_fonts();
+    // Title bars follow the display's scale; client windows are magnified
+    // to it by the renderer.
+    decorations::set_scale(fb_info.scale_factor());
+
     let pixel_format = fb_info.format;
+    if wm.renderer.load_wallpaper(pixel_format) {
+        tty::write(b"COMPOSITOR: wallpaper loaded\n");
+    }
+
+    // Super+Arrow shortcuts come to us instead of the focused window.
+    sys_input::set_shortcut_focus(process::getpid());
 
-    const TARGET_FRAME_MS: u64 = 16;
+    wm.hw_cursor = HardwareCursor::new(CURSOR_SHAPE_DEFAULT, wm.input.mouse_x, wm.input.mouse_y);
+    if wm.hw_cursor.is_some() {
+        tty::write(b"COMPOSITOR: hardware cursor plane\n");
+    }
+
+    // Frame budget for the metrics: one refresh.
+    let target_frame_ms = window::present_feedback()
+        .map_or(PRESENT_REFRESH_NS, |feedback| feedback.refresh_ns)
+        / 1_000_000;
     let mut frame_count: u32 = 0;
     let mut metrics = FrameMetrics::new();
 
@@ -391,15 +601,40 @@ pub fn compositor_user_main(_arg: *mut c_void) {
         window::drain_queue();
         sys_input::drain_queue();
 
+        if window::wallpaper_take_reload() {
+            wm.renderer.load_wallpaper(pixel_format);
+            wm.input.needs_full_redraw = true;
+        }
+
         wm.input.update_mouse();
-        wm.refresh_windows();
+        wm.refresh_windows(frame_start_ms);
         wm.input.update_pointer_focus(&wm.windows, wm.window_count);
         wm.input
             .process_pending_close_requests(&wm.windows, wm.window_count);
-        wm.input
-            .handle_mouse_events(fb_info.height as i32, &wm.windows, wm.window_count);
-
-        if wm.needs_redraw() {
+        wm.input.handle_shortcuts(
+            &wm.layout,
+            fb_info.height as i32,
+            &wm.windows,
+            wm.window_count,
+        );
+        if !wm.update_toast() {
+            wm.input.handle_mouse_events(
+                &wm.layout,
+                fb_info.height as i32,
+                &wm.windows,
+                wm.window_count,
+            );
+        }
+        let now_ms = sys_core::get_time_ms();
+        wm.update_start_menu_fade(now_ms);
+        wm.update_cpu_meter(now_ms);
+        wm.step_animations(now_ms);
+        let cursor_shape = wm.cursor_shape();
+        wm.update_hw_cursor(cursor_shape);
+        let software_cursor = wm.hw_cursor.is_none().then_some(cursor_shape);
+
+        let rendered = wm.needs_redraw();
+        if rendered {
             let force_full =
                 wm.first_frame || wm.input.needs_full_redraw || wm.output_damage.is_full_damage();
 
@@ -420,30 +655,20 @@ pub fn compositor_user_main(_arg: *mut c_void) {
             if let Some(mut buf) = output.draw_buffer() {
                 buf.set_pixel_format(pixel_format);
 
-                let cursor_shape = {
-                    let mut shape = 0u8;
-                    for i in (0..wm.window_count as usize).rev() {
-                        if wm.windows[i].state == WINDOW_STATE_MINIMIZED {
-                            continue;
-                        }
-                        if wm.input.hit_test_content_area(&wm.windows[i]) {
-                            shape = wm.windows[i].cursor_shape;
-                            break;
-                        }
-                    }
-                    shape
-                };
-
                 mode = wm.renderer.render(
                     &mut buf,
                     &wm.windows,
                     wm.window_count as usize,
                     wm.input.focused_task,
-                    wm.input.start_menu_open,
+                    wm.start_menu_alpha,
+                    wm.input.outline(&wm.layout, fb_info.height as i32),
                     wm.input.mouse_x,
                     wm.input.mouse_y,
-                    cursor_shape,
+                    software_cursor,
                     &wm.hover_registry,
+                    &wm.animator,
+                    &wm.toast,
+                    &wm.cpu_meter,
                     &mut wm.surface_cache,
                     force_full,
                     &damage_snapshot[..damage_count],
@@ -456,7 +681,9 @@ pub fn compositor_user_main(_arg: *mut c_void) {
                 &[]
             };
 
+            let present_start_ms = sys_core::get_time_ms();
             let flip_result = output.present(damage_slice);
+            let present_ms = sys_core::get_time_ms().saturating_sub(present_start_ms);
             if frame_count < 3 {
                 if flip_result {
                     tty::write(b"COMPOSITOR: fb_flip ok\n");
@@ -480,17 +707,48 @@ pub fn compositor_user_main(_arg: *mut c_void) {
                 mode,
                 damage_slice,
             );
-            metrics.record(mode, copied, frame_time, TARGET_FRAME_MS, flip_result);
+            metrics.record(
+                mode,
+                copied,
+                present_ms,
+                frame_time,
+                target_frame_ms,
+                flip_result,
+            );
+            if frame_count % FRAME_METRICS_REPORT_INTERVAL == 0 {
+                metrics.report();
+            }
 
             wm.input.needs_full_redraw = false;
             wm.first_frame = false;
             wm.taskbar_needs_redraw = false;
         }
 
-        let frame_end_ms = sys_core::get_time_ms();
-        let frame_time = frame_end_ms.saturating_sub(frame_start_ms);
-        if frame_time < TARGET_FRAME_MS {
-            sys_core::sleep_ms((TARGET_FRAME_MS - frame_time) as u32);
+        if rendered {
+            // At most one frame per refresh, started on a vblank; work
+            // arriving meanwhile is picked up by the wait below without
+            // blocking.
+            window::wait_vblank(0);
         }
+        let now_ms = sys_core::get_time_ms();
+
+        // Sleep until a client, the pointer, a timed present or a crash
+        // notice needs a frame.  Our own deadlines are a close request's
+        // grace period, the toast going away, the next CPU meter sample and
+        // the next animation step.
+        let animation = wm.animating().then_some(now_ms);
+        let deadline = [
+            wm.input.next_close_deadline(),
+            wm.toast.expires_ms(),
+            Some(wm.cpu_meter.next_sample_ms()),
+            animation,
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let timeout_ms = deadline.map_or(COMPOSITOR_WAIT_FOREVER, |deadline| {
+            deadline.saturating_sub(sys_core::get_time_ms())
+        });
+        window::compositor_wait(timeout_ms);
     }
 }
@@ -1,7 +1,9 @@
 //! Compositor output buffer and frame metrics.
 
+use slopos_lib::numfmt::{self, NumBuf};
+
 use crate::gfx::{DamageRect, DrawBuffer};
-use crate::syscall::{DisplayInfo, ShmBuffer, window};
+use crate::syscall::{DisplayInfo, ShmBuffer, tty, window};
 
 // ── Render mode ─────────────────────────────────────────────────────────────
 
@@ -58,8 +60,8 @@ impl CompositorOutput {
 
 // ── Window bounds ───────────────────────────────────────────────────────────
 
+use crate::decorations::decorations;
 use crate::syscall::UserWindowInfo;
-use crate::theme::TITLE_BAR_HEIGHT;
 
 pub const WINDOW_STATE_MINIMIZED: u8 = 1;
 
@@ -70,6 +72,8 @@ pub struct WindowBounds {
     pub y: i32,
     pub width: u32,
     pub height: u32,
+    /// Height of the title bar above the content, zero when undecorated.
+    pub top_inset: i32,
     pub visible: bool,
 }
 
@@ -80,6 +84,7 @@ impl WindowBounds {
             y: w.y,
             width: w.width,
             height: w.height,
+            top_inset: decorations().top_inset(w),
             visible: w.state != WINDOW_STATE_MINIMIZED,
         }
     }
@@ -91,7 +96,7 @@ impl WindowBounds {
         }
         DamageRect {
             x0: self.x,
-            y0: self.y - TITLE_BAR_HEIGHT,
+            y0: self.y - self.top_inset,
             x1: self.x + self.width as i32 - 1,
             y1: self.y + self.height as i32 - 1,
         }
@@ -102,10 +107,15 @@ impl WindowBounds {
 
 const FRAME_METRICS_WINDOW: usize = 128;
 
+/// Frames between metrics reports on the console.
+pub const FRAME_METRICS_REPORT_INTERVAL: u32 = 1024;
+
 pub struct FrameMetrics {
     full_redraw_frames: u64,
     partial_redraw_frames: u64,
     total_bytes_copied: u64,
+    /// Time spent in `present`, over the same frames as `total_bytes_copied`.
+    total_present_ms: u64,
     late_frames: u64,
     dropped_presents: u64,
     frame_times: [u64; FRAME_METRICS_WINDOW],
@@ -119,6 +129,7 @@ impl FrameMetrics {
             full_redraw_frames: 0,
             partial_redraw_frames: 0,
             total_bytes_copied: 0,
+            total_present_ms: 0,
             late_frames: 0,
             dropped_presents: 0,
             frame_times: [0; FRAME_METRICS_WINDOW],
@@ -131,6 +142,7 @@ impl FrameMetrics {
         &mut self,
         mode: RenderMode,
         bytes_copied: usize,
+        present_ms: u64,
         frame_time_ms: u64,
         target_frame_ms: u64,
         present_ok: bool,
@@ -142,6 +154,7 @@ impl FrameMetrics {
             }
         }
         self.total_bytes_copied = self.total_bytes_copied.saturating_add(bytes_copied as u64);
+        self.total_present_ms = self.total_present_ms.saturating_add(present_ms);
         if frame_time_ms > target_frame_ms {
             self.late_frames = self.late_frames.saturating_add(1);
         }
@@ -155,6 +168,30 @@ impl FrameMetrics {
             self.frame_times_count += 1;
         }
     }
+
+    /// Measured SHM-to-framebuffer copy bandwidth in KiB/s, averaged over
+    /// every present so far; 0 until presents have taken a measurable
+    /// millisecond.
+    pub fn copy_bandwidth_kib_s(&self) -> u64 {
+        if self.total_present_ms == 0 {
+            return 0;
+        }
+        self.total_bytes_copied.saturating_mul(1000) / 1024 / self.total_present_ms
+    }
+
+    /// Log present bandwidth and the late and dropped frame counts.
+    pub fn report(&self) {
+        let mut buf = NumBuf::<21>::new();
+        tty::write(b"COMPOSITOR: present ");
+        tty::write(numfmt::trim_nul(
+            buf.format_u64(self.copy_bandwidth_kib_s()),
+        ));
+        tty::write(b" KiB/s, ");
+        tty::write(numfmt::trim_nul(buf.format_u64(self.late_frames)));
+        tty::write(b" late, ");
+        tty::write(numfmt::trim_nul(buf.format_u64(self.dropped_presents)));
+        tty::write(b" dropped\n");
+    }
 }
 
 // ── Helpers ─────────────────────────────────────────────────────────────────
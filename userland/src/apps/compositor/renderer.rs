@@ -1,9 +1,14 @@
 use slopos_abi::draw::Color32;
+use slopos_abi::window::{SURFACE_BLEND_PIXEL_ALPHA, WINDOW_FLAG_SMOOTH_SCALE};
 
-use crate::gfx::{self, DamageRect, DrawBuffer};
+use crate::decorations::decorations;
+use crate::gfx::{self, DamageRect, DrawBuffer, PixelFormat, ScaleFilter, ScaledSource};
 use crate::syscall::UserWindowInfo;
 use crate::theme::*;
 
+use super::animation::{Animator, Transform};
+use super::cpu_meter::CpuMeter;
+use super::cursor::{cursor_bounds, draw_cursor};
 use super::hover::{
     HOVER_APP_BTN_BASE, HOVER_CLOSE_BASE, HOVER_MENU_ITEM_BASE, HOVER_MINIMIZE_BASE,
     HOVER_START_BTN, HoverRegistry,
@@ -11,14 +16,19 @@ use super::hover::{
 use super::output::{RenderMode, WINDOW_STATE_MINIMIZED};
 use super::surface_cache::ClientSurfaceCache;
 use super::taskbar::{self, START_MENU_ITEMS};
+use super::toast::CrashToast;
+use super::wallpaper::Wallpaper;
 
 const COLOR_WINDOW_PLACEHOLDER: Color32 = Color32::rgb(0x20, 0x20, 0x30);
+const COLOR_RESIZE_OUTLINE: Color32 = COLOR_TEXT;
+const RESIZE_OUTLINE_WIDTH: i32 = 2;
 
 pub struct Renderer {
     pub output_width: u32,
     pub output_height: u32,
     pub output_bytes_pp: u8,
     pub output_pitch: usize,
+    wallpaper: Wallpaper,
 }
 
 impl Renderer {
@@ -28,6 +38,7 @@ impl Renderer {
             output_height: 0,
             output_bytes_pp: 4,
             output_pitch: 0,
+            wallpaper: Wallpaper::new(),
         }
     }
 
@@ -38,52 +49,78 @@ impl Renderer {
         self.output_pitch = pitch;
     }
 
+    /// (Re)load the wallpaper for the current output.  Returns whether one
+    /// is shown; otherwise the desktop is plain [`COLOR_BACKGROUND`].
+    pub fn load_wallpaper(&mut self, format: PixelFormat) -> bool {
+        self.wallpaper.load(
+            self.output_width,
+            self.output_height,
+            self.output_pitch,
+            self.output_bytes_pp,
+            format,
+        )
+    }
+
     pub fn render(
         &self,
         buf: &mut DrawBuffer,
         windows: &[UserWindowInfo],
         window_count: usize,
         focused_task: u32,
-        start_menu_open: bool,
+        start_menu_alpha: u8,
+        resize_outline: Option<DamageRect>,
         mouse_x: i32,
         mouse_y: i32,
-        cursor_shape: u8,
+        software_cursor: Option<u8>,
         hover: &HoverRegistry,
+        animations: &Animator,
+        toast: &CrashToast,
+        cpu_meter: &CpuMeter,
         surface_cache: &mut ClientSurfaceCache,
         force_full: bool,
         damage_regions: &[DamageRect],
     ) -> RenderMode {
         if force_full {
             let full_clip = full_screen_clip(buf);
-            gfx::fill_rect(
-                buf,
-                0,
-                0,
-                buf.width() as i32,
-                buf.height() as i32,
-                COLOR_BACKGROUND,
-            );
+            self.wallpaper.fill(buf, &full_clip);
 
             for i in 0..window_count {
                 let window = windows[i];
+                if let Some(transform) = animations.transform(window.task_id) {
+                    self.draw_window_transformed(
+                        buf,
+                        &window,
+                        &transform,
+                        &full_clip,
+                        surface_cache,
+                    );
+                    continue;
+                }
                 if window.state == WINDOW_STATE_MINIMIZED {
                     continue;
                 }
                 self.draw_window_content(buf, &window, &full_clip, surface_cache);
                 self.draw_title_bar(buf, &window, focused_task, hover, &full_clip);
             }
+            if let Some(outline) = resize_outline {
+                draw_resize_outline(buf, &outline, &full_clip);
+            }
 
             self.draw_taskbar(
                 buf,
                 windows,
                 window_count,
                 focused_task,
-                start_menu_open,
+                start_menu_alpha != 0,
                 hover,
+                cpu_meter,
                 &full_clip,
             );
-            self.draw_start_menu(buf, start_menu_open, hover, &full_clip);
-            self.draw_cursor(buf, mouse_x, mouse_y, cursor_shape, &full_clip);
+            self.draw_start_menu(buf, start_menu_alpha, hover, &full_clip);
+            toast.draw(buf, &full_clip);
+            if let Some(shape) = software_cursor {
+                draw_cursor(buf, mouse_x, mouse_y, shape, &full_clip);
+            }
             RenderMode::Full
         } else if damage_regions.is_empty() {
             RenderMode::Partial
@@ -95,11 +132,15 @@ impl Renderer {
                     windows,
                     window_count,
                     focused_task,
-                    start_menu_open,
+                    start_menu_alpha,
+                    resize_outline,
                     mouse_x,
                     mouse_y,
-                    cursor_shape,
+                    software_cursor,
                     hover,
+                    animations,
+                    toast,
+                    cpu_meter,
                     surface_cache,
                 );
             }
@@ -114,28 +155,31 @@ impl Renderer {
         windows: &[UserWindowInfo],
         window_count: usize,
         focused_task: u32,
-        start_menu_open: bool,
+        start_menu_alpha: u8,
+        resize_outline: Option<DamageRect>,
         mouse_x: i32,
         mouse_y: i32,
-        cursor_shape: u8,
+        software_cursor: Option<u8>,
         hover: &HoverRegistry,
+        animations: &Animator,
+        toast: &CrashToast,
+        cpu_meter: &CpuMeter,
         surface_cache: &mut ClientSurfaceCache,
     ) {
         if !damage.is_valid() {
             return;
         }
 
-        gfx::fill_rect(
-            buf,
-            damage.x0,
-            damage.y0,
-            damage.x1 - damage.x0 + 1,
-            damage.y1 - damage.y0 + 1,
-            COLOR_BACKGROUND,
-        );
+        self.wallpaper.fill(buf, damage);
 
         for i in 0..window_count {
             let window = windows[i];
+            if let Some(transform) = animations.transform(window.task_id) {
+                if intersect_rect(damage, &transform.frame).is_some() {
+                    self.draw_window_transformed(buf, &window, &transform, damage, surface_cache);
+                }
+                continue;
+            }
             if window.state == WINDOW_STATE_MINIMIZED {
                 continue;
             }
@@ -150,16 +194,15 @@ impl Renderer {
                 self.draw_window_content(buf, &window, damage, surface_cache);
             }
 
-            let title_rect = DamageRect {
-                x0: window.x,
-                y0: window.y - TITLE_BAR_HEIGHT,
-                x1: window.x + window.width as i32 - 1,
-                y1: window.y - 1,
-            };
-            if intersect_rect(damage, &title_rect).is_some() {
+            if let Some(title_rect) = decorations().title_bar_rect(&window)
+                && intersect_rect(damage, &title_rect).is_some()
+            {
                 self.draw_title_bar(buf, &window, focused_task, hover, damage);
             }
         }
+        if let Some(outline) = resize_outline {
+            draw_resize_outline(buf, &outline, damage);
+        }
 
         let taskbar_y = buf.height() as i32 - TASKBAR_HEIGHT;
         let taskbar_rect = DamageRect {
@@ -174,13 +217,14 @@ impl Renderer {
                 windows,
                 window_count,
                 focused_task,
-                start_menu_open,
+                start_menu_alpha != 0,
                 hover,
+                cpu_meter,
                 damage,
             );
         }
 
-        if start_menu_open {
+        if start_menu_alpha != 0 {
             let menu_h = taskbar::start_menu_height();
             let fb_h = buf.height() as i32;
             let menu_rect = DamageRect {
@@ -190,13 +234,20 @@ impl Renderer {
                 y1: taskbar::start_menu_y(fb_h) + menu_h - 1,
             };
             if intersect_rect(damage, &menu_rect).is_some() {
-                self.draw_start_menu(buf, start_menu_open, hover, damage);
+                self.draw_start_menu(buf, start_menu_alpha, hover, damage);
             }
         }
 
-        let cursor_rect = cursor_bounds(mouse_x, mouse_y, cursor_shape);
-        if intersect_rect(damage, &cursor_rect).is_some() {
-            self.draw_cursor(buf, mouse_x, mouse_y, cursor_shape, damage);
+        if let Some(toast_rect) = toast.visible_bounds(buf.width() as i32, buf.height() as i32)
+            && intersect_rect(damage, &toast_rect).is_some()
+        {
+            toast.draw(buf, damage);
+        }
+
+        if let Some(shape) = software_cursor
+            && intersect_rect(damage, &cursor_bounds(mouse_x, mouse_y, shape)).is_some()
+        {
+            draw_cursor(buf, mouse_x, mouse_y, shape, damage);
         }
     }
 
@@ -208,54 +259,12 @@ impl Renderer {
         hover: &HoverRegistry,
         clip: &DamageRect,
     ) {
-        let focused = window.task_id == focused_task;
-        let color = if focused {
-            COLOR_TITLE_BAR_FOCUSED
-        } else {
-            COLOR_TITLE_BAR
-        };
-        let title_y = window.y - TITLE_BAR_HEIGHT;
-
-        gfx::fill_rect_clipped(
-            buf,
-            window.x,
-            title_y,
-            window.width as i32,
-            TITLE_BAR_HEIGHT,
-            color,
-            clip,
-        );
-
-        let title = title_to_str(&window.title);
-        gfx::draw_str_clipped(
-            buf,
-            window.x + 8,
-            title_y + 4,
-            title,
-            COLOR_TEXT,
-            color,
-            clip,
-        );
-
-        draw_button_clipped(
-            buf,
-            window.x + window.width as i32 - BUTTON_SIZE - BUTTON_PADDING,
-            title_y + BUTTON_PADDING,
-            BUTTON_SIZE,
-            "X",
-            hover.is_hovered(HOVER_CLOSE_BASE | window.task_id),
-            true,
-            clip,
-        );
-
-        draw_button_clipped(
+        decorations().draw(
             buf,
-            window.x + window.width as i32 - (BUTTON_SIZE * 2) - (BUTTON_PADDING * 2),
-            title_y + BUTTON_PADDING,
-            BUTTON_SIZE,
-            "_",
-            hover.is_hovered(HOVER_MINIMIZE_BASE | window.task_id),
-            false,
+            window,
+            window.task_id == focused_task,
+            hover.button_state(HOVER_CLOSE_BASE | window.task_id),
+            hover.button_state(HOVER_MINIMIZE_BASE | window.task_id),
             clip,
         );
     }
@@ -268,6 +277,7 @@ impl Renderer {
         focused_task: u32,
         start_menu_open: bool,
         hover: &HoverRegistry,
+        cpu_meter: &CpuMeter,
         clip: &DamageRect,
     ) {
         let taskbar_y = buf.height() as i32 - TASKBAR_HEIGHT;
@@ -366,16 +376,20 @@ impl Renderer {
 
             x += TASKBAR_BUTTON_WIDTH + TASKBAR_BUTTON_PADDING;
         }
+
+        cpu_meter.draw(buf, clip);
     }
 
+    /// Draw the start menu at `alpha` (0 when closed, 255 when fully
+    /// faded in).
     fn draw_start_menu(
         &self,
         buf: &mut DrawBuffer,
-        start_menu_open: bool,
+        alpha: u8,
         hover: &HoverRegistry,
         clip: &DamageRect,
     ) {
-        if !start_menu_open {
+        if alpha == 0 {
             return;
         }
 
@@ -384,14 +398,21 @@ impl Renderer {
         let menu_y = taskbar::start_menu_y(fb_height);
         let menu_h = taskbar::start_menu_height();
 
-        gfx::fill_rect_clipped(
+        let fill = |buf: &mut DrawBuffer, x, y, w, h, color: Color32| {
+            if alpha == u8::MAX {
+                gfx::fill_rect_clipped(buf, x, y, w, h, color, clip);
+            } else {
+                gfx::fill_rect_blended_clipped(buf, x, y, w, h, color.with_alpha(alpha), clip);
+            }
+        };
+
+        fill(
             buf,
             menu_x,
             menu_y,
             START_MENU_WIDTH,
             menu_h,
             COLOR_START_MENU_BG,
-            clip,
         );
 
         for (idx, item) in START_MENU_ITEMS.iter().enumerate() {
@@ -403,72 +424,38 @@ impl Renderer {
                 COLOR_START_MENU_BG
             };
 
-            gfx::fill_rect_clipped(
-                buf,
-                menu_x + START_MENU_PADDING,
-                item_y,
-                START_MENU_WIDTH - (START_MENU_PADDING * 2),
-                START_MENU_ITEM_HEIGHT,
-                item_color,
-                clip,
-            );
+            if item_hover {
+                fill(
+                    buf,
+                    menu_x + START_MENU_PADDING,
+                    item_y,
+                    START_MENU_WIDTH - (START_MENU_PADDING * 2),
+                    START_MENU_ITEM_HEIGHT,
+                    item_color,
+                );
+            }
+            // While fading, text fades in from the item colour and leaves
+            // its background to the blended fill.
+            let (text_color, text_bg) = if alpha == u8::MAX {
+                (COLOR_TEXT, item_color)
+            } else {
+                (
+                    gfx::mix(item_color, COLOR_TEXT, alpha),
+                    Color32::TRANSPARENT,
+                )
+            };
             gfx::draw_str_clipped(
                 buf,
                 menu_x + START_MENU_PADDING + 4,
                 item_y + 6,
                 item.label,
-                COLOR_TEXT,
-                item_color,
+                text_color,
+                text_bg,
                 clip,
             );
         }
     }
 
-    fn draw_cursor(
-        &self,
-        buf: &mut DrawBuffer,
-        mx: i32,
-        my: i32,
-        cursor_shape: u8,
-        clip: &DamageRect,
-    ) {
-        match cursor_shape {
-            1 => self.draw_cursor_text(buf, mx, my, clip),
-            _ => self.draw_cursor_default(buf, mx, my, clip),
-        }
-    }
-
-    fn draw_cursor_default(&self, buf: &mut DrawBuffer, mx: i32, my: i32, clip: &DamageRect) {
-        const CURSOR_SIZE: i32 = 9;
-        gfx::fill_rect_clipped(buf, mx - 4, my, CURSOR_SIZE, 1, COLOR_CURSOR, clip);
-        gfx::fill_rect_clipped(buf, mx, my - 4, 1, CURSOR_SIZE, COLOR_CURSOR, clip);
-    }
-
-    fn draw_cursor_text(&self, buf: &mut DrawBuffer, mx: i32, my: i32, clip: &DamageRect) {
-        const BEAM_HEIGHT: i32 = 16;
-        const SERIF_WIDTH: i32 = 5;
-        let top = my - BEAM_HEIGHT / 2;
-        gfx::fill_rect_clipped(buf, mx, top, 1, BEAM_HEIGHT, COLOR_CURSOR, clip);
-        gfx::fill_rect_clipped(
-            buf,
-            mx - SERIF_WIDTH / 2,
-            top,
-            SERIF_WIDTH,
-            1,
-            COLOR_CURSOR,
-            clip,
-        );
-        gfx::fill_rect_clipped(
-            buf,
-            mx - SERIF_WIDTH / 2,
-            top + BEAM_HEIGHT - 1,
-            SERIF_WIDTH,
-            1,
-            COLOR_CURSOR,
-            clip,
-        );
-    }
-
     fn draw_window_content(
         &self,
         buf: &mut DrawBuffer,
@@ -476,28 +463,16 @@ impl Renderer {
         clip: &DamageRect,
         surface_cache: &mut ClientSurfaceCache,
     ) {
+        if window.opacity == 0 {
+            return;
+        }
         let bytes_pp = self.output_bytes_pp as usize;
-        let src_pitch = (window.width as usize) * bytes_pp;
-        let buffer_size = src_pitch * (window.height as usize);
-
-        let cache_index = match surface_cache.get_or_create_index(
-            window.task_id,
-            window.shm_token,
-            buffer_size,
-        ) {
-            Some(idx) => idx,
-            None => {
-                self.draw_window_placeholder(buf, window, clip);
-                return;
-            }
-        };
+        let (buffer_width, buffer_height) = window.buffer_size();
+        let src_pitch = (buffer_width as usize) * bytes_pp;
 
-        let src_data = match surface_cache.get_slice(cache_index) {
-            Some(slice) => slice,
-            None => {
-                self.draw_window_placeholder(buf, window, clip);
-                return;
-            }
+        let Some(src_data) = self.surface_data(window, surface_cache) else {
+            self.draw_window_placeholder(buf, window, clip);
+            return;
         };
 
         let dst_pitch = self.output_pitch;
@@ -526,8 +501,44 @@ impl Renderer {
         let src_start_x = (x0 - window.x) as usize;
         let src_start_y = (y0 - window.y) as usize;
 
+        let blend = gfx::Blend {
+            opacity: window.opacity,
+            alpha_byte: if window.blend_flags & SURFACE_BLEND_PIXEL_ALPHA != 0 {
+                gfx::alpha_byte(buf.pixel_format())
+            } else {
+                None
+            },
+        };
         let dst_data = buf.data_mut();
 
+        if window.content_scale > 1 {
+            // A low-DPI buffer magnified to the display's scale.
+            let source = ScaledSource {
+                data: src_data,
+                pitch: src_pitch,
+                width: buffer_width,
+                height: buffer_height,
+                bytes_pp,
+                scale: window.content_scale as u32,
+            };
+            let filter = if window.window_flags & WINDOW_FLAG_SMOOTH_SCALE != 0 {
+                ScaleFilter::Linear
+            } else {
+                ScaleFilter::Nearest
+            };
+            for y in y0..y1 {
+                let src_y = (y - window.y) as u32;
+                for x in x0..x1 {
+                    let px = source.sample((x - window.x) as u32, src_y, filter);
+                    let dst_off = y as usize * dst_pitch + x as usize * bytes_pp;
+                    if let Some(dst) = dst_data.get_mut(dst_off..dst_off + bytes_pp) {
+                        gfx::blend_span(dst, &px[..bytes_pp], bytes_pp, blend);
+                    }
+                }
+            }
+            return;
+        }
+
         for row in 0..(y1 - y0) as usize {
             let src_row = src_start_y + row;
             let dst_row = (y0 as usize) + row;
@@ -540,7 +551,124 @@ impl Renderer {
             let dst_end = dst_off + copy_width;
 
             if src_end <= src_data.len() && dst_end <= dst_data.len() {
-                dst_data[dst_off..dst_end].copy_from_slice(&src_data[src_off..src_end]);
+                gfx::blend_span(
+                    &mut dst_data[dst_off..dst_end],
+                    &src_data[src_off..src_end],
+                    bytes_pp,
+                    blend,
+                );
+            }
+        }
+    }
+
+    /// The window's client buffer, mapped through the surface cache.
+    fn surface_data<'a>(
+        &self,
+        window: &UserWindowInfo,
+        surface_cache: &'a mut ClientSurfaceCache,
+    ) -> Option<&'a [u8]> {
+        let (width, height) = window.buffer_size();
+        let src_pitch = (width as usize) * self.output_bytes_pp as usize;
+        let buffer_size = src_pitch * (height as usize);
+        let cache_index =
+            surface_cache.get_or_create_index(window.task_id, window.shm_token, buffer_size)?;
+        surface_cache.get_slice(cache_index)
+    }
+
+    /// Draw an animating window squeezed into `transform.frame`: a plain
+    /// title bar strip over its content, scaled nearest-neighbour.
+    fn draw_window_transformed(
+        &self,
+        buf: &mut DrawBuffer,
+        window: &UserWindowInfo,
+        transform: &Transform,
+        clip: &DamageRect,
+        surface_cache: &mut ClientSurfaceCache,
+    ) {
+        let frame = transform.frame;
+        let alpha = (transform.alpha as u32 * window.opacity as u32 / 255) as u8;
+        if alpha == 0 || !frame.is_valid() || window.width == 0 || window.height == 0 {
+            return;
+        }
+        let frame_w = frame.x1 - frame.x0 + 1;
+        let frame_h = frame.y1 - frame.y0 + 1;
+        let inset = decorations().top_inset(window);
+        let full_h = window.height as i32 + inset;
+        let title_h = if inset > 0 {
+            (inset * frame_h / full_h).max(1)
+        } else {
+            0
+        };
+        gfx::fill_rect_blended_clipped(
+            buf,
+            frame.x0,
+            frame.y0,
+            frame_w,
+            title_h,
+            COLOR_TITLE_BAR.with_alpha(alpha),
+            clip,
+        );
+
+        let content = DamageRect {
+            x0: frame.x0,
+            y0: frame.y0 + title_h,
+            x1: frame.x1,
+            y1: frame.y1,
+        };
+        let Some(draw_rect) = intersect_rect(clip, &content) else {
+            return;
+        };
+        let Some(src_data) = self.surface_data(window, surface_cache) else {
+            gfx::fill_rect_blended_clipped(
+                buf,
+                content.x0,
+                content.y0,
+                frame_w,
+                content.y1 - content.y0 + 1,
+                COLOR_WINDOW_PLACEHOLDER.with_alpha(alpha),
+                clip,
+            );
+            return;
+        };
+
+        let x0 = draw_rect.x0.max(0);
+        let y0 = draw_rect.y0.max(0);
+        let x1 = (draw_rect.x1 + 1).min(buf.width() as i32);
+        let y1 = (draw_rect.y1 + 1).min(buf.height() as i32);
+        if x0 >= x1 || y0 >= y1 {
+            return;
+        }
+
+        let bytes_pp = self.output_bytes_pp as usize;
+        let (buffer_width, buffer_height) = window.buffer_size();
+        let src_pitch = (buffer_width as usize) * bytes_pp;
+        let dst_pitch = self.output_pitch;
+        let content_w = (content.x1 - content.x0 + 1) as usize;
+        let content_h = (content.y1 - content.y0 + 1) as usize;
+        let blend = gfx::Blend {
+            opacity: alpha,
+            alpha_byte: if window.blend_flags & SURFACE_BLEND_PIXEL_ALPHA != 0 {
+                gfx::alpha_byte(buf.pixel_format())
+            } else {
+                None
+            },
+        };
+        let dst_data = buf.data_mut();
+
+        for y in y0..y1 {
+            let src_y = (y - content.y0) as usize * buffer_height as usize / content_h;
+            for x in x0..x1 {
+                let src_x = (x - content.x0) as usize * buffer_width as usize / content_w;
+                let src_off = src_y * src_pitch + src_x * bytes_pp;
+                let dst_off = y as usize * dst_pitch + x as usize * bytes_pp;
+                if src_off + bytes_pp <= src_data.len() && dst_off + bytes_pp <= dst_data.len() {
+                    gfx::blend_span(
+                        &mut dst_data[dst_off..dst_off + bytes_pp],
+                        &src_data[src_off..src_off + bytes_pp],
+                        bytes_pp,
+                        blend,
+                    );
+                }
             }
         }
     }
@@ -578,6 +706,19 @@ impl Renderer {
     }
 }
 
+/// Outline `frame`, the window a drag-resize or snap would leave.
+fn draw_resize_outline(buf: &mut DrawBuffer, frame: &DamageRect, clip: &DamageRect) {
+    let x = frame.x0;
+    let y = frame.y0;
+    let w = frame.x1 - frame.x0 + 1;
+    let h = frame.y1 - frame.y0 + 1;
+    let t = RESIZE_OUTLINE_WIDTH;
+    gfx::fill_rect_clipped(buf, x, y, w, t, COLOR_RESIZE_OUTLINE, clip);
+    gfx::fill_rect_clipped(buf, x, y + h - t, w, t, COLOR_RESIZE_OUTLINE, clip);
+    gfx::fill_rect_clipped(buf, x, y, t, h, COLOR_RESIZE_OUTLINE, clip);
+    gfx::fill_rect_clipped(buf, x + w - t, y, t, h, COLOR_RESIZE_OUTLINE, clip);
+}
+
 fn full_screen_clip(buf: &DrawBuffer) -> DamageRect {
     DamageRect {
         x0: 0,
@@ -606,49 +747,3 @@ fn title_to_str(title: &[u8; 32]) -> &str {
     }
     core::str::from_utf8(&title[..len]).unwrap_or("<invalid>")
 }
-
-fn cursor_bounds(mx: i32, my: i32, cursor_shape: u8) -> DamageRect {
-    match cursor_shape {
-        1 => DamageRect {
-            x0: mx - 2,
-            y0: my - 8,
-            x1: mx + 2,
-            y1: my + 7,
-        },
-        _ => DamageRect {
-            x0: mx - 4,
-            y0: my - 4,
-            x1: mx + 4,
-            y1: my + 4,
-        },
-    }
-}
-
-fn draw_button_clipped(
-    buf: &mut DrawBuffer,
-    x: i32,
-    y: i32,
-    size: i32,
-    label: &str,
-    hover: bool,
-    is_close: bool,
-    clip: &DamageRect,
-) {
-    let color = if hover && is_close {
-        COLOR_BUTTON_CLOSE_HOVER
-    } else if hover {
-        COLOR_BUTTON_HOVER
-    } else {
-        COLOR_BUTTON
-    };
-    gfx::fill_rect_clipped(buf, x, y, size, size, color, clip);
-    gfx::draw_str_clipped(
-        buf,
-        x + size / 4,
-        y + size / 4,
-        label,
-        COLOR_TEXT,
-        color,
-        clip,
-    );
-}
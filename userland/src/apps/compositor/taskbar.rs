@@ -1,5 +1,6 @@
 //! Taskbar state tracking, start menu items, and layout geometry.
 
+use crate::gfx::DamageRect;
 use crate::syscall::UserWindowInfo;
 use crate::theme::*;
 
@@ -14,7 +15,7 @@ pub struct StartMenuItem {
     pub program_name: &'static [u8],
 }
 
-pub const START_MENU_ITEMS: [StartMenuItem; 3] = [
+pub const START_MENU_ITEMS: [StartMenuItem; 6] = [
     StartMenuItem {
         label: "Files",
         window_title: Some(b"Files"),
@@ -25,11 +26,26 @@ pub const START_MENU_ITEMS: [StartMenuItem; 3] = [
         window_title: Some(b"Sysinfo"),
         program_name: b"sysinfo",
     },
+    StartMenuItem {
+        label: "Tasks",
+        window_title: Some(b"htop"),
+        program_name: b"htop",
+    },
     StartMenuItem {
         label: "Shell",
         window_title: None,
         program_name: b"shell",
     },
+    StartMenuItem {
+        label: "Terminal",
+        window_title: None,
+        program_name: b"terminal",
+    },
+    StartMenuItem {
+        label: "Images",
+        window_title: None,
+        program_name: b"imgview",
+    },
 ];
 
 // ── Taskbar state (for conditional redraw) ──────────────────────────────────
@@ -95,6 +111,18 @@ pub fn start_button_height() -> i32 {
     TASKBAR_HEIGHT - (TASKBAR_BUTTON_PADDING * 2)
 }
 
+/// The taskbar button of the `index`th window.
+pub fn app_button_rect(index: usize, fb_height: i32) -> DamageRect {
+    let x = app_buttons_start_x() + index as i32 * (TASKBAR_BUTTON_WIDTH + TASKBAR_BUTTON_PADDING);
+    let y = start_button_y(fb_height);
+    DamageRect {
+        x0: x,
+        y0: y,
+        x1: x + TASKBAR_BUTTON_WIDTH - 1,
+        y1: y + start_button_height() - 1,
+    }
+}
+
 #[inline]
 pub fn start_menu_height() -> i32 {
     (START_MENU_ITEMS.len() as i32 * START_MENU_ITEM_HEIGHT) + (START_MENU_PADDING * 2)
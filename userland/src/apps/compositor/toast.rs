@@ -0,0 +1,153 @@
+//! Crash toast: a notice above the taskbar, bottom right, for each crash
+//! bundle the kernel writes.  It names the task and the bundle directory
+//! under `/var/crash`, and goes away after a few seconds or when clicked.
+
+use slopos_abi::crash::{CRASH_COMM_LEN, CRASH_PATH_LEN, CrashNotice};
+use slopos_abi::draw::Color32;
+use slopos_abi::font::{FONT_CHAR_HEIGHT, FONT_CHAR_WIDTH};
+
+use crate::gfx::{self, DamageRect, DrawBuffer};
+use crate::syscall::core as sys_core;
+use crate::theme::*;
+
+/// How long a toast stays up.
+const TOAST_MS: u64 = 6_000;
+const TOAST_PADDING: i32 = 8;
+const TOAST_MARGIN: i32 = 8;
+const TOAST_BORDER: i32 = 2;
+
+const COLOR_TOAST_BG: Color32 = Color32::rgb(0x3A, 0x14, 0x18);
+const COLOR_TOAST_BORDER: Color32 = Color32::rgb(0xE8, 0x11, 0x23);
+
+const HEADLINE_SUFFIX: &[u8] = b" crashed";
+const HEADLINE_LEN: usize = CRASH_COMM_LEN + HEADLINE_SUFFIX.len();
+
+pub struct CrashToast {
+    /// Sequence number of the next notice to show.
+    next_seq: u64,
+    headline: [u8; HEADLINE_LEN],
+    headline_len: usize,
+    path: [u8; CRASH_PATH_LEN],
+    path_len: usize,
+    /// Uptime at which the toast goes away; `None` when hidden.
+    expires_ms: Option<u64>,
+}
+
+impl CrashToast {
+    pub const fn new() -> Self {
+        Self {
+            next_seq: 0,
+            headline: [0; HEADLINE_LEN],
+            headline_len: 0,
+            path: [0; CRASH_PATH_LEN],
+            path_len: 0,
+            expires_ms: None,
+        }
+    }
+
+    fn show(&mut self, notice: &CrashNotice, now_ms: u64) {
+        let comm = notice.comm_str().as_bytes();
+        self.headline[..comm.len()].copy_from_slice(comm);
+        self.headline[comm.len()..comm.len() + HEADLINE_SUFFIX.len()]
+            .copy_from_slice(HEADLINE_SUFFIX);
+        self.headline_len = comm.len() + HEADLINE_SUFFIX.len();
+
+        let path = notice.path_str().as_bytes();
+        self.path[..path.len()].copy_from_slice(path);
+        self.path_len = path.len();
+        self.expires_ms = Some(now_ms + TOAST_MS);
+    }
+
+    /// Show the newest notice the kernel has published since the last
+    /// call, and hide an expired toast.  Returns true if the toast changed.
+    pub fn update(&mut self, now_ms: u64) -> bool {
+        let mut changed = false;
+        // Only the newest of a burst is shown; the rest are on disk.
+        while let Some(notice) = sys_core::crash_notice(self.next_seq) {
+            self.next_seq = notice.seq + 1;
+            self.show(&notice, now_ms);
+            changed = true;
+        }
+        if !changed && self.expires_ms.is_some_and(|at| now_ms >= at) {
+            self.expires_ms = None;
+            changed = true;
+        }
+        changed
+    }
+
+    pub fn dismiss(&mut self) {
+        self.expires_ms = None;
+    }
+
+    /// When the toast goes away, if it is up.
+    pub fn expires_ms(&self) -> Option<u64> {
+        self.expires_ms
+    }
+
+    fn headline_str(&self) -> &str {
+        core::str::from_utf8(&self.headline[..self.headline_len]).unwrap_or("task crashed")
+    }
+
+    fn path_str(&self) -> &str {
+        core::str::from_utf8(&self.path[..self.path_len]).unwrap_or("")
+    }
+
+    /// Where the toast is on a `fb_width` x `fb_height` screen, whether or
+    /// not it is up.
+    pub fn bounds(&self, fb_width: i32, fb_height: i32) -> DamageRect {
+        let chars = self.headline_len.max(self.path_len) as i32;
+        let width = (chars * FONT_CHAR_WIDTH + 2 * TOAST_PADDING).min(fb_width - 2 * TOAST_MARGIN);
+        let height = 2 * FONT_CHAR_HEIGHT + 2 * TOAST_PADDING + 4;
+        let x1 = fb_width - TOAST_MARGIN - 1;
+        let y1 = fb_height - TASKBAR_HEIGHT - TOAST_MARGIN - 1;
+        DamageRect {
+            x0: x1 - width + 1,
+            y0: y1 - height + 1,
+            x1,
+            y1,
+        }
+    }
+
+    /// The toast's bounds if it is up.
+    pub fn visible_bounds(&self, fb_width: i32, fb_height: i32) -> Option<DamageRect> {
+        self.expires_ms?;
+        Some(self.bounds(fb_width, fb_height))
+    }
+
+    pub fn draw(&self, buf: &mut DrawBuffer, clip: &DamageRect) {
+        let Some(r) = self.visible_bounds(buf.width() as i32, buf.height() as i32) else {
+            return;
+        };
+        let (w, h) = (r.x1 - r.x0 + 1, r.y1 - r.y0 + 1);
+        gfx::fill_rect_clipped(buf, r.x0, r.y0, w, h, COLOR_TOAST_BORDER, clip);
+        gfx::fill_rect_clipped(
+            buf,
+            r.x0 + TOAST_BORDER,
+            r.y0 + TOAST_BORDER,
+            w - 2 * TOAST_BORDER,
+            h - 2 * TOAST_BORDER,
+            COLOR_TOAST_BG,
+            clip,
+        );
+        let text_x = r.x0 + TOAST_PADDING;
+        let text_y = r.y0 + TOAST_PADDING;
+        gfx::draw_str_clipped(
+            buf,
+            text_x,
+            text_y,
+            self.headline_str(),
+            COLOR_TEXT,
+            COLOR_TOAST_BG,
+            clip,
+        );
+        gfx::draw_str_clipped(
+            buf,
+            text_x,
+            text_y + FONT_CHAR_HEIGHT + 4,
+            self.path_str(),
+            COLOR_TEXT,
+            COLOR_TOAST_BG,
+            clip,
+        );
+    }
+}
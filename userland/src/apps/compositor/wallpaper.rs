@@ -0,0 +1,116 @@
+//! Desktop background layer.
+//!
+//! The wallpaper is scaled to the output once, when it loads, into a
+//! buffer laid out like the output, so drawing the background under damage
+//! is a row copy.  Without a wallpaper the desktop is [`COLOR_BACKGROUND`].
+
+use core::ffi::c_char;
+
+use crate::gfx::wallpaper::{WALLPAPER_PATH, read_mode};
+use crate::gfx::{self, Bmp, DamageRect, DrawBuffer, PixelFormat, draw_image_scaled};
+use crate::syscall::{ShmBuffer, USER_FS_OPEN_READ, UserFsStat, fs};
+use crate::theme::COLOR_BACKGROUND;
+
+/// Largest wallpaper file loaded, enough for a 4K 32-bit image.
+const MAX_WALLPAPER_FILE: usize = 3840 * 2160 * 4 + 4096;
+
+pub struct Wallpaper {
+    /// The scaled image, `pitch` bytes per row.
+    scaled: Option<ShmBuffer>,
+    pitch: usize,
+    bytes_pp: usize,
+}
+
+impl Wallpaper {
+    pub const fn new() -> Self {
+        Self {
+            scaled: None,
+            pitch: 0,
+            bytes_pp: 0,
+        }
+    }
+
+    /// Load [`WALLPAPER_PATH`] scaled to an output of the given layout,
+    /// dropping any wallpaper shown so far.  Returns whether one loaded.
+    pub fn load(
+        &mut self,
+        width: u32,
+        height: u32,
+        pitch: usize,
+        bytes_pp: u8,
+        format: PixelFormat,
+    ) -> bool {
+        self.scaled = None;
+        let Some(file) = read_wallpaper_file() else {
+            return false;
+        };
+        let Ok(image) = Bmp::parse(file.as_slice()) else {
+            return false;
+        };
+        let Ok(mut scaled) = ShmBuffer::create(pitch * height as usize) else {
+            return false;
+        };
+        let Some(mut buf) = DrawBuffer::new(scaled.as_mut_slice(), width, height, pitch, bytes_pp)
+        else {
+            return false;
+        };
+        buf.set_pixel_format(format);
+        draw_image_scaled(&mut buf, &image, read_mode(), COLOR_BACKGROUND);
+
+        self.scaled = Some(scaled);
+        self.pitch = pitch;
+        self.bytes_pp = bytes_pp as usize;
+        true
+    }
+
+    /// Draw the background into `rect` of `buf`.
+    pub fn fill(&self, buf: &mut DrawBuffer, rect: &DamageRect) {
+        let width = rect.x1 - rect.x0 + 1;
+        let height = rect.y1 - rect.y0 + 1;
+        let Some(scaled) = self
+            .scaled
+            .as_ref()
+            .filter(|_| buf.pitch() == self.pitch && buf.bytes_pp() as usize == self.bytes_pp)
+        else {
+            gfx::fill_rect(buf, rect.x0, rect.y0, width, height, COLOR_BACKGROUND);
+            return;
+        };
+
+        let clipped = rect.clip(buf.width() as i32, buf.height() as i32);
+        if !clipped.is_valid() {
+            return;
+        }
+        let span = (clipped.x1 - clipped.x0 + 1) as usize * self.bytes_pp;
+        let src = scaled.as_slice();
+        let dst = buf.data_mut();
+        for y in clipped.y0..=clipped.y1 {
+            let off = y as usize * self.pitch + clipped.x0 as usize * self.bytes_pp;
+            dst[off..off + span].copy_from_slice(&src[off..off + span]);
+        }
+        buf.add_damage(clipped.x0, clipped.y0, clipped.x1, clipped.y1);
+    }
+}
+
+/// The wallpaper file's contents, in a buffer of exactly its size.
+fn read_wallpaper_file() -> Option<ShmBuffer> {
+    let path = WALLPAPER_PATH.as_ptr() as *const c_char;
+    let mut stat = UserFsStat::default();
+    fs::stat_path(path, &mut stat).ok()?;
+    let size = stat.size as usize;
+    if !stat.is_file() || size == 0 || size > MAX_WALLPAPER_FILE {
+        return None;
+    }
+
+    let mut file = ShmBuffer::create(size).ok()?;
+    let fd = fs::open_path(path, USER_FS_OPEN_READ).ok()?;
+    let data = file.as_mut_slice();
+    let mut len = 0;
+    while len < size {
+        match fs::read_slice(fd, &mut data[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => len += n,
+        }
+    }
+    let _ = fs::close_fd(fd);
+    (len == size).then_some(file)
+}
@@ -0,0 +1,194 @@
+//! `fetch [-i] [-o file] <url>` — GET an `http://` URL and write the body to
+//! stdout or a file.
+//!
+//! The scriptable sibling of `wget`: nothing but the body goes to stdout,
+//! so it composes with pipes, and diagnostics go to stderr.  `-i` puts the
+//! status line and headers in front of the body.  Exit status is 0 for a
+//! 2xx response delivered in full, 1 for any network or HTTP failure and 2
+//! for bad usage, which makes it the end-to-end check for DNS, TCP and the
+//! HTTP framing code in one command.
+
+use slopos_abi::net::{AF_INET, SOCK_STREAM};
+
+use crate::apps::cli::arg_at;
+use crate::apps::http::{self, HttpError, Response, Url};
+use crate::apps::wget::{create_output, write_all};
+use crate::syscall::core::exit_with_code;
+use crate::syscall::{RawFd, SockAddrIn, SyscallError, fs, net, tty};
+
+const USER_AGENT: &[u8] = b"slopfetch/0.1";
+const RECV_BUF_LEN: usize = 4096;
+const REQUEST_BUF_LEN: usize = 1024;
+/// Give up on a server that goes quiet for this long.
+const RECV_TIMEOUT_MS: u64 = 15_000;
+const STDOUT: RawFd = 1;
+
+fn write_err(buf: &[u8]) {
+    if fs::write_slice(2, buf).is_err() {
+        let _ = tty::write(buf);
+    }
+}
+
+fn die(sock: Option<RawFd>, msg: &[&[u8]]) -> ! {
+    if let Some(fd) = sock {
+        let _ = fs::close_fd(fd);
+    }
+    write_err(b"fetch: ");
+    for part in msg {
+        write_err(part);
+    }
+    write_err(b"\n");
+    exit_with_code(1);
+}
+
+fn usage() -> ! {
+    write_err(b"usage: fetch [-i] [-o file] <http://host[:port]/path>\n");
+    exit_with_code(2);
+}
+
+struct Options<'a> {
+    url: &'a [u8],
+    output: Option<*const u8>,
+    include_head: bool,
+}
+
+fn parse_args<'a>(argc: usize, argv: *const *const u8) -> Options<'a> {
+    let mut opts = Options {
+        url: &[],
+        output: None,
+        include_head: false,
+    };
+    let mut i = 1;
+    while i < argc {
+        match arg_at(argv, i) {
+            b"-i" => opts.include_head = true,
+            b"-o" if i + 1 < argc => {
+                i += 1;
+                opts.output = Some(unsafe { *argv.add(i) });
+            }
+            arg if !arg.starts_with(b"-") && opts.url.is_empty() => opts.url = arg,
+            _ => usage(),
+        }
+        i += 1;
+    }
+    if opts.url.is_empty() {
+        usage();
+    }
+    opts
+}
+
+fn connect(url: &Url<'_>) -> RawFd {
+    let Some(addr) = net::resolve(url.host) else {
+        die(None, &[b"cannot resolve ", url.host]);
+    };
+    let Ok(fd) = net::socket(AF_INET, SOCK_STREAM, 0) else {
+        die(None, &[b"socket creation failed"]);
+    };
+    let dest = SockAddrIn {
+        family: AF_INET,
+        port: url.port.to_be(),
+        addr,
+        _pad: [0; 8],
+    };
+    if let Err(err) = net::connect(fd, &dest) {
+        die(
+            Some(fd),
+            &[b"connect to ", url.host, b": ", err.as_str().as_bytes()],
+        );
+    }
+    let _ = net::set_recv_timeout(fd, Some(RECV_TIMEOUT_MS));
+    fd
+}
+
+fn send_all(fd: RawFd, mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        match net::send(fd, data, 0) {
+            Ok(0) | Err(_) => return false,
+            Ok(n) => data = &data[n..],
+        }
+    }
+    true
+}
+
+pub fn fetch_main_args(argc: usize, argv: *const *const u8) -> ! {
+    if argv.is_null() {
+        usage();
+    }
+    let opts = parse_args(argc, argv);
+    let Some(url) = http::parse_url(opts.url) else {
+        die(None, &[b"only http:// URLs are supported"]);
+    };
+    let mut request = [0u8; REQUEST_BUF_LEN];
+    let Some(request_len) = http::build_request(&url, USER_AGENT, &mut request) else {
+        die(None, &[b"URL too long"]);
+    };
+
+    let sock = connect(&url);
+    if !send_all(sock, &request[..request_len]) {
+        die(Some(sock), &[b"failed to send request"]);
+    }
+
+    let mut resp = Response::new();
+    let mut out: Option<RawFd> = None;
+    let mut write_failed = false;
+    let mut buf = [0u8; RECV_BUF_LEN];
+
+    let result: Result<(), HttpError> = loop {
+        let n = match net::recv(sock, &mut buf, 0) {
+            Ok(0) => break resp.finish(),
+            Ok(n) => n,
+            Err(SyscallError::EAGAIN) => die(Some(sock), &[b"timed out waiting for the server"]),
+            Err(err) => die(Some(sock), &[err.as_str().as_bytes()]),
+        };
+        let mut pos = 0;
+        let mut fed = Ok(());
+        while pos < n && fed.is_ok() && !write_failed {
+            let was_head = resp.head_done();
+            let fd = out;
+            let failed = &mut write_failed;
+            match resp.feed(&buf[pos..n], &mut |body| {
+                if let Some(fd) = fd
+                    && !write_all(fd, body)
+                {
+                    *failed = true;
+                }
+            }) {
+                Ok(used) => pos += used,
+                Err(err) => fed = Err(err),
+            }
+            if !was_head && resp.head_done() {
+                if !(200..300).contains(&resp.status) {
+                    let mut code = [0u8; 5];
+                    let code = http::format_dec(resp.status as u32, &mut code);
+                    die(Some(sock), &[b"server returned HTTP ", code]);
+                }
+                let fd = match opts.output {
+                    Some(path) => create_output(path)
+                        .unwrap_or_else(|| die(Some(sock), &[b"cannot create output file"])),
+                    None => STDOUT,
+                };
+                if opts.include_head {
+                    write_failed = !write_all(fd, resp.head()) || !write_all(fd, b"\r\n\r\n");
+                }
+                out = Some(fd);
+            }
+        }
+        if fed.is_err() || write_failed || resp.is_done() {
+            break fed;
+        }
+    };
+    let _ = fs::close_fd(sock);
+    if let Some(fd) = out
+        && fd != STDOUT
+    {
+        let _ = fs::close_fd(fd);
+    }
+
+    if write_failed {
+        die(None, &[b"write to output failed"]);
+    }
+    if let Err(err) = result {
+        die(None, &[err.as_str().as_bytes()]);
+    }
+    exit_with_code(0);
+}
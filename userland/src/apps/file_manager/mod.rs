@@ -0,0 +1,707 @@
+//! Two-pane file manager.
+//!
+//! Each pane lists a directory; the active one has the keyboard.  Files
+//! are copied or moved from the active pane into the other, either with a
+//! key or by dragging an entry across.  Copies run a few chunks per pass
+//! of the event loop behind a progress dialog, so large files don't stall
+//! the window.
+//!
+//! Keys: up and down, Page Up/Down, Home and End select, Enter or right
+//! opens a folder and Backspace or left goes up, Tab switches panes.  `c`
+//! copies and `m` moves the selection to the other pane, `r` renames it,
+//! `n` makes a new folder and Delete or `d` deletes, after asking.  Esc
+//! cancels a prompt or a copy in progress.  Ctrl+C copies the active
+//! pane's path and Ctrl+V opens a pasted one.
+
+mod ops;
+mod pane;
+
+use core::str;
+
+use slopos_abi::draw::Color32;
+use slopos_abi::input::{
+    SCANCODE_DELETE, SCANCODE_DOWN, SCANCODE_END, SCANCODE_HOME, SCANCODE_LEFT, SCANCODE_PAGE_DOWN,
+    SCANCODE_PAGE_UP, SCANCODE_RIGHT, SCANCODE_UP,
+};
+use slopos_lib::numfmt::{self, NumBuf, UnitBase};
+
+use crate::appkit::{self, ControlFlow, Event, Window, WindowedApp};
+use crate::gfx::font::FONT_CHAR_WIDTH;
+use crate::gfx::{self, DrawBuffer};
+use crate::syscall::{SyscallError, UserFsEntry};
+use crate::theme::*;
+
+use ops::{Step, Transfer, TransferKind};
+use pane::{NAME_MAX, PATH_MAX, Pane, entry_name};
+
+const FM_CONTENT_WIDTH: u32 = FM_WIDTH as u32;
+const FM_CONTENT_HEIGHT: u32 = (FM_HEIGHT - FM_TITLE_HEIGHT) as u32;
+const NAV_ROW_HEIGHT: i32 = 24;
+const STATUS_HEIGHT: i32 = 20;
+const MARGIN_X: i32 = 8;
+/// Rows a wheel detent scrolls.
+const SCROLL_ROWS: isize = 3;
+const BUTTON_LEFT: u8 = 0x01;
+
+const BACKSPACE: u8 = 0x08;
+const TAB: u8 = b'\t';
+const ESCAPE: u8 = 0x1B;
+
+const DIALOG_WIDTH: i32 = 320;
+const DIALOG_HEIGHT: i32 = 84;
+const PROGRESS_HEIGHT: i32 = 12;
+
+const COLOR_DIR: Color32 = Color32::rgb(0x40, 0x80, 0xFF);
+const COLOR_SIZE: Color32 = Color32::rgb(0x90, 0x90, 0x90);
+const COLOR_ROW_INACTIVE: Color32 = COLOR_BUTTON_PRESSED;
+const COLOR_ERROR: Color32 = Color32::rgb(0xE8, 0x11, 0x23);
+const COLOR_PROGRESS: Color32 = Color32::rgb(0x4E, 0xC9, 0x6B);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Rename,
+    NewFolder,
+    ConfirmDelete,
+}
+
+/// A question asked in the status bar.  Text prompts edit `text`.
+struct Prompt {
+    kind: PromptKind,
+    text: [u8; NAME_MAX],
+    len: usize,
+}
+
+/// One line of status text.
+struct Line {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl Line {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 128],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let take = bytes.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&bytes[..take]);
+        self.len += take;
+    }
+
+    fn bytes(&mut self, count: u64) {
+        let mut num = NumBuf::<32>::new();
+        self.push(numfmt::trim_nul(num.format_bytes(count, UnitBase::Binary)));
+    }
+
+    fn as_str(&self) -> &str {
+        str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+/// Draw as much of `text` as fits in `max_width` pixels.
+fn draw_clipped(
+    fb: &mut DrawBuffer<'_>,
+    x: i32,
+    y: i32,
+    text: &[u8],
+    max_width: i32,
+    fg: Color32,
+    bg: Color32,
+) {
+    let fits = (max_width / FONT_CHAR_WIDTH).max(0) as usize;
+    let text = &text[..text.len().min(fits)];
+    gfx::font::draw_string(fb, x, y, str::from_utf8(text).unwrap_or("?"), fg, bg);
+}
+
+fn visible_rows(height: i32) -> usize {
+    ((height - NAV_ROW_HEIGHT - STATUS_HEIGHT) / FM_ITEM_HEIGHT).max(1) as usize
+}
+
+pub struct FileManager {
+    panes: [Pane; 2],
+    active: usize,
+    prompt: Option<Prompt>,
+    /// The outcome of the last operation, and whether it failed.
+    message: Option<(&'static str, bool)>,
+    transfer: Option<Transfer>,
+    /// Pane and entry under the pointer when the left button went down.
+    press: Option<(usize, usize)>,
+}
+
+impl FileManager {
+    fn new() -> Self {
+        Self {
+            panes: [Pane::new(b"/"), Pane::new(b"/")],
+            active: 0,
+            prompt: None,
+            message: None,
+            transfer: None,
+            press: None,
+        }
+    }
+
+    fn other(&self) -> usize {
+        1 - self.active
+    }
+
+    fn refresh_all(&mut self) {
+        for pane in &mut self.panes {
+            pane.refresh();
+        }
+    }
+
+    fn fail(&mut self, message: &'static str) {
+        self.message = Some((message, true));
+    }
+
+    /// Name and kind of the active pane's selection, copied out so the
+    /// panes can change while it is used.
+    fn selection(&self) -> Option<([u8; NAME_MAX], usize, bool)> {
+        let entry = self.panes[self.active].selected_entry()?;
+        let name = entry_name(entry);
+        let mut out = [0u8; NAME_MAX];
+        out[..name.len()].copy_from_slice(name);
+        Some((out, name.len(), entry.is_directory()))
+    }
+
+    /// Paths of the selection in the active pane and under the same name
+    /// in `dest_pane`.
+    fn selection_paths(
+        &self,
+        name: &[u8],
+        dest_pane: usize,
+        dest_name: &[u8],
+    ) -> Option<([u8; PATH_MAX], [u8; PATH_MAX])> {
+        let mut from = [0u8; PATH_MAX];
+        let mut to = [0u8; PATH_MAX];
+        self.panes[self.active].child_path(name, &mut from)?;
+        self.panes[dest_pane].child_path(dest_name, &mut to)?;
+        Some((from, to))
+    }
+
+    fn open_selection(&mut self) {
+        let Some((name, len, is_dir)) = self.selection() else {
+            return;
+        };
+        if is_dir && !self.panes[self.active].enter(&name[..len]) {
+            self.fail("cannot open folder");
+        }
+    }
+
+    /// Copy or move the selection into the other pane's directory.
+    fn transfer_selection(&mut self, kind: TransferKind) {
+        let Some((name, len, _)) = self.selection() else {
+            return;
+        };
+        let name = &name[..len];
+        let dest = self.other();
+        if self.panes[self.active].path() == self.panes[dest].path() {
+            self.fail("both panes show the same folder");
+            return;
+        }
+        let Some((from, to)) = self.selection_paths(name, dest, name) else {
+            self.fail("path too long");
+            return;
+        };
+
+        if kind == TransferKind::Move {
+            // A rename moves folders too, and needs no copying.
+            match ops::rename(&from, &to) {
+                Ok(()) => {
+                    self.refresh_all();
+                    self.panes[dest].select_name(name);
+                    self.message = Some(("moved", false));
+                    return;
+                }
+                Err(SyscallError::EEXIST) => return self.fail("target already exists"),
+                Err(SyscallError::EXDEV | SyscallError::EOPNOTSUPP) => {}
+                Err(_) => return self.fail("cannot move"),
+            }
+        }
+        match Transfer::start(kind, &from, &to, name) {
+            Ok(transfer) => {
+                self.message = None;
+                self.transfer = Some(transfer);
+            }
+            Err(message) => self.fail(message),
+        }
+    }
+
+    fn start_prompt(&mut self, kind: PromptKind) {
+        let mut prompt = Prompt {
+            kind,
+            text: [0; NAME_MAX],
+            len: 0,
+        };
+        if kind != PromptKind::NewFolder {
+            let Some((name, len, _)) = self.selection() else {
+                return;
+            };
+            if kind == PromptKind::Rename {
+                prompt.text = name;
+                prompt.len = len;
+            }
+        }
+        self.message = None;
+        self.prompt = Some(prompt);
+    }
+
+    /// Handle a key while a prompt is open.
+    fn prompt_key(&mut self, ascii: u8) {
+        let Some(prompt) = &mut self.prompt else {
+            return;
+        };
+        if prompt.kind == PromptKind::ConfirmDelete {
+            self.prompt = None;
+            if ascii == b'y' || ascii == b'Y' {
+                self.delete_selection();
+            }
+            return;
+        }
+        match ascii {
+            ESCAPE => self.prompt = None,
+            BACKSPACE => prompt.len = prompt.len.saturating_sub(1),
+            b'\n' => {
+                let (kind, text, len) = (prompt.kind, prompt.text, prompt.len);
+                self.prompt = None;
+                self.commit_prompt(kind, &text[..len]);
+            }
+            // Names can't hold a slash, and keep room for the NUL.
+            0x20..=0x7E if ascii != b'/' && prompt.len < NAME_MAX - 1 => {
+                prompt.text[prompt.len] = ascii;
+                prompt.len += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn commit_prompt(&mut self, kind: PromptKind, text: &[u8]) {
+        if text.is_empty() || text == b"." || text == b".." {
+            return self.fail("invalid name");
+        }
+        let active = self.active;
+        let mut path = [0u8; PATH_MAX];
+        match kind {
+            PromptKind::Rename => {
+                let Some((name, len, _)) = self.selection() else {
+                    return;
+                };
+                if &name[..len] == text {
+                    return;
+                }
+                let Some((from, to)) = self.selection_paths(&name[..len], active, text) else {
+                    return self.fail("path too long");
+                };
+                match ops::rename(&from, &to) {
+                    Ok(()) => self.message = Some(("renamed", false)),
+                    Err(SyscallError::EEXIST) => return self.fail("name already taken"),
+                    Err(_) => return self.fail("cannot rename"),
+                }
+            }
+            PromptKind::NewFolder => {
+                if self.panes[active].child_path(text, &mut path).is_none() {
+                    return self.fail("path too long");
+                }
+                if let Err(message) = ops::make_dir(&path) {
+                    return self.fail(message);
+                }
+                self.message = Some(("folder created", false));
+            }
+            PromptKind::ConfirmDelete => return,
+        }
+        self.refresh_all();
+        self.panes[active].select_name(text);
+    }
+
+    fn delete_selection(&mut self) {
+        let Some((name, len, _)) = self.selection() else {
+            return;
+        };
+        let mut path = [0u8; PATH_MAX];
+        if self.panes[self.active]
+            .child_path(&name[..len], &mut path)
+            .is_none()
+        {
+            return self.fail("path too long");
+        }
+        match ops::delete(&path) {
+            Ok(()) => self.message = Some(("deleted", false)),
+            Err(message) => self.fail(message),
+        }
+        self.refresh_all();
+    }
+
+    fn on_key(&mut self, scancode: u8, ascii: u8, visible: usize) -> bool {
+        if self.transfer.is_some() {
+            if ascii == ESCAPE
+                && let Some(transfer) = self.transfer.take()
+            {
+                transfer.cancel();
+                self.fail("copy cancelled");
+                self.refresh_all();
+                return true;
+            }
+            return false;
+        }
+        if self.prompt.is_some() {
+            self.prompt_key(ascii);
+            return true;
+        }
+
+        self.message = None;
+        let pane = &mut self.panes[self.active];
+        match (scancode, ascii) {
+            (SCANCODE_UP, 0) => pane.move_selection(-1),
+            (SCANCODE_DOWN, 0) => pane.move_selection(1),
+            (SCANCODE_PAGE_UP, 0) => pane.move_selection(-(visible as isize)),
+            (SCANCODE_PAGE_DOWN, 0) => pane.move_selection(visible as isize),
+            (SCANCODE_HOME, 0) => pane.selected = 0,
+            (SCANCODE_END, 0) => pane.move_selection(isize::MAX),
+            (SCANCODE_LEFT, 0) | (_, BACKSPACE) => {
+                pane.up();
+            }
+            (SCANCODE_RIGHT, 0) | (_, b'\n') => self.open_selection(),
+            (SCANCODE_DELETE, 0) | (_, b'd') => self.start_prompt(PromptKind::ConfirmDelete),
+            (_, TAB) => self.active = self.other(),
+            (_, b'c') => self.transfer_selection(TransferKind::Copy),
+            (_, b'm') => self.transfer_selection(TransferKind::Move),
+            (_, b'r') => self.start_prompt(PromptKind::Rename),
+            (_, b'n') => self.start_prompt(PromptKind::NewFolder),
+            _ => return false,
+        }
+        self.panes[self.active].scroll_to_selection(visible);
+        true
+    }
+
+    fn pane_at(&self, x: i32, width: i32) -> usize {
+        usize::from(x >= width / 2)
+    }
+
+    /// The entry index under `y` in `pane`, if there is one.
+    fn row_at(&self, pane: usize, y: i32) -> Option<usize> {
+        if y < NAV_ROW_HEIGHT {
+            return None;
+        }
+        let pane = &self.panes[pane];
+        let index = pane.scroll_top + ((y - NAV_ROW_HEIGHT) / FM_ITEM_HEIGHT) as usize;
+        (index < pane.entries().len()).then_some(index)
+    }
+
+    fn on_press(&mut self, x: i32, y: i32, width: i32, height: i32) -> bool {
+        if self.transfer.is_some() || self.prompt.is_some() || y >= height - STATUS_HEIGHT {
+            return false;
+        }
+        let pane = self.pane_at(x, width);
+        self.active = pane;
+        self.message = None;
+        let pane_x = pane as i32 * (width / 2);
+        if y < NAV_ROW_HEIGHT {
+            if x >= pane_x + 4 && x < pane_x + 4 + BUTTON_SIZE {
+                self.panes[pane].up();
+            }
+            return true;
+        }
+        if let Some(index) = self.row_at(pane, y) {
+            self.panes[pane].selected = index;
+            self.press = Some((pane, index));
+        }
+        true
+    }
+
+    /// Finish a click, or drop a dragged entry onto the other pane.
+    fn on_release(&mut self, x: i32, y: i32, width: i32, height: i32) -> bool {
+        let Some((pane, index)) = self.press.take() else {
+            return false;
+        };
+        if y >= height - STATUS_HEIGHT {
+            return true;
+        }
+        if self.pane_at(x, width) != pane {
+            self.active = pane;
+            self.transfer_selection(TransferKind::Copy);
+            return true;
+        }
+        if self.row_at(pane, y) == Some(index) {
+            self.open_selection();
+        }
+        // Redraw either way to clear the drag hint.
+        true
+    }
+
+    fn draw_pane(&self, fb: &mut DrawBuffer<'_>, index: usize, x: i32, width: i32, bottom: i32) {
+        let pane = &self.panes[index];
+        let active = index == self.active;
+        let header_bg = if active {
+            COLOR_TITLE_BAR_FOCUSED
+        } else {
+            COLOR_TITLE_BAR
+        };
+        gfx::fill_rect(fb, x, 0, width, NAV_ROW_HEIGHT, header_bg);
+        gfx::fill_rect(fb, x + 4, 4, BUTTON_SIZE, BUTTON_SIZE - 8, COLOR_BUTTON);
+        gfx::font::draw_string(fb, x + 8, 4, "^", COLOR_TEXT, COLOR_BUTTON);
+
+        // Show the end of a long path, where the current folder is.
+        let path_x = x + 4 + BUTTON_SIZE + 8;
+        let fits = ((x + width - MARGIN_X - path_x) / FONT_CHAR_WIDTH).max(0) as usize;
+        let path = pane.path();
+        let path = &path[path.len().saturating_sub(fits)..];
+        draw_clipped(
+            fb,
+            path_x,
+            4,
+            path,
+            fits as i32 * FONT_CHAR_WIDTH,
+            COLOR_TEXT,
+            header_bg,
+        );
+
+        gfx::fill_rect(
+            fb,
+            x,
+            NAV_ROW_HEIGHT,
+            width,
+            bottom - NAV_ROW_HEIGHT,
+            FM_COLOR_BG,
+        );
+        let visible = ((bottom - NAV_ROW_HEIGHT) / FM_ITEM_HEIGHT).max(0) as usize;
+        let rows = pane.entries().iter().enumerate().skip(pane.scroll_top);
+        for (row, (i, entry)) in rows.take(visible).enumerate() {
+            let y = NAV_ROW_HEIGHT + row as i32 * FM_ITEM_HEIGHT;
+            let bg = match (i == pane.selected, active) {
+                (true, true) => FM_COLOR_HL,
+                (true, false) => COLOR_ROW_INACTIVE,
+                _ => FM_COLOR_BG,
+            };
+            gfx::fill_rect(fb, x, y, width, FM_ITEM_HEIGHT, bg);
+            self.draw_entry(fb, entry, x, y + 2, width, bg);
+        }
+    }
+
+    fn draw_entry(
+        &self,
+        fb: &mut DrawBuffer<'_>,
+        entry: &UserFsEntry,
+        x: i32,
+        y: i32,
+        width: i32,
+        bg: Color32,
+    ) {
+        let mut size = Line::new();
+        if !entry.is_directory() {
+            size.bytes(entry.size as u64);
+        }
+        let size_w = size.len as i32 * FONT_CHAR_WIDTH;
+        let size_x = x + width - MARGIN_X - size_w;
+        gfx::font::draw_string(fb, size_x, y, size.as_str(), COLOR_SIZE, bg);
+
+        let mut name = Line::new();
+        name.push(entry_name(entry));
+        let color = if entry.is_directory() {
+            name.push(b"/");
+            COLOR_DIR
+        } else {
+            FM_COLOR_FG
+        };
+        let name_w = size_x - MARGIN_X - (x + MARGIN_X);
+        draw_clipped(
+            fb,
+            x + MARGIN_X,
+            y,
+            &name.buf[..name.len],
+            name_w,
+            color,
+            bg,
+        );
+    }
+
+    fn draw_status(&self, fb: &mut DrawBuffer<'_>, y: i32, width: i32) {
+        gfx::fill_rect(fb, 0, y, width, STATUS_HEIGHT, COLOR_TITLE_BAR);
+        let mut line = Line::new();
+        let mut color = COLOR_TEXT;
+        if let Some(prompt) = &self.prompt {
+            match prompt.kind {
+                PromptKind::Rename => line.push(b"Rename to: "),
+                PromptKind::NewFolder => line.push(b"New folder: "),
+                PromptKind::ConfirmDelete => {
+                    line.push(b"Delete ");
+                    if let Some(entry) = self.panes[self.active].selected_entry() {
+                        line.push(entry_name(entry));
+                    }
+                    line.push(b"? (y/n)");
+                }
+            }
+            if prompt.kind != PromptKind::ConfirmDelete {
+                line.push(&prompt.text[..prompt.len]);
+                line.push(b"_");
+            }
+        } else if let Some((message, failed)) = self.message {
+            line.push(message.as_bytes());
+            if failed {
+                color = COLOR_ERROR;
+            }
+        } else if self.press.is_some() {
+            line.push(b"Drop on the other pane to copy");
+        } else {
+            line.push(b"Tab switch  c copy  m move  r rename  n new folder  d delete");
+        }
+        draw_clipped(
+            fb,
+            MARGIN_X,
+            y + 2,
+            &line.buf[..line.len],
+            width - 2 * MARGIN_X,
+            color,
+            COLOR_TITLE_BAR,
+        );
+    }
+
+    fn draw_progress(&self, fb: &mut DrawBuffer<'_>, transfer: &Transfer, width: i32, height: i32) {
+        let x = (width - DIALOG_WIDTH) / 2;
+        let y = (height - DIALOG_HEIGHT) / 2;
+        gfx::fill_rect(fb, x, y, DIALOG_WIDTH, DIALOG_HEIGHT, COLOR_BUTTON);
+        gfx::fill_rect(
+            fb,
+            x + 1,
+            y + 1,
+            DIALOG_WIDTH - 2,
+            DIALOG_HEIGHT - 2,
+            COLOR_TITLE_BAR,
+        );
+        let inner_x = x + MARGIN_X;
+        let inner_w = DIALOG_WIDTH - 2 * MARGIN_X;
+
+        let mut title = Line::new();
+        title.push(match transfer.kind() {
+            TransferKind::Copy => b"Copying ",
+            TransferKind::Move => b"Moving ",
+        });
+        title.push(transfer.name());
+        let title = &title.buf[..title.len];
+        draw_clipped(
+            fb,
+            inner_x,
+            y + 8,
+            title,
+            inner_w,
+            COLOR_TEXT,
+            COLOR_TITLE_BAR,
+        );
+
+        let (done, total) = transfer.progress();
+        let bar_y = y + 32;
+        gfx::fill_rect(fb, inner_x, bar_y, inner_w, PROGRESS_HEIGHT, FM_COLOR_BG);
+        let filled = if total == 0 {
+            inner_w
+        } else {
+            (done * inner_w as u64 / total) as i32
+        };
+        gfx::fill_rect(fb, inner_x, bar_y, filled, PROGRESS_HEIGHT, COLOR_PROGRESS);
+
+        let mut counts = Line::new();
+        counts.bytes(done);
+        counts.push(b" of ");
+        counts.bytes(total);
+        counts.push(b"  Esc cancels");
+        let counts = &counts.buf[..counts.len];
+        draw_clipped(
+            fb,
+            inner_x,
+            y + 56,
+            counts,
+            inner_w,
+            COLOR_TEXT,
+            COLOR_TITLE_BAR,
+        );
+    }
+}
+
+impl WindowedApp for FileManager {
+    fn init(&mut self, win: &mut Window) {
+        win.set_title("Files");
+        win.request_redraw();
+    }
+
+    fn on_event(&mut self, win: &mut Window, event: Event) -> ControlFlow {
+        let (width, height) = (win.width() as i32, win.height() as i32);
+        let changed = match event {
+            Event::CloseRequest => return ControlFlow::Exit,
+            Event::KeyPress {
+                scancode, ascii, ..
+            } => self.on_key(scancode, ascii, visible_rows(height)),
+            Event::PointerPress { button } if button & BUTTON_LEFT != 0 => {
+                let (x, y) = win.pointer();
+                self.on_press(x, y, width, height)
+            }
+            Event::PointerRelease { button } if button & BUTTON_LEFT != 0 => {
+                let (x, y) = win.pointer();
+                self.on_release(x, y, width, height)
+            }
+            Event::Scroll { dy, .. } => {
+                let pane = self.pane_at(win.pointer().0, width);
+                self.panes[pane].scroll_by(dy as isize * SCROLL_ROWS, visible_rows(height));
+                true
+            }
+            Event::Copy => {
+                let _ = appkit::clipboard::set_text(self.panes[self.active].path());
+                false
+            }
+            Event::Paste => {
+                let mut buf = [0u8; PATH_MAX];
+                let len = appkit::clipboard::get_text(&mut buf);
+                self.panes[self.active].set_path(buf[..len].trim_ascii())
+            }
+            Event::Configure { .. } => true,
+            _ => false,
+        };
+        if changed {
+            win.request_redraw();
+        }
+        ControlFlow::Continue
+    }
+
+    fn update(&mut self, win: &mut Window) {
+        let Some(transfer) = &mut self.transfer else {
+            return;
+        };
+        match transfer.step() {
+            Step::Running => {}
+            Step::Done => {
+                self.message = Some(match transfer.kind() {
+                    TransferKind::Copy => ("copied", false),
+                    TransferKind::Move => ("moved", false),
+                });
+                self.transfer = None;
+                self.refresh_all();
+            }
+            Step::Failed(message) => {
+                self.transfer = None;
+                self.fail(message);
+                self.refresh_all();
+            }
+        }
+        win.request_redraw();
+    }
+
+    fn draw(&mut self, fb: &mut DrawBuffer<'_>) {
+        let width = fb.width() as i32;
+        let height = fb.height() as i32;
+        let status_y = (height - STATUS_HEIGHT).max(0);
+        let half = width / 2;
+
+        self.draw_pane(fb, 0, 0, half, status_y);
+        self.draw_pane(fb, 1, half, width - half, status_y);
+        gfx::fill_rect(fb, half - 1, 0, 1, status_y, COLOR_BUTTON);
+        self.draw_status(fb, status_y, width);
+        if let Some(transfer) = &self.transfer {
+            self.draw_progress(fb, transfer, width, height);
+        }
+    }
+}
+
+pub fn file_manager_main(_arg: *mut core::ffi::c_void) -> ! {
+    let fm = FileManager::new();
+    appkit::run(fm, FM_CONTENT_WIDTH, FM_CONTENT_HEIGHT)
+}
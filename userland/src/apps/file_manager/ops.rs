@@ -0,0 +1,188 @@
+//! File operations.
+//!
+//! Renaming, deleting and making folders finish at once.  Copying a file
+//! is a [`Transfer`] the event loop steps a few chunks at a time, so a
+//! large file shows progress and can be cancelled; a move is a rename,
+//! falling back to a copy and delete across filesystems.
+
+use core::cell::SyncUnsafeCell;
+use core::ffi::c_char;
+
+use crate::syscall::{
+    FdGuard, SyscallError, USER_FS_OPEN_CREAT, USER_FS_OPEN_READ, USER_FS_OPEN_WRITE, UserFsStat,
+    fs,
+};
+
+use super::pane::{NAME_MAX, PATH_MAX};
+
+/// Bytes read and written per chunk.
+const CHUNK_SIZE: usize = 4096;
+/// Chunks copied per [`Transfer::step`].
+const CHUNKS_PER_STEP: usize = 16;
+
+/// Holds each chunk between the read and the write; kept off the stack.
+static COPY_BUF: SyncUnsafeCell<[u8; CHUNK_SIZE]> = SyncUnsafeCell::new([0; CHUNK_SIZE]);
+
+fn cstr(path: &[u8; PATH_MAX]) -> *const c_char {
+    path.as_ptr() as *const c_char
+}
+
+fn exists(path: &[u8; PATH_MAX]) -> bool {
+    let mut stat = UserFsStat::default();
+    fs::stat_path(cstr(path), &mut stat).is_ok()
+}
+
+/// Create the directory `path`.
+pub fn make_dir(path: &[u8; PATH_MAX]) -> Result<(), &'static str> {
+    if exists(path) {
+        return Err("already exists");
+    }
+    fs::mkdir_path(cstr(path)).map_err(|_| "cannot create folder")
+}
+
+/// Rename or move `from` to `to`, which must not exist yet.
+pub fn rename(from: &[u8; PATH_MAX], to: &[u8; PATH_MAX]) -> Result<(), SyscallError> {
+    if exists(to) {
+        return Err(SyscallError::EEXIST);
+    }
+    fs::rename(cstr(from), cstr(to))
+}
+
+/// Delete the file or empty directory `path`.
+pub fn delete(path: &[u8; PATH_MAX]) -> Result<(), &'static str> {
+    fs::unlink_path(cstr(path)).map_err(|err| {
+        if err == SyscallError::EISDIR {
+            "folder is not empty"
+        } else {
+            "cannot delete"
+        }
+    })
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum TransferKind {
+    Copy,
+    /// A copy that deletes the source once it is complete.
+    Move,
+}
+
+pub enum Step {
+    Running,
+    Done,
+    Failed(&'static str),
+}
+
+/// A file being copied a chunk at a time.
+pub struct Transfer {
+    kind: TransferKind,
+    /// Both closed once the copy stops, so the files can be deleted.
+    src: Option<FdGuard>,
+    dst: Option<FdGuard>,
+    src_path: [u8; PATH_MAX],
+    dst_path: [u8; PATH_MAX],
+    name: [u8; NAME_MAX],
+    name_len: usize,
+    total: u64,
+    done: u64,
+}
+
+impl Transfer {
+    /// Start copying the file `src` to `dst`, which must not exist yet.
+    pub fn start(
+        kind: TransferKind,
+        src: &[u8; PATH_MAX],
+        dst: &[u8; PATH_MAX],
+        name: &[u8],
+    ) -> Result<Self, &'static str> {
+        let mut stat = UserFsStat::default();
+        if fs::stat_path(cstr(src), &mut stat).is_err() {
+            return Err("source is gone");
+        }
+        if stat.is_directory() {
+            return Err("cannot copy a folder");
+        }
+        if exists(dst) {
+            return Err("target already exists");
+        }
+        let src_fd =
+            fs::open_path(cstr(src), USER_FS_OPEN_READ).map_err(|_| "cannot open source")?;
+        let src_fd = unsafe { FdGuard::from_raw(src_fd) };
+        let dst_fd = fs::open_path(cstr(dst), USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT)
+            .map_err(|_| "cannot create target")?;
+        let dst_fd = unsafe { FdGuard::from_raw(dst_fd) };
+
+        let mut transfer = Self {
+            kind,
+            src: Some(src_fd),
+            dst: Some(dst_fd),
+            src_path: *src,
+            dst_path: *dst,
+            name: [0; NAME_MAX],
+            name_len: name.len().min(NAME_MAX),
+            total: stat.size as u64,
+            done: 0,
+        };
+        transfer.name[..transfer.name_len].copy_from_slice(&name[..transfer.name_len]);
+        Ok(transfer)
+    }
+
+    pub fn kind(&self) -> TransferKind {
+        self.kind
+    }
+
+    pub fn name(&self) -> &[u8] {
+        &self.name[..self.name_len]
+    }
+
+    /// Bytes copied so far and in all.
+    pub fn progress(&self) -> (u64, u64) {
+        (self.done, self.total.max(self.done))
+    }
+
+    /// Copy the next few chunks.  A failed transfer has removed its
+    /// partial target.
+    pub fn step(&mut self) -> Step {
+        let (Some(src), Some(dst)) = (&self.src, &self.dst) else {
+            return Step::Failed("transfer closed");
+        };
+        // SAFETY: main thread only, and nothing else uses the buffer.
+        let buf = unsafe { &mut *COPY_BUF.get() };
+        for _ in 0..CHUNKS_PER_STEP {
+            let n = match src.read(buf) {
+                Ok(0) => return self.finish(),
+                Ok(n) => n,
+                Err(_) => return self.fail("read failed"),
+            };
+            let mut written = 0;
+            while written < n {
+                match dst.write(&buf[written..n]) {
+                    Ok(0) | Err(_) => return self.fail("write failed"),
+                    Ok(w) => written += w,
+                }
+            }
+            self.done += n as u64;
+        }
+        Step::Running
+    }
+
+    fn finish(&mut self) -> Step {
+        self.src = None;
+        self.dst = None;
+        if self.kind == TransferKind::Move && fs::unlink_path(cstr(&self.src_path)).is_err() {
+            return Step::Failed("copied, but cannot delete the source");
+        }
+        Step::Done
+    }
+
+    fn fail(&mut self, reason: &'static str) -> Step {
+        self.src = None;
+        self.dst = None;
+        let _ = fs::unlink_path(cstr(&self.dst_path));
+        Step::Failed(reason)
+    }
+
+    /// Stop, removing the partial target.
+    pub fn cancel(mut self) {
+        let _ = self.fail("cancelled");
+    }
+}
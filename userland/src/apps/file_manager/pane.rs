@@ -0,0 +1,211 @@
+//! One directory listing: its path, entries and selection.
+
+use core::ffi::c_char;
+
+use crate::syscall::{UserFsEntry, UserFsList, UserFsStat, fs};
+
+pub const PATH_MAX: usize = 256;
+/// Entries listed from one directory.
+pub const MAX_ENTRIES: usize = 64;
+/// Bytes in an entry name, NUL included.
+pub const NAME_MAX: usize = 64;
+
+pub fn entry_name(entry: &UserFsEntry) -> &[u8] {
+    let len = entry.name.iter().position(|&b| b == 0).unwrap_or(NAME_MAX);
+    &entry.name[..len]
+}
+
+pub struct Pane {
+    /// Absolute, without a trailing slash except for the root, and
+    /// NUL-terminated.
+    path: [u8; PATH_MAX],
+    path_len: usize,
+    /// Directories first, then files, each sorted by name.
+    entries: [UserFsEntry; MAX_ENTRIES],
+    count: usize,
+    pub selected: usize,
+    pub scroll_top: usize,
+}
+
+impl Pane {
+    pub fn new(path: &[u8]) -> Self {
+        let mut pane = Self {
+            path: [0; PATH_MAX],
+            path_len: 0,
+            entries: [UserFsEntry::new(); MAX_ENTRIES],
+            count: 0,
+            selected: 0,
+            scroll_top: 0,
+        };
+        if !pane.set_path(path) {
+            pane.set_path(b"/");
+        }
+        pane
+    }
+
+    pub fn path(&self) -> &[u8] {
+        &self.path[..self.path_len]
+    }
+
+    pub fn entries(&self) -> &[UserFsEntry] {
+        &self.entries[..self.count]
+    }
+
+    pub fn selected_entry(&self) -> Option<&UserFsEntry> {
+        self.entries().get(self.selected)
+    }
+
+    /// Show the directory at the absolute `path`.  False if it is not one.
+    pub fn set_path(&mut self, path: &[u8]) -> bool {
+        let path = match path {
+            [rest @ .., b'/'] if !rest.is_empty() => rest,
+            _ => path,
+        };
+        if path.first() != Some(&b'/') || path.len() >= PATH_MAX {
+            return false;
+        }
+        let mut cpath = [0u8; PATH_MAX];
+        cpath[..path.len()].copy_from_slice(path);
+        let mut stat = UserFsStat::default();
+        if fs::stat_path(cpath.as_ptr() as *const c_char, &mut stat).is_err()
+            || !stat.is_directory()
+        {
+            return false;
+        }
+        self.path = cpath;
+        self.path_len = path.len();
+        self.count = 0;
+        self.selected = 0;
+        self.scroll_top = 0;
+        self.refresh();
+        true
+    }
+
+    /// List the directory again, keeping the selection on the same name
+    /// where it still exists.
+    pub fn refresh(&mut self) {
+        let mut keep = [0u8; NAME_MAX];
+        let keep_len = self.selected_entry().map_or(0, |entry| {
+            let name = entry_name(entry);
+            keep[..name.len()].copy_from_slice(name);
+            name.len()
+        });
+
+        let mut list = UserFsList {
+            entries: self.entries.as_mut_ptr(),
+            max_entries: MAX_ENTRIES as u32,
+            count: 0,
+        };
+        self.count = 0;
+        if fs::list_dir(self.path.as_ptr() as *const c_char, &mut list).is_ok() {
+            self.count = (list.count as usize).min(MAX_ENTRIES);
+        }
+
+        // Drop "." and "..": going up has its own key and button.
+        let mut kept = 0;
+        for i in 0..self.count {
+            if !matches!(entry_name(&self.entries[i]), b"." | b"..") {
+                self.entries[kept] = self.entries[i];
+                kept += 1;
+            }
+        }
+        self.count = kept;
+        self.entries[..kept].sort_unstable_by(|a, b| {
+            b.is_directory()
+                .cmp(&a.is_directory())
+                .then_with(|| entry_name(a).cmp(entry_name(b)))
+        });
+
+        if !self.select_name(&keep[..keep_len]) {
+            self.selected = self.selected.min(self.count.saturating_sub(1));
+        }
+    }
+
+    /// Select the entry called `name`.  False if there is none.
+    pub fn select_name(&mut self, name: &[u8]) -> bool {
+        match self.entries().iter().position(|e| entry_name(e) == name) {
+            Some(index) if !name.is_empty() => {
+                self.selected = index;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Open the subdirectory `name`.
+    pub fn enter(&mut self, name: &[u8]) -> bool {
+        let mut path = [0u8; PATH_MAX];
+        match self.child_path(name, &mut path) {
+            Some(len) => self.set_path(&path[..len]),
+            None => false,
+        }
+    }
+
+    /// Go to the parent directory with the one just left selected.
+    pub fn up(&mut self) -> bool {
+        let path = self.path();
+        let Some(slash) = path.iter().rposition(|&b| b == b'/') else {
+            return false;
+        };
+        if path.len() <= 1 {
+            return false;
+        }
+        let mut left = [0u8; NAME_MAX];
+        let name = &path[slash + 1..];
+        let name_len = name.len().min(NAME_MAX);
+        left[..name_len].copy_from_slice(&name[..name_len]);
+
+        let mut parent = [0u8; PATH_MAX];
+        let parent_len = slash.max(1);
+        parent[..parent_len].copy_from_slice(&self.path[..parent_len]);
+        if !self.set_path(&parent[..parent_len]) {
+            return false;
+        }
+        self.select_name(&left[..name_len]);
+        true
+    }
+
+    /// Write the path of `name` in this directory to `out`, NUL-terminated.
+    /// Returns its length without the NUL, or `None` if it does not fit.
+    pub fn child_path(&self, name: &[u8], out: &mut [u8; PATH_MAX]) -> Option<usize> {
+        let dir = self.path();
+        let sep = usize::from(dir != b"/");
+        let len = dir.len() + sep + name.len();
+        if name.is_empty() || len >= PATH_MAX {
+            return None;
+        }
+        out[..dir.len()].copy_from_slice(dir);
+        if sep == 1 {
+            out[dir.len()] = b'/';
+        }
+        out[dir.len() + sep..len].copy_from_slice(name);
+        out[len] = 0;
+        Some(len)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.count == 0 {
+            return;
+        }
+        self.selected = self
+            .selected
+            .saturating_add_signed(delta)
+            .min(self.count - 1);
+    }
+
+    /// Scroll just far enough for the selection to be one of the
+    /// `visible` rows shown.
+    pub fn scroll_to_selection(&mut self, visible: usize) {
+        let visible = visible.max(1);
+        if self.selected < self.scroll_top {
+            self.scroll_top = self.selected;
+        } else if self.selected >= self.scroll_top + visible {
+            self.scroll_top = self.selected + 1 - visible;
+        }
+    }
+
+    pub fn scroll_by(&mut self, rows: isize, visible: usize) {
+        let max_top = self.count.saturating_sub(visible.max(1));
+        self.scroll_top = self.scroll_top.saturating_add_signed(rows).min(max_top);
+    }
+}
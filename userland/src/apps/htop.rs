@@ -0,0 +1,355 @@
+//! Task monitor: every live task with its state, priority, CPU use,
+//! resident size and window title, refreshed once a second from
+//! `SYSCALL_TASK_SNAPSHOT`.
+//!
+//! Keys: `c` / `p` / `m` / `n` sort by CPU, PID, memory or name (again to
+//! reverse), up and down move the selection, `k` sends the selected task
+//! SIGTERM and `K` SIGKILL, `q` quits.
+
+use core::ffi::c_void;
+
+use slopos_abi::draw::Color32;
+use slopos_abi::input::{SCANCODE_DOWN, SCANCODE_UP};
+use slopos_abi::signal::{SIGKILL, SIGTERM};
+use slopos_abi::task::{MAX_TASKS, TaskSnapshot, TaskStatus};
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::appkit::{self, ControlFlow, Event, Window, WindowedApp};
+use crate::gfx::{self, DrawBuffer};
+use crate::syscall::{core as sys_core, process};
+use crate::theme::*;
+
+const HTOP_WIDTH: u32 = 600;
+const HTOP_HEIGHT: u32 = 400;
+const ROW_HEIGHT: i32 = 18;
+const MARGIN_X: i32 = 8;
+/// Time between snapshots.
+const SAMPLE_MS: u64 = 1_000;
+/// KiB per page, for the RSS column.
+const PAGE_KIB: u64 = 4;
+
+const COLOR_HEADER: Color32 = COLOR_TITLE_BAR;
+const COLOR_SORTED: Color32 = Color32::rgb(0x4E, 0xC9, 0x6B);
+const COLOR_ROW_SELECTED: Color32 = COLOR_BUTTON_HOVER;
+const COLOR_ERROR: Color32 = Color32::rgb(0xE8, 0x11, 0x23);
+
+/// Column widths in characters, each followed by a space.
+const COL_PID: usize = 5;
+const COL_STATE: usize = 7;
+const COL_PRI: usize = 3;
+const COL_CPU: usize = 4;
+const COL_RSS: usize = 8;
+const COL_NAME: usize = 16;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Cpu,
+    Pid,
+    Rss,
+    Name,
+}
+
+impl SortKey {
+    fn from_key(ascii: u8) -> Option<Self> {
+        match ascii {
+            b'c' => Some(Self::Cpu),
+            b'p' => Some(Self::Pid),
+            b'm' => Some(Self::Rss),
+            b'n' => Some(Self::Name),
+            _ => None,
+        }
+    }
+
+    /// Busiest and biggest first, lowest PID and name first.
+    fn descending_by_default(self) -> bool {
+        matches!(self, Self::Cpu | Self::Rss)
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Row {
+    task: TaskSnapshot,
+    cpu_percent: u32,
+}
+
+/// One line of text built column by column.
+struct Line {
+    buf: [u8; 96],
+    len: usize,
+}
+
+impl Line {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 96],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let take = bytes.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&bytes[..take]);
+        self.len += take;
+    }
+
+    fn pad(&mut self, count: usize) {
+        for _ in 0..count {
+            self.push(b" ");
+        }
+    }
+
+    /// `text` cut or padded to `width`, then a space.
+    fn left(&mut self, text: &[u8], width: usize) {
+        let text = &text[..text.len().min(width)];
+        self.push(text);
+        self.pad(width - text.len() + 1);
+    }
+
+    /// `text` right-aligned in `width`, then a space.
+    fn right(&mut self, text: &[u8], width: usize) {
+        self.pad(width.saturating_sub(text.len()));
+        self.push(text);
+        self.pad(1);
+    }
+
+    fn number(&mut self, value: u64, width: usize) {
+        let mut num = NumBuf::<21>::new();
+        self.right(numfmt::trim_nul(num.format_u64(value)), width);
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+fn state_name(status: TaskStatus) -> &'static [u8] {
+    match status {
+        TaskStatus::Running => b"running",
+        TaskStatus::Ready => b"ready",
+        TaskStatus::Blocked => b"blocked",
+        TaskStatus::Terminated => b"zombie",
+        TaskStatus::Invalid => b"?",
+    }
+}
+
+pub struct HtopApp {
+    rows: [Row; MAX_TASKS],
+    row_count: usize,
+    previous: [TaskSnapshot; MAX_TASKS],
+    previous_count: usize,
+    sort: SortKey,
+    descending: bool,
+    /// Task ID of the selected row, kept across refreshes.
+    selected: Option<u32>,
+    /// Result of the last kill, shown in the footer.
+    status: Option<(&'static str, bool)>,
+    next_sample_ms: u64,
+}
+
+impl HtopApp {
+    fn new() -> Self {
+        Self {
+            rows: [Row::default(); MAX_TASKS],
+            row_count: 0,
+            previous: [TaskSnapshot::default(); MAX_TASKS],
+            previous_count: 0,
+            sort: SortKey::Cpu,
+            descending: true,
+            selected: None,
+            status: None,
+            next_sample_ms: 0,
+        }
+    }
+
+    fn sample(&mut self) {
+        let mut current = [TaskSnapshot::default(); MAX_TASKS];
+        let Ok(count) = sys_core::task_snapshot(&mut current) else {
+            return;
+        };
+        for (row, task) in self.rows.iter_mut().zip(&current[..count]) {
+            let earlier = self.previous[..self.previous_count]
+                .iter()
+                .find(|prev| prev.task_id == task.task_id);
+            *row = Row {
+                task: *task,
+                cpu_percent: earlier.map_or(0, |prev| task.cpu_percent_since(prev)),
+            };
+        }
+        self.row_count = count;
+        self.previous = current;
+        self.previous_count = count;
+        self.sort_rows();
+        if self.selected_index().is_none() {
+            self.selected = self.rows[..self.row_count].first().map(|r| r.task.task_id);
+        }
+    }
+
+    fn sort_rows(&mut self) {
+        let (sort, descending) = (self.sort, self.descending);
+        self.rows[..self.row_count].sort_unstable_by(|a, b| {
+            let order = match sort {
+                SortKey::Cpu => a.cpu_percent.cmp(&b.cpu_percent),
+                SortKey::Pid => a.task.task_id.cmp(&b.task.task_id),
+                SortKey::Rss => a.task.rss_pages.cmp(&b.task.rss_pages),
+                SortKey::Name => a.task.name_str().cmp(b.task.name_str()),
+            };
+            let order = if descending { order.reverse() } else { order };
+            order.then(a.task.task_id.cmp(&b.task.task_id))
+        });
+    }
+
+    fn set_sort(&mut self, key: SortKey) {
+        if self.sort == key {
+            self.descending = !self.descending;
+        } else {
+            self.sort = key;
+            self.descending = key.descending_by_default();
+        }
+        self.sort_rows();
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        let id = self.selected?;
+        self.rows[..self.row_count]
+            .iter()
+            .position(|row| row.task.task_id == id)
+    }
+
+    fn move_selection(&mut self, down: bool) {
+        if self.row_count == 0 {
+            return;
+        }
+        let index = match (self.selected_index(), down) {
+            (None, _) => 0,
+            (Some(i), true) => (i + 1).min(self.row_count - 1),
+            (Some(i), false) => i.saturating_sub(1),
+        };
+        self.selected = Some(self.rows[index].task.task_id);
+    }
+
+    fn kill_selected(&mut self, signum: u8) {
+        let Some(id) = self.selected else {
+            return;
+        };
+        self.status = Some(if process::kill(id, signum) == 0 {
+            ("signal sent", false)
+        } else {
+            ("kill failed: not permitted or gone", true)
+        });
+    }
+
+    fn draw_header(&self, fb: &mut DrawBuffer<'_>, width: i32) {
+        gfx::fill_rect(fb, 0, 0, width, ROW_HEIGHT, COLOR_HEADER);
+        let columns: [(&str, usize, Option<SortKey>); 7] = [
+            ("  PID", COL_PID, Some(SortKey::Pid)),
+            ("STATE", COL_STATE, None),
+            ("PRI", COL_PRI, None),
+            ("CPU%", COL_CPU, Some(SortKey::Cpu)),
+            ("RSS KiB", COL_RSS, Some(SortKey::Rss)),
+            ("NAME", COL_NAME, Some(SortKey::Name)),
+            ("TITLE", 0, None),
+        ];
+        let mut x = MARGIN_X;
+        for (label, chars, key) in columns {
+            let color = if key == Some(self.sort) {
+                COLOR_SORTED
+            } else {
+                COLOR_TEXT
+            };
+            gfx::font::draw_string(fb, x, 1, label, color, COLOR_HEADER);
+            x += (chars as i32 + 1) * gfx::font::FONT_CHAR_WIDTH;
+        }
+    }
+
+    fn draw_row(&self, fb: &mut DrawBuffer<'_>, row: &Row, y: i32, width: i32) {
+        let task = &row.task;
+        let mut line = Line::new();
+        line.number(task.task_id as u64, COL_PID);
+        line.left(state_name(task.status()), COL_STATE);
+        line.number(task.priority as u64, COL_PRI);
+        line.number(row.cpu_percent as u64, COL_CPU);
+        line.number(task.rss_pages as u64 * PAGE_KIB, COL_RSS);
+        line.left(task.name_str().as_bytes(), COL_NAME);
+        line.push(task.title_str().as_bytes());
+
+        let bg = if self.selected == Some(task.task_id) {
+            COLOR_ROW_SELECTED
+        } else {
+            FM_COLOR_BG
+        };
+        gfx::fill_rect(fb, 0, y, width, ROW_HEIGHT, bg);
+        gfx::font::draw_string(fb, MARGIN_X, y + 1, line.as_str(), FM_COLOR_FG, bg);
+    }
+}
+
+impl WindowedApp for HtopApp {
+    fn init(&mut self, win: &mut Window) {
+        win.set_title("htop");
+        win.request_redraw();
+    }
+
+    fn on_event(&mut self, win: &mut Window, event: Event) -> ControlFlow {
+        match event {
+            Event::CloseRequest => return ControlFlow::Exit,
+            Event::KeyPress { ascii: b'q', .. } => return ControlFlow::Exit,
+            Event::KeyPress {
+                scancode, ascii, ..
+            } => {
+                self.status = None;
+                match (scancode, ascii) {
+                    (SCANCODE_UP, 0) => self.move_selection(false),
+                    (SCANCODE_DOWN, 0) => self.move_selection(true),
+                    (_, b'k') => self.kill_selected(SIGTERM),
+                    (_, b'K') => self.kill_selected(SIGKILL),
+                    (_, key) => match SortKey::from_key(key) {
+                        Some(sort) => self.set_sort(sort),
+                        None => return ControlFlow::Continue,
+                    },
+                }
+                win.request_redraw();
+            }
+            _ => {}
+        }
+        ControlFlow::Continue
+    }
+
+    fn update(&mut self, win: &mut Window) {
+        let now = sys_core::get_time_ms();
+        if now >= self.next_sample_ms {
+            self.next_sample_ms = now + SAMPLE_MS;
+            self.sample();
+            win.request_redraw();
+        }
+    }
+
+    fn draw(&mut self, fb: &mut DrawBuffer<'_>) {
+        let width = fb.width() as i32;
+        let height = fb.height() as i32;
+        gfx::fill_rect(fb, 0, 0, width, height, FM_COLOR_BG);
+        self.draw_header(fb, width);
+
+        let footer_y = height - ROW_HEIGHT;
+        let visible = ((footer_y - ROW_HEIGHT) / ROW_HEIGHT).max(0) as usize;
+        // Scroll just far enough to keep the selection on screen.
+        let first = self
+            .selected_index()
+            .map_or(0, |i| (i + 1).saturating_sub(visible));
+        let mut y = ROW_HEIGHT;
+        for row in self.rows[..self.row_count].iter().skip(first).take(visible) {
+            self.draw_row(fb, row, y, width);
+            y += ROW_HEIGHT;
+        }
+
+        gfx::fill_rect(fb, 0, footer_y, width, ROW_HEIGHT, COLOR_HEADER);
+        let (text, color) = match self.status {
+            Some((message, true)) => (message, COLOR_ERROR),
+            Some((message, false)) => (message, COLOR_TEXT),
+            None => ("c/p/m/n sort  k term  K kill  q quit", COLOR_TEXT),
+        };
+        gfx::font::draw_string(fb, MARGIN_X, footer_y + 1, text, color, COLOR_HEADER);
+    }
+}
+
+pub fn htop_main(_arg: *mut c_void) -> ! {
+    appkit::run(HtopApp::new(), HTOP_WIDTH, HTOP_HEIGHT)
+}
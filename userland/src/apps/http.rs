@@ -0,0 +1,398 @@
+//! Minimal HTTP/1.1 client pieces shared by `wget` and `fetch`: URL
+//! splitting, GET request construction and an incremental response parser
+//! that strips the status line and headers and undoes chunked transfer
+//! encoding.
+
+pub const DEFAULT_PORT: u16 = 80;
+pub const MAX_HEAD_LEN: usize = 2048;
+
+/// A parsed `http://host[:port][/path]` URL borrowing from the argument.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Url<'a> {
+    pub host: &'a [u8],
+    pub port: u16,
+    pub path: &'a [u8],
+}
+
+pub fn parse_url(url: &[u8]) -> Option<Url<'_>> {
+    let rest = url.strip_prefix(b"http://").unwrap_or(url);
+    let split = rest.iter().position(|&b| b == b'/').unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(split);
+    let path: &[u8] = if path.is_empty() { b"/" } else { path };
+
+    let (host, port) = match authority.iter().position(|&b| b == b':') {
+        Some(colon) => (
+            &authority[..colon],
+            parse_dec(&authority[colon + 1..]).filter(|&p| p > 0 && p <= u16::MAX as u32)? as u16,
+        ),
+        None => (authority, DEFAULT_PORT),
+    };
+    if host.is_empty() || url.starts_with(b"https://") {
+        return None;
+    }
+    Some(Url { host, port, path })
+}
+
+/// Write a `GET` request for `url` into `buf`, returning its length or
+/// `None` if it does not fit.
+pub fn build_request(url: &Url<'_>, user_agent: &[u8], buf: &mut [u8]) -> Option<usize> {
+    let mut w = Writer { buf, len: 0 };
+    w.put(b"GET ")?;
+    w.put(url.path)?;
+    w.put(b" HTTP/1.1\r\nHost: ")?;
+    w.put(url.host)?;
+    if url.port != DEFAULT_PORT {
+        w.put(b":")?;
+        let mut tmp = [0u8; 5];
+        w.put(format_dec(url.port as u32, &mut tmp))?;
+    }
+    w.put(b"\r\nUser-Agent: ")?;
+    w.put(user_agent)?;
+    w.put(b"\r\nAccept: */*\r\nConnection: close\r\n\r\n")?;
+    Some(w.len)
+}
+
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, data: &[u8]) -> Option<()> {
+        let end = self.len.checked_add(data.len())?;
+        self.buf.get_mut(self.len..end)?.copy_from_slice(data);
+        self.len = end;
+        Some(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HttpError {
+    /// Status line or headers did not parse, or exceeded `MAX_HEAD_LEN`.
+    BadHeader,
+    /// A chunk-size line was not valid hex.
+    BadChunk,
+    /// The connection closed before the advertised body length arrived.
+    Truncated,
+}
+
+impl HttpError {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HttpError::BadHeader => "malformed response header",
+            HttpError::BadChunk => "malformed chunked encoding",
+            HttpError::Truncated => "connection closed early",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Head,
+    Body,
+    ChunkSize,
+    ChunkData,
+    ChunkEnd,
+    Trailer,
+    Done,
+}
+
+/// Incremental response parser. Feed it every received segment; body bytes
+/// are handed to the sink with headers and chunk framing removed.
+pub struct Response {
+    state: State,
+    head: [u8; MAX_HEAD_LEN],
+    head_len: usize,
+    pub status: u16,
+    /// `Content-Length`, when the server sent one.
+    pub content_length: Option<u64>,
+    pub chunked: bool,
+    /// Body bytes delivered so far.
+    pub received: u64,
+    /// Bytes left in the current chunk, or of the whole body when not chunked.
+    remaining: u64,
+    size: u64,
+    size_digits: u32,
+    /// Past the hex digits of a chunk-size line; the rest is extensions.
+    size_ext: bool,
+    line_empty: bool,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Response {
+    pub const fn new() -> Self {
+        Self {
+            state: State::Head,
+            head: [0; MAX_HEAD_LEN],
+            head_len: 0,
+            status: 0,
+            content_length: None,
+            chunked: false,
+            received: 0,
+            remaining: 0,
+            size: 0,
+            size_digits: 0,
+            size_ext: false,
+            line_empty: true,
+        }
+    }
+
+    /// Status line and headers as received, without the blank line that
+    /// ends them.  Empty until [`Response::head_done`].
+    pub fn head(&self) -> &[u8] {
+        if self.head_done() {
+            &self.head[..self.head_len - 4]
+        } else {
+            &[]
+        }
+    }
+
+    /// True once the status line and headers have been parsed.
+    pub fn head_done(&self) -> bool {
+        self.state != State::Head
+    }
+
+    /// True once the body is complete according to its framing. A body with
+    /// neither length nor chunking only ends when the connection closes.
+    pub fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    /// Consume `data`, passing body bytes to `sink`, and return how many
+    /// bytes were used. Stops right after the header block so the caller can
+    /// inspect the status before any body arrives; feed the rest again.
+    pub fn feed(&mut self, data: &[u8], sink: &mut dyn FnMut(&[u8])) -> Result<usize, HttpError> {
+        let total = data.len();
+        let mut data = data;
+        while !data.is_empty() {
+            data = match self.state {
+                State::Head => {
+                    let rest = self.feed_head(data)?;
+                    if self.head_done() {
+                        return Ok(total - rest.len());
+                    }
+                    rest
+                }
+                State::Body => {
+                    let take = match self.content_length {
+                        Some(_) => data.len().min(self.remaining as usize),
+                        None => data.len(),
+                    };
+                    self.deliver(&data[..take], sink);
+                    if self.content_length.is_some() && self.remaining == 0 {
+                        self.state = State::Done;
+                    }
+                    &data[take..]
+                }
+                State::ChunkSize => self.feed_chunk_size(data)?,
+                State::ChunkData => {
+                    let take = data.len().min(self.remaining as usize);
+                    self.deliver(&data[..take], sink);
+                    if self.remaining == 0 {
+                        self.state = State::ChunkEnd;
+                    }
+                    &data[take..]
+                }
+                State::ChunkEnd => {
+                    // Skip the CRLF that closes each chunk's data.
+                    match data.iter().position(|&b| b == b'\n') {
+                        Some(lf) => {
+                            self.state = State::ChunkSize;
+                            &data[lf + 1..]
+                        }
+                        None => &[],
+                    }
+                }
+                State::Trailer => self.feed_trailer(data),
+                State::Done => &[],
+            };
+        }
+        Ok(total)
+    }
+
+    /// Called when the peer closes the connection.
+    pub fn finish(&mut self) -> Result<(), HttpError> {
+        match self.state {
+            State::Head => Err(HttpError::BadHeader),
+            State::Body if self.content_length.is_none() => {
+                self.state = State::Done;
+                Ok(())
+            }
+            State::Done => Ok(()),
+            _ => Err(HttpError::Truncated),
+        }
+    }
+
+    fn deliver(&mut self, body: &[u8], sink: &mut dyn FnMut(&[u8])) {
+        if body.is_empty() {
+            return;
+        }
+        self.received += body.len() as u64;
+        self.remaining = self.remaining.saturating_sub(body.len() as u64);
+        sink(body);
+    }
+
+    fn feed_head<'d>(&mut self, data: &'d [u8]) -> Result<&'d [u8], HttpError> {
+        // Copy byte-wise so the terminator can straddle segments.
+        for (i, &b) in data.iter().enumerate() {
+            if self.head_len == MAX_HEAD_LEN {
+                return Err(HttpError::BadHeader);
+            }
+            self.head[self.head_len] = b;
+            self.head_len += 1;
+            if self.head[..self.head_len].ends_with(b"\r\n\r\n") {
+                self.parse_head()?;
+                return Ok(&data[i + 1..]);
+            }
+        }
+        Ok(&[])
+    }
+
+    fn parse_head(&mut self) -> Result<(), HttpError> {
+        let head_len = self.head_len - 4;
+        let mut lines = self.head[..head_len].split(|&b| b == b'\n');
+        let status_line = trim(lines.next().ok_or(HttpError::BadHeader)?);
+        if !status_line.starts_with(b"HTTP/1.") {
+            return Err(HttpError::BadHeader);
+        }
+        let code = status_line.get(9..12).ok_or(HttpError::BadHeader)?;
+        let status = parse_dec(code).ok_or(HttpError::BadHeader)?;
+
+        let mut content_length = None;
+        let mut chunked = false;
+        for line in lines {
+            let line = trim(line);
+            let Some(colon) = line.iter().position(|&b| b == b':') else {
+                continue;
+            };
+            let (name, value) = (&line[..colon], trim(&line[colon + 1..]));
+            if name.eq_ignore_ascii_case(b"content-length") {
+                content_length = Some(parse_dec_u64(value).ok_or(HttpError::BadHeader)?);
+            } else if name.eq_ignore_ascii_case(b"transfer-encoding") {
+                chunked = contains_ignore_case(value, b"chunked");
+            }
+        }
+
+        self.status = status as u16;
+        self.chunked = chunked;
+        // Chunked framing wins over Content-Length (RFC 9112 section 6.3).
+        self.content_length = if chunked { None } else { content_length };
+        self.state = if chunked {
+            State::ChunkSize
+        } else if self.content_length == Some(0) {
+            State::Done
+        } else {
+            State::Body
+        };
+        self.remaining = self.content_length.unwrap_or(0);
+        Ok(())
+    }
+
+    fn feed_chunk_size<'d>(&mut self, data: &'d [u8]) -> Result<&'d [u8], HttpError> {
+        for (i, &b) in data.iter().enumerate() {
+            match b {
+                b'\n' => {
+                    if self.size_digits == 0 {
+                        return Err(HttpError::BadChunk);
+                    }
+                    self.remaining = self.size;
+                    self.state = if self.size == 0 {
+                        self.line_empty = true;
+                        State::Trailer
+                    } else {
+                        State::ChunkData
+                    };
+                    self.size = 0;
+                    self.size_digits = 0;
+                    self.size_ext = false;
+                    return Ok(&data[i + 1..]);
+                }
+                // Chunk extensions (";name=value") and the CR are ignored.
+                _ if self.size_ext => {}
+                b';' | b'\r' | b' ' | b'\t' => self.size_ext = true,
+                _ => {
+                    let digit = (b as char).to_digit(16).ok_or(HttpError::BadChunk)?;
+                    if self.size_digits >= 15 {
+                        return Err(HttpError::BadChunk);
+                    }
+                    self.size = self.size << 4 | digit as u64;
+                    self.size_digits += 1;
+                }
+            }
+        }
+        Ok(&[])
+    }
+
+    fn feed_trailer<'d>(&mut self, data: &'d [u8]) -> &'d [u8] {
+        for (i, &b) in data.iter().enumerate() {
+            match b {
+                b'\n' if self.line_empty => {
+                    self.state = State::Done;
+                    return &data[i + 1..];
+                }
+                b'\n' => self.line_empty = true,
+                b'\r' => {}
+                _ => self.line_empty = false,
+            }
+        }
+        &[]
+    }
+}
+
+fn trim(mut s: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = s {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        s = rest;
+    }
+    while let [rest @ .., last] = s {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        s = rest;
+    }
+    s
+}
+
+fn contains_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|w| w.eq_ignore_ascii_case(needle))
+}
+
+fn parse_dec(s: &[u8]) -> Option<u32> {
+    parse_dec_u64(s).and_then(|v| u32::try_from(v).ok())
+}
+
+fn parse_dec_u64(s: &[u8]) -> Option<u64> {
+    if s.is_empty() {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for &b in s {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        value = value.checked_mul(10)?.checked_add((b - b'0') as u64)?;
+    }
+    Some(value)
+}
+
+pub fn format_dec(mut value: u32, tmp: &mut [u8]) -> &[u8] {
+    let mut n = tmp.len();
+    loop {
+        n -= 1;
+        tmp[n] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 || n == 0 {
+            break;
+        }
+    }
+    &tmp[n..]
+}
@@ -1,6 +1,13 @@
 use core::ffi::c_void;
 
-use crate::syscall::{UserNetInfo, core::exit_with_code, fs, net::net_info, tty};
+use slopos_abi::net::{
+    NET_CONFIG_DHCP, NET_CONFIG_STATIC, NET_LEASE_BOUND, NET_LEASE_INFINITE, NET_LEASE_INIT,
+    NET_LEASE_NONE, NET_LEASE_REBINDING, NET_LEASE_RENEWING, NET_LEASE_REQUESTING,
+    NET_LEASE_SELECTING, NetLease,
+};
+
+use crate::syscall::net::{net_info, net_lease};
+use crate::syscall::{UserNetInfo, core::exit_with_code, fs, tty};
 
 fn write_out(buf: &[u8]) {
     if fs::write_slice(1, buf).is_err() {
@@ -48,6 +55,66 @@ fn write_u16_dec(mut value: u16, out: &mut [u8], idx: &mut usize) {
     }
 }
 
+fn write_u32_dec(mut value: u32, out: &mut [u8], idx: &mut usize) {
+    let mut tmp = [0u8; 10];
+    let mut n = 0usize;
+    loop {
+        tmp[n] = b'0' + (value % 10) as u8;
+        value /= 10;
+        n += 1;
+        if value == 0 {
+            break;
+        }
+    }
+    while n > 0 {
+        n -= 1;
+        if *idx < out.len() {
+            out[*idx] = tmp[n];
+            *idx += 1;
+        }
+    }
+}
+
+fn write_bytes(bytes: &[u8], out: &mut [u8], idx: &mut usize) {
+    out[*idx..*idx + bytes.len()].copy_from_slice(bytes);
+    *idx += bytes.len();
+}
+
+fn write_secs(label: &[u8], secs: u32, out: &mut [u8], idx: &mut usize) {
+    write_bytes(label, out, idx);
+    if secs == NET_LEASE_INFINITE {
+        write_bytes(b"never", out, idx);
+    } else {
+        write_u32_dec(secs, out, idx);
+        write_bytes(b"s", out, idx);
+    }
+}
+
+/// The DHCP client state and, while a lease is held, its server and
+/// countdowns.
+fn write_lease(lease: &NetLease, out: &mut [u8], idx: &mut usize) {
+    let state: &[u8] = match lease.state {
+        NET_LEASE_INIT => b"init",
+        NET_LEASE_SELECTING => b"selecting",
+        NET_LEASE_REQUESTING => b"requesting",
+        NET_LEASE_BOUND => b"bound",
+        NET_LEASE_RENEWING => b"renewing",
+        NET_LEASE_REBINDING => b"rebinding",
+        _ => b"none",
+    };
+    write_bytes(b"           lease ", out, idx);
+    write_bytes(state, out, idx);
+    if lease.ipv4 != [0; 4] {
+        write_bytes(b"  server ", out, idx);
+        write_ipv4(lease.server, out, idx);
+        write_secs(b"  expires ", lease.expires_in, out, idx);
+        write_secs(b"  renew ", lease.renew_in, out, idx);
+        write_secs(b"  rebind ", lease.rebind_in, out, idx);
+    }
+    out[*idx] = b'\n';
+    *idx += 1;
+}
+
 fn write_hex_byte(value: u8, out: &mut [u8], idx: &mut usize) {
     const HEX: &[u8; 16] = b"0123456789abcdef";
     if *idx + 1 < out.len() {
@@ -82,7 +149,7 @@ pub fn ifconfig_main(_arg: *mut c_void) -> ! {
         exit_with_code(1);
     }
 
-    let mut line = [0u8; 196];
+    let mut line = [0u8; 384];
     let mut i = 0usize;
 
     line[i..i + 8].copy_from_slice(b"virtio0:");
@@ -140,6 +207,26 @@ pub fn ifconfig_main(_arg: *mut c_void) -> ! {
     line[i] = b'\n';
     i += 1;
 
+    let source: &[u8] = match info.config_source {
+        NET_CONFIG_DHCP => b"dhcp",
+        NET_CONFIG_STATIC => b"static",
+        _ => b"none",
+    };
+    line[i..i + 11].copy_from_slice(b"           ");
+    i += 11;
+    line[i..i + 7].copy_from_slice(b"config ");
+    i += 7;
+    line[i..i + source.len()].copy_from_slice(source);
+    i += source.len();
+    line[i] = b'\n';
+    i += 1;
+
+    if let Ok(lease) = net_lease()
+        && lease.state != NET_LEASE_NONE
+    {
+        write_lease(&lease, &mut line, &mut i);
+    }
+
     write_out(&line[..i]);
     crate::syscall::core::exit();
 }
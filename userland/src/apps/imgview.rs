@@ -0,0 +1,484 @@
+//! Image viewer: BMP, PPM and QOI files fitted to the window.
+//!
+//! `imgview [path]` opens an image, or the first image of a directory;
+//! without a path it browses [`IMAGE_DIR`].  The images next to the one
+//! shown are listed by name so the arrow keys can step through them.  The
+//! whole file is read into shared memory, and QOI images are decoded into
+//! a second buffer once, so redrawing after a resize only rescales.
+//!
+//! Keys: left and right (or up and down, Page Up/Down, Backspace and
+//! space) show the previous or next image, Home and End the first and
+//! last.  `f` switches between fitting the window and actual size, `q`
+//! quits.
+
+use core::ffi::c_char;
+
+use slopos_abi::draw::Color32;
+use slopos_abi::fs::{UserFsEntry, UserFsList};
+use slopos_abi::input::{
+    SCANCODE_DOWN, SCANCODE_END, SCANCODE_HOME, SCANCODE_LEFT, SCANCODE_PAGE_DOWN,
+    SCANCODE_PAGE_UP, SCANCODE_RIGHT, SCANCODE_UP,
+};
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::appkit::{self, ControlFlow, Event, Window, WindowedApp};
+use crate::apps::cli::arg_at;
+use crate::gfx::font::FONT_CHAR_WIDTH;
+use crate::gfx::{
+    self, Bmp, DrawBuffer, ImageError, ImageFormat, Ppm, Qoi, RgbaImage, ScaleMode,
+    draw_image_scaled,
+};
+use crate::syscall::{FdGuard, ShmBuffer, USER_FS_OPEN_READ, UserFsStat, fs};
+use crate::theme::*;
+
+const IMGVIEW_WIDTH: u32 = 640;
+const IMGVIEW_HEIGHT: u32 = 480;
+const STATUS_HEIGHT: i32 = 18;
+const MARGIN_X: i32 = 8;
+
+/// Directory browsed when no path is given.
+const IMAGE_DIR: &[u8] = b"/share/images";
+/// Images listed from one directory.
+const MAX_IMAGES: usize = 64;
+/// Directory entries read while looking for images.
+const MAX_DIR_ENTRIES: usize = 96;
+const PATH_MAX: usize = 256;
+/// Bytes in a directory entry name, NUL included.
+const NAME_MAX: usize = 64;
+/// Largest file loaded, and largest decoded QOI image: enough for a 4K
+/// 32-bit image.
+const MAX_IMAGE_BYTES: usize = 3840 * 2160 * 4 + 4096;
+
+const COLOR_IMAGE_BG: Color32 = Color32::rgb(0x10, 0x10, 0x10);
+const COLOR_STATUS: Color32 = COLOR_TITLE_BAR;
+const COLOR_ERROR: Color32 = Color32::rgb(0xE8, 0x11, 0x23);
+
+fn has_image_extension(name: &[u8]) -> bool {
+    [&b".bmp"[..], b".ppm", b".pgm", b".qoi"].iter().any(|ext| {
+        name.len() > ext.len() && name[name.len() - ext.len()..].eq_ignore_ascii_case(ext)
+    })
+}
+
+/// The name in `slot`, up to its NUL.
+fn slot_name(slot: &[u8; NAME_MAX]) -> &[u8] {
+    let len = slot.iter().position(|&b| b == 0).unwrap_or(NAME_MAX);
+    &slot[..len]
+}
+
+fn error_text(err: ImageError) -> &'static str {
+    match err {
+        ImageError::Truncated => "truncated image",
+        ImageError::BadMagic => "not a BMP, PPM or QOI image",
+        ImageError::Unsupported => "unsupported image variant",
+        ImageError::BufferTooSmall => "image too large",
+    }
+}
+
+/// An image file held in shared memory.
+struct Image {
+    file: ShmBuffer,
+    format: ImageFormat,
+    /// The pixels of a QOI image, decoded when it loads.
+    decoded: Option<ShmBuffer>,
+    width: u32,
+    height: u32,
+}
+
+impl Image {
+    fn load(path: *const c_char) -> Result<Self, &'static str> {
+        let mut stat = UserFsStat::default();
+        if fs::stat_path(path, &mut stat).is_err() || !stat.is_file() {
+            return Err("cannot open file");
+        }
+        let size = stat.size as usize;
+        if size == 0 {
+            return Err("empty file");
+        }
+        if size > MAX_IMAGE_BYTES {
+            return Err("file too large");
+        }
+        let Ok(fd) = fs::open_path(path, USER_FS_OPEN_READ) else {
+            return Err("cannot open file");
+        };
+        let fd = unsafe { FdGuard::from_raw(fd) };
+        let mut file = ShmBuffer::create(size).map_err(|_| "out of memory")?;
+        let data = file.as_mut_slice();
+        let mut len = 0;
+        while len < size {
+            match fd.read(&mut data[len..]) {
+                Ok(0) | Err(_) => return Err("read failed"),
+                Ok(n) => len += n,
+            }
+        }
+
+        let Some(format) = ImageFormat::detect(file.as_slice()) else {
+            return Err(error_text(ImageError::BadMagic));
+        };
+        let (width, height) = match format {
+            ImageFormat::Bmp => Bmp::parse(file.as_slice()).map(|i| (i.width(), i.height())),
+            ImageFormat::Ppm => Ppm::parse(file.as_slice()).map(|i| (i.width(), i.height())),
+            ImageFormat::Qoi => Qoi::parse(file.as_slice()).map(|i| (i.width(), i.height())),
+        }
+        .map_err(error_text)?;
+
+        let decoded = match format {
+            ImageFormat::Qoi => {
+                let Ok(qoi) = Qoi::parse(file.as_slice()) else {
+                    return Err(error_text(ImageError::Unsupported));
+                };
+                if qoi.decoded_len() > MAX_IMAGE_BYTES {
+                    return Err(error_text(ImageError::BufferTooSmall));
+                }
+                let mut pixels =
+                    ShmBuffer::create(qoi.decoded_len()).map_err(|_| "out of memory")?;
+                qoi.decode_into(pixels.as_mut_slice()).map_err(error_text)?;
+                Some(pixels)
+            }
+            ImageFormat::Bmp | ImageFormat::Ppm => None,
+        };
+
+        Ok(Self {
+            file,
+            format,
+            decoded,
+            width,
+            height,
+        })
+    }
+
+    /// Cover all of `target` with the image fitted by `mode`.
+    fn draw(&self, target: &mut DrawBuffer<'_>, mode: ScaleMode) {
+        let data = self.file.as_slice();
+        match self.format {
+            ImageFormat::Bmp => {
+                if let Ok(image) = Bmp::parse(data) {
+                    draw_image_scaled(target, &image, mode, COLOR_IMAGE_BG);
+                }
+            }
+            ImageFormat::Ppm => {
+                if let Ok(image) = Ppm::parse(data) {
+                    draw_image_scaled(target, &image, mode, COLOR_IMAGE_BG);
+                }
+            }
+            ImageFormat::Qoi => {
+                let pixels = self.decoded.as_ref().map_or(&[][..], |d| d.as_slice());
+                if let Some(image) = RgbaImage::new(pixels, self.width, self.height) {
+                    draw_image_scaled(target, &image, mode, COLOR_IMAGE_BG);
+                }
+            }
+        }
+    }
+}
+
+/// One line of status text.
+struct Line {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl Line {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 128],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let take = bytes.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&bytes[..take]);
+        self.len += take;
+    }
+
+    fn number(&mut self, value: u32) {
+        let mut num = NumBuf::<12>::new();
+        self.push(numfmt::trim_nul(num.format_u32(value)));
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+struct ImageViewer {
+    /// Directory the images are listed from, without a trailing slash.
+    dir: [u8; PATH_MAX],
+    dir_len: usize,
+    /// Image file names, NUL-terminated and sorted.
+    names: [[u8; NAME_MAX]; MAX_IMAGES],
+    count: usize,
+    current: usize,
+    image: Option<Image>,
+    /// Why the current file could not be shown.
+    error: Option<&'static str>,
+    mode: ScaleMode,
+    title_stale: bool,
+}
+
+impl ImageViewer {
+    fn new() -> Self {
+        Self {
+            dir: [0; PATH_MAX],
+            dir_len: 0,
+            names: [[0; NAME_MAX]; MAX_IMAGES],
+            count: 0,
+            current: 0,
+            image: None,
+            error: None,
+            mode: ScaleMode::Fit,
+            title_stale: true,
+        }
+    }
+
+    fn name(&self, index: usize) -> &[u8] {
+        slot_name(&self.names[index])
+    }
+
+    fn set_dir(&mut self, dir: &[u8]) {
+        // Keep the root's slash; drop any other trailing one.
+        let dir = match dir {
+            [] => &b"."[..],
+            [rest @ .., b'/'] if !rest.is_empty() => rest,
+            _ => dir,
+        };
+        let len = dir.len().min(PATH_MAX - 1);
+        self.dir[..len].copy_from_slice(&dir[..len]);
+        self.dir[len] = 0;
+        self.dir_len = len;
+    }
+
+    /// Browse the directory `path`, or the one holding the image `path`,
+    /// showing `path` itself first.
+    fn open(&mut self, path: &[u8]) {
+        let mut cpath = [0u8; PATH_MAX];
+        let len = path.len().min(PATH_MAX - 1);
+        cpath[..len].copy_from_slice(&path[..len]);
+        let mut stat = UserFsStat::default();
+        let is_dir = fs::stat_path(cpath.as_ptr() as *const c_char, &mut stat).is_ok()
+            && stat.is_directory();
+
+        if is_dir {
+            self.set_dir(path);
+            self.scan(None);
+            return;
+        }
+        let (dir, file) = match path.iter().rposition(|&b| b == b'/') {
+            Some(0) => (&b"/"[..], &path[1..]),
+            Some(slash) => (&path[..slash], &path[slash + 1..]),
+            None => (&b"."[..], path),
+        };
+        self.set_dir(dir);
+        self.scan(Some(file));
+    }
+
+    /// List the images in the directory and show `select`, or the first.
+    /// A `select` without an image extension is listed anyway.
+    fn scan(&mut self, select: Option<&[u8]>) {
+        self.count = 0;
+        let mut entries = [UserFsEntry::new(); MAX_DIR_ENTRIES];
+        let mut list = UserFsList {
+            entries: entries.as_mut_ptr(),
+            max_entries: entries.len() as u32,
+            count: 0,
+        };
+        if fs::list_dir(self.dir.as_ptr() as *const c_char, &mut list).is_ok() {
+            for entry in &entries[..(list.count as usize).min(MAX_DIR_ENTRIES)] {
+                let name = entry.name_str().as_bytes();
+                if !entry.is_directory() && has_image_extension(name) {
+                    self.add_name(name);
+                }
+            }
+        }
+        if let Some(file) = select
+            && !file.is_empty()
+            && !(0..self.count).any(|i| self.name(i) == file)
+        {
+            self.add_name(file);
+        }
+
+        let count = self.count;
+        self.names[..count].sort_unstable_by(|a, b| slot_name(a).cmp(slot_name(b)));
+        let first = select
+            .and_then(|file| (0..count).position(|i| self.name(i) == file))
+            .unwrap_or(0);
+        self.show(first);
+    }
+
+    fn add_name(&mut self, name: &[u8]) {
+        if self.count == MAX_IMAGES || name.len() >= NAME_MAX {
+            return;
+        }
+        let slot = &mut self.names[self.count];
+        *slot = [0; NAME_MAX];
+        slot[..name.len()].copy_from_slice(name);
+        self.count += 1;
+    }
+
+    /// Load and show the image at `index` in the list.
+    fn show(&mut self, index: usize) {
+        // Drop the old buffers before mapping the new ones.
+        self.image = None;
+        self.error = None;
+        self.current = index;
+        self.title_stale = true;
+        if index >= self.count {
+            self.error = Some("no images");
+            return;
+        }
+
+        let mut path = [0u8; PATH_MAX + NAME_MAX + 1];
+        let dir = &self.dir[..self.dir_len];
+        let name = self.name(index);
+        let mut len = dir.len();
+        path[..len].copy_from_slice(dir);
+        if dir != b"/" {
+            path[len] = b'/';
+            len += 1;
+        }
+        path[len..len + name.len()].copy_from_slice(name);
+        match Image::load(path.as_ptr() as *const c_char) {
+            Ok(image) => self.image = Some(image),
+            Err(message) => self.error = Some(message),
+        }
+    }
+
+    /// Show the image `delta` places along the list, if there is one.
+    fn step(&mut self, delta: isize) -> bool {
+        match self.current.checked_add_signed(delta) {
+            Some(index) if index < self.count => {
+                self.show(index);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update_title(&mut self, win: &Window) {
+        if !self.title_stale {
+            return;
+        }
+        self.title_stale = false;
+        let mut title = Line::new();
+        title.push(b"Image Viewer");
+        if self.current < self.count {
+            title.push(b": ");
+            title.push(self.name(self.current));
+        }
+        win.set_title(title.as_str());
+    }
+
+    fn draw_status(&self, fb: &mut DrawBuffer<'_>, y: i32, width: i32) {
+        gfx::fill_rect(fb, 0, y, width, STATUS_HEIGHT, COLOR_STATUS);
+        let mut line = Line::new();
+        let mut color = COLOR_TEXT;
+        if self.count > 0 {
+            line.number(self.current as u32 + 1);
+            line.push(b"/");
+            line.number(self.count as u32);
+            line.push(b"  ");
+        }
+        match (&self.image, self.error) {
+            (_, Some(message)) => {
+                line.push(message.as_bytes());
+                color = COLOR_ERROR;
+            }
+            (Some(image), None) => {
+                line.number(image.width);
+                line.push(b"x");
+                line.number(image.height);
+                line.push(b"  ");
+                line.push(self.mode.name());
+            }
+            (None, None) => {}
+        }
+        gfx::font::draw_string(fb, MARGIN_X, y + 1, line.as_str(), color, COLOR_STATUS);
+
+        let hint = "<- -> browse  f fit  q quit";
+        let hint_x = width - MARGIN_X - hint.len() as i32 * FONT_CHAR_WIDTH;
+        if hint_x > MARGIN_X + line.len as i32 * FONT_CHAR_WIDTH {
+            gfx::font::draw_string(fb, hint_x, y + 1, hint, COLOR_TEXT, COLOR_STATUS);
+        }
+    }
+}
+
+impl WindowedApp for ImageViewer {
+    fn init(&mut self, win: &mut Window) {
+        self.update_title(win);
+        win.request_redraw();
+    }
+
+    fn on_event(&mut self, win: &mut Window, event: Event) -> ControlFlow {
+        match event {
+            Event::CloseRequest => return ControlFlow::Exit,
+            Event::KeyPress { ascii: b'q', .. } => return ControlFlow::Exit,
+            Event::KeyPress {
+                scancode, ascii, ..
+            } => {
+                let changed = match (scancode, ascii) {
+                    (SCANCODE_LEFT | SCANCODE_UP | SCANCODE_PAGE_UP, 0) | (_, 0x08) => {
+                        self.step(-1)
+                    }
+                    (SCANCODE_RIGHT | SCANCODE_DOWN | SCANCODE_PAGE_DOWN, 0) | (_, b' ') => {
+                        self.step(1)
+                    }
+                    (SCANCODE_HOME, 0) => self.current != 0 && self.step(-(self.current as isize)),
+                    (SCANCODE_END, 0) => {
+                        let last = self.count.saturating_sub(1);
+                        self.current != last && self.step((last - self.current) as isize)
+                    }
+                    (_, b'f') => {
+                        self.mode = match self.mode {
+                            ScaleMode::Fit => ScaleMode::Center,
+                            _ => ScaleMode::Fit,
+                        };
+                        true
+                    }
+                    _ => false,
+                };
+                if changed {
+                    self.update_title(win);
+                    win.request_redraw();
+                }
+            }
+            Event::Configure { .. } => win.request_redraw(),
+            _ => {}
+        }
+        ControlFlow::Continue
+    }
+
+    fn draw(&mut self, fb: &mut DrawBuffer<'_>) {
+        let width = fb.width() as i32;
+        let height = fb.height() as i32;
+        let status_y = (height - STATUS_HEIGHT).max(0);
+
+        // The image gets its own view of the rows above the status bar, so
+        // scaling covers exactly that area.
+        let (pitch, bytes_pp, format) = (fb.pitch(), fb.bytes_pp(), fb.pixel_format());
+        let area_h = status_y as u32;
+        match &self.image {
+            Some(image) if area_h > 0 => {
+                let rows = &mut fb.data_mut()[..pitch * area_h as usize];
+                if let Some(mut area) = DrawBuffer::new(rows, width as u32, area_h, pitch, bytes_pp)
+                {
+                    area.set_pixel_format(format);
+                    image.draw(&mut area, self.mode);
+                }
+                fb.add_damage(0, 0, width - 1, status_y - 1);
+            }
+            _ => {
+                gfx::fill_rect(fb, 0, 0, width, status_y, COLOR_IMAGE_BG);
+            }
+        }
+        self.draw_status(fb, status_y, width);
+    }
+}
+
+pub fn imgview_main_args(argc: usize, argv: *const *const u8) -> ! {
+    let mut viewer = ImageViewer::new();
+    if argc > 1 && !argv.is_null() {
+        viewer.open(arg_at(argv, 1));
+    } else {
+        viewer.open(IMAGE_DIR);
+    }
+    appkit::run(viewer, IMGVIEW_WIDTH, IMGVIEW_HEIGHT)
+}
@@ -0,0 +1,331 @@
+//! Conway's Game of Life — demo app and reproducible load generator.
+//!
+//! The grid is a bit-packed torus (64 cells per `u64`) stepped with a
+//! bit-sliced neighbour adder, so a whole word of cells advances in a
+//! handful of ALU ops. Rendering replicates each encoded pixel row with
+//! AVX2 stores straight into the SHM surface.
+//!
+//! Frames are paced by the compositor's frame callbacks rather than a
+//! fixed sleep: a new generation is only drawn once the previous one was
+//! presented. The board is seeded from a fixed LCG, so every run produces
+//! the same workload — handy for scheduler and compositor benchmarks.
+//!
+//! Keys: `space` pauses, `r` reseeds, `n` single-steps while paused.
+
+use core::ffi::c_void;
+
+use slopos_abi::draw::Color32;
+use slopos_lib::numfmt::{NumBuf, trim_nul};
+
+use crate::appkit::{Event, Window};
+use crate::gfx::{self, DrawBuffer};
+use crate::syscall::{core as sys_core, tty};
+use crate::theme::COLOR_BACKGROUND;
+
+const GRID_WORDS: usize = 4;
+const GRID_W: usize = GRID_WORDS * 64;
+const GRID_H: usize = 160;
+const CELL_PX: usize = 3;
+
+const LIFE_WIDTH: u32 = (GRID_W * CELL_PX) as u32;
+const LIFE_HEIGHT: u32 = (GRID_H * CELL_PX) as u32;
+
+const COLOR_CELL: Color32 = Color32::rgb(0x4E, 0xC9, 0x6B);
+
+/// Fixed seed so successive runs generate identical workloads.
+const LIFE_SEED: u64 = 0x5107_0BAD_5EED;
+
+/// Print throughput to the console every this many generations.
+const STATS_INTERVAL: u64 = 240;
+
+/// Give up on a frame callback after this long (e.g. while minimized the
+/// compositor may never present us) and render anyway.
+const FRAME_TIMEOUT_MS: u64 = 100;
+
+type Rows = [[u64; GRID_WORDS]; GRID_H];
+
+struct LifeApp {
+    cells: Rows,
+    next: Rows,
+    seed: u64,
+    generation: u64,
+    alive: u32,
+    paused: bool,
+    dirty: bool,
+    stats_gen: u64,
+    stats_ms: u64,
+}
+
+impl LifeApp {
+    fn new() -> Self {
+        let mut app = Self {
+            cells: [[0; GRID_WORDS]; GRID_H],
+            next: [[0; GRID_WORDS]; GRID_H],
+            seed: LIFE_SEED,
+            generation: 0,
+            alive: 0,
+            paused: false,
+            dirty: true,
+            stats_gen: 0,
+            stats_ms: sys_core::get_time_ms(),
+        };
+        app.reseed();
+        app
+    }
+
+    fn reseed(&mut self) {
+        let mut state = self.seed;
+        let mut next_word = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state
+        };
+        let mut alive = 0;
+        for row in self.cells.iter_mut() {
+            for word in row.iter_mut() {
+                // AND of two draws gives ~25% density, a lively soup.
+                *word = next_word() & next_word();
+                alive += word.count_ones();
+            }
+        }
+        self.alive = alive;
+        self.generation = 0;
+        self.dirty = true;
+    }
+
+    fn step(&mut self) {
+        let cells = &self.cells;
+        let next = &mut self.next;
+        let mut alive = 0;
+
+        for y in 0..GRID_H {
+            let up = &cells[(y + GRID_H - 1) % GRID_H];
+            let mid = &cells[y];
+            let down = &cells[(y + 1) % GRID_H];
+
+            for w in 0..GRID_WORDS {
+                let neighbours = [
+                    west(up, w),
+                    up[w],
+                    east(up, w),
+                    west(mid, w),
+                    east(mid, w),
+                    west(down, w),
+                    down[w],
+                    east(down, w),
+                ];
+
+                // Three-bit counter per lane; a count of 8 wraps to 0,
+                // which is dead either way.
+                let (mut s0, mut s1, mut s2) = (0u64, 0u64, 0u64);
+                for n in neighbours {
+                    let c0 = s0 & n;
+                    s0 ^= n;
+                    let c1 = s1 & c0;
+                    s1 ^= c0;
+                    s2 ^= c1;
+                }
+
+                // Born with 3, survives with 2 or 3.
+                let word = s1 & !s2 & (s0 | mid[w]);
+                next[y][w] = word;
+                alive += word.count_ones();
+            }
+        }
+
+        core::mem::swap(&mut self.cells, &mut self.next);
+        self.alive = alive;
+        self.generation += 1;
+        self.dirty = true;
+    }
+
+    fn on_key(&mut self, ascii: u8) {
+        match ascii {
+            b' ' => self.paused = !self.paused,
+            b'r' | b'R' => {
+                self.seed = self.seed.wrapping_add(1);
+                self.reseed();
+            }
+            b'n' | b'N' if self.paused => self.step(),
+            _ => {}
+        }
+    }
+
+    fn draw(&mut self, fb: &mut DrawBuffer<'_>) {
+        if fb.bytes_pp() != 4 {
+            self.draw_slow(fb);
+            return;
+        }
+
+        let fmt = fb.pixel_format();
+        let cell_px = fmt.encode(COLOR_CELL).to_u32();
+        let bg_px = fmt.encode(COLOR_BACKGROUND).to_u32();
+        let pitch = fb.pitch();
+        let data = fb.data_mut();
+
+        let mut row_px = [0u32; GRID_W * CELL_PX];
+        for (y, row) in self.cells.iter().enumerate() {
+            for x in 0..GRID_W {
+                let live = (row[x / 64] >> (x % 64)) & 1 != 0;
+                let px = if live { cell_px } else { bg_px };
+                row_px[x * CELL_PX..(x + 1) * CELL_PX].fill(px);
+            }
+            for sub in 0..CELL_PX {
+                let off = (y * CELL_PX + sub) * pitch;
+                copy_row(&mut data[off..], &row_px);
+            }
+        }
+        self.dirty = false;
+    }
+
+    /// Per-cell fallback for 24bpp surfaces.
+    fn draw_slow(&mut self, fb: &mut DrawBuffer<'_>) {
+        gfx::fill_rect(
+            fb,
+            0,
+            0,
+            LIFE_WIDTH as i32,
+            LIFE_HEIGHT as i32,
+            COLOR_BACKGROUND,
+        );
+        for (y, row) in self.cells.iter().enumerate() {
+            for x in 0..GRID_W {
+                if (row[x / 64] >> (x % 64)) & 1 != 0 {
+                    gfx::fill_rect(
+                        fb,
+                        (x * CELL_PX) as i32,
+                        (y * CELL_PX) as i32,
+                        CELL_PX as i32,
+                        CELL_PX as i32,
+                        COLOR_CELL,
+                    );
+                }
+            }
+        }
+        self.dirty = false;
+    }
+
+    fn report_stats(&mut self) {
+        if self.generation < self.stats_gen + STATS_INTERVAL {
+            return;
+        }
+        let now = sys_core::get_time_ms();
+        let elapsed = now.saturating_sub(self.stats_ms).max(1);
+        let gens = self.generation - self.stats_gen;
+
+        let mut num = NumBuf::<21>::new();
+        let _ = tty::write(b"life: gen ");
+        let _ = tty::write(trim_nul(num.format_u64(self.generation)));
+        let _ = tty::write(b" alive ");
+        let _ = tty::write(trim_nul(num.format_u32(self.alive)));
+        let _ = tty::write(b" gen/s ");
+        let _ = tty::write(trim_nul(num.format_u64(gens * 1000 / elapsed)));
+        let _ = tty::write(b"\n");
+
+        self.stats_gen = self.generation;
+        self.stats_ms = now;
+    }
+}
+
+/// Cells shifted so each lane holds its western neighbour (x - 1).
+#[inline(always)]
+fn west(row: &[u64; GRID_WORDS], w: usize) -> u64 {
+    let prev = row[(w + GRID_WORDS - 1) % GRID_WORDS];
+    (row[w] << 1) | (prev >> 63)
+}
+
+/// Cells shifted so each lane holds its eastern neighbour (x + 1).
+#[inline(always)]
+fn east(row: &[u64; GRID_WORDS], w: usize) -> u64 {
+    let next = row[(w + 1) % GRID_WORDS];
+    (row[w] >> 1) | (next << 63)
+}
+
+/// Copy one encoded pixel row into a 32bpp surface row.
+fn copy_row(dst: &mut [u8], src: &[u32]) {
+    let dst = &mut dst[..src.len() * 4];
+    let done = copy_row_simd(dst, src);
+    for (i, px) in src[done..].iter().enumerate() {
+        let off = (done + i) * 4;
+        dst[off..off + 4].copy_from_slice(&px.to_le_bytes());
+    }
+}
+
+/// Bulk-copy whole 8-pixel lanes with unaligned AVX2 loads/stores.
+/// Returns the number of pixels copied; the caller finishes the tail.
+#[cfg(target_feature = "avx2")]
+fn copy_row_simd(dst: &mut [u8], src: &[u32]) -> usize {
+    use core::arch::x86_64::{__m256i, _mm256_loadu_si256, _mm256_storeu_si256};
+
+    let lanes = src.len() / 8;
+    for i in 0..lanes {
+        // SAFETY: `(i + 1) * 8 <= src.len()` and `dst.len() == src.len() * 4`,
+        // so both 32-byte unaligned accesses stay inside their slices.
+        unsafe {
+            let v = _mm256_loadu_si256(src.as_ptr().add(i * 8) as *const __m256i);
+            _mm256_storeu_si256(dst.as_mut_ptr().add(i * 32) as *mut __m256i, v);
+        }
+    }
+    lanes * 8
+}
+
+#[cfg(not(target_feature = "avx2"))]
+fn copy_row_simd(_dst: &mut [u8], _src: &[u32]) -> usize {
+    0
+}
+
+pub fn life_main(_arg: *mut c_void) -> ! {
+    let mut win = match Window::new(LIFE_WIDTH, LIFE_HEIGHT) {
+        Ok(w) => w,
+        Err(_) => {
+            let _ = tty::write(b"life: no display\n");
+            sys_core::exit_with_code(1);
+        }
+    };
+    win.set_title("Life");
+
+    let mut app = LifeApp::new();
+    let mut frame_pending_since: Option<u64> = None;
+
+    loop {
+        let mut close = false;
+        win.poll_events(|event| match event {
+            Event::CloseRequest => close = true,
+            Event::KeyPress { ascii, .. } => app.on_key(ascii),
+            _ => {}
+        });
+        if close {
+            sys_core::exit();
+        }
+
+        // Only start the next generation once the compositor has shown
+        // the previous one; this keeps us at exactly the display rate.
+        if let Some(since) = frame_pending_since {
+            let presented = win.surface().poll_frame_done().is_some();
+            let timed_out = sys_core::get_time_ms().saturating_sub(since) > FRAME_TIMEOUT_MS;
+            if !presented && !timed_out {
+                sys_core::yield_now();
+                continue;
+            }
+            frame_pending_since = None;
+        }
+
+        if !app.paused {
+            app.step();
+            app.report_stats();
+        }
+
+        if !app.dirty {
+            sys_core::yield_now();
+            continue;
+        }
+
+        if let Some(mut fb) = win.surface_mut().frame() {
+            app.draw(&mut fb);
+        }
+        win.surface().request_frame_callback();
+        win.surface().present_full();
+        frame_pending_since = Some(sys_core::get_time_ms());
+    }
+}
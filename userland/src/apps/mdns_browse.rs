@@ -0,0 +1,260 @@
+//! `mdns-browse [service] [timeout_ms]` — list mDNS/DNS-SD services on the
+//! local link.
+//!
+//! Joins 224.0.0.251 on port 5353, multicasts one PTR query (by default for
+//! `_services._dns-sd._udp.local`, which asks every responder for its
+//! service types) and prints the PTR, SRV and A records that come back until
+//! the timeout runs out.
+
+use slopos_abi::syscall::POLLIN;
+
+use crate::apps::cli::{Usage, arg_at, parse_u64, write_dec, write_ipv4, write_out};
+use crate::syscall::core::{exit_with_code, get_time_ms};
+use crate::syscall::{RawFd, SockAddrIn, UserPollFd, fs, net};
+
+const MDNS_GROUP: [u8; 4] = [224, 0, 0, 251];
+const MDNS_PORT: u16 = 5353;
+
+const DEFAULT_SERVICE: &[u8] = b"_services._dns-sd._udp.local";
+const DEFAULT_TIMEOUT_MS: u64 = 3000;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+const FLAG_RESPONSE: u16 = 0x8000;
+const HEADER_LEN: usize = 12;
+
+/// Longest dotted name we print; longer ones are cut off.
+const NAME_MAX: usize = 256;
+/// Compression pointers followed per name before giving up on a loop.
+const MAX_POINTER_HOPS: usize = 16;
+
+fn fail(msg: &[u8]) -> ! {
+    write_out(b"mdns-browse: ");
+    write_out(msg);
+    write_out(b"\n");
+    exit_with_code(1);
+}
+
+fn be16(msg: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*msg.get(off)?, *msg.get(off + 1)?]))
+}
+
+/// Build a PTR query for the dotted `service` name.  Returns the length.
+fn build_query(service: &[u8], out: &mut [u8; 512]) -> Option<usize> {
+    // ID 0, standard query, one question.
+    out[..HEADER_LEN].copy_from_slice(&[0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    let mut pos = HEADER_LEN;
+    for label in service.split(|&b| b == b'.').filter(|l| !l.is_empty()) {
+        if label.len() > 63 || pos + 1 + label.len() + 5 > out.len() {
+            return None;
+        }
+        out[pos] = label.len() as u8;
+        out[pos + 1..pos + 1 + label.len()].copy_from_slice(label);
+        pos += 1 + label.len();
+    }
+    out[pos] = 0;
+    out[pos + 1..pos + 3].copy_from_slice(&TYPE_PTR.to_be_bytes());
+    out[pos + 3..pos + 5].copy_from_slice(&CLASS_IN.to_be_bytes());
+    Some(pos + 5)
+}
+
+/// Decode the (possibly compressed) name at `off` into dotted form.
+/// Returns the name length in `out` and the offset just past the name.
+fn read_name(msg: &[u8], mut off: usize, out: &mut [u8; NAME_MAX]) -> Option<(usize, usize)> {
+    let mut len = 0;
+    let mut end = None;
+    let mut hops = 0;
+    loop {
+        let tag = *msg.get(off)?;
+        if tag == 0 {
+            break;
+        }
+        match tag & 0xC0 {
+            0x00 => {
+                let label = msg.get(off + 1..off + 1 + tag as usize)?;
+                if len > 0 && len < NAME_MAX {
+                    out[len] = b'.';
+                    len += 1;
+                }
+                let take = label.len().min(NAME_MAX - len);
+                out[len..len + take].copy_from_slice(&label[..take]);
+                len += take;
+                off += 1 + tag as usize;
+            }
+            0xC0 => {
+                hops += 1;
+                if hops > MAX_POINTER_HOPS {
+                    return None;
+                }
+                end.get_or_insert(off + 2);
+                off = (be16(msg, off)? & 0x3FFF) as usize;
+            }
+            _ => return None,
+        }
+    }
+    Some((len, end.unwrap_or(off + 1)))
+}
+
+/// Print the records of interest in one response.
+fn print_response(src: [u8; 4], msg: &[u8]) {
+    let Some(flags) = be16(msg, 2) else {
+        return;
+    };
+    if flags & FLAG_RESPONSE == 0 {
+        return;
+    }
+    let counts = [be16(msg, 4), be16(msg, 6), be16(msg, 8), be16(msg, 10)];
+    let [Some(qd), Some(an), Some(ns), Some(ar)] = counts else {
+        return;
+    };
+
+    let mut owner = [0u8; NAME_MAX];
+    let mut target = [0u8; NAME_MAX];
+    let mut off = HEADER_LEN;
+    for _ in 0..qd {
+        let Some((_, next)) = read_name(msg, off, &mut owner) else {
+            return;
+        };
+        off = next + 4;
+    }
+
+    for _ in 0..(an as u32 + ns as u32 + ar as u32) {
+        let Some((owner_len, next)) = read_name(msg, off, &mut owner) else {
+            return;
+        };
+        let (Some(rtype), Some(rdlen)) = (be16(msg, next), be16(msg, next + 8)) else {
+            return;
+        };
+        let rdata = next + 10;
+        off = rdata + rdlen as usize;
+        if off > msg.len() {
+            return;
+        }
+
+        match rtype {
+            TYPE_PTR => {
+                let Some((target_len, _)) = read_name(msg, rdata, &mut target) else {
+                    continue;
+                };
+                write_ipv4(src);
+                write_out(b"  PTR ");
+                write_out(&owner[..owner_len]);
+                write_out(b" -> ");
+                write_out(&target[..target_len]);
+                write_out(b"\n");
+            }
+            TYPE_SRV => {
+                let Some(port) = be16(msg, rdata + 4) else {
+                    continue;
+                };
+                let Some((target_len, _)) = read_name(msg, rdata + 6, &mut target) else {
+                    continue;
+                };
+                write_ipv4(src);
+                write_out(b"  SRV ");
+                write_out(&owner[..owner_len]);
+                write_out(b" -> ");
+                write_out(&target[..target_len]);
+                write_out(b":");
+                write_dec(port as u64);
+                write_out(b"\n");
+            }
+            TYPE_A if rdlen == 4 => {
+                write_ipv4(src);
+                write_out(b"  A   ");
+                write_out(&owner[..owner_len]);
+                write_out(b" = ");
+                write_ipv4([msg[rdata], msg[rdata + 1], msg[rdata + 2], msg[rdata + 3]]);
+                write_out(b"\n");
+            }
+            _ => {}
+        }
+    }
+}
+
+const USAGE: Usage = Usage::new(b"usage: mdns-browse [service] [timeout_ms]\n", 1);
+
+fn open_socket() -> RawFd {
+    let Ok(fd) = net::socket(slopos_abi::net::AF_INET, slopos_abi::net::SOCK_DGRAM, 0) else {
+        fail(b"socket creation failed");
+    };
+    // Other mDNS users may already hold the port.
+    let _ = net::set_reuse_addr(fd);
+    if net::bind_any(fd, MDNS_PORT).is_err() {
+        fail(b"cannot bind port 5353");
+    }
+    if net::join_group(fd, MDNS_GROUP).is_err() {
+        fail(b"cannot join 224.0.0.251");
+    }
+    if net::set_nonblocking(fd).is_err() {
+        fail(b"failed to set non-blocking");
+    }
+    fd
+}
+
+pub fn mdns_browse_main_args(argc: usize, argv: *const *const u8) -> ! {
+    if argc > 3 || (argc > 1 && argv.is_null()) {
+        USAGE.exit();
+    }
+    let service = if argc > 1 {
+        arg_at(argv, 1)
+    } else {
+        DEFAULT_SERVICE
+    };
+    let timeout_ms = if argc > 2 {
+        parse_u64(arg_at(argv, 2)).unwrap_or_else(|| USAGE.exit())
+    } else {
+        DEFAULT_TIMEOUT_MS
+    };
+
+    let mut query = [0u8; 512];
+    let Some(query_len) = build_query(service, &mut query) else {
+        fail(b"service name too long");
+    };
+
+    let fd = open_socket();
+    let dest = SockAddrIn {
+        family: slopos_abi::net::AF_INET,
+        port: MDNS_PORT.to_be(),
+        addr: MDNS_GROUP,
+        _pad: [0; 8],
+    };
+    if net::sendto(fd, &query[..query_len], 0, &dest).is_err() {
+        fail(b"query send failed");
+    }
+
+    write_out(b"browsing ");
+    write_out(service);
+    write_out(b" ...\n");
+
+    let mut buf = [0u8; 1500];
+    let deadline = get_time_ms() + timeout_ms;
+    let mut responses = 0u32;
+    while get_time_ms() < deadline {
+        let mut pfds = [UserPollFd {
+            fd,
+            events: POLLIN,
+            revents: 0,
+        }];
+        let _ = fs::poll(&mut pfds, 100);
+        if pfds[0].revents & POLLIN == 0 {
+            continue;
+        }
+        let mut src = SockAddrIn::default();
+        while let Ok(n) = net::recvfrom(fd, &mut buf, 0, Some(&mut src)) {
+            if n == 0 {
+                break;
+            }
+            responses += 1;
+            print_response(src.addr, &buf[..n]);
+        }
+    }
+
+    let _ = net::leave_group(fd, MDNS_GROUP);
+    let _ = fs::close_fd(fd);
+    write_dec(responses as u64);
+    write_out(b" datagram(s) received\n");
+    exit_with_code(0);
+}
@@ -1,9 +1,27 @@
+pub mod beep;
+pub mod cli;
 pub mod compositor;
+pub mod fetch;
 pub mod file_manager;
+pub mod htop;
+pub mod http;
 pub mod ifconfig;
+pub mod imgview;
 pub mod init_process;
+pub mod life;
+pub mod mdns_browse;
 pub mod nc;
+pub mod netstat;
 pub mod nmap;
+pub mod ping;
 pub mod roulette;
 pub mod shell;
+pub mod slopedit;
+pub mod sniff;
 pub mod sysinfo;
+pub mod terminal;
+pub mod top;
+pub mod traceroute;
+pub mod wavplay;
+pub mod wget;
+pub mod xrandr;
@@ -3,13 +3,16 @@
 //! Exercises the full socket lifecycle: socket() → bind()/connect() → send/recv → shutdown().
 //! Phase A supports UDP client and listen modes with half-duplex I/O.
 //! Phase B adds TCP client, listen (with `-k` keep-listening), and makes TCP the default.
+//! With `-k`, TCP listen mode serves several clients at once and sends each stdin line
+//! to all of them.
 
 pub mod tcp;
 pub mod udp;
 
 use core::ffi::c_void;
 
-use crate::syscall::{core::exit_with_code, fs, process, tty};
+use crate::syscall::core::{exit_with_code, get_time_ms, sleep_ms};
+use crate::syscall::{SyscallError, fs, net, process, tty};
 
 // ---------------------------------------------------------------------------
 // Types
@@ -258,7 +261,7 @@ fn print_usage() {
     write_out(b"  -u        UDP mode (default is TCP)\n");
     write_out(b"  -l        Listen mode (bind and accept/receive)\n");
     write_out(b"  -v        Verbose output\n");
-    write_out(b"  -k        Keep listening after client disconnects (TCP -l only)\n");
+    write_out(b"  -k        Keep listening, serving several clients (TCP -l only)\n");
     write_out(b"  -p port   Source port (client mode)\n");
     write_out(b"  -w secs   Timeout in seconds\n");
     write_out(b"  host      Remote hostname or IP (client mode)\n");
@@ -276,6 +279,15 @@ fn print_error(err: NcError) {
     write_out(msg);
 }
 
+/// Report a failed `bind()`, naming the conflict when the port is taken.
+fn print_bind_error(err: SyscallError) {
+    if err == SyscallError::EADDRINUSE {
+        write_out(b"nc: bind failed: address already in use\n");
+    } else {
+        write_out(b"nc: bind failed\n");
+    }
+}
+
 /// Parse a port number from a byte slice.  Returns `None` on invalid input.
 fn parse_port(s: &[u8]) -> Option<u16> {
     if s.is_empty() {
@@ -331,15 +343,29 @@ fn parse_ipv4(s: &[u8]) -> Option<[u8; 4]> {
     Some(octets)
 }
 
+/// How often a lookup bounded by `-w` checks for its answer.
+const RESOLVE_POLL_MS: u32 = 20;
+
 /// Resolve a host argument: try dotted-quad first, then kernel DNS.
-fn resolve_host(host: &[u8]) -> Result<[u8; 4], NcError> {
+///
+/// With a `-w` timeout the lookup is polled so a slow DNS server cannot
+/// hold nc past it.
+fn resolve_host(host: &[u8], timeout_ms: u32) -> Result<[u8; 4], NcError> {
     if let Some(ip) = parse_ipv4(host) {
         return Ok(ip);
     }
-    // Try kernel DNS resolution
-    match crate::syscall::net::resolve(host) {
-        Some(ip) => Ok(ip),
-        None => Err(NcError::ResolveFailed),
+    if timeout_ms == 0 {
+        return net::resolve(host).ok_or(NcError::ResolveFailed);
+    }
+    let deadline = get_time_ms() + timeout_ms as u64;
+    loop {
+        match net::resolve_nonblocking(host) {
+            Ok(ip) => return Ok(ip),
+            Err(err) if err == SyscallError::EAGAIN && get_time_ms() < deadline => {
+                sleep_ms(RESOLVE_POLL_MS);
+            }
+            Err(_) => return Err(NcError::ResolveFailed),
+        }
     }
 }
 
@@ -481,7 +507,7 @@ fn parse_args_from_slices(args: &[&[u8]]) -> Result<NcConfig, NcError> {
             if pos_count < 2 {
                 return Err(NcError::MissingPort);
             }
-            let addr = resolve_host(positional[0])?;
+            let addr = resolve_host(positional[0], timeout_secs * 1000)?;
             let port = parse_port(positional[1]).ok_or(NcError::InvalidPort)?;
             Ok(NcConfig {
                 mode,
@@ -1,11 +1,9 @@
-use crate::syscall::{
-    SockAddrIn, UserPollFd,
-    core::{get_time_ms, sleep_ms},
-    fs, net,
-};
+use crate::syscall::{RawFd, SockAddrIn, UserPollFd, core::get_time_ms, fs, net};
 use slopos_abi::syscall::POLLIN;
 
-use super::{NcConfig, StdinResult, verbose_addr, verbose_bytes, verbose_msg, write_out};
+use super::{
+    NcConfig, StdinResult, print_bind_error, verbose_addr, verbose_bytes, verbose_msg, write_out,
+};
 
 pub(super) fn tcp_client(config: &NcConfig) -> u8 {
     let fd = match net::socket(slopos_abi::net::AF_INET, slopos_abi::net::SOCK_STREAM, 0) {
@@ -17,8 +15,8 @@ pub(super) fn tcp_client(config: &NcConfig) -> u8 {
     };
 
     if config.local_port != 0 {
-        if let Err(_) = net::bind_any(fd, config.local_port) {
-            write_out(b"nc: bind failed (port in use?)\n");
+        if let Err(err) = net::bind_any(fd, config.local_port) {
+            print_bind_error(err);
             return 1;
         }
     }
@@ -154,6 +152,58 @@ pub(super) fn tcp_client(config: &NcConfig) -> u8 {
     }
 }
 
+/// Clients `-k` serves at once; further connections wait in the backlog.
+const MAX_CLIENTS: usize = 8;
+
+/// An accepted connection in listen mode.
+#[derive(Clone, Copy)]
+struct Client {
+    fd: RawFd,
+    last_activity_ms: u64,
+}
+
+fn drop_client(clients: &mut [Option<Client>], slot: usize) {
+    if let Some(client) = clients[slot].take() {
+        let _ = net::shutdown(client.fd, slopos_abi::syscall::SHUT_RDWR);
+        let _ = fs::close_fd(client.fd);
+    }
+}
+
+/// Set up an accepted connection and put it in a free slot.  Returns false
+/// if it was turned away.
+fn add_client(
+    config: &NcConfig,
+    clients: &mut [Option<Client>],
+    fd: RawFd,
+    peer: &SockAddrIn,
+    stdin_closed: bool,
+) -> bool {
+    verbose_addr(
+        config,
+        b"connection from ",
+        peer.addr,
+        u16::from_be(peer.port),
+    );
+    let Some(slot) = clients.iter().position(|c| c.is_none()) else {
+        write_out(b"nc: too many clients, connection refused\n");
+        let _ = fs::close_fd(fd);
+        return false;
+    };
+    if net::set_nonblocking(fd).is_err() {
+        write_out(b"nc: failed to set non-blocking on client socket\n");
+        let _ = fs::close_fd(fd);
+        return false;
+    }
+    if stdin_closed {
+        let _ = net::shutdown(fd, slopos_abi::syscall::SHUT_WR);
+    }
+    clients[slot] = Some(Client {
+        fd,
+        last_activity_ms: get_time_ms(),
+    });
+    true
+}
+
 pub(super) fn tcp_listen(config: &NcConfig) -> u8 {
     let fd = match net::socket(slopos_abi::net::AF_INET, slopos_abi::net::SOCK_STREAM, 0) {
         Ok(fd) => fd,
@@ -165,21 +215,16 @@ pub(super) fn tcp_listen(config: &NcConfig) -> u8 {
 
     let _ = net::set_reuse_addr(fd);
 
-    if let Err(_) = net::bind_any(fd, config.local_port) {
-        write_out(b"nc: bind failed (port in use?)\n");
+    if let Err(err) = net::bind_any(fd, config.local_port) {
+        print_bind_error(err);
         return 1;
     }
 
-    if let Err(_) = net::listen(fd, 1) {
+    if net::listen(fd, slopos_abi::net::SOMAXCONN).is_err() {
         write_out(b"nc: listen failed\n");
         return 1;
     }
 
-    if let Err(_) = net::set_nonblocking(fd) {
-        write_out(b"nc: failed to set non-blocking\n");
-        return 1;
-    }
-
     if config.verbose {
         let mut line = [0u8; 128];
         let mut i = 0usize;
@@ -189,112 +234,151 @@ pub(super) fn tcp_listen(config: &NcConfig) -> u8 {
         write_out(&line[..i]);
     }
 
-    let accept_start = get_time_ms();
+    let capacity = if config.keep_listen { MAX_CLIENTS } else { 1 };
+    let mut client_slots = [None; MAX_CLIENTS];
+    let clients = &mut client_slots[..capacity];
 
-    loop {
+    if config.keep_listen {
+        // Accepted from the poll loop, alongside the clients.
+        if net::set_nonblocking(fd).is_err() {
+            write_out(b"nc: failed to set non-blocking\n");
+            return 1;
+        }
+    } else {
+        // One client: sleep in accept until it connects.
+        if config.timeout_ms > 0 {
+            let _ = net::set_recv_timeout(fd, Some(config.timeout_ms as u64));
+        }
         let mut peer = SockAddrIn::default();
-        let client_fd = loop {
-            if config.timeout_ms > 0 {
-                let elapsed = get_time_ms().wrapping_sub(accept_start);
-                if elapsed >= config.timeout_ms as u64 {
+        let client_fd = match net::accept(fd, Some(&mut peer)) {
+            Ok(cfd) => cfd,
+            Err(_) => {
+                if config.timeout_ms > 0 {
                     write_out(b"nc: timeout waiting for connection\n");
-                    let _ = net::shutdown(fd, slopos_abi::syscall::SHUT_RDWR);
-                    return 1;
+                } else {
+                    write_out(b"nc: accept failed\n");
                 }
-            }
-
-            match net::accept(fd, Some(&mut peer)) {
-                Ok(cfd) => break cfd,
-                Err(_) => sleep_ms(10),
-            }
-        };
-
-        verbose_addr(
-            config,
-            b"connection from ",
-            peer.addr,
-            u16::from_be(peer.port),
-        );
-
-        if let Err(_) = net::set_nonblocking(client_fd) {
-            write_out(b"nc: failed to set non-blocking on client socket\n");
-            let _ = net::shutdown(client_fd, slopos_abi::syscall::SHUT_RDWR);
-            if !config.keep_listen {
+                let _ = net::shutdown(fd, slopos_abi::syscall::SHUT_RDWR);
                 return 1;
             }
-            continue;
+        };
+        if !add_client(config, clients, client_fd, &peer, false) {
+            let _ = net::shutdown(fd, slopos_abi::syscall::SHUT_RDWR);
+            return 1;
         }
+    }
 
-        let mut read_buf = [0u8; 64];
-        let mut line_buf = [0u8; 1024];
-        let mut line_pos = 0usize;
-        let mut recv_buf = [0u8; 2048];
-        let mut stdin_closed = false;
-        let mut last_activity_ms = get_time_ms();
-
-        let client_exit = 'client: loop {
-            let mut pfds = [
-                UserPollFd {
-                    fd: 0,
-                    events: if stdin_closed { 0 } else { POLLIN },
-                    revents: 0,
-                },
-                UserPollFd {
-                    fd: client_fd,
+    let mut read_buf = [0u8; 64];
+    let mut line_buf = [0u8; 1024];
+    let mut line_pos = 0usize;
+    let mut recv_buf = [0u8; 2048];
+    let mut stdin_closed = false;
+    let mut idle_since_ms = get_time_ms();
+
+    let exit_code = 'serve: loop {
+        // stdin, the listener while `-k` has room, then one entry per client.
+        let mut pfds = [UserPollFd {
+            fd: -1,
+            events: 0,
+            revents: 0,
+        }; 2 + MAX_CLIENTS];
+        let mut owner = [usize::MAX; 2 + MAX_CLIENTS];
+        pfds[0] = UserPollFd {
+            fd: 0,
+            events: if stdin_closed { 0 } else { POLLIN },
+            revents: 0,
+        };
+        let mut count = 1;
+        let has_room = clients.iter().any(|c| c.is_none());
+        let listener_polled = config.keep_listen && has_room;
+        if listener_polled {
+            pfds[count] = UserPollFd {
+                fd,
+                events: POLLIN,
+                revents: 0,
+            };
+            count += 1;
+        }
+        for (slot, client) in clients.iter().enumerate() {
+            if let Some(client) = client {
+                pfds[count] = UserPollFd {
+                    fd: client.fd,
                     events: POLLIN,
                     revents: 0,
-                },
-            ];
+                };
+                owner[count] = slot;
+                count += 1;
+            }
+        }
 
-            let _ = fs::poll(&mut pfds, 100);
+        let _ = fs::poll(&mut pfds[..count], 100);
 
-            // --- stdin (raw char-by-char) ---
-            if !stdin_closed && (pfds[0].revents & POLLIN) != 0 {
-                match fs::read_slice(0, &mut read_buf) {
-                    Ok(0) => {
-                        stdin_closed = true;
-                        verbose_msg(config, b"stdin EOF");
-                        let _ = net::shutdown(client_fd, slopos_abi::syscall::SHUT_WR);
+        // --- stdin (raw char-by-char), sent to every client ---
+        if !stdin_closed && (pfds[0].revents & POLLIN) != 0 {
+            match fs::read_slice(0, &mut read_buf) {
+                Ok(0) => {
+                    stdin_closed = true;
+                    verbose_msg(config, b"stdin EOF");
+                    for client in clients.iter().flatten() {
+                        let _ = net::shutdown(client.fd, slopos_abi::syscall::SHUT_WR);
                     }
-                    Ok(n) => {
-                        for i in 0..n {
-                            match super::process_raw_stdin_char(
-                                read_buf[i],
-                                &mut line_buf,
-                                &mut line_pos,
-                            ) {
-                                StdinResult::SendLine(len) => {
-                                    match net::send(client_fd, &line_buf[..len], 0) {
-                                        Ok(sent) => {
-                                            verbose_bytes(config, b"sent ", sent);
-                                            last_activity_ms = get_time_ms();
-                                        }
-                                        Err(_) => {
-                                            write_out(b"nc: send failed (broken pipe)\n");
-                                            let _ = net::shutdown(
-                                                client_fd,
-                                                slopos_abi::syscall::SHUT_RDWR,
-                                            );
-                                            break 'client Some(1u8);
-                                        }
+                }
+                Ok(n) => {
+                    for &c in &read_buf[..n] {
+                        let StdinResult::SendLine(len) =
+                            super::process_raw_stdin_char(c, &mut line_buf, &mut line_pos)
+                        else {
+                            continue;
+                        };
+                        for slot in 0..clients.len() {
+                            let Some(client) = clients[slot].as_mut() else {
+                                continue;
+                            };
+                            match net::send(client.fd, &line_buf[..len], 0) {
+                                Ok(sent) => {
+                                    verbose_bytes(config, b"sent ", sent);
+                                    client.last_activity_ms = get_time_ms();
+                                }
+                                Err(_) => {
+                                    write_out(b"nc: send failed (broken pipe)\n");
+                                    drop_client(clients, slot);
+                                    if !config.keep_listen {
+                                        break 'serve 1;
                                     }
-                                    line_pos = 0;
                                 }
-                                StdinResult::Continue => {}
                             }
                         }
+                        line_pos = 0;
                     }
-                    Err(_) => {}
                 }
+                Err(_) => {}
+            }
+        }
+
+        // --- new connections (`-k`) ---
+        if listener_polled && (pfds[1].revents & POLLIN) != 0 {
+            while clients.iter().any(|c| c.is_none()) {
+                let mut peer = SockAddrIn::default();
+                let Ok(client_fd) = net::accept(fd, Some(&mut peer)) else {
+                    break;
+                };
+                add_client(config, clients, client_fd, &peer, stdin_closed);
             }
+        }
 
-            // --- socket recv ---
-            if (pfds[1].revents & POLLIN) != 0 {
-                match net::recv(client_fd, &mut recv_buf, 0) {
+        // --- client data ---
+        let first_client = if listener_polled { 2 } else { 1 };
+        for i in first_client..count {
+            let slot = owner[i];
+            let Some(client) = clients[slot].as_mut() else {
+                continue;
+            };
+            let mut closed = false;
+            if (pfds[i].revents & POLLIN) != 0 {
+                match net::recv(client.fd, &mut recv_buf, 0) {
                     Ok(0) => {
                         verbose_msg(config, b"connection closed by remote");
-                        let _ = net::shutdown(client_fd, slopos_abi::syscall::SHUT_RDWR);
-                        break 'client None;
+                        closed = true;
                     }
                     Ok(received) => {
                         write_out(&recv_buf[..received]);
@@ -302,42 +386,52 @@ pub(super) fn tcp_listen(config: &NcConfig) -> u8 {
                             write_out(b"\n");
                         }
                         verbose_bytes(config, b"received ", received);
-                        last_activity_ms = get_time_ms();
+                        client.last_activity_ms = get_time_ms();
                     }
                     Err(_) => {}
                 }
             }
-
-            if (pfds[1].revents & (slopos_abi::syscall::POLLHUP | slopos_abi::syscall::POLLERR))
-                != 0
+            if !closed
+                && (pfds[i].revents & (slopos_abi::syscall::POLLHUP | slopos_abi::syscall::POLLERR))
+                    != 0
             {
                 verbose_msg(config, b"connection closed");
-                let _ = net::shutdown(client_fd, slopos_abi::syscall::SHUT_RDWR);
-                break 'client None;
+                closed = true;
             }
-
-            if config.timeout_ms > 0 {
-                let now = get_time_ms();
-                if now.wrapping_sub(last_activity_ms) >= config.timeout_ms as u64 {
-                    write_out(b"nc: timeout\n");
-                    let _ = net::shutdown(client_fd, slopos_abi::syscall::SHUT_RDWR);
-                    break 'client None;
+            if !closed
+                && config.timeout_ms > 0
+                && get_time_ms().wrapping_sub(client.last_activity_ms) >= config.timeout_ms as u64
+            {
+                write_out(b"nc: timeout\n");
+                closed = true;
+            }
+            if closed {
+                drop_client(clients, slot);
+                if !config.keep_listen {
+                    verbose_msg(config, b"exiting (single connection mode)");
+                    break 'serve 0;
                 }
+                idle_since_ms = get_time_ms();
+                verbose_msg(config, b"waiting for next connection");
             }
-        };
-
-        // If the inner loop requested a hard exit (broken pipe), propagate.
-        if let Some(code) = client_exit {
-            let _ = net::shutdown(fd, slopos_abi::syscall::SHUT_RDWR);
-            return code;
         }
 
-        if !config.keep_listen {
-            verbose_msg(config, b"exiting (single connection mode)");
-            let _ = net::shutdown(fd, slopos_abi::syscall::SHUT_RDWR);
-            return 0;
+        // `-w` also bounds how long `-k` waits with no clients at all.
+        if clients.iter().all(|c| c.is_none()) {
+            if config.timeout_ms > 0
+                && get_time_ms().wrapping_sub(idle_since_ms) >= config.timeout_ms as u64
+            {
+                write_out(b"nc: timeout waiting for connection\n");
+                break 'serve 1;
+            }
+        } else {
+            idle_since_ms = get_time_ms();
         }
+    };
 
-        verbose_msg(config, b"waiting for next connection");
+    for slot in 0..clients.len() {
+        drop_client(clients, slot);
     }
+    let _ = net::shutdown(fd, slopos_abi::syscall::SHUT_RDWR);
+    exit_code
 }
@@ -2,7 +2,8 @@ use crate::syscall::{SockAddrIn, UserPollFd, core::get_time_ms, fs, net};
 use slopos_abi::syscall::POLLIN;
 
 use super::{
-    NcConfig, StdinResult, verbose_addr, verbose_bytes, verbose_msg, verbose_recv, write_out,
+    NcConfig, StdinResult, print_bind_error, verbose_addr, verbose_bytes, verbose_msg,
+    verbose_recv, write_out,
 };
 
 pub(super) fn udp_client(config: &NcConfig) -> u8 {
@@ -15,8 +16,8 @@ pub(super) fn udp_client(config: &NcConfig) -> u8 {
     };
 
     if config.local_port != 0 {
-        if let Err(_) = net::bind_any(fd, config.local_port) {
-            write_out(b"nc: bind failed (port in use?)\n");
+        if let Err(err) = net::bind_any(fd, config.local_port) {
+            print_bind_error(err);
             return 1;
         }
     }
@@ -135,8 +136,8 @@ pub(super) fn udp_listen(config: &NcConfig) -> u8 {
 
     let _ = net::set_reuse_addr(fd);
 
-    if let Err(_) = net::bind_any(fd, config.local_port) {
-        write_out(b"nc: bind failed (port in use?)\n");
+    if let Err(err) = net::bind_any(fd, config.local_port) {
+        print_bind_error(err);
         return 1;
     }
 
@@ -0,0 +1,144 @@
+//! `netstat [-t] [-u] [-l]` — list sockets with their queues and counters.
+//!
+//! `-t` and `-u` pick protocols (both by default); `-l` shows only
+//! listening sockets.  For a listener Recv-Q is the number of connections
+//! waiting in `accept`.  Installed as the shell plugin `/bin/slop-netstat`.
+
+use slopos_abi::net::{
+    NET_SOCK_LISTEN, NET_SOCK_PROTO_TCP, NET_SOCK_PROTO_UDP, NetSocketStat, net_sock_state_name,
+};
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::apps::cli::{Usage, arg_at, write_out};
+use crate::syscall::core::exit_with_code;
+use crate::syscall::net;
+
+/// Sockets fetched per `SYSCALL_NET_STAT` call.
+const NETSTAT_BATCH: usize = 16;
+
+const USAGE: Usage = Usage::new(b"usage: netstat [-t] [-u] [-l]\n", 1);
+
+/// Append `bytes` to `line`, then pad with spaces to `width`.
+fn push_column(line: &mut [u8], len: &mut usize, bytes: &[u8], width: usize) {
+    for &b in bytes.iter().chain(core::iter::repeat_n(
+        &b' ',
+        width.saturating_sub(bytes.len()),
+    )) {
+        if *len < line.len() {
+            line[*len] = b;
+            *len += 1;
+        }
+    }
+}
+
+fn push_u64(line: &mut [u8], len: &mut usize, value: u64, width: usize) {
+    let mut buf = NumBuf::<21>::new();
+    let digits = numfmt::trim_nul(buf.format_u64(value));
+    push_column(line, len, &[], width.saturating_sub(digits.len()));
+    push_column(line, len, digits, 0);
+    push_column(line, len, b" ", 0);
+}
+
+fn push_endpoint(line: &mut [u8], len: &mut usize, addr: [u8; 4], port: u16) {
+    let mut text = [0u8; 21];
+    let mut text_len = 0;
+    for (i, octet) in addr.iter().enumerate() {
+        if i > 0 {
+            push_column(&mut text, &mut text_len, b".", 0);
+        }
+        let mut buf = NumBuf::<21>::new();
+        push_column(
+            &mut text,
+            &mut text_len,
+            numfmt::trim_nul(buf.format_u64(*octet as u64)),
+            0,
+        );
+    }
+    push_column(&mut text, &mut text_len, b":", 0);
+    if port == 0 {
+        push_column(&mut text, &mut text_len, b"*", 0);
+    } else {
+        let mut buf = NumBuf::<21>::new();
+        push_column(
+            &mut text,
+            &mut text_len,
+            numfmt::trim_nul(buf.format_u64(port as u64)),
+            0,
+        );
+    }
+    push_column(line, len, &text[..text_len], 22);
+}
+
+fn write_socket(stat: &NetSocketStat) {
+    let mut line = [0u8; 128];
+    let mut len = 0;
+    let proto: &[u8] = match stat.proto {
+        NET_SOCK_PROTO_TCP => b"tcp",
+        NET_SOCK_PROTO_UDP => b"udp",
+        _ => b"raw",
+    };
+    push_column(&mut line, &mut len, proto, 6);
+    push_u64(&mut line, &mut len, stat.recv_queue as u64, 6);
+    push_u64(&mut line, &mut len, stat.send_queue as u64, 6);
+    push_endpoint(&mut line, &mut len, stat.local_addr, stat.local_port);
+    push_endpoint(&mut line, &mut len, stat.remote_addr, stat.remote_port);
+    push_column(
+        &mut line,
+        &mut len,
+        net_sock_state_name(stat.state).as_bytes(),
+        12,
+    );
+    push_u64(&mut line, &mut len, stat.rx_bytes, 9);
+    push_u64(&mut line, &mut len, stat.tx_bytes, 9);
+    push_u64(&mut line, &mut len, stat.rx_dropped, 5);
+    push_column(&mut line, &mut len, b"\n", 0);
+    write_out(&line[..len]);
+}
+
+pub fn netstat_main_args(argc: usize, argv: *const *const u8) -> ! {
+    if argc > 1 && argv.is_null() {
+        USAGE.exit();
+    }
+    let (mut tcp, mut udp, mut listening) = (false, false, false);
+    for idx in 1..argc {
+        let word = arg_at(argv, idx);
+        if word.len() < 2 || word[0] != b'-' {
+            USAGE.exit();
+        }
+        for &flag in &word[1..] {
+            match flag {
+                b't' => tcp = true,
+                b'u' => udp = true,
+                b'l' => listening = true,
+                _ => USAGE.exit(),
+            }
+        }
+    }
+    if !tcp && !udp {
+        (tcp, udp) = (true, true);
+    }
+
+    write_out(b"Proto Recv-Q Send-Q Local Address         Foreign Address       State              RX        TX  Drop\n");
+    let mut batch = [NetSocketStat::default(); NETSTAT_BATCH];
+    let mut start = 0;
+    loop {
+        let Ok(count) = net::net_stat(&mut batch, start) else {
+            write_out(b"netstat: cannot read socket table\n");
+            exit_with_code(1);
+        };
+        for stat in &batch[..count] {
+            let shown = match stat.proto {
+                NET_SOCK_PROTO_TCP => tcp,
+                NET_SOCK_PROTO_UDP => udp,
+                _ => tcp && udp,
+            };
+            if shown && (!listening || stat.state == NET_SOCK_LISTEN) {
+                write_socket(stat);
+            }
+        }
+        if count < NETSTAT_BATCH {
+            exit_with_code(0);
+        }
+        start = batch[count - 1].index + 1;
+    }
+}
@@ -0,0 +1,171 @@
+//! `ping [-c count] [-s size] [-W timeout_ms] host` — send ICMP echo
+//! requests and report round-trip times.
+//!
+//! Sends one request a second (default four, since there is no Ctrl-C
+//! summary) through `SYSCALL_NET_PING` and prints the classic per-reply
+//! lines followed by loss and min/avg/max statistics.
+
+use slopos_abi::net::{NET_PING_MAX_PAYLOAD, NetPingRequest};
+
+use crate::apps::cli::{Usage, arg_at, parse_u32, write_dec, write_fixed, write_ipv4, write_out};
+use crate::syscall::core::{exit_with_code, get_time_ms, sleep_ms};
+use crate::syscall::process::getpid;
+use crate::syscall::{SyscallError, net};
+
+const DEFAULT_COUNT: u32 = 4;
+const DEFAULT_PAYLOAD: u16 = 56;
+const DEFAULT_TIMEOUT_MS: u32 = 1000;
+const INTERVAL_MS: u64 = 1000;
+
+const ICMP_HEADER_LEN: u64 = 8;
+
+const USAGE: Usage = Usage::new(
+    b"usage: ping [-c count] [-s size] [-W timeout_ms] host\n",
+    2,
+);
+
+struct Options<'a> {
+    host: &'a [u8],
+    count: u32,
+    payload_len: u16,
+    timeout_ms: u32,
+}
+
+fn parse_args<'a>(argc: usize, argv: *const *const u8) -> Options<'a> {
+    if argc < 2 || argv.is_null() {
+        USAGE.exit();
+    }
+    let mut opts = Options {
+        host: &[],
+        count: DEFAULT_COUNT,
+        payload_len: DEFAULT_PAYLOAD,
+        timeout_ms: DEFAULT_TIMEOUT_MS,
+    };
+    let mut idx = 1;
+    while idx < argc {
+        let arg = arg_at(argv, idx);
+        let flag = match arg {
+            b"-c" | b"-s" | b"-W" => arg,
+            _ if opts.host.is_empty() && !arg.starts_with(b"-") => {
+                opts.host = arg;
+                idx += 1;
+                continue;
+            }
+            _ => USAGE.exit(),
+        };
+        idx += 1;
+        if idx >= argc {
+            USAGE.exit();
+        }
+        let Some(value) = parse_u32(arg_at(argv, idx)) else {
+            USAGE.exit();
+        };
+        match flag {
+            b"-c" if value > 0 => opts.count = value,
+            b"-s" if value <= NET_PING_MAX_PAYLOAD as u32 => opts.payload_len = value as u16,
+            b"-W" if value > 0 => opts.timeout_ms = value,
+            _ => USAGE.exit(),
+        }
+        idx += 1;
+    }
+    if opts.host.is_empty() {
+        USAGE.exit();
+    }
+    opts
+}
+
+pub fn ping_main_args(argc: usize, argv: *const *const u8) -> ! {
+    let opts = parse_args(argc, argv);
+    let Some(addr) = net::resolve(opts.host) else {
+        write_out(b"ping: cannot resolve ");
+        write_out(opts.host);
+        write_out(b"\n");
+        exit_with_code(2);
+    };
+
+    write_out(b"PING ");
+    write_out(opts.host);
+    write_out(b" (");
+    write_ipv4(addr);
+    write_out(b"): ");
+    write_dec(opts.payload_len as u64);
+    write_out(b" data bytes\n");
+
+    let ident = getpid() as u16;
+    let mut transmitted = 0u32;
+    let mut received = 0u32;
+    let (mut min_us, mut max_us, mut sum_us) = (u64::MAX, 0u64, 0u64);
+
+    for seq in 0..opts.count {
+        let started = get_time_ms();
+        let request = NetPingRequest {
+            addr,
+            ident,
+            seq: seq as u16,
+            payload_len: opts.payload_len,
+            timeout_ms: opts.timeout_ms,
+            ..NetPingRequest::default()
+        };
+        match net::ping(&request) {
+            Ok(reply) => {
+                transmitted += 1;
+                received += 1;
+                min_us = min_us.min(reply.rtt_us);
+                max_us = max_us.max(reply.rtt_us);
+                sum_us += reply.rtt_us;
+                write_dec(reply.payload_len as u64 + ICMP_HEADER_LEN);
+                write_out(b" bytes from ");
+                write_ipv4(addr);
+                write_out(b": icmp_seq=");
+                write_dec(seq as u64);
+                write_out(b" ttl=");
+                write_dec(reply.ttl as u64);
+                write_out(b" time=");
+                write_fixed(reply.rtt_us, 3);
+                write_out(b" ms\n");
+            }
+            Err(SyscallError::ETIMEDOUT) => {
+                transmitted += 1;
+                write_out(b"Request timeout for icmp_seq ");
+                write_dec(seq as u64);
+                write_out(b"\n");
+            }
+            Err(err) => {
+                write_out(b"ping: sendto: ");
+                write_out(err.as_str().as_bytes());
+                write_out(b"\n");
+            }
+        }
+        if seq + 1 < opts.count {
+            let elapsed = get_time_ms().saturating_sub(started);
+            if elapsed < INTERVAL_MS {
+                sleep_ms((INTERVAL_MS - elapsed) as u32);
+            }
+        }
+    }
+
+    write_out(b"\n--- ");
+    write_out(opts.host);
+    write_out(b" ping statistics ---\n");
+    write_dec(transmitted as u64);
+    write_out(b" packets transmitted, ");
+    write_dec(received as u64);
+    write_out(b" packets received, ");
+    let loss_permille = if transmitted == 0 {
+        0
+    } else {
+        (transmitted - received) as u64 * 1000 / transmitted as u64
+    };
+    write_fixed(loss_permille, 1);
+    write_out(b"% packet loss\n");
+    if received > 0 {
+        write_out(b"round-trip min/avg/max = ");
+        write_fixed(min_us, 3);
+        write_out(b"/");
+        write_fixed(sum_us / received as u64, 3);
+        write_out(b"/");
+        write_fixed(max_us, 3);
+        write_out(b" ms\n");
+    }
+    exit_with_code(if received > 0 { 0 } else { 1 });
+}
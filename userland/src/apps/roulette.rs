@@ -1,3 +1,4 @@
+use crate::apps::beep;
 use crate::syscall::{DisplayInfo, core as sys_core, roulette, tty, window};
 use core::ffi::c_void;
 
@@ -50,6 +51,11 @@ pub fn roulette_user_main(_arg: *mut c_void) {
         let _ = roulette::draw(fate);
     }
 
+    // Odd fates are wins; celebrate if there is a sound card.
+    if fate & 1 == 1 {
+        beep::play_win_jingle();
+    }
+
     sys_core::sleep_ms(180);
     roulette::result(spin);
     sys_core::sleep_ms(120);
@@ -1,5 +1,7 @@
 //! Static command buffer management for the shell.
 
+use slopos_abi::crashdump::CRASHDUMP_SIZE;
+
 use crate::syscall::UserFsEntry;
 
 use super::SyncUnsafeCell;
@@ -7,6 +9,8 @@ use super::parser::{SHELL_MAX_TOKEN_LENGTH, SHELL_MAX_TOKENS};
 
 pub const SHELL_PATH_BUF: usize = 128;
 pub const EXPAND_BUF_SIZE: usize = 512;
+/// Largest script file `shell FILE` runs.
+pub const SCRIPT_BUF_SIZE: usize = 16 * 1024;
 
 static LINE_BUF: SyncUnsafeCell<[u8; 256]> = SyncUnsafeCell::new([0; 256]);
 
@@ -21,6 +25,12 @@ static PATH_BUF: SyncUnsafeCell<[u8; SHELL_PATH_BUF]> = SyncUnsafeCell::new([0;
 static LIST_ENTRIES: SyncUnsafeCell<[UserFsEntry; 32]> =
     SyncUnsafeCell::new([UserFsEntry::new(); 32]);
 
+static SCRIPT_BUF: SyncUnsafeCell<[u8; SCRIPT_BUF_SIZE]> =
+    SyncUnsafeCell::new([0; SCRIPT_BUF_SIZE]);
+
+static CRASHDUMP_BUF: SyncUnsafeCell<[u8; CRASHDUMP_SIZE]> =
+    SyncUnsafeCell::new([0; CRASHDUMP_SIZE]);
+
 pub fn with_line_buf<R, F: FnOnce(&mut [u8; 256]) -> R>(f: F) -> R {
     f(unsafe { &mut *LINE_BUF.get() })
 }
@@ -46,6 +56,14 @@ pub fn with_list_entries<R, F: FnOnce(&mut [UserFsEntry; 32]) -> R>(f: F) -> R {
     f(unsafe { &mut *LIST_ENTRIES.get() })
 }
 
+pub fn with_script_buf<R, F: FnOnce(&mut [u8; SCRIPT_BUF_SIZE]) -> R>(f: F) -> R {
+    f(unsafe { &mut *SCRIPT_BUF.get() })
+}
+
+pub fn with_crashdump_buf<R, F: FnOnce(&mut [u8; CRASHDUMP_SIZE]) -> R>(f: F) -> R {
+    f(unsafe { &mut *CRASHDUMP_BUF.get() })
+}
+
 pub fn token_ptr(idx: usize) -> *const u8 {
     unsafe { (*TOKEN_STORAGE.get())[idx].as_ptr() }
 }
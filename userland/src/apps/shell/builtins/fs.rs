@@ -1094,7 +1094,7 @@ fn entry_name_gt(a: &UserFsEntry, b: &UserFsEntry) -> bool {
     a_len > b_len
 }
 
-fn copy_file_inner(src_path: &[u8], dst_path: &[u8]) -> i32 {
+pub(super) fn copy_file_inner(src_path: &[u8], dst_path: &[u8]) -> i32 {
     let mut stat = UserFsStat::default();
     if fs::stat_path(src_path.as_ptr() as *const c_char, &mut stat).is_err() {
         shell_write_idx(ERR_NO_SUCH, COLOR_ERROR_RED);
@@ -1212,18 +1212,18 @@ fn write_wc_line(lines: usize, words: usize, chars: usize, name: &[u8]) {
     shell_write(NL);
 }
 
-fn write_hex_byte(b: u8) {
+pub(super) fn write_hex_byte(b: u8) {
     const HEX: &[u8; 16] = b"0123456789abcdef";
     let out = [HEX[(b >> 4) as usize], HEX[(b & 0x0F) as usize]];
     shell_write(&out);
 }
 
-fn write_hex_u16(val: u16) {
+pub(super) fn write_hex_u16(val: u16) {
     write_hex_byte((val >> 8) as u8);
     write_hex_byte(val as u8);
 }
 
-fn paths_equal(a: &[u8], b: &[u8]) -> bool {
+pub(super) fn paths_equal(a: &[u8], b: &[u8]) -> bool {
     let a_len = a.iter().position(|&c| c == 0).unwrap_or(a.len());
     let b_len = b.iter().position(|&c| c == 0).unwrap_or(b.len());
     if a_len != b_len {
@@ -2,7 +2,9 @@
 
 pub mod env;
 pub mod fs;
+pub mod net;
 pub mod process;
+pub mod script;
 pub mod system;
 pub mod utils;
 
@@ -17,6 +19,7 @@ pub enum BuiltinCategory {
     Filesystem,
     Process,
     Environment,
+    Scripting,
     Network,
     Utility,
 }
@@ -27,6 +30,7 @@ impl BuiltinCategory {
         BuiltinCategory::Filesystem,
         BuiltinCategory::Process,
         BuiltinCategory::Environment,
+        BuiltinCategory::Scripting,
         BuiltinCategory::Network,
         BuiltinCategory::Utility,
     ];
@@ -37,6 +41,7 @@ impl BuiltinCategory {
             BuiltinCategory::Filesystem => b"Filesystem",
             BuiltinCategory::Process => b"Process Control",
             BuiltinCategory::Environment => b"Environment",
+            BuiltinCategory::Scripting => b"Scripting",
             BuiltinCategory::Network => b"Network",
             BuiltinCategory::Utility => b"Utility",
         }
@@ -136,11 +141,19 @@ pub static BUILTINS: &[BuiltinEntry] = &[
         category: System,
         func: system::cmd_time,
     },
+    BuiltinEntry {
+        name: b"perf",
+        desc: b"Count CPU events of a command",
+        usage: b"perf stat <command> [args...]",
+        detail: b"Run a command and report the cycles, instructions,\ncache misses and branch misses its tasks caused,\nwith instructions per cycle and miss rates. Needs\nan Intel CPU with architectural perfmon v2.",
+        category: System,
+        func: system::cmd_perf,
+    },
     BuiltinEntry {
         name: b"date",
         desc: b"Show current time",
         usage: b"date",
-        detail: b"Display the current time relative to boot. SlopOS\nhas no real-time clock, so time is measured from\nsystem start in Sloptopia Local Time (SLT).",
+        detail: b"Display the current UTC date and time, read from\nthe CMOS real-time clock at boot and advanced by\nthe monotonic clock since.",
         category: System,
         func: system::cmd_date,
     },
@@ -160,6 +173,94 @@ pub static BUILTINS: &[BuiltinEntry] = &[
         category: System,
         func: system::cmd_whoami,
     },
+    BuiltinEntry {
+        name: b"loadkeys",
+        desc: b"Set keyboard layout",
+        usage: b"loadkeys [us|de|fr]",
+        detail: b"Switch the console keyboard layout. Without an\nargument, print the active layout. DE and FR\nsupport dead keys: press the accent, then the\nletter (^ then e types e-circumflex).",
+        category: System,
+        func: system::cmd_loadkeys,
+    },
+    BuiltinEntry {
+        name: b"setwallpaper",
+        desc: b"Set the desktop background",
+        usage: b"setwallpaper [file.bmp | none] [center|stretch|tile|fit]",
+        detail: b"Install a BMP image as the desktop wallpaper and\nhave the compositor reload it. Without a file,\nreload the current one; none removes it. The mode\nsets how it fits the screen (default stretch).",
+        category: System,
+        func: system::cmd_setwallpaper,
+    },
+    BuiltinEntry {
+        name: b"ktrace",
+        desc: b"Record a kernel event trace",
+        usage: b"ktrace start | stop | dump [file]",
+        detail: b"Record scheduler events, syscalls, IRQs and page\nfaults. dump stops tracing and writes Chrome trace JSON\nto a file, or to the serial port when no file is\ngiven. Open it in ui.perfetto.dev.",
+        category: System,
+        func: system::cmd_ktrace,
+    },
+    BuiltinEntry {
+        name: b"trace",
+        desc: b"Print a kernel event timeline",
+        usage: b"trace start | stop | show [count]",
+        detail: b"Record context switches, wakeups, syscalls, IRQs\nand page faults on every CPU. show stops tracing\nand prints the newest events (default 40) with\ntheir time, the gap since the previous event and,\nfor switches, how long the task waited after its\nwakeup.",
+        category: System,
+        func: system::cmd_trace,
+    },
+    BuiltinEntry {
+        name: b"loglevel",
+        desc: b"Show or set kernel log levels",
+        usage: b"loglevel [[subsystem] error|warn|info|debug|trace|default]",
+        detail: b"Without arguments, list the default level and the\nlevel of each subsystem (core boot mm sched net\nvideo fs input audio drivers). With a level, set\nthe default; with a subsystem too, set just that\none, or make it follow the default again.",
+        category: System,
+        func: system::cmd_loglevel,
+    },
+    BuiltinEntry {
+        name: b"lockstat",
+        desc: b"Show lock contention counters",
+        usage: b"lockstat [-r]",
+        detail: b"List acquisitions, contended acquisitions, spin\ntime and held time per lock class, busiest first.\n-r zeroes the counters after printing. Needs a\nkernel built with KERNEL_FEATURES=lockstat.",
+        category: System,
+        func: system::cmd_lockstat,
+    },
+    BuiltinEntry {
+        name: b"auditctl",
+        desc: b"Configure the audit log",
+        usage: b"auditctl [enable|disable <category|all>... | log | flush]",
+        detail: b"Without arguments, show the enabled categories\n(power net input debug audit acct) and counters.\nenable/disable switch categories; audit stays on.\nlog shows the newest records in memory; flush\nappends pending ones to /var/log/audit and acct.",
+        category: System,
+        func: system::cmd_auditctl,
+    },
+    BuiltinEntry {
+        name: b"crashdump",
+        desc: b"Show the last kernel panic",
+        usage: b"crashdump [clear]",
+        detail: b"Print the dump the kernel saved to\n/var/crash/panic.dump when it last panicked: the\nmessage, registers, backtrace, top of the stack,\ntasks, memory use and kernel log tail. clear\nmarks the dump as read.",
+        category: System,
+        func: system::cmd_crashdump,
+    },
+    BuiltinEntry {
+        name: b"memmap",
+        desc: b"Show the physical memory map",
+        usage: b"memmap",
+        detail: b"Print /proc/memmap: every usable, reserved and\nunlisted (hole) physical range in address order,\nthen the kernel image and the HHDM extent.",
+        category: System,
+        func: system::cmd_memmap,
+    },
+    BuiltinEntry {
+        name: b"lspci",
+        desc: b"List PCI devices",
+        usage: b"lspci",
+        detail: b"List every PCI function found at boot with its\nvendor:device id, class, and the driver bound to it.",
+        category: System,
+        func: system::cmd_lspci,
+    },
+    BuiltinEntry {
+        name: b"lsblk",
+        desc: b"List block devices",
+        usage: b"lsblk",
+        detail: b"List block devices with their capacity, block size,\ndriver, and the PCI slot they sit behind.",
+        category: System,
+        func: system::cmd_lsblk,
+    },
     // ── Filesystem ──────────────────────────────────────────────────────────
     BuiltinEntry {
         name: b"ls",
@@ -338,6 +439,14 @@ pub static BUILTINS: &[BuiltinEntry] = &[
         category: Process,
         func: process::cmd_ps,
     },
+    BuiltinEntry {
+        name: b"kstack",
+        desc: b"Show kernel stack high-water marks",
+        usage: b"kstack",
+        detail: b"List the deepest kernel stack use seen for each\ntask, fullest first. Needs a debug kernel, which\npoisons stacks and scans them on every switch.",
+        category: Process,
+        func: process::cmd_kstack,
+    },
     BuiltinEntry {
         name: b"wait",
         desc: b"Wait for process to exit",
@@ -354,6 +463,14 @@ pub static BUILTINS: &[BuiltinEntry] = &[
         category: Process,
         func: process::cmd_exec,
     },
+    BuiltinEntry {
+        name: b"ulimit",
+        desc: b"Show or set the core file limit",
+        usage: b"ulimit -c [KiB | unlimited]",
+        detail: b"Without a value, print the core file size limit in\nKiB. With one, set it for the shell and every\nprogram it starts afterwards. 0 disables core files.",
+        category: Process,
+        func: process::cmd_ulimit,
+    },
     // ── Environment ─────────────────────────────────────────────────────────
     BuiltinEntry {
         name: b"export",
@@ -387,6 +504,63 @@ pub static BUILTINS: &[BuiltinEntry] = &[
         category: Environment,
         func: env::cmd_set,
     },
+    // ── Scripting ───────────────────────────────────────────────────────────
+    BuiltinEntry {
+        name: b"test",
+        desc: b"Evaluate a condition",
+        usage: b"test <expr>",
+        detail: b"Return 0 if the expression is true, 1 if false and\n2 on a usage error. Tests: -e -f -d -s FILE,\n-n -z STR, A = B, A != B, N -eq -ne -lt -le -gt -ge M,\nand ! to negate.",
+        category: Scripting,
+        func: script::cmd_test,
+    },
+    BuiltinEntry {
+        name: b"[",
+        desc: b"Evaluate a condition",
+        usage: b"[ <expr> ]",
+        detail: b"Same as test, with a closing ] as the last argument.",
+        category: Scripting,
+        func: script::cmd_bracket,
+    },
+    BuiltinEntry {
+        name: b"exit",
+        desc: b"Leave the shell or script",
+        usage: b"exit [status]",
+        detail: b"Stop the script or the shell with the given status,\nor with the status of the last command.",
+        category: Scripting,
+        func: script::cmd_exit,
+    },
+    BuiltinEntry {
+        name: b"return",
+        desc: b"Leave a function",
+        usage: b"return [status]",
+        detail: b"Return from the running function with the given\nstatus, or with the status of the last command.",
+        category: Scripting,
+        func: script::cmd_return,
+    },
+    BuiltinEntry {
+        name: b"break",
+        desc: b"Leave a loop",
+        usage: b"break [n]",
+        detail: b"Leave the innermost loop, or the n innermost.",
+        category: Scripting,
+        func: script::cmd_break,
+    },
+    BuiltinEntry {
+        name: b"continue",
+        desc: b"Start the next pass of a loop",
+        usage: b"continue [n]",
+        detail: b"Skip to the next pass of the innermost loop, or of\nthe nth loop out.",
+        category: Scripting,
+        func: script::cmd_continue,
+    },
+    BuiltinEntry {
+        name: b"shift",
+        desc: b"Drop positional parameters",
+        usage: b"shift [n]",
+        detail: b"Drop $1 (or $1 to $n) and renumber the rest.",
+        category: Scripting,
+        func: script::cmd_shift,
+    },
     // ── Utility ─────────────────────────────────────────────────────────────
     BuiltinEntry {
         name: b"sleep",
@@ -461,6 +635,22 @@ pub static BUILTINS: &[BuiltinEntry] = &[
         category: Network,
         func: system::cmd_resolve,
     },
+    BuiltinEntry {
+        name: b"fw",
+        desc: b"Show or edit packet filter",
+        usage: b"fw [list|add|del|flush|policy] ...",
+        detail: b"Rules are tried in order; the first match decides.\nfw add <in|out|inout> <accept|drop>\n       [proto P] [net A.B.C.D[/N]] [port N[-M]]\nfw del <n>       remove rule n\nfw flush         remove all rules\nfw policy <in|out> <accept|drop>\n                 verdict when no rule matches",
+        category: Network,
+        func: net::cmd_fw,
+    },
+    BuiltinEntry {
+        name: b"route",
+        desc: b"Show or edit routing table",
+        usage: b"route [show|add|del] ...",
+        detail: b"The longest matching prefix wins; lower metric\nbreaks ties.\nroute add <A.B.C.D/N|default> [via GW]\n          [dev lo|ethN] [metric M]\nroute del <A.B.C.D/N|default> [dev lo|ethN]\nWithout dev, the interface comes from the route\nto the gateway.",
+        category: Network,
+        func: net::cmd_route,
+    },
 ];
 
 pub fn find_builtin(name: *const u8) -> Option<&'static BuiltinEntry> {
@@ -476,26 +666,6 @@ pub fn print_kv(key: &[u8], value: u64) {
     if !key.is_empty() {
         shell_write(key);
     }
-    let mut tmp = [0u8; 32];
-    let mut idx = 0usize;
-    if value == 0 {
-        tmp[idx] = b'0';
-        idx += 1;
-    } else {
-        let mut n = value;
-        let mut rev = [0u8; 32];
-        let mut r = 0usize;
-        while n != 0 && r < rev.len() {
-            rev[r] = b'0' + (n % 10) as u8;
-            n /= 10;
-            r += 1;
-        }
-        while r > 0 && idx < tmp.len() {
-            idx += 1;
-            tmp[idx - 1] = rev[r - 1];
-            r -= 1;
-        }
-    }
-    shell_write(&tmp[..idx]);
+    super::jobs::write_u64(value);
     shell_write(super::NL);
 }
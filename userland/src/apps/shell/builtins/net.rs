@@ -0,0 +1,512 @@
+//! Network builtins: fw, route.
+
+use slopos_abi::net::{
+    NET_FILTER_ACCEPT, NET_FILTER_DROP, NET_FILTER_IN, NET_FILTER_MAX_RULES, NET_FILTER_OUT,
+    NET_FILTER_PROTO_ANY, NET_ROUTE_DEV_AUTO, NET_ROUTE_MAX_ROUTES, NetFilterRule, NetRoute,
+};
+
+use crate::runtime;
+use crate::syscall::{SyscallError, net};
+
+use super::super::NL;
+use super::super::display::{COLOR_ERROR_RED, shell_write, shell_write_idx};
+use super::super::jobs::{parse_u32_arg, write_u64};
+
+const FW_USAGE: &[u8] = b"usage: fw [list | add <in|out|inout> <accept|drop> [proto P] [net A.B.C.D[/N]] [port N[-M]] | del <n> | flush | policy <in|out> <accept|drop>]\n";
+
+const ROUTE_USAGE: &[u8] = b"usage: route [show | add <A.B.C.D/N|default> [via GW] [dev IFACE] [metric M] | del <A.B.C.D/N|default> [dev IFACE]]\n";
+
+const PROTOCOLS: &[(&[u8], u8)] = &[
+    (b"any", NET_FILTER_PROTO_ANY),
+    (b"icmp", 1),
+    (b"igmp", 2),
+    (b"tcp", 6),
+    (b"udp", 17),
+];
+
+fn arg(ptr: *const u8) -> &'static [u8] {
+    if ptr.is_null() {
+        return &[];
+    }
+    unsafe { core::slice::from_raw_parts(ptr, runtime::u_strlen(ptr)) }
+}
+
+fn fw_error(msg: &[u8]) -> i32 {
+    shell_write_idx(b"fw: ", COLOR_ERROR_RED);
+    shell_write_idx(msg, COLOR_ERROR_RED);
+    shell_write(NL);
+    1
+}
+
+fn parse_num(bytes: &[u8], max: u32) -> Option<u32> {
+    if bytes.is_empty() {
+        return None;
+    }
+    let mut v: u32 = 0;
+    for &b in bytes {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        v = v.checked_mul(10)?.checked_add((b - b'0') as u32)?;
+    }
+    (v <= max).then_some(v)
+}
+
+fn parse_direction(word: &[u8]) -> Option<u8> {
+    match word {
+        b"in" => Some(NET_FILTER_IN),
+        b"out" => Some(NET_FILTER_OUT),
+        b"inout" => Some(NET_FILTER_IN | NET_FILTER_OUT),
+        _ => None,
+    }
+}
+
+fn parse_action(word: &[u8]) -> Option<u8> {
+    match word {
+        b"accept" => Some(NET_FILTER_ACCEPT),
+        b"drop" => Some(NET_FILTER_DROP),
+        _ => None,
+    }
+}
+
+fn parse_proto(word: &[u8]) -> Option<u8> {
+    PROTOCOLS
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|&(_, proto)| proto)
+        .or_else(|| parse_num(word, 255).map(|n| n as u8))
+}
+
+/// `A.B.C.D` or `A.B.C.D/N`; a bare address is a /32.
+fn parse_cidr(word: &[u8]) -> Option<([u8; 4], u8)> {
+    let (addr_part, prefix_len) = match word.iter().position(|&b| b == b'/') {
+        Some(slash) => (&word[..slash], parse_num(&word[slash + 1..], 32)? as u8),
+        None => (word, 32),
+    };
+    let mut addr = [0u8; 4];
+    let mut octets = addr_part.split(|&b| b == b'.');
+    for octet in &mut addr {
+        *octet = parse_num(octets.next()?, 255)? as u8;
+    }
+    octets.next().is_none().then_some((addr, prefix_len))
+}
+
+/// `N` or `N-M`.
+fn parse_ports(word: &[u8]) -> Option<(u16, u16)> {
+    let (min, max) = match word.iter().position(|&b| b == b'-') {
+        Some(dash) => (&word[..dash], &word[dash + 1..]),
+        None => (word, word),
+    };
+    let min = parse_num(min, u16::MAX as u32)? as u16;
+    let max = parse_num(max, u16::MAX as u32)? as u16;
+    (min != 0 && min <= max).then_some((min, max))
+}
+
+fn write_ipv4(addr: [u8; 4]) {
+    for (i, octet) in addr.iter().enumerate() {
+        if i > 0 {
+            shell_write(b".");
+        }
+        write_u64(*octet as u64);
+    }
+}
+
+fn write_direction(direction: u8) {
+    shell_write(match direction {
+        NET_FILTER_IN => b"in   ",
+        NET_FILTER_OUT => b"out  ",
+        _ => b"inout",
+    });
+}
+
+fn write_action(action: u8) {
+    shell_write(if action == NET_FILTER_DROP {
+        b"drop  "
+    } else {
+        b"accept"
+    });
+}
+
+fn write_rule(idx: usize, rule: &NetFilterRule) {
+    write_u64(idx as u64);
+    shell_write(b": ");
+    write_direction(rule.direction);
+    shell_write(b" ");
+    write_action(rule.action);
+    shell_write(b" ");
+    match PROTOCOLS.iter().find(|&&(_, proto)| proto == rule.proto) {
+        Some((name, _)) => {
+            shell_write(name);
+        }
+        None => write_u64(rule.proto as u64),
+    }
+    if rule.prefix_len > 0 {
+        shell_write(b" net ");
+        write_ipv4(rule.addr);
+        shell_write(b"/");
+        write_u64(rule.prefix_len as u64);
+    }
+    if rule.port_max != 0 {
+        shell_write(b" port ");
+        write_u64(rule.port_min as u64);
+        if rule.port_max != rule.port_min {
+            shell_write(b"-");
+            write_u64(rule.port_max as u64);
+        }
+    }
+    shell_write(b"  hits ");
+    write_u64(rule.hits);
+    shell_write(NL);
+}
+
+fn fw_list() -> i32 {
+    let Ok(status) = net::filter_status() else {
+        return fw_error(b"cannot query filter");
+    };
+    let mut rules = [NetFilterRule::default(); NET_FILTER_MAX_RULES];
+    let Ok(count) = net::filter_list(&mut rules) else {
+        return fw_error(b"cannot list rules");
+    };
+
+    shell_write(b"policy in ");
+    write_action(status.policy_in);
+    shell_write(b" (dropped ");
+    write_u64(status.dropped_in);
+    shell_write(b"), out ");
+    write_action(status.policy_out);
+    shell_write(b" (dropped ");
+    write_u64(status.dropped_out);
+    shell_write(b")\n");
+    if count == 0 {
+        shell_write(b"no rules\n");
+    }
+    for (idx, rule) in rules[..count].iter().enumerate() {
+        write_rule(idx, rule);
+    }
+    0
+}
+
+fn fw_add(args: &[*const u8]) -> i32 {
+    if args.len() < 2 {
+        shell_write(FW_USAGE);
+        return 1;
+    }
+    let Some(direction) = parse_direction(arg(args[0])) else {
+        return fw_error(b"direction must be in, out or inout");
+    };
+    let Some(action) = parse_action(arg(args[1])) else {
+        return fw_error(b"action must be accept or drop");
+    };
+    let mut rule = NetFilterRule {
+        direction,
+        action,
+        ..NetFilterRule::default()
+    };
+
+    for pair in args[2..].chunks(2) {
+        let [key, value] = pair else {
+            shell_write(FW_USAGE);
+            return 1;
+        };
+        let value = arg(*value);
+        match arg(*key) {
+            b"proto" => {
+                let Some(proto) = parse_proto(value) else {
+                    return fw_error(b"unknown protocol");
+                };
+                rule.proto = proto;
+            }
+            b"net" => {
+                let Some((addr, prefix_len)) = parse_cidr(value) else {
+                    return fw_error(b"bad address, expected A.B.C.D[/N]");
+                };
+                rule.addr = addr;
+                rule.prefix_len = prefix_len;
+            }
+            b"port" => {
+                let Some((min, max)) = parse_ports(value) else {
+                    return fw_error(b"bad port, expected N or N-M");
+                };
+                rule.port_min = min;
+                rule.port_max = max;
+            }
+            _ => {
+                shell_write(FW_USAGE);
+                return 1;
+            }
+        }
+    }
+
+    match net::filter_append(&rule) {
+        Ok(idx) => {
+            write_rule(idx, &rule);
+            0
+        }
+        Err(_) if rule.port_max != 0 && !matches!(rule.proto, 6 | 17) => {
+            fw_error(b"ports need proto tcp or udp")
+        }
+        Err(_) => fw_error(b"rule rejected (table full?)"),
+    }
+}
+
+pub fn cmd_fw(argc: i32, argv: &[*const u8]) -> i32 {
+    let argc = (argc.max(0) as usize).min(argv.len());
+    if argc < 2 {
+        return fw_list();
+    }
+    let args = &argv[2..argc];
+    match arg(argv[1]) {
+        b"list" => fw_list(),
+        b"add" => fw_add(args),
+        b"del" => {
+            let Some(idx) = args.first().and_then(|&ptr| parse_u32_arg(ptr)) else {
+                shell_write(FW_USAGE);
+                return 1;
+            };
+            match net::filter_delete(idx as usize) {
+                Ok(()) => 0,
+                Err(_) => fw_error(b"no such rule"),
+            }
+        }
+        b"flush" => match net::filter_flush() {
+            Ok(()) => 0,
+            Err(_) => fw_error(b"flush failed"),
+        },
+        b"policy" => {
+            let direction = args.first().and_then(|&ptr| parse_direction(arg(ptr)));
+            let action = args.get(1).and_then(|&ptr| parse_action(arg(ptr)));
+            let (Some(direction), Some(action)) = (direction, action) else {
+                shell_write(FW_USAGE);
+                return 1;
+            };
+            match net::filter_policy(direction, action) {
+                Ok(()) => 0,
+                Err(_) => fw_error(b"policy rejected"),
+            }
+        }
+        _ => {
+            shell_write(FW_USAGE);
+            1
+        }
+    }
+}
+
+fn route_error(msg: &[u8]) -> i32 {
+    shell_write_idx(b"route: ", COLOR_ERROR_RED);
+    shell_write_idx(msg, COLOR_ERROR_RED);
+    shell_write(NL);
+    1
+}
+
+/// Interface 0 is `lo`; the NICs after it are `eth0`, `eth1`, ...
+fn parse_iface(word: &[u8]) -> Option<u8> {
+    if word == b"lo" {
+        return Some(0);
+    }
+    match word.strip_prefix(b"eth") {
+        Some(n) => parse_num(n, NET_ROUTE_DEV_AUTO as u32 - 2).map(|n| n as u8 + 1),
+        None => parse_num(word, NET_ROUTE_DEV_AUTO as u32 - 1).map(|n| n as u8),
+    }
+}
+
+/// Append `bytes` to `line`, then pad with spaces to `width`.
+fn push_column(line: &mut [u8], len: &mut usize, bytes: &[u8], width: usize) {
+    for &b in bytes.iter().chain(core::iter::repeat_n(
+        &b' ',
+        width.saturating_sub(bytes.len()),
+    )) {
+        if *len < line.len() {
+            line[*len] = b;
+            *len += 1;
+        }
+    }
+}
+
+fn format_dec(mut value: u32, out: &mut [u8; 10]) -> &[u8] {
+    let mut n = out.len();
+    loop {
+        n -= 1;
+        out[n] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    &out[n..]
+}
+
+fn format_ipv4(addr: [u8; 4], out: &mut [u8; 15]) -> &[u8] {
+    let mut len = 0;
+    for (i, octet) in addr.iter().enumerate() {
+        if i > 0 {
+            out[len] = b'.';
+            len += 1;
+        }
+        let mut tmp = [0u8; 10];
+        for &b in format_dec(*octet as u32, &mut tmp) {
+            out[len] = b;
+            len += 1;
+        }
+    }
+    &out[..len]
+}
+
+fn write_route(route: &NetRoute) {
+    let mut line = [0u8; 64];
+    let mut len = 0;
+    let mut addr = [0u8; 15];
+    let mut num = [0u8; 10];
+
+    if route.prefix_len == 0 {
+        push_column(&mut line, &mut len, b"default", 19);
+    } else {
+        let mut dest = [0u8; 18];
+        let prefix = format_ipv4(route.prefix, &mut addr);
+        let bits = format_dec(route.prefix_len as u32, &mut num);
+        dest[..prefix.len()].copy_from_slice(prefix);
+        dest[prefix.len()] = b'/';
+        dest[prefix.len() + 1..prefix.len() + 1 + bits.len()].copy_from_slice(bits);
+        push_column(
+            &mut line,
+            &mut len,
+            &dest[..prefix.len() + 1 + bits.len()],
+            19,
+        );
+    }
+    if route.gateway == [0; 4] {
+        push_column(&mut line, &mut len, b"*", 16);
+    } else {
+        push_column(
+            &mut line,
+            &mut len,
+            format_ipv4(route.gateway, &mut addr),
+            16,
+        );
+    }
+    push_column(&mut line, &mut len, format_dec(route.metric, &mut num), 8);
+    if route.dev == 0 {
+        push_column(&mut line, &mut len, b"lo", 0);
+    } else {
+        push_column(&mut line, &mut len, b"eth", 0);
+        push_column(
+            &mut line,
+            &mut len,
+            format_dec(route.dev as u32 - 1, &mut num),
+            0,
+        );
+    }
+    shell_write(&line[..len]);
+    shell_write(NL);
+}
+
+fn route_show() -> i32 {
+    let mut routes = [NetRoute::default(); NET_ROUTE_MAX_ROUTES];
+    let Ok(count) = net::route_list(&mut routes) else {
+        return route_error(b"cannot read routing table");
+    };
+    shell_write(b"Destination        Gateway         Metric  Iface\n");
+    for route in &routes[..count] {
+        write_route(route);
+    }
+    0
+}
+
+/// Parse `<A.B.C.D/N|default> [key value]...` where `keys` lists the
+/// options the command accepts.
+fn parse_route(args: &[*const u8], keys: &[&[u8]]) -> Result<NetRoute, &'static [u8]> {
+    let Some(&dest) = args.first() else {
+        return Err(ROUTE_USAGE);
+    };
+    let (prefix, prefix_len) = match arg(dest) {
+        b"default" => ([0; 4], 0),
+        word => match parse_cidr(word) {
+            Some(cidr) => cidr,
+            None => return Err(b"bad destination, expected A.B.C.D[/N] or default"),
+        },
+    };
+    let mut route = NetRoute {
+        prefix,
+        prefix_len,
+        dev: NET_ROUTE_DEV_AUTO,
+        ..NetRoute::default()
+    };
+
+    for pair in args[1..].chunks(2) {
+        let [key, value] = pair else {
+            return Err(ROUTE_USAGE);
+        };
+        let key = arg(*key);
+        let value = arg(*value);
+        if !keys.contains(&key) {
+            return Err(ROUTE_USAGE);
+        }
+        match key {
+            b"via" => match parse_cidr(value) {
+                Some((gateway, 32)) => route.gateway = gateway,
+                _ => return Err(b"bad gateway address"),
+            },
+            b"dev" => match parse_iface(value) {
+                Some(dev) => route.dev = dev,
+                None => return Err(b"bad interface, expected lo, ethN or an index"),
+            },
+            b"metric" => match parse_num(value, u32::MAX) {
+                Some(metric) => route.metric = metric,
+                None => return Err(b"bad metric"),
+            },
+            _ => return Err(ROUTE_USAGE),
+        }
+    }
+    Ok(route)
+}
+
+fn route_add(args: &[*const u8]) -> i32 {
+    let route = match parse_route(args, &[b"via", b"dev", b"metric"]) {
+        Ok(route) => route,
+        Err(msg) if msg == ROUTE_USAGE => {
+            shell_write(ROUTE_USAGE);
+            return 1;
+        }
+        Err(msg) => return route_error(msg),
+    };
+    match net::route_add(&route) {
+        Ok(()) => 0,
+        Err(SyscallError::ENETUNREACH) => route_error(b"gateway is not on a connected network"),
+        Err(SyscallError::ENOBUFS) => route_error(b"too many routes of that length"),
+        Err(_) if route.gateway == [0; 4] && route.dev == NET_ROUTE_DEV_AUTO => {
+            route_error(b"a route without a gateway needs dev")
+        }
+        Err(_) => route_error(b"route rejected (host bits set or no such interface?)"),
+    }
+}
+
+fn route_del(args: &[*const u8]) -> i32 {
+    let route = match parse_route(args, &[b"dev"]) {
+        Ok(route) => route,
+        Err(msg) if msg == ROUTE_USAGE => {
+            shell_write(ROUTE_USAGE);
+            return 1;
+        }
+        Err(msg) => return route_error(msg),
+    };
+    match net::route_del(&route) {
+        Ok(()) => 0,
+        Err(_) => route_error(b"no such route"),
+    }
+}
+
+pub fn cmd_route(argc: i32, argv: &[*const u8]) -> i32 {
+    let argc = (argc.max(0) as usize).min(argv.len());
+    if argc < 2 {
+        return route_show();
+    }
+    let args = &argv[2..argc];
+    match arg(argv[1]) {
+        b"show" | b"list" => route_show(),
+        b"add" => route_add(args),
+        b"del" => route_del(args),
+        _ => {
+            shell_write(ROUTE_USAGE);
+            1
+        }
+    }
+}
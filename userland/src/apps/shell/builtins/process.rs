@@ -1,11 +1,14 @@
+use slopos_abi::rlimit::{RLIM_INFINITY, RLIMIT_CORE, RLimit};
 use slopos_abi::signal::{SIGCONT, SIGINT, SIGKILL};
+use slopos_abi::task::{MAX_TASKS, TASK_FLAG_KERNEL_MODE, TaskStackUsage};
 
 use crate::runtime;
-use crate::syscall::{UserSysInfo, WindowInfo, core as sys_core, process, window};
+use crate::syscall::{SyscallError, UserSysInfo, WindowInfo, core as sys_core, process, window};
 
 use super::super::display::{COLOR_ERROR_RED, shell_write, shell_write_idx};
 use super::super::exec;
 use super::super::jobs;
+use super::super::parser::u_streq_slice;
 
 fn parse_job_id(ptr: *const u8) -> Option<u16> {
     if ptr.is_null() {
@@ -169,6 +172,52 @@ pub fn cmd_exec(argc: i32, argv: &[*const u8]) -> i32 {
     }
 }
 
+/// `ulimit -c [KiB | unlimited]`: show or set the core file size limit.
+/// The new value applies to the shell and everything it starts afterwards.
+pub fn cmd_ulimit(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc < 2 || !u_streq_slice(argv[1], b"-c") {
+        shell_write_idx(
+            b"ulimit: usage: ulimit -c [KiB | unlimited]\n",
+            COLOR_ERROR_RED,
+        );
+        return 1;
+    }
+    let mut limit = RLimit::CORE_DEFAULT;
+    if process::getrlimit(RLIMIT_CORE, &mut limit) < 0 {
+        shell_write_idx(b"ulimit: failed\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    if argc < 3 {
+        if limit.cur == RLIM_INFINITY {
+            shell_write(b"unlimited");
+        } else {
+            jobs::write_u64(limit.cur / 1024);
+        }
+        shell_write(b"\n");
+        return 0;
+    }
+    let cur = if u_streq_slice(argv[2], b"unlimited") {
+        RLIM_INFINITY
+    } else {
+        match jobs::parse_u32_arg(argv[2]) {
+            Some(kib) => kib as u64 * 1024,
+            None => {
+                shell_write_idx(b"ulimit: invalid limit\n", COLOR_ERROR_RED);
+                return 1;
+            }
+        }
+    };
+    if cur > limit.max {
+        shell_write_idx(b"ulimit: above the hard limit\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    if process::setrlimit(RLIMIT_CORE, &RLimit::new(cur, limit.max)) < 0 {
+        shell_write_idx(b"ulimit: failed\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    0
+}
+
 pub fn cmd_ps(_argc: i32, _argv: &[*const u8]) -> i32 {
     let mut info = UserSysInfo::default();
     if sys_core::sys_info(&mut info) != 0 {
@@ -219,6 +268,52 @@ pub fn cmd_ps(_argc: i32, _argv: &[*const u8]) -> i32 {
     0
 }
 
+pub fn cmd_kstack(_argc: i32, _argv: &[*const u8]) -> i32 {
+    let mut usage = [TaskStackUsage::default(); MAX_TASKS];
+    let count = match sys_core::task_stack_usage(&mut usage) {
+        Ok(count) => count,
+        Err(SyscallError::EOPNOTSUPP) => {
+            shell_write_idx(
+                b"kstack: stack tracking needs a debug kernel\n",
+                COLOR_ERROR_RED,
+            );
+            return 1;
+        }
+        Err(_) => {
+            shell_write_idx(b"kstack: failed\n", COLOR_ERROR_RED);
+            return 1;
+        }
+    };
+    let usage = &mut usage[..count];
+    usage.sort_unstable_by_key(|u| core::cmp::Reverse(stack_percent(u)));
+
+    shell_write(b"pid used/size pct name\n");
+    for u in usage.iter() {
+        jobs::write_u64(u.task_id as u64);
+        shell_write(b" ");
+        jobs::write_u64(u.high_water);
+        shell_write(b"/");
+        jobs::write_u64(u.stack_size);
+        shell_write(b" ");
+        jobs::write_u64(stack_percent(u));
+        shell_write(b"% ");
+        let name_len = u.name.iter().position(|&b| b == 0).unwrap_or(u.name.len());
+        shell_write(&u.name[..name_len]);
+        if u.flags & TASK_FLAG_KERNEL_MODE as u32 != 0 {
+            shell_write(b" [kernel]");
+        }
+        shell_write(b"\n");
+    }
+    0
+}
+
+fn stack_percent(usage: &TaskStackUsage) -> u64 {
+    match usage.stack_size {
+        0 => 0,
+        size => usage.high_water * 100 / size,
+    }
+}
+
 pub fn maybe_handle_ctrl_c() -> bool {
     let fg = exec::foreground_pgid();
     if fg == 0 {
@@ -0,0 +1,218 @@
+//! Scripting builtins: test, [, exit, return, break, continue, shift.
+
+use core::ffi::c_char;
+
+use crate::runtime;
+use crate::syscall::{UserFsStat, fs};
+
+use super::super::display::{COLOR_ERROR_RED, shell_write_idx};
+use super::super::jobs::parse_u32_arg;
+use super::super::parser::normalize_path;
+use super::super::script::{self, Flow};
+
+// ─── Helpers ────────────────────────────────────────────────────────────────
+
+fn arg_bytes<'a>(ptr: *const u8) -> &'a [u8] {
+    if ptr.is_null() {
+        return &[];
+    }
+    unsafe { core::slice::from_raw_parts(ptr, runtime::u_strlen(ptr)) }
+}
+
+fn args(argc: i32, argv: &[*const u8]) -> &[*const u8] {
+    &argv[1..(argc.max(1) as usize).min(argv.len())]
+}
+
+fn parse_i64(bytes: &[u8]) -> Option<i64> {
+    let (negative, digits) = match bytes {
+        [b'-', rest @ ..] => (true, rest),
+        [b'+', rest @ ..] => (false, rest),
+        _ => (false, bytes),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut v: i64 = 0;
+    for &b in digits {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        v = v.checked_mul(10)?.checked_add((b - b'0') as i64)?;
+    }
+    Some(if negative { -v } else { v })
+}
+
+fn stat(path: *const u8) -> Option<UserFsStat> {
+    let mut buf = [0u8; 256];
+    if normalize_path(path, &mut buf) != 0 {
+        return None;
+    }
+    let mut stat = UserFsStat::default();
+    fs::stat_path(buf.as_ptr() as *const c_char, &mut stat).ok()?;
+    Some(stat)
+}
+
+fn unary(op: &[u8], operand: *const u8) -> Result<bool, &'static [u8]> {
+    Ok(match op {
+        b"-n" => !arg_bytes(operand).is_empty(),
+        b"-z" => arg_bytes(operand).is_empty(),
+        b"-e" => stat(operand).is_some(),
+        b"-f" => stat(operand).is_some_and(|s| !s.is_directory()),
+        b"-d" => stat(operand).is_some_and(|s| s.is_directory()),
+        b"-s" => stat(operand).is_some_and(|s| s.size > 0),
+        _ => return Err(b"unknown unary operator\n"),
+    })
+}
+
+/// `None` if `op` is not a binary operator.
+fn binary(left: &[u8], op: &[u8], right: &[u8]) -> Option<Result<bool, &'static [u8]>> {
+    let strings = match op {
+        b"=" | b"==" => Some(left == right),
+        b"!=" => Some(left != right),
+        _ => None,
+    };
+    if let Some(result) = strings {
+        return Some(Ok(result));
+    }
+    let compare: fn(&i64, &i64) -> bool = match op {
+        b"-eq" => i64::eq,
+        b"-ne" => i64::ne,
+        b"-lt" => i64::lt,
+        b"-le" => i64::le,
+        b"-gt" => i64::gt,
+        b"-ge" => i64::ge,
+        _ => return None,
+    };
+    Some(match (parse_i64(left), parse_i64(right)) {
+        (Some(l), Some(r)) => Ok(compare(&l, &r)),
+        _ => Err(b"integer expression expected\n"),
+    })
+}
+
+fn eval(args: &[*const u8]) -> Result<bool, &'static [u8]> {
+    let word = |i: usize| arg_bytes(args[i]);
+    match args.len() {
+        0 => Ok(false),
+        1 => Ok(!word(0).is_empty()),
+        2 if word(0) == b"!" => Ok(word(1).is_empty()),
+        2 => unary(word(0), args[1]),
+        3 => match binary(word(0), word(1), word(2)) {
+            Some(result) => result,
+            None if word(0) == b"!" => eval(&args[1..]).map(|v| !v),
+            None => Err(b"unknown binary operator\n"),
+        },
+        4 if word(0) == b"!" => eval(&args[1..]).map(|v| !v),
+        _ => Err(b"too many arguments\n"),
+    }
+}
+
+fn test_status(name: &[u8], args: &[*const u8]) -> i32 {
+    match eval(args) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(reason) => {
+            shell_write_idx(name, COLOR_ERROR_RED);
+            shell_write_idx(b": ", COLOR_ERROR_RED);
+            shell_write_idx(reason, COLOR_ERROR_RED);
+            2
+        }
+    }
+}
+
+/// The loop count for `break` or `continue`: its argument, 1 by default,
+/// and at most the loops there are.
+fn loop_count(name: &[u8], argc: i32, argv: &[*const u8]) -> Option<u32> {
+    let depth = script::loop_depth();
+    if depth == 0 {
+        shell_write_idx(name, COLOR_ERROR_RED);
+        shell_write_idx(b": only meaningful in a loop\n", COLOR_ERROR_RED);
+        return None;
+    }
+    let n = match args(argc, argv).first() {
+        Some(&arg) => parse_u32_arg(arg).filter(|&n| n > 0),
+        None => Some(1),
+    };
+    if n.is_none() {
+        shell_write_idx(name, COLOR_ERROR_RED);
+        shell_write_idx(b": loop count must be a positive number\n", COLOR_ERROR_RED);
+    }
+    n.map(|n| n.min(depth))
+}
+
+/// The status for `exit` or `return`: its argument, or `$?` without one.
+fn exit_status(name: &[u8], argc: i32, argv: &[*const u8]) -> i32 {
+    match args(argc, argv).first() {
+        Some(&arg) => match parse_u32_arg(arg) {
+            Some(n) => (n & 0xFF) as i32,
+            None => {
+                shell_write_idx(name, COLOR_ERROR_RED);
+                shell_write_idx(b": numeric argument required\n", COLOR_ERROR_RED);
+                2
+            }
+        },
+        None => super::super::last_exit_code(),
+    }
+}
+
+// ─── Commands ───────────────────────────────────────────────────────────────
+
+pub fn cmd_test(argc: i32, argv: &[*const u8]) -> i32 {
+    test_status(b"test", args(argc, argv))
+}
+
+pub fn cmd_bracket(argc: i32, argv: &[*const u8]) -> i32 {
+    let args = args(argc, argv);
+    match args.split_last() {
+        Some((&last, rest)) if arg_bytes(last) == b"]" => test_status(b"[", rest),
+        _ => {
+            shell_write_idx(b"[: missing ']'\n", COLOR_ERROR_RED);
+            2
+        }
+    }
+}
+
+pub fn cmd_exit(argc: i32, argv: &[*const u8]) -> i32 {
+    let status = exit_status(b"exit", argc, argv);
+    script::set_flow(Flow::Exit);
+    status
+}
+
+pub fn cmd_return(argc: i32, argv: &[*const u8]) -> i32 {
+    if !script::in_function() {
+        shell_write_idx(b"return: only meaningful in a function\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    let status = exit_status(b"return", argc, argv);
+    script::set_flow(Flow::Return);
+    status
+}
+
+pub fn cmd_break(argc: i32, argv: &[*const u8]) -> i32 {
+    let Some(n) = loop_count(b"break", argc, argv) else {
+        return 1;
+    };
+    script::set_flow(Flow::Break(n));
+    0
+}
+
+pub fn cmd_continue(argc: i32, argv: &[*const u8]) -> i32 {
+    let Some(n) = loop_count(b"continue", argc, argv) else {
+        return 1;
+    };
+    script::set_flow(Flow::Continue(n));
+    0
+}
+
+pub fn cmd_shift(argc: i32, argv: &[*const u8]) -> i32 {
+    let n = match args(argc, argv).first() {
+        Some(&arg) => parse_u32_arg(arg),
+        None => Some(1),
+    };
+    match n {
+        Some(n) if script::shift(n as usize) => 0,
+        _ => {
+            shell_write_idx(b"shift: shift count out of range\n", COLOR_ERROR_RED);
+            1
+        }
+    }
+}
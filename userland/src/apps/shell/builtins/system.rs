@@ -1,17 +1,58 @@
+use core::ffi::{CStr, c_char};
+use core::ptr;
+
+use slopos_abi::audit::{
+    AUDIT_CAT_ACCT, AUDIT_CAT_ALL, AUDIT_CAT_NAMES, AuditRecord, audit_cat_name,
+};
+use slopos_abi::crashdump::{
+    CRASHDUMP_REG_NAMES, CRASHDUMP_SECTION_BACKTRACE, CRASHDUMP_SECTION_EXCEPTION,
+    CRASHDUMP_SECTION_LOG, CRASHDUMP_SECTION_MESSAGE, CRASHDUMP_SECTION_REGS,
+    CRASHDUMP_SECTION_STACK, CrashDump,
+};
+use slopos_abi::hw::{HW_CLASS_BLOCK, HW_CLASS_PCI, HW_MAX_DEVICES, HwDevice};
+use slopos_abi::input::{KEYMAP_COUNT, keymap_from_name, keymap_name};
+use slopos_abi::klog::{
+    KLOG_LEVEL_INHERIT, KLOG_LEVEL_KEEP, KLOG_MODULE_DEFAULT, KLOG_MODULE_NAMES,
+    klog_level_from_name, klog_level_name, klog_module_from_name,
+};
+use slopos_abi::perf::{
+    PERF_EVENT_BRANCH_MISSES, PERF_EVENT_BRANCHES, PERF_EVENT_CACHE_MISSES,
+    PERF_EVENT_CACHE_REFERENCES, PERF_EVENT_COUNT, PERF_EVENT_CYCLES, PERF_EVENT_INSTRUCTIONS,
+    PERF_FLAG_CHILDREN_ONLY, perf_event_name,
+};
+use slopos_abi::syscall::{
+    KTRACE_KIND_IRQ_ENTER, KTRACE_KIND_IRQ_EXIT, KTRACE_KIND_PAGE_FAULT, KTRACE_KIND_SCHED_SWITCH,
+    KTRACE_KIND_SCHED_WAKEUP, KTRACE_KIND_SYSCALL_ENTER, KTRACE_KIND_SYSCALL_EXIT, KTRACE_NO_TASK,
+    KTRACE_OP_EXPORT_FILE, KTRACE_OP_EXPORT_SERIAL, KTRACE_OP_START, KTRACE_OP_STOP, KtraceRecord,
+    LOCK_STATS_MAX_CLASSES, LockStat,
+};
+use slopos_abi::task::TaskStatus;
+use slopos_abi::time::CivilTime;
+use slopos_lib::numfmt::{self, NumBuf, UnitBase};
+
+use crate::gfx::BMP_MAGIC;
+use crate::gfx::wallpaper::{self, ScaleMode, WALLPAPER_PATH};
 use crate::program_registry;
 use crate::runtime;
-use crate::syscall::{UserSysInfo, core as sys_core, process};
+use crate::syscall::{
+    SyscallError, Timespec, USER_FS_OPEN_READ, USER_FS_OPEN_WRITE, UserSysInfo, core as sys_core,
+    fs, input, process, window,
+};
 
+use super::super::buffers::with_crashdump_buf;
 use super::super::display::{
     COLOR_COMMENT_GRAY, COLOR_ERROR_RED, COLOR_EXEC_GREEN, COLOR_PROMPT_ACCENT,
     shell_console_clear, shell_write, shell_write_idx,
 };
-use super::super::jobs::write_u64;
-use super::super::parser::u_streq_slice;
-use super::super::{HALTED, NL, REBOOTING};
+use super::super::jobs::{parse_u32_arg, write_u64};
+use super::super::parser::{normalize_path, u_streq_slice};
+use super::super::plugins::{PluginNames, plugin_info};
+use super::super::{HALTED, NL, PATH_TOO_LONG, REBOOTING, SHELL_IO_MAX};
+use super::fs::{copy_file_inner, paths_equal, write_hex_byte, write_hex_u16};
 use super::{BUILTINS, BuiltinCategory, print_kv};
 
 const NAME_COL_WIDTH: usize = 12;
+const FREE_COL_WIDTH: usize = 13;
 const PADDING: &[u8] = b"            ";
 
 fn write_padded_colored(name: &[u8], color: u8) {
@@ -30,6 +71,7 @@ pub fn cmd_help(argc: i32, argv: &[*const u8]) -> i32 {
     shell_write_idx(b"SlopOS Shell v0.2\n", COLOR_PROMPT_ACCENT);
     shell_write(b"Type 'help <command>' for detailed usage.\n\n");
 
+    let plugins = PluginNames::scan();
     for &cat in BuiltinCategory::ALL {
         shell_write_idx(cat.label(), COLOR_PROMPT_ACCENT);
         shell_write(b":\n");
@@ -37,10 +79,14 @@ pub fn cmd_help(argc: i32, argv: &[*const u8]) -> i32 {
             if entry.category != cat {
                 continue;
             }
-            shell_write(b"  ");
-            write_padded_colored(entry.name, COLOR_EXEC_GREEN);
-            shell_write(entry.desc);
-            shell_write(NL);
+            write_summary(entry.name, entry.desc);
+        }
+        for name in plugins.iter() {
+            if let Some(info) = plugin_info(name)
+                && info.category() == cat.label()
+            {
+                write_summary(name, info.desc());
+            }
         }
         shell_write(NL);
     }
@@ -48,32 +94,66 @@ pub fn cmd_help(argc: i32, argv: &[*const u8]) -> i32 {
     shell_write_idx(b"Programs", COLOR_PROMPT_ACCENT);
     shell_write(b":\n");
     for spec in program_registry::user_programs() {
-        shell_write(b"  ");
-        write_padded_colored(spec.name, COLOR_EXEC_GREEN);
-        shell_write(spec.desc);
-        shell_write(NL);
+        write_summary(spec.name, spec.desc);
     }
     shell_write(NL);
 
+    // Plugins in a category of their own, or without a note.
+    let mut header = false;
+    for name in plugins.iter() {
+        let info = plugin_info(name);
+        let desc = match &info {
+            Some(info)
+                if BuiltinCategory::ALL
+                    .iter()
+                    .any(|c| c.label() == info.category()) =>
+            {
+                continue;
+            }
+            Some(info) => info.desc(),
+            None => &[],
+        };
+        if !header {
+            shell_write_idx(b"Plugins", COLOR_PROMPT_ACCENT);
+            shell_write(b":\n");
+            header = true;
+        }
+        write_summary(name, desc);
+    }
+    if header {
+        shell_write(NL);
+    }
+
     0
 }
 
+fn write_summary(name: &[u8], desc: &[u8]) {
+    shell_write(b"  ");
+    write_padded_colored(name, COLOR_EXEC_GREEN);
+    shell_write(desc);
+    shell_write(NL);
+}
+
+fn write_usage(name: &[u8], desc: &[u8], usage: &[u8], detail: &[u8]) {
+    shell_write_idx(name, COLOR_EXEC_GREEN);
+    shell_write(b" - ");
+    shell_write(desc);
+    shell_write(b"\n\n");
+    shell_write_idx(b"Usage: ", COLOR_COMMENT_GRAY);
+    shell_write(usage);
+    shell_write(b"\n\n");
+    if !detail.is_empty() {
+        shell_write(detail);
+        shell_write(NL);
+    }
+}
+
 fn cmd_help_single(name: *const u8) -> i32 {
     for entry in BUILTINS {
         if !u_streq_slice(name, entry.name) {
             continue;
         }
-        shell_write_idx(entry.name, COLOR_EXEC_GREEN);
-        shell_write(b" - ");
-        shell_write(entry.desc);
-        shell_write(b"\n\n");
-        shell_write_idx(b"Usage: ", COLOR_COMMENT_GRAY);
-        shell_write(entry.usage);
-        shell_write(b"\n\n");
-        if !entry.detail.is_empty() {
-            shell_write(entry.detail);
-            shell_write(NL);
-        }
+        write_usage(entry.name, entry.desc, entry.usage, entry.detail);
         return 0;
     }
 
@@ -88,12 +168,15 @@ fn cmd_help_single(name: *const u8) -> i32 {
         return 0;
     }
 
-    shell_write_idx(b"help: unknown command '", COLOR_ERROR_RED);
     let len = runtime::u_strlen(name);
-    shell_write_idx(
-        unsafe { core::slice::from_raw_parts(name, len) },
-        COLOR_ERROR_RED,
-    );
+    let name = unsafe { core::slice::from_raw_parts(name, len) };
+    if let Some(info) = plugin_info(name) {
+        write_usage(name, info.desc(), info.usage(), info.detail());
+        return 0;
+    }
+
+    shell_write_idx(b"help: unknown command '", COLOR_ERROR_RED);
+    shell_write_idx(name, COLOR_ERROR_RED);
     shell_write_idx(b"'\n", COLOR_ERROR_RED);
     1
 }
@@ -166,60 +249,14 @@ pub fn cmd_info(_argc: i32, _argv: &[*const u8]) -> i32 {
     0
 }
 
-fn write_zero_padded(buf: &mut [u8], pos: usize, value: u64) {
-    if pos + 1 < buf.len() {
-        buf[pos] = b'0' + ((value / 10) % 10) as u8;
-        buf[pos + 1] = b'0' + (value % 10) as u8;
-    }
-}
-
-/// Write a value as a 6-digit zero-padded decimal (microseconds).
-fn write_zero_padded_6(value: u64) {
-    let mut buf = [b'0'; 6];
-    let mut v = value;
-    let mut i = 5usize;
-    loop {
-        buf[i] = b'0' + (v % 10) as u8;
-        v /= 10;
-        if i == 0 {
-            break;
-        }
-        i -= 1;
-    }
-    shell_write(&buf);
-}
-
 pub fn cmd_uptime(_argc: i32, _argv: &[*const u8]) -> i32 {
-    let ns = sys_core::clock_gettime_ns();
-    let total_secs = ns / 1_000_000_000;
-    let sub_ms = (ns % 1_000_000_000) / 1_000_000;
-    let hours = total_secs / 3600;
-    let minutes = (total_secs % 3600) / 60;
-    let seconds = total_secs % 60;
+    let ms = sys_core::clock_gettime_ns() / 1_000_000;
 
+    let mut buf = NumBuf::<32>::new();
     shell_write(b"up ");
-
-    if hours > 0 {
-        write_u64(hours);
-        shell_write(b"h ");
-    }
-
-    let mut time_buf = [0u8; 5];
-    write_zero_padded(&mut time_buf, 0, minutes);
-    time_buf[2] = b':';
-    write_zero_padded(&mut time_buf, 3, seconds);
-    shell_write(&time_buf);
-
-    shell_write(b".");
-    // Write milliseconds zero-padded to 3 digits.
-    let mut ms_buf = [0u8; 3];
-    ms_buf[0] = b'0' + ((sub_ms / 100) % 10) as u8;
-    ms_buf[1] = b'0' + ((sub_ms / 10) % 10) as u8;
-    ms_buf[2] = b'0' + (sub_ms % 10) as u8;
-    shell_write(&ms_buf);
-
+    shell_write(numfmt::trim_nul(buf.format_duration_ms(ms)));
     shell_write(b" (");
-    write_u64(ns / 1_000_000);
+    shell_write(numfmt::trim_nul(buf.format_grouped(ms)));
     shell_write(b" ms)\n");
     0
 }
@@ -246,68 +283,51 @@ pub fn cmd_free(_argc: i32, _argv: &[*const u8]) -> i32 {
         return 1;
     }
 
-    const PAGE_SIZE_KB: u64 = 4; // 4 KiB per page
+    const PAGE_SIZE: u64 = 4096;
 
-    let total_kb = info.total_pages as u64 * PAGE_SIZE_KB;
-    let free_kb = info.free_pages as u64 * PAGE_SIZE_KB;
-    let used_kb = info.allocated_pages as u64 * PAGE_SIZE_KB;
+    let pages = [
+        info.total_pages as u64,
+        info.free_pages as u64,
+        info.allocated_pages as u64,
+    ];
 
     shell_write_idx(
-        b"              total       free       used\n",
+        b"               total         free         used\n",
         COLOR_COMMENT_GRAY,
     );
 
-    shell_write_idx(b"Pages:   ", COLOR_COMMENT_GRAY);
-    write_right_aligned(info.total_pages as u64, 10);
-    write_right_aligned(info.free_pages as u64, 11);
-    write_right_aligned(info.allocated_pages as u64, 11);
+    let mut buf = NumBuf::<32>::new();
+    shell_write_idx(b"Pages: ", COLOR_COMMENT_GRAY);
+    for count in pages {
+        write_right_aligned(buf.format_grouped(count), FREE_COL_WIDTH);
+    }
     shell_write(NL);
 
-    shell_write_idx(b"KiB:     ", COLOR_COMMENT_GRAY);
-    write_right_aligned(total_kb, 10);
-    write_right_aligned(free_kb, 11);
-    write_right_aligned(used_kb, 11);
+    shell_write_idx(b"KiB:   ", COLOR_COMMENT_GRAY);
+    for count in pages {
+        write_right_aligned(buf.format_grouped(count * PAGE_SIZE / 1024), FREE_COL_WIDTH);
+    }
     shell_write(NL);
 
-    shell_write_idx(b"MiB:     ", COLOR_COMMENT_GRAY);
-    write_right_aligned(total_kb / 1024, 10);
-    write_right_aligned(free_kb / 1024, 11);
-    write_right_aligned(used_kb / 1024, 11);
+    shell_write_idx(b"Size:  ", COLOR_COMMENT_GRAY);
+    for count in pages {
+        write_right_aligned(
+            buf.format_bytes(count * PAGE_SIZE, UnitBase::Binary),
+            FREE_COL_WIDTH,
+        );
+    }
     shell_write(NL);
 
     0
 }
 
-fn write_right_aligned(value: u64, width: usize) {
-    let mut tmp = [0u8; 20];
-    let digit_count = format_u64(value, &mut tmp);
-    let pad = width.saturating_sub(digit_count);
-    for _ in 0..pad {
+/// Write formatter output right-aligned in `width` columns.
+fn write_right_aligned(text: &[u8], width: usize) {
+    let text = numfmt::trim_nul(text);
+    for _ in text.len()..width {
         shell_write(b" ");
     }
-    shell_write(&tmp[..digit_count]);
-}
-
-fn format_u64(value: u64, buf: &mut [u8; 20]) -> usize {
-    if value == 0 {
-        buf[0] = b'0';
-        return 1;
-    }
-    let mut n = value;
-    let mut rev = [0u8; 20];
-    let mut r = 0usize;
-    while n != 0 && r < rev.len() {
-        rev[r] = b'0' + (n % 10) as u8;
-        n /= 10;
-        r += 1;
-    }
-    let mut idx = 0usize;
-    while r > 0 && idx < buf.len() {
-        buf[idx] = rev[r - 1];
-        idx += 1;
-        r -= 1;
-    }
-    idx
+    shell_write(text);
 }
 
 pub fn cmd_time(argc: i32, argv: &[*const u8]) -> i32 {
@@ -321,40 +341,133 @@ pub fn cmd_time(argc: i32, argv: &[*const u8]) -> i32 {
     let end_ns = sys_core::clock_gettime_ns();
     let elapsed_ns = end_ns.saturating_sub(start_ns);
 
-    let secs = elapsed_ns / 1_000_000_000;
-    let sub_us = (elapsed_ns % 1_000_000_000) / 1_000;
-
+    // Microsecond resolution for sub-millisecond commands.
+    let mut buf = NumBuf::<32>::new();
     shell_write(b"\nreal\t");
-    write_u64(secs);
-    shell_write(b".");
-    // Write microseconds zero-padded to 6 digits for sub-millisecond precision.
-    write_zero_padded_6(sub_us);
+    shell_write(numfmt::trim_nul(buf.format_fixed(elapsed_ns / 1_000, 6)));
     shell_write(b"s\n");
 
     rc
 }
 
+const PERF_USAGE: &[u8] = b"usage: perf stat <command> [args...]\n";
+const PERF_VALUE_WIDTH: usize = 16;
+
+/// Ratio `num / den` scaled by `scale`, or `None` when `den` is zero.
+fn perf_ratio(num: Option<u64>, den: Option<u64>, scale: u64) -> Option<u64> {
+    match (num, den) {
+        (Some(num), Some(den)) if den > 0 => {
+            Some((num as u128 * scale as u128 / den as u128) as u64)
+        }
+        _ => None,
+    }
+}
+
+pub fn cmd_perf(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc < 3 || argv[1].is_null() || !u_streq_slice(argv[1], b"stat") {
+        shell_write(PERF_USAGE);
+        return 1;
+    }
+
+    // Count the command's tasks only, not the shell waiting for them.
+    let mut ids: [Option<u32>; PERF_EVENT_COUNT] = [None; PERF_EVENT_COUNT];
+    let mut unsupported = false;
+    for (event, id) in ids.iter_mut().enumerate() {
+        match sys_core::perf_open(event as u32, 0, PERF_FLAG_CHILDREN_ONLY) {
+            Ok(counter) => *id = Some(counter),
+            Err(SyscallError::EOPNOTSUPP) => unsupported = true,
+            Err(_) => {}
+        }
+    }
+    if ids.iter().all(Option::is_none) {
+        if unsupported {
+            shell_write_idx(b"perf: no usable performance counters\n", COLOR_ERROR_RED);
+        } else {
+            shell_write_idx(b"perf: cannot open counters\n", COLOR_ERROR_RED);
+        }
+        return 1;
+    }
+
+    let start_ns = sys_core::clock_gettime_ns();
+    let rc = super::super::exec::execute_tokens(argc - 2, &argv[2..]);
+    let elapsed_ns = sys_core::clock_gettime_ns().saturating_sub(start_ns);
+
+    let mut values: [Option<u64>; PERF_EVENT_COUNT] = [None; PERF_EVENT_COUNT];
+    for (value, id) in values.iter_mut().zip(ids) {
+        let Some(id) = id else {
+            continue;
+        };
+        *value = sys_core::perf_read(id).ok().map(|reading| reading.value);
+        let _ = sys_core::perf_close(id);
+    }
+
+    let mut buf = NumBuf::<32>::new();
+    shell_write(b"\nPerformance counter stats:\n\n");
+    for (event, value) in values.iter().enumerate() {
+        match value {
+            Some(value) => write_right_aligned(buf.format_grouped(*value), PERF_VALUE_WIDTH),
+            None => {
+                shell_write_idx(b"   <not counted>", COLOR_COMMENT_GRAY);
+            }
+        }
+        shell_write(b"  ");
+        write_column(perf_event_name(event as u32).as_bytes(), 18);
+        let note = match event as u32 {
+            PERF_EVENT_INSTRUCTIONS => perf_ratio(
+                values[PERF_EVENT_INSTRUCTIONS as usize],
+                values[PERF_EVENT_CYCLES as usize],
+                100,
+            )
+            .map(|ipc| (ipc, &b" insn per cycle"[..])),
+            PERF_EVENT_CACHE_MISSES => perf_ratio(
+                values[PERF_EVENT_CACHE_MISSES as usize],
+                values[PERF_EVENT_CACHE_REFERENCES as usize],
+                10_000,
+            )
+            .map(|pct| (pct, &b"% of cache refs"[..])),
+            PERF_EVENT_BRANCH_MISSES => perf_ratio(
+                values[PERF_EVENT_BRANCH_MISSES as usize],
+                values[PERF_EVENT_BRANCHES as usize],
+                10_000,
+            )
+            .map(|pct| (pct, &b"% of branches"[..])),
+            _ => None,
+        };
+        if let Some((value, label)) = note {
+            shell_write_idx(b"# ", COLOR_COMMENT_GRAY);
+            shell_write(numfmt::trim_nul(buf.format_fixed(value, 2)));
+            shell_write_idx(label, COLOR_COMMENT_GRAY);
+        }
+        shell_write(NL);
+    }
+    shell_write(NL);
+    write_right_aligned(buf.format_fixed(elapsed_ns / 1_000, 6), PERF_VALUE_WIDTH);
+    shell_write(b"  seconds time elapsed\n");
+
+    rc
+}
+
 pub fn cmd_date(_argc: i32, _argv: &[*const u8]) -> i32 {
-    let ms = sys_core::get_time_ms();
-    let total_secs = ms / 1000;
-    let days = total_secs / 86400;
-    let hours = (total_secs % 86400) / 3600;
-    let minutes = (total_secs % 3600) / 60;
-    let seconds = total_secs % 60;
-
-    shell_write(b"Day ");
-    write_u64(days);
-    shell_write(b" ");
-
-    let mut time_buf = [0u8; 8];
-    write_zero_padded(&mut time_buf, 0, hours);
-    time_buf[2] = b':';
-    write_zero_padded(&mut time_buf, 3, minutes);
-    time_buf[5] = b':';
-    write_zero_padded(&mut time_buf, 6, seconds);
-    shell_write(&time_buf);
-
-    shell_write(b" SLT (Sloptopia Local Time)\n");
+    let mut ts = Timespec::default();
+    if sys_core::clock_gettime_realtime(&mut ts) < 0 {
+        shell_write_idx(b"date: wall clock unavailable\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    let now = CivilTime::from_unix_secs(ts.tv_sec);
+
+    let mut buf = NumBuf::<32>::new();
+    let fields: [(u64, usize, &[u8]); 6] = [
+        (now.year as u64, 4, b"-"),
+        (now.month as u64, 2, b"-"),
+        (now.day as u64, 2, b" "),
+        (now.hour as u64, 2, b":"),
+        (now.minute as u64, 2, b":"),
+        (now.second as u64, 2, b" UTC\n"),
+    ];
+    for (value, width, sep) in fields {
+        shell_write(numfmt::trim_nul(buf.format_padded(value, width, b'0')));
+        shell_write(sep);
+    }
     0
 }
 
@@ -461,3 +574,976 @@ pub fn cmd_resolve(argc: i32, argv: &[*const u8]) -> i32 {
         }
     }
 }
+
+fn write_keymap_list() {
+    shell_write(b"available:");
+    for id in 0..KEYMAP_COUNT {
+        shell_write(b" ");
+        shell_write(keymap_name(id).unwrap_or("?").as_bytes());
+    }
+    shell_write(NL);
+}
+
+pub fn cmd_loadkeys(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc < 2 || argv[1].is_null() {
+        let Ok(id) = input::keymap() else {
+            shell_write_idx(b"loadkeys: cannot query layout\n", COLOR_ERROR_RED);
+            return 1;
+        };
+        shell_write(b"keymap: ");
+        shell_write(keymap_name(id).unwrap_or("?").as_bytes());
+        shell_write(NL);
+        write_keymap_list();
+        return 0;
+    }
+
+    let name = unsafe { core::slice::from_raw_parts(argv[1], runtime::u_strlen(argv[1])) };
+    let Some(id) = keymap_from_name(name) else {
+        shell_write_idx(b"loadkeys: unknown layout ", COLOR_ERROR_RED);
+        shell_write(name);
+        shell_write(NL);
+        write_keymap_list();
+        return 1;
+    };
+    match input::set_keymap(id) {
+        Ok(prev) => {
+            shell_write(b"keymap: ");
+            shell_write(keymap_name(prev).unwrap_or("?").as_bytes());
+            shell_write(b" -> ");
+            shell_write(name);
+            shell_write(NL);
+            0
+        }
+        Err(_) => {
+            shell_write_idx(b"loadkeys: kernel rejected layout\n", COLOR_ERROR_RED);
+            1
+        }
+    }
+}
+
+const SETWALLPAPER_USAGE: &[u8] =
+    b"usage: setwallpaper [file.bmp | none] [center|stretch|tile|fit]\n";
+
+pub fn cmd_setwallpaper(argc: i32, argv: &[*const u8]) -> i32 {
+    let mut image = None;
+    let mut mode = None;
+    for &arg in argv.iter().take(argc as usize).skip(1) {
+        if arg.is_null() {
+            continue;
+        }
+        let name = unsafe { core::slice::from_raw_parts(arg, runtime::u_strlen(arg)) };
+        if let Some(m) = ScaleMode::from_name(name) {
+            mode = Some(m);
+        } else if image.is_none() {
+            image = Some((arg, name));
+        } else {
+            shell_write(SETWALLPAPER_USAGE);
+            return 1;
+        }
+    }
+
+    match image {
+        Some((_, b"none")) => {
+            let _ = fs::unlink_path(WALLPAPER_PATH.as_ptr() as *const c_char);
+        }
+        Some((arg, _)) => {
+            let mut path = [0u8; 256];
+            if normalize_path(arg, &mut path) != 0 {
+                shell_write_idx(PATH_TOO_LONG, COLOR_ERROR_RED);
+                return 1;
+            }
+            if !is_bmp(&path) {
+                shell_write_idx(b"setwallpaper: not a BMP image\n", COLOR_ERROR_RED);
+                return 1;
+            }
+            if !paths_equal(&path, WALLPAPER_PATH) && copy_file_inner(&path, WALLPAPER_PATH) != 0 {
+                return 1;
+            }
+        }
+        None => {}
+    }
+    if let Some(mode) = mode
+        && !wallpaper::write_mode(mode)
+    {
+        shell_write_idx(b"setwallpaper: cannot save mode\n", COLOR_ERROR_RED);
+        return 1;
+    }
+
+    window::wallpaper_reload();
+    shell_write(b"wallpaper: ");
+    shell_write(wallpaper::read_mode().name());
+    shell_write(NL);
+    0
+}
+
+/// Whether the file at `path` (NUL-terminated) starts like a BMP.
+fn is_bmp(path: &[u8]) -> bool {
+    let Ok(fd) = fs::open_path(path.as_ptr() as *const c_char, USER_FS_OPEN_READ) else {
+        return false;
+    };
+    let mut magic = [0u8; 2];
+    let len = fs::read_slice(fd, &mut magic).unwrap_or(0);
+    let _ = fs::close_fd(fd);
+    len == magic.len() && magic == BMP_MAGIC
+}
+
+const KTRACE_USAGE: &[u8] = b"usage: ktrace start | stop | dump [file]\n";
+
+pub fn cmd_ktrace(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc < 2 || argv[1].is_null() || argc > 3 {
+        shell_write(KTRACE_USAGE);
+        return 1;
+    }
+    let cmd = unsafe { core::slice::from_raw_parts(argv[1], runtime::u_strlen(argv[1])) };
+    let mut path = [0u8; 256];
+    let to_file = argc == 3;
+    let op = match cmd {
+        b"start" if !to_file => KTRACE_OP_START,
+        b"stop" if !to_file => KTRACE_OP_STOP,
+        b"dump" if to_file => {
+            if normalize_path(argv[2], &mut path) != 0 {
+                shell_write_idx(PATH_TOO_LONG, COLOR_ERROR_RED);
+                return 1;
+            }
+            KTRACE_OP_EXPORT_FILE
+        }
+        b"dump" => KTRACE_OP_EXPORT_SERIAL,
+        _ => {
+            shell_write(KTRACE_USAGE);
+            return 1;
+        }
+    };
+
+    let path_ptr = if to_file {
+        path.as_ptr() as *const c_char
+    } else {
+        ptr::null()
+    };
+    match sys_core::ktrace(op, path_ptr) {
+        Ok(_) if op == KTRACE_OP_START => {
+            shell_write(b"ktrace: tracing\n");
+        }
+        Ok(_) if op == KTRACE_OP_STOP => {
+            shell_write(b"ktrace: stopped\n");
+        }
+        Ok(events) => {
+            shell_write(b"ktrace: wrote ");
+            write_u64(events);
+            if to_file {
+                shell_write(b" events to ");
+                let len = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+                shell_write(&path[..len]);
+                shell_write(NL);
+            } else {
+                shell_write(b" events to serial\n");
+            }
+        }
+        Err(SyscallError::EBUSY) => {
+            shell_write_idx(b"ktrace: export already in progress\n", COLOR_ERROR_RED);
+            return 1;
+        }
+        Err(_) => {
+            shell_write_idx(b"ktrace: cannot write trace file\n", COLOR_ERROR_RED);
+            return 1;
+        }
+    }
+    0
+}
+
+const TRACE_USAGE: &[u8] = b"usage: trace start | stop | show [count]\n";
+
+/// Events `trace show` prints without a count.
+const TRACE_SHOW_DEFAULT: u32 = 40;
+
+/// Events fetched from the kernel per call.
+const TRACE_READ_CHUNK: usize = 32;
+
+/// Recent wakeups remembered to report how long a woken task waited for
+/// its CPU.
+const TRACE_WAKEUP_SLOTS: usize = 16;
+
+fn write_trace_task(task: u32) {
+    if task == KTRACE_NO_TASK {
+        shell_write(b"-");
+    } else {
+        write_u64(task as u64);
+    }
+}
+
+fn write_trace_us(ns: u64) {
+    let mut buf = NumBuf::<24>::new();
+    shell_write(numfmt::trim_nul(buf.format_fixed(ns, 3)));
+}
+
+/// Timeline state carried from one printed event to the next.
+struct TraceTimeline {
+    start_ns: u64,
+    prev_ns: u64,
+    /// (task, time) of recent wakeups, for wakeup-to-run latency.
+    wakeups: [(u32, u64); TRACE_WAKEUP_SLOTS],
+    next_wakeup: usize,
+}
+
+impl TraceTimeline {
+    fn new(start_ns: u64) -> Self {
+        Self {
+            start_ns,
+            prev_ns: start_ns,
+            wakeups: [(KTRACE_NO_TASK, 0); TRACE_WAKEUP_SLOTS],
+            next_wakeup: 0,
+        }
+    }
+
+    /// Forget and return when `task` was last woken.
+    fn take_wakeup(&mut self, task: u32) -> Option<u64> {
+        let slot = self.wakeups.iter_mut().find(|(t, _)| *t == task)?;
+        slot.0 = KTRACE_NO_TASK;
+        Some(slot.1)
+    }
+
+    /// Print one line: time since the first event, time since the
+    /// previous one, the CPU and what happened, all times in microseconds.
+    fn print(&mut self, rec: &KtraceRecord) {
+        let mut buf = NumBuf::<24>::new();
+        let ts = rec.timestamp_ns.max(self.start_ns);
+        write_column(
+            numfmt::trim_nul(buf.format_fixed(ts - self.start_ns, 3)),
+            12,
+        );
+        shell_write(b"+");
+        write_column(
+            numfmt::trim_nul(buf.format_fixed(ts.saturating_sub(self.prev_ns), 3)),
+            11,
+        );
+        self.prev_ns = ts;
+        write_column(numfmt::trim_nul(buf.format_u32(rec.cpu as u32)), 4);
+
+        match rec.kind {
+            KTRACE_KIND_SCHED_SWITCH => {
+                shell_write(b"switch   ");
+                write_trace_task(rec.arg0);
+                shell_write(b" -> ");
+                write_trace_task(rec.arg1);
+                if let Some(woken) = self.take_wakeup(rec.arg1) {
+                    shell_write_idx(b"  (woken ", COLOR_COMMENT_GRAY);
+                    write_trace_us(ts.saturating_sub(woken));
+                    shell_write_idx(b"us ago)", COLOR_COMMENT_GRAY);
+                }
+            }
+            KTRACE_KIND_SCHED_WAKEUP => {
+                shell_write(b"wakeup   ");
+                write_trace_task(rec.arg0);
+                if rec.arg1 != KTRACE_NO_TASK {
+                    shell_write(b" by ");
+                    write_trace_task(rec.arg1);
+                }
+                self.wakeups[self.next_wakeup] = (rec.arg0, ts);
+                self.next_wakeup = (self.next_wakeup + 1) % TRACE_WAKEUP_SLOTS;
+            }
+            KTRACE_KIND_SYSCALL_ENTER | KTRACE_KIND_SYSCALL_EXIT => {
+                shell_write(if rec.kind == KTRACE_KIND_SYSCALL_ENTER {
+                    b"sys-in   "
+                } else {
+                    b"sys-out  "
+                });
+                write_u64(rec.arg0 as u64);
+                shell_write(b" task ");
+                write_trace_task(rec.arg1);
+            }
+            KTRACE_KIND_IRQ_ENTER | KTRACE_KIND_IRQ_EXIT => {
+                shell_write(if rec.kind == KTRACE_KIND_IRQ_ENTER {
+                    b"irq-in   0x"
+                } else {
+                    b"irq-out  0x"
+                });
+                write_hex_byte(rec.arg0 as u8);
+            }
+            KTRACE_KIND_PAGE_FAULT => {
+                shell_write(b"fault    ");
+                shell_write(numfmt::trim_nul(buf.format_hex_u64(rec.addr)));
+                shell_write(b" err 0x");
+                write_hex_byte(rec.arg0 as u8);
+                shell_write(b" task ");
+                write_trace_task(rec.arg1);
+            }
+            _ => {
+                shell_write(b"?");
+            }
+        }
+        shell_write(NL);
+    }
+}
+
+/// Print the newest `count` events as a timeline.
+fn trace_show(count: u32) -> i32 {
+    let mut chunk = [KtraceRecord::default(); TRACE_READ_CHUNK];
+    let total = match sys_core::ktrace_read(&mut [], 0) {
+        Ok(total) => total,
+        Err(SyscallError::EBUSY) => {
+            shell_write_idx(b"trace: tracer busy\n", COLOR_ERROR_RED);
+            return 1;
+        }
+        Err(_) => {
+            shell_write_idx(b"trace: cannot read events\n", COLOR_ERROR_RED);
+            return 1;
+        }
+    };
+    if total == 0 {
+        shell_write(b"trace: no events recorded\n");
+        return 0;
+    }
+
+    let first = total.saturating_sub(count as usize);
+    let mut timeline: Option<TraceTimeline> = None;
+    let mut next = first;
+    while next < total {
+        let got = match sys_core::ktrace_read(&mut chunk, next) {
+            Ok(recorded) => recorded.min(total).saturating_sub(next).min(chunk.len()),
+            Err(_) => 0,
+        };
+        if got == 0 {
+            break;
+        }
+        for rec in &chunk[..got] {
+            if timeline.is_none() {
+                shell_write_idx(b"time(us)     delta(us)  cpu event\n", COLOR_PROMPT_ACCENT);
+            }
+            timeline
+                .get_or_insert_with(|| TraceTimeline::new(rec.timestamp_ns))
+                .print(rec);
+        }
+        next += got;
+    }
+
+    shell_write(b"trace: ");
+    write_u64((next - first) as u64);
+    shell_write(b" of ");
+    write_u64(total as u64);
+    shell_write(b" events\n");
+    0
+}
+
+pub fn cmd_trace(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc < 2 || argv[1].is_null() || argc > 3 {
+        shell_write(TRACE_USAGE);
+        return 1;
+    }
+    let cmd = unsafe { core::slice::from_raw_parts(argv[1], runtime::u_strlen(argv[1])) };
+    match (cmd, argc) {
+        (b"start", 2) => match sys_core::ktrace(KTRACE_OP_START, ptr::null()) {
+            Ok(_) => {
+                shell_write(b"trace: tracing\n");
+                0
+            }
+            Err(_) => {
+                shell_write_idx(b"trace: tracer busy\n", COLOR_ERROR_RED);
+                1
+            }
+        },
+        (b"stop", 2) => {
+            let _ = sys_core::ktrace(KTRACE_OP_STOP, ptr::null());
+            shell_write(b"trace: stopped\n");
+            0
+        }
+        (b"show", 2) => trace_show(TRACE_SHOW_DEFAULT),
+        (b"show", 3) => match parse_u32_arg(argv[2]) {
+            Some(count) if count > 0 => trace_show(count),
+            _ => {
+                shell_write(TRACE_USAGE);
+                1
+            }
+        },
+        _ => {
+            shell_write(TRACE_USAGE);
+            1
+        }
+    }
+}
+
+const LOGLEVEL_USAGE: &[u8] =
+    b"usage: loglevel [[subsystem] error|warn|info|debug|trace|default]\n";
+
+fn write_loglevels() -> i32 {
+    let Ok(default) = sys_core::klog_level(KLOG_MODULE_DEFAULT, KLOG_LEVEL_KEEP) else {
+        shell_write_idx(b"loglevel: cannot read levels\n", COLOR_ERROR_RED);
+        return 1;
+    };
+    write_column(b"default", NAME_COL_WIDTH);
+    shell_write(klog_level_name(default).as_bytes());
+    shell_write(NL);
+    for (module, name) in KLOG_MODULE_NAMES.iter().enumerate() {
+        let Ok(level) = sys_core::klog_level(module as u64, KLOG_LEVEL_KEEP) else {
+            continue;
+        };
+        write_column(name.as_bytes(), NAME_COL_WIDTH);
+        if level == KLOG_LEVEL_INHERIT {
+            shell_write_idx(b"default\n", COLOR_COMMENT_GRAY);
+        } else {
+            shell_write(klog_level_name(level).as_bytes());
+            shell_write(NL);
+        }
+    }
+    0
+}
+
+pub fn cmd_loglevel(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc < 2 || argv[1].is_null() {
+        return write_loglevels();
+    }
+    if argc > 3 {
+        shell_write(LOGLEVEL_USAGE);
+        return 1;
+    }
+    let arg =
+        |i: usize| unsafe { core::slice::from_raw_parts(argv[i], runtime::u_strlen(argv[i])) };
+
+    let (module, level_name) = if argc == 3 && !argv[2].is_null() {
+        let name = arg(1);
+        let Some(module) = klog_module_from_name(name) else {
+            shell_write_idx(b"loglevel: unknown subsystem ", COLOR_ERROR_RED);
+            shell_write(name);
+            shell_write(NL);
+            return 1;
+        };
+        (module as u64, arg(2))
+    } else {
+        (KLOG_MODULE_DEFAULT, arg(1))
+    };
+    let level = match klog_level_from_name(level_name) {
+        Some(level) => level as u64,
+        None if level_name == b"default" && module != KLOG_MODULE_DEFAULT => KLOG_LEVEL_INHERIT,
+        None => {
+            shell_write(LOGLEVEL_USAGE);
+            return 1;
+        }
+    };
+    if sys_core::klog_level(module, level).is_err() {
+        shell_write_idx(b"loglevel: kernel rejected level\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    write_loglevels()
+}
+
+const LOCKSTAT_USAGE: &[u8] = b"usage: lockstat [-r]\n";
+const LOCKSTAT_NAME_WIDTH: usize = 14;
+const LOCKSTAT_COL_WIDTH: usize = 13;
+
+pub fn cmd_lockstat(argc: i32, argv: &[*const u8]) -> i32 {
+    let reset = match argc {
+        1 => false,
+        2 if u_streq_slice(argv[1], b"-r") => true,
+        _ => {
+            shell_write(LOCKSTAT_USAGE);
+            return 1;
+        }
+    };
+
+    let mut stats = [LockStat::default(); LOCK_STATS_MAX_CLASSES];
+    let count = match sys_core::lock_stats(&mut stats, reset) {
+        Ok(count) => count,
+        Err(SyscallError::EOPNOTSUPP) => {
+            shell_write_idx(
+                b"lockstat: kernel built without lockstat\n",
+                COLOR_ERROR_RED,
+            );
+            return 1;
+        }
+        Err(_) => {
+            shell_write_idx(b"lockstat: failed to read counters\n", COLOR_ERROR_RED);
+            return 1;
+        }
+    };
+    let stats = &mut stats[..count];
+    stats.sort_unstable_by_key(|stat| core::cmp::Reverse(stat.total_held_ns));
+
+    shell_write_idx(
+        b"class              acquired    contended     wait(us) max wait(us)     held(us)\n",
+        COLOR_COMMENT_GRAY,
+    );
+    let mut buf = NumBuf::<32>::new();
+    for stat in stats.iter() {
+        let len = stat
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(stat.name.len());
+        shell_write(&stat.name[..len]);
+        for _ in len..LOCKSTAT_NAME_WIDTH {
+            shell_write(b" ");
+        }
+        for value in [
+            stat.acquisitions,
+            stat.contended,
+            stat.total_wait_ns / 1_000,
+            stat.max_wait_ns / 1_000,
+            stat.total_held_ns / 1_000,
+        ] {
+            write_right_aligned(buf.format_grouped(value), LOCKSTAT_COL_WIDTH);
+        }
+        shell_write(NL);
+    }
+    if reset {
+        shell_write(b"lockstat: counters reset\n");
+    }
+    0
+}
+
+const AUDITCTL_USAGE: &[u8] = b"usage: auditctl [enable|disable <category|all>... | log | flush]\n";
+/// Records `auditctl log` shows.
+const AUDITCTL_LOG_LINES: usize = 20;
+
+fn audit_category_bit(name: &[u8]) -> Option<u32> {
+    if name == b"all" {
+        return Some(AUDIT_CAT_ALL);
+    }
+    AUDIT_CAT_NAMES
+        .iter()
+        .find(|(cat, _)| cat.as_bytes() == name)
+        .map(|(_, bit)| *bit)
+}
+
+fn auditctl_status() -> i32 {
+    let Ok(status) = sys_core::audit_status() else {
+        shell_write_idx(b"auditctl: cannot read status\n", COLOR_ERROR_RED);
+        return 1;
+    };
+    shell_write(b"categories:");
+    for (name, bit) in AUDIT_CAT_NAMES {
+        shell_write(b" ");
+        if status.enabled & bit != 0 {
+            shell_write_idx(name.as_bytes(), COLOR_EXEC_GREEN);
+        } else {
+            shell_write_idx(name.as_bytes(), COLOR_COMMENT_GRAY);
+        }
+    }
+    shell_write(NL);
+    print_kv(b"recorded: ", status.recorded);
+    print_kv(b"written:  ", status.written);
+    print_kv(b"dropped:  ", status.dropped);
+    if status.logging != 0 {
+        shell_write(b"logging to /var/log/audit and /var/log/acct\n");
+    } else {
+        shell_write(b"not logging yet: root filesystem not writable\n");
+    }
+    0
+}
+
+fn auditctl_log() -> i32 {
+    let mut records = [AuditRecord::default(); AUDITCTL_LOG_LINES];
+    let Ok(count) = sys_core::audit_read(&mut records) else {
+        shell_write_idx(b"auditctl: cannot read records\n", COLOR_ERROR_RED);
+        return 1;
+    };
+    shell_write_idx(
+        b"seq     pid   comm            cat    event\n",
+        COLOR_COMMENT_GRAY,
+    );
+    let mut buf = NumBuf::<32>::new();
+    for record in &records[..count] {
+        write_column(buf.format_u64(record.seq), 7);
+        write_column(buf.format_u32(record.pid), 5);
+        write_column(record.comm_str().as_bytes(), 15);
+        write_column(audit_cat_name(record.category).as_bytes(), 6);
+        if record.category == AUDIT_CAT_ACCT {
+            shell_write(b"exit ");
+            shell_write(buf.format_i64(record.result));
+            shell_write(b" after ");
+            shell_write(buf.format_duration_ms(record.args[0]));
+        } else {
+            shell_write(b"syscall ");
+            shell_write(buf.format_u32(record.op));
+            shell_write(b" -> ");
+            shell_write(buf.format_i64(record.result));
+        }
+        shell_write(NL);
+    }
+    0
+}
+
+pub fn cmd_auditctl(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc < 2 || argv[1].is_null() {
+        return auditctl_status();
+    }
+    let cmd = unsafe { core::slice::from_raw_parts(argv[1], runtime::u_strlen(argv[1])) };
+    let enable = match cmd {
+        b"log" if argc == 2 => return auditctl_log(),
+        b"flush" if argc == 2 => {
+            return match sys_core::audit_flush() {
+                Ok(count) => {
+                    shell_write(b"auditctl: wrote ");
+                    write_u64(count as u64);
+                    shell_write(b" records\n");
+                    0
+                }
+                Err(SyscallError::EBUSY) => {
+                    shell_write_idx(b"auditctl: flush already in progress\n", COLOR_ERROR_RED);
+                    1
+                }
+                Err(_) => {
+                    shell_write_idx(
+                        b"auditctl: cannot write /var/log (root not writable?)\n",
+                        COLOR_ERROR_RED,
+                    );
+                    1
+                }
+            };
+        }
+        b"enable" if argc > 2 => true,
+        b"disable" if argc > 2 => false,
+        _ => {
+            shell_write(AUDITCTL_USAGE);
+            return 1;
+        }
+    };
+
+    let mut bits = 0u32;
+    for &arg in &argv[2..argc as usize] {
+        if arg.is_null() {
+            break;
+        }
+        let name = unsafe { core::slice::from_raw_parts(arg, runtime::u_strlen(arg)) };
+        let Some(bit) = audit_category_bit(name) else {
+            shell_write_idx(b"auditctl: unknown category ", COLOR_ERROR_RED);
+            shell_write(name);
+            shell_write(NL);
+            return 1;
+        };
+        bits |= bit;
+    }
+    let Ok(status) = sys_core::audit_status() else {
+        shell_write_idx(b"auditctl: cannot read status\n", COLOR_ERROR_RED);
+        return 1;
+    };
+    let mask = if enable {
+        status.enabled | bits
+    } else {
+        status.enabled & !bits
+    };
+    if sys_core::audit_set(mask).is_err() {
+        shell_write_idx(b"auditctl: kernel rejected mask\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    auditctl_status()
+}
+
+const CRASHDUMP_USAGE: &[u8] = b"usage: crashdump [clear]\n";
+/// `slopos_abi::crashdump::CRASHDUMP_PATH`, NUL-terminated.
+const CRASHDUMP_PATH_C: &CStr = c"/var/crash/panic.dump";
+/// Stack bytes `crashdump` shows; the dump keeps more.
+const CRASHDUMP_STACK_SHOWN: usize = 256;
+
+fn crashdump_read(buf: &mut [u8]) -> Option<usize> {
+    let fd = fs::open_path(CRASHDUMP_PATH_C.as_ptr(), USER_FS_OPEN_READ).ok()?;
+    let mut len = 0;
+    while len < buf.len() {
+        match fs::read_slice(fd, &mut buf[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => len += n,
+        }
+    }
+    let _ = fs::close_fd(fd);
+    Some(len)
+}
+
+/// Overwrite the magic so the dump no longer reads back.
+fn crashdump_clear() -> i32 {
+    let Ok(fd) = fs::open_path(CRASHDUMP_PATH_C.as_ptr(), USER_FS_OPEN_WRITE) else {
+        shell_write_idx(b"crashdump: no dump file\n", COLOR_ERROR_RED);
+        return 1;
+    };
+    let ok = matches!(fs::write_slice(fd, &[0u8; 8]), Ok(8));
+    let _ = fs::close_fd(fd);
+    if !ok {
+        shell_write_idx(b"crashdump: cannot clear dump\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    shell_write(b"crashdump: cleared\n");
+    0
+}
+
+fn task_state_name(status: u8) -> &'static [u8] {
+    match TaskStatus::from_u8(status) {
+        TaskStatus::Ready => b"ready",
+        TaskStatus::Running => b"running",
+        TaskStatus::Blocked => b"blocked",
+        TaskStatus::Terminated => b"exited",
+        TaskStatus::Invalid => b"?",
+    }
+}
+
+fn crashdump_header(dump: &CrashDump<'_>) {
+    let mut buf = NumBuf::<32>::new();
+    shell_write_idx(b"kernel panic", COLOR_ERROR_RED);
+    if dump.unix_secs != 0 {
+        let t = CivilTime::from_unix_secs(dump.unix_secs);
+        let fields: [(u64, usize, &[u8]); 6] = [
+            (t.year as u64, 4, b"-"),
+            (t.month as u64, 2, b"-"),
+            (t.day as u64, 2, b" "),
+            (t.hour as u64, 2, b":"),
+            (t.minute as u64, 2, b":"),
+            (t.second as u64, 2, b" UTC"),
+        ];
+        shell_write(b" at ");
+        for (value, width, sep) in fields {
+            shell_write(numfmt::trim_nul(buf.format_padded(value, width, b'0')));
+            shell_write(sep);
+        }
+    }
+    shell_write(b", ");
+    shell_write(buf.format_ms(dump.time_ns / 1_000_000));
+    shell_write(b"s after boot\n");
+    if let Some(message) = dump.section(CRASHDUMP_SECTION_MESSAGE) {
+        shell_write(message);
+        shell_write(NL);
+    }
+    let mut exception = dump.u64s(CRASHDUMP_SECTION_EXCEPTION);
+    if let (Some(vector), Some(error)) = (exception.next(), exception.next()) {
+        shell_write(b"exception ");
+        shell_write(buf.format_u64(vector));
+        shell_write(b", error code ");
+        shell_write(numfmt::trim_nul(buf.format_hex_u64(error)));
+        shell_write(NL);
+    }
+}
+
+fn crashdump_regs(dump: &CrashDump<'_>) {
+    let mut buf = NumBuf::<32>::new();
+    shell_write_idx(b"\nregisters\n", COLOR_PROMPT_ACCENT);
+    for (i, (name, value)) in CRASHDUMP_REG_NAMES
+        .iter()
+        .zip(dump.u64s(CRASHDUMP_SECTION_REGS))
+        .enumerate()
+    {
+        write_column(name.as_bytes(), 7);
+        write_column(numfmt::trim_nul(buf.format_hex_u64(value)), 20);
+        if i % 3 == 2 {
+            shell_write(NL);
+        }
+    }
+
+    let mut frames = dump.u64s(CRASHDUMP_SECTION_BACKTRACE).peekable();
+    if frames.peek().is_some() {
+        shell_write_idx(b"\nbacktrace\n", COLOR_PROMPT_ACCENT);
+        for (i, addr) in frames.enumerate() {
+            shell_write(b"  #");
+            write_column(buf.format_u64(i as u64), 3);
+            shell_write(numfmt::trim_nul(buf.format_hex_u64(addr)));
+            shell_write(NL);
+        }
+    }
+
+    let Some(stack) = dump.section(CRASHDUMP_SECTION_STACK) else {
+        return;
+    };
+    let Some((rsp, bytes)) = stack.split_first_chunk::<8>() else {
+        return;
+    };
+    let rsp = u64::from_le_bytes(*rsp);
+    shell_write_idx(b"\nstack\n", COLOR_PROMPT_ACCENT);
+    if bytes.is_empty() {
+        shell_write(b"  unreadable at ");
+        shell_write(numfmt::trim_nul(buf.format_hex_u64(rsp)));
+        shell_write(NL);
+    }
+    let shown = &bytes[..bytes.len().min(CRASHDUMP_STACK_SHOWN)];
+    for (i, line) in shown.chunks(16).enumerate() {
+        write_column(
+            numfmt::trim_nul(buf.format_hex_u64(rsp + i as u64 * 16)),
+            19,
+        );
+        for word in line.chunks_exact(8) {
+            let value = u64::from_le_bytes(word.try_into().unwrap_or([0; 8]));
+            write_column(numfmt::trim_nul(buf.format_hex_u64(value)), 19);
+        }
+        shell_write(NL);
+    }
+}
+
+fn crashdump_state(dump: &CrashDump<'_>) {
+    let mut buf = NumBuf::<32>::new();
+    let mut tasks = dump.tasks().peekable();
+    if tasks.peek().is_some() {
+        shell_write_idx(b"\ntasks\n", COLOR_PROMPT_ACCENT);
+        shell_write_idx(
+            b"id   parent state    cpu  cycles          name\n",
+            COLOR_COMMENT_GRAY,
+        );
+        for task in tasks {
+            write_column(buf.format_u32(task.task_id), 4);
+            write_column(buf.format_u32(task.parent_id), 6);
+            write_column(task_state_name(task.status), 8);
+            write_column(buf.format_u32(task.cpu as u32), 4);
+            write_column(buf.format_grouped(task.runtime), 15);
+            shell_write(task.name_str().as_bytes());
+            shell_write(NL);
+        }
+    }
+
+    if let Some(memory) = dump.memory() {
+        shell_write_idx(b"\nmemory\n", COLOR_PROMPT_ACCENT);
+        shell_write(b"pages  ");
+        shell_write(buf.format_grouped(memory.free_pages));
+        shell_write(b" free of ");
+        shell_write(buf.format_grouped(memory.total_pages));
+        shell_write(NL);
+        shell_write(b"heap   ");
+        shell_write(buf.format_bytes(memory.heap_allocated, UnitBase::Binary));
+        shell_write(b" used of ");
+        shell_write(buf.format_bytes(memory.heap_total, UnitBase::Binary));
+        shell_write(NL);
+    }
+
+    if let Some(log) = dump.section(CRASHDUMP_SECTION_LOG) {
+        shell_write_idx(b"\nkernel log\n", COLOR_PROMPT_ACCENT);
+        shell_write(log);
+        if !log.ends_with(b"\n") {
+            shell_write(NL);
+        }
+    }
+}
+
+pub fn cmd_crashdump(argc: i32, argv: &[*const u8]) -> i32 {
+    if argc == 2 && u_streq_slice(argv[1], b"clear") {
+        return crashdump_clear();
+    }
+    if argc != 1 {
+        shell_write(CRASHDUMP_USAGE);
+        return 1;
+    }
+    with_crashdump_buf(|buf| {
+        let Some(len) = crashdump_read(buf) else {
+            shell_write(b"crashdump: no dump\n");
+            return 0;
+        };
+        let Some(dump) = CrashDump::parse(&buf[..len]) else {
+            shell_write(b"crashdump: no dump\n");
+            return 0;
+        };
+        crashdump_header(&dump);
+        crashdump_regs(&dump);
+        crashdump_state(&dump);
+        0
+    })
+}
+
+pub fn cmd_memmap(argc: i32, _argv: &[*const u8]) -> i32 {
+    if argc != 1 {
+        shell_write(b"usage: memmap\n");
+        return 1;
+    }
+    let fd = match fs::open_path(c"/proc/memmap".as_ptr(), USER_FS_OPEN_READ) {
+        Ok(fd) => fd,
+        Err(_) => {
+            shell_write_idx(b"memmap: cannot open /proc/memmap\n", COLOR_ERROR_RED);
+            return 1;
+        }
+    };
+    let mut buf = [0u8; SHELL_IO_MAX];
+    let mut status = 0;
+    loop {
+        match fs::read_slice(fd, &mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                if !shell_write(&buf[..n]) {
+                    break;
+                }
+            }
+            Err(_) => {
+                shell_write_idx(b"memmap: read error\n", COLOR_ERROR_RED);
+                status = 1;
+                break;
+            }
+        }
+    }
+    let _ = fs::close_fd(fd);
+    status
+}
+
+/// Pad `text` with spaces to `width` columns, always leaving one gap.
+fn write_column(text: &[u8], width: usize) {
+    shell_write(text);
+    for _ in text.len()..width.max(text.len() + 1) {
+        shell_write(b" ");
+    }
+}
+
+fn pci_class_name(class: u8) -> &'static [u8] {
+    match class {
+        0x01 => b"storage",
+        0x02 => b"network",
+        0x03 => b"display",
+        0x04 => b"multimedia",
+        0x05 => b"memory",
+        0x06 => b"bridge",
+        0x07 => b"communication",
+        0x08 => b"system",
+        0x09 => b"input",
+        0x0C => b"serial bus",
+        _ => b"other",
+    }
+}
+
+pub fn cmd_lspci(argc: i32, _argv: &[*const u8]) -> i32 {
+    if argc != 1 {
+        shell_write(b"usage: lspci\n");
+        return 1;
+    }
+    let mut devices = [HwDevice::default(); HW_MAX_DEVICES];
+    let Ok(count) = sys_core::hw_inventory(&mut devices, HW_CLASS_PCI) else {
+        shell_write_idx(b"lspci: failed to read inventory\n", COLOR_ERROR_RED);
+        return 1;
+    };
+
+    shell_write_idx(
+        b"slot    id         class          driver\n",
+        COLOR_COMMENT_GRAY,
+    );
+    for dev in &devices[..count] {
+        let [_, ids, class, _] = dev.attrs;
+        write_column(dev.name_str().as_bytes(), 8);
+        write_hex_u16((ids >> 16) as u16);
+        shell_write(b":");
+        write_hex_u16(ids as u16);
+        shell_write(b"  ");
+        write_hex_byte((class >> 16) as u8);
+        write_hex_byte((class >> 8) as u8);
+        shell_write(b" ");
+        write_column(pci_class_name((class >> 16) as u8), 10);
+        shell_write(dev.driver_str().as_bytes());
+        shell_write(NL);
+    }
+    0
+}
+
+pub fn cmd_lsblk(argc: i32, _argv: &[*const u8]) -> i32 {
+    if argc != 1 {
+        shell_write(b"usage: lsblk\n");
+        return 1;
+    }
+    let mut devices = [HwDevice::default(); HW_MAX_DEVICES];
+    let Ok(count) = sys_core::hw_inventory(&mut devices, 0) else {
+        shell_write_idx(b"lsblk: failed to read inventory\n", COLOR_ERROR_RED);
+        return 1;
+    };
+    let devices = &devices[..count];
+
+    shell_write_idx(
+        b"name           size  block  driver      slot\n",
+        COLOR_COMMENT_GRAY,
+    );
+    let mut buf = NumBuf::<32>::new();
+    for dev in devices.iter().filter(|d| d.class == HW_CLASS_BLOCK) {
+        write_column(dev.name_str().as_bytes(), 9);
+        write_right_aligned(buf.format_bytes(dev.attrs[0], UnitBase::Binary), 10);
+        write_right_aligned(buf.format_u64(dev.attrs[1]), 7);
+        shell_write(b"  ");
+        write_column(dev.driver_str().as_bytes(), 12);
+        // HW_NO_PARENT is past the end of any inventory.
+        if let Some(parent) = devices.get(dev.parent as usize) {
+            shell_write(parent.name_str().as_bytes());
+        }
+        shell_write(NL);
+    }
+    0
+}
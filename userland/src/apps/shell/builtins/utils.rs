@@ -1,5 +1,7 @@
 //! Utility builtins: sleep, true, false, seq, yes, random, roulette, wl.
 
+use slopos_lib::numfmt::{self, NumBuf};
+
 use crate::runtime;
 use crate::syscall::{UserSysInfo, core as sys_core, roulette};
 
@@ -9,45 +11,9 @@ use super::super::jobs::{parse_u32_arg, write_u64};
 
 // ─── Helpers ────────────────────────────────────────────────────────────────
 
-fn format_i64(value: i64, buf: &mut [u8; 21]) -> usize {
-    if value == 0 {
-        buf[0] = b'0';
-        return 1;
-    }
-
-    let negative = value < 0;
-    // wrapping_neg handles i64::MIN without overflow (stays in u64 domain)
-    let mut n = if negative {
-        (value as u64).wrapping_neg()
-    } else {
-        value as u64
-    };
-
-    let mut rev = [0u8; 20];
-    let mut r = 0usize;
-    while n != 0 && r < rev.len() {
-        rev[r] = b'0' + (n % 10) as u8;
-        n /= 10;
-        r += 1;
-    }
-
-    let mut pos = 0usize;
-    if negative {
-        buf[pos] = b'-';
-        pos += 1;
-    }
-    while r > 0 {
-        buf[pos] = rev[r - 1];
-        pos += 1;
-        r -= 1;
-    }
-    pos
-}
-
 fn write_i64(value: i64) {
-    let mut buf = [0u8; 21];
-    let len = format_i64(value, &mut buf);
-    shell_write(&buf[..len]);
+    let mut buf = NumBuf::<21>::new();
+    shell_write(numfmt::trim_nul(buf.format_i64(value)));
 }
 
 fn parse_u64_arg(ptr: *const u8) -> Option<u64> {
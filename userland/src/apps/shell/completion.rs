@@ -5,6 +5,7 @@ use crate::syscall::{UserFsEntry, UserFsList, fs};
 
 use super::builtins::BUILTINS;
 use super::parser::is_space;
+use super::plugins::PluginNames;
 
 pub struct CompletionResult {
     pub insertion: [u8; 128],
@@ -64,6 +65,7 @@ pub fn try_complete(input: &[u8], len: usize, cursor_pos: usize, cwd: &[u8]) ->
 }
 
 fn complete_command(prefix: &[u8], prefix_len: usize, result: &mut CompletionResult) {
+    let plugins = PluginNames::scan();
     let mut matches: [&[u8]; 64] = [&[]; 64];
     let mut match_count = 0;
 
@@ -79,6 +81,12 @@ fn complete_command(prefix: &[u8], prefix_len: usize, result: &mut CompletionRes
         }
     }
 
+    for name in plugins.iter() {
+        if name.len() >= prefix_len && &name[..prefix_len] == prefix {
+            push_command_match(name, &mut matches, &mut match_count);
+        }
+    }
+
     if match_count == 0 {
         return;
     }
@@ -130,7 +138,7 @@ fn complete_command(prefix: &[u8], prefix_len: usize, result: &mut CompletionRes
     }
 }
 
-fn push_command_match(name: &'static [u8], matches: &mut [&'static [u8]; 64], count: &mut usize) {
+fn push_command_match<'a>(name: &'a [u8], matches: &mut [&'a [u8]; 64], count: &mut usize) {
     if *count >= matches.len() {
         return;
     }
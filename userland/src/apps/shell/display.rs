@@ -121,8 +121,35 @@ unsafe impl Sync for DisplayState {}
 
 pub static DISPLAY: DisplayState = DisplayState::new();
 static OUTPUT_FD: SyncUnsafeCell<i32> = SyncUnsafeCell::new(-1);
+/// Where text in [`COLOR_ERROR_RED`] goes while a builtin's stderr is
+/// redirected, -1 being the terminal.  `None` sends it with the rest.
+static ERROR_FD: SyncUnsafeCell<Option<i32>> = SyncUnsafeCell::new(None);
 static CURRENT_COLOR_IDX: SyncUnsafeCell<u8> = SyncUnsafeCell::new(0);
 
+/// The input line as last drawn on the serial TTY, so cursor blinks and
+/// pointer events that redraw the surface don't resend it.
+struct SerialLine {
+    text: [u8; 256],
+    len: usize,
+    cursor: usize,
+}
+
+static SERIAL_LINE: SyncUnsafeCell<SerialLine> = SyncUnsafeCell::new(SerialLine {
+    text: [0; 256],
+    len: 0,
+    cursor: 0,
+});
+
+/// Set by `shell --pty`: the shell has no window of its own, and its
+/// terminal is the pty on fd 1 rather than the console TTY.
+static ON_PTY: SyncUnsafeCell<bool> = SyncUnsafeCell::new(false);
+
+/// Set when the shell runs a script: output goes to fd 1 as under `--pty`,
+/// but without colour escapes, since fd 1 is as often a pipe or a file.
+static PLAIN_OUTPUT: SyncUnsafeCell<bool> = SyncUnsafeCell::new(false);
+
+const SGR_RESET: &[u8] = b"\x1B[0m";
+
 #[inline]
 fn current_color_idx() -> u8 {
     unsafe { *CURRENT_COLOR_IDX.get() }
@@ -143,6 +170,77 @@ fn palette_index_for(color: Color32) -> u8 {
     COLOR_DEFAULT
 }
 
+/// The ANSI escape closest to palette entry `color_idx`; `None` for the
+/// terminal's default colour.
+fn sgr_for(color_idx: u8) -> Option<&'static [u8]> {
+    Some(match color_idx {
+        COLOR_DIR_BLUE => b"\x1B[34m",
+        COLOR_EXEC_GREEN => b"\x1B[32m",
+        COLOR_ERROR_RED => b"\x1B[31m",
+        COLOR_WARN_YELLOW => b"\x1B[33m",
+        COLOR_PROMPT_ACCENT => b"\x1B[35m",
+        COLOR_COMMENT_GRAY => b"\x1B[90m",
+        COLOR_PATH_BLUE => b"\x1B[94m",
+        COLOR_SELECTION_BG => b"\x1B[7m",
+        _ => return None,
+    })
+}
+
+/// Run the shell on the pty it was started on instead of in a window.
+pub fn shell_set_on_pty() {
+    unsafe { *ON_PTY.get() = true }
+}
+
+pub fn shell_on_pty() -> bool {
+    unsafe { *ON_PTY.get() }
+}
+
+/// Write plain text to fd 1, for running a script without a window.
+pub fn shell_set_script_output() {
+    unsafe {
+        *ON_PTY.get() = true;
+        *PLAIN_OUTPUT.get() = true;
+    }
+}
+
+/// Write to the shell's terminal: the pty on fd 1 under `--pty`, the
+/// console TTY otherwise.
+pub fn term_write(buf: &[u8]) {
+    if shell_on_pty() {
+        let _ = fs::write_slice(1, buf);
+    } else {
+        let _ = crate::syscall::tty::write(buf);
+    }
+}
+
+/// Like [`term_write`], in palette colour `color_idx` when the terminal is
+/// a pty.  The console TTY only gets the text.
+pub fn term_write_idx(buf: &[u8], color_idx: u8) {
+    match sgr_for(color_idx) {
+        Some(sgr) if shell_on_pty() && unsafe { !*PLAIN_OUTPUT.get() } => {
+            term_write(sgr);
+            term_write(buf);
+            term_write(SGR_RESET);
+        }
+        _ => term_write(buf),
+    }
+}
+
+/// Call `f` with each run of `text` that shares one palette colour;
+/// `colors` holds one entry per byte and may be short.
+pub fn for_each_color_run(text: &[u8], colors: &[u8], mut f: impl FnMut(&[u8], u8)) {
+    let color_at = |i: usize| colors.get(i).copied().unwrap_or(COLOR_DEFAULT);
+    let mut i = 0;
+    while i < text.len() {
+        let color = color_at(i);
+        let start = i;
+        while i < text.len() && color_at(i) == color {
+            i += 1;
+        }
+        f(&text[start..i], color);
+    }
+}
+
 // =============================================================================
 // Scrollback module: safe accessors for large arrays
 // =============================================================================
@@ -299,7 +397,16 @@ pub mod scrollback {
 fn draw_char_at(buf: &mut DrawBuffer, col: i32, row: i32, c: u8, fg: Color32, bg: Color32) {
     let x = col * FONT_CHAR_WIDTH;
     let y = row * FONT_CHAR_HEIGHT;
-    gfx::font::draw_char(buf, x, y, c, fg, bg);
+    gfx::font::draw_cell(
+        buf,
+        x,
+        y,
+        FONT_CHAR_WIDTH as u32,
+        FONT_CHAR_HEIGHT as u32,
+        c,
+        fg,
+        bg,
+    );
 }
 
 fn clear_row(buf: &mut DrawBuffer, row: i32, width: i32, bg: Color32) {
@@ -747,6 +854,7 @@ pub fn shell_console_init() {
         DISPLAY.enabled.set(false);
         return;
     }
+    gfx::font::load_system_fonts();
 
     let mut info = DisplayInfo::default();
     let _ = window::fb_info(&mut info);
@@ -809,7 +917,7 @@ pub fn shell_write(buf: &[u8]) -> bool {
     if redirected_fd >= 0 {
         return fs::write_slice(redirected_fd, buf).is_ok();
     }
-    let _ = crate::syscall::tty::write(buf);
+    term_write(buf);
     shell_console_write(buf);
     shell_console_commit();
     true
@@ -818,15 +926,16 @@ pub fn shell_write(buf: &[u8]) -> bool {
 /// Write colored text to the current output destination.
 ///
 /// When output is redirected (pipe / file), color is stripped and only the raw
-/// text is written.  Otherwise the text goes to the serial TTY (uncolored) and
-/// the compositor surface (colored via the palette index matching `fg`).
+/// text is written.  Otherwise the text goes to the terminal (colored only on
+/// a pty) and the compositor surface (colored via the palette index matching
+/// `fg`).
 pub fn shell_write_colored(buf: &[u8], fg: Color32) -> bool {
     let redirected_fd = unsafe { *OUTPUT_FD.get() };
     if redirected_fd >= 0 {
         return fs::write_slice(redirected_fd, buf).is_ok();
     }
-    let _ = crate::syscall::tty::write(buf);
     let idx = palette_index_for(fg);
+    term_write_idx(buf, idx);
     shell_console_write_colored(buf, idx);
     shell_console_commit();
     true
@@ -835,13 +944,16 @@ pub fn shell_write_colored(buf: &[u8], fg: Color32) -> bool {
 /// Write text with a palette color index to the current output destination.
 ///
 /// Convenience wrapper that avoids a palette lookup when the caller already
-/// has an index.
+/// has an index.  Error text follows a builtin's redirected stderr.
 pub fn shell_write_idx(buf: &[u8], color_idx: u8) -> bool {
-    let redirected_fd = unsafe { *OUTPUT_FD.get() };
+    let redirected_fd = match unsafe { *ERROR_FD.get() } {
+        Some(fd) if color_idx == COLOR_ERROR_RED => fd,
+        _ => unsafe { *OUTPUT_FD.get() },
+    };
     if redirected_fd >= 0 {
         return fs::write_slice(redirected_fd, buf).is_ok();
     }
-    let _ = crate::syscall::tty::write(buf);
+    term_write_idx(buf, color_idx);
     shell_console_write_colored(buf, color_idx);
     shell_console_commit();
     true
@@ -859,9 +971,22 @@ pub fn shell_clear_output_fd() {
     }
 }
 
+/// Send builtins' error messages to `fd`, or to the terminal if it is -1.
+pub fn shell_set_error_fd(fd: i32) {
+    unsafe {
+        *ERROR_FD.get() = Some(fd);
+    }
+}
+
+pub fn shell_clear_error_fd() {
+    unsafe {
+        *ERROR_FD.get() = None;
+    }
+}
+
 pub fn shell_echo_char(c: u8) {
     let buf = [c];
-    let _ = crate::syscall::tty::write(&buf);
+    term_write(&buf);
     shell_console_write(&buf);
     shell_console_commit();
 }
@@ -922,4 +1047,43 @@ pub fn shell_redraw_input(
         );
         shell_console_commit();
     }
+    serial_rewrite_input(prompt, prompt_colors, input, cursor_pos);
+}
+
+/// Forget the serial line state; called once a fresh prompt is printed.
+pub fn shell_serial_line_reset() {
+    let line = unsafe { &mut *SERIAL_LINE.get() };
+    line.len = 0;
+    line.cursor = 0;
+}
+
+/// Mirror the edited line to the serial TTY, or the pty under `--pty`.  A
+/// serial terminal can't be redrawn in place, so reprint the line after a
+/// CR, blank out whatever the previous version left past its end, and back
+/// up to the cursor.
+fn serial_rewrite_input(prompt: &[u8], prompt_colors: &[u8], input: &[u8], cursor_pos: usize) {
+    const SPACES: [u8; 256] = [b' '; 256];
+    const BACKSPACES: [u8; 256] = [0x08; 256];
+
+    let line = unsafe { &mut *SERIAL_LINE.get() };
+    let input = &input[..input.len().min(line.text.len())];
+    let cursor_pos = cursor_pos.min(input.len());
+    if line.text[..line.len] == *input && line.cursor == cursor_pos {
+        return;
+    }
+    let stale = line.len.saturating_sub(input.len());
+    line.text[..input.len()].copy_from_slice(input);
+    line.len = input.len();
+    line.cursor = cursor_pos;
+
+    term_write(b"\r");
+    for_each_color_run(prompt, prompt_colors, term_write_idx);
+    term_write(input);
+    if stale > 0 {
+        term_write(&SPACES[..stale]);
+    }
+    let back = stale + input.len() - cursor_pos;
+    if back > 0 {
+        term_write(&BACKSPACES[..back]);
+    }
 }
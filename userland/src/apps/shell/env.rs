@@ -1,11 +1,18 @@
 //! Environment variable storage for the shell.
 
+use core::ptr;
+
+use slopos_abi::syscall::EXEC_MAX_ENVS;
+
 use super::SyncUnsafeCell;
 
 pub const MAX_ENV_ENTRIES: usize = 64;
 pub const ENV_KEY_MAX: usize = 64;
 pub const ENV_VALUE_MAX: usize = 256;
 
+/// Bytes of `KEY=VALUE\0` strings handed to a child program.
+const ENVP_BYTES: usize = 4096;
+
 #[derive(Clone, Copy)]
 struct EnvEntry {
     key: [u8; ENV_KEY_MAX],
@@ -41,6 +48,17 @@ impl Environment {
 
 static ENV: SyncUnsafeCell<Environment> = SyncUnsafeCell::new(Environment::new());
 
+/// The environment flattened for exec/spawn; rebuilt by [`envp`].
+struct EnvpBlock {
+    strings: [u8; ENVP_BYTES],
+    ptrs: [*const u8; EXEC_MAX_ENVS + 1],
+}
+
+static ENVP: SyncUnsafeCell<EnvpBlock> = SyncUnsafeCell::new(EnvpBlock {
+    strings: [0; ENVP_BYTES],
+    ptrs: [ptr::null(); EXEC_MAX_ENVS + 1],
+});
+
 fn with_env<R, F: FnOnce(&mut Environment) -> R>(f: F) -> R {
     f(unsafe { &mut *ENV.get() })
 }
@@ -132,6 +150,26 @@ pub fn initialize_defaults() {
     set(b"PS1", b"\\u@\\h:\\w\\$ ");
 }
 
+/// Set the `KEY=VALUE` entries of the null-terminated `envp` the shell
+/// was started with.
+pub fn import(envp: *const *const u8) {
+    if envp.is_null() {
+        return;
+    }
+    let mut index = 0;
+    loop {
+        let entry = unsafe { *envp.add(index) };
+        if entry.is_null() {
+            return;
+        }
+        let bytes = unsafe { core::slice::from_raw_parts(entry, crate::runtime::u_strlen(entry)) };
+        if let Some(eq) = bytes.iter().position(|&b| b == b'=') {
+            set(&bytes[..eq], &bytes[eq + 1..]);
+        }
+        index += 1;
+    }
+}
+
 pub fn for_each<F: FnMut(&[u8], &[u8])>(mut f: F) {
     with_env(|env| {
         for entry in &env.entries {
@@ -145,6 +183,31 @@ pub fn for_each<F: FnMut(&[u8], &[u8])>(mut f: F) {
     });
 }
 
+/// The current environment as a null-terminated `KEY=VALUE` array for
+/// `execve`/`spawnve`.  Valid until the next call; variables past the
+/// kernel's limit are left out.
+pub fn envp() -> *const *const u8 {
+    let block = unsafe { &mut *ENVP.get() };
+    let mut used = 0usize;
+    let mut count = 0usize;
+    for_each(|key, value| {
+        let len = key.len() + 1 + value.len() + 1;
+        if count == EXEC_MAX_ENVS || used + len > ENVP_BYTES {
+            return;
+        }
+        let dst = &mut block.strings[used..used + len];
+        dst[..key.len()].copy_from_slice(key);
+        dst[key.len()] = b'=';
+        dst[key.len() + 1..len - 1].copy_from_slice(value);
+        dst[len - 1] = 0;
+        block.ptrs[count] = block.strings[used..].as_ptr();
+        used += len;
+        count += 1;
+    });
+    block.ptrs[count] = ptr::null();
+    block.ptrs.as_ptr()
+}
+
 pub fn count() -> usize {
     with_env(|env| env.entries.iter().filter(|e| e.active).count())
 }
@@ -4,29 +4,47 @@ use core::ptr;
 use crate::program_registry;
 use crate::runtime;
 use crate::syscall::{
-    POLLHUP, POLLIN, USER_FS_OPEN_APPEND, USER_FS_OPEN_CREAT, USER_FS_OPEN_READ,
-    USER_FS_OPEN_WRITE, UserFsStat, UserPollFd, core as sys_core, fs, process,
+    O_CLOEXEC, POLLHUP, POLLIN, SyscallError, USER_FS_OPEN_APPEND, USER_FS_OPEN_CREAT,
+    USER_FS_OPEN_READ, USER_FS_OPEN_WRITE, UserFsStat, UserPollFd, core as sys_core, fs, process,
 };
 
 use super::SyncUnsafeCell;
 use super::builtins;
-use super::display::{shell_clear_output_fd, shell_set_output_fd, shell_write};
+use super::display::{
+    COLOR_ERROR_RED, shell_clear_error_fd, shell_clear_output_fd, shell_on_pty, shell_set_error_fd,
+    shell_set_output_fd, shell_write, shell_write_idx,
+};
+use super::env;
 use super::jobs;
-use super::parser::{SHELL_MAX_TOKENS, normalize_path, u_streq_slice};
+use super::parser::{SHELL_MAX_TOKENS, is_operator_token, normalize_path, u_streq_slice};
+use super::plugins;
 
 const MAX_PIPE_CMDS: usize = 8;
-const MAX_REDIRECTS: usize = 4;
+const MAX_REDIRECTS: usize = 8;
+/// Runs files that are neither ELF images nor `#!` scripts.
+const SHELL_PATH: &[u8] = b"/bin/shell\0";
+/// The target of the `2>&1` half of `&>`.
+const STDOUT_TARGET: &[u8] = b"1\0";
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum RedirectKind {
+    /// `[n]< file`
     Input,
+    /// `[n]> file`
     OutputTruncate,
+    /// `[n]>> file`
     OutputAppend,
+    /// `[n]>&m` or `[n]<&m`: make fd n a copy of fd m.
+    Duplicate,
+    /// `<<< word`: the word and a newline on stdin.
+    HereString,
 }
 
 #[derive(Clone, Copy)]
 struct Redirect {
     kind: RedirectKind,
+    /// The fd redirected.
+    fd: i32,
     target: *const u8,
 }
 
@@ -34,6 +52,7 @@ impl Redirect {
     const fn empty() -> Self {
         Self {
             kind: RedirectKind::Input,
+            fd: 0,
             target: ptr::null(),
         }
     }
@@ -118,6 +137,17 @@ pub fn initialize_job_control() {
     }
 }
 
+/// For a script run as `shell FILE`: its commands hand the terminal back
+/// to the script's process group, which it does not take for itself.
+pub fn initialize_script_job_control() {
+    let pgid = process::getpgid(0);
+    if pgid > 0 {
+        unsafe {
+            *SHELL_PGID.get() = pgid as u32;
+        }
+    }
+}
+
 fn shell_pgid() -> u32 {
     unsafe { *SHELL_PGID.get() }
 }
@@ -138,14 +168,60 @@ pub fn leave_foreground() {
     clear_foreground_pgid();
 }
 
-fn token_is(token: *const u8, text: &[u8]) -> bool {
-    u_streq_slice(token, text)
+fn token_bytes<'a>(token: *const u8) -> &'a [u8] {
+    unsafe { core::slice::from_raw_parts(token, runtime::u_strlen(token)) }
+}
+
+/// The redirection operator `token` as its kind and the fd it redirects.
+/// `&>` and `&>>` are left to the caller.
+fn redirect_op(token: &[u8]) -> Option<(RedirectKind, i32)> {
+    let digits = token.iter().take_while(|b| b.is_ascii_digit()).count();
+    let (kind, default_fd) = match &token[digits..] {
+        b"<" => (RedirectKind::Input, 0),
+        b">" => (RedirectKind::OutputTruncate, 1),
+        b">>" => (RedirectKind::OutputAppend, 1),
+        b"<&" => (RedirectKind::Duplicate, 0),
+        b">&" => (RedirectKind::Duplicate, 1),
+        b"<<<" if digits == 0 => (RedirectKind::HereString, 0),
+        _ => return None,
+    };
+    let fd = if digits == 0 {
+        default_fd
+    } else {
+        parse_fd(&token[..digits])?
+    };
+    Some((kind, fd))
+}
+
+fn parse_fd(digits: &[u8]) -> Option<i32> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0i32, |fd, &b| {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        fd.checked_mul(10)?.checked_add((b - b'0') as i32)
+    })
+}
+
+fn add_redirect(cmd: &mut ParsedCommand, redirect: Redirect) -> Result<(), &'static [u8]> {
+    if cmd.redirect_count >= MAX_REDIRECTS {
+        return Err(b"too many redirections");
+    }
+    cmd.redirects[cmd.redirect_count] = redirect;
+    cmd.redirect_count += 1;
+    Ok(())
 }
 
-fn parse_pipeline(argc: i32, argv: &[*const u8], out: &mut ParsedPipeline) -> Result<(), ()> {
+fn parse_pipeline(
+    argc: i32,
+    argv: &[*const u8],
+    out: &mut ParsedPipeline,
+) -> Result<(), &'static [u8]> {
     *out = ParsedPipeline::empty();
     if argc <= 0 {
-        return Err(());
+        return Err(b"empty command");
     }
 
     let mut cmd_idx = 0usize;
@@ -156,58 +232,59 @@ fn parse_pipeline(argc: i32, argv: &[*const u8], out: &mut ParsedPipeline) -> Re
         if token.is_null() {
             break;
         }
+        let text = token_bytes(token);
 
-        if token_is(token, b"&") {
+        if text == b"&" {
             if token_idx + 1 != argc as usize {
-                return Err(());
+                return Err(b"'&' must end the command");
             }
             out.background = true;
             token_idx += 1;
             continue;
         }
 
-        if token_is(token, b"|") {
+        if text == b"|" {
             if out.commands[cmd_idx].argc == 0 {
-                return Err(());
+                return Err(b"missing command before '|'");
             }
             cmd_idx += 1;
             if cmd_idx >= MAX_PIPE_CMDS {
-                return Err(());
+                return Err(b"too many commands in pipeline");
             }
             token_idx += 1;
             continue;
         }
 
-        let mut redirect_kind = None;
-        if token_is(token, b">") {
-            redirect_kind = Some(RedirectKind::OutputTruncate);
-        } else if token_is(token, b">>") {
-            redirect_kind = Some(RedirectKind::OutputAppend);
-        } else if token_is(token, b"<") {
-            redirect_kind = Some(RedirectKind::Input);
-        }
-
-        if let Some(kind) = redirect_kind {
-            if token_idx + 1 >= argc as usize || token_idx + 1 >= argv.len() {
-                return Err(());
-            }
-            if out.commands[cmd_idx].redirect_count >= MAX_REDIRECTS {
-                return Err(());
-            }
-            let target = argv[token_idx + 1];
-            if target.is_null() {
-                return Err(());
+        let both = match text {
+            b"&>" => Some(RedirectKind::OutputTruncate),
+            b"&>>" => Some(RedirectKind::OutputAppend),
+            _ => None,
+        };
+        let redirect = both.map(|kind| (kind, 1)).or_else(|| redirect_op(text));
+        if let Some((kind, fd)) = redirect {
+            let target = argv
+                .get(token_idx + 1)
+                .copied()
+                .filter(|&t| token_idx + 1 < argc as usize && !t.is_null())
+                .filter(|&t| !is_operator_token(token_bytes(t)))
+                .ok_or(&b"missing target after redirection"[..])?;
+            let cmd = &mut out.commands[cmd_idx];
+            add_redirect(cmd, Redirect { kind, fd, target })?;
+            if both.is_some() {
+                let stderr = Redirect {
+                    kind: RedirectKind::Duplicate,
+                    fd: 2,
+                    target: STDOUT_TARGET.as_ptr(),
+                };
+                add_redirect(cmd, stderr)?;
             }
-            let redir_idx = out.commands[cmd_idx].redirect_count;
-            out.commands[cmd_idx].redirects[redir_idx] = Redirect { kind, target };
-            out.commands[cmd_idx].redirect_count += 1;
             token_idx += 2;
             continue;
         }
 
         let cmd = &mut out.commands[cmd_idx];
         if cmd.argc >= SHELL_MAX_TOKENS - 1 {
-            return Err(());
+            return Err(b"too many arguments");
         }
         cmd.argv[cmd.argc] = token;
         cmd.argc += 1;
@@ -215,7 +292,11 @@ fn parse_pipeline(argc: i32, argv: &[*const u8], out: &mut ParsedPipeline) -> Re
     }
 
     if out.commands[cmd_idx].argc == 0 {
-        return Err(());
+        return Err(if cmd_idx > 0 {
+            b"missing command after '|'"
+        } else {
+            b"missing command"
+        });
     }
 
     out.command_count = cmd_idx + 1;
@@ -292,6 +373,10 @@ fn resolve_exec_path(command: *const u8, tmp: &mut [u8; 256]) -> Option<*const u
         return Some(tmp.as_ptr());
     }
 
+    if let Some(path) = plugins::resolve_plugin(name, tmp) {
+        return Some(path);
+    }
+
     resolve_via_path(name, tmp)
 }
 
@@ -342,6 +427,16 @@ fn command_name_bytes<'a>(cmd: &ParsedCommand) -> Option<&'a [u8]> {
     Some(unsafe { core::slice::from_raw_parts(cmd.argv[0], len) })
 }
 
+/// `cmd`'s arguments as the null-terminated argv every program receives,
+/// whether spawned or exec'd.
+fn exec_argv(cmd: &ParsedCommand) -> [*const u8; SHELL_MAX_TOKENS + 1] {
+    let mut argv = [ptr::null(); SHELL_MAX_TOKENS + 1];
+    for (slot, arg) in argv.iter_mut().zip(cmd.argv.iter().take(cmd.argc)) {
+        *slot = *arg;
+    }
+    argv
+}
+
 fn registry_spec_for_command(
     cmd: &ParsedCommand,
 ) -> Option<&'static program_registry::ProgramSpec> {
@@ -387,16 +482,18 @@ fn execute_registry_spawn(cmd: &ParsedCommand, background: bool) -> Option<i32>
     // For text programs, redirect our fd 1 to a pipe before spawning so
     // the child (which inherits our fd table) writes into the pipe.
     // After spawning, restore fd 1 and drain the pipe into shell_write.
-    let capture = !spec.gui && !background;
+    // The read end and the saved stdout are close-on-exec, so the child
+    // only inherits fd 1.  On a pty the child writes to it directly.
+    let capture = !spec.gui && !background && !shell_on_pty();
     let mut pipe_fds = [-1i32; 2];
     let mut backup_fd = -1i32;
 
     if capture {
-        if fs::pipe(&mut pipe_fds).is_err() {
+        if fs::pipe2(&mut pipe_fds, O_CLOEXEC as u32).is_err() {
             shell_write(b"pipe failed\n");
             return Some(1);
         }
-        backup_fd = match fs::dup(1) {
+        backup_fd = match fs::dup_cloexec(1) {
             Ok(fd) => fd,
             Err(_) => {
                 let _ = fs::close_fd(pipe_fds[0]);
@@ -416,8 +513,17 @@ fn execute_registry_spawn(cmd: &ParsedCommand, background: bool) -> Option<i32>
         let _ = fs::close_fd(pipe_fds[1]);
     }
 
-    let tid =
-        process::spawn_path_with_argv(spec.path, &cmd.argv[..cmd.argc], spec.priority, spec.flags);
+    let mut path = [0u8; 256];
+    let path_len = spec.path.len().min(path.len() - 1);
+    path[..path_len].copy_from_slice(&spec.path[..path_len]);
+    let argv = exec_argv(cmd);
+    let tid = process::spawnve(
+        path.as_ptr(),
+        argv.as_ptr(),
+        env::envp(),
+        spec.priority,
+        spec.flags,
+    );
 
     // Restore fd 1 immediately after spawn so shell output is normal again.
     if capture {
@@ -508,99 +614,238 @@ fn execute_registry_spawn(cmd: &ParsedCommand, background: bool) -> Option<i32>
     Some(status)
 }
 
-fn open_redirect_target(redir: Redirect, path_buf: &mut [u8; 256]) -> Result<(i32, i32), ()> {
+/// Open what `redir` reads or writes: a file, or a pipe holding a
+/// here-string.  The caller moves the fd into place and closes it.
+fn open_redirect_target(redir: Redirect, path_buf: &mut [u8; 256]) -> Result<i32, &'static [u8]> {
+    let flags = match redir.kind {
+        RedirectKind::Input => USER_FS_OPEN_READ,
+        RedirectKind::OutputTruncate => USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT,
+        RedirectKind::OutputAppend => USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT | USER_FS_OPEN_APPEND,
+        RedirectKind::HereString => return here_string(token_bytes(redir.target)),
+        RedirectKind::Duplicate => return Err(b"bad redirection"),
+    };
     if normalize_path(redir.target, path_buf) != 0 {
-        return Err(());
+        return Err(b"path too long");
     }
+    let path = path_buf.as_ptr() as *const c_char;
 
-    match redir.kind {
-        RedirectKind::Input => {
-            let fd = fs::open_path(path_buf.as_ptr() as *const c_char, USER_FS_OPEN_READ)
-                .map_err(|_| ())?;
-            Ok((0, fd))
+    let mut stat = UserFsStat::default();
+    let exists = fs::stat_path(path, &mut stat).is_ok();
+    if exists && stat.is_directory() {
+        return Err(b"Is a directory");
+    }
+    if !exists && redir.kind == RedirectKind::Input {
+        return Err(b"No such file or directory");
+    }
+    // Opening for writing keeps the old contents: truncate by replacing.
+    if exists && redir.kind == RedirectKind::OutputTruncate {
+        let _ = fs::unlink_path(path);
+    }
+    fs::open_path(path, flags).map_err(|_| {
+        if redir.kind == RedirectKind::Input {
+            &b"cannot open"[..]
+        } else {
+            b"cannot create"
         }
-        RedirectKind::OutputTruncate => {
-            let _ = fs::unlink_path(path_buf.as_ptr() as *const c_char);
-            let fd = fs::open_path(
-                path_buf.as_ptr() as *const c_char,
-                USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT,
-            )
-            .map_err(|_| ())?;
-            Ok((1, fd))
+    })
+}
+
+/// A pipe holding `word` and a newline, its write end closed, for `<<<`.
+/// A token always fits in the pipe's buffer.
+fn here_string(word: &[u8]) -> Result<i32, &'static [u8]> {
+    let mut fds = [-1i32; 2];
+    if fs::pipe2(&mut fds, O_CLOEXEC as u32).is_err() {
+        return Err(b"cannot create pipe");
+    }
+    let written = fs::write_slice(fds[1], word).is_ok() && fs::write_slice(fds[1], b"\n").is_ok();
+    let _ = fs::close_fd(fds[1]);
+    if !written {
+        let _ = fs::close_fd(fds[0]);
+        return Err(b"cannot write here-string");
+    }
+    Ok(fds[0])
+}
+
+/// The fd a `>&m` or `<&m` redirection copies.
+fn duplicate_source(redir: Redirect) -> Result<i32, &'static [u8]> {
+    parse_fd(token_bytes(redir.target)).ok_or(b"bad file descriptor")
+}
+
+/// Make `to` a copy of `from`, then close `from` if `close` is set.
+fn move_fd(from: i32, to: i32, close: bool) -> Result<(), &'static [u8]> {
+    if from == to {
+        return Ok(());
+    }
+    let moved = fs::dup2(from, to)
+        .map(|_| ())
+        .map_err(|_| &b"bad file descriptor"[..]);
+    if close {
+        let _ = fs::close_fd(from);
+    }
+    moved
+}
+
+fn redirects_fd(cmd: &ParsedCommand, fd: i32) -> bool {
+    cmd.redirects[..cmd.redirect_count]
+        .iter()
+        .any(|redir| redir.fd == fd)
+}
+
+/// Report a redirection that could not be set up as `TARGET: REASON`.
+fn report_redirect_error(redir: Redirect, reason: &[u8], write: impl Fn(&[u8])) {
+    write(token_bytes(redir.target));
+    write(b": ");
+    write(reason);
+    write(b"\n");
+}
+
+/// In a forked child: put `cmd`'s redirections in place on its fds,
+/// exiting with status 1 if one cannot be.
+fn apply_redirects_in_child(cmd: &ParsedCommand) {
+    let mut path_buf = [0u8; 256];
+    for redir in &cmd.redirects[..cmd.redirect_count] {
+        let applied = match redir.kind {
+            RedirectKind::Duplicate => {
+                duplicate_source(*redir).and_then(|src| move_fd(src, redir.fd, false))
+            }
+            _ => open_redirect_target(*redir, &mut path_buf)
+                .and_then(|fd| move_fd(fd, redir.fd, true)),
+        };
+        if let Err(reason) = applied {
+            report_redirect_error(*redir, reason, |text| {
+                let _ = fs::write_slice(2, text);
+            });
+            sys_core::exit_with_code(1);
+        }
+    }
+}
+
+/// Where a builtin run in the shell itself writes once its redirections
+/// are set up.
+struct BuiltinFds {
+    /// Stdout and stderr, -1 while they are the terminal.
+    out: [i32; 2],
+    /// Files opened for the builtin, closed once it is done.
+    opened: [i32; MAX_REDIRECTS],
+    opened_count: usize,
+}
+
+impl BuiltinFds {
+    const fn new() -> Self {
+        Self {
+            out: [-1; 2],
+            opened: [-1; MAX_REDIRECTS],
+            opened_count: 0,
         }
-        RedirectKind::OutputAppend => {
-            let fd = fs::open_path(
-                path_buf.as_ptr() as *const c_char,
-                USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT | USER_FS_OPEN_APPEND,
-            )
-            .map_err(|_| ())?;
-            Ok((1, fd))
+    }
+
+    fn close(&mut self) {
+        for &fd in &self.opened[..self.opened_count] {
+            let _ = fs::close_fd(fd);
         }
+        self.opened_count = 0;
     }
 }
 
+/// Set up `cmd`'s redirections for a builtin run in the shell itself.
+/// Stdout and stderr go to `fds` for the builtin to write to; any other
+/// fd is swapped on the shell, with the original kept in `saved`.
 fn apply_redirects_for_builtin(
     cmd: &ParsedCommand,
     saved: &mut [SavedFd; MAX_REDIRECTS],
-    output_fd: &mut i32,
-) -> bool {
+    fds: &mut BuiltinFds,
+) -> Result<(), (Redirect, &'static [u8])> {
     let mut path_buf = [0u8; 256];
-    let mut save_count = 0usize;
 
-    for redir in &cmd.redirects[..cmd.redirect_count] {
-        let Ok((target_fd, opened_fd)) = open_redirect_target(*redir, &mut path_buf) else {
-            shell_write(b"redirection failed\n");
-            return false;
+    for (index, redir) in cmd.redirects[..cmd.redirect_count].iter().enumerate() {
+        let to_output = matches!(redir.fd, 1 | 2);
+        let fd = match redir.kind {
+            RedirectKind::Duplicate => match duplicate_source(*redir) {
+                // Stdout or stderr as the builtin has it, unless another
+                // fd is to be the terminal: that is the shell's own.
+                Ok(src @ (1 | 2)) if to_output || fds.out[src as usize - 1] >= 0 => {
+                    fds.out[src as usize - 1]
+                }
+                Ok(src) => src,
+                Err(reason) => return Err((*redir, reason)),
+            },
+            _ => match open_redirect_target(*redir, &mut path_buf) {
+                Ok(fd) => {
+                    fds.opened[fds.opened_count] = fd;
+                    fds.opened_count += 1;
+                    fd
+                }
+                Err(reason) => return Err((*redir, reason)),
+            },
         };
 
-        if target_fd == 1 {
-            if *output_fd >= 0 {
-                let _ = fs::close_fd(*output_fd);
-            }
-            *output_fd = opened_fd;
+        if to_output {
+            fds.out[redir.fd as usize - 1] = fd;
             continue;
         }
 
-        let backup = match fs::dup(target_fd) {
-            Ok(fd) => fd,
-            Err(_) => {
-                let _ = fs::close_fd(opened_fd);
-                shell_write(b"redirection failed\n");
-                return false;
+        // -1 when the fd was not open: closed again afterwards.
+        let backup = fs::dup_cloexec(redir.fd).unwrap_or(-1);
+        if move_fd(fd, redir.fd, false).is_err() {
+            if backup >= 0 {
+                let _ = fs::close_fd(backup);
             }
-        };
-
-        if fs::dup2(opened_fd, target_fd).is_err() {
-            let _ = fs::close_fd(opened_fd);
-            let _ = fs::close_fd(backup);
-            shell_write(b"redirection failed\n");
-            return false;
-        }
-        let _ = fs::close_fd(opened_fd);
-
-        if save_count < saved.len() {
-            saved[save_count] = SavedFd {
-                fd: target_fd,
-                backup,
-            };
-            save_count += 1;
+            return Err((*redir, b"bad file descriptor"));
         }
+        saved[index] = SavedFd {
+            fd: redir.fd,
+            backup,
+        };
     }
 
-    true
+    Ok(())
 }
 
+/// Undo [`apply_redirects_for_builtin`], latest first, since one fd may
+/// have been redirected more than once.
 fn restore_redirects(saved: &mut [SavedFd; MAX_REDIRECTS]) {
-    for slot in saved {
-        if slot.fd < 0 || slot.backup < 0 {
+    for slot in saved.iter_mut().rev() {
+        if slot.fd < 0 {
             continue;
         }
-        let _ = fs::dup2(slot.backup, slot.fd);
-        let _ = fs::close_fd(slot.backup);
+        if slot.backup >= 0 {
+            let _ = fs::dup2(slot.backup, slot.fd);
+            let _ = fs::close_fd(slot.backup);
+        } else {
+            let _ = fs::close_fd(slot.fd);
+        }
         *slot = SavedFd::empty();
     }
 }
 
+/// Run the builtin `entry` for `cmd`, writing to `stdout_fd` (-1 for the
+/// terminal) and, if set, its errors to `stderr_fd`.
+fn run_builtin(
+    entry: &builtins::BuiltinEntry,
+    cmd: &ParsedCommand,
+    stdout_fd: i32,
+    stderr_fd: Option<i32>,
+) -> i32 {
+    if stdout_fd >= 0 {
+        shell_set_output_fd(stdout_fd);
+    }
+    if let Some(fd) = stderr_fd {
+        shell_set_error_fd(fd);
+    }
+    let mut args = [ptr::null(); SHELL_MAX_TOKENS];
+    for (i, slot) in args.iter_mut().enumerate().take(cmd.argc) {
+        *slot = cmd.argv[i];
+    }
+    let code = (entry.func)(cmd.argc as i32, &args);
+    if stderr_fd.is_some() {
+        shell_clear_error_fd();
+    }
+    if stdout_fd >= 0 {
+        shell_clear_output_fd();
+    }
+    code
+}
+
 fn command_text(pipeline: &ParsedPipeline, out: &mut [u8; 128]) -> usize {
     let mut pos = 0usize;
     for ci in 0..pipeline.command_count {
@@ -672,67 +917,28 @@ fn run_in_child(
         let _ = fs::close_fd(pipe[1]);
     }
 
-    if let Some(entry) = builtins::find_builtin(cmd.argv[0]) {
-        let mut builtin_output_fd = 1;
-        let mut path_buf = [0u8; 256];
-        for redir in &cmd.redirects[..cmd.redirect_count] {
-            let Ok((target_fd, opened_fd)) = open_redirect_target(*redir, &mut path_buf) else {
-                let _ = crate::syscall::tty::write(b"redirection failed\n");
-                sys_core::exit_with_code(1);
-            };
-            if target_fd == 1 {
-                if builtin_output_fd != 1 {
-                    let _ = fs::close_fd(builtin_output_fd);
-                }
-                builtin_output_fd = opened_fd;
-                continue;
-            }
-            if fs::dup2(opened_fd, target_fd).is_err() {
-                let _ = crate::syscall::tty::write(b"redirection failed\n");
-                let _ = fs::close_fd(opened_fd);
-                sys_core::exit_with_code(1);
-            }
-            let _ = fs::close_fd(opened_fd);
-        }
+    apply_redirects_in_child(cmd);
 
-        shell_set_output_fd(builtin_output_fd);
-        let mut args = [ptr::null(); SHELL_MAX_TOKENS];
-        for (i, slot) in args.iter_mut().enumerate().take(cmd.argc) {
-            *slot = cmd.argv[i];
-        }
-        let code = (entry.func)(cmd.argc as i32, &args);
-        shell_clear_output_fd();
-        if builtin_output_fd != 1 {
-            let _ = fs::close_fd(builtin_output_fd);
-        }
-        sys_core::exit_with_code(code);
+    if let Some(entry) = builtins::find_builtin(cmd.argv[0]) {
+        let stderr_fd = redirects_fd(cmd, 2).then_some(2);
+        sys_core::exit_with_code(run_builtin(entry, cmd, 1, stderr_fd));
     }
 
     let mut path_buf = [0u8; 256];
-    for redir in &cmd.redirects[..cmd.redirect_count] {
-        let Ok((target_fd, opened_fd)) = open_redirect_target(*redir, &mut path_buf) else {
-            let _ = crate::syscall::tty::write(b"redirection failed\n");
-            sys_core::exit_with_code(1);
-        };
-        if fs::dup2(opened_fd, target_fd).is_err() {
-            let _ = crate::syscall::tty::write(b"redirection failed\n");
-            let _ = fs::close_fd(opened_fd);
-            sys_core::exit_with_code(1);
-        }
-        let _ = fs::close_fd(opened_fd);
-    }
-
     let Some(path_ptr) = resolve_exec_path(cmd.argv[0], &mut path_buf) else {
         sys_core::exit_with_code(127);
     };
 
-    let mut argv = [ptr::null(); SHELL_MAX_TOKENS + 1];
-    for (idx, arg) in cmd.argv.iter().take(cmd.argc).enumerate() {
-        argv[idx] = *arg;
+    let argv = exec_argv(cmd);
+    let mut rc = process::execve(path_ptr, argv.as_ptr(), env::envp());
+    if rc == -(SyscallError::ENOEXEC.errno() as i64) {
+        // Neither an ELF nor a `#!` script: run it as a shell script.
+        let mut script_argv = [ptr::null(); SHELL_MAX_TOKENS + 2];
+        script_argv[0] = SHELL_PATH.as_ptr();
+        script_argv[1] = path_ptr;
+        script_argv[2..cmd.argc + 1].copy_from_slice(&cmd.argv[1..cmd.argc]);
+        rc = process::execve(SHELL_PATH.as_ptr(), script_argv.as_ptr(), env::envp());
     }
-    argv[cmd.argc] = ptr::null();
-
-    let rc = process::execve(path_ptr, argv.as_ptr(), ptr::null());
     if rc < 0 {
         let _ = crate::syscall::tty::write(b"exec failed\n");
     }
@@ -741,47 +947,41 @@ fn run_in_child(
 
 fn execute_single_builtin(cmd: &ParsedCommand) -> i32 {
     let mut saved = [SavedFd::empty(); MAX_REDIRECTS];
-    let mut output_fd = -1;
-    if !apply_redirects_for_builtin(cmd, &mut saved, &mut output_fd) {
-        restore_redirects(&mut saved);
-        if output_fd >= 0 {
-            let _ = fs::close_fd(output_fd);
+    let mut fds = BuiltinFds::new();
+    let code = match apply_redirects_for_builtin(cmd, &mut saved, &mut fds) {
+        Err((redir, reason)) => {
+            report_redirect_error(redir, reason, |text| {
+                shell_write_idx(text, COLOR_ERROR_RED);
+            });
+            1
         }
-        return 1;
-    }
-
-    let code = if let Some(entry) = builtins::find_builtin(cmd.argv[0]) {
-        if output_fd >= 0 {
-            shell_set_output_fd(output_fd);
-        }
-        let mut args = [ptr::null(); SHELL_MAX_TOKENS];
-        for (i, slot) in args.iter_mut().enumerate().take(cmd.argc) {
-            *slot = cmd.argv[i];
-        }
-        let rc = (entry.func)(cmd.argc as i32, &args);
-        if output_fd >= 0 {
-            shell_clear_output_fd();
-        }
-        rc
-    } else {
-        1
+        Ok(()) => match builtins::find_builtin(cmd.argv[0]) {
+            // With stdout redirected, errors stay on the terminal unless
+            // stderr is redirected too.
+            Some(entry) => {
+                let stderr_fd =
+                    (redirects_fd(cmd, 1) || redirects_fd(cmd, 2)).then_some(fds.out[1]);
+                run_builtin(entry, cmd, fds.out[0], stderr_fd)
+            }
+            None => 1,
+        },
     };
 
     restore_redirects(&mut saved);
-    if output_fd >= 0 {
-        let _ = fs::close_fd(output_fd);
-    }
+    fds.close();
     code
 }
 
 fn execute_pipeline(pipeline: &ParsedPipeline) -> i32 {
     let inter_pipes = pipeline.command_count.saturating_sub(1);
-    let capture_output = !pipeline.background;
+    let capture_output = !pipeline.background && !shell_on_pty();
 
     let total_pipes = inter_pipes + if capture_output { 1 } else { 0 };
     let mut pipes = [[-1; 2]; MAX_PIPE_CMDS];
+    // Close-on-exec: each child dup2()s its own ends onto 0/1 (which
+    // clears the flag), and exec drops the rest of the pipeline's fds.
     for pair in pipes.iter_mut().take(total_pipes) {
-        if fs::pipe(pair).is_err() {
+        if fs::pipe2(pair, O_CLOEXEC as u32).is_err() {
             shell_write(b"pipe failed\n");
             for p in pipes.iter().take(total_pipes) {
                 if p[0] >= 0 {
@@ -888,8 +1088,10 @@ fn execute_pipeline(pipeline: &ParsedPipeline) -> i32 {
 
 pub fn execute_tokens(argc: i32, argv: &[*const u8]) -> i32 {
     let mut pipeline = ParsedPipeline::empty();
-    if parse_pipeline(argc, argv, &mut pipeline).is_err() {
-        shell_write(b"syntax error\n");
+    if let Err(reason) = parse_pipeline(argc, argv, &mut pipeline) {
+        shell_write_idx(b"syntax error: ", COLOR_ERROR_RED);
+        shell_write_idx(reason, COLOR_ERROR_RED);
+        shell_write_idx(b"\n", COLOR_ERROR_RED);
         return 1;
     }
 
@@ -0,0 +1,301 @@
+//! Wildcard expansion: `*`, `?` and `[...]` matched against directory
+//! listings one path component at a time.
+
+use core::ffi::c_char;
+
+use crate::syscall::{USER_FS_MAX_ENTRIES, UserFsEntry, UserFsList, UserFsStat, fs};
+
+use super::parser::normalize_path;
+
+/// Longest path a pattern can match.
+pub const GLOB_PATH_MAX: usize = 256;
+/// Entries read from each directory a wildcard component is matched in,
+/// the most one `list_dir` call returns.  The listing cannot be paged, so a
+/// directory that fills it is an error rather than a silently short match.
+const GLOB_MAX_ENTRIES: usize = USER_FS_MAX_ENTRIES as usize;
+
+pub type Emit<'a> = dyn FnMut(&[u8]) -> Result<(), &'static [u8]> + 'a;
+
+/// Whether `pattern` has a wildcard that is not `literal` (quoted).
+pub fn has_wildcard(pattern: &[u8], literal: &[bool]) -> bool {
+    pattern
+        .iter()
+        .zip(literal)
+        .any(|(&b, &lit)| !lit && matches!(b, b'*' | b'?' | b'['))
+}
+
+/// Match `c` against the bracket expression after the `[` at
+/// `pattern[start - 1]`: `[abc]`, `[a-z]`, `[!x]` or `[^x]`.  `Some` with
+/// the result and the index past `]`, `None` if the `[` is not closed.
+fn class(pattern: &[u8], start: usize, c: u8) -> Option<(bool, usize)> {
+    let mut i = start;
+    let negate = matches!(pattern.get(i), Some(b'!' | b'^'));
+    if negate {
+        i += 1;
+    }
+    let first = i;
+    let mut hit = false;
+    while i < pattern.len() {
+        // A `]` right after the `[` is part of the set.
+        if pattern[i] == b']' && i > first {
+            return Some((hit != negate, i + 1));
+        }
+        if i + 2 < pattern.len() && pattern[i + 1] == b'-' && pattern[i + 2] != b']' {
+            hit |= pattern[i] <= c && c <= pattern[i + 2];
+            i += 3;
+        } else {
+            hit |= pattern[i] == c;
+            i += 1;
+        }
+    }
+    None
+}
+
+/// The pattern index after matching `c` at `pattern[p]`, which is not `*`.
+fn step(pattern: &[u8], literal: &[bool], p: usize, c: u8) -> Option<usize> {
+    match pattern[p] {
+        b'?' if !literal[p] => Some(p + 1),
+        b'[' if !literal[p] => match class(pattern, p + 1, c) {
+            Some((hit, end)) => hit.then_some(end),
+            // An unclosed `[` only matches itself.
+            None => (c == b'[').then_some(p + 1),
+        },
+        b => (b == c).then_some(p + 1),
+    }
+}
+
+/// Whether `name` matches `pattern`.  Bytes marked `literal` only match
+/// themselves.
+pub fn matches(pattern: &[u8], literal: &[bool], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // Where to resume after the last `*`, and the name byte it has eaten up
+    // to, so a failed match can retry with the star taking one more byte.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == b'*' && !literal[p] {
+            p += 1;
+            star = Some((p, n));
+            continue;
+        }
+        if p < pattern.len()
+            && let Some(next) = step(pattern, literal, p, name[n])
+        {
+            p = next;
+            n += 1;
+            continue;
+        }
+        let Some((resume, eaten)) = star else {
+            return false;
+        };
+        p = resume;
+        n = eaten + 1;
+        star = Some((resume, n));
+    }
+    (p..pattern.len()).all(|i| pattern[i] == b'*' && !literal[i])
+}
+
+fn entry_name(entry: &UserFsEntry) -> &[u8] {
+    let len = entry
+        .name
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(entry.name.len());
+    &entry.name[..len]
+}
+
+struct Walk<'a, 'e> {
+    pattern: &'a [u8],
+    literal: &'a [bool],
+    path: [u8; GLOB_PATH_MAX],
+    emit: &'a mut Emit<'e>,
+    found: usize,
+}
+
+impl Walk<'_, '_> {
+    /// Append `bytes`, and a `/` if `slash`, to the first `len` bytes of the
+    /// path; the new length, `None` if it does not fit.
+    fn push(&mut self, len: usize, bytes: &[u8], slash: bool) -> Option<usize> {
+        let end = len + bytes.len() + slash as usize;
+        if end >= GLOB_PATH_MAX {
+            return None;
+        }
+        self.path[len..len + bytes.len()].copy_from_slice(bytes);
+        if slash {
+            self.path[end - 1] = b'/';
+        }
+        Some(end)
+    }
+
+    /// The first `len` bytes of the path, resolved against the cwd and
+    /// NUL-terminated; `.` when `len` is 0.
+    fn resolve(&self, len: usize, out: &mut [u8; GLOB_PATH_MAX]) -> bool {
+        let mut path = [0u8; GLOB_PATH_MAX + 1];
+        if len == 0 {
+            path[0] = b'.';
+        } else {
+            path[..len].copy_from_slice(&self.path[..len]);
+        }
+        normalize_path(path.as_ptr(), out) == 0
+    }
+
+    fn exists(&self, len: usize) -> bool {
+        let mut abs = [0u8; GLOB_PATH_MAX];
+        let mut stat = UserFsStat::default();
+        self.resolve(len, &mut abs)
+            && fs::stat_path(abs.as_ptr() as *const c_char, &mut stat).is_ok()
+    }
+
+    /// The entries of the directory at the first `len` bytes of the path,
+    /// sorted by name; none if it is not a directory, an error if it may
+    /// have more than fit.
+    fn list(
+        &self,
+        len: usize,
+        entries: &mut [UserFsEntry; GLOB_MAX_ENTRIES],
+    ) -> Result<usize, &'static [u8]> {
+        let mut abs = [0u8; GLOB_PATH_MAX];
+        if !self.resolve(len, &mut abs) {
+            return Ok(0);
+        }
+        let mut list = UserFsList {
+            entries: entries.as_mut_ptr(),
+            max_entries: entries.len() as u32,
+            count: 0,
+        };
+        if fs::list_dir(abs.as_ptr() as *const c_char, &mut list).is_err() {
+            return Ok(0);
+        }
+        let count = (list.count as usize).min(entries.len());
+        if count == entries.len() {
+            return Err(b"glob: too many entries in directory");
+        }
+        entries[..count].sort_unstable_by(|a, b| entry_name(a).cmp(entry_name(b)));
+        Ok(count)
+    }
+
+    fn found(&mut self, len: usize) -> Result<(), &'static [u8]> {
+        self.found += 1;
+        (self.emit)(&self.path[..len])
+    }
+
+    /// Match the pattern from `from` on below the directory at the first
+    /// `len` bytes of the path.
+    fn component(&mut self, from: usize, len: usize) -> Result<(), &'static [u8]> {
+        let (all, all_literal) = (self.pattern, self.literal);
+        let end = all[from..]
+            .iter()
+            .position(|&b| b == b'/')
+            .map_or(all.len(), |i| from + i);
+        let last = end == all.len();
+        let (pattern, literal) = (&all[from..end], &all_literal[from..end]);
+
+        if !has_wildcard(pattern, literal) {
+            let Some(len) = self.push(len, pattern, !last) else {
+                return Ok(());
+            };
+            return if !last {
+                self.component(end + 1, len)
+            } else if self.exists(len) {
+                self.found(len)
+            } else {
+                Ok(())
+            };
+        }
+
+        let mut entries = [UserFsEntry::new(); GLOB_MAX_ENTRIES];
+        let count = self.list(len, &mut entries)?;
+        for entry in &entries[..count] {
+            let name = entry_name(entry);
+            if name.is_empty() || name == b"." || name == b".." {
+                continue;
+            }
+            // Hidden names only match a pattern that starts with a dot.
+            if name[0] == b'.' && pattern[0] != b'.' {
+                continue;
+            }
+            if !matches(pattern, literal, name) || !last && !entry.is_directory() {
+                continue;
+            }
+            let Some(next) = self.push(len, name, !last) else {
+                continue;
+            };
+            if last {
+                self.found(next)?;
+            } else {
+                self.component(end + 1, next)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Call `emit` with each path `pattern` matches, in sorted order, and
+/// return how many there were.  Matches keep the form of the pattern: a
+/// relative pattern gives paths relative to the cwd.
+pub fn expand(
+    pattern: &[u8],
+    literal: &[bool],
+    emit: &mut Emit<'_>,
+) -> Result<usize, &'static [u8]> {
+    let mut walk = Walk {
+        pattern,
+        literal,
+        path: [0; GLOB_PATH_MAX],
+        emit,
+        found: 0,
+    };
+    walk.component(0, 0)?;
+    Ok(walk.found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+        matches(pattern, &[false; GLOB_PATH_MAX][..pattern.len()], name)
+    }
+
+    #[test]
+    fn test_star_backtracks() {
+        assert!(glob_match(b"a*b*c", b"axbxbc"));
+        assert!(glob_match(b"*ab", b"aab"));
+        assert!(glob_match(b"*.rs", b"main.rs.rs"));
+        assert!(glob_match(b"a**", b"a"));
+        assert!(!glob_match(b"a*b*c", b"axbxcb"));
+        assert!(!glob_match(b"*.rs", b"main.rsx"));
+    }
+
+    #[test]
+    fn test_negated_range() {
+        assert!(glob_match(b"[!a-z]", b"A"));
+        assert!(glob_match(b"[!a-z]", b"0"));
+        assert!(!glob_match(b"[!a-z]", b"m"));
+        assert!(!glob_match(b"[!a-z]", b"z"));
+        assert!(glob_match(b"[^a-z]x", b"-x"));
+    }
+
+    #[test]
+    fn test_leading_bracket_in_class() {
+        assert!(glob_match(b"[]]", b"]"));
+        assert!(!glob_match(b"[]]", b"["));
+        assert!(glob_match(b"[]a]", b"a"));
+        assert!(glob_match(b"[!]]", b"x"));
+        assert!(!glob_match(b"[!]]", b"]"));
+    }
+
+    #[test]
+    fn test_unclosed_bracket_is_literal() {
+        assert!(glob_match(b"[ab", b"[ab"));
+        assert!(!glob_match(b"[ab", b"a"));
+        assert!(glob_match(b"x[*", b"x[yz"));
+    }
+
+    #[test]
+    fn test_literal_wildcards() {
+        assert!(matches(b"a*", &[false, true], b"a*"));
+        assert!(!matches(b"a*", &[false, true], b"ab"));
+        assert!(!has_wildcard(b"a*", &[false, true]));
+        assert!(has_wildcard(b"a?", &[false, false]));
+    }
+}
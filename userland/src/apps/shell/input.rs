@@ -1,10 +1,10 @@
 use core::cmp;
 use core::ffi::c_void;
-use core::ptr;
 
 use crate::runtime;
 use crate::syscall::core as sys_core;
 use crate::syscall::{InputEvent, InputEventType, UserPollFd, fs, input};
+use slopos_abi::input::CLIPBOARD_MIME_TEXT;
 use slopos_abi::syscall::{ECHO, ECHOE, ICANON, ISIG, POLLIN};
 
 use super::buffers;
@@ -14,7 +14,6 @@ use super::display::{
     shell_console_page_up, shell_redraw_input, shell_write,
 };
 use super::history;
-use super::parser::{SHELL_MAX_TOKENS, shell_parse_line};
 
 const KEY_PAGE_UP: u8 = 0x80;
 const KEY_PAGE_DOWN: u8 = 0x81;
@@ -48,11 +47,9 @@ static PROMPT_COLORS: super::SyncUnsafeCell<[u8; super::PROMPT_BUF_MAX]> =
     super::SyncUnsafeCell::new([0; super::PROMPT_BUF_MAX]);
 static PROMPT_COLORS_LEN: super::SyncUnsafeCell<usize> = super::SyncUnsafeCell::new(0);
 
-pub fn read_command_line(
-    tokens: &mut [*const u8; SHELL_MAX_TOKENS],
-    prompt: &[u8],
-    prompt_colors: &[u8],
-) -> i32 {
+/// Read a line into the line buffer, NUL-terminated.  Returns its length,
+/// 0 if it was cancelled with Ctrl+C and -1 on Ctrl+D at an empty line.
+pub fn read_command_line(prompt: &[u8], prompt_colors: &[u8]) -> i32 {
     unsafe {
         let colors = &mut *PROMPT_COLORS.get();
         let copy_len = prompt_colors.len().min(super::PROMPT_BUF_MAX);
@@ -72,7 +69,7 @@ pub fn read_command_line(
         let _ = fs::tcsetattr(0, &raw);
     }
 
-    let result = input_loop(tokens, prompt, 0, 0);
+    let result = input_loop(prompt, 0, 0);
 
     // Restore canonical mode so child processes (nc, etc.) get line-buffered input.
     if let Some(ref t) = saved_termios {
@@ -90,12 +87,7 @@ fn prompt_colors_slice() -> &'static [u8] {
     }
 }
 
-fn input_loop(
-    tokens: &mut [*const u8; SHELL_MAX_TOKENS],
-    prompt: &[u8],
-    mut len: usize,
-    mut cursor_pos: usize,
-) -> i32 {
+fn input_loop(prompt: &[u8], mut len: usize, mut cursor_pos: usize) -> i32 {
     use super::display::InputSelection;
 
     let mut line_row = super::display::shell_console_get_cursor().1;
@@ -438,7 +430,9 @@ fn input_loop(
                 shell_write(b"\x1B[2J\x1B[H");
                 shell_console_clear();
                 shell_write(prompt);
-                return input_loop(tokens, prompt, len, cursor_pos);
+                // The screen was wiped; have the line sent again in full.
+                super::display::shell_serial_line_reset();
+                return input_loop(prompt, len, cursor_pos);
             }
 
             CTRL_C => {
@@ -447,7 +441,7 @@ fn input_loop(
                     let hi = hi.min(len);
                     if lo < hi {
                         buffers::with_line_buf(|buf| {
-                            input::clipboard_copy(&buf[lo..hi]);
+                            let _ = input::clipboard_set(CLIPBOARD_MIME_TEXT, &buf[lo..hi]);
                         });
                     }
                     sel = InputSelection::NONE;
@@ -467,14 +461,24 @@ fn input_loop(
                     delete_selection(&mut sel, &mut len, &mut cursor_pos);
                 }
                 let mut paste_buf = [0u8; 256];
-                let pasted = input::clipboard_paste(&mut paste_buf);
+                let pasted = input::clipboard_get(CLIPBOARD_MIME_TEXT, &mut paste_buf)
+                    .map_or(0, |n| n.min(paste_buf.len()));
                 if pasted > 0 {
+                    // Only the first line fits a command; tabs become spaces.
                     let mut filtered = [0u8; 256];
                     let mut flen = 0;
                     for &b in &paste_buf[..pasted] {
-                        if (0x20..=0x7E).contains(&b) {
-                            filtered[flen] = b;
-                            flen += 1;
+                        match b {
+                            b'\n' | b'\r' => break,
+                            b'\t' => {
+                                filtered[flen] = b' ';
+                                flen += 1;
+                            }
+                            0x20..=0x7E => {
+                                filtered[flen] = b;
+                                flen += 1;
+                            }
+                            _ => {}
                         }
                     }
                     if flen > 0 {
@@ -515,7 +519,7 @@ fn input_loop(
                         );
                     }
 
-                    return input_loop(tokens, prompt, len, cursor_pos);
+                    return input_loop(prompt, len, cursor_pos);
                 } else if comp.insertion_len > 0 {
                     insert_text(
                         &comp.insertion,
@@ -554,20 +558,8 @@ fn input_loop(
     buffers::with_line_buf(|buf| {
         let capped = cmp::min(len, buf.len() - 1);
         buf[capped] = 0;
-    });
-
-    let expanded_len = buffers::with_line_buf(|line_buf| {
-        let line_len = line_buf
-            .iter()
-            .position(|&b| b == 0)
-            .unwrap_or(line_buf.len());
-        buffers::with_expand_buf(|expand_buf| {
-            super::parser::expand_variables(line_buf, line_len, expand_buf)
-        })
-    });
-
-    *tokens = [ptr::null(); SHELL_MAX_TOKENS];
-    buffers::with_expand_buf(|expand_buf| shell_parse_line(&expand_buf[..expanded_len], tokens))
+        capped as i32
+    })
 }
 
 /// Convert a pixel x-coordinate to a character offset within the input buffer.
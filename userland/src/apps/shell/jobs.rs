@@ -1,3 +1,5 @@
+use slopos_lib::numfmt::{self, NumBuf};
+
 use crate::runtime;
 use crate::syscall::process;
 
@@ -201,27 +203,8 @@ pub fn find_pgid_by_job_id(job_id: u16) -> Option<u32> {
 }
 
 pub fn write_u64(value: u64) {
-    let mut tmp = [0u8; 32];
-    let mut idx = 0usize;
-    if value == 0 {
-        tmp[0] = b'0';
-        idx = 1;
-    } else {
-        let mut n = value;
-        let mut rev = [0u8; 32];
-        let mut r = 0usize;
-        while n != 0 && r < rev.len() {
-            rev[r] = b'0' + (n % 10) as u8;
-            n /= 10;
-            r += 1;
-        }
-        while r > 0 {
-            idx += 1;
-            tmp[idx - 1] = rev[r - 1];
-            r -= 1;
-        }
-    }
-    shell_write(&tmp[..idx]);
+    let mut buf = NumBuf::<21>::new();
+    shell_write(numfmt::trim_nul(buf.format_u64(value)));
 }
 
 pub fn parse_u32_arg(ptr: *const u8) -> Option<u32> {
@@ -1,5 +1,4 @@
 use core::cell::UnsafeCell;
-use core::ffi::c_void;
 
 mod banner;
 pub mod buffers;
@@ -8,10 +7,13 @@ pub mod completion;
 pub mod display;
 pub mod env;
 pub mod exec;
+pub mod glob;
 pub mod history;
 pub mod input;
 pub mod jobs;
 pub mod parser;
+pub mod plugins;
+pub mod script;
 mod surface;
 
 #[repr(transparent)]
@@ -220,23 +222,14 @@ fn build_prompt(
 }
 
 fn write_colored_prompt(prompt: &[u8], colors: &[u8]) {
-    use display::{COLOR_DEFAULT, shell_console_commit, shell_console_write_colored};
-
-    let _ = crate::syscall::tty::write(prompt);
+    use display::{
+        for_each_color_run, shell_console_commit, shell_console_write_colored,
+        shell_serial_line_reset, term_write_idx,
+    };
 
-    let mut i = 0;
-    while i < prompt.len() {
-        let color = if i < colors.len() {
-            colors[i]
-        } else {
-            COLOR_DEFAULT
-        };
-        let start = i;
-        while i < prompt.len() && (i >= colors.len() || colors[i] == color) {
-            i += 1;
-        }
-        shell_console_write_colored(&prompt[start..i], color);
-    }
+    for_each_color_run(prompt, colors, term_write_idx);
+    shell_serial_line_reset();
+    for_each_color_run(prompt, colors, shell_console_write_colored);
     shell_console_commit();
 }
 
@@ -246,20 +239,52 @@ pub struct ShellState {
     pub prompt_len: usize,
 }
 
-pub fn shell_user_main(_arg: *mut c_void) {
+/// `shell [--pty]`, `shell -c COMMAND [NAME [ARGS...]]` or
+/// `shell FILE [ARGS...]`.
+///
+/// With `--pty` the shell opens no window and runs on the pty it was given
+/// as fd 0 and 1, as the terminal app starts it.  With `-c` or a script
+/// file it runs that instead of reading commands, and exits with its
+/// status.
+pub fn shell_main_args(argc: usize, argv: *const *const u8) {
     use slopos_abi::signal::SIGINT;
 
     use crate::syscall::process;
     use crate::syscall::window;
 
-    display::shell_console_init();
-    display::shell_console_clear();
+    let args: &[*const u8] = if argv.is_null() {
+        &[]
+    } else {
+        unsafe { core::slice::from_raw_parts(argv, argc) }
+    };
+    let arg = |index: usize| {
+        args.get(index)
+            .filter(|arg| !arg.is_null())
+            .map(|&arg| unsafe { core::slice::from_raw_parts(arg, crate::runtime::u_strlen(arg)) })
+    };
 
-    window::surface_set_title("SlopOS Shell");
-    window::set_cursor_shape(slopos_abi::CURSOR_SHAPE_TEXT);
+    match arg(1) {
+        Some(b"--pty") => display::shell_set_on_pty(),
+        Some(b"-c") => run_script(argv, argc, || {
+            let Some(command) = arg(2) else {
+                display::shell_write(b"shell: -c: missing command\n");
+                return 2;
+            };
+            script::run_command(command, &args[3..])
+        }),
+        Some(_) => run_script(argv, argc, || script::run_file(&args[1..])),
+        None => {
+            display::shell_console_init();
+            display::shell_console_clear();
+
+            window::surface_set_title("SlopOS Shell");
+            window::set_cursor_shape(slopos_abi::CURSOR_SHAPE_TEXT);
+        }
+    }
 
     cwd_set(b"/");
     env::initialize_defaults();
+    script::set_params(&args[..args.len().min(1)]);
     unsafe { *SHELL_PID.get() = process::getpid() }
     exec::initialize_job_control();
     let _ = process::ignore_signal(SIGINT);
@@ -279,18 +304,41 @@ pub fn shell_user_main(_arg: *mut c_void) {
 
         write_colored_prompt(prompt, &state.prompt_colors[..state.prompt_len]);
 
-        let mut tokens = [core::ptr::null(); parser::SHELL_MAX_TOKENS];
         let prompt_colors = &state.prompt_colors[..state.prompt_len];
-        let token_count = input::read_command_line(&mut tokens, prompt, prompt_colors);
+        let line_len = input::read_command_line(prompt, prompt_colors);
 
-        if token_count <= 0 {
+        if line_len <= 0 {
             continue;
         }
 
-        let rc = exec::execute_tokens(token_count, &tokens);
-        set_last_exit_code(rc);
-        if rc == 127 {
-            display::shell_write_idx(UNKNOWN_CMD, display::COLOR_ERROR_RED);
+        // A copy: commands the line runs reuse the line buffer.
+        let line = buffers::with_line_buf(|buf| *buf);
+        script::run_line(&line[..line_len as usize]);
+        if script::flow() == script::Flow::Exit {
+            crate::syscall::core::exit_with_code(last_exit_code());
         }
     }
 }
+
+/// Run a script without a window or prompt, in the directory and with the
+/// environment the shell was started with, and exit with its status.
+fn run_script(argv: *const *const u8, argc: usize, run: impl FnOnce() -> i32) -> ! {
+    use crate::syscall::process;
+
+    display::shell_set_script_output();
+    let mut cwd = [0u8; CWD_MAX];
+    let len = process::getcwd(&mut cwd);
+    if len > 1 {
+        cwd_set(&cwd[..len as usize - 1]);
+    } else {
+        cwd_set(b"/");
+    }
+    env::initialize_defaults();
+    // The environment follows argv's terminating null.
+    env::import(unsafe { argv.add(argc + 1) });
+    unsafe { *SHELL_PID.get() = process::getpid() }
+    exec::initialize_script_job_control();
+
+    let status = run();
+    crate::syscall::core::exit_with_code(status)
+}
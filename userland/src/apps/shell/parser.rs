@@ -1,10 +1,12 @@
-//! Command line parsing and path normalization.
+//! Command line parsing, brace and wildcard expansion, and path
+//! normalization.
 
 use core::ptr;
 
 use crate::runtime;
 
-use super::buffers;
+use super::buffers::{self, EXPAND_BUF_SIZE};
+use super::glob::{self, Emit, GLOB_PATH_MAX};
 
 pub const SHELL_MAX_TOKENS: usize = 16;
 pub const SHELL_MAX_TOKEN_LENGTH: usize = 64;
@@ -258,6 +260,30 @@ pub fn expand_variables(input: &[u8], input_len: usize, output: &mut [u8]) -> us
                 i += 2;
                 continue;
             }
+            if next.is_ascii_digit() {
+                let (val, val_len) = super::script::param((next - b'0') as usize);
+                emit_slice(output, &mut out, &val, val_len);
+                i += 2;
+                continue;
+            }
+            if next == b'#' {
+                let count = super::script::param_count() as u32;
+                let n = write_u32_to_buf(count, &mut output[out..]);
+                out += n;
+                i += 2;
+                continue;
+            }
+            if next == b'@' || next == b'*' {
+                for index in 1..=super::script::param_count() {
+                    if index > 1 {
+                        emit(output, &mut out, b' ');
+                    }
+                    let (val, val_len) = super::script::param(index);
+                    emit_slice(output, &mut out, &val, val_len);
+                }
+                i += 2;
+                continue;
+            }
             if next == b'{' {
                 i += 2;
                 let var_start = i;
@@ -301,6 +327,253 @@ fn is_operator(b: u8) -> bool {
     b == b'|' || b == b'<' || b == b'>' || b == b'&'
 }
 
+/// Indexes of the bytes of `word` that are outside quotes and not escaped,
+/// with quotes working as in [`shell_parse_line`].
+fn unquoted(word: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    let (mut in_single, mut in_double, mut escaped) = (false, false, false);
+    word.iter().enumerate().filter_map(move |(i, &c)| {
+        if escaped {
+            escaped = false;
+            return None;
+        }
+        match c {
+            b'\'' if !in_double => in_single = !in_single,
+            b'"' if !in_single => in_double = !in_double,
+            _ if in_single || in_double => {}
+            b'\\' => escaped = true,
+            _ => return Some(i),
+        }
+        None
+    })
+}
+
+/// End of the word starting at `input[start]`: its first unquoted blank or
+/// operator byte.
+fn word_end(input: &[u8], start: usize) -> usize {
+    let word = &input[start..];
+    let len = word.iter().position(|&b| b == 0).unwrap_or(word.len());
+    let word = &word[..len];
+    unquoted(word)
+        .find(|&i| is_space(word[i]) || is_operator(word[i]))
+        .map_or(start + len, |i| start + i)
+}
+
+/// The first unquoted `{` in `word` with a matching `}` and a comma between
+/// them at its own level, as the indexes of the two braces.
+fn find_brace(word: &[u8]) -> Option<(usize, usize)> {
+    let (mut open, mut depth, mut comma) = (0, 0, false);
+    for i in unquoted(word) {
+        match word[i] {
+            b'{' => {
+                if depth == 0 {
+                    open = i;
+                    comma = false;
+                }
+                depth += 1;
+            }
+            b'}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 && comma {
+                    return Some((open, i));
+                }
+            }
+            b',' if depth == 1 => comma = true,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Call `emit` with each word `word` stands for after brace expansion, in
+/// order: `x{a,b}y` gives `xay` then `xby`.  Braces nest; a quoted brace or
+/// one without a comma is left as it is.
+fn expand_braces(word: &[u8], emit: &mut Emit<'_>) -> Result<(), &'static [u8]> {
+    let Some((open, close)) = find_brace(word) else {
+        return emit(word);
+    };
+    let (prefix, inner, suffix) = (&word[..open], &word[open + 1..close], &word[close + 1..]);
+    let mut depth = 0;
+    let mut start = 0;
+    for i in unquoted(inner).chain(core::iter::once(inner.len())) {
+        match inner.get(i).copied().unwrap_or(b',') {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                // Shorter than `word`, which came out of a buffer this size.
+                let mut alternative = [0u8; EXPAND_BUF_SIZE];
+                let mut len = 0;
+                for part in [prefix, &inner[start..i], suffix] {
+                    alternative[len..len + part.len()].copy_from_slice(part);
+                    len += part.len();
+                }
+                expand_braces(&alternative[..len], emit)?;
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The bytes `word` stands for once [`shell_parse_line`] removes its quotes,
+/// with `literal` marking those that were quoted or escaped.  `None` if
+/// they do not fit in `pattern`.
+fn unquote(word: &[u8], pattern: &mut [u8], literal: &mut [bool]) -> Option<usize> {
+    let (mut in_single, mut in_double) = (false, false);
+    let mut len = 0;
+    let mut i = 0;
+    while i < word.len() {
+        let mut c = word[i];
+        i += 1;
+        if c == b'\'' && !in_double {
+            in_single = !in_single;
+            continue;
+        }
+        if c == b'"' && !in_single {
+            in_double = !in_double;
+            continue;
+        }
+        let mut quoted = in_single || in_double;
+        if !quoted && c == b'\\' && i < word.len() {
+            c = word[i];
+            i += 1;
+            quoted = true;
+        }
+        if len == pattern.len() {
+            return None;
+        }
+        pattern[len] = c;
+        literal[len] = quoted;
+        len += 1;
+    }
+    Some(len)
+}
+
+/// The words [`expand_words`] writes, separated by blanks.
+struct Words<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    count: usize,
+    /// Whether the next word needs a blank before it: not for the first
+    /// word an input word expands to, which keeps its place next to an
+    /// operator as in `2>file`.
+    separate: bool,
+}
+
+impl Words<'_> {
+    fn push(&mut self, byte: u8) -> Result<(), &'static [u8]> {
+        // Room is kept for the NUL.
+        if self.len + 1 >= self.buf.len() {
+            return Err(b"command too long after expansion");
+        }
+        self.buf[self.len] = byte;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn start(&mut self) -> Result<(), &'static [u8]> {
+        self.count += 1;
+        if self.count > SHELL_MAX_TOKENS {
+            return Err(b"too many arguments after expansion");
+        }
+        if self.separate {
+            self.push(b' ')?;
+        }
+        self.separate = true;
+        Ok(())
+    }
+
+    /// A word as it was written, quotes and all.
+    fn word(&mut self, word: &[u8]) -> Result<(), &'static [u8]> {
+        self.start()?;
+        word.iter().try_for_each(|&b| self.push(b))
+    }
+
+    /// A matched path, escaped so [`shell_parse_line`] keeps it one token.
+    fn path(&mut self, path: &[u8]) -> Result<(), &'static [u8]> {
+        self.start()?;
+        for &b in path {
+            if is_space(b) || is_operator(b) || matches!(b, b'\'' | b'"' | b'\\') {
+                self.push(b'\\')?;
+            }
+            self.push(b)?;
+        }
+        Ok(())
+    }
+
+    /// `word` replaced by the paths it matches if it has an unquoted
+    /// wildcard, and as it is otherwise or when nothing matches.
+    fn glob(&mut self, word: &[u8]) -> Result<(), &'static [u8]> {
+        let mut pattern = [0u8; GLOB_PATH_MAX];
+        let mut literal = [false; GLOB_PATH_MAX];
+        if let Some(len) = unquote(word, &mut pattern, &mut literal)
+            && glob::has_wildcard(&pattern[..len], &literal[..len])
+            && glob::expand(&pattern[..len], &literal[..len], &mut |path| {
+                self.path(path)
+            })? > 0
+        {
+            return Ok(());
+        }
+        self.word(word)
+    }
+}
+
+/// Expand `{a,b}` braces and then `*`, `?` and `[...]` wildcards in the
+/// words of `input`, the output of [`expand_variables`], into `output` for
+/// [`shell_parse_line`].  Quoted braces and wildcards are kept, as is a
+/// pattern that matches nothing.
+pub fn expand_words(input: &[u8], output: &mut [u8]) -> Result<usize, &'static [u8]> {
+    let mut out = Words {
+        buf: output,
+        len: 0,
+        count: 0,
+        separate: false,
+    };
+    let mut i = 0;
+    while i < input.len() && input[i] != 0 {
+        let c = input[i];
+        if is_space(c) || is_operator(c) {
+            out.push(c)?;
+            out.separate = false;
+            i += 1;
+            continue;
+        }
+        let end = word_end(input, i);
+        expand_braces(&input[i..end], &mut |word| out.glob(word))?;
+        out.separate = false;
+        i = end;
+    }
+    out.buf[out.len] = 0;
+    Ok(out.len)
+}
+
+/// Length of the operator token at the start of `rest`, 0 if there is
+/// none: `|`, `&`, `<`, `<<<`, `>`, `>>`, `<&`, `>&`, `&>` and `&>>`, the
+/// redirections other than `<<<` and `&>` optionally after an fd number
+/// (`2>`, `2>>`, `2>&`).
+fn operator_len(rest: &[u8]) -> usize {
+    let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+    // Longer numbers are left as words.
+    if digits > 2 {
+        return 0;
+    }
+    let len = match &rest[digits..] {
+        [b'<', b'<', b'<', ..] if digits == 0 => 3,
+        [b'&', b'>', b'>', ..] if digits == 0 => 3,
+        [b'&', b'>', ..] if digits == 0 => 2,
+        [b'>', b'>' | b'&', ..] | [b'<', b'&', ..] => 2,
+        [b'>' | b'<', ..] => 1,
+        [b'|' | b'&', ..] if digits == 0 => 1,
+        _ => return 0,
+    };
+    digits + len
+}
+
+/// Whether `token` is an operator as [`shell_parse_line`] splits them out.
+pub fn is_operator_token(token: &[u8]) -> bool {
+    !token.is_empty() && operator_len(token) == token.len()
+}
+
 pub fn shell_parse_line(line: &[u8], tokens: &mut [*const u8]) -> i32 {
     if line.is_empty() || tokens.is_empty() {
         return 0;
@@ -316,35 +589,15 @@ pub fn shell_parse_line(line: &[u8], tokens: &mut [*const u8]) -> i32 {
             break;
         }
 
-        if line[cursor] == b'|' || line[cursor] == b'<' || line[cursor] == b'&' {
-            let mut tok = [0u8; SHELL_MAX_TOKEN_LENGTH];
-            tok[0] = line[cursor];
-            cursor += 1;
+        let op_len = operator_len(&line[cursor..]);
+        if op_len > 0 {
             buffers::with_token_storage(|storage| {
-                storage[count][0] = tok[0];
-                storage[count][1] = 0;
-            });
-            tokens[count] = buffers::token_ptr(count);
-            count += 1;
-            continue;
-        }
-        if line[cursor] == b'>' {
-            cursor += 1;
-            let is_append = cursor < line.len() && line[cursor] == b'>';
-            if is_append {
-                cursor += 1;
-            }
-            buffers::with_token_storage(|storage| {
-                storage[count][0] = b'>';
-                if is_append {
-                    storage[count][1] = b'>';
-                    storage[count][2] = 0;
-                } else {
-                    storage[count][1] = 0;
-                }
+                storage[count][..op_len].copy_from_slice(&line[cursor..cursor + op_len]);
+                storage[count][op_len] = 0;
             });
             tokens[count] = buffers::token_ptr(count);
             count += 1;
+            cursor += op_len;
             continue;
         }
 
@@ -412,3 +665,47 @@ pub fn shell_parse_line(line: &[u8], tokens: &mut [*const u8]) -> i32 {
     }
     count as i32
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The words `word` brace-expands to, joined with spaces.
+    fn braces(word: &[u8]) -> ([u8; EXPAND_BUF_SIZE], usize) {
+        let mut out = [0u8; EXPAND_BUF_SIZE];
+        let mut len = 0;
+        expand_braces(word, &mut |w| {
+            if len > 0 {
+                out[len] = b' ';
+                len += 1;
+            }
+            out[len..len + w.len()].copy_from_slice(w);
+            len += w.len();
+            Ok(())
+        })
+        .unwrap();
+        (out, len)
+    }
+
+    fn assert_braces(word: &[u8], expected: &[u8]) {
+        let (out, len) = braces(word);
+        assert_eq!(&out[..len], expected);
+    }
+
+    #[test]
+    fn test_braces_nested() {
+        assert_braces(b"a{b,c{d,e}}f", b"abf acdf acef");
+        assert_braces(b"{x,{y,z}}", b"x y z");
+        assert_braces(b"{a,b}{1,2}", b"a1 a2 b1 b2");
+        assert_braces(b"{{a,b},c}.rs", b"a.rs b.rs c.rs");
+    }
+
+    #[test]
+    fn test_braces_kept() {
+        assert_braces(b"{a}", b"{a}");
+        assert_braces(b"'{a,b}'", b"'{a,b}'");
+        assert_braces(b"\\{a,b}", b"\\{a,b}");
+        assert_braces(b"{a,b", b"{a,b");
+        assert_braces(b"{}", b"{}");
+    }
+}
@@ -0,0 +1,115 @@
+//! Plugin commands: `/bin/slop-<name>` runs as `<name>` (see
+//! [`crate::plugin`]).  A command is looked up as a plugin after builtins
+//! and registered programs, before `PATH`; `help` and completion list the
+//! plugins by scanning `/bin`.
+
+use core::ffi::c_char;
+
+use crate::plugin::{self, PLUGIN_DIR, PLUGIN_PREFIX, PluginInfo};
+use crate::program_registry;
+use crate::syscall::{UserFsEntry, UserFsList, UserFsStat, fs};
+
+use super::builtins::BUILTINS;
+
+/// Plugins kept by one scan; the rest are not listed but still run.
+pub const MAX_PLUGINS: usize = 16;
+/// Directory entries read per scan.
+const SCAN_ENTRIES: usize = 64;
+const NAME_MAX: usize = 64;
+
+/// Write the NUL-terminated path of plugin `name` to `out`.
+fn plugin_path(name: &[u8], out: &mut [u8; 256]) -> Option<*const c_char> {
+    if name.is_empty() || name.contains(&b'/') {
+        return None;
+    }
+    let parts: [&[u8]; 4] = [PLUGIN_DIR, b"/", PLUGIN_PREFIX, name];
+    let mut pos = 0;
+    for part in parts {
+        out.get_mut(pos..pos + part.len())?.copy_from_slice(part);
+        pos += part.len();
+    }
+    *out.get_mut(pos)? = 0;
+    Some(out.as_ptr() as *const c_char)
+}
+
+/// The path of the plugin providing command `name`, written to `out`.
+pub fn resolve_plugin(name: &[u8], out: &mut [u8; 256]) -> Option<*const u8> {
+    let path = plugin_path(name, out)?;
+    let mut stat = UserFsStat::default();
+    fs::stat_path(path, &mut stat).ok()?;
+    (!stat.is_directory()).then_some(out.as_ptr())
+}
+
+/// The help text of plugin `name`, if it is installed and has a note.
+pub fn plugin_info(name: &[u8]) -> Option<PluginInfo> {
+    let mut path = [0u8; 256];
+    plugin::read_plugin_info(plugin_path(name, &mut path)?)
+}
+
+#[derive(Clone, Copy)]
+struct PluginName {
+    bytes: [u8; NAME_MAX],
+    len: usize,
+}
+
+/// Command names of the installed plugins, sorted.  Plugins shadowed by a
+/// builtin or a registered program are left out, since they never run.
+pub struct PluginNames {
+    names: [PluginName; MAX_PLUGINS],
+    count: usize,
+}
+
+impl PluginNames {
+    pub fn scan() -> Self {
+        let mut out = Self {
+            names: [PluginName {
+                bytes: [0; NAME_MAX],
+                len: 0,
+            }; MAX_PLUGINS],
+            count: 0,
+        };
+
+        let mut dir = [0u8; NAME_MAX];
+        dir[..PLUGIN_DIR.len()].copy_from_slice(PLUGIN_DIR);
+        let mut entries = [UserFsEntry::new(); SCAN_ENTRIES];
+        let mut list = UserFsList {
+            entries: entries.as_mut_ptr(),
+            max_entries: entries.len() as u32,
+            count: 0,
+        };
+        if fs::list_dir(dir.as_ptr() as *const c_char, &mut list).is_err() {
+            return out;
+        }
+
+        for entry in &entries[..(list.count as usize).min(SCAN_ENTRIES)] {
+            let len = entry
+                .name
+                .iter()
+                .position(|&b| b == 0)
+                .unwrap_or(entry.name.len());
+            let Some(name) = entry.name[..len].strip_prefix(PLUGIN_PREFIX) else {
+                continue;
+            };
+            if name.is_empty()
+                || entry.is_directory()
+                || out.count == MAX_PLUGINS
+                || BUILTINS.iter().any(|b| b.name == name)
+                || program_registry::resolve_program(name).is_some()
+            {
+                continue;
+            }
+            let slot = &mut out.names[out.count];
+            slot.bytes[..name.len()].copy_from_slice(name);
+            slot.len = name.len();
+            out.count += 1;
+        }
+        out.names[..out.count].sort_unstable_by(|a, b| a.bytes[..a.len].cmp(&b.bytes[..b.len]));
+        out
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.names[..self.count]
+            .iter()
+            .map(|name| &name.bytes[..name.len])
+    }
+}
@@ -0,0 +1,874 @@
+//! Shell scripts: `if`, `while`, `until` and `for`, functions, `{ }`
+//! groups, `&&`/`||` lists, `!` and positional parameters.
+//!
+//! Source text is run as it is read, without building a syntax tree.  A
+//! command under a false condition is still parsed, with `run` false, so
+//! the reader ends up past it; a loop seeks back to its condition for each
+//! pass.  Every simple command is expanded, split into tokens and run by
+//! [`exec::execute_tokens`], as a line typed at the prompt always was.
+
+use core::ffi::c_char;
+use core::ptr;
+
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::runtime;
+use crate::syscall::{FdGuard, USER_FS_OPEN_READ, fs};
+
+use super::buffers::{self, EXPAND_BUF_SIZE, SCRIPT_BUF_SIZE};
+use super::display::{COLOR_ERROR_RED, shell_write_idx};
+use super::env;
+use super::exec;
+use super::parser::{
+    SHELL_MAX_TOKEN_LENGTH, SHELL_MAX_TOKENS, expand_variables, expand_words, normalize_path,
+    shell_parse_line,
+};
+use super::{PATH_TOO_LONG, SyncUnsafeCell, UNKNOWN_CMD};
+
+/// Positional parameters `$1` to `$9`; arguments past these are dropped.
+pub const MAX_PARAMS: usize = 9;
+
+const MAX_FUNCTIONS: usize = 16;
+const FUNCTION_NAME_MAX: usize = 32;
+const FUNCTION_BODY_MAX: usize = 1024;
+/// Function calls in progress at once; each holds a copy of its body on
+/// the stack.
+const MAX_CALL_DEPTH: usize = 16;
+
+/// Bytes that end a word as [`Script::word`] reads it.
+const WORD_END: &[u8] = b"\0 \t\r\n;&|<>()";
+
+/// Words that end a list of commands where a command would start.
+const LIST_END: &[&[u8]] = &[b"then", b"elif", b"else", b"fi", b"do", b"done", b"}"];
+
+/// How control leaves the commands being run.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Normal,
+    /// `break n`: leave this loop and the `n - 1` around it.
+    Break(u32),
+    /// `continue n`: start the next pass of the `n`th loop out.
+    Continue(u32),
+    /// `return`: leave the function.
+    Return,
+    /// `exit`: stop the script, or the shell at the prompt.
+    Exit,
+}
+
+#[derive(Clone, Copy)]
+struct Params {
+    /// `$0` to `$9`.
+    values: [[u8; SHELL_MAX_TOKEN_LENGTH]; MAX_PARAMS + 1],
+    lens: [usize; MAX_PARAMS + 1],
+    /// `$#`.
+    count: usize,
+}
+
+impl Params {
+    const fn new() -> Self {
+        Self {
+            values: [[0; SHELL_MAX_TOKEN_LENGTH]; MAX_PARAMS + 1],
+            lens: [0; MAX_PARAMS + 1],
+            count: 0,
+        }
+    }
+
+    fn set(&mut self, index: usize, value: &[u8]) {
+        let len = value.len().min(SHELL_MAX_TOKEN_LENGTH);
+        self.values[index][..len].copy_from_slice(&value[..len]);
+        self.lens[index] = len;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Function {
+    name: [u8; FUNCTION_NAME_MAX],
+    name_len: usize,
+    /// The source between the braces.
+    body: [u8; FUNCTION_BODY_MAX],
+    body_len: usize,
+}
+
+static FLOW: SyncUnsafeCell<Flow> = SyncUnsafeCell::new(Flow::Normal);
+static LOOP_DEPTH: SyncUnsafeCell<u32> = SyncUnsafeCell::new(0);
+static CALL_DEPTH: SyncUnsafeCell<usize> = SyncUnsafeCell::new(0);
+static PARAMS: SyncUnsafeCell<Params> = SyncUnsafeCell::new(Params::new());
+static FUNCTIONS: SyncUnsafeCell<[Option<Function>; MAX_FUNCTIONS]> =
+    SyncUnsafeCell::new([None; MAX_FUNCTIONS]);
+
+pub fn flow() -> Flow {
+    unsafe { *FLOW.get() }
+}
+
+pub fn set_flow(flow: Flow) {
+    unsafe { *FLOW.get() = flow }
+}
+
+/// Loops around the running command, inside its function.
+pub fn loop_depth() -> u32 {
+    unsafe { *LOOP_DEPTH.get() }
+}
+
+pub fn in_function() -> bool {
+    unsafe { *CALL_DEPTH.get() > 0 }
+}
+
+/// `$n`, empty when unset.
+pub fn param(n: usize) -> ([u8; SHELL_MAX_TOKEN_LENGTH], usize) {
+    let params = unsafe { &*PARAMS.get() };
+    if n > params.count && n != 0 {
+        return ([0; SHELL_MAX_TOKEN_LENGTH], 0);
+    }
+    (params.values[n], params.lens[n])
+}
+
+/// `$#`.
+pub fn param_count() -> usize {
+    unsafe { (*PARAMS.get()).count }
+}
+
+/// Drop `$1` to `$n` and renumber the rest.  False if there are fewer
+/// than `n`.
+pub fn shift(n: usize) -> bool {
+    let params = unsafe { &mut *PARAMS.get() };
+    if n > params.count {
+        return false;
+    }
+    for i in 1..=params.count - n {
+        params.values[i] = params.values[i + n];
+        params.lens[i] = params.lens[i + n];
+    }
+    params.count -= n;
+    true
+}
+
+/// Make `argv` the script name `$0` and its parameters `$1`....
+pub fn set_params(argv: &[*const u8]) {
+    if let Some(&name) = argv.first() {
+        unsafe { (*PARAMS.get()).set(0, arg_bytes(name)) };
+    }
+    set_args(argv.get(1..).unwrap_or(&[]));
+}
+
+fn set_args(args: &[*const u8]) {
+    let params = unsafe { &mut *PARAMS.get() };
+    params.count = args.len().min(MAX_PARAMS);
+    for (index, &arg) in args.iter().take(MAX_PARAMS).enumerate() {
+        params.set(index + 1, arg_bytes(arg));
+    }
+}
+
+fn arg_bytes<'a>(arg: *const u8) -> &'a [u8] {
+    if arg.is_null() {
+        return &[];
+    }
+    unsafe { core::slice::from_raw_parts(arg, runtime::u_strlen(arg)) }
+}
+
+fn is_name(word: &[u8]) -> bool {
+    match word.split_first() {
+        Some((&first, rest)) => {
+            (first == b'_' || first.is_ascii_alphabetic())
+                && rest.iter().all(|&b| b == b'_' || b.is_ascii_alphanumeric())
+        }
+        None => false,
+    }
+}
+
+/// Run a line typed at the prompt.  An `exit` in it is left in [`flow`]
+/// for the caller.
+pub fn run_line(line: &[u8]) -> i32 {
+    let status = run_source(line, b"");
+    if flow() != Flow::Exit {
+        set_flow(Flow::Normal);
+    }
+    status
+}
+
+/// `shell -c COMMAND [NAME [ARGS...]]`: run `command` with `argv` as `$0`,
+/// `$1`....
+pub fn run_command(command: &[u8], argv: &[*const u8]) -> i32 {
+    set_params(argv);
+    run_source(command, b"")
+}
+
+/// `shell FILE [ARGS...]`: run the script `argv[0]` with the rest of `argv`
+/// as `$1`....  Returns the status of its last command.
+pub fn run_file(argv: &[*const u8]) -> i32 {
+    let Some(&path) = argv.first() else {
+        return 2;
+    };
+    let name = arg_bytes(path);
+    let mut path_buf = [0u8; 256];
+    if normalize_path(path, &mut path_buf) != 0 {
+        shell_write_idx(PATH_TOO_LONG, COLOR_ERROR_RED);
+        return 127;
+    }
+
+    buffers::with_script_buf(|buf| {
+        let len = match read_script(&path_buf, buf) {
+            Ok(len) => len,
+            Err((status, reason)) => {
+                shell_write_idx(name, COLOR_ERROR_RED);
+                shell_write_idx(b": ", COLOR_ERROR_RED);
+                shell_write_idx(reason, COLOR_ERROR_RED);
+                return status;
+            }
+        };
+        set_params(argv);
+        run_source(&buf[..len], name)
+    })
+}
+
+fn read_script(
+    path: &[u8; 256],
+    buf: &mut [u8; SCRIPT_BUF_SIZE],
+) -> Result<usize, (i32, &'static [u8])> {
+    let fd = fs::open_path(path.as_ptr() as *const c_char, USER_FS_OPEN_READ)
+        .map_err(|_| (127, &b"No such file or directory\n"[..]))?;
+    let fd = unsafe { FdGuard::from_raw(fd) };
+    let mut len = 0;
+    loop {
+        if len == buf.len() {
+            return Err((2, b"script too large\n"));
+        }
+        match fd.read(&mut buf[len..]) {
+            Ok(0) => return Ok(len),
+            Ok(n) => len += n,
+            Err(_) => return Err((2, b"read error\n")),
+        }
+    }
+}
+
+/// Run `src`, reporting a syntax error against the script `name`.
+fn run_source(src: &[u8], name: &[u8]) -> i32 {
+    let mut script = Script { src, pos: 0 };
+    match script.run() {
+        Ok(status) => status,
+        Err(err) => {
+            report(src, name, &err);
+            super::set_last_exit_code(2);
+            2
+        }
+    }
+}
+
+struct SyntaxError {
+    what: &'static [u8],
+    /// Offset in the source where it was found.
+    at: usize,
+}
+
+type Parse<T> = Result<T, SyntaxError>;
+
+/// Print `name: line N: syntax error: WHAT near 'WORD'`, leaving out the
+/// name and line for a line typed at the prompt.
+fn report(src: &[u8], name: &[u8], err: &SyntaxError) {
+    if !name.is_empty() {
+        let line = src[..err.at].iter().filter(|&&b| b == b'\n').count() + 1;
+        let mut buf = NumBuf::<21>::new();
+        shell_write_idx(name, COLOR_ERROR_RED);
+        shell_write_idx(b": line ", COLOR_ERROR_RED);
+        shell_write_idx(
+            numfmt::trim_nul(buf.format_u64(line as u64)),
+            COLOR_ERROR_RED,
+        );
+        shell_write_idx(b": ", COLOR_ERROR_RED);
+    }
+    shell_write_idx(b"syntax error: ", COLOR_ERROR_RED);
+    shell_write_idx(err.what, COLOR_ERROR_RED);
+
+    let rest = &src[err.at.min(src.len())..];
+    let word_len = rest
+        .iter()
+        .position(|&b| matches!(b, 0 | b' ' | b'\t' | b'\r' | b'\n'))
+        .unwrap_or(rest.len())
+        .min(32);
+    if word_len == 0 {
+        shell_write_idx(b" at end of line\n", COLOR_ERROR_RED);
+    } else {
+        shell_write_idx(b" near '", COLOR_ERROR_RED);
+        shell_write_idx(&rest[..word_len], COLOR_ERROR_RED);
+        shell_write_idx(b"'\n", COLOR_ERROR_RED);
+    }
+}
+
+/// A `for` loop's words, NUL-terminated.
+type Words = [[u8; SHELL_MAX_TOKEN_LENGTH]; SHELL_MAX_TOKENS];
+
+/// Expand `text` and split it into `tokens`, which point into the shared
+/// token storage until the next command is split.  `None` after reporting
+/// an expansion that does not fit.
+fn tokenize(text: &[u8], tokens: &mut [*const u8; SHELL_MAX_TOKENS]) -> Option<usize> {
+    buffers::with_expand_buf(|buf| {
+        let len = expand_variables(text, text.len(), buf);
+        let mut words = [0u8; EXPAND_BUF_SIZE];
+        match expand_words(&buf[..len], &mut words) {
+            Ok(len) => Some(shell_parse_line(&words[..len], tokens).max(0) as usize),
+            Err(reason) => {
+                shell_write_idx(b"shell: ", COLOR_ERROR_RED);
+                shell_write_idx(reason, COLOR_ERROR_RED);
+                shell_write_idx(b"\n", COLOR_ERROR_RED);
+                None
+            }
+        }
+    })
+}
+
+/// Run one simple command and record its status as `$?`.
+fn run_simple(text: &[u8]) -> i32 {
+    let mut tokens = [ptr::null(); SHELL_MAX_TOKENS];
+    let Some(argc) = tokenize(text, &mut tokens) else {
+        super::set_last_exit_code(1);
+        return 1;
+    };
+    let status = if argc == 0 || assign(&tokens[..argc]) {
+        0
+    } else if let Some(index) = find_function(arg_bytes(tokens[0])) {
+        call(index, &tokens[..argc])
+    } else {
+        let status = exec::execute_tokens(argc as i32, &tokens);
+        if status == 127 {
+            shell_write_idx(UNKNOWN_CMD, COLOR_ERROR_RED);
+        }
+        status
+    };
+    super::set_last_exit_code(status);
+    status
+}
+
+/// Set the variables of a command made only of `NAME=value` words.  False,
+/// having set nothing, for any other command.
+fn assign(argv: &[*const u8]) -> bool {
+    let split = |arg: *const u8| {
+        let word = arg_bytes(arg);
+        let eq = word.iter().position(|&b| b == b'=')?;
+        is_name(&word[..eq]).then(|| (&word[..eq], &word[eq + 1..]))
+    };
+    if !argv.iter().all(|&arg| split(arg).is_some()) {
+        return false;
+    }
+    for (name, value) in argv.iter().filter_map(|&arg| split(arg)) {
+        env::set(name, value);
+    }
+    true
+}
+
+fn find_function(name: &[u8]) -> Option<usize> {
+    let functions = unsafe { &*FUNCTIONS.get() };
+    functions.iter().position(|slot| {
+        slot.as_ref()
+            .is_some_and(|function| &function.name[..function.name_len] == name)
+    })
+}
+
+fn define_function(name: &[u8], body: &[u8]) -> Result<(), &'static [u8]> {
+    if name.len() > FUNCTION_NAME_MAX {
+        return Err(b"function name too long\n");
+    }
+    if body.len() > FUNCTION_BODY_MAX {
+        return Err(b"function body too long\n");
+    }
+    let functions = unsafe { &mut *FUNCTIONS.get() };
+    let Some(slot) = find_function(name).or_else(|| functions.iter().position(Option::is_none))
+    else {
+        return Err(b"too many functions\n");
+    };
+    let mut function = Function {
+        name: [0; FUNCTION_NAME_MAX],
+        name_len: name.len(),
+        body: [0; FUNCTION_BODY_MAX],
+        body_len: body.len(),
+    };
+    function.name[..name.len()].copy_from_slice(name);
+    function.body[..body.len()].copy_from_slice(body);
+    functions[slot] = Some(function);
+    Ok(())
+}
+
+/// Run function `index` with `argv[1..]` as its `$1`....
+fn call(index: usize, argv: &[*const u8]) -> i32 {
+    let depth = unsafe { *CALL_DEPTH.get() };
+    if depth >= MAX_CALL_DEPTH {
+        shell_write_idx(b"function calls nested too deeply\n", COLOR_ERROR_RED);
+        return 1;
+    }
+    // A copy, so the function may redefine itself while it runs.
+    let Some(function) = (unsafe { &*FUNCTIONS.get() })[index] else {
+        return 1;
+    };
+    let saved_params = unsafe { *PARAMS.get() };
+    let saved_loops = loop_depth();
+    set_args(&argv[1..]);
+    unsafe {
+        *CALL_DEPTH.get() = depth + 1;
+        *LOOP_DEPTH.get() = 0;
+    }
+
+    let status = run_source(&function.body[..function.body_len], b"");
+
+    unsafe {
+        *CALL_DEPTH.get() = depth;
+        *LOOP_DEPTH.get() = saved_loops;
+        *PARAMS.get() = saved_params;
+    }
+    // `return` stops here, and so does a loop control left over.
+    if flow() != Flow::Exit {
+        set_flow(Flow::Normal);
+    }
+    status
+}
+
+/// After a pass of a loop body: whether to go round again.  Takes the
+/// `break` or `continue` aimed at this loop.
+fn next_pass() -> bool {
+    match flow() {
+        Flow::Normal => true,
+        Flow::Break(n) => {
+            set_flow(if n > 1 {
+                Flow::Break(n - 1)
+            } else {
+                Flow::Normal
+            });
+            false
+        }
+        Flow::Continue(n) if n > 1 => {
+            set_flow(Flow::Continue(n - 1));
+            false
+        }
+        Flow::Continue(_) => {
+            set_flow(Flow::Normal);
+            true
+        }
+        Flow::Return | Flow::Exit => false,
+    }
+}
+
+/// Run `f` as the body of a loop, for `break` and `continue`.
+fn in_loop<T>(f: impl FnOnce() -> T) -> T {
+    unsafe { *LOOP_DEPTH.get() += 1 };
+    let result = f();
+    unsafe { *LOOP_DEPTH.get() -= 1 };
+    result
+}
+
+struct Script<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Script<'a> {
+    fn byte(&self, at: usize) -> u8 {
+        self.src.get(at).copied().unwrap_or(0)
+    }
+
+    fn peek(&self) -> u8 {
+        self.byte(self.pos)
+    }
+
+    fn at_end(&self) -> bool {
+        self.peek() == 0
+    }
+
+    fn error<T>(&self, what: &'static [u8]) -> Parse<T> {
+        Err(SyntaxError { what, at: self.pos })
+    }
+
+    fn run(&mut self) -> Parse<i32> {
+        let status = self.list(true)?;
+        if !self.at_end() {
+            return self.error(b"unexpected word");
+        }
+        Ok(status)
+    }
+
+    /// Skip spaces, tabs, line continuations and a comment running to the
+    /// end of the line.
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.peek() {
+                b' ' | b'\t' | b'\r' => self.pos += 1,
+                b'\\' if self.byte(self.pos + 1) == b'\n' => self.pos += 2,
+                b'#' => {
+                    while !matches!(self.peek(), 0 | b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        loop {
+            self.skip_blanks();
+            if self.peek() != b'\n' {
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Skip blanks, newlines and `;` between commands.
+    fn skip_separators(&mut self) {
+        loop {
+            self.skip_blanks();
+            if !matches!(self.peek(), b'\n' | b';') {
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// The word at the cursor as written, to spot reserved words and names.
+    fn word(&self) -> &'a [u8] {
+        let src = self.src;
+        let start = self.pos.min(src.len());
+        let len = src[start..]
+            .iter()
+            .position(|b| WORD_END.contains(b))
+            .unwrap_or(src.len() - start);
+        &src[start..start + len]
+    }
+
+    /// Move past the reserved word `word`, after any separators.
+    fn keyword(&mut self, word: &[u8], what: &'static [u8]) -> Parse<()> {
+        self.skip_separators();
+        if self.word() != word {
+            return self.error(what);
+        }
+        self.pos += word.len();
+        Ok(())
+    }
+
+    /// Commands up to the end of the source or a word in [`LIST_END`].
+    /// Returns the status of the last one run.
+    fn list(&mut self, run: bool) -> Parse<i32> {
+        let mut status = 0;
+        loop {
+            self.skip_separators();
+            if self.at_end() || LIST_END.contains(&self.word()) {
+                return Ok(status);
+            }
+            if run {
+                // Parse the whole command first so a syntax error in it
+                // stops it before any part has run.
+                let start = self.pos;
+                self.and_or(false)?;
+                self.pos = start;
+            }
+            let and_or = self.and_or(run)?;
+            if run && flow() == Flow::Normal {
+                status = and_or;
+            } else if run {
+                // Keep the status of the `break`, `return` or `exit`.
+                status = super::last_exit_code();
+            }
+        }
+    }
+
+    /// Pipelines joined by `&&` and `||`.
+    fn and_or(&mut self, run: bool) -> Parse<i32> {
+        let mut status = self.pipeline(run)?;
+        loop {
+            self.skip_blanks();
+            let and = match (self.peek(), self.byte(self.pos + 1)) {
+                (b'&', b'&') => true,
+                (b'|', b'|') => false,
+                _ => return Ok(status),
+            };
+            self.pos += 2;
+            self.skip_newlines();
+            let next = run && (status == 0) == and;
+            let next_status = self.pipeline(next)?;
+            if next {
+                status = next_status;
+            }
+        }
+    }
+
+    /// A command, or `! command` to invert its status.
+    fn pipeline(&mut self, run: bool) -> Parse<i32> {
+        self.skip_blanks();
+        if self.word() != b"!" {
+            return self.command(run);
+        }
+        self.pos += 1;
+        self.skip_blanks();
+        let status = i32::from(self.command(run)? == 0);
+        if run && flow() == Flow::Normal {
+            super::set_last_exit_code(status);
+        }
+        Ok(status)
+    }
+
+    fn command(&mut self, run: bool) -> Parse<i32> {
+        let run = run && flow() == Flow::Normal;
+        let word = self.word();
+        let status = match word {
+            b"if" => self.if_clause(run)?,
+            b"while" => self.while_clause(run, false)?,
+            b"until" => self.while_clause(run, true)?,
+            b"for" => self.for_clause(run)?,
+            b"{" => self.group(run)?,
+            b"function" => {
+                self.pos += word.len();
+                self.skip_blanks();
+                self.function(run)?
+            }
+            _ if self.at_function() => self.function(run)?,
+            _ if LIST_END.contains(&word)
+                || (word.is_empty() && !matches!(self.peek(), b'<' | b'>')) =>
+            {
+                return self.error(b"expected a command");
+            }
+            _ => return self.simple(run),
+        };
+        if run {
+            super::set_last_exit_code(status);
+        }
+        Ok(status)
+    }
+
+    /// Copy the simple command at the cursor to `out`, without its line
+    /// continuations, and move past it.  Stops before `;`, a newline, `&&`,
+    /// `||` or a comment; a `&` that puts it in the background is kept.
+    fn command_text(&mut self, out: &mut [u8; EXPAND_BUF_SIZE]) -> Parse<usize> {
+        let mut len = 0;
+        let mut quote = 0u8;
+        loop {
+            let c = self.peek();
+            let next = self.byte(self.pos + 1);
+            let prev = if len > 0 { out[len - 1] } else { b' ' };
+            if c == 0 {
+                break;
+            }
+            if c == b'\\' && next == b'\n' && quote != b'\'' {
+                self.pos += 2;
+                continue;
+            }
+            let mut take = 1;
+            let mut last = false;
+            if quote == 0 {
+                match c {
+                    b';' | b'\n' => break,
+                    b'&' | b'|' if next == c => break,
+                    b'#' if matches!(prev, b' ' | b'\t') => break,
+                    b'&' if !matches!(prev, b'<' | b'>') && next != b'>' => last = true,
+                    b'\\' if next != 0 => take = 2,
+                    b'\'' | b'"' => quote = c,
+                    _ => {}
+                }
+            } else if c == quote {
+                quote = 0;
+            } else if c == b'\\' && quote == b'"' && next != 0 {
+                take = 2;
+            }
+            if len + take >= out.len() {
+                return self.error(b"command too long");
+            }
+            out[len..len + take].copy_from_slice(&self.src[self.pos..self.pos + take]);
+            len += take;
+            self.pos += take;
+            if last {
+                break;
+            }
+        }
+        while len > 0 && matches!(out[len - 1], b' ' | b'\t' | b'\r') {
+            len -= 1;
+        }
+        Ok(len)
+    }
+
+    fn simple(&mut self, run: bool) -> Parse<i32> {
+        let mut text = [0u8; EXPAND_BUF_SIZE];
+        let len = self.command_text(&mut text)?;
+        if !run {
+            return Ok(0);
+        }
+        Ok(run_simple(&text[..len]))
+    }
+
+    /// `if list; then list; [elif list; then list;]... [else list;] fi`
+    fn if_clause(&mut self, run: bool) -> Parse<i32> {
+        self.pos += 2;
+        let mut status = 0;
+        // Until a branch is taken, conditions still run.
+        let mut pending = run;
+        loop {
+            let condition = self.list(pending)?;
+            self.keyword(b"then", b"expected 'then'")?;
+            let take = pending && flow() == Flow::Normal && condition == 0;
+            let branch = self.list(take)?;
+            if take {
+                status = branch;
+                pending = false;
+            }
+            self.skip_separators();
+            match self.word() {
+                b"elif" => self.pos += 4,
+                b"else" => {
+                    self.pos += 4;
+                    let take = pending && flow() == Flow::Normal;
+                    let branch = self.list(take)?;
+                    if take {
+                        status = branch;
+                    }
+                    self.keyword(b"fi", b"expected 'fi'")?;
+                    return Ok(status);
+                }
+                b"fi" => {
+                    self.pos += 2;
+                    return Ok(status);
+                }
+                _ => return self.error(b"expected 'fi'"),
+            }
+        }
+    }
+
+    /// `while list; do list; done`, or `until` to loop while it fails.
+    fn while_clause(&mut self, run: bool, until: bool) -> Parse<i32> {
+        // "while" and "until" are both five bytes.
+        self.pos += 5;
+        let start = self.pos;
+        in_loop(|| {
+            let mut status = 0;
+            loop {
+                self.pos = start;
+                let condition = self.list(run)?;
+                self.keyword(b"do", b"expected 'do'")?;
+                let pass = run && flow() == Flow::Normal && (condition == 0) != until;
+                let body = self.list(pass)?;
+                self.keyword(b"done", b"expected 'done'")?;
+                if !run {
+                    return Ok(status);
+                }
+                if pass {
+                    status = body;
+                } else if flow() == Flow::Normal {
+                    return Ok(status);
+                }
+                if !next_pass() {
+                    return Ok(status);
+                }
+            }
+        })
+    }
+
+    /// `for NAME [in WORDS...]; do list; done`.  Without `in` it goes over
+    /// the positional parameters.
+    fn for_clause(&mut self, run: bool) -> Parse<i32> {
+        self.pos += 3;
+        self.skip_blanks();
+        let name = self.word();
+        if !is_name(name) {
+            return self.error(b"expected a variable name");
+        }
+        self.pos += name.len();
+        self.skip_blanks();
+
+        let mut words: Words = [[0; SHELL_MAX_TOKEN_LENGTH]; SHELL_MAX_TOKENS];
+        let mut count = 0;
+        if self.word() == b"in" {
+            self.pos += 2;
+            let mut text = [0u8; EXPAND_BUF_SIZE];
+            let len = self.command_text(&mut text)?;
+            if run {
+                let mut tokens = [ptr::null(); SHELL_MAX_TOKENS];
+                count = tokenize(&text[..len], &mut tokens).unwrap_or(0);
+                for (word, &token) in words.iter_mut().zip(&tokens[..count]) {
+                    let bytes = arg_bytes(token);
+                    word[..bytes.len()].copy_from_slice(bytes);
+                }
+            }
+        } else if run {
+            count = param_count();
+            for (index, word) in words.iter_mut().enumerate().take(count) {
+                let (value, len) = param(index + 1);
+                word[..len.min(SHELL_MAX_TOKEN_LENGTH - 1)]
+                    .copy_from_slice(&value[..len.min(SHELL_MAX_TOKEN_LENGTH - 1)]);
+            }
+        }
+        self.keyword(b"do", b"expected 'do'")?;
+        let body = self.pos;
+
+        in_loop(|| {
+            let mut status = 0;
+            if !run || count == 0 {
+                self.list(false)?;
+                self.keyword(b"done", b"expected 'done'")?;
+                return Ok(status);
+            }
+            for word in &words[..count] {
+                let len = word.iter().position(|&b| b == 0).unwrap_or(word.len());
+                env::set(name, &word[..len]);
+                self.pos = body;
+                status = self.list(true)?;
+                self.keyword(b"done", b"expected 'done'")?;
+                if !next_pass() {
+                    break;
+                }
+            }
+            Ok(status)
+        })
+    }
+
+    /// `{ list; }`
+    fn group(&mut self, run: bool) -> Parse<i32> {
+        self.pos += 1;
+        let status = self.list(run)?;
+        self.keyword(b"}", b"expected '}'")?;
+        Ok(status)
+    }
+
+    /// Whether the cursor is at `NAME()`.
+    fn at_function(&self) -> bool {
+        let name = self.word();
+        if !is_name(name) {
+            return false;
+        }
+        let mut at = self.pos + name.len();
+        while matches!(self.byte(at), b' ' | b'\t') {
+            at += 1;
+        }
+        self.byte(at) == b'(' && self.byte(at + 1) == b')'
+    }
+
+    /// `NAME() { list; }`, after any `function` keyword.
+    fn function(&mut self, run: bool) -> Parse<i32> {
+        let name = self.word();
+        if !is_name(name) {
+            return self.error(b"expected a function name");
+        }
+        self.pos += name.len();
+        self.skip_blanks();
+        if self.peek() == b'(' {
+            self.pos += 1;
+            self.skip_blanks();
+            if self.peek() != b')' {
+                return self.error(b"expected ')'");
+            }
+            self.pos += 1;
+        }
+        self.skip_newlines();
+        if self.word() != b"{" {
+            return self.error(b"expected '{'");
+        }
+        self.pos += 1;
+        let start = self.pos;
+        self.list(false)?;
+        self.skip_separators();
+        let end = self.pos;
+        self.keyword(b"}", b"expected '}'")?;
+        if !run {
+            return Ok(0);
+        }
+        match define_function(name, &self.src[start..end]) {
+            Ok(()) => Ok(0),
+            Err(reason) => {
+                shell_write_idx(reason, COLOR_ERROR_RED);
+                Ok(1)
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Gap buffer: the text with a hole at the last edit, so typing and
+//! deleting there are cheap and moving the hole costs the distance moved.
+//! Storage comes from the userland heap and doubles as it fills.
+
+use core::ffi::c_void;
+use core::ptr;
+
+use crate::libc;
+
+/// Capacity of the first allocation.
+const INITIAL_CAPACITY: usize = 4096;
+
+pub struct GapBuffer {
+    data: *mut u8,
+    capacity: usize,
+    gap_start: usize,
+    gap_end: usize,
+}
+
+impl GapBuffer {
+    pub const fn new() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            capacity: 0,
+            gap_start: 0,
+            gap_end: 0,
+        }
+    }
+
+    #[inline]
+    fn gap_len(&self) -> usize {
+        self.gap_end - self.gap_start
+    }
+
+    /// Bytes of text held.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.capacity - self.gap_len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The byte at `pos`; `pos` must be below [`GapBuffer::len`].
+    #[inline]
+    pub fn byte_at(&self, pos: usize) -> u8 {
+        debug_assert!(pos < self.len());
+        let index = if pos < self.gap_start {
+            pos
+        } else {
+            pos + self.gap_len()
+        };
+        unsafe { *self.data.add(index) }
+    }
+
+    /// The text before and after the gap.
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        if self.data.is_null() {
+            return (&[], &[]);
+        }
+        unsafe {
+            (
+                core::slice::from_raw_parts(self.data, self.gap_start),
+                core::slice::from_raw_parts(
+                    self.data.add(self.gap_end),
+                    self.capacity - self.gap_end,
+                ),
+            )
+        }
+    }
+
+    /// Copy the text in `start..end` into `out`, as much as fits.  Returns
+    /// the bytes copied.
+    pub fn copy_range(&self, start: usize, end: usize, out: &mut [u8]) -> usize {
+        let end = end.min(self.len()).min(start + out.len());
+        for (i, pos) in (start..end).enumerate() {
+            out[i] = self.byte_at(pos);
+        }
+        end.saturating_sub(start)
+    }
+
+    /// Move the gap to start at text position `pos`.
+    fn move_gap(&mut self, pos: usize) {
+        let gap = self.gap_len();
+        unsafe {
+            if pos < self.gap_start {
+                let count = self.gap_start - pos;
+                ptr::copy(self.data.add(pos), self.data.add(pos + gap), count);
+            } else if pos > self.gap_start {
+                let count = pos - self.gap_start;
+                ptr::copy(
+                    self.data.add(self.gap_end),
+                    self.data.add(self.gap_start),
+                    count,
+                );
+            }
+        }
+        self.gap_start = pos;
+        self.gap_end = pos + gap;
+    }
+
+    /// Make the gap at least `extra` bytes.  False if the heap is full.
+    fn reserve(&mut self, extra: usize) -> bool {
+        if self.gap_len() >= extra {
+            return true;
+        }
+        let needed = self.len() + extra;
+        let mut capacity = self.capacity.max(INITIAL_CAPACITY);
+        while capacity < needed {
+            capacity *= 2;
+        }
+        let data = libc::realloc(self.data as *mut c_void, capacity) as *mut u8;
+        if data.is_null() {
+            return false;
+        }
+        // The text after the gap moves to the end of the new space.
+        let tail = self.capacity - self.gap_end;
+        unsafe {
+            ptr::copy(data.add(self.gap_end), data.add(capacity - tail), tail);
+        }
+        self.data = data;
+        self.gap_end = capacity - tail;
+        self.capacity = capacity;
+        true
+    }
+
+    /// Insert `bytes` at `pos`.  False, with the text unchanged, if the heap
+    /// is full.
+    pub fn insert(&mut self, pos: usize, bytes: &[u8]) -> bool {
+        if bytes.is_empty() {
+            return true;
+        }
+        if !self.reserve(bytes.len()) {
+            return false;
+        }
+        self.move_gap(pos.min(self.len()));
+        unsafe {
+            ptr::copy_nonoverlapping(bytes.as_ptr(), self.data.add(self.gap_start), bytes.len());
+        }
+        self.gap_start += bytes.len();
+        true
+    }
+
+    /// Remove the text in `start..end`.
+    pub fn delete(&mut self, start: usize, end: usize) {
+        let end = end.min(self.len());
+        if start >= end {
+            return;
+        }
+        self.move_gap(start);
+        self.gap_end += end - start;
+    }
+
+    /// Remove all text, keeping the storage.
+    pub fn clear(&mut self) {
+        self.gap_start = 0;
+        self.gap_end = self.capacity;
+    }
+}
+
+impl Drop for GapBuffer {
+    fn drop(&mut self) {
+        libc::dealloc(self.data as *mut c_void);
+    }
+}
@@ -0,0 +1,907 @@
+//! slopedit: a windowed text editor.
+//!
+//! `slopedit [path]` opens `path`, or starts it empty if it does not exist
+//! yet.  The text lives in a [`GapBuffer`] on the userland heap.  Edits and
+//! cursor moves mark the lines they touch, and only those rows are drawn
+//! again and presented as damage.
+//!
+//! Keys: arrows, Home and End (with Ctrl, the start and end of the text)
+//! and Page Up/Down move, with Shift they select.  Ctrl+A selects all,
+//! Ctrl+S saves, Ctrl+O opens another file and Ctrl+Q quits; with unsaved
+//! changes the last two want a second press.  Ctrl+C, Ctrl+X and Ctrl+V
+//! use the clipboard.  The mouse places the cursor, drags a selection and
+//! scrolls.
+
+mod gap_buffer;
+
+use core::ffi::{CStr, c_char};
+
+use slopos_abi::draw::Color32;
+use slopos_abi::input::{
+    CLIPBOARD_MAX_SIZE, KEY_MOD_CTRL, KEY_MOD_SHIFT, SCANCODE_DELETE, SCANCODE_DOWN, SCANCODE_END,
+    SCANCODE_HOME, SCANCODE_LEFT, SCANCODE_PAGE_DOWN, SCANCODE_PAGE_UP, SCANCODE_RIGHT,
+    SCANCODE_UP,
+};
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::appkit::{self, ControlFlow, Event, Window, WindowedApp};
+use crate::apps::cli::arg_at;
+use crate::gfx::font::{FONT_CHAR_HEIGHT, FONT_CHAR_WIDTH};
+use crate::gfx::{self, DrawBuffer};
+use crate::syscall::{FdGuard, USER_FS_OPEN_CREAT, USER_FS_OPEN_READ, USER_FS_OPEN_WRITE, fs};
+use crate::theme::*;
+
+pub use gap_buffer::GapBuffer;
+
+const EDIT_WIDTH: u32 = 640;
+const EDIT_HEIGHT: u32 = 420;
+const TEXT_X: i32 = 4;
+const STATUS_HEIGHT: i32 = FONT_CHAR_HEIGHT + 4;
+const TAB_WIDTH: usize = 4;
+/// Longest path, without the NUL.
+const PATH_MAX: usize = 255;
+const READ_CHUNK: usize = 4096;
+/// Lines moved per wheel detent.
+const SCROLL_LINES: usize = 3;
+/// Longest indentation carried onto a new line.
+const MAX_INDENT: usize = 64;
+const BUTTON_LEFT: u8 = 0x01;
+
+const CTRL_A: u8 = 0x01;
+const BACKSPACE: u8 = 0x08;
+const CTRL_O: u8 = 0x0F;
+const CTRL_Q: u8 = 0x11;
+const CTRL_S: u8 = 0x13;
+const ESCAPE: u8 = 0x1B;
+const DEL: u8 = 0x7F;
+
+const COLOR_EDIT_BG: Color32 = Color32::rgb(0x1E, 0x1E, 0x1E);
+const COLOR_SELECTION: Color32 = Color32::rgb(0x26, 0x4F, 0x78);
+const COLOR_STATUS_BG: Color32 = COLOR_TITLE_BAR_FOCUSED;
+const COLOR_HINT: Color32 = Color32::rgb(0x90, 0x90, 0x90);
+const COLOR_ERROR: Color32 = Color32::rgb(0xE8, 0x11, 0x23);
+
+/// What the next draw has to paint again.
+#[derive(Clone, Copy, Default)]
+struct Dirty {
+    /// Everything, e.g. after scrolling or a resize.
+    all: bool,
+    /// Text lines `first..=last`.
+    lines: Option<(usize, usize)>,
+    status: bool,
+}
+
+impl Dirty {
+    const ALL: Self = Self {
+        all: true,
+        lines: None,
+        status: true,
+    };
+
+    fn add_lines(&mut self, first: usize, last: usize) {
+        self.lines = Some(match self.lines {
+            Some((a, b)) => (a.min(first), b.max(last)),
+            None => (first, last),
+        });
+    }
+
+    fn covers(&self, line: usize) -> bool {
+        self.all || self.lines.is_some_and(|(a, b)| (a..=b).contains(&line))
+    }
+
+    fn is_clean(&self) -> bool {
+        !self.all && self.lines.is_none() && !self.status
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    Open,
+    SaveAs,
+}
+
+/// A path being typed into the status bar.
+struct Prompt {
+    kind: PromptKind,
+    input: [u8; PATH_MAX],
+    len: usize,
+}
+
+/// The status bar's text.
+struct StatusText {
+    buf: [u8; 160],
+    len: usize,
+}
+
+impl StatusText {
+    const fn new() -> Self {
+        Self {
+            buf: [0; 160],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let take = bytes.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&bytes[..take]);
+        self.len += take;
+    }
+
+    fn push_number(&mut self, value: usize) {
+        let mut num = NumBuf::<21>::new();
+        self.push(numfmt::trim_nul(num.format_u64(value as u64)));
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+pub struct Editor {
+    text: GapBuffer,
+    cursor: usize,
+    /// Other end of the selection; the cursor itself when nothing is
+    /// selected.
+    anchor: usize,
+    /// Column kept while moving up and down through shorter lines.
+    goal_col: Option<usize>,
+    top_line: usize,
+    left_col: usize,
+    /// Text rows and columns that fit the window.
+    rows: usize,
+    cols: usize,
+    /// NUL-terminated; empty until the text has a file.
+    path: [u8; PATH_MAX + 1],
+    path_len: usize,
+    modified: bool,
+    title_stale: bool,
+    prompt: Option<Prompt>,
+    /// Shown in the status bar until the next key; true for errors.
+    message: Option<(&'static str, bool)>,
+    /// Ctrl key pressed once with unsaved changes; pressing it again goes
+    /// ahead.
+    armed: Option<u8>,
+    dragging: bool,
+    dirty: Dirty,
+    drawn_size: (i32, i32),
+}
+
+impl Editor {
+    fn new() -> Self {
+        Self {
+            text: GapBuffer::new(),
+            cursor: 0,
+            anchor: 0,
+            goal_col: None,
+            top_line: 0,
+            left_col: 0,
+            rows: 1,
+            cols: 1,
+            path: [0; PATH_MAX + 1],
+            path_len: 0,
+            modified: false,
+            title_stale: true,
+            prompt: None,
+            message: None,
+            armed: None,
+            dragging: false,
+            dirty: Dirty::ALL,
+            drawn_size: (0, 0),
+        }
+    }
+
+    // --- Text queries ---
+
+    fn line_start(&self, pos: usize) -> usize {
+        let mut p = pos;
+        while p > 0 && self.text.byte_at(p - 1) != b'\n' {
+            p -= 1;
+        }
+        p
+    }
+
+    fn line_end(&self, pos: usize) -> usize {
+        let mut p = pos;
+        while p < self.text.len() && self.text.byte_at(p) != b'\n' {
+            p += 1;
+        }
+        p
+    }
+
+    /// Line number of `pos`, from 0.
+    fn line_of(&self, pos: usize) -> usize {
+        (0..pos).filter(|&p| self.text.byte_at(p) == b'\n').count()
+    }
+
+    /// Offset of the start of `line`, or `None` past the last line.
+    fn start_of_line(&self, line: usize) -> Option<usize> {
+        if line == 0 {
+            return Some(0);
+        }
+        let mut seen = 0;
+        for p in 0..self.text.len() {
+            if self.text.byte_at(p) == b'\n' {
+                seen += 1;
+                if seen == line {
+                    return Some(p + 1);
+                }
+            }
+        }
+        None
+    }
+
+    /// Screen column of `pos`, with tabs expanded.
+    fn column(&self, pos: usize) -> usize {
+        (self.line_start(pos)..pos).fold(0, |col, p| advance(col, self.text.byte_at(p)))
+    }
+
+    /// The offset in the line starting at `start` closest to `col`.
+    fn pos_at_column(&self, start: usize, col: usize) -> usize {
+        let end = self.line_end(start);
+        let mut c = 0;
+        for p in start..end {
+            let next = advance(c, self.text.byte_at(p));
+            if next > col {
+                return if col - c > (next - c) / 2 { p + 1 } else { p };
+            }
+            c = next;
+        }
+        end
+    }
+
+    fn selection(&self) -> (usize, usize) {
+        (self.cursor.min(self.anchor), self.cursor.max(self.anchor))
+    }
+
+    fn has_selection(&self) -> bool {
+        self.cursor != self.anchor
+    }
+
+    /// Lines the cursor and selection cover.
+    fn cursor_lines(&self) -> (usize, usize) {
+        let (lo, hi) = self.selection();
+        let first = self.line_of(lo);
+        let last = first + (lo..hi).filter(|&p| self.text.byte_at(p) == b'\n').count();
+        (first, last)
+    }
+
+    fn file_name(&self) -> &[u8] {
+        let path = &self.path[..self.path_len];
+        match path.iter().rposition(|&b| b == b'/') {
+            Some(slash) => &path[slash + 1..],
+            None => path,
+        }
+    }
+
+    // --- Moving ---
+
+    /// Put the cursor at `pos`, selecting from the anchor if `select`.
+    fn move_to(&mut self, pos: usize, select: bool) {
+        let (first, last) = self.cursor_lines();
+        self.dirty.add_lines(first, last);
+        self.cursor = pos.min(self.text.len());
+        if !select {
+            self.anchor = self.cursor;
+        }
+        self.goal_col = None;
+        let (first, last) = self.cursor_lines();
+        self.dirty.add_lines(first, last);
+        self.scroll_to_cursor();
+    }
+
+    fn move_lines(&mut self, delta: isize, select: bool) {
+        let goal = self.goal_col.unwrap_or_else(|| self.column(self.cursor));
+        let last_line = self.line_of(self.text.len());
+        let line = self.line_of(self.cursor).saturating_add_signed(delta);
+        let start = self.start_of_line(line.min(last_line)).unwrap_or(0);
+        let pos = self.pos_at_column(start, goal);
+        self.move_to(pos, select);
+        self.goal_col = Some(goal);
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        let line = self.line_of(self.cursor);
+        let col = self.column(self.cursor);
+        if line < self.top_line {
+            self.top_line = line;
+            self.dirty.all = true;
+        } else if line >= self.top_line + self.rows {
+            self.top_line = line + 1 - self.rows;
+            self.dirty.all = true;
+        }
+        if col < self.left_col {
+            self.left_col = col;
+            self.dirty.all = true;
+        } else if col >= self.left_col + self.cols {
+            self.left_col = col + 1 - self.cols;
+            self.dirty.all = true;
+        }
+        self.dirty.status = true;
+    }
+
+    fn scroll_by(&mut self, lines: isize) {
+        let last_line = self.line_of(self.text.len());
+        let top = self.top_line.saturating_add_signed(lines).min(last_line);
+        if top != self.top_line {
+            self.top_line = top;
+            self.dirty.all = true;
+        }
+    }
+
+    /// The text offset under window point `x, y`.
+    fn pos_at_point(&self, x: i32, y: i32) -> usize {
+        let row = (y.max(0) / FONT_CHAR_HEIGHT) as usize;
+        let last_line = self.line_of(self.text.len());
+        let line = (self.top_line + row).min(last_line);
+        let start = self.start_of_line(line).unwrap_or(0);
+        let col = ((x - TEXT_X).max(0) + FONT_CHAR_WIDTH / 2) / FONT_CHAR_WIDTH;
+        self.pos_at_column(start, self.left_col + col as usize)
+    }
+
+    // --- Editing ---
+
+    /// Replace the selection, or insert at the cursor, with `bytes`.
+    fn replace_selection(&mut self, bytes: &[u8]) {
+        let (lo, hi) = self.selection();
+        let (first, _) = self.cursor_lines();
+        let joins_lines = (lo..hi).any(|p| self.text.byte_at(p) == b'\n');
+        self.text.delete(lo, hi);
+        let inserted = if self.text.insert(lo, bytes) {
+            bytes.len()
+        } else {
+            self.message = Some(("out of memory", true));
+            0
+        };
+        self.cursor = lo + inserted;
+        self.anchor = self.cursor;
+        self.goal_col = None;
+        if joins_lines || bytes[..inserted].contains(&b'\n') {
+            self.dirty.add_lines(first, usize::MAX);
+        } else {
+            self.dirty.add_lines(first, first);
+        }
+        self.set_modified(true);
+        self.scroll_to_cursor();
+    }
+
+    fn backspace(&mut self) {
+        if !self.has_selection() {
+            if self.cursor == 0 {
+                return;
+            }
+            self.anchor = self.cursor - 1;
+        }
+        self.replace_selection(b"");
+    }
+
+    fn delete_forward(&mut self) {
+        if !self.has_selection() {
+            if self.cursor == self.text.len() {
+                return;
+            }
+            self.anchor = self.cursor + 1;
+        }
+        self.replace_selection(b"");
+    }
+
+    /// Break the line, carrying its indentation onto the new one.
+    fn newline(&mut self) {
+        let mut buf = [0u8; MAX_INDENT + 1];
+        buf[0] = b'\n';
+        let mut len = 1;
+        let start = self.line_start(self.cursor);
+        for p in start..self.cursor.min(start + MAX_INDENT) {
+            match self.text.byte_at(p) {
+                b @ (b' ' | b'\t') => {
+                    buf[len] = b;
+                    len += 1;
+                }
+                _ => break,
+            }
+        }
+        self.replace_selection(&buf[..len]);
+    }
+
+    fn select_all(&mut self) {
+        self.anchor = 0;
+        self.cursor = self.text.len();
+        self.dirty.all = true;
+        self.scroll_to_cursor();
+    }
+
+    fn copy(&mut self, cut: bool) {
+        if !self.has_selection() {
+            return;
+        }
+        let (lo, hi) = self.selection();
+        let mut buf = [0u8; CLIPBOARD_MAX_SIZE];
+        let len = self.text.copy_range(lo, hi, &mut buf);
+        if appkit::clipboard::set_text(&buf[..len]).is_err() {
+            self.message = Some(("clipboard unavailable", true));
+            self.dirty.status = true;
+            return;
+        }
+        if cut {
+            self.replace_selection(b"");
+        }
+    }
+
+    fn paste(&mut self) {
+        let mut buf = [0u8; CLIPBOARD_MAX_SIZE];
+        let len = appkit::clipboard::get_text(&mut buf);
+        if len != 0 {
+            self.replace_selection(&buf[..len]);
+        }
+    }
+
+    fn set_modified(&mut self, modified: bool) {
+        if self.modified != modified {
+            self.modified = modified;
+            self.title_stale = true;
+        }
+    }
+
+    // --- Files ---
+
+    fn set_path(&mut self, path: &[u8]) {
+        let len = path.len().min(PATH_MAX);
+        self.path = [0; PATH_MAX + 1];
+        self.path[..len].copy_from_slice(&path[..len]);
+        self.path_len = len;
+        self.title_stale = true;
+    }
+
+    /// Replace the text with the file at `path`.  A path that does not
+    /// exist yet starts an empty file there.
+    fn load(&mut self, path: &[u8]) {
+        if path.len() > PATH_MAX {
+            self.message = Some(("path too long", true));
+            self.dirty.status = true;
+            return;
+        }
+        self.set_path(path);
+        self.text.clear();
+        self.cursor = 0;
+        self.anchor = 0;
+        self.goal_col = None;
+        self.top_line = 0;
+        self.left_col = 0;
+        self.set_modified(false);
+        self.dirty = Dirty::ALL;
+
+        let cpath = self.path.as_ptr() as *const c_char;
+        let mut stat = Default::default();
+        if fs::stat_path(cpath, &mut stat).is_err() {
+            self.message = Some(("new file", false));
+            return;
+        }
+        if stat.is_directory() {
+            self.message = Some(("is a directory", true));
+            return;
+        }
+        let Ok(fd) = fs::open_path(cpath, USER_FS_OPEN_READ) else {
+            self.message = Some(("cannot open file", true));
+            return;
+        };
+        let file = unsafe { FdGuard::from_raw(fd) };
+        let mut chunk = [0u8; READ_CHUNK];
+        loop {
+            match file.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if !self.text.insert(self.text.len(), &chunk[..n]) {
+                        self.message = Some(("file too large, loaded in part", true));
+                        return;
+                    }
+                }
+                Err(_) => {
+                    self.message = Some(("read error, loaded in part", true));
+                    return;
+                }
+            }
+        }
+    }
+
+    fn write_file(&self) -> Result<(), &'static str> {
+        let path = CStr::from_bytes_until_nul(&self.path).map_err(|_| "bad path")?;
+        let _ = fs::unlink_path(path.as_ptr());
+        let file = FdGuard::open(path, USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT)
+            .map_err(|_| "cannot create file")?;
+        let (front, back) = self.text.as_slices();
+        for mut part in [front, back] {
+            while !part.is_empty() {
+                match file.write(part) {
+                    Ok(0) | Err(_) => return Err("write failed"),
+                    Ok(n) => part = &part[n..],
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) {
+        if self.path_len == 0 {
+            self.open_prompt(PromptKind::SaveAs);
+            return;
+        }
+        self.message = Some(match self.write_file() {
+            Ok(()) => {
+                self.set_modified(false);
+                ("saved", false)
+            }
+            Err(err) => (err, true),
+        });
+        self.dirty.status = true;
+    }
+
+    /// True if `key` may go ahead: nothing is unsaved, or it was pressed
+    /// a second time.
+    fn confirm_discard(&mut self, key: u8) -> bool {
+        if !self.modified || self.armed == Some(key) {
+            return true;
+        }
+        self.armed = Some(key);
+        self.message = Some(("unsaved changes; press again to discard", true));
+        self.dirty.status = true;
+        false
+    }
+
+    // --- Prompt ---
+
+    fn open_prompt(&mut self, kind: PromptKind) {
+        let mut prompt = Prompt {
+            kind,
+            input: [0; PATH_MAX],
+            len: 0,
+        };
+        if kind == PromptKind::SaveAs {
+            prompt.input[..self.path_len].copy_from_slice(&self.path[..self.path_len]);
+            prompt.len = self.path_len;
+        }
+        self.prompt = Some(prompt);
+        self.dirty.status = true;
+    }
+
+    fn prompt_key(&mut self, ascii: u8) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        self.dirty.status = true;
+        match ascii {
+            ESCAPE => self.prompt = None,
+            BACKSPACE => prompt.len = prompt.len.saturating_sub(1),
+            b'\n' => {
+                let Some(prompt) = self.prompt.take() else {
+                    return;
+                };
+                let path = &prompt.input[..prompt.len];
+                if path.is_empty() {
+                    return;
+                }
+                match prompt.kind {
+                    PromptKind::Open => self.load(path),
+                    PromptKind::SaveAs => {
+                        self.set_path(path);
+                        self.save();
+                    }
+                }
+            }
+            0x20..DEL | 0xA0.. if prompt.len < PATH_MAX => {
+                prompt.input[prompt.len] = ascii;
+                prompt.len += 1;
+            }
+            _ => {}
+        }
+    }
+
+    // --- Input ---
+
+    fn on_key(&mut self, scancode: u8, ascii: u8, modifiers: u8) -> ControlFlow {
+        if self.message.take().is_some() {
+            self.dirty.status = true;
+        }
+        if self.prompt.is_some() {
+            self.prompt_key(ascii);
+            return ControlFlow::Continue;
+        }
+        let armed = self.armed.take();
+        let select = modifiers & KEY_MOD_SHIFT != 0;
+        let ctrl = modifiers & KEY_MOD_CTRL != 0;
+        match (scancode, ascii) {
+            (SCANCODE_LEFT, 0) => {
+                let (lo, _) = self.selection();
+                let pos = if self.has_selection() && !select {
+                    lo
+                } else {
+                    self.cursor.saturating_sub(1)
+                };
+                self.move_to(pos, select);
+            }
+            (SCANCODE_RIGHT, 0) => {
+                let (_, hi) = self.selection();
+                let pos = if self.has_selection() && !select {
+                    hi
+                } else {
+                    self.cursor + 1
+                };
+                self.move_to(pos, select);
+            }
+            (SCANCODE_UP, 0) => self.move_lines(-1, select),
+            (SCANCODE_DOWN, 0) => self.move_lines(1, select),
+            (SCANCODE_PAGE_UP, 0) => self.move_lines(-(self.rows as isize), select),
+            (SCANCODE_PAGE_DOWN, 0) => self.move_lines(self.rows as isize, select),
+            (SCANCODE_HOME, 0) => {
+                let pos = if ctrl {
+                    0
+                } else {
+                    self.line_start(self.cursor)
+                };
+                self.move_to(pos, select);
+            }
+            (SCANCODE_END, 0) => {
+                let pos = if ctrl {
+                    self.text.len()
+                } else {
+                    self.line_end(self.cursor)
+                };
+                self.move_to(pos, select);
+            }
+            (SCANCODE_DELETE, 0) => self.delete_forward(),
+            (_, BACKSPACE) => self.backspace(),
+            (_, b'\n') => self.newline(),
+            (_, CTRL_A) => self.select_all(),
+            (_, CTRL_S) => self.save(),
+            (_, CTRL_O) => {
+                self.armed = armed;
+                if self.confirm_discard(CTRL_O) {
+                    self.open_prompt(PromptKind::Open);
+                }
+            }
+            (_, CTRL_Q) => {
+                self.armed = armed;
+                if self.confirm_discard(CTRL_Q) {
+                    return ControlFlow::Exit;
+                }
+            }
+            (_, b'\t' | 0x20..DEL | 0xA0..) => self.replace_selection(&[ascii]),
+            _ => {}
+        }
+        ControlFlow::Continue
+    }
+
+    // --- Drawing ---
+
+    fn layout(&mut self, width: i32, height: i32) {
+        self.rows = (((height - STATUS_HEIGHT) / FONT_CHAR_HEIGHT).max(1)) as usize;
+        self.cols = (((width - TEXT_X) / FONT_CHAR_WIDTH).max(1)) as usize;
+        self.scroll_to_cursor();
+        self.dirty = Dirty::ALL;
+    }
+
+    fn update_title(&mut self, win: &Window) {
+        if !self.title_stale {
+            return;
+        }
+        self.title_stale = false;
+        let mut title = StatusText::new();
+        title.push(b"slopedit: ");
+        if self.path_len == 0 {
+            title.push(b"untitled");
+        } else {
+            title.push(self.file_name());
+        }
+        if self.modified {
+            title.push(b"*");
+        }
+        win.set_title(title.as_str());
+    }
+
+    fn draw_row(&self, fb: &mut DrawBuffer<'_>, row: usize, start: Option<usize>, width: i32) {
+        let y = row as i32 * FONT_CHAR_HEIGHT;
+        gfx::fill_rect(fb, 0, y, width, FONT_CHAR_HEIGHT, COLOR_EDIT_BG);
+        let Some(start) = start else {
+            return;
+        };
+        let (lo, hi) = self.selection();
+        let right = self.left_col + self.cols;
+        let cell_x = |col: usize| TEXT_X + (col - self.left_col) as i32 * FONT_CHAR_WIDTH;
+
+        let mut col = 0;
+        let mut pos = start;
+        while pos < self.text.len() && col < right {
+            let byte = self.text.byte_at(pos);
+            if byte == b'\n' {
+                break;
+            }
+            let next = advance(col, byte);
+            let selected = (lo..hi).contains(&pos);
+            let glyph = if byte == b'\t' { b' ' } else { byte };
+            if glyph != b' ' || selected {
+                let bg = if selected {
+                    COLOR_SELECTION
+                } else {
+                    COLOR_EDIT_BG
+                };
+                for c in col.max(self.left_col)..next.min(right) {
+                    gfx::font::draw_char(fb, cell_x(c), y, glyph, COLOR_TEXT, bg);
+                }
+            }
+            col = next;
+            pos += 1;
+        }
+        // A selected line break shows as one selected cell.
+        if (lo..hi).contains(&pos) && (self.left_col..right).contains(&col) {
+            gfx::fill_rect(
+                fb,
+                cell_x(col),
+                y,
+                FONT_CHAR_WIDTH,
+                FONT_CHAR_HEIGHT,
+                COLOR_SELECTION,
+            );
+        }
+
+        if self.line_start(self.cursor) == start {
+            let col = self.column(self.cursor);
+            if (self.left_col..right).contains(&col) {
+                gfx::fill_rect(fb, cell_x(col), y, 2, FONT_CHAR_HEIGHT, COLOR_CURSOR);
+            }
+        }
+    }
+
+    fn draw_status(&self, fb: &mut DrawBuffer<'_>, width: i32, height: i32) {
+        let y = height - STATUS_HEIGHT;
+        let text_y = y + (STATUS_HEIGHT - FONT_CHAR_HEIGHT) / 2;
+        gfx::fill_rect(fb, 0, y, width, STATUS_HEIGHT, COLOR_STATUS_BG);
+
+        let mut status = StatusText::new();
+        let mut color = COLOR_TEXT;
+        if let Some(prompt) = &self.prompt {
+            let label: &[u8] = match prompt.kind {
+                PromptKind::Open => b"Open: ",
+                PromptKind::SaveAs => b"Save as: ",
+            };
+            status.push(label);
+            status.push(&prompt.input[..prompt.len]);
+            status.push(b"_");
+        } else if let Some((message, error)) = self.message {
+            status.push(message.as_bytes());
+            if error {
+                color = COLOR_ERROR;
+            }
+        } else {
+            if self.path_len == 0 {
+                status.push(b"untitled");
+            } else {
+                status.push(&self.path[..self.path_len]);
+            }
+            if self.modified {
+                status.push(b" [modified]");
+            }
+            status.push(b"  Ln ");
+            status.push_number(self.line_of(self.cursor) + 1);
+            status.push(b", Col ");
+            status.push_number(self.column(self.cursor) + 1);
+        }
+        gfx::font::draw_string(fb, TEXT_X, text_y, status.as_str(), color, COLOR_STATUS_BG);
+
+        let hint = "^S save  ^O open  ^Q quit";
+        let hint_x = width - TEXT_X - hint.len() as i32 * FONT_CHAR_WIDTH;
+        if hint_x > TEXT_X + (status.len as i32 + 2) * FONT_CHAR_WIDTH {
+            gfx::font::draw_string(fb, hint_x, text_y, hint, COLOR_HINT, COLOR_STATUS_BG);
+        }
+    }
+}
+
+/// The column after `byte` drawn at `col`.
+fn advance(col: usize, byte: u8) -> usize {
+    if byte == b'\t' {
+        (col / TAB_WIDTH + 1) * TAB_WIDTH
+    } else {
+        col + 1
+    }
+}
+
+impl WindowedApp for Editor {
+    fn init(&mut self, win: &mut Window) {
+        self.update_title(win);
+        win.request_redraw();
+    }
+
+    fn on_event(&mut self, win: &mut Window, event: Event) -> ControlFlow {
+        let flow = match event {
+            Event::CloseRequest => {
+                if self.confirm_discard(0) {
+                    return ControlFlow::Exit;
+                }
+                ControlFlow::Continue
+            }
+            Event::KeyPress {
+                scancode,
+                ascii,
+                modifiers,
+            } => self.on_key(scancode, ascii, modifiers),
+            Event::Copy => {
+                self.copy(false);
+                ControlFlow::Continue
+            }
+            Event::Cut => {
+                self.copy(true);
+                ControlFlow::Continue
+            }
+            Event::Paste => {
+                self.paste();
+                ControlFlow::Continue
+            }
+            Event::PointerPress { button } => {
+                let (x, y) = win.pointer();
+                if button & BUTTON_LEFT != 0 && y < self.rows as i32 * FONT_CHAR_HEIGHT {
+                    let pos = self.pos_at_point(x, y);
+                    self.move_to(pos, false);
+                    self.dragging = true;
+                }
+                ControlFlow::Continue
+            }
+            Event::PointerMotion { x, y } => {
+                if self.dragging {
+                    let pos = self.pos_at_point(x, y);
+                    if pos != self.cursor {
+                        self.move_to(pos, true);
+                    }
+                }
+                ControlFlow::Continue
+            }
+            Event::PointerRelease { button } => {
+                if button & BUTTON_LEFT != 0 {
+                    self.dragging = false;
+                }
+                ControlFlow::Continue
+            }
+            Event::Scroll { dy, .. } => {
+                self.scroll_by(dy as isize * SCROLL_LINES as isize);
+                ControlFlow::Continue
+            }
+            _ => ControlFlow::Continue,
+        };
+        self.update_title(win);
+        if !self.dirty.is_clean() {
+            win.request_redraw();
+        }
+        flow
+    }
+
+    fn draw(&mut self, fb: &mut DrawBuffer<'_>) {
+        let width = fb.width() as i32;
+        let height = fb.height() as i32;
+        if self.drawn_size != (width, height) {
+            self.drawn_size = (width, height);
+            self.layout(width, height);
+        }
+        let dirty = core::mem::take(&mut self.dirty);
+        if dirty.all {
+            gfx::fill_rect(fb, 0, 0, width, height - STATUS_HEIGHT, COLOR_EDIT_BG);
+        }
+
+        let mut start = self.start_of_line(self.top_line);
+        for row in 0..self.rows {
+            if dirty.covers(self.top_line + row) {
+                self.draw_row(fb, row, start, width);
+            }
+            start = start.and_then(|s| {
+                let end = self.line_end(s);
+                (end < self.text.len()).then_some(end + 1)
+            });
+        }
+        if dirty.all || dirty.status {
+            self.draw_status(fb, width, height);
+        }
+    }
+}
+
+pub fn slopedit_main_args(argc: usize, argv: *const *const u8) -> ! {
+    let mut editor = Editor::new();
+    if argc > 1 && !argv.is_null() {
+        editor.load(arg_at(argv, 1));
+    }
+    appkit::run(editor, EDIT_WIDTH, EDIT_HEIGHT)
+}
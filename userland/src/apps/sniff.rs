@@ -0,0 +1,194 @@
+//! `sniff [-p icmp|tcp|udp] [-c count]` — print a line per inbound IPv4
+//! datagram.
+//!
+//! Opens a raw socket for each selected protocol, all three by default, and
+//! prints when each datagram arrived, its endpoints (with ports for TCP and
+//! UDP), TCP flags or the ICMP type and code, and its length.  Raw sockets
+//! see traffic after the input filter and only in the receive direction, so
+//! outgoing datagrams show up only when sent over `lo`.  Runs until `count`
+//! datagrams have been printed, or forever.  Needs `TASK_FLAG_NET_RAW`.
+
+use slopos_abi::net::{AF_INET, SOCK_RAW};
+use slopos_abi::syscall::POLLIN;
+
+use crate::apps::cli::{Usage, arg_at, parse_u32, write_dec, write_ipv4, write_out};
+use crate::syscall::core::{exit_with_code, get_time_ms};
+use crate::syscall::{RawFd, UserPollFd, fs, net};
+
+const IPPROTO_ICMP: u8 = 1;
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+const PROTOCOLS: [(&[u8], u8); 3] = [
+    (b"icmp", IPPROTO_ICMP),
+    (b"tcp", IPPROTO_TCP),
+    (b"udp", IPPROTO_UDP),
+];
+
+const USAGE: Usage = Usage::new(b"usage: sniff [-p icmp|tcp|udp] [-c count]\n", 2);
+
+struct Options {
+    /// Protocol to watch, or all of [`PROTOCOLS`].
+    protocol: Option<u8>,
+    /// Datagrams to print before exiting; 0 runs forever.
+    count: u32,
+}
+
+fn parse_args(argc: usize, argv: *const *const u8) -> Options {
+    let mut opts = Options {
+        protocol: None,
+        count: 0,
+    };
+    if argc > 1 && argv.is_null() {
+        USAGE.exit();
+    }
+    let mut idx = 1;
+    while idx < argc {
+        let flag = arg_at(argv, idx);
+        idx += 1;
+        if idx >= argc {
+            USAGE.exit();
+        }
+        let value = arg_at(argv, idx);
+        match flag {
+            b"-p" => match PROTOCOLS.iter().find(|(name, _)| *name == value) {
+                Some(&(_, proto)) => opts.protocol = Some(proto),
+                None => USAGE.exit(),
+            },
+            b"-c" => match parse_u32(value) {
+                Some(count) if count > 0 => opts.count = count,
+                _ => USAGE.exit(),
+            },
+            _ => USAGE.exit(),
+        }
+        idx += 1;
+    }
+    opts
+}
+
+fn write_endpoint(ip: &[u8], port: Option<u16>) {
+    write_ipv4([ip[0], ip[1], ip[2], ip[3]]);
+    if let Some(port) = port {
+        write_out(b":");
+        write_dec(port as u64);
+    }
+}
+
+/// One line for `dgram`, a datagram with its IP header.
+fn print_datagram(dgram: &[u8]) {
+    if dgram.len() < 20 {
+        return;
+    }
+    let ihl = ((dgram[0] & 0x0F) as usize * 4).min(dgram.len());
+    let proto = dgram[9];
+    let l4 = &dgram[ihl..];
+    let ports = match proto {
+        IPPROTO_TCP | IPPROTO_UDP if l4.len() >= 4 => Some((
+            u16::from_be_bytes([l4[0], l4[1]]),
+            u16::from_be_bytes([l4[2], l4[3]]),
+        )),
+        _ => None,
+    };
+
+    let now = get_time_ms();
+    write_dec(now / 1000);
+    write_out(b".");
+    let ms = now % 1000;
+    write_out(&[
+        b'0' + (ms / 100) as u8,
+        b'0' + (ms / 10 % 10) as u8,
+        b'0' + (ms % 10) as u8,
+    ]);
+    write_out(b" ");
+    write_endpoint(&dgram[12..16], ports.map(|(src, _)| src));
+    write_out(b" > ");
+    write_endpoint(&dgram[16..20], ports.map(|(_, dst)| dst));
+
+    match proto {
+        IPPROTO_TCP => {
+            write_out(b" tcp");
+            if l4.len() >= 14 {
+                write_out(b" [");
+                for (bit, letter) in [(0x02, b'S'), (0x01, b'F'), (0x04, b'R'), (0x08, b'P')] {
+                    if l4[13] & bit != 0 {
+                        write_out(&[letter]);
+                    }
+                }
+                if l4[13] & 0x10 != 0 {
+                    write_out(b".");
+                }
+                write_out(b"]");
+            }
+        }
+        IPPROTO_UDP => write_out(b" udp"),
+        IPPROTO_ICMP => {
+            write_out(b" icmp");
+            if l4.len() >= 2 {
+                write_out(b" type ");
+                write_dec(l4[0] as u64);
+                write_out(b" code ");
+                write_dec(l4[1] as u64);
+            }
+        }
+        _ => {
+            write_out(b" proto ");
+            write_dec(proto as u64);
+        }
+    }
+    write_out(b" len ");
+    write_dec(dgram.len() as u64);
+    write_out(b"\n");
+}
+
+fn open_raw(proto: u8) -> RawFd {
+    match net::socket(AF_INET, SOCK_RAW, proto as u16) {
+        Ok(fd) => {
+            let _ = net::set_nonblocking(fd);
+            fd
+        }
+        Err(err) => {
+            write_out(b"sniff: socket: ");
+            write_out(err.as_str().as_bytes());
+            write_out(b"\n");
+            exit_with_code(2);
+        }
+    }
+}
+
+pub fn sniff_main_args(argc: usize, argv: *const *const u8) -> ! {
+    let opts = parse_args(argc, argv);
+
+    let mut pfds = [UserPollFd {
+        fd: -1,
+        events: POLLIN,
+        revents: 0,
+    }; PROTOCOLS.len()];
+    let mut open = 0;
+    for &(_, proto) in PROTOCOLS.iter() {
+        if opts.protocol.is_none_or(|p| p == proto) {
+            pfds[open].fd = open_raw(proto);
+            open += 1;
+        }
+    }
+    let pfds = &mut pfds[..open];
+
+    let mut buf = [0u8; 1500];
+    let mut printed = 0u32;
+    loop {
+        if fs::poll(pfds, -1).is_err() {
+            continue;
+        }
+        for pfd in pfds.iter_mut() {
+            if pfd.revents & POLLIN == 0 {
+                continue;
+            }
+            while let Ok(len) = net::recvfrom(pfd.fd, &mut buf, 0, None) {
+                print_datagram(&buf[..len]);
+                printed += 1;
+                if printed == opts.count {
+                    exit_with_code(0);
+                }
+            }
+        }
+    }
+}
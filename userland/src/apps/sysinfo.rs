@@ -1,6 +1,10 @@
 use core::ffi::c_void;
 
 use slopos_abi::PAGE_SIZE;
+use slopos_abi::hw::{
+    HW_CLASS_BLOCK, HW_CLASS_DISPLAY, HW_CLASS_INPUT, HW_CLASS_NET, HW_CLASS_PCI, HW_MAX_DEVICES,
+    HwDevice,
+};
 use slopos_lib::numfmt;
 
 use crate::appkit::{self, Window, WindowedApp};
@@ -9,16 +13,56 @@ use crate::syscall::{UserSysInfo, core as sys_core};
 use crate::theme::{COLOR_BACKGROUND, COLOR_TEXT};
 
 const SYSINFO_WIDTH: u32 = 360;
-const SYSINFO_HEIGHT: u32 = 258;
+const SYSINFO_HEIGHT: u32 = 276;
 const MARGIN_X: i32 = 12;
 const MARGIN_Y: i32 = 12;
 const LINE_HEIGHT: i32 = 18;
 
-pub struct SysinfoApp;
+/// Device counts from the boot hardware inventory.  It never changes after
+/// boot, so it is read once when the window opens.
+#[derive(Default)]
+struct HwSummary {
+    available: bool,
+    pci: u64,
+    block: u64,
+    net: u64,
+    input: u64,
+    display: Option<HwDevice>,
+}
+
+impl HwSummary {
+    fn read() -> Self {
+        let mut devices = [HwDevice::default(); HW_MAX_DEVICES];
+        let Ok(count) = sys_core::hw_inventory(&mut devices, 0) else {
+            return Self::default();
+        };
+        let mut summary = Self {
+            available: true,
+            ..Self::default()
+        };
+        for dev in &devices[..count] {
+            match dev.class {
+                HW_CLASS_PCI => summary.pci += 1,
+                HW_CLASS_BLOCK => summary.block += 1,
+                HW_CLASS_NET => summary.net += 1,
+                HW_CLASS_INPUT => summary.input += 1,
+                HW_CLASS_DISPLAY if summary.display.is_none() => summary.display = Some(*dev),
+                _ => {}
+            }
+        }
+        summary
+    }
+}
+
+#[derive(Default)]
+pub struct SysinfoApp {
+    hw: HwSummary,
+}
 
 impl WindowedApp for SysinfoApp {
     fn init(&mut self, win: &mut Window) {
         win.set_title("Sysinfo");
+        self.hw = HwSummary::read();
         win.request_redraw();
     }
 
@@ -137,12 +181,42 @@ impl WindowedApp for SysinfoApp {
             y += LINE_HEIGHT;
         }
 
-        draw_text(fb, MARGIN_X, y, "Drivers: kernel-managed");
+        if !self.hw.available {
+            draw_text(fb, MARGIN_X, y, "Devices: unavailable");
+            return;
+        }
+        let mut idx = copy_bytes(&mut line, 0, b"Devices: ");
+        for (count, label) in [
+            (self.hw.pci, &b" PCI, "[..]),
+            (self.hw.block, b" block, "),
+            (self.hw.net, b" net, "),
+            (self.hw.input, b" input"),
+        ] {
+            idx = copy_u64(&mut line, idx, count);
+            idx = copy_bytes(&mut line, idx, label);
+        }
+        draw_text(fb, MARGIN_X, y, as_str(&line, idx));
+        y += LINE_HEIGHT;
+
+        let text = match &self.hw.display {
+            Some(display) => {
+                let mut idx = copy_bytes(&mut line, 0, b"Display: ");
+                idx = copy_u64(&mut line, idx, display.attrs[0]);
+                idx = copy_bytes(&mut line, idx, b"x");
+                idx = copy_u64(&mut line, idx, display.attrs[1]);
+                idx = copy_bytes(&mut line, idx, b" (");
+                idx = copy_bytes(&mut line, idx, display.driver_str().as_bytes());
+                idx = copy_bytes(&mut line, idx, b")");
+                as_str(&line, idx)
+            }
+            None => "Display: none",
+        };
+        draw_text(fb, MARGIN_X, y, text);
     }
 }
 
 pub fn sysinfo_main(_arg: *mut c_void) -> ! {
-    appkit::run(SysinfoApp, SYSINFO_WIDTH, SYSINFO_HEIGHT)
+    appkit::run(SysinfoApp::default(), SYSINFO_WIDTH, SYSINFO_HEIGHT)
 }
 
 fn draw_text(fb: &mut DrawBuffer<'_>, x: i32, y: i32, text: &str) {
@@ -152,14 +226,20 @@ fn draw_text(fb: &mut DrawBuffer<'_>, x: i32, y: i32, text: &str) {
 fn format_line<'a>(buf: &'a mut [u8; 96], label: &str, value: u64, suffix: &str) -> &'a str {
     let mut idx = 0usize;
     idx = copy_bytes(buf, idx, label.as_bytes());
+    idx = copy_u64(buf, idx, value);
+    idx = copy_bytes(buf, idx, suffix.as_bytes());
+    as_str(buf, idx)
+}
 
+fn copy_u64(buf: &mut [u8; 96], idx: usize, value: u64) -> usize {
     let mut num = [0u8; 21];
     let formatted = numfmt::fmt_u64(value, &mut num);
     let digits = formatted.strip_suffix(&[0]).unwrap_or(formatted);
-    idx = copy_bytes(buf, idx, digits);
+    copy_bytes(buf, idx, digits)
+}
 
-    idx = copy_bytes(buf, idx, suffix.as_bytes());
-    core::str::from_utf8(&buf[..idx]).unwrap_or("???")
+fn as_str(buf: &[u8; 96], len: usize) -> &str {
+    core::str::from_utf8(&buf[..len]).unwrap_or("???")
 }
 
 fn pages_to_mib(pages: u64) -> u64 {
@@ -0,0 +1,323 @@
+//! Terminal emulator: a window showing a pty, with the shell on the other
+//! end.
+//!
+//! The terminal opens a pty pair and forks.  The child starts a session,
+//! puts the slave on fds 0–2 and execs `/bin/shell --pty`, which takes the
+//! slave as its controlling terminal.  The parent feeds what the master
+//! reads into a [`vt::Screen`], draws it with the system font and writes
+//! key presses to the master; resizing the window resizes the pty, so the
+//! shell's foreground job gets SIGWINCH.
+
+mod vt;
+
+use core::ffi::c_void;
+use core::ptr;
+
+use slopos_abi::draw::Color32;
+use slopos_abi::input::{
+    CLIPBOARD_MAX_SIZE, KEY_MOD_SHIFT, SCANCODE_DELETE, SCANCODE_DOWN, SCANCODE_END, SCANCODE_HOME,
+    SCANCODE_LEFT, SCANCODE_PAGE_DOWN, SCANCODE_PAGE_UP, SCANCODE_RIGHT, SCANCODE_UP,
+};
+use slopos_abi::signal::SIGHUP;
+use slopos_abi::syscall::{O_CLOEXEC, POLLIN, UserPollFd, UserWinsize};
+
+use crate::appkit::{self, ControlFlow, Event, Window, WindowedApp};
+use crate::gfx::font::{FONT_CHAR_HEIGHT, FONT_CHAR_WIDTH};
+use crate::gfx::{self, DrawBuffer};
+use crate::syscall::{RawFd, core as sys_core, fs, process};
+
+use vt::{BG_DEFAULT, Screen};
+
+/// 80 columns by 25 rows.
+const TERMINAL_WIDTH: u32 = 640;
+const TERMINAL_HEIGHT: u32 = 400;
+
+/// Reads from the master per pass of the event loop, so a program that
+/// writes without pause can't starve input.
+const MAX_READS: usize = 8;
+const READ_CHUNK: usize = 1024;
+
+/// The bytes the console sends for keys with no ASCII code, which the
+/// shell's line editor reads.
+const KEY_PAGE_UP: u8 = 0x80;
+const KEY_PAGE_DOWN: u8 = 0x81;
+const KEY_UP: u8 = 0x82;
+const KEY_DOWN: u8 = 0x83;
+const KEY_LEFT: u8 = 0x84;
+const KEY_RIGHT: u8 = 0x85;
+const KEY_HOME: u8 = 0x86;
+const KEY_END: u8 = 0x87;
+const KEY_DELETE: u8 = 0x88;
+const KEY_SHIFT_LEFT: u8 = 0x94;
+const KEY_SHIFT_RIGHT: u8 = 0x95;
+const KEY_SHIFT_HOME: u8 = 0x96;
+const KEY_SHIFT_END: u8 = 0x97;
+
+const CTRL_C: u8 = 0x03;
+const CTRL_X: u8 = 0x18;
+
+/// ANSI colours 0–15, then the default foreground and background.
+const PALETTE: [Color32; 18] = [
+    Color32::rgb(0x00, 0x00, 0x00),
+    Color32::rgb(0xCD, 0x31, 0x31),
+    Color32::rgb(0x0D, 0xBC, 0x79),
+    Color32::rgb(0xE5, 0xE5, 0x10),
+    Color32::rgb(0x24, 0x72, 0xC8),
+    Color32::rgb(0xBC, 0x3F, 0xBC),
+    Color32::rgb(0x11, 0xA8, 0xCD),
+    Color32::rgb(0xE5, 0xE5, 0xE5),
+    Color32::rgb(0x66, 0x66, 0x66),
+    Color32::rgb(0xF1, 0x4C, 0x4C),
+    Color32::rgb(0x23, 0xD1, 0x8B),
+    Color32::rgb(0xF5, 0xF5, 0x43),
+    Color32::rgb(0x3B, 0x8E, 0xEA),
+    Color32::rgb(0xD6, 0x70, 0xD6),
+    Color32::rgb(0x29, 0xB8, 0xDB),
+    Color32::rgb(0xFF, 0xFF, 0xFF),
+    Color32::rgb(0xE6, 0xE6, 0xE6),
+    Color32::rgb(0x1E, 0x1E, 0x1E),
+];
+
+/// Grid size for a window of `width` by `height` pixels.
+fn grid_size(width: u32, height: u32) -> (usize, usize) {
+    (
+        (width as i32 / FONT_CHAR_WIDTH).max(1) as usize,
+        (height as i32 / FONT_CHAR_HEIGHT).max(1) as usize,
+    )
+}
+
+fn winsize(cols: usize, rows: usize) -> UserWinsize {
+    UserWinsize {
+        ws_row: rows as u16,
+        ws_col: cols as u16,
+        ws_xpixel: (cols as i32 * FONT_CHAR_WIDTH) as u16,
+        ws_ypixel: (rows as i32 * FONT_CHAR_HEIGHT) as u16,
+    }
+}
+
+/// The byte sent for a key press, if any.
+fn key_byte(scancode: u8, ascii: u8, modifiers: u8) -> Option<u8> {
+    if ascii != 0 {
+        return Some(ascii);
+    }
+    let shift = modifiers & KEY_MOD_SHIFT != 0;
+    Some(match scancode {
+        SCANCODE_UP => KEY_UP,
+        SCANCODE_DOWN => KEY_DOWN,
+        SCANCODE_LEFT if shift => KEY_SHIFT_LEFT,
+        SCANCODE_LEFT => KEY_LEFT,
+        SCANCODE_RIGHT if shift => KEY_SHIFT_RIGHT,
+        SCANCODE_RIGHT => KEY_RIGHT,
+        SCANCODE_HOME if shift => KEY_SHIFT_HOME,
+        SCANCODE_HOME => KEY_HOME,
+        SCANCODE_END if shift => KEY_SHIFT_END,
+        SCANCODE_END => KEY_END,
+        SCANCODE_PAGE_UP => KEY_PAGE_UP,
+        SCANCODE_PAGE_DOWN => KEY_PAGE_DOWN,
+        SCANCODE_DELETE => KEY_DELETE,
+        _ => return None,
+    })
+}
+
+/// Open a pty of size `ws` and start the shell on it.  Returns the master
+/// and the shell's PID.
+fn spawn_shell(ws: &UserWinsize) -> Option<(RawFd, u32)> {
+    let [master, slave] = fs::openpty(O_CLOEXEC as u32).ok()?;
+    let _ = fs::tcsetwinsize(master, ws);
+    let pid = process::fork();
+    if pid == 0 {
+        exec_shell(slave);
+    }
+    let _ = fs::close_fd(slave);
+    if pid < 0 {
+        let _ = fs::close_fd(master);
+        return None;
+    }
+    Some((master, pid as u32))
+}
+
+/// In the child: a new session with the slave on fds 0–2, which the shell
+/// takes as its controlling terminal.
+fn exec_shell(slave: RawFd) -> ! {
+    process::setsid();
+    for target in 0..3 {
+        let _ = fs::dup2(slave, target);
+    }
+    let argv = [
+        c"shell".as_ptr() as *const u8,
+        c"--pty".as_ptr() as *const u8,
+        ptr::null(),
+    ];
+    process::execve(
+        c"/bin/shell".as_ptr() as *const u8,
+        argv.as_ptr(),
+        ptr::null(),
+    );
+    let _ = fs::write_slice(2, b"terminal: cannot start /bin/shell\n");
+    sys_core::exit_with_code(127)
+}
+
+pub struct Terminal {
+    screen: Screen,
+    /// Master end of the shell's pty.
+    master: RawFd,
+    child: u32,
+    /// Where the cursor was last drawn, to erase it when it moves.
+    drawn_cursor: Option<(usize, usize)>,
+    /// Window size the background was last filled for.
+    drawn_size: (u32, u32),
+}
+
+impl Terminal {
+    fn write_master(&self, bytes: &[u8]) {
+        let mut written = 0;
+        while written < bytes.len() {
+            match fs::write_slice(self.master, &bytes[written..]) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => written += n,
+            }
+        }
+    }
+
+    /// Feed the screen whatever the shell has written.
+    fn pump(&mut self) {
+        let mut buf = [0u8; READ_CHUNK];
+        for _ in 0..MAX_READS {
+            let mut fds = [UserPollFd {
+                fd: self.master,
+                events: POLLIN,
+                revents: 0,
+            }];
+            match fs::poll(&mut fds, 0) {
+                Ok(n) if n > 0 && fds[0].revents & POLLIN != 0 => {}
+                _ => break,
+            }
+            match fs::read_slice(self.master, &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => self.screen.feed(&buf[..n]),
+            }
+        }
+    }
+
+    fn paste(&self) {
+        let mut buf = [0u8; CLIPBOARD_MAX_SIZE];
+        let len = appkit::clipboard::get_text(&mut buf);
+        self.write_master(&buf[..len]);
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        let (cols, rows) = grid_size(width, height);
+        if (cols, rows) == (self.screen.cols(), self.screen.rows()) {
+            return;
+        }
+        self.screen.resize(cols, rows);
+        let cols = self.screen.cols();
+        let rows = self.screen.rows();
+        let _ = fs::tcsetwinsize(self.master, &winsize(cols, rows));
+    }
+
+    fn draw_row(&self, fb: &mut DrawBuffer<'_>, y: usize, cursor: Option<(usize, usize)>) {
+        let py = y as i32 * FONT_CHAR_HEIGHT;
+        for x in 0..self.screen.cols() {
+            let cell = self.screen.cell(x, y);
+            let (mut fg, mut bg) = (cell.fg, cell.bg);
+            if cursor == Some((x, y)) {
+                (fg, bg) = (bg, fg);
+            }
+            let px = x as i32 * FONT_CHAR_WIDTH;
+            let bg = PALETTE[bg as usize];
+            gfx::fill_rect(fb, px, py, FONT_CHAR_WIDTH, FONT_CHAR_HEIGHT, bg);
+            if cell.ch != b' ' {
+                gfx::font::draw_char(fb, px, py, cell.ch, PALETTE[fg as usize], bg);
+            }
+        }
+    }
+}
+
+impl WindowedApp for Terminal {
+    fn init(&mut self, win: &mut Window) {
+        win.set_title("Terminal");
+        win.request_redraw();
+    }
+
+    fn on_event(&mut self, win: &mut Window, event: Event) -> ControlFlow {
+        match event {
+            Event::CloseRequest => {
+                process::kill(self.child, SIGHUP);
+                return ControlFlow::Exit;
+            }
+            Event::KeyPress {
+                scancode,
+                ascii,
+                modifiers,
+            } => {
+                if let Some(byte) = key_byte(scancode, ascii, modifiers) {
+                    self.write_master(&[byte]);
+                }
+            }
+            // The clipboard shortcuts arrive as events of their own; Ctrl+C
+            // and Ctrl+X still go to the shell, Ctrl+V pastes.
+            Event::Copy => self.write_master(&[CTRL_C]),
+            Event::Cut => self.write_master(&[CTRL_X]),
+            Event::Paste => self.paste(),
+            Event::Configure { .. } => {
+                self.resize(win.width(), win.height());
+                win.request_redraw();
+            }
+            _ => {}
+        }
+        ControlFlow::Continue
+    }
+
+    fn update(&mut self, win: &mut Window) {
+        if process::waitpid_nohang(self.child).is_some() {
+            sys_core::exit();
+        }
+        self.pump();
+        let cursor = self.screen.cursor_visible.then(|| self.screen.cursor());
+        if self.screen.has_dirty() || cursor != self.drawn_cursor {
+            win.request_redraw();
+        }
+    }
+
+    fn draw(&mut self, fb: &mut DrawBuffer<'_>) {
+        let size = (fb.width(), fb.height());
+        if size != self.drawn_size {
+            // Fill the margin the grid doesn't cover.
+            let bg = PALETTE[BG_DEFAULT as usize];
+            gfx::fill_rect(fb, 0, 0, size.0 as i32, size.1 as i32, bg);
+            self.screen.mark_all_dirty();
+            self.drawn_size = size;
+        }
+
+        let cursor = self.screen.cursor_visible.then(|| self.screen.cursor());
+        let mut dirty = self.screen.take_dirty();
+        for (_, y) in [self.drawn_cursor, cursor].into_iter().flatten() {
+            dirty |= 1 << y;
+        }
+        for y in 0..self.screen.rows() {
+            if dirty & (1 << y) != 0 {
+                self.draw_row(fb, y, cursor);
+            }
+        }
+        self.drawn_cursor = cursor;
+    }
+}
+
+pub fn terminal_main(_arg: *mut c_void) -> ! {
+    let (cols, rows) = grid_size(TERMINAL_WIDTH, TERMINAL_HEIGHT);
+    let Some(screen) = Screen::new(cols, rows) else {
+        sys_core::exit_with_code(1)
+    };
+    let Some((master, child)) = spawn_shell(&winsize(screen.cols(), screen.rows())) else {
+        sys_core::exit_with_code(1)
+    };
+    let app = Terminal {
+        screen,
+        master,
+        child,
+        drawn_cursor: None,
+        drawn_size: (0, 0),
+    };
+    appkit::run(app, TERMINAL_WIDTH, TERMINAL_HEIGHT)
+}
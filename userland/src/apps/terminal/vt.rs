@@ -0,0 +1,505 @@
+//! VT100-style screen: a grid of cells fed with the bytes programs write to
+//! the pty.
+//!
+//! Handled: text with wrap at the right margin, CR, LF, BS, TAB and BEL;
+//! CSI cursor movement (`A`–`H`, `d`, `f`), erase (`J`, `K`, `X`), insert
+//! and delete (`@`, `P`), colours and reverse video (`m`), save and restore
+//! (`s`, `u`, ESC 7 / ESC 8) and cursor visibility (`?25h`, `?25l`); ESC D,
+//! E, M and c.  OSC strings are skipped, anything else is dropped.
+
+use core::ffi::c_void;
+use core::slice;
+
+use crate::libc;
+
+/// Largest grid kept; bigger windows leave the rest blank.
+pub const MAX_COLS: usize = 160;
+/// At most 64, so a row fits a bit in the dirty mask.
+pub const MAX_ROWS: usize = 64;
+
+/// Colour indices 0–15 are the ANSI palette; these two follow it.
+pub const FG_DEFAULT: u8 = 16;
+pub const BG_DEFAULT: u8 = 17;
+
+const GRID_CELLS: usize = MAX_COLS * MAX_ROWS;
+const TAB_STOP: usize = 8;
+const MAX_PARAMS: usize = 8;
+
+const BEL: u8 = 0x07;
+const BS: u8 = 0x08;
+const ESC: u8 = 0x1B;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: u8,
+    pub fg: u8,
+    pub bg: u8,
+}
+
+impl Cell {
+    const BLANK: Self = Self {
+        ch: b' ',
+        fg: FG_DEFAULT,
+        bg: BG_DEFAULT,
+    };
+}
+
+/// What new text is written with.
+#[derive(Clone, Copy)]
+struct Pen {
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    reverse: bool,
+}
+
+impl Pen {
+    const DEFAULT: Self = Self {
+        fg: FG_DEFAULT,
+        bg: BG_DEFAULT,
+        bold: false,
+        reverse: false,
+    };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    Csi,
+    /// Inside an OSC string, which ends at BEL or ESC \.
+    Osc,
+    OscEscape,
+}
+
+pub struct Screen {
+    /// `MAX_ROWS` rows of `MAX_COLS` cells, on the heap since they don't
+    /// fit the stack.
+    cells: *mut Cell,
+    cols: usize,
+    rows: usize,
+    cursor_x: usize,
+    cursor_y: usize,
+    /// Set after writing in the last column; the next character wraps.
+    wrap_pending: bool,
+    pub cursor_visible: bool,
+    pen: Pen,
+    saved: (usize, usize, Pen),
+    state: State,
+    params: [u16; MAX_PARAMS],
+    param_count: usize,
+    /// The sequence had a `?` (DEC private mode).
+    private: bool,
+    /// One bit per row changed since [`Screen::take_dirty`].
+    dirty: u64,
+}
+
+impl Screen {
+    /// A blank screen, or `None` if the heap is full.
+    pub fn new(cols: usize, rows: usize) -> Option<Self> {
+        let cells = libc::alloc(GRID_CELLS * size_of::<Cell>()) as *mut Cell;
+        if cells.is_null() {
+            return None;
+        }
+        let mut screen = Self {
+            cells,
+            cols: cols.clamp(1, MAX_COLS),
+            rows: rows.clamp(1, MAX_ROWS),
+            cursor_x: 0,
+            cursor_y: 0,
+            wrap_pending: false,
+            cursor_visible: true,
+            pen: Pen::DEFAULT,
+            saved: (0, 0, Pen::DEFAULT),
+            state: State::Ground,
+            params: [0; MAX_PARAMS],
+            param_count: 0,
+            private: false,
+            dirty: u64::MAX,
+        };
+        screen.grid_mut().fill(Cell::BLANK);
+        Some(screen)
+    }
+
+    fn grid(&self) -> &[Cell] {
+        unsafe { slice::from_raw_parts(self.cells, GRID_CELLS) }
+    }
+
+    fn grid_mut(&mut self) -> &mut [Cell] {
+        unsafe { slice::from_raw_parts_mut(self.cells, GRID_CELLS) }
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut [Cell] {
+        &mut self.grid_mut()[y * MAX_COLS..(y + 1) * MAX_COLS]
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.grid()[y * MAX_COLS + x]
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor_x, self.cursor_y)
+    }
+
+    /// Rows changed since the last call, one bit each.
+    pub fn take_dirty(&mut self) -> u64 {
+        core::mem::take(&mut self.dirty)
+    }
+
+    pub fn has_dirty(&self) -> bool {
+        self.dirty != 0
+    }
+
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = u64::MAX;
+    }
+
+    /// Change the grid size, keeping the text at the top left.  If the
+    /// cursor would fall off the bottom, the text moves up with it.
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let cols = cols.clamp(1, MAX_COLS);
+        let rows = rows.clamp(1, MAX_ROWS);
+        if self.cursor_y >= rows {
+            self.scroll_up(self.cursor_y + 1 - rows);
+            self.cursor_y = rows - 1;
+        }
+        let kept_cols = cols.min(self.cols);
+        for y in 0..rows {
+            self.row_mut(y)[kept_cols..].fill(Cell::BLANK);
+        }
+        let kept_rows = rows.min(self.rows);
+        self.grid_mut()[kept_rows * MAX_COLS..].fill(Cell::BLANK);
+        self.cols = cols;
+        self.rows = rows;
+        self.cursor_x = self.cursor_x.min(cols - 1);
+        self.wrap_pending = false;
+        self.mark_all_dirty();
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            match self.state {
+                State::Ground => self.ground(byte),
+                State::Escape => self.escape(byte),
+                State::Csi => self.csi(byte),
+                State::Osc => match byte {
+                    BEL => self.state = State::Ground,
+                    ESC => self.state = State::OscEscape,
+                    _ => {}
+                },
+                State::OscEscape => self.state = State::Ground,
+            }
+        }
+    }
+
+    fn mark_row(&mut self, y: usize) {
+        self.dirty |= 1 << y;
+    }
+
+    fn set_cursor(&mut self, x: usize, y: usize) {
+        self.mark_row(self.cursor_y);
+        self.cursor_x = x.min(self.cols - 1);
+        self.cursor_y = y.min(self.rows - 1);
+        self.wrap_pending = false;
+        self.mark_row(self.cursor_y);
+    }
+
+    /// The cell new text and erases produce.
+    fn pen_cell(&self, ch: u8) -> Cell {
+        let mut fg = self.pen.fg;
+        if self.pen.bold && fg < 8 {
+            fg += 8;
+        }
+        let (fg, bg) = if self.pen.reverse {
+            (self.pen.bg, fg)
+        } else {
+            (fg, self.pen.bg)
+        };
+        Cell { ch, fg, bg }
+    }
+
+    fn blank(&self) -> Cell {
+        Cell {
+            bg: self.pen.bg,
+            ..Cell::BLANK
+        }
+    }
+
+    fn ground(&mut self, byte: u8) {
+        match byte {
+            ESC => self.state = State::Escape,
+            b'\r' => self.set_cursor(0, self.cursor_y),
+            b'\n' | 0x0B | 0x0C => self.line_feed(),
+            BS => self.set_cursor(self.cursor_x.saturating_sub(1), self.cursor_y),
+            b'\t' => {
+                let next = (self.cursor_x / TAB_STOP + 1) * TAB_STOP;
+                self.set_cursor(next, self.cursor_y);
+            }
+            0x20..=0x7E | 0xA0.. => self.put(byte),
+            _ => {}
+        }
+    }
+
+    fn put(&mut self, byte: u8) {
+        if self.wrap_pending {
+            self.set_cursor(0, self.cursor_y);
+            self.line_feed();
+        }
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        let cell = self.pen_cell(byte);
+        self.row_mut(y)[x] = cell;
+        self.mark_row(y);
+        if self.cursor_x + 1 == self.cols {
+            self.wrap_pending = true;
+        } else {
+            self.cursor_x += 1;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        if self.cursor_y + 1 == self.rows {
+            self.scroll_up(1);
+            self.wrap_pending = false;
+        } else {
+            self.set_cursor(self.cursor_x, self.cursor_y + 1);
+        }
+    }
+
+    fn scroll_up(&mut self, lines: usize) {
+        let lines = lines.min(self.rows);
+        let blank = self.blank();
+        let end = self.rows * MAX_COLS;
+        let grid = self.grid_mut();
+        grid[..end].rotate_left(lines * MAX_COLS);
+        grid[end - lines * MAX_COLS..end].fill(blank);
+        self.mark_all_dirty();
+    }
+
+    fn scroll_down(&mut self, lines: usize) {
+        let lines = lines.min(self.rows);
+        let blank = self.blank();
+        let end = self.rows * MAX_COLS;
+        let grid = self.grid_mut();
+        grid[..end].rotate_right(lines * MAX_COLS);
+        grid[..lines * MAX_COLS].fill(blank);
+        self.mark_all_dirty();
+    }
+
+    fn escape(&mut self, byte: u8) {
+        self.state = State::Ground;
+        match byte {
+            b'[' => {
+                self.params = [0; MAX_PARAMS];
+                self.param_count = 0;
+                self.private = false;
+                self.state = State::Csi;
+            }
+            b']' => self.state = State::Osc,
+            b'7' => self.saved = (self.cursor_x, self.cursor_y, self.pen),
+            b'8' => self.restore_cursor(),
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.set_cursor(0, self.cursor_y);
+                self.line_feed();
+            }
+            b'M' => {
+                if self.cursor_y == 0 {
+                    self.scroll_down(1);
+                } else {
+                    self.set_cursor(self.cursor_x, self.cursor_y - 1);
+                }
+            }
+            b'c' => self.reset(),
+            _ => {}
+        }
+    }
+
+    fn restore_cursor(&mut self) {
+        let (x, y, pen) = self.saved;
+        self.pen = pen;
+        self.set_cursor(x, y);
+    }
+
+    fn reset(&mut self) {
+        self.grid_mut().fill(Cell::BLANK);
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+        self.wrap_pending = false;
+        self.cursor_visible = true;
+        self.pen = Pen::DEFAULT;
+        self.saved = (0, 0, Pen::DEFAULT);
+        self.mark_all_dirty();
+    }
+
+    fn csi(&mut self, byte: u8) {
+        match byte {
+            b'0'..=b'9' => {
+                if self.param_count == 0 {
+                    self.param_count = 1;
+                }
+                let param = &mut self.params[self.param_count - 1];
+                *param = param
+                    .saturating_mul(10)
+                    .saturating_add((byte - b'0') as u16);
+            }
+            b';' => {
+                if self.param_count == 0 {
+                    self.param_count = 1;
+                }
+                if self.param_count < MAX_PARAMS {
+                    self.param_count += 1;
+                }
+            }
+            b'?' => self.private = true,
+            // Intermediate bytes; none of the sequences handled use them.
+            0x20..=0x2F => {}
+            0x40..=0x7E => {
+                self.state = State::Ground;
+                self.dispatch(byte);
+            }
+            ESC => self.state = State::Escape,
+            // Control characters take effect in the middle of a sequence.
+            0x00..=0x1F => self.ground(byte),
+            _ => self.state = State::Ground,
+        }
+    }
+
+    /// Parameter `i`, or `default` if it is missing or 0.
+    fn param(&self, i: usize, default: usize) -> usize {
+        match self.params[..self.param_count].get(i) {
+            Some(&value) if value != 0 => value as usize,
+            _ => default,
+        }
+    }
+
+    fn dispatch(&mut self, command: u8) {
+        let n = self.param(0, 1);
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        match command {
+            b'A' => self.set_cursor(x, y.saturating_sub(n)),
+            b'B' => self.set_cursor(x, y + n),
+            b'C' => self.set_cursor(x + n, y),
+            b'D' => self.set_cursor(x.saturating_sub(n), y),
+            b'E' => self.set_cursor(0, y + n),
+            b'F' => self.set_cursor(0, y.saturating_sub(n)),
+            b'G' => self.set_cursor(n - 1, y),
+            b'd' => self.set_cursor(x, n - 1),
+            b'H' | b'f' => self.set_cursor(self.param(1, 1) - 1, n - 1),
+            b'J' => self.erase_display(self.param(0, 0)),
+            b'K' => self.erase_line(self.param(0, 0)),
+            b'X' => self.erase_cells(y, x, x + n),
+            b'P' => self.delete_chars(n),
+            b'@' => self.insert_chars(n),
+            b'm' => self.select_graphic_rendition(),
+            b's' => self.saved = (x, y, self.pen),
+            b'u' => self.restore_cursor(),
+            b'h' | b'l' if self.private => {
+                if self.params[..self.param_count].contains(&25) {
+                    self.cursor_visible = command == b'h';
+                    self.mark_row(y);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn erase_cells(&mut self, y: usize, from: usize, to: usize) {
+        let blank = self.blank();
+        let to = to.min(self.cols);
+        if from < to {
+            self.row_mut(y)[from..to].fill(blank);
+        }
+        self.mark_row(y);
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        let rows = match mode {
+            0 => {
+                self.erase_cells(y, x, self.cols);
+                y + 1..self.rows
+            }
+            1 => {
+                self.erase_cells(y, 0, x + 1);
+                0..y
+            }
+            _ => 0..self.rows,
+        };
+        for row in rows {
+            self.erase_cells(row, 0, self.cols);
+        }
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        match mode {
+            0 => self.erase_cells(y, x, self.cols),
+            1 => self.erase_cells(y, 0, x + 1),
+            _ => self.erase_cells(y, 0, self.cols),
+        }
+    }
+
+    fn delete_chars(&mut self, n: usize) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        let n = n.min(self.cols - x);
+        let cols = self.cols;
+        self.row_mut(y)[x..cols].rotate_left(n);
+        self.erase_cells(y, self.cols - n, self.cols);
+    }
+
+    fn insert_chars(&mut self, n: usize) {
+        let (x, y) = (self.cursor_x, self.cursor_y);
+        let n = n.min(self.cols - x);
+        let cols = self.cols;
+        self.row_mut(y)[x..cols].rotate_right(n);
+        self.erase_cells(y, x, x + n);
+    }
+
+    fn select_graphic_rendition(&mut self) {
+        if self.param_count == 0 {
+            self.pen = Pen::DEFAULT;
+            return;
+        }
+        let mut i = 0;
+        while i < self.param_count {
+            match self.params[i] {
+                0 => self.pen = Pen::DEFAULT,
+                1 => self.pen.bold = true,
+                22 => self.pen.bold = false,
+                7 => self.pen.reverse = true,
+                27 => self.pen.reverse = false,
+                p @ 30..=37 => self.pen.fg = (p - 30) as u8,
+                39 => self.pen.fg = FG_DEFAULT,
+                p @ 40..=47 => self.pen.bg = (p - 40) as u8,
+                49 => self.pen.bg = BG_DEFAULT,
+                p @ 90..=97 => self.pen.fg = (p - 90 + 8) as u8,
+                p @ 100..=107 => self.pen.bg = (p - 100 + 8) as u8,
+                // 256-colour and RGB forms aren't supported; skip their
+                // arguments so they aren't read as attributes.
+                38 | 48 => {
+                    i += match self.params.get(i + 1) {
+                        Some(5) => 2,
+                        Some(2) => 4,
+                        _ => 0,
+                    };
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        libc::dealloc(self.cells as *mut c_void);
+    }
+}
@@ -0,0 +1,160 @@
+//! `top [-n count]` — per-CPU load, busiest first, refreshed every second.
+//!
+//! Each refresh prints one row per CPU with the share of the last second it
+//! spent off its idle task, its run queue depth and the context switches
+//! and preemptions it took in that second, then the overall load.  Runs
+//! until interrupted, or for `count` refreshes.  Installed as the shell
+//! plugin `/bin/slop-top`.
+
+use slopos_abi::task::CpuUsage;
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::apps::cli::{Usage, arg_at, write_out};
+use crate::syscall::core::{self as sys_core, exit_with_code};
+
+/// CPUs shown.
+const TOP_MAX_CPUS: usize = 64;
+/// Time between refreshes.
+const TOP_INTERVAL_MS: u32 = 1_000;
+
+const USAGE: Usage = Usage::new(b"usage: top [-n count]\n", 1);
+
+fn parse_count(word: &[u8]) -> Option<u64> {
+    if word.is_empty() || word.len() > 9 {
+        return None;
+    }
+    word.iter().try_fold(0u64, |acc, &b| {
+        b.is_ascii_digit().then(|| acc * 10 + (b - b'0') as u64)
+    })
+}
+
+/// Append `bytes` to `line`, dropping whatever does not fit.
+fn push(line: &mut [u8], len: &mut usize, bytes: &[u8]) {
+    let n = bytes.len().min(line.len() - *len);
+    line[*len..*len + n].copy_from_slice(&bytes[..n]);
+    *len += n;
+}
+
+/// Append the label `name` followed by `index` (if any), right-aligned in
+/// `width`, then a space.
+fn push_label(line: &mut [u8], len: &mut usize, name: &[u8], index: Option<u32>, width: usize) {
+    let mut buf = NumBuf::<12>::new();
+    let digits = index.map_or(&[][..], |i| numfmt::trim_nul(buf.format_u32(i)));
+    for _ in name.len() + digits.len()..width {
+        push(line, len, b" ");
+    }
+    push(line, len, name);
+    push(line, len, digits);
+    push(line, len, b" ");
+}
+
+fn push_u64(line: &mut [u8], len: &mut usize, value: u64, width: usize) {
+    let mut buf = NumBuf::<21>::new();
+    let digits = buf.format_padded(value, width, b' ');
+    push(line, len, numfmt::trim_nul(digits));
+    push(line, len, b" ");
+}
+
+/// `percent` and a `%` sign, right-aligned in `width`, then a space.
+fn push_percent(line: &mut [u8], len: &mut usize, percent: u32, width: usize) {
+    let mut buf = NumBuf::<21>::new();
+    let digits = buf.format_padded(percent as u64, width.saturating_sub(1), b' ');
+    push(line, len, numfmt::trim_nul(digits));
+    push(line, len, b"% ");
+}
+
+/// Turn the separator after the last column into the line's newline.
+fn end_line(line: &mut [u8], len: usize) -> &[u8] {
+    line[len - 1] = b'\n';
+    &line[..len]
+}
+
+/// One CPU's change between two samples.
+#[derive(Clone, Copy, Default)]
+struct CpuRow {
+    cpu: u32,
+    percent: u32,
+    ready: u32,
+    switches: u64,
+    preemptions: u64,
+}
+
+fn write_row(row: &CpuRow) {
+    let mut line = [0u8; 64];
+    let mut len = 0;
+    push_label(&mut line, &mut len, b"cpu", Some(row.cpu), 5);
+    push_percent(&mut line, &mut len, row.percent, 5);
+    push_u64(&mut line, &mut len, row.ready as u64, 5);
+    push_u64(&mut line, &mut len, row.switches, 8);
+    push_u64(&mut line, &mut len, row.preemptions, 8);
+    write_out(end_line(&mut line, len));
+}
+
+/// Print the change from `before` to `after`, busiest CPU first.
+fn write_report(before: &[CpuUsage], after: &[CpuUsage]) {
+    let mut rows = [CpuRow::default(); TOP_MAX_CPUS];
+    let (mut busy, mut total) = (0u128, 0u128);
+    for (i, (now, then)) in after.iter().zip(before).enumerate() {
+        rows[i] = CpuRow {
+            cpu: now.cpu,
+            percent: now.busy_percent_since(then),
+            ready: now.ready,
+            switches: now.switches.saturating_sub(then.switches),
+            preemptions: now.preemptions.saturating_sub(then.preemptions),
+        };
+        let cpu_busy = now.busy_cycles.saturating_sub(then.busy_cycles) as u128;
+        busy += cpu_busy;
+        total += cpu_busy + now.idle_cycles.saturating_sub(then.idle_cycles) as u128;
+    }
+    let rows = &mut rows[..after.len()];
+    rows.sort_unstable_by(|a, b| b.percent.cmp(&a.percent).then(a.cpu.cmp(&b.cpu)));
+
+    write_out(b"\n  CPU  BUSY READY SWITCHES PREEMPTS\n");
+    for row in rows.iter() {
+        write_row(row);
+    }
+    let overall = if total == 0 { 0 } else { busy * 100 / total };
+    let mut line = [0u8; 32];
+    let mut len = 0;
+    push_label(&mut line, &mut len, b"all", None, 5);
+    push_percent(&mut line, &mut len, overall as u32, 5);
+    write_out(end_line(&mut line, len));
+}
+
+pub fn top_main_args(argc: usize, argv: *const *const u8) -> ! {
+    if argc > 1 && argv.is_null() {
+        USAGE.exit();
+    }
+    let mut count = None;
+    match argc {
+        0 | 1 => {}
+        3 if arg_at(argv, 1) == b"-n" => match parse_count(arg_at(argv, 2)) {
+            Some(n) if n > 0 => count = Some(n),
+            _ => USAGE.exit(),
+        },
+        _ => USAGE.exit(),
+    }
+
+    let mut before = [CpuUsage::default(); TOP_MAX_CPUS];
+    let mut after = [CpuUsage::default(); TOP_MAX_CPUS];
+    let Ok(mut cpus) = sys_core::cpu_usage(&mut before) else {
+        write_out(b"top: cannot read CPU usage\n");
+        exit_with_code(1);
+    };
+    let mut shown = 0;
+    while count.is_none_or(|n| shown < n) {
+        sys_core::sleep_ms(TOP_INTERVAL_MS);
+        let Ok(now) = sys_core::cpu_usage(&mut after) else {
+            write_out(b"top: cannot read CPU usage\n");
+            exit_with_code(1);
+        };
+        // A CPU that came online since the last sample is shown from the
+        // next one.
+        cpus = cpus.min(now);
+        write_report(&before[..cpus], &after[..cpus]);
+        cpus = now;
+        before = after;
+        shown += 1;
+    }
+    exit_with_code(0);
+}
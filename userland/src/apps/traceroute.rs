@@ -0,0 +1,260 @@
+//! `traceroute [-m max_hops] [-w timeout_ms] host` — print the routers on
+//! the way to a host.
+//!
+//! Sends ICMP echo requests from a raw socket with `IP_HDRINCL`, raising
+//! the TTL by one per hop, three probes a hop.  A router that drops a probe
+//! for running out of TTL answers with a time exceeded message, which names
+//! it; the destination answers with an echo reply and ends the trace.
+//! Needs `TASK_FLAG_NET_RAW`.
+
+use slopos_abi::net::{AF_INET, SOCK_RAW, SockAddrIn};
+
+use crate::apps::cli::{Usage, arg_at, parse_u32, write_dec, write_fixed, write_ipv4, write_out};
+use crate::syscall::core::{clock_gettime_ns, exit_with_code};
+use crate::syscall::process::getpid;
+use crate::syscall::{RawFd, fs, net};
+
+const DEFAULT_MAX_HOPS: u32 = 30;
+const DEFAULT_TIMEOUT_MS: u32 = 3000;
+const PROBES_PER_HOP: u16 = 3;
+
+const IPPROTO_ICMP: u8 = 1;
+const IP_HEADER_LEN: usize = 20;
+const ICMP_HEADER_LEN: usize = 8;
+const PROBE_DATA_LEN: usize = 32;
+const PROBE_LEN: usize = IP_HEADER_LEN + ICMP_HEADER_LEN + PROBE_DATA_LEN;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_DEST_UNREACHABLE: u8 = 3;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_TIME_EXCEEDED: u8 = 11;
+
+const USAGE: Usage = Usage::new(b"usage: traceroute [-m max_hops] [-w timeout_ms] host\n", 2);
+
+struct Options<'a> {
+    host: &'a [u8],
+    max_hops: u32,
+    timeout_ms: u32,
+}
+
+fn parse_args<'a>(argc: usize, argv: *const *const u8) -> Options<'a> {
+    if argc < 2 || argv.is_null() {
+        USAGE.exit();
+    }
+    let mut opts = Options {
+        host: &[],
+        max_hops: DEFAULT_MAX_HOPS,
+        timeout_ms: DEFAULT_TIMEOUT_MS,
+    };
+    let mut idx = 1;
+    while idx < argc {
+        let arg = arg_at(argv, idx);
+        let flag = match arg {
+            b"-m" | b"-w" => arg,
+            _ if opts.host.is_empty() && !arg.starts_with(b"-") => {
+                opts.host = arg;
+                idx += 1;
+                continue;
+            }
+            _ => USAGE.exit(),
+        };
+        idx += 1;
+        if idx >= argc {
+            USAGE.exit();
+        }
+        let Some(value) = parse_u32(arg_at(argv, idx)) else {
+            USAGE.exit();
+        };
+        match flag {
+            b"-m" if (1..=255).contains(&value) => opts.max_hops = value,
+            b"-w" if value > 0 => opts.timeout_ms = value,
+            _ => USAGE.exit(),
+        }
+        idx += 1;
+    }
+    if opts.host.is_empty() {
+        USAGE.exit();
+    }
+    opts
+}
+
+/// RFC 1071 Internet checksum.
+fn inet_checksum(data: &[u8]) -> u16 {
+    let mut sum = 0u32;
+    for chunk in data.chunks(2) {
+        let hi = chunk[0] as u32;
+        let lo = chunk.get(1).copied().unwrap_or(0) as u32;
+        sum += (hi << 8) | lo;
+    }
+    while sum >> 16 != 0 {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// An echo request with its IP header.  The kernel fills in the total
+/// length, the header checksum and the source address.
+fn build_probe(dst: [u8; 4], ttl: u8, ident: u16, seq: u16) -> [u8; PROBE_LEN] {
+    let mut probe = [0u8; PROBE_LEN];
+    let (ip, icmp) = probe.split_at_mut(IP_HEADER_LEN);
+    ip[0] = 0x45;
+    ip[4..6].copy_from_slice(&seq.to_be_bytes());
+    ip[8] = ttl;
+    ip[9] = IPPROTO_ICMP;
+    ip[16..20].copy_from_slice(&dst);
+
+    icmp[0] = ICMP_ECHO_REQUEST;
+    icmp[4..6].copy_from_slice(&ident.to_be_bytes());
+    icmp[6..8].copy_from_slice(&seq.to_be_bytes());
+    for (i, b) in icmp[ICMP_HEADER_LEN..].iter_mut().enumerate() {
+        *b = b'a' + (i % 26) as u8;
+    }
+    let checksum = inet_checksum(icmp);
+    icmp[2..4].copy_from_slice(&checksum.to_be_bytes());
+    probe
+}
+
+/// If `pkt` answers probe `seq`, the ICMP type of the answer and who sent
+/// it.  Errors quote the probe's IP header and first 8 bytes, which hold
+/// the echo identifier and sequence number.
+fn match_reply(pkt: &[u8], ident: u16, seq: u16) -> Option<(u8, [u8; 4])> {
+    let ihl = (*pkt.first()? & 0x0F) as usize * 4;
+    let src: [u8; 4] = pkt.get(12..16)?.try_into().ok()?;
+    let icmp = pkt.get(ihl..)?;
+    let kind = *icmp.first()?;
+    let echo = match kind {
+        ICMP_ECHO_REPLY => icmp,
+        ICMP_TIME_EXCEEDED | ICMP_DEST_UNREACHABLE => {
+            let quoted = icmp.get(ICMP_HEADER_LEN..)?;
+            let quoted_ihl = (*quoted.first()? & 0x0F) as usize * 4;
+            if quoted.get(9) != Some(&IPPROTO_ICMP) {
+                return None;
+            }
+            let echo = quoted.get(quoted_ihl..)?;
+            if echo.first() != Some(&ICMP_ECHO_REQUEST) {
+                return None;
+            }
+            echo
+        }
+        _ => return None,
+    };
+    let echo_ident = u16::from_be_bytes([*echo.get(4)?, *echo.get(5)?]);
+    let echo_seq = u16::from_be_bytes([*echo.get(6)?, *echo.get(7)?]);
+    (echo_ident == ident && echo_seq == seq).then_some((kind, src))
+}
+
+/// Wait up to `timeout_ms` for the answer to probe `seq`.  Returns the ICMP
+/// type, the sender and the round trip in microseconds.
+fn await_reply(
+    fd: RawFd,
+    ident: u16,
+    seq: u16,
+    sent_ns: u64,
+    timeout_ms: u32,
+) -> Option<(u8, [u8; 4], u64)> {
+    let deadline_ns = sent_ns + timeout_ms as u64 * 1_000_000;
+    let mut buf = [0u8; 576];
+    loop {
+        let left_ms = deadline_ns.saturating_sub(clock_gettime_ns()) / 1_000_000;
+        if left_ms == 0 || net::set_recv_timeout(fd, Some(left_ms)).is_err() {
+            return None;
+        }
+        // Anything else arriving on the socket is skipped; a timeout ends
+        // the wait.
+        let len = net::recvfrom(fd, &mut buf, 0, None).ok()?;
+        if let Some((kind, from)) = match_reply(&buf[..len], ident, seq) {
+            let rtt_us = clock_gettime_ns().saturating_sub(sent_ns) / 1000;
+            return Some((kind, from, rtt_us));
+        }
+    }
+}
+
+pub fn traceroute_main_args(argc: usize, argv: *const *const u8) -> ! {
+    let opts = parse_args(argc, argv);
+    let Some(addr) = net::resolve(opts.host) else {
+        write_out(b"traceroute: cannot resolve ");
+        write_out(opts.host);
+        write_out(b"\n");
+        exit_with_code(2);
+    };
+
+    let fd = match net::socket(AF_INET, SOCK_RAW, IPPROTO_ICMP as u16) {
+        Ok(fd) => fd,
+        Err(err) => {
+            write_out(b"traceroute: socket: ");
+            write_out(err.as_str().as_bytes());
+            write_out(b"\n");
+            exit_with_code(2);
+        }
+    };
+    if net::set_hdrincl(fd, true).is_err() {
+        write_out(b"traceroute: cannot set IP_HDRINCL\n");
+        exit_with_code(2);
+    }
+
+    write_out(b"traceroute to ");
+    write_out(opts.host);
+    write_out(b" (");
+    write_ipv4(addr);
+    write_out(b"), ");
+    write_dec(opts.max_hops as u64);
+    write_out(b" hops max, ");
+    write_dec(PROBE_LEN as u64);
+    write_out(b" byte packets\n");
+
+    let dst = SockAddrIn {
+        family: AF_INET,
+        port: 0,
+        addr,
+        _pad: [0; 8],
+    };
+    let ident = getpid() as u16;
+    let mut seq = 0u16;
+    let mut reached = false;
+    for ttl in 1..=opts.max_hops {
+        if ttl < 10 {
+            write_out(b" ");
+        }
+        write_dec(ttl as u64);
+        let mut last_from = None;
+        for _ in 0..PROBES_PER_HOP {
+            seq = seq.wrapping_add(1);
+            let probe = build_probe(addr, ttl as u8, ident, seq);
+            let sent_ns = clock_gettime_ns();
+            if let Err(err) = net::sendto(fd, &probe, 0, &dst) {
+                write_out(b"\ntraceroute: sendto: ");
+                write_out(err.as_str().as_bytes());
+                write_out(b"\n");
+                exit_with_code(1);
+            }
+            let Some((kind, from, rtt_us)) = await_reply(fd, ident, seq, sent_ns, opts.timeout_ms)
+            else {
+                write_out(b"  *");
+                continue;
+            };
+            if last_from != Some(from) {
+                write_out(b"  ");
+                write_ipv4(from);
+                last_from = Some(from);
+            }
+            write_out(b"  ");
+            write_fixed(rtt_us, 3);
+            write_out(b" ms");
+            match kind {
+                ICMP_ECHO_REPLY => reached = true,
+                ICMP_DEST_UNREACHABLE => {
+                    write_out(b" !U");
+                    reached = true;
+                }
+                _ => {}
+            }
+        }
+        write_out(b"\n");
+        if reached {
+            break;
+        }
+    }
+
+    let _ = fs::close_fd(fd);
+    exit_with_code(if reached { 0 } else { 1 });
+}
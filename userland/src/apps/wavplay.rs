@@ -0,0 +1,198 @@
+//! `wavplay <file.wav>` — play an uncompressed PCM WAV file.
+//!
+//! Accepts 8- or 16-bit mono or stereo at any rate up to 192 kHz and
+//! converts to the kernel's 48 kHz stereo with nearest-neighbour
+//! resampling — crude, but enough for sound effects.
+
+use core::ffi::CStr;
+
+use slopos_abi::audio::{AUDIO_FRAME_BYTES, AUDIO_SAMPLE_RATE};
+use slopos_abi::syscall::SEEK_CUR;
+
+use crate::syscall::core::exit_with_code;
+use crate::syscall::{FdGuard, RawFd, USER_FS_OPEN_READ, audio, fs, tty};
+
+const WAVE_FORMAT_PCM: u16 = 1;
+const MAX_SAMPLE_RATE: u32 = 192_000;
+
+/// Source bytes read per refill; a multiple of every supported frame size.
+const IN_CHUNK: usize = 4096;
+const OUT_FRAMES: usize = 1024;
+
+fn write_out(buf: &[u8]) {
+    if fs::write_slice(1, buf).is_err() {
+        let _ = tty::write(buf);
+    }
+}
+
+fn fail(msg: &[u8]) -> ! {
+    write_out(b"wavplay: ");
+    write_out(msg);
+    write_out(b"\n");
+    exit_with_code(1);
+}
+
+#[derive(Clone, Copy)]
+struct WavFormat {
+    channels: u16,
+    sample_rate: u32,
+    bits: u16,
+}
+
+impl WavFormat {
+    fn frame_bytes(&self) -> usize {
+        self.channels as usize * self.bits as usize / 8
+    }
+
+    /// Left/right sample of the frame at `buf[off..]`, widened to 16 bits.
+    fn frame_at(&self, buf: &[u8], off: usize) -> (i16, i16) {
+        let sample = |i: usize| -> i16 {
+            if self.bits == 8 {
+                ((buf[off + i] as i16) - 128) << 8
+            } else {
+                i16::from_le_bytes([buf[off + 2 * i], buf[off + 2 * i + 1]])
+            }
+        };
+        let left = sample(0);
+        let right = if self.channels == 2 { sample(1) } else { left };
+        (left, right)
+    }
+}
+
+/// Fill `buf` completely unless EOF comes first.  Returns bytes read.
+fn read_full(fd: RawFd, buf: &mut [u8]) -> usize {
+    let mut done = 0;
+    while done < buf.len() {
+        match fs::read_slice(fd, &mut buf[done..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => done += n,
+        }
+    }
+    done
+}
+
+fn le16(b: &[u8]) -> u16 {
+    u16::from_le_bytes([b[0], b[1]])
+}
+
+fn le32(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// Walk the RIFF chunks up to `data`.  Returns the format and data length.
+fn read_header(fd: RawFd) -> Result<(WavFormat, u32), &'static [u8]> {
+    let mut riff = [0u8; 12];
+    if read_full(fd, &mut riff) != riff.len() || &riff[..4] != b"RIFF" || &riff[8..] != b"WAVE" {
+        return Err(b"not a RIFF/WAVE file");
+    }
+
+    let mut format = None;
+    loop {
+        let mut hdr = [0u8; 8];
+        if read_full(fd, &mut hdr) != hdr.len() {
+            return Err(b"no data chunk");
+        }
+        let size = le32(&hdr[4..]);
+        match &hdr[..4] {
+            b"fmt " => {
+                let mut body = [0u8; 16];
+                if size < 16 || read_full(fd, &mut body) != body.len() {
+                    return Err(b"truncated fmt chunk");
+                }
+                if le16(&body[0..]) != WAVE_FORMAT_PCM {
+                    return Err(b"only uncompressed PCM is supported");
+                }
+                format = Some(WavFormat {
+                    channels: le16(&body[2..]),
+                    sample_rate: le32(&body[4..]),
+                    bits: le16(&body[14..]),
+                });
+                // Skip any extension plus the RIFF pad byte.
+                let rest = (size - 16) + (size & 1);
+                if rest > 0 && fs::lseek(fd, rest as i64, SEEK_CUR as u32).is_err() {
+                    return Err(b"seek failed");
+                }
+            }
+            b"data" => return format.map(|f| (f, size)).ok_or(b"data before fmt"),
+            _ => {
+                let skip = size as i64 + (size & 1) as i64;
+                if fs::lseek(fd, skip, SEEK_CUR as u32).is_err() {
+                    return Err(b"seek failed");
+                }
+            }
+        }
+    }
+}
+
+fn play(fd: RawFd, format: WavFormat, data_len: u32) -> Result<(), &'static [u8]> {
+    let frame_bytes = format.frame_bytes();
+    // Source frames advanced per output frame, 16.16 fixed point.
+    let step = ((format.sample_rate as u64) << 16) / AUDIO_SAMPLE_RATE as u64;
+
+    let mut input = [0u8; IN_CHUNK];
+    let mut output = [0u8; OUT_FRAMES * AUDIO_FRAME_BYTES];
+    let mut remaining = data_len as usize;
+    let mut in_frames = 0usize;
+    // Position in `input`, in source frames, 16.16 fixed point.
+    let mut pos: u64 = 0;
+    let mut out_len = 0usize;
+
+    loop {
+        let idx = (pos >> 16) as usize;
+        if idx >= in_frames {
+            pos -= (in_frames as u64) << 16;
+            let want = remaining.min(IN_CHUNK / frame_bytes * frame_bytes);
+            let got = read_full(fd, &mut input[..want]);
+            remaining -= got;
+            in_frames = got / frame_bytes;
+            if in_frames == 0 {
+                break;
+            }
+            continue;
+        }
+
+        let (left, right) = format.frame_at(&input, idx * frame_bytes);
+        output[out_len..out_len + 2].copy_from_slice(&left.to_le_bytes());
+        output[out_len + 2..out_len + 4].copy_from_slice(&right.to_le_bytes());
+        out_len += AUDIO_FRAME_BYTES;
+        pos += step;
+
+        if out_len == output.len() {
+            audio::write(&output).map_err(|_| b"playback failed" as &[u8])?;
+            out_len = 0;
+        }
+    }
+    if out_len > 0 {
+        audio::write(&output[..out_len]).map_err(|_| b"playback failed" as &[u8])?;
+    }
+    Ok(())
+}
+
+pub fn wavplay_main_args(argc: usize, argv: *const *const u8) -> ! {
+    if argc != 2 || argv.is_null() {
+        write_out(b"usage: wavplay <file.wav>\n");
+        exit_with_code(1);
+    }
+    if !audio::info().is_ok_and(|i| i.is_present()) {
+        fail(b"no sound device");
+    }
+
+    let path = unsafe { CStr::from_ptr(*argv.add(1) as *const _) };
+    let Ok(file) = FdGuard::open(path, USER_FS_OPEN_READ) else {
+        fail(b"cannot open file");
+    };
+
+    let (format, data_len) = read_header(file.as_raw()).unwrap_or_else(|msg| fail(msg));
+    if !matches!(format.channels, 1 | 2)
+        || !matches!(format.bits, 8 | 16)
+        || format.sample_rate == 0
+        || format.sample_rate > MAX_SAMPLE_RATE
+    {
+        fail(b"unsupported format (need 8/16-bit mono/stereo PCM)");
+    }
+
+    if let Err(msg) = play(file.as_raw(), format, data_len) {
+        fail(msg);
+    }
+    exit_with_code(0);
+}
@@ -0,0 +1,218 @@
+//! `wget <url> <file>` — fetch an `http://` URL into a file.
+//!
+//! Resolves the host, opens a TCP connection, sends a single `GET` with
+//! `Connection: close` and streams the response body into the VFS while
+//! printing progress. Exercises DNS, TCP receive flow control and large
+//! sequential writes through the block cache in one go.
+
+use core::ffi::c_char;
+
+use slopos_abi::fs::{USER_FS_OPEN_CREAT, USER_FS_OPEN_WRITE};
+use slopos_abi::net::{AF_INET, SOCK_STREAM};
+
+use crate::apps::cli::{arg_at, write_dec, write_ipv4, write_out};
+use crate::apps::http::{self, HttpError, Response, Url};
+use crate::apps::shell::parser::normalize_path_with_cwd;
+use crate::syscall::core::{exit_with_code, get_time_ms};
+use crate::syscall::process::getcwd;
+use crate::syscall::{RawFd, SockAddrIn, fs, net};
+
+const USER_AGENT: &[u8] = b"slopos-wget/0.1";
+const RECV_BUF_LEN: usize = 4096;
+const REQUEST_BUF_LEN: usize = 1024;
+const PATH_MAX: usize = 256;
+/// Redraw the progress line at most this often.
+const PROGRESS_INTERVAL_MS: u64 = 250;
+
+fn die(msg: &[u8]) -> ! {
+    write_out(b"wget: ");
+    write_out(msg);
+    write_out(b"\n");
+    exit_with_code(1);
+}
+
+pub(crate) fn write_all(fd: RawFd, mut data: &[u8]) -> bool {
+    while !data.is_empty() {
+        match fs::write_slice(fd, data) {
+            Ok(0) | Err(_) => return false,
+            Ok(n) => data = &data[n..],
+        }
+    }
+    true
+}
+
+fn print_progress(resp: &Response, elapsed_ms: u64) {
+    write_out(b"\r");
+    write_dec(resp.received);
+    if let Some(total) = resp.content_length {
+        write_out(b" / ");
+        write_dec(total);
+        write_out(b" bytes (");
+        write_dec(resp.received * 100 / total.max(1));
+        write_out(b"%)");
+    } else {
+        write_out(b" bytes");
+    }
+    if let Some(rate) = (resp.received * 1000).checked_div(elapsed_ms) {
+        write_out(b", ");
+        write_dec(rate / 1024);
+        write_out(b" KiB/s");
+    }
+    write_out(b"   ");
+}
+
+/// Open `path` (relative to the cwd) for writing, replacing any existing file.
+pub(crate) fn create_output(path: *const u8) -> Option<RawFd> {
+    let mut cwd = [0u8; PATH_MAX];
+    if getcwd(&mut cwd) < 0 {
+        cwd[0] = b'/';
+    }
+    let mut full = [0u8; PATH_MAX];
+    if normalize_path_with_cwd(path, &mut full, &cwd) != 0 {
+        return None;
+    }
+    let _ = fs::unlink_path(full.as_ptr() as *const c_char);
+    fs::open_path(
+        full.as_ptr() as *const c_char,
+        USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT,
+    )
+    .ok()
+}
+
+fn connect(url: &Url<'_>) -> RawFd {
+    let Some(addr) = net::resolve(url.host) else {
+        write_out(b"wget: cannot resolve ");
+        write_out(url.host);
+        write_out(b"\n");
+        exit_with_code(1);
+    };
+    write_out(b"Connecting to ");
+    write_out(url.host);
+    write_out(b" (");
+    write_ipv4(addr);
+    write_out(b"):");
+    write_dec(url.port as u64);
+    write_out(b"... ");
+
+    let Ok(fd) = net::socket(AF_INET, SOCK_STREAM, 0) else {
+        die(b"socket creation failed");
+    };
+    let dest = SockAddrIn {
+        family: AF_INET,
+        port: url.port.to_be(),
+        addr,
+        _pad: [0; 8],
+    };
+    if let Err(err) = net::connect(fd, &dest) {
+        write_out(b"failed: ");
+        write_out(err.as_str().as_bytes());
+        write_out(b"\n");
+        exit_with_code(1);
+    }
+    write_out(b"connected.\n");
+    fd
+}
+
+pub fn wget_main_args(argc: usize, argv: *const *const u8) -> ! {
+    if argc != 3 || argv.is_null() {
+        write_out(b"usage: wget <http://host[:port]/path> <file>\n");
+        exit_with_code(2);
+    }
+    let Some(url) = http::parse_url(arg_at(argv, 1)) else {
+        die(b"only http:// URLs are supported");
+    };
+    let mut request = [0u8; REQUEST_BUF_LEN];
+    let Some(request_len) = http::build_request(&url, USER_AGENT, &mut request) else {
+        die(b"URL too long");
+    };
+
+    let sock = connect(&url);
+    if net::send(sock, &request[..request_len], 0) != Ok(request_len) {
+        let _ = fs::close_fd(sock);
+        die(b"failed to send request");
+    }
+    write_out(b"HTTP request sent, awaiting response... ");
+
+    let mut resp = Response::new();
+    let mut out: Option<RawFd> = None;
+    let mut write_failed = false;
+    let mut buf = [0u8; RECV_BUF_LEN];
+    let started = get_time_ms();
+    let mut last_progress = 0u64;
+
+    let result: Result<(), HttpError> = loop {
+        let n = match net::recv(sock, &mut buf, 0) {
+            Ok(0) => break resp.finish(),
+            Ok(n) => n,
+            Err(err) => {
+                write_out(b"\n");
+                let _ = fs::close_fd(sock);
+                die(err.as_str().as_bytes());
+            }
+        };
+        let mut pos = 0;
+        let mut fed = Ok(());
+        while pos < n && fed.is_ok() && !write_failed {
+            let was_head = resp.head_done();
+            let fd = out;
+            let failed = &mut write_failed;
+            match resp.feed(&buf[pos..n], &mut |body| {
+                if let Some(fd) = fd
+                    && !write_all(fd, body)
+                {
+                    *failed = true;
+                }
+            }) {
+                Ok(used) => pos += used,
+                Err(err) => fed = Err(err),
+            }
+            if !was_head && resp.head_done() {
+                write_dec(resp.status as u64);
+                write_out(b"\n");
+                if !(200..300).contains(&resp.status) {
+                    let _ = fs::close_fd(sock);
+                    die(b"server returned an error status");
+                }
+                out = create_output(arg_at(argv, 2).as_ptr());
+                if out.is_none() {
+                    let _ = fs::close_fd(sock);
+                    die(b"cannot create output file");
+                }
+            }
+        }
+        if fed.is_err() || write_failed {
+            break fed;
+        }
+        let now = get_time_ms();
+        if resp.head_done() && now.saturating_sub(last_progress) >= PROGRESS_INTERVAL_MS {
+            print_progress(&resp, now.saturating_sub(started));
+            last_progress = now;
+        }
+        if resp.is_done() {
+            break Ok(());
+        }
+    };
+    let _ = fs::close_fd(sock);
+
+    let elapsed = get_time_ms().saturating_sub(started);
+    if resp.head_done() {
+        print_progress(&resp, elapsed);
+        write_out(b"\n");
+    }
+    if let Some(fd) = out {
+        let _ = fs::close_fd(fd);
+    }
+    if write_failed {
+        die(b"write to output file failed");
+    }
+    if let Err(err) = result {
+        die(err.as_str().as_bytes());
+    }
+
+    write_out(b"Saved ");
+    write_dec(resp.received);
+    write_out(b" bytes to ");
+    write_out(arg_at(argv, 2));
+    write_out(b"\n");
+    exit_with_code(0);
+}
@@ -0,0 +1,133 @@
+//! `xrandr [--output <name|id> [--pos XxY] [--mode WxH] [--primary]]` —
+//! list or arrange display outputs.
+//!
+//! Without arguments it lists each output with its geometry on the
+//! desktop.  `--output` picks one to move with `--pos` or make primary
+//! with `--primary`; `--mode` only checks the mode, as outputs keep the one
+//! firmware set.  Installed as the shell plugin `/bin/slop-xrandr`.
+
+use slopos_abi::OutputInfo;
+use slopos_abi::display::{MAX_OUTPUTS, OUTPUT_CONFIGURE_PRIMARY};
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::apps::cli::{Usage, arg_at, parse_u32, write_out};
+use crate::syscall::core::exit_with_code;
+use crate::syscall::window;
+
+const USAGE: Usage = Usage::new(
+    b"usage: xrandr [--output <name|id> [--pos XxY] [--mode WxH] [--primary]]\n",
+    1,
+);
+
+fn fail(msg: &[u8]) -> ! {
+    write_out(b"xrandr: ");
+    write_out(msg);
+    write_out(b"\n");
+    exit_with_code(1);
+}
+
+/// Parse `AxB`, as in `1920x1080` or `1920x0`.
+fn parse_pair(arg: &[u8]) -> Option<(u32, u32)> {
+    let split = arg.iter().position(|&b| b == b'x')?;
+    Some((parse_u32(&arg[..split])?, parse_u32(&arg[split + 1..])?))
+}
+
+/// Append `bytes` to `line`, then pad with spaces to `width`.
+fn push_column(line: &mut [u8], len: &mut usize, bytes: &[u8], width: usize) {
+    for &b in bytes.iter().chain(core::iter::repeat_n(
+        &b' ',
+        width.saturating_sub(bytes.len()),
+    )) {
+        if *len < line.len() {
+            line[*len] = b;
+            *len += 1;
+        }
+    }
+}
+
+fn push_i64(line: &mut [u8], len: &mut usize, value: i64) {
+    let mut buf = NumBuf::<21>::new();
+    push_column(line, len, numfmt::trim_nul(buf.format_i64(value)), 0);
+}
+
+fn write_output(output: &OutputInfo) {
+    let mut line = [0u8; 80];
+    let mut len = 0;
+    push_column(&mut line, &mut len, output.name_str().as_bytes(), 8);
+    push_i64(&mut line, &mut len, output.id as i64);
+    push_column(&mut line, &mut len, b"  ", 0);
+    push_i64(&mut line, &mut len, output.width as i64);
+    push_column(&mut line, &mut len, b"x", 0);
+    push_i64(&mut line, &mut len, output.height as i64);
+    push_column(&mut line, &mut len, b"+", 0);
+    push_i64(&mut line, &mut len, output.x as i64);
+    push_column(&mut line, &mut len, b"+", 0);
+    push_i64(&mut line, &mut len, output.y as i64);
+    if output.is_primary() {
+        push_column(&mut line, &mut len, b" primary", 0);
+    }
+    push_column(&mut line, &mut len, b"\n", 0);
+    write_out(&line[..len]);
+}
+
+pub fn xrandr_main_args(argc: usize, argv: *const *const u8) -> ! {
+    if argc > 1 && argv.is_null() {
+        USAGE.exit();
+    }
+    let mut outputs = [OutputInfo::default(); MAX_OUTPUTS];
+    let count = window::output_enumerate(&mut outputs).min(MAX_OUTPUTS);
+    let outputs = &outputs[..count];
+    if argc <= 1 {
+        if outputs.is_empty() {
+            fail(b"no outputs");
+        }
+        for output in outputs {
+            write_output(output);
+        }
+        exit_with_code(0);
+    }
+
+    let (mut target, mut pos, mut mode, mut flags) = (None, None, None, 0);
+    let mut idx = 1;
+    while idx < argc {
+        let word = arg_at(argv, idx);
+        let value = || {
+            if idx + 1 < argc {
+                arg_at(argv, idx + 1)
+            } else {
+                USAGE.exit()
+            }
+        };
+        match word {
+            b"--output" => {
+                let name = value();
+                let found = outputs
+                    .iter()
+                    .find(|o| o.name_str().as_bytes() == name || parse_u32(name) == Some(o.id));
+                target = Some(*found.unwrap_or_else(|| fail(b"no such output")));
+                idx += 1;
+            }
+            b"--pos" => {
+                pos = Some(parse_pair(value()).unwrap_or_else(|| USAGE.exit()));
+                idx += 1;
+            }
+            b"--mode" => {
+                mode = Some(parse_pair(value()).unwrap_or_else(|| USAGE.exit()));
+                idx += 1;
+            }
+            b"--primary" => flags |= OUTPUT_CONFIGURE_PRIMARY,
+            _ => USAGE.exit(),
+        }
+        idx += 1;
+    }
+
+    let Some(output) = target else {
+        USAGE.exit();
+    };
+    let (x, y) = pos.map_or((output.x, output.y), |(x, y)| (x as i32, y as i32));
+    let (width, height) = mode.unwrap_or((0, 0));
+    if window::output_configure(output.id, x, y, width, height, flags).is_err() {
+        fail(b"output cannot take that configuration");
+    }
+    exit_with_code(0);
+}
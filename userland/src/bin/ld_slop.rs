@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[cfg(not(feature = "shared"))]
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    let _ = slopos_userland::syscall::tty::write(b"ld.slop: panic\n");
+    slopos_userland::syscall::core::exit_with_code(127);
+}
+
+/// Entry point for ld.slop — the kernel starts us on the program's initial
+/// stack.  Map and relocate the program, restore the stack exactly as it
+/// was and jump to the program's own entry point.
+#[unsafe(naked)]
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    core::arch::naked_asm!(
+        "mov r12, rsp",         // initial stack, callee-saved
+        "mov rdi, rsp",         // argc, argv, envp, auxv
+        "and rsp, -16",         // 16-byte stack alignment for call
+        "call {entry}",
+        "mov rsp, r12",
+        "xor edx, edx",         // no exit handler to register
+        "xor ebp, ebp",
+        "jmp rax",              // program entry
+        entry = sym ld_slop_entry,
+    );
+}
+
+extern "C" fn ld_slop_entry(sp: *const u64) -> u64 {
+    unsafe { slopos_userland::ldso::load_program(sp) }
+}
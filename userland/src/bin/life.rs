@@ -0,0 +1,3 @@
+#![no_std]
+#![no_main]
+slopos_userland::entry!(slopos_userland::apps::life::life_main);
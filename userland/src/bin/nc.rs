@@ -1,10 +1,10 @@
 #![no_std]
 #![no_main]
 
+#[cfg(not(feature = "shared"))]
 #[panic_handler]
-fn panic(_info: &core::panic::PanicInfo) -> ! {
-    let _ = slopos_userland::syscall::tty::write(b"panic!\n");
-    slopos_userland::syscall::core::exit_with_code(101);
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    slopos_userland::runtime::backtrace::report_panic(info)
 }
 
 /// Entry point for nc — extracts argc/argv from the user stack
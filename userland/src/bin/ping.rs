@@ -0,0 +1,30 @@
+#![no_std]
+#![no_main]
+
+#[cfg(not(feature = "shared"))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    slopos_userland::runtime::backtrace::report_panic(info)
+}
+
+/// Entry point for ping — extracts argc/argv from the user stack
+/// (placed there by the kernel's exec handler) and dispatches to
+/// ping_main_args.
+#[unsafe(naked)]
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    core::arch::naked_asm!(
+        "mov rdi, [rsp]",       // argc
+        "lea rsi, [rsp + 8]",   // argv
+        "and rsp, -16",         // 16-byte stack alignment for call
+        "call {entry}",
+        "ud2",
+        entry = sym ping_entry,
+    );
+}
+
+#[allow(unreachable_code)]
+extern "C" fn ping_entry(argc: usize, argv: *const *const u8) -> ! {
+    slopos_userland::apps::ping::ping_main_args(argc, argv);
+    slopos_userland::syscall::core::exit();
+}
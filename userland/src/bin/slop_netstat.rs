@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+slopos_userland::slop_plugin! {
+    category: b"Network",
+    desc: b"List sockets and their queues",
+    usage: b"netstat [-t] [-u] [-l]",
+    detail: b"Show every socket with its addresses, state, queue\ndepths and byte counters.\n-t / -u   only TCP / only UDP sockets\n-l        only listening sockets\nFor a listener, Recv-Q counts connections waiting\nto be accepted. Also readable as /proc/net/sockets.",
+}
+
+#[cfg(not(feature = "shared"))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    slopos_userland::runtime::backtrace::report_panic(info)
+}
+
+/// Entry point for the netstat plugin — extracts argc/argv from the user stack
+/// (placed there by the kernel's exec handler) and dispatches to
+/// netstat_main_args.
+#[unsafe(naked)]
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    core::arch::naked_asm!(
+        "mov rdi, [rsp]",       // argc
+        "lea rsi, [rsp + 8]",   // argv
+        "and rsp, -16",         // 16-byte stack alignment for call
+        "call {entry}",
+        "ud2",
+        entry = sym netstat_entry,
+    );
+}
+
+#[allow(unreachable_code)]
+extern "C" fn netstat_entry(argc: usize, argv: *const *const u8) -> ! {
+    slopos_userland::apps::netstat::netstat_main_args(argc, argv);
+    slopos_userland::syscall::core::exit();
+}
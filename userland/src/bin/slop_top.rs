@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+slopos_userland::slop_plugin! {
+    category: b"System",
+    desc: b"Show per-CPU load, busiest first",
+    usage: b"top [-n count]",
+    detail: b"Refresh every second with each CPU's busy share of\nthe last second, its run queue depth and the context\nswitches and preemptions it took.\n-n count  stop after count refreshes\nRaw counters are in /proc/stat.",
+}
+
+#[cfg(not(feature = "shared"))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    slopos_userland::runtime::backtrace::report_panic(info)
+}
+
+/// Entry point for the top plugin — extracts argc/argv from the user stack
+/// (placed there by the kernel's exec handler) and dispatches to
+/// top_main_args.
+#[unsafe(naked)]
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    core::arch::naked_asm!(
+        "mov rdi, [rsp]",       // argc
+        "lea rsi, [rsp + 8]",   // argv
+        "and rsp, -16",         // 16-byte stack alignment for call
+        "call {entry}",
+        "ud2",
+        entry = sym top_entry,
+    );
+}
+
+#[allow(unreachable_code)]
+extern "C" fn top_entry(argc: usize, argv: *const *const u8) -> ! {
+    slopos_userland::apps::top::top_main_args(argc, argv);
+    slopos_userland::syscall::core::exit();
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+slopos_userland::slop_plugin! {
+    category: b"System",
+    desc: b"List and arrange display outputs",
+    usage: b"xrandr [--output <name|id> [--pos XxY] [--mode WxH] [--primary]]",
+    detail: b"Without arguments, list each output with its mode\nand position on the desktop.\n--output   the output to configure, by name or id\n--pos      move it to XxY on the desktop\n--mode     check it shows WxH (modes are fixed)\n--primary  open stranded windows on it",
+}
+
+#[cfg(not(feature = "shared"))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    slopos_userland::runtime::backtrace::report_panic(info)
+}
+
+/// Entry point for the xrandr plugin — extracts argc/argv from the user stack
+/// (placed there by the kernel's exec handler) and dispatches to
+/// xrandr_main_args.
+#[unsafe(naked)]
+#[unsafe(no_mangle)]
+pub extern "C" fn _start() -> ! {
+    core::arch::naked_asm!(
+        "mov rdi, [rsp]",       // argc
+        "lea rsi, [rsp + 8]",   // argv
+        "and rsp, -16",         // 16-byte stack alignment for call
+        "call {entry}",
+        "ud2",
+        entry = sym xrandr_entry,
+    );
+}
+
+#[allow(unreachable_code)]
+extern "C" fn xrandr_entry(argc: usize, argv: *const *const u8) -> ! {
+    slopos_userland::apps::xrandr::xrandr_main_args(argc, argv);
+    slopos_userland::syscall::core::exit();
+}
@@ -0,0 +1,276 @@
+//! Window decorations: the title bar around a window's content and its
+//! close and minimize buttons.
+//!
+//! Drawing and hit-testing both lay the frame out from one
+//! [`DecorationTheme`], so what is drawn is what is clicked.  Windows that
+//! set `WINDOW_FLAG_UNDECORATED` get no frame at all.  The compositor
+//! scales [`decorations`] to the display with [`set_scale`].
+
+use core::cell::SyncUnsafeCell;
+
+use slopos_abi::draw::Color32;
+use slopos_abi::window::WindowInfo;
+
+use crate::gfx::{self, DamageRect, DrawBuffer, font};
+use crate::theme::*;
+
+/// Sizes of the decoration frame, in pixels.
+#[derive(Copy, Clone)]
+pub struct DecorationMetrics {
+    pub title_bar_height: i32,
+    pub title_font_size: u32,
+    pub title_inset: i32,
+    pub button_size: i32,
+    /// Gap around each button.
+    pub button_padding: i32,
+}
+
+#[derive(Copy, Clone)]
+pub struct DecorationColors {
+    pub title_bar: Color32,
+    pub title_bar_focused: Color32,
+    pub text: Color32,
+    pub button: Color32,
+    pub button_hover: Color32,
+    pub button_pressed: Color32,
+    pub close_hover: Color32,
+    pub close_pressed: Color32,
+}
+
+#[derive(Copy, Clone)]
+pub struct DecorationTheme {
+    pub metrics: DecorationMetrics,
+    pub colors: DecorationColors,
+}
+
+/// Decorations in the system theme.
+pub const DECORATIONS: DecorationTheme = DecorationTheme {
+    metrics: DecorationMetrics {
+        title_bar_height: TITLE_BAR_HEIGHT,
+        title_font_size: TITLE_FONT_SIZE,
+        title_inset: TITLE_TEXT_INSET,
+        button_size: BUTTON_SIZE,
+        button_padding: BUTTON_PADDING,
+    },
+    colors: DecorationColors {
+        title_bar: COLOR_TITLE_BAR,
+        title_bar_focused: COLOR_TITLE_BAR_FOCUSED,
+        text: COLOR_TEXT,
+        button: COLOR_BUTTON,
+        button_hover: COLOR_BUTTON_HOVER,
+        button_pressed: COLOR_BUTTON_PRESSED,
+        close_hover: COLOR_BUTTON_CLOSE_HOVER,
+        close_pressed: COLOR_BUTTON_CLOSE_PRESSED,
+    },
+};
+
+// Set once at startup and read from the main thread only.
+static SCALED: SyncUnsafeCell<DecorationTheme> = SyncUnsafeCell::new(DECORATIONS);
+
+/// Scale [`decorations`] for a display at UI scale `scale`.
+pub fn set_scale(scale: u32) {
+    // SAFETY: main thread only.
+    unsafe { *SCALED.get() = DECORATIONS.scaled(scale) }
+}
+
+/// The system decorations at the display's scale.
+pub fn decorations() -> &'static DecorationTheme {
+    // SAFETY: main thread only.
+    unsafe { &*SCALED.get() }
+}
+
+/// A part of the frame the pointer can be over.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecorationPart {
+    /// The title bar outside the buttons; dragging it moves the window.
+    TitleBar,
+    Close,
+    Minimize,
+}
+
+/// How a button is drawn.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ButtonState {
+    #[default]
+    Normal,
+    Hovered,
+    /// Held down with the pointer over it.
+    Pressed,
+}
+
+impl DecorationMetrics {
+    /// Every size multiplied by `scale`.
+    pub const fn scaled(self, scale: u32) -> Self {
+        let s = scale as i32;
+        Self {
+            title_bar_height: self.title_bar_height * s,
+            title_font_size: self.title_font_size * scale,
+            title_inset: self.title_inset * s,
+            button_size: self.button_size * s,
+            button_padding: self.button_padding * s,
+        }
+    }
+}
+
+impl DecorationTheme {
+    /// The same theme with its metrics multiplied by `scale`.
+    pub const fn scaled(self, scale: u32) -> Self {
+        Self {
+            metrics: self.metrics.scaled(scale),
+            colors: self.colors,
+        }
+    }
+
+    /// Height of the frame above `window`'s content: the title bar, or
+    /// nothing for an undecorated window.
+    pub fn top_inset(&self, window: &WindowInfo) -> i32 {
+        if window.is_decorated() {
+            self.metrics.title_bar_height
+        } else {
+            0
+        }
+    }
+
+    /// `window`'s content and frame together.
+    pub fn frame_rect(&self, window: &WindowInfo) -> DamageRect {
+        let mut rect = window.bounds();
+        rect.y0 -= self.top_inset(window);
+        rect
+    }
+
+    pub fn title_bar_rect(&self, window: &WindowInfo) -> Option<DamageRect> {
+        if !window.is_decorated() {
+            return None;
+        }
+        Some(DamageRect {
+            x0: window.x,
+            y0: window.y - self.metrics.title_bar_height,
+            x1: window.x + window.width as i32 - 1,
+            y1: window.y - 1,
+        })
+    }
+
+    /// Where button `part` sits on `window`'s title bar.  Buttons line up
+    /// from the right edge: close, then minimize.
+    pub fn button_rect(&self, window: &WindowInfo, part: DecorationPart) -> Option<DamageRect> {
+        let slot = match part {
+            DecorationPart::Close => 1,
+            DecorationPart::Minimize => 2,
+            DecorationPart::TitleBar => return None,
+        };
+        let bar = self.title_bar_rect(window)?;
+        let m = &self.metrics;
+        let x0 = bar.x1 + 1 - slot * (m.button_size + m.button_padding);
+        let y0 = bar.y0 + m.button_padding;
+        Some(DamageRect {
+            x0,
+            y0,
+            x1: x0 + m.button_size - 1,
+            y1: y0 + m.button_size - 1,
+        })
+    }
+
+    /// The part of `window`'s frame at (`x`, `y`), if any.
+    pub fn hit_test(&self, window: &WindowInfo, x: i32, y: i32) -> Option<DecorationPart> {
+        let contains = |r: DamageRect| (r.x0..=r.x1).contains(&x) && (r.y0..=r.y1).contains(&y);
+        if !contains(self.title_bar_rect(window)?) {
+            return None;
+        }
+        [DecorationPart::Close, DecorationPart::Minimize]
+            .into_iter()
+            .find(|&part| self.button_rect(window, part).is_some_and(contains))
+            .or(Some(DecorationPart::TitleBar))
+    }
+
+    /// Draw `window`'s frame within `clip`.
+    pub fn draw(
+        &self,
+        buf: &mut DrawBuffer,
+        window: &WindowInfo,
+        focused: bool,
+        close: ButtonState,
+        minimize: ButtonState,
+        clip: &DamageRect,
+    ) {
+        let Some(bar) = self.title_bar_rect(window) else {
+            return;
+        };
+        let m = &self.metrics;
+        let c = &self.colors;
+        let color = if focused {
+            c.title_bar_focused
+        } else {
+            c.title_bar
+        };
+        gfx::fill_rect_clipped(
+            buf,
+            bar.x0,
+            bar.y0,
+            bar.x1 - bar.x0 + 1,
+            m.title_bar_height,
+            color,
+            clip,
+        );
+
+        let font = font::font_for_size(m.title_font_size);
+        font::draw_text(
+            buf,
+            &font,
+            m.title_font_size,
+            bar.x0 + m.title_inset,
+            bar.y0 + (m.title_bar_height - m.title_font_size as i32) / 2,
+            window.title_str(),
+            c.text,
+            color,
+            clip,
+        );
+
+        for (part, state, label) in [
+            (DecorationPart::Close, close, "X"),
+            (DecorationPart::Minimize, minimize, "_"),
+        ] {
+            if let Some(rect) = self.button_rect(window, part) {
+                let is_close = part == DecorationPart::Close;
+                self.draw_button(buf, &font, &rect, label, state, is_close, clip);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn draw_button(
+        &self,
+        buf: &mut DrawBuffer,
+        font: &font::Font,
+        rect: &DamageRect,
+        label: &str,
+        state: ButtonState,
+        is_close: bool,
+        clip: &DamageRect,
+    ) {
+        let c = &self.colors;
+        let color = match (state, is_close) {
+            (ButtonState::Normal, _) => c.button,
+            (ButtonState::Hovered, true) => c.close_hover,
+            (ButtonState::Hovered, false) => c.button_hover,
+            (ButtonState::Pressed, true) => c.close_pressed,
+            (ButtonState::Pressed, false) => c.button_pressed,
+        };
+        let m = &self.metrics;
+        let size = m.button_size;
+        gfx::fill_rect_clipped(buf, rect.x0, rect.y0, size, size, color, clip);
+        // Centred, at the title's size unless that overflows the button.
+        let text_size = m.title_font_size.min(size as u32);
+        let text_w = font::text_width(font, text_size, label);
+        font::draw_text(
+            buf,
+            font,
+            text_size,
+            rect.x0 + (size - text_w) / 2,
+            rect.y0 + (size - text_size as i32) / 2,
+            label,
+            c.text,
+            color,
+            clip,
+        );
+    }
+}
@@ -1,11 +1,138 @@
+use core::cell::SyncUnsafeCell;
+use core::ffi::c_char;
+
+use slopos_abi::damage::DamageRect;
+use slopos_abi::draw::{Canvas, Color32};
 pub use slopos_abi::font::{
     FONT_CHAR_COUNT, FONT_CHAR_HEIGHT, FONT_CHAR_WIDTH, FONT_DATA, FONT_FIRST_CHAR, FONT_LAST_CHAR,
     get_glyph,
 };
+use slopos_abi::fs::{USER_FS_OPEN_READ, UserFsEntry, UserFsList};
 pub use slopos_gfx::canvas_font::{
     draw_char, draw_str as draw_string, str_lines as string_lines, str_width as string_width,
 };
+pub use slopos_gfx::font_render::{
+    Font, FontError, FontRegistry, MAX_FONTS, Psf2Font, draw_glyph, draw_text, text_width,
+};
+
+use crate::syscall::fs;
 
 pub fn string_height(text: &str) -> i32 {
     string_lines(text) * FONT_CHAR_HEIGHT
 }
+
+/// Directory [`load_system_fonts`] reads PSF2 fonts (`*.psf`) from.
+pub const FONT_DIR: &[u8] = b"/share/fonts\0";
+
+/// Bytes of font files a process can keep loaded.
+const FONT_STORE_SIZE: usize = 128 * 1024;
+const FONT_DIR_ENTRIES: usize = 16;
+
+// Fonts borrow from the store, which only grows, so they live as long as
+// the process.  Like the rest of the app state, these are touched from
+// the main thread only.
+static FONT_STORE: SyncUnsafeCell<[u8; FONT_STORE_SIZE]> =
+    SyncUnsafeCell::new([0; FONT_STORE_SIZE]);
+static FONT_STORE_USED: SyncUnsafeCell<usize> = SyncUnsafeCell::new(0);
+static REGISTRY: SyncUnsafeCell<FontRegistry<'static>> = SyncUnsafeCell::new(FontRegistry::new());
+
+/// Load the PSF2 font at `path` (NUL-terminated) and register it.
+/// `None` when the file can't be read or parsed, or the registry is full.
+pub fn load_font(path: &[u8]) -> Option<Font<'static>> {
+    // SAFETY: main thread only.
+    if unsafe { (*REGISTRY.get()).count() } == MAX_FONTS {
+        return None;
+    }
+    let fd = fs::open_path(path.as_ptr() as *const c_char, USER_FS_OPEN_READ).ok()?;
+
+    // SAFETY: main thread only; bytes past FONT_STORE_USED are unused.
+    let free = unsafe {
+        let used = *FONT_STORE_USED.get();
+        &mut (&mut *FONT_STORE.get())[used..]
+    };
+    let mut len = 0;
+    while len < free.len() {
+        match fs::read_slice(fd, &mut free[len..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => len += n,
+        }
+    }
+    let _ = fs::close_fd(fd);
+
+    let font = Font::Psf2(Psf2Font::parse(&free[..len]).ok()?);
+    // SAFETY: main thread only.  The font's bytes are kept by moving the
+    // store's free mark past them.
+    unsafe {
+        (*REGISTRY.get()).register(font);
+        *FONT_STORE_USED.get() += len;
+    }
+    Some(font)
+}
+
+/// Register every font in [`FONT_DIR`].  Returns how many loaded.
+pub fn load_system_fonts() -> usize {
+    let mut entries = [UserFsEntry::new(); FONT_DIR_ENTRIES];
+    let mut list = UserFsList {
+        entries: entries.as_mut_ptr(),
+        max_entries: entries.len() as u32,
+        count: 0,
+    };
+    if fs::list_dir(FONT_DIR.as_ptr() as *const c_char, &mut list).is_err() {
+        return 0;
+    }
+
+    let mut loaded = 0;
+    let mut path = [0u8; 128];
+    let dir = &FONT_DIR[..FONT_DIR.len() - 1];
+    for entry in &entries[..(list.count as usize).min(FONT_DIR_ENTRIES)] {
+        let name = entry.name_str().as_bytes();
+        if entry.is_directory() || !name.ends_with(b".psf") {
+            continue;
+        }
+        let len = dir.len() + 1 + name.len();
+        if len >= path.len() {
+            continue;
+        }
+        path[..dir.len()].copy_from_slice(dir);
+        path[dir.len()] = b'/';
+        path[dir.len() + 1..len].copy_from_slice(name);
+        path[len] = 0;
+        if load_font(&path[..=len]).is_some() {
+            loaded += 1;
+        }
+    }
+    loaded
+}
+
+/// The registered font best suited to text `size` pixels tall.
+pub fn font_for_size(size: u32) -> Font<'static> {
+    // SAFETY: main thread only.
+    unsafe { (*REGISTRY.get()).font_for_size(size) }
+}
+
+/// Draw `ch` filling the `w`x`h` cell at (`x`, `y`) with the font
+/// registered for that height, and report the cell as damaged.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_cell<T: Canvas>(
+    target: &mut T,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    ch: u8,
+    fg: Color32,
+    bg: Color32,
+) {
+    let cell = DamageRect {
+        x0: x,
+        y0: y,
+        x1: x + w as i32 - 1,
+        y1: y + h as i32 - 1,
+    };
+    let font = font_for_size(h);
+    draw_glyph(target, &font, ch as char, x, y, w, h, fg, bg, &cell);
+    let clipped = cell.clip(target.width() as i32, target.height() as i32);
+    if clipped.is_valid() {
+        target.report_damage(clipped);
+    }
+}
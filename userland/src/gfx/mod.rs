@@ -1,4 +1,5 @@
 pub mod font;
+pub mod wallpaper;
 
 pub use slopos_abi::Canvas;
 pub use slopos_abi::damage::{self, DamageRect, MAX_DAMAGE_REGIONS};
@@ -11,4 +12,10 @@ pub use slopos_gfx::canvas_ops::{
     line as draw_line, rect as draw_rect,
 };
 
+pub use slopos_gfx::blend::{Blend, alpha_byte, blend_span, fill_rect_blended_clipped, mix};
 pub use slopos_gfx::canvas_font::{draw_char_clipped, draw_str_clipped};
+pub use slopos_gfx::image::{
+    BMP_MAGIC, Bmp, ImageError, ImageFormat, PixelSource, Ppm, Qoi, RgbaImage, ScaleMode,
+    draw_image_scaled,
+};
+pub use slopos_gfx::scale::{ScaleFilter, ScaledSource};
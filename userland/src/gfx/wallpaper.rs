@@ -0,0 +1,42 @@
+//! Where the desktop wallpaper and its scaling mode live on disk.  The
+//! compositor reads them at startup and when asked to reload; the shell's
+//! `setwallpaper` writes them.
+
+use core::ffi::c_char;
+
+use slopos_abi::fs::{USER_FS_OPEN_CREAT, USER_FS_OPEN_READ, USER_FS_OPEN_WRITE};
+pub use slopos_gfx::image::ScaleMode;
+
+use crate::syscall::fs;
+
+/// The wallpaper image, an uncompressed BMP.
+pub const WALLPAPER_PATH: &[u8] = b"/etc/wallpaper.bmp\0";
+/// The [`ScaleMode`] name to fit the wallpaper with.
+pub const WALLPAPER_MODE_PATH: &[u8] = b"/etc/wallpaper.mode\0";
+
+/// The configured scaling mode, [`ScaleMode::default`] when unset.
+pub fn read_mode() -> ScaleMode {
+    let Ok(fd) = fs::open_path(
+        WALLPAPER_MODE_PATH.as_ptr() as *const c_char,
+        USER_FS_OPEN_READ,
+    ) else {
+        return ScaleMode::default();
+    };
+    let mut name = [0u8; 16];
+    let len = fs::read_slice(fd, &mut name).unwrap_or(0);
+    let _ = fs::close_fd(fd);
+    let name = name[..len].trim_ascii();
+    ScaleMode::from_name(name).unwrap_or_default()
+}
+
+/// Save `mode` as the scaling mode.  False when the file can't be written.
+pub fn write_mode(mode: ScaleMode) -> bool {
+    let path = WALLPAPER_MODE_PATH.as_ptr() as *const c_char;
+    let _ = fs::unlink_path(path);
+    let Ok(fd) = fs::open_path(path, USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT) else {
+        return false;
+    };
+    let written = fs::write_slice(fd, mode.name()).is_ok();
+    let _ = fs::close_fd(fd);
+    written
+}
@@ -0,0 +1,649 @@
+//! `ld.slop`, the runtime loader for dynamically linked programs.
+//!
+//! The kernel maps a program whose `PT_INTERP` names `/lib/ld.slop`, then
+//! maps the loader at `AT_BASE` with its own relative relocations already
+//! applied and starts it on the program's initial stack.  The loader maps
+//! every `DT_NEEDED` library from `/lib`, binds all relocations eagerly and
+//! returns the program's entry point; `_start` in `bin/ld_slop.rs` jumps
+//! there with the stack untouched.
+//!
+//! Symbols resolve in one global scope: the program first, then the
+//! libraries in load order.  Only `DT_HASH` tables are searched, so shared
+//! objects must be linked with `--hash-style=sysv`.  Init and fini arrays
+//! are not run.
+
+use core::ffi::c_char;
+use core::ptr;
+
+use slopos_abi::auxv::{AT_ENTRY, AT_NULL, AT_PHDR, AT_PHNUM};
+
+use crate::syscall::{
+    MAP_FIXED, PROT_EXEC, PROT_READ, PROT_WRITE, USER_FS_OPEN_READ, UserFsStat, core as sys_core,
+    fs, memory, tty,
+};
+
+const ET_DYN: u16 = 3;
+const EM_X86_64: u16 = 0x3E;
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_PHDR: u32 = 6;
+
+const PF_X: u32 = 0x1;
+const PF_W: u32 = 0x2;
+const PF_R: u32 = 0x4;
+
+const DT_NULL: u64 = 0;
+const DT_NEEDED: u64 = 1;
+const DT_PLTRELSZ: u64 = 2;
+const DT_HASH: u64 = 4;
+const DT_STRTAB: u64 = 5;
+const DT_SYMTAB: u64 = 6;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_JMPREL: u64 = 23;
+
+const R_X86_64_NONE: u32 = 0;
+const R_X86_64_64: u32 = 1;
+const R_X86_64_GLOB_DAT: u32 = 6;
+const R_X86_64_JUMP_SLOT: u32 = 7;
+const R_X86_64_RELATIVE: u32 = 8;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STB_WEAK: u8 = 2;
+const SHN_UNDEF: u16 = 0;
+
+const ELF_HEADER_SIZE: u64 = 64;
+const PHDR_SIZE: u64 = 56;
+const DYN_SIZE: u64 = 16;
+const RELA_SIZE: u64 = 24;
+const SYM_SIZE: u64 = 24;
+const PAGE_SIZE: u64 = 4096;
+
+/// Directory searched for `DT_NEEDED` libraries.
+pub const LIB_DIR: &[u8] = b"/lib/";
+
+/// Program plus libraries.
+const MAX_OBJECTS: usize = 8;
+const MAX_SEGMENTS: usize = 8;
+const MAX_NAME_LEN: usize = 255;
+const MAX_LIB_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Exit status when the program cannot be loaded.
+const LOAD_FAILURE_EXIT: i32 = 127;
+
+struct LoadError {
+    what: &'static str,
+    name: &'static [u8],
+}
+
+impl LoadError {
+    fn new(what: &'static str, name: &'static [u8]) -> Self {
+        Self { what, name }
+    }
+}
+
+type LoadResult<T> = Result<T, LoadError>;
+
+#[derive(Clone, Copy, Default)]
+struct Segment {
+    start: u64,
+    end: u64,
+    prot: u64,
+}
+
+/// A mapped ELF object and the parts of its dynamic section we use.
+/// Table addresses are already biased.
+#[derive(Clone, Copy, Default)]
+struct Object {
+    /// `DT_NEEDED` name; empty for the program.
+    name: &'static [u8],
+    bias: u64,
+    dynamic: u64,
+    strtab: u64,
+    symtab: u64,
+    hash: u64,
+    rela: u64,
+    relasz: u64,
+    jmprel: u64,
+    pltrelsz: u64,
+    /// Segments to protect once relocated; the kernel already mapped the
+    /// program's with their final permissions.
+    segments: [Segment; MAX_SEGMENTS],
+    segment_count: usize,
+}
+
+#[derive(Default)]
+struct AuxInfo {
+    phdr: u64,
+    phnum: u64,
+    entry: u64,
+}
+
+struct Loader {
+    objects: [Object; MAX_OBJECTS],
+    count: usize,
+}
+
+#[inline]
+unsafe fn read_u8(addr: u64) -> u8 {
+    ptr::read(addr as *const u8)
+}
+
+#[inline]
+unsafe fn read_u16(addr: u64) -> u16 {
+    ptr::read_unaligned(addr as *const u16)
+}
+
+#[inline]
+unsafe fn read_u32(addr: u64) -> u32 {
+    ptr::read_unaligned(addr as *const u32)
+}
+
+#[inline]
+unsafe fn read_u64(addr: u64) -> u64 {
+    ptr::read_unaligned(addr as *const u64)
+}
+
+/// The NUL-terminated string at `addr`; mapped images are never unmapped,
+/// so it lives for the rest of the program.
+unsafe fn cstr_at(addr: u64) -> &'static [u8] {
+    let mut len = 0;
+    while len < MAX_NAME_LEN && read_u8(addr + len as u64) != 0 {
+        len += 1;
+    }
+    core::slice::from_raw_parts(addr as *const u8, len)
+}
+
+fn page_down(addr: u64) -> u64 {
+    addr & !(PAGE_SIZE - 1)
+}
+
+fn page_up(addr: u64) -> u64 {
+    (addr + PAGE_SIZE - 1) & !(PAGE_SIZE - 1)
+}
+
+/// SysV ELF symbol hash.
+fn elf_hash(name: &[u8]) -> u32 {
+    let mut h: u32 = 0;
+    for &b in name {
+        h = (h << 4).wrapping_add(b as u32);
+        let g = h & 0xF000_0000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+unsafe fn read_auxv(sp: *const u64) -> AuxInfo {
+    let argc = *sp as usize;
+    let mut p = sp.add(1 + argc + 1);
+    while *p != 0 {
+        p = p.add(1);
+    }
+    p = p.add(1);
+
+    let mut aux = AuxInfo::default();
+    while *p != AT_NULL {
+        let val = *p.add(1);
+        match *p {
+            AT_PHDR => aux.phdr = val,
+            AT_PHNUM => aux.phnum = val,
+            AT_ENTRY => aux.entry = val,
+            _ => {}
+        }
+        p = p.add(2);
+    }
+    aux
+}
+
+unsafe fn parse_dynamic(name: &'static [u8], bias: u64, dynamic: u64) -> Object {
+    let mut obj = Object {
+        name,
+        bias,
+        dynamic,
+        ..Object::default()
+    };
+    let mut entry = dynamic;
+    loop {
+        let val = read_u64(entry + 8);
+        match read_u64(entry) {
+            DT_NULL => break,
+            DT_HASH => obj.hash = bias + val,
+            DT_STRTAB => obj.strtab = bias + val,
+            DT_SYMTAB => obj.symtab = bias + val,
+            DT_RELA => obj.rela = bias + val,
+            DT_RELASZ => obj.relasz = val,
+            DT_JMPREL => obj.jmprel = bias + val,
+            DT_PLTRELSZ => obj.pltrelsz = val,
+            _ => {}
+        }
+        entry += DYN_SIZE;
+    }
+    obj
+}
+
+/// The program the kernel mapped, found through its program headers.
+unsafe fn program_object(aux: &AuxInfo) -> LoadResult<Object> {
+    if aux.phdr == 0 || aux.entry == 0 {
+        return Err(LoadError::new("program headers not mapped", b""));
+    }
+
+    let mut phdr_vaddr = None;
+    let mut first_load = None;
+    let mut dynamic = None;
+    for i in 0..aux.phnum {
+        let ph = aux.phdr + i * PHDR_SIZE;
+        let vaddr = read_u64(ph + 16);
+        match read_u32(ph) {
+            PT_PHDR => phdr_vaddr = Some(vaddr),
+            PT_LOAD if read_u64(ph + 8) == 0 && first_load.is_none() => first_load = Some(vaddr),
+            PT_DYNAMIC => dynamic = Some(vaddr),
+            _ => {}
+        }
+    }
+
+    // Without PT_PHDR the headers sit right after the ELF header at the
+    // start of the first segment.
+    let bias = match (phdr_vaddr, first_load) {
+        (Some(vaddr), _) => aux.phdr.wrapping_sub(vaddr),
+        (None, Some(vaddr)) => aux.phdr.wrapping_sub(vaddr + ELF_HEADER_SIZE),
+        (None, None) => return Err(LoadError::new("cannot locate program image", b"")),
+    };
+    let Some(dynamic) = dynamic else {
+        return Err(LoadError::new("program has no PT_DYNAMIC", b""));
+    };
+    Ok(parse_dynamic(b"", bias, bias.wrapping_add(dynamic)))
+}
+
+/// An anonymous mapping holding a library file, unmapped on drop.
+struct FileMapping {
+    addr: u64,
+    len: u64,
+}
+
+impl FileMapping {
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: `addr` is a live read/write mapping of `len` bytes that
+        // only this value refers to.
+        unsafe { core::slice::from_raw_parts(self.addr as *const u8, self.len as usize) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as in `bytes`, and `&mut self` makes the borrow unique.
+        unsafe { core::slice::from_raw_parts_mut(self.addr as *mut u8, self.len as usize) }
+    }
+}
+
+impl Drop for FileMapping {
+    fn drop(&mut self) {
+        memory::munmap(self.addr, self.len);
+    }
+}
+
+/// Read `/lib/<name>` into a temporary anonymous mapping.
+unsafe fn read_library(name: &'static [u8]) -> LoadResult<FileMapping> {
+    let mut path = [0u8; LIB_DIR.len() + MAX_NAME_LEN + 1];
+    path[..LIB_DIR.len()].copy_from_slice(LIB_DIR);
+    path[LIB_DIR.len()..LIB_DIR.len() + name.len()].copy_from_slice(name);
+    let path_ptr = path.as_ptr() as *const c_char;
+
+    let mut stat = UserFsStat::default();
+    if fs::stat_path(path_ptr, &mut stat).is_err() {
+        return Err(LoadError::new("cannot find library", name));
+    }
+    let size = stat.size as u64;
+    if size < ELF_HEADER_SIZE || size > MAX_LIB_SIZE {
+        return Err(LoadError::new("bad library size", name));
+    }
+
+    let addr = memory::mmap_anon(0, size, PROT_READ | PROT_WRITE, 0);
+    if addr == 0 {
+        return Err(LoadError::new("out of memory reading", name));
+    }
+    let mut mapping = FileMapping { addr, len: size };
+    let image = mapping.bytes_mut();
+
+    let Ok(fd) = fs::open_path(path_ptr, USER_FS_OPEN_READ) else {
+        return Err(LoadError::new("cannot open library", name));
+    };
+    let mut filled = 0;
+    while filled < image.len() {
+        match fs::read_slice(fd, &mut image[filled..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => filled += n,
+        }
+    }
+    let _ = fs::close_fd(fd);
+    if filled < image.len() {
+        return Err(LoadError::new("short read on library", name));
+    }
+    Ok(mapping)
+}
+
+/// Map the PT_LOAD segments of a shared object at an address of the
+/// kernel's choosing and parse its dynamic section.
+unsafe fn map_library(name: &'static [u8], file: &[u8]) -> LoadResult<Object> {
+    let bad = || LoadError::new("not a shared object", name);
+    let field_u16 = |off: usize| u16::from_le_bytes([file[off], file[off + 1]]);
+    let field_u64 = |off: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&file[off..off + 8]);
+        u64::from_le_bytes(bytes)
+    };
+
+    if file[0..4] != [0x7F, b'E', b'L', b'F']
+        || file[4] != 2
+        || file[5] != 1
+        || field_u16(16) != ET_DYN
+        || field_u16(18) != EM_X86_64
+    {
+        return Err(bad());
+    }
+    let phoff = field_u64(32);
+    let phentsize = field_u16(54) as u64;
+    let phnum = field_u16(56) as u64;
+    if phentsize < PHDR_SIZE
+        || phoff
+            .checked_add(phnum * phentsize)
+            .is_none_or(|end| end > file.len() as u64)
+    {
+        return Err(bad());
+    }
+
+    let mut low = u64::MAX;
+    let mut high = 0;
+    let mut dynamic = None;
+    for i in 0..phnum {
+        let ph = (phoff + i * phentsize) as usize;
+        let p_type = u32::from_le_bytes([file[ph], file[ph + 1], file[ph + 2], file[ph + 3]]);
+        let (offset, vaddr) = (field_u64(ph + 8), field_u64(ph + 16));
+        let (filesz, memsz) = (field_u64(ph + 32), field_u64(ph + 40));
+        match p_type {
+            PT_LOAD => {
+                if filesz > memsz
+                    || offset
+                        .checked_add(filesz)
+                        .is_none_or(|end| end > file.len() as u64)
+                    || vaddr
+                        .checked_add(memsz)
+                        .is_none_or(|end| end > MAX_LIB_SIZE)
+                {
+                    return Err(bad());
+                }
+                low = low.min(page_down(vaddr));
+                high = high.max(page_up(vaddr + memsz));
+            }
+            PT_DYNAMIC => dynamic = Some(vaddr),
+            _ => {}
+        }
+    }
+    let Some(dynamic) = dynamic else {
+        return Err(bad());
+    };
+    if low >= high {
+        return Err(bad());
+    }
+
+    // Let the kernel pick a hole big enough for the whole image, then map
+    // each segment into it separately.
+    let span = high - low;
+    let base = memory::mmap_anon(0, span, PROT_READ | PROT_WRITE, 0);
+    if base == 0 {
+        return Err(LoadError::new("out of memory mapping", name));
+    }
+    memory::munmap(base, span);
+    let bias = base.wrapping_sub(low);
+
+    let mapped = map_segments(name, file, bias, phoff, phentsize, phnum)?;
+    Ok(Object {
+        segments: mapped.segments,
+        segment_count: mapped.segment_count,
+        ..parse_dynamic(name, bias, bias + dynamic)
+    })
+}
+
+/// Map and fill each PT_LOAD segment of `file` at `bias`.
+unsafe fn map_segments(
+    name: &'static [u8],
+    file: &[u8],
+    bias: u64,
+    phoff: u64,
+    phentsize: u64,
+    phnum: u64,
+) -> LoadResult<Object> {
+    let mut obj = Object {
+        name,
+        bias,
+        ..Object::default()
+    };
+    let field_u64 = |off: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&file[off..off + 8]);
+        u64::from_le_bytes(bytes)
+    };
+
+    for i in 0..phnum {
+        let ph = (phoff + i * phentsize) as usize;
+        if u32::from_le_bytes([file[ph], file[ph + 1], file[ph + 2], file[ph + 3]]) != PT_LOAD {
+            continue;
+        }
+        if obj.segment_count == MAX_SEGMENTS {
+            return Err(LoadError::new("too many segments in", name));
+        }
+        let flags = u32::from_le_bytes([file[ph + 4], file[ph + 5], file[ph + 6], file[ph + 7]]);
+        if flags & (PF_W | PF_X) == (PF_W | PF_X) {
+            return Err(LoadError::new("writable and executable segment in", name));
+        }
+        let (offset, vaddr) = (field_u64(ph + 8), field_u64(ph + 16));
+        let (filesz, memsz) = (field_u64(ph + 32), field_u64(ph + 40));
+
+        let start = page_down(bias + vaddr);
+        let end = page_up(bias + vaddr + memsz);
+        if memory::mmap_anon(start, end - start, PROT_READ | PROT_WRITE, MAP_FIXED) != start {
+            return Err(LoadError::new("cannot map segment of", name));
+        }
+        ptr::copy_nonoverlapping(
+            file.as_ptr().add(offset as usize),
+            (bias + vaddr) as *mut u8,
+            filesz as usize,
+        );
+        // Fault every page in now: mprotect only rewrites pages that are
+        // present, and adjacent anonymous mappings may share one VMA.
+        let mut page = start;
+        while page < end {
+            let p = page as *mut u8;
+            ptr::write_volatile(p, ptr::read_volatile(p));
+            page += PAGE_SIZE;
+        }
+
+        let mut prot = 0;
+        if flags & PF_R != 0 {
+            prot |= PROT_READ;
+        }
+        if flags & PF_W != 0 {
+            prot |= PROT_WRITE;
+        }
+        if flags & PF_X != 0 {
+            prot |= PROT_EXEC;
+        }
+        obj.segments[obj.segment_count] = Segment { start, end, prot };
+        obj.segment_count += 1;
+    }
+    Ok(obj)
+}
+
+impl Loader {
+    fn new() -> Self {
+        Self {
+            objects: [Object::default(); MAX_OBJECTS],
+            count: 0,
+        }
+    }
+
+    fn push(&mut self, obj: Object) -> LoadResult<()> {
+        if self.count == MAX_OBJECTS {
+            return Err(LoadError::new("too many libraries", obj.name));
+        }
+        self.objects[self.count] = obj;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn is_loaded(&self, name: &[u8]) -> bool {
+        self.objects[1..self.count].iter().any(|o| o.name == name)
+    }
+
+    /// Load the DT_NEEDED closure of every object, breadth first.
+    unsafe fn load_needed(&mut self) -> LoadResult<()> {
+        let mut next = 0;
+        while next < self.count {
+            let obj = self.objects[next];
+            let mut entry = obj.dynamic;
+            loop {
+                let tag = read_u64(entry);
+                if tag == DT_NULL {
+                    break;
+                }
+                if tag == DT_NEEDED {
+                    let name = cstr_at(obj.strtab + read_u64(entry + 8));
+                    if !name.is_empty() && !self.is_loaded(name) {
+                        let file = read_library(name)?;
+                        let mapped = map_library(name, file.bytes());
+                        drop(file);
+                        self.push(mapped?)?;
+                    }
+                }
+                entry += DYN_SIZE;
+            }
+            next += 1;
+        }
+        Ok(())
+    }
+
+    /// Address of the first global definition of `name` in load order.
+    unsafe fn lookup(&self, name: &[u8]) -> Option<u64> {
+        let hash = elf_hash(name);
+        for obj in &self.objects[..self.count] {
+            if obj.hash == 0 || obj.symtab == 0 {
+                continue;
+            }
+            let nbucket = read_u32(obj.hash);
+            let nchain = read_u32(obj.hash + 4);
+            if nbucket == 0 {
+                continue;
+            }
+            let buckets = obj.hash + 8;
+            let chains = buckets + nbucket as u64 * 4;
+
+            let mut index = read_u32(buckets + (hash % nbucket) as u64 * 4);
+            let mut steps = 0;
+            while index != 0 && index < nchain && steps < nchain {
+                let sym = obj.symtab + index as u64 * SYM_SIZE;
+                let bind = read_u8(sym + 4) >> 4;
+                if read_u16(sym + 6) != SHN_UNDEF
+                    && (bind == STB_GLOBAL || bind == STB_WEAK)
+                    && cstr_at(obj.strtab + read_u32(sym) as u64) == name
+                {
+                    return Some(obj.bias.wrapping_add(read_u64(sym + 8)));
+                }
+                index = read_u32(chains + index as u64 * 4);
+                steps += 1;
+            }
+        }
+        None
+    }
+
+    unsafe fn resolve(&self, obj: &Object, index: u32) -> LoadResult<u64> {
+        let sym = obj.symtab + index as u64 * SYM_SIZE;
+        let bind = read_u8(sym + 4) >> 4;
+        if bind == STB_LOCAL && read_u16(sym + 6) != SHN_UNDEF {
+            return Ok(obj.bias.wrapping_add(read_u64(sym + 8)));
+        }
+        let name = cstr_at(obj.strtab + read_u32(sym) as u64);
+        match self.lookup(name) {
+            Some(addr) => Ok(addr),
+            None if bind == STB_WEAK => Ok(0),
+            None => Err(LoadError::new("undefined symbol", name)),
+        }
+    }
+
+    unsafe fn apply_relocations(&self, obj: &Object, table: u64, size: u64) -> LoadResult<()> {
+        if table == 0 {
+            return Ok(());
+        }
+        let mut entry = table;
+        while entry < table + size {
+            let info = read_u64(entry + 8);
+            let addend = read_u64(entry + 16) as i64;
+            let target = obj.bias.wrapping_add(read_u64(entry));
+            let symbol = (info >> 32) as u32;
+            let value = match (info & 0xFFFF_FFFF) as u32 {
+                R_X86_64_NONE => None,
+                R_X86_64_RELATIVE => Some(obj.bias.wrapping_add_signed(addend)),
+                R_X86_64_GLOB_DAT | R_X86_64_JUMP_SLOT => Some(self.resolve(obj, symbol)?),
+                R_X86_64_64 => Some(self.resolve(obj, symbol)?.wrapping_add_signed(addend)),
+                _ => return Err(LoadError::new("unsupported relocation in", obj.name)),
+            };
+            if let Some(value) = value {
+                ptr::write_unaligned(target as *mut u64, value);
+            }
+            entry += RELA_SIZE;
+        }
+        Ok(())
+    }
+
+    /// Relocate every object, then drop the write access the libraries
+    /// were mapped with.
+    unsafe fn relocate_all(&self) -> LoadResult<()> {
+        for obj in self.objects[..self.count].iter().rev() {
+            self.apply_relocations(obj, obj.rela, obj.relasz)?;
+            self.apply_relocations(obj, obj.jmprel, obj.pltrelsz)?;
+        }
+        for obj in &self.objects[1..self.count] {
+            for seg in &obj.segments[..obj.segment_count] {
+                if memory::mprotect(seg.start, seg.end - seg.start, seg.prot) != 0 {
+                    return Err(LoadError::new("cannot protect segment of", obj.name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+unsafe fn run(sp: *const u64) -> LoadResult<u64> {
+    let aux = read_auxv(sp);
+    let mut loader = Loader::new();
+    loader.push(program_object(&aux)?)?;
+    loader.load_needed()?;
+    loader.relocate_all()?;
+    Ok(aux.entry)
+}
+
+fn fail(err: LoadError) -> ! {
+    let _ = tty::write(b"ld.slop: ");
+    let _ = tty::write(err.what.as_bytes());
+    if !err.name.is_empty() {
+        let _ = tty::write(b" ");
+        let _ = tty::write(err.name);
+    }
+    let _ = tty::write(b"\n");
+    sys_core::exit_with_code(LOAD_FAILURE_EXIT);
+}
+
+/// Map and relocate the program whose initial stack starts at `sp` and
+/// return its entry point; exits with status 127 if that fails.
+///
+/// # Safety
+///
+/// `sp` must point at the stack the kernel prepared (argc, argv, envp,
+/// auxv) and nothing else may have run in the process yet.
+pub unsafe fn load_program(sp: *const u64) -> u64 {
+    match run(sp) {
+        Ok(entry) => entry,
+        Err(err) => fail(err),
+    }
+}
@@ -4,8 +4,11 @@
 
 pub mod appkit;
 pub mod apps;
+pub mod decorations;
 pub mod gfx;
+pub mod ldso;
 pub mod libc;
+pub mod plugin;
 pub mod program_registry;
 pub mod runtime;
 pub mod syscall;
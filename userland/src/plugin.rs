@@ -0,0 +1,222 @@
+//! Shell plugins: executables installed as `/bin/slop-<name>` that the
+//! shell runs as the command `<name>`.
+//!
+//! A plugin describes itself to `help` with an ELF note declared by
+//! [`slop_plugin!`](crate::slop_plugin) in its binary.  The note goes in the
+//! `.note.slopos` section, which `userland.ld` keeps, so the linker puts it
+//! in a `PT_NOTE` segment where [`read_plugin_info`] finds it without
+//! running the program.  Its owner is `SlopOS` and its descriptor holds four
+//! NUL-terminated strings: the help category, a one-line description, the
+//! usage line and the detail text.
+
+use core::ffi::c_char;
+
+use slopos_abi::syscall::SEEK_SET;
+
+use crate::syscall::{RawFd, USER_FS_OPEN_READ, fs};
+
+/// Directory searched for plugins.
+pub const PLUGIN_DIR: &[u8] = b"/bin";
+/// File name prefix that marks an executable in [`PLUGIN_DIR`] as a plugin.
+pub const PLUGIN_PREFIX: &[u8] = b"slop-";
+/// Note owner, NUL-terminated and padded to four bytes.
+pub const PLUGIN_NOTE_OWNER: [u8; 8] = *b"SlopOS\0\0";
+/// Note type of the plugin description.
+pub const NT_SLOPOS_PLUGIN: u32 = 1;
+/// Longest note descriptor [`read_plugin_info`] accepts.
+pub const PLUGIN_NOTE_MAX: usize = 1024;
+
+const PT_NOTE: u32 = 4;
+const ELF_HEADER_LEN: usize = 64;
+const PHDR_LEN: usize = 56;
+const NOTE_HEADER_LEN: usize = 12;
+
+/// The note as laid out in the binary.  Declared through
+/// [`slop_plugin!`](crate::slop_plugin).
+#[repr(C, align(4))]
+pub struct PluginNote<const N: usize> {
+    namesz: u32,
+    descsz: u32,
+    kind: u32,
+    name: [u8; 8],
+    desc: [u8; N],
+}
+
+/// Descriptor size for `fields`, padded to four bytes.
+pub const fn plugin_note_len(fields: [&[u8]; 4]) -> usize {
+    let len = fields[0].len() + fields[1].len() + fields[2].len() + fields[3].len() + 4;
+    (len + 3) & !3
+}
+
+impl<const N: usize> PluginNote<N> {
+    /// `N` must be [`plugin_note_len`] of `fields`.
+    pub const fn new(fields: [&[u8]; 4]) -> Self {
+        let mut desc = [0u8; N];
+        let mut pos = 0;
+        let mut f = 0;
+        while f < fields.len() {
+            let field = fields[f];
+            let mut i = 0;
+            while i < field.len() {
+                desc[pos] = field[i];
+                pos += 1;
+                i += 1;
+            }
+            // The terminating NUL is already there.
+            pos += 1;
+            f += 1;
+        }
+        Self {
+            namesz: 7,
+            descsz: pos as u32,
+            kind: NT_SLOPOS_PLUGIN,
+            name: PLUGIN_NOTE_OWNER,
+            desc,
+        }
+    }
+}
+
+/// Describe the binary as a shell plugin.  Each field is a byte string;
+/// `category` is a `help` section such as `b"Network"`, and plugins with a
+/// category the shell does not know are listed under "Plugins".
+#[macro_export]
+macro_rules! slop_plugin {
+    (
+        category: $category:expr,
+        desc: $desc:expr,
+        usage: $usage:expr,
+        detail: $detail:expr $(,)?
+    ) => {
+        const _: () = {
+            const FIELDS: [&[u8]; 4] = [$category, $desc, $usage, $detail];
+            #[used]
+            #[unsafe(link_section = ".note.slopos")]
+            static PLUGIN_NOTE: $crate::plugin::PluginNote<
+                { $crate::plugin::plugin_note_len(FIELDS) },
+            > = $crate::plugin::PluginNote::new(FIELDS);
+        };
+    };
+}
+
+/// A plugin's description, read back from its note.
+pub struct PluginInfo {
+    desc: [u8; PLUGIN_NOTE_MAX],
+    /// Start of each field in `desc`; field `i` ends at the NUL before
+    /// `starts[i + 1]`.
+    starts: [usize; 5],
+}
+
+impl PluginInfo {
+    fn parse(desc: &[u8]) -> Option<Self> {
+        let mut info = Self {
+            desc: [0; PLUGIN_NOTE_MAX],
+            starts: [0; 5],
+        };
+        info.desc.get_mut(..desc.len())?.copy_from_slice(desc);
+        let mut pos = 0;
+        for start in info.starts.iter_mut().skip(1) {
+            pos += desc.get(pos..)?.iter().position(|&b| b == 0)? + 1;
+            *start = pos;
+        }
+        Some(info)
+    }
+
+    fn field(&self, idx: usize) -> &[u8] {
+        &self.desc[self.starts[idx]..self.starts[idx + 1] - 1]
+    }
+
+    pub fn category(&self) -> &[u8] {
+        self.field(0)
+    }
+
+    pub fn desc(&self) -> &[u8] {
+        self.field(1)
+    }
+
+    pub fn usage(&self) -> &[u8] {
+        self.field(2)
+    }
+
+    pub fn detail(&self) -> &[u8] {
+        self.field(3)
+    }
+}
+
+fn read_at(fd: RawFd, offset: u64, buf: &mut [u8]) -> Option<()> {
+    fs::lseek(fd, offset as i64, SEEK_SET as u32).ok()?;
+    (fs::read_slice(fd, buf).ok()? == buf.len()).then_some(())
+}
+
+fn u16_at(buf: &[u8], off: usize) -> usize {
+    u16::from_le_bytes([buf[off], buf[off + 1]]) as usize
+}
+
+fn u32_at(buf: &[u8], off: usize) -> u32 {
+    u32::from_le_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+fn u64_at(buf: &[u8], off: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[off..off + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Find the plugin note among the notes in `notes`.
+fn find_note(notes: &[u8]) -> Option<PluginInfo> {
+    let mut pos = 0;
+    while pos + NOTE_HEADER_LEN <= notes.len() {
+        let namesz = u32_at(notes, pos) as usize;
+        let descsz = u32_at(notes, pos + 4) as usize;
+        let kind = u32_at(notes, pos + 8);
+        let name_start = pos + NOTE_HEADER_LEN;
+        let desc_start = name_start + namesz.next_multiple_of(4);
+        let desc = notes.get(desc_start..desc_start.checked_add(descsz)?)?;
+        if kind == NT_SLOPOS_PLUGIN
+            && namesz == 7
+            && notes[name_start..name_start + namesz] == PLUGIN_NOTE_OWNER[..namesz]
+        {
+            return PluginInfo::parse(desc);
+        }
+        pos = desc_start + descsz.next_multiple_of(4);
+    }
+    None
+}
+
+fn read_info(fd: RawFd) -> Option<PluginInfo> {
+    let mut ehdr = [0u8; ELF_HEADER_LEN];
+    read_at(fd, 0, &mut ehdr)?;
+    // 64-bit little-endian ELF only.
+    if ehdr[..4] != *b"\x7fELF" || ehdr[4] != 2 || ehdr[5] != 1 {
+        return None;
+    }
+    let phoff = u64_at(&ehdr, 0x20);
+    let phentsize = u16_at(&ehdr, 0x36);
+    let phnum = u16_at(&ehdr, 0x38);
+    if phentsize < PHDR_LEN {
+        return None;
+    }
+
+    let mut phdr = [0u8; PHDR_LEN];
+    let mut notes = [0u8; PLUGIN_NOTE_MAX + 64];
+    for i in 0..phnum {
+        read_at(fd, phoff + (i * phentsize) as u64, &mut phdr)?;
+        if u32_at(&phdr, 0) != PT_NOTE {
+            continue;
+        }
+        let filesz = (u64_at(&phdr, 0x20) as usize).min(notes.len());
+        read_at(fd, u64_at(&phdr, 8), &mut notes[..filesz])?;
+        if let Some(info) = find_note(&notes[..filesz]) {
+            return Some(info);
+        }
+    }
+    None
+}
+
+/// Read the plugin note of the executable at `path`, a NUL-terminated path.
+/// `None` if the file is not an ELF binary or has no plugin note.
+pub fn read_plugin_info(path: *const c_char) -> Option<PluginInfo> {
+    let fd = fs::open_path(path, USER_FS_OPEN_READ).ok()?;
+    let info = read_info(fd);
+    let _ = fs::close_fd(fd);
+    info
+}
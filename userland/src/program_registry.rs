@@ -1,4 +1,6 @@
-use slopos_abi::task::{TASK_FLAG_COMPOSITOR, TASK_FLAG_DISPLAY_EXCLUSIVE, TASK_FLAG_USER_MODE};
+use slopos_abi::task::{
+    TASK_FLAG_COMPOSITOR, TASK_FLAG_DISPLAY_EXCLUSIVE, TASK_FLAG_NET_RAW, TASK_FLAG_USER_MODE,
+};
 
 #[derive(Clone, Copy)]
 pub struct ProgramSpec {
@@ -26,7 +28,7 @@ const PROGRAM_REGISTRY: &[ProgramSpec] = &[
         name: b"shell",
         path: b"/bin/shell",
         priority: 5,
-        flags: TASK_FLAG_USER_MODE,
+        flags: TASK_FLAG_USER_MODE | TASK_FLAG_NET_RAW,
         desc: b"",
         gui: false,
     },
@@ -34,7 +36,7 @@ const PROGRAM_REGISTRY: &[ProgramSpec] = &[
         name: b"compositor",
         path: b"/bin/compositor",
         priority: 4,
-        flags: TASK_FLAG_USER_MODE | TASK_FLAG_COMPOSITOR,
+        flags: TASK_FLAG_USER_MODE | TASK_FLAG_COMPOSITOR | TASK_FLAG_NET_RAW,
         desc: b"",
         gui: true,
     },
@@ -51,7 +53,7 @@ const PROGRAM_REGISTRY: &[ProgramSpec] = &[
         path: b"/bin/file_manager",
         priority: 5,
         flags: TASK_FLAG_USER_MODE,
-        desc: b"Browse filesystem",
+        desc: b"Browse, copy and move files",
         gui: true,
     },
     ProgramSpec {
@@ -62,6 +64,38 @@ const PROGRAM_REGISTRY: &[ProgramSpec] = &[
         desc: b"System information panel",
         gui: true,
     },
+    ProgramSpec {
+        name: b"htop",
+        path: b"/bin/htop",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Monitor and kill tasks",
+        gui: true,
+    },
+    ProgramSpec {
+        name: b"slopedit",
+        path: b"/bin/slopedit",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Edit text files",
+        gui: true,
+    },
+    ProgramSpec {
+        name: b"terminal",
+        path: b"/bin/terminal",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Terminal emulator running the shell",
+        gui: true,
+    },
+    ProgramSpec {
+        name: b"imgview",
+        path: b"/bin/imgview",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"View BMP, PPM and QOI images",
+        gui: true,
+    },
     ProgramSpec {
         name: b"nmap",
         path: b"/bin/nmap",
@@ -86,6 +120,78 @@ const PROGRAM_REGISTRY: &[ProgramSpec] = &[
         desc: b"Network Swiss army knife",
         gui: false,
     },
+    ProgramSpec {
+        name: b"life",
+        path: b"/bin/life",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Game of Life demo",
+        gui: true,
+    },
+    ProgramSpec {
+        name: b"beep",
+        path: b"/bin/beep",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Play a tone",
+        gui: false,
+    },
+    ProgramSpec {
+        name: b"wavplay",
+        path: b"/bin/wavplay",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Play a WAV file",
+        gui: false,
+    },
+    ProgramSpec {
+        name: b"mdns-browse",
+        path: b"/bin/mdns-browse",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Browse mDNS services",
+        gui: false,
+    },
+    ProgramSpec {
+        name: b"ping",
+        path: b"/bin/ping",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Send ICMP echo requests",
+        gui: false,
+    },
+    ProgramSpec {
+        name: b"wget",
+        path: b"/bin/wget",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Download a file over HTTP",
+        gui: false,
+    },
+    ProgramSpec {
+        name: b"fetch",
+        path: b"/bin/fetch",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE,
+        desc: b"Print an HTTP resource to stdout",
+        gui: false,
+    },
+    ProgramSpec {
+        name: b"traceroute",
+        path: b"/bin/traceroute",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE | TASK_FLAG_NET_RAW,
+        desc: b"Show the route packets take to a host",
+        gui: false,
+    },
+    ProgramSpec {
+        name: b"sniff",
+        path: b"/bin/sniff",
+        priority: 5,
+        flags: TASK_FLAG_USER_MODE | TASK_FLAG_NET_RAW,
+        desc: b"Print incoming IPv4 packets",
+        gui: false,
+    },
     #[cfg(feature = "testbins")]
     ProgramSpec {
         name: b"fork_test",
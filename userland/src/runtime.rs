@@ -1,3 +1,5 @@
+pub mod backtrace;
+
 use core::ffi::c_void;
 pub fn u_memcpy(dst: *mut c_void, src: *const c_void, n: usize) -> *mut c_void {
     if dst.is_null() || src.is_null() || n == 0 {
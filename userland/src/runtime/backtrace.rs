@@ -0,0 +1,180 @@
+//! Panic reports with symbolized frame-pointer backtraces.
+//!
+//! Userland is built with frame pointers (see the userland target JSON), so
+//! each frame starts with the caller's `rbp` followed by the return address.
+//! [`report_panic`] walks that chain from its own frame and resolves every
+//! return address against the symbol table `scripts/link_userland.sh` embeds
+//! in the `.slopos.syms` section: one `<16 hex digit address> <name>` line
+//! per function, in address order.  Programs linked without the table
+//! (dynamically linked ones) print bare addresses.
+//!
+//! The report goes to the TTY and to [`CRASH_DIR`]`/crash.<pid>`.
+
+use core::ffi::c_char;
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use slopos_lib::numfmt::{self, NumBuf};
+
+use crate::syscall::{
+    FdGuard, USER_FS_OPEN_CREAT, USER_FS_OPEN_WRITE, core as sys_core, fs, process, tty,
+};
+
+/// Directory the crash file is written to.
+pub const CRASH_DIR: &[u8] = b"/tmp";
+/// Exit code of a task that panicked.
+pub const PANIC_EXIT_CODE: i32 = 101;
+/// Frames printed before the walk gives up.
+pub const BACKTRACE_MAX_FRAMES: usize = 32;
+
+/// User addresses are below the canonical hole.
+const USER_ADDR_LIMIT: u64 = 0x0000_8000_0000_0000;
+/// Largest plausible gap between two frames.
+const MAX_FRAME_SIZE: u64 = 1 << 20;
+
+unsafe extern "C" {
+    static __slopos_syms_start: u8;
+    static __slopos_syms_end: u8;
+}
+
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// The embedded symbol table, empty if the program was linked without one.
+fn symbol_table() -> &'static [u8] {
+    unsafe {
+        let start = &raw const __slopos_syms_start;
+        let end = &raw const __slopos_syms_end;
+        core::slice::from_raw_parts(start, end as usize - start as usize)
+    }
+}
+
+fn parse_hex(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() || digits.len() > 16 {
+        return None;
+    }
+    digits.iter().try_fold(0u64, |acc, &b| {
+        let nibble = (b as char).to_digit(16)?;
+        Some((acc << 4) | nibble as u64)
+    })
+}
+
+/// The function containing `addr` and `addr`'s offset into it.
+pub fn symbolize(addr: u64) -> Option<(&'static str, u64)> {
+    let mut best = None;
+    for line in symbol_table().split(|&b| b == b'\n') {
+        let Some(space) = line.iter().position(|&b| b == b' ') else {
+            continue;
+        };
+        let Some(start) = parse_hex(&line[..space]) else {
+            continue;
+        };
+        if start > addr {
+            break;
+        }
+        best = Some((&line[space + 1..], start));
+    }
+    let (name, start) = best?;
+    Some((core::str::from_utf8(name).ok()?, addr - start))
+}
+
+/// Return addresses on the frame-pointer chain starting at `rbp`.  The walk
+/// stops at the null `rbp` `_start` leaves, or at anything that does not
+/// look like a frame further up the same stack.
+pub fn walk_frames(mut rbp: u64, mut visit: impl FnMut(u64)) {
+    for _ in 0..BACKTRACE_MAX_FRAMES {
+        if rbp == 0 || rbp & 0x7 != 0 || rbp >= USER_ADDR_LIMIT - 16 {
+            return;
+        }
+        let (next, ret) = unsafe {
+            let frame = rbp as *const u64;
+            (frame.read(), frame.add(1).read())
+        };
+        if ret == 0 || ret >= USER_ADDR_LIMIT {
+            return;
+        }
+        visit(ret);
+        if next <= rbp || next - rbp > MAX_FRAME_SIZE {
+            return;
+        }
+        rbp = next;
+    }
+}
+
+/// Writes everything to the TTY and, when it could be opened, the crash file.
+struct Report {
+    file: Option<FdGuard>,
+}
+
+impl Report {
+    fn open() -> Self {
+        // Zeroed, so the directory and then the whole path stay NUL-terminated.
+        let mut path = [0u8; 48];
+        path[..CRASH_DIR.len()].copy_from_slice(CRASH_DIR);
+        let _ = fs::mkdir_path(path.as_ptr() as *const c_char);
+
+        let mut pid = NumBuf::<21>::new();
+        let mut len = CRASH_DIR.len();
+        for part in [
+            &b"/crash."[..],
+            numfmt::trim_nul(pid.format_u64(process::getpid() as u64)),
+        ] {
+            path[len..len + part.len()].copy_from_slice(part);
+            len += part.len();
+        }
+        let path = path.as_ptr() as *const c_char;
+        let _ = fs::unlink_path(path);
+        let file = fs::open_path(path, USER_FS_OPEN_WRITE | USER_FS_OPEN_CREAT)
+            .ok()
+            .map(|fd| unsafe { FdGuard::from_raw(fd) });
+        Self { file }
+    }
+}
+
+impl Write for Report {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let _ = tty::write(s.as_bytes());
+        if let Some(file) = &self.file {
+            let _ = file.write(s.as_bytes());
+        }
+        Ok(())
+    }
+}
+
+/// Report a panic with a backtrace of the panicking thread and exit with
+/// [`PANIC_EXIT_CODE`].  Every userland panic handler ends up here.
+pub fn report_panic(info: &PanicInfo) -> ! {
+    if PANICKING.swap(true, Ordering::Relaxed) {
+        let _ = tty::write(b"panic while panicking\n");
+        sys_core::exit_with_code(PANIC_EXIT_CODE);
+    }
+    let rbp: u64;
+    unsafe { core::arch::asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack)) };
+
+    let mut report = Report::open();
+    let _ = match info.location() {
+        Some(loc) => writeln!(
+            report,
+            "panic at {}:{}: {}",
+            loc.file(),
+            loc.line(),
+            info.message()
+        ),
+        None => writeln!(report, "panic: {}", info.message()),
+    };
+    let _ = writeln!(report, "backtrace:");
+    let mut depth = 0;
+    walk_frames(rbp, |ret| {
+        // The call instruction ends at `ret`; look up the byte before it so
+        // a call in a function's last instruction resolves to that function.
+        let _ = match symbolize(ret - 1) {
+            Some((name, offset)) => {
+                writeln!(report, "  #{depth:<2} {ret:#018x} {name}+{:#x}", offset + 1)
+            }
+            None => writeln!(report, "  #{depth:<2} {ret:#018x} ?"),
+        };
+        depth += 1;
+    });
+    drop(report);
+    sys_core::exit_with_code(PANIC_EXIT_CODE);
+}
@@ -0,0 +1,56 @@
+//! PCM audio output (48 kHz s16le stereo).
+
+use slopos_abi::audio::{AUDIO_CTL_INFO, AUDIO_CTL_SET_VOLUME, AUDIO_CTL_STOP, AudioInfo};
+
+use super::error::{SyscallResult, demux};
+use super::numbers::*;
+use super::raw::syscall2;
+
+/// Queue interleaved frames, blocking while the kernel's ring is full.
+///
+/// # Returns
+/// Bytes queued
+///
+/// # Errors
+/// * `ENODEV` - No sound device
+/// * `EINVAL` - Shorter than one frame
+#[inline(always)]
+pub fn write(frames: &[u8]) -> SyscallResult<usize> {
+    let result = unsafe {
+        syscall2(
+            SYSCALL_AUDIO_WRITE,
+            frames.as_ptr() as u64,
+            frames.len() as u64,
+        )
+    };
+    demux(result).map(|v| v as usize)
+}
+
+/// Query the output stream.  Succeeds without a device; check
+/// [`AudioInfo::is_present`].
+#[inline(always)]
+pub fn info() -> SyscallResult<AudioInfo> {
+    let mut info = AudioInfo::default();
+    let result = unsafe {
+        syscall2(
+            SYSCALL_AUDIO_CTL,
+            AUDIO_CTL_INFO,
+            &mut info as *mut _ as u64,
+        )
+    };
+    demux(result).map(|_| info)
+}
+
+/// Set the software volume, 0..=`AUDIO_VOLUME_MAX`.
+#[inline(always)]
+pub fn set_volume(volume: u32) -> SyscallResult<()> {
+    let result = unsafe { syscall2(SYSCALL_AUDIO_CTL, AUDIO_CTL_SET_VOLUME, volume as u64) };
+    demux(result).map(|_| ())
+}
+
+/// Drop everything queued and halt playback.
+#[inline(always)]
+pub fn stop() -> SyscallResult<()> {
+    let result = unsafe { syscall2(SYSCALL_AUDIO_CTL, AUDIO_CTL_STOP, 0) };
+    demux(result).map(|_| ())
+}
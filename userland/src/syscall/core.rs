@@ -1,7 +1,18 @@
 //! Core syscalls: yield, exit, sleep, time, CPU info.
 
+use core::ffi::c_char;
+
+use slopos_abi::audit::{
+    AUDIT_OP_FLUSH, AUDIT_OP_READ, AUDIT_OP_SET, AUDIT_OP_STATUS, AuditRecord, AuditStatus,
+};
+use slopos_abi::crash::CrashNotice;
+use slopos_abi::hw::HwDevice;
+use slopos_abi::perf::PerfReading;
+use slopos_abi::task::{CpuUsage, TaskSnapshot, TaskStackUsage};
+
+use super::error::{SyscallResult, demux};
 use super::numbers::*;
-use super::raw::{syscall0, syscall1, syscall2};
+use super::raw::{syscall0, syscall1, syscall2, syscall3, syscall4};
 
 #[inline(always)]
 pub fn yield_now() {
@@ -51,6 +62,12 @@ pub fn clock_gettime(ts: &mut Timespec) -> i64 {
     unsafe { syscall2(SYSCALL_CLOCK_GETTIME, CLOCK_MONOTONIC, ts as *mut _ as u64) as i64 }
 }
 
+/// Query the wall clock (UTC since the Unix epoch).
+#[inline(always)]
+pub fn clock_gettime_realtime(ts: &mut Timespec) -> i64 {
+    unsafe { syscall2(SYSCALL_CLOCK_GETTIME, CLOCK_REALTIME, ts as *mut _ as u64) as i64 }
+}
+
 /// Read the monotonic clock and return total nanoseconds since boot.
 ///
 /// Convenience wrapper that avoids callers having to build a [`Timespec`].
@@ -82,6 +99,183 @@ pub fn random_next() -> u32 {
     unsafe { syscall0(SYSCALL_RANDOM_NEXT) as u32 }
 }
 
+/// Fill `buf` from the kernel CSPRNG. Blocks until the RNG is seeded
+/// unless `GRND_NONBLOCK` is set. Returns bytes written or a negative errno.
+#[inline(always)]
+pub fn getrandom(buf: &mut [u8], flags: u64) -> i64 {
+    unsafe {
+        syscall3(
+            SYSCALL_GETRANDOM,
+            buf.as_mut_ptr() as u64,
+            buf.len() as u64,
+            flags,
+        ) as i64
+    }
+}
+
+/// Read kernel warning counters. `panic` is one of `KWARN_PANIC_KEEP`,
+/// `KWARN_PANIC_OFF` or `KWARN_PANIC_ON`.
+#[inline(always)]
+pub fn kwarn_stats(stats: &mut KwarnStats, panic: u64) -> i64 {
+    unsafe { syscall2(SYSCALL_KWARN_STATS, stats as *mut _ as u64, panic) as i64 }
+}
+
+/// Read or change the log level of kernel subsystem `module`, or of
+/// `KLOG_MODULE_DEFAULT`.  `level` is a level number, `KLOG_LEVEL_INHERIT`
+/// or `KLOG_LEVEL_KEEP`; returns the setting after any change.
+pub fn klog_level(module: u64, level: u64) -> SyscallResult<u64> {
+    demux(unsafe { syscall2(SYSCALL_KLOG_LEVEL, module, level) })
+}
+
+/// Run a `KTRACE_OP_*` tracer command.  `path` is only read by
+/// `KTRACE_OP_EXPORT_FILE`; exports return the number of events written.
+pub fn ktrace(op: u64, path: *const c_char) -> SyscallResult<u64> {
+    demux(unsafe { syscall2(SYSCALL_KTRACE, op, path as u64) })
+}
+
+/// Stop tracing and copy recorded events into `out`, starting at the
+/// `first` oldest.  Returns how many events were recorded in total; `out`
+/// holds the ones from `first` on that fit.
+pub fn ktrace_read(out: &mut [KtraceRecord], first: usize) -> SyscallResult<usize> {
+    demux(unsafe {
+        syscall4(
+            SYSCALL_KTRACE,
+            KTRACE_OP_READ,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+            first as u64,
+        )
+    })
+    .map(|n| n as usize)
+}
+
+/// Read lock contention counters into `out`, one entry per lock class.
+/// With `reset`, the kernel zeroes its counters after reading them.
+/// Fails with `EOPNOTSUPP` on kernels built without `lockstat`.
+pub fn lock_stats(out: &mut [LockStat], reset: bool) -> SyscallResult<usize> {
+    let flags = if reset { LOCK_STATS_RESET } else { 0 };
+    demux(unsafe {
+        syscall3(
+            SYSCALL_LOCK_STATS,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+            flags,
+        )
+    })
+    .map(|n| n as usize)
+}
+
+/// Read the kernel stack high-water mark of each live task into `out`.
+/// Fails with `EOPNOTSUPP` on kernels built without stack tracking.
+pub fn task_stack_usage(out: &mut [TaskStackUsage]) -> SyscallResult<usize> {
+    demux(unsafe {
+        syscall2(
+            SYSCALL_TASK_STACK_USAGE,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+        )
+    })
+    .map(|n| n as usize)
+}
+
+/// Read the scheduler time of each CPU into `out`, one entry per CPU.
+pub fn cpu_usage(out: &mut [CpuUsage]) -> SyscallResult<usize> {
+    demux(unsafe { syscall2(SYSCALL_CPU_USAGE, out.as_mut_ptr() as u64, out.len() as u64) })
+        .map(|n| n as usize)
+}
+
+/// Describe every live task in `out`, window titles included.
+pub fn task_snapshot(out: &mut [TaskSnapshot]) -> SyscallResult<usize> {
+    demux(unsafe {
+        syscall2(
+            SYSCALL_TASK_SNAPSHOT,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+        )
+    })
+    .map(|n| n as usize)
+}
+
+/// Read the boot hardware inventory into `out`.  `class` is an
+/// `HW_CLASS_*` value to list one class, or 0 for every device.
+pub fn hw_inventory(out: &mut [HwDevice], class: u8) -> SyscallResult<usize> {
+    demux(unsafe {
+        syscall3(
+            SYSCALL_HW_INVENTORY,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+            class as u64,
+        )
+    })
+    .map(|n| n as usize)
+}
+
+/// Read the audit mask and counters.
+pub fn audit_status() -> SyscallResult<AuditStatus> {
+    let mut status = AuditStatus::default();
+    demux(unsafe {
+        syscall2(
+            SYSCALL_AUDIT_CTL,
+            AUDIT_OP_STATUS,
+            &mut status as *mut _ as u64,
+        )
+    })?;
+    Ok(status)
+}
+
+/// Enable exactly the `AUDIT_CAT_*` categories in `mask`; returns the
+/// previous mask.
+pub fn audit_set(mask: u32) -> SyscallResult<u32> {
+    demux(unsafe { syscall2(SYSCALL_AUDIT_CTL, AUDIT_OP_SET, mask as u64) }).map(|m| m as u32)
+}
+
+/// Copy the newest audit records still in kernel memory into `out`,
+/// oldest first.
+pub fn audit_read(out: &mut [AuditRecord]) -> SyscallResult<usize> {
+    demux(unsafe {
+        syscall3(
+            SYSCALL_AUDIT_CTL,
+            AUDIT_OP_READ,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+        )
+    })
+    .map(|n| n as usize)
+}
+
+/// Append pending audit records to the log files now.
+pub fn audit_flush() -> SyscallResult<usize> {
+    demux(unsafe { syscall1(SYSCALL_AUDIT_CTL, AUDIT_OP_FLUSH) }).map(|n| n as usize)
+}
+
+/// The oldest crash notice the kernel still has with `seq` at or above
+/// the given one.
+pub fn crash_notice(seq: u64) -> Option<CrashNotice> {
+    let mut notice = CrashNotice::default();
+    let written =
+        demux(unsafe { syscall2(SYSCALL_CRASH_NOTICE, seq, &mut notice as *mut _ as u64) }).ok()?;
+    (written == 1).then_some(notice)
+}
+
+/// Open a counter for `PERF_EVENT_*` `event` on `task` (0 for the caller)
+/// with `PERF_FLAG_*` `flags`.  Returns the counter id.
+pub fn perf_open(event: u32, task: u32, flags: u64) -> SyscallResult<u32> {
+    demux(unsafe { syscall3(SYSCALL_PERF_OPEN, event as u64, task as u64, flags) })
+        .map(|id| id as u32)
+}
+
+/// Read counter `id`.
+pub fn perf_read(id: u32) -> SyscallResult<PerfReading> {
+    let mut reading = PerfReading::default();
+    demux(unsafe { syscall2(SYSCALL_PERF_READ, id as u64, &mut reading as *mut _ as u64) })?;
+    Ok(reading)
+}
+
+/// Close counter `id`.
+pub fn perf_close(id: u32) -> SyscallResult<()> {
+    demux(unsafe { syscall1(SYSCALL_PERF_CLOSE, id as u64) }).map(|_| ())
+}
+
 #[inline(always)]
 pub fn sys_info(info: &mut UserSysInfo) -> i64 {
     unsafe { syscall1(SYSCALL_SYS_INFO, info as *mut _ as u64) as i64 }
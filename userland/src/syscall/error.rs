@@ -69,8 +69,20 @@ impl SyscallError {
     pub const EPIPE: Self = Self(32);
     /// Function not implemented
     pub const ENOSYS: Self = Self(38);
+    /// Operation not supported
+    pub const EOPNOTSUPP: Self = Self(95);
+    /// Address already in use
+    pub const EADDRINUSE: Self = Self(98);
+    /// Network is unreachable
+    pub const ENETUNREACH: Self = Self(101);
+    /// No buffer space available
+    pub const ENOBUFS: Self = Self(105);
+    /// Connection timed out
+    pub const ETIMEDOUT: Self = Self(110);
     /// Connection refused
     pub const ECONNREFUSED: Self = Self(111);
+    /// No route to host
+    pub const EHOSTUNREACH: Self = Self(113);
 
     /// Create a SyscallError from a raw errno value.
     #[inline]
@@ -115,7 +127,13 @@ impl SyscallError {
             30 => "Read-only file system",
             32 => "Broken pipe",
             38 => "Function not implemented",
+            95 => "Operation not supported",
+            98 => "Address already in use",
+            101 => "Network is unreachable",
+            105 => "No buffer space available",
+            110 => "Connection timed out",
             111 => "Connection refused",
+            113 => "No route to host",
             _ => "Unknown error",
         }
     }
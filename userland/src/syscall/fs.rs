@@ -12,7 +12,7 @@ use super::RawFd;
 use super::error::{SyscallResult, demux};
 use super::numbers::*;
 use super::raw::{syscall1, syscall2, syscall3};
-use slopos_abi::syscall::{TIOCSCTTY, UserPollFd, UserTermios, UserTimeval};
+use slopos_abi::syscall::{TIOCSCTTY, UserPollFd, UserTermios, UserTimeval, UserWinsize};
 use slopos_abi::{UserFsList, UserFsStat};
 
 // =============================================================================
@@ -157,6 +157,28 @@ pub fn rename(old_path: *const c_char, new_path: *const c_char) -> SyscallResult
     demux(result).map(|_| ())
 }
 
+/// Change the permission bits of a file or directory.
+///
+/// # Errors
+/// * `ENOENT` - Path not found
+/// * `EPERM` - Caller is neither the owner nor root
+#[inline(always)]
+pub fn chmod(path: *const c_char, mode: u32) -> SyscallResult<()> {
+    let result = unsafe { syscall2(SYSCALL_CHMOD, path as u64, mode as u64) };
+    demux(result).map(|_| ())
+}
+
+/// Change the owner and group of a file; `u32::MAX` keeps the current one.
+///
+/// # Errors
+/// * `ENOENT` - Path not found
+/// * `EPERM` - Caller is not root
+#[inline(always)]
+pub fn chown(path: *const c_char, uid: u32, gid: u32) -> SyscallResult<()> {
+    let result = unsafe { syscall3(SYSCALL_CHOWN, path as u64, uid as u64, gid as u64) };
+    demux(result).map(|_| ())
+}
+
 /// List directory contents.
 ///
 /// # Arguments
@@ -184,6 +206,20 @@ pub fn dup2(old_fd: RawFd, new_fd: RawFd) -> SyscallResult<RawFd> {
     demux(result).map(|v| v as RawFd)
 }
 
+/// `fcntl(2)`; returns the command's result value.
+#[inline(always)]
+pub fn fcntl(fd: RawFd, cmd: u64, arg: u64) -> SyscallResult<i64> {
+    let result = unsafe { syscall3(SYSCALL_FCNTL, fd as u64, cmd, arg) };
+    demux(result).map(|v| v as i64)
+}
+
+/// Duplicate `fd` with `FD_CLOEXEC` set, so spawned and exec'd programs
+/// do not inherit the copy.
+#[inline(always)]
+pub fn dup_cloexec(fd: RawFd) -> SyscallResult<RawFd> {
+    fcntl(fd, F_DUPFD_CLOEXEC, 0).map(|v| v as RawFd)
+}
+
 #[inline(always)]
 pub fn lseek(fd: RawFd, offset: i64, whence: u32) -> SyscallResult<i64> {
     let result = unsafe { syscall3(SYSCALL_LSEEK, fd as u64, offset as u64, whence as u64) };
@@ -202,6 +238,15 @@ pub fn pipe2(fds: &mut [i32; 2], flags: u32) -> SyscallResult<()> {
     demux(result).map(|_| ())
 }
 
+/// Open a new pty pair as `[master, slave]`.  `flags` takes `O_CLOEXEC`
+/// and `O_NONBLOCK`.
+#[inline(always)]
+pub fn openpty(flags: u32) -> SyscallResult<[RawFd; 2]> {
+    let mut fds = [-1; 2];
+    let result = unsafe { syscall2(SYSCALL_OPENPTY, fds.as_mut_ptr() as u64, flags as u64) };
+    demux(result).map(|_| fds)
+}
+
 #[inline(always)]
 pub fn poll(fds: &mut [UserPollFd], timeout_ms: i64) -> SyscallResult<usize> {
     let result = unsafe {
@@ -270,6 +315,36 @@ pub fn tiocsctty(fd: RawFd) -> SyscallResult<()> {
     demux(result).map(|_| ())
 }
 
+/// Number `N` of the `/dev/pts/N` slave behind pty master `fd`.
+#[inline(always)]
+pub fn tiocgptn(fd: RawFd) -> SyscallResult<u32> {
+    let mut number = 0u32;
+    let result = unsafe {
+        syscall3(
+            SYSCALL_IOCTL,
+            fd as u64,
+            TIOCGPTN,
+            (&mut number as *mut u32) as u64,
+        )
+    };
+    demux(result).map(|_| number)
+}
+
+/// Set the terminal size of `fd`; the foreground process group gets
+/// SIGWINCH if it changed.
+#[inline(always)]
+pub fn tcsetwinsize(fd: RawFd, ws: &UserWinsize) -> SyscallResult<()> {
+    let result = unsafe {
+        syscall3(
+            SYSCALL_IOCTL,
+            fd as u64,
+            TIOCSWINSZ,
+            (ws as *const UserWinsize) as u64,
+        )
+    };
+    demux(result).map(|_| ())
+}
+
 #[inline(always)]
 pub fn tcgetattr(fd: RawFd) -> SyscallResult<UserTermios> {
     let mut t = UserTermios::default();
@@ -1,8 +1,12 @@
 //! Input event syscalls.
 
+use super::error::{SyscallResult, demux};
 use super::numbers::*;
-use super::raw::{syscall0, syscall1, syscall2, syscall3};
-use slopos_abi::{INPUT_FOCUS_KEYBOARD, INPUT_FOCUS_POINTER, InputEvent};
+use super::raw::{syscall0, syscall1, syscall2, syscall3, syscall4};
+use slopos_abi::{
+    INPUT_FOCUS_KEYBOARD, INPUT_FOCUS_KEYS, INPUT_FOCUS_POINTER, INPUT_FOCUS_SHORTCUTS, InputEvent,
+    KEYMAP_QUERY,
+};
 
 pub fn poll(event_out: &mut InputEvent) -> Option<InputEvent> {
     let result = unsafe { syscall1(SYSCALL_INPUT_POLL, event_out as *mut InputEvent as u64) };
@@ -42,13 +46,32 @@ pub fn set_pointer_focus(target_task_id: u32) -> i64 {
     set_focus(target_task_id, INPUT_FOCUS_POINTER)
 }
 
-pub fn set_pointer_focus_with_offset(target_task_id: u32, offset_x: i32, offset_y: i32) -> i64 {
+/// Route Super+key presses to `target_task_id` (compositor only).
+pub fn set_shortcut_focus(target_task_id: u32) -> i64 {
+    set_focus(target_task_id, INPUT_FOCUS_SHORTCUTS)
+}
+
+/// Take key presses as input events instead of reading them from the TTY
+/// whenever this task has keyboard focus.
+pub fn take_keys() -> i64 {
+    set_focus(0, INPUT_FOCUS_KEYS)
+}
+
+/// Give `target_task_id` pointer focus (compositor only).  Its pointer
+/// events are relative to `offset_x, offset_y` and divided by `scale`.
+pub fn set_pointer_focus_with_offset(
+    target_task_id: u32,
+    offset_x: i32,
+    offset_y: i32,
+    scale: u32,
+) -> i64 {
     unsafe {
-        syscall3(
+        syscall4(
             SYSCALL_INPUT_SET_FOCUS_WITH_OFFSET,
             target_task_id as u64,
             offset_x as u64,
             offset_y as u64,
+            scale as u64,
         ) as i64
     }
 }
@@ -57,6 +80,17 @@ pub fn request_close(target_task_id: u32) -> i64 {
     unsafe { syscall1(SYSCALL_INPUT_REQUEST_CLOSE, target_task_id as u64) as i64 }
 }
 
+pub fn request_configure(target_task_id: u32, width: u32, height: u32) -> i64 {
+    unsafe {
+        syscall3(
+            SYSCALL_INPUT_REQUEST_CONFIGURE,
+            target_task_id as u64,
+            width as u64,
+            height as u64,
+        ) as i64
+    }
+}
+
 pub fn get_pointer_pos() -> (i32, i32) {
     let result = unsafe { syscall0(SYSCALL_INPUT_GET_POINTER_POS) };
     let x = (result >> 32) as i32;
@@ -100,3 +134,64 @@ pub fn clipboard_paste(buf: &mut [u8]) -> usize {
         ) as usize
     }
 }
+
+/// Replace the clipboard with `data` of MIME type `mime`.
+///
+/// # Returns
+/// The number of bytes stored
+///
+/// # Errors
+/// * `EINVAL` - Malformed MIME type
+/// * `ERANGE` - `data` longer than `CLIPBOARD_MAX_SIZE`
+pub fn clipboard_set(mime: &[u8], data: &[u8]) -> SyscallResult<usize> {
+    let result = unsafe {
+        syscall4(
+            SYSCALL_CLIPBOARD_SET,
+            mime.as_ptr() as u64,
+            mime.len() as u64,
+            data.as_ptr() as u64,
+            data.len() as u64,
+        )
+    };
+    demux(result).map(|v| v as usize)
+}
+
+/// Read the clipboard into `buf` if it holds MIME type `mime`.
+///
+/// # Returns
+/// The full length of the clipboard data; only the first `buf.len()`
+/// bytes are written when it is longer
+///
+/// # Errors
+/// * `ENOENT` - The clipboard is empty or holds another type
+/// * `EINVAL` - Malformed MIME type
+pub fn clipboard_get(mime: &[u8], buf: &mut [u8]) -> SyscallResult<usize> {
+    let result = unsafe {
+        syscall4(
+            SYSCALL_CLIPBOARD_GET,
+            mime.as_ptr() as u64,
+            mime.len() as u64,
+            buf.as_mut_ptr() as u64,
+            buf.len() as u64,
+        )
+    };
+    demux(result).map(|v| v as usize)
+}
+
+/// Switch the keyboard layout to `id` (`KEYMAP_*`).
+///
+/// # Returns
+/// The previously active layout
+///
+/// # Errors
+/// * `EINVAL` - Unknown layout
+pub fn set_keymap(id: u32) -> SyscallResult<u32> {
+    let result = unsafe { syscall1(SYSCALL_SET_KEYMAP, id as u64) };
+    demux(result).map(|v| v as u32)
+}
+
+/// The active keyboard layout (`KEYMAP_*`).
+pub fn keymap() -> SyscallResult<u32> {
+    let result = unsafe { syscall1(SYSCALL_SET_KEYMAP, KEYMAP_QUERY) };
+    demux(result).map(|v| v as u32)
+}
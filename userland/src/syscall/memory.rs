@@ -1,9 +1,9 @@
-//! Memory management syscalls: brk, sbrk, shared memory.
+//! Memory management syscalls: brk, sbrk, mmap, framebuffer mmap, shared memory.
 
 use core::ffi::c_void;
 
 use super::numbers::*;
-use super::raw::{syscall1, syscall2};
+use super::raw::{syscall1, syscall2, syscall3, syscall6};
 use slopos_abi::PixelFormat;
 
 #[inline(always)]
@@ -32,6 +32,52 @@ pub fn sbrk(increment: isize) -> *mut c_void {
     }
 }
 
+/// Map `length` bytes of anonymous private memory.  `addr` is a hint, or
+/// the exact address with `MAP_FIXED` in `flags`; returns 0 on failure.
+#[inline(always)]
+pub fn mmap_anon(addr: u64, length: u64, prot: u64, flags: u64) -> u64 {
+    let ret = unsafe {
+        syscall6(
+            SYSCALL_MMAP,
+            addr,
+            length,
+            prot,
+            flags | MAP_ANONYMOUS | MAP_PRIVATE,
+            u64::MAX,
+            0,
+        )
+    };
+    if (ret as i64) < 0 { 0 } else { ret }
+}
+
+#[inline(always)]
+pub fn munmap(addr: u64, length: u64) -> i64 {
+    unsafe { syscall2(SYSCALL_MUNMAP, addr, length) as i64 }
+}
+
+#[inline(always)]
+pub fn mprotect(addr: u64, length: u64, prot: u64) -> i64 {
+    unsafe { syscall3(SYSCALL_MPROTECT, addr, length, prot) as i64 }
+}
+
+/// Map `length` bytes of the scanout at `offset`, write-combined.
+/// Display-exclusive tasks only; returns 0 on failure.
+#[inline(always)]
+pub fn mmap_framebuffer(length: u64, offset: u64) -> u64 {
+    let ret = unsafe {
+        syscall6(
+            SYSCALL_MMAP,
+            0,
+            length,
+            PROT_READ | PROT_WRITE,
+            MAP_FRAMEBUFFER,
+            u64::MAX,
+            offset,
+        )
+    };
+    if (ret as i64) < 0 { 0 } else { ret }
+}
+
 #[inline(always)]
 pub fn shm_create(size: u64, flags: u32) -> u32 {
     unsafe { syscall2(SYSCALL_SHM_CREATE, size, flags as u64) as u32 }
@@ -17,6 +17,7 @@
 //! | `raw` | Low-level inline asm syscall primitives |
 //! | `error` | `SyscallError`, `SyscallResult`, `demux()` |
 //! | `numbers` | Re-exports syscall numbers from `slopos_abi` |
+//! | `audio` | PCM playback |
 //! | `core` | Yield, exit, sleep, time, CPU info |
 //! | `tty` | TTY/console I/O (not file descriptors!) |
 //! | `fs` | File descriptor operations |
@@ -27,6 +28,7 @@
 //! | `roulette` | Wheel of Fate syscalls |
 //! | `wrappers` | RAII types (ShmBuffer, FdGuard) |
 
+pub mod audio;
 pub mod core;
 pub mod error;
 pub mod fs;
@@ -47,12 +49,13 @@ pub use numbers::*;
 
 // Re-export ABI types used by syscalls
 pub use slopos_abi::syscall::{Timespec, UserSysInfo};
+pub use slopos_abi::syscall_filter::SyscallFilter;
 pub use slopos_abi::{
     DamageRect, DisplayInfo, INPUT_FOCUS_KEYBOARD, INPUT_FOCUS_POINTER, InputEvent, InputEventData,
     InputEventType, MAX_WINDOW_DAMAGE_REGIONS, PixelFormat, SHM_ACCESS_RO, SHM_ACCESS_RW, ShmError,
-    SockAddrIn, SurfaceRole, USER_FS_OPEN_APPEND, USER_FS_OPEN_CREAT, USER_FS_OPEN_READ,
-    USER_FS_OPEN_WRITE, USER_NET_MAX_MEMBERS, UserFsEntry, UserFsList, UserFsStat, UserNetInfo,
-    UserNetMember, WindowInfo,
+    SockAddrIn, SurfaceRole, USER_FS_MAX_ENTRIES, USER_FS_OPEN_APPEND, USER_FS_OPEN_CREAT,
+    USER_FS_OPEN_READ, USER_FS_OPEN_WRITE, USER_NET_MAX_MEMBERS, UserFsEntry, UserFsList,
+    UserFsStat, UserNetInfo, UserNetMember, WindowInfo,
 };
 
 pub use wrappers::fd::FdGuard;
@@ -2,12 +2,18 @@ use super::RawFd;
 use super::error::{SyscallResult, demux};
 use super::numbers::{
     SYSCALL_ACCEPT, SYSCALL_BIND, SYSCALL_CONNECT, SYSCALL_FCNTL, SYSCALL_GETSOCKOPT,
-    SYSCALL_LISTEN, SYSCALL_NET_INFO, SYSCALL_NET_SCAN, SYSCALL_RECV, SYSCALL_RECVFROM,
+    SYSCALL_LISTEN, SYSCALL_NET_FILTER, SYSCALL_NET_INFO, SYSCALL_NET_LEASE, SYSCALL_NET_PING,
+    SYSCALL_NET_ROUTE, SYSCALL_NET_SCAN, SYSCALL_NET_STAT, SYSCALL_RECV, SYSCALL_RECVFROM,
     SYSCALL_RESOLVE, SYSCALL_SEND, SYSCALL_SENDTO, SYSCALL_SETSOCKOPT, SYSCALL_SHUTDOWN,
     SYSCALL_SOCKET,
 };
 use super::raw::{syscall1, syscall2, syscall3, syscall4, syscall5, syscall6};
-use slopos_abi::net::{SockAddrIn, UserNetInfo, UserNetMember};
+use slopos_abi::net::{
+    IpMreq, NET_FILTER_OP_APPEND, NET_FILTER_OP_DELETE, NET_FILTER_OP_FLUSH, NET_FILTER_OP_LIST,
+    NET_FILTER_OP_POLICY, NET_FILTER_OP_STATUS, NET_RESOLVE_NONBLOCK, NET_ROUTE_OP_ADD,
+    NET_ROUTE_OP_DEL, NET_ROUTE_OP_LIST, NetFilterRule, NetFilterStatus, NetLease, NetPingReply,
+    NetPingRequest, NetRoute, NetSocketStat, SockAddrIn, UserNetInfo, UserNetMember,
+};
 use slopos_abi::syscall::{F_GETFL, F_SETFL, O_NONBLOCK};
 
 #[inline(always)]
@@ -193,20 +199,280 @@ pub fn set_reuse_addr(fd: RawFd) -> SyscallResult<()> {
     )
 }
 
+/// Set `SO_LINGER`: `Some(secs)` makes `close` wait for the peer to ack
+/// (reset immediately for 0), `None` restores the default background close.
+pub fn set_linger(fd: RawFd, linger: Option<u32>) -> SyscallResult<()> {
+    let mut val = [0u8; 8];
+    val[..4].copy_from_slice(&(linger.is_some() as i32).to_ne_bytes());
+    val[4..].copy_from_slice(&(linger.unwrap_or(0) as i32).to_ne_bytes());
+    setsockopt(
+        fd,
+        slopos_abi::syscall::SOL_SOCKET,
+        slopos_abi::syscall::SO_LINGER,
+        &val,
+    )
+}
+
+/// Set `SO_BROADCAST`, which sending to a broadcast address requires.
+pub fn set_broadcast(fd: RawFd, enable: bool) -> SyscallResult<()> {
+    let val = enable as i32;
+    setsockopt(
+        fd,
+        slopos_abi::syscall::SOL_SOCKET,
+        slopos_abi::syscall::SO_BROADCAST,
+        &val.to_ne_bytes(),
+    )
+}
+
+/// Set `IP_HDRINCL` on a raw socket: sent data then starts with the IPv4
+/// header, of which the kernel fills in the length, checksum and, when
+/// zero, the source address.
+pub fn set_hdrincl(fd: RawFd, enable: bool) -> SyscallResult<()> {
+    let val = enable as i32;
+    setsockopt(
+        fd,
+        slopos_abi::syscall::IPPROTO_IP,
+        slopos_abi::syscall::IP_HDRINCL,
+        &val.to_ne_bytes(),
+    )
+}
+
+/// Set `TCP_NODELAY`, which sends short segments without waiting for the
+/// ACK of earlier data (Nagle's algorithm).
+pub fn set_nodelay(fd: RawFd, enable: bool) -> SyscallResult<()> {
+    let val = enable as i32;
+    setsockopt(
+        fd,
+        slopos_abi::syscall::IPPROTO_TCP,
+        slopos_abi::syscall::TCP_NODELAY,
+        &val.to_ne_bytes(),
+    )
+}
+
+/// Set `SO_RCVTIMEO`: blocking receives and accepts fail with `EAGAIN`
+/// after `ms` milliseconds.  `None` waits forever.
+pub fn set_recv_timeout(fd: RawFd, ms: Option<u64>) -> SyscallResult<()> {
+    setsockopt(
+        fd,
+        slopos_abi::syscall::SOL_SOCKET,
+        slopos_abi::syscall::SO_RCVTIMEO,
+        &ms.unwrap_or(0).to_ne_bytes(),
+    )
+}
+
+/// Set `SO_SNDTIMEO`: blocking sends fail with `EAGAIN` after `ms`
+/// milliseconds.  `None` waits forever.
+pub fn set_send_timeout(fd: RawFd, ms: Option<u64>) -> SyscallResult<()> {
+    setsockopt(
+        fd,
+        slopos_abi::syscall::SOL_SOCKET,
+        slopos_abi::syscall::SO_SNDTIMEO,
+        &ms.unwrap_or(0).to_ne_bytes(),
+    )
+}
+
+/// Set `SO_RCVBUF`, the bytes a socket may hold unread.
+pub fn set_recv_buf(fd: RawFd, bytes: u32) -> SyscallResult<()> {
+    setsockopt(
+        fd,
+        slopos_abi::syscall::SOL_SOCKET,
+        slopos_abi::syscall::SO_RCVBUF,
+        &bytes.to_ne_bytes(),
+    )
+}
+
+/// Set `SO_SNDBUF`, the bytes a TCP socket may hold unacknowledged.
+pub fn set_send_buf(fd: RawFd, bytes: u32) -> SyscallResult<()> {
+    setsockopt(
+        fd,
+        slopos_abi::syscall::SOL_SOCKET,
+        slopos_abi::syscall::SO_SNDBUF,
+        &bytes.to_ne_bytes(),
+    )
+}
+
+/// Join a multicast group on the default interface (`IP_ADD_MEMBERSHIP`).
+pub fn join_group(fd: RawFd, group: [u8; 4]) -> SyscallResult<()> {
+    let mut val = [0u8; core::mem::size_of::<IpMreq>()];
+    val[..4].copy_from_slice(&group);
+    setsockopt(
+        fd,
+        slopos_abi::syscall::IPPROTO_IP,
+        slopos_abi::syscall::IP_ADD_MEMBERSHIP,
+        &val,
+    )
+}
+
+/// Leave a multicast group joined with [`join_group`].
+pub fn leave_group(fd: RawFd, group: [u8; 4]) -> SyscallResult<()> {
+    let mut val = [0u8; core::mem::size_of::<IpMreq>()];
+    val[..4].copy_from_slice(&group);
+    setsockopt(
+        fd,
+        slopos_abi::syscall::IPPROTO_IP,
+        slopos_abi::syscall::IP_DROP_MEMBERSHIP,
+        &val,
+    )
+}
+
+/// Append a packet filter rule.  Returns its index.
+pub fn filter_append(rule: &NetFilterRule) -> SyscallResult<usize> {
+    let result = unsafe {
+        syscall2(
+            SYSCALL_NET_FILTER,
+            NET_FILTER_OP_APPEND,
+            rule as *const NetFilterRule as u64,
+        )
+    };
+    demux(result).map(|idx| idx as usize)
+}
+
+/// Delete the packet filter rule at `idx`.
+pub fn filter_delete(idx: usize) -> SyscallResult<()> {
+    let result = unsafe { syscall2(SYSCALL_NET_FILTER, NET_FILTER_OP_DELETE, idx as u64) };
+    demux(result).map(|_| ())
+}
+
+/// Remove every packet filter rule.
+pub fn filter_flush() -> SyscallResult<()> {
+    let result = unsafe { syscall1(SYSCALL_NET_FILTER, NET_FILTER_OP_FLUSH) };
+    demux(result).map(|_| ())
+}
+
+/// Read the packet filter rules, in evaluation order.
+pub fn filter_list(out: &mut [NetFilterRule]) -> SyscallResult<usize> {
+    let result = unsafe {
+        syscall3(
+            SYSCALL_NET_FILTER,
+            NET_FILTER_OP_LIST,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+        )
+    };
+    demux(result).map(|count| count as usize)
+}
+
+/// Set the verdict for packets no rule matches in `direction`.
+pub fn filter_policy(direction: u8, action: u8) -> SyscallResult<()> {
+    let result = unsafe {
+        syscall3(
+            SYSCALL_NET_FILTER,
+            NET_FILTER_OP_POLICY,
+            direction as u64,
+            action as u64,
+        )
+    };
+    demux(result).map(|_| ())
+}
+
+pub fn filter_status() -> SyscallResult<NetFilterStatus> {
+    let mut status = NetFilterStatus::default();
+    let result = unsafe {
+        syscall2(
+            SYSCALL_NET_FILTER,
+            NET_FILTER_OP_STATUS,
+            &mut status as *mut NetFilterStatus as u64,
+        )
+    };
+    demux(result).map(|_| status)
+}
+
+/// Send one ICMP echo request and wait for its reply.
+pub fn ping(request: &NetPingRequest) -> SyscallResult<NetPingReply> {
+    let mut reply = NetPingReply::default();
+    let result = unsafe {
+        syscall2(
+            SYSCALL_NET_PING,
+            request as *const NetPingRequest as u64,
+            &mut reply as *mut NetPingReply as u64,
+        )
+    };
+    demux(result).map(|_| reply)
+}
+
+/// Add a route, replacing one for the same prefix and interface.
+pub fn route_add(route: &NetRoute) -> SyscallResult<()> {
+    let result = unsafe {
+        syscall2(
+            SYSCALL_NET_ROUTE,
+            NET_ROUTE_OP_ADD,
+            route as *const NetRoute as u64,
+        )
+    };
+    demux(result).map(|_| ())
+}
+
+/// Delete the route for `route.prefix/route.prefix_len`.
+pub fn route_del(route: &NetRoute) -> SyscallResult<()> {
+    let result = unsafe {
+        syscall2(
+            SYSCALL_NET_ROUTE,
+            NET_ROUTE_OP_DEL,
+            route as *const NetRoute as u64,
+        )
+    };
+    demux(result).map(|_| ())
+}
+
+/// Read the routing table.
+pub fn route_list(out: &mut [NetRoute]) -> SyscallResult<usize> {
+    let result = unsafe {
+        syscall3(
+            SYSCALL_NET_ROUTE,
+            NET_ROUTE_OP_LIST,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+        )
+    };
+    demux(result).map(|count| count as usize)
+}
+
+/// Query the DHCP lease of the network interface.
+pub fn net_lease() -> SyscallResult<NetLease> {
+    let mut lease = NetLease::default();
+    let result = unsafe { syscall1(SYSCALL_NET_LEASE, &mut lease as *mut NetLease as u64) };
+    demux(result).map(|_| lease)
+}
+
+/// List kernel sockets with index `start` or above, in index order.
+/// Returns how many were copied.
+pub fn net_stat(out: &mut [NetSocketStat], start: u32) -> SyscallResult<usize> {
+    let result = unsafe {
+        syscall3(
+            SYSCALL_NET_STAT,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+            start as u64,
+        )
+    };
+    demux(result).map(|count| count as usize)
+}
+
 /// Resolve a hostname to an IPv4 address via the in-kernel DNS client.
 ///
 /// Returns `Some([a, b, c, d])` on success, or `None` if resolution fails.
 pub fn resolve(hostname: &[u8]) -> Option<[u8; 4]> {
+    resolve_with_flags(hostname, 0).ok()
+}
+
+/// Look `hostname` up without waiting: `Err(EAGAIN)` while the query is in
+/// flight, so call again later (e.g. from a poll loop) for the address.
+pub fn resolve_nonblocking(hostname: &[u8]) -> SyscallResult<[u8; 4]> {
+    resolve_with_flags(hostname, NET_RESOLVE_NONBLOCK)
+}
+
+fn resolve_with_flags(hostname: &[u8], flags: u64) -> SyscallResult<[u8; 4]> {
     let mut result = [0u8; 4];
     let rc = unsafe {
-        syscall3(
+        syscall4(
             SYSCALL_RESOLVE,
             hostname.as_ptr() as u64,
             hostname.len() as u64,
             &mut result as *mut [u8; 4] as u64,
+            flags,
         )
     };
-    if (rc as i64) < 0 { None } else { Some(result) }
+    demux(rc).map(|_| result)
 }
 
 pub fn bind_any(fd: RawFd, port: u16) -> SyscallResult<()> {
@@ -236,6 +502,14 @@ pub fn set_nonblocking(fd: RawFd) -> SyscallResult<()> {
     Ok(())
 }
 
+/// Clear `O_NONBLOCK` set by [`set_nonblocking`].
+pub fn set_blocking(fd: RawFd) -> SyscallResult<()> {
+    let current = demux(unsafe { syscall3(SYSCALL_FCNTL, fd as u64, F_GETFL, 0) })?;
+    let flags = (current as u64) & !O_NONBLOCK;
+    let _ = demux(unsafe { syscall3(SYSCALL_FCNTL, fd as u64, F_SETFL, flags) })?;
+    Ok(())
+}
+
 pub fn udp_echo_test() -> SyscallResult<()> {
     Ok(())
 }
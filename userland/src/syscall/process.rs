@@ -1,8 +1,10 @@
 //! Process management syscalls: spawn, exec, fork, halt, reboot.
 
 use super::numbers::*;
-use super::raw::{syscall0, syscall1, syscall2, syscall3, syscall4, syscall6};
+use super::raw::{syscall0, syscall1, syscall2, syscall3, syscall4, syscall5, syscall6};
+use slopos_abi::rlimit::RLimit;
 use slopos_abi::signal::{SIG_IGN, SigSet, UserSigaction};
+use slopos_abi::syscall_filter::SyscallFilter;
 
 #[inline(always)]
 pub fn getpid() -> u32 {
@@ -14,6 +16,28 @@ pub fn getuid() -> u32 {
     unsafe { syscall0(SYSCALL_GETUID) as u32 }
 }
 
+#[inline(always)]
+pub fn getgid() -> u32 {
+    unsafe { syscall0(SYSCALL_GETGID) as u32 }
+}
+
+#[inline(always)]
+pub fn geteuid() -> u32 {
+    unsafe { syscall0(SYSCALL_GETEUID) as u32 }
+}
+
+/// Root sets both the real and effective uid; anyone else may only drop
+/// the effective uid back to the real one.
+#[inline(always)]
+pub fn setuid(uid: u32) -> i64 {
+    unsafe { syscall1(SYSCALL_SETUID, uid as u64) as i64 }
+}
+
+#[inline(always)]
+pub fn setgid(gid: u32) -> i64 {
+    unsafe { syscall1(SYSCALL_SETGID, gid as u64) as i64 }
+}
+
 #[inline(always)]
 pub fn chdir(path: *const u8) -> i64 {
     unsafe { syscall1(SYSCALL_CHDIR, path as u64) as i64 }
@@ -24,6 +48,41 @@ pub fn getcwd(buf: &mut [u8]) -> i64 {
     unsafe { syscall2(SYSCALL_GETCWD, buf.as_mut_ptr() as u64, buf.len() as u64) as i64 }
 }
 
+/// Restrict this task (and everything it later forks, spawns or execs) to
+/// the syscalls `filter` permits.  Filters stack and cannot be removed.
+#[inline(always)]
+pub fn set_syscall_filter(filter: &SyscallFilter) -> i64 {
+    unsafe {
+        syscall1(
+            SYSCALL_SET_SYSCALL_FILTER,
+            filter as *const SyscallFilter as u64,
+        ) as i64
+    }
+}
+
+#[inline(always)]
+pub fn getrlimit(resource: u32, limit: &mut RLimit) -> i64 {
+    unsafe {
+        syscall2(
+            SYSCALL_GETRLIMIT,
+            resource as u64,
+            limit as *mut RLimit as u64,
+        ) as i64
+    }
+}
+
+/// Lowering the hard limit is one-way unless the caller is root.
+#[inline(always)]
+pub fn setrlimit(resource: u32, limit: &RLimit) -> i64 {
+    unsafe {
+        syscall2(
+            SYSCALL_SETRLIMIT,
+            resource as u64,
+            limit as *const RLimit as u64,
+        ) as i64
+    }
+}
+
 #[inline(always)]
 pub fn spawn_path(path: &[u8]) -> i32 {
     spawn_path_with_attrs(path, 5, 0)
@@ -59,6 +118,28 @@ pub fn spawn_path_with_argv(path: &[u8], argv: &[*const u8], priority: u8, flags
     }
 }
 
+/// Spawn `path` (NUL-terminated) with null-terminated `argv` and `envp`
+/// arrays, either of which may be null.
+#[inline(always)]
+pub fn spawnve(
+    path: *const u8,
+    argv: *const *const u8,
+    envp: *const *const u8,
+    priority: u8,
+    flags: u16,
+) -> i32 {
+    unsafe {
+        syscall5(
+            SYSCALL_SPAWNVE,
+            path as u64,
+            argv as u64,
+            envp as u64,
+            priority as u64,
+            flags as u64,
+        ) as i32
+    }
+}
+
 #[inline(always)]
 pub fn waitpid(task_id: u32) -> i32 {
     unsafe { syscall2(SYSCALL_WAITPID, task_id as u64, 0) as i32 }
@@ -99,6 +180,13 @@ pub fn fork() -> i32 {
     unsafe { syscall0(SYSCALL_FORK) as i32 }
 }
 
+/// Start a new session with the caller as its leader, dropping any
+/// controlling terminal.  Fails for a process group leader.
+#[inline(always)]
+pub fn setsid() -> i32 {
+    unsafe { syscall0(SYSCALL_SETSID) as i32 }
+}
+
 #[inline(always)]
 pub fn setpgid(pid: u32, pgid: u32) -> i32 {
     unsafe { syscall2(SYSCALL_SETPGID, pid as u64, pgid as u64) as i32 }
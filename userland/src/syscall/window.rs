@@ -1,9 +1,12 @@
 //! Window and surface management syscalls.
 
+use super::error::{SyscallResult, demux};
 use super::numbers::*;
-use super::raw::{syscall0, syscall1, syscall2, syscall3, syscall4};
+use super::raw::{syscall0, syscall1, syscall2, syscall3, syscall4, syscall6};
 use slopos_abi::damage::DamageRect;
-use slopos_abi::{DisplayInfo, SurfaceRole, WindowInfo};
+use slopos_abi::present::PresentFeedback;
+use slopos_abi::window::CURSOR_IMAGE_BYTES;
+use slopos_abi::{DisplayInfo, OutputInfo, SurfaceRole, WindowInfo};
 
 #[inline(always)]
 pub fn fb_info(out: &mut DisplayInfo) -> i64 {
@@ -15,11 +18,38 @@ pub fn fb_flip(token: u32) -> i64 {
     unsafe { syscall3(SYSCALL_FB_FLIP, token as u64, 0, 0) as i64 }
 }
 
+/// Push writes made through a `mmap_framebuffer` mapping to the display.
+#[inline(always)]
+pub fn fb_flush() -> i64 {
+    unsafe { syscall0(SYSCALL_FB_FLUSH) as i64 }
+}
+
 #[inline(always)]
 pub fn drain_queue() -> i64 {
     unsafe { syscall0(SYSCALL_DRAIN_QUEUE) as i64 }
 }
 
+/// Block until a client, pointer input or timed present gives the
+/// compositor work, or `timeout_ms` elapses (`COMPOSITOR_WAIT_FOREVER`:
+/// never).  Returns `true` if woken by work.
+#[inline(always)]
+pub fn compositor_wait(timeout_ms: u64) -> bool {
+    unsafe { syscall1(SYSCALL_COMPOSITOR_WAIT, timeout_ms) == 1 }
+}
+
+/// Ask the compositor to reload the wallpaper.
+#[inline(always)]
+pub fn wallpaper_reload() {
+    unsafe { syscall0(SYSCALL_WALLPAPER_RELOAD) };
+}
+
+/// Compositor only: whether a wallpaper reload was requested since the
+/// last call.
+#[inline(always)]
+pub fn wallpaper_take_reload() -> bool {
+    unsafe { syscall0(SYSCALL_WALLPAPER_TAKE_RELOAD) == 1 }
+}
+
 #[inline(always)]
 pub fn fb_flip_damage(token: u32, damage: &[DamageRect]) -> i64 {
     if damage.is_empty() {
@@ -62,6 +92,77 @@ pub fn poll_frame_done() -> u64 {
     unsafe { syscall0(SYSCALL_POLL_FRAME_DONE) }
 }
 
+/// Block until a vblank after `after_seq` (0: the next one) and return
+/// its number.
+#[inline(always)]
+pub fn wait_vblank(after_seq: u64) -> u64 {
+    unsafe { syscall1(SYSCALL_WAIT_VBLANK, after_seq) }
+}
+
+/// Show `image` on the hardware cursor plane with its hotspot at
+/// `hot_x, hot_y` (compositor only).  Fails with `ENODEV` when the display
+/// has no cursor plane and the caller has to draw the cursor.
+pub fn cursor_set_image(
+    image: &[u8; CURSOR_IMAGE_BYTES],
+    hot_x: u32,
+    hot_y: u32,
+) -> SyscallResult<()> {
+    let result = unsafe {
+        syscall3(
+            SYSCALL_CURSOR_SET_IMAGE,
+            image.as_ptr() as u64,
+            hot_x as u64,
+            hot_y as u64,
+        )
+    };
+    demux(result).map(|_| ())
+}
+
+/// Move the hardware cursor's hotspot to `x, y` (compositor only).
+#[inline(always)]
+pub fn cursor_move(x: i32, y: i32) {
+    unsafe {
+        syscall2(SYSCALL_CURSOR_MOVE, x as u64, y as u64);
+    }
+}
+
+/// Fill `out` with the display outputs and return how many there are,
+/// which may exceed `out.len()`.
+pub fn output_enumerate(out: &mut [OutputInfo]) -> usize {
+    let result = unsafe {
+        syscall2(
+            SYSCALL_OUTPUT_ENUMERATE,
+            out.as_mut_ptr() as u64,
+            out.len() as u64,
+        )
+    };
+    demux(result).map_or(0, |count| count as usize)
+}
+
+/// Move output `id` to `x, y` on the desktop.  `width` and `height` of 0
+/// keep its mode; `flags` are `OUTPUT_CONFIGURE_*`.
+pub fn output_configure(
+    id: u32,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    flags: u32,
+) -> SyscallResult<()> {
+    let result = unsafe {
+        syscall6(
+            SYSCALL_OUTPUT_CONFIGURE,
+            id as u64,
+            x as u64,
+            y as u64,
+            width as u64,
+            height as u64,
+            flags as u64,
+        )
+    };
+    demux(result).map(|_| ())
+}
+
 #[inline(always)]
 pub fn mark_frames_done(present_time_ms: u64) {
     unsafe {
@@ -114,6 +215,47 @@ pub fn surface_set_title(title: &str) -> i64 {
     }
 }
 
+/// Set the opacity (255 is opaque) and `SURFACE_BLEND_*` flags the
+/// compositor draws this task's surface with.
+#[inline(always)]
+pub fn surface_set_opacity(opacity: u8, flags: u8) -> i64 {
+    unsafe { syscall2(SYSCALL_SURFACE_SET_OPACITY, opacity as u64, flags as u64) as i64 }
+}
+
+/// Set this task's `WINDOW_FLAG_*` flags, e.g. `WINDOW_FLAG_UNDECORATED`.
+#[inline(always)]
+pub fn surface_set_window_flags(flags: u8) -> i64 {
+    unsafe { syscall1(SYSCALL_SURFACE_SET_WINDOW_FLAGS, flags as u64) as i64 }
+}
+
+/// Tell the compositor this task's buffer is drawn at `scale` (see
+/// `DisplayInfo::scale`); lower-scale buffers are magnified to match.
+#[inline(always)]
+pub fn surface_set_buffer_scale(scale: u32) -> i64 {
+    unsafe { syscall1(SYSCALL_SURFACE_SET_BUFFER_SCALE, scale as u64) as i64 }
+}
+
+/// Draw a thumbnail of `task_id`'s surface, fitted to `max_width` x
+/// `max_height`, into the shared buffer `token`.  Returns the thumbnail's
+/// size; its rows are packed at that width.
+pub fn surface_thumbnail(
+    task_id: u32,
+    token: u32,
+    max_width: u32,
+    max_height: u32,
+) -> SyscallResult<(u32, u32)> {
+    let result = unsafe {
+        syscall4(
+            SYSCALL_SURFACE_THUMBNAIL,
+            task_id as u64,
+            token as u64,
+            max_width as u64,
+            max_height as u64,
+        )
+    };
+    demux(result).map(|size| ((size >> 32) as u32, size as u32))
+}
+
 #[inline(always)]
 pub fn enumerate_windows(windows: &mut [WindowInfo]) -> i64 {
     unsafe {
@@ -151,3 +293,36 @@ pub fn raise_window(task_id: u32) -> i64 {
 pub fn set_cursor_shape(shape: u8) -> i64 {
     unsafe { syscall1(SYSCALL_SET_CURSOR_SHAPE, shape as u64) as i64 }
 }
+
+/// Queue `token` to be shown on the refresh nearest `target_ns`
+/// (`CLOCK_MONOTONIC`; 0 for the next one).  Blocks while the present queue
+/// is full.
+///
+/// # Returns
+/// The buffer's sequence number; compare against
+/// [`PresentFeedback::presented_seq`] to know when it may be reused.
+///
+/// # Errors
+/// * `ENOENT` - No surface attached (composited present)
+/// * `EPERM` - `PRESENT_FLAG_BYPASS` without display-exclusive access
+/// * `EINVAL` - Bad token or flags, or a target before the previous one
+/// * `EAGAIN` - The queue stayed full
+#[inline(always)]
+pub fn present(token: u32, target_ns: u64, flags: u32) -> SyscallResult<u64> {
+    let result = unsafe {
+        syscall3(
+            SYSCALL_SURFACE_PRESENT,
+            token as u64,
+            target_ns,
+            flags as u64,
+        )
+    };
+    demux(result)
+}
+
+#[inline(always)]
+pub fn present_feedback() -> SyscallResult<PresentFeedback> {
+    let mut feedback = PresentFeedback::default();
+    let result = unsafe { syscall1(SYSCALL_PRESENT_FEEDBACK, &mut feedback as *mut _ as u64) };
+    demux(result).map(|_| feedback)
+}
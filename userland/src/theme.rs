@@ -2,8 +2,16 @@ use slopos_abi::draw::Color32;
 
 // Window / UI Sizes
 pub const TITLE_BAR_HEIGHT: i32 = 24;
+/// Pixel height window titles are drawn at.
+pub const TITLE_FONT_SIZE: u32 = 16;
+/// Gap between a title bar's left edge and the title.
+pub const TITLE_TEXT_INSET: i32 = 8;
 pub const BUTTON_SIZE: i32 = 20;
 pub const BUTTON_PADDING: i32 = 2;
+/// Width of the grab band around a window's frame for resizing.
+pub const RESIZE_BORDER: i32 = 5;
+pub const MIN_WINDOW_WIDTH: i32 = 96;
+pub const MIN_WINDOW_HEIGHT: i32 = 48;
 
 // Taskbar Sizes
 pub const TASKBAR_HEIGHT: i32 = 32;
@@ -20,7 +28,9 @@ pub const COLOR_TITLE_BAR: Color32 = Color32::rgb(0x1E, 0x1E, 0x1E);
 pub const COLOR_TITLE_BAR_FOCUSED: Color32 = Color32::rgb(0x2D, 0x2D, 0x30);
 pub const COLOR_BUTTON: Color32 = Color32::rgb(0x3E, 0x3E, 0x42);
 pub const COLOR_BUTTON_HOVER: Color32 = Color32::rgb(0x50, 0x50, 0x52);
+pub const COLOR_BUTTON_PRESSED: Color32 = Color32::rgb(0x2A, 0x2A, 0x2C);
 pub const COLOR_BUTTON_CLOSE_HOVER: Color32 = Color32::rgb(0xE8, 0x11, 0x23);
+pub const COLOR_BUTTON_CLOSE_PRESSED: Color32 = Color32::rgb(0xA0, 0x0C, 0x18);
 pub const COLOR_TEXT: Color32 = Color32::rgb(0xE0, 0xE0, 0xE0);
 pub const COLOR_TASKBAR: Color32 = Color32::rgb(0x25, 0x25, 0x26);
 pub const COLOR_CURSOR: Color32 = Color32::rgb(0xFF, 0xFF, 0xFF);
@@ -28,8 +38,8 @@ pub const COLOR_BACKGROUND: Color32 = Color32::rgb(0x00, 0x11, 0x22);
 pub const COLOR_START_MENU_BG: Color32 = Color32::rgb(0x1A, 0x1A, 0x1C);
 
 // File Manager Specific
-pub const FM_WIDTH: i32 = 400;
-pub const FM_HEIGHT: i32 = 300;
+pub const FM_WIDTH: i32 = 640;
+pub const FM_HEIGHT: i32 = 400;
 pub const FM_TITLE_HEIGHT: i32 = TITLE_BAR_HEIGHT;
 pub const FM_ITEM_HEIGHT: i32 = 20;
 pub const FM_COLOR_BG: Color32 = Color32::rgb(0x25, 0x25, 0x26);
@@ -4,7 +4,8 @@
 //! - Single lock protects all compositor state
 //! - CLIENT operations (commit, register, unregister) enqueue and return immediately
 //! - COMPOSITOR operations (set_position, set_state, raise, enumerate) execute immediately
-//! - Compositor drains the queue at the start of each frame
+//! - Compositor drains the queue at the start of each frame; every enqueue
+//!   wakes it (see `slopos_lib::compositor_wake`)
 //!
 //! Buffer Ownership Model (Wayland-aligned):
 //! - Client owns the buffer (ShmBuffer in userland)
@@ -15,13 +16,23 @@
 //! - NO kernel-side buffer copies
 
 use alloc::collections::{BTreeMap, VecDeque};
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use slopos_abi::{
-    CompositorError, DamageRect, MAX_CHILDREN, MAX_WINDOW_DAMAGE_REGIONS, SurfaceRole,
-    WINDOW_STATE_NORMAL, WindowInfo,
+    COMPOSITOR_WAIT_FOREVER, CompositorError, DamageRect, DisplayInfo, MAX_CHILDREN,
+    MAX_WINDOW_DAMAGE_REGIONS, SURFACE_BLEND_FLAGS, SurfaceRole, WINDOW_FLAGS,
+    WINDOW_OPACITY_OPAQUE, WINDOW_STATE_MAX, WINDOW_STATE_MINIMIZED, WINDOW_STATE_NORMAL,
+    WindowInfo, window_state_is_fitted,
 };
+use slopos_core::ktimer::{KtimerMode, ktimer_add, ktimer_cancel_sync};
+use slopos_core::scheduler::sleep::sleep_current_task_ms;
 use slopos_gfx::damage::InternalDamageTracker;
 use slopos_lib::IrqMutex;
+use slopos_lib::clock::monotonic_ns;
+use slopos_lib::compositor_wake::{compositor_kick, compositor_take_wake, compositor_wake};
+
+use crate::{framebuffer, present, vblank};
 
 type DamageTracker = InternalDamageTracker;
 
@@ -85,6 +96,22 @@ enum ClientOp {
         task_id: u32,
         shape: u8,
     },
+    /// Set how the surface blends onto what is under it
+    SetOpacity {
+        task_id: u32,
+        opacity: u8,
+        flags: u8,
+    },
+    /// Set `WINDOW_FLAG_*` flags, such as going undecorated
+    SetWindowFlags {
+        task_id: u32,
+        flags: u8,
+    },
+    /// Set the scale the client's buffer is drawn at
+    SetBufferScale {
+        task_id: u32,
+        scale: u8,
+    },
 }
 
 impl ClientOp {
@@ -98,7 +125,10 @@ impl ClientOp {
             | ClientOp::SetParent { task_id, .. }
             | ClientOp::SetRelativePosition { task_id, .. }
             | ClientOp::SetTitle { task_id, .. }
-            | ClientOp::SetCursorShape { task_id, .. } => *task_id,
+            | ClientOp::SetCursorShape { task_id, .. }
+            | ClientOp::SetOpacity { task_id, .. }
+            | ClientOp::SetWindowFlags { task_id, .. }
+            | ClientOp::SetBufferScale { task_id, .. } => *task_id,
         }
     }
 }
@@ -131,10 +161,18 @@ struct SurfaceState {
     z_order: u32,
     /// Whether window is visible
     visible: bool,
-    /// Window state (normal, minimized, maximized)
+    /// Window state (normal, minimized, maximized, tiled)
     window_state: u8,
+    /// State to go back to when a minimized window is restored
+    unminimized_state: u8,
+    /// Content area (x, y, width, height) from before the window was
+    /// maximized or tiled
+    restore_geometry: Option<(i32, i32, u32, u32)>,
     /// True if client has requested a frame callback (Wayland wl_surface.frame)
     frame_callback_pending: bool,
+    /// True once the requested frame has been flipped; the callback fires
+    /// on the next vblank
+    frame_callback_flipped: bool,
     /// Timestamp (ms) when the frame was presented, 0 if not yet presented
     last_present_time_ms: u64,
     /// Role of this surface (toplevel, popup, subsurface)
@@ -151,6 +189,13 @@ struct SurfaceState {
     /// Window title (UTF-8, null-terminated)
     title: [u8; 32],
     cursor_shape: u8,
+    /// Opacity and `SURFACE_BLEND_*` flags for compositing
+    opacity: u8,
+    blend_flags: u8,
+    /// `WINDOW_FLAG_*` flags
+    window_flags: u8,
+    /// Scale the client drew its buffer at
+    buffer_scale: u8,
 }
 
 impl SurfaceState {
@@ -168,7 +213,10 @@ impl SurfaceState {
             z_order: 0,
             visible: true,
             window_state: WINDOW_STATE_NORMAL,
+            unminimized_state: WINDOW_STATE_NORMAL,
+            restore_geometry: None,
             frame_callback_pending: false,
+            frame_callback_flipped: false,
             last_present_time_ms: 0,
             role: SurfaceRole::None,
             parent_task: None,
@@ -178,6 +226,10 @@ impl SurfaceState {
             relative_y: 0,
             title: [0; 32],
             cursor_shape: 0,
+            opacity: WINDOW_OPACITY_OPAQUE,
+            blend_flags: 0,
+            window_flags: 0,
+            buffer_scale: 1,
         }
     }
 
@@ -207,8 +259,57 @@ impl SurfaceState {
         });
     }
 
-    fn export_damage(&self) -> ([DamageRect; MAX_WINDOW_DAMAGE_REGIONS], u8) {
-        export_damage_to_window_format(&self.committed_damage)
+    /// Desktop pixels per buffer pixel on a display at `display_scale`.
+    /// Buffers drawn for a lower scale are magnified; others show 1:1.
+    fn content_scale(&self, display_scale: u32) -> u32 {
+        (display_scale / self.buffer_scale as u32).max(1)
+    }
+
+    /// Move to window state `state`.  Minimizing remembers the state to
+    /// restore into, and maximizing or tiling a floating window remembers
+    /// its geometry, in desktop pixels, until it floats again.
+    fn set_window_state(&mut self, state: u8, display_scale: u32) {
+        let current = self.window_state;
+        let state = if state == WINDOW_STATE_NORMAL && current == WINDOW_STATE_MINIMIZED {
+            self.unminimized_state
+        } else {
+            state
+        };
+        if state == WINDOW_STATE_MINIMIZED {
+            if current != WINDOW_STATE_MINIMIZED {
+                self.unminimized_state = current;
+            }
+        } else if window_state_is_fitted(state) {
+            if self.restore_geometry.is_none() {
+                let scale = self.content_scale(display_scale);
+                self.restore_geometry = Some((
+                    self.window_x,
+                    self.window_y,
+                    self.width * scale,
+                    self.height * scale,
+                ));
+            }
+        } else {
+            self.restore_geometry = None;
+            self.unminimized_state = WINDOW_STATE_NORMAL;
+        }
+        self.window_state = state;
+    }
+
+    /// Committed damage in desktop pixels, for a window magnified by
+    /// `scale`.
+    fn export_damage(&self, scale: u32) -> ([DamageRect; MAX_WINDOW_DAMAGE_REGIONS], u8) {
+        let (mut regions, count) = export_damage_to_window_format(&self.committed_damage);
+        if scale > 1 {
+            let scale = scale as i32;
+            for rect in &mut regions[..(count as usize).min(MAX_WINDOW_DAMAGE_REGIONS)] {
+                rect.x0 *= scale;
+                rect.y0 *= scale;
+                rect.x1 = rect.x1 * scale + scale - 1;
+                rect.y1 = rect.y1 * scale + scale - 1;
+            }
+        }
+        (regions, count)
     }
 }
 
@@ -265,6 +366,16 @@ impl CompositorContext {
 
 static CONTEXT: IrqMutex<CompositorContext> = IrqMutex::new(CompositorContext::new());
 
+/// Set by a client asking for the wallpaper to be reloaded; the compositor
+/// clears it when it picks the request up.
+static WALLPAPER_RELOAD: AtomicBool = AtomicBool::new(false);
+
+/// Queue a client operation and wake the compositor to process it.
+fn enqueue(op: ClientOp) {
+    CONTEXT.lock().queue.push_back(op);
+    compositor_wake();
+}
+
 // =============================================================================
 // PUBLIC API - Client Operations (ENQUEUE and return immediately)
 // =============================================================================
@@ -275,12 +386,12 @@ static CONTEXT: IrqMutex<CompositorContext> = IrqMutex::new(CompositorContext::n
 /// Note: This is now zero-copy. The compositor reads directly from the client's
 /// shared memory buffer. Only damage tracking is transferred on commit.
 pub fn surface_commit(task_id: u32) -> Result<(), CompositorError> {
-    let mut ctx = CONTEXT.lock();
-    ctx.queue.push_back(ClientOp::Commit { task_id });
+    enqueue(ClientOp::Commit { task_id });
     Ok(())
 }
 
-/// Register a surface for a task when it calls surface_attach.
+/// Register a surface for a task when it calls surface_attach, or move an
+/// existing one onto a new buffer (a resize).
 /// Called by CLIENT tasks. Enqueues the registration for processing by compositor.
 pub fn register_surface_for_task(
     task_id: u32,
@@ -288,8 +399,7 @@ pub fn register_surface_for_task(
     height: u32,
     shm_token: u32,
 ) -> Result<(), CompositorError> {
-    let mut ctx = CONTEXT.lock();
-    ctx.queue.push_back(ClientOp::Register {
+    enqueue(ClientOp::Register {
         task_id,
         width,
         height,
@@ -310,6 +420,9 @@ pub fn unregister_surface_for_task(task_id: u32) {
         }
     }
     ctx.queue = filtered;
+    drop(ctx);
+    // The window is gone from the screen on the next frame.
+    compositor_wake();
 }
 
 // =============================================================================
@@ -347,8 +460,20 @@ pub fn drain_queue() {
                 height,
                 shm_token,
             } => {
-                // Skip if already registered
-                if ctx.surfaces.contains_key(&task_id) {
+                // Attaching again swaps the surface onto the new buffer,
+                // keeping its place, stacking and window state.
+                if let Some(surface) = ctx.surfaces.get_mut(&task_id) {
+                    if surface.shm_token != shm_token
+                        || surface.width != width
+                        || surface.height != height
+                    {
+                        surface.shm_token = shm_token;
+                        surface.width = width;
+                        surface.height = height;
+                        surface.pending_damage.clear();
+                        surface.committed_damage.set_full_damage();
+                        surface.dirty = true;
+                    }
                     processed += 1;
                     continue;
                 }
@@ -448,10 +573,40 @@ pub fn drain_queue() {
                     surface.cursor_shape = shape;
                 }
             }
+            ClientOp::SetOpacity {
+                task_id,
+                opacity,
+                flags,
+            } => {
+                if let Some(surface) = ctx.surfaces.get_mut(&task_id) {
+                    surface.opacity = opacity;
+                    surface.blend_flags = flags;
+                    surface.dirty = true;
+                }
+            }
+            ClientOp::SetWindowFlags { task_id, flags } => {
+                if let Some(surface) = ctx.surfaces.get_mut(&task_id) {
+                    surface.window_flags = flags;
+                    surface.dirty = true;
+                }
+            }
+            ClientOp::SetBufferScale { task_id, scale } => {
+                if let Some(surface) = ctx.surfaces.get_mut(&task_id)
+                    && surface.buffer_scale != scale
+                {
+                    surface.buffer_scale = scale;
+                    surface.committed_damage.set_full_damage();
+                    surface.dirty = true;
+                }
+            }
         }
         processed += 1;
     }
     // Any remaining ops are processed next frame
+    if !ctx.queue.is_empty() {
+        drop(ctx);
+        compositor_wake();
+    }
 }
 
 /// Set window position. IMMEDIATE - called by COMPOSITOR only.
@@ -469,9 +624,13 @@ pub fn surface_set_window_position(task_id: u32, x: i32, y: i32) -> Result<(), C
 
 /// Set window state. IMMEDIATE - called by COMPOSITOR only.
 pub fn surface_set_window_state(task_id: u32, state: u8) -> Result<(), CompositorError> {
+    if state > WINDOW_STATE_MAX {
+        return Err(CompositorError::InvalidArgument);
+    }
+    let display_scale = display_scale();
     let mut ctx = CONTEXT.lock();
     if let Some(surface) = ctx.surfaces.get_mut(&task_id) {
-        surface.window_state = state;
+        surface.set_window_state(state, display_scale);
         surface.dirty = true;
         Ok(())
     } else {
@@ -480,9 +639,7 @@ pub fn surface_set_window_state(task_id: u32, state: u8) -> Result<(), Composito
 }
 
 pub fn surface_set_cursor_shape(task_id: u32, shape: u8) -> Result<(), CompositorError> {
-    let mut ctx = CONTEXT.lock();
-    ctx.queue
-        .push_back(ClientOp::SetCursorShape { task_id, shape });
+    enqueue(ClientOp::SetCursorShape { task_id, shape });
     Ok(())
 }
 
@@ -513,22 +670,23 @@ pub fn surface_raise_window(task_id: u32) -> Result<(), CompositorError> {
 /// Static windows may report stale damage, but that's preferable to losing damage.
 ///
 /// For subsurfaces, the absolute position is calculated as parent position + relative offset.
-pub fn surface_enumerate_windows(out_buffer: *mut WindowInfo, max_count: u32) -> u32 {
-    if out_buffer.is_null() || max_count == 0 {
+pub fn surface_enumerate_windows(out: &mut [WindowInfo]) -> u32 {
+    if out.is_empty() {
         return 0;
     }
 
+    let display_scale = display_scale();
     let ctx = CONTEXT.lock();
     let mut count = 0u32;
 
     // First pass: collect task IDs and their info (need to look up parents)
     for (&task_id, surface) in ctx.surfaces.iter() {
-        if count >= max_count {
+        if count as usize >= out.len() {
             break;
         }
 
-        // Skip invisible windows
-        if !surface.visible {
+        // Skip invisible windows and off-screen buffers
+        if !surface.visible || surface.role == SurfaceRole::Offscreen {
             continue;
         }
 
@@ -554,23 +712,31 @@ pub fn surface_enumerate_windows(out_buffer: *mut WindowInfo, max_count: u32) ->
             (surface.window_x, surface.window_y)
         };
 
-        let (regions, dmg_count) = surface.export_damage();
-
-        unsafe {
-            let info = &mut *out_buffer.add(count as usize);
-            info.task_id = task_id;
-            info.x = abs_x;
-            info.y = abs_y;
-            info.width = surface.width;
-            info.height = surface.height;
-            info.state = surface.window_state;
-            info.damage_count = dmg_count;
-            info.cursor_shape = surface.cursor_shape;
-            info._padding = 0;
-            info.shm_token = surface.shm_token;
-            info.damage_regions = regions;
-            info.title = surface.title;
-        }
+        let scale = surface.content_scale(display_scale);
+        let (regions, dmg_count) = surface.export_damage(scale);
+
+        let info = &mut out[count as usize];
+        info.task_id = task_id;
+        info.x = abs_x;
+        info.y = abs_y;
+        info.width = surface.width * scale;
+        info.height = surface.height * scale;
+        info.state = surface.window_state;
+        info.damage_count = dmg_count;
+        info.cursor_shape = surface.cursor_shape;
+        info.opacity = surface.opacity;
+        info.shm_token = surface.shm_token;
+        info.damage_regions = regions;
+        info.title = surface.title;
+        info.blend_flags = surface.blend_flags;
+        info.window_flags = surface.window_flags;
+        info.content_scale = scale as u8;
+        info._padding = [0; 1];
+        let (rx, ry, rw, rh) = surface.restore_geometry.unwrap_or_default();
+        info.restore_x = rx;
+        info.restore_y = ry;
+        info.restore_width = rw;
+        info.restore_height = rh;
 
         // Damage is acknowledged and cleared in `surface_mark_frames_done()` after
         // successful present. Do not clear here to avoid losing damage if present fails.
@@ -587,25 +753,42 @@ pub fn surface_enumerate_windows(out_buffer: *mut WindowInfo, max_count: u32) ->
 /// Request a frame callback. Called by CLIENT tasks.
 /// Enqueues the request for processing by compositor.
 pub fn surface_request_frame_callback(task_id: u32) -> Result<(), CompositorError> {
-    let mut ctx = CONTEXT.lock();
-    ctx.queue
-        .push_back(ClientOp::RequestFrameCallback { task_id });
+    enqueue(ClientOp::RequestFrameCallback { task_id });
     Ok(())
 }
 
-/// Mark frame as done for all surfaces with pending callbacks.
+/// Mark frame as flipped for all surfaces with pending callbacks.
 /// Called by COMPOSITOR after presenting a frame.
-/// Sets last_present_time_ms for surfaces that had frame_callback_pending.
-pub fn surface_mark_frames_done(present_time_ms: u64) {
-    let mut ctx = CONTEXT.lock();
+/// The callbacks complete on the next vblank, which arms the vblank timer.
+pub fn surface_mark_frames_done(_present_time_ms: u64) {
+    let mut flipped = false;
+    {
+        let mut ctx = CONTEXT.lock();
+        for surface in ctx.surfaces.values_mut() {
+            // Compositor only calls this after a successful present. At this point,
+            // the previously committed damage has been consumed and can be cleared.
+            surface.committed_damage.clear();
+            if surface.frame_callback_pending {
+                surface.frame_callback_pending = false;
+                surface.frame_callback_flipped = true;
+                flipped = true;
+            }
+        }
+    }
+    if flipped {
+        vblank::vblank_arm();
+    }
+}
 
+/// Complete flipped frame callbacks.  Called from the vblank timer with the
+/// vblank's time; sets last_present_time_ms for those surfaces.
+pub fn surface_complete_frame_callbacks(vblank_time_ms: u64) {
+    let mut ctx = CONTEXT.lock();
     for surface in ctx.surfaces.values_mut() {
-        // Compositor only calls this after a successful present. At this point,
-        // the previously committed damage has been consumed and can be cleared.
-        surface.committed_damage.clear();
-        if surface.frame_callback_pending {
-            surface.last_present_time_ms = present_time_ms;
-            surface.frame_callback_pending = false;
+        if surface.frame_callback_flipped {
+            surface.frame_callback_flipped = false;
+            // 0 means "not presented" to pollers.
+            surface.last_present_time_ms = vblank_time_ms.max(1);
         }
     }
 }
@@ -627,6 +810,44 @@ pub fn surface_poll_frame_done(task_id: u32) -> u64 {
     }
 }
 
+// =============================================================================
+// Timed Presentation (see `present`)
+// =============================================================================
+
+/// Dimensions of a task's surface, if it has one.
+pub fn surface_size(task_id: u32) -> Option<(u32, u32)> {
+    let ctx = CONTEXT.lock();
+    ctx.surfaces.get(&task_id).map(|s| (s.width, s.height))
+}
+
+/// The NUL-padded title of a task's surface, if it has one.
+pub fn surface_title(task_id: u32) -> Option<[u8; 32]> {
+    let ctx = CONTEXT.lock();
+    ctx.surfaces.get(&task_id).map(|s| s.title)
+}
+
+/// A task's surface buffer token and dimensions, if it has a surface.
+pub fn surface_buffer(task_id: u32) -> Option<(u32, u32, u32)> {
+    let ctx = CONTEXT.lock();
+    ctx.surfaces
+        .get(&task_id)
+        .map(|s| (s.shm_token, s.width, s.height))
+}
+
+/// Swap a surface onto a new buffer and damage all of it.  IMMEDIATE -
+/// called by the present queue while the compositor drains.
+pub fn surface_latch_buffer(task_id: u32, shm_token: u32) -> Result<(), CompositorError> {
+    let mut ctx = CONTEXT.lock();
+    let surface = ctx
+        .surfaces
+        .get_mut(&task_id)
+        .ok_or(CompositorError::SurfaceNotFound)?;
+    surface.shm_token = shm_token;
+    surface.committed_damage.set_full_damage();
+    surface.dirty = true;
+    Ok(())
+}
+
 // =============================================================================
 // Damage Tracking Protocol (Wayland wl_surface.damage)
 // =============================================================================
@@ -641,8 +862,7 @@ pub fn surface_add_damage(
     width: i32,
     height: i32,
 ) -> Result<(), CompositorError> {
-    let mut ctx = CONTEXT.lock();
-    ctx.queue.push_back(ClientOp::AddDamage {
+    enqueue(ClientOp::AddDamage {
         task_id,
         x,
         y,
@@ -678,16 +898,14 @@ pub fn surface_set_role(task_id: u32, role: u8) -> Result<(), CompositorError> {
         None => return Err(CompositorError::InvalidRole),
     };
 
-    let mut ctx = CONTEXT.lock();
-    ctx.queue.push_back(ClientOp::SetRole { task_id, role });
+    enqueue(ClientOp::SetRole { task_id, role });
     Ok(())
 }
 
 /// Set the parent surface for a subsurface. Called by CLIENT tasks.
 /// Only valid for surfaces with role Subsurface.
 pub fn surface_set_parent(task_id: u32, parent_task_id: u32) -> Result<(), CompositorError> {
-    let mut ctx = CONTEXT.lock();
-    ctx.queue.push_back(ClientOp::SetParent {
+    enqueue(ClientOp::SetParent {
         task_id,
         parent_task_id,
     });
@@ -702,8 +920,7 @@ pub fn surface_set_relative_position(
     rel_x: i32,
     rel_y: i32,
 ) -> Result<(), CompositorError> {
-    let mut ctx = CONTEXT.lock();
-    ctx.queue.push_back(ClientOp::SetRelativePosition {
+    enqueue(ClientOp::SetRelativePosition {
         task_id,
         rel_x,
         rel_y,
@@ -720,10 +937,107 @@ pub fn surface_set_title(task_id: u32, title: &[u8]) -> Result<(), CompositorErr
     // Ensure null termination
     title_buf[copy_len] = 0;
 
-    let mut ctx = CONTEXT.lock();
-    ctx.queue.push_back(ClientOp::SetTitle {
+    enqueue(ClientOp::SetTitle {
         task_id,
         title: title_buf,
     });
     Ok(())
 }
+
+/// Set the surface's opacity and `SURFACE_BLEND_*` flags. Called by CLIENT
+/// tasks.
+pub fn surface_set_opacity(task_id: u32, opacity: u8, flags: u8) -> Result<(), CompositorError> {
+    if flags & !SURFACE_BLEND_FLAGS != 0 {
+        return Err(CompositorError::InvalidArgument);
+    }
+    enqueue(ClientOp::SetOpacity {
+        task_id,
+        opacity,
+        flags,
+    });
+    Ok(())
+}
+
+/// Set the surface's `WINDOW_FLAG_*` flags. Called by CLIENT tasks.
+pub fn surface_set_window_flags(task_id: u32, flags: u8) -> Result<(), CompositorError> {
+    if flags & !WINDOW_FLAGS != 0 {
+        return Err(CompositorError::InvalidArgument);
+    }
+    enqueue(ClientOp::SetWindowFlags { task_id, flags });
+    Ok(())
+}
+
+/// Set the scale the surface's buffer is drawn at. Called by CLIENT tasks.
+pub fn surface_set_buffer_scale(task_id: u32, scale: u32) -> Result<(), CompositorError> {
+    if !(1..=DisplayInfo::MAX_SCALE).contains(&scale) {
+        return Err(CompositorError::InvalidArgument);
+    }
+    enqueue(ClientOp::SetBufferScale {
+        task_id,
+        scale: scale as u8,
+    });
+    Ok(())
+}
+
+/// The display's UI scale, 1 before there is a display.
+fn display_scale() -> u32 {
+    framebuffer::get_display_info().map_or(1, |info| info.scale_factor())
+}
+
+// =============================================================================
+// Idle Wait (SYSCALL_COMPOSITOR_WAIT)
+// =============================================================================
+
+fn wait_timer_fired(context: *mut c_void) {
+    // SAFETY: `compositor_wait` cancels the timer synchronously before the
+    // flag goes out of scope.
+    let expired = unsafe { &*(context as *const AtomicBool) };
+    expired.store(true, Ordering::Release);
+    compositor_kick();
+}
+
+/// Block the compositor until a client, pointer input or timed present gives
+/// it work, or `timeout_ms` elapses.  The wait never outlasts the next due
+/// composited present.  Returns whether work was signalled.
+pub fn compositor_wait(timeout_ms: u64) -> bool {
+    let timeout_ms = match present::next_composited_refresh() {
+        Some(refresh) => {
+            let due_ms = refresh.saturating_sub(monotonic_ns()).div_ceil(1_000_000);
+            timeout_ms.min(due_ms)
+        }
+        None => timeout_ms,
+    };
+    if timeout_ms == 0 {
+        return compositor_take_wake();
+    }
+    if timeout_ms == COMPOSITOR_WAIT_FOREVER {
+        return slopos_lib::compositor_wake::compositor_wait(|| false);
+    }
+
+    let expired = AtomicBool::new(false);
+    let ms = timeout_ms.min(u32::MAX as u64) as u32;
+    let Some(timer) = ktimer_add(
+        KtimerMode::OneShot,
+        ms,
+        wait_timer_fired,
+        &expired as *const AtomicBool as *mut c_void,
+    ) else {
+        // No timer to wake us: sleep out the timeout instead.
+        sleep_current_task_ms(ms);
+        return compositor_take_wake();
+    };
+    let woken = slopos_lib::compositor_wake::compositor_wait(|| expired.load(Ordering::Acquire));
+    ktimer_cancel_sync(timer);
+    woken
+}
+
+/// Ask the compositor to reload the wallpaper on its next frame.
+pub fn wallpaper_request_reload() {
+    WALLPAPER_RELOAD.store(true, Ordering::Release);
+    compositor_wake();
+}
+
+/// Whether a wallpaper reload was requested since the last call.
+pub fn wallpaper_take_reload() -> bool {
+    WALLPAPER_RELOAD.swap(false, Ordering::AcqRel)
+}
@@ -0,0 +1,60 @@
+//! Hardware cursor plane (`SYSCALL_CURSOR_SET_IMAGE`, `SYSCALL_CURSOR_MOVE`).
+//!
+//! Backends with a cursor plane register it at init.  The compositor hands
+//! it the cursor image and moves it as the pointer moves, so pointer motion
+//! alone never needs a composite; on the plain framebuffer and virtio-gpu
+//! backends there is no plane and the compositor draws the cursor itself.
+
+use slopos_abi::window::{CURSOR_IMAGE_BYTES, CURSOR_IMAGE_SIZE};
+use slopos_lib::IrqMutex;
+
+/// A backend's cursor plane.
+#[derive(Clone, Copy)]
+pub struct CursorPlane {
+    /// Show a `CURSOR_IMAGE_BYTES` image with the given hotspot; false if
+    /// the plane could not take it.
+    pub set_image: fn(&[u8], u32, u32) -> bool,
+    /// Move the hotspot to a screen position.
+    pub move_to: fn(i32, i32),
+    pub hide: fn(),
+}
+
+static CURSOR_PLANE: IrqMutex<Option<CursorPlane>> = IrqMutex::new(None);
+
+pub fn register_cursor_plane(plane: CursorPlane) {
+    swap_cursor_plane(Some(plane));
+}
+
+/// Install `plane` (or none) and return the one it replaces.
+pub fn swap_cursor_plane(plane: Option<CursorPlane>) -> Option<CursorPlane> {
+    core::mem::replace(&mut *CURSOR_PLANE.lock(), plane)
+}
+
+/// Whether the display has a hardware cursor plane.
+pub fn cursor_plane_present() -> bool {
+    CURSOR_PLANE.lock().is_some()
+}
+
+/// Show `image` on the cursor plane, or hide the cursor when `image` is
+/// empty.  Returns false when there is no plane to show it, or the image
+/// or hotspot is malformed.
+pub fn surface_set_cursor_image(image: &[u8], hot_x: u32, hot_y: u32) -> bool {
+    let Some(plane) = *CURSOR_PLANE.lock() else {
+        return false;
+    };
+    if image.is_empty() {
+        (plane.hide)();
+        return true;
+    }
+    if image.len() != CURSOR_IMAGE_BYTES || hot_x >= CURSOR_IMAGE_SIZE || hot_y >= CURSOR_IMAGE_SIZE
+    {
+        return false;
+    }
+    (plane.set_image)(image, hot_x, hot_y)
+}
+
+pub fn surface_move_cursor(x: i32, y: i32) {
+    if let Some(plane) = *CURSOR_PLANE.lock() {
+        (plane.move_to)(x, y);
+    }
+}
@@ -2,9 +2,13 @@ use core::ffi::c_int;
 use core::ptr;
 
 use slopos_abi::addr::{PhysAddr, VirtAddr};
-use slopos_abi::{DisplayInfo, PixelFormat};
-use slopos_lib::{IrqMutex, klog_debug, klog_warn};
-use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_abi::damage::{DamageRect, MAX_DAMAGE_REGIONS};
+use slopos_abi::{DisplayInfo, FramebufferData, PixelFormat};
+use slopos_lib::clock::monotonic_ns;
+use slopos_lib::cpu::fastcopy;
+use slopos_lib::{IrqMutex, klog_debug, klog_warn, kwarn};
+use slopos_mm::hhdm::{PhysAddrHhdm, VirtAddrHhdm};
+use slopos_mm::mmio::MmioRegion;
 
 const MIN_FRAMEBUFFER_WIDTH: u32 = 320;
 const MIN_FRAMEBUFFER_HEIGHT: u32 = 240;
@@ -75,6 +79,61 @@ impl FramebufferState {
 static FRAMEBUFFER: IrqMutex<FramebufferState> = IrqMutex::new(FramebufferState::new());
 static FRAMEBUFFER_FLUSH: IrqMutex<Option<fn() -> c_int>> = IrqMutex::new(None);
 
+/// Copies damage (everything if empty) from an SHM buffer laid out like
+/// the scanout onto it on the GPU; false leaves the copy to the CPU.
+type BlitFromShm = fn(PhysAddr, usize, &[DamageRect]) -> bool;
+static FRAMEBUFFER_BLIT: IrqMutex<Option<BlitFromShm>> = IrqMutex::new(None);
+
+/// A backend that scans out of two buffers and flips between them at
+/// vblank.
+#[derive(Clone, Copy)]
+pub struct PageFlip {
+    /// Physical address of the buffer off screen; `None` if there is only
+    /// one.
+    pub back_buffer: fn() -> Option<PhysAddr>,
+    /// Put the back buffer on screen at the next vblank.
+    pub flip: fn() -> bool,
+    /// Whether the last flip has reached the screen.
+    pub flip_done: fn() -> bool,
+}
+
+struct PageFlipState {
+    backend: Option<PageFlip>,
+    /// Write-combining mappings of the scanout buffers, by physical address.
+    mappings: [Option<(PhysAddr, VirtAddr)>; 2],
+    /// What the previous frame drew.  The back buffer was last drawn the
+    /// frame before that, so it lacks this too.
+    prev_damage: [DamageRect; MAX_DAMAGE_REGIONS],
+    prev_damage_count: usize,
+    /// The back buffer needs everything copied.
+    prev_full: bool,
+}
+
+impl PageFlipState {
+    const fn new() -> Self {
+        Self {
+            backend: None,
+            mappings: [None; 2],
+            prev_damage: [DamageRect::invalid(); MAX_DAMAGE_REGIONS],
+            prev_damage_count: 0,
+            prev_full: true,
+        }
+    }
+
+    fn mapping(&mut self, phys: PhysAddr, size: usize) -> Option<VirtAddr> {
+        if let Some((_, virt)) = self.mappings.iter().flatten().find(|(p, _)| *p == phys) {
+            return Some(*virt);
+        }
+        let slot = self.mappings.iter_mut().find(|m| m.is_none())?;
+        let region = MmioRegion::map_wc(phys, size)?;
+        let virt = VirtAddr::try_new(region.virt_base())?;
+        *slot = Some((phys, virt));
+        Some(virt)
+    }
+}
+
+static PAGE_FLIP: IrqMutex<PageFlipState> = IrqMutex::new(PageFlipState::new());
+
 fn init_state_from_raw(addr: u64, width: u32, height: u32, pitch: u32, bpp: u8) -> i32 {
     if addr == 0 || width < MIN_FRAMEBUFFER_WIDTH || width > DisplayInfo::MAX_DIMENSION {
         return -1;
@@ -120,6 +179,36 @@ fn init_state_from_raw(addr: u64, width: u32, height: u32, pitch: u32, bpp: u8)
     0
 }
 
+/// Alias a scanout buffer write-combining in the MMIO window.
+///
+/// Boot and Xe framebuffers arrive as write-back HHDM pointers; with WC,
+/// full-screen blits leave the CPU as bursts instead of per-line cache
+/// evictions.  Returns `fb` unchanged if the buffer can't be remapped.
+pub fn map_write_combining(fb: FramebufferData) -> FramebufferData {
+    let addr = fb.address as u64;
+    let phys = match slopos_mm::hhdm::try_offset() {
+        Some(hhdm_base) if addr >= hhdm_base => {
+            VirtAddr::try_new(addr).and_then(|virt| virt.to_phys_walk())
+        }
+        _ => PhysAddr::try_new(addr),
+    };
+    let size = fb.info.pitch as usize * fb.info.height as usize;
+
+    match phys.and_then(|phys| MmioRegion::map_wc(phys, size)) {
+        Some(region) => FramebufferData {
+            address: region.virt_base() as *mut u8,
+            info: fb.info,
+        },
+        None => {
+            klog_warn!(
+                "Framebuffer: WC remap of 0x{:x} failed; staying write-back",
+                addr
+            );
+            fb
+        }
+    }
+}
+
 pub fn init_with_display_info(address: *mut u8, info: &DisplayInfo) -> i32 {
     let rc = init_state_from_raw(
         address as u64,
@@ -141,7 +230,7 @@ pub fn init_with_display_info(address: *mut u8, info: &DisplayInfo) -> i32 {
                 fb.bpp()
             );
         } else {
-            klog_warn!("Framebuffer init: state missing after init");
+            kwarn!(true, "Framebuffer init: state missing after init");
         }
     } else {
         klog_warn!(
@@ -160,6 +249,61 @@ pub fn get_display_info() -> Option<DisplayInfo> {
     FRAMEBUFFER.lock().fb.map(|fb| fb.info)
 }
 
+/// Copy raw scanout bytes starting at `offset` into `dst` (`/dev/fb0` read).
+///
+/// Returns the number of bytes copied; 0 at or past the end of the buffer.
+pub fn fb_read(offset: usize, dst: &mut [u8]) -> usize {
+    let Some(fb) = snapshot() else {
+        return 0;
+    };
+    let len = dst.len().min(fb.buffer_size().saturating_sub(offset));
+    if len == 0 {
+        return 0;
+    }
+    let Some(src) = fb.checked_ptr(offset, len) else {
+        return 0;
+    };
+    // SAFETY: checked_ptr bounds-checked [offset, offset + len) against the
+    // mapped framebuffer; dst holds at least len bytes.
+    unsafe {
+        ptr::copy_nonoverlapping(src, dst.as_mut_ptr(), len);
+    }
+    len
+}
+
+/// Copy `src` into the scanout starting at `offset` (`/dev/fb0` write).
+///
+/// Writes are clipped to the framebuffer size; returns the bytes written.
+pub fn fb_write(offset: usize, src: &[u8]) -> usize {
+    let Some(fb) = snapshot() else {
+        return 0;
+    };
+    let len = src.len().min(fb.buffer_size().saturating_sub(offset));
+    if len == 0 {
+        return 0;
+    }
+    let Some(dst) = fb.checked_ptr(offset, len) else {
+        return 0;
+    };
+    // SAFETY: checked_ptr bounds-checked [offset, offset + len) against the
+    // mapped framebuffer; src holds at least len bytes.
+    unsafe {
+        ptr::copy_nonoverlapping(src.as_ptr(), dst, len);
+    }
+    framebuffer_flush();
+    len
+}
+
+/// Physical base and byte size of the scanout, for mapping it into a
+/// display-exclusive task.  Every backend's scanout is physically
+/// contiguous (Limine's linear framebuffer, the Xe and virtio-gpu single
+/// backing allocations).
+pub fn scanout_region() -> Option<(PhysAddr, usize)> {
+    let fb = snapshot()?;
+    let phys = fb.base.to_phys_walk()?;
+    Some((phys, fb.buffer_size()))
+}
+
 pub(crate) fn snapshot() -> Option<FbState> {
     FRAMEBUFFER.lock().fb
 }
@@ -169,11 +313,62 @@ pub fn register_flush_callback(callback: fn() -> c_int) {
     *guard = Some(callback);
 }
 
+pub fn register_blit_callback(callback: BlitFromShm) {
+    *FRAMEBUFFER_BLIT.lock() = Some(callback);
+}
+
+pub fn register_page_flip(backend: PageFlip) {
+    let mut state = PAGE_FLIP.lock();
+    state.backend = Some(backend);
+    state.prev_full = true;
+}
+
+/// Whether the last page flip has reached the screen; always true on
+/// single-buffered backends.
+pub fn page_flip_done() -> bool {
+    let backend = PAGE_FLIP.lock().backend;
+    backend.is_none_or(|backend| (backend.flip_done)())
+}
+
+fn blit_from_shm(shm_phys: PhysAddr, size: usize, damage: &[DamageRect]) -> bool {
+    let blit = *FRAMEBUFFER_BLIT.lock();
+    blit.is_some_and(|blit| blit(shm_phys, size, damage))
+}
+
 pub fn framebuffer_flush() -> c_int {
     let guard = FRAMEBUFFER_FLUSH.lock();
     if let Some(cb) = *guard { cb() } else { 0 }
 }
 
+/// Row gaps up to this size are copied along with the rows around them,
+/// so a rect nearly as wide as the pitch becomes one span.  The SHM buffer
+/// holds the whole frame, so the extra bytes are current too.
+const BATCH_GAP_BYTES: usize = 256;
+
+/// Copy `len` bytes at `off` from the SHM buffer to the same offset in `fb`.
+fn copy_span_from_shm(
+    fb: &FbState,
+    shm_virt: *const u8,
+    shm_size: usize,
+    off: usize,
+    len: usize,
+) -> bool {
+    let Some(src_end) = off.checked_add(len) else {
+        return false;
+    };
+    if src_end > shm_size {
+        return false;
+    }
+    let Some(dst_ptr) = fb.checked_ptr(off, len) else {
+        return false;
+    };
+    // SAFETY: src range is checked against shm_size, dst range checked by checked_ptr.
+    unsafe {
+        fastcopy::copy_span(dst_ptr, shm_virt.add(off), len);
+    }
+    true
+}
+
 fn copy_rect_from_shm(
     fb: &FbState,
     shm_virt: *const u8,
@@ -197,36 +392,101 @@ fn copy_rect_from_shm(
     }
 
     let row_bytes = (cx1 - cx0 + 1) as usize * bytes_pp;
-    for row in cy0..=cy1 {
-        let row_usize = row as usize;
-        let src_off = row_usize
+    let Some(first) = (cy0 as usize)
+        .checked_mul(fb_pitch)
+        .and_then(|v| v.checked_add(cx0 as usize * bytes_pp))
+    else {
+        return false;
+    };
+    let rows = (cy1 - cy0) as usize;
+
+    if fb_pitch.saturating_sub(row_bytes) <= BATCH_GAP_BYTES {
+        let Some(len) = rows
             .checked_mul(fb_pitch)
-            .and_then(|v| v.checked_add(cx0 as usize * bytes_pp));
-        let dst_off = src_off;
-        let Some(src_off) = src_off else {
-            return false;
-        };
-        let Some(dst_off) = dst_off else {
+            .and_then(|v| v.checked_add(row_bytes))
+        else {
             return false;
         };
+        return copy_span_from_shm(fb, shm_virt, shm_size, first, len);
+    }
 
-        let Some(src_end) = src_off.checked_add(row_bytes) else {
-            return false;
-        };
-        if src_end > shm_size {
-            return false;
-        }
+    (0..=rows)
+        .all(|row| copy_span_from_shm(fb, shm_virt, shm_size, first + row * fb_pitch, row_bytes))
+}
 
-        let Some(dst_ptr) = fb.checked_ptr(dst_off, row_bytes) else {
+/// Copy `damage` (everything if empty) from the SHM buffer into `fb`.
+fn copy_from_shm(fb: &FbState, shm_ptr: *const u8, size: usize, damage: &[DamageRect]) -> bool {
+    if damage.is_empty() {
+        let Some(dst_ptr) = fb.checked_ptr(0, size) else {
             return false;
         };
-        // SAFETY: src range is checked against shm_size, dst range checked by checked_ptr.
+        // SAFETY: source and destination have been validated and are non-overlapping.
         unsafe {
-            ptr::copy_nonoverlapping(shm_virt.add(src_off), dst_ptr, row_bytes);
+            fastcopy::copy_span(dst_ptr, shm_ptr, size);
         }
+        return true;
     }
+    damage
+        .iter()
+        .filter(|rect| rect.is_valid())
+        .all(|rect| copy_rect_from_shm(fb, shm_ptr, size, rect.x0, rect.y0, rect.x1, rect.y1))
+}
 
-    true
+/// Wait for the last flip to land before drawing into the buffer it took
+/// off screen.  Gives up after two refreshes rather than stall.
+fn wait_for_flip(backend: &PageFlip) {
+    let deadline = monotonic_ns() + 2 * crate::vblank::refresh_ns();
+    while !(backend.flip_done)() && monotonic_ns() < deadline {
+        core::hint::spin_loop();
+    }
+}
+
+/// Draw the frame into the off-screen buffer and flip to it.  That buffer
+/// was last drawn two frames ago, so it also gets the previous frame's
+/// damage.  `None` when the backend is single-buffered.
+fn page_flip_from_shm(
+    fb: &FbState,
+    shm_phys: PhysAddr,
+    shm_ptr: *const u8,
+    size: usize,
+    damage: &[DamageRect],
+) -> Option<c_int> {
+    let backend = PAGE_FLIP.lock().backend?;
+    let back_phys = (backend.back_buffer)()?;
+    wait_for_flip(&backend);
+
+    let mut state = PAGE_FLIP.lock();
+    let back_virt = state.mapping(back_phys, fb.buffer_size())?;
+    let back = FbState {
+        base: back_virt,
+        info: fb.info,
+    };
+
+    let mut combined = [DamageRect::invalid(); MAX_DAMAGE_REGIONS * 2];
+    let regions: &[DamageRect] = if damage.is_empty() || state.prev_full {
+        &[]
+    } else {
+        let prev = &state.prev_damage[..state.prev_damage_count];
+        combined[..damage.len()].copy_from_slice(damage);
+        combined[damage.len()..damage.len() + prev.len()].copy_from_slice(prev);
+        &combined[..damage.len() + prev.len()]
+    };
+    let copied =
+        blit_from_shm(shm_phys, size, regions) || copy_from_shm(&back, shm_ptr, size, regions);
+
+    state.prev_full = damage.is_empty() || !copied;
+    state.prev_damage_count = damage.len();
+    state.prev_damage[..damage.len()].copy_from_slice(damage);
+    drop(state);
+    if !copied || !(backend.flip)() {
+        return Some(-1);
+    }
+
+    // Direct kernel drawing goes to the buffer on screen.
+    if let Some(fb) = FRAMEBUFFER.lock().fb.as_mut() {
+        fb.base = back_virt;
+    }
+    Some(0)
 }
 
 pub fn fb_flip_from_shm(shm_phys: PhysAddr, size: usize) -> c_int {
@@ -236,7 +496,7 @@ pub fn fb_flip_from_shm(shm_phys: PhysAddr, size: usize) -> c_int {
 pub fn fb_flip_from_shm_damage(
     shm_phys: PhysAddr,
     size: usize,
-    damage: *const slopos_abi::damage::DamageRect,
+    damage: *const DamageRect,
     damage_count: u32,
 ) -> c_int {
     let fb = match FRAMEBUFFER.lock().fb {
@@ -257,29 +517,22 @@ pub fn fb_flip_from_shm_damage(
 
     let shm_ptr = shm_virt as *const u8;
 
-    if damage.is_null() || damage_count == 0 {
-        let Some(dst_ptr) = fb.checked_ptr(0, copy_size) else {
-            return -1;
-        };
-        // SAFETY: source and destination have been validated and are non-overlapping.
-        unsafe {
-            ptr::copy_nonoverlapping(shm_ptr, dst_ptr, copy_size);
-        }
-        return framebuffer_flush();
-    }
-
-    let max_regions = slopos_abi::damage::MAX_DAMAGE_REGIONS as u32;
-    let region_count = damage_count.min(max_regions) as usize;
-    // SAFETY: kernel syscall path validates this pointer and length before calling us.
-    let regions = unsafe { core::slice::from_raw_parts(damage, region_count) };
+    // An empty damage list means the whole buffer.
+    let regions: &[DamageRect] = if damage.is_null() || damage_count == 0 {
+        &[]
+    } else {
+        let region_count = damage_count.min(MAX_DAMAGE_REGIONS as u32) as usize;
+        // SAFETY: kernel syscall path validates this pointer and length before calling us.
+        unsafe { core::slice::from_raw_parts(damage, region_count) }
+    };
 
-    for rect in regions {
-        if !rect.is_valid() {
-            continue;
-        }
-        if !copy_rect_from_shm(&fb, shm_ptr, copy_size, rect.x0, rect.y0, rect.x1, rect.y1) {
-            return -1;
-        }
+    if let Some(rc) = page_flip_from_shm(&fb, shm_phys, shm_ptr, copy_size, regions) {
+        return rc;
+    }
+    if !blit_from_shm(shm_phys, copy_size, regions)
+        && !copy_from_shm(&fb, shm_ptr, copy_size, regions)
+    {
+        return -1;
     }
     framebuffer_flush()
 }
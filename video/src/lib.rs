@@ -8,29 +8,51 @@ use slopos_abi::CompositorError;
 use slopos_abi::FramebufferData;
 use slopos_abi::addr::PhysAddr;
 use slopos_abi::damage::DamageRect;
-use slopos_abi::video_traits::VideoResult;
+use slopos_abi::video_traits::{VideoError, VideoResult, video_result_from_code};
 use slopos_core::task::register_task_resource_cleanup_hook;
+use slopos_drivers::virtio_gpu;
 #[cfg(feature = "xe-gpu")]
 use slopos_drivers::xe;
 use slopos_lib::kernel_services::syscall_services::video::{
     VideoServices, register_video_services,
 };
-use slopos_lib::{klog_info, klog_warn};
+use slopos_lib::{IrqMutex, klog_info, klog_warn};
 
 pub mod compositor_context;
+pub mod cursor;
 pub mod framebuffer;
 pub mod graphics;
+pub mod output;
 pub mod panic_screen;
+pub mod present;
 pub mod roulette_core;
 pub mod splash;
+pub mod thumbnail;
+pub mod vblank;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum VideoBackend {
     Framebuffer,
+    /// virtio-gpu 2D scanout; flushing transfers the dirty pages to the host.
+    VirtioGpu,
     #[cfg(feature = "xe-gpu")]
     Xe,
 }
 
+impl VideoBackend {
+    /// Driver name reported in the hardware inventory.
+    pub const fn driver_name(self) -> &'static str {
+        match self {
+            Self::Framebuffer => "framebuffer",
+            Self::VirtioGpu => "virtio-gpu",
+            #[cfg(feature = "xe-gpu")]
+            Self::Xe => "xe",
+        }
+    }
+}
+
+static ACTIVE_BACKEND: IrqMutex<VideoBackend> = IrqMutex::new(VideoBackend::Framebuffer);
+
 fn video_fb_flip(
     shm_phys: PhysAddr,
     size: usize,
@@ -40,6 +62,18 @@ fn video_fb_flip(
     framebuffer::fb_flip_from_shm_damage(shm_phys, size, damage, damage_count)
 }
 
+/// The compositor's frame starts here, so this is where timed presents
+/// latch onto their surfaces.
+fn video_drain_queue() {
+    compositor_context::drain_queue();
+    present::present_latch_composited();
+}
+
+fn video_mark_frames_done(present_time_ms: u64) {
+    compositor_context::surface_mark_frames_done(present_time_ms);
+    present::present_frames_done();
+}
+
 fn video_roulette_draw(fate: u32) -> VideoResult {
     roulette_core::roulette_draw_kernel(fate)
 }
@@ -67,6 +101,10 @@ fn video_surface_set_title(
 
 static VIDEO_SERVICES: VideoServices = VideoServices {
     get_display_info: framebuffer::get_display_info,
+    fb_read: framebuffer::fb_read,
+    fb_write: framebuffer::fb_write,
+    fb_flush: framebuffer::framebuffer_flush,
+    scanout_region: framebuffer::scanout_region,
     roulette_draw: video_roulette_draw,
     surface_enumerate_windows: compositor_context::surface_enumerate_windows,
     surface_set_window_position: compositor_context::surface_set_window_position,
@@ -75,36 +113,78 @@ static VIDEO_SERVICES: VideoServices = VideoServices {
     surface_raise_window: compositor_context::surface_raise_window,
     surface_commit: compositor_context::surface_commit,
     register_surface: compositor_context::register_surface_for_task,
-    drain_queue: compositor_context::drain_queue,
+    drain_queue: video_drain_queue,
+    compositor_wait: compositor_context::compositor_wait,
+    wallpaper_request_reload: compositor_context::wallpaper_request_reload,
+    wallpaper_take_reload: compositor_context::wallpaper_take_reload,
     fb_flip: video_fb_flip,
     surface_request_frame_callback: compositor_context::surface_request_frame_callback,
-    surface_mark_frames_done: compositor_context::surface_mark_frames_done,
+    surface_mark_frames_done: video_mark_frames_done,
     surface_poll_frame_done: compositor_context::surface_poll_frame_done,
+    wait_vblank: vblank::wait_vblank,
+    surface_set_cursor_image: cursor::surface_set_cursor_image,
+    surface_move_cursor: cursor::surface_move_cursor,
+    output_enumerate: output::output_enumerate,
+    output_configure: output::output_configure,
     surface_add_damage: compositor_context::surface_add_damage,
     surface_get_buffer_age: compositor_context::surface_get_buffer_age,
+    surface_title: compositor_context::surface_title,
     surface_set_role: compositor_context::surface_set_role,
     surface_set_parent: compositor_context::surface_set_parent,
     surface_set_relative_position: compositor_context::surface_set_relative_position,
+    surface_set_opacity: compositor_context::surface_set_opacity,
+    surface_set_window_flags: compositor_context::surface_set_window_flags,
+    surface_set_buffer_scale: compositor_context::surface_set_buffer_scale,
+    surface_render_thumbnail: thumbnail::surface_render_thumbnail,
     surface_set_title: video_surface_set_title,
+    surface_present: present::surface_present,
+    present_feedback: present::present_feedback,
 };
 
 fn task_cleanup_callback(task_id: u32) {
     compositor_context::unregister_surface_for_task(task_id);
+    present::present_forget_task(task_id);
 }
 
 // =============================================================================
 // Initialization
 // =============================================================================
 
-pub fn init(framebuffer: Option<FramebufferData>, _backend: VideoBackend) {
+pub fn init(framebuffer: Option<FramebufferData>, backend: VideoBackend) {
     register_task_resource_cleanup_hook(task_cleanup_callback);
 
+    *ACTIVE_BACKEND.lock() = backend;
+    if backend == VideoBackend::VirtioGpu {
+        framebuffer::register_flush_callback(virtio_gpu::virtio_gpu_flush);
+    }
     #[cfg(feature = "xe-gpu")]
-    if _backend == VideoBackend::Xe {
+    if backend == VideoBackend::Xe {
         framebuffer::register_flush_callback(xe::xe_flush);
+        framebuffer::register_blit_callback(xe::xe_flip_from_shm);
+        framebuffer::register_page_flip(framebuffer::PageFlip {
+            back_buffer: xe::xe_back_buffer,
+            flip: xe::xe_page_flip,
+            flip_done: xe::xe_flip_done,
+        });
+        cursor::register_cursor_plane(cursor::CursorPlane {
+            set_image: xe::xe_cursor_set_image,
+            move_to: xe::xe_cursor_move,
+            hide: xe::xe_cursor_hide,
+        });
+        output::register_output_backend(output::OutputBackend {
+            enumerate: xe::xe_outputs,
+            set_position: xe::xe_output_set_position,
+        });
     }
 
-    let fb_to_use = framebuffer;
+    // virtio-gpu scans out of guest RAM that the host copies on flush, so
+    // WC buys nothing there.
+    let fb_to_use = match framebuffer {
+        Some(fb) if backend != VideoBackend::VirtioGpu => {
+            Some(framebuffer::map_write_combining(fb))
+        }
+        other => other,
+    };
 
     if let Some(fb) = fb_to_use {
         klog_info!(
@@ -135,6 +215,29 @@ pub fn init(framebuffer: Option<FramebufferData>, _backend: VideoBackend) {
     }
 }
 
+pub fn active_backend() -> VideoBackend {
+    *ACTIVE_BACKEND.lock()
+}
+
+/// Change the display resolution.  Only the virtio-gpu backend can do this;
+/// the boot framebuffer's mode is fixed by firmware.
+///
+/// The previous scanout stays alive until the framebuffer state points at
+/// the new one, so concurrent drawing never lands in freed pages.
+pub fn set_mode(width: u32, height: u32) -> VideoResult {
+    if active_backend() != VideoBackend::VirtioGpu {
+        return Err(VideoError::Invalid);
+    }
+    let fb = virtio_gpu::virtio_gpu_set_mode(width, height).ok_or(VideoError::Invalid)?;
+    if framebuffer::init_with_display_info(fb.address, &fb.info) != 0 {
+        return Err(VideoError::Invalid);
+    }
+    virtio_gpu::virtio_gpu_release_retired();
+    slopos_drivers::mouse::set_bounds(width as i32, height as i32);
+    klog_info!("Display mode changed to {}x{}", width, height);
+    video_result_from_code(framebuffer::framebuffer_flush())
+}
+
 fn paint_banner() {
     use slopos_abi::draw::Color32;
     use slopos_gfx::canvas_ops;
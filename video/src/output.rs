@@ -0,0 +1,113 @@
+//! Display outputs (`SYSCALL_OUTPUT_ENUMERATE`, `SYSCALL_OUTPUT_CONFIGURE`).
+//!
+//! The desktop is the one framebuffer `DisplayInfo` describes; each output
+//! scans out a rectangle of it.  Backends driving several pipes register an
+//! [`OutputBackend`]; the others have one output covering the framebuffer.
+//! Outputs keep the mode firmware set, so configuring one moves it within
+//! the desktop and picks the primary output, where the compositor puts
+//! windows left on no output.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use slopos_abi::display::{
+    MAX_OUTPUTS, OUTPUT_CONFIGURE_FLAGS, OUTPUT_CONFIGURE_PRIMARY, OUTPUT_FLAG_PRIMARY,
+};
+use slopos_abi::{CompositorError, OutputInfo};
+use slopos_lib::IrqMutex;
+use slopos_lib::compositor_wake::compositor_wake;
+
+use crate::framebuffer;
+
+/// A backend's outputs.
+#[derive(Clone, Copy)]
+pub struct OutputBackend {
+    /// Fill the slice (`MAX_OUTPUTS` long) with the outputs; returns how
+    /// many it wrote.
+    pub enumerate: fn(&mut [OutputInfo]) -> usize,
+    /// Move an output to a desktop position; false if it cannot go there.
+    pub set_position: fn(u32, i32, i32) -> bool,
+}
+
+static OUTPUT_BACKEND: IrqMutex<Option<OutputBackend>> = IrqMutex::new(None);
+static PRIMARY_OUTPUT: AtomicU32 = AtomicU32::new(0);
+
+pub fn register_output_backend(backend: OutputBackend) {
+    swap_output_backend(Some(backend));
+}
+
+/// Install `backend` (or none) and return the one it replaces.
+pub fn swap_output_backend(backend: Option<OutputBackend>) -> Option<OutputBackend> {
+    core::mem::replace(&mut *OUTPUT_BACKEND.lock(), backend)
+}
+
+/// Fill `out` with the outputs, primary one flagged, and return how many
+/// there are, which may be more than fit.
+pub fn output_enumerate(out: &mut [OutputInfo]) -> usize {
+    let mut all = [OutputInfo::default(); MAX_OUTPUTS];
+    let backend = *OUTPUT_BACKEND.lock();
+    let count = match backend {
+        Some(backend) => (backend.enumerate)(&mut all),
+        None => match framebuffer::get_display_info() {
+            Some(info) => {
+                all[0] = OutputInfo::new(0, b"fb0", 0, 0, info.width, info.height);
+                1
+            }
+            None => 0,
+        },
+    };
+    let outputs = &mut all[..count];
+
+    let primary = PRIMARY_OUTPUT.load(Ordering::Relaxed);
+    let primary = if outputs.iter().any(|o| o.id == primary) {
+        primary
+    } else {
+        outputs.first().map_or(0, |o| o.id)
+    };
+    for output in outputs.iter_mut() {
+        output.flags = if output.id == primary {
+            OUTPUT_FLAG_PRIMARY
+        } else {
+            0
+        };
+    }
+    let shown = count.min(out.len());
+    out[..shown].copy_from_slice(&all[..shown]);
+    count
+}
+
+/// Move output `id` to desktop position `x, y` and apply
+/// `OUTPUT_CONFIGURE_*` flags.  `width` and `height` of 0 keep the mode;
+/// otherwise they must be the output's current mode, the only one there is.
+pub fn output_configure(
+    id: u32,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    flags: u32,
+) -> Result<(), CompositorError> {
+    if flags & !OUTPUT_CONFIGURE_FLAGS != 0 {
+        return Err(CompositorError::InvalidArgument);
+    }
+    let mut outputs = [OutputInfo::default(); MAX_OUTPUTS];
+    let count = output_enumerate(&mut outputs);
+    let Some(output) = outputs[..count].iter().find(|o| o.id == id) else {
+        return Err(CompositorError::InvalidArgument);
+    };
+    if (width, height) != (0, 0) && (width, height) != (output.width, output.height) {
+        return Err(CompositorError::InvalidArgument);
+    }
+
+    if (x, y) != (output.x, output.y) {
+        let backend = *OUTPUT_BACKEND.lock();
+        if !backend.is_some_and(|backend| (backend.set_position)(id, x, y)) {
+            return Err(CompositorError::InvalidArgument);
+        }
+    }
+    if flags & OUTPUT_CONFIGURE_PRIMARY != 0 {
+        PRIMARY_OUTPUT.store(id, Ordering::Relaxed);
+    }
+    // The compositor picks up the new layout on its next frame.
+    compositor_wake();
+    Ok(())
+}
@@ -1,13 +1,21 @@
 //! Kernel panic screen display.
 //!
-//! Renders a full-screen panic message when the kernel encounters
-//! an unrecoverable error. Designed to work with minimal dependencies
-//! since most subsystems may be in undefined states during panic.
+//! Renders a full-screen panic report when the kernel encounters an
+//! unrecoverable error, and lets the keyboard page through it: the
+//! registers (and the interrupt frame, for exceptions), the stack trace
+//! and the recent kernel log.  Designed to work with minimal dependencies
+//! since most subsystems may be in undefined states during panic; keys
+//! come from a caller-supplied poll.
 
+use core::fmt::{self, Write};
+
+use slopos_abi::damage::DamageRect;
 use slopos_abi::draw::{Canvas, Color32};
 use slopos_abi::font::{FONT_CHAR_HEIGHT, FONT_CHAR_WIDTH};
 use slopos_gfx::canvas_font;
-use slopos_lib::numfmt;
+use slopos_lib::kdiag::exception_name;
+use slopos_lib::stacktrace::StacktraceEntry;
+use slopos_lib::{InterruptFrame, RegSnapshot};
 
 use crate::framebuffer;
 use crate::graphics::GraphicsContext;
@@ -15,47 +23,460 @@ use crate::graphics::GraphicsContext;
 const PANIC_BG_COLOR: Color32 = Color32(0xFF8B0000);
 const PANIC_FG_COLOR: Color32 = Color32(0xFFFFFFFF);
 const PANIC_HEADER_COLOR: Color32 = Color32(0xFFFF4444);
+const PANIC_DIM_COLOR: Color32 = Color32(0xFF888888);
+const PANIC_TAB_COLOR: Color32 = Color32(0xFFB22222);
+
+const MARGIN: i32 = 40;
+const LINE_HEIGHT: i32 = FONT_CHAR_HEIGHT + 2;
+/// Lines of the reason shown above the pages before it is cut off.
+const MAX_REASON_LINES: i32 = 3;
+
+/// Everything the panic screen shows, captured by the panic handler.
+pub struct PanicReport<'a> {
+    pub message: Option<&'a str>,
+    /// Registers inside the panic handler.
+    pub regs: RegSnapshot,
+    /// The exception that led to the panic, if any.
+    pub frame: Option<&'a InterruptFrame>,
+    /// Where the CPU was when it stopped, when known without a frame
+    /// (e.g. a watchdog hang).
+    pub rip: Option<u64>,
+    pub rsp: u64,
+    pub cr0: u64,
+    pub cr2: u64,
+    pub cr3: u64,
+    pub cr4: u64,
+    /// Most recent call first.
+    pub backtrace: &'a [StacktraceEntry],
+    /// Recent kernel log text, oldest first.
+    pub log: &'a [u8],
+}
+
+/// A page of the report.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PanicPage {
+    Registers,
+    Stack,
+    Log,
+}
+
+impl PanicPage {
+    const ALL: [PanicPage; 3] = [PanicPage::Registers, PanicPage::Stack, PanicPage::Log];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            PanicPage::Registers => "Registers",
+            PanicPage::Stack => "Stack",
+            PanicPage::Log => "Log",
+        }
+    }
+}
+
+/// A key the panic screen responds to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PanicKey {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    NextPage,
+    PrevPage,
+    /// Leave the panic screen.
+    Exit,
+}
+
+/// One line of a page, cut off at [`LineBuf::CAPACITY`] bytes.
+struct LineBuf {
+    buf: [u8; Self::CAPACITY],
+    len: usize,
+}
+
+impl LineBuf {
+    const CAPACITY: usize = 128;
+
+    const fn new() -> Self {
+        Self {
+            buf: [0; Self::CAPACITY],
+            len: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl Write for LineBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let take = s.len().min(Self::CAPACITY - self.len);
+        self.buf[self.len..self.len + take].copy_from_slice(&s.as_bytes()[..take]);
+        self.len += take;
+        Ok(())
+    }
+}
+
+/// Which page is shown and how far each one is scrolled.
+pub struct PanicBrowser<'a> {
+    report: &'a PanicReport<'a>,
+    page: PanicPage,
+    scroll: [usize; 3],
+    rows: usize,
+}
+
+impl<'a> PanicBrowser<'a> {
+    /// A browser showing `rows` lines at a time, opened on the registers
+    /// with the log scrolled to its most recent lines.
+    pub fn new(report: &'a PanicReport<'a>, rows: usize) -> Self {
+        let mut browser = Self {
+            report,
+            page: PanicPage::Registers,
+            scroll: [0; 3],
+            rows: rows.max(1),
+        };
+        browser.scroll[PanicPage::Log.index()] = browser.max_scroll(PanicPage::Log);
+        browser
+    }
+
+    pub fn page(&self) -> PanicPage {
+        self.page
+    }
 
-fn draw_register_line(ctx: &mut GraphicsContext, x: i32, y: i32, label: &[u8], value: u64) {
-    canvas_font::draw_string(ctx, x, y, label, PANIC_FG_COLOR, PANIC_BG_COLOR);
+    /// Index of the first line shown on `page`.
+    pub fn scroll(&self, page: PanicPage) -> usize {
+        self.scroll[page.index()]
+    }
+
+    /// Number of lines on `page`.
+    pub fn line_count(&self, page: PanicPage) -> usize {
+        let mut count = 0;
+        self.for_each_line(page, |_, _| count += 1);
+        count
+    }
 
-    let mut hex_buf = numfmt::NumBuf::<19>::new();
-    let hex_text = hex_buf.format_hex_u64(value);
-    let label_width = (label.len() as i32 - 1) * FONT_CHAR_WIDTH;
-    canvas_font::draw_string(
+    /// Apply `key`.  Returns false once the user asks to leave.
+    pub fn handle_key(&mut self, key: PanicKey) -> bool {
+        let pages = PanicPage::ALL.len();
+        let current = self.scroll[self.page.index()];
+        let target = match key {
+            PanicKey::Exit => return false,
+            PanicKey::NextPage | PanicKey::PrevPage => {
+                let step = if key == PanicKey::NextPage {
+                    1
+                } else {
+                    pages - 1
+                };
+                self.page = PanicPage::ALL[(self.page.index() + step) % pages];
+                return true;
+            }
+            PanicKey::Up => current.saturating_sub(1),
+            PanicKey::Down => current + 1,
+            PanicKey::PageUp => current.saturating_sub(self.rows),
+            PanicKey::PageDown => current + self.rows,
+            PanicKey::Home => 0,
+            PanicKey::End => usize::MAX,
+        };
+        self.scroll[self.page.index()] = target.min(self.max_scroll(self.page));
+        true
+    }
+
+    fn max_scroll(&self, page: PanicPage) -> usize {
+        self.line_count(page).saturating_sub(self.rows)
+    }
+
+    /// Call `f` with the index and text of each line of `page`.
+    fn for_each_line(&self, page: PanicPage, mut f: impl FnMut(usize, &[u8])) {
+        let mut index = 0;
+        let mut emit = |args: fmt::Arguments<'_>| {
+            let mut line = LineBuf::new();
+            let _ = line.write_fmt(args);
+            f(index, line.as_bytes());
+            index += 1;
+        };
+        let r = self.report;
+        match page {
+            PanicPage::Registers => {
+                if let Some(frame) = r.frame {
+                    emit(format_args!(
+                        "Exception {} ({})  error code 0x{:x}",
+                        frame.vector,
+                        exception_name(frame.vector as u8),
+                        frame.error_code
+                    ));
+                    emit(format_args!(
+                        "  RIP 0x{:016x}  CS 0x{:x}  RFLAGS 0x{:x}",
+                        frame.rip, frame.cs, frame.rflags
+                    ));
+                    emit(format_args!(
+                        "  RSP 0x{:016x}  SS 0x{:x}",
+                        frame.rsp, frame.ss
+                    ));
+                    let gprs = [
+                        ("RAX", frame.rax),
+                        ("RBX", frame.rbx),
+                        ("RCX", frame.rcx),
+                        ("RDX", frame.rdx),
+                        ("RSI", frame.rsi),
+                        ("RDI", frame.rdi),
+                        ("RBP", frame.rbp),
+                        ("R8 ", frame.r8),
+                        ("R9 ", frame.r9),
+                        ("R10", frame.r10),
+                        ("R11", frame.r11),
+                        ("R12", frame.r12),
+                        ("R13", frame.r13),
+                        ("R14", frame.r14),
+                        ("R15", frame.r15),
+                    ];
+                    for row in gprs.chunks(3) {
+                        emit(format_args!("{}", RegisterRow(row)));
+                    }
+                    emit(format_args!(""));
+                } else if let Some(rip) = r.rip {
+                    emit(format_args!(
+                        "Stopped at RIP 0x{:016x}  RSP 0x{:016x}",
+                        rip, r.rsp
+                    ));
+                    emit(format_args!(""));
+                }
+
+                let g = &r.regs;
+                emit(format_args!("Registers in the panic handler:"));
+                let gprs = [
+                    ("RAX", g.rax),
+                    ("RBX", g.rbx),
+                    ("RCX", g.rcx),
+                    ("RDX", g.rdx),
+                    ("RSI", g.rsi),
+                    ("RDI", g.rdi),
+                    ("RBP", g.rbp),
+                    ("RSP", g.rsp),
+                    ("R8 ", g.r8),
+                    ("R9 ", g.r9),
+                    ("R10", g.r10),
+                    ("R11", g.r11),
+                    ("R12", g.r12),
+                    ("R13", g.r13),
+                    ("R14", g.r14),
+                    ("R15", g.r15),
+                ];
+                for row in gprs.chunks(3) {
+                    emit(format_args!("{}", RegisterRow(row)));
+                }
+                emit(format_args!(""));
+                emit(format_args!(
+                    "{}",
+                    RegisterRow(&[("CR0", r.cr0), ("CR2", r.cr2)])
+                ));
+                emit(format_args!(
+                    "{}",
+                    RegisterRow(&[("CR3", r.cr3), ("CR4", r.cr4)])
+                ));
+            }
+            PanicPage::Stack => {
+                if r.backtrace.is_empty() {
+                    emit(format_args!("<no frames>"));
+                }
+                for (i, entry) in r.backtrace.iter().enumerate() {
+                    emit(format_args!(
+                        "#{:<2} rip 0x{:016x}  rbp 0x{:016x}",
+                        i, entry.return_address, entry.frame_pointer
+                    ));
+                }
+            }
+            PanicPage::Log => {
+                let log = r.log.strip_suffix(b"\n").unwrap_or(r.log);
+                if log.is_empty() {
+                    emit(format_args!("<empty>"));
+                    return;
+                }
+                for line in log.split(|&b| b == b'\n') {
+                    emit(format_args!("{}", AsciiLine(line)));
+                }
+            }
+        }
+    }
+}
+
+/// Indented `NAME 0x...` pairs.
+struct RegisterRow<'a>(&'a [(&'a str, u64)]);
+
+impl fmt::Display for RegisterRow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.0 {
+            write!(f, "  {} 0x{:016x}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Log bytes with anything outside printable ASCII shown as `.`.
+struct AsciiLine<'a>(&'a [u8]);
+
+impl fmt::Display for AsciiLine<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &b in self.0 {
+            let c = if (0x20..0x7F).contains(&b) {
+                b as char
+            } else {
+                '.'
+            };
+            f.write_char(c)?;
+        }
+        Ok(())
+    }
+}
+
+fn draw_text(ctx: &mut GraphicsContext, x: i32, y: i32, text: &[u8], fg: Color32, bg: Color32) {
+    let clip = DamageRect {
+        x0: x,
+        y0: y,
+        x1: ctx.width() as i32 - MARGIN,
+        y1: y + FONT_CHAR_HEIGHT - 1,
+    };
+    for (i, &ch) in text.iter().enumerate() {
+        let cx = x + i as i32 * FONT_CHAR_WIDTH;
+        if cx > clip.x1 {
+            break;
+        }
+        canvas_font::draw_char_clipped(ctx, cx, y, ch, fg, bg, &clip);
+    }
+}
+
+fn draw_centered(ctx: &mut GraphicsContext, y: i32, text: &str, fg: Color32) {
+    let x = (ctx.width() as i32 - canvas_font::str_width(text)) / 2;
+    draw_text(ctx, x.max(MARGIN), y, text.as_bytes(), fg, PANIC_BG_COLOR);
+}
+
+/// Where the parts of the screen go.
+struct Layout {
+    tabs_y: i32,
+    pane_y: i32,
+    rows: usize,
+    status_y: i32,
+}
+
+impl Layout {
+    fn new(height: i32, reason_lines: i32) -> Self {
+        let tabs_y = 60 + FONT_CHAR_HEIGHT * 2 + (reason_lines + 1) * LINE_HEIGHT;
+        let pane_y = tabs_y + LINE_HEIGHT * 2;
+        let status_y = height - 60 - LINE_HEIGHT * 2;
+        Self {
+            tabs_y,
+            pane_y,
+            rows: ((status_y - pane_y) / LINE_HEIGHT).max(1) as usize,
+            status_y,
+        }
+    }
+}
+
+/// Draw the reason, wrapped to the screen.  Returns the lines used.
+fn draw_reason(ctx: &mut GraphicsContext, y: i32, message: &str) -> i32 {
+    let label = "Reason: ";
+    draw_text(
         ctx,
-        x + label_width,
+        MARGIN,
         y,
-        hex_text,
+        label.as_bytes(),
         PANIC_FG_COLOR,
         PANIC_BG_COLOR,
     );
+    let x = MARGIN + canvas_font::str_width(label);
+    let per_line = ((ctx.width() as i32 - MARGIN - x) / FONT_CHAR_WIDTH).max(1) as usize;
+    let bytes = message.as_bytes();
+    let mut lines = 0;
+    for chunk in bytes.chunks(per_line).take(MAX_REASON_LINES as usize) {
+        draw_text(
+            ctx,
+            x,
+            y + lines * LINE_HEIGHT,
+            chunk,
+            PANIC_FG_COLOR,
+            PANIC_BG_COLOR,
+        );
+        lines += 1;
+    }
+    lines.max(1)
 }
 
-/// Display the kernel panic screen.
-///
-/// Clears framebuffer to dark red, displays panic information,
-/// and shows "Press Enter to shutdown" prompt.
+fn draw_tabs(ctx: &mut GraphicsContext, y: i32, current: PanicPage) {
+    let mut x = MARGIN;
+    for page in PanicPage::ALL {
+        let title = page.title();
+        let w = canvas_font::str_width(title) + 2 * FONT_CHAR_WIDTH;
+        let (fg, bg) = if page == current {
+            (PANIC_FG_COLOR, PANIC_TAB_COLOR)
+        } else {
+            (PANIC_DIM_COLOR, PANIC_BG_COLOR)
+        };
+        let px = ctx.pixel_format().encode(bg);
+        ctx.fill_rect_encoded(x, y - 1, w, LINE_HEIGHT, px);
+        draw_text(ctx, x + FONT_CHAR_WIDTH, y, title.as_bytes(), fg, bg);
+        x += w + FONT_CHAR_WIDTH;
+    }
+}
+
+fn draw_pane(ctx: &mut GraphicsContext, layout: &Layout, browser: &PanicBrowser<'_>) {
+    let width = ctx.width() as i32;
+    let bg_px = ctx.pixel_format().encode(PANIC_BG_COLOR);
+    ctx.fill_rect_encoded(
+        0,
+        layout.tabs_y - 1,
+        width,
+        layout.status_y + LINE_HEIGHT - layout.tabs_y + 1,
+        bg_px,
+    );
+    draw_tabs(ctx, layout.tabs_y, browser.page());
+
+    let page = browser.page();
+    let first = browser.scroll(page);
+    let last = first + layout.rows;
+    browser.for_each_line(page, |i, text| {
+        if (first..last).contains(&i) {
+            let y = layout.pane_y + (i - first) as i32 * LINE_HEIGHT;
+            draw_text(ctx, MARGIN, y, text, PANIC_FG_COLOR, PANIC_BG_COLOR);
+        }
+    });
+
+    let total = browser.line_count(page);
+    let mut status = LineBuf::new();
+    let _ = write!(
+        status,
+        "lines {}-{} of {}",
+        (first + 1).min(total),
+        last.min(total),
+        total
+    );
+    draw_text(
+        ctx,
+        MARGIN,
+        layout.status_y,
+        status.as_bytes(),
+        PANIC_DIM_COLOR,
+        PANIC_BG_COLOR,
+    );
+}
+
+/// Whether [`run_panic_screen`] has a framebuffer to draw on.
+pub fn is_available() -> bool {
+    framebuffer::snapshot().is_some()
+}
+
+/// Display the kernel panic screen and browse it until [`PanicKey::Exit`].
 ///
-/// # Arguments
-/// * `message` - Optional panic message/reason
-/// * `rip` - Optional instruction pointer value
-/// * `rsp` - Optional stack pointer value
-/// * `cr0` - CR0 control register value
-/// * `cr3` - CR3 control register value (page directory base)
-/// * `cr4` - CR4 control register value
+/// Clears the framebuffer to dark red, draws the header and reason, then
+/// redraws the current page after every key from `next_key`, which should
+/// block until a key arrives.
 ///
 /// # Returns
 /// `true` if the panic screen was displayed, `false` if framebuffer unavailable.
-pub fn display_panic_screen(
-    message: Option<&str>,
-    rip: Option<u64>,
-    rsp: Option<u64>,
-    cr0: u64,
-    cr3: u64,
-    cr4: u64,
-) -> bool {
-    if framebuffer::snapshot().is_none() {
+pub fn run_panic_screen(report: &PanicReport<'_>, mut next_key: impl FnMut() -> PanicKey) -> bool {
+    if !is_available() {
         return false;
     }
 
@@ -68,138 +489,45 @@ pub fn display_panic_screen(
     let bg_px = ctx.pixel_format().encode(PANIC_BG_COLOR);
     ctx.clear_canvas(bg_px);
 
-    let width = ctx.width() as i32;
     let height = ctx.height() as i32;
 
-    let char_height = FONT_CHAR_HEIGHT;
-    let char_width = FONT_CHAR_WIDTH;
-
     let mut y = 60; // Start from top with margin
+    draw_centered(&mut ctx, y, "=== KERNEL PANIC ===", PANIC_HEADER_COLOR);
+    y += FONT_CHAR_HEIGHT * 2;
 
-    // Draw header: "KERNEL PANIC"
-    let header = b"=== KERNEL PANIC ===\0";
-    let header_len = 21;
-    let header_width = header_len * char_width;
-    let header_x = (width - header_width) / 2;
-    canvas_font::draw_string(
-        &mut ctx,
-        header_x,
-        y,
-        header,
-        PANIC_HEADER_COLOR,
-        PANIC_BG_COLOR,
-    );
-    y += char_height * 2;
-
-    // Draw subtitle
-    let subtitle = b"An unrecoverable error has occurred\0";
-    let subtitle_len = 36;
-    let subtitle_width = subtitle_len * char_width;
-    let subtitle_x = (width - subtitle_width) / 2;
-    canvas_font::draw_string(
-        &mut ctx,
-        subtitle_x,
-        y,
-        subtitle,
-        PANIC_FG_COLOR,
-        PANIC_BG_COLOR,
-    );
-    y += char_height * 2;
-
-    // Draw separator
-    y += char_height;
-
-    // Draw panic message if provided
-    if let Some(msg) = message {
-        let msg_label = b"Reason: \0";
-        canvas_font::draw_string(&mut ctx, 40, y, msg_label, PANIC_FG_COLOR, PANIC_BG_COLOR);
-
-        // Draw message character by character
-        let mut x = 40 + 8 * char_width;
-        let max_x = width - 40;
-        for &byte in msg.as_bytes() {
-            if byte == 0 {
-                break;
-            }
-            if x + char_width > max_x {
-                // Wrap to next line
-                y += char_height;
-                x = 40 + 8 * char_width;
-                if y > height - 120 {
-                    break; // Don't overflow into prompt area
-                }
-            }
-            canvas_font::draw_char(&mut ctx, x, y, byte, PANIC_FG_COLOR, PANIC_BG_COLOR);
-            x += char_width;
+    let reason_lines = match report.message {
+        Some(msg) => draw_reason(&mut ctx, y, msg),
+        None => {
+            draw_centered(
+                &mut ctx,
+                y,
+                "An unrecoverable error has occurred",
+                PANIC_FG_COLOR,
+            );
+            1
         }
-        y += char_height * 2;
-    }
-
-    // Draw register info section
-    y += char_height;
-    let reg_header = b"CPU State:\0";
-    canvas_font::draw_string(
-        &mut ctx,
-        40,
-        y,
-        reg_header,
-        PANIC_HEADER_COLOR,
-        PANIC_BG_COLOR,
-    );
-    y += char_height + 8;
-
-    // Draw RIP if available
-    if let Some(rip_val) = rip {
-        draw_register_line(&mut ctx, 60, y, b"RIP: \0", rip_val);
-        y += char_height + 4;
-    }
-
-    // Draw RSP if available
-    if let Some(rsp_val) = rsp {
-        draw_register_line(&mut ctx, 60, y, b"RSP: \0", rsp_val);
-        y += char_height + 4;
-    }
-
-    // Draw control registers
-    draw_register_line(&mut ctx, 60, y, b"CR0: \0", cr0);
-    y += char_height + 4;
-
-    draw_register_line(&mut ctx, 60, y, b"CR3: \0", cr3);
-    y += char_height + 4;
-
-    draw_register_line(&mut ctx, 60, y, b"CR4: \0", cr4);
+    };
 
-    // Draw prompt at bottom
-    let prompt = b"Press ENTER to shutdown\0";
-    let prompt_len = 24;
-    let prompt_width = prompt_len * char_width;
-    let prompt_x = (width - prompt_width) / 2;
-    let prompt_y = height - 60;
-    canvas_font::draw_string(
+    draw_centered(
         &mut ctx,
-        prompt_x,
-        prompt_y,
-        prompt,
+        height - 60,
+        "Tab/Left/Right: page   Up/Down/PgUp/PgDn/Home/End: scroll   Enter: shutdown",
         PANIC_FG_COLOR,
-        PANIC_BG_COLOR,
     );
-
-    // Draw a small note about serial output
-    let serial_note = b"(Debug output also available on serial console)\0";
-    let note_len = 47;
-    let note_width = note_len * char_width;
-    let note_x = (width - note_width) / 2;
-    let note_y = height - 40;
-    canvas_font::draw_string(
+    draw_centered(
         &mut ctx,
-        note_x,
-        note_y,
-        serial_note,
-        Color32(0xFF888888), // Gray
-        PANIC_BG_COLOR,
+        height - 40,
+        "(Debug output also available on serial console)",
+        PANIC_DIM_COLOR,
     );
 
-    ctx.flush();
-
-    true
+    let layout = Layout::new(height, reason_lines);
+    let mut browser = PanicBrowser::new(report, layout.rows);
+    loop {
+        draw_pane(&mut ctx, &layout, &browser);
+        ctx.flush();
+        if !browser.handle_key(next_key()) {
+            return true;
+        }
+    }
 }
@@ -0,0 +1,356 @@
+//! Timed buffer presentation (`SYSCALL_SURFACE_PRESENT`).
+//!
+//! Each task has a short FIFO of buffers tagged with target times.  The
+//! display refreshes on the vblank grid ([`vblank::refresh_ns`]); a buffer is
+//! due on the refresh nearest its target, and when several are due on the
+//! same refresh only the newest is shown and the rest count as dropped.
+//! Clients can therefore queue several frames ahead and never wait on the
+//! compositor per frame.
+//!
+//! - Composited queues are latched by [`present_latch_composited`] right
+//!   after the compositor drains its client queue, so the due buffer becomes
+//!   the surface's buffer for the frame being drawn.  It counts as presented
+//!   once the compositor reports a successful flip.  An idle compositor is
+//!   woken when a buffer is queued and sleeps no later than the next due
+//!   refresh ([`next_composited_refresh`]).
+//! - Bypass queues (display-exclusive tasks) are serviced by a pacer kthread
+//!   that sleeps until the next due refresh and copies the buffer straight
+//!   to the scanout.  It is spawned on first use and parks on a wait queue
+//!   while nothing is queued.
+//!
+//! Lock order: `QUEUES` before the compositor context.  Scanout copies run
+//! with no lock held.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::ptr;
+
+use slopos_abi::present::{
+    PRESENT_FLAG_BYPASS, PRESENT_FLAGS_ALL, PRESENT_QUEUE_DEPTH, PresentError, PresentFeedback,
+};
+use slopos_abi::task::INVALID_TASK_ID;
+use slopos_core::kthread::kthread_spawn;
+use slopos_core::scheduler::sleep::sleep_current_task_ms;
+use slopos_lib::clock::monotonic_ns;
+use slopos_lib::compositor_wake::compositor_wake;
+use slopos_lib::{InitFlag, IrqMutex, WaitQueue, klog_info};
+use slopos_mm::shared_memory::shm_get_buffer_info;
+
+use crate::{compositor_context, framebuffer, vblank};
+
+/// A presenter blocked on a full queue gives up after this long.  Only a
+/// composited queue with no compositor running should ever get here.
+const QUEUE_STALL_MS: u64 = 1000;
+
+struct Pending {
+    seq: u64,
+    token: u32,
+    target_ns: u64,
+}
+
+struct PresentQueue {
+    /// Mode of the buffers in `pending`; may only change while it is empty.
+    bypass: bool,
+    pending: VecDeque<Pending>,
+    next_seq: u64,
+    /// Latched into the surface, waiting for the compositor's flip.
+    latched_seq: u64,
+    feedback: PresentFeedback,
+}
+
+impl PresentQueue {
+    fn new() -> Self {
+        Self {
+            bypass: false,
+            pending: VecDeque::with_capacity(PRESENT_QUEUE_DEPTH),
+            next_seq: 1,
+            latched_seq: 0,
+            feedback: PresentFeedback::default(),
+        }
+    }
+
+    /// Append a buffer.  `Ok(None)` means the queue is full.
+    fn push(
+        &mut self,
+        token: u32,
+        target_ns: u64,
+        bypass: bool,
+    ) -> Result<Option<u64>, PresentError> {
+        if let Some(last) = self.pending.back()
+            && (bypass != self.bypass || target_ns < last.target_ns)
+        {
+            return Err(PresentError::InvalidArgument);
+        }
+        if self.pending.len() >= PRESENT_QUEUE_DEPTH {
+            return Ok(None);
+        }
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.bypass = bypass;
+        self.pending.push_back(Pending {
+            seq,
+            token,
+            target_ns,
+        });
+        Ok(Some(seq))
+    }
+
+    /// Pop the newest buffer due at `now`, dropping any older due ones.
+    fn take_due(&mut self, now: u64) -> Option<Pending> {
+        let mut shown = None;
+        while self
+            .pending
+            .front()
+            .is_some_and(|p| is_due(p.target_ns, now))
+        {
+            if shown.is_some() {
+                self.feedback.dropped += 1;
+            }
+            shown = self.pending.pop_front();
+        }
+        shown
+    }
+
+    fn mark_presented(&mut self, seq: u64, when_ns: u64) {
+        self.feedback.presented_seq = seq;
+        self.feedback.present_time_ns = when_ns;
+        self.feedback.presented += 1;
+    }
+}
+
+static QUEUES: IrqMutex<BTreeMap<u32, PresentQueue>> = IrqMutex::new(BTreeMap::new());
+static SPACE_WAITERS: WaitQueue = WaitQueue::new();
+static PACER_WAITERS: WaitQueue = WaitQueue::new();
+static PACER_SPAWNED: InitFlag = InitFlag::new();
+
+/// Whether a buffer targeting `target_ns` belongs on the refresh at `now`.
+fn is_due(target_ns: u64, now: u64) -> bool {
+    target_ns <= now + vblank::refresh_ns() / 2
+}
+
+/// The refresh a buffer targeting `target_ns` is shown on.
+fn refresh_for(target_ns: u64) -> u64 {
+    let refresh = vblank::refresh_ns();
+    target_ns.saturating_sub(refresh / 2).div_ceil(refresh) * refresh
+}
+
+fn has_space(task_id: u32) -> bool {
+    QUEUES
+        .lock()
+        .get(&task_id)
+        .is_none_or(|q| q.pending.len() < PRESENT_QUEUE_DEPTH)
+}
+
+/// Queue `token` for display at `target_ns` (0: the next refresh), blocking
+/// while the queue is full.  Returns the buffer's sequence number.
+pub fn surface_present(
+    task_id: u32,
+    process_id: u32,
+    token: u32,
+    target_ns: u64,
+    flags: u32,
+    display_exclusive: bool,
+) -> Result<u64, PresentError> {
+    if flags & !PRESENT_FLAGS_ALL != 0 {
+        return Err(PresentError::InvalidArgument);
+    }
+    let bypass = flags & PRESENT_FLAG_BYPASS != 0;
+    if bypass && !display_exclusive {
+        return Err(PresentError::PermissionDenied);
+    }
+
+    let (phys, size, owner) = shm_get_buffer_info(token);
+    if phys.is_null() || owner != process_id {
+        return Err(PresentError::InvalidToken);
+    }
+    let needed = if bypass {
+        framebuffer::get_display_info()
+            .ok_or(PresentError::InvalidArgument)?
+            .buffer_size()
+    } else {
+        let (width, height) =
+            compositor_context::surface_size(task_id).ok_or(PresentError::SurfaceNotFound)?;
+        width as usize * height as usize * 4
+    };
+    if size < needed {
+        return Err(PresentError::InvalidToken);
+    }
+
+    let target_ns = if target_ns == 0 {
+        monotonic_ns()
+    } else {
+        target_ns
+    };
+    let seq = loop {
+        let pushed = QUEUES
+            .lock()
+            .entry(task_id)
+            .or_insert_with(PresentQueue::new)
+            .push(token, target_ns, bypass)?;
+        if let Some(seq) = pushed {
+            break seq;
+        }
+        if !SPACE_WAITERS.wait_event_timeout(|| has_space(task_id), QUEUE_STALL_MS) {
+            return Err(PresentError::QueueFull);
+        }
+    };
+
+    if bypass {
+        ensure_pacer();
+        PACER_WAITERS.wake_one();
+    } else {
+        compositor_wake();
+    }
+    Ok(seq)
+}
+
+/// Snapshot of a task's presentation statistics.
+pub fn present_feedback(task_id: u32) -> PresentFeedback {
+    let queues = QUEUES.lock();
+    let mut feedback = queues
+        .get(&task_id)
+        .map_or_else(PresentFeedback::default, |q| {
+            let mut feedback = q.feedback;
+            feedback.queued = q.pending.len() as u32;
+            feedback
+        });
+    feedback.refresh_ns = vblank::refresh_ns();
+    feedback
+}
+
+/// Latch due buffers into their surfaces.  Called after the compositor
+/// drains its client queue, i.e. at the start of every composited frame.
+pub fn present_latch_composited() {
+    let now = monotonic_ns();
+    let mut latched = false;
+    {
+        let mut queues = QUEUES.lock();
+        for (&task_id, queue) in queues.iter_mut().filter(|(_, q)| !q.bypass) {
+            let Some(next) = queue.take_due(now) else {
+                continue;
+            };
+            latched = true;
+            if queue.latched_seq != 0 {
+                // The previous frame's flip failed, so it never showed.
+                queue.feedback.dropped += 1;
+                queue.latched_seq = 0;
+            }
+            if compositor_context::surface_latch_buffer(task_id, next.token).is_ok() {
+                queue.latched_seq = next.seq;
+            } else {
+                queue.feedback.dropped += 1;
+            }
+        }
+    }
+    if latched {
+        SPACE_WAITERS.wake_all();
+    }
+}
+
+/// The compositor flipped successfully; latched buffers are on screen.
+pub fn present_frames_done() {
+    let now = monotonic_ns();
+    let mut queues = QUEUES.lock();
+    for queue in queues.values_mut() {
+        if queue.latched_seq != 0 {
+            queue.mark_presented(queue.latched_seq, now);
+            queue.latched_seq = 0;
+        }
+    }
+}
+
+/// Copy every due bypass buffer to the scanout.  Returns how many were
+/// shown; exposed so the pacer's work can be driven without the kthread.
+pub fn present_bypass_once() -> usize {
+    let now = monotonic_ns();
+    let due: Vec<(u32, Pending)> = {
+        let mut queues = QUEUES.lock();
+        queues
+            .iter_mut()
+            .filter(|(_, q)| q.bypass)
+            .filter_map(|(&task_id, q)| q.take_due(now).map(|p| (task_id, p)))
+            .collect()
+    };
+    if due.is_empty() {
+        return 0;
+    }
+
+    let mut shown = 0;
+    for (task_id, pending) in due {
+        // The buffer may have been destroyed since it was queued.
+        let (phys, size, _) = shm_get_buffer_info(pending.token);
+        let ok = !phys.is_null() && framebuffer::fb_flip_from_shm(phys, size) == 0;
+        let when = monotonic_ns();
+        if let Some(queue) = QUEUES.lock().get_mut(&task_id) {
+            if ok {
+                queue.mark_presented(pending.seq, when);
+                shown += 1;
+            } else {
+                queue.feedback.dropped += 1;
+            }
+        }
+    }
+    SPACE_WAITERS.wake_all();
+    shown
+}
+
+/// Earliest refresh any queued buffer of the given mode is due on.
+fn next_refresh(bypass: bool) -> Option<u64> {
+    QUEUES
+        .lock()
+        .values()
+        .filter(|q| q.bypass == bypass)
+        .filter_map(|q| q.pending.front())
+        .map(|p| refresh_for(p.target_ns))
+        .min()
+}
+
+fn next_bypass_refresh() -> Option<u64> {
+    next_refresh(true)
+}
+
+/// Earliest refresh a composited buffer is due on; the compositor must
+/// draw a frame by then.
+pub fn next_composited_refresh() -> Option<u64> {
+    next_refresh(false)
+}
+
+fn bypass_pending() -> bool {
+    next_bypass_refresh().is_some()
+}
+
+fn present_pacer_task(_arg: *mut c_void) {
+    loop {
+        PACER_WAITERS.wait_event(bypass_pending);
+        let Some(refresh) = next_bypass_refresh() else {
+            continue;
+        };
+        let now = monotonic_ns();
+        if refresh > now {
+            sleep_current_task_ms((refresh - now).div_ceil(1_000_000) as u32);
+            continue;
+        }
+        present_bypass_once();
+    }
+}
+
+fn ensure_pacer() {
+    if !PACER_SPAWNED.claim() {
+        return;
+    }
+    let task_id = kthread_spawn(
+        c"present_pacer".as_ptr(),
+        Some(present_pacer_task),
+        ptr::null_mut(),
+    );
+    if task_id == INVALID_TASK_ID {
+        klog_info!("present: failed to spawn pacer thread");
+        PACER_SPAWNED.reset();
+    }
+}
+
+/// Drop a task's queue when it exits.
+pub fn present_forget_task(task_id: u32) {
+    QUEUES.lock().remove(&task_id);
+}
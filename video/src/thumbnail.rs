@@ -0,0 +1,120 @@
+//! Scaled-down copies of surfaces (`SYSCALL_SURFACE_THUMBNAIL`).
+//!
+//! Any task can ask for a thumbnail of any surface, on screen or
+//! off-screen, drawn into a buffer it owns.  The copy is taken from the
+//! surface's current buffer at call time, so calling it once per frame
+//! gives a live preview (Alt-Tab switchers, taskbar hover previews).
+//!
+//! Each thumbnail pixel averages a grid of at most [`MAX_TAPS`] x
+//! [`MAX_TAPS`] source pixels from the box it covers, so the cost is
+//! bounded by the thumbnail size rather than the window size.
+
+use slopos_abi::CompositorError;
+use slopos_abi::addr::PhysAddr;
+use slopos_mm::hhdm::PhysAddrHhdm;
+use slopos_mm::shared_memory::shm_get_buffer_info;
+
+use crate::compositor_context;
+
+/// Samples per axis averaged into one thumbnail pixel.
+const MAX_TAPS: u32 = 4;
+
+/// The largest size with `width`x`height`'s aspect ratio that fits in
+/// `max_width`x`max_height`.  Surfaces already small enough keep their size.
+pub fn thumbnail_size(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    if width <= max_width && height <= max_height {
+        return (width, height);
+    }
+    let (w, h) = (width as u64, height as u64);
+    let (max_w, max_h) = (max_width as u64, max_height as u64);
+    if w * max_h >= h * max_w {
+        (max_width, (h * max_w / w).max(1) as u32)
+    } else {
+        ((w * max_h / h).max(1) as u32, max_height)
+    }
+}
+
+/// Map a shared buffer of at least `len` pixels.
+fn pixels(phys: PhysAddr, size: usize, len: usize) -> Option<*mut u32> {
+    if phys.is_null() || size < len * 4 {
+        return None;
+    }
+    Some(phys.to_virt_checked()?.as_mut_ptr())
+}
+
+/// Average of the four byte lanes of `taps` pixels, lane by lane.
+fn average(sum: [u32; 4], taps: u32) -> u32 {
+    sum.iter()
+        .enumerate()
+        .fold(0, |px, (lane, &s)| px | ((s / taps) << (lane * 8)))
+}
+
+/// Source rows or columns `[start, end)` covered by thumbnail index `i`.
+fn span(i: u32, dst_len: u32, src_len: u32) -> (u32, u32) {
+    let start = (i as u64 * src_len as u64 / dst_len as u64) as u32;
+    let end = ((i as u64 + 1) * src_len as u64 / dst_len as u64) as u32;
+    (start, end.max(start + 1).min(src_len))
+}
+
+/// Draw a thumbnail of `target_task`'s surface into `dst_token`, a buffer
+/// owned by `process_id`, fitted to `max_width`x`max_height` and packed at
+/// the thumbnail's own width.  Returns the size drawn.
+pub fn surface_render_thumbnail(
+    target_task: u32,
+    process_id: u32,
+    dst_token: u32,
+    max_width: u32,
+    max_height: u32,
+) -> Result<(u32, u32), CompositorError> {
+    if max_width == 0 || max_height == 0 {
+        return Err(CompositorError::InvalidArgument);
+    }
+    let (src_token, width, height) =
+        compositor_context::surface_buffer(target_task).ok_or(CompositorError::SurfaceNotFound)?;
+    if width == 0 || height == 0 {
+        return Err(CompositorError::SurfaceNotFound);
+    }
+
+    let (dst_phys, dst_size, owner) = shm_get_buffer_info(dst_token);
+    if dst_phys.is_null() {
+        return Err(CompositorError::BufferNotFound);
+    }
+    if owner != process_id {
+        return Err(CompositorError::PermissionDenied);
+    }
+
+    let (thumb_w, thumb_h) = thumbnail_size(width, height, max_width, max_height);
+    let dst = pixels(dst_phys, dst_size, thumb_w as usize * thumb_h as usize)
+        .ok_or(CompositorError::InvalidArgument)?;
+    let (src_phys, src_size, _) = shm_get_buffer_info(src_token);
+    let src = pixels(src_phys, src_size, width as usize * height as usize)
+        .ok_or(CompositorError::BufferNotFound)?;
+
+    for ty in 0..thumb_h {
+        let (y0, y1) = span(ty, thumb_h, height);
+        let y_step = ((y1 - y0) / MAX_TAPS).max(1);
+        for tx in 0..thumb_w {
+            let (x0, x1) = span(tx, thumb_w, width);
+            let x_step = ((x1 - x0) / MAX_TAPS).max(1);
+
+            let mut sum = [0u32; 4];
+            let mut taps = 0;
+            for sy in (y0..y1).step_by(y_step as usize).take(MAX_TAPS as usize) {
+                let row = sy as usize * width as usize;
+                for sx in (x0..x1).step_by(x_step as usize).take(MAX_TAPS as usize) {
+                    // SAFETY: `src` holds width * height pixels and sx, sy
+                    // are within them.
+                    let px = unsafe { src.add(row + sx as usize).read() };
+                    for (lane, s) in sum.iter_mut().enumerate() {
+                        *s += (px >> (lane * 8)) & 0xFF;
+                    }
+                    taps += 1;
+                }
+            }
+            let offset = ty as usize * thumb_w as usize + tx as usize;
+            // SAFETY: `dst` holds thumb_w * thumb_h pixels.
+            unsafe { dst.add(offset).write(average(sum, taps)) };
+        }
+    }
+    Ok((thumb_w, thumb_h))
+}
@@ -0,0 +1,118 @@
+//! Vertical blank notification (`SYSCALL_WAIT_VBLANK`).
+//!
+//! None of the scanout backends here raise a vblank interrupt, so vblank is
+//! a one-shot kernel timer rearmed for each refresh on a fixed grid of
+//! `CLOCK_MONOTONIC`: vblank `n` happens at `n * refresh_ns()`.  The same
+//! grid paces timed presents (see `present`).
+//!
+//! The timer only runs while someone needs it: a task blocked in
+//! [`wait_vblank`], or a frame callback whose frame has been flipped and is
+//! waiting for the vblank that puts it on screen.  On a page-flipping
+//! backend that is the first vblank after the flip has latched.
+
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use slopos_abi::present::PRESENT_REFRESH_NS;
+use slopos_core::ktimer::{KtimerMode, ktimer_add};
+use slopos_lib::WaitQueue;
+use slopos_lib::clock::monotonic_ns;
+
+use crate::{compositor_context, framebuffer};
+
+/// Refresh rates `video.refresh_hz=` accepts.
+pub const REFRESH_HZ_MIN: u32 = 24;
+pub const REFRESH_HZ_MAX: u32 = 240;
+
+static REFRESH_NS: AtomicU64 = AtomicU64::new(PRESENT_REFRESH_NS);
+/// Latest vblank the timer has signalled.
+static SIGNALLED_SEQ: AtomicU64 = AtomicU64::new(0);
+static TIMER_ARMED: AtomicBool = AtomicBool::new(false);
+static VBLANK_WAITERS: WaitQueue = WaitQueue::new();
+
+/// Current refresh period.
+pub fn refresh_ns() -> u64 {
+    REFRESH_NS.load(Ordering::Relaxed)
+}
+
+/// Set the refresh period.  Periods outside the supported refresh rates
+/// are rejected.
+pub fn set_refresh_ns(period_ns: u64) -> bool {
+    let fastest = 1_000_000_000 / REFRESH_HZ_MAX as u64;
+    let slowest = 1_000_000_000 / REFRESH_HZ_MIN as u64;
+    if !(fastest..=slowest).contains(&period_ns) {
+        return false;
+    }
+    REFRESH_NS.store(period_ns, Ordering::Relaxed);
+    true
+}
+
+/// Set the refresh rate in Hz (`video.refresh_hz=`).
+pub fn set_refresh_hz(hz: u32) -> bool {
+    (REFRESH_HZ_MIN..=REFRESH_HZ_MAX).contains(&hz) && set_refresh_ns(1_000_000_000 / hz as u64)
+}
+
+/// The last vblank at or before `now_ns`.
+pub fn vblank_seq_at(now_ns: u64) -> u64 {
+    now_ns / refresh_ns()
+}
+
+/// `CLOCK_MONOTONIC` time of vblank `seq`.
+pub fn vblank_time_ns(seq: u64) -> u64 {
+    seq.saturating_mul(refresh_ns())
+}
+
+/// Make sure the timer fires on the next vblank.
+pub fn vblank_arm() {
+    if TIMER_ARMED.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let now = monotonic_ns();
+    let next = vblank_time_ns(vblank_seq_at(now) + 1);
+    let delay_ms = (next - now).div_ceil(1_000_000).max(1) as u32;
+    if ktimer_add(KtimerMode::OneShot, delay_ms, vblank_fired, ptr::null_mut()).is_none() {
+        TIMER_ARMED.store(false, Ordering::Release);
+    }
+}
+
+/// Timer callback: runs in interrupt context.
+fn vblank_fired(_context: *mut core::ffi::c_void) {
+    TIMER_ARMED.store(false, Ordering::Release);
+    let seq = vblank_seq_at(monotonic_ns());
+    if seq <= SIGNALLED_SEQ.load(Ordering::Acquire) {
+        // Tick rounding fired us just short of the vblank.
+        vblank_arm();
+        return;
+    }
+    SIGNALLED_SEQ.store(seq, Ordering::Release);
+    if framebuffer::page_flip_done() {
+        compositor_context::surface_complete_frame_callbacks(vblank_time_ns(seq) / 1_000_000);
+    } else {
+        // The flip missed this vblank; its frame shows on the next one.
+        vblank_arm();
+    }
+    VBLANK_WAITERS.wake_all();
+}
+
+/// Block until a vblank after `after_seq` (0: after now) and return its
+/// sequence number.
+pub fn wait_vblank(after_seq: u64) -> u64 {
+    let target = if after_seq == 0 {
+        vblank_seq_at(monotonic_ns())
+    } else {
+        after_seq
+    };
+    loop {
+        let seq = vblank_seq_at(monotonic_ns());
+        if seq > target {
+            return seq;
+        }
+        vblank_arm();
+        if !VBLANK_WAITERS.wait_event(|| vblank_seq_at(monotonic_ns()) > target) {
+            // Wait queue full: fall back to sleeping through the refresh.
+            slopos_core::scheduler::sleep::sleep_current_task_ms(
+                (refresh_ns().div_ceil(1_000_000)) as u32,
+            );
+        }
+    }
+}